@@ -47,6 +47,7 @@ const X86: &str = "x86";
 const X86_64: &str = "x86_64";
 const AARCH64: &str = "aarch64";
 const ARM: &str = "arm";
+const WASM32: &str = "wasm32";
 const NEVER: &str = "Don't ever build this file.";
 
 #[cfg_attr(rustfmt, rustfmt_skip)]
@@ -108,8 +109,9 @@ const RING_SRCS: &[(&[&str], &str)] = &[
     (&[ARM], "crypto/fipsmodule/sha/asm/sha256-armv4.pl"),
     (&[ARM], "crypto/fipsmodule/sha/asm/sha512-armv4.pl"),
 
-    (&[AARCH64], "crypto/fipsmodule/aes/aes.c"),
+    (&[AARCH64, WASM32], "crypto/fipsmodule/aes/aes.c"),
     (&[AARCH64], "crypto/fipsmodule/bn/asm/armv8-mont.pl"),
+    (&[AARCH64], "crypto/cpu-aarch64-bsd.c"),
     (&[AARCH64], "crypto/cpu-aarch64-linux.c"),
     (&[AARCH64], "crypto/chacha/asm/chacha-armv8.pl"),
     (&[AARCH64], "crypto/fipsmodule/ec/asm/ecp_nistz256-armv8.pl"),
@@ -132,6 +134,7 @@ const RING_INCLUDES: &[&str] =
       "crypto/fipsmodule/cipher/internal.h",
       "crypto/fipsmodule/ec/ecp_nistz256_table.inl",
       "crypto/fipsmodule/ec/ecp_nistz384.inl",
+      "crypto/fipsmodule/ec/ecp_nistz384_table.inl",
       "crypto/fipsmodule/ec/ecp_nistz.h",
       "crypto/fipsmodule/ec/ecp_nistz384.h",
       "crypto/fipsmodule/ec/ecp_nistz256.h",
@@ -139,12 +142,18 @@ const RING_INCLUDES: &[&str] =
       "crypto/limbs/limbs.h",
       "crypto/limbs/limbs.inl",
       "crypto/fipsmodule/modes/internal.h",
+      "crypto/nostdlibinc/assert.h",
+      "crypto/nostdlibinc/inttypes.h",
+      "crypto/nostdlibinc/stdlib.h",
+      "crypto/nostdlibinc/string.h",
+      "crypto/nostdlibinc/sys/types.h",
       "include/GFp/aes.h",
       "include/GFp/arm_arch.h",
       "include/GFp/base.h",
       "include/GFp/cpu.h",
       "include/GFp/mem.h",
       "include/GFp/type_check.h",
+      "include/ring.h",
       "third_party/fiat/curve25519_tables.h",
       "third_party/fiat/internal.h",
     ];
@@ -249,6 +258,7 @@ const ASM_TARGETS: &[(&str, Option<&str>, &str)] = &[
 ];
 
 const WINDOWS: &'static str = "windows";
+const UEFI: &str = "uefi";
 const MSVC: &'static str = "msvc";
 const MSVC_OBJ_OPT: &'static str = "/Fo";
 const MSVC_OBJ_EXT: &'static str = "obj";
@@ -343,6 +353,19 @@ impl Target {
     pub fn os(&self) -> &str { &self.os }
     pub fn env(&self) -> &str { &self.env }
     pub fn is_debug(&self) -> bool { self.is_debug }
+
+    // UEFI uses the calling convention and object file format of Windows, so
+    // it uses the Windows assembly language code.
+    pub fn asm_os(&self) -> &str {
+        if self.os == UEFI {
+            WINDOWS
+        } else {
+            &self.os
+        }
+    }
+
+    // Whether the target has no C standard library.
+    pub fn is_freestanding(&self) -> bool { self.arch == WASM32 || self.os == UEFI }
 }
 
 fn build_c_code(target: &Target, pregenerated: PathBuf, out_dir: &Path) {
@@ -365,13 +388,12 @@ fn build_c_code(target: &Target, pregenerated: PathBuf, out_dir: &Path) {
         }
     }
 
-    let (_, _, perlasm_format) = ASM_TARGETS
-        .iter()
-        .find(|entry| {
-            let &(entry_arch, entry_os, _) = *entry;
-            entry_arch == target.arch() && is_none_or_equals(entry_os, target.os())
-        })
-        .unwrap();
+    // There is no assembly language code for the targets that aren't in
+    // `ASM_TARGETS`, e.g. wasm32; base.h defines `OPENSSL_NO_ASM` for them.
+    let asm_target = ASM_TARGETS.iter().find(|entry| {
+        let &(entry_arch, entry_os, _) = *entry;
+        entry_arch == target.arch() && is_none_or_equals(entry_os, target.asm_os())
+    });
 
     let is_git = std::fs::metadata(".git").is_ok();
 
@@ -384,24 +406,35 @@ fn build_c_code(target: &Target, pregenerated: PathBuf, out_dir: &Path) {
         out_dir
     };
 
-    let perlasm_src_dsts =
-        perlasm_src_dsts(asm_dir, target.arch(), Some(target.os()), perlasm_format);
+    let perlasm_src_dsts = match asm_target {
+        Some(&(_, _, perlasm_format)) => {
+            let perlasm_src_dsts = perlasm_src_dsts(
+                asm_dir,
+                target.arch(),
+                Some(target.asm_os()),
+                perlasm_format,
+            );
+
+            if !use_pregenerated {
+                perlasm(
+                    &perlasm_src_dsts[..],
+                    target.arch(),
+                    perlasm_format,
+                    Some(includes_modified),
+                );
+            }
 
-    if !use_pregenerated {
-        perlasm(
-            &perlasm_src_dsts[..],
-            target.arch(),
-            perlasm_format,
-            Some(includes_modified),
-        );
-    }
+            perlasm_src_dsts
+        },
+        None => Vec::new(),
+    };
 
     let mut asm_srcs = asm_srcs(perlasm_src_dsts);
 
     // For Windows we also pregenerate the object files for non-Git builds so
     // the user doesn't need to install the assembler. On other platforms we
     // assume the C compiler also assembles.
-    if use_pregenerated && target.os() == WINDOWS {
+    if use_pregenerated && target.asm_os() == WINDOWS {
         // The pregenerated object files always use ".obj" as the extension,
         // even when the C/C++ compiler outputs files with the ".o" extension.
         asm_srcs = asm_srcs
@@ -417,14 +450,16 @@ fn build_c_code(target: &Target, pregenerated: PathBuf, out_dir: &Path) {
 
     let test_srcs = RING_TEST_SRCS.iter().map(PathBuf::from).collect::<Vec<_>>();
 
-    let libs = [
-        ("ring-core", &core_srcs[..], &asm_srcs[..]),
-        ("ring-test", &test_srcs[..], &[]),
-    ];
+    let mut libs = vec![("ring-core", &core_srcs[..], &asm_srcs[..])];
+
+    // The tests use the C standard library's `<stdio.h>`.
+    if !target.is_freestanding() {
+        libs.push(("ring-test", &test_srcs[..], &[]));
+    }
 
     // XXX: Ideally, ring-test would only be built for `cargo test`, but Cargo
     // can't do that yet.
-    libs.into_iter()
+    libs.iter()
         .for_each(|&(lib_name, srcs, additional_srcs)| {
             build_library(
                 &target,
@@ -509,7 +544,7 @@ fn compile(
         let mut out_path = out_dir.clone().join(p.file_name().unwrap());
         assert!(out_path.set_extension(target.obj_ext));
         if need_run(&p, &out_path, includes_modified) {
-            let cmd = if target.os() != WINDOWS || ext != "asm" {
+            let cmd = if target.asm_os() != WINDOWS || ext != "asm" {
                 cc(p, ext, target, warnings_are_errors, &out_path)
             } else {
                 yasm(p, target.arch(), &out_path)
@@ -543,9 +578,19 @@ fn cc(
     for f in cpp_flags(target) {
         let _ = c.flag(&f);
     }
-    if target.os() != "none" && target.os() != "redox" && target.os() != "windows" {
+    if target.os() != "none"
+        && target.os() != "redox"
+        && target.os() != "windows"
+        && !target.is_freestanding()
+    {
         let _ = c.flag("-fstack-protector");
     }
+    if target.is_freestanding() {
+        // Use the replacements for the few parts of the C standard library
+        // that are needed.
+        let _ = c.flag("-nostdlibinc");
+        let _ = c.include("crypto/nostdlibinc");
+    }
 
     match (target.os(), target.env()) {
         // ``-gfull`` is required for Darwin's |-dead_strip|.
@@ -560,6 +605,13 @@ fn cc(
     if !target.is_debug() {
         let _ = c.define("NDEBUG", None);
     }
+    if target.os() == UEFI {
+        // Compilers treat UEFI targets as Windows targets; see base.h.
+        let _ = c.define("OPENSSL_UEFI", None);
+    }
+    if std::env::var_os("CARGO_FEATURE_LARGE_EC_TABLES").is_some() {
+        let _ = c.define("GFp_LARGE_EC_TABLES", None);
+    }
 
     if target.env() == "msvc" {
         if std::env::var("OPT_LEVEL").unwrap() == "0" {
@@ -52,7 +52,8 @@
 #	low to justify the [maintenance] effort;
 # (iv)	Bulldozer actually executes 4xXOP code path that delivers 2.20;
 #
-# Modified from upstream OpenSSL to remove the XOP code.
+# Modified from upstream OpenSSL to remove the XOP code, and to fix the
+# misaligned store at the end of ChaCha20_avx512.
 
 $flavour = shift;
 $output  = shift;
@@ -65,7 +66,7 @@ $0 =~ m/(.*[\/\\])[^\/\\]+$/; $dir=$1;
 ( $xlate="${dir}../../perlasm/x86_64-xlate.pl" and -f $xlate) or
 die "can't locate x86_64-xlate.pl";
 
-$avx = 2;
+$avx = 3;
 
 open OUT,"| \"$^X\" \"$xlate\" $flavour \"$output\"";
 *STDOUT=*OUT;
@@ -1980,7 +1981,7 @@ $code.=<<___;
 	dec		$len
 	jnz		.Loop_tail_avx512
 
-	vmovdqa32	$a_,0x00(%rsp)
+	vmovdqu32	$a_,0x00(%rsp)		# %rsp is only 16-byte aligned
 
 .Ldone_avx512:
 	vzeroall
@@ -0,0 +1,126 @@
+/* Copyright 2019 Brian Smith.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+#include <GFp/cpu.h>
+
+#if defined(OPENSSL_AARCH64) && !defined(OPENSSL_STATIC_ARMCAP) && \
+    (defined(__FreeBSD__) || defined(__OpenBSD__) || defined(__NetBSD__))
+
+#include <sys/types.h>
+
+#if defined(__FreeBSD__)
+#include <sys/auxv.h>
+#elif defined(__OpenBSD__)
+#include <sys/sysctl.h>
+#include <machine/cpu.h>
+#else
+#include <sys/sysctl.h>
+#include <aarch64/armreg.h>
+#endif
+
+#include <GFp/arm_arch.h>
+
+#include "internal.h"
+
+
+extern uint32_t GFp_armcap_P;
+
+#if defined(__FreeBSD__)
+
+void GFp_cpuid_setup(void) {
+  unsigned long hwcap = 0;
+  if (elf_aux_info(AT_HWCAP, &hwcap, sizeof(hwcap)) != 0) {
+    return;
+  }
+
+  // FreeBSD uses the same values as Linux; see cpu-aarch64-linux.c.
+  static const unsigned long kNEON = 1 << 1;
+  static const unsigned long kAES = 1 << 3;
+  static const unsigned long kPMULL = 1 << 4;
+  static const unsigned long kSHA1 = 1 << 5;
+  static const unsigned long kSHA256 = 1 << 6;
+
+  if ((hwcap & kNEON) == 0) {
+    return;
+  }
+
+  GFp_armcap_P |= ARMV7_NEON;
+
+  if (hwcap & kAES) {
+    GFp_armcap_P |= ARMV8_AES;
+  }
+  if (hwcap & kPMULL) {
+    GFp_armcap_P |= ARMV8_PMULL;
+  }
+  if (hwcap & kSHA1) {
+    GFp_armcap_P |= ARMV8_SHA1;
+  }
+  if (hwcap & kSHA256) {
+    GFp_armcap_P |= ARMV8_SHA256;
+  }
+}
+
+#else
+
+// OpenBSD and NetBSD don't provide HWCAP, but they make the ID_AA64ISAR0_EL1
+// register, which userspace can't read directly, available through sysctl.
+static int read_id_aa64isar0(uint64_t *out) {
+#if defined(__OpenBSD__)
+  int mib[2] = {CTL_MACHDEP, CPU_ID_AA64ISAR0};
+  size_t len = sizeof(*out);
+  return sysctl(mib, 2, out, &len, NULL, 0) == 0 && len == sizeof(*out);
+#else
+  struct aarch64_sysctl_cpu_id id;
+  size_t len = sizeof(id);
+  if (sysctlbyname("machdep.cpu0.cpu_id", &id, &len, NULL, 0) != 0 ||
+      len != sizeof(id)) {
+    return 0;
+  }
+  *out = id.ac_aa64isar0;
+  return 1;
+#endif
+}
+
+void GFp_cpuid_setup(void) {
+  uint64_t isar0;
+  if (!read_id_aa64isar0(&isar0)) {
+    return;
+  }
+
+  // Advanced SIMD is part of every AArch64 CPU that these systems support.
+  GFp_armcap_P |= ARMV7_NEON;
+
+  // See the description of ID_AA64ISAR0_EL1 in the ARM Architecture
+  // Reference Manual. The AES field is 1 for AES and 2 for AES and PMULL.
+  uint64_t aes = (isar0 >> 4) & 0xf;
+  uint64_t sha1 = (isar0 >> 8) & 0xf;
+  uint64_t sha2 = (isar0 >> 12) & 0xf;
+
+  if (aes >= 1) {
+    GFp_armcap_P |= ARMV8_AES;
+  }
+  if (aes >= 2) {
+    GFp_armcap_P |= ARMV8_PMULL;
+  }
+  if (sha1 >= 1) {
+    GFp_armcap_P |= ARMV8_SHA1;
+  }
+  if (sha2 >= 1) {
+    GFp_armcap_P |= ARMV8_SHA256;
+  }
+}
+
+#endif
+
+#endif  // OPENSSL_AARCH64 && !OPENSSL_STATIC_ARMCAP && (BSD)
@@ -14,7 +14,8 @@
 
 #include <GFp/cpu.h>
 
-#if defined(OPENSSL_AARCH64) && !defined(OPENSSL_STATIC_ARMCAP)
+#if defined(OPENSSL_AARCH64) && !defined(OPENSSL_STATIC_ARMCAP) && \
+    defined(__linux__)
 
 #include <sys/auxv.h>
 
@@ -58,4 +59,4 @@ void GFp_cpuid_setup(void) {
   }
 }
 
-#endif  // OPENSSL_AARCH64 && !OPENSSL_STATIC_ARMCAP
+#endif  // OPENSSL_AARCH64 && !OPENSSL_STATIC_ARMCAP && __linux__
@@ -19,6 +19,8 @@
 
 #include <GFp/arm_arch.h>
 
+#include "internal.h"
+
 
 extern uint32_t GFp_armcap_P;
 
@@ -34,5 +36,25 @@ int GFp_is_ARMv8_PMULL_capable(void) {
   return (GFp_armcap_P & ARMV8_PMULL) != 0;
 }
 
+#if defined(OPENSSL_AARCH64) && !defined(__linux__) && \
+    !defined(__FreeBSD__) && !defined(__OpenBSD__) && !defined(__NetBSD__)
+// There's no way to detect the features on other systems, e.g. bare metal, so
+// only the features assumed by `cpu::arm::assume_features()` are used.
+void GFp_cpuid_setup(void) {
+}
+#endif
+
 #endif  /* (defined(OPENSSL_ARM) || defined(OPENSSL_AARCH64)) &&
            !defined(OPENSSL_STATIC_ARMCAP) */
+
+#if defined(OPENSSL_ARM) && defined(OPENSSL_STATIC_ARMCAP) && \
+    !defined(OPENSSL_APPLE)
+
+#include "internal.h"
+
+// M-profile CPUs use the static ARM configuration, so there's nothing to set
+// up.
+void GFp_cpuid_setup(void) {
+}
+
+#endif
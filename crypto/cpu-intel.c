@@ -118,10 +118,10 @@ static uint64_t OPENSSL_xgetbv(uint32_t xcr) {
 #endif
 }
 
-void GFp_cpuid_setup(void) {
+void GFp_cpuid_setup_with(GFp_cpuid_fn cpuid) {
   // Determine the vendor and maximum input value.
   uint32_t eax, ebx, ecx, edx;
-  OPENSSL_cpuid(&eax, &ebx, &ecx, &edx, 0);
+  cpuid(&eax, &ebx, &ecx, &edx, 0);
 
   uint32_t num_ids = eax;
 
@@ -135,7 +135,7 @@ void GFp_cpuid_setup(void) {
 
   uint32_t extended_features[2] = {0};
   if (num_ids >= 7) {
-    OPENSSL_cpuid(&eax, &ebx, &ecx, &edx, 7);
+    cpuid(&eax, &ebx, &ecx, &edx, 7);
     extended_features[0] = ebx;
     extended_features[1] = ecx;
   }
@@ -150,11 +150,11 @@ void GFp_cpuid_setup(void) {
   } else if (num_ids >= 4) {
     // TODO(davidben): The Intel manual says this CPUID leaf enumerates all
     // caches using ECX and doesn't say which is first. Does this matter?
-    OPENSSL_cpuid(&eax, &ebx, &ecx, &edx, 4);
+    cpuid(&eax, &ebx, &ecx, &edx, 4);
     cores_per_cache = 1 + ((eax >> 14) & 0xfff);
   }
 
-  OPENSSL_cpuid(&eax, &ebx, &ecx, &edx, 1);
+  cpuid(&eax, &ebx, &ecx, &edx, 1);
 
   // Adjust the hyper-threading bit.
   if (edx & (1u << 28)) {
@@ -223,4 +223,8 @@ void GFp_cpuid_setup(void) {
   GFp_ia32cap_P[3] = extended_features[1];
 }
 
+void GFp_cpuid_setup(void) {
+  GFp_cpuid_setup_with(OPENSSL_cpuid);
+}
+
 #endif  // !OPENSSL_NO_ASM && (OPENSSL_X86 || OPENSSL_X86_64)
@@ -43,7 +43,7 @@
 // undesirable, so all assembly-referenced symbols should be hidden. CPU
 // capabilities are the only such symbols defined in C. Explicitly hide them,
 // rather than rely on being built with -fvisibility=hidden.
-#if defined(OPENSSL_WINDOWS)
+#if defined(OPENSSL_WINDOWS) || defined(OPENSSL_UEFI)
 #define HIDDEN
 #else
 #define HIDDEN __attribute__((visibility("hidden")))
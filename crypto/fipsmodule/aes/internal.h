@@ -30,6 +30,8 @@ static inline int hwaes_capable(void) {
   return GFp_is_ARMv8_AES_capable();
 #elif defined(OPENSSL_PPC64LE)
   return GFp_is_PPC64LE_vcrypto_capable();
+#else
+  return 0;
 #endif
 }
 
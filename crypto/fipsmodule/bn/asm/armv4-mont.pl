@@ -99,7 +99,9 @@ $code=<<___;
 
 @ Silence ARMv8 deprecated IT instruction warnings. This file is used by both
 @ ARMv7 and ARMv8 processors and does not use ARMv8 instructions.
+#if __ARM_MAX_ARCH__>=7
 .arch  armv7-a
+#endif
 
 .text
 #if defined(__thumb2__)
@@ -161,3 +161,32 @@ int GFp_bn_from_montgomery_in_place(BN_ULONG r[], size_t num_r, BN_ULONG a[],
   }
   return 1;
 }
+
+#if defined(OPENSSL_NO_ASM)
+
+// The largest modulus that |GFp_bn_mul_mont| supports, which is the largest
+// RSA modulus that *ring* supports (8192 bits).
+#define BN_MONT_MAX_LIMBS (8192 / BN_BITS2)
+
+void GFp_bn_mul_mont(BN_ULONG *rp, const BN_ULONG *ap, const BN_ULONG *bp,
+                     const BN_ULONG *np, const BN_ULONG *n0, size_t num) {
+  assert(num <= BN_MONT_MAX_LIMBS);
+
+  // |tmp| = |ap| * |bp|. |rp| may alias |ap| or |bp|, so |rp| isn't written
+  // until the product has been computed.
+  BN_ULONG tmp[2 * BN_MONT_MAX_LIMBS];
+  for (size_t i = 0; i < num; ++i) {
+    tmp[i] = 0;
+  }
+  for (size_t i = 0; i < num; ++i) {
+    tmp[num + i] = GFp_bn_mul_add_words(tmp + i, ap, num, bp[i]);
+  }
+
+  int ok = GFp_bn_from_montgomery_in_place(rp, num, tmp, 2 * num, np, num, n0);
+#if defined(NDEBUG)
+  (void)ok;
+#endif
+  assert(ok);
+}
+
+#endif  // OPENSSL_NO_ASM
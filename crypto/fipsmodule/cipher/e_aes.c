@@ -90,6 +90,13 @@ void GFp_AES_encrypt(const uint8_t *in, uint8_t *out, const AES_KEY *key);
 
 
 
+#if !defined(OPENSSL_NO_ASM) && \
+    (!defined(OPENSSL_ARM) || __ARM_MAX_ARCH__ >= 7)
+// On 32-bit ARM, the hardware AES implementation is only assembled when the
+// target might have the ARMv8 crypto instructions; see |__ARM_MAX_ARCH__|.
+#define HWAES
+#endif
+
 #if !defined(OPENSSL_NO_ASM) && \
     (defined(OPENSSL_X86_64) || defined(OPENSSL_X86))
 #define VPAES
@@ -134,10 +141,12 @@ void GFp_AES_set_encrypt_key(const uint8_t *user_key, unsigned bits,
   assert(key != NULL);
   assert(bits == 128 || bits == 256);
 
+#if defined(HWAES)
   if (hwaes_capable()) {
     (void) GFp_aes_hw_set_encrypt_key(user_key, bits, key);
     return;
   }
+#endif
 
 #if defined(VPAES)
 #if defined(BSAES)
@@ -154,9 +163,11 @@ void GFp_AES_set_encrypt_key(const uint8_t *user_key, unsigned bits,
 
 static aes_block_f aes_block(void) {
   // Keep this in sync with |GFp_AES_set_encrypt_key| and |aes_ctr|.
+#if defined(HWAES)
   if (hwaes_capable()) {
     return GFp_aes_hw_encrypt;
   }
+#endif
 
 #if defined(VPAES)
 #if defined(BSAES)
@@ -177,9 +188,11 @@ void GFp_AES_encrypt(const uint8_t *in, uint8_t *out, const AES_KEY *key) {
 static aes_ctr_f aes_ctr(void) {
   // Keep this in sync with |set_set_key| and |aes_block|.
 
+#if defined(HWAES)
   if (hwaes_capable()) {
     return GFp_aes_hw_ctr32_encrypt_blocks;
   }
+#endif
 
 #if defined(BSAES)
   if (bsaes_capable()) {
@@ -27,6 +27,10 @@
 void GFp_nistz384_point_mul(P384_POINT *r, const BN_ULONG p_scalar[P384_LIMBS],
                             const BN_ULONG p_x[P384_LIMBS],
                             const BN_ULONG p_y[P384_LIMBS]);
+#if defined(GFp_LARGE_EC_TABLES)
+void GFp_nistz384_point_mul_base(P384_POINT *r,
+                                 const BN_ULONG g_scalar[P384_LIMBS]);
+#endif
 
 
 static BN_ULONG is_zero(const BN_ULONG a[P384_LIMBS]) {
@@ -259,3 +263,58 @@ void GFp_nistz384_point_mul(P384_POINT *r, const BN_ULONG p_scalar[P384_LIMBS],
   wvalue = (wvalue << 1) & kMask;
   add_precomputed_w5(r, wvalue, table);
 }
+
+#if defined(GFp_LARGE_EC_TABLES)
+
+#include "ecp_nistz384_table.inl"
+
+typedef P384_POINT_AFFINE PRECOMP384_ROW[16];
+
+static void add_precomputed_affine_w5(P384_POINT *r, unsigned wvalue,
+                                      const PRECOMP384_ROW row) {
+  BN_ULONG recoded_is_negative;
+  unsigned int recoded;
+  booth_recode(&recoded_is_negative, &recoded, wvalue, 5);
+
+  alignas(64) P384_POINT h;
+  gfp_p384_point_select_affine_w5(&h, row, recoded);
+
+  alignas(64) BN_ULONG tmp[P384_LIMBS];
+  GFp_p384_elem_neg(tmp, h.Y);
+  copy_conditional(h.Y, tmp, recoded_is_negative);
+
+  GFp_nistz384_point_add(r, r, &h);
+}
+
+/* Like `GFp_nistz384_point_mul` with the generator, but the multiples of the
+ * generator for every window are precomputed, so no doublings are needed. */
+void GFp_nistz384_point_mul_base(P384_POINT *r,
+                                 const BN_ULONG g_scalar[P384_LIMBS]) {
+  static const unsigned kWindowSize = 5;
+  static const unsigned kMask = (1 << (5 /* kWindowSize */ + 1)) - 1;
+
+  uint8_t p_str[(P384_LIMBS * sizeof(Limb)) + 1];
+  gfp_little_endian_bytes_from_scalar(p_str, sizeof(p_str) / sizeof(p_str[0]),
+                                      g_scalar, P384_LIMBS);
+
+  const PRECOMP384_ROW *const precomputed_table =
+      (const PRECOMP384_ROW *)GFp_nistz384_precomputed;
+
+  /* Start at the point at infinity. */
+  memset(r, 0, sizeof(*r));
+
+  /* First window */
+  unsigned wvalue = (p_str[0] << 1) & kMask;
+  add_precomputed_affine_w5(r, wvalue, precomputed_table[0]);
+
+  unsigned index = kWindowSize;
+  for (size_t i = 1; i < 77; ++i) {
+    unsigned off = (index - 1) / 8;
+    wvalue = p_str[off] | p_str[off + 1] << 8;
+    wvalue = (wvalue >> ((index - 1) % 8)) & kMask;
+    index += kWindowSize;
+    add_precomputed_affine_w5(r, wvalue, precomputed_table[i]);
+  }
+}
+
+#endif
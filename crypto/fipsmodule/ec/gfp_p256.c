@@ -15,6 +15,8 @@
 #include "ecp_nistz256.h"
 #include "../../limbs/limbs.h"
 
+#include <string.h>
+
 #include "../../internal.h"
 #include "../bn/internal.h"
 #include "../../limbs/limbs.inl"
@@ -31,14 +33,14 @@ void GFp_p256_scalar_sqr_mont(ScalarMont r, const ScalarMont a);
 void GFp_p256_scalar_sqr_rep_mont(ScalarMont r, const ScalarMont a, int rep);
 
 
-#if defined(OPENSSL_ARM) || defined(OPENSSL_X86)
+#if defined(OPENSSL_ARM) || defined(OPENSSL_X86) || defined(OPENSSL_NO_ASM)
 void GFp_nistz256_sqr_mont(Elem r, const Elem a) {
   /* XXX: Inefficient. TODO: optimize with dedicated squaring routine. */
   GFp_nistz256_mul_mont(r, a, a);
 }
 #endif
 
-#if !defined(OPENSSL_X86_64)
+#if defined(OPENSSL_NO_ASM) || !defined(OPENSSL_X86_64)
 void GFp_p256_scalar_mul_mont(ScalarMont r, const ScalarMont a,
                               const ScalarMont b) {
   static const BN_ULONG N[] = {
@@ -55,7 +57,7 @@ void GFp_p256_scalar_mul_mont(ScalarMont r, const ScalarMont a,
 }
 #endif
 
-#if defined(OPENSSL_X86_64)
+#if defined(OPENSSL_X86_64) && !defined(OPENSSL_NO_ASM)
 void GFp_p256_scalar_sqr_mont(ScalarMont r, const ScalarMont a) {
   GFp_p256_scalar_sqr_rep_mont(r, a, 1);
 }
@@ -74,9 +76,275 @@ void GFp_p256_scalar_sqr_rep_mont(ScalarMont r, const ScalarMont a, int rep) {
 #endif
 
 
-#if !defined(OPENSSL_X86_64)
+#if defined(OPENSSL_NO_ASM)
 
-#include <string.h>
+/* The field arithmetic and point operations that are otherwise implemented
+ * in assembly language, for targets without it, e.g. wasm32. They are like
+ * the P-384 ones in gfp_p384.c and ecp_nistz384.inl. */
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+void GFp_nistz256_add(Elem r, const Elem a, const Elem b);
+void GFp_nistz256_neg(Elem r, const Elem a);
+void GFp_nistz256_point_double(P256_POINT *r, const P256_POINT *a);
+void GFp_nistz256_point_add(P256_POINT *r, const P256_POINT *a,
+                            const P256_POINT *b);
+void GFp_nistz256_point_add_affine(P256_POINT *r, const P256_POINT *a,
+                                   const P256_POINT_AFFINE *b);
+
+static const BN_ULONG Q[P256_LIMBS] = {
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0x00000000, 0xffffffff),
+  TOBN(0x00000000, 0x00000000),
+  TOBN(0xffffffff, 0x00000001),
+};
+
+/* One converted into the Montgomery domain */
+static const BN_ULONG ONE[P256_LIMBS] = {
+  TOBN(0x00000000, 0x00000001), TOBN(0xffffffff, 0x00000000),
+  TOBN(0xffffffff, 0xffffffff), TOBN(0x00000000, 0xfffffffe),
+};
+
+static Limb is_equal(const Elem a, const Elem b) {
+  return LIMBS_equal(a, b, P256_LIMBS);
+}
+
+static Limb is_zero(const Elem a) {
+  return LIMBS_are_zero(a, P256_LIMBS);
+}
+
+static void copy_conditional(Elem r, const Elem a, const Limb condition) {
+  for (size_t i = 0; i < P256_LIMBS; ++i) {
+    r[i] = constant_time_select_w(condition, a[i], r[i]);
+  }
+}
+
+static void elem_add(Elem r, const Elem a, const Elem b) {
+  LIMBS_add_mod(r, a, b, Q, P256_LIMBS);
+}
+
+static void elem_sub(Elem r, const Elem a, const Elem b) {
+  LIMBS_sub_mod(r, a, b, Q, P256_LIMBS);
+}
+
+/* See |elem_div_by_2| in gfp_p384.c for why no reduction is needed. */
+static void elem_div_by_2(Elem r, const Elem a) {
+  Limb is_odd = constant_time_is_nonzero_w(a[0] & 1);
+
+  /* r = a >> 1. */
+  Limb carry = a[P256_LIMBS - 1] & 1;
+  r[P256_LIMBS - 1] = a[P256_LIMBS - 1] >> 1;
+  for (size_t i = 1; i < P256_LIMBS; ++i) {
+    Limb new_carry = a[P256_LIMBS - i - 1];
+    r[P256_LIMBS - i - 1] =
+        (a[P256_LIMBS - i - 1] >> 1) | (carry << (LIMB_BITS - 1));
+    carry = new_carry;
+  }
+
+  static const Elem Q_PLUS_1_SHR_1 = {
+    TOBN(0x00000000, 0x00000000), TOBN(0x00000000, 0x80000000),
+    TOBN(0x80000000, 0x00000000), TOBN(0x7fffffff, 0x80000000),
+  };
+
+  Elem adjusted;
+  BN_ULONG carry2 = limbs_add(adjusted, r, Q_PLUS_1_SHR_1, P256_LIMBS);
+#if defined(NDEBUG)
+  (void)carry2;
+#endif
+  assert(carry2 == 0);
+
+  copy_conditional(r, adjusted, is_odd);
+}
+
+static void elem_mul_mont(Elem r, const Elem a, const Elem b) {
+  static const BN_ULONG Q_N0[] = {
+    BN_MONT_CTX_N0(0x0, 0x1)
+  };
+  /* XXX: Not (clearly) constant-time; inefficient.*/
+  GFp_bn_mul_mont(r, a, b, Q, Q_N0, P256_LIMBS);
+}
+
+static void elem_mul_by_2(Elem r, const Elem a) {
+  LIMBS_shl_mod(r, a, Q, P256_LIMBS);
+}
+
+static void elem_mul_by_3(Elem r, const Elem a) {
+  /* XXX: inefficient. TODO: Replace with an integrated shift + add. */
+  Elem doubled;
+  elem_add(doubled, a, a);
+  elem_add(r, doubled, a);
+}
+
+static void elem_sqr_mont(Elem r, const Elem a) {
+  /* XXX: Inefficient. TODO: Add a dedicated squaring routine. */
+  elem_mul_mont(r, a, a);
+}
+
+void GFp_nistz256_add(Elem r, const Elem a, const Elem b) {
+  elem_add(r, a, b);
+}
+
+void GFp_nistz256_neg(Elem r, const Elem a) {
+  Limb zero = is_zero(a);
+  Carry borrow = limbs_sub(r, Q, a, P256_LIMBS);
+#if defined(NDEBUG)
+  (void)borrow;
+#endif
+  assert(borrow == 0);
+  for (size_t i = 0; i < P256_LIMBS; ++i) {
+    r[i] = constant_time_select_w(zero, 0, r[i]);
+  }
+}
+
+void GFp_nistz256_mul_mont(Elem r, const Elem a, const Elem b) {
+  elem_mul_mont(r, a, b);
+}
+
+/* Point double: r = 2*a */
+void GFp_nistz256_point_double(P256_POINT *r, const P256_POINT *a) {
+  Elem S;
+  Elem M;
+  Elem Zsqr;
+  Elem tmp0;
+
+  const Limb *in_x = a->X;
+  const Limb *in_y = a->Y;
+  const Limb *in_z = a->Z;
+
+  Limb *res_x = r->X;
+  Limb *res_y = r->Y;
+  Limb *res_z = r->Z;
+
+  elem_mul_by_2(S, in_y);
+
+  elem_sqr_mont(Zsqr, in_z);
+
+  elem_sqr_mont(S, S);
+
+  elem_mul_mont(res_z, in_z, in_y);
+  elem_mul_by_2(res_z, res_z);
+
+  elem_add(M, in_x, Zsqr);
+  elem_sub(Zsqr, in_x, Zsqr);
+
+  elem_sqr_mont(res_y, S);
+  elem_div_by_2(res_y, res_y);
+
+  elem_mul_mont(M, M, Zsqr);
+  elem_mul_by_3(M, M);
+
+  elem_mul_mont(S, S, in_x);
+  elem_mul_by_2(tmp0, S);
+
+  elem_sqr_mont(res_x, M);
+
+  elem_sub(res_x, res_x, tmp0);
+  elem_sub(S, S, res_x);
+
+  elem_mul_mont(S, S, M);
+  elem_sub(res_y, S, res_y);
+}
+
+/* Point addition: r = a+b */
+void GFp_nistz256_point_add(P256_POINT *r, const P256_POINT *a,
+                            const P256_POINT *b) {
+  Elem U2, S2;
+  Elem U1, S1;
+  Elem Z1sqr;
+  Elem Z2sqr;
+  Elem H, R;
+  Elem Hsqr;
+  Elem Rsqr;
+  Elem Hcub;
+
+  Elem res_x;
+  Elem res_y;
+  Elem res_z;
+
+  const Limb *in1_x = a->X;
+  const Limb *in1_y = a->Y;
+  const Limb *in1_z = a->Z;
+
+  const Limb *in2_x = b->X;
+  const Limb *in2_y = b->Y;
+  const Limb *in2_z = b->Z;
+
+  Limb in1infty = is_zero(a->Z);
+  Limb in2infty = is_zero(b->Z);
+
+  elem_sqr_mont(Z2sqr, in2_z); /* Z2^2 */
+  elem_sqr_mont(Z1sqr, in1_z); /* Z1^2 */
+
+  elem_mul_mont(S1, Z2sqr, in2_z); /* S1 = Z2^3 */
+  elem_mul_mont(S2, Z1sqr, in1_z); /* S2 = Z1^3 */
+
+  elem_mul_mont(S1, S1, in1_y); /* S1 = Y1*Z2^3 */
+  elem_mul_mont(S2, S2, in2_y); /* S2 = Y2*Z1^3 */
+  elem_sub(R, S2, S1);          /* R = S2 - S1 */
+
+  elem_mul_mont(U1, in1_x, Z2sqr); /* U1 = X1*Z2^2 */
+  elem_mul_mont(U2, in2_x, Z1sqr); /* U2 = X2*Z1^2 */
+  elem_sub(H, U2, U1);             /* H = U2 - U1 */
+
+  /* This should not happen during sign/ecdh,
+   * so no constant time violation */
+  if (is_equal(U1, U2) && !in1infty && !in2infty) {
+    if (is_equal(S1, S2)) {
+      GFp_nistz256_point_double(r, a);
+    } else {
+      memset(r, 0, sizeof(*r));
+    }
+    return;
+  }
+
+  elem_sqr_mont(Rsqr, R);             /* R^2 */
+  elem_mul_mont(res_z, H, in1_z);     /* Z3 = H*Z1*Z2 */
+  elem_sqr_mont(Hsqr, H);             /* H^2 */
+  elem_mul_mont(res_z, res_z, in2_z); /* Z3 = H*Z1*Z2 */
+  elem_mul_mont(Hcub, Hsqr, H);       /* H^3 */
+
+  elem_mul_mont(U2, U1, Hsqr); /* U1*H^2 */
+  elem_mul_by_2(Hsqr, U2);     /* 2*U1*H^2 */
+
+  elem_sub(res_x, Rsqr, Hsqr);
+  elem_sub(res_x, res_x, Hcub);
+
+  elem_sub(res_y, U2, res_x);
+
+  elem_mul_mont(S2, S1, Hcub);
+  elem_mul_mont(res_y, R, res_y);
+  elem_sub(res_y, res_y, S2);
+
+  copy_conditional(res_x, in2_x, in1infty);
+  copy_conditional(res_y, in2_y, in1infty);
+  copy_conditional(res_z, in2_z, in1infty);
+
+  copy_conditional(res_x, in1_x, in2infty);
+  copy_conditional(res_y, in1_y, in2infty);
+  copy_conditional(res_z, in1_z, in2infty);
+
+  limbs_copy(r->X, res_x, P256_LIMBS);
+  limbs_copy(r->Y, res_y, P256_LIMBS);
+  limbs_copy(r->Z, res_z, P256_LIMBS);
+}
+
+/* Point addition when |b| is affine: r = a+b. Like the assembly language
+ * implementations, (0, 0) is taken to be the point at infinity. */
+void GFp_nistz256_point_add_affine(P256_POINT *r, const P256_POINT *a,
+                                   const P256_POINT_AFFINE *b) {
+  P256_POINT b_jacobian;
+  limbs_copy(b_jacobian.X, b->X, P256_LIMBS);
+  limbs_copy(b_jacobian.Y, b->Y, P256_LIMBS);
+  Limb b_is_infinity = is_zero(b->X) & is_zero(b->Y);
+  for (size_t i = 0; i < P256_LIMBS; ++i) {
+    b_jacobian.Z[i] = constant_time_select_w(b_is_infinity, 0, ONE[i]);
+  }
+  GFp_nistz256_point_add(r, a, &b_jacobian);
+}
+
+#endif
+
+
+#if defined(OPENSSL_NO_ASM) || !defined(OPENSSL_X86_64)
 
 /* TODO(perf): Optimize these. */
 
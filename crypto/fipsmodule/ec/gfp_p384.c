@@ -252,5 +252,29 @@ static void gfp_p384_point_select_w5(P384_POINT *out,
   limbs_copy(out->Z, z, P384_LIMBS);
 }
 
+#if defined(GFp_LARGE_EC_TABLES)
+/* Like `gfp_p384_point_select_w5`, but for a table of affine points. The
+ * result is the point at infinity, with Z == 0, when |index| is zero. */
+static void gfp_p384_point_select_affine_w5(
+    P384_POINT *out, const P384_POINT_AFFINE table[16], size_t index) {
+  Elem x; memset(x, 0, sizeof(x));
+  Elem y; memset(y, 0, sizeof(y));
+  Elem z; memset(z, 0, sizeof(z));
+
+  for (size_t i = 0; i < 16; ++i) {
+    Limb mask = constant_time_eq_w(index, i + 1);
+    for (size_t j = 0; j < P384_LIMBS; ++j) {
+      x[j] |= table[i].X[j] & mask;
+      y[j] |= table[i].Y[j] & mask;
+      z[j] |= ONE[j] & mask;
+    }
+  }
+
+  limbs_copy(out->X, x, P384_LIMBS);
+  limbs_copy(out->Y, y, P384_LIMBS);
+  limbs_copy(out->Z, z, P384_LIMBS);
+}
+#endif
+
 
 #include "ecp_nistz384.inl"
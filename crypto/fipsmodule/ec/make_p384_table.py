@@ -0,0 +1,125 @@
+#!/usr/bin/env python3
+# Copyright 2019 Brian Smith.
+#
+# Permission to use, copy, modify, and/or distribute this software for any
+# purpose with or without fee is hereby granted, provided that the above
+# copyright notice and this permission notice appear in all copies.
+#
+# THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+# WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+# MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+# SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+# WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+# OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+# CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+# Generates ecp_nistz384_table.inl:
+#
+#   python3 make_p384_table.py > ecp_nistz384_table.inl
+
+import sys
+
+p = 2**384 - 2**128 - 2**96 + 2**32 - 1
+a = p - 3
+g = (0xaa87ca22be8b05378eb1c71ef320ad746e1d3b628ba79b9859f741e082542a385502f25dbf55296c3a545e3872760ab7,
+     0x3617de4a96262c6f5d9e98bf9292dc29f8f41dbd289a147ce9da3113b5f0b8c00a60b1ce1d7e819d7a431d7c90ea0e5f)
+
+R = 2**384
+
+# Keep these in sync with `HEADER` and `GFp_nistz384_point_mul_base`.
+WINDOW_BITS = 5
+ROWS = 77
+MULTIPLES = 16
+
+HEADER = """/* Copyright 2019 Brian Smith.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+// This is the precomputed constant time access table for
+// `GFp_nistz384_point_mul_base`, for the default generator. The table consists
+// of 77 subtables, each subtable contains 16 affine points. The affine points
+// are encoded as twelve uint64's, six for the x coordinate and six for the y,
+// in the Montgomery domain. Both values are in little-endian order. There are
+// 77 tables because a signed, 5-bit recoding of the scalar is used and
+// ceil((384 + 1)/5) = 77. Within each table there are 16 values because the
+// 5-bit recoding can take 16 nonzero values, ignoring the sign, which is
+// implemented by negating the affine point when required.
+
+// This file is generated by make_p384_table.py.
+
+static const alignas(64) BN_ULONG
+    GFp_nistz384_precomputed[77][16 * sizeof(P384_POINT_AFFINE) /
+                                 sizeof(BN_ULONG)] = {
+"""
+
+
+def modp_inv(x):
+    return pow(x, p - 2, p)
+
+
+# Points are represented as affine tuples (x, y), with None as the point at
+# infinity.
+
+def point_add(P, Q):
+    if P is None:
+        return Q
+    if Q is None:
+        return P
+    x1, y1 = P
+    x2, y2 = Q
+    if x1 == x2:
+        if (y1 + y2) % p == 0:
+            return None
+        l = (3 * x1 * x1 + a) * modp_inv(2 * y1) % p
+    else:
+        l = (y2 - y1) * modp_inv(x2 - x1) % p
+    x3 = (l * l - x1 - x2) % p
+    y3 = (l * (x1 - x3) - y1) % p
+    return (x3, y3)
+
+
+def point_double(P):
+    return point_add(P, P)
+
+
+def limbs(n):
+    n = n * R % p
+    return ["TOBN(0x%08x, 0x%08x)" % ((n >> (64 * i + 32)) & 0xffffffff,
+                                      (n >> (64 * i)) & 0xffffffff)
+            for i in range(6)]
+
+
+def main():
+    out = sys.stdout
+    out.write(HEADER)
+
+    base = g
+    rows = []
+    for _ in range(ROWS):
+        # For each window, encode the multiples 1 through `MULTIPLES` of
+        # 2**(WINDOW_BITS * window) * G.
+        multiple = None
+        nums = []
+        for _ in range(MULTIPLES):
+            multiple = point_add(multiple, base)
+            nums += limbs(multiple[0]) + limbs(multiple[1])
+        lines = [", ".join(nums[i:i + 2]) for i in range(0, len(nums), 2)]
+        rows.append("        {" + ",\n         ".join(lines) + "}")
+        for _ in range(WINDOW_BITS):
+            base = point_double(base)
+    out.write(",\n".join(rows))
+    out.write("};\n")
+
+
+if __name__ == "__main__":
+    main()
@@ -153,6 +153,17 @@
 void GFp_cpuid_setup(void);
 #endif
 
+#if defined(OPENSSL_X86) || defined(OPENSSL_X86_64)
+// GFp_cpuid_fn has the signature of |OPENSSL_cpuid| in cpu-intel.c.
+typedef void (*GFp_cpuid_fn)(uint32_t *out_eax, uint32_t *out_ebx,
+                             uint32_t *out_ecx, uint32_t *out_edx,
+                             uint32_t leaf);
+
+// GFp_cpuid_setup_with is |GFp_cpuid_setup| using |cpuid| instead of the
+// CPUID instruction, which can't be used within SGX enclaves.
+void GFp_cpuid_setup_with(GFp_cpuid_fn cpuid);
+#endif
+
 #define OPENSSL_LITTLE_ENDIAN 1
 #define OPENSSL_BIG_ENDIAN 2
 
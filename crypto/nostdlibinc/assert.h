@@ -0,0 +1,31 @@
+/* Copyright 2019 Brian Smith.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+/* The headers in this directory replace the few parts of the C standard
+ * library that *ring*'s C code uses, for targets that have no C standard
+ * library, e.g. wasm32-unknown-unknown and the UEFI targets. build.rs puts
+ * this directory on the include path, after passing |-nostdlibinc|, only for
+ * those targets. The compiler's own freestanding headers, e.g. <stddef.h> and
+ * <stdint.h>, are still used. */
+
+/* Like the standard <assert.h>, this has no include guard so that |assert|
+ * follows the definition of |NDEBUG| at each inclusion. */
+
+#undef assert
+
+#if defined(NDEBUG)
+#define assert(x) ((void)0)
+#else
+#define assert(x) ((x) ? (void)0 : __builtin_trap())
+#endif
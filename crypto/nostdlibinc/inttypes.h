@@ -0,0 +1,24 @@
+/* Copyright 2019 Brian Smith.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+/* See assert.h in this directory. crypto/cpu-intel.c includes <inttypes.h>,
+ * but only uses the fixed-width integer types from it, which <stdint.h>
+ * declares. */
+
+#ifndef RING_NOSTDLIBINC_INTTYPES_H
+#define RING_NOSTDLIBINC_INTTYPES_H
+
+#include <stdint.h>
+
+#endif  /* RING_NOSTDLIBINC_INTTYPES_H */
@@ -0,0 +1,33 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ring::signature;
+
+// The first byte selects the algorithm.
+static ALGORITHMS: &[&signature::ECDSAVerification] = &[
+    &signature::ECDSA_P256_SHA256_ASN1,
+    &signature::ECDSA_P256_SHA256_FIXED,
+    &signature::ECDSA_P384_SHA384_ASN1,
+    &signature::ECDSA_P384_SHA384_FIXED,
+];
+
+fuzz_target!(|data: &[u8]| {
+    if let Some((selector, signature)) = data.split_first() {
+        let alg = ALGORITHMS[usize::from(*selector) % ALGORITHMS.len()];
+        let _ = ring::fuzz::ecdsa_signature(alg, signature);
+    }
+});
@@ -0,0 +1,31 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ring::signature;
+
+static ALGORITHMS: &[&signature::SigningAlgorithm] = &[
+    &signature::ECDSA_P256_SHA256_ASN1_SIGNING,
+    &signature::ECDSA_P384_SHA384_ASN1_SIGNING,
+];
+
+fuzz_target!(|data: &[u8]| {
+    for alg in ALGORITHMS {
+        let _ = ring::fuzz::key_pair_from_pkcs8(*alg, data);
+    }
+    let _ = ring::fuzz::ed25519_key_pair_from_pkcs8(data);
+    let _ = ring::fuzz::rsa_key_pair_from_der(data);
+});
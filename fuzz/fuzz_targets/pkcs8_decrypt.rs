@@ -0,0 +1,27 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The first byte is the length of the password, which precedes the encrypted
+// document.
+fuzz_target!(|data: &[u8]| {
+    if let Some((password_len, rest)) = data.split_first() {
+        let password_len = usize::from(*password_len).min(rest.len());
+        let (password, encrypted) = rest.split_at(password_len);
+        let _ = ring::fuzz::decrypt_pkcs8(encrypted, password);
+    }
+});
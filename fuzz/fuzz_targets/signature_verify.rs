@@ -0,0 +1,42 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ring::{fuzz::UnparsedPublicKey, signature};
+
+// The first byte selects the algorithm, the next two are the lengths of the
+// public key and the signature, and the rest is the public key, the
+// signature, and the message.
+static ALGORITHMS: &[&signature::VerificationAlgorithm] = &[
+    &signature::ECDSA_P256_SHA256_ASN1,
+    &signature::ECDSA_P256_SHA256_FIXED,
+    &signature::ECDSA_P384_SHA384_ASN1,
+    &signature::ECDSA_P384_SHA384_FIXED,
+    &signature::ED25519,
+    &signature::RSA_PKCS1_2048_8192_SHA256,
+    &signature::RSA_PSS_2048_8192_SHA256,
+];
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 3 {
+        return;
+    }
+    let alg = ALGORITHMS[usize::from(data[0]) % ALGORITHMS.len()];
+    let (lengths, rest) = data[1..].split_at(2);
+    let (public_key, rest) = rest.split_at(usize::from(lengths[0]).min(rest.len()));
+    let (signature, msg) = rest.split_at(usize::from(lengths[1]).min(rest.len()));
+    let _ = UnparsedPublicKey::new(alg, public_key).verify(msg, signature);
+});
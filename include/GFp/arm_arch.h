@@ -98,6 +98,14 @@
 # endif
 #endif
 
+// M-profile CPUs, e.g. the Cortex-M4 and Cortex-M33, have neither NEON nor the
+// ARMv8 crypto instructions, and can't execute ARM (A32) code, so only the
+// Thumb-2 integer implementations are included for them.
+#if !defined(__ARM_MAX_ARCH__) && defined(__ARM_ARCH_PROFILE) && \
+    __ARM_ARCH_PROFILE == 'M'
+#define __ARM_MAX_ARCH__ 6
+#endif
+
 // Even when building for 32-bit ARM, support for aarch64 crypto instructions
 // will be included.
 #if !defined(__ARM_MAX_ARCH__)
@@ -94,6 +94,9 @@
 #define OPENSSL_PNACL
 #elif defined(__myriad2__)
 #define OPENSSL_32_BIT
+#elif defined(__wasm32__)
+#define OPENSSL_32_BIT
+#define OPENSSL_WASM32
 #else
 // Note BoringSSL only supports standard 32-bit and 64-bit two's-complement,
 // little-endian architectures. Functions will not produce the correct answer
@@ -106,7 +109,9 @@
 #define OPENSSL_APPLE
 #endif
 
-#if defined(_WIN32)
+// Compilers treat UEFI targets as Windows targets, but there is no Windows
+// API in UEFI. build.rs defines OPENSSL_UEFI for them.
+#if defined(_WIN32) && !defined(OPENSSL_UEFI)
 #define OPENSSL_WINDOWS
 #endif
 
@@ -138,6 +143,11 @@
 #endif
 #endif
 
+// There is no assembly language code for WebAssembly.
+#if defined(OPENSSL_WASM32)
+#define OPENSSL_ASM_INCOMPATIBLE
+#endif
+
 #if defined(OPENSSL_ASM_INCOMPATIBLE)
 #undef OPENSSL_ASM_INCOMPATIBLE
 #if !defined(OPENSSL_NO_ASM)
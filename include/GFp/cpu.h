@@ -96,6 +96,12 @@ extern uint32_t GFp_ia32cap_P[4];
 #define OPENSSL_STATIC_ARMCAP
 #endif
 
+#if defined(__ARM_ARCH_PROFILE) && __ARM_ARCH_PROFILE == 'M'
+// M-profile CPUs have none of the optional features, so there's nothing to
+// detect.
+#define OPENSSL_STATIC_ARMCAP
+#endif
+
 #if !defined(OPENSSL_STATIC_ARMCAP)
 
 // GFp_is_NEON_capable_at_runtime returns true if the current CPU has a NEON
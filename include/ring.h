@@ -0,0 +1,172 @@
+/* Copyright 2019 Brian Smith.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+/* The C API of *ring*, which is enabled by its `capi` feature. See the
+ * documentation of `ring::capi` for how to build the library.
+ *
+ * Functions that return |int| return one on success and zero on failure.
+ * Outputs are written to |out|, which has room for |max_out_len| bytes, and
+ * their length is written to |*out_len|. Pointers may be NULL where the
+ * corresponding length is zero. Every |*_free| function accepts NULL.
+ *
+ * Keys and contexts may be used from several threads at once, except that a
+ * |RING_DIGEST_CTX| must not be updated by two threads at once. */
+
+#ifndef RING_HEADER_RING_H
+#define RING_HEADER_RING_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#if defined(__cplusplus)
+extern "C" {
+#endif
+
+
+/* Digests. */
+
+typedef struct RING_DIGEST_ALGORITHM RING_DIGEST_ALGORITHM;
+typedef struct RING_DIGEST_CTX RING_DIGEST_CTX;
+
+/* SHA-1 is only for legacy protocols. */
+const RING_DIGEST_ALGORITHM *RING_digest_sha1(void);
+const RING_DIGEST_ALGORITHM *RING_digest_sha256(void);
+const RING_DIGEST_ALGORITHM *RING_digest_sha384(void);
+const RING_DIGEST_ALGORITHM *RING_digest_sha512(void);
+const RING_DIGEST_ALGORITHM *RING_digest_sha512_256(void);
+
+/* RING_digest_output_len returns the length of |alg|'s digests. */
+size_t RING_digest_output_len(const RING_DIGEST_ALGORITHM *alg);
+
+/* RING_digest computes the digest of |in| with |alg|. */
+int RING_digest(const RING_DIGEST_ALGORITHM *alg, uint8_t *out,
+                size_t *out_len, size_t max_out_len, const uint8_t *in,
+                size_t in_len);
+
+/* RING_digest_ctx_new returns a context for a multi-step digest with |alg|,
+ * or NULL on failure. */
+RING_DIGEST_CTX *RING_digest_ctx_new(const RING_DIGEST_ALGORITHM *alg);
+
+/* RING_digest_ctx_update adds |in| to the data being digested. */
+int RING_digest_ctx_update(RING_DIGEST_CTX *ctx, const uint8_t *in,
+                           size_t in_len);
+
+/* RING_digest_ctx_final writes the digest of the data that was added to |ctx|
+ * and frees |ctx|, even on failure. */
+int RING_digest_ctx_final(RING_DIGEST_CTX *ctx, uint8_t *out, size_t *out_len,
+                          size_t max_out_len);
+
+/* RING_digest_ctx_free frees |ctx| without finishing the digest. */
+void RING_digest_ctx_free(RING_DIGEST_CTX *ctx);
+
+
+/* HMAC. */
+
+typedef struct RING_HMAC_KEY RING_HMAC_KEY;
+
+/* RING_hmac_key_new returns an HMAC key that uses |alg|, or NULL on
+ * failure. */
+RING_HMAC_KEY *RING_hmac_key_new(const RING_DIGEST_ALGORITHM *alg,
+                                 const uint8_t *key, size_t key_len);
+
+void RING_hmac_key_free(RING_HMAC_KEY *key);
+
+/* RING_hmac_sign computes the HMAC of |in| with |key|. */
+int RING_hmac_sign(const RING_HMAC_KEY *key, uint8_t *out, size_t *out_len,
+                   size_t max_out_len, const uint8_t *in, size_t in_len);
+
+/* RING_hmac_verify checks, in constant time, that |tag| is the HMAC of |in|
+ * with |key|. */
+int RING_hmac_verify(const RING_HMAC_KEY *key, const uint8_t *in,
+                     size_t in_len, const uint8_t *tag, size_t tag_len);
+
+
+/* AEADs. */
+
+typedef struct RING_AEAD_ALGORITHM RING_AEAD_ALGORITHM;
+typedef struct RING_AEAD_CTX RING_AEAD_CTX;
+
+const RING_AEAD_ALGORITHM *RING_aead_aes_128_gcm(void);
+const RING_AEAD_ALGORITHM *RING_aead_aes_256_gcm(void);
+const RING_AEAD_ALGORITHM *RING_aead_chacha20_poly1305(void);
+
+size_t RING_aead_key_len(const RING_AEAD_ALGORITHM *alg);
+size_t RING_aead_nonce_len(const RING_AEAD_ALGORITHM *alg);
+size_t RING_aead_tag_len(const RING_AEAD_ALGORITHM *alg);
+
+/* RING_aead_ctx_new returns a context for sealing and opening with |alg| and
+ * |key|, or NULL if the key has the wrong length. */
+RING_AEAD_CTX *RING_aead_ctx_new(const RING_AEAD_ALGORITHM *alg,
+                                 const uint8_t *key, size_t key_len);
+
+void RING_aead_ctx_free(RING_AEAD_CTX *ctx);
+
+/* RING_aead_seal encrypts and authenticates |in| and authenticates |ad|,
+ * writing the ciphertext followed by the tag to |out|, which must have room
+ * for |in_len| plus the tag length. |in| and |out| may be equal but must not
+ * otherwise overlap. The nonce must never be reused with the same key. */
+int RING_aead_seal(const RING_AEAD_CTX *ctx, uint8_t *out, size_t *out_len,
+                   size_t max_out_len, const uint8_t *nonce, size_t nonce_len,
+                   const uint8_t *in, size_t in_len, const uint8_t *ad,
+                   size_t ad_len);
+
+/* RING_aead_open authenticates |in|, which is the ciphertext followed by the
+ * tag, and |ad|, and decrypts the ciphertext to |out|, which must have room
+ * for |in_len| minus the tag length. On failure, |out| may have been
+ * overwritten with zeros. |in| and |out| may be equal but must not otherwise
+ * overlap. */
+int RING_aead_open(const RING_AEAD_CTX *ctx, uint8_t *out, size_t *out_len,
+                   size_t max_out_len, const uint8_t *nonce, size_t nonce_len,
+                   const uint8_t *in, size_t in_len, const uint8_t *ad,
+                   size_t ad_len);
+
+
+/* Ed25519. */
+
+typedef struct RING_ED25519_KEY_PAIR RING_ED25519_KEY_PAIR;
+
+/* RING_ed25519_key_pair_from_seed returns the key pair for the 32-byte
+ * |seed|, or NULL on failure. */
+RING_ED25519_KEY_PAIR *RING_ed25519_key_pair_from_seed(const uint8_t *seed,
+                                                       size_t seed_len);
+
+/* RING_ed25519_key_pair_from_pkcs8 returns the key pair in the v2 PKCS#8
+ * document |pkcs8|, or NULL if it is invalid. */
+RING_ED25519_KEY_PAIR *RING_ed25519_key_pair_from_pkcs8(const uint8_t *pkcs8,
+                                                        size_t pkcs8_len);
+
+void RING_ed25519_key_pair_free(RING_ED25519_KEY_PAIR *key_pair);
+
+/* RING_ed25519_public_key writes the 32-byte public key of |key_pair|. */
+int RING_ed25519_public_key(const RING_ED25519_KEY_PAIR *key_pair,
+                            uint8_t *out, size_t *out_len,
+                            size_t max_out_len);
+
+/* RING_ed25519_sign writes the 64-byte signature of |msg| by |key_pair|. */
+int RING_ed25519_sign(const RING_ED25519_KEY_PAIR *key_pair, uint8_t *out,
+                      size_t *out_len, size_t max_out_len, const uint8_t *msg,
+                      size_t msg_len);
+
+/* RING_ed25519_verify checks that |sig| is a valid signature of |msg| by the
+ * 32-byte |public_key|. */
+int RING_ed25519_verify(const uint8_t *public_key, size_t public_key_len,
+                        const uint8_t *msg, size_t msg_len, const uint8_t *sig,
+                        size_t sig_len);
+
+
+#if defined(__cplusplus)
+}  /* extern "C" */
+#endif
+
+#endif  /* RING_HEADER_RING_H */
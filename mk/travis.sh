@@ -79,6 +79,13 @@ else
   target_dir=target/$TARGET_X/debug
 fi
 
+# The Wycheproof test vectors aren't distributed with ring, but
+# tests/wycheproof_tests.rs requires them.
+if [[ ! -d third_party/wycheproof ]]; then
+  git clone --depth 1 https://github.com/google/wycheproof third_party/wycheproof
+  git -C third_party/wycheproof log -1 --format=%H
+fi
+
 case $TARGET_X in
 armv7-linux-androideabi)
   cargo test -vv -j2 --no-run ${mode-} ${FEATURES_X-} --target=$TARGET_X
@@ -88,6 +95,14 @@ armv7-linux-androideabi)
   ;;
 *)
   cargo test -vv -j2 ${mode-} ${FEATURES_X-} --target=$TARGET_X
+
+  # The RustCrypto trait implementations are optional, so they aren't covered
+  # by the feature sets above.
+  cargo test -j2 ${mode-} --features=rustcrypto --target=$TARGET_X \
+    --test rustcrypto_tests
+
+  # Likewise for `SystemRandom` using the getrandom crate.
+  cargo test -j2 ${mode-} --features=getrandom --target=$TARGET_X --lib rand::
   ;;
 esac
 
@@ -121,4 +136,8 @@ fi
 # code builds.
 cargo build -vv -j2 ${mode-} ${FEATURES_X-} --target=$TARGET_X
 
+# Verify that the heap-using functionality builds without `std`.
+cargo build -j2 ${mode-} --no-default-features --features="alloc rsa_signing" \
+  --target=$TARGET_X
+
 echo end of mk/travis.sh
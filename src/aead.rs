@@ -31,6 +31,9 @@ use crate::{
     polyfill::{self, convert::*},
 };
 
+#[cfg(all(feature = "alloc", feature = "zeroize"))]
+use alloc::vec::Vec;
+
 pub use self::{
     aes_gcm::{AES_128_GCM, AES_256_GCM},
     chacha20_poly1305::CHACHA20_POLY1305,
@@ -41,10 +44,16 @@ pub use self::{
 /// C analog: `EVP_AEAD_CTX` with direction `evp_aead_open`
 ///
 /// Go analog: [`crypto.cipher.AEAD`]
+///
+/// A single `OpeningKey` may be shared across multiple threads safely.
 pub struct OpeningKey {
     key: Key,
 }
 
+// Each `Key` zeroizes its key schedule when it is dropped.
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for OpeningKey {}
+
 impl OpeningKey {
     /// Create a new opening key.
     ///
@@ -65,6 +74,28 @@ impl OpeningKey {
         })
     }
 
+    /// Like `OpeningKey::new()`, but takes ownership of `key_bytes` so that they
+    /// are overwritten when they are no longer needed.
+    #[cfg(all(feature = "alloc", feature = "zeroize"))]
+    #[inline]
+    pub fn from_zeroizing(
+        algorithm: &'static Algorithm, key_bytes: ::zeroize::Zeroizing<Vec<u8>>,
+    ) -> Result<OpeningKey, error::Unspecified> {
+        OpeningKey::new(algorithm, &key_bytes)
+    }
+
+    /// Like `OpeningKey::new(&CHACHA20_POLY1305, key_bytes)`, but usable in
+    /// `const` and `static` initializers, e.g. to keep a key in flash.
+    ///
+    /// There is no such constructor for AES-GCM keys because the AES key
+    /// schedule depends on the CPU features, which are detected at run time.
+    #[inline]
+    pub const fn new_chacha20_poly1305(key_bytes: &[u8; chacha::KEY_LEN]) -> OpeningKey {
+        OpeningKey {
+            key: Key::new_chacha20_poly1305(key_bytes),
+        }
+    }
+
     /// The key's AEAD algorithm.
     ///
     /// C analog: `EVP_AEAD_CTX.aead`
@@ -124,19 +155,45 @@ pub fn open_in_place<'a>(
     key: &OpeningKey, nonce: &[u8], ad: &[u8], in_prefix_len: usize,
     ciphertext_and_tag_modified_in_place: &'a mut [u8],
 ) -> Result<&'a mut [u8], error::Unspecified> {
-    let nonce: &[u8; NONCE_LEN] = nonce.try_into_()?;
-    let ciphertext_and_tag_len = ciphertext_and_tag_modified_in_place
-        .len()
+    Ok(open_in_place_detailed(
+        key,
+        nonce,
+        ad,
+        in_prefix_len,
+        ciphertext_and_tag_modified_in_place,
+    )?)
+}
+
+/// Like `open_in_place()`, but a failure is reported as an `error::Detailed`
+/// that says, e.g., which input has the wrong length.
+pub fn open_in_place_detailed<'a>(
+    key: &OpeningKey, nonce: &[u8], ad: &[u8], in_prefix_len: usize,
+    ciphertext_and_tag_modified_in_place: &'a mut [u8],
+) -> Result<&'a mut [u8], error::Detailed> {
+    const OPERATION: &str = "aead::open_in_place";
+    cpu::cache_detected_features();
+    let nonce = check_nonce(OPERATION, nonce)?;
+    let in_out_len = ciphertext_and_tag_modified_in_place.len();
+    let ciphertext_len = in_out_len
         .checked_sub(in_prefix_len)
-        .ok_or(error::Unspecified)?;
-    let ciphertext_len = ciphertext_and_tag_len
-        .checked_sub(TAG_LEN)
-        .ok_or(error::Unspecified)?;
-    check_per_nonce_max_bytes(key.key.algorithm, ciphertext_len)?;
+        .and_then(|ciphertext_and_tag_len| ciphertext_and_tag_len.checked_sub(TAG_LEN))
+        .ok_or_else(|| {
+            error::Detailed::new(
+                OPERATION,
+                error::Reason::TooShort {
+                    input: "ciphertext_and_tag_modified_in_place",
+                    min: in_prefix_len.saturating_add(TAG_LEN),
+                    actual: in_out_len,
+                },
+            )
+        })?;
+    check_per_nonce_max_bytes(OPERATION, key.key.algorithm, "ciphertext", ciphertext_len)?;
+    let unspecified = |_| error::Detailed::new(OPERATION, error::Reason::Unspecified);
     let (in_out, received_tag) =
         ciphertext_and_tag_modified_in_place.split_at_mut(in_prefix_len + ciphertext_len);
     let Tag(calculated_tag) =
-        (key.key.algorithm.open)(&key.key.inner, nonce, &ad, in_prefix_len, in_out)?;
+        (key.key.algorithm.open)(&key.key.inner, nonce, &ad, in_prefix_len, in_out)
+            .map_err(unspecified)?;
     if constant_time::verify_slices_are_equal(calculated_tag.as_ref(), received_tag).is_err() {
         // Zero out the plaintext so that it isn't accidentally leaked or used
         // after verification fails. It would be safest if we could check the
@@ -145,21 +202,46 @@ pub fn open_in_place<'a>(
         for b in &mut in_out[..ciphertext_len] {
             *b = 0;
         }
-        return Err(error::Unspecified);
+        return Err(unspecified(error::Unspecified));
     }
     // `ciphertext_len` is also the plaintext length.
     Ok(&mut in_out[..ciphertext_len])
 }
 
+// Like `open_in_place()` without a prefix, but the tag is separate from the
+// ciphertext.
+#[cfg(any(feature = "capi", feature = "rustcrypto"))]
+pub(crate) fn open_in_place_separate_tag(
+    key: &OpeningKey, nonce: &[u8], ad: &[u8], in_out: &mut [u8], received_tag: &[u8],
+) -> Result<(), error::Unspecified> {
+    const OPERATION: &str = "aead::open_in_place";
+    cpu::cache_detected_features();
+    let nonce = check_nonce(OPERATION, nonce)?;
+    check_per_nonce_max_bytes(OPERATION, key.key.algorithm, "ciphertext", in_out.len())?;
+    let Tag(calculated_tag) = (key.key.algorithm.open)(&key.key.inner, nonce, ad, 0, in_out)?;
+    if constant_time::verify_slices_are_equal(calculated_tag.as_ref(), received_tag).is_err() {
+        for b in in_out.iter_mut() {
+            *b = 0;
+        }
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
 /// A key for encrypting and signing (“sealing”) data.
 ///
 /// C analog: `EVP_AEAD_CTX` with direction `evp_aead_seal`.
 ///
 /// Go analog: [`AEAD`](https://golang.org/pkg/crypto/cipher/#AEAD)
+///
+/// A single `SealingKey` may be shared across multiple threads safely.
 pub struct SealingKey {
     key: Key,
 }
 
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SealingKey {}
+
 impl SealingKey {
     /// C analogs: `EVP_AEAD_CTX_init_with_direction` with direction
     ///            `evp_aead_seal`, `EVP_AEAD_CTX_init`.
@@ -176,6 +258,26 @@ impl SealingKey {
         })
     }
 
+    /// Like `SealingKey::new()`, but takes ownership of `key_bytes` so that they
+    /// are overwritten when they are no longer needed.
+    #[cfg(all(feature = "alloc", feature = "zeroize"))]
+    #[inline]
+    pub fn from_zeroizing(
+        algorithm: &'static Algorithm, key_bytes: ::zeroize::Zeroizing<Vec<u8>>,
+    ) -> Result<SealingKey, error::Unspecified> {
+        SealingKey::new(algorithm, &key_bytes)
+    }
+
+    /// Like `SealingKey::new(&CHACHA20_POLY1305, key_bytes)`, but usable in
+    /// `const` and `static` initializers. See
+    /// `OpeningKey::new_chacha20_poly1305()`.
+    #[inline]
+    pub const fn new_chacha20_poly1305(key_bytes: &[u8; chacha::KEY_LEN]) -> SealingKey {
+        SealingKey {
+            key: Key::new_chacha20_poly1305(key_bytes),
+        }
+    }
+
     /// The key's AEAD algorithm.
     ///
     /// C analog: `EVP_AEAD_CTX.aead`
@@ -207,24 +309,73 @@ impl SealingKey {
 pub fn seal_in_place(
     key: &SealingKey, nonce: &[u8], ad: &[u8], in_out: &mut [u8], out_suffix_capacity: usize,
 ) -> Result<usize, error::Unspecified> {
+    Ok(seal_in_place_detailed(key, nonce, ad, in_out, out_suffix_capacity)?)
+}
+
+/// Like `seal_in_place()`, but a failure is reported as an `error::Detailed`
+/// that says, e.g., which input has the wrong length.
+pub fn seal_in_place_detailed(
+    key: &SealingKey, nonce: &[u8], ad: &[u8], in_out: &mut [u8], out_suffix_capacity: usize,
+) -> Result<usize, error::Detailed> {
+    const OPERATION: &str = "aead::seal_in_place";
+    cpu::cache_detected_features();
     if out_suffix_capacity < key.key.algorithm.tag_len() {
-        return Err(error::Unspecified);
+        return Err(error::Detailed::new(
+            OPERATION,
+            error::Reason::TooShort {
+                input: "out_suffix_capacity",
+                min: key.key.algorithm.tag_len(),
+                actual: out_suffix_capacity,
+            },
+        ));
     }
-    let nonce: &[u8; NONCE_LEN] = nonce.try_into_()?;
-    let in_out_len = in_out
-        .len()
-        .checked_sub(out_suffix_capacity)
-        .ok_or(error::Unspecified)?;
-    check_per_nonce_max_bytes(key.key.algorithm, in_out_len)?;
+    let nonce = check_nonce(OPERATION, nonce)?;
+    let in_out_len = in_out.len().checked_sub(out_suffix_capacity).ok_or_else(|| {
+        error::Detailed::new(
+            OPERATION,
+            error::Reason::TooShort {
+                input: "in_out",
+                min: out_suffix_capacity,
+                actual: in_out.len(),
+            },
+        )
+    })?;
+    check_per_nonce_max_bytes(OPERATION, key.key.algorithm, "plaintext", in_out_len)?;
     let (in_out, tag_out) = in_out.split_at_mut(in_out_len);
 
-    let tag_out: &mut [u8; TAG_LEN] = tag_out.try_into_()?;
-    let Tag(tag) = (key.key.algorithm.seal)(&key.key.inner, nonce, ad, in_out)?;
+    let tag_out: &mut [u8; TAG_LEN] = tag_out.try_into_().map_err(|_| {
+        error::Detailed::new(
+            OPERATION,
+            error::Reason::WrongLength {
+                input: "out_suffix_capacity",
+                expected: TAG_LEN,
+                actual: out_suffix_capacity,
+            },
+        )
+    })?;
+    let Tag(tag) = (key.key.algorithm.seal)(&key.key.inner, nonce, ad, in_out)
+        .map_err(|_| error::Detailed::new(OPERATION, error::Reason::Unspecified))?;
     tag_out.copy_from_slice(tag.as_ref());
 
     Ok(in_out_len + TAG_LEN)
 }
 
+// Like `seal_in_place()`, but the tag is returned instead of being written
+// after the ciphertext.
+#[cfg(feature = "rustcrypto")]
+pub(crate) fn seal_in_place_separate_tag(
+    key: &SealingKey, nonce: &[u8], ad: &[u8], in_out: &mut [u8],
+) -> Result<[u8; TAG_LEN], error::Unspecified> {
+    const OPERATION: &str = "aead::seal_in_place";
+    cpu::cache_detected_features();
+    let nonce = check_nonce(OPERATION, nonce)?;
+    check_per_nonce_max_bytes(OPERATION, key.key.algorithm, "plaintext", in_out.len())?;
+    let Tag(tag) = (key.key.algorithm.seal)(&key.key.inner, nonce, ad, in_out)?;
+    let mut r = [0; TAG_LEN];
+    r.copy_from_slice(tag.as_ref());
+    Ok(r)
+}
+
 /// `OpeningKey` and `SealingKey` are type-safety wrappers around `Key`, which
 /// does all the actual work via the C AEAD interface.
 ///
@@ -254,6 +405,14 @@ impl Key {
         })
     }
 
+    // Doesn't detect the CPU features, so every operation on the key must.
+    const fn new_chacha20_poly1305(key_bytes: &[u8; chacha::KEY_LEN]) -> Self {
+        Key {
+            inner: KeyInner::ChaCha20Poly1305(chacha::Key::new(key_bytes)),
+            algorithm: &CHACHA20_POLY1305,
+        }
+    }
+
     /// The key's AEAD algorithm.
     #[inline(always)]
     fn algorithm(&self) -> &'static Algorithm { self.algorithm }
@@ -302,7 +461,7 @@ impl Algorithm {
     ///
     /// C analog: `EVP_AEAD_key_length`
     #[inline(always)]
-    pub fn key_len(&self) -> usize { self.key_len }
+    pub const fn key_len(&self) -> usize { self.key_len }
 
     /// The length of a tag.
     ///
@@ -313,7 +472,7 @@ impl Algorithm {
     /// Go analog:
     ///   [`crypto.cipher.AEAD.Overhead`](https://golang.org/pkg/crypto/cipher/#AEAD)
     #[inline(always)]
-    pub fn tag_len(&self) -> usize { TAG_LEN }
+    pub const fn tag_len(&self) -> usize { TAG_LEN }
 
     /// The length of the nonces.
     ///
@@ -322,7 +481,7 @@ impl Algorithm {
     /// Go analog:
     ///   [`crypto.cipher.AEAD.NonceSize`](https://golang.org/pkg/crypto/cipher/#AEAD)
     #[inline(always)]
-    pub fn nonce_len(&self) -> usize { NONCE_LEN }
+    pub const fn nonce_len(&self) -> usize { NONCE_LEN }
 }
 
 derive_debug_via_self!(Algorithm, self.id);
@@ -354,9 +513,34 @@ pub const MAX_TAG_LEN: usize = TAG_LEN;
 // All the AEADs we support use 96-bit nonces.
 const NONCE_LEN: usize = 96 / 8;
 
-fn check_per_nonce_max_bytes(alg: &Algorithm, in_out_len: usize) -> Result<(), error::Unspecified> {
+fn check_nonce<'a>(
+    operation: &'static str, nonce: &'a [u8],
+) -> Result<&'a [u8; NONCE_LEN], error::Detailed> {
+    nonce.try_into_().map_err(|_| {
+        error::Detailed::new(
+            operation,
+            error::Reason::WrongLength {
+                input: "nonce",
+                expected: NONCE_LEN,
+                actual: nonce.len(),
+            },
+        )
+    })
+}
+
+fn check_per_nonce_max_bytes(
+    operation: &'static str, alg: &Algorithm, input: &'static str, in_out_len: usize,
+) -> Result<(), error::Detailed> {
     if polyfill::u64_from_usize(in_out_len) > alg.max_input_len {
-        return Err(error::Unspecified);
+        // Not reached when `max_input_len` doesn't fit in a `usize`.
+        return Err(error::Detailed::new(
+            operation,
+            error::Reason::TooLong {
+                input,
+                max: alg.max_input_len as usize,
+                actual: in_out_len,
+            },
+        ));
     }
     Ok(())
 }
@@ -367,8 +551,21 @@ enum Direction {
 }
 
 mod aes_gcm;
+
+#[cfg(target_arch = "x86_64")]
+mod aes_gcm_avx;
+
+#[cfg(target_arch = "aarch64")]
+mod aes_gcm_neon;
+
+#[cfg(target_arch = "x86_64")]
+mod aes_gcm_vaes;
+
 mod block;
-mod chacha;
+pub(crate) mod chacha;
 mod chacha20_poly1305;
 pub mod chacha20_poly1305_openssh;
 mod poly1305;
+
+#[cfg(target_arch = "x86_64")]
+mod poly1305_ifma;
@@ -13,10 +13,103 @@
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
 use super::{Block, Tag, BLOCK_LEN};
-use crate::{aead, bssl, c, error};
+use crate::{aead, bssl, c, error, zeroize};
 
 #[repr(align(16))]
-pub struct Key([u8; AES_KEY_CTX_BUF_LEN]);
+pub struct Key {
+    ctx: [u8; AES_KEY_CTX_BUF_LEN],
+
+    // Used instead of `ctx` for long inputs, if the CPU supports it.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    wide: Option<WideKey>,
+}
+
+// The implementations that encrypt and authenticate several blocks at a time,
+// from the widest that the CPU supports.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+#[derive(Clone, Copy)]
+enum WideKey {
+    #[cfg(target_arch = "x86_64")]
+    Vaes(super::aes_gcm_vaes::Key),
+
+    #[cfg(target_arch = "x86_64")]
+    Avx(super::aes_gcm_avx::Key),
+
+    #[cfg(target_arch = "aarch64")]
+    Neon(super::aes_gcm_neon::Key),
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+impl WideKey {
+    fn new(key: &[u8]) -> Option<Self> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            use super::{aes_gcm_avx, aes_gcm_vaes};
+            if aes_gcm_vaes::is_supported() {
+                return Some(WideKey::Vaes(aes_gcm_vaes::Key::new(key)));
+            }
+            if aes_gcm_avx::is_supported() {
+                return Some(WideKey::Avx(aes_gcm_avx::Key::new(key)));
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            use super::aes_gcm_neon;
+            if aes_gcm_neon::is_supported() {
+                return Some(WideKey::Neon(aes_gcm_neon::Key::new(key)));
+            }
+        }
+        None
+    }
+
+    // Shorter inputs are left to the C implementation.
+    fn min_len(&self) -> usize {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            WideKey::Vaes(_) => super::aes_gcm_vaes::MIN_LEN,
+            #[cfg(target_arch = "x86_64")]
+            WideKey::Avx(_) => super::aes_gcm_avx::MIN_LEN,
+            #[cfg(target_arch = "aarch64")]
+            WideKey::Neon(_) => super::aes_gcm_neon::MIN_LEN,
+        }
+    }
+
+    fn seal(&self, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], in_out: &mut [u8]) -> Tag {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            WideKey::Vaes(key) => key.seal(nonce, ad, in_out),
+            #[cfg(target_arch = "x86_64")]
+            WideKey::Avx(key) => key.seal(nonce, ad, in_out),
+            #[cfg(target_arch = "aarch64")]
+            WideKey::Neon(key) => key.seal(nonce, ad, in_out),
+        }
+    }
+
+    fn open(
+        &self, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], in_prefix_len: usize,
+        in_out: &mut [u8],
+    ) -> Tag {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            WideKey::Vaes(key) => key.open(nonce, ad, in_prefix_len, in_out),
+            #[cfg(target_arch = "x86_64")]
+            WideKey::Avx(key) => key.open(nonce, ad, in_prefix_len, in_out),
+            #[cfg(target_arch = "aarch64")]
+            WideKey::Neon(key) => key.open(nonce, ad, in_prefix_len, in_out),
+        }
+    }
+
+    fn zeroize(&mut self) {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            WideKey::Vaes(key) => zeroize::zeroize_value(key),
+            #[cfg(target_arch = "x86_64")]
+            WideKey::Avx(key) => zeroize::zeroize_value(key),
+            #[cfg(target_arch = "aarch64")]
+            WideKey::Neon(key) => zeroize::zeroize_value(key),
+        }
+    }
+}
 
 /// AES-128 in GCM mode with 128-bit tags and 96 bit nonces.
 ///
@@ -47,24 +140,50 @@ pub static AES_256_GCM: aead::Algorithm = aead::Algorithm {
 };
 
 fn aes_gcm_init(key: &[u8]) -> Result<super::KeyInner, error::Unspecified> {
-    let mut r = Key([0u8; AES_KEY_CTX_BUF_LEN]);
+    let mut ctx = [0u8; AES_KEY_CTX_BUF_LEN];
     Result::from(unsafe {
-        GFp_aes_gcm_init(r.0.as_mut_ptr(), r.0.len(), key.as_ptr(), key.len())
+        GFp_aes_gcm_init(ctx.as_mut_ptr(), ctx.len(), key.as_ptr(), key.len())
     })?;
-    Ok(super::KeyInner::AesGcm(r))
+    let key = Key {
+        ctx,
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        wide: WideKey::new(key),
+    };
+    zeroize::zeroize(&mut ctx);
+    Ok(super::KeyInner::AesGcm(key))
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        zeroize::zeroize(&mut self.ctx);
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        {
+            if let Some(wide) = &mut self.wide {
+                wide.zeroize();
+            }
+        }
+    }
 }
 
 fn aes_gcm_seal(
     key: &super::KeyInner, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], in_out: &mut [u8],
 ) -> Result<Tag, error::Unspecified> {
-    let ctx = match key {
-        super::KeyInner::AesGcm(Key(ctx)) => ctx,
+    let key = match key {
+        super::KeyInner::AesGcm(key) => key,
         _ => unreachable!(),
     };
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    {
+        if let Some(wide) = &key.wide {
+            if in_out.len() >= wide.min_len() {
+                return Ok(wide.seal(nonce, ad, in_out));
+            }
+        }
+    }
     let mut tag = Tag(Block::zero());
     Result::from(unsafe {
         GFp_aes_gcm_seal(
-            ctx.as_ptr(),
+            key.ctx.as_ptr(),
             in_out.as_mut_ptr(),
             in_out.len(),
             &mut tag,
@@ -80,14 +199,22 @@ fn aes_gcm_open(
     key: &super::KeyInner, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], in_prefix_len: usize,
     in_out: &mut [u8],
 ) -> Result<Tag, error::Unspecified> {
-    let ctx = match key {
-        super::KeyInner::AesGcm(Key(ctx)) => ctx,
+    let key = match key {
+        super::KeyInner::AesGcm(key) => key,
         _ => unreachable!(),
     };
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    {
+        if let Some(wide) = &key.wide {
+            if in_out.len() - in_prefix_len >= wide.min_len() {
+                return Ok(wide.open(nonce, ad, in_prefix_len, in_out));
+            }
+        }
+    }
     let mut tag = Tag(Block::zero());
     Result::from(unsafe {
         GFp_aes_gcm_open(
-            ctx.as_ptr(),
+            key.ctx.as_ptr(),
             in_out.as_mut_ptr(),
             in_out.len() - in_prefix_len,
             &mut tag,
@@ -138,63 +265,6 @@ extern "C" {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{bits::BitLength, c, polyfill::convert::*, test};
-
-    #[test]
-    pub fn test_aes() {
-        test::from_file("src/aead/aes_tests.txt", |section, test_case| {
-            assert_eq!(section, "");
-            let key = test_case.consume_bytes("Key");
-            let input = test_case.consume_bytes("Input");
-            let input: &[u8; AES_BLOCK_SIZE] = input.as_slice().try_into_().unwrap();
-            let expected_output = test_case.consume_bytes("Output");
-
-            // Key setup.
-            let key_bits = BitLength::from_usize_bytes(key.len()).unwrap();
-            assert!(
-                key_bits == BitLength::from_usize_bits(128)
-                    || key_bits == BitLength::from_usize_bits(256)
-            );
-            let key_bits = key_bits.as_usize_bits() as c::uint;
-            let mut aes_key = AES_KEY {
-                rd_key: [0u32; 4 * (AES_MAX_ROUNDS + 1)],
-                rounds: 0,
-            };
-            unsafe {
-                GFp_AES_set_encrypt_key(key.as_ptr(), key_bits, &mut aes_key);
-            }
-
-            // Test encryption into a separate buffer.
-            let mut output_buf = [0u8; AES_BLOCK_SIZE];
-            unsafe {
-                GFp_AES_encrypt(input.as_ptr(), output_buf.as_mut_ptr(), &aes_key);
-            }
-            assert_eq!(&output_buf[..], &expected_output[..]);
-
-            // Test in-place encryption.
-            output_buf.copy_from_slice(&input[..]);
-            unsafe {
-                GFp_AES_encrypt(output_buf.as_ptr(), output_buf.as_mut_ptr(), &aes_key);
-            }
-            assert_eq!(&output_buf[..], &expected_output[..]);
-
-            Ok(())
-        })
-    }
-
-    const AES_BLOCK_SIZE: usize = 16;
-
-    // Keep this in sync with AES_KEY in aes.h.
-    #[repr(C)]
-    pub struct AES_KEY {
-        pub rd_key: [u32; 4 * (AES_MAX_ROUNDS + 1)],
-        pub rounds: c::uint,
-    }
-
-    extern "C" {
-        fn GFp_AES_set_encrypt_key(key: *const u8, bits: c::uint, aes_key: *mut AES_KEY);
-        fn GFp_AES_encrypt(in_: *const u8, out: *mut u8, key: *const AES_KEY);
-    }
 
     #[test]
     fn max_input_len_test() {
@@ -210,4 +280,72 @@ mod tests {
         assert_eq!(AES_128_GCM.max_input_len * 8, NIST_SP800_38D_MAX_BITS);
         assert_eq!(AES_256_GCM.max_input_len * 8, NIST_SP800_38D_MAX_BITS);
     }
+
+    // Each wide implementation that the CPU supports must agree with the C
+    // implementation for every combination of lengths around the block and
+    // wide-loop boundaries.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    #[test]
+    fn test_wide_matches_ctx() {
+        use super::super::KeyInner;
+        use crate::cpu;
+
+        cpu::cache_detected_features();
+
+        let nonce = [7u8; aead::NONCE_LEN];
+        let input: Vec<u8> = (0..1000).map(|i| (i * 31 + 5) as u8).collect();
+        let ad: Vec<u8> = (0..300).map(|i| (i * 17 + 3) as u8).collect();
+        for key_len in &[16, 32] {
+            let key_bytes: Vec<u8> = (0..*key_len).map(|i| i as u8).collect();
+            let without_wide = match aes_gcm_init(&key_bytes).unwrap() {
+                KeyInner::AesGcm(key) => KeyInner::AesGcm(Key { wide: None, ..key }),
+                _ => unreachable!(),
+            };
+
+            let mut wide_keys = Vec::new();
+            #[cfg(target_arch = "x86_64")]
+            {
+                use super::super::{aes_gcm_avx, aes_gcm_vaes};
+                if aes_gcm_vaes::is_supported() {
+                    wide_keys.push(WideKey::Vaes(aes_gcm_vaes::Key::new(&key_bytes)));
+                }
+                if aes_gcm_avx::is_supported() {
+                    wide_keys.push(WideKey::Avx(aes_gcm_avx::Key::new(&key_bytes)));
+                }
+            }
+            #[cfg(target_arch = "aarch64")]
+            {
+                use super::super::aes_gcm_neon;
+                if aes_gcm_neon::is_supported() {
+                    wide_keys.push(WideKey::Neon(aes_gcm_neon::Key::new(&key_bytes)));
+                }
+            }
+
+            for wide in &wide_keys {
+                for &len in &[
+                    0, 1, 16, 17, 127, 128, 129, 255, 256, 257, 271, 272, 383, 384, 385, 511,
+                    512, 513, 767, 1000,
+                ] {
+                    for &ad_len in &[0, 1, 16, 17, 100, 128, 129, 256, 257, 300] {
+                        let ad = &ad[..ad_len];
+                        let mut expected = input[..len].to_vec();
+                        let Tag(expected_tag) =
+                            aes_gcm_seal(&without_wide, &nonce, ad, &mut expected).unwrap();
+                        let mut actual = input[..len].to_vec();
+                        let Tag(tag) = wide.seal(&nonce, ad, &mut actual);
+                        assert_eq!(actual, expected);
+                        assert_eq!(tag.as_ref(), expected_tag.as_ref());
+
+                        for &prefix_len in &[0, 1, 16, 33] {
+                            let mut in_out = vec![0u8; prefix_len];
+                            in_out.extend_from_slice(&expected);
+                            let Tag(tag) = wide.open(&nonce, ad, prefix_len, &mut in_out);
+                            assert_eq!(&in_out[..len], &input[..len]);
+                            assert_eq!(tag.as_ref(), expected_tag.as_ref());
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,305 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! AES-GCM using AES-NI and PCLMULQDQ with the AVX encodings, for CPUs that
+//! don't support the VAES instructions that `aes_gcm_vaes` uses.
+//!
+//! Eight blocks are encrypted at a time, and their GHASH is stitched into the
+//! AES rounds of the next eight blocks so that the AES and carry-less
+//! multiplication units are busy at the same time. When opening, the
+//! ciphertext is known up front, so the blocks being decrypted are hashed
+//! instead. The field arithmetic is that of `aes_gcm_vaes`.
+
+use super::{
+    aes_gcm_vaes::{bswap, expand_key_128, expand_key_256, ghash_mul, preprocess_h, reduce},
+    Block, Tag, BLOCK_LEN,
+};
+use crate::{aead, cpu, zeroize};
+use core::arch::x86_64::*;
+
+/// Inputs shorter than this are left to the C implementation. Even though the
+/// C implementation has a six-block stitched loop of its own, this is faster
+/// for every input of at least one batch of eight blocks.
+pub(super) const MIN_LEN: usize = WIDE_LEN;
+
+// The number of blocks that are encrypted and authenticated at a time.
+const WIDE_BLOCKS: usize = 8;
+const WIDE_LEN: usize = WIDE_BLOCKS * BLOCK_LEN;
+
+const MAX_ROUNDS: usize = super::aes_gcm_vaes::MAX_ROUNDS;
+
+/// Whether the CPU supports everything this implementation uses.
+pub(super) fn is_supported() -> bool {
+    let features = cpu::features();
+    features.aes() && features.pclmulqdq() && features.ssse3() && features.avx()
+}
+
+#[derive(Clone, Copy)]
+pub(super) struct Key {
+    round_keys: [__m128i; MAX_ROUNDS + 1],
+    rounds: usize,
+
+    // `h_powers[i]` is H^(8 - i), preprocessed for `ghash_mul`.
+    h_powers: [__m128i; WIDE_BLOCKS],
+}
+
+unsafe impl zeroize::Zeroable for Key {}
+
+impl Key {
+    /// Must only be called if `is_supported()`. `key` is 16 or 32 bytes.
+    pub(super) fn new(key: &[u8]) -> Self { unsafe { new_key(key) } }
+
+    pub(super) fn seal(
+        &self, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], in_out: &mut [u8],
+    ) -> Tag {
+        let len = in_out.len();
+        let in_out = in_out.as_mut_ptr();
+        unsafe { crypt(self, nonce, ad, in_out, in_out, len, Direction::Sealing) }
+    }
+
+    pub(super) fn open(
+        &self, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], in_prefix_len: usize,
+        in_out: &mut [u8],
+    ) -> Tag {
+        let len = in_out.len() - in_prefix_len;
+        let output = in_out.as_mut_ptr();
+        let input = in_out[in_prefix_len..].as_ptr();
+        unsafe { crypt(self, nonce, ad, input, output, len, Direction::Opening) }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Opening,
+    Sealing,
+}
+
+#[target_feature(enable = "aes,pclmulqdq,ssse3,avx")]
+unsafe fn new_key(key: &[u8]) -> Key {
+    let mut round_keys = [_mm_setzero_si128(); MAX_ROUNDS + 1];
+    let rounds = if key.len() == 128 / 8 {
+        expand_key_128(key, &mut round_keys);
+        10
+    } else {
+        expand_key_256(key, &mut round_keys);
+        14
+    };
+    let mut key = Key {
+        round_keys,
+        rounds,
+        h_powers: [_mm_setzero_si128(); WIDE_BLOCKS],
+    };
+
+    let h = preprocess_h(encrypt_block(&key, _mm_setzero_si128()));
+    let mut h_power = h;
+    for i in (0..WIDE_BLOCKS).rev() {
+        key.h_powers[i] = h_power;
+        h_power = ghash_mul(h_power, h);
+    }
+    key
+}
+
+#[inline]
+#[target_feature(enable = "aes,avx")]
+unsafe fn encrypt_block(key: &Key, block: __m128i) -> __m128i {
+    let mut block = _mm_xor_si128(block, key.round_keys[0]);
+    for round_key in &key.round_keys[1..key.rounds] {
+        block = _mm_aesenc_si128(block, *round_key);
+    }
+    _mm_aesenclast_si128(block, key.round_keys[key.rounds])
+}
+
+// Accumulates the unreduced product of byte-reflected `a` and preprocessed `b`.
+#[inline]
+#[target_feature(enable = "pclmulqdq,avx")]
+unsafe fn mul_accumulate(
+    a: __m128i, b: __m128i, lo: &mut __m128i, mi: &mut __m128i, hi: &mut __m128i,
+) {
+    *lo = _mm_xor_si128(*lo, _mm_clmulepi64_si128(a, b, 0x00));
+    *mi = _mm_xor_si128(*mi, _mm_clmulepi64_si128(a, b, 0x01));
+    *mi = _mm_xor_si128(*mi, _mm_clmulepi64_si128(a, b, 0x10));
+    *hi = _mm_xor_si128(*hi, _mm_clmulepi64_si128(a, b, 0x11));
+}
+
+// Encrypts the eight counter blocks `blocks` in place. If `to_hash` isn't
+// `None`, its eight byte-reflected blocks are mixed into `acc`, the first one
+// multiplied by H^8, the second by H^7, and so on; one multiplication is done
+// after each of the first eight AES rounds. Returns the new `acc`.
+#[inline]
+#[target_feature(enable = "aes,pclmulqdq,avx")]
+unsafe fn encrypt_and_ghash(
+    key: &Key, blocks: &mut [__m128i; WIDE_BLOCKS], to_hash: Option<&[__m128i; WIDE_BLOCKS]>,
+    acc: __m128i,
+) -> __m128i {
+    let round_key = key.round_keys[0];
+    for block in blocks.iter_mut() {
+        *block = _mm_xor_si128(*block, round_key);
+    }
+    let mut lo = _mm_setzero_si128();
+    let mut mi = _mm_setzero_si128();
+    let mut hi = _mm_setzero_si128();
+    // There are at least ten rounds, so every block is hashed in this loop.
+    for (r, round_key) in key.round_keys[1..key.rounds].iter().enumerate() {
+        for block in blocks.iter_mut() {
+            *block = _mm_aesenc_si128(*block, *round_key);
+        }
+        if let Some(to_hash) = to_hash {
+            if r < WIDE_BLOCKS {
+                let block = if r == 0 { _mm_xor_si128(to_hash[0], acc) } else { to_hash[r] };
+                mul_accumulate(block, key.h_powers[r], &mut lo, &mut mi, &mut hi);
+            }
+        }
+    }
+    let round_key = key.round_keys[key.rounds];
+    for block in blocks.iter_mut() {
+        *block = _mm_aesenclast_si128(*block, round_key);
+    }
+    match to_hash {
+        Some(_) => reduce(lo, mi, hi),
+        None => acc,
+    }
+}
+
+// Mixes eight byte-reflected blocks into `acc`, like `encrypt_and_ghash`.
+#[inline]
+#[target_feature(enable = "pclmulqdq,avx")]
+unsafe fn ghash_wide(key: &Key, acc: __m128i, blocks: &[__m128i; WIDE_BLOCKS]) -> __m128i {
+    let mut lo = _mm_setzero_si128();
+    let mut mi = _mm_setzero_si128();
+    let mut hi = _mm_setzero_si128();
+    for (i, block) in blocks.iter().enumerate() {
+        let block = if i == 0 { _mm_xor_si128(*block, acc) } else { *block };
+        mul_accumulate(block, key.h_powers[i], &mut lo, &mut mi, &mut hi);
+    }
+    reduce(lo, mi, hi)
+}
+
+// Mixes `data`, padded with zeros to a multiple of the block length, into
+// `acc`.
+#[target_feature(enable = "pclmulqdq,ssse3,avx")]
+unsafe fn ghash(key: &Key, mut acc: __m128i, data: &[u8]) -> __m128i {
+    let mut chunks = data.chunks_exact(WIDE_LEN);
+    for chunk in &mut chunks {
+        let mut blocks = [_mm_setzero_si128(); WIDE_BLOCKS];
+        for (i, block) in blocks.iter_mut().enumerate() {
+            let block_ptr = chunk[(BLOCK_LEN * i)..].as_ptr();
+            *block = bswap(_mm_loadu_si128(block_ptr as *const __m128i));
+        }
+        acc = ghash_wide(key, acc, &blocks);
+    }
+    let h = key.h_powers[WIDE_BLOCKS - 1];
+    for block in chunks.remainder().chunks(BLOCK_LEN) {
+        let mut padded = [0u8; BLOCK_LEN];
+        padded[..block.len()].copy_from_slice(block);
+        let block = bswap(_mm_loadu_si128(padded.as_ptr() as *const __m128i));
+        acc = ghash_mul(_mm_xor_si128(acc, block), h);
+    }
+    acc
+}
+
+// Encrypts or decrypts the `len` bytes at `input` into `output`, which may be
+// the same as `input` or any address before it, and returns the tag.
+#[target_feature(enable = "aes,pclmulqdq,ssse3,avx")]
+unsafe fn crypt(
+    key: &Key, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], input: *const u8, output: *mut u8,
+    len: usize, direction: Direction,
+) -> Tag {
+    let mut acc = ghash(key, _mm_setzero_si128(), ad);
+
+    // The counter blocks are kept byte-reflected so that the 32-bit counter
+    // is in the low 32 bits, where it can be incremented.
+    let mut j0 = [0u8; BLOCK_LEN];
+    j0[..aead::NONCE_LEN].copy_from_slice(nonce);
+    j0[BLOCK_LEN - 1] = 1;
+    let j0 = bswap(_mm_loadu_si128(j0.as_ptr() as *const __m128i));
+    let one = _mm_set_epi32(0, 0, 0, 1);
+    let mut counter = _mm_add_epi32(j0, one);
+
+    // When sealing, the ciphertext of each eight blocks is hashed while the
+    // next eight blocks are encrypted.
+    let mut pending: Option<[__m128i; WIDE_BLOCKS]> = None;
+
+    let mut i = 0;
+    while len - i >= WIDE_LEN {
+        let mut input_blocks = [_mm_setzero_si128(); WIDE_BLOCKS];
+        let mut key_stream = [_mm_setzero_si128(); WIDE_BLOCKS];
+        for (j, (input_block, key_stream)) in
+            input_blocks.iter_mut().zip(key_stream.iter_mut()).enumerate()
+        {
+            *input_block = _mm_loadu_si128(input.add(i + (BLOCK_LEN * j)) as *const __m128i);
+            *key_stream = bswap(counter);
+            counter = _mm_add_epi32(counter, one);
+        }
+        let to_hash = match direction {
+            Direction::Opening => {
+                let mut ciphertext = input_blocks;
+                for block in ciphertext.iter_mut() {
+                    *block = bswap(*block);
+                }
+                Some(ciphertext)
+            },
+            Direction::Sealing => pending,
+        };
+        acc = encrypt_and_ghash(key, &mut key_stream, to_hash.as_ref(), acc);
+        let mut ciphertext = [_mm_setzero_si128(); WIDE_BLOCKS];
+        for (j, key_stream) in key_stream.iter().enumerate() {
+            let output_block = _mm_xor_si128(input_blocks[j], *key_stream);
+            _mm_storeu_si128(output.add(i + (BLOCK_LEN * j)) as *mut __m128i, output_block);
+            ciphertext[j] = bswap(output_block);
+        }
+        if direction == Direction::Sealing {
+            pending = Some(ciphertext);
+        }
+        i += WIDE_LEN;
+    }
+    if let Some(pending) = pending {
+        acc = ghash_wide(key, acc, &pending);
+    }
+
+    let h = key.h_powers[WIDE_BLOCKS - 1];
+    while i < len {
+        let block_len = core::cmp::min(len - i, BLOCK_LEN);
+        let mut input_block = [0u8; BLOCK_LEN];
+        core::ptr::copy_nonoverlapping(input.add(i), input_block.as_mut_ptr(), block_len);
+        let input_block = _mm_loadu_si128(input_block.as_ptr() as *const __m128i);
+        let key_stream = encrypt_block(key, bswap(counter));
+        counter = _mm_add_epi32(counter, one);
+        let mut output_block = [0u8; BLOCK_LEN];
+        _mm_storeu_si128(
+            output_block.as_mut_ptr() as *mut __m128i,
+            _mm_xor_si128(input_block, key_stream),
+        );
+        core::ptr::copy_nonoverlapping(output_block.as_ptr(), output.add(i), block_len);
+        let ciphertext_block = match direction {
+            Direction::Opening => input_block,
+            Direction::Sealing => {
+                for b in &mut output_block[block_len..] {
+                    *b = 0;
+                }
+                _mm_loadu_si128(output_block.as_ptr() as *const __m128i)
+            },
+        };
+        acc = ghash_mul(_mm_xor_si128(acc, bswap(ciphertext_block)), h);
+        i += block_len;
+    }
+
+    // The byte-reflected lengths block.
+    let lengths = _mm_set_epi64x((ad.len() * 8) as i64, (len * 8) as i64);
+    acc = ghash_mul(_mm_xor_si128(acc, lengths), h);
+
+    let tag = _mm_xor_si128(bswap(acc), encrypt_block(key, bswap(j0)));
+    let mut tag_block = Block::zero();
+    _mm_storeu_si128(tag_block.as_mut().as_mut_ptr() as *mut __m128i, tag);
+    Tag(tag_block)
+}
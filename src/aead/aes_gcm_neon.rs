@@ -0,0 +1,355 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! AES-GCM using the ARMv8 AES and PMULL instructions, with the GHASH of each
+//! eight blocks interleaved with the AES rounds of the next eight, like
+//! `aes_gcm_avx`.
+//!
+//! The C implementation encrypts with `aes_hw_ctr32_encrypt_blocks` and then
+//! hashes the ciphertext in a separate pass, which leaves the PMULL unit idle
+//! during encryption and the AES unit idle during hashing.
+//!
+//! A block is hashed as the integer that its bytes encode in big-endian
+//! order, which is the byte-reflected block of `aes_gcm_vaes`, so the same
+//! preprocessing of H and the same reduction are used.
+
+use super::{Block, Tag, BLOCK_LEN};
+use crate::{aead, cpu, zeroize};
+use core::arch::aarch64::*;
+
+/// Inputs shorter than this, i.e. all but the records of bulk transfers, are
+/// left to the C implementation.
+pub(super) const MIN_LEN: usize = 8192;
+
+// The number of blocks that are encrypted and authenticated at a time.
+const WIDE_BLOCKS: usize = 8;
+const WIDE_LEN: usize = WIDE_BLOCKS * BLOCK_LEN;
+
+const MAX_ROUNDS: usize = 14;
+
+/// Whether the CPU supports everything this implementation uses.
+pub(super) fn is_supported() -> bool {
+    let features = cpu::features();
+    features.neon() && features.aes() && features.pmull()
+}
+
+#[derive(Clone, Copy)]
+pub(super) struct Key {
+    round_keys: [uint8x16_t; MAX_ROUNDS + 1],
+    rounds: usize,
+
+    // `h_powers[i]` is H^(8 - i), preprocessed for `ghash_mul`.
+    h_powers: [u128; WIDE_BLOCKS],
+}
+
+unsafe impl zeroize::Zeroable for Key {}
+
+impl Key {
+    /// Must only be called if `is_supported()`. `key` is 16 or 32 bytes.
+    pub(super) fn new(key: &[u8]) -> Self { unsafe { new_key(key) } }
+
+    pub(super) fn seal(
+        &self, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], in_out: &mut [u8],
+    ) -> Tag {
+        let len = in_out.len();
+        let in_out = in_out.as_mut_ptr();
+        unsafe { crypt(self, nonce, ad, in_out, in_out, len, Direction::Sealing) }
+    }
+
+    pub(super) fn open(
+        &self, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], in_prefix_len: usize,
+        in_out: &mut [u8],
+    ) -> Tag {
+        let len = in_out.len() - in_prefix_len;
+        let output = in_out.as_mut_ptr();
+        let input = in_out[in_prefix_len..].as_ptr();
+        unsafe { crypt(self, nonce, ad, input, output, len, Direction::Opening) }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Opening,
+    Sealing,
+}
+
+#[target_feature(enable = "neon,aes")]
+unsafe fn new_key(key: &[u8]) -> Key {
+    let rounds = if key.len() == 128 / 8 { 10 } else { 14 };
+    let mut key = Key {
+        round_keys: expand_key(key, rounds),
+        rounds,
+        h_powers: [0; WIDE_BLOCKS],
+    };
+
+    let mut h = [0u8; BLOCK_LEN];
+    vst1q_u8(h.as_mut_ptr(), encrypt_block(&key, vdupq_n_u8(0)));
+    let h = preprocess_h(u128::from_be_bytes(h));
+    let mut h_power = h;
+    for i in (0..WIDE_BLOCKS).rev() {
+        key.h_powers[i] = h_power;
+        h_power = ghash_mul(h_power, h);
+    }
+    key
+}
+
+// FIPS 197 Section 5.2. There's no equivalent of x86's `AESKEYGENASSIST`, but
+// `AESE` with a zero round key on a state whose four columns are all `w` is
+// SubWord(w) in each column, as ShiftRows doesn't change such a state.
+#[target_feature(enable = "neon,aes")]
+unsafe fn expand_key(key: &[u8], rounds: usize) -> [uint8x16_t; MAX_ROUNDS + 1] {
+    const RCON: [u32; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+    let sub_word = |w: u32| {
+        let state = vaeseq_u8(vreinterpretq_u8_u32(vdupq_n_u32(w)), vdupq_n_u8(0));
+        vgetq_lane_u32::<0>(vreinterpretq_u32_u8(state))
+    };
+
+    // The words are little-endian, so RotWord is a rotation right by a byte.
+    let nk = key.len() / 4;
+    let mut w = [0u32; 4 * (MAX_ROUNDS + 1)];
+    for (w, word) in w.iter_mut().zip(key.chunks(4)) {
+        *w = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in nk..(4 * (rounds + 1)) {
+        let mut t = w[i - 1];
+        if i % nk == 0 {
+            t = sub_word(t.rotate_right(8)) ^ RCON[(i / nk) - 1];
+        } else if nk > 6 && i % nk == 4 {
+            t = sub_word(t);
+        }
+        w[i] = w[i - nk] ^ t;
+    }
+
+    let mut round_keys = [vdupq_n_u8(0); MAX_ROUNDS + 1];
+    for (round_key, words) in round_keys.iter_mut().zip(w.chunks(4)) {
+        let mut bytes = [0u8; BLOCK_LEN];
+        for (bytes, word) in bytes.chunks_mut(4).zip(words) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        *round_key = vld1q_u8(bytes.as_ptr());
+    }
+    zeroize::zeroize(&mut w);
+    round_keys
+}
+
+#[inline]
+#[target_feature(enable = "neon,aes")]
+unsafe fn encrypt_block(key: &Key, block: uint8x16_t) -> uint8x16_t {
+    let mut block = block;
+    for round_key in &key.round_keys[..(key.rounds - 1)] {
+        block = vaesmcq_u8(vaeseq_u8(block, *round_key));
+    }
+    block = vaeseq_u8(block, key.round_keys[key.rounds - 1]);
+    veorq_u8(block, key.round_keys[key.rounds])
+}
+
+// Multiplies H by x^-1, like `aes_gcm_vaes::preprocess_h`.
+fn preprocess_h(h: u128) -> u128 {
+    const GFPOLY_AND_CARRY: u128 = (0xc200_0000_0000_0000 << 64) | 1;
+    let carry = 0u128.wrapping_sub(h >> 127);
+    (h << 1) ^ (carry & GFPOLY_AND_CARRY)
+}
+
+#[inline]
+#[target_feature(enable = "neon,aes")]
+unsafe fn mul_accumulate(a: u128, b: u128, lo: &mut u128, mi: &mut u128, hi: &mut u128) {
+    let (a0, a1) = (a as u64, (a >> 64) as u64);
+    let (b0, b1) = (b as u64, (b >> 64) as u64);
+    *lo ^= vmull_p64(a0, b0);
+    *mi ^= vmull_p64(a1, b0) ^ vmull_p64(a0, b1);
+    *hi ^= vmull_p64(a1, b1);
+}
+
+// Reduces the 256-bit product `hi:mi:lo`, like `aes_gcm_vaes::reduce`.
+#[inline]
+#[target_feature(enable = "neon,aes")]
+unsafe fn reduce(lo: u128, mi: u128, hi: u128) -> u128 {
+    const GFPOLY_HI: u64 = 0xc200_0000_0000_0000;
+    let mi = mi ^ lo.rotate_left(64) ^ vmull_p64(GFPOLY_HI, lo as u64);
+    hi ^ mi.rotate_left(64) ^ vmull_p64(GFPOLY_HI, mi as u64)
+}
+
+#[inline]
+#[target_feature(enable = "neon,aes")]
+unsafe fn ghash_mul(a: u128, b: u128) -> u128 {
+    let (mut lo, mut mi, mut hi) = (0, 0, 0);
+    mul_accumulate(a, b, &mut lo, &mut mi, &mut hi);
+    reduce(lo, mi, hi)
+}
+
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn to_u128(block: uint8x16_t) -> u128 {
+    let mut bytes = [0u8; BLOCK_LEN];
+    vst1q_u8(bytes.as_mut_ptr(), block);
+    u128::from_be_bytes(bytes)
+}
+
+// Encrypts the eight counter blocks `blocks` in place. If `to_hash` isn't
+// `None`, its eight blocks are mixed into `acc`, like `aes_gcm_avx`.
+#[inline]
+#[target_feature(enable = "neon,aes")]
+unsafe fn encrypt_and_ghash(
+    key: &Key, blocks: &mut [uint8x16_t; WIDE_BLOCKS], to_hash: Option<&[u128; WIDE_BLOCKS]>,
+    acc: u128,
+) -> u128 {
+    let (mut lo, mut mi, mut hi) = (0, 0, 0);
+    // There are at least ten rounds, so every block is hashed in this loop.
+    for (r, round_key) in key.round_keys[..(key.rounds - 1)].iter().enumerate() {
+        for block in blocks.iter_mut() {
+            *block = vaesmcq_u8(vaeseq_u8(*block, *round_key));
+        }
+        if let Some(to_hash) = to_hash {
+            if r < WIDE_BLOCKS {
+                let block = if r == 0 { to_hash[0] ^ acc } else { to_hash[r] };
+                mul_accumulate(block, key.h_powers[r], &mut lo, &mut mi, &mut hi);
+            }
+        }
+    }
+    let (round_key, last_round_key) =
+        (key.round_keys[key.rounds - 1], key.round_keys[key.rounds]);
+    for block in blocks.iter_mut() {
+        *block = veorq_u8(vaeseq_u8(*block, round_key), last_round_key);
+    }
+    match to_hash {
+        Some(_) => reduce(lo, mi, hi),
+        None => acc,
+    }
+}
+
+#[inline]
+#[target_feature(enable = "neon,aes")]
+unsafe fn ghash_wide(key: &Key, acc: u128, blocks: &[u128; WIDE_BLOCKS]) -> u128 {
+    let (mut lo, mut mi, mut hi) = (0, 0, 0);
+    for (i, block) in blocks.iter().enumerate() {
+        let block = if i == 0 { *block ^ acc } else { *block };
+        mul_accumulate(block, key.h_powers[i], &mut lo, &mut mi, &mut hi);
+    }
+    reduce(lo, mi, hi)
+}
+
+// Mixes `data`, padded with zeros to a multiple of the block length, into
+// `acc`.
+#[target_feature(enable = "neon,aes")]
+unsafe fn ghash(key: &Key, mut acc: u128, data: &[u8]) -> u128 {
+    let mut chunks = data.chunks_exact(WIDE_LEN);
+    for chunk in &mut chunks {
+        let mut blocks = [0; WIDE_BLOCKS];
+        for (block, bytes) in blocks.iter_mut().zip(chunk.chunks(BLOCK_LEN)) {
+            *block = to_u128(vld1q_u8(bytes.as_ptr()));
+        }
+        acc = ghash_wide(key, acc, &blocks);
+    }
+    let h = key.h_powers[WIDE_BLOCKS - 1];
+    for block in chunks.remainder().chunks(BLOCK_LEN) {
+        let mut padded = [0u8; BLOCK_LEN];
+        padded[..block.len()].copy_from_slice(block);
+        acc = ghash_mul(acc ^ u128::from_be_bytes(padded), h);
+    }
+    acc
+}
+
+// Encrypts or decrypts the `len` bytes at `input` into `output`, which may be
+// the same as `input` or any address before it, and returns the tag.
+#[target_feature(enable = "neon,aes")]
+unsafe fn crypt(
+    key: &Key, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], input: *const u8, output: *mut u8,
+    len: usize, direction: Direction,
+) -> Tag {
+    let mut acc = ghash(key, 0, ad);
+
+    // The counter is the last 32 bits of each counter block, in big-endian
+    // order; the lane is stored in little-endian order.
+    let mut j0 = [0u8; BLOCK_LEN];
+    j0[..aead::NONCE_LEN].copy_from_slice(nonce);
+    let j0 = vreinterpretq_u32_u8(vld1q_u8(j0.as_ptr()));
+    let counter_block =
+        |counter: u32| vreinterpretq_u8_u32(vsetq_lane_u32::<3>(counter.to_be(), j0));
+    let mut counter = 2u32;
+
+    // When sealing, the ciphertext of each eight blocks is hashed while the
+    // next eight blocks are encrypted.
+    let mut pending: Option<[u128; WIDE_BLOCKS]> = None;
+
+    let mut i = 0;
+    while len - i >= WIDE_LEN {
+        let mut input_blocks = [vdupq_n_u8(0); WIDE_BLOCKS];
+        let mut key_stream = [vdupq_n_u8(0); WIDE_BLOCKS];
+        for (j, (input_block, key_stream)) in
+            input_blocks.iter_mut().zip(key_stream.iter_mut()).enumerate()
+        {
+            *input_block = vld1q_u8(input.add(i + (BLOCK_LEN * j)));
+            *key_stream = counter_block(counter);
+            counter = counter.wrapping_add(1);
+        }
+        let to_hash = match direction {
+            Direction::Opening => {
+                let mut ciphertext = [0; WIDE_BLOCKS];
+                for (ciphertext, input_block) in ciphertext.iter_mut().zip(&input_blocks) {
+                    *ciphertext = to_u128(*input_block);
+                }
+                Some(ciphertext)
+            },
+            Direction::Sealing => pending,
+        };
+        acc = encrypt_and_ghash(key, &mut key_stream, to_hash.as_ref(), acc);
+        let mut ciphertext = [0; WIDE_BLOCKS];
+        for (j, key_stream) in key_stream.iter().enumerate() {
+            let output_block = veorq_u8(input_blocks[j], *key_stream);
+            vst1q_u8(output.add(i + (BLOCK_LEN * j)), output_block);
+            ciphertext[j] = to_u128(output_block);
+        }
+        if direction == Direction::Sealing {
+            pending = Some(ciphertext);
+        }
+        i += WIDE_LEN;
+    }
+    if let Some(pending) = pending {
+        acc = ghash_wide(key, acc, &pending);
+    }
+
+    let h = key.h_powers[WIDE_BLOCKS - 1];
+    while i < len {
+        let block_len = core::cmp::min(len - i, BLOCK_LEN);
+        let mut input_block = [0u8; BLOCK_LEN];
+        core::ptr::copy_nonoverlapping(input.add(i), input_block.as_mut_ptr(), block_len);
+        let key_stream = encrypt_block(key, counter_block(counter));
+        counter = counter.wrapping_add(1);
+        let mut output_block = [0u8; BLOCK_LEN];
+        vst1q_u8(output_block.as_mut_ptr(), veorq_u8(vld1q_u8(input_block.as_ptr()), key_stream));
+        core::ptr::copy_nonoverlapping(output_block.as_ptr(), output.add(i), block_len);
+        let ciphertext_block = match direction {
+            Direction::Opening => input_block,
+            Direction::Sealing => {
+                for b in &mut output_block[block_len..] {
+                    *b = 0;
+                }
+                output_block
+            },
+        };
+        acc = ghash_mul(acc ^ u128::from_be_bytes(ciphertext_block), h);
+        i += block_len;
+    }
+
+    let lengths = (((ad.len() * 8) as u128) << 64) | ((len * 8) as u128);
+    acc = ghash_mul(acc ^ lengths, h);
+
+    let mut tag = [0u8; BLOCK_LEN];
+    vst1q_u8(tag.as_mut_ptr(), encrypt_block(key, counter_block(1)));
+    let tag = u128::from_be_bytes(tag) ^ acc;
+    let mut tag_block = Block::zero();
+    tag_block.as_mut().copy_from_slice(&tag.to_be_bytes());
+    Tag(tag_block)
+}
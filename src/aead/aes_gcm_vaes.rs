@@ -0,0 +1,382 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! AES-GCM using the VAES and VPCLMULQDQ instructions on 512-bit vectors,
+//! which encrypt and authenticate four blocks per instruction.
+//!
+//! The GHASH implementation operates on byte-reflected blocks, with the
+//! powers of the hash key preprocessed so that no bit reflection is needed,
+//! as in the Linux kernel's aes-gcm-avx10-x86_64.S.
+
+use super::{Block, Tag, BLOCK_LEN};
+use crate::{aead, cpu, zeroize};
+use core::arch::x86_64::*;
+
+/// Inputs shorter than this are left to the C implementation, for which the
+/// setup of the wide registers isn't worth it.
+pub(super) const MIN_LEN: usize = WIDE_LEN;
+
+// The number of blocks that are encrypted and authenticated at a time.
+const WIDE_BLOCKS: usize = 16;
+const WIDE_LEN: usize = WIDE_BLOCKS * BLOCK_LEN;
+
+pub(super) const MAX_ROUNDS: usize = 14;
+
+/// Whether the CPU supports everything this implementation uses.
+pub(super) fn is_supported() -> bool {
+    let features = cpu::features();
+    features.aes()
+        && features.pclmulqdq()
+        && features.avx512f()
+        && features.avx512bw()
+        && features.vaes()
+        && features.vpclmulqdq()
+}
+
+#[derive(Clone, Copy)]
+pub(super) struct Key {
+    round_keys: [__m128i; MAX_ROUNDS + 1],
+    rounds: usize,
+
+    // `h_powers[i]` is H^(16 - i), preprocessed for `ghash_mul`.
+    h_powers: [__m128i; WIDE_BLOCKS],
+}
+
+unsafe impl zeroize::Zeroable for Key {}
+
+impl Key {
+    /// Must only be called if `is_supported()`. `key` is 16 or 32 bytes.
+    pub(super) fn new(key: &[u8]) -> Self { unsafe { new_key(key) } }
+
+    pub(super) fn seal(
+        &self, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], in_out: &mut [u8],
+    ) -> Tag {
+        let len = in_out.len();
+        let in_out = in_out.as_mut_ptr();
+        unsafe { crypt(self, nonce, ad, in_out, in_out, len, Direction::Sealing) }
+    }
+
+    pub(super) fn open(
+        &self, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], in_prefix_len: usize,
+        in_out: &mut [u8],
+    ) -> Tag {
+        let len = in_out.len() - in_prefix_len;
+        let output = in_out.as_mut_ptr();
+        let input = in_out[in_prefix_len..].as_ptr();
+        unsafe { crypt(self, nonce, ad, input, output, len, Direction::Opening) }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Opening,
+    Sealing,
+}
+
+// Reverses the bytes of each 128-bit lane.
+#[inline]
+#[target_feature(enable = "ssse3")]
+pub(super) unsafe fn bswap(a: __m128i) -> __m128i {
+    _mm_shuffle_epi8(a, _mm_set_epi8(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15))
+}
+
+#[inline]
+#[target_feature(enable = "avx512f,avx512bw")]
+unsafe fn bswap_wide(a: __m512i) -> __m512i {
+    let mask = _mm_set_epi8(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+    _mm512_shuffle_epi8(a, _mm512_broadcast_i32x4(mask))
+}
+
+#[target_feature(enable = "aes,pclmulqdq,ssse3")]
+unsafe fn new_key(key: &[u8]) -> Key {
+    let mut round_keys = [_mm_setzero_si128(); MAX_ROUNDS + 1];
+    let rounds = if key.len() == 128 / 8 {
+        expand_key_128(key, &mut round_keys);
+        10
+    } else {
+        expand_key_256(key, &mut round_keys);
+        14
+    };
+    let mut key = Key {
+        round_keys,
+        rounds,
+        h_powers: [_mm_setzero_si128(); WIDE_BLOCKS],
+    };
+
+    let h = preprocess_h(encrypt_block(&key, _mm_setzero_si128()));
+    let mut h_power = h;
+    for i in (0..WIDE_BLOCKS).rev() {
+        key.h_powers[i] = h_power;
+        h_power = ghash_mul(h_power, h);
+    }
+    key
+}
+
+// Reflects the bytes of the hash key H and multiplies it by x^-1, which is a
+// left shift by one bit followed by a reduction if a bit was shifted out.
+// There's no 128-bit shift, so each 64-bit half is doubled and the carry from
+// the low half is added to the high half along with the reduction.
+#[inline]
+#[target_feature(enable = "ssse3")]
+pub(super) unsafe fn preprocess_h(h: __m128i) -> __m128i {
+    let h = bswap(h);
+    let carries = _mm_srai_epi32(_mm_shuffle_epi32(h, 0xd3), 31);
+    let gfpoly_and_internal_carry = _mm_set_epi64x(0xc200_0000_0000_0001u64 as i64, 1);
+    _mm_xor_si128(_mm_add_epi64(h, h), _mm_and_si128(carries, gfpoly_and_internal_carry))
+}
+
+macro_rules! expand_round {
+    ( $prev:expr, $assist:expr, $shuffle:expr ) => {{
+        let assist = _mm_shuffle_epi32(_mm_aeskeygenassist_si128($assist, $shuffle.0), $shuffle.1);
+        let mut k = $prev;
+        k = _mm_xor_si128(k, _mm_slli_si128(k, 4));
+        k = _mm_xor_si128(k, _mm_slli_si128(k, 4));
+        k = _mm_xor_si128(k, _mm_slli_si128(k, 4));
+        _mm_xor_si128(k, assist)
+    }};
+}
+
+#[inline]
+#[target_feature(enable = "aes")]
+pub(super) unsafe fn expand_key_128(key: &[u8], rk: &mut [__m128i; MAX_ROUNDS + 1]) {
+    rk[0] = _mm_loadu_si128(key.as_ptr() as *const __m128i);
+    rk[1] = expand_round!(rk[0], rk[0], (0x01, 0xff));
+    rk[2] = expand_round!(rk[1], rk[1], (0x02, 0xff));
+    rk[3] = expand_round!(rk[2], rk[2], (0x04, 0xff));
+    rk[4] = expand_round!(rk[3], rk[3], (0x08, 0xff));
+    rk[5] = expand_round!(rk[4], rk[4], (0x10, 0xff));
+    rk[6] = expand_round!(rk[5], rk[5], (0x20, 0xff));
+    rk[7] = expand_round!(rk[6], rk[6], (0x40, 0xff));
+    rk[8] = expand_round!(rk[7], rk[7], (0x80, 0xff));
+    rk[9] = expand_round!(rk[8], rk[8], (0x1b, 0xff));
+    rk[10] = expand_round!(rk[9], rk[9], (0x36, 0xff));
+}
+
+#[inline]
+#[target_feature(enable = "aes")]
+pub(super) unsafe fn expand_key_256(key: &[u8], rk: &mut [__m128i; MAX_ROUNDS + 1]) {
+    rk[0] = _mm_loadu_si128(key.as_ptr() as *const __m128i);
+    rk[1] = _mm_loadu_si128(key[BLOCK_LEN..].as_ptr() as *const __m128i);
+    rk[2] = expand_round!(rk[0], rk[1], (0x01, 0xff));
+    rk[3] = expand_round!(rk[1], rk[2], (0x00, 0xaa));
+    rk[4] = expand_round!(rk[2], rk[3], (0x02, 0xff));
+    rk[5] = expand_round!(rk[3], rk[4], (0x00, 0xaa));
+    rk[6] = expand_round!(rk[4], rk[5], (0x04, 0xff));
+    rk[7] = expand_round!(rk[5], rk[6], (0x00, 0xaa));
+    rk[8] = expand_round!(rk[6], rk[7], (0x08, 0xff));
+    rk[9] = expand_round!(rk[7], rk[8], (0x00, 0xaa));
+    rk[10] = expand_round!(rk[8], rk[9], (0x10, 0xff));
+    rk[11] = expand_round!(rk[9], rk[10], (0x00, 0xaa));
+    rk[12] = expand_round!(rk[10], rk[11], (0x20, 0xff));
+    rk[13] = expand_round!(rk[11], rk[12], (0x00, 0xaa));
+    rk[14] = expand_round!(rk[12], rk[13], (0x40, 0xff));
+}
+
+#[inline]
+#[target_feature(enable = "aes")]
+unsafe fn encrypt_block(key: &Key, block: __m128i) -> __m128i {
+    let mut block = _mm_xor_si128(block, key.round_keys[0]);
+    for round_key in &key.round_keys[1..key.rounds] {
+        block = _mm_aesenc_si128(block, *round_key);
+    }
+    _mm_aesenclast_si128(block, key.round_keys[key.rounds])
+}
+
+#[inline]
+#[target_feature(enable = "avx512f,vaes")]
+unsafe fn encrypt_wide(key: &Key, blocks: &mut [__m512i; WIDE_BLOCKS / 4]) {
+    let round_key = _mm512_broadcast_i32x4(key.round_keys[0]);
+    for block in blocks.iter_mut() {
+        *block = _mm512_xor_si512(*block, round_key);
+    }
+    for round_key in &key.round_keys[1..key.rounds] {
+        let round_key = _mm512_broadcast_i32x4(*round_key);
+        for block in blocks.iter_mut() {
+            *block = _mm512_aesenc_epi128(*block, round_key);
+        }
+    }
+    let round_key = _mm512_broadcast_i32x4(key.round_keys[key.rounds]);
+    for block in blocks.iter_mut() {
+        *block = _mm512_aesenclast_epi128(*block, round_key);
+    }
+}
+
+// The product of byte-reflected `a` and preprocessed `b`. The 256-bit
+// product is reduced modulo x^128 + x^7 + x^2 + x + 1 in two folding steps,
+// each of which multiplies the low 64 bits by x^63 + x^62 + x^57.
+#[inline]
+#[target_feature(enable = "pclmulqdq,sse2")]
+pub(super) unsafe fn ghash_mul(a: __m128i, b: __m128i) -> __m128i {
+    let lo = _mm_clmulepi64_si128(a, b, 0x00);
+    let mi = _mm_xor_si128(_mm_clmulepi64_si128(a, b, 0x01), _mm_clmulepi64_si128(a, b, 0x10));
+    let hi = _mm_clmulepi64_si128(a, b, 0x11);
+    reduce(lo, mi, hi)
+}
+
+// Reduces the 256-bit product `hi:mi:lo`, where `mi` overlaps the high half of
+// `lo` and the low half of `hi`, as in `ghash_mul`.
+#[inline]
+#[target_feature(enable = "pclmulqdq,sse2")]
+pub(super) unsafe fn reduce(lo: __m128i, mi: __m128i, hi: __m128i) -> __m128i {
+    let gfpoly = _mm_set_epi64x(0xc200_0000_0000_0000u64 as i64, 1);
+    let t = _mm_clmulepi64_si128(gfpoly, lo, 0x01);
+    let mi = _mm_xor_si128(_mm_xor_si128(mi, _mm_shuffle_epi32(lo, 0x4e)), t);
+    let t = _mm_clmulepi64_si128(gfpoly, mi, 0x01);
+    _mm_xor_si128(_mm_xor_si128(hi, _mm_shuffle_epi32(mi, 0x4e)), t)
+}
+
+// Mixes 16 byte-reflected blocks into `acc`: the first block is multiplied
+// by H^16, the second by H^15, and so on. The products are summed before
+// they are reduced.
+#[inline]
+#[target_feature(enable = "avx512f,vpclmulqdq")]
+unsafe fn ghash_wide(key: &Key, acc: __m128i, blocks: &[__m512i; WIDE_BLOCKS / 4]) -> __m128i {
+    let mut lo = _mm512_setzero_si512();
+    let mut mi = _mm512_setzero_si512();
+    let mut hi = _mm512_setzero_si512();
+    for (i, block) in blocks.iter().enumerate() {
+        let block = if i == 0 {
+            _mm512_xor_si512(*block, _mm512_zextsi128_si512(acc))
+        } else {
+            *block
+        };
+        let h = _mm512_loadu_si512(key.h_powers[(4 * i)..].as_ptr() as *const _);
+        lo = _mm512_xor_si512(lo, _mm512_clmulepi64_epi128(block, h, 0x00));
+        mi = _mm512_xor_si512(mi, _mm512_clmulepi64_epi128(block, h, 0x01));
+        mi = _mm512_xor_si512(mi, _mm512_clmulepi64_epi128(block, h, 0x10));
+        hi = _mm512_xor_si512(hi, _mm512_clmulepi64_epi128(block, h, 0x11));
+    }
+
+    let gfpoly = _mm512_broadcast_i32x4(_mm_set_epi64x(0xc200_0000_0000_0000u64 as i64, 1));
+    let t = _mm512_clmulepi64_epi128(gfpoly, lo, 0x01);
+    let mi = _mm512_xor_si512(_mm512_xor_si512(mi, _mm512_shuffle_epi32(lo, _MM_PERM_BADC)), t);
+    let t = _mm512_clmulepi64_epi128(gfpoly, mi, 0x01);
+    let r = _mm512_xor_si512(_mm512_xor_si512(hi, _mm512_shuffle_epi32(mi, _MM_PERM_BADC)), t);
+
+    _mm_xor_si128(
+        _mm_xor_si128(_mm512_extracti32x4_epi32(r, 0), _mm512_extracti32x4_epi32(r, 1)),
+        _mm_xor_si128(_mm512_extracti32x4_epi32(r, 2), _mm512_extracti32x4_epi32(r, 3)),
+    )
+}
+
+// Mixes `data`, padded with zeros to a multiple of the block length, into
+// `acc`.
+#[target_feature(enable = "avx512f,avx512bw,vpclmulqdq,pclmulqdq,ssse3")]
+unsafe fn ghash(key: &Key, mut acc: __m128i, data: &[u8]) -> __m128i {
+    let mut chunks = data.chunks_exact(WIDE_LEN);
+    for chunk in &mut chunks {
+        let mut blocks = [_mm512_setzero_si512(); WIDE_BLOCKS / 4];
+        for (i, block) in blocks.iter_mut().enumerate() {
+            let block_ptr = chunk[(64 * i)..].as_ptr();
+            *block = bswap_wide(_mm512_loadu_si512(block_ptr as *const _));
+        }
+        acc = ghash_wide(key, acc, &blocks);
+    }
+    let h = key.h_powers[WIDE_BLOCKS - 1];
+    for block in chunks.remainder().chunks(BLOCK_LEN) {
+        let mut padded = [0u8; BLOCK_LEN];
+        padded[..block.len()].copy_from_slice(block);
+        let block = bswap(_mm_loadu_si128(padded.as_ptr() as *const __m128i));
+        acc = ghash_mul(_mm_xor_si128(acc, block), h);
+    }
+    acc
+}
+
+// Encrypts or decrypts the `len` bytes at `input` into `output`, which may be
+// the same as `input` or any address before it, and returns the tag.
+#[target_feature(enable = "avx512f,avx512bw,vaes,vpclmulqdq,aes,pclmulqdq,ssse3")]
+unsafe fn crypt(
+    key: &Key, nonce: &[u8; aead::NONCE_LEN], ad: &[u8], input: *const u8, output: *mut u8,
+    len: usize, direction: Direction,
+) -> Tag {
+    let mut acc = ghash(key, _mm_setzero_si128(), ad);
+
+    // The counter blocks are kept byte-reflected so that the 32-bit counter
+    // is in the low 32 bits of each lane, where it can be incremented.
+    let mut j0 = [0u8; BLOCK_LEN];
+    j0[..aead::NONCE_LEN].copy_from_slice(nonce);
+    j0[BLOCK_LEN - 1] = 1;
+    let j0 = bswap(_mm_loadu_si128(j0.as_ptr() as *const __m128i));
+    let mut counter = _mm_add_epi32(j0, _mm_set_epi32(0, 0, 0, 1));
+
+    let mut i = 0;
+    if len >= WIDE_LEN {
+        let mut counters = _mm512_add_epi32(
+            _mm512_broadcast_i32x4(counter),
+            _mm512_set_epi32(0, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0),
+        );
+        let four = _mm512_set_epi32(0, 0, 0, 4, 0, 0, 0, 4, 0, 0, 0, 4, 0, 0, 0, 4);
+        while len - i >= WIDE_LEN {
+            let mut input_blocks = [_mm512_setzero_si512(); WIDE_BLOCKS / 4];
+            let mut key_stream = [_mm512_setzero_si512(); WIDE_BLOCKS / 4];
+            for (j, (input_block, key_stream)) in
+                input_blocks.iter_mut().zip(key_stream.iter_mut()).enumerate()
+            {
+                *input_block = _mm512_loadu_si512(input.add(i + (64 * j)) as *const _);
+                *key_stream = bswap_wide(counters);
+                counters = _mm512_add_epi32(counters, four);
+            }
+            encrypt_wide(key, &mut key_stream);
+            let mut ciphertext = [_mm512_setzero_si512(); WIDE_BLOCKS / 4];
+            for (j, key_stream) in key_stream.iter().enumerate() {
+                let output_block = _mm512_xor_si512(input_blocks[j], *key_stream);
+                _mm512_storeu_si512(output.add(i + (64 * j)) as *mut _, output_block);
+                let ciphertext_block = match direction {
+                    Direction::Opening => input_blocks[j],
+                    Direction::Sealing => output_block,
+                };
+                ciphertext[j] = bswap_wide(ciphertext_block);
+            }
+            acc = ghash_wide(key, acc, &ciphertext);
+            i += WIDE_LEN;
+        }
+        counter = _mm512_extracti32x4_epi32(counters, 0);
+    }
+
+    let h = key.h_powers[WIDE_BLOCKS - 1];
+    while i < len {
+        let block_len = core::cmp::min(len - i, BLOCK_LEN);
+        let mut input_block = [0u8; BLOCK_LEN];
+        core::ptr::copy_nonoverlapping(input.add(i), input_block.as_mut_ptr(), block_len);
+        let input_block = _mm_loadu_si128(input_block.as_ptr() as *const __m128i);
+        let key_stream = encrypt_block(key, bswap(counter));
+        counter = _mm_add_epi32(counter, _mm_set_epi32(0, 0, 0, 1));
+        let mut output_block = [0u8; BLOCK_LEN];
+        _mm_storeu_si128(
+            output_block.as_mut_ptr() as *mut __m128i,
+            _mm_xor_si128(input_block, key_stream),
+        );
+        core::ptr::copy_nonoverlapping(output_block.as_ptr(), output.add(i), block_len);
+        let ciphertext_block = match direction {
+            Direction::Opening => input_block,
+            Direction::Sealing => {
+                for b in &mut output_block[block_len..] {
+                    *b = 0;
+                }
+                _mm_loadu_si128(output_block.as_ptr() as *const __m128i)
+            },
+        };
+        acc = ghash_mul(_mm_xor_si128(acc, bswap(ciphertext_block)), h);
+        i += block_len;
+    }
+
+    // The byte-reflected lengths block.
+    let lengths = _mm_set_epi64x((ad.len() * 8) as i64, (len * 8) as i64);
+    acc = ghash_mul(_mm_xor_si128(acc, lengths), h);
+
+    let tag = _mm_xor_si128(bswap(acc), encrypt_block(key, bswap(j0)));
+    let mut tag_block = Block::zero();
+    _mm_storeu_si128(tag_block.as_mut().as_mut_ptr() as *mut __m128i, tag);
+    Tag(tag_block)
+}
@@ -12,7 +12,7 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-use crate::{endian::*, polyfill::convert::*};
+use crate::{endian::*, polyfill::convert::*, zeroize};
 
 /// An array of 16 bytes that can (in the x86_64 and AAarch64 ABIs, at least)
 /// be efficiently passed by value and returned by value (i.e. in registers),
@@ -24,6 +24,8 @@ pub struct Block {
     subblocks: [u64; 2],
 }
 
+unsafe impl zeroize::Zeroable for Block {}
+
 pub const BLOCK_LEN: usize = 16;
 
 impl Block {
@@ -15,16 +15,29 @@
 
 use super::block::{Block, BLOCK_LEN};
 use crate::{
-    c,
     polyfill::{convert::*, slice::u32_from_le_u8},
+    zeroize,
 };
 use core;
 
 #[repr(C)]
 pub struct Key([Block; KEY_BLOCKS]);
 
+impl Key {
+    /// Like `Key::from`, but usable in `const` contexts.
+    #[inline]
+    pub const fn new(value: &[u8; KEY_LEN]) -> Self {
+        // `Block` is `#[repr(C)]` and is exactly `BLOCK_LEN` bytes long.
+        Key(unsafe { core::mem::transmute::<[u8; KEY_LEN], [Block; KEY_BLOCKS]>(*value) })
+    }
+}
+
 impl<'a> From<&'a [u8; KEY_LEN]> for Key {
-    fn from(value: &[u8; KEY_LEN]) -> Self { Key(<[Block; KEY_BLOCKS]>::from_(value)) }
+    fn from(value: &[u8; KEY_LEN]) -> Self { Key::new(value) }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.0) }
 }
 
 #[inline]
@@ -68,10 +81,18 @@ pub fn chacha20_xor_overlapping(
     }
 }
 
+#[cfg(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64"
+))]
 #[inline]
 unsafe fn chacha20_xor_inner(
     key: &Key, counter: &Counter, input: *const u8, in_out_len: usize, output: *mut u8,
 ) {
+    use crate::c;
+
     extern "C" {
         fn GFp_ChaCha20_ctr32(
             out: *mut u8, in_: *const u8, in_len: c::size_t, key: &Key, counter: &Counter,
@@ -80,6 +101,69 @@ unsafe fn chacha20_xor_inner(
     GFp_ChaCha20_ctr32(output, input, in_out_len, key, counter);
 }
 
+/// The implementation for targets without an assembly language
+/// implementation, e.g. wasm32-unknown-unknown. Like the assembly language
+/// code, it allows `output` to overlap `input` as long as it doesn't come
+/// after it, since each block of the input is read before the block of the
+/// output at the same offset is written.
+#[cfg(not(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64"
+)))]
+unsafe fn chacha20_xor_inner(
+    key: &Key, counter: &Counter, input: *const u8, in_out_len: usize, output: *mut u8,
+) {
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&SIGMA);
+    for (state, bytes) in state[4..12]
+        .iter_mut()
+        .zip(key.0.iter().flat_map(|block| block.as_ref().chunks(4)))
+    {
+        *state = u32_from_le_u8(bytes.try_into_().unwrap());
+    }
+    state[12] = u32::from_le(counter[0]);
+    state[13..].copy_from_slice(&counter[1..]);
+
+    let mut working = [0u32; 16];
+    let mut buf = [0u8; 64];
+    let mut offset = 0;
+    while offset < in_out_len {
+        working.copy_from_slice(&state);
+        for _ in 0..10 {
+            quarter_round(&mut working, 0, 4, 8, 12);
+            quarter_round(&mut working, 1, 5, 9, 13);
+            quarter_round(&mut working, 2, 6, 10, 14);
+            quarter_round(&mut working, 3, 7, 11, 15);
+            quarter_round(&mut working, 0, 5, 10, 15);
+            quarter_round(&mut working, 1, 6, 11, 12);
+            quarter_round(&mut working, 2, 7, 8, 13);
+            quarter_round(&mut working, 3, 4, 9, 14);
+        }
+
+        let chunk_len = core::cmp::min(buf.len(), in_out_len - offset);
+        core::ptr::copy(input.add(offset), buf.as_mut_ptr(), chunk_len);
+        for (bytes, (working, state)) in buf[..chunk_len]
+            .chunks_mut(4)
+            .zip(working.iter().zip(state.iter()))
+        {
+            let keystream = working.wrapping_add(*state).to_le_bytes();
+            for (b, k) in bytes.iter_mut().zip(keystream.iter()) {
+                *b ^= *k;
+            }
+        }
+        core::ptr::copy(buf.as_ptr(), output.add(offset), chunk_len);
+
+        state[12] = state[12].wrapping_add(1);
+        offset += chunk_len;
+    }
+
+    zeroize::zeroize(&mut state);
+    zeroize::zeroize(&mut working);
+    zeroize::zeroize(&mut buf);
+}
+
 pub type Counter = [u32; 4];
 
 #[inline]
@@ -92,11 +176,85 @@ pub fn make_counter(nonce: &[u8; NONCE_LEN], counter: u32) -> Counter {
     ]
 }
 
+/// HChaCha20, of [draft-irtf-cfrg-xchacha Section 2.2]: derives the key with
+/// which XChaCha20 encrypts, with the last 8 bytes of its 24-byte nonce,
+/// from `key` and the first 16 bytes of the nonce. The subkey is returned as
+/// bytes so that it can also key ChaCha20-Poly1305, for XChaCha20-Poly1305.
+///
+/// [draft-irtf-cfrg-xchacha Section 2.2]: https://tools.ietf.org/html/draft-irtf-cfrg-xchacha-03#section-2.2
+#[cfg(feature = "alloc")]
+pub fn hchacha20(
+    key: &[u8; KEY_LEN], nonce: &[u8; HCHACHA20_NONCE_LEN],
+) -> zeroize::Zeroizing<[u8; KEY_LEN]> {
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&SIGMA);
+    for (state, bytes) in state[4..].iter_mut().zip(key.chunks(4).chain(nonce.chunks(4))) {
+        *state = u32_from_le_u8(bytes.try_into_().unwrap());
+    }
+
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+
+    // Unlike the block function, the input isn't added to the output.
+    let mut subkey = zeroize::Zeroizing::new([0u8; KEY_LEN]);
+    for (bytes, state) in subkey
+        .chunks_mut(4)
+        .zip(state[..4].iter().chain(state[12..].iter()))
+    {
+        bytes.copy_from_slice(&state.to_le_bytes());
+    }
+    zeroize::zeroize(&mut state);
+    subkey
+}
+
+#[cfg(any(
+    feature = "alloc",
+    not(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "arm",
+        target_arch = "aarch64"
+    ))
+))]
+#[inline(always)]
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+#[cfg(any(
+    feature = "alloc",
+    not(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "arm",
+        target_arch = "aarch64"
+    ))
+))]
+const SIGMA: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
 const KEY_BLOCKS: usize = 2;
 pub const KEY_LEN: usize = KEY_BLOCKS * BLOCK_LEN;
 
 pub const NONCE_LEN: usize = 12; // 96 bits
 
+#[cfg(feature = "alloc")]
+pub const HCHACHA20_NONCE_LEN: usize = 16;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,4 +341,20 @@ mod tests {
             }
         }
     }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn hchacha20_test() {
+        // draft-irtf-cfrg-xchacha-03 Section 2.2.1.
+        let mut key = [0u8; KEY_LEN];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let nonce = test::from_hex("000000090000004a0000000031415927").unwrap();
+        let expected =
+            test::from_hex("82413b4227b27bfed30e42508a877d73a0f9e4d58a74a853c12ec41326d3ecdc")
+                .unwrap();
+        let subkey = hchacha20(&key, nonce.as_slice().try_into_().unwrap());
+        assert_eq!(&subkey[..], &expected[..]);
+    }
 }
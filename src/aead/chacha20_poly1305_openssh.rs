@@ -39,7 +39,7 @@ pub struct SealingKey {
 
 impl SealingKey {
     /// Constructs a new `SealingKey`.
-    pub fn new(key_material: &[u8; KEY_LEN]) -> SealingKey {
+    pub const fn new(key_material: &[u8; KEY_LEN]) -> SealingKey {
         SealingKey {
             key: Key::new(key_material),
         }
@@ -52,10 +52,16 @@ impl SealingKey {
     /// `padding_length||payload||random padding`. It will be overwritten by
     /// `encrypted_packet_length||ciphertext`, where `encrypted_packet_length`
     /// is encrypted with `K_1` and `ciphertext` is encrypted by `K_2`.
+    ///
+    /// Fails, without changing anything, if `plaintext_in_ciphertext_out` is
+    /// shorter than `PACKET_LENGTH_LEN` bytes.
     pub fn seal_in_place(
         &self, sequence_number: u32, plaintext_in_ciphertext_out: &mut [u8],
         tag_out: &mut [u8; TAG_LEN],
-    ) {
+    ) -> Result<(), error::Unspecified> {
+        if plaintext_in_ciphertext_out.len() < PACKET_LENGTH_LEN {
+            return Err(error::Unspecified);
+        }
         let mut counter = make_counter(sequence_number);
 
         {
@@ -72,6 +78,7 @@ impl SealingKey {
         let poly_key = derive_poly1305_key(&self.key.k_2, &counter);
         let Tag(tag) = poly1305::sign(poly_key, plaintext_in_ciphertext_out);
         tag_out.copy_from_slice(tag.as_ref());
+        Ok(())
     }
 }
 
@@ -82,7 +89,7 @@ pub struct OpeningKey {
 
 impl OpeningKey {
     /// Constructs a new `OpeningKey`.
-    pub fn new(key_material: &[u8; KEY_LEN]) -> OpeningKey {
+    pub const fn new(key_material: &[u8; KEY_LEN]) -> OpeningKey {
         OpeningKey {
             key: Key::new(key_material),
         }
@@ -109,10 +116,14 @@ impl OpeningKey {
     /// replaced by the plaintext and the result is `Ok(plaintext)`, where
     /// `plaintext` is `&ciphertext_in_plaintext_out[PACKET_LENGTH_LEN..]`;
     /// otherwise the contents of `ciphertext_in_plaintext_out` are unspecified
-    /// and must not be used.
+    /// and must not be used. Fails if `ciphertext_in_plaintext_out` is shorter
+    /// than `PACKET_LENGTH_LEN` bytes.
     pub fn open_in_place<'a>(
         &self, sequence_number: u32, ciphertext_in_plaintext_out: &'a mut [u8], tag: &[u8; TAG_LEN],
     ) -> Result<&'a [u8], error::Unspecified> {
+        if ciphertext_in_plaintext_out.len() < PACKET_LENGTH_LEN {
+            return Err(error::Unspecified);
+        }
         let mut counter = make_counter(sequence_number);
 
         // We must verify the tag before decrypting so that
@@ -135,12 +146,14 @@ struct Key {
 }
 
 impl Key {
-    pub fn new(key_material: &[u8; KEY_LEN]) -> Key {
+    pub const fn new(key_material: &[u8; KEY_LEN]) -> Key {
         // The first half becomes K_2 and the second half becomes K_1.
-        let (k_2, k_1) = key_material.into_();
+        let halves = unsafe {
+            core::mem::transmute::<[u8; KEY_LEN], [[u8; chacha::KEY_LEN]; 2]>(*key_material)
+        };
         Key {
-            k_1: chacha::Key::from(k_1),
-            k_2: chacha::Key::from(k_2),
+            k_1: chacha::Key::new(&halves[1]),
+            k_2: chacha::Key::new(&halves[0]),
         }
     }
 }
@@ -19,7 +19,7 @@ use super::{
     block::{Block, BLOCK_LEN},
     Tag,
 };
-use crate::{bssl, c, error};
+use crate::{c, error, zeroize};
 
 /// A Poly1305 key.
 pub struct Key([Block; KEY_BLOCKS]);
@@ -28,12 +28,20 @@ impl From<[Block; KEY_BLOCKS]> for Key {
     fn from(value: [Block; KEY_BLOCKS]) -> Self { Key(value) }
 }
 
+impl Drop for Key {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.0) }
+}
+
 pub const KEY_BLOCKS: usize = 2;
 
 pub struct Context {
     opaque: Opaque,
     nonce: Nonce,
     func: Funcs,
+
+    // Used instead of `opaque` and `func`, if the CPU supports it.
+    #[cfg(target_arch = "x86_64")]
+    ifma: Option<super::poly1305_ifma::State>,
 }
 
 /// The memory manipulated by the assembly.
@@ -43,23 +51,19 @@ const OPAQUE_LEN: usize = 192;
 
 impl Context {
     #[inline]
-    pub fn from_key(Key(key_and_nonce): Key) -> Context {
-        extern "C" {
-            fn GFp_poly1305_blocks(
-                state: &mut Opaque, input: *const u8, len: c::size_t, should_pad: Pad,
-            );
-            fn GFp_poly1305_emit(state: &mut Opaque, tag: &mut Tag, nonce: &Nonce);
-        }
-
-        let key = DerivedKey(key_and_nonce[0].clone());
-        let nonce = Nonce(key_and_nonce[1].clone());
+    pub fn from_key(key: Key) -> Context {
+        let nonce = Nonce(key.0[1].clone());
+        let key = DerivedKey(key.0[0].clone());
 
         let mut ctx = Context {
             opaque: Opaque([0u8; OPAQUE_LEN]),
             nonce,
-            func: Funcs {
-                blocks_fn: GFp_poly1305_blocks,
-                emit_fn: GFp_poly1305_emit,
+            func: Funcs::new(),
+            #[cfg(target_arch = "x86_64")]
+            ifma: if super::poly1305_ifma::is_supported() {
+                Some(super::poly1305_ifma::State::new(&key.0))
+            } else {
+                None
             },
         };
 
@@ -75,15 +79,49 @@ impl Context {
     }
 
     pub fn update_block(&mut self, block: Block, pad: Pad) {
-        self.func.blocks(&mut self.opaque, block.as_ref(), pad);
+        self.blocks(block.as_ref(), pad);
     }
 
     pub fn update_blocks(&mut self, input: &[u8]) {
         debug_assert_eq!(input.len() % BLOCK_LEN, 0);
-        self.func.blocks(&mut self.opaque, input, Pad::Pad);
+        self.blocks(input, Pad::Pad);
     }
 
-    pub(super) fn finish(mut self) -> Tag { self.func.emit(&mut self.opaque, &self.nonce) }
+    pub(super) fn finish(mut self) -> Tag {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if let Some(ifma) = &self.ifma {
+                return Tag(ifma.emit(&self.nonce.0));
+            }
+        }
+        self.func.emit(&mut self.opaque, &self.nonce)
+    }
+
+    fn blocks(&mut self, input: &[u8], pad: Pad) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if let Some(ifma) = &mut self.ifma {
+                ifma.blocks(input, pad == Pad::Pad);
+                return;
+            }
+        }
+        self.func.blocks(&mut self.opaque, input, pad);
+    }
+}
+
+// The state includes the one-time key, which would allow forgeries of other
+// messages with the same nonce.
+impl Drop for Context {
+    fn drop(&mut self) {
+        zeroize::zeroize(&mut self.opaque.0);
+        zeroize::zeroize_value(&mut self.nonce.0);
+        #[cfg(target_arch = "x86_64")]
+        {
+            if let Some(ifma) = &mut self.ifma {
+                ifma.zeroize();
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -119,23 +157,76 @@ struct Funcs {
     emit_fn: unsafe extern "C" fn(&mut Opaque, &mut Tag, nonce: &Nonce),
 }
 
+#[cfg(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64"
+))]
 #[inline]
 fn init(state: &mut Opaque, key: DerivedKey, func: &mut Funcs) -> Result<(), error::Unspecified> {
     extern "C" {
         fn GFp_poly1305_init_asm(
             state: &mut Opaque, key: &DerivedKey, out_func: &mut Funcs,
-        ) -> bssl::Result;
+        ) -> crate::bssl::Result;
     }
     Result::from(unsafe { GFp_poly1305_init_asm(state, &key, func) })
 }
 
+#[cfg(not(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64"
+)))]
+#[inline]
+fn init(state: &mut Opaque, key: DerivedKey, _: &mut Funcs) -> Result<(), error::Unspecified> {
+    nohw::init(state, &key);
+    Ok(())
+}
+
 #[repr(u32)]
+#[derive(PartialEq)]
 pub enum Pad {
     AlreadyPadded = 0,
     Pad = 1,
 }
 
 impl Funcs {
+    #[cfg(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "arm",
+        target_arch = "aarch64"
+    ))]
+    #[inline]
+    fn new() -> Self {
+        extern "C" {
+            fn GFp_poly1305_blocks(
+                state: &mut Opaque, input: *const u8, len: c::size_t, should_pad: Pad,
+            );
+            fn GFp_poly1305_emit(state: &mut Opaque, tag: &mut Tag, nonce: &Nonce);
+        }
+        Funcs {
+            blocks_fn: GFp_poly1305_blocks,
+            emit_fn: GFp_poly1305_emit,
+        }
+    }
+
+    #[cfg(not(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "arm",
+        target_arch = "aarch64"
+    )))]
+    #[inline]
+    fn new() -> Self {
+        Funcs {
+            blocks_fn: nohw::blocks,
+            emit_fn: nohw::emit,
+        }
+    }
+
     #[inline]
     fn blocks(&self, state: &mut Opaque, data: &[u8], should_pad: Pad) {
         unsafe {
@@ -172,6 +263,168 @@ pub(super) fn sign(key: Key, input: &[u8]) -> Tag {
     ctx.finish()
 }
 
+/// The implementation for targets without an assembly language
+/// implementation, e.g. wasm32-unknown-unknown. It is poly1305-donna's
+/// 32-bit implementation, with the accumulator `h` and the key `r` in
+/// 26-bit limbs, which are stored in the `Opaque` state in little-endian
+/// order.
+#[cfg(not(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64"
+)))]
+mod nohw {
+    use super::{super::block::BLOCK_LEN, c, DerivedKey, Nonce, Opaque, Pad, Tag};
+    use crate::polyfill::{convert::*, slice::u32_from_le_u8};
+
+    const MASK: u32 = 0x3ff_ffff;
+
+    struct State {
+        r: [u32; 5],
+        h: [u32; 5],
+    }
+
+    impl State {
+        fn load(opaque: &Opaque) -> Self {
+            let mut limbs = [0u32; 10];
+            for (limb, bytes) in limbs.iter_mut().zip(opaque.0.chunks(4)) {
+                *limb = u32_from_le_u8(bytes.try_into_().unwrap());
+            }
+            let mut state = State { r: [0; 5], h: [0; 5] };
+            state.r.copy_from_slice(&limbs[..5]);
+            state.h.copy_from_slice(&limbs[5..]);
+            state
+        }
+
+        fn store(&self, opaque: &mut Opaque) {
+            for (bytes, limb) in opaque.0.chunks_mut(4).zip(self.r.iter().chain(self.h.iter())) {
+                bytes.copy_from_slice(&limb.to_le_bytes());
+            }
+        }
+    }
+
+    // Reads the four bytes of `bytes` at `offset`, shifted right by `shift`.
+    #[inline]
+    fn load26(bytes: &[u8], offset: usize, shift: u32) -> u32 {
+        u32_from_le_u8(bytes[offset..][..4].try_into_().unwrap()) >> shift
+    }
+
+    pub(super) fn init(opaque: &mut Opaque, key: &DerivedKey) {
+        let key = key.0.as_ref();
+        let state = State {
+            r: [
+                load26(key, 0, 0) & 0x3ff_ffff,
+                load26(key, 3, 2) & 0x3ff_ff03,
+                load26(key, 6, 4) & 0x3ff_c0ff,
+                load26(key, 9, 6) & 0x3f0_3fff,
+                load26(key, 12, 8) & 0x00f_ffff,
+            ],
+            h: [0; 5],
+        };
+        state.store(opaque);
+    }
+
+    pub(super) unsafe extern "C" fn blocks(
+        opaque: &mut Opaque, input: *const u8, input_len: c::size_t, should_pad: Pad,
+    ) {
+        let input = core::slice::from_raw_parts(input, input_len - (input_len % BLOCK_LEN));
+        let hibit = if should_pad == Pad::Pad { 1 << 24 } else { 0 };
+
+        let mut state = State::load(opaque);
+        let [r0, r1, r2, r3, r4] = state.r;
+        let [s1, s2, s3, s4] = [r1 * 5, r2 * 5, r3 * 5, r4 * 5];
+        let [mut h0, mut h1, mut h2, mut h3, mut h4] = state.h;
+
+        for block in input.chunks(BLOCK_LEN) {
+            h0 += load26(block, 0, 0) & MASK;
+            h1 += load26(block, 3, 2) & MASK;
+            h2 += load26(block, 6, 4) & MASK;
+            h3 += load26(block, 9, 6) & MASK;
+            h4 += load26(block, 12, 8) | hibit;
+
+            let mul = |a: u32, b: u32| u64::from(a) * u64::from(b);
+            let d0 = mul(h0, r0) + mul(h1, s4) + mul(h2, s3) + mul(h3, s2) + mul(h4, s1);
+            let mut d1 = mul(h0, r1) + mul(h1, r0) + mul(h2, s4) + mul(h3, s3) + mul(h4, s2);
+            let mut d2 = mul(h0, r2) + mul(h1, r1) + mul(h2, r0) + mul(h3, s4) + mul(h4, s3);
+            let mut d3 = mul(h0, r3) + mul(h1, r2) + mul(h2, r1) + mul(h3, r0) + mul(h4, s4);
+            let mut d4 = mul(h0, r4) + mul(h1, r3) + mul(h2, r2) + mul(h3, r1) + mul(h4, r0);
+
+            d1 += d0 >> 26;
+            h0 = (d0 as u32) & MASK;
+            d2 += d1 >> 26;
+            h1 = (d1 as u32) & MASK;
+            d3 += d2 >> 26;
+            h2 = (d2 as u32) & MASK;
+            d4 += d3 >> 26;
+            h3 = (d3 as u32) & MASK;
+            h0 += ((d4 >> 26) as u32) * 5;
+            h4 = (d4 as u32) & MASK;
+            h1 += h0 >> 26;
+            h0 &= MASK;
+        }
+
+        state.h = [h0, h1, h2, h3, h4];
+        state.store(opaque);
+    }
+
+    pub(super) unsafe extern "C" fn emit(opaque: &mut Opaque, tag: &mut Tag, nonce: &Nonce) {
+        let [mut h0, mut h1, mut h2, mut h3, mut h4] = State::load(opaque).h;
+
+        // Fully carry `h`.
+        h2 += h1 >> 26;
+        h1 &= MASK;
+        h3 += h2 >> 26;
+        h2 &= MASK;
+        h4 += h3 >> 26;
+        h3 &= MASK;
+        h0 += (h4 >> 26) * 5;
+        h4 &= MASK;
+        h1 += h0 >> 26;
+        h0 &= MASK;
+
+        // Compute `g = h - p` and select it, in constant time, if it isn't
+        // negative.
+        let mut g0 = h0.wrapping_add(5);
+        let mut g1 = h1.wrapping_add(g0 >> 26);
+        g0 &= MASK;
+        let mut g2 = h2.wrapping_add(g1 >> 26);
+        g1 &= MASK;
+        let mut g3 = h3.wrapping_add(g2 >> 26);
+        g2 &= MASK;
+        let mut g4 = h4.wrapping_add(g3 >> 26).wrapping_sub(1 << 26);
+        g3 &= MASK;
+        let select_g = (g4 >> 31).wrapping_sub(1);
+        g0 &= select_g;
+        g1 &= select_g;
+        g2 &= select_g;
+        g3 &= select_g;
+        g4 &= select_g;
+        let select_h = !select_g;
+        h0 = (h0 & select_h) | g0;
+        h1 = (h1 & select_h) | g1;
+        h2 = (h2 & select_h) | g2;
+        h3 = (h3 & select_h) | g3;
+        h4 = (h4 & select_h) | g4;
+
+        // `tag = (h + nonce) % 2**128`.
+        let h = [
+            h0 | (h1 << 26),
+            (h1 >> 6) | (h2 << 20),
+            (h2 >> 12) | (h3 << 14),
+            (h3 >> 18) | (h4 << 8),
+        ];
+        let nonce = nonce.0.as_ref();
+        let mut carry = 0u64;
+        for (i, (out, h)) in tag.0.as_mut().chunks_mut(4).zip(h.iter()).enumerate() {
+            let n = u32_from_le_u8(nonce[(4 * i)..][..4].try_into_().unwrap());
+            let f = u64::from(*h) + u64::from(n) + carry;
+            out.copy_from_slice(&(f as u32).to_le_bytes());
+            carry = f >> 32;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{super::block::*, *};
@@ -197,4 +450,36 @@ mod tests {
             Ok(())
         })
     }
+
+    // The IFMA implementation must agree with the assembly for every split
+    // of the input into calls, around the eight-block boundaries.
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    pub fn test_ifma_matches_asm() {
+        if !super::super::poly1305_ifma::is_supported() {
+            return;
+        }
+        let input: Vec<u8> = (0..(40 * BLOCK_LEN)).map(|i| (i * 131 + 7) as u8).collect();
+        for key_byte in &[0x00, 0x5a, 0xff] {
+            let key = [Block::from(&[*key_byte; BLOCK_LEN]), Block::from(&[0xa5; BLOCK_LEN])];
+            for &first_blocks in &[0, 1, 7, 8, 9, 16, 23] {
+                for &last_blocks in &[0, 1, 8, 15, 17] {
+                    let first = &input[..(first_blocks * BLOCK_LEN)];
+                    let last = &input[(first_blocks * BLOCK_LEN)..][..(last_blocks * BLOCK_LEN)];
+                    let tag = |ifma: bool| {
+                        let mut ctx = Context::from_key(Key::from(key.clone()));
+                        if !ifma {
+                            ctx.ifma = None;
+                        }
+                        ctx.update_blocks(first);
+                        ctx.update_block(Block::from(&[0x3c; BLOCK_LEN]), Pad::AlreadyPadded);
+                        ctx.update_blocks(last);
+                        let Tag(tag) = ctx.finish();
+                        tag
+                    };
+                    assert_eq!(tag(true).as_ref(), tag(false).as_ref());
+                }
+            }
+        }
+    }
 }
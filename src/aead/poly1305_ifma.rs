@@ -0,0 +1,290 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Poly1305 using the AVX-512 IFMA instructions, which multiply eight pairs
+//! of 52-bit integers per instruction.
+//!
+//! Field elements are represented in base 2<sup>44</sup>, as three limbs of
+//! 44, 44, and 42 bits, so that the products of limbs fit in the 104 bits
+//! that the instructions compute. Eight blocks are authenticated at a time,
+//! each lane accumulating every eighth block with r<sup>8</sup>, as in
+//! OpenSSL's `poly1305_blocks_vpmadd52_4x`.
+
+use super::block::{Block, BLOCK_LEN};
+use crate::{cpu, zeroize};
+use core::arch::x86_64::*;
+
+// The number of blocks that are authenticated at a time.
+const WIDE_BLOCKS: usize = 8;
+const WIDE_LEN: usize = WIDE_BLOCKS * BLOCK_LEN;
+
+const MASK_44: u64 = (1 << 44) - 1;
+const MASK_42: u64 = (1 << 42) - 1;
+
+/// Whether the CPU supports everything this implementation uses.
+pub(super) fn is_supported() -> bool {
+    let features = cpu::features();
+    features.avx512f() && features.avx512ifma()
+}
+
+type Limbs = [u64; 3];
+
+pub(super) struct State {
+    h: Limbs,
+    r: Limbs,
+
+    // `powers[j][i]` is limb `j` of r^(8 - i), computed by the first call to
+    // `blocks()` that has enough input to use them, so that short messages,
+    // e.g. most packets, don't pay for them.
+    powers: Option<[[u64; WIDE_BLOCKS]; 3]>,
+}
+
+impl State {
+    /// Must only be called if `is_supported()`.
+    pub(super) fn new(key: &Block) -> Self {
+        let r = u128::from_le_bytes(*key.as_ref()) & 0x0ffffffc_0ffffffc_0ffffffc_0fffffff;
+        Self {
+            h: [0; 3],
+            r: [(r as u64) & MASK_44, ((r >> 44) as u64) & MASK_44, (r >> 88) as u64],
+            powers: None,
+        }
+    }
+
+    /// Overwrites the key and the state with zeros.
+    pub(super) fn zeroize(&mut self) {
+        zeroize::zeroize_value(&mut self.h);
+        zeroize::zeroize_value(&mut self.r);
+        if let Some(powers) = &mut self.powers {
+            zeroize::zeroize_value(powers);
+        }
+    }
+
+    /// Authenticates `input`, whose length is a multiple of `BLOCK_LEN`. If
+    /// `pad` is true, 2<sup>128</sup> is added to each block.
+    pub(super) fn blocks(&mut self, input: &[u8], pad: bool) {
+        debug_assert_eq!(input.len() % BLOCK_LEN, 0);
+        let hibit = if pad { 1 << 40 } else { 0 };
+
+        let wide_len = input.len() - (input.len() % WIDE_LEN);
+        let (wide, rest) = input.split_at(wide_len);
+        if !wide.is_empty() {
+            let r = self.r;
+            let powers = self.powers.get_or_insert_with(|| powers(&r));
+            self.h = unsafe { blocks_wide(&self.h, powers, wide, hibit) };
+        }
+
+        for block in rest.chunks(BLOCK_LEN) {
+            let lo = u64::from_le_bytes(block_word(block, 0));
+            let hi = u64::from_le_bytes(block_word(block, 1));
+            let h = [
+                self.h[0] + (lo & MASK_44),
+                self.h[1] + (((lo >> 44) | (hi << 20)) & MASK_44),
+                self.h[2] + ((hi >> 24) | hibit),
+            ];
+            self.h = mul(&h, &self.r);
+        }
+    }
+
+    pub(super) fn emit(&self, nonce: &Block) -> Block {
+        // Reduce `h` completely; after the carries `h` < 2p, so subtracting p
+        // once is enough.
+        let mut h = self.h;
+        h[1] += h[0] >> 44;
+        h[0] &= MASK_44;
+        h[2] += h[1] >> 44;
+        h[1] &= MASK_44;
+        h[0] += (h[2] >> 42) * 5;
+        h[2] &= MASK_42;
+        h[1] += h[0] >> 44;
+        h[0] &= MASK_44;
+        h[2] += h[1] >> 44;
+        h[1] &= MASK_44;
+
+        let mut g = [h[0] + 5, h[1], h[2]];
+        g[1] += g[0] >> 44;
+        g[0] &= MASK_44;
+        g[2] += g[1] >> 44;
+        g[1] &= MASK_44;
+        // All ones if `h + 5 >= 2^130`, i.e. `h >= p`, and zero otherwise.
+        let use_g = 0u64.wrapping_sub(g[2] >> 42);
+        g[2] &= MASK_42;
+        for (h, g) in h.iter_mut().zip(&g) {
+            *h = (*g & use_g) | (*h & !use_g);
+        }
+
+        let h = u128::from(h[0]) | (u128::from(h[1]) << 44) | (u128::from(h[2]) << 88);
+        let tag = h.wrapping_add(u128::from_le_bytes(*nonce.as_ref()));
+        Block::from(&tag.to_le_bytes())
+    }
+}
+
+fn block_word(block: &[u8], i: usize) -> [u8; 8] {
+    let mut word = [0; 8];
+    word.copy_from_slice(&block[(i * 8)..][..8]);
+    word
+}
+
+// Returns `a * b`, partially reduced, for limbs of at most 45, 45, and 43
+// bits. The limbs of the product have at most 44, 44 + 1, and 42 bits.
+fn mul(a: &Limbs, b: &Limbs) -> Limbs {
+    // 2^132 = 4 * 2^130, which is congruent to 4 * 5 modulo p.
+    let s1 = b[1] * 20;
+    let s2 = b[2] * 20;
+    let m = |x: u64, y: u64| u128::from(x) * u128::from(y);
+
+    let d0 = m(a[0], b[0]) + m(a[1], s2) + m(a[2], s1);
+    let d1 = m(a[0], b[1]) + m(a[1], b[0]) + m(a[2], s2);
+    let d2 = m(a[0], b[2]) + m(a[1], b[1]) + m(a[2], b[0]);
+
+    let d1 = d1 + (d0 >> 44);
+    let d2 = d2 + (d1 >> 44);
+    let mut h = [(d0 as u64) & MASK_44, (d1 as u64) & MASK_44, (d2 as u64) & MASK_42];
+    h[0] += ((d2 >> 42) as u64) * 5;
+    h[1] += h[0] >> 44;
+    h[0] &= MASK_44;
+    h
+}
+
+fn powers(r: &Limbs) -> [[u64; WIDE_BLOCKS]; 3] {
+    let mut powers = [[0; WIDE_BLOCKS]; 3];
+    let mut power = *r;
+    for i in (0..WIDE_BLOCKS).rev() {
+        for (limbs, limb) in powers.iter_mut().zip(&power) {
+            limbs[i] = *limb;
+        }
+        power = mul(&power, r);
+    }
+    powers
+}
+
+// The vector equivalent of `mul()`, for each lane.
+#[inline]
+#[target_feature(enable = "avx512f,avx512ifma")]
+unsafe fn mul_wide(a: &[__m512i; 3], b: &[__m512i; 3]) -> [__m512i; 3] {
+    let s1 = times_20(b[1]);
+    let s2 = times_20(b[2]);
+    let zero = _mm512_setzero_si512();
+
+    // Each product is the sum of its low 52 bits and its high 52 bits
+    // shifted left by 52.
+    let mut lo = [zero; 3];
+    let mut hi = [zero; 3];
+    for &(i, x, y) in &[
+        (0, a[0], b[0]),
+        (0, a[1], s2),
+        (0, a[2], s1),
+        (1, a[0], b[1]),
+        (1, a[1], b[0]),
+        (1, a[2], s2),
+        (2, a[0], b[2]),
+        (2, a[1], b[1]),
+        (2, a[2], b[0]),
+    ] {
+        lo[i] = _mm512_madd52lo_epu64(lo[i], x, y);
+        hi[i] = _mm512_madd52hi_epu64(hi[i], x, y);
+    }
+
+    let mask_44 = _mm512_set1_epi64(MASK_44 as i64);
+    let mask_42 = _mm512_set1_epi64(MASK_42 as i64);
+    let h0 = _mm512_and_si512(lo[0], mask_44);
+    let carry = _mm512_add_epi64(_mm512_srli_epi64(lo[0], 44), _mm512_slli_epi64(hi[0], 8));
+    let d1 = _mm512_add_epi64(lo[1], carry);
+    let h1 = _mm512_and_si512(d1, mask_44);
+    let carry = _mm512_add_epi64(_mm512_srli_epi64(d1, 44), _mm512_slli_epi64(hi[1], 8));
+    let d2 = _mm512_add_epi64(lo[2], carry);
+    let h2 = _mm512_and_si512(d2, mask_42);
+    let carry = _mm512_add_epi64(_mm512_srli_epi64(d2, 42), _mm512_slli_epi64(hi[2], 10));
+    let h0 = _mm512_add_epi64(h0, _mm512_add_epi64(carry, _mm512_slli_epi64(carry, 2)));
+    let h1 = _mm512_add_epi64(h1, _mm512_srli_epi64(h0, 44));
+    let h0 = _mm512_and_si512(h0, mask_44);
+    [h0, h1, h2]
+}
+
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn times_20(a: __m512i) -> __m512i {
+    _mm512_add_epi64(_mm512_slli_epi64(a, 4), _mm512_slli_epi64(a, 2))
+}
+
+// Loads the limbs of `WIDE_BLOCKS` blocks, one block per lane.
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn load_wide(input: &[u8], hibit: u64) -> [__m512i; 3] {
+    debug_assert_eq!(input.len(), WIDE_LEN);
+    let a = _mm512_loadu_si512(input.as_ptr() as *const _);
+    let b = _mm512_loadu_si512(input[(WIDE_LEN / 2)..].as_ptr() as *const _);
+    let lo = _mm512_permutex2var_epi64(a, _mm512_set_epi64(14, 12, 10, 8, 6, 4, 2, 0), b);
+    let hi = _mm512_permutex2var_epi64(a, _mm512_set_epi64(15, 13, 11, 9, 7, 5, 3, 1), b);
+
+    let mask_44 = _mm512_set1_epi64(MASK_44 as i64);
+    let m0 = _mm512_and_si512(lo, mask_44);
+    let m1 = _mm512_or_si512(_mm512_srli_epi64(lo, 44), _mm512_slli_epi64(hi, 20));
+    let m1 = _mm512_and_si512(m1, mask_44);
+    let m2 = _mm512_or_si512(_mm512_srli_epi64(hi, 24), _mm512_set1_epi64(hibit as i64));
+    [m0, m1, m2]
+}
+
+// Returns `h` updated with `input`, whose length is a non-zero multiple of
+// `WIDE_LEN`.
+#[target_feature(enable = "avx512f,avx512ifma")]
+unsafe fn blocks_wide(
+    h: &Limbs, powers: &[[u64; WIDE_BLOCKS]; 3], input: &[u8], hibit: u64,
+) -> Limbs {
+    let mut batches = input.chunks(WIDE_LEN);
+    let mut acc = match batches.next() {
+        Some(batch) => load_wide(batch, hibit),
+        None => return *h,
+    };
+    // `h` is multiplied by r^8 along with the first block, in lane 0.
+    for (acc, h) in acc.iter_mut().zip(h) {
+        *acc = _mm512_add_epi64(*acc, _mm512_set_epi64(0, 0, 0, 0, 0, 0, 0, *h as i64));
+    }
+
+    let r8 = [
+        _mm512_set1_epi64(powers[0][0] as i64),
+        _mm512_set1_epi64(powers[1][0] as i64),
+        _mm512_set1_epi64(powers[2][0] as i64),
+    ];
+    for batch in batches {
+        let product = mul_wide(&acc, &r8);
+        let m = load_wide(batch, hibit);
+        for ((acc, product), m) in acc.iter_mut().zip(&product).zip(&m) {
+            *acc = _mm512_add_epi64(*product, *m);
+        }
+    }
+
+    // Lane `i` accumulated the blocks `i`, `i + 8`, ...; the last of them
+    // is followed by `8 - i - 1` blocks, so it is multiplied by r^(8 - i).
+    let r = [
+        _mm512_loadu_si512(powers[0].as_ptr() as *const _),
+        _mm512_loadu_si512(powers[1].as_ptr() as *const _),
+        _mm512_loadu_si512(powers[2].as_ptr() as *const _),
+    ];
+    let acc = mul_wide(&acc, &r);
+
+    let mut h = [
+        _mm512_reduce_add_epi64(acc[0]) as u64,
+        _mm512_reduce_add_epi64(acc[1]) as u64,
+        _mm512_reduce_add_epi64(acc[2]) as u64,
+    ];
+    h[1] += h[0] >> 44;
+    h[0] &= MASK_44;
+    h[2] += h[1] >> 44;
+    h[1] &= MASK_44;
+    h[0] += (h[2] >> 42) * 5;
+    h[2] &= MASK_42;
+    h[1] += h[0] >> 44;
+    h[0] &= MASK_44;
+    h
+}
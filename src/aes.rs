@@ -0,0 +1,97 @@
+// Copyright 2015-2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The AES block cipher, for the modes that are implemented in Rust.
+//!
+//! AES-GCM doesn't use this; it keeps its own copy of the key schedule in the
+//! context that the C code uses.
+
+use crate::{c, cpu, error, zeroize};
+
+pub const BLOCK_LEN: usize = 16;
+
+pub type Block = [u8; BLOCK_LEN];
+
+/// An AES-128 or AES-256 encryption key.
+pub struct Key(AES_KEY);
+
+impl Key {
+    pub fn new(key: &[u8]) -> Result<Self, error::Unspecified> {
+        let key_bits = match key.len() {
+            16 => 128,
+            32 => 256,
+            _ => {
+                return Err(error::Unspecified);
+            },
+        };
+        cpu::cache_detected_features();
+        let mut aes_key = AES_KEY {
+            rd_key: [0; 4 * (MAX_ROUNDS + 1)],
+            rounds: 0,
+        };
+        unsafe {
+            GFp_AES_set_encrypt_key(key.as_ptr(), key_bits, &mut aes_key);
+        }
+        Ok(Key(aes_key))
+    }
+
+    pub fn encrypt_block(&self, block: &mut Block) {
+        unsafe {
+            GFp_AES_encrypt(block.as_ptr(), block.as_mut_ptr(), &self.0);
+        }
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.0.rd_key) }
+}
+
+// Keep this in sync with `AES_MAXNR` in aes.h.
+const MAX_ROUNDS: usize = 14;
+
+// Keep this in sync with `AES_KEY` in aes.h.
+#[repr(C)]
+struct AES_KEY {
+    rd_key: [u32; 4 * (MAX_ROUNDS + 1)],
+    rounds: c::uint,
+}
+
+extern "C" {
+    fn GFp_AES_set_encrypt_key(key: *const u8, bits: c::uint, aes_key: *mut AES_KEY);
+    fn GFp_AES_encrypt(in_: *const u8, out: *mut u8, key: *const AES_KEY);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    pub fn test_aes() {
+        test::from_file("src/aead/aes_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key = test_case.consume_bytes("Key");
+            let input = test_case.consume_bytes("Input");
+            let expected_output = test_case.consume_bytes("Output");
+
+            let key = Key::new(&key).unwrap();
+            let mut block = [0u8; BLOCK_LEN];
+            block.copy_from_slice(&input);
+            key.encrypt_block(&mut block);
+            assert_eq!(&block[..], &expected_output[..]);
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,334 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! AES-CBC with PKCS#7 padding, for file formats that require it.
+//!
+//! CBC mode is not an AEAD and must not be used for new protocols. It is only
+//! provided to interoperate with existing formats. Callers must authenticate
+//! the ciphertext before decrypting it unless, like for password-protected
+//! PKCS#8 keys, the format gives them no way to do so.
+//!
+//! Encryption uses the same block function as AES-GCM. Decryption needs the
+//! inverse cipher, which isn't otherwise needed by *ring*; it is implemented
+//! here in portable code that computes the S-box arithmetically instead of
+//! using lookup tables, so that it doesn't leak the key or the plaintext
+//! through the cache. It is slow, but the formats that need it only decrypt
+//! small amounts of data.
+
+use crate::{aes, error, zeroize};
+use alloc::vec::Vec;
+
+pub use crate::aes::BLOCK_LEN;
+
+use crate::aes::Block;
+
+const MAX_ROUNDS: usize = 14;
+
+/// An AES-128 or AES-256 key.
+pub struct Key {
+    encrypt_key: aes::Key,
+    round_keys: [Block; MAX_ROUNDS + 1],
+    rounds: usize,
+}
+
+impl Key {
+    pub fn new(key: &[u8]) -> Result<Self, error::Unspecified> {
+        let rounds = match key.len() {
+            16 => 10,
+            32 => 14,
+            _ => {
+                return Err(error::Unspecified);
+            },
+        };
+        Ok(Self {
+            encrypt_key: aes::Key::new(key)?,
+            round_keys: expand_key(key, rounds),
+            rounds,
+        })
+    }
+
+    pub fn encrypt_block(&self, block: &mut Block) { self.encrypt_key.encrypt_block(block) }
+
+    // FIPS 197 Section 5.3.
+    pub fn decrypt_block(&self, block: &mut Block) {
+        xor_block(block, &self.round_keys[self.rounds]);
+        for round in (1..self.rounds).rev() {
+            inv_shift_rows(block);
+            inv_sub_bytes(block);
+            xor_block(block, &self.round_keys[round]);
+            inv_mix_columns(block);
+        }
+        inv_shift_rows(block);
+        inv_sub_bytes(block);
+        xor_block(block, &self.round_keys[0]);
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.round_keys) }
+}
+
+/// Returns `plaintext`, padded as described in [RFC 5652 Section 6.3] and
+/// encrypted in CBC mode.
+///
+/// [RFC 5652 Section 6.3]: https://tools.ietf.org/html/rfc5652#section-6.3
+pub fn encrypt_padded(key: &Key, iv: &Block, plaintext: &[u8]) -> Vec<u8> {
+    let padding_len = BLOCK_LEN - (plaintext.len() % BLOCK_LEN);
+    let mut in_out = Vec::with_capacity(plaintext.len() + padding_len);
+    in_out.extend_from_slice(plaintext);
+    in_out.resize(plaintext.len() + padding_len, padding_len as u8);
+
+    let mut chain = *iv;
+    for block in in_out.chunks_mut(BLOCK_LEN) {
+        xor_block(&mut chain, block);
+        key.encrypt_block(&mut chain);
+        block.copy_from_slice(&chain);
+    }
+    in_out
+}
+
+/// Decrypts `ciphertext` and removes the padding added by
+/// `encrypt_padded()`.
+///
+/// The length of the padding is found without branching on the plaintext, but
+/// whether the padding is valid is returned as a single error.
+pub fn decrypt_padded(
+    key: &Key, iv: &Block, ciphertext: &[u8],
+) -> Result<Vec<u8>, error::Unspecified> {
+    if ciphertext.is_empty() || ciphertext.len() % BLOCK_LEN != 0 {
+        return Err(error::Unspecified);
+    }
+    let mut in_out = ciphertext.to_vec();
+    let mut chain = *iv;
+    for block in in_out.chunks_mut(BLOCK_LEN) {
+        let mut b = [0u8; BLOCK_LEN];
+        b.copy_from_slice(block);
+        let next_chain = b;
+        key.decrypt_block(&mut b);
+        xor_block(&mut b, &chain);
+        block.copy_from_slice(&b);
+        chain = next_chain;
+    }
+
+    // Check the padding in constant time with respect to its contents.
+    let last_block = &in_out[(in_out.len() - BLOCK_LEN)..];
+    let padding_len = last_block[BLOCK_LEN - 1];
+    let mut bad = ct_is_zero(padding_len) | ct_lt(BLOCK_LEN as u8, padding_len);
+    for (i, b) in last_block.iter().rev().enumerate() {
+        // Only bytes within the padding must equal `padding_len`.
+        let in_padding = ct_lt(i as u8, padding_len);
+        bad |= in_padding & !ct_is_zero(b ^ padding_len);
+    }
+    if bad != 0 {
+        zeroize::zeroize(&mut in_out);
+        return Err(error::Unspecified);
+    }
+    let len = in_out.len() - usize::from(padding_len);
+    in_out.truncate(len);
+    Ok(in_out)
+}
+
+// Returns 0xff if `a == 0`, or 0 otherwise.
+fn ct_is_zero(a: u8) -> u8 {
+    let a = u32::from(a);
+    ((a.wrapping_sub(1) >> 8) & 0xff) as u8
+}
+
+// Returns 0xff if `a < b`, or 0 otherwise.
+fn ct_lt(a: u8, b: u8) -> u8 {
+    let d = u32::from(a).wrapping_sub(u32::from(b));
+    ((d >> 8) & 0xff) as u8
+}
+
+fn xor_block(a: &mut Block, b: &[u8]) {
+    for (a, b) in a.iter_mut().zip(b) {
+        *a ^= *b;
+    }
+}
+
+// Multiplication in GF(2**8) modulo x**8 + x**4 + x**3 + x + 1, without
+// branches or table lookups.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut r = 0;
+    for _ in 0..8 {
+        r ^= a & 0u8.wrapping_sub(b & 1);
+        a = (a << 1) ^ (0x1b & 0u8.wrapping_sub(a >> 7));
+        b >>= 1;
+    }
+    r
+}
+
+// Returns `a**254`, which is the multiplicative inverse of `a`, or zero if `a`
+// is zero.
+fn gf_inv(a: u8) -> u8 {
+    let mut square = gf_mul(a, a);
+    let mut result = square;
+    for _ in 0..6 {
+        square = gf_mul(square, square);
+        result = gf_mul(result, square);
+    }
+    result
+}
+
+// FIPS 197 Section 5.1.1.
+fn sub_byte(a: u8) -> u8 {
+    let b = gf_inv(a);
+    b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63
+}
+
+// FIPS 197 Section 5.3.2.
+fn inv_sub_byte(a: u8) -> u8 {
+    gf_inv(a.rotate_left(1) ^ a.rotate_left(3) ^ a.rotate_left(6) ^ 0x05)
+}
+
+fn inv_sub_bytes(block: &mut Block) {
+    for b in block.iter_mut() {
+        *b = inv_sub_byte(*b);
+    }
+}
+
+// The state is stored column-major, as in FIPS 197 Section 3.4, so row `r` of
+// column `c` is at `block[4 * c + r]`.
+fn inv_shift_rows(block: &mut Block) {
+    let copy = *block;
+    for c in 0..4 {
+        for r in 1..4 {
+            block[(4 * ((c + r) % 4)) + r] = copy[(4 * c) + r];
+        }
+    }
+}
+
+fn inv_mix_columns(block: &mut Block) {
+    for column in block.chunks_mut(4) {
+        let (a0, a1, a2, a3) = (column[0], column[1], column[2], column[3]);
+        column[0] = gf_mul(a0, 0x0e) ^ gf_mul(a1, 0x0b) ^ gf_mul(a2, 0x0d) ^ gf_mul(a3, 0x09);
+        column[1] = gf_mul(a0, 0x09) ^ gf_mul(a1, 0x0e) ^ gf_mul(a2, 0x0b) ^ gf_mul(a3, 0x0d);
+        column[2] = gf_mul(a0, 0x0d) ^ gf_mul(a1, 0x09) ^ gf_mul(a2, 0x0e) ^ gf_mul(a3, 0x0b);
+        column[3] = gf_mul(a0, 0x0b) ^ gf_mul(a1, 0x0d) ^ gf_mul(a2, 0x09) ^ gf_mul(a3, 0x0e);
+    }
+}
+
+// FIPS 197 Section 5.2.
+fn expand_key(key: &[u8], rounds: usize) -> [Block; MAX_ROUNDS + 1] {
+    let nk = key.len() / 4;
+    let mut w = [[0u8; 4]; 4 * (MAX_ROUNDS + 1)];
+    for (w, k) in w.iter_mut().zip(key.chunks(4)) {
+        w.copy_from_slice(k);
+    }
+    let mut rcon = 1u8;
+    for i in nk..(4 * (rounds + 1)) {
+        let mut temp = w[i - 1];
+        if i % nk == 0 {
+            temp = [
+                sub_byte(temp[1]) ^ rcon,
+                sub_byte(temp[2]),
+                sub_byte(temp[3]),
+                sub_byte(temp[0]),
+            ];
+            rcon = gf_mul(rcon, 2);
+        } else if nk > 6 && i % nk == 4 {
+            for t in temp.iter_mut() {
+                *t = sub_byte(*t);
+            }
+        }
+        for j in 0..4 {
+            w[i][j] = w[i - nk][j] ^ temp[j];
+        }
+    }
+
+    let mut round_keys = [[0u8; BLOCK_LEN]; MAX_ROUNDS + 1];
+    for (round_key, words) in round_keys.iter_mut().zip(w.chunks(4)) {
+        for (out, word) in round_key.chunks_mut(4).zip(words) {
+            out.copy_from_slice(word);
+        }
+    }
+    round_keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_sub_byte() {
+        // FIPS 197 Figure 7 and Figure 14.
+        assert_eq!(sub_byte(0x00), 0x63);
+        assert_eq!(sub_byte(0x53), 0xed);
+        assert_eq!(inv_sub_byte(0x63), 0x00);
+        assert_eq!(inv_sub_byte(0xed), 0x53);
+        for a in 0..=255u8 {
+            assert_eq!(inv_sub_byte(sub_byte(a)), a);
+        }
+    }
+
+    #[test]
+    fn test_decrypt_block() {
+        test::from_file("src/aead/aes_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key = test_case.consume_bytes("Key");
+            let plaintext = test_case.consume_bytes("Input");
+            let ciphertext = test_case.consume_bytes("Output");
+
+            let key = Key::new(&key).unwrap();
+            let mut block = [0u8; BLOCK_LEN];
+            block.copy_from_slice(&ciphertext);
+            key.decrypt_block(&mut block);
+            assert_eq!(&block[..], &plaintext[..]);
+            key.encrypt_block(&mut block);
+            assert_eq!(&block[..], &ciphertext[..]);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_cbc() {
+        test::from_file("src/aes_cbc_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key = test_case.consume_bytes("Key");
+            let iv = test_case.consume_bytes("IV");
+            let plaintext = test_case.consume_bytes("Plaintext");
+            let ciphertext = test_case.consume_bytes("Ciphertext");
+
+            let key = Key::new(&key).unwrap();
+            let mut iv_block = [0u8; BLOCK_LEN];
+            iv_block.copy_from_slice(&iv);
+            assert_eq!(encrypt_padded(&key, &iv_block, &plaintext), ciphertext);
+            assert_eq!(decrypt_padded(&key, &iv_block, &ciphertext).unwrap(), plaintext);
+
+            // Truncated ciphertexts and ciphertexts with corrupted padding
+            // are rejected.
+            let truncated = &ciphertext[..(ciphertext.len() - 1)];
+            assert!(decrypt_padded(&key, &iv_block, truncated).is_err());
+            let mut corrupted = iv.clone();
+            corrupted.extend_from_slice(&ciphertext);
+            let len = corrupted.len();
+            corrupted[len - BLOCK_LEN - 1] ^= 0x80;
+            let (corrupted_iv, corrupted) = corrupted.split_at(BLOCK_LEN);
+            iv_block.copy_from_slice(corrupted_iv);
+            assert!(decrypt_padded(&key, &iv_block, corrupted).is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_key_len() {
+        assert!(Key::new(&[0u8; 16]).is_ok());
+        assert!(Key::new(&[0u8; 24]).is_err());
+        assert!(Key::new(&[0u8; 32]).is_ok());
+        assert!(Key::new(&[]).is_err());
+    }
+}
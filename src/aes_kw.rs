@@ -0,0 +1,269 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! AES key wrap ([RFC 3394]) and AES key wrap with padding ([RFC 5649]).
+//!
+//! Key wrap with padding uses the wrapping function of key wrap with an
+//! alternative initial value that encodes the length of the key data, so that
+//! key data of any length can be wrapped. Unwrapping uses the inverse cipher
+//! of `aes_cbc`, which is slow, but keys are short.
+//!
+//! [RFC 3394]: https://tools.ietf.org/html/rfc3394
+//! [RFC 5649]: https://tools.ietf.org/html/rfc5649
+
+use crate::{
+    aes_cbc::{self, Key},
+    constant_time, error, zeroize,
+};
+use alloc::vec::Vec;
+
+const SEMIBLOCK_LEN: usize = 8;
+
+type Semiblock = [u8; SEMIBLOCK_LEN];
+
+/// The largest key data that can be wrapped, in bytes.
+pub const MAX_KEY_DATA_LEN: usize = 0xffff_ffff;
+
+// RFC 3394 Section 2.2.3.1.
+const IV: Semiblock = [0xa6; SEMIBLOCK_LEN];
+
+// RFC 5649 Section 3.
+const AIV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+/// Wraps `key_data`, whose length must be a multiple of 8 bytes and at least
+/// 16 bytes, without padding.
+pub fn wrap(kek: &Key, key_data: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+    if key_data.len() < 2 * SEMIBLOCK_LEN
+        || key_data.len() % SEMIBLOCK_LEN != 0
+        || key_data.len() > MAX_KEY_DATA_LEN
+    {
+        return Err(error::Unspecified);
+    }
+    let mut out = Vec::with_capacity(SEMIBLOCK_LEN + key_data.len());
+    out.extend_from_slice(&IV);
+    out.extend_from_slice(key_data);
+    let (a, r) = out.split_at_mut(SEMIBLOCK_LEN);
+    let mut a = semiblock(a);
+    w(kek, &mut a, r);
+    out[..SEMIBLOCK_LEN].copy_from_slice(&a);
+    Ok(out)
+}
+
+/// Unwraps `wrapped`, which was returned by `wrap()`, returning the key data.
+pub fn unwrap(kek: &Key, wrapped: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+    if wrapped.len() < 3 * SEMIBLOCK_LEN || wrapped.len() % SEMIBLOCK_LEN != 0 {
+        return Err(error::Unspecified);
+    }
+    let mut out = wrapped.to_vec();
+    let (a, r) = out.split_at_mut(SEMIBLOCK_LEN);
+    let mut a = semiblock(a);
+    w_inverse(kek, &mut a, r);
+    let _ = out.drain(..SEMIBLOCK_LEN);
+    if constant_time::verify_slices_are_equal(&a, &IV).is_err() {
+        zeroize::zeroize(&mut out);
+        return Err(error::Unspecified);
+    }
+    Ok(out)
+}
+
+/// Wraps `key_data`, which must not be empty or longer than
+/// `MAX_KEY_DATA_LEN`.
+pub fn wrap_padded(kek: &Key, key_data: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+    if key_data.is_empty() || key_data.len() > MAX_KEY_DATA_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut aiv = [0u8; SEMIBLOCK_LEN];
+    aiv[..4].copy_from_slice(&AIV_PREFIX);
+    aiv[4..].copy_from_slice(&(key_data.len() as u32).to_be_bytes());
+
+    let padded_len = (key_data.len() + SEMIBLOCK_LEN - 1) / SEMIBLOCK_LEN * SEMIBLOCK_LEN;
+    let mut out = Vec::with_capacity(SEMIBLOCK_LEN + padded_len);
+    out.extend_from_slice(&aiv);
+    out.extend_from_slice(key_data);
+    out.resize(SEMIBLOCK_LEN + padded_len, 0);
+
+    if padded_len == SEMIBLOCK_LEN {
+        // RFC 5649 Section 4.1, step 2: a single block is just encrypted.
+        let mut block = [0u8; aes_cbc::BLOCK_LEN];
+        block.copy_from_slice(&out);
+        kek.encrypt_block(&mut block);
+        out.copy_from_slice(&block);
+        zeroize::zeroize(&mut block);
+    } else {
+        let (a, r) = out.split_at_mut(SEMIBLOCK_LEN);
+        let mut a = semiblock(a);
+        w(kek, &mut a, r);
+        out[..SEMIBLOCK_LEN].copy_from_slice(&a);
+    }
+    Ok(out)
+}
+
+/// Unwraps `wrapped`, which was returned by `wrap_padded()`, returning the key
+/// data.
+pub fn unwrap_padded(kek: &Key, wrapped: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+    if wrapped.len() < 2 * SEMIBLOCK_LEN || wrapped.len() % SEMIBLOCK_LEN != 0 {
+        return Err(error::Unspecified);
+    }
+    let mut out = wrapped.to_vec();
+    let a = if out.len() == 2 * SEMIBLOCK_LEN {
+        let mut block = [0u8; aes_cbc::BLOCK_LEN];
+        block.copy_from_slice(&out);
+        kek.decrypt_block(&mut block);
+        out.copy_from_slice(&block);
+        zeroize::zeroize(&mut block);
+        semiblock(&out[..SEMIBLOCK_LEN])
+    } else {
+        let (a, r) = out.split_at_mut(SEMIBLOCK_LEN);
+        let mut a = semiblock(a);
+        w_inverse(kek, &mut a, r);
+        a
+    };
+    let _ = out.drain(..SEMIBLOCK_LEN);
+
+    // RFC 5649 Section 3: check the AIV and that all of the padding is zero.
+    let key_data_len = u32::from_be_bytes([a[4], a[5], a[6], a[7]]) as usize;
+    let valid = constant_time::verify_slices_are_equal(&a[..4], &AIV_PREFIX).is_ok()
+        && key_data_len <= out.len()
+        && key_data_len + SEMIBLOCK_LEN > out.len();
+    if !valid || !out[key_data_len..].iter().all(|b| *b == 0) {
+        zeroize::zeroize(&mut out);
+        return Err(error::Unspecified);
+    }
+    out.truncate(key_data_len);
+    Ok(out)
+}
+
+fn semiblock(bytes: &[u8]) -> Semiblock {
+    let mut r = [0u8; SEMIBLOCK_LEN];
+    r.copy_from_slice(bytes);
+    r
+}
+
+// RFC 3394 Section 2.2.1, the index-based version.
+fn w(kek: &Key, a: &mut Semiblock, r: &mut [u8]) {
+    let n = r.len() / SEMIBLOCK_LEN;
+    let mut b = [0u8; aes_cbc::BLOCK_LEN];
+    for j in 0..6 {
+        for (i, r) in r.chunks_mut(SEMIBLOCK_LEN).enumerate() {
+            b[..SEMIBLOCK_LEN].copy_from_slice(a);
+            b[SEMIBLOCK_LEN..].copy_from_slice(r);
+            kek.encrypt_block(&mut b);
+            let t = ((n * j) + i + 1) as u64;
+            for (a, (b, t)) in a.iter_mut().zip(b.iter().zip(&t.to_be_bytes())) {
+                *a = b ^ t;
+            }
+            r.copy_from_slice(&b[SEMIBLOCK_LEN..]);
+        }
+    }
+    zeroize::zeroize(&mut b);
+}
+
+// RFC 3394 Section 2.2.2, the index-based version.
+fn w_inverse(kek: &Key, a: &mut Semiblock, r: &mut [u8]) {
+    let n = r.len() / SEMIBLOCK_LEN;
+    let mut b = [0u8; aes_cbc::BLOCK_LEN];
+    for j in (0..6).rev() {
+        for (i, r) in r.chunks_mut(SEMIBLOCK_LEN).enumerate().rev() {
+            let t = ((n * j) + i + 1) as u64;
+            for (b, (a, t)) in b.iter_mut().zip(a.iter().zip(&t.to_be_bytes())) {
+                *b = a ^ t;
+            }
+            b[SEMIBLOCK_LEN..].copy_from_slice(r);
+            kek.decrypt_block(&mut b);
+            a.copy_from_slice(&b[..SEMIBLOCK_LEN]);
+            r.copy_from_slice(&b[SEMIBLOCK_LEN..]);
+        }
+    }
+    zeroize::zeroize(&mut b);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_aes_kw() {
+        test::from_file("src/aes_kw_tests.txt", |section, test_case| {
+            let kek = Key::new(&test_case.consume_bytes("KEK"))?;
+            let plaintext = test_case.consume_bytes("Plaintext");
+            let ciphertext = test_case.consume_bytes("Ciphertext");
+
+            match section {
+                "KW" => {
+                    assert_eq!(wrap(&kek, &plaintext)?, ciphertext);
+                    assert_eq!(unwrap(&kek, &ciphertext)?, plaintext);
+
+                    // Any change to the ciphertext is detected.
+                    for i in 0..ciphertext.len() {
+                        let mut modified = ciphertext.clone();
+                        modified[i] ^= 1;
+                        assert!(unwrap(&kek, &modified).is_err());
+                    }
+
+                    // The wrapping function that `wrap_padded()` is built on.
+                    let mut a = IV;
+                    let mut r = plaintext.clone();
+                    w(&kek, &mut a, &mut r);
+                    assert_eq!(&a[..], &ciphertext[..SEMIBLOCK_LEN]);
+                    assert_eq!(&r[..], &ciphertext[SEMIBLOCK_LEN..]);
+                    w_inverse(&kek, &mut a, &mut r);
+                    assert_eq!(a, IV);
+                    assert_eq!(r, plaintext);
+                },
+                "KWP" => {
+                    assert_eq!(wrap_padded(&kek, &plaintext)?, ciphertext);
+                    assert_eq!(unwrap_padded(&kek, &ciphertext)?, plaintext);
+
+                    // Any change to the ciphertext is detected.
+                    for i in 0..ciphertext.len() {
+                        let mut modified = ciphertext.clone();
+                        modified[i] ^= 1;
+                        assert!(unwrap_padded(&kek, &modified).is_err());
+                    }
+                    assert!(unwrap_padded(&kek, &ciphertext[..ciphertext.len() - 1]).is_err());
+                    assert!(
+                        unwrap_padded(&kek, &ciphertext[..ciphertext.len() - SEMIBLOCK_LEN])
+                            .is_err()
+                    );
+                },
+                _ => unreachable!(),
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_aes_kw_lengths() {
+        let kek = Key::new(&[0; 16]).unwrap();
+        assert!(wrap_padded(&kek, &[]).is_err());
+        assert!(unwrap_padded(&kek, &[]).is_err());
+        assert!(unwrap_padded(&kek, &[0; SEMIBLOCK_LEN]).is_err());
+        assert!(wrap(&kek, &[0; SEMIBLOCK_LEN]).is_err());
+        assert!(wrap(&kek, &[0; 2 * SEMIBLOCK_LEN + 1]).is_err());
+        assert!(unwrap(&kek, &[0; 2 * SEMIBLOCK_LEN]).is_err());
+        assert!(unwrap(&kek, &[0; 3 * SEMIBLOCK_LEN + 1]).is_err());
+
+        // A key data length that is inconsistent with the ciphertext's length
+        // is rejected even though the integrity check value is correct.
+        let mut a = [0u8; SEMIBLOCK_LEN];
+        a[..4].copy_from_slice(&AIV_PREFIX);
+        a[7] = 17;
+        let mut r = [0u8; 2 * SEMIBLOCK_LEN];
+        w(&kek, &mut a, &mut r);
+        let mut wrapped = a.to_vec();
+        wrapped.extend_from_slice(&r);
+        assert!(unwrap_padded(&kek, &wrapped).is_err());
+    }
+}
@@ -0,0 +1,781 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The [age] v1 file encryption format.
+//!
+//! An age file is a text header followed by the binary payload. The header
+//! lists one stanza per recipient, each of which wraps the 16-byte file key
+//! for that recipient, and ends with an HMAC-SHA256 of the header, keyed by
+//! `HKDF-SHA256(file_key, "", "header")`:
+//!
+//! ```text
+//! age-encryption.org/v1
+//! -> X25519 <ephemeral share>
+//! <wrapped file key>
+//! -> scrypt <salt> <log2(N)>
+//! <wrapped file key>
+//! --- <MAC>
+//! ```
+//!
+//! The payload is a 16-byte nonce followed by the plaintext in 64 KiB chunks,
+//! each sealed with ChaCha20-Poly1305 under the key
+//! `HKDF-SHA256(file_key, nonce, "payload")` ("STREAM"). The nonce of a
+//! chunk is its index as an 11-byte big-endian integer followed by 1 for the
+//! last chunk and 0 for the others. `PayloadSealer` and `PayloadOpener` seal
+//! and open the chunks one by one; `encrypt()` and `decrypt()` do the whole
+//! file at once.
+//!
+//! X25519 recipients (`age1...`) and identities (`AGE-SECRET-KEY-1...`) and
+//! scrypt passphrases are supported; a passphrase-encrypted file must have a
+//! single scrypt stanza. Other kinds of stanzas are parsed and kept, so
+//! callers can handle them, and are otherwise ignored. The ASCII-armored
+//! (PEM) form isn't supported.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{age, rand};
+//!
+//! # fn encrypt_and_decrypt() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let identity = age::X25519Identity::generate(&rng)?;
+//! let recipient = age::X25519Recipient::from_bech32(&identity.recipient().to_bech32())?;
+//!
+//! let file_key = age::FileKey::generate(&rng)?;
+//! let stanza = recipient.wrap(&file_key, &rng)?;
+//! let file = age::encrypt(&file_key, vec![stanza], b"a secret", &rng)?;
+//!
+//! let plaintext = age::decrypt(&file, |stanzas| identity.unwrap(stanzas))?;
+//! assert_eq!(plaintext, b"a secret");
+//! # Ok(())
+//! # }
+//! # fn main() { encrypt_and_decrypt().unwrap() }
+//! ```
+//!
+//! [age]: https://age-encryption.org/v1
+
+use crate::{
+    aead, agreement, digest, ec, error, hkdf, hmac,
+    io::base64::{self, Alphabet, Padding},
+    rand, scrypt, zeroize,
+};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use untrusted;
+
+mod bech32;
+
+/// The length of a file key.
+pub const FILE_KEY_LEN: usize = 16;
+
+/// The length of the nonce at the start of the payload.
+pub const PAYLOAD_NONCE_LEN: usize = 16;
+
+/// The length of the plaintext of every chunk of the payload except the
+/// last, which may be shorter.
+pub const CHUNK_LEN: usize = 64 * 1024;
+
+/// The length of the tag that follows each sealed chunk.
+pub const TAG_LEN: usize = 16;
+
+const X25519_KEY_LEN: usize = 32;
+const WRAPPED_FILE_KEY_LEN: usize = FILE_KEY_LEN + TAG_LEN;
+const SCRYPT_SALT_LEN: usize = 16;
+const MAC_LEN: usize = digest::SHA256_OUTPUT_LEN;
+
+const VERSION_LINE: &[u8] = b"age-encryption.org/v1\n";
+const STANZA_PREFIX: &[u8] = b"-> ";
+const MAC_PREFIX: &[u8] = b"---";
+const LINE_LEN: usize = 64;
+
+const X25519_TAG: &str = "X25519";
+const X25519_LABEL: &[u8] = b"age-encryption.org/v1/X25519";
+const SCRYPT_TAG: &str = "scrypt";
+const SCRYPT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+
+const RECIPIENT_HRP: &str = "age";
+const IDENTITY_HRP: &str = "age-secret-key-";
+
+static BASE64: base64::Config = base64::Config::new(Alphabet::Standard, Padding::Omitted);
+
+/// The key that the payload is encrypted with, and that each stanza wraps.
+pub struct FileKey([u8; FILE_KEY_LEN]);
+
+impl FileKey {
+    /// Generates a new file key.
+    pub fn generate(rng: &rand::SecureRandom) -> Result<Self, error::Unspecified> {
+        let mut key = Self([0; FILE_KEY_LEN]);
+        rng.fill(&mut key.0)?;
+        Ok(key)
+    }
+
+    fn hkdf(&self, salt: &[u8], info: &[u8]) -> [u8; 32] {
+        let mut out = [0; 32];
+        let salt = hmac::SigningKey::new(&digest::SHA256, salt);
+        hkdf::extract_and_expand(&salt, &self.0, info, &mut out);
+        out
+    }
+}
+
+impl Drop for FileKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.0) }
+}
+
+/// A header stanza: its tag, e.g. `X25519`, its other arguments, and its
+/// decoded body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stanza {
+    /// The first argument, which identifies the kind of recipient.
+    pub tag: String,
+
+    /// The other arguments.
+    pub args: Vec<String>,
+
+    /// The body, e.g. the wrapped file key.
+    pub body: Vec<u8>,
+}
+
+/// An X25519 recipient, i.e. a public key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct X25519Recipient {
+    public_key: [u8; X25519_KEY_LEN],
+}
+
+impl X25519Recipient {
+    /// Parses an `age1...` recipient.
+    pub fn from_bech32(encoded: &str) -> Result<Self, error::Unspecified> {
+        let (hrp, public_key) = bech32::decode(encoded)?;
+        if hrp != RECIPIENT_HRP {
+            return Err(error::Unspecified);
+        }
+        Self::from_public_key(&public_key)
+    }
+
+    /// Constructs a recipient from the raw 32-byte X25519 public key.
+    pub fn from_public_key(public_key: &[u8]) -> Result<Self, error::Unspecified> {
+        if public_key.len() != X25519_KEY_LEN {
+            return Err(error::Unspecified);
+        }
+        let mut r = Self {
+            public_key: [0; X25519_KEY_LEN],
+        };
+        r.public_key.copy_from_slice(public_key);
+        Ok(r)
+    }
+
+    /// Encodes the recipient as `age1...`.
+    pub fn to_bech32(&self) -> String { bech32::encode(RECIPIENT_HRP, &self.public_key) }
+
+    /// The raw X25519 public key.
+    #[inline]
+    pub fn public_key_bytes(&self) -> &[u8] { &self.public_key }
+
+    /// Wraps `file_key` for this recipient, using a new ephemeral key.
+    pub fn wrap(
+        &self, file_key: &FileKey, rng: &rand::SecureRandom,
+    ) -> Result<Stanza, error::Unspecified> {
+        let ephemeral = ec::PrivateKey::generate(agreement::X25519.i.curve, rng)?;
+        let mut share = [0; X25519_KEY_LEN];
+        ephemeral.compute_public_key(agreement::X25519.i.curve, &mut share)?;
+        let mut wrap_key = x25519_wrap_key(&ephemeral, &self.public_key, &share, &self.public_key)?;
+        let body = seal_file_key(&wrap_key, file_key);
+        zeroize::zeroize(&mut wrap_key);
+        Ok(Stanza {
+            tag: X25519_TAG.into(),
+            args: vec![base64::encode(&share, &BASE64)],
+            body: body?,
+        })
+    }
+}
+
+/// An X25519 identity, i.e. a private key.
+pub struct X25519Identity {
+    private_key: ec::PrivateKey,
+    recipient: X25519Recipient,
+}
+
+impl X25519Identity {
+    /// Generates a new identity.
+    pub fn generate(rng: &rand::SecureRandom) -> Result<Self, error::Unspecified> {
+        Self::new(ec::PrivateKey::generate(agreement::X25519.i.curve, rng)?)
+    }
+
+    /// Parses an `AGE-SECRET-KEY-1...` identity.
+    pub fn from_bech32(encoded: &str) -> Result<Self, error::Unspecified> {
+        let (hrp, mut private_key) = bech32::decode(encoded)?;
+        let result = if hrp == IDENTITY_HRP {
+            ec::PrivateKey::from_bytes(
+                agreement::X25519.i.curve,
+                untrusted::Input::from(&private_key),
+            )
+        } else {
+            Err(error::Unspecified)
+        };
+        zeroize::zeroize(&mut private_key);
+        Self::new(result?)
+    }
+
+    fn new(private_key: ec::PrivateKey) -> Result<Self, error::Unspecified> {
+        let mut recipient = X25519Recipient {
+            public_key: [0; X25519_KEY_LEN],
+        };
+        private_key.compute_public_key(agreement::X25519.i.curve, &mut recipient.public_key)?;
+        Ok(Self {
+            private_key,
+            recipient,
+        })
+    }
+
+    /// Encodes the identity as `AGE-SECRET-KEY-1...`.
+    pub fn to_bech32(&self) -> String {
+        let encoded = bech32::encode(
+            IDENTITY_HRP,
+            self.private_key.bytes(agreement::X25519.i.curve),
+        );
+        encoded.to_ascii_uppercase()
+    }
+
+    /// The identity's recipient.
+    #[inline]
+    pub fn recipient(&self) -> &X25519Recipient { &self.recipient }
+
+    /// Unwraps the file key from the first of `stanzas` that is an X25519
+    /// stanza for this identity. Fails if there is none, or if an X25519
+    /// stanza is malformed.
+    pub fn unwrap(&self, stanzas: &[Stanza]) -> Result<FileKey, error::Unspecified> {
+        for stanza in stanzas.iter().filter(|stanza| stanza.tag == X25519_TAG) {
+            if stanza.args.len() != 1 || stanza.body.len() != WRAPPED_FILE_KEY_LEN {
+                return Err(error::Unspecified);
+            }
+            let share = base64::decode(&stanza.args[0], &BASE64)?;
+            if share.len() != X25519_KEY_LEN {
+                return Err(error::Unspecified);
+            }
+            let recipient = &self.recipient.public_key;
+            let mut wrap_key = x25519_wrap_key(&self.private_key, &share, &share, recipient)?;
+            let file_key = open_file_key(&wrap_key, &stanza.body);
+            zeroize::zeroize(&mut wrap_key);
+            if let Ok(file_key) = file_key {
+                return Ok(file_key);
+            }
+        }
+        Err(error::Unspecified)
+    }
+}
+
+/// Wraps `file_key` with a key derived from `passphrase` with scrypt, with
+/// N = 2**`log_n`, r = 8, and p = 1, and a new random salt.
+pub fn scrypt_wrap(
+    passphrase: &[u8], log_n: u8, file_key: &FileKey, rng: &rand::SecureRandom,
+) -> Result<Stanza, error::Unspecified> {
+    let mut salt = [0; SCRYPT_SALT_LEN];
+    rng.fill(&mut salt)?;
+    let mut wrap_key = scrypt_wrap_key(passphrase, &salt, log_n)?;
+    let body = seal_file_key(&wrap_key, file_key);
+    zeroize::zeroize(&mut wrap_key);
+    Ok(Stanza {
+        tag: SCRYPT_TAG.into(),
+        args: vec![base64::encode(&salt, &BASE64), log_n.to_string()],
+        body: body?,
+    })
+}
+
+/// Unwraps the file key from the scrypt stanza in `stanzas` with
+/// `passphrase`. Fails unless the scrypt stanza is the only stanza, as age
+/// requires, and its `log2(N)` is at most `max_log_n`, which bounds the work
+/// that an untrusted file can demand.
+pub fn scrypt_unwrap(
+    passphrase: &[u8], max_log_n: u8, stanzas: &[Stanza],
+) -> Result<FileKey, error::Unspecified> {
+    let stanza = match stanzas {
+        [stanza] if stanza.tag == SCRYPT_TAG => stanza,
+        _ => return Err(error::Unspecified),
+    };
+    if stanza.args.len() != 2 || stanza.body.len() != WRAPPED_FILE_KEY_LEN {
+        return Err(error::Unspecified);
+    }
+    let salt = base64::decode(&stanza.args[0], &BASE64)?;
+    let log_n = parse_decimal(&stanza.args[1]).ok_or(error::Unspecified)?;
+    if salt.len() != SCRYPT_SALT_LEN || log_n > max_log_n {
+        return Err(error::Unspecified);
+    }
+    let mut wrap_key = scrypt_wrap_key(passphrase, &salt, log_n)?;
+    let file_key = open_file_key(&wrap_key, &stanza.body);
+    zeroize::zeroize(&mut wrap_key);
+    file_key
+}
+
+/// A parsed or constructed header.
+pub struct Header {
+    stanzas: Vec<Stanza>,
+    // Everything up to and including `---`, which the MAC covers.
+    encoded: Vec<u8>,
+    mac: [u8; MAC_LEN],
+}
+
+impl Header {
+    /// Constructs the header with `stanzas`, which must not be empty, and
+    /// computes its MAC with `file_key`.
+    pub fn new(file_key: &FileKey, stanzas: Vec<Stanza>) -> Result<Self, error::Unspecified> {
+        if stanzas.is_empty() {
+            return Err(error::Unspecified);
+        }
+        let mut encoded = VERSION_LINE.to_vec();
+        for stanza in &stanzas {
+            if !is_valid_arg(&stanza.tag) || !stanza.args.iter().all(|arg| is_valid_arg(arg)) {
+                return Err(error::Unspecified);
+            }
+            encoded.extend_from_slice(STANZA_PREFIX);
+            encoded.extend_from_slice(stanza.tag.as_bytes());
+            for arg in &stanza.args {
+                encoded.push(b' ');
+                encoded.extend_from_slice(arg.as_bytes());
+            }
+            encoded.push(b'\n');
+            // Every line of the body is full except the last, which may be
+            // empty.
+            let body = base64::encode(&stanza.body, &BASE64);
+            let mut lines = body.as_bytes().chunks(LINE_LEN);
+            loop {
+                let line = lines.next().unwrap_or(&[]);
+                encoded.extend_from_slice(line);
+                encoded.push(b'\n');
+                if line.len() < LINE_LEN {
+                    break;
+                }
+            }
+        }
+        encoded.extend_from_slice(MAC_PREFIX);
+        let mac = header_mac(file_key, &encoded);
+        Ok(Self {
+            stanzas,
+            encoded,
+            mac,
+        })
+    }
+
+    /// Parses the header at the start of `file`, returning it and the rest of
+    /// `file`, the payload. The header must be encoded canonically. The MAC
+    /// isn't verified; see `verify()`.
+    pub fn parse(file: &[u8]) -> Result<(Self, &[u8]), error::Unspecified> {
+        let mut lines = Lines { input: file, pos: 0 };
+        if lines.next()? != &VERSION_LINE[..(VERSION_LINE.len() - 1)] {
+            return Err(error::Unspecified);
+        }
+        let mut stanzas = Vec::new();
+        loop {
+            let line_start = lines.pos;
+            let line = lines.next()?;
+            if line.starts_with(STANZA_PREFIX) {
+                stanzas.push(parse_stanza(&line[STANZA_PREFIX.len()..], &mut lines)?);
+            } else if line.starts_with(MAC_PREFIX) && line.get(MAC_PREFIX.len()) == Some(&b' ') {
+                let encoded_mac = core::str::from_utf8(&line[(MAC_PREFIX.len() + 1)..])
+                    .map_err(|_| error::Unspecified)?;
+                let decoded_mac = base64::decode(encoded_mac, &BASE64)?;
+                if decoded_mac.len() != MAC_LEN || stanzas.is_empty() {
+                    return Err(error::Unspecified);
+                }
+                let mut mac = [0; MAC_LEN];
+                mac.copy_from_slice(&decoded_mac);
+                let header = Self {
+                    stanzas,
+                    encoded: file[..(line_start + MAC_PREFIX.len())].to_vec(),
+                    mac,
+                };
+                return Ok((header, &file[lines.pos..]));
+            } else {
+                return Err(error::Unspecified);
+            }
+        }
+    }
+
+    /// The stanzas, in order.
+    #[inline]
+    pub fn stanzas(&self) -> &[Stanza] { &self.stanzas }
+
+    /// Verifies the header's MAC with `file_key`, which must be done before
+    /// using the file key to decrypt the payload.
+    pub fn verify(&self, file_key: &FileKey) -> Result<(), error::Unspecified> {
+        let mut mac_key = file_key.hkdf(&[], b"header");
+        let key = hmac::VerificationKey::new(&digest::SHA256, &mac_key);
+        zeroize::zeroize(&mut mac_key);
+        hmac::verify(&key, &self.encoded, &self.mac)
+    }
+
+    /// Encodes the header, including the final line break.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.encoded.clone();
+        out.push(b' ');
+        out.extend_from_slice(base64::encode(&self.mac, &BASE64).as_bytes());
+        out.push(b'\n');
+        out
+    }
+}
+
+/// Seals the chunks of a payload, in order.
+pub struct PayloadSealer {
+    key: aead::SealingKey,
+    counter: u64,
+    finished: bool,
+}
+
+impl PayloadSealer {
+    /// Starts sealing a payload with `file_key`. `nonce` is the nonce that
+    /// must be written at the start of the payload, which must be random.
+    pub fn new(file_key: &FileKey, nonce: &[u8; PAYLOAD_NONCE_LEN]) -> Self {
+        let mut key_bytes = file_key.hkdf(nonce, b"payload");
+        let key = aead::SealingKey::new_chacha20_poly1305(&key_bytes);
+        zeroize::zeroize(&mut key_bytes);
+        Self {
+            key,
+            counter: 0,
+            finished: false,
+        }
+    }
+
+    /// Seals the next chunk in place. The plaintext is
+    /// `in_out[..(in_out.len() - TAG_LEN)]`, which must be `CHUNK_LEN` bytes
+    /// unless `last` is true, and may be empty only if this is the only
+    /// chunk. The tag is written over the last `TAG_LEN` bytes.
+    pub fn seal_chunk_in_place(
+        &mut self, in_out: &mut [u8], last: bool,
+    ) -> Result<(), error::Unspecified> {
+        let nonce = chunk_nonce(self.counter, in_out.len(), last, self.finished)?;
+        let _ = aead::seal_in_place(&self.key, &nonce, &[], in_out, TAG_LEN)?;
+        self.counter += 1;
+        self.finished = last;
+        Ok(())
+    }
+}
+
+/// Opens the chunks of a payload, in order.
+pub struct PayloadOpener {
+    key: aead::OpeningKey,
+    counter: u64,
+    finished: bool,
+}
+
+impl PayloadOpener {
+    /// Starts opening a payload with `file_key`, which must have been checked
+    /// with `Header::verify()`, and the nonce from the start of the payload.
+    pub fn new(file_key: &FileKey, nonce: &[u8; PAYLOAD_NONCE_LEN]) -> Self {
+        let mut key_bytes = file_key.hkdf(nonce, b"payload");
+        let key = aead::OpeningKey::new_chacha20_poly1305(&key_bytes);
+        zeroize::zeroize(&mut key_bytes);
+        Self {
+            key,
+            counter: 0,
+            finished: false,
+        }
+    }
+
+    /// Opens the next sealed chunk in place, returning the plaintext. `last`
+    /// must be true exactly when the payload ends after this chunk, which is
+    /// how truncation is detected.
+    pub fn open_chunk_in_place<'a>(
+        &mut self, in_out: &'a mut [u8], last: bool,
+    ) -> Result<&'a mut [u8], error::Unspecified> {
+        let nonce = chunk_nonce(self.counter, in_out.len(), last, self.finished)?;
+        let plaintext = aead::open_in_place(&self.key, &nonce, &[], 0, in_out)?;
+        self.counter += 1;
+        self.finished = last;
+        Ok(plaintext)
+    }
+}
+
+/// Encrypts `plaintext` to a whole age file with `file_key`, whose header has
+/// `stanzas`, e.g. from `X25519Recipient::wrap()`.
+pub fn encrypt(
+    file_key: &FileKey, stanzas: Vec<Stanza>, plaintext: &[u8], rng: &rand::SecureRandom,
+) -> Result<Vec<u8>, error::Unspecified> {
+    let mut out = Header::new(file_key, stanzas)?.to_bytes();
+    let mut nonce = [0; PAYLOAD_NONCE_LEN];
+    rng.fill(&mut nonce)?;
+    out.extend_from_slice(&nonce);
+
+    let mut sealer = PayloadSealer::new(file_key, &nonce);
+    let num_chunks = core::cmp::max((plaintext.len() + CHUNK_LEN - 1) / CHUNK_LEN, 1);
+    out.reserve(plaintext.len() + (num_chunks * TAG_LEN));
+    for i in 0..num_chunks {
+        let end = core::cmp::min((i + 1) * CHUNK_LEN, plaintext.len());
+        let chunk = &plaintext[(i * CHUNK_LEN)..end];
+        let start = out.len();
+        out.extend_from_slice(chunk);
+        out.extend_from_slice(&[0; TAG_LEN]);
+        sealer.seal_chunk_in_place(&mut out[start..], i == num_chunks - 1)?;
+    }
+    Ok(out)
+}
+
+/// Decrypts a whole age file. `unwrap` is called with the stanzas of the
+/// header to get the file key, e.g. with `X25519Identity::unwrap()`; the
+/// header's MAC is verified with it before the payload is decrypted.
+pub fn decrypt<F>(file: &[u8], unwrap: F) -> Result<Vec<u8>, error::Unspecified>
+where
+    F: FnOnce(&[Stanza]) -> Result<FileKey, error::Unspecified>,
+{
+    let (header, payload) = Header::parse(file)?;
+    let file_key = unwrap(header.stanzas())?;
+    header.verify(&file_key)?;
+
+    if payload.len() <= PAYLOAD_NONCE_LEN {
+        return Err(error::Unspecified);
+    }
+    let (nonce, sealed) = payload.split_at(PAYLOAD_NONCE_LEN);
+    let mut nonce_bytes = [0; PAYLOAD_NONCE_LEN];
+    nonce_bytes.copy_from_slice(nonce);
+    let mut opener = PayloadOpener::new(&file_key, &nonce_bytes);
+
+    let mut out = Vec::with_capacity(sealed.len());
+    let mut chunks = sealed.chunks(CHUNK_LEN + TAG_LEN).peekable();
+    while let Some(chunk) = chunks.next() {
+        let start = out.len();
+        out.extend_from_slice(chunk);
+        let last = chunks.peek().is_none();
+        let plaintext_len = opener.open_chunk_in_place(&mut out[start..], last)?.len();
+        out.truncate(start + plaintext_len);
+    }
+    Ok(out)
+}
+
+// `HKDF-SHA256(X25519(private_key, peer), share || recipient, label)`, where
+// `private_key` and `peer` are the ephemeral private key and the recipient
+// when wrapping and the identity and the share when unwrapping.
+fn x25519_wrap_key(
+    private_key: &ec::PrivateKey, peer: &[u8], share: &[u8], recipient: &[u8; X25519_KEY_LEN],
+) -> Result<[u8; 32], error::Unspecified> {
+    let mut shared_secret = [0; X25519_KEY_LEN];
+    (agreement::X25519.i.ecdh)(&mut shared_secret, private_key, untrusted::Input::from(peer))?;
+    let mut salt = [0; 2 * X25519_KEY_LEN];
+    salt[..X25519_KEY_LEN].copy_from_slice(share);
+    salt[X25519_KEY_LEN..].copy_from_slice(recipient);
+    let mut wrap_key = [0; 32];
+    hkdf::extract_and_expand(
+        &hmac::SigningKey::new(&digest::SHA256, &salt),
+        &shared_secret,
+        X25519_LABEL,
+        &mut wrap_key,
+    );
+    zeroize::zeroize(&mut shared_secret);
+    Ok(wrap_key)
+}
+
+fn scrypt_wrap_key(
+    passphrase: &[u8], salt: &[u8], log_n: u8,
+) -> Result<[u8; 32], error::Unspecified> {
+    let mut labeled_salt = [0; SCRYPT_LABEL.len() + SCRYPT_SALT_LEN];
+    labeled_salt[..SCRYPT_LABEL.len()].copy_from_slice(SCRYPT_LABEL);
+    labeled_salt[SCRYPT_LABEL.len()..].copy_from_slice(salt);
+    let params = scrypt::Params { log_n, r: 8, p: 1 };
+    let mut wrap_key = [0; 32];
+    scrypt::derive(&params, &labeled_salt, passphrase, &mut wrap_key)?;
+    Ok(wrap_key)
+}
+
+// The wrapped file key is the file key sealed with ChaCha20-Poly1305 and an
+// all-zero nonce, which is safe because each wrap key is used once.
+fn seal_file_key(wrap_key: &[u8; 32], file_key: &FileKey) -> Result<Vec<u8>, error::Unspecified> {
+    let mut body = [0; WRAPPED_FILE_KEY_LEN];
+    body[..FILE_KEY_LEN].copy_from_slice(&file_key.0);
+    let key = aead::SealingKey::new_chacha20_poly1305(wrap_key);
+    let _ = aead::seal_in_place(&key, &[0; 12], &[], &mut body, TAG_LEN)?;
+    Ok(body.to_vec())
+}
+
+fn open_file_key(wrap_key: &[u8; 32], body: &[u8]) -> Result<FileKey, error::Unspecified> {
+    let mut in_out = [0; WRAPPED_FILE_KEY_LEN];
+    in_out.copy_from_slice(body);
+    let key = aead::OpeningKey::new_chacha20_poly1305(wrap_key);
+    let result = aead::open_in_place(&key, &[0; 12], &[], 0, &mut in_out).map(|file_key| {
+        let mut r = FileKey([0; FILE_KEY_LEN]);
+        r.0.copy_from_slice(file_key);
+        r
+    });
+    zeroize::zeroize(&mut in_out);
+    result
+}
+
+fn header_mac(file_key: &FileKey, encoded: &[u8]) -> [u8; MAC_LEN] {
+    let mut mac_key = file_key.hkdf(&[], b"header");
+    let key = hmac::SigningKey::new(&digest::SHA256, &mac_key);
+    zeroize::zeroize(&mut mac_key);
+    let mut mac = [0; MAC_LEN];
+    mac.copy_from_slice(hmac::sign(&key, encoded).as_ref());
+    mac
+}
+
+fn chunk_nonce(
+    counter: u64, in_out_len: usize, last: bool, finished: bool,
+) -> Result<[u8; 12], error::Unspecified> {
+    let chunk_len = in_out_len.checked_sub(TAG_LEN).ok_or(error::Unspecified)?;
+    if finished
+        || chunk_len > CHUNK_LEN
+        || (!last && chunk_len != CHUNK_LEN)
+        || (last && chunk_len == 0 && counter != 0)
+        || counter == u64::max_value()
+    {
+        return Err(error::Unspecified);
+    }
+    let mut nonce = [0; 12];
+    nonce[3..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    Ok(nonce)
+}
+
+// Arguments are nonempty strings of printable ASCII other than space.
+fn is_valid_arg(arg: &str) -> bool {
+    !arg.is_empty() && arg.bytes().all(|b| (0x21..=0x7e).contains(&b))
+}
+
+// A decimal number without leading zeros.
+fn parse_decimal(s: &str) -> Option<u8> {
+    if s.is_empty() || (s.len() > 1 && s.starts_with('0')) || !s.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn parse_stanza(args_line: &[u8], lines: &mut Lines) -> Result<Stanza, error::Unspecified> {
+    let args_line = core::str::from_utf8(args_line).map_err(|_| error::Unspecified)?;
+    let mut args = args_line.split(' ').map(String::from);
+    let tag = args.next().ok_or(error::Unspecified)?;
+    let args: Vec<String> = args.collect();
+    if !is_valid_arg(&tag) || !args.iter().all(|arg| is_valid_arg(arg)) {
+        return Err(error::Unspecified);
+    }
+
+    let mut body = String::new();
+    loop {
+        let line = lines.next()?;
+        if line.len() > LINE_LEN {
+            return Err(error::Unspecified);
+        }
+        body.push_str(core::str::from_utf8(line).map_err(|_| error::Unspecified)?);
+        if line.len() < LINE_LEN {
+            break;
+        }
+    }
+    Ok(Stanza {
+        tag,
+        args,
+        body: base64::decode(&body, &BASE64)?,
+    })
+}
+
+struct Lines<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lines<'a> {
+    // Returns the next line, without its line feed, failing at the end of the
+    // input or if the line doesn't end with a line feed.
+    fn next(&mut self) -> Result<&'a [u8], error::Unspecified> {
+        let rest = &self.input[self.pos..];
+        let len = rest.iter().position(|b| *b == b'\n').ok_or(error::Unspecified)?;
+        self.pos += len + 1;
+        Ok(&rest[..len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_x25519_identity_encoding() {
+        // The identity whose private key is 32 bytes of 0x42.
+        let identity = X25519Identity::from_bech32(
+            "AGE-SECRET-KEY-1GFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPQ4EGAEX",
+        )
+        .unwrap();
+        assert_eq!(identity.private_key.bytes(agreement::X25519.i.curve), &[0x42; 32][..]);
+        assert_eq!(
+            identity.recipient().to_bech32(),
+            "age1zvkyg2lqzraa2lnjvqej32nkuu0ues2s82hzrye869xeexvn73equnujwj"
+        );
+        assert!(X25519Identity::from_bech32(&identity.recipient().to_bech32()).is_err());
+        assert!(X25519Recipient::from_bech32(&identity.to_bech32()).is_err());
+    }
+
+    #[test]
+    fn test_age() {
+        test::from_file("src/age_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let recipients = test_case.consume_optional_string("Recipients");
+            let identity = test_case.consume_optional_string("Identity");
+            let passphrase = test_case.consume_optional_string("Passphrase");
+            let passphrase = passphrase.map(|p| p.trim_matches('"').to_owned());
+            let log_n = test_case.consume_optional_string("LogN");
+            let file_key_bytes = test_case.consume_bytes("FileKey");
+            let random = test_case.consume_string("Random");
+            let nonce = test_case.consume_bytes("Nonce");
+            let plaintext_len = test_case.consume_usize("PlaintextLen");
+            let expected_file = test_case.consume_optional_string("File");
+            let expected_digest = test_case.consume_bytes("FileDigest");
+
+            let random: Vec<Vec<u8>> =
+                random.split(',').map(|r| test::from_hex(r).unwrap()).collect();
+            let plaintext: Vec<u8> = (0..plaintext_len).map(|i| (i % 251) as u8).collect();
+            let new_file_key = || {
+                let mut file_key = FileKey([0; FILE_KEY_LEN]);
+                file_key.0.copy_from_slice(&file_key_bytes);
+                file_key
+            };
+
+            let stanzas = {
+                let random: Vec<&[u8]> = random.iter().map(|r| &r[..]).collect();
+                let rng = test::rand::FixedSliceSequenceRandom::new(&random);
+                match (&recipients, &passphrase, &log_n) {
+                    (Some(recipients), None, None) => recipients
+                        .split(',')
+                        .map(|r| X25519Recipient::from_bech32(r)?.wrap(&new_file_key(), &rng))
+                        .collect::<Result<Vec<_>, _>>()
+                        .unwrap(),
+                    (None, Some(passphrase), Some(log_n)) => vec![scrypt_wrap(
+                        passphrase.as_bytes(),
+                        log_n.parse().unwrap(),
+                        &new_file_key(),
+                        &rng,
+                    )
+                    .unwrap()],
+                    _ => unreachable!(),
+                }
+            };
+            let rng = test::rand::FixedSliceRandom { bytes: &nonce };
+            let file = encrypt(&new_file_key(), stanzas, &plaintext, &rng).unwrap();
+            if let Some(expected_file) = expected_file {
+                assert_eq!(file, test::from_hex(&expected_file).unwrap());
+            }
+            assert_eq!(digest::digest(&digest::SHA256, &file).as_ref(), &expected_digest[..]);
+
+            let decrypted = match (identity, passphrase) {
+                (Some(identity), None) => {
+                    let identity = X25519Identity::from_bech32(&identity).unwrap();
+                    decrypt(&file, |stanzas| identity.unwrap(stanzas))
+                },
+                (None, Some(passphrase)) => decrypt(&file, |stanzas| {
+                    scrypt_unwrap(passphrase.as_bytes(), 10, stanzas)
+                }),
+                _ => unreachable!(),
+            };
+            assert_eq!(decrypted.unwrap(), plaintext);
+            Ok(())
+        });
+    }
+}
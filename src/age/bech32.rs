@@ -0,0 +1,215 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Bech32 ([BIP 173]), used only for age's recipients and identities.
+//!
+//! Like `io::base64`, the digits are converted without branches or
+//! data-dependent table indexes, since identities are secret keys. Unlike
+//! BIP 173, the length isn't limited to 90 characters.
+//!
+//! [BIP 173]: https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+
+use crate::{
+    constant_time::{self, Choice, CtOption},
+    error,
+};
+use alloc::{string::String, vec::Vec};
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const CHECKSUM_LEN: usize = 6;
+
+const INVALID: u8 = 0xff;
+
+static DIGITS: [[u8; 1]; 32] = {
+    let mut table = [[0]; 32];
+    let mut i = 0;
+    while i < CHARSET.len() {
+        table[i] = [CHARSET[i]];
+        i += 1;
+    }
+    table
+};
+
+static DIGIT_VALUES: [[u8; 1]; 128] = {
+    let mut table = [[INVALID]; 128];
+    let mut i = 0;
+    while i < CHARSET.len() {
+        table[CHARSET[i] as usize] = [i as u8];
+        i += 1;
+    }
+    table
+};
+
+/// Encodes `data` with the human-readable part `hrp`, which must be
+/// lowercase, in lowercase.
+pub fn encode(hrp: &str, data: &[u8]) -> String {
+    let mut values = Vec::with_capacity(((data.len() * 8) + 4) / 5 + CHECKSUM_LEN);
+    let mut bits = 0u32;
+    let mut bits_len = 0;
+    for byte in data {
+        bits = (bits << 8) | u32::from(*byte);
+        bits_len += 8;
+        while bits_len >= 5 {
+            bits_len -= 5;
+            values.push(((bits >> bits_len) & 31) as u8);
+        }
+    }
+    if bits_len > 0 {
+        values.push(((bits << (5 - bits_len)) & 31) as u8);
+    }
+    let checksum = polymod(hrp, &values, &[0; CHECKSUM_LEN]) ^ 1;
+    for i in 0..CHECKSUM_LEN {
+        values.push(((checksum >> (5 * (CHECKSUM_LEN - 1 - i))) & 31) as u8);
+    }
+
+    let mut out = String::with_capacity(hrp.len() + 1 + values.len());
+    out.push_str(hrp);
+    out.push('1');
+    for value in values {
+        let digit = constant_time::lookup(&DIGITS, usize::from(value)).map(|[digit]| digit);
+        let digit = digit.unwrap_or(0);
+        out.push(char::from(digit));
+    }
+    out
+}
+
+/// Decodes `encoded`, which may be all lowercase or all uppercase, returning
+/// the human-readable part in lowercase and the data. The data must be a
+/// whole number of bytes, with any unused bits zero.
+pub fn decode(encoded: &str) -> Result<(String, Vec<u8>), error::Unspecified> {
+    let encoded = encoded.as_bytes();
+    let separator = encoded.iter().rposition(|b| *b == b'1').ok_or(error::Unspecified)?;
+    let (hrp, digits) = (&encoded[..separator], &encoded[(separator + 1)..]);
+    if hrp.is_empty() || digits.len() < CHECKSUM_LEN {
+        return Err(error::Unspecified);
+    }
+
+    let mut any_upper = Choice::from(false);
+    let mut any_lower = Choice::from(false);
+    let mut lowercase = |b: u8| {
+        let is_upper = constant_time::less_than(b.wrapping_sub(b'A'), 26);
+        let is_lower = constant_time::less_than(b.wrapping_sub(b'a'), 26);
+        any_upper = any_upper | is_upper;
+        any_lower = any_lower | is_lower;
+        b | CtOption::new(0x20, is_upper).unwrap_or(0)
+    };
+
+    let mut hrp_lowercase = String::with_capacity(hrp.len());
+    for b in hrp {
+        if *b < 33 || *b > 126 {
+            return Err(error::Unspecified);
+        }
+        hrp_lowercase.push(char::from(lowercase(*b)));
+    }
+
+    let mut valid = Choice::from(true);
+    let mut values = Vec::with_capacity(digits.len());
+    for digit in digits {
+        let value = constant_time::lookup(&DIGIT_VALUES, usize::from(lowercase(*digit)))
+            .and_then(|[value]| CtOption::new(value, !constant_time::words_equal(value, INVALID)));
+        valid = valid & value.is_some();
+        values.push(value.unwrap_or(0));
+    }
+    let (data_values, checksum) = values.split_at(values.len() - CHECKSUM_LEN);
+    valid = valid & constant_time::words_equal(polymod(&hrp_lowercase, data_values, checksum), 1);
+
+    let mut data = Vec::with_capacity((data_values.len() * 5) / 8);
+    let mut bits = 0u32;
+    let mut bits_len = 0;
+    for value in data_values {
+        bits = (bits << 5) | u32::from(*value);
+        bits_len += 5;
+        if bits_len >= 8 {
+            bits_len -= 8;
+            data.push((bits >> bits_len) as u8);
+        }
+    }
+    if bits_len >= 5 {
+        valid = Choice::from(false);
+    }
+    valid = valid & constant_time::words_equal(bits & ((1 << bits_len) - 1), 0);
+
+    if !valid.expose() || (any_upper & any_lower).expose() {
+        return Err(error::Unspecified);
+    }
+    Ok((hrp_lowercase, data))
+}
+
+fn polymod(hrp: &str, values: &[u8], checksum: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let hrp = hrp.as_bytes();
+    let expanded_hrp = hrp
+        .iter()
+        .map(|b| b >> 5)
+        .chain(core::iter::once(0))
+        .chain(hrp.iter().map(|b| b & 31));
+    let mut chk = 1u32;
+    for value in expanded_hrp.chain(values.iter().cloned()).chain(checksum.iter().cloned()) {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            chk ^= generator & ((top >> i) & 1).wrapping_neg();
+        }
+    }
+    chk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From BIP 173.
+    static VALID: &[(&str, &str, &str)] = &[
+        ("A12UEL5L", "a", ""),
+        ("a12uel5l", "a", ""),
+        (
+            "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+            "abcdef",
+            "00443214c74254b635cf84653a56d7c675be77df",
+        ),
+        (
+            "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+            "split",
+            "c5f38b70305f519bf66d85fb6cf03058f3dde463ecd7918f2dc743918f2d",
+        ),
+        ("?1ezyfcl", "?", ""),
+    ];
+
+    #[test]
+    fn test_valid() {
+        for &(encoded, hrp, data) in VALID {
+            let data = crate::test::from_hex(data).unwrap();
+            let (decoded_hrp, decoded) = decode(encoded).unwrap();
+            assert_eq!(decoded_hrp, hrp);
+            assert_eq!(decoded, data);
+            assert_eq!(encode(hrp, &data), encoded.to_lowercase());
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        for bad in &[
+            "A12UEL5l",     // Mixed case.
+            "a12uel5m",     // Wrong checksum.
+            "1qzzfhee",     // Empty human-readable part.
+            "a1qqqqq",      // Too short for a checksum.
+            "a12uelbl",     // 'b' isn't a digit.
+            "pzry9x0s0muk", // No separator.
+            "a\x7f1uel5l",  // Invalid human-readable character.
+        ] {
+            assert!(decode(bad).is_err(), "{}", bad);
+        }
+    }
+}
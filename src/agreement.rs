@@ -71,9 +71,12 @@
 // The "NSA Guide" steps here are from from section 3.1, "Ephemeral Unified
 // Model."
 
-use crate::{ec, error, rand};
+use crate::{ec, error, rand, spki, zeroize};
 use untrusted;
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
 pub use crate::ec::{
     curve25519::x25519::X25519,
     suite_b::ecdh::{ECDH_P256, ECDH_P384},
@@ -96,6 +99,10 @@ pub struct EphemeralPrivateKey {
     alg: &'static Algorithm,
 }
 
+// `ec::PrivateKey` zeroizes itself when it is dropped.
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for EphemeralPrivateKey {}
+
 impl<'a> EphemeralPrivateKey {
     /// Generate a new ephemeral private key for the given algorithm.
     ///
@@ -133,6 +140,35 @@ impl<'a> EphemeralPrivateKey {
         self.private_key.compute_public_key(&self.alg.i.curve, out)
     }
 
+    /// The size in bytes of the public key encoded in compressed form.
+    #[inline(always)]
+    pub fn compressed_public_key_len(&self) -> usize { self.alg.i.curve.compressed_public_key_len }
+
+    /// Like `compute_public_key()`, but fills `out` with the public point
+    /// encoded in compressed form: the X coordinate, prefixed with 2 if the Y
+    /// coordinate is even or 3 if it is odd, for `ECDH_P256` and `ECDH_P384`.
+    /// X25519 public keys have no Y coordinate, so for `X25519` this is the
+    /// same as `compute_public_key()`.
+    ///
+    /// `out.len()` must be equal to the value returned by
+    /// `compressed_public_key_len`.
+    pub fn compute_public_key_compressed(&self, out: &mut [u8]) -> Result<(), error::Unspecified> {
+        self.private_key.compute_public_key_compressed(&self.alg.i.curve, out)
+    }
+
+    /// Computes the public key from the private key's value.
+    ///
+    /// `public_key.as_ref()` is what `compute_public_key()` would write to
+    /// `out`, and `public_key.to_spki_der()` encodes it in a
+    /// `SubjectPublicKeyInfo`.
+    pub fn public_key(&self) -> Result<spki::PublicKey, error::Unspecified> {
+        let curve = &self.alg.i.curve;
+        let mut public_key = [0u8; PUBLIC_KEY_MAX_LEN];
+        let public_key = &mut public_key[..curve.public_key_len];
+        self.compute_public_key(public_key)?;
+        Ok(spki::PublicKey::from_ec_public_key(curve, public_key))
+    }
+
     #[cfg(test)]
     pub fn bytes(&'a self, curve: &ec::Curve) -> &'a [u8] { self.private_key.bytes(curve) }
 }
@@ -195,14 +231,57 @@ where
 
     // NSA Guide Steps 2, 3, and 4.
     //
-    // We have a pretty liberal interpretation of the NIST's spec's "Destroy"
-    // that doesn't meet the NSA requirement to "zeroize."
+    // The private key is zeroized when `my_private_key` is dropped, at the
+    // end of this function.
     (alg.ecdh)(shared_key, &my_private_key.private_key, peer_public_key)
         .map_err(|_| error_value)?;
 
     // NSA Guide Steps 5 and 6.
     //
-    // Again, we have a pretty liberal interpretation of the NIST's spec's
-    // "Destroy" that doesn't meet the NSA requirement to "zeroize."
-    kdf(shared_key)
+    // The shared secret is zeroized after the KDF has been applied to it.
+    let result = kdf(shared_key);
+    zeroize::zeroize(shared_key);
+    result
+}
+
+/// Performs an X25519 key agreement of each private key in `my_private_keys`
+/// with the peer public key at the same index of `peer_public_keys`, like
+/// `agree_ephemeral`, doing several of the scalar multiplications at once
+/// when the CPU supports AVX2 or NEON.
+///
+/// `kdf` is called with the key material of each agreement that succeeds, in
+/// order. The result for `my_private_keys[i]` is at index `i` of the returned
+/// vector: what `kdf` returned for it, or `Err(error_value.clone())` if its
+/// algorithm isn't `X25519` or the agreement fails as it would in
+/// `agree_ephemeral`.
+///
+/// # Panics
+///
+/// `x25519_multi` panics if `my_private_keys.len()` isn't equal to
+/// `peer_public_keys.len()`.
+#[cfg(feature = "alloc")]
+pub fn x25519_multi<F, R, E>(
+    my_private_keys: Vec<EphemeralPrivateKey>, peer_public_keys: &[untrusted::Input],
+    error_value: E, mut kdf: F,
+) -> Vec<Result<R, E>>
+where
+    F: FnMut(&[u8]) -> Result<R, E>,
+    E: Clone,
+{
+    let private_keys: Vec<&ec::PrivateKey> =
+        my_private_keys.iter().map(|key| &key.private_key).collect();
+    let mut curve_ids = my_private_keys.iter().map(|key| key.alg.i.curve.id);
+    let mut results = Vec::with_capacity(my_private_keys.len());
+
+    // The private keys are zeroized when `my_private_keys` is dropped, at the
+    // end of this function, and each shared key is zeroized once `kdf` has
+    // been applied to it.
+    ec::curve25519::x25519::x25519_ecdh_multi(&private_keys, peer_public_keys, |shared_key| {
+        let result = match (curve_ids.next(), shared_key) {
+            (Some(ec::CurveID::Curve25519), Ok(shared_key)) => kdf(shared_key),
+            _ => Err(error_value.clone()),
+        };
+        results.push(result);
+    });
+    results
 }
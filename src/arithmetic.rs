@@ -12,4 +12,9 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-pub mod montgomery;
+//! Modular arithmetic.
+
+#[cfg(feature = "unstable_bigint")]
+pub mod bigint;
+
+pub(crate) mod montgomery;
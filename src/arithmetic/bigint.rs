@@ -0,0 +1,223 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Modular arithmetic on large integers, for implementing protocols that
+//! *ring* doesn't implement, e.g. Paillier encryption, SRP variants, and
+//! other RSA-like schemes, with the same code that *ring* uses for RSA.
+//!
+//! This module is only available with the `unstable_bigint` feature. **Its
+//! API isn't stable**: unlike the rest of *ring*'s API, it may change in any
+//! release.
+//!
+//! A `Modulus` is an odd modulus *m* along with the precomputed values that
+//! Montgomery multiplication modulo *m* needs; compute it once and reuse it.
+//! An `Elem` is an element of ℤ/mℤ, i.e. an integer in [0, m), that is tied
+//! to the `Modulus` it was created with; combining elements of different
+//! `Modulus` values fails, even if their values are equal. Integers are
+//! encoded big-endian, and encoded elements are padded to the length of *m*.
+//!
+//! Addition, subtraction, multiplication, exponentiation, and comparison are
+//! done in constant time, but `Elem::inverse_vartime()` isn't.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::arithmetic::bigint;
+//!
+//! # fn fermat() -> Result<(), ring::error::Unspecified> {
+//! // The largest prime less than 2**512.
+//! let mut p = [0xff; 64];
+//! p[62] = 0xfd;
+//! p[63] = 0xc7;
+//! let m = bigint::Modulus::from_be_bytes(&p)?;
+//!
+//! // Fermat's little theorem: a**(p - 1) == 1 (mod p).
+//! let a = m.elem(&[2])?;
+//! let mut p_minus_1 = p;
+//! p_minus_1[63] -= 1;
+//! let one = m.elem(&[1])?;
+//! assert!(a.exp(&p_minus_1)?.verify_equal(&one).is_ok());
+//! # Ok(())
+//! # }
+//! # fn main() { fermat().unwrap() }
+//! ```
+
+use super::montgomery::{Unencoded, R};
+use crate::{
+    error, rand,
+    rsa::bigint::{self, PrivateExponent},
+};
+use untrusted;
+
+// All moduli have the same type; `Elem` tracks its modulus at runtime
+// instead.
+enum M {}
+
+/// The smallest modulus, in bits.
+pub const MODULUS_MIN_BITS: usize = 256;
+
+/// The largest modulus, in bits. This is the largest RSA modulus that *ring*
+/// supports, which the `rsa_max_bits_*` features reduce.
+pub const MODULUS_MAX_BITS: usize = crate::spki::RSA_MODULUS_MAX_BITS;
+
+/// An odd modulus *m*, along with the precomputed values needed for
+/// Montgomery multiplication modulo *m*.
+pub struct Modulus {
+    m: bigint::Modulus<M>,
+    len: usize,
+}
+
+impl Modulus {
+    /// Parses a big-endian modulus, which must be odd, must not have leading
+    /// zeros, and must be between `MODULUS_MIN_BITS` and `MODULUS_MAX_BITS`
+    /// bits long.
+    pub fn from_be_bytes(input: &[u8]) -> Result<Self, error::Unspecified> {
+        let (m, bits) =
+            bigint::Modulus::from_be_bytes_with_bit_length(untrusted::Input::from(input))
+                .map_err(|_| error::Unspecified)?;
+        if bits.as_usize_bits() < MODULUS_MIN_BITS {
+            return Err(error::Unspecified);
+        }
+        Ok(Self {
+            m,
+            len: bits.as_usize_bytes_rounded_up(),
+        })
+    }
+
+    /// The length of encoded elements, in bytes, which is the length of *m*.
+    #[inline]
+    pub fn elem_len(&self) -> usize { self.len }
+
+    /// Parses a big-endian element, which must be less than *m*. The input
+    /// may be shorter than `elem_len()`.
+    pub fn elem(&self, input: &[u8]) -> Result<Elem, error::Unspecified> {
+        let value = bigint::Elem::from_be_bytes_padded(untrusted::Input::from(input), &self.m)?;
+        Ok(Elem { m: self, value })
+    }
+
+    /// Returns a uniformly random element in [1, m).
+    pub fn random(&self, rng: &rand::SecureRandom) -> Result<Elem, error::Unspecified> {
+        Ok(Elem {
+            m: self,
+            value: bigint::elem_random(&self.m, rng)?,
+        })
+    }
+
+    // Converts `a` into the Montgomery domain.
+    fn to_r(&self, a: &bigint::Elem<M, Unencoded>) -> bigint::Elem<M, R> {
+        bigint::elem_mul(self.m.oneRR().as_ref(), a.clone(), &self.m)
+    }
+}
+
+impl core::fmt::Debug for Modulus {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+        fmt.debug_struct("Modulus").field("len", &self.len).finish()
+    }
+}
+
+/// An element of ℤ/mℤ for a `Modulus` *m*.
+///
+/// Elements may be secret: they are overwritten with zeros when they are
+/// dropped.
+#[derive(Clone)]
+pub struct Elem<'a> {
+    m: &'a Modulus,
+    value: bigint::Elem<M, Unencoded>,
+}
+
+impl<'a> Elem<'a> {
+    /// Returns `self + b (mod m)`.
+    pub fn add(&self, b: &Self) -> Result<Self, error::Unspecified> {
+        let m = self.same_modulus(b)?;
+        Ok(self.with(bigint::elem_add(self.value.clone(), b.value.clone(), &m.m)))
+    }
+
+    /// Returns `self - b (mod m)`.
+    pub fn sub(&self, b: &Self) -> Result<Self, error::Unspecified> {
+        let m = self.same_modulus(b)?;
+        Ok(self.with(bigint::elem_sub(self.value.clone(), &b.value, &m.m)))
+    }
+
+    /// Returns `self * b (mod m)`.
+    pub fn mul(&self, b: &Self) -> Result<Self, error::Unspecified> {
+        let m = self.same_modulus(b)?;
+        let a = m.to_r(&self.value);
+        Ok(self.with(bigint::elem_mul(&a, b.value.clone(), &m.m)))
+    }
+
+    /// Returns `self**exponent (mod m)` for a big-endian `exponent`, which
+    /// must be less than *m*, in time that doesn't depend on the value of
+    /// `self` or `exponent`.
+    ///
+    /// Only moduli that occupy a multiple of 512 bits, when rounded up to a
+    /// whole number of machine words, are supported, e.g. 2048-bit RSA moduli
+    /// and their squares; this fails for other moduli.
+    pub fn exp(&self, exponent: &[u8]) -> Result<Self, error::Unspecified> {
+        let m = &self.m.m;
+        let exponent =
+            PrivateExponent::from_be_bytes_padded_any(untrusted::Input::from(exponent), m)?;
+        let base = self.m.to_r(&self.value);
+        Ok(self.with(bigint::elem_exp_consttime(base, &exponent, m)?))
+    }
+
+    /// Returns `self**-1 (mod m)`, failing if `self` and *m* aren't coprime.
+    ///
+    /// The value of `self` is hidden by multiplying it by a random element
+    /// before inverting it, so `self` may be secret.
+    pub fn inverse(&self, rng: &rand::SecureRandom) -> Result<Self, error::Unspecified> {
+        Ok(self.with(bigint::elem_inverse_blinded(&self.value, &self.m.m, rng)?))
+    }
+
+    /// Returns `self**-1 (mod m)` like `inverse()`, but in time that depends
+    /// on the value of `self`, which therefore must not be secret.
+    pub fn inverse_vartime(&self) -> Result<Self, error::Unspecified> {
+        Ok(self.with(bigint::elem_inverse_vartime(&self.value, &self.m.m)?))
+    }
+
+    /// Checks, in constant time, that `self == b`.
+    pub fn verify_equal(&self, b: &Self) -> Result<(), error::Unspecified> {
+        let _ = self.same_modulus(b)?;
+        bigint::elem_verify_equal_consttime(&self.value, &b.value)
+    }
+
+    /// Returns true if `self` is zero.
+    #[inline]
+    pub fn is_zero(&self) -> bool { self.value.is_zero() }
+
+    /// Writes `self` to `out`, big-endian, padded to `out.len()`, which must
+    /// be the modulus's `elem_len()`.
+    pub fn fill_be_bytes(&self, out: &mut [u8]) -> Result<(), error::Unspecified> {
+        if out.len() != self.m.len {
+            return Err(error::Unspecified);
+        }
+        self.value.fill_be_bytes(out);
+        Ok(())
+    }
+
+    /// The element's modulus.
+    #[inline]
+    pub fn modulus(&self) -> &'a Modulus { self.m }
+
+    fn same_modulus(&self, b: &Self) -> Result<&'a Modulus, error::Unspecified> {
+        if !core::ptr::eq(self.m, b.m) {
+            return Err(error::Unspecified);
+        }
+        Ok(self.m)
+    }
+
+    #[inline]
+    fn with(&self, value: bigint::Elem<M, Unencoded>) -> Self { Self { m: self.m, value } }
+}
+
+derive_debug_via_self!(Elem<'_>, self.m);
@@ -39,7 +39,7 @@ impl BitLength {
     #[inline]
     pub fn as_usize_bits(&self) -> usize { self.0 }
 
-    #[cfg(feature = "use_heap")]
+    #[cfg(feature = "alloc")]
     #[inline]
     pub fn as_usize_bytes_rounded_up(&self) -> usize {
         // Equivalent to (self.0 + 7) / 8, except with no potential for
@@ -51,7 +51,7 @@ impl BitLength {
         (self.0 / 8) + round_up
     }
 
-    #[cfg(feature = "use_heap")]
+    #[cfg(feature = "alloc")]
     #[inline]
     pub fn try_sub_1(self) -> Result<BitLength, error::Unspecified> {
         let sum = self.0.checked_sub(1).ok_or(error::Unspecified)?;
@@ -0,0 +1,250 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! BLAKE2s ([RFC 7693]), for WireGuard, which uses it unkeyed as its hash,
+//! keyed with a 16-byte output as its MAC, and in HMAC.
+//!
+//! [RFC 7693]: https://tools.ietf.org/html/rfc7693
+
+use crate::{polyfill, zeroize};
+
+pub const OUTPUT_LEN: usize = 32;
+pub const MAX_KEY_LEN: usize = 32;
+pub const BLOCK_LEN: usize = 64;
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+pub struct Context {
+    h: [u32; 8],
+    t: u64,
+    pending: [u8; BLOCK_LEN],
+    num_pending: usize,
+    output_len: usize,
+}
+
+impl Context {
+    pub fn new() -> Self { Self::new_keyed(&[], OUTPUT_LEN) }
+
+    /// Constructs a context for the keyed hash, with `output_len` bytes of
+    /// output. `key` must be at most `MAX_KEY_LEN` bytes and `output_len`
+    /// between 1 and `OUTPUT_LEN`.
+    pub fn new_keyed(key: &[u8], output_len: usize) -> Self {
+        assert!(key.len() <= MAX_KEY_LEN);
+        assert!((1..=OUTPUT_LEN).contains(&output_len));
+        let mut h = IV;
+        // Parameter block: digest length, key length, fanout 1, depth 1.
+        h[0] ^= 0x0101_0000 ^ ((key.len() as u32) << 8) ^ (output_len as u32);
+        let mut r = Self {
+            h,
+            t: 0,
+            pending: [0; BLOCK_LEN],
+            num_pending: 0,
+            output_len,
+        };
+        // The key, padded with zeros, is the first block.
+        if !key.is_empty() {
+            r.pending[..key.len()].copy_from_slice(key);
+            r.num_pending = BLOCK_LEN;
+        }
+        r
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            // The final block must be compressed with the finalization flag
+            // set, so a full pending block is only compressed once more data
+            // arrives.
+            if self.num_pending == BLOCK_LEN {
+                self.t += polyfill::u64_from_usize(BLOCK_LEN);
+                let block = self.pending;
+                self.compress(&block, false);
+                self.num_pending = 0;
+            }
+            let to_copy = core::cmp::min(BLOCK_LEN - self.num_pending, data.len());
+            self.pending[self.num_pending..][..to_copy].copy_from_slice(&data[..to_copy]);
+            self.num_pending += to_copy;
+            data = &data[to_copy..];
+        }
+    }
+
+    pub fn finish(mut self) -> Output {
+        self.t += polyfill::u64_from_usize(self.num_pending);
+        polyfill::slice::fill(&mut self.pending[self.num_pending..], 0);
+        let block = self.pending;
+        self.compress(&block, true);
+
+        let mut out = Output {
+            bytes: [0u8; OUTPUT_LEN],
+            len: self.output_len,
+        };
+        for (out, h) in out.bytes.chunks_mut(4).zip(self.h.iter()) {
+            out.copy_from_slice(&h.to_le_bytes());
+        }
+        out
+    }
+
+    fn compress(&mut self, block: &[u8; BLOCK_LEN], is_last: bool) {
+        let mut m = [0u32; 16];
+        for (m, bytes) in m.iter_mut().zip(block.chunks(4)) {
+            let mut word = [0u8; 4];
+            word.copy_from_slice(bytes);
+            *m = u32::from_le_bytes(word);
+        }
+
+        let mut v = [0u32; 16];
+        v[..8].copy_from_slice(&self.h);
+        v[8..].copy_from_slice(&IV);
+        v[12] ^= self.t as u32;
+        v[13] ^= (self.t >> 32) as u32;
+        if is_last {
+            v[14] = !v[14];
+        }
+
+        for s in SIGMA.iter() {
+            g(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+            g(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+            g(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+            g(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+            g(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+            g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+            g(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+            g(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+        }
+
+        for i in 0..8 {
+            self.h[i] ^= v[i] ^ v[i + 8];
+        }
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        // The pending block may be the key.
+        zeroize::zeroize(&mut self.pending);
+    }
+}
+
+/// The output of a `Context`.
+pub struct Output {
+    bytes: [u8; OUTPUT_LEN],
+    len: usize,
+}
+
+impl AsRef<[u8]> for Output {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+impl Drop for Output {
+    // The output of the keyed hash may be a key.
+    fn drop(&mut self) { zeroize::zeroize(&mut self.bytes) }
+}
+
+#[inline(always)]
+fn g(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(12);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(8);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(7);
+}
+
+/// The hash of the concatenation of `data`.
+pub fn digest(data: &[&[u8]]) -> Output { keyed(&[], OUTPUT_LEN, data) }
+
+/// The keyed hash of the concatenation of `data` with `output_len` bytes of
+/// output; see `Context::new_keyed()`.
+pub fn keyed(key: &[u8], output_len: usize, data: &[&[u8]]) -> Output {
+    let mut ctx = Context::new_keyed(key, output_len);
+    for data in data {
+        ctx.update(data);
+    }
+    ctx.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test;
+
+    #[test]
+    fn test_rfc7693_abc() {
+        let expected = test::from_hex(
+            "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982",
+        )
+        .unwrap();
+        assert_eq!(super::digest(&[b"abc"]).as_ref(), &expected[..]);
+    }
+
+    #[test]
+    fn test_multi_block_and_split_updates() {
+        // Generated with Python's `hashlib.blake2s`.
+        let expected = test::from_hex(
+            "68dbd8479e93231473bd1069a3ea7429461c0f9637759070ec4027882c478735",
+        )
+        .unwrap();
+        let input = [0x61u8; 300];
+        let one_shot = super::digest(&[&input]);
+        let mut ctx = super::Context::new();
+        ctx.update(&input[..1]);
+        ctx.update(&input[1..64]);
+        ctx.update(&input[64..128]);
+        ctx.update(&input[128..]);
+        assert_eq!(one_shot.as_ref(), &expected[..]);
+        assert_eq!(ctx.finish().as_ref(), &expected[..]);
+    }
+
+    #[test]
+    fn test_keyed() {
+        // The first is from the keyed KAT of the BLAKE2 reference code; the
+        // others were generated with Python's `hashlib.blake2s`.
+        let mut key = [0u8; 32];
+        let mut data = [0u8; 200];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let expected = test::from_hex(
+            "48a8997da407876b3d79c0d92325ad3b89cbb754d86ab71aee047ad345fd2c49",
+        )
+        .unwrap();
+        assert_eq!(super::keyed(&key, 32, &[]).as_ref(), &expected[..]);
+        let expected = test::from_hex("61ba5f165c194692e09d12520cc4c74a").unwrap();
+        assert_eq!(super::keyed(&key, 16, &[b"a", b"bc"]).as_ref(), &expected[..]);
+        let expected = test::from_hex(
+            "725027f209c4c36d0fd828818d395a82ea5e5aee8499ecbd625cf58f4869e70a",
+        )
+        .unwrap();
+        assert_eq!(super::keyed(b"k", 32, &[&data]).as_ref(), &expected[..]);
+    }
+}
@@ -22,7 +22,7 @@ use crate::{c, error};
 #[repr(transparent)]
 pub struct Result(c::int);
 
-impl From<Result> for ::std::result::Result<(), error::Unspecified> {
+impl From<Result> for core::result::Result<(), error::Unspecified> {
     fn from(ret: Result) -> Self {
         match ret.0 {
             1 => Ok(()),
@@ -40,7 +40,7 @@ impl From<Result> for ::std::result::Result<(), error::Unspecified> {
 // were all executables with their own `main` functions. Those main functions
 // have been replaced with uniquely-named functions so that they can all be
 // linked into the same executable.
-#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 macro_rules! bssl_test {
     ( $fn_name:ident, $bssl_test_main_fn_name:ident ) => {
         #[test]
@@ -92,7 +92,8 @@ define_type!(
     "The C `unsigned int` type. Equivalent to `libc::c_uint`."
 );
 
-#[cfg(any(target_os = "windows", target_pointer_width = "32"))]
+// `long` is 32 bits wide on UEFI targets, which use the Windows ABI.
+#[cfg(any(target_os = "windows", target_os = "uefi", target_pointer_width = "32"))]
 define_type!(
     long,
     i32,
@@ -102,7 +103,7 @@ define_type!(
     "The C `long` type. Equivalent to `libc::c_long`."
 );
 
-#[cfg(not(any(target_os = "windows", target_pointer_width = "32")))]
+#[cfg(not(any(target_os = "windows", target_os = "uefi", target_pointer_width = "32")))]
 define_type!(
     long,
     i64,
@@ -0,0 +1,452 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! A C API for digests, HMAC, AEADs, and Ed25519, declared in
+//! `include/ring.h`, for C and C++ programs that use *ring* as their
+//! cryptography library.
+//!
+//! The API is modeled on BoringSSL's: algorithms are pointers returned by
+//! functions like `RING_digest_sha256()`, keys and contexts are opaque
+//! pointers that are freed with the matching `_free` function, and
+//! functions return 1 on success and 0 on failure. Outputs are written to
+//! `out`, which has room for `max_out_len` bytes, and their length is
+//! written to `*out_len`. Null pointers are allowed where the length is
+//! zero, and every `_free` function accepts a null pointer.
+//!
+//! To build a shared library, e.g. `libring.so`:
+//!
+//! ```sh
+//! cargo rustc --release --lib --features capi --crate-type cdylib
+//! ```
+//!
+//! Use `--crate-type staticlib` for a static library.
+
+use crate::{aead, c, digest, error, hmac, signature};
+use alloc::boxed::Box;
+use core;
+use untrusted;
+
+/// Returns SHA-1, which is only for legacy protocols.
+#[no_mangle]
+pub extern "C" fn RING_digest_sha1() -> *const digest::Algorithm { &digest::SHA1 }
+
+/// Returns SHA-256.
+#[no_mangle]
+pub extern "C" fn RING_digest_sha256() -> *const digest::Algorithm { &digest::SHA256 }
+
+/// Returns SHA-384.
+#[no_mangle]
+pub extern "C" fn RING_digest_sha384() -> *const digest::Algorithm { &digest::SHA384 }
+
+/// Returns SHA-512.
+#[no_mangle]
+pub extern "C" fn RING_digest_sha512() -> *const digest::Algorithm { &digest::SHA512 }
+
+/// Returns SHA-512/256.
+#[no_mangle]
+pub extern "C" fn RING_digest_sha512_256() -> *const digest::Algorithm { &digest::SHA512_256 }
+
+/// Returns the length of `alg`'s digests, or 0 if `alg` is null.
+#[no_mangle]
+pub unsafe extern "C" fn RING_digest_output_len(alg: *const digest::Algorithm) -> c::size_t {
+    match alg.as_ref() {
+        Some(alg) => alg.output_len,
+        None => 0,
+    }
+}
+
+/// Computes the digest of `in` with `alg`.
+#[no_mangle]
+pub unsafe extern "C" fn RING_digest(
+    alg: *const digest::Algorithm, out: *mut u8, out_len: *mut c::size_t, max_out_len: c::size_t,
+    in_: *const u8, in_len: c::size_t,
+) -> c::int {
+    let alg = match static_ref(alg) {
+        Some(alg) => alg,
+        None => return FAILURE,
+    };
+    let digest = digest::digest(alg, input(in_, in_len));
+    result(write_output(digest.as_ref(), out, out_len, max_out_len))
+}
+
+/// Returns a new context for a multi-step digest with `alg`, or null if
+/// `alg` is null.
+#[no_mangle]
+pub unsafe extern "C" fn RING_digest_ctx_new(
+    alg: *const digest::Algorithm,
+) -> *mut digest::Context {
+    match static_ref(alg) {
+        Some(alg) => Box::into_raw(Box::new(digest::Context::new(alg))),
+        None => core::ptr::null_mut(),
+    }
+}
+
+/// Adds `in` to the data being digested.
+#[no_mangle]
+pub unsafe extern "C" fn RING_digest_ctx_update(
+    ctx: *mut digest::Context, in_: *const u8, in_len: c::size_t,
+) -> c::int {
+    match ctx.as_mut() {
+        Some(ctx) => {
+            ctx.update(input(in_, in_len));
+            SUCCESS
+        },
+        None => FAILURE,
+    }
+}
+
+/// Writes the digest of the data that was added to `ctx` and frees `ctx`,
+/// even on failure.
+#[no_mangle]
+pub unsafe extern "C" fn RING_digest_ctx_final(
+    ctx: *mut digest::Context, out: *mut u8, out_len: *mut c::size_t, max_out_len: c::size_t,
+) -> c::int {
+    if ctx.is_null() {
+        return FAILURE;
+    }
+    let digest = Box::from_raw(ctx).finish();
+    result(write_output(digest.as_ref(), out, out_len, max_out_len))
+}
+
+/// Frees `ctx` without finishing the digest.
+#[no_mangle]
+pub unsafe extern "C" fn RING_digest_ctx_free(ctx: *mut digest::Context) { free(ctx) }
+
+/// Returns a new HMAC key for `alg`, or null if `alg` is null.
+#[no_mangle]
+pub unsafe extern "C" fn RING_hmac_key_new(
+    alg: *const digest::Algorithm, key: *const u8, key_len: c::size_t,
+) -> *mut hmac::SigningKey {
+    match static_ref(alg) {
+        Some(alg) => Box::into_raw(Box::new(hmac::SigningKey::new(alg, input(key, key_len)))),
+        None => core::ptr::null_mut(),
+    }
+}
+
+/// Frees `key`.
+#[no_mangle]
+pub unsafe extern "C" fn RING_hmac_key_free(key: *mut hmac::SigningKey) { free(key) }
+
+/// Computes the HMAC of `in` with `key`.
+#[no_mangle]
+pub unsafe extern "C" fn RING_hmac_sign(
+    key: *const hmac::SigningKey, out: *mut u8, out_len: *mut c::size_t, max_out_len: c::size_t,
+    in_: *const u8, in_len: c::size_t,
+) -> c::int {
+    let key = match key.as_ref() {
+        Some(key) => key,
+        None => return FAILURE,
+    };
+    let tag = hmac::sign(key, input(in_, in_len));
+    result(write_output(tag.as_ref(), out, out_len, max_out_len))
+}
+
+/// Checks, in constant time, that `tag` is the HMAC of `in` with `key`.
+#[no_mangle]
+pub unsafe extern "C" fn RING_hmac_verify(
+    key: *const hmac::SigningKey, in_: *const u8, in_len: c::size_t, tag: *const u8,
+    tag_len: c::size_t,
+) -> c::int {
+    let key = match key.as_ref() {
+        Some(key) => key,
+        None => return FAILURE,
+    };
+    result(hmac::verify_with_own_key(
+        key,
+        input(in_, in_len),
+        input(tag, tag_len),
+    ))
+}
+
+/// Returns AES-128-GCM.
+#[no_mangle]
+pub extern "C" fn RING_aead_aes_128_gcm() -> *const aead::Algorithm { &aead::AES_128_GCM }
+
+/// Returns AES-256-GCM.
+#[no_mangle]
+pub extern "C" fn RING_aead_aes_256_gcm() -> *const aead::Algorithm { &aead::AES_256_GCM }
+
+/// Returns ChaCha20-Poly1305.
+#[no_mangle]
+pub extern "C" fn RING_aead_chacha20_poly1305() -> *const aead::Algorithm {
+    &aead::CHACHA20_POLY1305
+}
+
+/// Returns the length of `alg`'s keys, or 0 if `alg` is null.
+#[no_mangle]
+pub unsafe extern "C" fn RING_aead_key_len(alg: *const aead::Algorithm) -> c::size_t {
+    match alg.as_ref() {
+        Some(alg) => alg.key_len(),
+        None => 0,
+    }
+}
+
+/// Returns the length of `alg`'s nonces, or 0 if `alg` is null.
+#[no_mangle]
+pub unsafe extern "C" fn RING_aead_nonce_len(alg: *const aead::Algorithm) -> c::size_t {
+    match alg.as_ref() {
+        Some(alg) => alg.nonce_len(),
+        None => 0,
+    }
+}
+
+/// Returns the length of `alg`'s tags, or 0 if `alg` is null.
+#[no_mangle]
+pub unsafe extern "C" fn RING_aead_tag_len(alg: *const aead::Algorithm) -> c::size_t {
+    match alg.as_ref() {
+        Some(alg) => alg.tag_len(),
+        None => 0,
+    }
+}
+
+/// An AEAD key for both sealing and opening, like BoringSSL's
+/// `EVP_AEAD_CTX`.
+pub struct AeadContext {
+    sealing_key: aead::SealingKey,
+    opening_key: aead::OpeningKey,
+}
+
+derive_debug_via_self!(AeadContext, self.sealing_key.algorithm());
+
+/// Returns a new AEAD context for `alg` and `key`, or null if `alg` is null
+/// or the key has the wrong length.
+#[no_mangle]
+pub unsafe extern "C" fn RING_aead_ctx_new(
+    alg: *const aead::Algorithm, key: *const u8, key_len: c::size_t,
+) -> *mut AeadContext {
+    let alg = match static_ref(alg) {
+        Some(alg) => alg,
+        None => return core::ptr::null_mut(),
+    };
+    let key = input(key, key_len);
+    match (aead::SealingKey::new(alg, key), aead::OpeningKey::new(alg, key)) {
+        (Ok(sealing_key), Ok(opening_key)) => Box::into_raw(Box::new(AeadContext {
+            sealing_key,
+            opening_key,
+        })),
+        _ => core::ptr::null_mut(),
+    }
+}
+
+/// Frees `ctx`.
+#[no_mangle]
+pub unsafe extern "C" fn RING_aead_ctx_free(ctx: *mut AeadContext) { free(ctx) }
+
+/// Encrypts and authenticates `in` and authenticates `ad`, writing the
+/// ciphertext followed by the tag to `out`, which must have room for
+/// `in_len` plus the tag length. `in` and `out` may be equal, but must not
+/// otherwise overlap.
+#[no_mangle]
+pub unsafe extern "C" fn RING_aead_seal(
+    ctx: *const AeadContext, out: *mut u8, out_len: *mut c::size_t, max_out_len: c::size_t,
+    nonce: *const u8, nonce_len: c::size_t, in_: *const u8, in_len: c::size_t, ad: *const u8,
+    ad_len: c::size_t,
+) -> c::int {
+    let ctx = match ctx.as_ref() {
+        Some(ctx) => ctx,
+        None => return FAILURE,
+    };
+    let sealed_len = match in_len.checked_add(aead::MAX_TAG_LEN) {
+        Some(sealed_len) if sealed_len <= max_out_len && !out.is_null() && !out_len.is_null() => {
+            sealed_len
+        },
+        _ => return FAILURE,
+    };
+    if in_len > 0 {
+        core::ptr::copy(in_, out, in_len);
+    }
+    match aead::seal_in_place(
+        &ctx.sealing_key,
+        input(nonce, nonce_len),
+        input(ad, ad_len),
+        output(out, sealed_len),
+        aead::MAX_TAG_LEN,
+    ) {
+        Ok(len) => {
+            *out_len = len;
+            SUCCESS
+        },
+        Err(error::Unspecified) => FAILURE,
+    }
+}
+
+/// Authenticates `in`, which is the ciphertext followed by the tag, and `ad`,
+/// and decrypts the ciphertext to `out`, which must have room for `in_len`
+/// minus the tag length. On failure, `out` may have been overwritten with
+/// zeros. `in` and `out` may be equal, but must not otherwise overlap.
+#[no_mangle]
+pub unsafe extern "C" fn RING_aead_open(
+    ctx: *const AeadContext, out: *mut u8, out_len: *mut c::size_t, max_out_len: c::size_t,
+    nonce: *const u8, nonce_len: c::size_t, in_: *const u8, in_len: c::size_t, ad: *const u8,
+    ad_len: c::size_t,
+) -> c::int {
+    let ctx = match ctx.as_ref() {
+        Some(ctx) => ctx,
+        None => return FAILURE,
+    };
+    let ciphertext_len = match in_len.checked_sub(aead::MAX_TAG_LEN) {
+        Some(len) if len <= max_out_len && !out.is_null() && !out_len.is_null() => len,
+        _ => return FAILURE,
+    };
+    // Copy the tag first, as `out` may be `in`.
+    let mut tag = [0u8; aead::MAX_TAG_LEN];
+    tag.copy_from_slice(input(in_.add(ciphertext_len), aead::MAX_TAG_LEN));
+    if ciphertext_len > 0 {
+        core::ptr::copy(in_, out, ciphertext_len);
+    }
+    let ciphertext = output(out, ciphertext_len);
+    match aead::open_in_place_separate_tag(
+        &ctx.opening_key,
+        input(nonce, nonce_len),
+        input(ad, ad_len),
+        ciphertext,
+        &tag,
+    ) {
+        Ok(()) => {
+            *out_len = ciphertext_len;
+            SUCCESS
+        },
+        Err(error::Unspecified) => FAILURE,
+    }
+}
+
+/// Returns a new Ed25519 key pair for the 32-byte `seed`, or null if the
+/// seed has the wrong length.
+#[no_mangle]
+pub unsafe extern "C" fn RING_ed25519_key_pair_from_seed(
+    seed: *const u8, seed_len: c::size_t,
+) -> *mut signature::Ed25519KeyPair {
+    key_pair(signature::Ed25519KeyPair::from_seed_unchecked(
+        untrusted::Input::from(input(seed, seed_len)),
+    ))
+}
+
+/// Returns a new Ed25519 key pair parsed from a v2 PKCS#8 document, as
+/// `Ed25519KeyPair::from_pkcs8()` does, or null if it is invalid.
+#[no_mangle]
+pub unsafe extern "C" fn RING_ed25519_key_pair_from_pkcs8(
+    pkcs8: *const u8, pkcs8_len: c::size_t,
+) -> *mut signature::Ed25519KeyPair {
+    key_pair(signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(
+        input(pkcs8, pkcs8_len),
+    )))
+}
+
+/// Frees `key_pair`.
+#[no_mangle]
+pub unsafe extern "C" fn RING_ed25519_key_pair_free(key_pair: *mut signature::Ed25519KeyPair) {
+    free(key_pair)
+}
+
+/// Writes the 32-byte public key of `key_pair`.
+#[no_mangle]
+pub unsafe extern "C" fn RING_ed25519_public_key(
+    key_pair: *const signature::Ed25519KeyPair, out: *mut u8, out_len: *mut c::size_t,
+    max_out_len: c::size_t,
+) -> c::int {
+    match key_pair.as_ref() {
+        Some(key_pair) => result(write_output(
+            key_pair.public_key_bytes(),
+            out,
+            out_len,
+            max_out_len,
+        )),
+        None => FAILURE,
+    }
+}
+
+/// Writes the 64-byte signature of `msg` by `key_pair`.
+#[no_mangle]
+pub unsafe extern "C" fn RING_ed25519_sign(
+    key_pair: *const signature::Ed25519KeyPair, out: *mut u8, out_len: *mut c::size_t,
+    max_out_len: c::size_t, msg: *const u8, msg_len: c::size_t,
+) -> c::int {
+    let key_pair = match key_pair.as_ref() {
+        Some(key_pair) => key_pair,
+        None => return FAILURE,
+    };
+    let signature = key_pair.sign(input(msg, msg_len));
+    result(write_output(signature.as_ref(), out, out_len, max_out_len))
+}
+
+/// Checks that `sig` is a valid signature of `msg` by the 32-byte
+/// `public_key`.
+#[no_mangle]
+pub unsafe extern "C" fn RING_ed25519_verify(
+    public_key: *const u8, public_key_len: c::size_t, msg: *const u8, msg_len: c::size_t,
+    sig: *const u8, sig_len: c::size_t,
+) -> c::int {
+    result(signature::verify(
+        &signature::ED25519,
+        untrusted::Input::from(input(public_key, public_key_len)),
+        untrusted::Input::from(input(msg, msg_len)),
+        untrusted::Input::from(input(sig, sig_len)),
+    ))
+}
+
+const SUCCESS: c::int = 1;
+const FAILURE: c::int = 0;
+
+fn result(r: Result<(), error::Unspecified>) -> c::int {
+    match r {
+        Ok(()) => SUCCESS,
+        Err(error::Unspecified) => FAILURE,
+    }
+}
+
+// The algorithms are all statics, so the pointers to them are too.
+unsafe fn static_ref<T>(ptr: *const T) -> Option<&'static T> { ptr.as_ref() }
+
+// Null pointers are allowed for empty inputs and outputs.
+unsafe fn input<'a>(ptr: *const u8, len: c::size_t) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        core::slice::from_raw_parts(ptr, len)
+    }
+}
+
+unsafe fn output<'a>(ptr: *mut u8, len: c::size_t) -> &'a mut [u8] {
+    if len == 0 {
+        &mut []
+    } else {
+        core::slice::from_raw_parts_mut(ptr, len)
+    }
+}
+
+unsafe fn write_output(
+    value: &[u8], out: *mut u8, out_len: *mut c::size_t, max_out_len: c::size_t,
+) -> Result<(), error::Unspecified> {
+    if value.len() > max_out_len || out_len.is_null() || (out.is_null() && !value.is_empty()) {
+        return Err(error::Unspecified);
+    }
+    output(out, value.len()).copy_from_slice(value);
+    *out_len = value.len();
+    Ok(())
+}
+
+fn key_pair(
+    key_pair: Result<signature::Ed25519KeyPair, error::KeyRejected>,
+) -> *mut signature::Ed25519KeyPair {
+    match key_pair {
+        Ok(key_pair) => Box::into_raw(Box::new(key_pair)),
+        Err(_) => core::ptr::null_mut(),
+    }
+}
+
+unsafe fn free<T>(ptr: *mut T) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr));
+    }
+}
@@ -13,24 +13,478 @@
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
 //! Constant-time operations.
+//!
+//! Secret conditions are `Choice`s, such as the results of `slices_equal()`,
+//! `eq()`, `words_equal()`, and `less_than()`, and secret optional values are
+//! `CtOption`s; both can be combined without branching on them. `select()`,
+//! `swap_if()`, and `lookup()` choose between values based on a `Choice` by
+//! turning it into a mask that is passed through `core::hint::black_box()`,
+//! so the compiler can't see that it's all zeros or all ones and turn the
+//! masking back into a branch.
+//!
+//! `io::hex` and `io::base64` encode and decode secrets, e.g. keys loaded
+//! from configuration files, the same way.
 
 use crate::{c, error};
+use core::ops::{BitAnd, BitOr, BitXor, Not};
 
 /// Returns `Ok(())` if `a == b` and `Err(error::Unspecified)` otherwise.
 /// The comparison of `a` and `b` is done in constant time with respect to the
 /// contents of each, but NOT in constant time with respect to the lengths of
 /// `a` and `b`.
 pub fn verify_slices_are_equal(a: &[u8], b: &[u8]) -> Result<(), error::Unspecified> {
+    if slices_equal(a, b).expose() {
+        Ok(())
+    } else {
+        Err(error::Unspecified)
+    }
+}
+
+/// Returns whether `a == b`, without revealing it.
+///
+/// Like `verify_slices_are_equal()`, the comparison is done in constant time
+/// with respect to the contents of `a` and `b`, but NOT with respect to their
+/// lengths.
+pub fn slices_equal(a: &[u8], b: &[u8]) -> Choice {
     if a.len() != b.len() {
-        return Err(error::Unspecified);
+        return Choice::from(false);
     }
     let result = unsafe { GFp_memcmp(a.as_ptr(), b.as_ptr(), a.len()) };
-    match result {
-        0 => Ok(()),
-        _ => Err(error::Unspecified),
+    words_equal(result as u32, 0)
+}
+
+/// Returns whether `a == b`, without revealing it.
+///
+/// Unlike `slices_equal()`, the lengths can't differ, so the whole comparison
+/// is done in constant time.
+///
+/// ```
+/// use ring::constant_time;
+///
+/// let tag = [0x5au8; 16];
+/// assert!(constant_time::eq(&tag, &[0x5a; 16]).expose());
+/// assert!(!constant_time::eq::<16>(&tag, &[0; 16]).expose());
+/// ```
+#[inline]
+pub fn eq<const N: usize>(a: &[u8; N], b: &[u8; N]) -> Choice { slices_equal(a, b) }
+
+/// Returns whether `a == b`, without revealing it.
+///
+/// ```
+/// use ring::constant_time;
+///
+/// assert!(constant_time::words_equal(7u32, 7).expose());
+/// assert!(!constant_time::words_equal(7u64, 8).expose());
+/// ```
+#[inline]
+pub fn words_equal<T: Word>(a: T, b: T) -> Choice { (a ^ b).is_zero() }
+
+/// Returns whether `a < b`, without revealing it.
+///
+/// ```
+/// use ring::constant_time;
+///
+/// assert!(constant_time::less_than(7u32, 8).expose());
+/// assert!(!constant_time::less_than(8u64, 8).expose());
+/// ```
+#[inline]
+pub fn less_than<T: Word>(a: T, b: T) -> Choice { a.less_than(b) }
+
+/// A secret condition, e.g. the result of `slices_equal()`.
+///
+/// A `Choice` can be combined with `&`, `|`, `^`, and `!`, and used by
+/// `select()`, `swap_if()`, and `CtOption`, none of which branch on it. It has
+/// to be explicitly `expose()`d to be used as a `bool`, which makes it easy to
+/// find the places where a secret condition is revealed.
+#[derive(Clone, Copy)]
+pub struct Choice(u8); // 0 or 1.
+
+impl Choice {
+    /// Reveals the condition.
+    #[inline]
+    pub fn expose(self) -> bool { self.0 != 0 }
+
+    // `mask` must be 0xff or 0.
+    #[inline]
+    pub(crate) fn from_mask(mask: u8) -> Self { Choice(core::hint::black_box(mask) & 1) }
+}
+
+impl From<bool> for Choice {
+    #[inline]
+    fn from(value: bool) -> Self { Choice(core::hint::black_box(value as u8)) }
+}
+
+impl BitAnd for Choice {
+    type Output = Self;
+
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self { Choice(self.0 & rhs.0) }
+}
+
+impl BitOr for Choice {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self { Choice(self.0 | rhs.0) }
+}
+
+impl BitXor for Choice {
+    type Output = Self;
+
+    #[inline]
+    fn bitxor(self, rhs: Self) -> Self { Choice(self.0 ^ rhs.0) }
+}
+
+impl Not for Choice {
+    type Output = Self;
+
+    #[inline]
+    fn not(self) -> Self { Choice(self.0 ^ 1) }
+}
+
+/// A value that may or may not be present, without revealing which, e.g. the
+/// result of a constant-time decoding.
+///
+/// The value is computed whether or not it's present, and `map()` and
+/// `and_then()` always call their functions, so code that uses them does the
+/// same work either way. Like a `Choice`, it has to be explicitly `expose()`d.
+///
+/// ```
+/// use ring::constant_time::{self, CtOption};
+///
+/// fn unlock(pin: &[u8], stored_pin: &[u8], secret: u32) -> CtOption<u32> {
+///     CtOption::new(secret, constant_time::slices_equal(pin, stored_pin))
+/// }
+///
+/// assert_eq!(unlock(b"1234", b"1234", 42).expose(), Some(42));
+/// assert_eq!(unlock(b"0000", b"1234", 42).unwrap_or(0), 0);
+/// let doubled = unlock(b"1234", b"1234", 42).map(|secret| secret * 2);
+/// assert_eq!(doubled.expose(), Some(84));
+/// ```
+#[derive(Clone, Copy)]
+pub struct CtOption<T> {
+    value: T,
+    is_some: Choice,
+}
+
+impl<T> CtOption<T> {
+    /// Constructs a `CtOption` that contains `value` if `is_some` is true.
+    #[inline]
+    pub fn new(value: T, is_some: Choice) -> Self { Self { value, is_some } }
+
+    /// Whether the value is present.
+    #[inline]
+    pub fn is_some(&self) -> Choice { self.is_some }
+
+    /// Whether the value is absent.
+    #[inline]
+    pub fn is_none(&self) -> Choice { !self.is_some }
+
+    /// Applies `f` to the value, whether or not it's present.
+    #[inline]
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> CtOption<U> {
+        CtOption::new(f(self.value), self.is_some)
+    }
+
+    /// Applies `f` to the value, whether or not it's present, and returns its
+    /// result if the value is present.
+    #[inline]
+    pub fn and_then<U>(self, f: impl FnOnce(T) -> CtOption<U>) -> CtOption<U> {
+        let result = f(self.value);
+        CtOption::new(result.value, self.is_some & result.is_some)
+    }
+
+    /// Reveals whether the value is present, and returns it if it is.
+    #[inline]
+    pub fn expose(self) -> Option<T> {
+        if self.is_some.expose() {
+            Some(self.value)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Word> CtOption<T> {
+    /// Returns the value if it's present and `default` otherwise, without
+    /// revealing which.
+    #[inline]
+    pub fn unwrap_or(self, default: T) -> T { select_word(self.is_some, self.value, default) }
+}
+
+/// An integer type whose values `select()`, `swap_if()`, `lookup()`,
+/// `words_equal()`, and `less_than()` work on: `u8`, `u16`, `u32`, `u64`, and
+/// `usize`, which include the limb types of multi-precision integers on every
+/// target.
+pub trait Word: Copy + sealed::Word {}
+
+/// Sets `out` to `a` if `cond` is true and to `b` otherwise.
+///
+/// This is done in constant time with respect to `cond` and the contents of
+/// `a` and `b`, but NOT with respect to their lengths. Fails, without writing
+/// anything, unless `a`, `b`, and `out` all have the same length.
+///
+/// ```
+/// use ring::constant_time;
+///
+/// let mut out = [0u8; 3];
+/// let cond = constant_time::slices_equal(b"secret", b"guess");
+/// constant_time::select(cond, b"abc", b"xyz", &mut out).unwrap();
+/// assert_eq!(&out, b"xyz");
+///
+/// let mut limbs = [0u64; 2];
+/// constant_time::select(true.into(), &[1, 2], &[3, 4], &mut limbs).unwrap();
+/// assert_eq!(limbs, [1, 2]);
+/// ```
+pub fn select<T: Word>(
+    cond: Choice, a: &[T], b: &[T], out: &mut [T],
+) -> Result<(), error::Unspecified> {
+    if a.len() != out.len() || b.len() != out.len() {
+        return Err(error::Unspecified);
+    }
+    for ((out, &a), &b) in out.iter_mut().zip(a).zip(b) {
+        *out = select_word(cond, a, b);
+    }
+    Ok(())
+}
+
+#[inline]
+fn select_word<T: Word>(cond: Choice, a: T, b: T) -> T { b ^ ((a ^ b) & T::mask(cond)) }
+
+/// Swaps the contents of `a` and `b` if `cond` is true.
+///
+/// This is done in constant time with respect to `cond` and the contents of
+/// `a` and `b`, but NOT with respect to their lengths. Fails, without changing
+/// anything, unless `a` and `b` have the same length.
+///
+/// ```
+/// use ring::constant_time;
+///
+/// let (mut a, mut b) = ([1u32, 2], [3u32, 4]);
+/// constant_time::swap_if(true.into(), &mut a, &mut b).unwrap();
+/// assert_eq!((a, b), ([3, 4], [1, 2]));
+/// ```
+pub fn swap_if<T: Word>(cond: Choice, a: &mut [T], b: &mut [T]) -> Result<(), error::Unspecified> {
+    if a.len() != b.len() {
+        return Err(error::Unspecified);
+    }
+    let mask = T::mask(cond);
+    for (a, b) in a.iter_mut().zip(b.iter_mut()) {
+        let t = (*a ^ *b) & mask;
+        *a = *a ^ t;
+        *b = *b ^ t;
+    }
+    Ok(())
+}
+
+/// Returns `table[index]` if `index < table.len()`, without revealing
+/// `index`.
+///
+/// Every entry of `table` is read, so the time this takes depends on the
+/// length of `table` but not on `index` or the contents of `table`. If
+/// `index` is out of range, the result is absent, also without revealing it.
+///
+/// ```
+/// use ring::constant_time;
+///
+/// const TABLE: [[u8; 2]; 3] = [[1, 2], [3, 4], [5, 6]];
+/// let secret_index = 2;
+/// assert_eq!(constant_time::lookup(&TABLE, secret_index).expose(), Some([5, 6]));
+/// assert_eq!(constant_time::lookup(&TABLE, 3).expose(), None);
+/// ```
+pub fn lookup<T: Word, const N: usize>(table: &[[T; N]], index: usize) -> CtOption<[T; N]> {
+    let mut result = [T::ZERO; N];
+    let mut found = Choice::from(false);
+    for (i, entry) in table.iter().enumerate() {
+        let is_index = words_equal(i, index);
+        for (result, &entry) in result.iter_mut().zip(entry) {
+            *result = select_word(is_index, entry, *result);
+        }
+        found = found | is_index;
+    }
+    CtOption::new(result, found)
+}
+
+macro_rules! word {
+    ( $word:ty ) => {
+        impl Word for $word {}
+
+        impl sealed::Word for $word {
+            const ZERO: Self = 0;
+
+            #[inline]
+            fn mask(cond: Choice) -> Self {
+                core::hint::black_box(<$word>::from(cond.0).wrapping_neg())
+            }
+
+            #[allow(trivial_numeric_casts)]
+            #[inline]
+            fn is_zero(self) -> Choice {
+                // The high bit of `a | -a` is set iff `a != 0`.
+                let a = core::hint::black_box(self);
+                let high_bit = (a | a.wrapping_neg()) >> (<$word>::BITS - 1);
+                Choice((high_bit as u8) ^ 1)
+            }
+
+            #[allow(trivial_numeric_casts)]
+            #[inline]
+            fn less_than(self, b: Self) -> Choice {
+                // As in `constant_time_lt_w()` in crypto/internal.h.
+                let a = core::hint::black_box(self);
+                let lt = a ^ ((a ^ b) | (a.wrapping_sub(b) ^ a));
+                Choice((lt >> (<$word>::BITS - 1)) as u8)
+            }
+        }
+    };
+}
+
+word!(u8);
+word!(u16);
+word!(u32);
+word!(u64);
+word!(usize);
+
+mod sealed {
+    use super::{BitAnd, BitXor, Choice};
+
+    pub trait Word: BitAnd<Output = Self> + BitXor<Output = Self> + Sized {
+        const ZERO: Self;
+
+        // Returns all ones if `cond` is true and all zeros otherwise, hidden
+        // from the optimizer.
+        fn mask(cond: Choice) -> Self;
+
+        fn is_zero(self) -> Choice;
+
+        fn less_than(self, b: Self) -> Choice;
     }
 }
 
 extern "C" {
     fn GFp_memcmp(a: *const u8, b: *const u8, len: c::size_t) -> c::int;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choice() {
+        let (t, f) = (Choice::from(true), Choice::from(false));
+        assert!(t.expose() && !f.expose());
+        assert!(!(t & f).expose() && (t & t).expose());
+        assert!((t | f).expose() && !(f | f).expose());
+        assert!((t ^ f).expose() && !(t ^ t).expose());
+        assert!(!(!t).expose() && (!f).expose());
+    }
+
+    #[test]
+    fn test_slices_equal() {
+        assert!(slices_equal(b"", b"").expose());
+        assert!(slices_equal(b"abc", b"abc").expose());
+        assert!(!slices_equal(b"abc", b"abd").expose());
+        assert!(!slices_equal(b"\x80", b"\x00").expose());
+        assert!(!slices_equal(b"abc", b"ab").expose());
+        assert!(verify_slices_are_equal(b"abc", b"abc").is_ok());
+        assert!(verify_slices_are_equal(b"abc", b"abd").is_err());
+    }
+
+    #[test]
+    fn test_eq() {
+        assert!(eq(&[], &[]).expose());
+        assert!(eq(&[1, 2, 3], &[1, 2, 3]).expose());
+        assert!(!eq(&[1, 2, 3], &[1, 2, 4]).expose());
+        assert!(!eq(&[0x80], &[0]).expose());
+    }
+
+    #[test]
+    fn test_words_equal_and_less_than() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                assert_eq!(words_equal(a, b).expose(), a == b);
+                assert_eq!(less_than(a, b).expose(), a < b);
+            }
+        }
+        let max = u64::max_value();
+        for &(a, b) in &[(0, 0), (0, 1), (1, 0), (0, max), (max, 0), (max, max), (1 << 63, 1)] {
+            assert_eq!(words_equal(a, b).expose(), a == b);
+            assert_eq!(less_than(a, b).expose(), a < b);
+            let (a, b) = (a as u32, b as u32);
+            assert_eq!(words_equal(a, b).expose(), a == b);
+            assert_eq!(less_than(a, b).expose(), a < b);
+        }
+        assert!(less_than(0usize, usize::max_value()).expose());
+        assert!(words_equal(0x8000u16, 0x8000).expose());
+    }
+
+    #[test]
+    fn test_ct_option() {
+        let some = CtOption::new(5u32, Choice::from(true));
+        let none = CtOption::new(7u32, Choice::from(false));
+        assert!(some.is_some().expose() && !some.is_none().expose());
+        assert!(!none.is_some().expose() && none.is_none().expose());
+        assert_eq!(some.expose(), Some(5));
+        assert_eq!(none.expose(), None);
+        assert_eq!(some.unwrap_or(0), 5);
+        assert_eq!(none.unwrap_or(0), 0);
+
+        // `map()` and `and_then()` call their functions even for `none`.
+        let calls = core::cell::Cell::new(0);
+        let count = |v: u32| {
+            calls.set(calls.get() + 1);
+            v
+        };
+        assert_eq!(none.map(|v| count(v) + 1).expose(), None);
+        assert_eq!(some.map(|v| count(v) + 1).expose(), Some(6));
+        assert_eq!(some.and_then(|v| none.map(|w| count(v) + w)).expose(), None);
+        assert_eq!(some.and_then(|v| some.map(|w| count(v) + w)).expose(), Some(10));
+        assert_eq!(none.and_then(|v| some.map(|w| count(v) + w)).expose(), None);
+        assert_eq!(calls.get(), 5);
+    }
+
+    #[test]
+    fn test_select() {
+        let mut out = [0xffu8; 3];
+        assert!(select(Choice::from(true), &[1, 2, 3], &[4, 5, 6], &mut out).is_ok());
+        assert_eq!(out, [1, 2, 3]);
+        assert!(select(Choice::from(false), &[1, 2, 3], &[4, 5, 6], &mut out).is_ok());
+        assert_eq!(out, [4, 5, 6]);
+
+        let mut limbs = [0usize; 2];
+        let max = usize::max_value();
+        assert!(select(Choice::from(true), &[max, 0], &[0, 1], &mut limbs).is_ok());
+        assert_eq!(limbs, [max, 0]);
+
+        assert!(select(Choice::from(true), &[1, 2], &[3], &mut out[..2]).is_err());
+        assert!(select(Choice::from(true), &[1, 2], &[3, 4], &mut out).is_err());
+        assert_eq!(out, [4, 5, 6]);
+        assert!(select::<u16>(Choice::from(false), &[], &[], &mut []).is_ok());
+    }
+
+    #[test]
+    fn test_lookup() {
+        let table = [[1u8, 2, 3], [4, 5, 6], [7, 8, 9]];
+        for (i, entry) in table.iter().enumerate() {
+            assert_eq!(lookup(&table, i).expose(), Some(*entry));
+        }
+        assert_eq!(lookup(&table, 3).expose(), None);
+        assert_eq!(lookup(&table, usize::max_value()).expose(), None);
+        assert_eq!(lookup::<u8, 3>(&[], 0).expose(), None);
+
+        let limbs = [[u64::max_value()], [0], [1 << 63]];
+        assert_eq!(lookup(&limbs, 0).expose(), Some([u64::max_value()]));
+        assert_eq!(lookup(&limbs, 2).expose(), Some([1 << 63]));
+    }
+
+    #[test]
+    fn test_swap_if() {
+        let (mut a, mut b) = ([1u64, u64::max_value()], [3u64, 0]);
+        assert!(swap_if(Choice::from(false), &mut a, &mut b).is_ok());
+        assert_eq!((a, b), ([1, u64::max_value()], [3, 0]));
+        assert!(swap_if(Choice::from(true), &mut a, &mut b).is_ok());
+        assert_eq!((a, b), ([3, 0], [1, u64::max_value()]));
+
+        assert!(swap_if(Choice::from(true), &mut a, &mut b[..1]).is_err());
+        assert_eq!((a, b), ([3, 0], [1, u64::max_value()]));
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Public keys in CBOR Object Signing and Encryption (COSE) `COSE_Key`s
+//! ([RFC 8152 Section 7]), e.g. WebAuthn credential public keys.
+//!
+//! The supported key types are `OKP` with the `Ed25519` and `X25519` curves
+//! and `EC2` with the `P-256` and `P-384` curves, using uncompressed points.
+//! Keys are parsed into `spki::PublicKey`, whose `as_ref()` is what
+//! `signature::verify()` and `agreement::agree_ephemeral()` take as the
+//! public key.
+//!
+//! The CBOR must be canonically encoded (RFC 7049 Section 3.9), except that
+//! the map labels may be in any order, and the key parameters must not be
+//! repeated. Parameters other than `kty`, `alg`, `crv`, `x`, and `y` are
+//! ignored, including the private key parameter `d`.
+//!
+//! ```
+//! use ring::{cose, signature};
+//!
+//! // {1: 1, 3: -8, -1: 6, -2: h'd75a...511a'}, an EdDSA credential public key.
+//! let cose_key = [
+//!     0xa4, 0x01, 0x01, 0x03, 0x27, 0x20, 0x06, 0x21, 0x58, 0x20, 0xd7, 0x5a, 0x98, 0x01, 0x82,
+//!     0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a, 0x0e, 0xe1, 0x72, 0xf3,
+//!     0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a,
+//! ];
+//! let key = cose::parse_public_key(untrusted::Input::from(&cose_key)).unwrap();
+//! assert_eq!(key.algorithm(), Some(cose::ALG_EDDSA));
+//! assert_eq!(key.public_key().as_ref().len(), signature::ED25519_PUBLIC_KEY_LEN);
+//! ```
+//!
+//! [RFC 8152 Section 7]: https://tools.ietf.org/html/rfc8152#section-7
+
+use crate::{
+    agreement,
+    ec::{self, suite_b},
+    error, signature, spki,
+};
+use untrusted;
+
+mod cbor;
+
+/// The COSE algorithm identifier of ECDSA with SHA-256 (`ES256`).
+pub const ALG_ES256: i64 = -7;
+
+/// The COSE algorithm identifier of ECDSA with SHA-384 (`ES384`).
+pub const ALG_ES384: i64 = -35;
+
+/// The COSE algorithm identifier of EdDSA.
+pub const ALG_EDDSA: i64 = -8;
+
+// The labels of the key parameters (RFC 8152 Sections 7.1, 13.1.1, and
+// 13.2).
+const LABEL_KTY: i64 = 1;
+const LABEL_ALG: i64 = 3;
+const LABEL_CRV: i64 = -1;
+const LABEL_X: i64 = -2;
+const LABEL_Y: i64 = -3;
+
+// Key types (RFC 8152 Section 13).
+const KTY_OKP: i64 = 1;
+const KTY_EC2: i64 = 2;
+
+// Elliptic curves (RFC 8152 Section 13.1).
+const CRV_P256: i64 = 1;
+const CRV_P384: i64 = 2;
+const CRV_X25519: i64 = 4;
+const CRV_ED25519: i64 = 6;
+
+/// A public key parsed from a `COSE_Key`.
+#[derive(Clone, Debug)]
+pub struct PublicKey {
+    public_key: spki::PublicKey,
+    algorithm: Option<i64>,
+    agreement_algorithm: Option<&'static agreement::Algorithm>,
+}
+
+impl PublicKey {
+    /// The public key.
+    #[inline]
+    pub fn public_key(&self) -> &spki::PublicKey { &self.public_key }
+
+    /// The value of the `alg` parameter, if present, e.g. `ALG_ES256`,
+    /// `ALG_ES384`, or `ALG_EDDSA`.
+    ///
+    /// The `alg` isn't checked against the key type. Note that ECDSA
+    /// signatures in WebAuthn are ASN.1 DER-encoded, so they are verified with
+    /// e.g. `signature::ECDSA_P256_SHA256_ASN1`, whereas ECDSA signatures in
+    /// COSE structures are verified with e.g.
+    /// `signature::ECDSA_P256_SHA256_FIXED`.
+    #[inline]
+    pub fn algorithm(&self) -> Option<i64> { self.algorithm }
+
+    /// The key agreement algorithm for the key's curve: `agreement::X25519`
+    /// for an `X25519` key, `agreement::ECDH_P256` or `agreement::ECDH_P384`
+    /// for an `EC2` key, and `None` for an `Ed25519` key.
+    #[inline]
+    pub fn agreement_algorithm(&self) -> Option<&'static agreement::Algorithm> {
+        self.agreement_algorithm
+    }
+}
+
+/// Parses a `COSE_Key`, which must be all of `cose_key`.
+pub fn parse_public_key(cose_key: untrusted::Input) -> Result<PublicKey, error::KeyRejected> {
+    cose_key.read_all(error::KeyRejected::invalid_encoding(), read_public_key)
+}
+
+/// Parses a `COSE_Key` at the start of `input`, leaving `input` positioned
+/// after it.
+///
+/// In WebAuthn authenticator data the credential public key is followed by
+/// the extensions, if any, so it has to be read this way.
+pub fn read_public_key(input: &mut untrusted::Reader) -> Result<PublicKey, error::KeyRejected> {
+    let parameters = Parameters::read(input)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    let algorithm = match parameters.alg {
+        Some(alg) => Some(integer(alg)?),
+        None => None,
+    };
+    let kty = integer(parameters.kty.ok_or_else(error::KeyRejected::invalid_encoding)?)?;
+    if kty != KTY_OKP && kty != KTY_EC2 {
+        return Err(error::KeyRejected::wrong_algorithm());
+    }
+    let crv = integer(parameters.crv.ok_or_else(error::KeyRejected::invalid_encoding)?)?;
+    let x = parameters.x.ok_or_else(error::KeyRejected::invalid_encoding)?;
+    if kty == KTY_OKP {
+        let (alg_id, agreement_algorithm) = match crv {
+            CRV_ED25519 => (spki::ED25519, None),
+            CRV_X25519 => (spki::X25519, Some(&agreement::X25519)),
+            _ => return Err(error::KeyRejected::wrong_algorithm()),
+        };
+        let x = fixed_len_bytes(x, signature::ED25519_PUBLIC_KEY_LEN)?;
+        return Ok(PublicKey {
+            public_key: spki::PublicKey::new(alg_id, x),
+            algorithm,
+            agreement_algorithm,
+        });
+    }
+
+    let (curve, agreement_algorithm): (&ec::Curve, _) = match crv {
+        CRV_P256 => (&suite_b::curve::P256, &agreement::ECDH_P256),
+        CRV_P384 => (&suite_b::curve::P384, &agreement::ECDH_P384),
+        _ => return Err(error::KeyRejected::wrong_algorithm()),
+    };
+    // `y` is a boolean for a compressed point, which isn't supported.
+    let y = parameters.y.ok_or_else(error::KeyRejected::invalid_encoding)?;
+    let x = fixed_len_bytes(x, curve.elem_and_scalar_len)?;
+    let y = fixed_len_bytes(y, curve.elem_and_scalar_len)?;
+    let mut point = [0; ec::PUBLIC_KEY_MAX_LEN];
+    point[0] = 4;
+    point[1..][..x.len()].copy_from_slice(x);
+    point[(1 + x.len())..][..y.len()].copy_from_slice(y);
+    Ok(PublicKey {
+        public_key: spki::PublicKey::from_ec_public_key(curve, &point[..curve.public_key_len]),
+        algorithm,
+        agreement_algorithm: Some(agreement_algorithm),
+    })
+}
+
+// The values of the key parameters that are used.
+#[derive(Default)]
+struct Parameters<'a> {
+    kty: Option<cbor::Value<'a>>,
+    alg: Option<cbor::Value<'a>>,
+    crv: Option<cbor::Value<'a>>,
+    x: Option<cbor::Value<'a>>,
+    y: Option<cbor::Value<'a>>,
+}
+
+impl<'a> Parameters<'a> {
+    fn read(input: &mut untrusted::Reader<'a>) -> Result<Self, error::Unspecified> {
+        let mut parameters = Self::default();
+        let len = cbor::read_map_len(input)?;
+        for _ in 0..len {
+            let label = cbor::read_label(input)?;
+            let value = cbor::read_value(input)?;
+            let parameter = match label {
+                cbor::Label::Integer(LABEL_KTY) => &mut parameters.kty,
+                cbor::Label::Integer(LABEL_ALG) => &mut parameters.alg,
+                cbor::Label::Integer(LABEL_CRV) => &mut parameters.crv,
+                cbor::Label::Integer(LABEL_X) => &mut parameters.x,
+                cbor::Label::Integer(LABEL_Y) => &mut parameters.y,
+                _ => continue,
+            };
+            if parameter.is_some() {
+                return Err(error::Unspecified);
+            }
+            *parameter = Some(value);
+        }
+        Ok(parameters)
+    }
+}
+
+fn integer(value: cbor::Value) -> Result<i64, error::KeyRejected> {
+    match value {
+        cbor::Value::Integer(value) => Ok(value),
+        _ => Err(error::KeyRejected::invalid_encoding()),
+    }
+}
+
+fn fixed_len_bytes<'a>(
+    value: cbor::Value<'a>, len: usize,
+) -> Result<&'a [u8], error::KeyRejected> {
+    match value {
+        cbor::Value::Bytes(bytes) if bytes.len() == len => Ok(bytes.as_slice_less_safe()),
+        _ => Err(error::KeyRejected::invalid_encoding()),
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Just enough CBOR ([RFC 7049]) for `COSE_Key`s: maps whose labels are
+//! integers or text strings and whose values are returned if they are
+//! integers or byte strings. Values of other types are validated and then
+//! skipped.
+//!
+//! Only definite lengths and the shortest encoding of each integer and
+//! length are accepted, as in the canonical CBOR of RFC 7049 Section 3.9,
+//! except that map keys may be in any order.
+//!
+//! [RFC 7049]: https://tools.ietf.org/html/rfc7049
+
+use crate::error;
+use untrusted;
+
+// Nested arrays, maps, and tags deeper than this are rejected.
+const MAX_DEPTH: usize = 16;
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+const MAJOR_SIMPLE: u8 = 7;
+
+/// A map label.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Label<'a> {
+    Integer(i64),
+    Text(&'a str),
+}
+
+/// A map value.
+#[derive(Clone, Copy)]
+pub enum Value<'a> {
+    Integer(i64),
+    Bytes(untrusted::Input<'a>),
+
+    /// A value of any other type.
+    Other,
+}
+
+/// Reads the header of a map and returns its number of entries.
+pub fn read_map_len(input: &mut untrusted::Reader) -> Result<usize, error::Unspecified> {
+    match read_header(input)? {
+        (MAJOR_MAP, len) => to_usize(len),
+        _ => Err(error::Unspecified),
+    }
+}
+
+pub fn read_label<'a>(input: &mut untrusted::Reader<'a>) -> Result<Label<'a>, error::Unspecified> {
+    match read_header(input)? {
+        (MAJOR_UNSIGNED, value) => Ok(Label::Integer(unsigned(value)?)),
+        (MAJOR_NEGATIVE, value) => Ok(Label::Integer(negative(value)?)),
+        (MAJOR_TEXT, len) => Ok(Label::Text(text(input, len)?)),
+        _ => Err(error::Unspecified),
+    }
+}
+
+pub fn read_value<'a>(input: &mut untrusted::Reader<'a>) -> Result<Value<'a>, error::Unspecified> {
+    read_value_(input, 1)
+}
+
+fn read_value_<'a>(
+    input: &mut untrusted::Reader<'a>, depth: usize,
+) -> Result<Value<'a>, error::Unspecified> {
+    if depth > MAX_DEPTH {
+        return Err(error::Unspecified);
+    }
+    match read_header(input)? {
+        (MAJOR_UNSIGNED, value) => Ok(Value::Integer(unsigned(value)?)),
+        (MAJOR_NEGATIVE, value) => Ok(Value::Integer(negative(value)?)),
+        (MAJOR_BYTES, len) => {
+            let bytes = input.skip_and_get_input(to_usize(len)?)?;
+            Ok(Value::Bytes(bytes))
+        },
+        (MAJOR_TEXT, len) => {
+            let _ = text(input, len)?;
+            Ok(Value::Other)
+        },
+        (MAJOR_ARRAY, len) => {
+            for _ in 0..len {
+                let _ = read_value_(input, depth + 1)?;
+            }
+            Ok(Value::Other)
+        },
+        (MAJOR_MAP, len) => {
+            for _ in 0..len {
+                let _ = read_value_(input, depth + 1)?;
+                let _ = read_value_(input, depth + 1)?;
+            }
+            Ok(Value::Other)
+        },
+        (MAJOR_TAG, _) => {
+            let _ = read_value_(input, depth + 1)?;
+            Ok(Value::Other)
+        },
+        _ => Ok(Value::Other),
+    }
+}
+
+// Returns the major type and the argument: the value of an integer, the
+// length of a string, array, or map, the tag number, or the bits of a simple
+// value or float.
+fn read_header(input: &mut untrusted::Reader) -> Result<(u8, u64), error::Unspecified> {
+    let initial = input.read_byte()?;
+    let major = initial >> 5;
+    let info = initial & 0x1f;
+    let (len, min) = match info {
+        0..=23 => return Ok((major, u64::from(info))),
+        24 => (1, 24),
+        25 => (2, 0x100),
+        26 => (4, 0x1_0000),
+        27 => (8, 0x1_0000_0000),
+        // 28 through 30 are reserved and 31 is an indefinite length or a
+        // "break".
+        _ => return Err(error::Unspecified),
+    };
+    let mut value = 0u64;
+    for _ in 0..len {
+        value = (value << 8) | u64::from(input.read_byte()?);
+    }
+    let min = match (major, info) {
+        // Floats have no shorter encoding to compare against.
+        (MAJOR_SIMPLE, 25..=27) => 0,
+        // Simple values less than 32 must use the one-byte encoding.
+        (MAJOR_SIMPLE, 24) => 32,
+        _ => min,
+    };
+    if value < min {
+        return Err(error::Unspecified);
+    }
+    Ok((major, value))
+}
+
+fn unsigned(value: u64) -> Result<i64, error::Unspecified> {
+    if value > (i64::max_value() as u64) {
+        return Err(error::Unspecified);
+    }
+    Ok(value as i64)
+}
+
+// Major type 1 encodes the integer -1 - `value`.
+fn negative(value: u64) -> Result<i64, error::Unspecified> { Ok(-1 - unsigned(value)?) }
+
+fn text<'a>(input: &mut untrusted::Reader<'a>, len: u64) -> Result<&'a str, error::Unspecified> {
+    let bytes = input.skip_and_get_input(to_usize(len)?)?;
+    core::str::from_utf8(bytes.as_slice_less_safe()).map_err(|_| error::Unspecified)
+}
+
+fn to_usize(value: u64) -> Result<usize, error::Unspecified> {
+    if value > (usize::max_value() as u64) {
+        return Err(error::Unspecified);
+    }
+    Ok(value as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(input: &[u8]) -> Result<Value<'_>, error::Unspecified> {
+        untrusted::Input::from(input).read_all(error::Unspecified, read_value)
+    }
+
+    #[test]
+    fn test_integers() {
+        for &(input, expected) in &[
+            (&[0x00][..], 0),
+            (&[0x17], 23),
+            (&[0x18, 0x18], 24),
+            (&[0x19, 0x01, 0x00], 0x100),
+            (&[0x1a, 0x00, 0x01, 0x00, 0x00], 0x1_0000),
+            (&[0x1b, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00], 0x1_0000_0000),
+            (&[0x1b, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], i64::max_value()),
+            (&[0x20], -1),
+            (&[0x26], -7),
+            (&[0x39, 0x01, 0x00], -257),
+            (&[0x3b, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], i64::min_value()),
+        ] {
+            match value(input) {
+                Ok(Value::Integer(actual)) => assert_eq!(actual, expected),
+                _ => panic!("{:?}", input),
+            }
+        }
+    }
+
+    #[test]
+    fn test_rejects_noncanonical_and_unsupported() {
+        for bad in &[
+            &[][..],
+            &[0x18, 0x17],
+            &[0x19, 0x00, 0xff],
+            &[0x1a, 0x00, 0x00, 0xff, 0xff],
+            &[0x1b, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff],
+            &[0x1b, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            &[0x1c],
+            &[0x1f],
+            &[0x5f, 0x41, 0x00, 0xff],
+            &[0x9f, 0xff],
+            &[0xbf, 0xff],
+            &[0xf8, 0x14],
+            &[0x42, 0x00],
+            &[0x62, 0xc3, 0x28],
+            &[0x00, 0x00],
+        ] {
+            assert!(value(bad).is_err(), "{:?}", bad);
+        }
+        let mut nested = [0x81; MAX_DEPTH + 1];
+        nested[MAX_DEPTH] = 0x80;
+        assert!(value(&nested).is_err());
+        assert!(value(&nested[1..]).is_ok());
+    }
+
+    #[test]
+    fn test_other_values_are_skipped() {
+        for input in &[
+            &[0xf4][..],
+            &[0xf5],
+            &[0xf6],
+            &[0xf8, 0x20],
+            &[0xf9, 0x3c, 0x00],
+            &[0xfb, 0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            &[0x63, 0x61, 0x62, 0x63],
+            &[0x82, 0x01, 0x41, 0x00],
+            &[0xa1, 0x01, 0xa1, 0x61, 0x61, 0x80],
+            &[0xc2, 0x41, 0x01],
+        ] {
+            match value(input) {
+                Ok(Value::Other) => (),
+                _ => panic!("{:?}", input),
+            }
+        }
+    }
+
+    #[test]
+    fn test_map() {
+        let input = untrusted::Input::from(&[0xa2, 0x01, 0x42, 0xab, 0xcd, 0x61, 0x78, 0x20]);
+        input
+            .read_all(error::Unspecified, |input| {
+                assert_eq!(read_map_len(input)?, 2);
+                assert_eq!(read_label(input)?, Label::Integer(1));
+                match read_value(input)? {
+                    Value::Bytes(bytes) => assert_eq!(bytes.as_slice_less_safe(), &[0xab, 0xcd]),
+                    _ => panic!(),
+                }
+                assert_eq!(read_label(input)?, Label::Text("x"));
+                match read_value(input)? {
+                    Value::Integer(-1) => (),
+                    _ => panic!(),
+                }
+                Ok(())
+            })
+            .unwrap();
+        assert!(untrusted::Input::from(&[0x80])
+            .read_all(error::Unspecified, read_map_len)
+            .is_err());
+        assert!(untrusted::Input::from(&[0x41, 0x00])
+            .read_all(error::Unspecified, read_label)
+            .is_err());
+    }
+}
@@ -12,15 +12,714 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
+//! Run-time detection of the CPU features that *ring* uses.
+//!
+//! *ring* chooses between its implementations of AES, GCM, SHA-2, ChaCha20,
+//! and other algorithms based on the CPU features that are detected the first
+//! time they're needed. `features()` reports them, e.g. so that an
+//! application can log whether hardware-accelerated AES-GCM will be used:
+//!
+//! ```
+//! use ring::cpu;
+//!
+//! let features = cpu::features();
+//! let hardware_aes_gcm = features.aes() && (features.pclmulqdq() || features.pmull());
+//! println!("CPU features: {:?}, hardware AES-GCM: {}", features, hardware_aes_gcm);
+//! ```
+//!
+//! Detected features can also be disabled, so that *ring* uses the
+//! implementations that don't need them, e.g. to work around CPU errata or
+//! to test those implementations on modern hardware. Either call
+//! `disable_features()` before using any other part of *ring*, or, with the
+//! `std` feature, set the `RING_DISABLE_CPU_FEATURES` environment variable to
+//! the same comma-separated list of feature names, e.g.
+//! `RING_DISABLE_CPU_FEATURES=avx2,sha2`. Unknown names in the environment
+//! variable are ignored.
+
+use crate::error;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The name of the environment variable that lists the CPU features to
+/// disable when the features are first detected. See `disable_features()`.
+pub const DISABLE_FEATURES_ENV_VAR: &str = "RING_DISABLE_CPU_FEATURES";
+
+// The state of the detection. `std::sync::Once` isn't available without
+// `std`, so the threads that don't detect the features spin until the one
+// that does is done, like `rand::spin_lock::SpinLock`.
+static INIT: AtomicUsize = AtomicUsize::new(INIT_NOT_STARTED);
+const INIT_NOT_STARTED: usize = 0;
+const INIT_RUNNING: usize = 1;
+const INIT_DONE: usize = 2;
+
 #[inline(always)]
-pub fn cache_detected_features() {
-    #[cfg(not(target_os = "ios"))]
+pub(crate) fn cache_detected_features() {
+    if INIT.load(Ordering::Acquire) != INIT_DONE {
+        detect_features_once();
+    }
+}
+
+#[cold]
+fn detect_features_once() {
+    if try_detect_features(|| ()).is_err() {
+        while INIT.load(Ordering::Acquire) != INIT_DONE {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+// Detects the features and then calls `adjust`, which may change them
+// because no other thread reads them until `INIT` is `INIT_DONE`. Fails if
+// another thread has started detecting the features.
+fn try_detect_features(adjust: impl FnOnce()) -> Result<(), error::Unspecified> {
+    if INIT
+        .compare_exchange(INIT_NOT_STARTED, INIT_RUNNING, Ordering::Acquire, Ordering::Acquire)
+        .is_err()
+    {
+        return Err(error::Unspecified);
+    }
+    #[cfg(all(
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64"
+        ),
+        not(any(target_os = "ios", target_env = "sgx"))
+    ))]
     {
-        use std;
         extern "C" {
             fn GFp_cpuid_setup();
         }
-        static INIT: std::sync::Once = std::sync::ONCE_INIT;
-        INIT.call_once(|| unsafe { GFp_cpuid_setup() });
+        unsafe { GFp_cpuid_setup() };
+    }
+    #[cfg(all(target_arch = "x86_64", target_env = "sgx"))]
+    sgx::setup(None);
+    disable_features_from_env_var();
+    adjust();
+    INIT.store(INIT_DONE, Ordering::Release);
+    Ok(())
+}
+
+#[cfg(not(feature = "std"))]
+fn disable_features_from_env_var() {}
+
+#[cfg(feature = "std")]
+fn disable_features_from_env_var() {
+    if let Some(names) = std::env::var_os(DISABLE_FEATURES_ENV_VAR) {
+        if let Some(names) = names.to_str() {
+            for name in feature_names(names) {
+                disable_feature(name);
+            }
+        }
+    }
+}
+
+/// Disables the CPU features named in `names`, a comma-separated list of the
+/// names of `Features`' methods, e.g. `"aes,pclmulqdq"`.
+///
+/// Disabling a feature also disables the features that extend it: `avx`
+/// disables `avx2`, `avx2` disables `avx512f`, `avx512f` disables
+/// `avx512bw` and `avx512ifma`, `aes` disables `vaes`, and `pclmulqdq`
+/// disables `vpclmulqdq`. Features that weren't detected, or that don't exist
+/// on the target's architecture, are ignored.
+///
+/// The features are disabled as they are detected, so this must be called
+/// before any other part of *ring* is used: keys that have already been
+/// constructed may depend on the disabled features. Fails, without disabling
+/// anything, if any name is unknown or if the features have already been
+/// detected, e.g. by an earlier call.
+///
+/// ```
+/// use ring::cpu;
+///
+/// assert!(cpu::disable_features("3dnow").is_err());
+/// cpu::disable_features("aes, avx2").unwrap();
+/// assert!(!cpu::features().aes());
+/// assert!(cpu::disable_features("sha2").is_err());
+/// ```
+pub fn disable_features(names: &str) -> Result<(), error::Unspecified> {
+    if !feature_names(names).all(|name| FEATURE_NAMES.contains(&name)) {
+        return Err(error::Unspecified);
+    }
+    try_detect_features(|| {
+        for name in feature_names(names) {
+            disable_feature(name);
+        }
+    })
+}
+
+const FEATURE_NAMES: [&str; 16] = [
+    "aes",
+    "pclmulqdq",
+    "pmull",
+    "ssse3",
+    "avx",
+    "avx2",
+    "avx512f",
+    "avx512bw",
+    "avx512ifma",
+    "vaes",
+    "vpclmulqdq",
+    "movbe",
+    "neon",
+    "sha2",
+    "rdrand",
+    "rdseed",
+];
+
+fn feature_names(names: &str) -> impl Iterator<Item = &str> {
+    names.split(',').map(|name| name.trim()).filter(|name| !name.is_empty())
+}
+
+// Keep in sync with `Features::detected()`.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn disable_feature(name: &str) {
+    let bits: &[(usize, u32)] = match name {
+        "aes" => &[(1, 25), (3, 9)],
+        "pclmulqdq" => &[(1, 1), (3, 10)],
+        "ssse3" => &[(1, 9)],
+        "avx" => &[(1, 28), (2, 5), (2, 16), (2, 21), (2, 30)],
+        "avx2" => &[(2, 5), (2, 16), (2, 21), (2, 30)],
+        "avx512f" => &[(2, 16), (2, 21), (2, 30)],
+        "avx512bw" => &[(2, 30)],
+        "avx512ifma" => &[(2, 21)],
+        "vaes" => &[(3, 9)],
+        "vpclmulqdq" => &[(3, 10)],
+        "movbe" => &[(1, 22)],
+        "sha2" => &[(2, 29)],
+        "rdrand" => &[(1, 30)],
+        "rdseed" => &[(2, 18)],
+        _ => &[],
+    };
+    for &(i, bit) in bits {
+        unsafe { GFp_ia32cap_P[i] &= !(1 << bit) };
+    }
+}
+
+#[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+fn disable_feature(name: &str) {
+    if let Some(bit) = armcap_bit(name) {
+        unsafe { GFp_armcap_P &= !(1 << bit) };
+    }
+}
+
+#[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+const NEON_BIT: u32 = 0;
+
+// Keep in sync with `Features::detected()`.
+#[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+fn armcap_bit(name: &str) -> Option<u32> {
+    match name {
+        "neon" => Some(NEON_BIT),
+        "aes" => Some(2),
+        "sha2" => Some(4),
+        "pmull" => Some(5),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64"
+)))]
+fn disable_feature(_: &str) {}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+extern "C" {
+    static mut GFp_ia32cap_P: [u32; 4];
+}
+
+#[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+extern "C" {
+    static mut GFp_armcap_P: u32;
+}
+
+/// CPU features specific to ARM and AArch64.
+#[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+pub mod arm {
+    use super::{armcap_bit, feature_names, try_detect_features, GFp_armcap_P, NEON_BIT};
+    use crate::error;
+
+    /// Makes *ring* use the CPU features named in `names`, a comma-separated
+    /// list of `"neon"`, `"aes"`, `"pmull"`, and `"sha2"`, whether or not they
+    /// were detected.
+    ///
+    /// The features are detected at run time only on Linux, Android, FreeBSD,
+    /// OpenBSD, and NetBSD. Elsewhere, e.g. on bare-metal AArch64, no
+    /// features are used unless they're assumed. Assuming `aes`, `pmull`, or
+    /// `sha2` also assumes `neon`, which every CPU that has them has. A CPU
+    /// that doesn't have an assumed feature will fault when *ring* uses it.
+    ///
+    /// Like `disable_features()`, this must be called before any other part
+    /// of *ring* is used. Fails, without assuming anything, if any name is
+    /// unknown or if the features have already been detected.
+    ///
+    /// ```
+    /// use ring::cpu;
+    ///
+    /// # if false {
+    /// cpu::arm::assume_features("neon,aes,pmull,sha2").unwrap();
+    /// # }
+    /// assert!(cpu::arm::assume_features("avx2").is_err());
+    /// ```
+    pub fn assume_features(names: &str) -> Result<(), error::Unspecified> {
+        let mut armcap = 0;
+        for name in feature_names(names) {
+            let bit = armcap_bit(name).ok_or(error::Unspecified)?;
+            armcap |= (1 << bit) | (1 << NEON_BIT);
+        }
+        try_detect_features(|| unsafe { GFp_armcap_P |= armcap })
+    }
+}
+
+/// CPU feature detection within SGX enclaves.
+///
+/// The `CPUID` instruction can't be used within an SGX enclave, so by default
+/// *ring* uses only the features that the enclave is compiled with, e.g. with
+/// `-C target-feature=+aes,+pclmulqdq`. `detect_features()` lets the
+/// application provide the results of `CPUID` some other way, e.g. from the
+/// enclave runner, so that one enclave can use the features of whichever CPU
+/// it runs on.
+#[cfg(all(target_arch = "x86_64", target_env = "sgx"))]
+pub mod sgx {
+    use super::{
+        disable_features_from_env_var, GFp_ia32cap_P, INIT, INIT_DONE, INIT_NOT_STARTED,
+        INIT_RUNNING,
+    };
+    use crate::error;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    pub use core::arch::x86_64::CpuidResult;
+
+    /// A function that returns the result of `CPUID` for `leaf` and
+    /// `sub_leaf`, like `core::arch::x86_64::__cpuid_count()`.
+    pub type Cpuid = fn(leaf: u32, sub_leaf: u32) -> CpuidResult;
+
+    /// Detects the CPU features from the results of `cpuid` instead of the
+    /// `CPUID` instruction.
+    ///
+    /// The results are checked the same way as those of `CPUID` outside an
+    /// enclave; e.g. AVX is used only if XCR0, which the enclave reads
+    /// itself, shows that its state is enabled. The features that the enclave
+    /// is compiled with are used regardless, and `RING_DISABLE_CPU_FEATURES`
+    /// still applies.
+    ///
+    /// `cpuid` is usually answered by the untrusted host. A host that reports
+    /// features the CPU doesn't have only makes the enclave fault, and one
+    /// that hides features only makes *ring* use slower implementations;
+    /// neither changes the results of any operation.
+    ///
+    /// Fails if the features have already been detected, so this must be
+    /// called before any other part of *ring* is used.
+    pub fn detect_features(cpuid: Cpuid) -> Result<(), error::Unspecified> {
+        if INIT
+            .compare_exchange(INIT_NOT_STARTED, INIT_RUNNING, Ordering::Acquire, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(error::Unspecified);
+        }
+        setup(Some(cpuid));
+        disable_features_from_env_var();
+        INIT.store(INIT_DONE, Ordering::Release);
+        Ok(())
+    }
+
+    // Sets `GFp_ia32cap_P` from the results of `cpuid`, if any, and the
+    // features that the enclave is compiled with.
+    pub(super) fn setup(cpuid: Option<Cpuid>) {
+        extern "C" {
+            fn GFp_cpuid_setup_with(
+                cpuid: extern "C" fn(&mut u32, &mut u32, &mut u32, &mut u32, u32),
+            );
+        }
+
+        if let Some(cpuid) = cpuid {
+            CPUID.store(cpuid as usize, Ordering::Relaxed);
+            unsafe { GFp_cpuid_setup_with(cpuid_trampoline) };
+        }
+
+        // The bits that `Features::detected()` and the C and assembly code
+        // check.
+        let compiled: &[(bool, usize, u32)] = &[
+            (cfg!(target_feature = "fxsr"), 0, 24),
+            (cfg!(target_feature = "sse"), 0, 25),
+            (cfg!(target_feature = "sse2"), 0, 26),
+            (cfg!(target_feature = "aes"), 1, 25),
+            (cfg!(target_feature = "pclmulqdq"), 1, 1),
+            (cfg!(target_feature = "ssse3"), 1, 9),
+            (cfg!(target_feature = "avx"), 1, 28),
+            (cfg!(target_feature = "avx2"), 2, 5),
+            (cfg!(target_feature = "avx512f"), 2, 16),
+            (cfg!(target_feature = "avx512bw"), 2, 30),
+            (cfg!(target_feature = "avx512ifma"), 2, 21),
+            (cfg!(target_feature = "vaes"), 3, 9),
+            (cfg!(target_feature = "vpclmulqdq"), 3, 10),
+            (cfg!(target_feature = "movbe"), 1, 22),
+            (cfg!(target_feature = "sha"), 2, 29),
+            (cfg!(target_feature = "rdrand"), 1, 30),
+            (cfg!(target_feature = "rdseed"), 2, 18),
+        ];
+        for &(enabled, i, bit) in compiled {
+            if enabled {
+                unsafe { GFp_ia32cap_P[i] |= 1 << bit };
+            }
+        }
+    }
+
+    // The `Cpuid` that `cpuid_trampoline` calls, which is set by `setup()`
+    // while `INIT` is held.
+    static CPUID: AtomicUsize = AtomicUsize::new(0);
+
+    // Has the signature of `OPENSSL_cpuid` in crypto/cpu-intel.c.
+    extern "C" fn cpuid_trampoline(
+        out_eax: &mut u32, out_ebx: &mut u32, out_ecx: &mut u32, out_edx: &mut u32, leaf: u32,
+    ) {
+        let cpuid: Cpuid = unsafe { core::mem::transmute(CPUID.load(Ordering::Relaxed)) };
+        let result = cpuid(leaf, 0);
+        *out_eax = result.eax;
+        *out_ebx = result.ebx;
+        *out_ecx = result.ecx;
+        *out_edx = result.edx;
+    }
+}
+
+/// Returns the CPU features that *ring* has detected.
+///
+/// On iOS, where they can't be detected at run time, these are the features
+/// that the target is built with; on other ARM and AArch64 targets where they
+/// can't be detected, e.g. bare metal, they're the features assumed with
+/// `arm::assume_features()`. On architectures other than x86, x86-64, ARM,
+/// and AArch64, no features are reported. Within SGX enclaves, they're the
+/// features that the enclave is compiled with, plus those detected with
+/// `sgx::detect_features()`.
+pub fn features() -> Features {
+    cache_detected_features();
+    Features::detected()
+}
+
+/// The CPU features that *ring* has detected. See `features()`.
+///
+/// Each feature is reported only if *ring* has an implementation that uses
+/// it, and only on the architectures it exists on; e.g. `neon()` is always
+/// `false` on x86.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Features {
+    aes: bool,
+    pclmulqdq: bool,
+    pmull: bool,
+    ssse3: bool,
+    avx: bool,
+    avx2: bool,
+    avx512f: bool,
+    avx512bw: bool,
+    avx512ifma: bool,
+    vaes: bool,
+    vpclmulqdq: bool,
+    movbe: bool,
+    neon: bool,
+    sha2: bool,
+    rdrand: bool,
+    rdseed: bool,
+}
+
+impl Features {
+    /// Whether the AES instructions (AES-NI on x86, the ARMv8 Cryptography
+    /// Extensions on ARM) are supported.
+    #[inline]
+    pub fn aes(&self) -> bool { self.aes }
+
+    /// Whether the x86 `PCLMULQDQ` carry-less multiplication instruction,
+    /// used for GCM, is supported.
+    #[inline]
+    pub fn pclmulqdq(&self) -> bool { self.pclmulqdq }
+
+    /// Whether the ARMv8 `PMULL` polynomial multiplication instruction, used
+    /// for GCM, is supported.
+    #[inline]
+    pub fn pmull(&self) -> bool { self.pmull }
+
+    /// Whether the x86 SSSE3 instructions are supported.
+    #[inline]
+    pub fn ssse3(&self) -> bool { self.ssse3 }
+
+    /// Whether the x86 AVX instructions are supported, and enabled by the
+    /// operating system.
+    #[inline]
+    pub fn avx(&self) -> bool { self.avx }
+
+    /// Whether the x86 AVX2 instructions are supported, and enabled by the
+    /// operating system.
+    #[inline]
+    pub fn avx2(&self) -> bool { self.avx2 }
+
+    /// Whether the x86 AVX-512 Foundation instructions are supported, and
+    /// enabled by the operating system.
+    #[inline]
+    pub fn avx512f(&self) -> bool { self.avx512f }
+
+    /// Whether the x86 AVX-512 byte and word instructions are supported, and
+    /// enabled by the operating system.
+    #[inline]
+    pub fn avx512bw(&self) -> bool { self.avx512bw }
+
+    /// Whether the x86 AVX-512 52-bit integer multiply-add instructions are
+    /// supported, and enabled by the operating system.
+    #[inline]
+    pub fn avx512ifma(&self) -> bool { self.avx512ifma }
+
+    /// Whether the x86 `VAES` instructions, which apply AES rounds to each
+    /// 128-bit lane of a vector register, are supported.
+    #[inline]
+    pub fn vaes(&self) -> bool { self.vaes }
+
+    /// Whether the x86 `VPCLMULQDQ` instruction, which is `PCLMULQDQ` for each
+    /// 128-bit lane of a vector register, is supported.
+    #[inline]
+    pub fn vpclmulqdq(&self) -> bool { self.vpclmulqdq }
+
+    /// Whether the x86 `MOVBE` instruction is supported.
+    #[inline]
+    pub fn movbe(&self) -> bool { self.movbe }
+
+    /// Whether the ARM NEON instructions are supported.
+    #[inline]
+    pub fn neon(&self) -> bool { self.neon }
+
+    /// Whether the SHA-256 instructions (the SHA extensions on x86, the ARMv8
+    /// Cryptography Extensions on ARM) are supported.
+    #[inline]
+    pub fn sha2(&self) -> bool { self.sha2 }
+
+    /// Whether the x86 `RDRAND` instruction is supported.
+    #[inline]
+    pub fn rdrand(&self) -> bool { self.rdrand }
+
+    /// Whether the x86 `RDSEED` instruction is supported.
+    #[inline]
+    pub fn rdseed(&self) -> bool { self.rdseed }
+
+    pub(crate) const NONE: Self = Self {
+        aes: false,
+        pclmulqdq: false,
+        pmull: false,
+        ssse3: false,
+        avx: false,
+        avx2: false,
+        avx512f: false,
+        avx512bw: false,
+        avx512ifma: false,
+        vaes: false,
+        vpclmulqdq: false,
+        movbe: false,
+        neon: false,
+        sha2: false,
+        rdrand: false,
+        rdseed: false,
+    };
+
+    // The features that `init_checked()` requires. Within SGX enclaves,
+    // these are AES-NI and `PCLMULQDQ`, because without them AES and GHASH
+    // use tables indexed by secret data, and an enclave's untrusted host can
+    // observe which cache lines the enclave accesses.
+    #[cfg(all(target_arch = "x86_64", target_env = "sgx"))]
+    pub(crate) const REQUIRED: Self = Self {
+        aes: true,
+        pclmulqdq: true,
+        ..Self::NONE
+    };
+
+    #[cfg(not(all(target_arch = "x86_64", target_env = "sgx")))]
+    pub(crate) const REQUIRED: Self = Self::NONE;
+
+    // The features of `required` that `self` doesn't have, if any.
+    pub(crate) fn missing(&self, required: &Self) -> Option<Self> {
+        let missing = |has: bool, required: bool| required && !has;
+        let missing = Self {
+            aes: missing(self.aes, required.aes),
+            pclmulqdq: missing(self.pclmulqdq, required.pclmulqdq),
+            pmull: missing(self.pmull, required.pmull),
+            ssse3: missing(self.ssse3, required.ssse3),
+            avx: missing(self.avx, required.avx),
+            avx2: missing(self.avx2, required.avx2),
+            avx512f: missing(self.avx512f, required.avx512f),
+            avx512bw: missing(self.avx512bw, required.avx512bw),
+            avx512ifma: missing(self.avx512ifma, required.avx512ifma),
+            vaes: missing(self.vaes, required.vaes),
+            vpclmulqdq: missing(self.vpclmulqdq, required.vpclmulqdq),
+            movbe: missing(self.movbe, required.movbe),
+            neon: missing(self.neon, required.neon),
+            sha2: missing(self.sha2, required.sha2),
+            rdrand: missing(self.rdrand, required.rdrand),
+            rdseed: missing(self.rdseed, required.rdseed),
+        };
+        if missing == Self::NONE {
+            None
+        } else {
+            Some(missing)
+        }
+    }
+
+    // Keep in sync with `GFp_cpuid_setup` in crypto/cpu-intel.c.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn detected() -> Self {
+        let ia32cap = unsafe { GFp_ia32cap_P };
+        let leaf_1_ecx = |bit: u32| (ia32cap[1] & (1 << bit)) != 0;
+        let leaf_7_ebx = |bit: u32| (ia32cap[2] & (1 << bit)) != 0;
+        let leaf_7_ecx = |bit: u32| (ia32cap[3] & (1 << bit)) != 0;
+        // `GFp_cpuid_setup` clears only the AVX-512F bit when the operating
+        // system doesn't save the AVX-512 state.
+        let avx512 = |bit: u32| leaf_7_ebx(16) && leaf_7_ebx(bit);
+        Self {
+            aes: leaf_1_ecx(25),
+            pclmulqdq: leaf_1_ecx(1),
+            ssse3: leaf_1_ecx(9),
+            avx: leaf_1_ecx(28),
+            avx2: leaf_7_ebx(5),
+            avx512f: leaf_7_ebx(16),
+            avx512bw: avx512(30),
+            avx512ifma: avx512(21),
+            vaes: leaf_7_ecx(9),
+            vpclmulqdq: leaf_7_ecx(10),
+            movbe: leaf_1_ecx(22),
+            sha2: leaf_7_ebx(29),
+            rdrand: leaf_1_ecx(30),
+            rdseed: leaf_7_ebx(18),
+            ..Self::NONE
+        }
+    }
+
+    // Keep in sync with include/GFp/arm_arch.h.
+    #[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+    fn detected() -> Self {
+        let armcap = unsafe { GFp_armcap_P };
+        let armcap = |bit: u32| (armcap & (1 << bit)) != 0;
+        Self {
+            neon: armcap(0),
+            aes: armcap(2),
+            sha2: armcap(4),
+            pmull: armcap(5),
+            ..Self::NONE
+        }
+    }
+
+    #[cfg(not(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "arm",
+        target_arch = "aarch64"
+    )))]
+    fn detected() -> Self { Self::NONE }
+}
+
+impl core::fmt::Debug for Features {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+        let names = [
+            ("aes", self.aes),
+            ("pclmulqdq", self.pclmulqdq),
+            ("pmull", self.pmull),
+            ("ssse3", self.ssse3),
+            ("avx", self.avx),
+            ("avx2", self.avx2),
+            ("avx512f", self.avx512f),
+            ("avx512bw", self.avx512bw),
+            ("avx512ifma", self.avx512ifma),
+            ("vaes", self.vaes),
+            ("vpclmulqdq", self.vpclmulqdq),
+            ("movbe", self.movbe),
+            ("neon", self.neon),
+            ("sha2", self.sha2),
+            ("rdrand", self.rdrand),
+            ("rdseed", self.rdseed),
+        ];
+        f.debug_set()
+            .entries(names.iter().filter(|(_, detected)| *detected).map(|(name, _)| name))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_features() {
+        let features = features();
+        assert_eq!(features, super::features());
+        if features.avx2() {
+            assert!(features.avx());
+        }
+        if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+            assert!(!features.neon() && !features.pmull());
+        } else {
+            assert!(!features.avx() && !features.rdrand());
+        }
+    }
+
+    #[test]
+    fn test_feature_names() {
+        let names = feature_names(" aes,,avx2 , ").collect::<Vec<_>>();
+        assert_eq!(names, ["aes", "avx2"]);
+        assert_eq!(feature_names("").count(), 0);
+
+        // The features have already been detected, so nothing is disabled.
+        let features = features();
+        assert!(disable_features("").is_err());
+        assert!(disable_features("aes").is_err());
+        assert!(disable_features("aes,unknown").is_err());
+        assert!(disable_features("AES").is_err());
+        assert_eq!(features, super::features());
+    }
+
+    #[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+    #[test]
+    fn test_assume_features() {
+        // The features have already been detected, so nothing is assumed.
+        let features = features();
+        assert!(arm::assume_features("").is_err());
+        assert!(arm::assume_features(" neon, aes ").is_err());
+        assert!(arm::assume_features("neon,avx2").is_err());
+        assert!(arm::assume_features("NEON").is_err());
+        assert_eq!(features, super::features());
+    }
+
+    #[cfg(all(target_arch = "x86_64", target_env = "sgx"))]
+    #[test]
+    fn test_sgx_detect_features() {
+        // The features have already been detected, so `cpuid` isn't called.
+        let features = features();
+        assert!(sgx::detect_features(|_, _| unreachable!()).is_err());
+        assert_eq!(features, super::features());
+        if cfg!(target_feature = "aes") {
+            assert!(features.aes());
+        }
+        if cfg!(target_feature = "rdrand") {
+            assert!(features.rdrand());
+        }
+    }
+
+    #[test]
+    fn test_features_missing() {
+        let mut required = Features::NONE;
+        assert_eq!(Features::NONE.missing(&required), None);
+        required.aes = true;
+        required.pclmulqdq = true;
+        let mut features = Features::NONE;
+        features.aes = true;
+        features.avx = true;
+        let missing = features.missing(&required).unwrap();
+        assert_eq!(format!("{:?}", missing), "{\"pclmulqdq\"}");
+        features.pclmulqdq = true;
+        assert_eq!(features.missing(&required), None);
+    }
+
+    #[test]
+    fn test_features_debug() {
+        let mut features = Features::NONE;
+        assert_eq!(format!("{:?}", features), "{}");
+        features.aes = true;
+        features.avx2 = true;
+        assert_eq!(format!("{:?}", features), "{\"aes\", \"avx2\"}");
     }
 }
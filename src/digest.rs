@@ -24,11 +24,30 @@
 // The goal for this implementation is to drive the overhead as close to zero
 // as possible.
 
-use crate::{c, cpu, endian::*, polyfill};
+use crate::{c, cpu, endian::*, polyfill, zeroize};
 use core::{self, num::Wrapping};
 
+#[cfg(feature = "serde")]
+use crate::error;
+
 mod sha1;
 
+#[cfg(not(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64"
+)))]
+mod sha2;
+
+#[cfg(not(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64"
+)))]
+use self::sha2::{GFp_sha256_block_data_order, GFp_sha512_block_data_order};
+
 /// A context for multi-step (Init-Update-Finish) digest calculations.
 ///
 /// C analog: `EVP_MD_CTX`.
@@ -126,54 +145,93 @@ impl Context {
         }
     }
 
+    /// Like `update()`, for inputs that are much larger than the CPU's caches
+    /// and that won't be read again soon, e.g. the contents of a backup.
+    ///
+    /// On x86 and x86-64, the input is prefetched a page ahead with
+    /// `PREFETCHNTA`, which on many CPUs keeps it out of the outer cache levels
+    /// so that hashing it evicts less of the rest of the application's working
+    /// set from them. How much less depends on the CPU, and the prefetching
+    /// costs some throughput, so `update()` is better for other inputs. On
+    /// other architectures this is the same as `update()`.
+    pub fn update_nontemporal(&mut self, data: &[u8]) {
+        #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse"))]
+        {
+            #[cfg(target_arch = "x86")]
+            use core::arch::x86::{_mm_prefetch, _MM_HINT_NTA};
+            #[cfg(target_arch = "x86_64")]
+            use core::arch::x86_64::{_mm_prefetch, _MM_HINT_NTA};
+
+            const PREFETCH_LEN: usize = 4096;
+            const CACHE_LINE_LEN: usize = 64;
+
+            let mut chunks = data.chunks(PREFETCH_LEN).peekable();
+            while let Some(chunk) = chunks.next() {
+                if let Some(next) = chunks.peek() {
+                    for line in next.chunks(CACHE_LINE_LEN) {
+                        unsafe { _mm_prefetch::<_MM_HINT_NTA>(line.as_ptr() as *const i8) };
+                    }
+                }
+                self.update(chunk);
+            }
+        }
+
+        #[cfg(not(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse")))]
+        self.update(data)
+    }
+
     /// Finalizes the digest calculation and returns the digest value. `finish`
     /// consumes the context so it cannot be (mis-)used after `finish` has been
     /// called.
     ///
     /// C analogs: `EVP_DigestFinal`, `EVP_DigestFinal_ex`
     pub fn finish(mut self) -> Digest {
-        // We know |num_pending < self.algorithm.block_len|, because we would
-        // have processed the block otherwise.
+        finish(
+            self.algorithm,
+            &mut self.state,
+            self.completed_data_blocks,
+            &mut self.pending,
+            self.num_pending,
+        )
+    }
 
-        let mut padding_pos = self.num_pending;
-        self.pending[padding_pos] = 0x80;
-        padding_pos += 1;
+    /// Like `self.clone()` followed by `update(data)` and `finish()`, but when
+    /// no input is pending, e.g. for the contexts of an `hmac::SigningKey`,
+    /// only the state is copied.
+    pub(crate) fn finish_with(&self, data: &[u8]) -> Digest {
+        if self.num_pending != 0 {
+            let mut ctx = self.clone();
+            ctx.update(data);
+            return ctx.finish();
+        }
 
-        if padding_pos > self.algorithm.block_len - self.algorithm.len_len {
-            polyfill::slice::fill(&mut self.pending[padding_pos..self.algorithm.block_len], 0);
+        let mut state = self.state;
+        let num_blocks = data.len() / self.algorithm.block_len;
+        if num_blocks > 0 {
             unsafe {
-                (self.algorithm.block_data_order)(&mut self.state, self.pending.as_ptr(), 1);
+                (self.algorithm.block_data_order)(&mut state, data.as_ptr(), num_blocks);
             }
-            // We don't increase |self.completed_data_blocks| because the
-            // padding isn't data, and so it isn't included in the data length.
-            padding_pos = 0;
         }
-
-        polyfill::slice::fill(
-            &mut self.pending[padding_pos..(self.algorithm.block_len - 8)],
-            0,
-        );
-
-        // Output the length, in bits, in big endian order.
-        let completed_data_bits = self
+        let completed_data_blocks = self
             .completed_data_blocks
-            .checked_mul(polyfill::u64_from_usize(self.algorithm.block_len))
-            .unwrap()
-            .checked_add(polyfill::u64_from_usize(self.num_pending))
-            .unwrap()
-            .checked_mul(8)
+            .checked_add(polyfill::u64_from_usize(num_blocks))
             .unwrap();
-        self.pending[(self.algorithm.block_len - 8)..self.algorithm.block_len]
-            .copy_from_slice(BigEndian::from(completed_data_bits).as_ref());
 
-        unsafe {
-            (self.algorithm.block_data_order)(&mut self.state, self.pending.as_ptr(), 1);
-        }
+        let remaining = &data[(num_blocks * self.algorithm.block_len)..];
+        let mut pending = [0u8; MAX_BLOCK_LEN];
+        pending[..remaining.len()].copy_from_slice(remaining);
+        let digest = finish(
+            self.algorithm,
+            &mut state,
+            completed_data_blocks,
+            &mut pending,
+            remaining.len(),
+        );
 
-        Digest {
-            algorithm: self.algorithm,
-            value: (self.algorithm.format_output)(self.state),
-        }
+        // Unlike the pending input of `self`, `pending` only holds some of
+        // `data`, which the caller owns.
+        zeroize::zeroize_value(&mut state);
+        digest
     }
 
     /// The algorithm that this context is using.
@@ -181,6 +239,62 @@ impl Context {
     pub fn algorithm(&self) -> &'static Algorithm { self.algorithm }
 }
 
+// The state and the pending input may be secret, e.g. when the context is
+// part of an `hmac::SigningKey`.
+impl Drop for Context {
+    fn drop(&mut self) {
+        zeroize::zeroize_value(&mut self.state);
+        zeroize::zeroize(&mut self.pending);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Context {}
+
+fn finish(
+    algorithm: &'static Algorithm, state: &mut State, completed_data_blocks: u64,
+    pending: &mut [u8; MAX_BLOCK_LEN], num_pending: usize,
+) -> Digest {
+    // We know |num_pending < algorithm.block_len|, because we would
+    // have processed the block otherwise.
+
+    let mut padding_pos = num_pending;
+    pending[padding_pos] = 0x80;
+    padding_pos += 1;
+
+    if padding_pos > algorithm.block_len - algorithm.len_len {
+        polyfill::slice::fill(&mut pending[padding_pos..algorithm.block_len], 0);
+        unsafe {
+            (algorithm.block_data_order)(state, pending.as_ptr(), 1);
+        }
+        // We don't increase |completed_data_blocks| because the
+        // padding isn't data, and so it isn't included in the data length.
+        padding_pos = 0;
+    }
+
+    polyfill::slice::fill(&mut pending[padding_pos..(algorithm.block_len - 8)], 0);
+
+    // Output the length, in bits, in big endian order.
+    let completed_data_bits = completed_data_blocks
+        .checked_mul(polyfill::u64_from_usize(algorithm.block_len))
+        .unwrap()
+        .checked_add(polyfill::u64_from_usize(num_pending))
+        .unwrap()
+        .checked_mul(8)
+        .unwrap();
+    pending[(algorithm.block_len - 8)..algorithm.block_len]
+        .copy_from_slice(BigEndian::from(completed_data_bits).as_ref());
+
+    unsafe {
+        (algorithm.block_data_order)(state, pending.as_ptr(), 1);
+    }
+
+    Digest {
+        algorithm,
+        value: (algorithm.format_output)(*state),
+    }
+}
+
 /// Returns the digest of `data` using the given digest algorithm.
 ///
 /// C analog: `EVP_Digest`
@@ -188,7 +302,7 @@ impl Context {
 /// # Examples:
 ///
 /// ```
-/// # #[cfg(feature = "use_heap")]
+/// # #[cfg(feature = "alloc")]
 /// # fn main() {
 /// use ring::{digest, test};
 ///
@@ -199,7 +313,7 @@ impl Context {
 /// assert_eq!(&expected, &actual.as_ref());
 /// # }
 ///
-/// # #[cfg(not(feature = "use_heap"))]
+/// # #[cfg(not(feature = "alloc"))]
 /// # fn main() { }
 /// ```
 pub fn digest(algorithm: &'static Algorithm, data: &[u8]) -> Digest {
@@ -221,6 +335,31 @@ impl Digest {
     /// The algorithm that was used to calculate the digest value.
     #[inline(always)]
     pub fn algorithm(&self) -> &'static Algorithm { self.algorithm }
+
+    // Constructs a `Digest` with the value `value`, which must be
+    // `algorithm.output_len` bytes long.
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_value(
+        algorithm: &'static Algorithm, value: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        if value.len() != algorithm.output_len {
+            return Err(error::Unspecified);
+        }
+        let mut as64 = [BigEndian::from(0u64); MAX_OUTPUT_LEN / 8];
+        for (word, bytes) in as64.iter_mut().zip(value.chunks(8)) {
+            let mut padded = [0u8; 8];
+            padded[..bytes.len()].copy_from_slice(bytes);
+            *word = BigEndian::from(u64::from_be_bytes(padded));
+        }
+        Ok(Self {
+            value: Output { as64 },
+            algorithm,
+        })
+    }
+
+    /// Overwrites the digest value, when it is a secret, with zeros. `Digest`
+    /// is `Copy`, so it can't do this when it is dropped.
+    pub(crate) fn zeroize(&mut self) { zeroize::zeroize_value(&mut self.value) }
 }
 
 impl AsRef<[u8]> for Digest {
@@ -422,6 +561,8 @@ union State {
     as32: [Wrapping<u32>; 256 / 8 / core::mem::size_of::<Wrapping<u32>>()],
 }
 
+unsafe impl zeroize::Zeroable for State {}
+
 #[derive(Clone, Copy)]
 #[repr(C)]
 union Output {
@@ -429,6 +570,8 @@ union Output {
     as32: [BigEndian<u32>; 256 / 8 / core::mem::size_of::<BigEndian<u32>>()],
 }
 
+unsafe impl zeroize::Zeroable for Output {}
+
 /// The maximum block length (`Algorithm::block_len`) of all the algorithms in
 /// this module.
 pub const MAX_BLOCK_LEN: usize = 1024 / 8;
@@ -494,6 +637,12 @@ const SHA512_BLOCK_LEN: usize = 1024 / 8;
 /// The length of the length field for SHA-512-based algorithms, in bytes.
 const SHA512_LEN_LEN: usize = 128 / 8;
 
+#[cfg(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64"
+))]
 extern "C" {
     fn GFp_sha256_block_data_order(state: &mut State, data: *const u8, num: c::size_t);
     fn GFp_sha512_block_data_order(state: &mut State, data: *const u8, num: c::size_t);
@@ -0,0 +1,148 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The SHA-256 and SHA-512 block functions for targets without an assembly
+//! language implementation, e.g. wasm32-unknown-unknown. Like SHA-1, this
+//! implementation favors size and simplicity over speed.
+
+use crate::c;
+
+pub(super) unsafe extern "C" fn GFp_sha256_block_data_order(
+    state: &mut super::State, data: *const u8, num: c::size_t,
+) {
+    let blocks = core::slice::from_raw_parts(data as *const [[u8; 4]; 16], num);
+    let state = &mut state.as32;
+    for block in blocks {
+        let mut w = [0u32; 16];
+        for (w, word) in w.iter_mut().zip(block.iter()) {
+            *w = u32::from_be_bytes(*word);
+        }
+        let mut s = [0u32; 8];
+        for (s, state) in s.iter_mut().zip(state.iter()) {
+            *s = state.0;
+        }
+        compress_256(&mut s, &mut w);
+        for (state, s) in state.iter_mut().zip(s.iter()) {
+            state.0 = state.0.wrapping_add(*s);
+        }
+    }
+}
+
+pub(super) unsafe extern "C" fn GFp_sha512_block_data_order(
+    state: &mut super::State, data: *const u8, num: c::size_t,
+) {
+    let blocks = core::slice::from_raw_parts(data as *const [[u8; 8]; 16], num);
+    let state = &mut state.as64;
+    for block in blocks {
+        let mut w = [0u64; 16];
+        for (w, word) in w.iter_mut().zip(block.iter()) {
+            *w = u64::from_be_bytes(*word);
+        }
+        let mut s = [0u64; 8];
+        for (s, state) in s.iter_mut().zip(state.iter()) {
+            *s = state.0;
+        }
+        compress_512(&mut s, &mut w);
+        for (state, s) in state.iter_mut().zip(s.iter()) {
+            state.0 = state.0.wrapping_add(*s);
+        }
+    }
+}
+
+// Both compression functions keep the message schedule in a 16-word ring
+// buffer, computing each word of the schedule just before it is used.
+macro_rules! compress {
+    ( $name:ident, $word:ty, $k:expr,
+      $big_sigma0:expr, $big_sigma1:expr, $small_sigma0:expr, $small_sigma1:expr ) => {
+        fn $name(s: &mut [$word; 8], w: &mut [$word; 16]) {
+            let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *s;
+            for (t, k) in $k.iter().enumerate() {
+                if t >= 16 {
+                    let w15 = w[(t - 15) % 16];
+                    let w2 = w[(t - 2) % 16];
+                    let sigma0 = w15.rotate_right($small_sigma0.0)
+                        ^ w15.rotate_right($small_sigma0.1)
+                        ^ (w15 >> $small_sigma0.2);
+                    let sigma1 = w2.rotate_right($small_sigma1.0)
+                        ^ w2.rotate_right($small_sigma1.1)
+                        ^ (w2 >> $small_sigma1.2);
+                    w[t % 16] = w[t % 16]
+                        .wrapping_add(sigma0)
+                        .wrapping_add(w[(t - 7) % 16])
+                        .wrapping_add(sigma1);
+                }
+                let big_sigma1 = e.rotate_right($big_sigma1.0)
+                    ^ e.rotate_right($big_sigma1.1)
+                    ^ e.rotate_right($big_sigma1.2);
+                let ch = (e & f) ^ (!e & g);
+                let t1 = h
+                    .wrapping_add(big_sigma1)
+                    .wrapping_add(ch)
+                    .wrapping_add(*k)
+                    .wrapping_add(w[t % 16]);
+                let big_sigma0 = a.rotate_right($big_sigma0.0)
+                    ^ a.rotate_right($big_sigma0.1)
+                    ^ a.rotate_right($big_sigma0.2);
+                let maj = (a & b) ^ (a & c) ^ (b & c);
+                let t2 = big_sigma0.wrapping_add(maj);
+                h = g;
+                g = f;
+                f = e;
+                e = d.wrapping_add(t1);
+                d = c;
+                c = b;
+                b = a;
+                a = t1.wrapping_add(t2);
+            }
+            *s = [a, b, c, d, e, f, g, h];
+        }
+    };
+}
+
+compress!(compress_256, u32, K_256, (2, 13, 22), (6, 11, 25), (7, 18, 3), (17, 19, 10));
+compress!(compress_512, u64, K_512, (28, 34, 39), (14, 18, 41), (1, 8, 7), (19, 61, 6));
+
+const K_256: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const K_512: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
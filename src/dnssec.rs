@@ -0,0 +1,565 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! DNSSEC RRSIG verification ([RFC 4034], [RFC 4035]).
+//!
+//! This module handles the cryptographic part of DNSSEC validation: mapping
+//! DNSSEC algorithm numbers to *ring*'s verification algorithms, decoding
+//! DNSKEY public keys, reconstructing the signed data of an RRSIG over an
+//! RRset in canonical form, and verifying the signature. Building the chain
+//! of trust, i.e. deciding which DNSKEYs are trusted, is left to the caller.
+//!
+//! All names are in uncompressed DNS wire format. RDATA must already be in
+//! the canonical form of [RFC 4034 Section 6.2]; in particular, domain names
+//! embedded in the RDATA of the record types listed there must be
+//! uncompressed and lowercased by the caller, since doing so requires
+//! knowledge of each record type's RDATA layout.
+//!
+//! Only the algorithms recommended for DNSSEC validation by [RFC 8624] are
+//! supported: RSASHA256 (8), ECDSAP256SHA256 (13), ECDSAP384SHA384 (14), and
+//! ED25519 (15). Like the rest of *ring*, RSA keys smaller than 2048 bits are
+//! rejected.
+//!
+//! [RFC 4034]: https://tools.ietf.org/html/rfc4034
+//! [RFC 4035]: https://tools.ietf.org/html/rfc4035
+//! [RFC 4034 Section 6.2]: https://tools.ietf.org/html/rfc4034#section-6.2
+//! [RFC 8624]: https://tools.ietf.org/html/rfc8624
+
+use crate::{error, signature};
+use alloc::vec::Vec;
+use untrusted;
+
+/// A DNSSEC signature algorithm.
+pub struct Algorithm {
+    number: u8,
+    verify: fn(
+        public_key: untrusted::Input,
+        msg: untrusted::Input,
+        signature: untrusted::Input,
+    ) -> Result<(), error::Unspecified>,
+}
+
+impl Algorithm {
+    /// The DNSSEC algorithm number assigned to the algorithm by IANA.
+    #[inline]
+    pub fn number(&self) -> u8 { self.number }
+}
+
+derive_debug_via_self!(Algorithm, self.number);
+
+impl PartialEq for Algorithm {
+    fn eq(&self, other: &Self) -> bool { self.number == other.number }
+}
+
+impl Eq for Algorithm {}
+
+/// RSA/SHA-256 ([RFC 5702]), algorithm number 8.
+///
+/// The public key must be 2048-8192 bits.
+///
+/// [RFC 5702]: https://tools.ietf.org/html/rfc5702
+pub static RSASHA256: Algorithm = Algorithm {
+    number: 8,
+    verify: rsa_sha256_verify,
+};
+
+/// ECDSA Curve P-256 with SHA-256 ([RFC 6605]), algorithm number 13.
+///
+/// [RFC 6605]: https://tools.ietf.org/html/rfc6605
+pub static ECDSAP256SHA256: Algorithm = Algorithm {
+    number: 13,
+    verify: ecdsa_p256_sha256_verify,
+};
+
+/// ECDSA Curve P-384 with SHA-384 ([RFC 6605]), algorithm number 14.
+///
+/// [RFC 6605]: https://tools.ietf.org/html/rfc6605
+pub static ECDSAP384SHA384: Algorithm = Algorithm {
+    number: 14,
+    verify: ecdsa_p384_sha384_verify,
+};
+
+/// Ed25519 ([RFC 8080]), algorithm number 15.
+///
+/// [RFC 8080]: https://tools.ietf.org/html/rfc8080
+pub static ED25519: Algorithm = Algorithm {
+    number: 15,
+    verify: ed25519_verify,
+};
+
+/// Returns the algorithm with the IANA-assigned DNSSEC algorithm number
+/// `number`, or `None` if the algorithm isn't supported.
+pub fn algorithm_from_number(number: u8) -> Option<&'static Algorithm> {
+    match number {
+        8 => Some(&RSASHA256),
+        13 => Some(&ECDSAP256SHA256),
+        14 => Some(&ECDSAP384SHA384),
+        15 => Some(&ED25519),
+        _ => None,
+    }
+}
+
+fn rsa_sha256_verify(
+    public_key: untrusted::Input, msg: untrusted::Input, signature: untrusted::Input,
+) -> Result<(), error::Unspecified> {
+    // RFC 3110 Section 2.
+    let (n, e) = public_key.read_all(error::Unspecified, |input| {
+        let e_len = match input.read_byte()? {
+            0 => (usize::from(input.read_byte()?) << 8) | usize::from(input.read_byte()?),
+            e_len => usize::from(e_len),
+        };
+        let e = input.skip_and_get_input(e_len)?;
+        let n = input.skip_to_end();
+        Ok((n, e))
+    })?;
+    signature::primitive::verify_rsa(&signature::RSA_PKCS1_2048_8192_SHA256, (n, e), msg, signature)
+}
+
+fn ecdsa_p256_sha256_verify(
+    public_key: untrusted::Input, msg: untrusted::Input, signature: untrusted::Input,
+) -> Result<(), error::Unspecified> {
+    ecdsa_verify(&signature::ECDSA_P256_SHA256_FIXED, 2 * 32, public_key, msg, signature)
+}
+
+fn ecdsa_p384_sha384_verify(
+    public_key: untrusted::Input, msg: untrusted::Input, signature: untrusted::Input,
+) -> Result<(), error::Unspecified> {
+    ecdsa_verify(&signature::ECDSA_P384_SHA384_FIXED, 2 * 48, public_key, msg, signature)
+}
+
+fn ecdsa_verify(
+    alg: &signature::ECDSAVerification, public_key_len: usize, public_key: untrusted::Input,
+    msg: untrusted::Input, signature: untrusted::Input,
+) -> Result<(), error::Unspecified> {
+    // RFC 6605 Section 4: The public key is the uncompressed point without
+    // the leading 0x04 byte that SEC 1 adds.
+    if public_key.len() != public_key_len {
+        return Err(error::Unspecified);
+    }
+    let mut uncompressed = [0u8; 1 + (2 * 48)];
+    let uncompressed = &mut uncompressed[..(1 + public_key_len)];
+    uncompressed[0] = 0x04;
+    uncompressed[1..].copy_from_slice(public_key.as_slice_less_safe());
+    signature::verify(alg, untrusted::Input::from(uncompressed), msg, signature)
+}
+
+fn ed25519_verify(
+    public_key: untrusted::Input, msg: untrusted::Input, signature: untrusted::Input,
+) -> Result<(), error::Unspecified> {
+    signature::verify(&signature::ED25519, public_key, msg, signature)
+}
+
+/// A DNSKEY resource record ([RFC 4034 Section 2]).
+///
+/// [RFC 4034 Section 2]: https://tools.ietf.org/html/rfc4034#section-2
+#[derive(Debug)]
+pub struct DnsKey<'a> {
+    owner: untrusted::Input<'a>,
+    flags: u16,
+    algorithm: u8,
+    public_key: untrusted::Input<'a>,
+    key_tag: u16,
+}
+
+const DNSKEY_PROTOCOL: u8 = 3;
+
+const DNSKEY_FLAG_ZONE_KEY: u16 = 0x0100;
+const DNSKEY_FLAG_REVOKE: u16 = 0x0080;
+const DNSKEY_FLAG_SECURE_ENTRY_POINT: u16 = 0x0001;
+
+impl<'a> DnsKey<'a> {
+    /// Parses the DNSKEY RR with the owner name `owner` and the RDATA
+    /// `rdata`.
+    ///
+    /// The algorithm isn't required to be supported, so that the key tags of
+    /// keys using unsupported algorithms can still be computed.
+    pub fn from_rdata(
+        owner: untrusted::Input<'a>, rdata: untrusted::Input<'a>,
+    ) -> Result<Self, error::Unspecified> {
+        let _ = name_label_count(owner)?;
+        let (flags, algorithm, public_key) = rdata.read_all(error::Unspecified, |input| {
+            let flags = read_u16(input)?;
+            if input.read_byte()? != DNSKEY_PROTOCOL {
+                return Err(error::Unspecified);
+            }
+            let algorithm = input.read_byte()?;
+            Ok((flags, algorithm, input.skip_to_end()))
+        })?;
+        Ok(Self {
+            owner,
+            flags,
+            algorithm,
+            public_key,
+            key_tag: key_tag(rdata.as_slice_less_safe()),
+        })
+    }
+
+    /// The DNSKEY's Flags field.
+    #[inline]
+    pub fn flags(&self) -> u16 { self.flags }
+
+    /// Whether the Zone Key flag is set. Only zone keys may be used to verify
+    /// RRSIGs.
+    #[inline]
+    pub fn is_zone_key(&self) -> bool { self.flags & DNSKEY_FLAG_ZONE_KEY != 0 }
+
+    /// Whether the Secure Entry Point flag is set.
+    #[inline]
+    pub fn is_secure_entry_point(&self) -> bool {
+        self.flags & DNSKEY_FLAG_SECURE_ENTRY_POINT != 0
+    }
+
+    /// Whether the key has been revoked ([RFC 5011]).
+    ///
+    /// [RFC 5011]: https://tools.ietf.org/html/rfc5011
+    #[inline]
+    pub fn is_revoked(&self) -> bool { self.flags & DNSKEY_FLAG_REVOKE != 0 }
+
+    /// The DNSKEY's Algorithm field.
+    #[inline]
+    pub fn algorithm_number(&self) -> u8 { self.algorithm }
+
+    /// The encoded public key, in the algorithm-specific format.
+    #[inline]
+    pub fn public_key(&self) -> untrusted::Input<'a> { self.public_key }
+
+    /// The key tag, calculated as described in [RFC 4034 Appendix B].
+    ///
+    /// [RFC 4034 Appendix B]: https://tools.ietf.org/html/rfc4034#appendix-B
+    #[inline]
+    pub fn key_tag(&self) -> u16 { self.key_tag }
+}
+
+// RFC 4034 Appendix B. Algorithm 1 (RSA/MD5) uses a different calculation
+// but it isn't supported.
+fn key_tag(rdata: &[u8]) -> u16 {
+    let mut ac: u32 = 0;
+    for (i, b) in rdata.iter().enumerate() {
+        ac += if i & 1 == 0 {
+            u32::from(*b) << 8
+        } else {
+            u32::from(*b)
+        };
+    }
+    ac += (ac >> 16) & 0xffff;
+    (ac & 0xffff) as u16
+}
+
+/// An RRSIG resource record ([RFC 4034 Section 3]).
+///
+/// [RFC 4034 Section 3]: https://tools.ietf.org/html/rfc4034#section-3
+#[derive(Debug)]
+pub struct Rrsig<'a> {
+    type_covered: u16,
+    algorithm: u8,
+    labels: u8,
+    original_ttl: u32,
+    expiration: u32,
+    inception: u32,
+    key_tag: u16,
+    signer_name: untrusted::Input<'a>,
+    signature: untrusted::Input<'a>,
+
+    // The RDATA up to, but not including, the signer's name.
+    fixed_fields: untrusted::Input<'a>,
+}
+
+impl<'a> Rrsig<'a> {
+    /// Parses the RDATA of an RRSIG RR.
+    pub fn from_rdata(rdata: untrusted::Input<'a>) -> Result<Self, error::Unspecified> {
+        rdata.read_all(error::Unspecified, |input| {
+            let start = input.mark();
+            let type_covered = read_u16(input)?;
+            let algorithm = input.read_byte()?;
+            let labels = input.read_byte()?;
+            let original_ttl = read_u32(input)?;
+            let expiration = read_u32(input)?;
+            let inception = read_u32(input)?;
+            let key_tag = read_u16(input)?;
+            let fixed_fields = input.get_input_between_marks(start, input.mark())?;
+            let signer_name = read_name(input)?;
+            let signature = input.skip_to_end();
+            Ok(Self {
+                type_covered,
+                algorithm,
+                labels,
+                original_ttl,
+                expiration,
+                inception,
+                key_tag,
+                signer_name,
+                signature,
+                fixed_fields,
+            })
+        })
+    }
+
+    /// The type of the RRset covered by the signature.
+    #[inline]
+    pub fn type_covered(&self) -> u16 { self.type_covered }
+
+    /// The RRSIG's Algorithm field.
+    #[inline]
+    pub fn algorithm_number(&self) -> u8 { self.algorithm }
+
+    /// The number of labels in the original owner name, not counting the root
+    /// label or a leading wildcard label.
+    #[inline]
+    pub fn labels(&self) -> u8 { self.labels }
+
+    /// The TTL of the RRset as it appears in the authoritative zone.
+    #[inline]
+    pub fn original_ttl(&self) -> u32 { self.original_ttl }
+
+    /// The expiration time, in seconds since the Unix epoch modulo 2**32.
+    #[inline]
+    pub fn expiration(&self) -> u32 { self.expiration }
+
+    /// The inception time, in seconds since the Unix epoch modulo 2**32.
+    #[inline]
+    pub fn inception(&self) -> u32 { self.inception }
+
+    /// The key tag of the DNSKEY that made the signature.
+    #[inline]
+    pub fn key_tag(&self) -> u16 { self.key_tag }
+
+    /// The owner name of the DNSKEY that made the signature.
+    #[inline]
+    pub fn signer_name(&self) -> untrusted::Input<'a> { self.signer_name }
+
+    /// The signature.
+    #[inline]
+    pub fn signature(&self) -> untrusted::Input<'a> { self.signature }
+
+    /// Whether `now`, in seconds since the Unix epoch modulo 2**32, is within
+    /// the signature's validity period, using serial number arithmetic
+    /// ([RFC 1982]) as required by [RFC 4034 Section 3.1.5].
+    ///
+    /// [RFC 1982]: https://tools.ietf.org/html/rfc1982
+    /// [RFC 4034 Section 3.1.5]: https://tools.ietf.org/html/rfc4034#section-3.1.5
+    pub fn is_valid_at(&self, now: u32) -> bool {
+        serial_le(self.inception, now) && serial_le(now, self.expiration)
+    }
+}
+
+fn serial_le(a: u32, b: u32) -> bool { b.wrapping_sub(a) < 0x8000_0000 }
+
+/// Verifies the RRSIG `rrsig` over the RRset with owner name `owner`, type
+/// `rr_type`, and class `class`, whose records have the RDATA `rdatas`, using
+/// the public key in `dnskey`.
+///
+/// `now` is the current time in seconds since the Unix epoch (modulo 2**32).
+///
+/// The RDATA may be given in any order and may contain duplicates; they are
+/// sorted and deduplicated into canonical RRset order ([RFC 4034 Section
+/// 6.3]). The owner name may be given in any case. If the RRSIG's Labels
+/// field indicates that the RRset was synthesized from a wildcard, the
+/// wildcard owner name is reconstructed as described in [RFC 4035 Section
+/// 5.3.2].
+///
+/// In addition to verifying the signature, the checks in [RFC 4035 Section
+/// 5.3.1] are done: the RRSIG must cover `rr_type`, must have been made by
+/// `dnskey` (key tag, algorithm, and signer name must match) which must be a
+/// zone key, the signer name must be the owner name or an ancestor of it, and
+/// `now` must be within the signature's validity period.
+///
+/// [RFC 4034 Section 6.3]: https://tools.ietf.org/html/rfc4034#section-6.3
+/// [RFC 4035 Section 5.3.1]: https://tools.ietf.org/html/rfc4035#section-5.3.1
+/// [RFC 4035 Section 5.3.2]: https://tools.ietf.org/html/rfc4035#section-5.3.2
+pub fn verify_rrset(
+    dnskey: &DnsKey, rrsig: &Rrsig, owner: untrusted::Input, rr_type: u16, class: u16,
+    rdatas: &[untrusted::Input], now: u32,
+) -> Result<(), error::Unspecified> {
+    let alg = algorithm_from_number(rrsig.algorithm).ok_or(error::Unspecified)?;
+    if rrsig.type_covered != rr_type
+        || rrsig.algorithm != dnskey.algorithm
+        || rrsig.key_tag != dnskey.key_tag
+        || !dnskey.is_zone_key()
+        || !names_equal(rrsig.signer_name, dnskey.owner)
+        || !rrsig.is_valid_at(now)
+    {
+        return Err(error::Unspecified);
+    }
+
+    let owner_labels = name_label_count(owner)?;
+    let signer_labels = name_label_count(rrsig.signer_name)?;
+    if signer_labels > owner_labels
+        || !names_equal(
+            rrsig.signer_name,
+            untrusted::Input::from(skip_labels(owner, owner_labels - signer_labels)),
+        )
+    {
+        return Err(error::Unspecified);
+    }
+
+    // RFC 4035 Section 5.3.2.
+    let owner = owner.as_slice_less_safe();
+    let owner_labels = if owner.starts_with(b"\x01*") {
+        owner_labels - 1
+    } else {
+        owner_labels
+    };
+    let rrsig_labels = usize::from(rrsig.labels);
+    let canonical_owner = if rrsig_labels == owner_labels {
+        lowercase(owner)
+    } else if rrsig_labels < owner_labels {
+        let mut wildcard = b"\x01*".to_vec();
+        wildcard.extend_from_slice(&lowercase(skip_labels(
+            untrusted::Input::from(owner),
+            owner_labels - rrsig_labels,
+        )));
+        wildcard
+    } else {
+        return Err(error::Unspecified);
+    };
+
+    let mut rdatas: Vec<&[u8]> = rdatas.iter().map(|rdata| rdata.as_slice_less_safe()).collect();
+    rdatas.sort();
+    rdatas.dedup();
+
+    let mut signed_data = Vec::new();
+    signed_data.extend_from_slice(rrsig.fixed_fields.as_slice_less_safe());
+    signed_data.extend_from_slice(&lowercase(rrsig.signer_name.as_slice_less_safe()));
+    for rdata in rdatas {
+        if rdata.len() > usize::from(u16::max_value()) {
+            return Err(error::Unspecified);
+        }
+        signed_data.extend_from_slice(&canonical_owner);
+        signed_data.extend_from_slice(&rr_type.to_be_bytes());
+        signed_data.extend_from_slice(&class.to_be_bytes());
+        signed_data.extend_from_slice(&rrsig.original_ttl.to_be_bytes());
+        signed_data.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        signed_data.extend_from_slice(rdata);
+    }
+
+    (alg.verify)(
+        dnskey.public_key,
+        untrusted::Input::from(&signed_data),
+        rrsig.signature,
+    )
+}
+
+fn read_u16(input: &mut untrusted::Reader) -> Result<u16, error::Unspecified> {
+    let hi = input.read_byte()?;
+    let lo = input.read_byte()?;
+    Ok(u16::from_be_bytes([hi, lo]))
+}
+
+fn read_u32(input: &mut untrusted::Reader) -> Result<u32, error::Unspecified> {
+    let hi = read_u16(input)?;
+    let lo = read_u16(input)?;
+    Ok((u32::from(hi) << 16) | u32::from(lo))
+}
+
+const MAX_LABEL_LEN: u8 = 63;
+const MAX_NAME_LEN: usize = 255;
+
+// Reads an uncompressed wire-format name, returning the name including its
+// terminating root label.
+fn read_name<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<untrusted::Input<'a>, error::Unspecified> {
+    let start = input.mark();
+    loop {
+        let label_len = input.read_byte()?;
+        if label_len > MAX_LABEL_LEN {
+            return Err(error::Unspecified);
+        }
+        if label_len == 0 {
+            break;
+        }
+        input.skip(usize::from(label_len))?;
+    }
+    let name = input.get_input_between_marks(start, input.mark())?;
+    if name.len() > MAX_NAME_LEN {
+        return Err(error::Unspecified);
+    }
+    Ok(name)
+}
+
+// The number of labels in `name`, not counting the root label.
+fn name_label_count(name: untrusted::Input) -> Result<usize, error::Unspecified> {
+    let name = name.read_all(error::Unspecified, read_name)?;
+    let mut count = 0;
+    let mut name = name.as_slice_less_safe();
+    while name[0] != 0 {
+        name = &name[1 + usize::from(name[0])..];
+        count += 1;
+    }
+    Ok(count)
+}
+
+// Returns `name` without its leftmost `n` labels. `name` must be a
+// well-formed name with at least `n` labels.
+fn skip_labels<'a>(name: untrusted::Input<'a>, n: usize) -> &'a [u8] {
+    let mut name = name.as_slice_less_safe();
+    for _ in 0..n {
+        name = &name[1 + usize::from(name[0])..];
+    }
+    name
+}
+
+// Lowercases the ASCII letters in `name`. The length octets never fall within
+// 'A'..='Z' since labels are at most 63 bytes long.
+fn lowercase(name: &[u8]) -> Vec<u8> { name.to_ascii_lowercase() }
+
+fn names_equal(a: untrusted::Input, b: untrusted::Input) -> bool {
+    a.as_slice_less_safe()
+        .eq_ignore_ascii_case(b.as_slice_less_safe())
+}
+
+#[cfg(test)]
+mod tests {
+    use untrusted;
+
+    #[test]
+    fn test_name_label_count() {
+        for &(name, expected) in &[
+            (&b"\x00"[..], Some(0)),
+            (b"\x07example\x03com\x00", Some(2)),
+            (b"\x01*\x07example\x03com\x00", Some(3)),
+            (b"\x07example\x03com", None),
+            (b"\x07example\x03com\x00\x00", None),
+            (b"\x08example\x03com\x00", None),
+            (b"", None),
+        ] {
+            assert_eq!(
+                super::name_label_count(untrusted::Input::from(name)).ok(),
+                expected
+            );
+        }
+
+        let mut long_label = vec![64u8];
+        long_label.extend_from_slice(&[b'a'; 64]);
+        long_label.push(0);
+        assert!(super::name_label_count(untrusted::Input::from(&long_label)).is_err());
+
+        let mut long_name = Vec::new();
+        for _ in 0..4 {
+            long_name.push(63);
+            long_name.extend_from_slice(&[b'a'; 63]);
+        }
+        long_name.push(0);
+        assert!(super::name_label_count(untrusted::Input::from(&long_name)).is_err());
+    }
+
+    #[test]
+    fn test_serial_le() {
+        assert!(super::serial_le(1, 1));
+        assert!(super::serial_le(1, 2));
+        assert!(!super::serial_le(2, 1));
+        assert!(super::serial_le(0xffff_ffff, 0));
+        assert!(!super::serial_le(0, 0xffff_ffff));
+    }
+}
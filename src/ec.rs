@@ -12,12 +12,59 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-use crate::{cpu, error, rand};
+//! Low-level arithmetic on the points of elliptic curves, for implementing
+//! protocols that *ring* doesn't implement, e.g. VRFs, OPRFs with other
+//! ciphersuites, and threshold signatures, on top of *ring*'s constant-time
+//! field arithmetic.
+//!
+//! This module is only available with the `unstable_ec` feature. **Its API
+//! isn't stable**: unlike the rest of *ring*'s API, it may change in any
+//! release. It is also easy to misuse: protocols built on it need the same
+//! care as the protocols that *ring* implements, e.g. domain separation and
+//! validation of the peer's points.
+//!
+//! The groups are `P256`, `P384`, and `EDWARDS25519`. A `Scalar` is an
+//! integer in [1, n), where n is the order of the group's generator, and a
+//! `Point` is any point on the curve except the point at infinity (the
+//! identity); the operations whose result would be the point at infinity
+//! fail. Arithmetic on points and scalars is done in constant time, but
+//! parsing a point isn't, as points are usually public.
+
+// The example only compiles when the module is public.
+#![cfg_attr(
+    feature = "unstable_ec",
+    doc = r#"
+# Example
+
+```
+use ring::{ec, rand};
+
+# fn diffie_hellman() -> Result<(), ring::error::Unspecified> {
+let rng = rand::SystemRandom::new();
+let a = ec::Scalar::generate(&ec::P256, &rng)?;
+let b = ec::Scalar::generate(&ec::P256, &rng)?;
+
+// Each side sends a compressed `x*G` to the other...
+let a_g = ec::Point::mul_base(&a)?.encode_compressed();
+let b_g = ec::Point::mul_base(&b)?.encode_compressed();
+
+// ...and multiplies the peer's point by its own scalar.
+let a_b_g = ec::Point::from_bytes(&ec::P256, b_g.as_ref())?.mul(&a)?;
+let b_a_g = ec::Point::from_bytes(&ec::P256, a_g.as_ref())?.mul(&b)?;
+assert_eq!(a_b_g.encode().as_ref(), b_a_g.encode().as_ref());
+# Ok(())
+# }
+# fn main() { diffie_hellman().unwrap() }
+```
+"#
+)]
+
+use crate::{cpu, error, rand, zeroize};
 use untrusted;
 
 /// A key agreement algorithm.
 // XXX: This doesn't seem like the best place for this.
-pub struct AgreementAlgorithmImpl {
+pub(crate) struct AgreementAlgorithmImpl {
     pub curve: &'static Curve,
     pub ecdh: fn(
         out: &mut [u8],
@@ -34,8 +81,14 @@ impl PartialEq for AgreementAlgorithmImpl {
 
 impl Eq for AgreementAlgorithmImpl {}
 
-pub struct Curve {
+pub(crate) struct Curve {
     pub public_key_len: usize,
+
+    // The length of a public key encoded as a compressed point. X25519 public
+    // keys are already just an X coordinate, so they are their own compressed
+    // form.
+    pub compressed_public_key_len: usize,
+
     pub elem_and_scalar_len: usize,
 
     pub id: CurveID,
@@ -52,18 +105,18 @@ pub struct Curve {
 derive_debug_via_self!(Curve, self.id);
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub enum CurveID {
+pub(crate) enum CurveID {
     Curve25519,
     P256,
     P384,
 }
 
-pub struct KeyPair {
+pub(crate) struct KeyPair {
     pub private_key: PrivateKey,
     pub public_key: [u8; PUBLIC_KEY_MAX_LEN],
 }
 
-pub struct PrivateKey {
+pub(crate) struct PrivateKey {
     bytes: [u8; SCALAR_MAX_BYTES],
 }
 
@@ -102,12 +155,28 @@ impl PrivateKey {
         }
         (curve.public_from_private)(out, self)
     }
+
+    pub fn compute_public_key_compressed(
+        &self, curve: &Curve, out: &mut [u8],
+    ) -> Result<(), error::Unspecified> {
+        if curve.id == CurveID::Curve25519 {
+            return self.compute_public_key(curve, out);
+        }
+        let mut uncompressed = [0; PUBLIC_KEY_MAX_LEN];
+        let uncompressed = &mut uncompressed[..curve.public_key_len];
+        self.compute_public_key(curve, uncompressed)?;
+        suite_b::compress_public_key(curve, uncompressed, out)
+    }
+}
+
+impl Drop for PrivateKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.bytes) }
 }
 
 const ELEM_MAX_BITS: usize = 384;
-pub const ELEM_MAX_BYTES: usize = (ELEM_MAX_BITS + 7) / 8;
+pub(crate) const ELEM_MAX_BYTES: usize = (ELEM_MAX_BITS + 7) / 8;
 
-pub const SCALAR_MAX_BYTES: usize = ELEM_MAX_BYTES;
+pub(crate) const SCALAR_MAX_BYTES: usize = ELEM_MAX_BYTES;
 
 /// The maximum length, in bytes, of an encoded public key.
 pub const PUBLIC_KEY_MAX_LEN: usize = 1 + (2 * ELEM_MAX_BYTES);
@@ -120,7 +189,14 @@ pub const PUBLIC_KEY_MAX_LEN: usize = 1 + (2 * ELEM_MAX_BYTES);
 /// `40` is the length of the P-384 template. It is actually one byte shorter
 /// than the P-256 template, but the private key and the public key are much
 /// longer.
-pub const PKCS8_DOCUMENT_MAX_LEN: usize = 40 + SCALAR_MAX_BYTES + PUBLIC_KEY_MAX_LEN;
+pub(crate) const PKCS8_DOCUMENT_MAX_LEN: usize = 40 + SCALAR_MAX_BYTES + PUBLIC_KEY_MAX_LEN;
+
+pub(crate) mod curve25519;
+pub(crate) mod hash_to_curve;
+pub(crate) mod suite_b;
+
+#[cfg(feature = "unstable_ec")]
+mod group;
 
-pub mod curve25519;
-pub mod suite_b;
+#[cfg(feature = "unstable_ec")]
+pub use self::group::{EncodedPoint, Group, Point, Scalar, EDWARDS25519, P256, P384};
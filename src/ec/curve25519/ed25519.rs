@@ -14,7 +14,7 @@
 
 //! EdDSA Signatures.
 
-use super::ops::ELEM_LEN;
+use super::ops::{ELEM_LEN, SCALAR_LEN};
 
 mod digest;
 pub mod signing;
@@ -22,3 +22,9 @@ pub mod verification;
 
 /// The length of an Ed25519 public key.
 pub const PUBLIC_KEY_LEN: usize = ELEM_LEN;
+
+/// The length of an Ed25519 private key seed.
+pub const SEED_LEN: usize = 32;
+
+/// The length of an Ed25519 signature.
+pub const SIGNATURE_LEN: usize = ELEM_LEN + SCALAR_LEN;
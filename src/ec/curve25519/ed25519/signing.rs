@@ -14,14 +14,29 @@
 
 //! EdDSA Signatures.
 
-use super::{super::ops::*, PUBLIC_KEY_LEN};
-use crate::{der, digest, error, pkcs8, polyfill::convert::*, rand, signature, signature_impl};
+use super::{super::ops::*, PUBLIC_KEY_LEN, SEED_LEN, SIGNATURE_LEN};
+use crate::{
+    digest, error,
+    io::der,
+    pkcs8,
+    polyfill::convert::*,
+    rand, signature, signature_impl, spki,
+    zeroize::{self, Zeroizing},
+};
+
+#[cfg(feature = "alloc")]
+use crate::pem;
 use core;
 use untrusted;
 
+#[cfg(all(feature = "alloc", feature = "zeroize"))]
+use alloc::vec::Vec;
+
 use super::digest::*;
 
 /// An Ed25519 key pair, for signing.
+///
+/// A single `Ed25519KeyPair` may be shared across multiple threads safely.
 pub struct KeyPair {
     // RFC 8032 Section 5.1.6 calls this *s*.
     private_scalar: Scalar,
@@ -74,6 +89,36 @@ impl<'a> KeyPair {
         Self::from_seed_and_public_key(seed, public_key.unwrap())
     }
 
+    /// Like `Ed25519KeyPair::from_pkcs8()`, but the PKCS#8 document is
+    /// PEM-encoded with the label `PRIVATE KEY`.
+    #[cfg(feature = "alloc")]
+    pub fn from_pkcs8_pem(input: &str) -> Result<Self, error::KeyRejected> {
+        pem::decode_private_key(input, pem::PRIVATE_KEY, Self::from_pkcs8)
+    }
+
+    /// Like `Ed25519KeyPair::from_pkcs8()`, but takes ownership of the PKCS#8
+    /// document so that it is overwritten when it is no longer needed.
+    #[cfg(all(feature = "alloc", feature = "zeroize"))]
+    pub fn from_pkcs8_zeroizing(
+        input: ::zeroize::Zeroizing<Vec<u8>>,
+    ) -> Result<Self, error::KeyRejected> {
+        Self::from_pkcs8(untrusted::Input::from(&input[..]))
+    }
+
+    /// Like `Ed25519KeyPair::from_pkcs8()`, but the PKCS#8 document is
+    /// encrypted with `password` as described in `pkcs8::decrypt()`.
+    ///
+    /// To load an encrypted PKCS#8 v1 key, decrypt it with
+    /// `pkcs8::decrypt()` and then use
+    /// `Ed25519KeyPair::from_pkcs8_maybe_unchecked()`.
+    #[cfg(feature = "alloc")]
+    pub fn from_encrypted_pkcs8(
+        input: untrusted::Input, password: &[u8],
+    ) -> Result<Self, error::KeyRejected> {
+        let der = pkcs8::decrypt(input, password)?;
+        Self::from_pkcs8(untrusted::Input::from(&der))
+    }
+
     /// Constructs an Ed25519 key pair by parsing an unencrypted PKCS#8 v1 or v2
     /// Ed25519 private key.
     ///
@@ -105,6 +150,10 @@ impl<'a> KeyPair {
     /// the private key and public key, or using the wrong private key for the
     /// public key). This also detects any corruption of the public or private
     /// key.
+    ///
+    /// This is useful for key management systems that store the
+    /// `ED25519_SEED_LEN`-byte seed and the public key instead of a PKCS#8
+    /// document.
     pub fn from_seed_and_public_key(
         seed: untrusted::Input, public_key: untrusted::Input,
     ) -> Result<Self, error::KeyRejected> {
@@ -143,7 +192,7 @@ impl<'a> KeyPair {
     }
 
     fn from_seed_(seed: &Seed) -> Self {
-        let h = digest::digest(&digest::SHA512, seed);
+        let mut h = digest::digest(&digest::SHA512, seed);
         let (scalar_encoded, prefix_encoded) = h.as_ref().split_at(SCALAR_LEN);
 
         let mut scalar = [0u8; SCALAR_LEN];
@@ -153,51 +202,83 @@ impl<'a> KeyPair {
         let mut prefix = [0u8; PREFIX_LEN];
         prefix.copy_from_slice(prefix_encoded);
 
-        let mut a = ExtPoint::new_at_infinity();
-        unsafe {
-            GFp_x25519_ge_scalarmult_base(&mut a, &scalar);
-        }
+        let a = ExtPoint::mul_base(&scalar);
 
-        Self {
+        let key_pair = Self {
             private_scalar: scalar,
             private_prefix: prefix,
             public_key: a.into_encoded_point(),
-        }
+        };
+        h.zeroize();
+        zeroize::zeroize(&mut scalar);
+        zeroize::zeroize(&mut prefix);
+        key_pair
     }
 
     /// Returns a reference to the little-endian-encoded public key bytes.
     pub fn public_key_bytes(&'a self) -> &'a [u8] { &self.public_key }
 
+    /// Returns the public key.
+    pub fn public_key(&self) -> spki::PublicKey {
+        spki::PublicKey::new(spki::ED25519, &self.public_key)
+    }
+
     /// Returns the signature of the message `msg`.
     pub fn sign(&self, msg: &[u8]) -> signature::Signature {
         let mut signature_bytes = [0u8; SIGNATURE_LEN];
-        {
-            // Borrow `signature_bytes`.
-            let (signature_r, signature_s) = (&mut signature_bytes).into_();
-            let nonce = {
-                let mut ctx = digest::Context::new(&digest::SHA512);
-                ctx.update(&self.private_prefix);
-                ctx.update(msg);
-                ctx.finish()
-            };
-            let nonce = digest_scalar(nonce);
-
-            let mut r = ExtPoint::new_at_infinity();
-            unsafe {
-                GFp_x25519_ge_scalarmult_base(&mut r, &nonce);
-            }
-            *signature_r = r.into_encoded_point();
-            let hram_digest = eddsa_digest(signature_r, &self.public_key, msg);
-            let hram = digest_scalar(hram_digest);
-            unsafe {
-                GFp_x25519_sc_muladd(signature_s, &hram, &self.private_scalar, &nonce);
-            }
-        }
+        self.sign_(msg, &mut signature_bytes);
         signature_impl::signature_from_bytes(&signature_bytes)
     }
+
+    /// Writes the signature of the message `msg` into the beginning of `out`,
+    /// returning the length of the signature, `SIGNATURE_LEN`.
+    ///
+    /// Unlike `sign()`, this doesn't construct a value that is sized for the
+    /// largest signature *ring* can produce.
+    ///
+    /// Fails if `out` is shorter than `SIGNATURE_LEN`.
+    pub fn sign_into(&self, msg: &[u8], out: &mut [u8]) -> Result<usize, error::Unspecified> {
+        let out: &mut [u8; SIGNATURE_LEN] = out
+            .get_mut(..SIGNATURE_LEN)
+            .ok_or(error::Unspecified)?
+            .try_into_()?;
+        self.sign_(msg, out);
+        Ok(SIGNATURE_LEN)
+    }
+
+    fn sign_(&self, msg: &[u8], signature_bytes: &mut [u8; SIGNATURE_LEN]) {
+        let (signature_r, signature_s) = signature_bytes.into_();
+        let nonce = {
+            let mut ctx = digest::Context::new(&digest::SHA512);
+            ctx.update(&self.private_prefix);
+            ctx.update(msg);
+            let mut nonce = ctx.finish();
+            let scalar = digest_scalar(nonce);
+            nonce.zeroize();
+            Zeroizing::new(scalar)
+        };
+
+        let r = ExtPoint::mul_base(&nonce);
+        *signature_r = r.into_encoded_point();
+        let hram_digest = eddsa_digest(signature_r, &self.public_key, msg);
+        let hram = digest_scalar(hram_digest);
+        unsafe {
+            GFp_x25519_sc_muladd(signature_s, &hram, &self.private_scalar, &nonce);
+        }
+    }
+}
+
+impl Drop for KeyPair {
+    fn drop(&mut self) {
+        zeroize::zeroize(&mut self.private_scalar);
+        zeroize::zeroize(&mut self.private_prefix);
+    }
 }
 
-fn unwrap_pkcs8(
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for KeyPair {}
+
+pub(crate) fn unwrap_pkcs8(
     version: pkcs8::Version, input: untrusted::Input,
 ) -> Result<(untrusted::Input, Option<untrusted::Input>), error::KeyRejected> {
     let (private_key, public_key) = pkcs8::unwrap_key(&PKCS8_TEMPLATE, version, input)?;
@@ -210,7 +291,6 @@ fn unwrap_pkcs8(
 }
 
 extern "C" {
-    fn GFp_x25519_ge_scalarmult_base(h: &mut ExtPoint, a: &Seed);
     fn GFp_x25519_sc_mask(a: &mut Scalar);
     fn GFp_x25519_sc_muladd(s: &mut Scalar, a: &Scalar, b: &Scalar, c: &Scalar);
 }
@@ -220,12 +300,9 @@ type PublicKey = [u8; PUBLIC_KEY_LEN];
 type Prefix = [u8; PREFIX_LEN];
 const PREFIX_LEN: usize = digest::SHA512_OUTPUT_LEN - SCALAR_LEN;
 
-const SIGNATURE_LEN: usize = ELEM_LEN + SCALAR_LEN;
-
 type Seed = [u8; SEED_LEN];
-const SEED_LEN: usize = 32;
 
-static PKCS8_TEMPLATE: pkcs8::Template = pkcs8::Template {
+pub(crate) static PKCS8_TEMPLATE: pkcs8::Template = pkcs8::Template {
     bytes: include_bytes!("ed25519_pkcs8_v2_template.der"),
     alg_id_range: core::ops::Range { start: 7, end: 12 },
     curve_id_index: 0,
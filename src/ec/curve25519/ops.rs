@@ -16,14 +16,17 @@
 //! and Edwards25519.
 
 use crate::{
-    bssl, error,
+    bssl,
+    constant_time::{self, Choice},
+    error,
     limb::{Limb, LIMB_BITS},
 };
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
 // Elem<T>` is `fe` in curve25519/internal.h.
 // Elem<L> is `fe_loose` in curve25519/internal.h.
 // Keep this in sync with curve25519/internal.h.
+#[derive(Clone)]
 #[repr(C)]
 pub struct Elem<E: Encoding> {
     limbs: [Limb; ELEM_LIMBS], // This is called `v` in the C code.
@@ -31,8 +34,13 @@ pub struct Elem<E: Encoding> {
 }
 
 pub trait Encoding {}
+#[derive(Clone)]
 pub struct T;
 impl Encoding for T {}
+#[cfg(feature = "alloc")]
+pub struct L;
+#[cfg(feature = "alloc")]
+impl Encoding for L {}
 
 const ELEM_LIMBS: usize = 5 * 64 / LIMB_BITS;
 
@@ -46,6 +54,74 @@ impl<E: Encoding> Elem<E> {
 }
 
 impl Elem<T> {
+    /// Decodes the little-endian `bytes`, ignoring the most significant bit.
+    pub fn from_bytes(bytes: &[u8; ELEM_LEN]) -> Self {
+        let mut r = Self::zero();
+        unsafe { GFp_x25519_fe_frombytes(&mut r, bytes) };
+        r
+    }
+
+    pub fn one() -> Self {
+        let mut bytes = [0; ELEM_LEN];
+        bytes[0] = 1;
+        Self::from_bytes(&bytes)
+    }
+
+    /// The canonical little-endian encoding.
+    pub fn to_bytes(&self) -> [u8; ELEM_LEN] {
+        let mut bytes = [0; ELEM_LEN];
+        unsafe { GFp_x25519_fe_tobytes(&mut bytes, self) };
+        bytes
+    }
+
+    pub fn add(&self, b: &Self) -> Self {
+        let mut r = Self::zero();
+        unsafe { GFp_x25519_fe_add(&mut r, self, b) };
+        r
+    }
+
+    pub fn sub(&self, b: &Self) -> Self {
+        let mut r = Self::zero();
+        unsafe { GFp_x25519_fe_sub(&mut r, self, b) };
+        r
+    }
+
+    pub fn mul(&self, b: &Self) -> Self {
+        let mut r = Self::zero();
+        unsafe { GFp_x25519_fe_mul_ttt(&mut r, self, b) };
+        r
+    }
+
+    pub fn square(&self) -> Self {
+        let mut r = Self::zero();
+        unsafe { GFp_x25519_fe_sq(&mut r, self) };
+        r
+    }
+
+    /// `self**((q - 5) / 8)`.
+    pub fn pow22523(&self) -> Self {
+        let mut r = Self::zero();
+        unsafe { GFp_x25519_fe_pow22523(&mut r, self) };
+        r
+    }
+
+    pub fn negated(&self) -> Self { Self::zero().sub(self) }
+
+    /// Whether the canonical encoding is odd.
+    pub fn is_negative(&self) -> Choice {
+        Choice::from_mask(unsafe { GFp_x25519_fe_isnegative(self) }.wrapping_neg())
+    }
+
+    pub fn is_zero(&self) -> Choice { constant_time::eq(&self.to_bytes(), &[0; ELEM_LEN]) }
+
+    /// `a` if `cond` is true, `b` otherwise.
+    pub fn select(cond: Choice, a: &Self, b: &Self) -> Self {
+        let mut r = Self::zero();
+        // The lengths are equal, so this can't fail.
+        let _ = constant_time::select(cond, &a.limbs, &b.limbs, &mut r.limbs);
+        r
+    }
+
     fn negate(&mut self) {
         unsafe {
             GFp_x25519_fe_neg(self);
@@ -66,15 +142,16 @@ pub type Scalar = [u8; SCALAR_LEN];
 pub const SCALAR_LEN: usize = 32;
 
 pub type UnreducedScalar = [u8; UNREDUCED_SCALAR_LEN];
-const UNREDUCED_SCALAR_LEN: usize = SCALAR_LEN * 2;
+pub const UNREDUCED_SCALAR_LEN: usize = SCALAR_LEN * 2;
 
 // Keep this in sync with `ge_p3` in curve25519/internal.h.
+#[derive(Clone)]
 #[repr(C)]
 pub struct ExtPoint {
-    x: Elem<T>,
-    y: Elem<T>,
-    z: Elem<T>,
-    t: Elem<T>,
+    pub(super) x: Elem<T>,
+    pub(super) y: Elem<T>,
+    pub(super) z: Elem<T>,
+    pub(super) t: Elem<T>,
 }
 
 impl ExtPoint {
@@ -100,6 +177,27 @@ impl ExtPoint {
         self.x.negate();
         self.t.negate();
     }
+
+    /// `self + b`.
+    pub fn add(&self, b: &Self) -> Self {
+        let mut r = Self::new_at_infinity();
+        unsafe { GFp_x25519_ge_add(&mut r, self, b) };
+        r
+    }
+
+    /// `a * self`, in constant time. `a` must be reduced.
+    pub fn mul(&self, a: &Scalar) -> Self {
+        let mut r = Self::new_at_infinity();
+        unsafe { GFp_x25519_ge_scalarmult(&mut r, a, self) };
+        r
+    }
+
+    /// `a * B`, in constant time. `a` must be reduced.
+    pub fn mul_base(a: &Scalar) -> Self {
+        let mut r = Self::new_at_infinity();
+        unsafe { GFp_x25519_ge_scalarmult_base(&mut r, a) };
+        r
+    }
 }
 
 // Keep this in sync with `ge_p2` in curve25519/internal.h.
@@ -122,6 +220,28 @@ impl Point {
     pub fn into_encoded_point(self) -> EncodedPoint { encode_point(self.x, self.y, self.z) }
 }
 
+// Keep this in sync with `ge_cached` in curve25519/internal.h.
+#[cfg(feature = "alloc")]
+#[repr(C)]
+pub struct CachedPoint {
+    y_plus_x: Elem<L>,
+    y_minus_x: Elem<L>,
+    z: Elem<L>,
+    t2d: Elem<L>,
+}
+
+#[cfg(feature = "alloc")]
+impl CachedPoint {
+    pub fn zero() -> Self {
+        CachedPoint {
+            y_plus_x: Elem::zero(),
+            y_minus_x: Elem::zero(),
+            z: Elem::zero(),
+            t2d: Elem::zero(),
+        }
+    }
+}
+
 fn encode_point(x: Elem<T>, y: Elem<T>, z: Elem<T>) -> EncodedPoint {
     let mut bytes = [0; ELEM_LEN];
 
@@ -147,10 +267,18 @@ fn encode_point(x: Elem<T>, y: Elem<T>, z: Elem<T>) -> EncodedPoint {
 }
 
 extern "C" {
+    fn GFp_x25519_fe_add(h: &mut Elem<T>, f: &Elem<T>, g: &Elem<T>);
+    fn GFp_x25519_fe_frombytes(h: &mut Elem<T>, s: &[u8; ELEM_LEN]);
     fn GFp_x25519_fe_invert(out: &mut Elem<T>, z: &Elem<T>);
     fn GFp_x25519_fe_isnegative(elem: &Elem<T>) -> u8;
     fn GFp_x25519_fe_mul_ttt(h: &mut Elem<T>, f: &Elem<T>, g: &Elem<T>);
     fn GFp_x25519_fe_neg(f: &mut Elem<T>);
+    fn GFp_x25519_fe_pow22523(out: &mut Elem<T>, z: &Elem<T>);
+    fn GFp_x25519_fe_sq(h: &mut Elem<T>, f: &Elem<T>);
+    fn GFp_x25519_fe_sub(h: &mut Elem<T>, f: &Elem<T>, g: &Elem<T>);
     fn GFp_x25519_fe_tobytes(bytes: &mut EncodedPoint, elem: &Elem<T>);
+    fn GFp_x25519_ge_add(r: &mut ExtPoint, a: &ExtPoint, b: &ExtPoint);
     fn GFp_x25519_ge_frombytes_vartime(h: &mut ExtPoint, s: &EncodedPoint) -> bssl::Result;
+    fn GFp_x25519_ge_scalarmult(h: &mut ExtPoint, a: &Scalar, p: &ExtPoint);
+    fn GFp_x25519_ge_scalarmult_base(h: &mut ExtPoint, a: &Scalar);
 }
@@ -0,0 +1,316 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//! The ristretto255 group of RFC 9496, e.g. for OPRF(ristretto255, SHA-512)
+//! in `crate::oprf`.
+//!
+//! An element is represented by any of the points of Edwards25519 in its
+//! coset, so elements can only be compared by their encodings. Everything
+//! here is done in constant time.
+
+pub use super::ops::{ExtPoint, Scalar, UnreducedScalar, SCALAR_LEN, UNREDUCED_SCALAR_LEN};
+
+use super::ops::{Elem, ELEM_LEN, T};
+use crate::{
+    constant_time::{self, Choice},
+    error,
+};
+
+/// The length of an encoded element.
+pub const ELEMENT_LEN: usize = ELEM_LEN;
+
+/// The length of the input of `from_uniform_bytes`.
+pub const UNIFORM_BYTES_LEN: usize = 2 * ELEM_LEN;
+
+/// Decodes `bytes`, failing if it isn't the canonical encoding of an element.
+pub fn decode(bytes: &[u8; ELEMENT_LEN]) -> Result<ExtPoint, error::Unspecified> {
+    let s = Elem::from_bytes(bytes);
+    let is_canonical = constant_time::eq(&s.to_bytes(), bytes);
+
+    let one = Elem::one();
+    let ss = s.square();
+    let u1 = one.sub(&ss);
+    let u2 = one.add(&ss);
+    let u2_sqr = u2.square();
+    let v = constant(&D).mul(&u1.square()).negated().sub(&u2_sqr);
+    let (was_square, invsqrt) = sqrt_ratio_m1(&one, &v.mul(&u2_sqr));
+    let den_x = invsqrt.mul(&u2);
+    let den_y = invsqrt.mul(&den_x).mul(&v);
+    let x = abs(&s.add(&s).mul(&den_x));
+    let y = u1.mul(&den_y);
+    let t = x.mul(&y);
+
+    let is_valid =
+        is_canonical & !s.is_negative() & was_square & !t.is_negative() & !y.is_zero();
+    if !is_valid.expose() {
+        return Err(error::Unspecified);
+    }
+    Ok(ExtPoint { x, y, z: one, t })
+}
+
+/// The canonical encoding of `p`.
+pub fn encode(p: &ExtPoint) -> [u8; ELEMENT_LEN] {
+    let (x0, y0, z0, t0) = (&p.x, &p.y, &p.z, &p.t);
+    let u1 = z0.add(y0).mul(&z0.sub(y0));
+    let u2 = x0.mul(y0);
+    let (_, invsqrt) = sqrt_ratio_m1(&Elem::one(), &u1.mul(&u2.square()));
+    let den1 = invsqrt.mul(&u1);
+    let den2 = invsqrt.mul(&u2);
+    let z_inv = den1.mul(&den2).mul(t0);
+    let sqrt_m1 = constant(&SQRT_M1);
+    let ix0 = x0.mul(&sqrt_m1);
+    let iy0 = y0.mul(&sqrt_m1);
+    let enchanted_denominator = den1.mul(&constant(&INVSQRT_A_MINUS_D));
+
+    let rotate = t0.mul(&z_inv).is_negative();
+    let x = Elem::select(rotate, &iy0, x0);
+    let y = Elem::select(rotate, &ix0, y0);
+    let den_inv = Elem::select(rotate, &enchanted_denominator, &den2);
+    let y = Elem::select(x.mul(&z_inv).is_negative(), &y.negated(), &y);
+    abs(&den_inv.mul(&z0.sub(&y))).to_bytes()
+}
+
+/// The one-way map from uniformly random bytes to the group, e.g. the output
+/// of `expand_message_xmd` for hashing to the group.
+pub fn from_uniform_bytes(bytes: &[u8; UNIFORM_BYTES_LEN]) -> ExtPoint {
+    let (a, b) = bytes.split_at(ELEM_LEN);
+    map(a).add(&map(b))
+}
+
+/// Whether `p` is the identity element.
+pub fn is_identity(p: &ExtPoint) -> Choice { constant_time::eq(&encode(p), &[0; ELEMENT_LEN]) }
+
+// `MAP(t)`, where `t` is the 32 bytes, without their most significant bit.
+fn map(t: &[u8]) -> ExtPoint {
+    let mut bytes = [0; ELEM_LEN];
+    bytes.copy_from_slice(t);
+    let t = Elem::from_bytes(&bytes);
+
+    let one = Elem::one();
+    let d = constant(&D);
+    let r = constant(&SQRT_M1).mul(&t.square());
+    let u = r.add(&one).mul(&constant(&ONE_MINUS_D_SQ));
+    let v = one.negated().sub(&r.mul(&d)).mul(&r.add(&d));
+    let (was_square, s) = sqrt_ratio_m1(&u, &v);
+    let s_prime = abs(&s.mul(&t)).negated();
+    let s = Elem::select(was_square, &s, &s_prime);
+    let c = Elem::select(was_square, &one.negated(), &r);
+    let n = c.mul(&r.sub(&one)).mul(&constant(&D_MINUS_ONE_SQ)).sub(&v);
+
+    let w0 = s.add(&s).mul(&v);
+    let w1 = n.mul(&constant(&SQRT_AD_MINUS_ONE));
+    let ss = s.square();
+    let w2 = one.sub(&ss);
+    let w3 = one.add(&ss);
+    ExtPoint {
+        x: w0.mul(&w3),
+        y: w2.mul(&w1),
+        z: w1.mul(&w3),
+        t: w0.mul(&w2),
+    }
+}
+
+// `SQRT_RATIO_M1(u, v)`: whether `u / v` is a square, and the nonnegative
+// square root of `u / v` if it is, or of `SQRT_M1 * u / v` if it isn't.
+fn sqrt_ratio_m1(u: &Elem<T>, v: &Elem<T>) -> (Choice, Elem<T>) {
+    let sqrt_m1 = constant(&SQRT_M1);
+    let v3 = v.square().mul(v);
+    let v7 = v3.square().mul(v);
+    let r = u.mul(&v3).mul(&u.mul(&v7).pow22523());
+    let check = v.mul(&r.square());
+
+    let u_neg = u.negated();
+    let correct_sign_sqrt = elems_equal(&check, u);
+    let flipped_sign_sqrt = elems_equal(&check, &u_neg);
+    let flipped_sign_sqrt_i = elems_equal(&check, &u_neg.mul(&sqrt_m1));
+    let r = Elem::select(flipped_sign_sqrt | flipped_sign_sqrt_i, &sqrt_m1.mul(&r), &r);
+    (correct_sign_sqrt | flipped_sign_sqrt, abs(&r))
+}
+
+fn abs(a: &Elem<T>) -> Elem<T> { Elem::select(a.is_negative(), &a.negated(), a) }
+
+fn elems_equal(a: &Elem<T>, b: &Elem<T>) -> Choice {
+    constant_time::eq(&a.to_bytes(), &b.to_bytes())
+}
+
+#[inline]
+fn constant(bytes: &[u8; ELEM_LEN]) -> Elem<T> { Elem::from_bytes(bytes) }
+
+/// Reduces the 64-byte little-endian `bytes` modulo the group order ℓ.
+pub fn scalar_reduced(bytes: &UnreducedScalar) -> Scalar {
+    let mut unreduced = *bytes;
+    unsafe { GFp_x25519_sc_reduce(&mut unreduced) };
+    let mut r = [0; SCALAR_LEN];
+    r.copy_from_slice(&unreduced[..SCALAR_LEN]);
+    r
+}
+
+/// Whether the little-endian `a` is in [1, ℓ), i.e. a valid private scalar.
+pub fn scalar_is_valid(a: &Scalar) -> Choice {
+    let mut less = Choice::from(false);
+    let mut equal = Choice::from(true);
+    for (&a, &l) in a.iter().zip(ORDER.iter()).rev() {
+        less = less | (equal & constant_time::less_than(a, l));
+        equal = equal & constant_time::words_equal(a, l);
+    }
+    less & !constant_time::eq(a, &[0; SCALAR_LEN])
+}
+
+/// `a*b + c` (mod ℓ).
+pub fn scalar_mul_add(a: &Scalar, b: &Scalar, c: &Scalar) -> Scalar {
+    let mut r = [0; SCALAR_LEN];
+    unsafe { GFp_x25519_sc_muladd(&mut r, a, b, c) };
+    r
+}
+
+/// `1/a` (mod ℓ), computed as `a**(ℓ - 2)`. `a` must not be zero.
+pub fn scalar_inverse(a: &Scalar) -> Scalar {
+    let zero = [0; SCALAR_LEN];
+    let mut exponent = ORDER;
+    exponent[0] -= 2;
+    let mut r = [0; SCALAR_LEN];
+    r[0] = 1;
+    for byte in exponent.iter().rev() {
+        for i in (0..8).rev() {
+            r = scalar_mul_add(&r, &r, &zero);
+            if (byte >> i) & 1 == 1 {
+                r = scalar_mul_add(&r, a, &zero);
+            }
+        }
+    }
+    r
+}
+
+/// `-a` (mod ℓ).
+pub fn scalar_negated(a: &Scalar) -> Scalar {
+    let mut minus_one = ORDER;
+    minus_one[0] -= 1;
+    scalar_mul_add(a, &minus_one, &[0; SCALAR_LEN])
+}
+
+// ℓ = 2**252 + 27742317777372353535851937790883648493, little-endian.
+const ORDER: Scalar = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+// The constants of RFC 9496 Section 4.1, little-endian.
+const D: [u8; ELEM_LEN] = [
+    0xa3, 0x78, 0x59, 0x13, 0xca, 0x4d, 0xeb, 0x75, 0xab, 0xd8, 0x41, 0x41, 0x4d, 0x0a, 0x70, 0x00,
+    0x98, 0xe8, 0x79, 0x77, 0x79, 0x40, 0xc7, 0x8c, 0x73, 0xfe, 0x6f, 0x2b, 0xee, 0x6c, 0x03, 0x52,
+];
+
+const SQRT_M1: [u8; ELEM_LEN] = [
+    0xb0, 0xa0, 0x0e, 0x4a, 0x27, 0x1b, 0xee, 0xc4, 0x78, 0xe4, 0x2f, 0xad, 0x06, 0x18, 0x43, 0x2f,
+    0xa7, 0xd7, 0xfb, 0x3d, 0x99, 0x00, 0x4d, 0x2b, 0x0b, 0xdf, 0xc1, 0x4f, 0x80, 0x24, 0x83, 0x2b,
+];
+
+const SQRT_AD_MINUS_ONE: [u8; ELEM_LEN] = [
+    0x1b, 0x2e, 0x7b, 0x49, 0xa0, 0xf6, 0x97, 0x7e, 0xbd, 0x54, 0x78, 0x1b, 0x0c, 0x8e, 0x9d, 0xaf,
+    0xfd, 0xd1, 0xf5, 0x31, 0xc9, 0xfc, 0x3c, 0x0f, 0xac, 0x48, 0x83, 0x2b, 0xbf, 0x31, 0x69, 0x37,
+];
+
+const INVSQRT_A_MINUS_D: [u8; ELEM_LEN] = [
+    0xea, 0x40, 0x5d, 0x80, 0xaa, 0xfd, 0xc8, 0x99, 0xbe, 0x72, 0x41, 0x5a, 0x17, 0x16, 0x2f, 0x9d,
+    0x40, 0xd8, 0x01, 0xfe, 0x91, 0x7b, 0xc2, 0x16, 0xa2, 0xfc, 0xaf, 0xcf, 0x05, 0x89, 0x6c, 0x78,
+];
+
+const ONE_MINUS_D_SQ: [u8; ELEM_LEN] = [
+    0x76, 0xc1, 0x5f, 0x94, 0xc1, 0x09, 0x7c, 0xe2, 0x0f, 0x35, 0x5e, 0xcd, 0x38, 0xa1, 0x81, 0x2c,
+    0xe4, 0xdf, 0x70, 0xbe, 0xdd, 0xab, 0x94, 0x99, 0xd7, 0xe0, 0xb3, 0xb2, 0xa8, 0x72, 0x90, 0x02,
+];
+
+const D_MINUS_ONE_SQ: [u8; ELEM_LEN] = [
+    0x20, 0x4d, 0xed, 0x44, 0xaa, 0x5a, 0xad, 0x31, 0x99, 0x19, 0x1e, 0xb0, 0x2c, 0x4a, 0x9e, 0xd2,
+    0xeb, 0x4e, 0x9b, 0x52, 0x2f, 0xd3, 0xdc, 0x4c, 0x41, 0x22, 0x6c, 0xf6, 0x7a, 0xb3, 0x68, 0x59,
+];
+
+extern "C" {
+    fn GFp_x25519_sc_muladd(s: &mut Scalar, a: &Scalar, b: &Scalar, c: &Scalar);
+    fn GFp_x25519_sc_reduce(s: &mut UnreducedScalar);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    fn consume_element(test_case: &mut test::TestCase, key: &str) -> [u8; ELEMENT_LEN] {
+        let mut r = [0; ELEMENT_LEN];
+        r.copy_from_slice(&test_case.consume_bytes(key));
+        r
+    }
+
+    #[test]
+    fn test_ristretto255() {
+        test::from_file("src/ec/curve25519/ristretto255_tests.txt", |section, test_case| {
+            match section {
+                "Multiples" => {
+                    let n = test_case.consume_usize("n");
+                    let expected = consume_element(test_case, "P");
+                    let mut scalar = [0; SCALAR_LEN];
+                    scalar[0] = n as u8;
+                    let p = ExtPoint::mul_base(&scalar);
+                    assert_eq!(encode(&p), expected);
+                    assert_eq!(is_identity(&p).expose(), n == 0);
+                    assert_eq!(encode(&decode(&expected).unwrap()), expected);
+                },
+                "Invalid" => {
+                    let encoded = consume_element(test_case, "P");
+                    assert!(decode(&encoded).is_err());
+                },
+                "FromUniformBytes" => {
+                    let mut input = [0; UNIFORM_BYTES_LEN];
+                    input.copy_from_slice(&test_case.consume_bytes("Input"));
+                    let expected = consume_element(test_case, "P");
+                    assert_eq!(encode(&from_uniform_bytes(&input)), expected);
+                },
+                "Mul" => {
+                    let mut a = [0; SCALAR_LEN];
+                    a.copy_from_slice(&test_case.consume_bytes("a"));
+                    let p = decode(&consume_element(test_case, "P")).unwrap();
+                    let q = decode(&consume_element(test_case, "Q")).unwrap();
+                    let expected_product = consume_element(test_case, "aP");
+                    let expected_sum = consume_element(test_case, "PplusQ");
+                    assert_eq!(encode(&p.mul(&a)), expected_product);
+                    assert_eq!(encode(&p.add(&q)), expected_sum);
+
+                    // 1/a * (a * P) == P.
+                    let inverse = scalar_inverse(&a);
+                    assert_eq!(encode(&p.mul(&a).mul(&inverse)), encode(&p));
+                    let zero = [0; SCALAR_LEN];
+                    assert_eq!(scalar_mul_add(&a, &scalar_negated(&inverse), &zero), {
+                        let mut minus_one = ORDER;
+                        minus_one[0] -= 1;
+                        minus_one
+                    });
+                },
+                _ => unreachable!(),
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_scalar_is_valid() {
+        let mut a = ORDER;
+        assert!(!scalar_is_valid(&a).expose());
+        a[0] -= 1;
+        assert!(scalar_is_valid(&a).expose());
+        assert!(!scalar_is_valid(&[0; SCALAR_LEN]).expose());
+        let mut one = [0; SCALAR_LEN];
+        one[0] = 1;
+        assert!(scalar_is_valid(&one).expose());
+        assert!(!scalar_is_valid(&[0xff; SCALAR_LEN]).expose());
+    }
+}
@@ -18,8 +18,18 @@ use super::ops;
 use crate::{agreement, constant_time, ec, error, polyfill::convert::*, rand};
 use untrusted;
 
+#[cfg(all(feature = "alloc", target_arch = "x86_64"))]
+mod avx2;
+
+#[cfg(all(feature = "alloc", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod lanes;
+
+#[cfg(all(feature = "alloc", target_arch = "aarch64"))]
+mod neon;
+
 static CURVE25519: ec::Curve = ec::Curve {
     public_key_len: PUBLIC_KEY_LEN,
+    compressed_public_key_len: PUBLIC_KEY_LEN,
     elem_and_scalar_len: ELEM_AND_SCALAR_LEN,
     id: ec::CurveID::Curve25519,
     check_private_key_bytes: x25519_check_private_key_bytes,
@@ -93,6 +103,108 @@ fn x25519_ecdh(
     Ok(())
 }
 
+/// Calls `f` with the result of `x25519_ecdh` for each private key in
+/// `my_private_keys` and the peer public key at the same index of
+/// `peer_public_keys`, in order. With AVX2 or NEON, the scalar
+/// multiplications are done in batches; see `lanes`.
+#[cfg(feature = "alloc")]
+pub(crate) fn x25519_ecdh_multi(
+    my_private_keys: &[&ec::PrivateKey], peer_public_keys: &[untrusted::Input],
+    mut f: impl FnMut(Result<&[u8], error::Unspecified>),
+) {
+    assert_eq!(my_private_keys.len(), peer_public_keys.len());
+
+    // A batch takes about as long as three keys done one at a time, so the
+    // keys left over after the last full batch are done one at a time.
+    let batched = x25519_ecdh_batches(my_private_keys, peer_public_keys, &mut f);
+
+    let rest = my_private_keys[batched..]
+        .iter()
+        .zip(&peer_public_keys[batched..]);
+    for (my_private_key, peer_public_key) in rest {
+        let mut out = [0u8; SHARED_SECRET_LEN];
+        let result = x25519_ecdh(&mut out, my_private_key, *peer_public_key);
+        f(result.map(|()| &out[..]));
+        crate::zeroize::zeroize(&mut out);
+    }
+}
+
+// Returns the number of keys that were done.
+#[cfg(all(feature = "alloc", any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn x25519_ecdh_batches(
+    my_private_keys: &[&ec::PrivateKey], peer_public_keys: &[untrusted::Input],
+    f: &mut impl FnMut(Result<&[u8], error::Unspecified>),
+) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::cpu::features().avx2() {
+            return x25519_ecdh_lanes::<avx2::Avx2>(my_private_keys, peer_public_keys, f);
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        if crate::cpu::features().neon() {
+            return x25519_ecdh_lanes::<neon::Neon>(my_private_keys, peer_public_keys, f);
+        }
+    }
+
+    0
+}
+
+#[cfg(all(feature = "alloc", not(any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn x25519_ecdh_batches(
+    _: &[&ec::PrivateKey], _: &[untrusted::Input],
+    _: &mut impl FnMut(Result<&[u8], error::Unspecified>),
+) -> usize {
+    0
+}
+
+// Does as many full batches of `V::LANES` keys as there are, returning the
+// number of keys that were done.
+#[cfg(all(feature = "alloc", any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn x25519_ecdh_lanes<V: lanes::Lanes>(
+    my_private_keys: &[&ec::PrivateKey], peer_public_keys: &[untrusted::Input],
+    f: &mut impl FnMut(Result<&[u8], error::Unspecified>),
+) -> usize {
+    let batches = my_private_keys
+        .chunks_exact(V::LANES)
+        .zip(peer_public_keys.chunks_exact(V::LANES));
+    for (my_private_keys, peer_public_keys) in batches {
+        let mut scalars = [[0u8; PRIVATE_KEY_LEN]; lanes::MAX_LANES];
+        for (scalar, my_private_key) in scalars.iter_mut().zip(my_private_keys) {
+            scalar.copy_from_slice(&my_private_key.bytes[..PRIVATE_KEY_LEN]);
+        }
+        let mut points = [[0u8; PUBLIC_KEY_LEN]; lanes::MAX_LANES];
+        let mut valid = [false; lanes::MAX_LANES];
+        for ((point, valid), peer_public_key) in
+            points.iter_mut().zip(valid.iter_mut()).zip(peer_public_keys)
+        {
+            let peer_public_key = peer_public_key.as_slice_less_safe();
+            *valid = peer_public_key.len() == PUBLIC_KEY_LEN;
+            if *valid {
+                point.copy_from_slice(peer_public_key);
+            }
+        }
+
+        let mut out = [[0u8; SHARED_SECRET_LEN]; lanes::MAX_LANES];
+        lanes::x25519::<V>(&mut out, &scalars, &points);
+        crate::zeroize::zeroize(&mut scalars);
+
+        let zeros: SharedSecret = [0; SHARED_SECRET_LEN];
+        for (out, valid) in out.iter().zip(valid.iter()).take(V::LANES) {
+            // All-zero output results when the input is a point of small order.
+            if !valid || constant_time::verify_slices_are_equal(out, &zeros).is_ok() {
+                f(Err(error::Unspecified));
+            } else {
+                f(Ok(&out[..]));
+            }
+        }
+        crate::zeroize::zeroize(&mut out);
+    }
+    my_private_keys.len() - my_private_keys.len() % V::LANES
+}
+
 const ELEM_AND_SCALAR_LEN: usize = ops::ELEM_LEN;
 
 // An X25519 private key as an unmasked scalar.
@@ -113,3 +225,118 @@ extern "C" {
         out: &mut ops::EncodedPoint, scalar: &ops::Scalar, point: &ops::EncodedPoint,
     );
 }
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::digest;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_x25519_ecdh_multi() {
+        // p - 1, p, p + 1, and values that are only canonical mod p once the
+        // most significant bit is ignored.
+        let mut peer_public_keys: Vec<PublicKey> = vec![[0; 32], [0xff; 32], [0x7f; 32]];
+        for &low in &[0xec, 0xed, 0xee] {
+            let mut p_plus = [0xffu8; 32];
+            p_plus[0] = low;
+            p_plus[31] = 0x7f;
+            peer_public_keys.push(p_plus);
+            p_plus[31] = 0xff;
+            peer_public_keys.push(p_plus);
+        }
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        peer_public_keys.push(one);
+        for i in 0..40u8 {
+            let mut random = [0u8; 32];
+            random.copy_from_slice(&digest::digest(&digest::SHA256, &[i]).as_ref()[..32]);
+            peer_public_keys.push(random);
+        }
+
+        let private_keys: Vec<ec::PrivateKey> = (0..peer_public_keys.len())
+            .map(|i| {
+                let mut private_key = ec::PrivateKey {
+                    bytes: [0; ec::SCALAR_MAX_BYTES],
+                };
+                let seed = digest::digest(&digest::SHA256, &[0xa5, i as u8]);
+                private_key.bytes[..32].copy_from_slice(seed.as_ref());
+                if i % 7 == 0 {
+                    // Scalars are clamped, so the ignored bits may be anything.
+                    private_key.bytes[..32].copy_from_slice(&[0xff; 32]);
+                }
+                private_key
+            })
+            .collect();
+
+        let expected: Vec<Option<SharedSecret>> = private_keys
+            .iter()
+            .zip(peer_public_keys.iter())
+            .map(|(private_key, peer_public_key)| {
+                let mut out = [0u8; SHARED_SECRET_LEN];
+                let peer_public_key = untrusted::Input::from(peer_public_key);
+                x25519_ecdh(&mut out, private_key, peer_public_key)
+                    .ok()
+                    .map(|()| out)
+            })
+            .collect();
+        assert!(expected.iter().any(Option::is_none));
+
+        let check = |f: &Fn(&[&ec::PrivateKey], &[untrusted::Input], &mut Vec<_>)| {
+            for len in (0..10).chain(Some(private_keys.len())) {
+                let my_private_keys: Vec<&ec::PrivateKey> = private_keys[..len].iter().collect();
+                let inputs: Vec<untrusted::Input> = peer_public_keys[..len]
+                    .iter()
+                    .map(|peer_public_key| untrusted::Input::from(peer_public_key))
+                    .collect();
+                let mut actual = Vec::new();
+                f(&my_private_keys, &inputs, &mut actual);
+                assert_eq!(&actual[..], &expected[..len]);
+            }
+        };
+        let collect = |actual: &mut Vec<_>, result: Result<&[u8], error::Unspecified>| {
+            actual.push(result.ok().map(|out| {
+                let mut shared_secret = [0u8; SHARED_SECRET_LEN];
+                shared_secret.copy_from_slice(out);
+                shared_secret
+            }))
+        };
+
+        check(&|keys, inputs, actual| {
+            x25519_ecdh_multi(keys, inputs, |result| collect(actual, result))
+        });
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            if crate::cpu::features().avx2() {
+                check(&|keys, inputs, actual| {
+                    let batched = x25519_ecdh_lanes::<avx2::Avx2>(keys, inputs, &mut |result| {
+                        collect(actual, result)
+                    });
+                    assert_eq!(batched, keys.len() - keys.len() % 4);
+                    x25519_ecdh_multi(&keys[batched..], &inputs[batched..], |result| {
+                        collect(actual, result)
+                    });
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn test_x25519_ecdh_multi_wrong_length() {
+        let private_key = ec::PrivateKey {
+            bytes: [1; ec::SCALAR_MAX_BYTES],
+        };
+        let peer_public_keys = [[9u8; 33], [9u8; 33]];
+        let inputs = [
+            untrusted::Input::from(&peer_public_keys[0][..32]),
+            untrusted::Input::from(&peer_public_keys[1][..]),
+            untrusted::Input::from(&peer_public_keys[1][..31]),
+        ];
+        let mut results = Vec::new();
+        x25519_ecdh_multi(&[&private_key; 3], &inputs, |result| {
+            results.push(result.is_ok())
+        });
+        assert_eq!(results, [true, false, false]);
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! `lanes::Lanes` for AVX2, four keys at a time.
+
+use super::{
+    lanes::{self, Fe, Lanes, MAX_LANES},
+    PrivateKey,
+};
+use core::{
+    arch::x86_64::*,
+    ops::{Add, BitAnd, BitXor, Mul, Sub},
+};
+
+#[derive(Clone, Copy)]
+pub(super) struct Avx2(__m256i);
+
+impl Lanes for Avx2 {
+    const LANES: usize = 4;
+
+    #[inline(always)]
+    fn splat(value: u64) -> Self { Avx2(unsafe { _mm256_set1_epi64x(value as i64) }) }
+
+    #[inline(always)]
+    fn load(values: &[u64; MAX_LANES]) -> Self {
+        Avx2(unsafe { _mm256_loadu_si256(values.as_ptr() as *const __m256i) })
+    }
+
+    #[inline(always)]
+    fn store(self, out: &mut [u64; MAX_LANES]) {
+        unsafe { _mm256_storeu_si256(out.as_mut_ptr() as *mut __m256i, self.0) }
+    }
+
+    #[inline(always)]
+    fn shl<const N: i32>(self) -> Self { Avx2(unsafe { _mm256_slli_epi64::<N>(self.0) }) }
+
+    #[inline(always)]
+    fn shr<const N: i32>(self) -> Self { Avx2(unsafe { _mm256_srli_epi64::<N>(self.0) }) }
+
+    #[inline(always)]
+    fn fe_mul(f: &Fe<Self>, g: &Fe<Self>) -> Fe<Self> { unsafe { mul(f, g) } }
+
+    #[inline(always)]
+    fn fe_square(f: &Fe<Self>) -> Fe<Self> { unsafe { square(f) } }
+
+    #[inline(always)]
+    fn fe_square_n(f: &Fe<Self>, n: usize) -> Fe<Self> { unsafe { square_n(f, n) } }
+
+    #[inline(always)]
+    fn ladder(scalars: &[PrivateKey; MAX_LANES], u: &Fe<Self>) -> (Fe<Self>, Fe<Self>) {
+        unsafe { ladder(scalars, u) }
+    }
+}
+
+impl Add for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, other: Self) -> Self { Avx2(unsafe { _mm256_add_epi64(self.0, other.0) }) }
+}
+
+impl Sub for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, other: Self) -> Self { Avx2(unsafe { _mm256_sub_epi64(self.0, other.0) }) }
+}
+
+impl Mul for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, other: Self) -> Self { Avx2(unsafe { _mm256_mul_epu32(self.0, other.0) }) }
+}
+
+impl BitAnd for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitand(self, other: Self) -> Self { Avx2(unsafe { _mm256_and_si256(self.0, other.0) }) }
+}
+
+impl BitXor for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitxor(self, other: Self) -> Self { Avx2(unsafe { _mm256_xor_si256(self.0, other.0) }) }
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn mul(f: &Fe<Avx2>, g: &Fe<Avx2>) -> Fe<Avx2> { lanes::mul(f, g) }
+
+#[target_feature(enable = "avx2")]
+unsafe fn square(f: &Fe<Avx2>) -> Fe<Avx2> { lanes::square(f) }
+
+#[target_feature(enable = "avx2")]
+unsafe fn square_n(f: &Fe<Avx2>, n: usize) -> Fe<Avx2> { lanes::square_n(f, n) }
+
+#[target_feature(enable = "avx2")]
+unsafe fn ladder(scalars: &[PrivateKey; MAX_LANES], u: &Fe<Avx2>) -> (Fe<Avx2>, Fe<Avx2>) {
+    lanes::ladder(scalars, u)
+}
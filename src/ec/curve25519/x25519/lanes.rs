@@ -0,0 +1,404 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! X25519 for several keys at once, with each key's field arithmetic in its
+//! own 64-bit lane of a vector register: four keys at a time with AVX2
+//! (`avx2`) and two at a time with NEON (`neon`).
+//!
+//! Field elements have ten limbs of alternately 26 and 25 bits, like the
+//! 32-bit code in third_party/fiat/curve25519.c, so that every limb fits in
+//! the low half of a lane and one `vpmuludq` or `umull` multiplies a limb of
+//! every key at once. The ladder is that of [RFC 7748 Section 5], with the
+//! conditional swaps done with masks, so it is constant-time in the same way
+//! as `GFp_x25519_scalar_mult`.
+//!
+//! Limbs are at most 2**26 (even) and 2**25 (odd) plus a little after
+//! `carry`, and at most three times that after `add` or `sub`. With inputs
+//! that small, 19 times any limb fits in 32 bits and the sums of products in
+//! `mul` and `square` fit in 64 bits.
+//!
+//! [RFC 7748 Section 5]: https://tools.ietf.org/html/rfc7748#section-5
+
+use super::{PrivateKey, PublicKey, SharedSecret, ELEM_AND_SCALAR_LEN};
+use crate::zeroize;
+use core::ops::{Add, BitAnd, BitXor, Mul, Sub};
+
+/// The most keys that any implementation of `Lanes` processes at a time.
+pub(super) const MAX_LANES: usize = 4;
+
+/// The limbs of a field element, least significant first, for each lane.
+pub(super) type Fe<V> = [V; 10];
+
+/// A vector of 64-bit lanes.
+///
+/// `Mul` multiplies the low 32 bits of each lane, giving a 64-bit product.
+///
+/// `fe_mul`, `fe_square`, `fe_square_n`, and `ladder` are implemented by
+/// calling `mul`, `square`, `square_n`, and `ladder` in this module from a
+/// function that has the target features of the implementation enabled, so
+/// that the operators are inlined into them.
+pub(super) trait Lanes:
+    Copy
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + BitAnd<Output = Self>
+    + BitXor<Output = Self>
+{
+    /// The number of keys that are processed at a time.
+    const LANES: usize;
+
+    fn splat(value: u64) -> Self;
+
+    /// Loads the first `LANES` values of `values`.
+    fn load(values: &[u64; MAX_LANES]) -> Self;
+
+    /// Stores the lanes in the first `LANES` values of `out`.
+    fn store(self, out: &mut [u64; MAX_LANES]);
+
+    fn shl<const N: i32>(self) -> Self;
+    fn shr<const N: i32>(self) -> Self;
+
+    fn fe_mul(f: &Fe<Self>, g: &Fe<Self>) -> Fe<Self>;
+    fn fe_square(f: &Fe<Self>) -> Fe<Self>;
+    fn fe_square_n(f: &Fe<Self>, n: usize) -> Fe<Self>;
+    fn ladder(scalars: &[PrivateKey; MAX_LANES], u: &Fe<Self>) -> (Fe<Self>, Fe<Self>);
+}
+
+/// Sets `out[i]` to the X25519 function of `scalars[i]` and `points[i]`, per
+/// RFC 7748 Section 5, for the first `V::LANES` values of each. Checking the
+/// result for zero is left to the caller.
+pub(super) fn x25519<V: Lanes>(
+    out: &mut [SharedSecret; MAX_LANES], scalars: &[PrivateKey; MAX_LANES],
+    points: &[PublicKey; MAX_LANES],
+) {
+    let mut clamped = *scalars;
+    for scalar in clamped.iter_mut() {
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+    }
+
+    let mut limbs = [[0u64; 10]; MAX_LANES];
+    for (limbs, point) in limbs.iter_mut().zip(points.iter()) {
+        *limbs = from_bytes(point);
+    }
+    let u = transpose::<V>(&limbs);
+
+    let (x2, z2) = V::ladder(&clamped, &u);
+    zeroize::zeroize(&mut clamped);
+    let result = V::fe_mul(&x2, &invert(&z2));
+
+    let mut lanes = [0u64; MAX_LANES];
+    for (i, limb) in result.iter().enumerate() {
+        limb.store(&mut lanes);
+        for (limbs, lane) in limbs.iter_mut().zip(lanes.iter()) {
+            limbs[i] = *lane;
+        }
+    }
+    for (out, limbs) in out.iter_mut().zip(limbs.iter()) {
+        *out = to_bytes(limbs);
+    }
+}
+
+// The Montgomery ladder of RFC 7748 Section 5, returning (x_2, z_2) before
+// the final division.
+#[inline(always)]
+pub(super) fn ladder<V: Lanes>(scalars: &[PrivateKey; MAX_LANES], u: &Fe<V>) -> (Fe<V>, Fe<V>) {
+    let mut x2 = constant::<V>(1);
+    let mut z2 = constant::<V>(0);
+    let mut x3 = *u;
+    let mut z3 = constant::<V>(1);
+    let mut swap = V::splat(0);
+
+    for t in (0..255).rev() {
+        let mut bits = [0u64; MAX_LANES];
+        for (bit, scalar) in bits.iter_mut().zip(scalars.iter()) {
+            *bit = u64::from((scalar[t / 8] >> (t % 8)) & 1).wrapping_neg();
+        }
+        let k_t = V::load(&bits);
+
+        swap = swap ^ k_t;
+        cswap(swap, &mut x2, &mut x3);
+        cswap(swap, &mut z2, &mut z3);
+        swap = k_t;
+
+        let a = add(&x2, &z2);
+        let aa = V::fe_square(&a);
+        let b = sub(&x2, &z2);
+        let bb = V::fe_square(&b);
+        let e = sub(&aa, &bb);
+        let c = add(&x3, &z3);
+        let d = sub(&x3, &z3);
+        let da = V::fe_mul(&d, &a);
+        let cb = V::fe_mul(&c, &b);
+        x3 = V::fe_square(&add(&da, &cb));
+        z3 = V::fe_mul(u, &V::fe_square(&sub(&da, &cb)));
+        x2 = V::fe_mul(&aa, &bb);
+        z2 = V::fe_mul(&e, &add(&aa, &mul_121665(&e)));
+    }
+
+    cswap(swap, &mut x2, &mut x3);
+    cswap(swap, &mut z2, &mut z3);
+    (x2, z2)
+}
+
+// z**(p - 2), with the addition chain of `fe_invert` in curve25519.c.
+fn invert<V: Lanes>(z: &Fe<V>) -> Fe<V> {
+    let t0 = V::fe_square(z);
+    let t1 = V::fe_square_n(&t0, 2);
+    let t1 = V::fe_mul(z, &t1);
+    let t0 = V::fe_mul(&t0, &t1);
+    let t2 = V::fe_square(&t0);
+    let t1 = V::fe_mul(&t1, &t2);
+    let t2 = V::fe_square_n(&t1, 5);
+    let t1 = V::fe_mul(&t2, &t1);
+    let t2 = V::fe_square_n(&t1, 10);
+    let t2 = V::fe_mul(&t2, &t1);
+    let t3 = V::fe_square_n(&t2, 20);
+    let t2 = V::fe_mul(&t3, &t2);
+    let t2 = V::fe_square_n(&t2, 10);
+    let t1 = V::fe_mul(&t2, &t1);
+    let t2 = V::fe_square_n(&t1, 50);
+    let t2 = V::fe_mul(&t2, &t1);
+    let t3 = V::fe_square_n(&t2, 100);
+    let t2 = V::fe_mul(&t3, &t2);
+    let t2 = V::fe_square_n(&t2, 50);
+    let t1 = V::fe_mul(&t2, &t1);
+    let t1 = V::fe_square_n(&t1, 5);
+    V::fe_mul(&t1, &t0)
+}
+
+#[inline(always)]
+fn constant<V: Lanes>(value: u64) -> Fe<V> {
+    let mut r = [V::splat(0); 10];
+    r[0] = V::splat(value);
+    r
+}
+
+// Swaps `a` and `b` in the lanes where `mask` is all ones.
+#[inline(always)]
+fn cswap<V: Lanes>(mask: V, a: &mut Fe<V>, b: &mut Fe<V>) {
+    for (a, b) in a.iter_mut().zip(b.iter_mut()) {
+        let x = mask & (*a ^ *b);
+        *a = *a ^ x;
+        *b = *b ^ x;
+    }
+}
+
+#[inline(always)]
+fn add<V: Lanes>(f: &Fe<V>, g: &Fe<V>) -> Fe<V> {
+    let mut h = *f;
+    for (h, g) in h.iter_mut().zip(g.iter()) {
+        *h = *h + *g;
+    }
+    h
+}
+
+// Adds 2p before subtracting so that no limb goes negative; `g` must have
+// been carried.
+#[inline(always)]
+fn sub<V: Lanes>(f: &Fe<V>, g: &Fe<V>) -> Fe<V> {
+    let two_p_0 = V::splat(0x7ff_ffda);
+    let two_p_even = V::splat(0x7ff_fffe);
+    let two_p_odd = V::splat(0x3ff_fffe);
+    let mut h = *f;
+    for (i, (h, g)) in h.iter_mut().zip(g.iter()).enumerate() {
+        let two_p = match (i, LIMB_BITS[i]) {
+            (0, _) => two_p_0,
+            (_, 26) => two_p_even,
+            _ => two_p_odd,
+        };
+        *h = *h + two_p - *g;
+    }
+    h
+}
+
+#[inline(always)]
+fn mul_121665<V: Lanes>(f: &Fe<V>) -> Fe<V> {
+    let a24 = V::splat(121665);
+    let mut h = *f;
+    for h in h.iter_mut() {
+        *h = *h * a24;
+    }
+    carry(h)
+}
+
+#[inline(always)]
+pub(super) fn mul<V: Lanes>(f: &Fe<V>, g: &Fe<V>) -> Fe<V> {
+    let [f0, f1, f2, f3, f4, f5, f6, f7, f8, f9] = *f;
+    let [g0, g1, g2, g3, g4, g5, g6, g7, g8, g9] = *g;
+    let nineteen = V::splat(19);
+    let (f1_2, f3_2, f5_2, f7_2, f9_2) = (f1 + f1, f3 + f3, f5 + f5, f7 + f7, f9 + f9);
+    let (g1_19, g2_19, g3_19) = (g1 * nineteen, g2 * nineteen, g3 * nineteen);
+    let (g4_19, g5_19, g6_19) = (g4 * nineteen, g5 * nineteen, g6 * nineteen);
+    let (g7_19, g8_19, g9_19) = (g7 * nineteen, g8 * nineteen, g9 * nineteen);
+
+    let h0 = f0 * g0 + f1_2 * g9_19 + f2 * g8_19 + f3_2 * g7_19 + f4 * g6_19 + f5_2 * g5_19
+        + f6 * g4_19 + f7_2 * g3_19 + f8 * g2_19 + f9_2 * g1_19;
+    let h1 = f0 * g1 + f1 * g0 + f2 * g9_19 + f3 * g8_19 + f4 * g7_19 + f5 * g6_19 + f6 * g5_19
+        + f7 * g4_19 + f8 * g3_19 + f9 * g2_19;
+    let h2 = f0 * g2 + f1_2 * g1 + f2 * g0 + f3_2 * g9_19 + f4 * g8_19 + f5_2 * g7_19 + f6 * g6_19
+        + f7_2 * g5_19 + f8 * g4_19 + f9_2 * g3_19;
+    let h3 = f0 * g3 + f1 * g2 + f2 * g1 + f3 * g0 + f4 * g9_19 + f5 * g8_19 + f6 * g7_19
+        + f7 * g6_19 + f8 * g5_19 + f9 * g4_19;
+    let h4 = f0 * g4 + f1_2 * g3 + f2 * g2 + f3_2 * g1 + f4 * g0 + f5_2 * g9_19 + f6 * g8_19
+        + f7_2 * g7_19 + f8 * g6_19 + f9_2 * g5_19;
+    let h5 = f0 * g5 + f1 * g4 + f2 * g3 + f3 * g2 + f4 * g1 + f5 * g0 + f6 * g9_19 + f7 * g8_19
+        + f8 * g7_19 + f9 * g6_19;
+    let h6 = f0 * g6 + f1_2 * g5 + f2 * g4 + f3_2 * g3 + f4 * g2 + f5_2 * g1 + f6 * g0
+        + f7_2 * g9_19 + f8 * g8_19 + f9_2 * g7_19;
+    let h7 = f0 * g7 + f1 * g6 + f2 * g5 + f3 * g4 + f4 * g3 + f5 * g2 + f6 * g1 + f7 * g0
+        + f8 * g9_19 + f9 * g8_19;
+    let h8 = f0 * g8 + f1_2 * g7 + f2 * g6 + f3_2 * g5 + f4 * g4 + f5_2 * g3 + f6 * g2 + f7_2 * g1
+        + f8 * g0 + f9_2 * g9_19;
+    let h9 = f0 * g9 + f1 * g8 + f2 * g7 + f3 * g6 + f4 * g5 + f5 * g4 + f6 * g3 + f7 * g2
+        + f8 * g1 + f9 * g0;
+    carry([h0, h1, h2, h3, h4, h5, h6, h7, h8, h9])
+}
+
+#[inline(always)]
+pub(super) fn square<V: Lanes>(f: &Fe<V>) -> Fe<V> {
+    let [f0, f1, f2, f3, f4, f5, f6, f7, f8, f9] = *f;
+    let nineteen = V::splat(19);
+    let (f0_2, f1_2, f2_2, f3_2, f4_2) = (f0 + f0, f1 + f1, f2 + f2, f3 + f3, f4 + f4);
+    let (f5_2, f6_2, f7_2, f8_2, f9_2) = (f5 + f5, f6 + f6, f7 + f7, f8 + f8, f9 + f9);
+    let (f1_4, f3_4, f5_4, f7_4) = (f1_2 + f1_2, f3_2 + f3_2, f5_2 + f5_2, f7_2 + f7_2);
+    let (f5_19, f6_19, f7_19) = (f5 * nineteen, f6 * nineteen, f7 * nineteen);
+    let (f8_19, f9_19) = (f8 * nineteen, f9 * nineteen);
+
+    let h0 = f0 * f0 + f1_4 * f9_19 + f2_2 * f8_19 + f3_4 * f7_19 + f4_2 * f6_19 + f5_2 * f5_19;
+    let h1 = f0_2 * f1 + f2_2 * f9_19 + f3_2 * f8_19 + f4_2 * f7_19 + f5_2 * f6_19;
+    let h2 = f0_2 * f2 + f1_2 * f1 + f3_4 * f9_19 + f4_2 * f8_19 + f5_4 * f7_19 + f6 * f6_19;
+    let h3 = f0_2 * f3 + f1_2 * f2 + f4_2 * f9_19 + f5_2 * f8_19 + f6_2 * f7_19;
+    let h4 = f0_2 * f4 + f1_4 * f3 + f2 * f2 + f5_4 * f9_19 + f6_2 * f8_19 + f7_2 * f7_19;
+    let h5 = f0_2 * f5 + f1_2 * f4 + f2_2 * f3 + f6_2 * f9_19 + f7_2 * f8_19;
+    let h6 = f0_2 * f6 + f1_4 * f5 + f2_2 * f4 + f3_2 * f3 + f7_4 * f9_19 + f8 * f8_19;
+    let h7 = f0_2 * f7 + f1_2 * f6 + f2_2 * f5 + f3_2 * f4 + f8_2 * f9_19;
+    let h8 = f0_2 * f8 + f1_4 * f7 + f2_2 * f6 + f3_4 * f5 + f4 * f4 + f9_2 * f9_19;
+    let h9 = f0_2 * f9 + f1_2 * f8 + f2_2 * f7 + f3_2 * f6 + f4_2 * f5;
+    carry([h0, h1, h2, h3, h4, h5, h6, h7, h8, h9])
+}
+
+#[inline(always)]
+pub(super) fn square_n<V: Lanes>(f: &Fe<V>, n: usize) -> Fe<V> {
+    let mut h = square(f);
+    for _ in 1..n {
+        h = square(&h);
+    }
+    h
+}
+
+// Reduces each limb to 26 or 25 bits, plus a little in `h[1]` and `h[5]`,
+// in the order used by `fe_mul` in curve25519.c.
+#[inline(always)]
+fn carry<V: Lanes>(mut h: Fe<V>) -> Fe<V> {
+    let mask_26 = V::splat((1 << 26) - 1);
+    let mask_25 = V::splat((1 << 25) - 1);
+    let carry_26 = |h: &mut Fe<V>, i: usize| {
+        h[i + 1] = h[i + 1] + h[i].shr::<26>();
+        h[i] = h[i] & mask_26;
+    };
+    let carry_25 = |h: &mut Fe<V>, i: usize| {
+        h[i + 1] = h[i + 1] + h[i].shr::<25>();
+        h[i] = h[i] & mask_25;
+    };
+    carry_26(&mut h, 0);
+    carry_26(&mut h, 4);
+    carry_25(&mut h, 1);
+    carry_25(&mut h, 5);
+    carry_26(&mut h, 2);
+    carry_26(&mut h, 6);
+    carry_25(&mut h, 3);
+    carry_25(&mut h, 7);
+    carry_26(&mut h, 4);
+    carry_26(&mut h, 8);
+    // The carry out of `h[9]` can be wider than 32 bits, so it is multiplied
+    // by 19 = 16 + 2 + 1 with shifts.
+    let c = h[9].shr::<25>();
+    h[9] = h[9] & mask_25;
+    h[0] = h[0] + c + c.shl::<1>() + c.shl::<4>();
+    carry_26(&mut h, 0);
+    h
+}
+
+fn transpose<V: Lanes>(limbs: &[[u64; 10]; MAX_LANES]) -> Fe<V> {
+    let mut r = [V::splat(0); 10];
+    for (i, r) in r.iter_mut().enumerate() {
+        let mut lanes = [0u64; MAX_LANES];
+        for (lane, limbs) in lanes.iter_mut().zip(limbs.iter()) {
+            *lane = limbs[i];
+        }
+        *r = V::load(&lanes);
+    }
+    r
+}
+
+// The width of each limb and the position of its least significant bit.
+const LIMB_BITS: [usize; 10] = [26, 25, 26, 25, 26, 25, 26, 25, 26, 25];
+const LIMB_OFFSETS: [usize; 10] = [0, 26, 51, 77, 102, 128, 153, 179, 204, 230];
+
+// Decodes a u-coordinate, ignoring the most significant bit as RFC 7748
+// requires. Non-canonical values are reduced by the arithmetic.
+fn from_bytes(bytes: &[u8; ELEM_AND_SCALAR_LEN]) -> [u64; 10] {
+    let mut padded = [0u8; ELEM_AND_SCALAR_LEN + 8];
+    padded[..ELEM_AND_SCALAR_LEN].copy_from_slice(bytes);
+    padded[ELEM_AND_SCALAR_LEN - 1] &= 0x7f;
+    let mut r = [0u64; 10];
+    for (i, r) in r.iter_mut().enumerate() {
+        let offset = LIMB_OFFSETS[i];
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&padded[(offset / 8)..((offset / 8) + 8)]);
+        *r = (u64::from_le_bytes(word) >> (offset % 8)) & ((1 << LIMB_BITS[i]) - 1);
+    }
+    r
+}
+
+// Encodes a carried field element canonically, like `fe_tobytes` in
+// curve25519.c: `q` is 1 exactly when the value is at least p, in which case
+// p is subtracted by adding 19 and dropping bit 255.
+fn to_bytes(limbs: &[u64; 10]) -> [u8; ELEM_AND_SCALAR_LEN] {
+    let mut h = *limbs;
+    let mut q = (19 * h[9] + (1 << 24)) >> 25;
+    for (i, h) in h.iter().enumerate() {
+        q = (h + q) >> LIMB_BITS[i];
+    }
+    h[0] += 19 * q;
+    for i in 0..9 {
+        let c = h[i] >> LIMB_BITS[i];
+        h[i + 1] += c;
+        h[i] &= (1 << LIMB_BITS[i]) - 1;
+    }
+    h[9] &= (1 << 25) - 1;
+
+    let mut out = [0u8; ELEM_AND_SCALAR_LEN];
+    let mut acc: u64 = 0;
+    let mut acc_bits = 0;
+    let mut bytes = out.iter_mut();
+    for (i, h) in h.iter().enumerate() {
+        acc |= h << acc_bits;
+        acc_bits += LIMB_BITS[i];
+        while acc_bits >= 8 {
+            if let Some(byte) = bytes.next() {
+                *byte = acc as u8;
+            }
+            acc >>= 8;
+            acc_bits -= 8;
+        }
+    }
+    if let Some(byte) = bytes.next() {
+        *byte = acc as u8;
+    }
+    out
+}
@@ -0,0 +1,113 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! `lanes::Lanes` for NEON, two keys at a time.
+
+use super::{
+    lanes::{self, Fe, Lanes, MAX_LANES},
+    PrivateKey,
+};
+use core::{
+    arch::aarch64::*,
+    ops::{Add, BitAnd, BitXor, Mul, Sub},
+};
+
+#[derive(Clone, Copy)]
+pub(super) struct Neon(uint64x2_t);
+
+impl Lanes for Neon {
+    const LANES: usize = 2;
+
+    #[inline(always)]
+    fn splat(value: u64) -> Self { Neon(unsafe { vdupq_n_u64(value) }) }
+
+    #[inline(always)]
+    fn load(values: &[u64; MAX_LANES]) -> Self { Neon(unsafe { vld1q_u64(values.as_ptr()) }) }
+
+    #[inline(always)]
+    fn store(self, out: &mut [u64; MAX_LANES]) {
+        unsafe { vst1q_u64(out.as_mut_ptr(), self.0) }
+    }
+
+    #[inline(always)]
+    fn shl<const N: i32>(self) -> Self { Neon(unsafe { vshlq_n_u64::<N>(self.0) }) }
+
+    #[inline(always)]
+    fn shr<const N: i32>(self) -> Self { Neon(unsafe { vshrq_n_u64::<N>(self.0) }) }
+
+    #[inline(always)]
+    fn fe_mul(f: &Fe<Self>, g: &Fe<Self>) -> Fe<Self> { unsafe { mul(f, g) } }
+
+    #[inline(always)]
+    fn fe_square(f: &Fe<Self>) -> Fe<Self> { unsafe { square(f) } }
+
+    #[inline(always)]
+    fn fe_square_n(f: &Fe<Self>, n: usize) -> Fe<Self> { unsafe { square_n(f, n) } }
+
+    #[inline(always)]
+    fn ladder(scalars: &[PrivateKey; MAX_LANES], u: &Fe<Self>) -> (Fe<Self>, Fe<Self>) {
+        unsafe { ladder(scalars, u) }
+    }
+}
+
+impl Add for Neon {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, other: Self) -> Self { Neon(unsafe { vaddq_u64(self.0, other.0) }) }
+}
+
+impl Sub for Neon {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, other: Self) -> Self { Neon(unsafe { vsubq_u64(self.0, other.0) }) }
+}
+
+impl Mul for Neon {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, other: Self) -> Self {
+        Neon(unsafe { vmull_u32(vmovn_u64(self.0), vmovn_u64(other.0)) })
+    }
+}
+
+impl BitAnd for Neon {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitand(self, other: Self) -> Self { Neon(unsafe { vandq_u64(self.0, other.0) }) }
+}
+
+impl BitXor for Neon {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitxor(self, other: Self) -> Self { Neon(unsafe { veorq_u64(self.0, other.0) }) }
+}
+
+#[target_feature(enable = "neon")]
+unsafe fn mul(f: &Fe<Neon>, g: &Fe<Neon>) -> Fe<Neon> { lanes::mul(f, g) }
+
+#[target_feature(enable = "neon")]
+unsafe fn square(f: &Fe<Neon>) -> Fe<Neon> { lanes::square(f) }
+
+#[target_feature(enable = "neon")]
+unsafe fn square_n(f: &Fe<Neon>, n: usize) -> Fe<Neon> { lanes::square_n(f, n) }
+
+#[target_feature(enable = "neon")]
+unsafe fn ladder(scalars: &[PrivateKey; MAX_LANES], u: &Fe<Neon>) -> (Fe<Neon>, Fe<Neon>) {
+    lanes::ladder(scalars, u)
+}
@@ -0,0 +1,388 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+// The public API of `crate::ec`. P-256 and P-384 use the group operations of
+// `suite_b::oprf`, and edwards25519 those of `curve25519`, whose scalars are
+// those of ristretto255, as both groups have the same order ℓ.
+
+use super::{
+    curve25519::ristretto255::{self, ExtPoint, UNREDUCED_SCALAR_LEN},
+    suite_b::oprf as nist,
+};
+use crate::{cpu, error, rand, zeroize};
+
+/// A group of elliptic curve points: the points of a curve, with addition.
+pub struct Group {
+    id: &'static str,
+    scalar_len: usize,
+    point_len: usize,
+    compressed_point_len: usize,
+
+    // `None` for edwards25519.
+    nist: Option<&'static nist::Curve>,
+}
+
+derive_debug_via_self!(Group, self.id);
+
+impl PartialEq for Group {
+    fn eq(&self, other: &Self) -> bool { self.id == other.id }
+}
+
+impl Eq for Group {}
+
+/// The points of the NIST P-256 curve. Scalars and points are encoded as in
+/// SEC 1: scalars are big-endian, and points are uncompressed or compressed.
+pub static P256: Group = Group {
+    id: "P-256",
+    scalar_len: 256 / 8,
+    point_len: 1 + (2 * 256 / 8),
+    compressed_point_len: 1 + 256 / 8,
+    nist: Some(&nist::P256),
+};
+
+/// The points of the NIST P-384 curve. Scalars and points are encoded as in
+/// SEC 1: scalars are big-endian, and points are uncompressed or compressed.
+pub static P384: Group = Group {
+    id: "P-384",
+    scalar_len: 384 / 8,
+    point_len: 1 + (2 * 384 / 8),
+    compressed_point_len: 1 + 384 / 8,
+    nist: Some(&nist::P384),
+};
+
+/// The points of edwards25519, the twisted Edwards curve that is birationally
+/// equivalent to Curve25519, as used by Ed25519. Scalars are little-endian
+/// and points are encoded as in RFC 8032; both are 32 bytes long.
+///
+/// The curve's order is 8ℓ, where ℓ is the order of the generator. Points of
+/// small order, and other points outside the subgroup of order ℓ, aren't
+/// rejected; protocols that need a prime-order group must check for them,
+/// e.g. by multiplying by the cofactor 8, or use ristretto255, e.g. through
+/// `crate::oprf`.
+pub static EDWARDS25519: Group = Group {
+    id: "edwards25519",
+    scalar_len: ristretto255::SCALAR_LEN,
+    point_len: ristretto255::ELEMENT_LEN,
+    compressed_point_len: ristretto255::ELEMENT_LEN,
+    nist: None,
+};
+
+impl Group {
+    /// The length of an encoded scalar.
+    #[inline]
+    pub fn scalar_len(&self) -> usize { self.scalar_len }
+
+    /// The length of the points that `Point::encode()` encodes.
+    #[inline]
+    pub fn point_len(&self) -> usize { self.point_len }
+
+    /// The length of the points that `Point::encode_compressed()` encodes.
+    #[inline]
+    pub fn compressed_point_len(&self) -> usize { self.compressed_point_len }
+}
+
+// The length of the longest encoded scalar, that of P-384.
+const MAX_SCALAR_LEN: usize = 384 / 8;
+
+// The length of the longest encoded point, that of an uncompressed P-384
+// point.
+const MAX_POINT_LEN: usize = 1 + (2 * MAX_SCALAR_LEN);
+
+/// An integer in [1, n), where n is the order of the group's generator.
+///
+/// Scalars are secret: they are overwritten with zeros when they are dropped,
+/// and are only used in constant time.
+pub struct Scalar {
+    group: &'static Group,
+    bytes: [u8; MAX_SCALAR_LEN],
+}
+
+impl Scalar {
+    /// Parses an encoded scalar, which must be in [1, n).
+    pub fn from_bytes(group: &'static Group, input: &[u8]) -> Result<Self, error::Unspecified> {
+        if input.len() != group.scalar_len {
+            return Err(error::Unspecified);
+        }
+        let mut r = Self {
+            group,
+            bytes: [0; MAX_SCALAR_LEN],
+        };
+        r.bytes[..input.len()].copy_from_slice(input);
+        let valid = match group.nist {
+            Some(curve) => curve.deserialize_scalar(input).is_ok(),
+            None => ristretto255::scalar_is_valid(&r.edwards25519()).expose(),
+        };
+        if !valid {
+            return Err(error::Unspecified);
+        }
+        Ok(r)
+    }
+
+    /// Reduces an encoded integer that may be up to twice as long as a scalar
+    /// modulo n, e.g. to derive a scalar from the output of a hash function.
+    /// The result must not be zero.
+    pub fn from_bytes_reduced(
+        group: &'static Group, input: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        match group.nist {
+            Some(curve) => {
+                let a = curve.scalar_reduced(input)?;
+                let mut r = Self {
+                    group,
+                    bytes: [0; MAX_SCALAR_LEN],
+                };
+                curve.serialize_scalar(&a, &mut r.bytes[..group.scalar_len]);
+                Ok(r)
+            },
+            None => {
+                if input.len() > UNREDUCED_SCALAR_LEN {
+                    return Err(error::Unspecified);
+                }
+                let mut unreduced = [0; UNREDUCED_SCALAR_LEN];
+                unreduced[..input.len()].copy_from_slice(input);
+                let mut a = ristretto255::scalar_reduced(&unreduced);
+                let r = Self::from_bytes(group, &a);
+                zeroize::zeroize(&mut unreduced);
+                zeroize::zeroize(&mut a);
+                r
+            },
+        }
+    }
+
+    /// Generates a random scalar.
+    pub fn generate(
+        group: &'static Group, rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        match group.nist {
+            Some(curve) => {
+                let a = curve.random_scalar(rng)?;
+                let mut r = Self {
+                    group,
+                    bytes: [0; MAX_SCALAR_LEN],
+                };
+                curve.serialize_scalar(&a, &mut r.bytes[..group.scalar_len]);
+                Ok(r)
+            },
+            None => {
+                // Reducing twice as many bits as ℓ has makes the bias
+                // negligible.
+                let mut bytes = [0; UNREDUCED_SCALAR_LEN];
+                rng.fill(&mut bytes)?;
+                let r = Self::from_bytes_reduced(group, &bytes);
+                zeroize::zeroize(&mut bytes);
+                r
+            },
+        }
+    }
+
+    /// The scalar's group.
+    #[inline]
+    pub fn group(&self) -> &'static Group { self.group }
+
+    fn edwards25519(&self) -> ristretto255::Scalar {
+        let mut r = [0; ristretto255::SCALAR_LEN];
+        r.copy_from_slice(&self.bytes[..ristretto255::SCALAR_LEN]);
+        r
+    }
+}
+
+impl AsRef<[u8]> for Scalar {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.group.scalar_len] }
+}
+
+impl Drop for Scalar {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.bytes) }
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Scalar {}
+
+derive_debug_via_self!(Scalar, self.group);
+
+/// A point on the group's curve other than the point at infinity.
+#[derive(Clone)]
+pub struct Point {
+    group: &'static Group,
+    repr: Repr,
+}
+
+#[derive(Clone)]
+enum Repr {
+    Nist(&'static nist::Curve, nist::Element),
+    Edwards25519(ExtPoint),
+}
+
+impl Point {
+    /// Parses and validates an encoded point.
+    ///
+    /// P-256 and P-384 points may be uncompressed or compressed. edwards25519
+    /// points must be encoded canonically.
+    pub fn from_bytes(group: &'static Group, input: &[u8]) -> Result<Self, error::Unspecified> {
+        cpu::cache_detected_features();
+        let repr = match group.nist {
+            Some(curve) => {
+                let p = if input.first() == Some(&4) {
+                    curve.deserialize_uncompressed(input)?
+                } else {
+                    curve.deserialize(input)?
+                };
+                Repr::Nist(curve, p)
+            },
+            None => {
+                if input.len() != group.point_len {
+                    return Err(error::Unspecified);
+                }
+                let mut encoded = [0; ristretto255::ELEMENT_LEN];
+                encoded.copy_from_slice(input);
+                Repr::Edwards25519(ExtPoint::from_encoded_point_vartime(&encoded)?)
+            },
+        };
+        let r = Self::new(group, repr)?;
+        if r.encode_compressed().as_ref() != input && r.encode().as_ref() != input {
+            return Err(error::Unspecified);
+        }
+        Ok(r)
+    }
+
+    /// Returns `a*G`, where G is the generator of `a`'s group.
+    pub fn mul_base(a: &Scalar) -> Result<Self, error::Unspecified> {
+        cpu::cache_detected_features();
+        let repr = match a.group.nist {
+            Some(curve) => {
+                let a = curve.deserialize_scalar(a.as_ref())?;
+                Repr::Nist(curve, curve.mul_base(&a)?)
+            },
+            None => Repr::Edwards25519(ExtPoint::mul_base(&a.edwards25519())),
+        };
+        Self::new(a.group, repr)
+    }
+
+    /// Returns `self + other`, failing if the points are in different groups
+    /// or if the sum is the point at infinity.
+    pub fn add(&self, other: &Self) -> Result<Self, error::Unspecified> {
+        if self.group != other.group {
+            return Err(error::Unspecified);
+        }
+        let repr = match (&self.repr, &other.repr) {
+            (Repr::Nist(curve, p), Repr::Nist(_, q)) => Repr::Nist(curve, curve.add(p, q)?),
+            (Repr::Edwards25519(p), Repr::Edwards25519(q)) => Repr::Edwards25519(p.add(q)),
+            _ => return Err(error::Unspecified),
+        };
+        Self::new(self.group, repr)
+    }
+
+    /// Returns `-self`.
+    pub fn neg(&self) -> Self {
+        let repr = match &self.repr {
+            Repr::Nist(curve, p) => Repr::Nist(curve, curve.neg(p)),
+            Repr::Edwards25519(p) => {
+                let mut r = p.clone();
+                // Despite its name, this is constant-time: it negates x and t.
+                r.invert_vartime();
+                Repr::Edwards25519(r)
+            },
+        };
+        Self {
+            group: self.group,
+            repr,
+        }
+    }
+
+    /// Returns `a*self`, failing if `a` is in a different group or if the
+    /// product is the point at infinity, which is only possible for
+    /// edwards25519 points outside the subgroup of order ℓ.
+    pub fn mul(&self, a: &Scalar) -> Result<Self, error::Unspecified> {
+        if self.group != a.group {
+            return Err(error::Unspecified);
+        }
+        let repr = match &self.repr {
+            Repr::Nist(curve, p) => {
+                let a = curve.deserialize_scalar(a.as_ref())?;
+                Repr::Nist(curve, curve.mul(&a, p)?)
+            },
+            Repr::Edwards25519(p) => Repr::Edwards25519(p.mul(&a.edwards25519())),
+        };
+        Self::new(self.group, repr)
+    }
+
+    /// The point's group.
+    #[inline]
+    pub fn group(&self) -> &'static Group { self.group }
+
+    /// Encodes the point: uncompressed for P-256 and P-384, and as in RFC
+    /// 8032 for edwards25519.
+    pub fn encode(&self) -> EncodedPoint {
+        let mut r = EncodedPoint::new(self.group.point_len);
+        match &self.repr {
+            Repr::Nist(curve, p) => curve.serialize_uncompressed(p, r.as_mut()),
+            Repr::Edwards25519(p) => r.as_mut().copy_from_slice(&p.clone().into_encoded_point()),
+        }
+        r
+    }
+
+    /// Encodes the point: compressed for P-256 and P-384, and as in RFC 8032
+    /// for edwards25519, like `encode()`.
+    pub fn encode_compressed(&self) -> EncodedPoint {
+        match &self.repr {
+            Repr::Nist(curve, p) => {
+                let mut r = EncodedPoint::new(self.group.compressed_point_len);
+                curve.serialize(p, r.as_mut());
+                r
+            },
+            Repr::Edwards25519(_) => self.encode(),
+        }
+    }
+
+    // The NIST operations already fail instead of returning the point at
+    // infinity, which edwards25519 represents like any other point.
+    fn new(group: &'static Group, repr: Repr) -> Result<Self, error::Unspecified> {
+        let r = Self { group, repr };
+        if let Repr::Edwards25519(_) = r.repr {
+            let mut identity = [0; ristretto255::ELEMENT_LEN];
+            identity[0] = 1;
+            if r.encode().as_ref() == &identity[..] {
+                return Err(error::Unspecified);
+            }
+        }
+        Ok(r)
+    }
+}
+
+derive_debug_via_self!(Point, self.encode());
+
+/// An encoded point.
+#[derive(Clone, Copy)]
+pub struct EncodedPoint {
+    bytes: [u8; MAX_POINT_LEN],
+    len: usize,
+}
+
+impl EncodedPoint {
+    fn new(len: usize) -> Self {
+        Self {
+            bytes: [0; MAX_POINT_LEN],
+            len,
+        }
+    }
+
+    fn as_mut(&mut self) -> &mut [u8] { &mut self.bytes[..self.len] }
+}
+
+impl AsRef<[u8]> for EncodedPoint {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+derive_debug_via_self!(EncodedPoint, self.as_ref());
@@ -0,0 +1,62 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Hashing to elliptic curves, as specified in RFC 9380.
+
+use crate::digest;
+
+/// `expand_message_xmd(msg, DST, out.len())` of RFC 9380 Section 5.3.1 with
+/// `digest_alg`, where `msg` and `dst` are the concatenations of their parts.
+/// The output and the DST must be shorter than 256 blocks and 256 bytes.
+pub fn expand_message_xmd(
+    digest_alg: &'static digest::Algorithm, msg: &[&[u8]], dst: &[&[u8]], out: &mut [u8],
+) {
+    let dst_len: usize = dst.iter().map(|part| part.len()).sum();
+    debug_assert!(dst_len <= 255);
+    let output_len = digest_alg.output_len;
+    debug_assert!(out.len() <= 255 * output_len);
+    let update_dst_prime = |ctx: &mut digest::Context| {
+        for part in dst {
+            ctx.update(part);
+        }
+        ctx.update(&[dst_len as u8]);
+    };
+
+    // b_0 = H(Z_pad || msg || I2OSP(len, 2) || I2OSP(0, 1) || DST_prime).
+    let mut ctx = digest::Context::new(digest_alg);
+    ctx.update(&[0; digest::MAX_BLOCK_LEN][..digest_alg.block_len]);
+    for part in msg {
+        ctx.update(part);
+    }
+    ctx.update(&(out.len() as u16).to_be_bytes());
+    ctx.update(&[0]);
+    update_dst_prime(&mut ctx);
+    let b_0 = ctx.finish();
+
+    // b_1 = H(b_0 || I2OSP(1, 1) || DST_prime) and
+    // b_i = H((b_0 xor b_(i - 1)) || I2OSP(i, 1) || DST_prime).
+    let mut b_i = [0; digest::MAX_OUTPUT_LEN];
+    let b_i = &mut b_i[..output_len];
+    for (i, chunk) in out.chunks_mut(output_len).enumerate() {
+        for (b, b_0) in b_i.iter_mut().zip(b_0.as_ref()) {
+            *b ^= b_0;
+        }
+        let mut ctx = digest::Context::new(digest_alg);
+        ctx.update(b_i);
+        ctx.update(&[(i + 1) as u8]);
+        update_dst_prime(&mut ctx);
+        b_i.copy_from_slice(ctx.finish().as_ref());
+        chunk.copy_from_slice(&b_i[..chunk.len()]);
+    }
+}
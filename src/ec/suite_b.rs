@@ -15,7 +15,7 @@
 //! Elliptic curve operations on P-256 & P-384.
 
 use self::ops::*;
-use crate::{arithmetic::montgomery::*, der, ec, error, pkcs8};
+use crate::{arithmetic::montgomery::*, ec, error, io::der, pkcs8};
 use untrusted;
 
 // NIST SP 800-56A Step 3: "If q is an odd prime p, verify that
@@ -224,9 +224,29 @@ pub fn key_pair_from_bytes(
     })
 }
 
+/// Encodes `uncompressed`, a public key encoded in uncompressed form, in
+/// compressed form, which is the X coordinate prefixed with 2 if the Y
+/// coordinate is even or 3 if it is odd.
+pub fn compress_public_key(
+    curve: &ec::Curve, uncompressed: &[u8], out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    if uncompressed.len() != curve.public_key_len
+        || uncompressed[0] != 4
+        || out.len() != curve.compressed_public_key_len
+    {
+        return Err(error::Unspecified);
+    }
+    let (x, y) = uncompressed[1..].split_at(curve.elem_and_scalar_len);
+    out[0] = 2 | (y[y.len() - 1] & 1);
+    out[1..].copy_from_slice(x);
+    Ok(())
+}
+
 pub mod curve;
 pub mod ecdh;
 pub mod ecdsa;
+pub mod oprf;
+pub mod pake;
 
 #[macro_use]
 mod ops;
@@ -19,8 +19,8 @@ macro_rules! suite_b_curve {
     ( $NAME:ident, $bits:expr, $private_key_ops:expr, $id:expr,
       $check_private_key_bytes:ident, $generate_private_key:ident,
       $public_from_private:ident) => {
-        /// Public keys are encoding in uncompressed form using the
-        /// Octet-String-to-Elliptic-Curve-Point algorithm in
+        /// Public keys are encoding in uncompressed or compressed form using
+        /// the Octet-String-to-Elliptic-Curve-Point algorithm in
         /// [SEC 1: Elliptic Curve Cryptography, Version 2.0]. Public keys are
         /// validated during key agreement according to
         /// [NIST Special Publication 800-56A, revision 2] and Appendix B.3 of
@@ -34,6 +34,7 @@ macro_rules! suite_b_curve {
         ///     https://github.com/briansmith/ring/blob/master/doc/ecdh.pdf
         pub static $NAME: ec::Curve = ec::Curve {
             public_key_len: 1 + (2 * (($bits + 7) / 8)),
+            compressed_public_key_len: 1 + (($bits + 7) / 8),
             elem_and_scalar_len: ($bits + 7) / 8,
             id: $id,
             check_private_key_bytes: $check_private_key_bytes,
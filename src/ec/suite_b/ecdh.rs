@@ -15,7 +15,7 @@
 //! ECDH key agreement using the P-256 and P-384 curves.
 
 use super::{ops::*, private_key::*, public_key::*};
-use crate::{agreement, ec, error};
+use crate::{agreement, ec, error, zeroize::Zeroizing};
 use untrusted;
 
 /// A key agreement algorithm.
@@ -26,8 +26,8 @@ macro_rules! ecdh {
         #[doc=$name_str]
         #[doc = "curve."]
         ///
-        /// Public keys are encoding in uncompressed form using the
-        /// Octet-String-to-Elliptic-Curve-Point algorithm in
+        /// Public keys are encoding in uncompressed or compressed form using
+        /// the Octet-String-to-Elliptic-Curve-Point algorithm in
         /// [SEC 1: Elliptic Curve Cryptography, Version 2.0]. Public keys are
         /// validated during key agreement according to
         /// [NIST Special Publication 800-56A, revision 2] and Appendix B.3 of
@@ -93,10 +93,10 @@ fn ecdh(
     // NIST SP 800-56Ar2 5.6.2.2.2.
     // NSA Guide Step 2.
     //
-    // `parse_uncompressed_point` verifies that the point is not at infinity
-    // and that it is on the curve, using the Partial Public-Key Validation
-    // Routine.
-    let peer_public_key = parse_uncompressed_point(public_key_ops, peer_public_key)?;
+    // `parse_point` verifies that the point is not at infinity and that it is
+    // on the curve, using the Partial Public-Key Validation Routine. The point
+    // may be compressed.
+    let peer_public_key = parse_point(public_key_ops, peer_public_key)?;
 
     // NIST SP 800-56Ar2 Step 1.
     // NSA Guide Step 3 (except point at infinity check).
@@ -106,33 +106,29 @@ fn ecdh(
     //
     // It is impossible for the result to be the point at infinity because our
     // private key is in the range [1, n) and the curve has prime order and
-    // `parse_uncompressed_point` verified that the peer public key is on the
-    // curve and not at infinity. However, since the standards require the
-    // check, we do it using `assert!`.
+    // `parse_point` verified that the peer public key is on the curve and not
+    // at infinity. However, since the standards require the check, we do it
+    // using `assert!`.
     //
     // NIST SP 800-56Ar2 defines "Destroy" thusly: "In this Recommendation, to
     // destroy is an action applied to a key or a piece of secret data. After
     // a key or a piece of secret data is destroyed, no information about its
-    // value can be recovered." We interpret "destroy" somewhat liberally: we
-    // assume that since we throw away the values to be destroyed, no
-    // information about their values can be recovered. This doesn't meet the
-    // NSA guide's explicit requirement to "zeroize" them though.
-    let my_private_key = private_key_as_scalar(private_key_ops, my_private_key);
+    // value can be recovered." The copy of the private key is zeroized when
+    // it is dropped, as the NSA guide requires. The product is only as secret
+    // as the shared secret that the caller is given.
+    let my_private_key = Zeroizing::new(private_key_as_scalar(private_key_ops, my_private_key));
     let product = private_key_ops.point_mul(&my_private_key, &peer_public_key);
 
     // NIST SP 800-56Ar2 Steps 2, 3, 4, and 5.
     // NSA Guide Steps 3 (point at infinity check) and 4.
     //
-    // Again, we have a pretty liberal interpretation of the NIST's spec's
-    // "Destroy" that doesn't meet the NSA requirement to "zeroize."
     // `big_endian_affine_from_jacobian` verifies that the result is not at
     // infinity and also does an extra check to verify that the point is on
     // the curve.
     big_endian_affine_from_jacobian(private_key_ops, Some(out), None, &product)
 
-    // NSA Guide Step 5 & 6 are deferred to the caller. Again, we have a
-    // pretty liberal interpretation of the NIST's spec's "Destroy" that
-    // doesn't meet the NSA requirement to "zeroize."
+    // NSA Guide Step 5 & 6 are deferred to the caller, which zeroizes the
+    // shared secret; see `agreement::agree_ephemeral()`.
 }
 
 #[cfg(test)]
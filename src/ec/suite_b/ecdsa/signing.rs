@@ -17,16 +17,27 @@
 use super::digest_scalar::digest_scalar;
 use crate::{
     arithmetic::montgomery::*,
-    der, digest,
+    digest,
     ec::{
         self,
         suite_b::{ops::*, private_key},
     },
-    error, limb, pkcs8, private, rand, signature, signature_impl,
+    error,
+    io::der,
+    limb,
+    locked::Locked,
+    pkcs8, private, rand, signature, signature_impl, spki,
+    zeroize::{self, Zeroizing},
 };
 use core;
+
+#[cfg(feature = "alloc")]
+use crate::pem;
 use untrusted;
 
+#[cfg(all(feature = "alloc", feature = "zeroize"))]
+use alloc::vec::Vec;
+
 /// An ECDSA signing algorithm.
 pub struct Algorithm {
     curve: &'static ec::Curve,
@@ -57,7 +68,7 @@ impl Eq for Algorithm {}
 
 impl private::Sealed for Algorithm {}
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 impl signature::SigningAlgorithm for Algorithm {
     fn from_pkcs8(
         &'static self, input: untrusted::Input,
@@ -67,8 +78,11 @@ impl signature::SigningAlgorithm for Algorithm {
 }
 
 /// An ECDSA key pair, used for signing.
+///
+/// A single `ECDSAKeyPair` may be shared across multiple threads safely.
 pub struct Key {
-    d: Scalar<R>,
+    d: Locked<Scalar<R>>,
+    public_key: spki::PublicKey,
     alg: &'static Algorithm,
 }
 
@@ -117,6 +131,34 @@ impl Key {
         Ok(Self::new(alg, key_pair))
     }
 
+    /// Like `ECDSAKeyPair::from_pkcs8()`, but the PKCS#8 document is
+    /// PEM-encoded with the label `PRIVATE KEY`.
+    #[cfg(feature = "alloc")]
+    pub fn from_pkcs8_pem(
+        alg: &'static Algorithm, input: &str,
+    ) -> Result<Self, error::KeyRejected> {
+        pem::decode_private_key(input, pem::PRIVATE_KEY, |der| Self::from_pkcs8(alg, der))
+    }
+
+    /// Like `ECDSAKeyPair::from_pkcs8()`, but takes ownership of the PKCS#8
+    /// document so that it is overwritten when it is no longer needed.
+    #[cfg(all(feature = "alloc", feature = "zeroize"))]
+    pub fn from_pkcs8_zeroizing(
+        alg: &'static Algorithm, input: ::zeroize::Zeroizing<Vec<u8>>,
+    ) -> Result<Self, error::KeyRejected> {
+        Self::from_pkcs8(alg, untrusted::Input::from(&input[..]))
+    }
+
+    /// Like `ECDSAKeyPair::from_pkcs8()`, but the PKCS#8 document is
+    /// encrypted with `password` as described in `pkcs8::decrypt()`.
+    #[cfg(feature = "alloc")]
+    pub fn from_encrypted_pkcs8(
+        alg: &'static Algorithm, input: untrusted::Input, password: &[u8],
+    ) -> Result<Self, error::KeyRejected> {
+        let der = pkcs8::decrypt(input, password)?;
+        Self::from_pkcs8(alg, untrusted::Input::from(&der))
+    }
+
     /// Constructs an ECDSA key pair directly from the big-endian-encoded
     /// private key and public key bytes.
     ///
@@ -136,8 +178,30 @@ impl Key {
             .private_scalar_ops
             .scalar_ops
             .scalar_product(&d, &alg.private_scalar_ops.oneRR_mod_n);
+        let public_key = spki::PublicKey::from_ec_public_key(
+            alg.curve,
+            &key_pair.public_key[..alg.curve.public_key_len],
+        );
+
+        Self {
+            d: Locked::new(d),
+            public_key,
+            alg,
+        }
+    }
 
-        Self { d, alg }
+    /// Returns the public key, which is encoded as an uncompressed point.
+    pub fn public_key(&self) -> spki::PublicKey { self.public_key.clone() }
+
+    /// Returns the public key encoded as a compressed point, which
+    /// `signature::verify()` also accepts.
+    pub fn public_key_compressed(&self) -> spki::PublicKey {
+        let curve = self.alg.curve;
+        let mut compressed = [0; ec::PUBLIC_KEY_MAX_LEN];
+        let compressed = &mut compressed[..curve.compressed_public_key_len];
+        // `self.public_key` is a valid uncompressed point, so this can't fail.
+        let _ = ec::suite_b::compress_public_key(curve, self.public_key.as_ref(), compressed);
+        spki::PublicKey::from_ec_public_key(curve, compressed)
     }
 
     /// Deprecated.
@@ -149,6 +213,23 @@ impl Key {
         self.sign_(&h, rng)
     }
 
+    /// Writes the signature of the message `msg` into the beginning of `out`,
+    /// returning the length of the signature.
+    ///
+    /// The signature is at most `ECDSA_MAX_SIGNATURE_LEN` bytes long;
+    /// signatures in the fixed format are exactly twice the length of a
+    /// scalar. Fails if `out` is too short to hold the signature.
+    pub fn sign_into(
+        &self, rng: &rand::SecureRandom, msg: untrusted::Input, out: &mut [u8],
+    ) -> Result<usize, error::Unspecified> {
+        let signature = self.sign(msg, rng)?;
+        let signature = signature.as_ref();
+        out.get_mut(..signature.len())
+            .ok_or(error::Unspecified)?
+            .copy_from_slice(signature);
+        Ok(signature.len())
+    }
+
     /// Returns the signature of message digest `h` using a "random" nonce
     /// generated by `rng`.
     fn sign_(
@@ -186,8 +267,8 @@ impl Key {
         for _ in 0..100 {
             // XXX: iteration conut?
             // Step 1.
-            let k = private_key::random_scalar(self.alg.private_key_ops, rng)?;
-            let k_inv = scalar_ops.scalar_inv_to_mont(&k);
+            let k = Zeroizing::new(private_key::random_scalar(self.alg.private_key_ops, rng)?);
+            let k_inv = Zeroizing::new(scalar_ops.scalar_inv_to_mont(&k));
 
             // Step 2.
             let r = private_key_ops.point_mul_base(&k);
@@ -209,8 +290,8 @@ impl Key {
 
             // Step 6.
             let s = {
-                let dr = scalar_ops.scalar_product(&self.d, &r);
-                let e_plus_dr = scalar_sum(cops, &e, &dr);
+                let dr = Zeroizing::new(scalar_ops.scalar_product(&self.d, &r));
+                let e_plus_dr = Zeroizing::new(scalar_sum(cops, &e, &dr));
                 scalar_ops.scalar_product(&k_inv, &e_plus_dr)
             };
             if cops.is_zero(&s) {
@@ -227,7 +308,14 @@ impl Key {
     }
 }
 
-#[cfg(feature = "use_heap")]
+impl Drop for Key {
+    fn drop(&mut self) { zeroize::zeroize_value(&mut *self.d) }
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Key {}
+
+#[cfg(feature = "alloc")]
 impl signature::KeyPairImpl for Key {
     /// Returns the signature of the message `msg`.
     fn sign(
@@ -358,14 +446,14 @@ pub static ECDSA_P384_SHA384_ASN1_SIGNING: Algorithm = Algorithm {
     id: AlgorithmID::ECDSA_P384_SHA384_ASN1_SIGNING,
 };
 
-static EC_PUBLIC_KEY_P256_PKCS8_V1_TEMPLATE: pkcs8::Template = pkcs8::Template {
+pub(crate) static EC_PUBLIC_KEY_P256_PKCS8_V1_TEMPLATE: pkcs8::Template = pkcs8::Template {
     bytes: include_bytes!("ecPublicKey_p256_pkcs8_v1_template.der"),
     alg_id_range: core::ops::Range { start: 8, end: 27 },
     curve_id_index: 9,
     private_key_index: 0x24,
 };
 
-static EC_PUBLIC_KEY_P384_PKCS8_V1_TEMPLATE: pkcs8::Template = pkcs8::Template {
+pub(crate) static EC_PUBLIC_KEY_P384_PKCS8_V1_TEMPLATE: pkcs8::Template = pkcs8::Template {
     bytes: include_bytes!("ecPublicKey_p384_pkcs8_v1_template.der"),
     alg_id_range: core::ops::Range { start: 8, end: 24 },
     curve_id_index: 9,
@@ -460,6 +548,11 @@ mod tests {
 
                 assert_eq!(actual_result.as_ref(), &expected_result[..]);
 
+                let mut out = [0u8; signature::ECDSA_MAX_SIGNATURE_LEN];
+                let len = private_key.sign_into(&rng, msg, &mut out).unwrap();
+                assert_eq!(&out[..len], &expected_result[..]);
+                assert!(private_key.sign_into(&rng, msg, &mut out[..(len - 1)]).is_err());
+
                 Ok(())
             },
         );
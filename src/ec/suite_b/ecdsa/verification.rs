@@ -17,9 +17,12 @@
 use super::digest_scalar::digest_scalar;
 use crate::{
     arithmetic::montgomery::*,
-    der, digest,
+    digest,
     ec::suite_b::{ops::*, public_key::*, verify_jacobian_point_is_on_the_curve},
-    error, limb, private, signature,
+    error,
+    io::der,
+    limb::{self, Limb, LIMB_BITS},
+    private, signature,
 };
 use untrusted;
 
@@ -52,21 +55,55 @@ impl signature::VerificationAlgorithm for Algorithm {
     fn verify(
         &self, public_key: untrusted::Input, msg: untrusted::Input, signature: untrusted::Input,
     ) -> Result<(), error::Unspecified> {
-        let e = {
-            // NSA Guide Step 2: "Use the selected hash function to compute H =
-            // Hash(M)."
-            let h = digest::digest(self.digest_alg, msg.as_slice_less_safe());
-
-            // NSA Guide Step 3: "Convert the bit string H to an integer e as
-            // described in Appendix B.2."
-            digest_scalar(self.ops.scalar_ops, &h)
-        };
-
+        let e = self.digest_msg(msg);
         self.verify_digest(public_key, e, signature)
     }
 }
 
 impl Algorithm {
+    /// Verifies the signature of each `(msg, signature)` in `msgs_and_sigs`
+    /// with `public_key`, which is parsed only once; see
+    /// `signature::verify_batch_same_key()`.
+    pub(crate) fn verify_batch_same_key(
+        &self, public_key: untrusted::Input,
+        msgs_and_sigs: &[(untrusted::Input, untrusted::Input)],
+    ) -> Result<(), error::Unspecified> {
+        // See `verify_digest` regarding the validation of `public_key`.
+        let peer_pub_key = parse_point(self.ops.public_key_ops, public_key)?;
+        let table = PublicKeyTable::new(self.ops.public_key_ops.common, &peer_pub_key);
+        for &(msg, signature) in msgs_and_sigs {
+            let e = self.digest_msg(msg);
+            self.verify_digest_with(e, signature, |u2| table.mul(u2))?;
+        }
+        Ok(())
+    }
+
+    fn digest_msg(&self, msg: untrusted::Input) -> Scalar {
+        // NSA Guide Step 2: "Use the selected hash function to compute H =
+        // Hash(M)."
+        let h = digest::digest(self.digest_alg, msg.as_slice_less_safe());
+
+        // NSA Guide Step 3: "Convert the bit string H to an integer e as
+        // described in Appendix B.2."
+        digest_scalar(self.ops.scalar_ops, &h)
+    }
+
+    /// Parses `signature` into `(r, s)`.
+    pub(crate) fn parse_signature(
+        &self, signature: untrusted::Input,
+    ) -> Result<(Scalar, Scalar), error::Unspecified> {
+        let public_key_ops = self.ops.public_key_ops;
+        let (r, s) = signature.read_all(error::Unspecified, |input| {
+            (self.split_rs)(self.ops.scalar_ops, input)
+        })?;
+
+        // NSA Guide Step 1: "If r and s are not both integers in the interval
+        // [1, n − 1], output INVALID."
+        let r = scalar_parse_big_endian_variable(public_key_ops.common, limb::AllowZero::No, r)?;
+        let s = scalar_parse_big_endian_variable(public_key_ops.common, limb::AllowZero::No, s)?;
+        Ok((r, s))
+    }
+
     /// This is intentionally not public.
     fn verify_digest(
         &self, public_key: untrusted::Input, e: Scalar, signature: untrusted::Input,
@@ -74,7 +111,6 @@ impl Algorithm {
         // NSA Suite B Implementer's Guide to ECDSA Section 3.4.2.
 
         let public_key_ops = self.ops.public_key_ops;
-        let scalar_ops = self.ops.scalar_ops;
 
         // NSA Guide Prerequisites:
         //
@@ -92,17 +128,23 @@ impl Algorithm {
         // Prerequisites #1 and #4 are outside the scope of what this function
         // can do. Prerequisite #2 is handled implicitly as the domain
         // parameters are hard-coded into the source. Prerequisite #3 is
-        // handled by `parse_uncompressed_point`.
-        let peer_pub_key = parse_uncompressed_point(public_key_ops, public_key)?;
+        // handled by `parse_point`.
+        let peer_pub_key = parse_point(public_key_ops, public_key)?;
 
-        let (r, s) = signature.read_all(error::Unspecified, |input| {
-            (self.split_rs)(scalar_ops, input)
-        })?;
+        self.verify_digest_with(e, signature, |u2| {
+            self.ops.private_key_ops.point_mul(u2, &peer_pub_key)
+        })
+    }
 
-        // NSA Guide Step 1: "If r and s are not both integers in the interval
-        // [1, n − 1], output INVALID."
-        let r = scalar_parse_big_endian_variable(public_key_ops.common, limb::AllowZero::No, r)?;
-        let s = scalar_parse_big_endian_variable(public_key_ops.common, limb::AllowZero::No, s)?;
+    /// Like `verify_digest`, with the public key given as `public_key_mul`,
+    /// which multiplies it by a scalar.
+    fn verify_digest_with(
+        &self, e: Scalar, signature: untrusted::Input, public_key_mul: impl Fn(&Scalar) -> Point,
+    ) -> Result<(), error::Unspecified> {
+        let public_key_ops = self.ops.public_key_ops;
+        let scalar_ops = self.ops.scalar_ops;
+
+        let (r, s) = self.parse_signature(signature)?;
 
         // NSA Guide Step 4: "Compute w = s**−1 mod n, using the routine in
         // Appendix B.1."
@@ -116,7 +158,7 @@ impl Algorithm {
         // NSA Guide Step 6: "Compute the elliptic curve point
         // R = (xR, yR) = u1*G + u2*Q, using EC scalar multiplication and EC
         // addition. If R is equal to the point at infinity, output INVALID."
-        let product = twin_mul(self.ops.private_key_ops, &u1, &u2, &peer_pub_key);
+        let product = twin_mul(self.ops.private_key_ops, &u1, &u2, public_key_mul);
 
         // Verify that the point we computed is on the curve; see
         // `verify_affine_point_is_on_the_curve_scaled` for details on why. It
@@ -179,14 +221,78 @@ fn split_rs_asn1<'a>(
 }
 
 fn twin_mul(
-    ops: &PrivateKeyOps, g_scalar: &Scalar, p_scalar: &Scalar, p_xy: &(Elem<R>, Elem<R>),
+    ops: &PrivateKeyOps, g_scalar: &Scalar, p_scalar: &Scalar, p_mul: impl Fn(&Scalar) -> Point,
 ) -> Point {
     // XXX: Inefficient. TODO: implement interleaved wNAF multiplication.
     let scaled_g = ops.point_mul_base(g_scalar);
-    let scaled_p = ops.point_mul(p_scalar, p_xy);
+    let scaled_p = p_mul(p_scalar);
     ops.common.point_sum(&scaled_g, &scaled_p)
 }
 
+// The number of bits of a scalar that `PublicKeyTable::mul` handles at once.
+const WINDOW_BITS: usize = 5;
+
+/// The multiples 1*Q, 2*Q, ..., (2**WINDOW_BITS - 1)*Q of a public key Q.
+///
+/// Unlike `PrivateKeyOps::point_mul`, which has to recompute its table for
+/// every multiplication, `mul` isn't constant-time. That's fine because the
+/// scalars it is used with in ECDSA verification are public.
+struct PublicKeyTable {
+    ops: &'static CommonOps,
+    multiples: [Point; (1 << WINDOW_BITS) - 1],
+}
+
+impl PublicKeyTable {
+    fn new(ops: &'static CommonOps, q: &(Elem<R>, Elem<R>)) -> Self {
+        let mut multiples = [Point::new_at_infinity(); (1 << WINDOW_BITS) - 1];
+        multiples[0] = ops.point_from_affine(q);
+        for i in 1..multiples.len() {
+            // `multiples[i]` is (i + 1)*Q.
+            multiples[i] = if i % 2 == 1 {
+                ops.point_double(&multiples[i / 2])
+            } else {
+                ops.point_sum(&multiples[i - 1], &multiples[0])
+            };
+        }
+        Self { ops, multiples }
+    }
+
+    fn mul(&self, a: &Scalar) -> Point {
+        let limbs = &a.limbs[..self.ops.num_limbs];
+        let num_windows = (limbs.len() * LIMB_BITS + WINDOW_BITS - 1) / WINDOW_BITS;
+        let mut acc: Option<Point> = None;
+        for i in (0..num_windows).rev() {
+            if let Some(acc) = &mut acc {
+                for _ in 0..WINDOW_BITS {
+                    *acc = self.ops.point_double(acc);
+                }
+            }
+            let window = window(limbs, i * WINDOW_BITS);
+            if window != 0 {
+                let multiple = &self.multiples[window - 1];
+                acc = Some(match &acc {
+                    Some(acc) => self.ops.point_sum(acc, multiple),
+                    None => *multiple,
+                });
+            }
+        }
+        acc.unwrap_or_else(Point::new_at_infinity)
+    }
+}
+
+/// The `WINDOW_BITS` bits of `limbs` starting at bit `bit`.
+fn window(limbs: &[Limb], bit: usize) -> usize {
+    let i = bit / LIMB_BITS;
+    let shift = bit % LIMB_BITS;
+    let mut window = limbs[i] >> shift;
+    if shift + WINDOW_BITS > LIMB_BITS {
+        if let Some(next) = limbs.get(i + 1) {
+            window |= next << (LIMB_BITS - shift);
+        }
+    }
+    (window as usize) & ((1 << WINDOW_BITS) - 1)
+}
+
 /// Verification of fixed-length (PKCS#11 style) ECDSA signatures using the
 /// P-256 curve and SHA-256.
 ///
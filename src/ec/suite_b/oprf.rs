@@ -0,0 +1,384 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The P-256 and P-384 group operations of OPRF(P-256, SHA-256) and
+//! OPRF(P-384, SHA-384), see `crate::oprf`, which `crate::ec` also uses.
+//!
+//! Elements are encoded in compressed form, and inputs are hashed to the
+//! curve with the `P256_XMD:SHA-256_SSWU_RO_` and `P384_XMD:SHA-384_SSWU_RO_`
+//! suites of RFC 9380.
+
+use super::{ops::*, pake, private_key::*};
+use crate::{
+    arithmetic::montgomery::*,
+    constant_time::{self, Choice},
+    digest,
+    ec::hash_to_curve::expand_message_xmd,
+    error,
+    limb::{self, Limb, LimbMask},
+    rand,
+};
+use core::marker::PhantomData;
+use untrusted;
+
+pub use super::ops::Scalar;
+
+/// A point other than the point at infinity, in affine coordinates.
+pub type Element = (Elem<R>, Elem<R>);
+
+/// The length of an encoded P-256 element.
+pub const P256_ELEMENT_LEN: usize = 1 + 256 / 8;
+
+/// The length of the longest encoded scalar, that of P-384.
+pub const MAX_SCALAR_LEN: usize = 384 / 8;
+
+/// The length of the longest encoded element, that of P-384.
+pub const MAX_ELEMENT_LEN: usize = 1 + MAX_SCALAR_LEN;
+
+// The length `L` of the input of `hash_to_field` and `HashToScalar`.
+const MAX_L: usize = 72;
+
+pub struct Curve {
+    private_key_ops: &'static PrivateKeyOps,
+    scalar_ops: &'static ScalarOps,
+    private_scalar_ops: &'static PrivateScalarOps,
+    pake: &'static pake::Curve,
+    elem_sqrt_candidate: fn(&Elem<R>) -> Elem<R>,
+    hash: &'static digest::Algorithm,
+    l: usize,
+
+    // -Z of the simplified SWU map.
+    minus_z: Limb,
+}
+
+pub static P256: Curve = Curve {
+    private_key_ops: &p256::PRIVATE_KEY_OPS,
+    scalar_ops: &p256::SCALAR_OPS,
+    private_scalar_ops: &p256::PRIVATE_SCALAR_OPS,
+    pake: &pake::P256,
+    elem_sqrt_candidate: p256::elem_sqrt_candidate,
+    hash: &digest::SHA256,
+    l: 48,
+    minus_z: 10,
+};
+
+pub static P384: Curve = Curve {
+    private_key_ops: &p384::PRIVATE_KEY_OPS,
+    scalar_ops: &p384::SCALAR_OPS,
+    private_scalar_ops: &p384::PRIVATE_SCALAR_OPS,
+    pake: &pake::P384,
+    elem_sqrt_candidate: p384::elem_sqrt_candidate,
+    hash: &digest::SHA384,
+    l: 72,
+    minus_z: 12,
+};
+
+impl Curve {
+    /// The length of an encoded scalar.
+    #[inline]
+    pub fn scalar_len(&self) -> usize { self.scalar_ops.scalar_bytes_len() }
+
+    /// The length of a compressed element.
+    #[inline]
+    pub fn element_len(&self) -> usize { 1 + self.scalar_len() }
+
+    /// `hash_to_curve(msg)` with the domain separation tag `dst`, the
+    /// concatenation of its parts. This fails only if the result is the
+    /// point at infinity.
+    pub fn hash_to_curve(&self, msg: &[u8], dst: &[&[u8]]) -> Result<Element, error::Unspecified> {
+        let mut uniform_bytes = [0; 2 * MAX_L];
+        let uniform_bytes = &mut uniform_bytes[..(2 * self.l)];
+        expand_message_xmd(self.hash, &[msg], dst, uniform_bytes);
+        let (u0, u1) = uniform_bytes.split_at(self.l);
+        let q0 = self.map_to_curve(&self.hash_to_field(u0)?);
+        let q1 = self.map_to_curve(&self.hash_to_field(u1)?);
+        self.add(&q0, &q1)
+    }
+
+    /// `HashToScalar(msg)` of RFC 9497 with the domain separation tag `dst`;
+    /// `msg` and `dst` are the concatenations of their parts. This fails only
+    /// if the result is zero.
+    pub fn hash_to_scalar(
+        &self, msg: &[&[u8]], dst: &[&[u8]],
+    ) -> Result<Scalar, error::Unspecified> {
+        let mut uniform_bytes = [0; MAX_L];
+        let uniform_bytes = &mut uniform_bytes[..self.l];
+        expand_message_xmd(self.hash, msg, dst, uniform_bytes);
+        self.pake.scalar_reduced(uniform_bytes)
+    }
+
+    pub fn random_scalar(&self, rng: &rand::SecureRandom) -> Result<Scalar, error::Unspecified> {
+        random_scalar(self.private_key_ops, rng)
+    }
+
+    /// Returns 1/`a` (mod n). `a` must not be zero.
+    pub fn scalar_inverse(&self, a: &Scalar) -> Scalar {
+        let mut one = Scalar::zero();
+        one.limbs[0] = 1;
+        let inverse: Scalar<R> = self.scalar_ops.scalar_inv_to_mont(a);
+        self.scalar_ops.scalar_product(&inverse, &one)
+    }
+
+    /// Returns `r - c*k` (mod n).
+    pub fn scalar_mul_sub(&self, r: &Scalar, c: &Scalar, k: &Scalar) -> Scalar {
+        let ops = self.scalar_ops;
+        let c: Scalar<R> = ops.scalar_product(c, &self.private_scalar_ops.oneRR_mod_n);
+        scalar_difference(ops.common, r, &ops.scalar_product(&c, k))
+    }
+
+    /// Parses a big-endian scalar, which must be in [1, n).
+    pub fn deserialize_scalar(&self, input: &[u8]) -> Result<Scalar, error::Unspecified> {
+        let ops = self.scalar_ops.common;
+        scalar_parse_big_endian_fixed_consttime(ops, untrusted::Input::from(input))
+    }
+
+    pub fn serialize_scalar(&self, a: &Scalar, out: &mut [u8]) { self.pake.scalar_to_bytes(a, out) }
+
+    /// Parses a big-endian integer that may be up to twice as long as a
+    /// scalar and reduces it modulo n. The result must not be zero.
+    #[cfg(feature = "unstable_ec")]
+    pub fn scalar_reduced(&self, input: &[u8]) -> Result<Scalar, error::Unspecified> {
+        self.pake.scalar_reduced(input)
+    }
+
+    /// Returns `p + q`, failing if it's the point at infinity.
+    pub fn add(&self, p: &Element, q: &Element) -> Result<Element, error::Unspecified> {
+        let ops = self.private_key_ops.common;
+        self.to_affine(&ops.point_sum(&ops.point_from_affine(p), &ops.point_from_affine(q)))
+    }
+
+    /// Returns `a*p`, failing if it's the point at infinity.
+    pub fn mul(&self, a: &Scalar, p: &Element) -> Result<Element, error::Unspecified> {
+        self.to_affine(&self.private_key_ops.point_mul(a, p))
+    }
+
+    /// Returns `a*G`, failing if it's the point at infinity.
+    pub fn mul_base(&self, a: &Scalar) -> Result<Element, error::Unspecified> {
+        self.to_affine(&self.private_key_ops.point_mul_base(a))
+    }
+
+    /// Writes the compressed form of `p` to `out`, which must be
+    /// `element_len()` bytes long.
+    pub fn serialize(&self, p: &Element, out: &mut [u8]) {
+        let ops = self.private_key_ops.common;
+        let x = ops.elem_unencoded(&p.0);
+        let y = ops.elem_unencoded(&p.1);
+        out[0] = 2 | (y.limbs[0] & 1) as u8;
+        limb::big_endian_from_limbs(&x.limbs[..ops.num_limbs], &mut out[1..]);
+    }
+
+    /// Returns `-p`.
+    #[cfg(feature = "unstable_ec")]
+    pub fn neg(&self, p: &Element) -> Element { (p.0, self.elem_negated(&p.1)) }
+
+    /// Writes the uncompressed form of `p` to `out`, which must be
+    /// `1 + 2 * scalar_len()` bytes long.
+    #[cfg(feature = "unstable_ec")]
+    pub fn serialize_uncompressed(&self, p: &Element, out: &mut [u8]) {
+        let ops = self.private_key_ops.common;
+        out[0] = 4;
+        let (x_out, y_out) = out[1..].split_at_mut(self.scalar_len());
+        limb::big_endian_from_limbs(&ops.elem_unencoded(&p.0).limbs[..ops.num_limbs], x_out);
+        limb::big_endian_from_limbs(&ops.elem_unencoded(&p.1).limbs[..ops.num_limbs], y_out);
+    }
+
+    /// Parses and validates an uncompressed element.
+    #[cfg(feature = "unstable_ec")]
+    pub fn deserialize_uncompressed(&self, input: &[u8]) -> Result<Element, error::Unspecified> {
+        self.pake.parse(input)
+    }
+
+    /// Parses and validates a compressed element.
+    pub fn deserialize(&self, input: &[u8]) -> Result<Element, error::Unspecified> {
+        let ops = self.private_key_ops.common;
+        if input.len() != self.element_len() || (input[0] != 2 && input[0] != 3) {
+            return Err(error::Unspecified);
+        }
+        let x = elem_parse_big_endian_fixed_consttime(ops, untrusted::Input::from(&input[1..]))?;
+        let x = ops.elem_to_mont(&x);
+        let y_squared = self.curve_rhs(&x);
+        let y = (self.elem_sqrt_candidate)(&y_squared);
+        if !ops.elems_are_equal(&ops.elem_squared(&y), &y_squared) {
+            return Err(error::Unspecified);
+        }
+        // There are no points with y == 0, so the parity determines y.
+        let y = if self.sgn0(&y).expose() != (input[0] == 3) {
+            self.elem_negated(&y)
+        } else {
+            y
+        };
+        Ok((x, y))
+    }
+
+    // Reduces the `L`-byte big-endian integer `input` modulo q.
+    fn hash_to_field(&self, input: &[u8]) -> Result<Elem<R>, error::Unspecified> {
+        let ops = self.private_key_ops.common;
+        let (hi, lo) = input.split_at(self.l - self.scalar_len());
+
+        // Both halves are less than 2**(8 * scalar_len()) < 2*q, so parsing
+        // reduces them completely.
+        let parse = |bytes| {
+            elem_parse_big_endian_partially_reduced_consttime(ops, untrusted::Input::from(bytes))
+        };
+        // R == 2**(8 * scalar_len()), so the Montgomery encoding of `hi` is
+        // `hi` * R (mod q) as an unencoded value.
+        let hi_shifted = ops.elem_to_mont(&parse(hi)?);
+        let mut r: Elem<Unencoded> = Elem {
+            limbs: hi_shifted.limbs,
+            m: PhantomData,
+            encoding: PhantomData,
+        };
+        ops.elem_add(&mut r, &parse(lo)?);
+        Ok(ops.elem_to_mont(&r))
+    }
+
+    // The simplified SWU map of RFC 9380 Section 6.6.2, computed without
+    // branching on `u`, which is derived from a secret, e.g. a password.
+    fn map_to_curve(&self, u: &Elem<R>) -> Element {
+        let ops = self.private_key_ops.common;
+        let z = self.elem_negated(&self.elem_from_limb(self.minus_z));
+
+        // tv1 = 1 / (Z**2 * u**4 + Z * u**2), or 0 if the denominator is 0.
+        let z_u2 = ops.elem_product(&z, &ops.elem_squared(u));
+        let mut tv1 = ops.elem_squared(&z_u2);
+        ops.elem_add(&mut tv1, &z_u2);
+        let tv1_is_zero = Choice::from(ops.is_zero(&tv1));
+        let tv1 = self.elem_inverse(&tv1);
+
+        // x1 = (-B / A) * (1 + tv1), or B / (Z * A) if tv1 is 0. Since A = -3,
+        // these are (B / 3) * (1 + tv1) and B / (-3 * Z).
+        let mut one_plus_tv1 = self.elem_from_limb(1);
+        ops.elem_add(&mut one_plus_tv1, &tv1);
+        let x1 = elem_select(
+            tv1_is_zero,
+            &ops.elem_product(&ops.b, &self.elem_inverse(&self.elem_from_limb(3 * self.minus_z))),
+            &ops.elem_product(
+                &ops.elem_product(&ops.b, &self.elem_inverse(&self.elem_from_limb(3))),
+                &one_plus_tv1,
+            ),
+        );
+        let x2 = ops.elem_product(&z_u2, &x1);
+
+        // Exactly one of gx1 and gx2 is a square.
+        let (gx1, gx2) = (self.curve_rhs(&x1), self.curve_rhs(&x2));
+        let y1 = (self.elem_sqrt_candidate)(&gx1);
+        let y2 = (self.elem_sqrt_candidate)(&gx2);
+        let gx1_is_square = self.elems_equal(&ops.elem_squared(&y1), &gx1);
+        let x = elem_select(gx1_is_square, &x1, &x2);
+        let y = elem_select(gx1_is_square, &y1, &y2);
+
+        // sgn0(y) must equal sgn0(u).
+        let y = elem_select(self.sgn0(u) ^ self.sgn0(&y), &self.elem_negated(&y), &y);
+        (x, y)
+    }
+
+    fn to_affine(&self, p: &Point) -> Result<Element, error::Unspecified> {
+        let ops = self.private_key_ops;
+        ops.common.elem_verify_is_not_zero(&ops.common.point_z(p))?;
+        affine_from_jacobian(ops, p)
+    }
+
+    // x**3 + a*x + b, computed as (x**2 + a)*x + b.
+    fn curve_rhs(&self, x: &Elem<R>) -> Elem<R> {
+        let ops = self.private_key_ops.common;
+        let mut r = ops.elem_squared(x);
+        ops.elem_add(&mut r, &ops.a);
+        ops.elem_mul(&mut r, x);
+        ops.elem_add(&mut r, &ops.b);
+        r
+    }
+
+    fn elem_from_limb(&self, a: Limb) -> Elem<R> {
+        let mut r = Elem::<Unencoded>::zero();
+        r.limbs[0] = a;
+        self.private_key_ops.common.elem_to_mont(&r)
+    }
+
+    // a * a**-2 == 1/a, and 0 for 0.
+    fn elem_inverse(&self, a: &Elem<R>) -> Elem<R> {
+        let ops = self.private_key_ops;
+        ops.common.elem_product(a, &ops.elem_inverse_squared(a))
+    }
+
+    fn elem_negated(&self, a: &Elem<R>) -> Elem<R> {
+        let mut r = Elem::zero();
+        self.private_key_ops.common.elem_sub(&mut r, a);
+        r
+    }
+
+    fn elems_equal(&self, a: &Elem<R>, b: &Elem<R>) -> Choice {
+        let num_limbs = self.private_key_ops.common.num_limbs;
+        Choice::from(
+            limb::limbs_equal_limbs_consttime(&a.limbs[..num_limbs], &b.limbs[..num_limbs])
+                == LimbMask::True,
+        )
+    }
+
+    // The parity of the unencoded value of `a`.
+    fn sgn0(&self, a: &Elem<R>) -> Choice {
+        let a = self.private_key_ops.common.elem_unencoded(a);
+        Choice::from_mask(((a.limbs[0] & 1) as u8).wrapping_neg())
+    }
+}
+
+fn elem_select(cond: Choice, a: &Elem<R>, b: &Elem<R>) -> Elem<R> {
+    let mut r = Elem::zero();
+    // The lengths are equal, so this can't fail.
+    let _ = constant_time::select(cond, &a.limbs, &b.limbs, &mut r.limbs);
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_hash_to_curve() {
+        test::from_file("src/ec/suite_b/oprf_tests.txt", |section, test_case| {
+            let curve = match section {
+                "P-256" => &P256,
+                "P-384" => &P384,
+                _ => unreachable!(),
+            };
+            let dst = test_case.consume_bytes("DST");
+            let msg = test_case.consume_bytes("msg");
+            let expected = test_case.consume_bytes("P");
+            let p = curve.hash_to_curve(&msg, &[&dst]).unwrap();
+            let mut encoded = [0; MAX_ELEMENT_LEN];
+            let encoded = &mut encoded[..curve.element_len()];
+            curve.serialize(&p, encoded);
+            assert_eq!(&encoded[..], &expected[..]);
+            let q = curve.deserialize(encoded).unwrap();
+            let ops = curve.private_key_ops.common;
+            assert!(ops.elems_are_equal(&p.0, &q.0) && ops.elems_are_equal(&p.1, &q.1));
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_deserialize_rejects_invalid() {
+        for curve in [&P256, &P384].iter() {
+            // 1 isn't the x coordinate of a point on P-256 or P-384.
+            let len = curve.element_len();
+            let mut bad = [0; MAX_ELEMENT_LEN];
+            bad[0] = 2;
+            bad[len - 1] = 1;
+            assert!(curve.deserialize(&bad[..len]).is_err());
+            bad[0] = 4;
+            assert!(curve.deserialize(&bad[..len]).is_err());
+            assert!(curve.deserialize(&bad[1..len]).is_err());
+        }
+    }
+}
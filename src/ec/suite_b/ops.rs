@@ -34,6 +34,7 @@ pub type Scalar<E = Unencoded> = elem::Elem<N, E>;
 #[derive(Clone, Copy)]
 pub enum N {}
 
+#[derive(Clone, Copy)]
 pub struct Point {
     // The coordinates are stored in a contiguous array, where the first
     // `ops.num_limbs` elements are the X coordinate, the next
@@ -100,6 +101,7 @@ pub struct CommonOps {
     elem_sqr_mont: unsafe extern "C" fn(r: *mut Limb, a: *const Limb),
 
     point_add_jacobian_impl: unsafe extern "C" fn(r: *mut Limb, a: *const Limb, b: *const Limb),
+    point_double_jacobian_impl: unsafe extern "C" fn(r: *mut Limb, a: *const Limb),
 }
 
 impl CommonOps {
@@ -108,6 +110,19 @@ impl CommonOps {
         binary_op_assign(self.elem_add_impl, a, b)
     }
 
+    #[inline]
+    pub fn elem_sub<E: Encoding>(&self, a: &mut Elem<E>, b: &Elem<E>) {
+        unsafe {
+            LIMBS_sub_mod(
+                a.limbs.as_mut_ptr(),
+                a.limbs.as_ptr(),
+                b.limbs.as_ptr(),
+                self.q.p.as_ptr(),
+                self.num_limbs,
+            )
+        }
+    }
+
     pub fn elems_are_equal(&self, a: &Elem<R>, b: &Elem<R>) -> bool {
         for i in 0..self.num_limbs {
             if a.limbs[i] != b.limbs[i] {
@@ -120,6 +135,16 @@ impl CommonOps {
     #[inline]
     pub fn elem_unencoded(&self, a: &Elem<R>) -> Elem<Unencoded> { self.elem_product(a, &ONE) }
 
+    #[inline]
+    pub fn elem_to_mont(&self, a: &Elem<Unencoded>) -> Elem<R> {
+        let rr: Elem<RR> = Elem {
+            limbs: self.q.rr,
+            m: PhantomData,
+            encoding: PhantomData,
+        };
+        self.elem_product(a, &rr)
+    }
+
     #[inline]
     pub fn elem_mul(&self, a: &mut Elem<R>, b: &Elem<R>) {
         binary_op_assign(self.elem_mul_mont, a, b)
@@ -162,6 +187,29 @@ impl CommonOps {
         r
     }
 
+    pub fn point_double(&self, a: &Point) -> Point {
+        let mut r = Point::new_at_infinity();
+        unsafe { (self.point_double_jacobian_impl)(r.xyz.as_mut_ptr(), a.xyz.as_ptr()) }
+        r
+    }
+
+    /// The Jacobian coordinates (x, y, 1) of the affine point `(x, y)`.
+    pub fn point_from_affine(&self, (x, y): &(Elem<R>, Elem<R>)) -> Point {
+        let n = self.num_limbs;
+        let mut r = Point::new_at_infinity();
+        r.xyz[..n].copy_from_slice(&x.limbs[..n]);
+        r.xyz[n..(2 * n)].copy_from_slice(&y.limbs[..n]);
+        // Montgomery encode 1 (elem_to_mont).
+        unsafe {
+            (self.elem_mul_mont)(
+                r.xyz[(2 * n)..].as_mut_ptr(),
+                self.q.rr.as_ptr(),
+                ONE.limbs.as_ptr(),
+            )
+        }
+        r
+    }
+
     pub fn point_x(&self, p: &Point) -> Elem<R> {
         let mut r = Elem::zero();
         r.limbs[..self.num_limbs].copy_from_slice(&p.xyz[0..self.num_limbs]);
@@ -226,6 +274,7 @@ impl PrivateKeyOps {
 /// agreement and ECDSA verification).
 pub struct PublicKeyOps {
     pub common: &'static CommonOps,
+    elem_sqrt_candidate: fn(a: &Elem<R>) -> Elem<R>,
 }
 
 impl PublicKeyOps {
@@ -249,6 +298,11 @@ impl PublicKeyOps {
         }
         Ok(r)
     }
+
+    /// Returns a square root of `a` if `a` is a square; otherwise, the result
+    /// is an unspecified value that isn't a square root of `a`.
+    #[inline]
+    pub fn elem_sqrt_candidate(&self, a: &Elem<R>) -> Elem<R> { (self.elem_sqrt_candidate)(a) }
 }
 
 // Operations used by both ECDSA signing and ECDSA verification. In general
@@ -357,6 +411,20 @@ pub fn scalar_sum(ops: &CommonOps, a: &Scalar, b: &Scalar) -> Scalar {
     r
 }
 
+pub fn scalar_difference(ops: &CommonOps, a: &Scalar, b: &Scalar) -> Scalar {
+    let mut r = Scalar::zero();
+    unsafe {
+        LIMBS_sub_mod(
+            r.limbs.as_mut_ptr(),
+            a.limbs.as_ptr(),
+            b.limbs.as_ptr(),
+            ops.n.limbs.as_ptr(),
+            ops.num_limbs,
+        )
+    }
+    r
+}
+
 // Returns (`a` squared `squarings` times) * `b`.
 fn elem_sqr_mul(ops: &CommonOps, a: &Elem<R>, squarings: usize, b: &Elem<R>) -> Elem<R> {
     debug_assert!(squarings >= 1);
@@ -383,6 +451,19 @@ pub fn elem_parse_big_endian_fixed_consttime(
     parse_big_endian_fixed_consttime(ops, bytes, AllowZero::Yes, &ops.q.p[..ops.num_limbs])
 }
 
+pub fn elem_parse_big_endian_partially_reduced_consttime(
+    ops: &CommonOps, bytes: untrusted::Input,
+) -> Result<Elem<Unencoded>, error::Unspecified> {
+    let mut r = Elem::zero();
+    parse_big_endian_in_range_partially_reduced_and_pad_consttime(
+        bytes,
+        AllowZero::Yes,
+        &ops.q.p[..ops.num_limbs],
+        &mut r.limbs[..ops.num_limbs],
+    )?;
+    Ok(r)
+}
+
 #[inline]
 pub fn scalar_parse_big_endian_fixed_consttime(
     ops: &CommonOps, bytes: untrusted::Input,
@@ -437,6 +518,9 @@ extern "C" {
     fn LIMBS_add_mod(
         r: *mut Limb, a: *const Limb, b: *const Limb, m: *const Limb, num_limbs: c::size_t,
     );
+    fn LIMBS_sub_mod(
+        r: *mut Limb, a: *const Limb, b: *const Limb, m: *const Limb, num_limbs: c::size_t,
+    );
 }
 
 #[cfg(test)]
@@ -15,6 +15,7 @@
 use crate::{
     arithmetic::montgomery::{Encoding, ProductEncoding},
     limb::{Limb, LIMB_BITS},
+    zeroize,
 };
 use core::marker::PhantomData;
 
@@ -33,6 +34,8 @@ pub struct Elem<M, E: Encoding> {
     pub encoding: PhantomData<E>,
 }
 
+unsafe impl<M: Copy, E: Encoding + Copy> zeroize::Zeroable for Elem<M, E> {}
+
 impl<M, E: Encoding> Elem<M, E> {
     // There's no need to convert `value` to the Montgomery domain since
     // 0 * R**2 (mod m) == 0, so neither the modulus nor the encoding are needed
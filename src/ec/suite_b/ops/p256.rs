@@ -73,6 +73,7 @@ pub static COMMON_OPS: CommonOps = CommonOps {
     elem_sqr_mont: GFp_nistz256_sqr_mont,
 
     point_add_jacobian_impl: GFp_nistz256_point_add,
+    point_double_jacobian_impl: GFp_nistz256_point_double,
 };
 
 pub static PRIVATE_KEY_OPS: PrivateKeyOps = PrivateKeyOps {
@@ -127,6 +128,41 @@ fn p256_elem_inv_squared(a: &Elem<R>) -> Elem<R> {
     acc
 }
 
+/// Returns a**((q + 1) / 4) (mod q), which is a square root of `a` if `a` has
+/// one, since q == 3 (mod 4).
+pub fn elem_sqrt_candidate(a: &Elem<R>) -> Elem<R> {
+    // The exponent (q + 1) / 4 is:
+    //
+    //    0x3fffffffc0000000400000000000000000000000400000000000000000000000
+    //
+    // which is (2**32 - 1) * 2**222 + 2**190 + 2**94.
+
+    #[inline]
+    fn sqr_mul(a: &Elem<R>, squarings: usize, b: &Elem<R>) -> Elem<R> {
+        elem_sqr_mul(&COMMON_OPS, a, squarings, b)
+    }
+
+    let b_1 = &a;
+    let b_11 = sqr_mul(b_1, 1, b_1);
+    let f = sqr_mul(&b_11, 2, &b_11);
+    let ff = sqr_mul(&f, 4, &f);
+    let ffff = sqr_mul(&ff, 8, &ff);
+    let ffffffff = sqr_mul(&ffff, 16, &ffff);
+
+    // ffffffff00000001
+    let mut acc = sqr_mul(&ffffffff, 32, b_1);
+
+    // ffffffff00000001000000000000000000000001
+    elem_sqr_mul_acc(&COMMON_OPS, &mut acc, 96, b_1);
+
+    // 3fffffffc0000000400000000000000000000000400000000000000000000000
+    for _ in 0..94 {
+        COMMON_OPS.elem_square(&mut acc);
+    }
+
+    acc
+}
+
 fn p256_point_mul_base_impl(g_scalar: &Scalar) -> Point {
     let mut r = Point::new_at_infinity();
     unsafe {
@@ -137,6 +173,7 @@ fn p256_point_mul_base_impl(g_scalar: &Scalar) -> Point {
 
 pub static PUBLIC_KEY_OPS: PublicKeyOps = PublicKeyOps {
     common: &COMMON_OPS,
+    elem_sqrt_candidate,
 };
 
 pub static SCALAR_OPS: ScalarOps = ScalarOps {
@@ -312,6 +349,10 @@ extern "C" {
         a: *const Limb, // [3][COMMON_OPS.num_limbs]
         b: *const Limb, // [3][COMMON_OPS.num_limbs]
     );
+    fn GFp_nistz256_point_double(
+        r: *mut Limb,   // [3][COMMON_OPS.num_limbs]
+        a: *const Limb, // [3][COMMON_OPS.num_limbs]
+    );
     fn GFp_nistz256_point_mul(
         r: *mut Limb,          // [3][COMMON_OPS.num_limbs]
         p_scalar: *const Limb, // [COMMON_OPS.num_limbs]
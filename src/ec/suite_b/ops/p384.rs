@@ -70,6 +70,7 @@ pub static COMMON_OPS: CommonOps = CommonOps {
     elem_sqr_mont: GFp_p384_elem_sqr_mont,
 
     point_add_jacobian_impl: GFp_nistz384_point_add,
+    point_double_jacobian_impl: GFp_nistz384_point_double,
 };
 
 pub static PRIVATE_KEY_OPS: PrivateKeyOps = PrivateKeyOps {
@@ -135,8 +136,72 @@ fn p384_elem_inv_squared(a: &Elem<R>) -> Elem<R> {
     acc
 }
 
+/// Returns a**((q + 1) / 4) (mod q), which is a square root of `a` if `a` has
+/// one, since q == 3 (mod 4).
+pub fn elem_sqrt_candidate(a: &Elem<R>) -> Elem<R> {
+    // The exponent (q + 1) / 4 is:
+    //
+    //    0x3fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+    //      bfffffffc00000000000000040000000
+    //
+    // which is (2**255 - 1) * 2**127 + (2**32 - 1) * 2**94 + 2**30.
+
+    #[inline]
+    fn sqr_mul(a: &Elem<R>, squarings: usize, b: &Elem<R>) -> Elem<R> {
+        elem_sqr_mul(&COMMON_OPS, a, squarings, b)
+    }
+
+    #[inline]
+    fn sqr_mul_acc(a: &mut Elem<R>, squarings: usize, b: &Elem<R>) {
+        elem_sqr_mul_acc(&COMMON_OPS, a, squarings, b)
+    }
+
+    let b_1 = &a;
+    let b_11 = sqr_mul(b_1, 1, b_1);
+    let b_111 = sqr_mul(&b_11, 1, b_1);
+    let f_11 = sqr_mul(&b_111, 3, &b_111);
+    let fff = sqr_mul(&f_11, 6, &f_11);
+    let fff_111 = sqr_mul(&fff, 3, &b_111);
+    let fffffff_11 = sqr_mul(&fff_111, 15, &fff_111);
+    let fffffffffffffff = sqr_mul(&fffffff_11, 30, &fffffff_11);
+    let ffffffffffffffffffffffffffffff = sqr_mul(&fffffffffffffff, 60, &fffffffffffffff);
+
+    // 255 ones.
+    let mut acc = sqr_mul(
+        &ffffffffffffffffffffffffffffff,
+        120,
+        &ffffffffffffffffffffffffffffff,
+    );
+    sqr_mul_acc(&mut acc, 15, &fff_111);
+
+    // A zero and 32 ones.
+    sqr_mul_acc(&mut acc, 1 + 30, &fffffff_11);
+    sqr_mul_acc(&mut acc, 2, &b_11);
+
+    // 63 zeros and a one.
+    sqr_mul_acc(&mut acc, 64, b_1);
+
+    // 30 zeros.
+    for _ in 0..30 {
+        COMMON_OPS.elem_square(&mut acc);
+    }
+
+    acc
+}
+
+#[cfg(feature = "large_ec_tables")]
+fn p384_point_mul_base_impl(a: &Scalar) -> Point {
+    let mut r = Point::new_at_infinity();
+    unsafe {
+        GFp_nistz384_point_mul_base(r.xyz.as_mut_ptr(), a.limbs.as_ptr());
+    }
+    r
+}
+
+#[cfg(not(feature = "large_ec_tables"))]
 fn p384_point_mul_base_impl(a: &Scalar) -> Point {
-    // XXX: Not efficient. TODO: Precompute multiples of the generator.
+    // XXX: Not efficient. The `large_ec_tables` feature precomputes multiples
+    // of the generator, but the table is too big to use by default.
     static P384_GENERATOR: (Elem<R>, Elem<R>) = (
         Elem {
             limbs: p384_limbs![
@@ -161,6 +226,7 @@ fn p384_point_mul_base_impl(a: &Scalar) -> Point {
 
 pub static PUBLIC_KEY_OPS: PublicKeyOps = PublicKeyOps {
     common: &COMMON_OPS,
+    elem_sqrt_candidate,
 };
 
 pub static SCALAR_OPS: ScalarOps = ScalarOps {
@@ -354,12 +420,21 @@ extern "C" {
         a: *const Limb, // [3][COMMON_OPS.num_limbs]
         b: *const Limb, // [3][COMMON_OPS.num_limbs]
     );
+    fn GFp_nistz384_point_double(
+        r: *mut Limb,   // [3][COMMON_OPS.num_limbs]
+        a: *const Limb, // [3][COMMON_OPS.num_limbs]
+    );
     fn GFp_nistz384_point_mul(
         r: *mut Limb,          // [3][COMMON_OPS.num_limbs]
         p_scalar: *const Limb, // [COMMON_OPS.num_limbs]
         p_x: *const Limb,      // [COMMON_OPS.num_limbs]
         p_y: *const Limb,      // [COMMON_OPS.num_limbs]
     );
+    #[cfg(feature = "large_ec_tables")]
+    fn GFp_nistz384_point_mul_base(
+        r: *mut Limb,          // [3][COMMON_OPS.num_limbs]
+        g_scalar: *const Limb, // [COMMON_OPS.num_limbs]
+    );
 
     fn GFp_p384_scalar_mul_mont(
         r: *mut Limb,   // [COMMON_OPS.num_limbs]
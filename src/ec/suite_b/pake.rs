@@ -0,0 +1,260 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The curve operations of SPAKE2 and SPAKE2+; see `crate::pake`.
+
+use super::{ops::*, private_key::*, public_key::*};
+use crate::{
+    arithmetic::montgomery::R,
+    error,
+    limb::{self, AllowZero, LIMB_BYTES},
+    rand,
+};
+use core::marker::PhantomData;
+use untrusted;
+
+pub use super::ops::Scalar;
+
+pub struct Curve {
+    private_key_ops: &'static PrivateKeyOps,
+    public_key_ops: &'static PublicKeyOps,
+    scalar_ops: &'static ScalarOps,
+    private_scalar_ops: &'static PrivateScalarOps,
+
+    // The uncompressed encodings of M and N of RFC 9382 Section 6 and their
+    // negations.
+    pub m: &'static [u8],
+    pub n: &'static [u8],
+    m_neg: &'static [u8],
+    n_neg: &'static [u8],
+}
+
+pub static P256: Curve = Curve {
+    private_key_ops: &p256::PRIVATE_KEY_OPS,
+    public_key_ops: &p256::PUBLIC_KEY_OPS,
+    scalar_ops: &p256::SCALAR_OPS,
+    private_scalar_ops: &p256::PRIVATE_SCALAR_OPS,
+    m: &P256_M,
+    n: &P256_N,
+    m_neg: &P256_M_NEG,
+    n_neg: &P256_N_NEG,
+};
+
+pub static P384: Curve = Curve {
+    private_key_ops: &p384::PRIVATE_KEY_OPS,
+    public_key_ops: &p384::PUBLIC_KEY_OPS,
+    scalar_ops: &p384::SCALAR_OPS,
+    private_scalar_ops: &p384::PRIVATE_SCALAR_OPS,
+    m: &P384_M,
+    n: &P384_N,
+    m_neg: &P384_M_NEG,
+    n_neg: &P384_N_NEG,
+};
+
+/// Which of M and N blinds a share.
+#[derive(Clone, Copy)]
+pub enum Blinding {
+    M,
+    N,
+}
+
+impl Curve {
+    #[inline]
+    pub fn scalar_len(&self) -> usize { self.scalar_ops.scalar_bytes_len() }
+
+    #[inline]
+    pub fn point_len(&self) -> usize { 1 + 2 * self.scalar_len() }
+
+    pub fn random_scalar(&self, rng: &rand::SecureRandom) -> Result<Scalar, error::Unspecified> {
+        random_scalar(self.private_key_ops, rng)
+    }
+
+    /// Parses a password-derived value, e.g. `w0s`, as a big-endian integer
+    /// and reduces it modulo the group order *n*. The input may be up to
+    /// twice as long as a scalar. The result must not be zero.
+    pub fn scalar_reduced(&self, input: &[u8]) -> Result<Scalar, error::Unspecified> {
+        let common = self.scalar_ops.common;
+        let len = self.scalar_len();
+        if input.is_empty() || input.len() > 2 * len {
+            return Err(error::Unspecified);
+        }
+        let (hi, lo) = input.split_at(input.len().saturating_sub(len));
+
+        // Both halves are less than 2**(8 * len) < 2*n, so parsing reduces
+        // them completely.
+        let parse = |bytes| {
+            scalar_parse_big_endian_partially_reduced_variable_consttime(
+                common,
+                AllowZero::Yes,
+                untrusted::Input::from(bytes),
+            )
+        };
+        let mut r = parse(lo)?;
+        if !hi.is_empty() {
+            // R == 2**(8 * len), so the Montgomery product of `hi` and R**2 is
+            // `hi` * 2**(8 * len) (mod n) as an unencoded value.
+            let hi_shifted: Scalar<R> =
+                self.scalar_ops.scalar_product(&parse(hi)?, &self.private_scalar_ops.oneRR_mod_n);
+            let hi_shifted = Scalar {
+                limbs: hi_shifted.limbs,
+                m: PhantomData,
+                encoding: PhantomData,
+            };
+            r = scalar_sum(common, &hi_shifted, &r);
+        }
+        if common.is_zero(&r) {
+            return Err(error::Unspecified);
+        }
+        Ok(r)
+    }
+
+    pub fn scalar_to_bytes(&self, a: &Scalar, out: &mut [u8]) {
+        let num_limbs = self.scalar_ops.common.num_limbs;
+        limb::big_endian_from_limbs(&a.limbs[..num_limbs], out)
+    }
+
+    /// Writes `x*G + w*M` or `x*G + w*N` to `out`.
+    pub fn share(
+        &self, x: &Scalar, w: &Scalar, blinding: Blinding, out: &mut [u8],
+    ) -> Result<(), error::Unspecified> {
+        let ops = self.private_key_ops;
+        let (m, _) = self.blinding_points(blinding);
+        let blind = ops.point_mul(w, &parse_uncompressed_point(self.public_key_ops, m)?);
+        let share = ops.common.point_sum(&ops.point_mul_base(x), &blind);
+        self.encode(&share, out)
+    }
+
+    /// Parses and validates the peer's share and returns `share - w*M` or
+    /// `share - w*N`. This fails if the result is the point at infinity,
+    /// which the peer can only cause if it knows `w`.
+    pub fn unblind(
+        &self, peer_share: &[u8], w: &Scalar, blinding: Blinding,
+    ) -> Result<(Elem<R>, Elem<R>), error::Unspecified> {
+        let ops = self.private_key_ops;
+        let peer_share = self.parse(peer_share)?;
+        let (_, m_neg) = self.blinding_points(blinding);
+        let blind = ops.point_mul(w, &parse_uncompressed_point(self.public_key_ops, m_neg)?);
+        let unblinded = ops.common.point_sum(&ops.common.point_from_affine(&peer_share), &blind);
+        ops.common.elem_verify_is_not_zero(&ops.common.point_z(&unblinded))?;
+        affine_from_jacobian(ops, &unblinded)
+    }
+
+    /// Parses and validates an encoded point, e.g. a share or `L`.
+    pub fn parse(&self, input: &[u8]) -> Result<(Elem<R>, Elem<R>), error::Unspecified> {
+        parse_uncompressed_point(self.public_key_ops, untrusted::Input::from(input))
+    }
+
+    /// Writes `a*p` to `out`.
+    pub fn mul(
+        &self, a: &Scalar, p: &(Elem<R>, Elem<R>), out: &mut [u8],
+    ) -> Result<(), error::Unspecified> {
+        self.encode(&self.private_key_ops.point_mul(a, p), out)
+    }
+
+    /// Writes `a*G` to `out`.
+    pub fn mul_base(&self, a: &Scalar, out: &mut [u8]) -> Result<(), error::Unspecified> {
+        self.encode(&self.private_key_ops.point_mul_base(a), out)
+    }
+
+    fn blinding_points(
+        &self, blinding: Blinding,
+    ) -> (untrusted::Input<'static>, untrusted::Input<'static>) {
+        let (m, m_neg) = match blinding {
+            Blinding::M => (self.m, self.m_neg),
+            Blinding::N => (self.n, self.n_neg),
+        };
+        (untrusted::Input::from(m), untrusted::Input::from(m_neg))
+    }
+
+    fn encode(&self, p: &Point, out: &mut [u8]) -> Result<(), error::Unspecified> {
+        let ops = self.private_key_ops;
+        debug_assert_eq!(out.len(), self.point_len());
+        ops.common.elem_verify_is_not_zero(&ops.common.point_z(p))?;
+        out[0] = 4; // Uncompressed encoding.
+        let (x_out, y_out) = out[1..].split_at_mut(ops.common.num_limbs * LIMB_BYTES);
+        big_endian_affine_from_jacobian(ops, Some(x_out), Some(y_out), p)
+    }
+}
+
+const P256_M: [u8; 65] = [
+    0x04, 0x88, 0x6e, 0x2f, 0x97, 0xac, 0xe4, 0x6e, 0x55, 0xba, 0x9d, 0xd7, 0x24, 0x25, 0x79, 0xf2,
+    0x99, 0x3b, 0x64, 0xe1, 0x6e, 0xf3, 0xdc, 0xab, 0x95, 0xaf, 0xd4, 0x97, 0x33, 0x3d, 0x8f, 0xa1,
+    0x2f, 0x5f, 0xf3, 0x55, 0x16, 0x3e, 0x43, 0xce, 0x22, 0x4e, 0x0b, 0x0e, 0x65, 0xff, 0x02, 0xac,
+    0x8e, 0x5c, 0x7b, 0xe0, 0x94, 0x19, 0xc7, 0x85, 0xe0, 0xca, 0x54, 0x7d, 0x55, 0xa1, 0x2e, 0x2d,
+    0x20,
+];
+
+const P256_M_NEG: [u8; 65] = [
+    0x04, 0x88, 0x6e, 0x2f, 0x97, 0xac, 0xe4, 0x6e, 0x55, 0xba, 0x9d, 0xd7, 0x24, 0x25, 0x79, 0xf2,
+    0x99, 0x3b, 0x64, 0xe1, 0x6e, 0xf3, 0xdc, 0xab, 0x95, 0xaf, 0xd4, 0x97, 0x33, 0x3d, 0x8f, 0xa1,
+    0x2f, 0xa0, 0x0c, 0xaa, 0xe8, 0xc1, 0xbc, 0x31, 0xde, 0xb1, 0xf4, 0xf1, 0x9a, 0x00, 0xfd, 0x53,
+    0x71, 0xa3, 0x84, 0x1f, 0x6c, 0xe6, 0x38, 0x7a, 0x1f, 0x35, 0xab, 0x82, 0xaa, 0x5e, 0xd1, 0xd2,
+    0xdf,
+];
+
+const P256_N: [u8; 65] = [
+    0x04, 0xd8, 0xbb, 0xd6, 0xc6, 0x39, 0xc6, 0x29, 0x37, 0xb0, 0x4d, 0x99, 0x7f, 0x38, 0xc3, 0x77,
+    0x07, 0x19, 0xc6, 0x29, 0xd7, 0x01, 0x4d, 0x49, 0xa2, 0x4b, 0x4f, 0x98, 0xba, 0xa1, 0x29, 0x2b,
+    0x49, 0x07, 0xd6, 0x0a, 0xa6, 0xbf, 0xad, 0xe4, 0x50, 0x08, 0xa6, 0x36, 0x33, 0x7f, 0x51, 0x68,
+    0xc6, 0x4d, 0x9b, 0xd3, 0x60, 0x34, 0x80, 0x8c, 0xd5, 0x64, 0x49, 0x0b, 0x1e, 0x65, 0x6e, 0xdb,
+    0xe7,
+];
+
+const P256_N_NEG: [u8; 65] = [
+    0x04, 0xd8, 0xbb, 0xd6, 0xc6, 0x39, 0xc6, 0x29, 0x37, 0xb0, 0x4d, 0x99, 0x7f, 0x38, 0xc3, 0x77,
+    0x07, 0x19, 0xc6, 0x29, 0xd7, 0x01, 0x4d, 0x49, 0xa2, 0x4b, 0x4f, 0x98, 0xba, 0xa1, 0x29, 0x2b,
+    0x49, 0xf8, 0x29, 0xf5, 0x58, 0x40, 0x52, 0x1b, 0xb0, 0xf7, 0x59, 0xc9, 0xcc, 0x80, 0xae, 0x97,
+    0x39, 0xb2, 0x64, 0x2c, 0xa0, 0xcb, 0x7f, 0x73, 0x2a, 0x9b, 0xb6, 0xf4, 0xe1, 0x9a, 0x91, 0x24,
+    0x18,
+];
+
+const P384_M: [u8; 97] = [
+    0x04, 0x0f, 0xf0, 0x89, 0x5a, 0xe5, 0xeb, 0xf6, 0x18, 0x70, 0x80, 0xa8, 0x2d, 0x82, 0xb4, 0x2e,
+    0x27, 0x65, 0xe3, 0xb2, 0xf8, 0x74, 0x9c, 0x7e, 0x05, 0xeb, 0xa3, 0x66, 0x43, 0x4b, 0x36, 0x3d,
+    0x3d, 0xc3, 0x6f, 0x15, 0x31, 0x47, 0x39, 0x07, 0x4d, 0x2e, 0xb8, 0x61, 0x3f, 0xce, 0xec, 0x28,
+    0x53, 0x97, 0x59, 0x2c, 0x55, 0x79, 0x7c, 0xdd, 0x77, 0xc0, 0x71, 0x5c, 0xb7, 0xdf, 0x21, 0x50,
+    0x22, 0x0a, 0x01, 0x19, 0x86, 0x64, 0x86, 0xaf, 0x42, 0x34, 0xf3, 0x90, 0xaa, 0xd1, 0xf6, 0xad,
+    0xdd, 0xe5, 0x93, 0x09, 0x09, 0xad, 0xc6, 0x7a, 0x1f, 0xc0, 0xc9, 0x9b, 0xa3, 0xd5, 0x2d, 0xc5,
+    0xdd,
+];
+
+const P384_M_NEG: [u8; 97] = [
+    0x04, 0x0f, 0xf0, 0x89, 0x5a, 0xe5, 0xeb, 0xf6, 0x18, 0x70, 0x80, 0xa8, 0x2d, 0x82, 0xb4, 0x2e,
+    0x27, 0x65, 0xe3, 0xb2, 0xf8, 0x74, 0x9c, 0x7e, 0x05, 0xeb, 0xa3, 0x66, 0x43, 0x4b, 0x36, 0x3d,
+    0x3d, 0xc3, 0x6f, 0x15, 0x31, 0x47, 0x39, 0x07, 0x4d, 0x2e, 0xb8, 0x61, 0x3f, 0xce, 0xec, 0x28,
+    0x53, 0x68, 0xa6, 0xd3, 0xaa, 0x86, 0x83, 0x22, 0x88, 0x3f, 0x8e, 0xa3, 0x48, 0x20, 0xde, 0xaf,
+    0xdd, 0xf5, 0xfe, 0xe6, 0x79, 0x9b, 0x79, 0x50, 0xbd, 0xcb, 0x0c, 0x6f, 0x55, 0x2e, 0x09, 0x52,
+    0x21, 0x1a, 0x6c, 0xf6, 0xf5, 0x52, 0x39, 0x85, 0xe0, 0x3f, 0x36, 0x64, 0x5d, 0x2a, 0xd2, 0x3a,
+    0x22,
+];
+
+const P384_N: [u8; 97] = [
+    0x04, 0xc7, 0x2c, 0xf2, 0xe3, 0x90, 0x85, 0x3a, 0x1c, 0x1c, 0x4a, 0xd8, 0x16, 0xa6, 0x2f, 0xd1,
+    0x58, 0x24, 0xf5, 0x60, 0x78, 0x91, 0x8f, 0x43, 0xf9, 0x22, 0xca, 0x21, 0x51, 0x8f, 0x9c, 0x54,
+    0x3b, 0xb2, 0x52, 0xc5, 0x49, 0x02, 0x14, 0xcf, 0x9a, 0xa3, 0xf0, 0xba, 0xab, 0x4b, 0x66, 0x5c,
+    0x10, 0xc3, 0x8b, 0x7d, 0x7f, 0x4e, 0x7f, 0x32, 0x03, 0x17, 0xcd, 0x71, 0x73, 0x15, 0xa7, 0x97,
+    0xc7, 0xe0, 0x29, 0x33, 0xae, 0xf6, 0x8b, 0x36, 0x4c, 0xbf, 0x84, 0xeb, 0xc6, 0x19, 0xbe, 0xdb,
+    0xe2, 0x1f, 0xf5, 0xc6, 0x9e, 0xa0, 0xf1, 0xfe, 0xd5, 0xd7, 0xe3, 0x20, 0x04, 0x18, 0x07, 0x3f,
+    0x40,
+];
+
+const P384_N_NEG: [u8; 97] = [
+    0x04, 0xc7, 0x2c, 0xf2, 0xe3, 0x90, 0x85, 0x3a, 0x1c, 0x1c, 0x4a, 0xd8, 0x16, 0xa6, 0x2f, 0xd1,
+    0x58, 0x24, 0xf5, 0x60, 0x78, 0x91, 0x8f, 0x43, 0xf9, 0x22, 0xca, 0x21, 0x51, 0x8f, 0x9c, 0x54,
+    0x3b, 0xb2, 0x52, 0xc5, 0x49, 0x02, 0x14, 0xcf, 0x9a, 0xa3, 0xf0, 0xba, 0xab, 0x4b, 0x66, 0x5c,
+    0x10, 0x3c, 0x74, 0x82, 0x80, 0xb1, 0x80, 0xcd, 0xfc, 0xe8, 0x32, 0x8e, 0x8c, 0xea, 0x58, 0x68,
+    0x38, 0x1f, 0xd6, 0xcc, 0x51, 0x09, 0x74, 0xc9, 0xb3, 0x40, 0x7b, 0x14, 0x39, 0xe6, 0x41, 0x24,
+    0x1c, 0xe0, 0x0a, 0x39, 0x60, 0x5f, 0x0e, 0x01, 0x2a, 0x28, 0x1c, 0xdf, 0xfc, 0xe7, 0xf8, 0xc0,
+    0xbf,
+];
@@ -64,6 +64,65 @@ pub fn parse_uncompressed_point(
     Ok((x, y))
 }
 
+/// Parses a public key encoded in either uncompressed or compressed form.
+/// Compressed points are decompressed using the
+/// Octet-String-to-Elliptic-Curve-Point algorithm in [SEC 1: Elliptic Curve
+/// Cryptography, Version 2.0] Section 2.3.4, which validates them as
+/// thoroughly as `parse_uncompressed_point` validates uncompressed points.
+///
+/// [SEC 1: Elliptic Curve Cryptography, Version 2.0]:
+///     http://www.secg.org/sec1-v2.pdf
+pub fn parse_point(
+    ops: &PublicKeyOps, input: untrusted::Input,
+) -> Result<(Elem<R>, Elem<R>), error::Unspecified> {
+    match input.as_slice_less_safe().first() {
+        Some(2) | Some(3) => parse_compressed_point(ops, input),
+        _ => parse_uncompressed_point(ops, input),
+    }
+}
+
+fn parse_compressed_point(
+    ops: &PublicKeyOps, input: untrusted::Input,
+) -> Result<(Elem<R>, Elem<R>), error::Unspecified> {
+    let (y_is_odd, x) = input.read_all(error::Unspecified, |input| {
+        let y_is_odd = match input.read_byte()? {
+            2 => false,
+            3 => true,
+            _ => return Err(error::Unspecified),
+        };
+
+        // NIST SP 800-56A Step 2, for the X coordinate.
+        let x = ops.elem_parse(input)?;
+        Ok((y_is_odd, x))
+    })?;
+
+    // The Y coordinate is a square root of x**3 + a*x + b; if there is none,
+    // then there is no point with the X coordinate `x`. Since the Y coordinate
+    // is computed from the curve equation, this also does NIST SP 800-56A
+    // Step 3.
+    let common = ops.common;
+    let mut y_squared = common.elem_squared(&x);
+    common.elem_add(&mut y_squared, &common.a);
+    common.elem_mul(&mut y_squared, &x);
+    common.elem_add(&mut y_squared, &common.b);
+    let y = ops.elem_sqrt_candidate(&y_squared);
+    if !common.elems_are_equal(&common.elem_squared(&y), &y_squared) {
+        return Err(error::Unspecified);
+    }
+
+    // The square roots are `y` and `q - y`, which have different parities
+    // since q is odd and there are no points with a Y coordinate of zero.
+    let y = if (common.elem_unencoded(&y).limbs[0] & 1 == 1) != y_is_odd {
+        let mut neg_y = Elem::zero();
+        common.elem_sub(&mut neg_y, &y);
+        neg_y
+    } else {
+        y
+    };
+
+    Ok((x, y))
+}
+
 #[cfg(test)]
 mod tests {
     use super::{super::ops, *};
@@ -96,6 +155,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_point_test() {
+        test::from_file(
+            "src/ec/suite_b/suite_b_compressed_public_key_tests.txt",
+            |section, test_case| {
+                assert_eq!(section, "");
+
+                let curve_name = test_case.consume_string("Curve");
+                let public_key = test_case.consume_bytes("Q");
+                let expected = test_case
+                    .consume_optional_string("Point")
+                    .map(|point| test::from_hex(&point).unwrap());
+                let valid = test_case.consume_string("Result") == "P";
+                assert_eq!(valid, expected.is_some());
+
+                let curve_ops = public_key_ops_from_curve_name(&curve_name);
+
+                let result = parse_point(curve_ops, untrusted::Input::from(&public_key));
+                assert_eq!(valid, result.is_ok());
+
+                // The decompressed point must be the one that the uncompressed
+                // encoding `Point` encodes.
+                if let (Ok((x, y)), Some(expected)) = (result, expected) {
+                    let (expected_x, expected_y) =
+                        parse_uncompressed_point(curve_ops, untrusted::Input::from(&expected))
+                            .unwrap();
+                    let common = curve_ops.common;
+                    assert!(common.elems_are_equal(&x, &expected_x));
+                    assert!(common.elems_are_equal(&y, &expected_y));
+                }
+
+                Ok(())
+            },
+        );
+    }
+
     fn public_key_ops_from_curve_name(curve_name: &str) -> &'static PublicKeyOps {
         if curve_name == "P-256" {
             &ops::p256::PUBLIC_KEY_OPS
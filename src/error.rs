@@ -14,11 +14,11 @@
 
 //! Error reporting.
 
-use crate::polyfill::convert::*;
+use crate::{cpu, polyfill::convert::*};
 use core;
 use untrusted;
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "std")]
 use std;
 
 /// An error with absolutely no details.
@@ -41,7 +41,7 @@ use std;
 /// enum Error {
 ///     CryptoError,
 ///
-/// #  #[cfg(feature = "use_heap")]
+/// #  #[cfg(feature = "alloc")]
 ///     IOError(std::io::Error),
 ///     // [...]
 /// }
@@ -93,7 +93,7 @@ impl core::fmt::Display for Unspecified {
     }
 }
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "std")]
 impl std::error::Error for Unspecified {
     #[inline]
     fn cause(&self) -> Option<&std::error::Error> { None }
@@ -138,49 +138,147 @@ impl From<TryFromSliceError> for Unspecified {
 ///  * Wrong algorithm: The key is not valid for the algorithm in which it was
 ///    being used.
 ///
+///  * Decryption failed: The key is encrypted and either the password is
+///    wrong or the encrypted key was corrupted.
+///
 ///  * Unexpected errors: Report this as a bug.
-#[derive(Copy, Clone, Debug)]
-pub struct KeyRejected(&'static str);
+///
+/// `reason()` returns the reason as a `KeyRejectedReason`, for applications
+/// that need to handle some reasons differently, e.g. to tell the user that
+/// the password is wrong.
+#[derive(Copy, Clone)]
+pub struct KeyRejected(KeyRejectedReason);
 
 impl KeyRejected {
     /// The value returned from <Self as std::error::Error>::description()
-    pub fn description_(&self) -> &'static str { self.0 }
+    pub fn description_(&self) -> &'static str {
+        match self.0 {
+            KeyRejectedReason::DecryptionFailed => "DecryptionFailed",
+            KeyRejectedReason::InconsistentComponents => "InconsistentComponents",
+            KeyRejectedReason::InvalidComponent => "InvalidComponent",
+            KeyRejectedReason::InvalidEncoding => "InvalidEncoding",
+            KeyRejectedReason::PublicKeyIsMissing => "PublicKeyIsMissing",
+            KeyRejectedReason::TooSmall => "TooSmall",
+            KeyRejectedReason::TooLarge => "TooLarge",
+            KeyRejectedReason::VersionNotSupported => "VersionNotSupported",
+            KeyRejectedReason::WrongAlgorithm => "WrongAlgorithm",
+            KeyRejectedReason::PrivateModulusLenNotMultipleOf512Bits => {
+                "PrivateModulusLenNotMultipleOf512Bits"
+            },
+            KeyRejectedReason::UnexpectedError => "UnexpectedError",
+        }
+    }
 
-    pub(crate) fn inconsistent_components() -> Self { KeyRejected("InconsistentComponents") }
+    /// Why the key was rejected.
+    ///
+    /// ```
+    /// use ring::{error::KeyRejectedReason, signature};
+    ///
+    /// let rejected = signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(b""))
+    ///     .err()
+    ///     .unwrap();
+    /// assert_eq!(rejected.reason(), KeyRejectedReason::InvalidEncoding);
+    /// ```
+    #[inline]
+    pub fn reason(&self) -> KeyRejectedReason { self.0 }
+
+    #[cfg(feature = "alloc")]
+    pub(crate) fn decryption_failed() -> Self { KeyRejected(KeyRejectedReason::DecryptionFailed) }
 
-    pub(crate) fn invalid_component() -> Self { KeyRejected("InvalidComponent") }
+    pub(crate) fn inconsistent_components() -> Self {
+        KeyRejected(KeyRejectedReason::InconsistentComponents)
+    }
+
+    pub(crate) fn invalid_component() -> Self { KeyRejected(KeyRejectedReason::InvalidComponent) }
 
     #[inline]
-    pub(crate) fn invalid_encoding() -> Self { KeyRejected("InvalidEncoding") }
+    pub(crate) fn invalid_encoding() -> Self { KeyRejected(KeyRejectedReason::InvalidEncoding) }
 
-    pub(crate) fn public_key_is_missing() -> Self { KeyRejected("PublicKeyIsMissing") }
+    pub(crate) fn public_key_is_missing() -> Self {
+        KeyRejected(KeyRejectedReason::PublicKeyIsMissing)
+    }
 
-    #[cfg(feature = "use_heap")]
-    pub(crate) fn too_small() -> Self { KeyRejected("TooSmall") }
+    #[cfg(feature = "alloc")]
+    pub(crate) fn too_small() -> Self { KeyRejected(KeyRejectedReason::TooSmall) }
 
-    #[cfg(feature = "use_heap")]
-    pub(crate) fn too_large() -> Self { KeyRejected("TooLarge") }
+    #[cfg(feature = "alloc")]
+    pub(crate) fn too_large() -> Self { KeyRejected(KeyRejectedReason::TooLarge) }
 
-    pub(crate) fn version_not_supported() -> Self { KeyRejected("VersionNotSupported") }
+    pub(crate) fn version_not_supported() -> Self {
+        KeyRejected(KeyRejectedReason::VersionNotSupported)
+    }
 
-    pub(crate) fn wrong_algorithm() -> Self { KeyRejected("WrongAlgorithm") }
+    pub(crate) fn wrong_algorithm() -> Self { KeyRejected(KeyRejectedReason::WrongAlgorithm) }
 
     #[cfg(feature = "rsa_signing")]
     pub(crate) fn private_modulus_len_not_multiple_of_512_bits() -> Self {
-        KeyRejected("PrivateModulusLenNotMultipleOf512Bits")
+        KeyRejected(KeyRejectedReason::PrivateModulusLenNotMultipleOf512Bits)
+    }
+
+    pub(crate) fn unexpected_error() -> Self { KeyRejected(KeyRejectedReason::UnexpectedError) }
+}
+
+// Like `#[derive(Debug)]` when `KeyRejected` wrapped the description.
+impl core::fmt::Debug for KeyRejected {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_tuple("KeyRejected").field(&self.description_()).finish()
     }
+}
+
+/// Why a key was rejected; see `KeyRejected`.
+///
+/// More reasons may be added, and which reason is reported in which
+/// situation may change, so code that matches on this should handle the
+/// reasons it doesn't know about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum KeyRejectedReason {
+    /// The key is encrypted and either the password is wrong or the encrypted
+    /// key was corrupted.
+    DecryptionFailed,
+
+    /// The mathematical relationship between two or more components that a
+    /// valid key requires doesn't hold.
+    InconsistentComponents,
+
+    /// A component of the key has an invalid value.
+    InvalidComponent,
+
+    /// The encoding of the key is invalid, e.g. it isn't in the expected
+    /// format.
+    InvalidEncoding,
+
+    /// The public key is required but the encoding doesn't include it.
+    PublicKeyIsMissing,
+
+    /// A primary component of the key, e.g. the modulus, is too small.
+    TooSmall,
 
-    pub(crate) fn unexpected_error() -> Self { KeyRejected("UnexpectedError") }
+    /// A primary component of the key is unnecessarily large.
+    TooLarge,
+
+    /// The key is encoded in a version of the encoding that isn't supported,
+    /// e.g. a multi-prime RSA key.
+    VersionNotSupported,
+
+    /// The key isn't valid for the algorithm it was used with.
+    WrongAlgorithm,
+
+    /// The length of the RSA modulus isn't a multiple of 512 bits.
+    PrivateModulusLenNotMultipleOf512Bits,
+
+    /// An unexpected error; report this as a bug.
+    UnexpectedError,
 }
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "std")]
 impl std::error::Error for KeyRejected {
     fn cause(&self) -> Option<&std::error::Error> { None }
 
     fn description(&self) -> &str { self.description_() }
 }
 
-impl std::fmt::Display for KeyRejected {
+impl core::fmt::Display for KeyRejected {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         f.write_str(self.description_())
     }
@@ -189,3 +287,203 @@ impl std::fmt::Display for KeyRejected {
 impl From<KeyRejected> for Unspecified {
     fn from(_: KeyRejected) -> Self { Unspecified }
 }
+
+/// An error that says which operation failed and, when it isn't secret, why.
+///
+/// A few functions have variants that return a `Detailed` error instead of
+/// `Unspecified`, e.g. `aead::open_in_place_detailed()`, so that a failure
+/// deep within a protocol implementation can be debugged from its `Display`
+/// or `Debug` output alone. A `Detailed` error converts to `Unspecified` with
+/// `?`. Like `Unspecified`, it never reports anything secret: e.g. a wrong
+/// tag or a wrong key is reported as `Reason::Unspecified`.
+///
+/// ```
+/// use ring::{aead, error};
+///
+/// let key = aead::OpeningKey::new(&aead::AES_128_GCM, &[0; 16]).unwrap();
+/// let mut in_out = [0u8; 8];
+/// let err = aead::open_in_place_detailed(&key, &[0; 12], &[], 0, &mut in_out).unwrap_err();
+/// assert_eq!(err.operation(), "aead::open_in_place");
+/// assert_eq!(
+///     err.reason(),
+///     error::Reason::TooShort {
+///         input: "ciphertext_and_tag_modified_in_place",
+///         min: 16,
+///         actual: 8,
+///     }
+/// );
+/// assert_eq!(
+///     err.to_string(),
+///     "aead::open_in_place failed: `ciphertext_and_tag_modified_in_place` is 8 bytes long; at \
+///      least 16 bytes are required"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Detailed {
+    operation: &'static str,
+    reason: Reason,
+}
+
+impl Detailed {
+    #[inline]
+    pub(crate) fn new(operation: &'static str, reason: Reason) -> Self {
+        Self { operation, reason }
+    }
+
+    /// The name of the function that failed, e.g. `"aead::seal_in_place"`.
+    #[inline]
+    pub fn operation(&self) -> &'static str { self.operation }
+
+    /// Why the operation failed.
+    #[inline]
+    pub fn reason(&self) -> Reason { self.reason }
+}
+
+/// Why an operation failed; see `Detailed`.
+///
+/// The lengths are those of the inputs as passed, in bytes, and `input` is
+/// the name of the parameter or of the part of it that has the wrong length.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Reason {
+    /// An input doesn't have the required length.
+    WrongLength {
+        /// The input.
+        input: &'static str,
+        /// The required length.
+        expected: usize,
+        /// The input's length.
+        actual: usize,
+    },
+
+    /// An input is shorter than the minimum length.
+    TooShort {
+        /// The input.
+        input: &'static str,
+        /// The minimum length.
+        min: usize,
+        /// The input's length.
+        actual: usize,
+    },
+
+    /// An input is longer than the maximum length.
+    TooLong {
+        /// The input.
+        input: &'static str,
+        /// The maximum length.
+        max: usize,
+        /// The input's length.
+        actual: usize,
+    },
+
+    /// A key was rejected.
+    KeyRejected(KeyRejectedReason),
+
+    /// The reason isn't reported, e.g. because it's secret, as when an
+    /// authentication tag is wrong.
+    Unspecified,
+}
+
+impl core::fmt::Display for Detailed {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{} failed", self.operation)?;
+        match self.reason {
+            Reason::WrongLength {
+                input,
+                expected,
+                actual,
+            } => write!(
+                f,
+                ": `{}` is {} bytes long; {} bytes are required",
+                input, actual, expected
+            ),
+            Reason::TooShort { input, min, actual } => write!(
+                f,
+                ": `{}` is {} bytes long; at least {} bytes are required",
+                input, actual, min
+            ),
+            Reason::TooLong { input, max, actual } => write!(
+                f,
+                ": `{}` is {} bytes long; at most {} bytes are allowed",
+                input, actual, max
+            ),
+            Reason::KeyRejected(reason) => write!(f, ": {}", KeyRejected(reason)),
+            Reason::Unspecified => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Detailed {
+    fn cause(&self) -> Option<&std::error::Error> { None }
+
+    fn description(&self) -> &str { self.operation }
+}
+
+impl From<Detailed> for Unspecified {
+    fn from(_: Detailed) -> Self { Unspecified }
+}
+
+/// The error returned by `init_checked()`: a CPU feature that *ring* requires
+/// on the target is missing, or a known-answer self-test failed.
+///
+/// ```
+/// # fn main() -> Result<(), ring::error::MissingFeatures> {
+/// if let Err(err) = ring::init_checked() {
+///     eprintln!("can't start: {}", err);
+///     return Err(err);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MissingFeatures {
+    missing: cpu::Features,
+    failed_self_test: Option<&'static str>,
+}
+
+impl MissingFeatures {
+    pub(crate) fn features(missing: cpu::Features) -> Self {
+        Self {
+            missing,
+            failed_self_test: None,
+        }
+    }
+
+    pub(crate) fn self_test(algorithm: &'static str) -> Self {
+        Self {
+            missing: cpu::Features::NONE,
+            failed_self_test: Some(algorithm),
+        }
+    }
+
+    /// The required CPU features that are missing; none if a self-test
+    /// failed instead.
+    #[inline]
+    pub fn missing(&self) -> cpu::Features { self.missing }
+
+    /// The name of the algorithm whose self-test failed, e.g. `"AES-128-GCM"`,
+    /// if any.
+    #[inline]
+    pub fn failed_self_test(&self) -> Option<&'static str> { self.failed_self_test }
+}
+
+impl core::fmt::Display for MissingFeatures {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.failed_self_test {
+            Some(algorithm) => write!(f, "the {} self-test failed", algorithm),
+            None => write!(f, "required CPU features are missing: {:?}", self.missing),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MissingFeatures {
+    fn cause(&self) -> Option<&std::error::Error> { None }
+
+    fn description(&self) -> &str { "ring::error::MissingFeatures" }
+}
+
+impl From<MissingFeatures> for Unspecified {
+    fn from(_: MissingFeatures) -> Self { Unspecified }
+}
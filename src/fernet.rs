@@ -0,0 +1,229 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! [Fernet] tokens, for interoperating with services that use the `Fernet`
+//! class of Python's `cryptography` package.
+//!
+//! A key is 32 bytes, the HMAC-SHA256 signing key followed by the AES-128
+//! encryption key, and is usually stored as URL-safe Base64 with padding, as
+//! `generate_key()` returns it. A token is the URL-safe Base64 encoding, with
+//! padding, of
+//!
+//! ```text
+//! 0x80 || timestamp || iv || ciphertext || hmac
+//! ```
+//!
+//! where `timestamp` is the 8-byte big-endian UNIX time at which the token
+//! was created, `ciphertext` is the plaintext encrypted with AES-128-CBC
+//! with PKCS#7 padding, and `hmac` is the HMAC-SHA256 of everything before
+//! it. The MAC is verified before anything is decrypted.
+//!
+//! New protocols should use an AEAD from `ring::aead` instead.
+//!
+//! Like `otp`, the functions take the current time instead of reading the
+//! clock, in seconds since the UNIX epoch.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{fernet, rand};
+//!
+//! # fn fernet() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let key = fernet::Key::from_base64(&fernet::generate_key(&rng)?)?;
+//!
+//! let token = fernet::encrypt(&key, b"secret message", 1_000_000_000, &rng)?;
+//!
+//! // The token is accepted for 60 seconds...
+//! let ttl = Some(60);
+//! assert_eq!(fernet::decrypt(&key, &token, ttl, 1_000_000_060)?, b"secret message");
+//!
+//! // ...and then rejected.
+//! assert!(fernet::decrypt(&key, &token, ttl, 1_000_000_061).is_err());
+//! # Ok(())
+//! # }
+//! # fn main() { fernet().unwrap() }
+//! ```
+//!
+//! [Fernet]: https://github.com/fernet/spec/blob/master/Spec.md
+
+use crate::{
+    aes_cbc, digest, error, hmac,
+    io::base64::{self, Alphabet, Padding},
+    rand, zeroize,
+};
+use alloc::{string::String, vec::Vec};
+
+/// The length of a key, before it is Base64-encoded.
+pub const KEY_LEN: usize = 2 * 16;
+
+/// How far in the future the timestamp of a token may be, in seconds, when
+/// `decrypt` is given a TTL, to allow for clocks that are off.
+pub const MAX_CLOCK_SKEW: u64 = 60;
+
+const VERSION: u8 = 0x80;
+const TIMESTAMP_LEN: usize = 8;
+const IV_LEN: usize = aes_cbc::BLOCK_LEN;
+const HMAC_LEN: usize = digest::SHA256_OUTPUT_LEN;
+
+// `version || timestamp || iv`.
+const HEADER_LEN: usize = 1 + TIMESTAMP_LEN + IV_LEN;
+
+static BASE64: base64::Config = base64::Config::new(Alphabet::UrlSafe, Padding::Required);
+
+/// A key for encrypting and decrypting tokens.
+pub struct Key {
+    signing_key: hmac::SigningKey,
+    encryption_key: aes_cbc::Key,
+}
+
+impl Key {
+    /// Constructs a key from its `KEY_LEN` bytes.
+    pub fn new(value: &[u8]) -> Result<Self, error::Unspecified> {
+        if value.len() != KEY_LEN {
+            return Err(error::Unspecified);
+        }
+        let (signing_key, encryption_key) = value.split_at(KEY_LEN / 2);
+        Ok(Self {
+            signing_key: hmac::SigningKey::new(&digest::SHA256, signing_key),
+            encryption_key: aes_cbc::Key::new(encryption_key)?,
+        })
+    }
+
+    /// Constructs a key from its URL-safe Base64 encoding with padding, the
+    /// form that `generate_key()` and Python's `Fernet.generate_key()`
+    /// return.
+    pub fn from_base64(encoded: &str) -> Result<Self, error::Unspecified> {
+        let mut value = base64::decode(encoded, &BASE64)?;
+        let key = Self::new(&value);
+        zeroize::zeroize(&mut value);
+        key
+    }
+}
+
+/// Generates a random key, encoded as URL-safe Base64 with padding.
+pub fn generate_key(rng: &rand::SecureRandom) -> Result<String, error::Unspecified> {
+    let mut value = [0; KEY_LEN];
+    rng.fill(&mut value)?;
+    let encoded = base64::encode(&value, &BASE64);
+    zeroize::zeroize(&mut value);
+    Ok(encoded)
+}
+
+/// Encrypts `plaintext` into a token created at the UNIX time `now`, in
+/// seconds.
+pub fn encrypt(
+    key: &Key, plaintext: &[u8], now: u64, rng: &rand::SecureRandom,
+) -> Result<String, error::Unspecified> {
+    let mut iv = [0; IV_LEN];
+    rng.fill(&mut iv)?;
+
+    let ciphertext = aes_cbc::encrypt_padded(&key.encryption_key, &iv, plaintext);
+    let mut token = Vec::with_capacity(HEADER_LEN + ciphertext.len() + HMAC_LEN);
+    token.push(VERSION);
+    token.extend_from_slice(&now.to_be_bytes());
+    token.extend_from_slice(&iv);
+    token.extend_from_slice(&ciphertext);
+    let tag = hmac::sign(&key.signing_key, &token);
+    token.extend_from_slice(tag.as_ref());
+    Ok(base64::encode(&token, &BASE64))
+}
+
+/// Decrypts `token` at the UNIX time `now`, in seconds.
+///
+/// If `ttl` is `Some`, the token is rejected if it was created more than
+/// `ttl` seconds before `now` or more than `MAX_CLOCK_SKEW` seconds after
+/// it. Otherwise its age isn't checked.
+///
+/// Fails if `token` wasn't created with `key`, if it was modified, or if it
+/// isn't valid at `now`.
+pub fn decrypt(
+    key: &Key, token: &str, ttl: Option<u64>, now: u64,
+) -> Result<Vec<u8>, error::Unspecified> {
+    let token = base64::decode(token, &BASE64)?;
+    if token.len() < HEADER_LEN + HMAC_LEN || token[0] != VERSION {
+        return Err(error::Unspecified);
+    }
+    let (signed, tag) = token.split_at(token.len() - HMAC_LEN);
+    hmac::verify_with_own_key(&key.signing_key, signed, tag)?;
+
+    let mut timestamp = [0; TIMESTAMP_LEN];
+    timestamp.copy_from_slice(&signed[1..][..TIMESTAMP_LEN]);
+    let timestamp = u64::from_be_bytes(timestamp);
+    if let Some(ttl) = ttl {
+        if timestamp.saturating_add(ttl) < now || timestamp > now.saturating_add(MAX_CLOCK_SKEW) {
+            return Err(error::Unspecified);
+        }
+    }
+
+    let mut iv = [0; IV_LEN];
+    iv.copy_from_slice(&signed[(1 + TIMESTAMP_LEN)..HEADER_LEN]);
+    aes_cbc::decrypt_padded(&key.encryption_key, &iv, &signed[HEADER_LEN..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_fernet() {
+        test::from_file("src/fernet_tests.txt", |section, test_case| {
+            let key = Key::from_base64(&consume_str(test_case, "Secret")).unwrap();
+            let now = test_case.consume_usize("Now") as u64;
+            match section {
+                "Encrypt" => {
+                    let iv = test_case.consume_bytes("IV");
+                    let plaintext = consume_str(test_case, "Plaintext");
+                    let expected = consume_str(test_case, "Token");
+                    let rng = test::rand::FixedSliceRandom { bytes: &iv };
+                    let token = encrypt(&key, plaintext.as_bytes(), now, &rng).unwrap();
+                    assert_eq!(token, expected);
+                    assert_eq!(decrypt(&key, &token, None, now).unwrap(), plaintext.as_bytes());
+                },
+                "Decrypt" => {
+                    let ttl = consume_ttl(test_case);
+                    let token = consume_str(test_case, "Token");
+                    let plaintext = consume_str(test_case, "Plaintext");
+                    assert_eq!(decrypt(&key, &token, ttl, now).unwrap(), plaintext.as_bytes());
+                },
+                "Invalid" => {
+                    let ttl = consume_ttl(test_case);
+                    let token = consume_str(test_case, "Token");
+                    assert!(decrypt(&key, &token, ttl, now).is_err());
+                },
+                _ => unreachable!(),
+            }
+            Ok(())
+        });
+    }
+
+    // The quoted attributes are ASCII strings.
+    fn consume_str(test_case: &mut test::TestCase, key: &str) -> String {
+        String::from_utf8(test_case.consume_bytes(key)).unwrap()
+    }
+
+    fn consume_ttl(test_case: &mut test::TestCase) -> Option<u64> {
+        test_case
+            .consume_optional_string("TTL")
+            .map(|ttl| ttl.parse().unwrap())
+    }
+
+    #[test]
+    fn test_key_len() {
+        for len in 0..=(KEY_LEN + 1) {
+            assert_eq!(Key::new(&[0; KEY_LEN + 1][..len]).is_ok(), len == KEY_LEN);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Entry points for fuzzing *ring*'s parsers.
+//!
+//! This module is only enabled by the `fuzzing` feature and isn't meant for
+//! anything else. Every function takes arbitrary bytes, so that fuzz targets
+//! can pass the fuzzer's input to them directly, and each of them either
+//! parses its input or fails; none panics for any input. The cargo-fuzz
+//! targets in the `fuzz` directory of *ring*'s source repository use them.
+
+use crate::{error, io::der, signature};
+use untrusted;
+
+#[cfg(feature = "alloc")]
+use {crate::pkcs8, alloc::vec::Vec};
+
+/// A public key that is parsed only when a signature is verified with it.
+///
+/// Unlike the key types of `signature`, whose constructors reject keys of the
+/// wrong length, `UnparsedPublicKey` accepts any bytes, so that fuzzing
+/// covers the parsing that `signature::verify()` does.
+#[derive(Clone, Copy, Debug)]
+pub struct UnparsedPublicKey<'a> {
+    algorithm: &'static signature::VerificationAlgorithm,
+    bytes: &'a [u8],
+}
+
+impl<'a> UnparsedPublicKey<'a> {
+    /// Constructs a public key for `algorithm` from `bytes`, of any length.
+    pub fn new(algorithm: &'static signature::VerificationAlgorithm, bytes: &'a [u8]) -> Self {
+        Self { algorithm, bytes }
+    }
+
+    /// Verifies the signature `signature` of the message `msg` with the key.
+    pub fn verify(&self, msg: &[u8], signature: &[u8]) -> Result<(), error::Unspecified> {
+        signature::verify(
+            self.algorithm,
+            untrusted::Input::from(self.bytes),
+            untrusted::Input::from(msg),
+            untrusted::Input::from(signature),
+        )
+    }
+}
+
+/// Parses `input` as an unencrypted PKCS#8 document with a key pair for
+/// `alg`, like `signature::key_pair_from_pkcs8()`.
+#[cfg(feature = "alloc")]
+pub fn key_pair_from_pkcs8(
+    alg: &'static signature::SigningAlgorithm, input: &[u8],
+) -> Result<signature::KeyPair, error::KeyRejected> {
+    signature::key_pair_from_pkcs8(alg, untrusted::Input::from(input))
+}
+
+/// Parses `input` as an unencrypted PKCS#8 document with an Ed25519 key pair,
+/// like `Ed25519KeyPair::from_pkcs8_maybe_unchecked()`, which accepts both v1
+/// and v2 documents.
+pub fn ed25519_key_pair_from_pkcs8(
+    input: &[u8],
+) -> Result<signature::Ed25519KeyPair, error::KeyRejected> {
+    signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(untrusted::Input::from(input))
+}
+
+/// Parses `input` as an unencrypted PKCS#8 document or an `RSAPrivateKey`
+/// with an RSA key pair, like `RSAKeyPair::from_der()`.
+#[cfg(feature = "rsa_signing")]
+pub fn rsa_key_pair_from_der(input: &[u8]) -> Result<signature::RSAKeyPair, error::KeyRejected> {
+    signature::RSAKeyPair::from_der(untrusted::Input::from(input))
+}
+
+/// Decrypts `input`, an encrypted PKCS#8 document, like `pkcs8::decrypt()`.
+#[cfg(feature = "alloc")]
+pub fn decrypt_pkcs8(input: &[u8], password: &[u8]) -> Result<Vec<u8>, error::KeyRejected> {
+    pkcs8::decrypt(untrusted::Input::from(input), password)
+}
+
+/// Parses `signature` as an ECDSA signature in the format of `alg`, ASN.1 or
+/// fixed-length, and checks that `r` and `s` are in range, like
+/// `signature::verify()` does before doing any arithmetic.
+pub fn ecdsa_signature(
+    alg: &'static signature::ECDSAVerification, signature: &[u8],
+) -> Result<(), error::Unspecified> {
+    let _ = alg.parse_signature(untrusted::Input::from(signature))?;
+    Ok(())
+}
+
+/// Parses `input` as a sequence of DER-encoded values with the functions in
+/// `io::der`, descending into constructed values, and returns the number of
+/// values. `INTEGER`s are parsed as positive integers and `BIT STRING`s must
+/// not have unused bits, as *ring* requires everywhere.
+pub fn der(input: &[u8]) -> Result<usize, error::Unspecified> {
+    untrusted::Input::from(input).read_all(error::Unspecified, |input| der_values(input, 0))
+}
+
+// Values that are nested deeper than this are rejected, so that the recursion
+// is bounded.
+const MAX_DER_DEPTH: usize = 32;
+
+fn der_values(input: &mut untrusted::Reader, depth: usize) -> Result<usize, error::Unspecified> {
+    if depth > MAX_DER_DEPTH {
+        return Err(error::Unspecified);
+    }
+    let mut count = 0;
+    while !input.at_end() {
+        if input.peek(der::Tag::Integer as u8) {
+            let _ = der::positive_integer(input)?;
+        } else if input.peek(der::Tag::BitString as u8) {
+            let _ = der::bit_string_with_no_unused_bits(input)?;
+        } else {
+            let (tag, value) = der::read_tag_and_get_value(input)?;
+            if tag & der::CONSTRUCTED != 0 {
+                count += value.read_all(error::Unspecified, |value| der_values(value, depth + 1))?;
+            }
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_der() {
+        // SEQUENCE { INTEGER 128, [0] { NULL }, BIT STRING 0xff }
+        let input = [
+            0x30, 0x0c, 0x02, 0x02, 0x00, 0x80, 0xa0, 0x02, 0x05, 0x00, 0x03, 0x02, 0x00, 0xff,
+        ];
+        assert_eq!(der(&input), Ok(5));
+        assert_eq!(der(&[]), Ok(0));
+        assert!(der(&input[..input.len() - 1]).is_err());
+        assert!(der(&[0x02, 0x01, 0x00]).is_err());
+
+        fn sequence(value: &[u8]) -> Vec<u8> {
+            let mut sequence = vec![0x30, value.len() as u8];
+            sequence.extend_from_slice(value);
+            sequence
+        }
+        let mut nested = vec![];
+        for _ in 0..MAX_DER_DEPTH {
+            nested = sequence(&nested);
+        }
+        assert_eq!(der(&nested), Ok(MAX_DER_DEPTH));
+        assert!(der(&sequence(&nested)).is_err());
+    }
+
+    #[test]
+    fn test_ecdsa_signature() {
+        let mut fixed = [0u8; 64];
+        assert!(ecdsa_signature(&signature::ECDSA_P256_SHA256_FIXED, &fixed).is_err());
+        fixed[31] = 1;
+        fixed[63] = 1;
+        assert!(ecdsa_signature(&signature::ECDSA_P256_SHA256_FIXED, &fixed).is_ok());
+        assert!(ecdsa_signature(&signature::ECDSA_P384_SHA384_FIXED, &fixed).is_err());
+
+        let asn1 = [0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01];
+        assert!(ecdsa_signature(&signature::ECDSA_P256_SHA256_ASN1, &asn1).is_ok());
+        assert!(ecdsa_signature(&signature::ECDSA_P256_SHA256_ASN1, &asn1[1..]).is_err());
+    }
+
+    #[test]
+    fn test_unparsed_public_key() {
+        for len in 0..40 {
+            let key = UnparsedPublicKey::new(&signature::ED25519, &[0; 40][..len]);
+            assert!(key.verify(b"", &[0; 64]).is_err());
+        }
+    }
+}
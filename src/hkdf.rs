@@ -19,7 +19,8 @@
 //! In most situations, it is best to use `extract_and_expand` to do both the
 //! HKDF-Extract and HKDF-Expand as one atomic operation. It is only necessary
 //! to use the separate `expand` and `extract` functions if a single derived
-//! `PRK` (defined in RFC 5869) is used more than once.
+//! `PRK` (defined in RFC 5869) is used more than once. The `try_` variants
+//! fail instead of panicking when the output is too long.
 //!
 //! Salts have type `hmac::SigningKey` instead of `&[u8]` because they are
 //! frequently used for multiple HKDF operations, and it is more efficient to
@@ -29,7 +30,7 @@
 //!
 //! [RFC 5869]: https://tools.ietf.org/html/rfc5869
 
-use crate::hmac;
+use crate::{error, hmac};
 
 /// Fills `out` with the output of the HKDF Extract-and-Expand operation for
 /// the given inputs.
@@ -55,6 +56,15 @@ pub fn extract_and_expand(salt: &hmac::SigningKey, secret: &[u8], info: &[u8], o
     expand(&prk, info, out)
 }
 
+/// Like `extract_and_expand`, but fails instead of panicking if `expand`
+/// would panic; see `try_expand`.
+pub fn try_extract_and_expand(
+    salt: &hmac::SigningKey, secret: &[u8], info: &[u8], out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    let prk = extract(salt, secret);
+    try_expand(&prk, info, out)
+}
+
 /// The HKDF-Extract operation.
 ///
 /// | Parameter                 | RFC 5869 Term
@@ -91,39 +101,50 @@ pub fn extract(salt: &hmac::SigningKey, secret: &[u8]) -> hmac::SigningKey {
 /// size of the digest algorithm, i.e. if
 /// `out.len() > 255 * salt.digest_algorithm().output_len`. This is the limit
 /// imposed by the HKDF specification, and is necessary to prevent overflow of
-/// the 8-bit iteration counter in the expansion step.
+/// the 8-bit iteration counter in the expansion step. Use `try_expand` when
+/// the length isn't known to be valid, e.g. when it comes from configuration.
 pub fn expand(prk: &hmac::SigningKey, info: &[u8], out: &mut [u8]) {
+    try_expand(prk, info, out).unwrap()
+}
+
+/// Like `expand`, but fails instead of panicking if
+/// `out.len() > 255 * prk.digest_algorithm().output_len`.
+///
+/// ```
+/// use ring::{digest, hkdf, hmac};
+///
+/// let prk = hmac::SigningKey::new(&digest::SHA256, b"prk");
+/// let mut out = [0u8; 255 * 32];
+/// assert!(hkdf::try_expand(&prk, b"info", &mut out).is_ok());
+/// let mut out = [0u8; (255 * 32) + 1];
+/// assert!(hkdf::try_expand(&prk, b"info", &mut out).is_err());
+/// ```
+pub fn try_expand(
+    prk: &hmac::SigningKey, info: &[u8], out: &mut [u8],
+) -> Result<(), error::Unspecified> {
     let digest_alg = prk.digest_algorithm();
-    assert!(out.len() <= 255 * digest_alg.output_len);
+    if out.len() > 255 * digest_alg.output_len {
+        return Err(error::Unspecified);
+    }
     assert!(digest_alg.block_len >= digest_alg.output_len);
 
     let mut ctx = hmac::SigningContext::with_key(prk);
 
     let mut n = 1u8;
-    let mut pos = 0;
-    loop {
+    for chunk in out.chunks_mut(digest_alg.output_len) {
         ctx.update(info);
         ctx.update(&[n]);
 
         let t = ctx.sign();
+        let t_bytes = t.as_ref();
 
         // Append `t` to the output.
-        let to_copy = if out.len() - pos < digest_alg.output_len {
-            out.len() - pos
-        } else {
-            digest_alg.output_len
-        };
-        let t_bytes = t.as_ref();
-        for i in 0..to_copy {
-            out[pos + i] = t_bytes[i];
-        }
-        if to_copy < digest_alg.output_len {
-            break;
-        }
-        pos += digest_alg.output_len;
+        chunk.copy_from_slice(&t_bytes[..chunk.len()]);
 
         ctx = hmac::SigningContext::with_key(prk);
         ctx.update(t_bytes);
-        n += 1;
+        // This wraps only after the 255th and final chunk.
+        n = n.wrapping_add(1);
     }
+    Ok(())
 }
@@ -154,7 +154,10 @@
 //! [code for `ring::hkdf`]:
 //!     https://github.com/briansmith/ring/blob/master/src/hkdf.rs
 
-use crate::{constant_time, digest, error, rand};
+use crate::{constant_time, digest, error, locked::Locked, rand};
+
+#[cfg(all(feature = "alloc", feature = "zeroize"))]
+use alloc::vec::Vec;
 
 /// An HMAC signature.
 ///
@@ -164,8 +167,10 @@ use crate::{constant_time, digest, error, rand};
 pub struct Signature(digest::Digest);
 
 /// A key to use for HMAC signing.
+///
+/// A single `SigningKey` may be shared across multiple threads safely.
 pub struct SigningKey {
-    ctx_prototype: SigningContext,
+    ctx_prototype: Locked<SigningContext>,
 }
 
 derive_debug_via_self!(SigningKey, self.ctx_prototype.inner.algorithm());
@@ -175,6 +180,12 @@ impl AsRef<[u8]> for Signature {
     fn as_ref(&self) -> &[u8] { self.0.as_ref() }
 }
 
+impl Signature {
+    /// Overwrites the signature, when it is a secret (e.g. in PBKDF2), with
+    /// zeros.
+    pub(crate) fn zeroize(&mut self) { self.0.zeroize() }
+}
+
 impl SigningKey {
     /// Generate an HMAC signing key using the given digest algorithm with a
     /// random value generated from `rng`.
@@ -230,18 +241,20 @@ impl SigningKey {
     /// removed in a future version of *ring*.
     pub fn new(digest_alg: &'static digest::Algorithm, key_value: &[u8]) -> SigningKey {
         let mut key = SigningKey {
-            ctx_prototype: SigningContext {
+            ctx_prototype: Locked::new(SigningContext {
                 inner: digest::Context::new(digest_alg),
                 outer: digest::Context::new(digest_alg),
-            },
+            }),
         };
 
-        let key_hash;
-        let key_value = if key_value.len() <= digest_alg.block_len {
-            key_value
+        let mut key_hash = if key_value.len() <= digest_alg.block_len {
+            None
         } else {
-            key_hash = digest::digest(digest_alg, key_value);
-            key_hash.as_ref()
+            Some(digest::digest(digest_alg, key_value))
+        };
+        let key_value = match &key_hash {
+            Some(key_hash) => key_hash.as_ref(),
+            None => key_value,
         };
 
         const IPAD: u8 = 0x36;
@@ -259,15 +272,32 @@ impl SigningKey {
             key.ctx_prototype.outer.update(&[OPAD]);
         }
 
+        if let Some(key_hash) = key_hash.as_mut() {
+            key_hash.zeroize();
+        }
+
         key
     }
 
+    /// Like `SigningKey::new()`, but takes ownership of `key_value` so that
+    /// it is overwritten when it is no longer needed.
+    #[cfg(all(feature = "alloc", feature = "zeroize"))]
+    pub fn from_zeroizing(
+        digest_alg: &'static digest::Algorithm, key_value: ::zeroize::Zeroizing<Vec<u8>>,
+    ) -> SigningKey {
+        SigningKey::new(digest_alg, &key_value)
+    }
+
     /// The digest algorithm for the key.
     pub fn digest_algorithm(&self) -> &'static digest::Algorithm {
         self.ctx_prototype.inner.algorithm()
     }
 }
 
+// The key is in the digest contexts, which are zeroized when dropped.
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SigningKey {}
+
 /// A context for multi-step (Init-Update-Finish) HMAC signing.
 ///
 /// Use `sign` for single-step HMAC signing.
@@ -281,6 +311,9 @@ pub struct SigningContext {
 
 derive_debug_via_self!(SigningContext, self.inner.algorithm());
 
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SigningContext {}
+
 impl SigningContext {
     /// Constructs a new HMAC signing context using the given digest algorithm
     /// and key.
@@ -325,12 +358,31 @@ impl SigningContext {
 ///
 /// C analog: `HMAC_CTX_init` + `HMAC_Update` + `HMAC_Final`.
 pub fn sign(key: &SigningKey, data: &[u8]) -> Signature {
-    let mut ctx = SigningContext::with_key(key);
-    ctx.update(data);
-    ctx.sign()
+    // Equivalent to using a `SigningContext`, but without copying the
+    // contexts of `key`, which matters for short inputs.
+    let inner = key.ctx_prototype.inner.finish_with(data);
+    Signature(key.ctx_prototype.outer.finish_with(inner.as_ref()))
+}
+
+/// Like `sign()`, but writes the signature into the beginning of `out` and
+/// returns its length, which is `key.digest_algorithm().output_len`, instead
+/// of returning a `Signature`.
+///
+/// Fails if `out` is too short to hold the signature.
+pub fn sign_into(
+    key: &SigningKey, data: &[u8], out: &mut [u8],
+) -> Result<usize, error::Unspecified> {
+    let signature = sign(key, data);
+    let signature = signature.as_ref();
+    out.get_mut(..signature.len())
+        .ok_or(error::Unspecified)?
+        .copy_from_slice(signature);
+    Ok(signature.len())
 }
 
 /// A key to use for HMAC authentication.
+///
+/// A single `VerificationKey` may be shared across multiple threads safely.
 pub struct VerificationKey {
     wrapped: SigningKey,
 }
@@ -351,11 +403,23 @@ impl VerificationKey {
         }
     }
 
+    /// Like `VerificationKey::new()`, but takes ownership of `key_value` so
+    /// that it is overwritten when it is no longer needed.
+    #[cfg(all(feature = "alloc", feature = "zeroize"))]
+    pub fn from_zeroizing(
+        digest_alg: &'static digest::Algorithm, key_value: ::zeroize::Zeroizing<Vec<u8>>,
+    ) -> VerificationKey {
+        VerificationKey::new(digest_alg, &key_value)
+    }
+
     /// The digest algorithm for the key.
     #[inline]
     pub fn digest_algorithm(&self) -> &'static digest::Algorithm { self.wrapped.digest_algorithm() }
 }
 
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for VerificationKey {}
+
 /// Calculates the HMAC of `data` using the key `key`, and verifies whether the
 /// resultant value equals `signature`, in one step.
 ///
@@ -0,0 +1,173 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use crate::{aead, cpu, digest, error};
+
+/// Detects the CPU features, checks that those that *ring* requires on the
+/// target are available, and runs known-answer self-tests of SHA-256,
+/// AES-GCM, and ChaCha20-Poly1305 with the implementations that the features
+/// select, so that an application or enclave can refuse to start, with an
+/// error that says why, instead of failing later within e.g. `aead`.
+///
+/// Within SGX enclaves, AES-NI and `PCLMULQDQ` are required, because without
+/// them AES-GCM uses tables indexed by secret data and the untrusted host can
+/// observe which cache lines the enclave accesses; `SystemRandom` is also
+/// tested, which runs the start-up health tests of its `RDSEED` or `RDRAND`
+/// source. Nothing is required on other targets.
+///
+/// To detect the features some other way, e.g. with
+/// `cpu::sgx::detect_features()`, or to disable some of them, do so before
+/// calling this. Calling this more than once is harmless.
+///
+/// ```
+/// let capabilities = ring::init_checked().unwrap();
+/// println!("hardware AES-GCM: {}", capabilities.hardware_aes_gcm());
+/// ```
+pub fn init_checked() -> Result<Capabilities, error::MissingFeatures> {
+    let features = cpu::features();
+    if let Some(missing) = features.missing(&cpu::Features::REQUIRED) {
+        return Err(error::MissingFeatures::features(missing));
+    }
+    self_test("SHA-256", sha256_self_test())?;
+    self_test("AES-128-GCM", aes_128_gcm_self_test())?;
+    self_test("ChaCha20-Poly1305", chacha20_poly1305_self_test())?;
+    #[cfg(all(target_arch = "x86_64", target_env = "sgx"))]
+    self_test("SystemRandom", system_random_self_test())?;
+    Ok(Capabilities { features })
+}
+
+/// The capabilities that `init_checked()` found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    features: cpu::Features,
+}
+
+impl Capabilities {
+    /// The CPU features that *ring* uses; see `cpu::features()`.
+    #[inline]
+    pub fn features(&self) -> cpu::Features { self.features }
+
+    /// Whether AES-GCM uses the CPU's AES and carry-less multiplication
+    /// instructions.
+    #[inline]
+    pub fn hardware_aes_gcm(&self) -> bool {
+        self.features.aes() && (self.features.pclmulqdq() || self.features.pmull())
+    }
+}
+
+fn self_test(
+    algorithm: &'static str, result: Result<(), error::Unspecified>,
+) -> Result<(), error::MissingFeatures> {
+    result.map_err(|error::Unspecified| error::MissingFeatures::self_test(algorithm))
+}
+
+// The expected outputs were generated with Python's `hashlib` and
+// `cryptography` packages.
+
+const MESSAGE: &[u8; MESSAGE_LEN] = b"ring self-test";
+const MESSAGE_LEN: usize = 14;
+const AD: &[u8] = b"ad";
+
+fn sha256_self_test() -> Result<(), error::Unspecified> {
+    const EXPECTED: [u8; 32] = [
+        0x19, 0x8b, 0xfb, 0xa4, 0xe9, 0x93, 0x47, 0x63, 0xe6, 0xa8, 0x4e, 0x40, 0x8b, 0x23, 0xfa,
+        0x30, 0x62, 0x99, 0x69, 0x8f, 0x9c, 0xdd, 0xea, 0xef, 0xff, 0x36, 0x9b, 0xd4, 0x80, 0x4c,
+        0x37, 0xa2,
+    ];
+    check(digest::digest(&digest::SHA256, MESSAGE).as_ref() == &EXPECTED[..])
+}
+
+fn aes_128_gcm_self_test() -> Result<(), error::Unspecified> {
+    const KEY: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+    const EXPECTED: [u8; 30] = [
+        0xe1, 0x05, 0xc9, 0xa9, 0x46, 0x68, 0x92, 0x38, 0x2d, 0xff, 0x15, 0xef, 0x45, 0xd7, 0x44,
+        0x2f, 0x0e, 0xaa, 0xbc, 0xa9, 0xa1, 0x91, 0x3b, 0x13, 0xd0, 0xe7, 0xca, 0x19, 0xe6, 0x06,
+    ];
+    aead_self_test(&aead::AES_128_GCM, &KEY, &EXPECTED)?;
+
+    // Long inputs may use a different implementation than short ones. This
+    // is the SHA-256 digest of the sealed zeros.
+    const EXPECTED_LONG: [u8; 32] = [
+        0x85, 0x01, 0xe3, 0x41, 0x04, 0xb4, 0x6c, 0x17, 0x30, 0x62, 0x5d, 0x6f, 0x57, 0x9b, 0x8d,
+        0x8c, 0x4b, 0x3e, 0xa9, 0xaa, 0x23, 0x00, 0x4b, 0x5e, 0x8a, 0xb9, 0xec, 0xdf, 0xf3, 0xc7,
+        0x3d, 0x19,
+    ];
+    let key = aead::SealingKey::new(&aead::AES_128_GCM, &KEY)?;
+    let mut in_out = [0u8; 1024 + aead::MAX_TAG_LEN];
+    let len = aead::seal_in_place(&key, &NONCE, AD, &mut in_out, aead::MAX_TAG_LEN)?;
+    check(digest::digest(&digest::SHA256, &in_out[..len]).as_ref() == &EXPECTED_LONG[..])
+}
+
+fn chacha20_poly1305_self_test() -> Result<(), error::Unspecified> {
+    const KEY: [u8; 32] = [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+        25, 26, 27, 28, 29, 30, 31,
+    ];
+    const EXPECTED: [u8; 30] = [
+        0xfb, 0x92, 0x66, 0x67, 0x09, 0x64, 0xc0, 0x2c, 0xd1, 0xae, 0x4b, 0x96, 0xeb, 0x69, 0x1d,
+        0xf0, 0xd4, 0x34, 0x55, 0x63, 0x3f, 0x01, 0x27, 0xcd, 0x62, 0x60, 0x8c, 0x12, 0x4d, 0x13,
+    ];
+    aead_self_test(&aead::CHACHA20_POLY1305, &KEY, &EXPECTED)
+}
+
+const NONCE: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+// Seals `MESSAGE`, checks that the result is `expected`, and opens it again.
+fn aead_self_test(
+    algorithm: &'static aead::Algorithm, key: &[u8], expected: &[u8; 30],
+) -> Result<(), error::Unspecified> {
+    let mut in_out = [0u8; MESSAGE_LEN + aead::MAX_TAG_LEN];
+    in_out[..MESSAGE.len()].copy_from_slice(MESSAGE);
+    let sealing_key = aead::SealingKey::new(algorithm, key)?;
+    let len = aead::seal_in_place(&sealing_key, &NONCE, AD, &mut in_out, aead::MAX_TAG_LEN)?;
+    check(in_out[..len] == expected[..])?;
+    let opening_key = aead::OpeningKey::new(algorithm, key)?;
+    let opened = aead::open_in_place(&opening_key, &NONCE, AD, 0, &mut in_out[..len])?;
+    check(opened[..] == MESSAGE[..])
+}
+
+#[cfg(all(target_arch = "x86_64", target_env = "sgx"))]
+fn system_random_self_test() -> Result<(), error::Unspecified> {
+    use crate::rand::{SecureRandom, SystemRandom};
+    SystemRandom::new().fill(&mut [0; 32])
+}
+
+fn check(ok: bool) -> Result<(), error::Unspecified> {
+    if ok {
+        Ok(())
+    } else {
+        Err(error::Unspecified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_checked() {
+        let capabilities = init_checked().unwrap();
+        assert_eq!(capabilities.features(), cpu::features());
+        assert_eq!(init_checked(), Ok(capabilities));
+    }
+
+    #[test]
+    fn test_self_test() {
+        assert_eq!(self_test("SHA-256", Ok(())), Ok(()));
+        let err = self_test("SHA-256", Err(error::Unspecified)).unwrap_err();
+        assert_eq!(err.failed_self_test(), Some("SHA-256"));
+        assert_eq!(err.missing(), cpu::Features::NONE);
+        assert_eq!(err.to_string(), "the SHA-256 self-test failed");
+    }
+}
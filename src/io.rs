@@ -0,0 +1,34 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Serialization and deserialization of the encodings used for keys and
+//! signatures.
+
+#[cfg(feature = "alloc")]
+pub mod base64;
+
+pub mod der;
+pub mod hex;
+
+mod positive;
+
+pub use self::positive::Positive;
+
+// Returns 0xff if `a < b` and 0 otherwise, without branching.
+#[inline]
+fn lt_mask(a: u8, b: u8) -> u8 { (u16::from(a).wrapping_sub(u16::from(b)) >> 8) as u8 }
+
+// Returns 0xff if `lo <= a <= hi` and 0 otherwise, without branching.
+#[inline]
+fn range_mask(a: u8, lo: u8, hi: u8) -> u8 { !lt_mask(a, lo) & lt_mask(a, hi + 1) }
@@ -0,0 +1,319 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Base64 with the standard alphabet ([RFC 4648 Section 4]) and the URL-safe
+//! alphabet ([RFC 4648 Section 5]).
+//!
+//! Decoding is strict: padding must be present or absent as the `Config`
+//! requires, whitespace is rejected unless the `Config` allows it, and any
+//! unused trailing bits must be zero, so every value has exactly one
+//! encoding.
+//!
+//! The conversions between digits and their values are done with arithmetic
+//! instead of table lookups, so that the memory access pattern doesn't
+//! depend on the data; encoding and decoding private keys doesn't leak them
+//! through the cache. Invalid digits and nonzero unused bits are only
+//! reported after the whole input has been decoded, so the timing doesn't
+//! depend on the digits either. The output length, the positions of any
+//! whitespace and padding, and whether decoding fails necessarily do depend
+//! on the input.
+//!
+//! [RFC 4648 Section 4]: https://tools.ietf.org/html/rfc4648#section-4
+//! [RFC 4648 Section 5]: https://tools.ietf.org/html/rfc4648#section-5
+
+use super::{lt_mask, range_mask};
+use crate::{
+    constant_time::{self, Choice, CtOption},
+    error,
+};
+use alloc::{string::String, vec::Vec};
+
+/// An alphabet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alphabet {
+    /// The standard alphabet, with `+` and `/` as the last two digits.
+    Standard,
+
+    /// The URL- and filename-safe alphabet, with `-` and `_` as the last two
+    /// digits.
+    UrlSafe,
+}
+
+/// Whether the encoding is padded with `=` to a multiple of four characters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Padding {
+    /// Padding is written when encoding and is required when decoding.
+    Required,
+
+    /// Padding isn't written when encoding and is rejected when decoding.
+    Omitted,
+}
+
+/// The encoding parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    alphabet: Alphabet,
+    padding: Padding,
+    allow_whitespace: bool,
+}
+
+impl Config {
+    /// Constructs a configuration that doesn't allow whitespace.
+    pub const fn new(alphabet: Alphabet, padding: Padding) -> Self {
+        Self {
+            alphabet,
+            padding,
+            allow_whitespace: false,
+        }
+    }
+
+    /// Returns a configuration like `self` except that decoding skips spaces,
+    /// tabs, carriage returns, and line feeds anywhere in the input, e.g. line
+    /// breaks in wrapped Base64.
+    ///
+    /// Encoding never writes whitespace.
+    pub fn allowing_whitespace(self) -> Self {
+        Self {
+            allow_whitespace: true,
+            ..self
+        }
+    }
+}
+
+/// The standard alphabet with padding, as in PEM and OpenSSH keys.
+pub static STANDARD: Config = Config::new(Alphabet::Standard, Padding::Required);
+
+/// The URL-safe alphabet without padding, which [RFC 7515 Section 2] calls
+/// `base64url` and JOSE (JWK, JWS) uses.
+///
+/// [RFC 7515 Section 2]: https://tools.ietf.org/html/rfc7515#section-2
+pub static URL_SAFE_NO_PAD: Config = Config::new(Alphabet::UrlSafe, Padding::Omitted);
+
+/// Encodes `input`.
+pub fn encode(input: &[u8], config: &Config) -> String {
+    let mut out = String::with_capacity(((input.len() + 2) / 3) * 4);
+    for chunk in input.chunks(3) {
+        let mut bits = 0u32;
+        for i in 0..3 {
+            bits = (bits << 8) | u32::from(chunk.get(i).cloned().unwrap_or(0));
+        }
+        let num_digits = chunk.len() + 1;
+        for i in 0..num_digits {
+            let value = ((bits >> (18 - (6 * i))) & 0x3f) as u8;
+            out.push(char::from(encode_digit(value, config.alphabet)));
+        }
+        if config.padding == Padding::Required {
+            for _ in num_digits..4 {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes `input`, which must be canonically encoded as described in the
+/// module documentation.
+pub fn decode(input: &str, config: &Config) -> Result<Vec<u8>, error::Unspecified> {
+    let without_whitespace: Vec<u8>;
+    let mut input = input.as_bytes();
+    if config.allow_whitespace {
+        without_whitespace = input
+            .iter()
+            .cloned()
+            .filter(|b| !is_whitespace(*b))
+            .collect();
+        input = &without_whitespace;
+    }
+
+    let digits = match config.padding {
+        Padding::Required => {
+            if input.len() % 4 != 0 {
+                return Err(error::Unspecified);
+            }
+            let padding_len = input.iter().rev().take(2).take_while(|b| **b == b'=').count();
+            &input[..(input.len() - padding_len)]
+        },
+        Padding::Omitted => input,
+    };
+    if digits.len() % 4 == 1 {
+        return Err(error::Unspecified);
+    }
+
+    let mut out = Vec::with_capacity((digits.len() * 3) / 4);
+    let mut valid = Choice::from(true);
+    for chunk in digits.chunks(4) {
+        let mut bits = 0u32;
+        for digit in chunk {
+            let value = decode_digit(*digit, config.alphabet);
+            valid = valid & value.is_some();
+            bits = (bits << 6) | u32::from(value.unwrap_or(0));
+        }
+        // The final one or two bytes of a short chunk have unused bits, which
+        // must be zero.
+        let (len, unused_bits) = match chunk.len() {
+            4 => (3, 0),
+            3 => (2, 2),
+            _ => (1, 4),
+        };
+        valid = valid & constant_time::words_equal(bits & ((1 << unused_bits) - 1), 0);
+        bits >>= unused_bits;
+        for i in (0..len).rev() {
+            out.push((bits >> (8 * i)) as u8);
+        }
+    }
+    if !valid.expose() {
+        return Err(error::Unspecified);
+    }
+    Ok(out)
+}
+
+fn is_whitespace(b: u8) -> bool {
+    match b {
+        b' ' | b'\t' | b'\r' | b'\n' => true,
+        _ => false,
+    }
+}
+
+// The last two digits of the alphabet.
+fn last_digits(alphabet: Alphabet) -> (u8, u8) {
+    match alphabet {
+        Alphabet::Standard => (b'+', b'/'),
+        Alphabet::UrlSafe => (b'-', b'_'),
+    }
+}
+
+// Returns the digit for `value`, which must be less than 64.
+fn encode_digit(value: u8, alphabet: Alphabet) -> u8 {
+    let (digit_62, digit_63) = last_digits(alphabet);
+    // Start with 'A'..='Z' and shift the later ranges into place.
+    let mut digit = value.wrapping_add(b'A');
+    digit = digit.wrapping_add(!lt_mask(value, 26) & (b'a' - b'Z' - 1));
+    digit = digit.wrapping_add(!lt_mask(value, 52) & b'0'.wrapping_sub(b'z' + 1));
+    digit = digit.wrapping_add(!lt_mask(value, 62) & digit_62.wrapping_sub(b'9' + 1));
+    digit = digit.wrapping_add(!lt_mask(value, 63) & digit_63.wrapping_sub(digit_62 + 1));
+    digit
+}
+
+fn decode_digit(digit: u8, alphabet: Alphabet) -> CtOption<u8> {
+    let (digit_62, digit_63) = last_digits(alphabet);
+    let upper = range_mask(digit, b'A', b'Z');
+    let lower = range_mask(digit, b'a', b'z');
+    let decimal = range_mask(digit, b'0', b'9');
+    let is_62 = range_mask(digit, digit_62, digit_62);
+    let is_63 = range_mask(digit, digit_63, digit_63);
+    let value = (upper & digit.wrapping_sub(b'A'))
+        | (lower & digit.wrapping_sub(b'a').wrapping_add(26))
+        | (decimal & digit.wrapping_sub(b'0').wrapping_add(52))
+        | (is_62 & 62)
+        | (is_63 & 63);
+    CtOption::new(value, Choice::from_mask(upper | lower | decimal | is_62 | is_63))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From RFC 4648 Section 10.
+    static TEST_VECTORS: &[(&str, &str)] = &[
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn test_rfc4648_vectors() {
+        for &(decoded, encoded) in TEST_VECTORS {
+            assert_eq!(encode(decoded.as_bytes(), &STANDARD), encoded);
+            assert_eq!(decode(encoded, &STANDARD).unwrap(), decoded.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_noncanonical() {
+        for bad in &[
+            "Zg", "Zg=", "Zh==", "Zm9=", "Zg==Zg==", "Zm9v\n", " Zm9v", "Zm=v", "Zm9-", "Z===",
+            "====",
+        ] {
+            assert!(decode(bad, &STANDARD).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_url_safe_no_pad() {
+        for &(decoded, encoded) in TEST_VECTORS {
+            let encoded = encoded.trim_end_matches('=');
+            assert_eq!(encode(decoded.as_bytes(), &URL_SAFE_NO_PAD), encoded);
+            assert_eq!(
+                decode(encoded, &URL_SAFE_NO_PAD).unwrap(),
+                decoded.as_bytes()
+            );
+        }
+        assert_eq!(encode(&[0xfb, 0xff], &URL_SAFE_NO_PAD), "-_8");
+        assert_eq!(decode("-_8", &URL_SAFE_NO_PAD).unwrap(), &[0xfb, 0xff]);
+        for bad in &["Zg==", "Zm9=", "+/8", "Z", "Zh", "Zm9v\n"] {
+            assert!(decode(bad, &URL_SAFE_NO_PAD).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_other_configs() {
+        let url_safe = Config::new(Alphabet::UrlSafe, Padding::Required);
+        assert_eq!(encode(&[0xfb, 0xff], &url_safe), "-_8=");
+        assert_eq!(decode("-_8=", &url_safe).unwrap(), &[0xfb, 0xff]);
+        assert!(decode("-_8", &url_safe).is_err());
+
+        let standard_no_pad = Config::new(Alphabet::Standard, Padding::Omitted);
+        assert_eq!(encode(&[0xfb, 0xff], &standard_no_pad), "+/8");
+        assert_eq!(decode("+/8", &standard_no_pad).unwrap(), &[0xfb, 0xff]);
+        assert!(decode("+/8=", &standard_no_pad).is_err());
+    }
+
+    #[test]
+    fn test_allowing_whitespace() {
+        let config = STANDARD.allowing_whitespace();
+        assert_eq!(
+            decode(" Zm9v\r\nYmE=\n", &config).unwrap(),
+            b"fooba".as_ref()
+        );
+        assert_eq!(decode("Zm9vYmE=\t", &config).unwrap(), b"fooba".as_ref());
+        assert!(decode("Zm9vYmE\n=", &config).is_ok());
+        assert!(decode("Zm9vYmE", &config).is_err());
+        assert!(decode("Zm\u{a0}9v", &config).is_err());
+        assert_eq!(encode(b"fooba", &config), "Zm9vYmE=");
+    }
+
+    #[test]
+    fn test_digits() {
+        const STANDARD_DIGITS: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        const URL_SAFE_DIGITS: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        for &(alphabet, digits) in &[
+            (Alphabet::Standard, STANDARD_DIGITS),
+            (Alphabet::UrlSafe, URL_SAFE_DIGITS),
+        ] {
+            for value in 0..64u8 {
+                assert_eq!(encode_digit(value, alphabet), digits[usize::from(value)]);
+            }
+            for digit in 0..=255u8 {
+                let expected = digits.iter().position(|d| *d == digit).map(|v| v as u8);
+                assert_eq!(decode_digit(digit, alphabet).expose(), expected);
+            }
+        }
+    }
+}
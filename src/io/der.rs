@@ -0,0 +1,578 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Building blocks for parsing and writing DER-encoded ASN.1 structures.
+//!
+//! This module contains the foundational parts of an ASN.1 DER parser, which
+//! works on `untrusted::Reader`s, and `Writer`, an encoder for the same
+//! subset of DER. Only the low tag number form is supported, and the parser
+//! only accepts lengths shorter than 2**16 bytes.
+//!
+//! `StreamReader` reads DER incrementally instead, for inputs that are too
+//! large to hold in memory, e.g. big CMS messages and certificate bundles.
+
+use crate::error;
+use untrusted;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+mod stream;
+
+#[cfg(feature = "std")]
+pub use self::stream::IoReader;
+pub use self::stream::{Event, StreamReader};
+
+/// The bit of a tag that marks the value as constructed.
+pub const CONSTRUCTED: u8 = 1 << 5;
+
+/// The class bits of a context-specific tag.
+pub const CONTEXT_SPECIFIC: u8 = 2 << 6;
+
+/// A tag.
+#[derive(Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum Tag {
+    /// `BOOLEAN`.
+    Boolean = 0x01,
+    /// `INTEGER`.
+    Integer = 0x02,
+    /// `BIT STRING`.
+    BitString = 0x03,
+    /// `OCTET STRING`.
+    OctetString = 0x04,
+    /// `NULL`.
+    Null = 0x05,
+    /// `OBJECT IDENTIFIER`.
+    OID = 0x06,
+    /// `SEQUENCE` and `SEQUENCE OF`.
+    Sequence = CONSTRUCTED | 0x10, // 0x30
+    /// `SET` and `SET OF`.
+    Set = CONSTRUCTED | 0x11, // 0x31
+    /// `UTCTime`.
+    UTCTime = 0x17,
+    /// `GeneralizedTime`.
+    GeneralizedTime = 0x18,
+
+    /// `[0]`, for an explicitly tagged value or an implicitly tagged
+    /// constructed one.
+    ContextSpecificConstructed0 = CONTEXT_SPECIFIC | CONSTRUCTED | 0,
+    /// `[1]`, for an explicitly tagged value or an implicitly tagged
+    /// constructed one.
+    ContextSpecificConstructed1 = CONTEXT_SPECIFIC | CONSTRUCTED | 1,
+    /// `[3]`, for an explicitly tagged value or an implicitly tagged
+    /// constructed one.
+    ContextSpecificConstructed3 = CONTEXT_SPECIFIC | CONSTRUCTED | 3,
+}
+
+/// Reads a value with the tag `tag`, returning its contents.
+pub fn expect_tag_and_get_value<'a>(
+    input: &mut untrusted::Reader<'a>, tag: Tag,
+) -> Result<untrusted::Input<'a>, error::Unspecified> {
+    let (actual_tag, inner) = read_tag_and_get_value(input)?;
+    if (tag as usize) != (actual_tag as usize) {
+        return Err(error::Unspecified);
+    }
+    Ok(inner)
+}
+
+/// Reads a value, returning its tag and its contents.
+pub fn read_tag_and_get_value<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<(u8, untrusted::Input<'a>), error::Unspecified> {
+    let tag = input.read_byte()?;
+    if (tag & 0x1F) == 0x1F {
+        return Err(error::Unspecified); // High tag number form is not allowed.
+    }
+
+    // If the high order bit of the first byte is set to zero then the length
+    // is encoded in the seven remaining bits of that byte. Otherwise, those
+    // seven bits represent the number of bytes used to encode the length.
+    let length = match input.read_byte()? {
+        n if (n & 0x80) == 0 => n as usize,
+        0x81 => {
+            let second_byte = input.read_byte()?;
+            if second_byte < 128 {
+                return Err(error::Unspecified); // Not the canonical encoding.
+            }
+            second_byte as usize
+        },
+        0x82 => {
+            let second_byte = input.read_byte()? as usize;
+            let third_byte = input.read_byte()? as usize;
+            let combined = (second_byte << 8) | third_byte;
+            if combined < 256 {
+                return Err(error::Unspecified); // Not the canonical encoding.
+            }
+            combined
+        },
+        _ => {
+            return Err(error::Unspecified); // We don't support longer lengths.
+        },
+    };
+
+    let inner = input.skip_and_get_input(length)?;
+    Ok((tag, inner))
+}
+
+/// Reads a `BIT STRING` whose length is a multiple of eight bits, returning
+/// its contents without the unused-bits byte.
+pub fn bit_string_with_no_unused_bits<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<untrusted::Input<'a>, error::Unspecified> {
+    nested(input, Tag::BitString, error::Unspecified, |value| {
+        let unused_bits_at_end = value.read_byte().map_err(|_| error::Unspecified)?;
+        if unused_bits_at_end != 0 {
+            return Err(error::Unspecified);
+        }
+        Ok(value.skip_to_end())
+    })
+}
+
+/// Reads a value with the tag `tag` and parses its contents with `decoder`,
+/// which must consume them entirely.
+// TODO: investigate taking decoder as a reference to reduce generated code
+// size.
+pub fn nested<'a, F, R, E: Copy>(
+    input: &mut untrusted::Reader<'a>, tag: Tag, error: E, decoder: F,
+) -> Result<R, E>
+where
+    F: FnOnce(&mut untrusted::Reader<'a>) -> Result<R, E>,
+{
+    let inner = expect_tag_and_get_value(input, tag).map_err(|_| error)?;
+    inner.read_all(error, decoder)
+}
+
+fn nonnegative_integer<'a>(
+    input: &mut untrusted::Reader<'a>, min_value: u8,
+) -> Result<untrusted::Input<'a>, error::Unspecified> {
+    // Verify that |input|, which has had any leading zero stripped off, is the
+    // encoding of a value of at least |min_value|.
+    fn check_minimum(input: untrusted::Input, min_value: u8) -> Result<(), error::Unspecified> {
+        input.read_all(error::Unspecified, |input| {
+            let first_byte = input.read_byte()?;
+            if input.at_end() && first_byte < min_value {
+                return Err(error::Unspecified);
+            }
+            let _ = input.skip_to_end();
+            Ok(())
+        })
+    }
+
+    let value = expect_tag_and_get_value(input, Tag::Integer)?;
+
+    value.read_all(error::Unspecified, |input| {
+        // Empty encodings are not allowed.
+        let first_byte = input.read_byte()?;
+
+        if first_byte == 0 {
+            if input.at_end() {
+                // |value| is the legal encoding of zero.
+                if min_value > 0 {
+                    return Err(error::Unspecified);
+                }
+                return Ok(value);
+            }
+
+            let r = input.skip_to_end();
+            r.read_all(error::Unspecified, |input| {
+                let second_byte = input.read_byte()?;
+                if (second_byte & 0x80) == 0 {
+                    // A leading zero is only allowed when the value's high bit
+                    // is set.
+                    return Err(error::Unspecified);
+                }
+                let _ = input.skip_to_end();
+                Ok(())
+            })?;
+            check_minimum(r, min_value)?;
+            return Ok(r);
+        }
+
+        // Negative values are not allowed.
+        if (first_byte & 0x80) != 0 {
+            return Err(error::Unspecified);
+        }
+
+        let _ = input.skip_to_end();
+        check_minimum(value, min_value)?;
+        Ok(value)
+    })
+}
+
+/// Parse as integer with a value in the in the range [0, 255], returning its
+/// numeric value. This is typically used for parsing version numbers.
+#[inline]
+pub fn small_nonnegative_integer(input: &mut untrusted::Reader) -> Result<u8, error::Unspecified> {
+    let value = nonnegative_integer(input, 0)?;
+    value.read_all(error::Unspecified, |input| {
+        let r = input.read_byte()?;
+        Ok(r)
+    })
+}
+
+/// Parses a positive DER integer, returning the big-endian-encoded value, sans
+/// any leading zero byte.
+#[inline]
+pub fn positive_integer<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<untrusted::Input<'a>, error::Unspecified> {
+    nonnegative_integer(input, 1)
+}
+
+/// A DER encoder.
+///
+/// Each `write_*` method appends one value. The contents of constructed
+/// values are written by the closure passed to `write_sequence()`,
+/// `write_nested()`, `write_explicit()`, or `write_implicit_constructed()`,
+/// so the lengths are always consistent with the contents. Object
+/// identifiers are given in their encoded form, as the parser returns them.
+///
+/// The writer doesn't sort the elements of `SET OF` values; callers that
+/// write them must write the elements in DER order.
+///
+/// ```
+/// use ring::io::der;
+///
+/// // SEQUENCE { INTEGER 128, [0] EXPLICIT NULL }
+/// let mut writer = der::Writer::new();
+/// writer.write_sequence(|writer| {
+///     writer.write_integer(&[0x80]);
+///     writer.write_explicit(0, |writer| writer.write_null());
+/// });
+/// assert_eq!(
+///     writer.as_ref(),
+///     &[0x30, 0x08, 0x02, 0x02, 0x00, 0x80, 0xa0, 0x02, 0x05, 0x00][..]
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, Default)]
+pub struct Writer {
+    bytes: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl Writer {
+    /// Constructs an empty writer.
+    pub fn new() -> Self { Self { bytes: Vec::new() } }
+
+    /// Writes a value with the tag `tag` and the contents `value`.
+    pub fn write_tlv(&mut self, tag: Tag, value: &[u8]) { self.write_tlv_(tag as u8, value) }
+
+    /// Writes a value with the tag `tag` whose contents are written by
+    /// `write_value`.
+    pub fn write_nested<F>(&mut self, tag: Tag, write_value: F)
+    where
+        F: FnOnce(&mut Writer),
+    {
+        self.write_nested_(tag as u8, write_value)
+    }
+
+    /// Writes a `SEQUENCE` whose contents are written by `write_value`.
+    pub fn write_sequence<F>(&mut self, write_value: F)
+    where
+        F: FnOnce(&mut Writer),
+    {
+        self.write_nested(Tag::Sequence, write_value)
+    }
+
+    /// Writes an `INTEGER` with the non-negative value `value`, which is
+    /// big-endian encoded.
+    ///
+    /// Leading zeros are removed and a zero byte is prepended when the high
+    /// bit is set, so `value` may have any number of leading zeros, and may
+    /// be a fixed-width encoding like `u32::to_be_bytes()`. An empty `value`
+    /// encodes zero.
+    pub fn write_integer(&mut self, value: &[u8]) {
+        let leading_zeros = value.iter().take_while(|b| **b == 0).count();
+        let value = &value[leading_zeros..];
+        // Zero is encoded as a single zero byte.
+        let needs_leading_zero = value.first().map_or(true, |b| (b & 0x80) != 0);
+        self.write_header(Tag::Integer as u8, value.len() + (needs_leading_zero as usize));
+        if needs_leading_zero {
+            self.bytes.push(0);
+        }
+        self.bytes.extend_from_slice(value);
+    }
+
+    /// Writes a `BIT STRING` with no unused bits and the contents `value`.
+    pub fn write_bit_string(&mut self, value: &[u8]) {
+        self.write_nested(Tag::BitString, |writer| {
+            writer.bytes.push(0);
+            writer.bytes.extend_from_slice(value);
+        })
+    }
+
+    /// Writes an `OCTET STRING` with the contents `value`.
+    pub fn write_octet_string(&mut self, value: &[u8]) { self.write_tlv(Tag::OctetString, value) }
+
+    /// Writes an `OBJECT IDENTIFIER`, where `oid` is the encoded value
+    /// without the tag and length.
+    pub fn write_oid(&mut self, oid: &[u8]) { self.write_tlv(Tag::OID, oid) }
+
+    /// Writes a `NULL`.
+    pub fn write_null(&mut self) { self.write_tlv(Tag::Null, &[]) }
+
+    /// Writes the explicitly tagged value `[number] EXPLICIT`, whose inner
+    /// value is written by `write_value`.
+    ///
+    /// Panics if `number` is larger than 30.
+    pub fn write_explicit<F>(&mut self, number: u8, write_value: F)
+    where
+        F: FnOnce(&mut Writer),
+    {
+        self.write_nested_(context_specific(number) | CONSTRUCTED, write_value)
+    }
+
+    /// Writes the implicitly tagged primitive value `[number] IMPLICIT`, e.g.
+    /// an implicitly tagged `OCTET STRING`, with the contents `value`.
+    ///
+    /// Panics if `number` is larger than 30.
+    pub fn write_implicit(&mut self, number: u8, value: &[u8]) {
+        self.write_tlv_(context_specific(number), value)
+    }
+
+    /// Writes the implicitly tagged constructed value `[number] IMPLICIT`,
+    /// e.g. an implicitly tagged `SEQUENCE`, whose contents are written by
+    /// `write_value`.
+    ///
+    /// Panics if `number` is larger than 30.
+    pub fn write_implicit_constructed<F>(&mut self, number: u8, write_value: F)
+    where
+        F: FnOnce(&mut Writer),
+    {
+        self.write_explicit(number, write_value)
+    }
+
+    /// Appends `der`, which must be one or more DER-encoded values.
+    pub fn write_raw(&mut self, der: &[u8]) { self.bytes.extend_from_slice(der) }
+
+    /// Returns the encoded values.
+    pub fn into_bytes(self) -> Vec<u8> { self.bytes }
+
+    fn write_tlv_(&mut self, tag: u8, value: &[u8]) {
+        self.write_header(tag, value.len());
+        self.bytes.extend_from_slice(value);
+    }
+
+    fn write_nested_<F>(&mut self, tag: u8, write_value: F)
+    where
+        F: FnOnce(&mut Writer),
+    {
+        let mut value = Writer::new();
+        write_value(&mut value);
+        self.write_tlv_(tag, &value.bytes);
+    }
+
+    fn write_header(&mut self, tag: u8, value_len: usize) {
+        self.bytes.push(tag);
+        self.write_length(value_len);
+    }
+
+    fn write_length(&mut self, len: usize) {
+        if len < 0x80 {
+            self.bytes.push(len as u8);
+        } else {
+            let len_bytes = (len as u64).to_be_bytes();
+            let leading_zeros = len_bytes.iter().take_while(|b| **b == 0).count();
+            self.bytes.push(0x80 | ((len_bytes.len() - leading_zeros) as u8));
+            self.bytes.extend_from_slice(&len_bytes[leading_zeros..]);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AsRef<[u8]> for Writer {
+    fn as_ref(&self) -> &[u8] { &self.bytes }
+}
+
+#[cfg(feature = "alloc")]
+fn context_specific(number: u8) -> u8 {
+    // Larger numbers require the high tag number form.
+    assert!(number < 0x1f);
+    CONTEXT_SPECIFIC | number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error;
+    use untrusted;
+
+    fn with_good_i<F, R>(value: &[u8], f: F)
+    where
+        F: FnOnce(&mut untrusted::Reader) -> Result<R, error::Unspecified>,
+    {
+        let r = untrusted::Input::from(value).read_all(error::Unspecified, f);
+        assert!(r.is_ok());
+    }
+
+    fn with_bad_i<F, R>(value: &[u8], f: F)
+    where
+        F: FnOnce(&mut untrusted::Reader) -> Result<R, error::Unspecified>,
+    {
+        let r = untrusted::Input::from(value).read_all(error::Unspecified, f);
+        assert!(r.is_err());
+    }
+
+    static ZERO_INTEGER: &'static [u8] = &[0x02, 0x01, 0x00];
+
+    static GOOD_POSITIVE_INTEGERS: &'static [(&'static [u8], u8)] = &[
+        (&[0x02, 0x01, 0x01], 0x01),
+        (&[0x02, 0x01, 0x02], 0x02),
+        (&[0x02, 0x01, 0x7e], 0x7e),
+        (&[0x02, 0x01, 0x7f], 0x7f),
+        // Values that need to have an 0x00 prefix to disambiguate them from
+        // them from negative values.
+        (&[0x02, 0x02, 0x00, 0x80], 0x80),
+        (&[0x02, 0x02, 0x00, 0x81], 0x81),
+        (&[0x02, 0x02, 0x00, 0xfe], 0xfe),
+        (&[0x02, 0x02, 0x00, 0xff], 0xff),
+    ];
+
+    static BAD_NONNEGATIVE_INTEGERS: &'static [&'static [u8]] = &[
+        &[],           // At end of input
+        &[0x02],       // Tag only
+        &[0x02, 0x00], // Empty value
+        // Length mismatch
+        &[0x02, 0x00, 0x01],
+        &[0x02, 0x01],
+        &[0x02, 0x01, 0x00, 0x01],
+        &[0x02, 0x01, 0x01, 0x00], // Would be valid if last byte is ignored.
+        &[0x02, 0x02, 0x01],
+        // Negative values
+        &[0x02, 0x01, 0x80],
+        &[0x02, 0x01, 0xfe],
+        &[0x02, 0x01, 0xff],
+        // Values that have an unnecessary leading 0x00
+        &[0x02, 0x02, 0x00, 0x00],
+        &[0x02, 0x02, 0x00, 0x01],
+        &[0x02, 0x02, 0x00, 0x02],
+        &[0x02, 0x02, 0x00, 0x7e],
+        &[0x02, 0x02, 0x00, 0x7f],
+    ];
+
+    #[test]
+    fn test_small_nonnegative_integer() {
+        with_good_i(ZERO_INTEGER, |input| {
+            assert_eq!(small_nonnegative_integer(input)?, 0x00);
+            Ok(())
+        });
+        for &(test_in, test_out) in GOOD_POSITIVE_INTEGERS.iter() {
+            with_good_i(test_in, |input| {
+                assert_eq!(small_nonnegative_integer(input)?, test_out);
+                Ok(())
+            });
+        }
+        for &test_in in BAD_NONNEGATIVE_INTEGERS.iter() {
+            with_bad_i(test_in, |input| {
+                let _ = small_nonnegative_integer(input)?;
+                Ok(())
+            });
+        }
+    }
+
+    #[test]
+    fn test_positive_integer() {
+        with_bad_i(ZERO_INTEGER, |input| {
+            let _ = positive_integer(input)?;
+            Ok(())
+        });
+        for &(test_in, test_out) in GOOD_POSITIVE_INTEGERS.iter() {
+            with_good_i(test_in, |input| {
+                let test_out = [test_out];
+                assert_eq!(
+                    positive_integer(input)?,
+                    untrusted::Input::from(&test_out[..])
+                );
+                Ok(())
+            });
+        }
+        for &test_in in BAD_NONNEGATIVE_INTEGERS.iter() {
+            with_bad_i(test_in, |input| {
+                let _ = positive_integer(input)?;
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_writer_integer() {
+        fn write_integer(value: &[u8]) -> Vec<u8> {
+            let mut writer = Writer::new();
+            writer.write_integer(value);
+            writer.into_bytes()
+        }
+        assert_eq!(write_integer(&[]), ZERO_INTEGER);
+        assert_eq!(write_integer(&[0x00, 0x00]), ZERO_INTEGER);
+        for &(test_out, test_in) in GOOD_POSITIVE_INTEGERS.iter() {
+            assert_eq!(write_integer(&[test_in]), test_out);
+            assert_eq!(write_integer(&[0x00, 0x00, test_in]), test_out);
+            assert_eq!(write_integer(&u32::from(test_in).to_be_bytes()), test_out);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_writer_round_trip() {
+        let long = [0xab; 0x100];
+        let mut writer = Writer::new();
+        writer.write_sequence(|writer| {
+            writer.write_oid(&[0x2b, 0x65, 0x70]);
+            writer.write_bit_string(&[0x01, 0x02]);
+            writer.write_explicit(1, |writer| writer.write_octet_string(&long));
+            writer.write_implicit(2, &[0x03]);
+            writer.write_implicit_constructed(3, |writer| writer.write_null());
+        });
+        let encoded = writer.into_bytes();
+        assert_eq!(
+            &encoded[..12],
+            &[0x30, 0x82, 0x01, 0x19, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x03, 0x00]
+        );
+
+        untrusted::Input::from(&encoded)
+            .read_all(error::Unspecified, |input| {
+                nested(input, Tag::Sequence, error::Unspecified, |input| {
+                    assert_eq!(
+                        expect_tag_and_get_value(input, Tag::OID)?,
+                        untrusted::Input::from(&[0x2b, 0x65, 0x70])
+                    );
+                    assert_eq!(
+                        bit_string_with_no_unused_bits(input)?,
+                        untrusted::Input::from(&[0x01, 0x02])
+                    );
+                    let octet_string = nested(
+                        input,
+                        Tag::ContextSpecificConstructed1,
+                        error::Unspecified,
+                        |input| expect_tag_and_get_value(input, Tag::OctetString),
+                    )?;
+                    assert_eq!(octet_string, untrusted::Input::from(&long[..]));
+                    let (tag, value) = read_tag_and_get_value(input)?;
+                    assert_eq!(tag, CONTEXT_SPECIFIC | 2);
+                    assert_eq!(value, untrusted::Input::from(&[0x03]));
+                    nested(input, Tag::ContextSpecificConstructed3, error::Unspecified, |input| {
+                        expect_tag_and_get_value(input, Tag::Null).map(|_| ())
+                    })
+                })
+            })
+            .unwrap();
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic]
+    fn test_writer_rejects_high_tag_number() { Writer::new().write_implicit(31, &[]); }
+}
@@ -0,0 +1,498 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! A pull-based DER reader that works on input that arrives in chunks.
+
+use crate::error;
+use core;
+
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+
+// The maximum nesting depth of constructed values.
+const MAX_DEPTH: usize = 16;
+
+// A tag, the initial length byte, and up to `size_of::<usize>()` more length
+// bytes.
+const MAX_HEADER_LEN: usize = 2 + core::mem::size_of::<usize>();
+
+/// Something read by a `StreamReader`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// The header of a constructed value. The values it contains follow,
+    /// and then a matching `End`.
+    Start {
+        /// The tag.
+        tag: u8,
+        /// The length of the contents.
+        len: usize,
+    },
+
+    /// The end of the innermost constructed value that hasn't ended.
+    End {
+        /// The tag.
+        tag: u8,
+    },
+
+    /// The header of a primitive value. Its contents follow in `Data`
+    /// events, unless they are empty.
+    Primitive {
+        /// The tag.
+        tag: u8,
+        /// The length of the contents.
+        len: usize,
+    },
+
+    /// Part of the contents of the current primitive value. The contents may
+    /// be split across any number of `Data` events, depending on how the
+    /// input is chunked.
+    Data(&'a [u8]),
+}
+
+/// A pull-based DER reader.
+///
+/// The input is passed to `next()`, one chunk at a time, and `next()`
+/// returns the events for as much of it as it can. Only the header being
+/// read and the lengths of the open constructed values are kept between
+/// chunks, so memory use doesn't depend on the size of the input. Any
+/// number of top-level values may follow each other, as in a certificate
+/// bundle.
+///
+/// The same subset of DER as the rest of this module is accepted, except
+/// that lengths can be as large as `usize::max_value()`. Constructed values
+/// may be nested up to 16 deep. The contents of primitive values aren't
+/// interpreted.
+///
+/// ```
+/// use ring::io::der::{self, Event, StreamReader};
+///
+/// // SEQUENCE { INTEGER 1, OCTET STRING 'abc' }, in two chunks.
+/// let chunks: [&[u8]; 2] = [&[0x30, 0x08, 0x02, 0x01, 0x01, 0x04], &[0x03, b'a', b'b', b'c']];
+///
+/// let mut reader = StreamReader::new();
+/// let mut events = Vec::new();
+/// for chunk in chunks.iter() {
+///     let mut chunk = *chunk;
+///     while let Some(event) = reader.next(&mut chunk).unwrap() {
+///         events.push(event);
+///     }
+/// }
+/// reader.finish().unwrap();
+///
+/// let integer = der::Tag::Integer as u8;
+/// let octet_string = der::Tag::OctetString as u8;
+/// assert_eq!(events, [
+///     Event::Start { tag: der::Tag::Sequence as u8, len: 8 },
+///     Event::Primitive { tag: integer, len: 1 },
+///     Event::Data(&[0x01]),
+///     Event::Primitive { tag: octet_string, len: 3 },
+///     Event::Data(b"abc"),
+///     Event::End { tag: der::Tag::Sequence as u8 },
+/// ]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct StreamReader {
+    header: [u8; MAX_HEADER_LEN],
+    header_len: usize,
+
+    // The remaining length of the contents of the current primitive value.
+    primitive_remaining: usize,
+
+    // The tags of the open constructed values and the remaining lengths of
+    // their contents.
+    open: [(u8, usize); MAX_DEPTH],
+    depth: usize,
+
+    failed: bool,
+}
+
+impl StreamReader {
+    /// Constructs a reader that is at the start of the input.
+    pub fn new() -> Self {
+        Self {
+            header: [0; MAX_HEADER_LEN],
+            header_len: 0,
+            primitive_remaining: 0,
+            open: [(0, 0); MAX_DEPTH],
+            depth: 0,
+            failed: false,
+        }
+    }
+
+    /// Reads the next event from `input`, advancing `input` past what was
+    /// read.
+    ///
+    /// Returns `Ok(None)` when all of `input` has been consumed and more
+    /// input is needed for the next event. Once an error is returned, every
+    /// later call returns an error too.
+    pub fn next<'a>(
+        &mut self, input: &mut &'a [u8],
+    ) -> Result<Option<Event<'a>>, error::Unspecified> {
+        if self.failed {
+            return Err(error::Unspecified);
+        }
+        let result = self.next_(input);
+        if result.is_err() {
+            self.failed = true;
+        }
+        result
+    }
+
+    /// Checks that the input ended between top-level values.
+    pub fn finish(&self) -> Result<(), error::Unspecified> {
+        if self.failed || !self.is_between_values() {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+
+    /// The number of constructed values that have started and haven't ended.
+    #[inline]
+    pub fn depth(&self) -> usize { self.depth }
+
+    fn is_between_values(&self) -> bool {
+        self.depth == 0 && self.header_len == 0 && self.primitive_remaining == 0
+    }
+
+    fn next_<'a>(
+        &mut self, input: &mut &'a [u8],
+    ) -> Result<Option<Event<'a>>, error::Unspecified> {
+        if self.primitive_remaining > 0 {
+            if input.is_empty() {
+                return Ok(None);
+            }
+            let len = core::cmp::min(self.primitive_remaining, input.len());
+            let (data, rest) = input.split_at(len);
+            *input = rest;
+            self.primitive_remaining -= len;
+            return Ok(Some(Event::Data(data)));
+        }
+
+        if self.depth > 0 {
+            let (tag, remaining) = self.open[self.depth - 1];
+            if remaining == 0 {
+                self.depth -= 1;
+                return Ok(Some(Event::End { tag }));
+            }
+        }
+
+        let (tag, len) = match self.read_header(input)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+
+        // The whole value must fit in the value that contains it.
+        if self.depth > 0 {
+            let remaining = &mut self.open[self.depth - 1].1;
+            *remaining = remaining
+                .checked_sub(self.header_len)
+                .and_then(|remaining| remaining.checked_sub(len))
+                .ok_or(error::Unspecified)?;
+        }
+        self.header_len = 0;
+
+        if (tag & super::CONSTRUCTED) != 0 {
+            if self.depth == MAX_DEPTH {
+                return Err(error::Unspecified);
+            }
+            self.open[self.depth] = (tag, len);
+            self.depth += 1;
+            Ok(Some(Event::Start { tag, len }))
+        } else {
+            self.primitive_remaining = len;
+            Ok(Some(Event::Primitive { tag, len }))
+        }
+    }
+
+    // Reads bytes of the header into `self.header` until it is complete, and
+    // then returns the tag and the length. `self.header_len` is left as the
+    // length of the header.
+    fn read_header(
+        &mut self, input: &mut &[u8],
+    ) -> Result<Option<(u8, usize)>, error::Unspecified> {
+        loop {
+            if let Some(header) = self.parse_header()? {
+                return Ok(Some(header));
+            }
+            match input.split_first() {
+                Some((b, rest)) => {
+                    self.header[self.header_len] = *b;
+                    self.header_len += 1;
+                    *input = rest;
+                },
+                None => return Ok(None),
+            }
+        }
+    }
+
+    // Parses `self.header`, returning `None` if it's incomplete.
+    fn parse_header(&self) -> Result<Option<(u8, usize)>, error::Unspecified> {
+        let header = &self.header[..self.header_len];
+        if header.is_empty() {
+            return Ok(None);
+        }
+        let tag = header[0];
+        if (tag & 0x1F) == 0x1F {
+            return Err(error::Unspecified); // High tag number form is not allowed.
+        }
+        if header.len() < 2 {
+            return Ok(None);
+        }
+        let first_len_byte = header[1];
+        if (first_len_byte & 0x80) == 0 {
+            return Ok(Some((tag, usize::from(first_len_byte))));
+        }
+
+        let len_len = usize::from(first_len_byte & 0x7f);
+        // 0x80 is the indefinite length form, which isn't DER.
+        if len_len == 0 || len_len > core::mem::size_of::<usize>() {
+            return Err(error::Unspecified);
+        }
+        let len_bytes = &header[2..];
+        if len_bytes.len() < len_len {
+            return Ok(None);
+        }
+        // The length must be encoded in as few bytes as possible, and the
+        // short form must be used when possible.
+        if len_bytes[0] == 0 || (len_len == 1 && len_bytes[0] < 0x80) {
+            return Err(error::Unspecified);
+        }
+        let len = len_bytes
+            .iter()
+            .fold(0usize, |len, b| (len << 8) | usize::from(*b));
+        Ok(Some((tag, len)))
+    }
+}
+
+impl Default for StreamReader {
+    fn default() -> Self { Self::new() }
+}
+
+/// A `StreamReader` that reads its input from a `std::io::Read`.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoReader<R: std::io::Read> {
+    source: R,
+    reader: StreamReader,
+    buffer: Vec<u8>,
+    start: usize,
+    end: usize,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> IoReader<R> {
+    /// Constructs a reader that reads from `source` in chunks of up to
+    /// `buffer_len` bytes, which must not be zero.
+    pub fn new(source: R, buffer_len: usize) -> Self {
+        assert!(buffer_len > 0);
+        Self {
+            source,
+            reader: StreamReader::new(),
+            buffer: vec![0; buffer_len],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Reads the next event.
+    ///
+    /// Returns `Ok(None)` at the end of the input if it ended between
+    /// top-level values. Malformed or truncated input is an error of kind
+    /// `std::io::ErrorKind::InvalidData`.
+    pub fn next(&mut self) -> std::io::Result<Option<Event<'_>>> {
+        loop {
+            if self.start == self.end {
+                self.start = 0;
+                self.end = loop {
+                    match self.source.read(&mut self.buffer) {
+                        Ok(n) => break n,
+                        Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+                        Err(e) => return Err(e),
+                    }
+                };
+                if self.end == 0 {
+                    return self.reader.finish().map(|()| None).map_err(invalid_data);
+                }
+            }
+
+            let mut input = &self.buffer[self.start..self.end];
+            let event = self.reader.next(&mut input).map_err(invalid_data)?;
+            self.start = self.end - input.len();
+            if let Some(event) = event {
+                // Rebuild the event so that its lifetime isn't tied to the
+                // loop.
+                let event = match event {
+                    Event::Start { tag, len } => Event::Start { tag, len },
+                    Event::End { tag } => Event::End { tag },
+                    Event::Primitive { tag, len } => Event::Primitive { tag, len },
+                    Event::Data(data) => {
+                        let data_start = self.start - data.len();
+                        Event::Data(&self.buffer[data_start..self.start])
+                    },
+                };
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    /// The number of constructed values that have started and haven't ended.
+    #[inline]
+    pub fn depth(&self) -> usize { self.reader.depth() }
+}
+
+#[cfg(feature = "std")]
+fn invalid_data(error::Unspecified: error::Unspecified) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid DER")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::io::der;
+    use alloc::vec::Vec;
+
+    // The events, with the `Data` of each primitive value merged.
+    #[derive(Debug, PartialEq)]
+    enum Owned {
+        Start(u8, usize),
+        End(u8),
+        Primitive(u8, Vec<u8>),
+    }
+
+    fn add(events: &mut Vec<Owned>, event: Event) {
+        match event {
+            Event::Start { tag, len } => events.push(Owned::Start(tag, len)),
+            Event::End { tag } => events.push(Owned::End(tag)),
+            Event::Primitive { tag, len } => {
+                events.push(Owned::Primitive(tag, Vec::with_capacity(len)))
+            },
+            Event::Data(data) => match events.last_mut() {
+                Some(Owned::Primitive(_, contents)) => contents.extend_from_slice(data),
+                _ => panic!(),
+            },
+        }
+    }
+
+    fn read_in_chunks(input: &[u8], chunk_len: usize) -> Result<Vec<Owned>, error::Unspecified> {
+        let mut reader = StreamReader::new();
+        let mut events = Vec::new();
+        for chunk in input.chunks(chunk_len) {
+            let mut chunk = chunk;
+            while let Some(event) = reader.next(&mut chunk)? {
+                add(&mut events, event);
+            }
+            assert!(chunk.is_empty());
+        }
+        reader.finish()?;
+        Ok(events)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn expected_events() -> (Vec<u8>, Vec<Owned>) {
+        let big = vec![0xab; 0x1_0001];
+        let mut writer = der::Writer::new();
+        writer.write_sequence(|writer| {
+            writer.write_integer(&[0x01]);
+            writer.write_explicit(0, |writer| {
+                writer.write_null();
+                writer.write_sequence(|_| ());
+            });
+            writer.write_octet_string(&big);
+        });
+        writer.write_oid(&[0x2b, 0x65, 0x70]);
+        let expected = vec![
+            Owned::Start(0x30, 0x1_000f),
+            Owned::Primitive(0x02, vec![0x01]),
+            Owned::Start(0xa0, 4),
+            Owned::Primitive(0x05, vec![]),
+            Owned::Start(0x30, 0),
+            Owned::End(0x30),
+            Owned::End(0xa0),
+            Owned::Primitive(0x04, big),
+            Owned::End(0x30),
+            Owned::Primitive(0x06, vec![0x2b, 0x65, 0x70]),
+        ];
+        (writer.into_bytes(), expected)
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_chunks() {
+        let (input, expected) = expected_events();
+        for &chunk_len in &[1, 2, 3, 7, 1000, input.len()] {
+            assert_eq!(read_in_chunks(&input, chunk_len).unwrap(), expected);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_io_reader() {
+        let (input, expected) = expected_events();
+        for &buffer_len in &[1, 5, 4096] {
+            let mut reader = IoReader::new(&input[..], buffer_len);
+            let mut events = Vec::new();
+            while let Some(event) = reader.next().unwrap() {
+                add(&mut events, event);
+            }
+            assert_eq!(events, expected);
+            assert_eq!(reader.depth(), 0);
+        }
+
+        let mut reader = IoReader::new(&input[..(input.len() - 1)], 4096);
+        let err = loop {
+            match reader.next() {
+                Ok(Some(_)) => (),
+                Ok(None) => panic!(),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        for bad in &[
+            &[0x1f, 0x01, 0x00][..],       // High tag number form.
+            &[0x04, 0x80, 0x00, 0x00],     // Indefinite length.
+            &[0x04, 0x81, 0x01, 0x00],     // Short form should have been used.
+            &[0x04, 0x82, 0x00, 0x80],     // Leading zero in the length.
+            &[0x04, 0x89, 0x01],           // Length too long for `usize`.
+            &[0x30, 0x02, 0x04, 0x01],     // Contents longer than the parent.
+            &[0x30, 0x03, 0x30, 0x01, 0x04, 0x00, 0x00], // Child longer than parent.
+            &[0x30, 0x03, 0x04, 0x01],     // Truncated.
+            &[0x04],                       // Truncated header.
+            &[0x04, 0x82, 0x01],           // Truncated length.
+        ] {
+            for &chunk_len in &[1, bad.len()] {
+                assert!(read_in_chunks(bad, chunk_len).is_err(), "{:?}", bad);
+            }
+        }
+
+        let mut nested = vec![0x30; 2 * (MAX_DEPTH + 1)];
+        for (i, len) in nested.iter_mut().skip(1).step_by(2).enumerate() {
+            *len = (2 * (MAX_DEPTH - i)) as u8;
+        }
+        assert!(read_in_chunks(&nested, 1).is_err());
+        assert!(read_in_chunks(&nested[2..], 1).is_ok());
+    }
+
+    #[test]
+    fn test_error_is_sticky() {
+        let mut reader = StreamReader::new();
+        assert!(reader.next(&mut &[0x04, 0x80][..]).is_err());
+        assert!(reader.next(&mut &[0x05, 0x00][..]).is_err());
+        assert!(reader.finish().is_err());
+    }
+}
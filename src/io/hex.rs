@@ -0,0 +1,172 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Hexadecimal encoding ([RFC 4648 Section 8]).
+//!
+//! Encoding writes lowercase digits. Decoding accepts uppercase and lowercase
+//! digits but is otherwise strict: the input must be an even number of digits
+//! with no whitespace, separators, or `0x` prefix.
+//!
+//! Like `io::base64`, the conversions between digits and their values don't
+//! use table lookups or data-dependent branches, and an invalid digit is only
+//! reported after the whole input has been decoded, so decoding a secret
+//! doesn't leak it through the cache or the timing of the loop.
+//!
+//! `decode_to_slice` decodes into a fixed-size array without allocating:
+//!
+//! ```
+//! use ring::io::hex;
+//!
+//! let mut key = [0u8; 4];
+//! hex::decode_to_slice("0123abCD", &mut key).unwrap();
+//! assert_eq!(key, [0x01, 0x23, 0xab, 0xcd]);
+//!
+//! // The input must be exactly twice as long as the output.
+//! assert!(hex::decode_to_slice("0123ab", &mut key).is_err());
+//! ```
+//!
+//! [RFC 4648 Section 8]: https://tools.ietf.org/html/rfc4648#section-8
+
+use super::{lt_mask, range_mask};
+use crate::{
+    constant_time::{Choice, CtOption},
+    error,
+};
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+/// Encodes `input` as lowercase hex digits.
+#[cfg(feature = "alloc")]
+pub fn encode(input: &[u8]) -> String {
+    let mut out = String::with_capacity(input.len() * 2);
+    for b in input {
+        out.push(char::from(encode_digit(b >> 4)));
+        out.push(char::from(encode_digit(b & 0xf)));
+    }
+    out
+}
+
+/// Encodes `input` as lowercase hex digits into the beginning of `out`,
+/// returning the encoded part.
+///
+/// Fails if `out` is shorter than `2 * input.len()` bytes.
+pub fn encode_to_slice<'o>(
+    input: &[u8], out: &'o mut [u8],
+) -> Result<&'o str, error::Unspecified> {
+    let out = out.get_mut(..(input.len() * 2)).ok_or(error::Unspecified)?;
+    for (b, digits) in input.iter().zip(out.chunks_mut(2)) {
+        digits[0] = encode_digit(b >> 4);
+        digits[1] = encode_digit(b & 0xf);
+    }
+    // Every byte written is an ASCII digit.
+    core::str::from_utf8(out).map_err(|_| error::Unspecified)
+}
+
+/// Decodes `input`, which must consist of an even number of hex digits.
+#[cfg(feature = "alloc")]
+pub fn decode(input: &str) -> Result<Vec<u8>, error::Unspecified> {
+    if input.len() % 2 != 0 {
+        return Err(error::Unspecified);
+    }
+    let mut out = vec![0; input.len() / 2];
+    decode_to_slice(input, &mut out)?;
+    Ok(out)
+}
+
+/// Decodes `input` into `out`, which must be exactly half as long as
+/// `input`.
+///
+/// On failure the contents of `out` are unspecified.
+pub fn decode_to_slice(input: &str, out: &mut [u8]) -> Result<(), error::Unspecified> {
+    let input = input.as_bytes();
+    if input.len() != out.len() * 2 {
+        return Err(error::Unspecified);
+    }
+    let mut valid = Choice::from(true);
+    for (digits, b) in input.chunks(2).zip(out.iter_mut()) {
+        let high = decode_digit(digits[0]);
+        let low = decode_digit(digits[1]);
+        valid = valid & high.is_some() & low.is_some();
+        *b = (high.unwrap_or(0) << 4) | low.unwrap_or(0);
+    }
+    if !valid.expose() {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
+// Returns the lowercase digit for `value`, which must be less than 16.
+fn encode_digit(value: u8) -> u8 {
+    value
+        .wrapping_add(b'0')
+        .wrapping_add(!lt_mask(value, 10) & (b'a' - b'0' - 10))
+}
+
+pub(crate) fn decode_digit(digit: u8) -> CtOption<u8> {
+    let decimal = range_mask(digit, b'0', b'9');
+    let lower = range_mask(digit, b'a', b'f');
+    let upper = range_mask(digit, b'A', b'F');
+    let value = (decimal & digit.wrapping_sub(b'0'))
+        | (lower & digit.wrapping_sub(b'a').wrapping_add(10))
+        | (upper & digit.wrapping_sub(b'A').wrapping_add(10));
+    CtOption::new(value, Choice::from_mask(decimal | lower | upper))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_encode_decode() {
+        assert_eq!(encode(&[]), "");
+        assert_eq!(encode(&[0x00, 0x09, 0x0a, 0x7f, 0x80, 0xff]), "00090a7f80ff");
+        assert_eq!(decode("").unwrap(), &[]);
+        assert_eq!(decode("00090a7F80fF").unwrap(), &[0x00, 0x09, 0x0a, 0x7f, 0x80, 0xff]);
+        for bad in &["0", "000", "0g", "g0", " 00", "00 ", "0x00", "0-", "\u{e9}"] {
+            assert!(decode(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_to_slice() {
+        let mut out = [0u8; 5];
+        assert_eq!(encode_to_slice(&[0xab, 0x01], &mut out).unwrap(), "ab01");
+        assert_eq!(&out, b"ab01\0");
+        assert!(encode_to_slice(&[0xab, 0x01, 0x02], &mut out).is_err());
+
+        let mut out = [0u8; 2];
+        assert!(decode_to_slice("ab01", &mut out).is_ok());
+        assert_eq!(out, [0xab, 0x01]);
+        assert!(decode_to_slice("ab0", &mut out).is_err());
+        assert!(decode_to_slice("ab01ff", &mut out).is_err());
+        assert!(decode_to_slice("ab0z", &mut out).is_err());
+    }
+
+    #[test]
+    fn test_digits() {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        for value in 0..16u8 {
+            assert_eq!(encode_digit(value), DIGITS[usize::from(value)]);
+        }
+        for digit in 0..=255u8 {
+            let expected = DIGITS
+                .iter()
+                .position(|d| *d == digit.to_ascii_lowercase())
+                .map(|v| v as u8);
+            assert_eq!(decode_digit(digit).expose(), expected);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Serialized positive integers.
+
+use crate::{error, polyfill};
+use core::cmp::Ordering;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// A serialized positive integer.
+///
+/// The value is kept in its minimal big-endian form, without leading zeros,
+/// which is the form used by JWK `Base64urlUInt` values and, after removing
+/// the sign byte, by DER `INTEGER`s and SSH `mpint`s. Comparisons are of the
+/// numeric values.
+#[derive(Clone, Copy, Debug)]
+pub struct Positive<'a>(&'a [u8]);
+
+impl<'a> Positive<'a> {
+    /// Constructs a `Positive` from the big-endian encoding `bytes`, which may
+    /// have any number of leading zeros, e.g. the fixed-width encoding of a
+    /// field element or scalar.
+    ///
+    /// Fails if the value is zero, including if `bytes` is empty.
+    pub fn from_be_bytes(bytes: &'a [u8]) -> Result<Self, error::Unspecified> {
+        let leading_zeros = bytes.iter().take_while(|b| **b == 0).count();
+        Self::from_minimal_be_bytes(&bytes[leading_zeros..])
+    }
+
+    /// Constructs a `Positive` from the big-endian encoding `bytes`, which
+    /// must not have any leading zeros.
+    ///
+    /// Fails if the value is zero, including if `bytes` is empty.
+    pub fn from_minimal_be_bytes(bytes: &'a [u8]) -> Result<Self, error::Unspecified> {
+        match bytes.first() {
+            Some(first_byte) if *first_byte != 0 => Ok(Positive(bytes)),
+            _ => Err(error::Unspecified),
+        }
+    }
+
+    /// Returns the big-endian encoding of the value without leading zeros.
+    /// This is never empty.
+    #[inline]
+    pub fn big_endian_without_leading_zero(&self) -> &'a [u8] { self.0 }
+
+    /// Returns the first byte of the minimal big-endian encoding, which is
+    /// never zero.
+    #[inline]
+    pub fn first_byte(&self) -> u8 { self.0[0] }
+
+    /// Writes the value into `out` as a big-endian value with as many leading
+    /// zeros as needed to fill it, as in the fixed-width encodings of ECDSA
+    /// signatures and coordinates.
+    ///
+    /// Fails if the value doesn't fit in `out`.
+    pub fn write_fixed_width(&self, out: &mut [u8]) -> Result<(), error::Unspecified> {
+        let padding_len = out.len().checked_sub(self.0.len()).ok_or(error::Unspecified)?;
+        let (padding, value) = out.split_at_mut(padding_len);
+        polyfill::slice::fill(padding, 0);
+        value.copy_from_slice(self.0);
+        Ok(())
+    }
+
+    /// Returns the value as a big-endian value of exactly `len` bytes.
+    ///
+    /// Fails if the value doesn't fit in `len` bytes.
+    #[cfg(feature = "alloc")]
+    pub fn to_fixed_width(&self, len: usize) -> Result<Vec<u8>, error::Unspecified> {
+        let mut out = vec![0; len];
+        self.write_fixed_width(&mut out)?;
+        Ok(out)
+    }
+
+    /// Returns the minimal big-endian two's complement encoding of the value,
+    /// which has a leading zero exactly when the high bit of `first_byte()` is
+    /// set. That is the value of a DER `INTEGER` and of an SSH `mpint`.
+    #[cfg(feature = "alloc")]
+    pub fn to_signed_be_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.0.len() + 1);
+        if (self.first_byte() & 0x80) != 0 {
+            out.push(0);
+        }
+        out.extend_from_slice(self.0);
+        out
+    }
+}
+
+impl AsRef<[u8]> for Positive<'_> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { self.0 }
+}
+
+impl PartialEq for Positive<'_> {
+    fn eq(&self, other: &Self) -> bool { self.0 == other.0 }
+}
+
+impl Eq for Positive<'_> {}
+
+impl PartialOrd for Positive<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for Positive<'_> {
+    // Without leading zeros, the longer encoding is the larger value, and
+    // encodings of the same length compare like their bytes.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.cmp(other.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_be_bytes() {
+        for &(input, expected) in &[
+            (&[0x01][..], &[0x01][..]),
+            (&[0x00, 0x00, 0x80, 0x00], &[0x80, 0x00]),
+            (&[0xff], &[0xff]),
+        ] {
+            let positive = Positive::from_be_bytes(input).unwrap();
+            assert_eq!(positive.big_endian_without_leading_zero(), expected);
+            assert_eq!(positive.first_byte(), expected[0]);
+        }
+        for zero in &[&[][..], &[0x00], &[0x00, 0x00]] {
+            assert!(Positive::from_be_bytes(zero).is_err());
+            assert!(Positive::from_minimal_be_bytes(zero).is_err());
+        }
+        assert!(Positive::from_minimal_be_bytes(&[0x00, 0x01]).is_err());
+        assert!(Positive::from_minimal_be_bytes(&[0x01, 0x00]).is_ok());
+    }
+
+    #[test]
+    fn test_fixed_width() {
+        let positive = Positive::from_be_bytes(&[0x00, 0x01, 0x02]).unwrap();
+        let mut out = [0xff; 4];
+        positive.write_fixed_width(&mut out).unwrap();
+        assert_eq!(out, [0x00, 0x00, 0x01, 0x02]);
+        let mut out = [0xff; 2];
+        positive.write_fixed_width(&mut out).unwrap();
+        assert_eq!(out, [0x01, 0x02]);
+        assert!(positive.write_fixed_width(&mut [0; 1]).is_err());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_to_bytes() {
+        let positive = Positive::from_be_bytes(&[0x00, 0x7f, 0xff]).unwrap();
+        assert_eq!(positive.to_signed_be_bytes(), &[0x7f, 0xff]);
+        assert_eq!(positive.to_fixed_width(3).unwrap(), &[0x00, 0x7f, 0xff]);
+        assert!(positive.to_fixed_width(1).is_err());
+        let positive = Positive::from_be_bytes(&[0x80, 0x00]).unwrap();
+        assert_eq!(positive.to_signed_be_bytes(), &[0x00, 0x80, 0x00]);
+    }
+
+    #[test]
+    fn test_ordering() {
+        let values = [&[0x01][..], &[0xff], &[0x01, 0x00], &[0x01, 0x01], &[0x80, 0x00, 0x00]];
+        for (i, a) in values.iter().enumerate() {
+            for (j, b) in values.iter().enumerate() {
+                let a = Positive::from_minimal_be_bytes(a).unwrap();
+                let b = Positive::from_minimal_be_bytes(b).unwrap();
+                assert_eq!(a.cmp(&b), i.cmp(&j));
+                assert_eq!(a == b, i == j);
+            }
+        }
+        assert_eq!(
+            Positive::from_be_bytes(&[0x00, 0x05]).unwrap(),
+            Positive::from_be_bytes(&[0x05]).unwrap()
+        );
+    }
+}
@@ -0,0 +1,471 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! JSON Web Encryption ([RFC 7516]) to elliptic curve keys, using ECDH-ES key
+//! agreement ([RFC 7518 Section 4.6]) and AES-GCM content encryption.
+//!
+//! With `ECDH_ES`, the content encryption key is derived from the shared
+//! secret of an ephemeral key and the recipient's key; with `ECDH_ES_A256KW`,
+//! a random content encryption key is wrapped with AES key wrap under a key
+//! derived from it. The recipient's key is on P-256, P-384, or, as in
+//! [RFC 8037], Curve25519, and it is given to `encrypt` as an
+//! `spki::PublicKey`, e.g. from `jwk::parse_public_key()`. The content
+//! encryption algorithms are `A128GCM` and `A256GCM`.
+//!
+//! Only the compact serialization is supported. The protected header that
+//! `encrypt` generates has the `alg`, `enc`, `epk`, and optionally `kid`
+//! parameters. `decrypt` also uses the `apu` and `apv` parameters, which are
+//! inputs to the key derivation, and rejects tokens that use compression
+//! (`zip`) or critical header parameters (`crit`); other parameters are
+//! ignored. The caller chooses the algorithms that `decrypt` accepts, so the
+//! header can't choose them.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{agreement, jose::jwe, rand};
+//!
+//! # fn jwe() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let private_key = jwe::PrivateKey::generate(&agreement::ECDH_P256, &rng)?;
+//!
+//! let token = jwe::encrypt(
+//!     &private_key.public_key()?,
+//!     &jwe::ECDH_ES_A256KW,
+//!     &jwe::A256GCM,
+//!     Some("2019-key"),
+//!     b"hello",
+//!     &rng,
+//! )?;
+//!
+//! assert_eq!(jwe::key_id(&token)?.as_ref().map(|kid| kid.as_str()), Some("2019-key"));
+//! let plaintext = jwe::decrypt(&private_key, &jwe::ECDH_ES_A256KW, &jwe::A256GCM, &token)?;
+//! assert_eq!(plaintext, b"hello");
+//! # Ok(())
+//! # }
+//! # fn main() { jwe().unwrap() }
+//! ```
+//!
+//! [RFC 7516]: https://tools.ietf.org/html/rfc7516
+//! [RFC 7518 Section 4.6]: https://tools.ietf.org/html/rfc7518#section-4.6
+//! [RFC 8037]: https://tools.ietf.org/html/rfc8037
+
+use crate::{
+    aead, aes_cbc, aes_kw, agreement, digest, ec, error,
+    io::base64,
+    jwk::{self, json},
+    rand, spki,
+    zeroize::{self, Zeroizing},
+};
+use alloc::{string::String, vec::Vec};
+use core;
+use untrusted;
+
+/// A key management algorithm, the `alg` header parameter.
+pub struct KeyManagementAlgorithm {
+    id: &'static str,
+    key_wrap: bool,
+}
+
+derive_debug_via_self!(KeyManagementAlgorithm, self.id);
+
+impl KeyManagementAlgorithm {
+    // The `AlgorithmID` input of the key derivation, which is `enc` when the
+    // output is the content encryption key and `alg` when it wraps it.
+    fn kdf_algorithm_id(&self, enc: &ContentEncryptionAlgorithm) -> &'static str {
+        if self.key_wrap {
+            self.id
+        } else {
+            enc.id
+        }
+    }
+}
+
+/// `ECDH-ES`: the content encryption key is the output of the key derivation.
+pub static ECDH_ES: KeyManagementAlgorithm = KeyManagementAlgorithm {
+    id: "ECDH-ES",
+    key_wrap: false,
+};
+
+/// `ECDH-ES+A256KW`: the content encryption key is random and is wrapped
+/// with AES-256 key wrap under the output of the key derivation.
+pub static ECDH_ES_A256KW: KeyManagementAlgorithm = KeyManagementAlgorithm {
+    id: "ECDH-ES+A256KW",
+    key_wrap: true,
+};
+
+/// A content encryption algorithm, the `enc` header parameter.
+pub struct ContentEncryptionAlgorithm {
+    id: &'static str,
+    aead: &'static aead::Algorithm,
+}
+
+derive_debug_via_self!(ContentEncryptionAlgorithm, self.id);
+
+/// `A128GCM`: AES-128-GCM.
+pub static A128GCM: ContentEncryptionAlgorithm = ContentEncryptionAlgorithm {
+    id: "A128GCM",
+    aead: &aead::AES_128_GCM,
+};
+
+/// `A256GCM`: AES-256-GCM.
+pub static A256GCM: ContentEncryptionAlgorithm = ContentEncryptionAlgorithm {
+    id: "A256GCM",
+    aead: &aead::AES_256_GCM,
+};
+
+// The length of the AES-256 key wrap key.
+const KEK_LEN: usize = 32;
+
+// The longest content encryption key, for `A256GCM`.
+const CEK_MAX_LEN: usize = 32;
+
+const IV_LEN: usize = 12;
+const TAG_LEN: usize = aead::MAX_TAG_LEN;
+
+/// A recipient's private key.
+pub struct PrivateKey {
+    private_key: ec::PrivateKey,
+    alg: &'static agreement::Algorithm,
+}
+
+impl PrivateKey {
+    /// Generates a private key for `alg`, which is `agreement::ECDH_P256`,
+    /// `agreement::ECDH_P384`, or `agreement::X25519`.
+    pub fn generate(
+        alg: &'static agreement::Algorithm, rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        Ok(Self {
+            private_key: ec::PrivateKey::generate(alg.i.curve, rng)?,
+            alg,
+        })
+    }
+
+    /// Constructs a private key for `alg` from its bytes, the decoded `d`
+    /// parameter of its JWK: the big-endian scalar for P-256 and P-384, and
+    /// the 32-byte private key for X25519.
+    pub fn from_bytes(
+        alg: &'static agreement::Algorithm, bytes: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        Ok(Self {
+            private_key: ec::PrivateKey::from_bytes(alg.i.curve, untrusted::Input::from(bytes))?,
+            alg,
+        })
+    }
+
+    /// The public key, which is what `encrypt` takes and what
+    /// `jwk::encode_public_key()` encodes.
+    pub fn public_key(&self) -> Result<spki::PublicKey, error::Unspecified> {
+        public_key(&self.private_key, self.alg)
+    }
+}
+
+/// Encrypts `plaintext` to `recipient`, with a `kid` header parameter of
+/// `key_id` if it isn't `None`, and returns the token in the compact
+/// serialization.
+pub fn encrypt(
+    recipient: &spki::PublicKey, alg: &'static KeyManagementAlgorithm,
+    enc: &'static ContentEncryptionAlgorithm, key_id: Option<&str>, plaintext: &[u8],
+    rng: &rand::SecureRandom,
+) -> Result<String, error::Unspecified> {
+    let agreement_alg = agreement_algorithm(recipient)?;
+    let ephemeral = ec::PrivateKey::generate(agreement_alg.i.curve, rng)?;
+    let epk = jwk::encode_public_key(&public_key(&ephemeral, agreement_alg)?);
+
+    let mut header = vec![
+        (String::from("alg"), json::Value::String(alg.id.into())),
+        (String::from("enc"), json::Value::String(enc.id.into())),
+        (String::from("epk"), json::parse(&epk)?),
+    ];
+    if let Some(key_id) = key_id {
+        header.push((String::from("kid"), json::Value::String(key_id.into())));
+    }
+    let header = encode(json::write(&json::Value::Object(header)).as_bytes());
+
+    let alg_id = alg.kdf_algorithm_id(enc);
+    let mut cek = Zeroizing::new([0; CEK_MAX_LEN]);
+    let cek = &mut cek[..enc.aead.key_len()];
+    let encrypted_key = if alg.key_wrap {
+        rng.fill(cek)?;
+        let mut kek = Zeroizing::new([0; KEK_LEN]);
+        derive_key(alg_id, &ephemeral, agreement_alg, recipient.as_ref(), b"", b"", &mut *kek)?;
+        aes_kw::wrap(&aes_cbc::Key::new(&*kek)?, cek)?
+    } else {
+        derive_key(alg_id, &ephemeral, agreement_alg, recipient.as_ref(), b"", b"", cek)?;
+        Vec::new()
+    };
+
+    let mut iv = [0; IV_LEN];
+    rng.fill(&mut iv)?;
+    let key = aead::SealingKey::new(enc.aead, cek)?;
+    let mut in_out = Vec::with_capacity(plaintext.len() + TAG_LEN);
+    in_out.extend_from_slice(plaintext);
+    in_out.resize(plaintext.len() + TAG_LEN, 0);
+    let _ = aead::seal_in_place(&key, &iv, header.as_bytes(), &mut in_out, TAG_LEN)?;
+    let (ciphertext, tag) = in_out.split_at(plaintext.len());
+
+    let mut token = header;
+    for part in &[&encrypted_key[..], &iv, ciphertext, tag] {
+        token.push('.');
+        token.push_str(&encode(part));
+    }
+    Ok(token)
+}
+
+/// Decrypts `token`, which must be in the compact serialization and use the
+/// algorithms `alg` and `enc`, with `private_key`.
+pub fn decrypt(
+    private_key: &PrivateKey, alg: &'static KeyManagementAlgorithm,
+    enc: &'static ContentEncryptionAlgorithm, token: &str,
+) -> Result<Vec<u8>, error::Unspecified> {
+    let parts = split(token)?;
+    let header = parse_header(parts[0])?;
+    if string_member(&header, "alg")? != Some(alg.id)
+        || string_member(&header, "enc")? != Some(enc.id)
+    {
+        return Err(error::Unspecified);
+    }
+    let epk = header.get("epk").ok_or(error::Unspecified)?;
+    let epk = jwk::parse_public_key(&json::write(epk))?;
+    if agreement_algorithm(&epk)? != private_key.alg {
+        return Err(error::Unspecified);
+    }
+    let apu = optional_bytes_member(&header, "apu")?;
+    let apv = optional_bytes_member(&header, "apv")?;
+
+    let encrypted_key = decode(parts[1])?;
+    let alg_id = alg.kdf_algorithm_id(enc);
+    let (agreement_alg, private_key) = (private_key.alg, &private_key.private_key);
+    let mut cek = Zeroizing::new([0; CEK_MAX_LEN]);
+    let cek = &mut cek[..enc.aead.key_len()];
+    if alg.key_wrap {
+        let mut kek = Zeroizing::new([0; KEK_LEN]);
+        derive_key(alg_id, private_key, agreement_alg, epk.as_ref(), &apu, &apv, &mut *kek)?;
+        let mut unwrapped = aes_kw::unwrap(&aes_cbc::Key::new(&*kek)?, &encrypted_key)?;
+        let valid = unwrapped.len() == cek.len();
+        if valid {
+            cek.copy_from_slice(&unwrapped);
+        }
+        zeroize::zeroize(&mut unwrapped);
+        if !valid {
+            return Err(error::Unspecified);
+        }
+    } else {
+        if !encrypted_key.is_empty() {
+            return Err(error::Unspecified);
+        }
+        derive_key(alg_id, private_key, agreement_alg, epk.as_ref(), &apu, &apv, cek)?;
+    }
+
+    let key = aead::OpeningKey::new(enc.aead, cek)?;
+    let iv = decode(parts[2])?;
+    let mut in_out = decode(parts[3])?;
+    let tag = decode(parts[4])?;
+    if tag.len() != TAG_LEN {
+        return Err(error::Unspecified);
+    }
+    in_out.extend_from_slice(&tag);
+    let plaintext_len = aead::open_in_place(&key, &iv, parts[0].as_bytes(), 0, &mut in_out)?.len();
+    in_out.truncate(plaintext_len);
+    Ok(in_out)
+}
+
+/// Returns the `kid` header parameter of `token`, if it has one, without
+/// decrypting or authenticating the token.
+///
+/// This is for choosing the private key with which to decrypt the token. The
+/// key ID must not be trusted until the token has been decrypted.
+pub fn key_id(token: &str) -> Result<Option<String>, error::Unspecified> {
+    let header = parse_header(split(token)?[0])?;
+    Ok(string_member(&header, "kid")?.map(String::from))
+}
+
+// Splits a token in the compact serialization into its five parts.
+fn split(token: &str) -> Result<[&str; 5], error::Unspecified> {
+    let mut parts = [""; 5];
+    let mut split = token.split('.');
+    for part in parts.iter_mut() {
+        *part = split.next().ok_or(error::Unspecified)?;
+    }
+    if split.next().is_some() {
+        return Err(error::Unspecified);
+    }
+    Ok(parts)
+}
+
+// Decodes the protected header, which must be a JSON object without `crit`
+// or `zip` parameters.
+fn parse_header(encoded: &str) -> Result<json::Value, error::Unspecified> {
+    let header = decode(encoded)?;
+    let header = core::str::from_utf8(&header).map_err(|_| error::Unspecified)?;
+    let header = json::parse(header)?;
+    match header {
+        json::Value::Object(_) => (),
+        _ => return Err(error::Unspecified),
+    }
+    if header.get("crit").is_some() || header.get("zip").is_some() {
+        return Err(error::Unspecified);
+    }
+    Ok(header)
+}
+
+// Returns the value of the string-valued header parameter `name`, if present.
+fn string_member<'a>(
+    header: &'a json::Value, name: &str,
+) -> Result<Option<&'a str>, error::Unspecified> {
+    match header.get(name) {
+        Some(json::Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(error::Unspecified),
+        None => Ok(None),
+    }
+}
+
+// Returns the decoded value of the `base64url`-encoded header parameter
+// `name`, which is empty if it isn't present.
+fn optional_bytes_member(header: &json::Value, name: &str) -> Result<Vec<u8>, error::Unspecified> {
+    match string_member(header, name)? {
+        Some(value) => decode(value),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn encode(bytes: &[u8]) -> String { base64::encode(bytes, &base64::URL_SAFE_NO_PAD) }
+
+fn decode(encoded: &str) -> Result<Vec<u8>, error::Unspecified> {
+    base64::decode(encoded, &base64::URL_SAFE_NO_PAD)
+}
+
+fn public_key(
+    private_key: &ec::PrivateKey, alg: &agreement::Algorithm,
+) -> Result<spki::PublicKey, error::Unspecified> {
+    let curve = alg.i.curve;
+    let mut public_key = [0u8; ec::PUBLIC_KEY_MAX_LEN];
+    let public_key = &mut public_key[..curve.public_key_len];
+    private_key.compute_public_key(curve, public_key)?;
+    Ok(spki::PublicKey::from_ec_public_key(curve, public_key))
+}
+
+fn agreement_algorithm(
+    public_key: &spki::PublicKey,
+) -> Result<&'static agreement::Algorithm, error::Unspecified> {
+    let alg_id = public_key.alg_id();
+    if alg_id == spki::EC_PUBLIC_KEY_P256 {
+        Ok(&agreement::ECDH_P256)
+    } else if alg_id == spki::EC_PUBLIC_KEY_P384 {
+        Ok(&agreement::ECDH_P384)
+    } else if alg_id == spki::X25519 {
+        Ok(&agreement::X25519)
+    } else {
+        Err(error::Unspecified)
+    }
+}
+
+// Derives `out`, the content encryption key with `ECDH_ES` and the key
+// wrapping key with `ECDH_ES_A256KW`, from the shared secret of `private_key`
+// and `peer_public_key` (RFC 7518 Section 4.6.2).
+fn derive_key(
+    alg_id: &str, private_key: &ec::PrivateKey, agreement_alg: &agreement::Algorithm,
+    peer_public_key: &[u8], apu: &[u8], apv: &[u8], out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    let mut z = Zeroizing::new([0u8; ec::ELEM_MAX_BYTES]);
+    let z = &mut z[..agreement_alg.i.curve.elem_and_scalar_len];
+    (agreement_alg.i.ecdh)(z, private_key, untrusted::Input::from(peer_public_key))?;
+    concat_kdf(z, alg_id.as_bytes(), apu, apv, out);
+    Ok(())
+}
+
+// The single-step KDF of NIST SP 800-56A Section 5.8.1 with SHA-256, with the
+// `OtherInfo` of RFC 7518 Section 4.6.2.
+fn concat_kdf(z: &[u8], alg_id: &[u8], apu: &[u8], apv: &[u8], out: &mut [u8]) {
+    let key_data_len_bits = (out.len() * 8) as u32;
+    for (i, out) in out.chunks_mut(digest::SHA256_OUTPUT_LEN).enumerate() {
+        let mut ctx = digest::Context::new(&digest::SHA256);
+        ctx.update(&((i + 1) as u32).to_be_bytes());
+        ctx.update(z);
+        for field in &[alg_id, apu, apv] {
+            ctx.update(&(field.len() as u32).to_be_bytes());
+            ctx.update(field);
+        }
+        ctx.update(&key_data_len_bits.to_be_bytes());
+        out.copy_from_slice(&ctx.finish().as_ref()[..out.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_jwe() {
+        test::from_file("src/jose/jwe_tests.txt", |section, test_case| {
+            let agreement_alg = match test_case.consume_string("Curve").as_str() {
+                "P-256" => &agreement::ECDH_P256,
+                "P-384" => &agreement::ECDH_P384,
+                "X25519" => &agreement::X25519,
+                _ => unreachable!(),
+            };
+            let alg = match test_case.consume_string("Alg").as_str() {
+                "ECDH-ES" => &ECDH_ES,
+                "ECDH-ES+A256KW" => &ECDH_ES_A256KW,
+                _ => unreachable!(),
+            };
+            let enc = match test_case.consume_string("Enc").as_str() {
+                "A128GCM" => &A128GCM,
+                "A256GCM" => &A256GCM,
+                _ => unreachable!(),
+            };
+            let private_key =
+                PrivateKey::from_bytes(agreement_alg, &test_case.consume_bytes("RecipientKey"))?;
+            let plaintext = test_case.consume_bytes("Plaintext");
+            let token = test_case.consume_string("Token");
+
+            if section == "Encrypt" {
+                let ephemeral_key = test_case.consume_bytes("EphemeralKey");
+                let cek = test_case.consume_optional_string("CEK");
+                let cek = cek.map(|cek| test::from_hex(&cek).unwrap());
+                let iv = test_case.consume_bytes("IV");
+                let kid = test_case.consume_optional_string("KeyID");
+
+                let mut random = vec![&ephemeral_key[..]];
+                random.extend(cek.as_ref().map(|cek| &cek[..]));
+                random.push(&iv);
+                let rng = test::rand::FixedSliceSequenceRandom::new(&random);
+                let public_key = private_key.public_key()?;
+                let kid = kid.as_ref().map(|kid| kid.as_str());
+                assert_eq!(encrypt(&public_key, alg, enc, kid, &plaintext, &rng)?, token);
+                assert_eq!(key_id(&token)?.as_ref().map(|kid| kid.as_str()), kid);
+            }
+
+            assert_eq!(decrypt(&private_key, alg, enc, &token)?, plaintext);
+
+            // The caller's choice of algorithms is enforced.
+            let other_alg = if alg.key_wrap { &ECDH_ES } else { &ECDH_ES_A256KW };
+            let other_enc = if enc.id == A128GCM.id { &A256GCM } else { &A128GCM };
+            assert!(decrypt(&private_key, other_alg, enc, &token).is_err());
+            assert!(decrypt(&private_key, alg, other_enc, &token).is_err());
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_concat_kdf() {
+        // RFC 7518 Appendix C.
+        let z = test::from_hex("9e56d91d817135d372834283bf84269cfb316ea3da806a48f6daa7798cfe90c4")
+            .unwrap();
+        let mut out = [0; 16];
+        concat_kdf(&z, b"A128GCM", b"Alice", b"Bob", &mut out);
+        assert_eq!(encode(&out), "VqqN6vgjbSBcIijNcacQGg");
+    }
+}
@@ -0,0 +1,376 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! JSON Web Keys ([RFC 7517]).
+//!
+//! Public keys are converted between JWKs and `spki::PublicKey`, which is what
+//! the `public_key()` methods of the key pair types return and what
+//! `signature::verify()` consumes via `as_ref()`. Private keys are converted
+//! between JWKs and PKCS#8 documents, which are what the key pair types are
+//! constructed from. The supported key types are `OKP` with the `Ed25519`
+//! curve ([RFC 8037]; `X25519` public keys too), `EC` with the `P-256` and
+//! `P-384` curves, and `RSA` ([RFC 7518 Section 6]). Private `RSA` keys
+//! require the `rsa_signing` feature, and multi-prime RSA keys are not
+//! supported.
+//!
+//! JWKs are parsed strictly: the JSON must be a single well-formed object
+//! without duplicate member names, the `base64url` values must be canonical,
+//! and integers and coordinates must have exactly the lengths RFC 7518
+//! requires. Members other than the key parameters, e.g. `alg`, `use`, `kid`,
+//! and `key_ops`, are ignored. Generated JWKs contain only the key
+//! parameters, in lexicographic order without whitespace, so the generated
+//! public JWK is also the input to its [RFC 7638] thumbprint.
+//!
+//! [RFC 7517]: https://tools.ietf.org/html/rfc7517
+//! [RFC 7518 Section 6]: https://tools.ietf.org/html/rfc7518#section-6
+//! [RFC 7638]: https://tools.ietf.org/html/rfc7638
+//! [RFC 8037]: https://tools.ietf.org/html/rfc8037
+
+use crate::{
+    digest,
+    ec::{self, curve25519::ed25519, suite_b},
+    error,
+    io::{self, base64, der},
+    pkcs8, signature, spki,
+};
+use alloc::{string::String, vec::Vec};
+use untrusted;
+
+pub(crate) mod json;
+
+/// Parses a public JWK.
+///
+/// Private JWKs are accepted too; the private key parameters are ignored.
+pub fn parse_public_key(jwk: &str) -> Result<spki::PublicKey, error::KeyRejected> {
+    let members = Members::parse(jwk)?;
+    match members.get("kty")? {
+        "OKP" => {
+            let alg_id = match members.get("crv")? {
+                "Ed25519" => spki::ED25519,
+                "X25519" => spki::X25519,
+                _ => return Err(error::KeyRejected::wrong_algorithm()),
+            };
+            let x = members.fixed_len_bytes("x", signature::ED25519_PUBLIC_KEY_LEN)?;
+            Ok(spki::PublicKey::new(alg_id, &x))
+        },
+        "EC" => {
+            let (curve, _) = ec_curve(&members)?;
+            let public_key = ec_public_key(&members, curve)?;
+            Ok(spki::PublicKey::from_ec_public_key(curve, &public_key))
+        },
+        "RSA" => {
+            let n = members.integer("n", spki::RSA_MODULUS_MAX_LEN)?;
+            let e = members.integer("e", spki::RSA_EXPONENT_MAX_LEN)?;
+            Ok(spki::PublicKey::from_rsa_components(&n, &e))
+        },
+        _ => Err(error::KeyRejected::wrong_algorithm()),
+    }
+}
+
+/// Encodes `public_key` as a public JWK.
+pub fn encode_public_key(public_key: &spki::PublicKey) -> String {
+    let key = public_key.as_ref();
+    let alg_id = public_key.alg_id();
+    if alg_id == spki::ED25519 || alg_id == spki::X25519 {
+        let crv = if alg_id == spki::ED25519 {
+            "Ed25519"
+        } else {
+            "X25519"
+        };
+        let x = base64::encode(key, &base64::URL_SAFE_NO_PAD);
+        json::write_object(&[("crv", crv), ("kty", "OKP"), ("x", &x)])
+    } else if alg_id == spki::RSA_ENCRYPTION {
+        // `public_key` was constructed from a well-formed `RSAPublicKey`.
+        let (n, e) = untrusted::Input::from(key)
+            .read_all(error::Unspecified, |input| {
+                der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+                    let n = der::positive_integer(input)?;
+                    let e = der::positive_integer(input)?;
+                    Ok((n, e))
+                })
+            })
+            .unwrap();
+        let n = base64::encode(n.as_slice_less_safe(), &base64::URL_SAFE_NO_PAD);
+        let e = base64::encode(e.as_slice_less_safe(), &base64::URL_SAFE_NO_PAD);
+        json::write_object(&[("e", &e), ("kty", "RSA"), ("n", &n)])
+    } else {
+        let crv = if alg_id == spki::EC_PUBLIC_KEY_P256 {
+            "P-256"
+        } else {
+            "P-384"
+        };
+        let (x, y) = key[1..].split_at((key.len() - 1) / 2);
+        let x = base64::encode(x, &base64::URL_SAFE_NO_PAD);
+        let y = base64::encode(y, &base64::URL_SAFE_NO_PAD);
+        json::write_object(&[("crv", crv), ("kty", "EC"), ("x", &x), ("y", &y)])
+    }
+}
+
+/// Computes the [RFC 7638] thumbprint of `public_key` using `digest_alg`,
+/// which is usually `digest::SHA256`.
+///
+/// Use `base64url` to encode the result as is done for `kid` values and in
+/// ACME.
+///
+/// [RFC 7638]: https://tools.ietf.org/html/rfc7638
+pub fn thumbprint(
+    public_key: &spki::PublicKey, digest_alg: &'static digest::Algorithm,
+) -> digest::Digest {
+    digest::digest(digest_alg, encode_public_key(public_key).as_bytes())
+}
+
+/// Parses a private JWK and returns the key as a PKCS#8 document.
+///
+/// The public key parameters are required and are verified to be consistent
+/// with the private key. `Ed25519` keys are returned as PKCS#8 v2 documents
+/// suitable for `Ed25519KeyPair::from_pkcs8()`; `EC` keys are returned as the
+/// PKCS#8 v1 documents that `ECDSAKeyPair::from_pkcs8()` expects; and `RSA`
+/// keys are returned in the form `RSAKeyPair::from_pkcs8()` expects. `X25519`
+/// private keys are rejected because `agreement` only supports ephemeral keys.
+pub fn parse_private_key(jwk: &str) -> Result<Vec<u8>, error::KeyRejected> {
+    let members = Members::parse(jwk)?;
+    match members.get("kty")? {
+        "OKP" => {
+            if members.get("crv")? != "Ed25519" {
+                return Err(error::KeyRejected::wrong_algorithm());
+            }
+            let d = members.fixed_len_bytes("d", signature::ED25519_SEED_LEN)?;
+            let x = members.fixed_len_bytes("x", signature::ED25519_PUBLIC_KEY_LEN)?;
+            let _ = signature::Ed25519KeyPair::from_seed_and_public_key(
+                untrusted::Input::from(&d),
+                untrusted::Input::from(&x),
+            )?;
+            Ok(pkcs8::wrap_key(&ed25519::signing::PKCS8_TEMPLATE, &d, &x)
+                .as_ref()
+                .to_vec())
+        },
+        "EC" => {
+            let (curve, template) = ec_curve(&members)?;
+            let public_key = ec_public_key(&members, curve)?;
+            let d = members.fixed_len_bytes("d", curve.elem_and_scalar_len)?;
+            let _ = suite_b::key_pair_from_bytes(
+                curve,
+                untrusted::Input::from(&d),
+                untrusted::Input::from(&public_key),
+            )?;
+            Ok(pkcs8::wrap_key(template, &d, &public_key).as_ref().to_vec())
+        },
+        #[cfg(feature = "rsa_signing")]
+        "RSA" => rsa_private_key_to_pkcs8(&members),
+        _ => Err(error::KeyRejected::wrong_algorithm()),
+    }
+}
+
+/// Encodes the PKCS#8 private key `pkcs8` as a private JWK.
+///
+/// The document is validated exactly as the corresponding key pair type's
+/// `from_pkcs8()` does, except that Ed25519 PKCS#8 v1 documents are accepted
+/// as with `Ed25519KeyPair::from_pkcs8_maybe_unchecked()`.
+pub fn encode_private_key(pkcs8: untrusted::Input) -> Result<String, error::KeyRejected> {
+    let alg_id = pkcs8_alg_id(pkcs8)?;
+    if alg_id == spki::ED25519 {
+        let key_pair = signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(pkcs8)?;
+        let (seed, _) = ed25519::signing::unwrap_pkcs8(pkcs8::Version::V1OrV2, pkcs8)?;
+        let d = base64::encode(seed.as_slice_less_safe(), &base64::URL_SAFE_NO_PAD);
+        let x = base64::encode(key_pair.public_key_bytes(), &base64::URL_SAFE_NO_PAD);
+        return Ok(json::write_object(&[
+            ("crv", "Ed25519"),
+            ("d", &d),
+            ("kty", "OKP"),
+            ("x", &x),
+        ]));
+    }
+    #[cfg(feature = "rsa_signing")]
+    {
+        if alg_id == spki::RSA_ENCRYPTION {
+            return rsa_private_key_from_pkcs8(pkcs8);
+        }
+    }
+    let (crv, curve, template) = if alg_id == spki::EC_PUBLIC_KEY_P256 {
+        (
+            "P-256",
+            &suite_b::curve::P256,
+            &suite_b::ecdsa::signing::EC_PUBLIC_KEY_P256_PKCS8_V1_TEMPLATE,
+        )
+    } else if alg_id == spki::EC_PUBLIC_KEY_P384 {
+        (
+            "P-384",
+            &suite_b::curve::P384,
+            &suite_b::ecdsa::signing::EC_PUBLIC_KEY_P384_PKCS8_V1_TEMPLATE,
+        )
+    } else {
+        return Err(error::KeyRejected::wrong_algorithm());
+    };
+    let key_pair = suite_b::key_pair_from_pkcs8(curve, template, pkcs8)?;
+    let d = base64::encode(key_pair.private_key.bytes(curve), &base64::URL_SAFE_NO_PAD);
+    let (x, y) = key_pair.public_key[1..curve.public_key_len].split_at(curve.elem_and_scalar_len);
+    let x = base64::encode(x, &base64::URL_SAFE_NO_PAD);
+    let y = base64::encode(y, &base64::URL_SAFE_NO_PAD);
+    Ok(json::write_object(&[
+        ("crv", crv),
+        ("d", &d),
+        ("kty", "EC"),
+        ("x", &x),
+        ("y", &y),
+    ]))
+}
+
+// Returns the value of the `AlgorithmIdentifier` of a PKCS#8 document.
+fn pkcs8_alg_id(pkcs8: untrusted::Input) -> Result<untrusted::Input, error::KeyRejected> {
+    let mut input = untrusted::Reader::new(pkcs8);
+    der::nested(&mut input, der::Tag::Sequence, error::Unspecified, |input| {
+        let _ = der::small_nonnegative_integer(input)?;
+        let alg_id = der::expect_tag_and_get_value(input, der::Tag::Sequence)?;
+        let _ = input.skip_to_end();
+        Ok(alg_id)
+    })
+    .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+}
+
+fn ec_curve(
+    members: &Members,
+) -> Result<(&'static ec::Curve, &'static pkcs8::Template), error::KeyRejected> {
+    match members.get("crv")? {
+        "P-256" => Ok((
+            &suite_b::curve::P256,
+            &suite_b::ecdsa::signing::EC_PUBLIC_KEY_P256_PKCS8_V1_TEMPLATE,
+        )),
+        "P-384" => Ok((
+            &suite_b::curve::P384,
+            &suite_b::ecdsa::signing::EC_PUBLIC_KEY_P384_PKCS8_V1_TEMPLATE,
+        )),
+        _ => Err(error::KeyRejected::wrong_algorithm()),
+    }
+}
+
+// Returns the uncompressed point for the `x` and `y` coordinates.
+fn ec_public_key(members: &Members, curve: &ec::Curve) -> Result<Vec<u8>, error::KeyRejected> {
+    let x = members.fixed_len_bytes("x", curve.elem_and_scalar_len)?;
+    let y = members.fixed_len_bytes("y", curve.elem_and_scalar_len)?;
+    let mut public_key = Vec::with_capacity(curve.public_key_len);
+    public_key.push(4);
+    public_key.extend_from_slice(&x);
+    public_key.extend_from_slice(&y);
+    Ok(public_key)
+}
+
+// The order of the components in an `RSAPrivateKey` after the version.
+#[cfg(feature = "rsa_signing")]
+const RSA_PRIVATE_KEY_MEMBERS: [&str; 8] = ["n", "e", "d", "p", "q", "dp", "dq", "qi"];
+
+#[cfg(feature = "rsa_signing")]
+fn rsa_private_key_to_pkcs8(members: &Members) -> Result<Vec<u8>, error::KeyRejected> {
+    if members.contains("oth") {
+        return Err(error::KeyRejected::wrong_algorithm());
+    }
+    let values = RSA_PRIVATE_KEY_MEMBERS
+        .iter()
+        .map(|name| members.integer(name, spki::RSA_MODULUS_MAX_LEN))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut pkcs8 = der::Writer::new();
+    pkcs8.write_sequence(|pkcs8| {
+        pkcs8.write_integer(&[0]);
+        pkcs8.write_tlv(der::Tag::Sequence, spki::RSA_ENCRYPTION);
+        pkcs8.write_nested(der::Tag::OctetString, |private_key| {
+            private_key.write_sequence(|rsa_private_key| {
+                rsa_private_key.write_integer(&[0]);
+                for value in &values {
+                    rsa_private_key.write_integer(value);
+                }
+            })
+        });
+    });
+    let pkcs8 = pkcs8.into_bytes();
+
+    let _ = signature::RSAKeyPair::from_pkcs8(untrusted::Input::from(&pkcs8))?;
+    Ok(pkcs8)
+}
+
+#[cfg(feature = "rsa_signing")]
+fn rsa_private_key_from_pkcs8(pkcs8: untrusted::Input) -> Result<String, error::KeyRejected> {
+    let _ = signature::RSAKeyPair::from_pkcs8(pkcs8)?;
+    let (rsa_private_key, _) =
+        pkcs8::unwrap_key_(spki::RSA_ENCRYPTION, pkcs8::Version::V1Only, pkcs8)?;
+    let values = rsa_private_key
+        .read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+                let _ = der::small_nonnegative_integer(input)?;
+                let mut values = Vec::with_capacity(RSA_PRIVATE_KEY_MEMBERS.len());
+                for _ in RSA_PRIVATE_KEY_MEMBERS.iter() {
+                    let value = der::positive_integer(input)?;
+                    let value = value.as_slice_less_safe();
+                    values.push(base64::encode(value, &base64::URL_SAFE_NO_PAD));
+                }
+                Ok(values)
+            })
+        })
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+
+    let mut members: Vec<(&str, &str)> = RSA_PRIVATE_KEY_MEMBERS
+        .iter()
+        .cloned()
+        .zip(values.iter().map(|v| v.as_str()))
+        .collect();
+    members.push(("kty", "RSA"));
+    members.sort();
+    Ok(json::write_object(&members))
+}
+
+struct Members(Vec<(String, Option<String>)>);
+
+impl Members {
+    fn parse(jwk: &str) -> Result<Self, error::KeyRejected> {
+        json::parse_object(jwk)
+            .map(Members)
+            .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    fn contains(&self, name: &str) -> bool { self.0.iter().any(|(n, _)| n == name) }
+
+    // Returns the value of the string-valued member `name`.
+    fn get(&self, name: &str) -> Result<&str, error::KeyRejected> {
+        self.0
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, value)| value.as_ref())
+            .map(|value| value.as_str())
+            .ok_or_else(error::KeyRejected::invalid_encoding)
+    }
+
+    fn bytes(&self, name: &str) -> Result<Vec<u8>, error::KeyRejected> {
+        base64::decode(self.get(name)?, &base64::URL_SAFE_NO_PAD)
+            .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+    }
+
+    fn fixed_len_bytes(&self, name: &str, len: usize) -> Result<Vec<u8>, error::KeyRejected> {
+        let value = self.bytes(name)?;
+        if value.len() != len {
+            return Err(error::KeyRejected::invalid_encoding());
+        }
+        Ok(value)
+    }
+
+    // RFC 7518 Section 2 `Base64urlUInt`, which must be positive here.
+    fn integer(&self, name: &str, max_len: usize) -> Result<Vec<u8>, error::KeyRejected> {
+        let value = self.bytes(name)?;
+        let positive = io::Positive::from_minimal_be_bytes(&value)
+            .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+        if positive.big_endian_without_leading_zero().len() > max_len {
+            return Err(error::KeyRejected::too_large());
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,466 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Just enough JSON ([RFC 8259]) for JWKs and for *ring*'s test vectors.
+//! `parse_object` returns the members of a top-level object, with the values
+//! of the string-valued ones. Values of other types are validated and then
+//! skipped, so that members like `key_ops` and `ext` don't cause keys to be
+//! rejected. `parse` returns the whole value, e.g. for the Wycheproof test
+//! vector files read by `test::wycheproof`.
+//!
+//! [RFC 8259]: https://tools.ietf.org/html/rfc8259
+
+use crate::error;
+use alloc::{string::String, vec::Vec};
+
+// Nested arrays and objects deeper than this are rejected.
+const MAX_DEPTH: usize = 16;
+
+/// A JSON value.
+///
+/// Numbers are kept in their textual form; they are only validated.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+
+    /// The members of an object, in order. Member names are unique.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Returns the value of the member named `name`, if this is an object
+    /// that has one.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Parses `input`, which must be a single JSON value. Duplicate member names
+/// are rejected.
+pub fn parse(input: &str) -> Result<Value, error::Unspecified> {
+    let mut parser = Parser {
+        input: input.as_bytes(),
+        i: 0,
+    };
+    parser.skip_whitespace();
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.i != parser.input.len() {
+        return Err(error::Unspecified);
+    }
+    Ok(value)
+}
+
+/// Parses `input`, which must be a single JSON object, and returns the names
+/// of its members and, for members that have string values, their values.
+/// Duplicate member names are rejected.
+pub fn parse_object(input: &str) -> Result<Vec<(String, Option<String>)>, error::Unspecified> {
+    match parse(input)? {
+        Value::Object(members) => Ok(members
+            .into_iter()
+            .map(|(name, value)| match value {
+                Value::String(value) => (name, Some(value)),
+                _ => (name, None),
+            })
+            .collect()),
+        _ => Err(error::Unspecified),
+    }
+}
+
+/// Encodes `members` as a JSON object, in the given order and without
+/// whitespace.
+pub fn write_object(members: &[(&str, &str)]) -> String {
+    let mut out = String::from("{");
+    for (i, (name, value)) in members.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_string(&mut out, name);
+        out.push(':');
+        write_string(&mut out, value);
+    }
+    out.push('}');
+    out
+}
+
+/// Encodes `value` without whitespace.
+pub fn write(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(true) => out.push_str("true"),
+        Value::Bool(false) => out.push_str("false"),
+        Value::Number(n) => out.push_str(n),
+        Value::String(s) => write_string(out, s),
+        Value::Array(elements) => {
+            out.push('[');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, element);
+            }
+            out.push(']');
+        },
+        Value::Object(members) => {
+            out.push('{');
+            for (i, (name, value)) in members.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, name);
+                out.push(':');
+                write_value(out, value);
+            }
+            out.push('}');
+        },
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                const HEX: &[u8; 16] = b"0123456789abcdef";
+                out.push_str("\\u00");
+                out.push(char::from(HEX[(c as usize) >> 4]));
+                out.push(char::from(HEX[(c as usize) & 0xf]));
+            },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    i: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> { self.input.get(self.i).cloned() }
+
+    fn next(&mut self) -> Result<u8, error::Unspecified> {
+        let b = self.peek().ok_or(error::Unspecified)?;
+        self.i += 1;
+        Ok(b)
+    }
+
+    fn consume(&mut self, b: u8) -> bool {
+        if self.peek() == Some(b) {
+            self.i += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Result<(), error::Unspecified> {
+        if self.consume(b) {
+            Ok(())
+        } else {
+            Err(error::Unspecified)
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.i += 1;
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, error::Unspecified> {
+        if depth > MAX_DEPTH {
+            return Err(error::Unspecified);
+        }
+        let value = match self.peek().ok_or(error::Unspecified)? {
+            b'"' => Value::String(self.string()?),
+            b'{' => Value::Object(self.object(depth)?),
+            b'[' => Value::Array(self.array(depth)?),
+            b't' => {
+                self.literal(b"true")?;
+                Value::Bool(true)
+            },
+            b'f' => {
+                self.literal(b"false")?;
+                Value::Bool(false)
+            },
+            b'n' => {
+                self.literal(b"null")?;
+                Value::Null
+            },
+            _ => Value::Number(self.number()?),
+        };
+        Ok(value)
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Vec<(String, Value)>, error::Unspecified> {
+        let mut members: Vec<(String, Value)> = Vec::new();
+        self.i += 1;
+        self.skip_whitespace();
+        if self.consume(b'}') {
+            return Ok(members);
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+            if members.iter().any(|(n, _)| *n == name) {
+                return Err(error::Unspecified);
+            }
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+            let value = self.value(depth + 1)?;
+            members.push((name, value));
+            self.skip_whitespace();
+            if self.consume(b'}') {
+                return Ok(members);
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Vec<Value>, error::Unspecified> {
+        let mut elements = Vec::new();
+        self.i += 1;
+        self.skip_whitespace();
+        if self.consume(b']') {
+            return Ok(elements);
+        }
+        loop {
+            self.skip_whitespace();
+            elements.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            if self.consume(b']') {
+                return Ok(elements);
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn literal(&mut self, literal: &[u8]) -> Result<(), error::Unspecified> {
+        if !self.input[self.i..].starts_with(literal) {
+            return Err(error::Unspecified);
+        }
+        self.i += literal.len();
+        Ok(())
+    }
+
+    // `number = [ minus ] int [ frac ] [ exp ]`
+    fn number(&mut self) -> Result<String, error::Unspecified> {
+        let start = self.i;
+        let _ = self.consume(b'-');
+        if !self.consume(b'0') {
+            self.digits()?;
+        }
+        if self.consume(b'.') {
+            self.digits()?;
+        }
+        if self.consume(b'e') || self.consume(b'E') {
+            let _ = self.consume(b'+') || self.consume(b'-');
+            self.digits()?;
+        }
+        // The number is all ASCII, so this never fails.
+        String::from_utf8(self.input[start..self.i].to_vec()).map_err(|_| error::Unspecified)
+    }
+
+    // One or more digits.
+    fn digits(&mut self) -> Result<(), error::Unspecified> {
+        let start = self.i;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.i += 1;
+        }
+        if self.i == start {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<String, error::Unspecified> {
+        self.expect(b'"')?;
+        let mut out = Vec::new();
+        loop {
+            match self.next()? {
+                b'"' => break,
+                b'\\' => {
+                    let c = match self.next()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(error::Unspecified),
+                    };
+                    let mut buf = [0; 4];
+                    out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                },
+                b if b < 0x20 => return Err(error::Unspecified),
+                b => out.push(b),
+            }
+        }
+        // `input` is a `str` and the string ends at an ASCII character, so
+        // this never fails.
+        String::from_utf8(out).map_err(|_| error::Unspecified)
+    }
+
+    // Parses the rest of a `\uXXXX` escape, including the second half of a
+    // surrogate pair.
+    fn unicode_escape(&mut self) -> Result<char, error::Unspecified> {
+        let first = self.hex4()?;
+        let code_point = match first {
+            0xd800..=0xdbff => {
+                self.expect(b'\\')?;
+                self.expect(b'u')?;
+                let second = self.hex4()?;
+                if second < 0xdc00 || second > 0xdfff {
+                    return Err(error::Unspecified);
+                }
+                0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)
+            },
+            0xdc00..=0xdfff => return Err(error::Unspecified),
+            _ => first,
+        };
+        core::char::from_u32(code_point).ok_or(error::Unspecified)
+    }
+
+    fn hex4(&mut self) -> Result<u32, error::Unspecified> {
+        let mut r = 0;
+        for _ in 0..4 {
+            let digit = match self.next()? {
+                b @ b'0'..=b'9' => b - b'0',
+                b @ b'a'..=b'f' => b - b'a' + 10,
+                b @ b'A'..=b'F' => b - b'A' + 10,
+                _ => return Err(error::Unspecified),
+            };
+            r = (r << 4) | u32::from(digit);
+        }
+        Ok(r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_object() {
+        let members = parse_object(
+            " {\"kty\" : \"OKP\", \"key_ops\": [\"sign\", {\"a\": [1, -2.5e+3, null]}],\n\
+             \"ext\":true, \"x\":\"a\\u00e9\\ud83d\\ude00\\\"\\/\"} ",
+        )
+        .unwrap();
+        assert_eq!(
+            members,
+            vec![
+                (String::from("kty"), Some(String::from("OKP"))),
+                (String::from("key_ops"), None),
+                (String::from("ext"), None),
+                (String::from("x"), Some(String::from("a\u{e9}\u{1f600}\"/"))),
+            ]
+        );
+        assert_eq!(parse_object("{}").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_parse_object_rejects_malformed() {
+        for bad in &[
+            "",
+            "[]",
+            "\"a\"",
+            "{",
+            "{\"a\":\"b\",}",
+            "{\"a\":\"b\"} x",
+            "{\"a\":\"b\",\"a\":\"c\"}",
+            "{\"a\":1,\"a\":\"c\"}",
+            "{\"a\":01}",
+            "{\"a\":1.}",
+            "{\"a\":-}",
+            "{\"a\":tru}",
+            "{\"a\":\"\\ud800\"}",
+            "{\"a\":\"\\udc00\"}",
+            "{\"a\":\"\\x\"}",
+            "{\"a\":\"\t\"}",
+            "{'a':\"b\"}",
+            "{\"a\":[[[[[[[[[[[[[[[[[[]]]]]]]]]]]]]]]]]]}",
+        ] {
+            assert!(parse_object(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let value = parse("[{\"a\": [true, false, null]}, -0.5e1, \"b\", 0, {}]").unwrap();
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                Value::Object(vec![(
+                    String::from("a"),
+                    Value::Array(vec![Value::Bool(true), Value::Bool(false), Value::Null]),
+                )]),
+                Value::Number(String::from("-0.5e1")),
+                Value::String(String::from("b")),
+                Value::Number(String::from("0")),
+                Value::Object(vec![]),
+            ])
+        );
+        assert_eq!(value.get("a"), None);
+
+        let value = parse("{\"a\": 1, \"b\": {\"c\": \"d\"}}").unwrap();
+        assert_eq!(value.get("a"), Some(&Value::Number(String::from("1"))));
+        assert_eq!(
+            value.get("b").and_then(|b| b.get("c")),
+            Some(&Value::String(String::from("d")))
+        );
+        assert_eq!(value.get("c"), None);
+
+        let json = "[{\"a\":[true,false,null]},-0.5e1,\"b\\u0001\",0,{}]";
+        assert_eq!(write(&parse(json).unwrap()), json);
+
+        for bad in &["", "[1,]", "[1] 2", "{\"a\":{\"b\":1,\"b\":2}}", "[\"a\" 1]"] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_write_object() {
+        let json = write_object(&[("kty", "OKP"), ("x", "a\"\\\n")]);
+        assert_eq!(json, "{\"kty\":\"OKP\",\"x\":\"a\\\"\\\\\\u000a\"}");
+        assert_eq!(
+            parse_object(&json).unwrap(),
+            vec![
+                (String::from("kty"), Some(String::from("OKP"))),
+                (String::from("x"), Some(String::from("a\"\\\n"))),
+            ]
+        );
+    }
+}
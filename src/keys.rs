@@ -0,0 +1,601 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Opaque key handles that can only be exported wrapped.
+//!
+//! A `Handle` holds a key for signing, for an AEAD, or for HMAC, and can be
+//! used like the corresponding key type, but its key material can't be read
+//! back unless the handle was constructed with `Extractable::Yes`. Otherwise
+//! the only way to get a key out of a `Handle` is `Handle::wrap()`, which
+//! encrypts the key material under a key encryption key (KEK) that the
+//! application provides; `Handle::unwrap()` turns the wrapped key back into
+//! a `Handle`. Applications can store wrapped keys anywhere, and only need to
+//! keep the KEK itself safe, e.g. in a secrets manager, or derive it from a
+//! password with `pbkdf2`.
+//!
+//! # Wrapped Key Format
+//!
+//! A wrapped key is a version byte, which is 1, a byte that identifies the
+//! `WrappingAlgorithm`, a byte that identifies the `KeyType`, a byte that is
+//! 1 if the key is extractable and 0 otherwise, and then the encrypted key
+//! material:
+//!
+//!   * For `KW_AES_128` and `KW_AES_256`, the first four bytes followed by
+//!     the key material, wrapped with AES key wrap with padding
+//!     ([RFC 5649]).
+//!
+//!   * For `KW_AES_256_GCM` and `KW_CHACHA20_POLY1305`, a random nonce
+//!     followed by the key material sealed with the first four bytes as the
+//!     additional authenticated data.
+//!
+//! Either way the first four bytes are authenticated, so a wrapped key can't
+//! be unwrapped as a key of another type, or as an extractable key when it
+//! wasn't one. The key material is a
+//! PKCS#8 document for signing keys and the raw key otherwise, so the same
+//! keys can be imported with `Handle::import()`.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{keys, rand};
+//!
+//! # fn wrap_and_unwrap() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let mut kek_bytes = [0u8; 32];
+//! rand::SecureRandom::fill(&rng, &mut kek_bytes)?;
+//! let kek = keys::Kek::new(&keys::KW_AES_256, &kek_bytes)?;
+//!
+//! let handle = keys::Handle::generate(&keys::ED25519, keys::Extractable::No, &rng)?;
+//! let wrapped = handle.wrap(&kek, &rng)?;
+//!
+//! // ... store `wrapped` and later load it again ...
+//!
+//! let handle = keys::Handle::unwrap(&kek, &wrapped)?;
+//! let signature = handle.sign(&rng, b"hello, world")?;
+//! # Ok(())
+//! # }
+//! # fn main() { wrap_and_unwrap().unwrap() }
+//! ```
+//!
+//! [RFC 5649]: https://tools.ietf.org/html/rfc5649
+
+use crate::{
+    aead, aes_cbc, aes_kw, digest, ec::suite_b::ecdsa, error, hmac, locked::LockedSlice, rand,
+    signature, spki, zeroize,
+};
+use alloc::vec::Vec;
+use untrusted;
+
+/// A type of key that a `Handle` can hold.
+pub struct KeyType {
+    id: KeyTypeID,
+    kind: KeyKind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+enum KeyTypeID {
+    ED25519 = 1,
+    ECDSA_P256_SHA256_ASN1_SIGNING = 2,
+    ECDSA_P384_SHA384_ASN1_SIGNING = 3,
+    AES_128_GCM = 4,
+    AES_256_GCM = 5,
+    CHACHA20_POLY1305 = 6,
+    HMAC_SHA256 = 7,
+    HMAC_SHA384 = 8,
+    HMAC_SHA512 = 9,
+}
+
+enum KeyKind {
+    Ed25519,
+    Ecdsa(&'static ecdsa::signing::Algorithm),
+    Aead(&'static aead::Algorithm),
+    Hmac(&'static digest::Algorithm),
+}
+
+derive_debug_via_self!(KeyType, self.id);
+
+impl PartialEq for KeyType {
+    fn eq(&self, other: &Self) -> bool { self.id == other.id }
+}
+
+impl Eq for KeyType {}
+
+/// Ed25519 signing keys, as PKCS#8 documents.
+pub static ED25519: KeyType = KeyType {
+    id: KeyTypeID::ED25519,
+    kind: KeyKind::Ed25519,
+};
+
+/// ECDSA signing keys for `signature::ECDSA_P256_SHA256_ASN1_SIGNING`, as
+/// PKCS#8 documents.
+pub static ECDSA_P256_SHA256_ASN1_SIGNING: KeyType = KeyType {
+    id: KeyTypeID::ECDSA_P256_SHA256_ASN1_SIGNING,
+    kind: KeyKind::Ecdsa(&signature::ECDSA_P256_SHA256_ASN1_SIGNING),
+};
+
+/// ECDSA signing keys for `signature::ECDSA_P384_SHA384_ASN1_SIGNING`, as
+/// PKCS#8 documents.
+pub static ECDSA_P384_SHA384_ASN1_SIGNING: KeyType = KeyType {
+    id: KeyTypeID::ECDSA_P384_SHA384_ASN1_SIGNING,
+    kind: KeyKind::Ecdsa(&signature::ECDSA_P384_SHA384_ASN1_SIGNING),
+};
+
+/// `aead::AES_128_GCM` keys.
+pub static AES_128_GCM: KeyType = KeyType {
+    id: KeyTypeID::AES_128_GCM,
+    kind: KeyKind::Aead(&aead::AES_128_GCM),
+};
+
+/// `aead::AES_256_GCM` keys.
+pub static AES_256_GCM: KeyType = KeyType {
+    id: KeyTypeID::AES_256_GCM,
+    kind: KeyKind::Aead(&aead::AES_256_GCM),
+};
+
+/// `aead::CHACHA20_POLY1305` keys.
+pub static CHACHA20_POLY1305: KeyType = KeyType {
+    id: KeyTypeID::CHACHA20_POLY1305,
+    kind: KeyKind::Aead(&aead::CHACHA20_POLY1305),
+};
+
+/// HMAC-SHA256 keys.
+pub static HMAC_SHA256: KeyType = KeyType {
+    id: KeyTypeID::HMAC_SHA256,
+    kind: KeyKind::Hmac(&digest::SHA256),
+};
+
+/// HMAC-SHA384 keys.
+pub static HMAC_SHA384: KeyType = KeyType {
+    id: KeyTypeID::HMAC_SHA384,
+    kind: KeyKind::Hmac(&digest::SHA384),
+};
+
+/// HMAC-SHA512 keys.
+pub static HMAC_SHA512: KeyType = KeyType {
+    id: KeyTypeID::HMAC_SHA512,
+    kind: KeyKind::Hmac(&digest::SHA512),
+};
+
+static KEY_TYPES: [&KeyType; 9] = [
+    &ED25519,
+    &ECDSA_P256_SHA256_ASN1_SIGNING,
+    &ECDSA_P384_SHA384_ASN1_SIGNING,
+    &AES_128_GCM,
+    &AES_256_GCM,
+    &CHACHA20_POLY1305,
+    &HMAC_SHA256,
+    &HMAC_SHA384,
+    &HMAC_SHA512,
+];
+
+/// An algorithm for wrapping keys under a `Kek`.
+pub struct WrappingAlgorithm {
+    id: WrappingAlgorithmID,
+    key_len: usize,
+    aead: Option<&'static aead::Algorithm>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+enum WrappingAlgorithmID {
+    KW_AES_128 = 1,
+    KW_AES_256 = 2,
+    KW_AES_256_GCM = 3,
+    KW_CHACHA20_POLY1305 = 4,
+}
+
+derive_debug_via_self!(WrappingAlgorithm, self.id);
+
+impl PartialEq for WrappingAlgorithm {
+    fn eq(&self, other: &Self) -> bool { self.id == other.id }
+}
+
+impl Eq for WrappingAlgorithm {}
+
+impl WrappingAlgorithm {
+    /// The length of the KEK in bytes.
+    #[inline]
+    pub fn key_len(&self) -> usize { self.key_len }
+}
+
+/// AES-128 key wrap with padding, as specified in [RFC 5649].
+///
+/// [RFC 5649]: https://tools.ietf.org/html/rfc5649
+pub static KW_AES_128: WrappingAlgorithm = WrappingAlgorithm {
+    id: WrappingAlgorithmID::KW_AES_128,
+    key_len: 16,
+    aead: None,
+};
+
+/// AES-256 key wrap with padding, as specified in [RFC 5649].
+///
+/// [RFC 5649]: https://tools.ietf.org/html/rfc5649
+pub static KW_AES_256: WrappingAlgorithm = WrappingAlgorithm {
+    id: WrappingAlgorithmID::KW_AES_256,
+    key_len: 32,
+    aead: None,
+};
+
+/// Sealing with `aead::AES_256_GCM` under a random nonce.
+///
+/// Random nonces are only safe for up to 2**32 keys wrapped under the same
+/// KEK.
+pub static KW_AES_256_GCM: WrappingAlgorithm = WrappingAlgorithm {
+    id: WrappingAlgorithmID::KW_AES_256_GCM,
+    key_len: 32,
+    aead: Some(&aead::AES_256_GCM),
+};
+
+/// Sealing with `aead::CHACHA20_POLY1305` under a random nonce.
+///
+/// Random nonces are only safe for up to 2**32 keys wrapped under the same
+/// KEK.
+pub static KW_CHACHA20_POLY1305: WrappingAlgorithm = WrappingAlgorithm {
+    id: WrappingAlgorithmID::KW_CHACHA20_POLY1305,
+    key_len: 32,
+    aead: Some(&aead::CHACHA20_POLY1305),
+};
+
+/// A key encryption key, for wrapping and unwrapping keys.
+pub struct Kek {
+    algorithm: &'static WrappingAlgorithm,
+    key: KekKey,
+}
+
+enum KekKey {
+    Aes(aes_cbc::Key),
+    Aead(aead::SealingKey, aead::OpeningKey),
+}
+
+derive_debug_via_self!(Kek, self.algorithm);
+
+impl Kek {
+    /// Constructs a KEK for `algorithm`.
+    ///
+    /// `key_bytes` must be exactly `algorithm.key_len()` bytes long.
+    pub fn new(
+        algorithm: &'static WrappingAlgorithm, key_bytes: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        if key_bytes.len() != algorithm.key_len {
+            return Err(error::Unspecified);
+        }
+        let key = match algorithm.aead {
+            None => KekKey::Aes(aes_cbc::Key::new(key_bytes)?),
+            Some(aead_alg) => KekKey::Aead(
+                aead::SealingKey::new(aead_alg, key_bytes)?,
+                aead::OpeningKey::new(aead_alg, key_bytes)?,
+            ),
+        };
+        Ok(Kek { algorithm, key })
+    }
+
+    /// The KEK's wrapping algorithm.
+    #[inline]
+    pub fn algorithm(&self) -> &'static WrappingAlgorithm { self.algorithm }
+}
+
+/// Whether the key material of a `Handle` can be exported unwrapped, like
+/// the `extractable` flag of keys in the Web Cryptography API.
+///
+/// Making keys non-extractable ensures that they are only ever stored
+/// wrapped, regardless of what the code that uses them does.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Extractable {
+    /// The key material can only be exported with `Handle::wrap()`.
+    No,
+
+    /// The key material can also be exported with `Handle::export()`.
+    Yes,
+}
+
+const WRAPPED_KEY_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = 4;
+
+/// A key whose key material can only be exported wrapped under a `Kek`,
+/// unless it is extractable.
+pub struct Handle {
+    key_type: &'static KeyType,
+    extractable: Extractable,
+    material: LockedSlice<u8>,
+    key: HandleKey,
+}
+
+enum HandleKey {
+    Ed25519(signature::Ed25519KeyPair),
+    Ecdsa(signature::ECDSAKeyPair),
+    Aead(aead::SealingKey, aead::OpeningKey),
+    Hmac(hmac::SigningKey),
+}
+
+derive_debug_via_self!(Handle, self.key_type);
+
+impl Handle {
+    /// Generates a new key of type `key_type`.
+    ///
+    /// AEAD keys are `key_len()` random bytes long and HMAC keys are
+    /// `hmac::recommended_key_len()` random bytes long.
+    pub fn generate(
+        key_type: &'static KeyType, extractable: Extractable, rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        let generated = match key_type.kind {
+            KeyKind::Ed25519 => {
+                let mut pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(rng)?;
+                let handle = Self::import(key_type, extractable, untrusted::Input::from(&pkcs8));
+                zeroize::zeroize(&mut pkcs8);
+                return Ok(handle?);
+            },
+            KeyKind::Ecdsa(alg) => {
+                let pkcs8 = signature::ECDSAKeyPair::generate_pkcs8(alg, rng)?;
+                pkcs8.as_ref().to_vec()
+            },
+            KeyKind::Aead(alg) => random_bytes(alg.key_len(), rng)?,
+            KeyKind::Hmac(digest_alg) => {
+                random_bytes(hmac::recommended_key_len(digest_alg), rng)?
+            },
+        };
+        Ok(Self::from_vec(key_type, extractable, generated)?)
+    }
+
+    /// Constructs a handle from unwrapped key material of type `key_type`.
+    ///
+    /// Signing keys must be PKCS#8 documents like the ones that the
+    /// corresponding `from_pkcs8()` functions accept, except that Ed25519 keys
+    /// may be in PKCS#8 v1 format, without the public key. AEAD keys must be
+    /// exactly `key_len()` bytes long and HMAC keys must not be empty.
+    pub fn import(
+        key_type: &'static KeyType, extractable: Extractable, material: untrusted::Input,
+    ) -> Result<Self, error::KeyRejected> {
+        Self::from_vec(key_type, extractable, material.as_slice_less_safe().to_vec())
+    }
+
+    fn from_vec(
+        key_type: &'static KeyType, extractable: Extractable, material: Vec<u8>,
+    ) -> Result<Self, error::KeyRejected> {
+        let mut material = LockedSlice::from(material.into_boxed_slice());
+        let key = match HandleKey::new(key_type, &material) {
+            Ok(key) => key,
+            Err(e) => {
+                zeroize::zeroize(&mut material);
+                return Err(e);
+            },
+        };
+        material.lock();
+        Ok(Handle {
+            key_type,
+            extractable,
+            material,
+            key,
+        })
+    }
+
+    /// Unwraps `wrapped`, which was returned by `wrap()` with a KEK with the
+    /// same algorithm and key.
+    pub fn unwrap(kek: &Kek, wrapped: &[u8]) -> Result<Self, error::KeyRejected> {
+        if wrapped.len() < HEADER_LEN {
+            return Err(error::KeyRejected::invalid_encoding());
+        }
+        let (header, body) = wrapped.split_at(HEADER_LEN);
+        if header[0] != WRAPPED_KEY_VERSION {
+            return Err(error::KeyRejected::version_not_supported());
+        }
+        if header[1] != kek.algorithm.id as u8 {
+            return Err(error::KeyRejected::wrong_algorithm());
+        }
+        let key_type = KEY_TYPES
+            .iter()
+            .find(|key_type| key_type.id as u8 == header[2])
+            .ok_or_else(error::KeyRejected::wrong_algorithm)?;
+        let extractable = match header[3] {
+            0 => Extractable::No,
+            1 => Extractable::Yes,
+            _ => {
+                return Err(error::KeyRejected::invalid_encoding());
+            },
+        };
+
+        let material = match &kek.key {
+            KekKey::Aes(key) => {
+                let mut unwrapped = aes_kw::unwrap_padded(key, body)
+                    .map_err(|error::Unspecified| error::KeyRejected::decryption_failed())?;
+                if unwrapped.len() < HEADER_LEN || unwrapped[..HEADER_LEN] != *header {
+                    zeroize::zeroize(&mut unwrapped);
+                    return Err(error::KeyRejected::decryption_failed());
+                }
+                let material = unwrapped[HEADER_LEN..].to_vec();
+                zeroize::zeroize(&mut unwrapped);
+                material
+            },
+            KekKey::Aead(_, opening_key) => {
+                let nonce_len = opening_key.algorithm().nonce_len();
+                if body.len() < nonce_len {
+                    return Err(error::KeyRejected::invalid_encoding());
+                }
+                let (nonce, ciphertext) = body.split_at(nonce_len);
+                let mut in_out = ciphertext.to_vec();
+                let material = aead::open_in_place(opening_key, nonce, header, 0, &mut in_out)
+                    .map(|plaintext| plaintext.to_vec());
+                zeroize::zeroize(&mut in_out);
+                material.map_err(|error::Unspecified| error::KeyRejected::decryption_failed())?
+            },
+        };
+        Self::from_vec(key_type, extractable, material)
+    }
+
+    /// Wraps the key under `kek`.
+    ///
+    /// `rng` is used to generate the nonce for AEAD-based wrapping algorithms.
+    pub fn wrap(&self, kek: &Kek, rng: &rand::SecureRandom) -> Result<Vec<u8>, error::Unspecified> {
+        let header = [
+            WRAPPED_KEY_VERSION,
+            kek.algorithm.id as u8,
+            self.key_type.id as u8,
+            match self.extractable {
+                Extractable::No => 0,
+                Extractable::Yes => 1,
+            },
+        ];
+        let mut wrapped = header.to_vec();
+        match &kek.key {
+            KekKey::Aes(key) => {
+                let mut plaintext = header.to_vec();
+                plaintext.extend_from_slice(&self.material);
+                let body = aes_kw::wrap_padded(key, &plaintext);
+                zeroize::zeroize(&mut plaintext);
+                wrapped.extend_from_slice(&body?);
+            },
+            KekKey::Aead(sealing_key, _) => {
+                let tag_len = sealing_key.algorithm().tag_len();
+                let nonce = random_bytes(sealing_key.algorithm().nonce_len(), rng)?;
+                wrapped.extend_from_slice(&nonce);
+                let mut in_out = self.material.to_vec();
+                in_out.resize(self.material.len() + tag_len, 0);
+                let sealed =
+                    aead::seal_in_place(sealing_key, &nonce, &header, &mut in_out, tag_len);
+                if sealed.is_err() {
+                    zeroize::zeroize(&mut in_out);
+                }
+                wrapped.extend_from_slice(&in_out[..sealed?]);
+            },
+        }
+        Ok(wrapped)
+    }
+
+    /// The type of the key.
+    #[inline]
+    pub fn key_type(&self) -> &'static KeyType { self.key_type }
+
+    /// Whether the key material can be exported unwrapped.
+    #[inline]
+    pub fn extractable(&self) -> Extractable { self.extractable }
+
+    /// Returns the key material, in the format that `import()` accepts.
+    ///
+    /// Fails unless the handle was constructed with `Extractable::Yes`.
+    pub fn export(&self) -> Result<&[u8], error::Unspecified> {
+        match self.extractable {
+            Extractable::Yes => Ok(&self.material),
+            Extractable::No => Err(error::Unspecified),
+        }
+    }
+
+    /// The public key of a signing key.
+    pub fn public_key(&self) -> Result<spki::PublicKey, error::Unspecified> {
+        match &self.key {
+            HandleKey::Ed25519(key_pair) => Ok(key_pair.public_key()),
+            HandleKey::Ecdsa(key_pair) => Ok(key_pair.public_key()),
+            HandleKey::Aead(..) | HandleKey::Hmac(_) => Err(error::Unspecified),
+        }
+    }
+
+    /// Signs `msg` with a signing key. `rng` is only used for ECDSA.
+    pub fn sign(
+        &self, rng: &rand::SecureRandom, msg: &[u8],
+    ) -> Result<signature::Signature, error::Unspecified> {
+        match &self.key {
+            HandleKey::Ed25519(key_pair) => Ok(key_pair.sign(msg)),
+            HandleKey::Ecdsa(key_pair) => key_pair.sign(untrusted::Input::from(msg), rng),
+            HandleKey::Aead(..) | HandleKey::Hmac(_) => Err(error::Unspecified),
+        }
+    }
+
+    /// Like `aead::seal_in_place()`, with an AEAD key.
+    pub fn seal_in_place(
+        &self, nonce: &[u8], ad: &[u8], in_out: &mut [u8], out_suffix_capacity: usize,
+    ) -> Result<usize, error::Unspecified> {
+        match &self.key {
+            HandleKey::Aead(sealing_key, _) => {
+                aead::seal_in_place(sealing_key, nonce, ad, in_out, out_suffix_capacity)
+            },
+            _ => Err(error::Unspecified),
+        }
+    }
+
+    /// Like `aead::open_in_place()`, with an AEAD key.
+    pub fn open_in_place<'a>(
+        &self, nonce: &[u8], ad: &[u8], in_prefix_len: usize,
+        ciphertext_and_tag_modified_in_place: &'a mut [u8],
+    ) -> Result<&'a mut [u8], error::Unspecified> {
+        match &self.key {
+            HandleKey::Aead(_, opening_key) => aead::open_in_place(
+                opening_key,
+                nonce,
+                ad,
+                in_prefix_len,
+                ciphertext_and_tag_modified_in_place,
+            ),
+            _ => Err(error::Unspecified),
+        }
+    }
+
+    /// Like `hmac::sign()`, with an HMAC key.
+    pub fn hmac_sign(&self, data: &[u8]) -> Result<hmac::Signature, error::Unspecified> {
+        match &self.key {
+            HandleKey::Hmac(key) => Ok(hmac::sign(key, data)),
+            _ => Err(error::Unspecified),
+        }
+    }
+
+    /// Like `hmac::verify_with_own_key()`, with an HMAC key.
+    pub fn hmac_verify(&self, data: &[u8], signature: &[u8]) -> Result<(), error::Unspecified> {
+        match &self.key {
+            HandleKey::Hmac(key) => hmac::verify_with_own_key(key, data, signature),
+            _ => Err(error::Unspecified),
+        }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.material) }
+}
+
+impl HandleKey {
+    fn new(key_type: &'static KeyType, material: &[u8]) -> Result<Self, error::KeyRejected> {
+        let input = untrusted::Input::from(material);
+        match key_type.kind {
+            KeyKind::Ed25519 => {
+                signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(input).map(HandleKey::Ed25519)
+            },
+            KeyKind::Ecdsa(alg) => {
+                signature::ECDSAKeyPair::from_pkcs8(alg, input).map(HandleKey::Ecdsa)
+            },
+            KeyKind::Aead(alg) => {
+                if material.len() < alg.key_len() {
+                    return Err(error::KeyRejected::too_small());
+                }
+                if material.len() > alg.key_len() {
+                    return Err(error::KeyRejected::too_large());
+                }
+                let sealing_key = aead::SealingKey::new(alg, material)
+                    .map_err(|error::Unspecified| error::KeyRejected::unexpected_error())?;
+                let opening_key = aead::OpeningKey::new(alg, material)
+                    .map_err(|error::Unspecified| error::KeyRejected::unexpected_error())?;
+                Ok(HandleKey::Aead(sealing_key, opening_key))
+            },
+            KeyKind::Hmac(digest_alg) => {
+                if material.is_empty() {
+                    return Err(error::KeyRejected::too_small());
+                }
+                Ok(HandleKey::Hmac(hmac::SigningKey::new(digest_alg, material)))
+            },
+        }
+    }
+}
+
+fn random_bytes(len: usize, rng: &rand::SecureRandom) -> Result<Vec<u8>, error::Unspecified> {
+    let mut bytes = vec![0u8; len];
+    rng.fill(&mut bytes)?;
+    Ok(bytes)
+}
@@ -17,11 +17,37 @@
 //!
 //! <code>git clone https://github.com/briansmith/ring</code>
 //!
+//! # Thread Safety
+//!
+//! Keys and key pairs, e.g. `aead::SealingKey`, `aead::OpeningKey`,
+//! `hmac::SigningKey`, `signature::Ed25519KeyPair`, and
+//! `signature::ECDSAKeyPair`, are `Send` and `Sync`: they are immutable once
+//! constructed, so a single key may be shared across threads, e.g. in an
+//! `Arc`. The multi-step contexts, e.g. `digest::Context` and
+//! `hmac::SigningContext`, are `Send` and `Sync` too, and may be moved to
+//! another thread between steps. The random number generators that keep
+//! mutable state without locking, `rand::HmacDrbg`, `rand::CtrDrbg`,
+//! `rand::SystemDrbg`, and `rand::JitterRandom`, are `Send` but not `Sync`;
+//! `rand::SystemRandom` is both. These guarantees are checked by the tests,
+//! and by the `compile_fail` examples in the DRBGs' documentation.
+//!
 //! # Feature Flags
 //!
 //! <table>
 //! <tr><th>Feature
 //!     <th>Description
+//! <tr><td><code>alloc</code>
+//!     <td>Enable the features that need a heap, using the <code>alloc</code>
+//!         crate, without requiring the rest of <code>std</code>, for targets
+//!         that have an allocator but no operating system, e.g. kernels and
+//!         WASM runtimes. Without <code>std</code>,
+//!         <code>test::from_file()</code> is replaced by
+//!         <code>test::from_str()</code>.
+//! <tr><td><code>capi</code>
+//!     <td>Enable <code>ring::capi</code>, a C API for digests, HMAC,
+//!         AEADs, and Ed25519 that is declared in <code>include/ring.h</code>,
+//!         for building <i>ring</i> as a C library. This implies
+//!         <code>alloc</code>.
 //! <tr><td><code>dev_urandom_fallback (default)</code>
 //!     <td>This is only applicable to Linux. On Linux, by default,
 //!         <code>ring::rand::SystemRandom</code> will fall back to reading
@@ -30,8 +56,100 @@
 //!         <code>dev_urandom_fallback</code> feature is disabled, such
 //!         fallbacks will not occur. See the documentation for
 //!         <code>rand::SystemRandom</code> for more details.
+//! <tr><td><code>fuzzing</code>
+//!     <td>Enable <code>ring::fuzz</code>, entry points for fuzzing
+//!         <i>ring</i>'s parsers with arbitrary bytes.
+//! <tr><td><code>getrandom</code>
+//!     <td>Implement <code>rand::SystemRandom</code> with the
+//!         <code>getrandom</code> crate instead of <i>ring</i>'s own code
+//!         for each operating system, for targets that <i>ring</i> doesn't
+//!         support directly. See the documentation for
+//!         <code>rand::SystemRandom</code> for more details.
+//! <tr><td><code>large_ec_tables</code>
+//!     <td>Use a 118KB table of precomputed multiples of the P-384
+//!         generator, which makes P-384 ECDSA signing and ECDH key generation
+//!         about three times faster, for servers and other applications where
+//!         code size matters less. Without it, the multiples of the generator
+//!         are computed like the multiples of any other point. P-256 always
+//!         uses its 150KB table.
+//! <tr><td><code>locked_memory</code>
+//!     <td>Keep long-lived private keys (<code>ECDSAKeyPair</code>,
+//!         <code>RSAKeyPair</code>, and <code>hmac::SigningKey</code>, which
+//!         is also the type of HKDF PRKs) in locked, guard-paged memory so
+//!         that they aren't written to swap, on Linux and Windows. Keys are
+//!         kept in ordinary memory when locking fails, e.g. because
+//!         <code>RLIMIT_MEMLOCK</code> is exhausted. This implies
+//!         <code>alloc</code>.
+//! <tr><td><code>parallel</code>
+//!     <td>Enable <code>pbkdf2::verify_batch()</code> and compute the
+//!         <code>p</code> independent lanes of scrypt concurrently, using
+//!         <code>std::thread</code>. This implies <code>use_heap</code>.
+//! <tr><td><code>std</code>
+//!     <td>Implement <code>std::error::Error</code> for the error types in
+//!         <code>ring::error</code>, read test vector files with
+//!         <code>test::from_file()</code>, and read
+//!         <code>RING_DISABLE_CPU_FEATURES</code>. This is implied by
+//!         <code>use_heap</code>.
+//! <tr><td><code>rsa_max_bits_2048</code>, <code>rsa_max_bits_3072</code>,
+//!         <code>rsa_max_bits_4096</code>
+//!     <td>Reject RSA keys with a modulus larger than 2048, 3072, or 4096
+//!         bits, instead of 8192 bits, so that the buffers for RSA
+//!         operations, many of which are on the stack, are smaller, e.g. for
+//!         microcontrollers. If more than one is enabled, the smallest limit
+//!         applies.
 //! <tr><td><code>rsa_signing</code>
-//!     <td>Enable RSA signing (<code>RSAKeyPair</code> and related things).
+//!     <td>Enable RSA signing (<code>RSAKeyPair</code> and related things)
+//!         and the blind RSA tokens of <code>ring::privacypass</code>. This
+//!         implies <code>alloc</code>.
+//! <tr><td><code>rustcrypto</code>
+//!     <td>Enable <code>ring::rustcrypto</code>, implementations of the
+//!         traits of the RustCrypto project's <code>digest</code>,
+//!         <code>signature</code>, and <code>aead</code> crates, which are
+//!         renamed <code>rustcrypto_digest</code>,
+//!         <code>rustcrypto_signature</code>, and
+//!         <code>rustcrypto_aead</code>.
+//! <tr><td><code>serde</code>
+//!     <td>Implement <code>serde::Serialize</code> and
+//!         <code>serde::Deserialize</code> for the types that aren't secret:
+//!         <code>signature::Signature</code>, <code>spki::PublicKey</code>,
+//!         <code>digest::Digest</code>, and <code>digest::Algorithm</code>
+//!         and <code>aead::Algorithm</code>, by name. Byte strings are hex
+//!         digits in human-readable formats.
+//! <tr><td><code>srp</code>
+//!     <td>Enable <code>ring::srp</code>, SRP-6a password-authenticated key
+//!         exchange. This implies <code>rsa_signing</code>, whose
+//!         constant-time modular exponentiation it uses.
+//! <tr><td><code>test_util</code>
+//!     <td>Enable <code>rand::test_util</code>, deterministic (insecure)
+//!         implementations of <code>rand::SecureRandom</code> for
+//!         known-answer tests.
+//! <tr><td><code>unstable_bigint</code>
+//!     <td>Enable <code>ring::arithmetic::bigint</code>, modular arithmetic
+//!         on large integers for protocols that *ring* doesn't implement.
+//!         This implies <code>rsa_signing</code>. Its API isn't stable and
+//!         may change in any release.
+//! <tr><td><code>unstable_ec</code>
+//!     <td>Enable <code>ring::ec</code>, arithmetic on elliptic curve points
+//!         and scalars for protocols that *ring* doesn't implement. Its API
+//!         isn't stable and may change in any release.
+//! <tr><td><code>use_heap (default)</code>
+//!     <td>Both <code>alloc</code> and <code>std</code>.
+//! <tr><td><code>wasm32_unknown_unknown_js</code>
+//!     <td>On wasm32-unknown-unknown, implement
+//!         <code>rand::SystemRandom</code> with the Web Crypto API's
+//!         <code>crypto.getRandomValues()</code>, using
+//!         <code>web-sys</code>. Without it, <code>SystemRandom</code> only
+//!         works on that target with a registered entropy source. This has
+//!         no effect on other targets.
+//! <tr><td><code>zeroize</code>
+//!     <td>Implement the <code>zeroize</code> crate's
+//!         <code>ZeroizeOnDrop</code> for the types that already overwrite
+//!         their secrets when they are dropped, e.g. the keys and key pairs,
+//!         and <code>Zeroize</code> for <code>pkcs8::Document</code> and
+//!         <code>pake::SharedKey</code>. With <code>alloc</code>, enable the
+//!         constructors that take a key as a <code>Zeroizing&lt;Vec&lt;u8&gt;&gt;</code>,
+//!         e.g. <code>hmac::SigningKey::from_zeroizing()</code>, so that
+//!         the caller's copy is overwritten too.
 //! </table>
 
 #![doc(html_root_url = "https://briansmith.org/rustdoc/")]
@@ -59,22 +177,29 @@
     unused_results,
     warnings
 )]
+// `std` is only needed for `std::error::Error`, for the test vector files,
+// and for reading `/dev/urandom`. Keep the `cfg` conditions in sync with the
+// conditions in rand.rs.
 #![cfg_attr(
-    any(
-        target_os = "redox",
+    not(any(
+        test,
+        feature = "std",
         all(
-            not(test),
-            not(feature = "use_heap"),
-            unix,
+            not(feature = "getrandom"),
+            any(target_os = "redox", unix),
             not(any(target_os = "macos", target_os = "ios")),
-            any(not(target_os = "linux"), feature = "dev_urandom_fallback")
+            not(all(target_os = "linux", not(feature = "dev_urandom_fallback")))
         )
-    ),
+    )),
     no_std
 )]
 #![cfg_attr(feature = "internal_benches", allow(unstable_features))]
 #![cfg_attr(feature = "internal_benches", feature(test))]
 
+#[cfg(any(test, feature = "alloc"))]
+#[macro_use]
+extern crate alloc;
+
 #[macro_use]
 mod debug;
 
@@ -84,39 +209,154 @@ mod bssl;
 #[macro_use]
 mod polyfill;
 
+#[cfg(feature = "unstable_bigint")]
+pub mod arithmetic;
+
+#[cfg(not(feature = "unstable_bigint"))]
 mod arithmetic;
 
 pub mod aead;
+mod aes;
+
+#[cfg(feature = "alloc")]
+mod aes_cbc;
+
+#[cfg(feature = "alloc")]
+mod aes_kw;
+
+#[cfg(feature = "alloc")]
+pub mod age;
+
 pub mod agreement;
 
-#[cfg(any(test, feature = "use_heap"))]
+#[cfg(any(test, feature = "alloc"))]
 mod bits;
 
+#[cfg(feature = "alloc")]
+mod blake2b;
+
+#[cfg(feature = "alloc")]
+mod blake2s;
+
 mod c;
-pub mod constant_time;
 
-#[doc(hidden)]
-pub mod der;
+#[cfg(feature = "capi")]
+pub mod capi;
 
-mod cpu;
+pub mod constant_time;
+pub mod cose;
+pub mod cpu;
 pub mod digest;
+
+#[cfg(feature = "alloc")]
+pub mod dnssec;
+
+#[cfg(feature = "unstable_ec")]
+pub mod ec;
+
+#[cfg(not(feature = "unstable_ec"))]
 mod ec;
+
 mod endian;
 pub mod error;
+
+#[cfg(feature = "alloc")]
+pub mod fernet;
+
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
+
 pub mod hkdf;
 pub mod hmac;
+mod init;
+pub mod io;
+
+#[cfg(feature = "alloc")]
+pub mod jose;
+
+#[cfg(feature = "alloc")]
+pub mod jwk;
+
+#[cfg(feature = "alloc")]
+pub mod keys;
+
 mod limb;
+mod locked;
+
+#[cfg(feature = "alloc")]
+pub mod minisign;
+
+pub mod opaque;
+pub mod oprf;
+pub mod otp;
+pub mod pake;
+
+#[cfg(feature = "parallel")]
+mod parallel;
+
+#[cfg(feature = "alloc")]
+pub mod paseto;
+
+#[cfg(feature = "alloc")]
+pub mod password_box;
+
 pub mod pbkdf2;
-mod pkcs8;
+
+#[cfg(feature = "alloc")]
+pub mod pem;
+
+#[cfg(feature = "alloc")]
+pub mod pkcs12;
+
+pub mod pkcs8;
+
+#[cfg(feature = "alloc")]
+pub mod privacypass;
+
+pub mod quic;
 pub mod rand;
+pub mod ratchet;
+pub mod rotation;
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 mod rsa;
 
+#[cfg(feature = "rustcrypto")]
+pub mod rustcrypto;
+
+#[cfg(feature = "alloc")]
+mod scrypt;
+
+#[cfg(feature = "alloc")]
+pub mod secret_sharing;
+
+#[cfg(feature = "serde")]
+mod serialization;
+
+pub mod sgx;
+
 pub mod signature;
 mod signature_impl;
+pub mod spki;
+
+#[cfg(feature = "srp")]
+pub mod srp;
+
+#[cfg(feature = "alloc")]
+pub mod srtp;
+
+#[cfg(feature = "alloc")]
+pub mod ssh;
 
 pub mod test;
+pub mod tls13;
+
+#[cfg(feature = "alloc")]
+pub mod wireguard;
+
+mod zeroize;
+
+pub use crate::init::{init_checked, Capabilities};
 
 mod private {
     /// Traits that are designed to only be implemented internally in *ring*.
@@ -134,7 +374,8 @@ mod private {
     pub trait Sealed {}
 }
 
-#[cfg(test)]
+// The C tests aren't built for wasm32; see build.rs.
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     bssl_test!(test_constant_time, bssl_constant_time_test_main);
 }
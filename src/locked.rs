@@ -0,0 +1,511 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Memory for long-lived secrets that is locked into RAM.
+//!
+//! When the `locked_memory` feature is enabled, `Locked::new()` and
+//! `LockedSlice::lock()` move values into pages of their own that are
+//! locked (with `mlock()` on Linux and `VirtualLock()` on Windows) so that
+//! they are never written to swap, and that are surrounded by inaccessible
+//! guard pages so that overruns from neighbouring memory fault instead of
+//! reading the secret. On Linux the pages are also excluded from core dumps.
+//!
+//! Locking is best effort. When it fails, e.g. because the process has
+//! exhausted `RLIMIT_MEMLOCK`, or on other platforms, the values are stored
+//! like they are when the feature is disabled: `Locked<T>` inline and
+//! `LockedSlice<T>` in an ordinary heap allocation.
+
+use core::ops::{Deref, DerefMut};
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+#[cfg(feature = "locked_memory")]
+use core::{marker::PhantomData, ptr::NonNull};
+
+/// A value that is stored in locked memory, when possible.
+pub(crate) struct Locked<T>(Storage<T>);
+
+#[cfg(not(feature = "locked_memory"))]
+struct Storage<T>(T);
+
+#[cfg(feature = "locked_memory")]
+enum Storage<T> {
+    Unlocked(T),
+    Locked(Pages, PhantomData<T>),
+}
+
+impl<T> Locked<T> {
+    #[cfg(not(feature = "locked_memory"))]
+    #[inline]
+    pub(crate) fn new(value: T) -> Self { Locked(Storage(value)) }
+
+    #[cfg(feature = "locked_memory")]
+    pub(crate) fn new(value: T) -> Self {
+        let pages = match Pages::new(core::alloc::Layout::new::<T>()) {
+            Some(pages) => pages,
+            None => {
+                return Locked(Storage::Unlocked(value));
+            },
+        };
+        unsafe { pages.data.cast::<T>().as_ptr().write(value) };
+        Locked(Storage::Locked(pages, PhantomData))
+    }
+
+    /// Whether the value is in locked memory.
+    #[cfg(test)]
+    fn is_locked(&self) -> bool {
+        #[cfg(not(feature = "locked_memory"))]
+        return false;
+
+        #[cfg(feature = "locked_memory")]
+        match &self.0 {
+            Storage::Unlocked(_) => false,
+            Storage::Locked(..) => true,
+        }
+    }
+}
+
+impl<T> Deref for Locked<T> {
+    type Target = T;
+
+    #[cfg(not(feature = "locked_memory"))]
+    #[inline]
+    fn deref(&self) -> &T { &(self.0).0 }
+
+    #[cfg(feature = "locked_memory")]
+    #[inline]
+    fn deref(&self) -> &T {
+        match &self.0 {
+            Storage::Unlocked(value) => value,
+            Storage::Locked(pages, _) => unsafe { pages.data.cast::<T>().as_ref() },
+        }
+    }
+}
+
+impl<T> DerefMut for Locked<T> {
+    #[cfg(not(feature = "locked_memory"))]
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T { &mut (self.0).0 }
+
+    #[cfg(feature = "locked_memory")]
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        match &mut self.0 {
+            Storage::Unlocked(value) => value,
+            Storage::Locked(pages, _) => unsafe { pages.data.cast::<T>().as_mut() },
+        }
+    }
+}
+
+impl<T: Clone> Clone for Locked<T> {
+    fn clone(&self) -> Self { Self::new(self.deref().clone()) }
+}
+
+#[cfg(feature = "locked_memory")]
+impl<T> Drop for Storage<T> {
+    fn drop(&mut self) {
+        if let Storage::Locked(pages, _) = self {
+            // `pages` is zeroized and freed afterwards.
+            unsafe { core::ptr::drop_in_place(pages.data.cast::<T>().as_ptr()) };
+        }
+    }
+}
+
+// `Locked<T>` owns its value like `Box<T>` does.
+#[cfg(feature = "locked_memory")]
+unsafe impl<T: Send> Send for Storage<T> {}
+#[cfg(feature = "locked_memory")]
+unsafe impl<T: Sync> Sync for Storage<T> {}
+
+/// A slice that is stored on the heap and that can be moved to locked memory.
+///
+/// Unlike `Locked<T>`, values are only locked when `lock()` is called, so
+/// that temporaries, which are cloned from locked values, aren't locked.
+#[cfg(feature = "alloc")]
+pub(crate) struct LockedSlice<T: Copy>(SliceStorage<T>);
+
+#[cfg(all(feature = "alloc", not(feature = "locked_memory")))]
+struct SliceStorage<T>(Box<[T]>);
+
+#[cfg(feature = "locked_memory")]
+enum SliceStorage<T> {
+    Unlocked(Box<[T]>),
+    Locked { len: usize, pages: Pages, values: PhantomData<T> },
+}
+
+#[cfg(feature = "alloc")]
+impl<T: crate::zeroize::Zeroable> LockedSlice<T> {
+    /// Moves the values into locked memory, if possible.
+    #[cfg(not(feature = "locked_memory"))]
+    #[inline]
+    pub(crate) fn lock(&mut self) {}
+
+    /// Moves the values into locked memory, if possible.
+    #[cfg(feature = "locked_memory")]
+    pub(crate) fn lock(&mut self) {
+        let layout = match core::alloc::Layout::array::<T>(self.len()) {
+            Ok(layout) => layout,
+            Err(_) => {
+                return;
+            },
+        };
+        let pages = match Pages::new(layout) {
+            Some(pages) => pages,
+            None => {
+                return;
+            },
+        };
+        let len = self.len();
+        unsafe { core::ptr::copy_nonoverlapping(self.as_ptr(), pages.data.cast().as_ptr(), len) };
+        crate::zeroize::zeroize(self);
+        self.0 = SliceStorage::Locked {
+            len,
+            pages,
+            values: PhantomData,
+        };
+    }
+
+    /// Whether the values are in locked memory.
+    #[cfg(test)]
+    fn is_locked(&self) -> bool {
+        #[cfg(not(feature = "locked_memory"))]
+        return false;
+
+        #[cfg(feature = "locked_memory")]
+        match &self.0 {
+            SliceStorage::Unlocked(_) => false,
+            SliceStorage::Locked { .. } => true,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Copy> From<Box<[T]>> for LockedSlice<T> {
+    #[cfg(not(feature = "locked_memory"))]
+    #[inline]
+    fn from(values: Box<[T]>) -> Self { LockedSlice(SliceStorage(values)) }
+
+    #[cfg(feature = "locked_memory")]
+    #[inline]
+    fn from(values: Box<[T]>) -> Self { LockedSlice(SliceStorage::Unlocked(values)) }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Copy> Deref for LockedSlice<T> {
+    type Target = [T];
+
+    #[cfg(not(feature = "locked_memory"))]
+    #[inline]
+    fn deref(&self) -> &[T] { &(self.0).0 }
+
+    #[cfg(feature = "locked_memory")]
+    #[inline]
+    fn deref(&self) -> &[T] {
+        match &self.0 {
+            SliceStorage::Unlocked(values) => values,
+            SliceStorage::Locked { len, pages, .. } => unsafe {
+                core::slice::from_raw_parts(pages.data.cast().as_ptr(), *len)
+            },
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Copy> DerefMut for LockedSlice<T> {
+    #[cfg(not(feature = "locked_memory"))]
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] { &mut (self.0).0 }
+
+    #[cfg(feature = "locked_memory")]
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        match &mut self.0 {
+            SliceStorage::Unlocked(values) => values,
+            SliceStorage::Locked { len, pages, .. } => unsafe {
+                core::slice::from_raw_parts_mut(pages.data.cast().as_ptr(), *len)
+            },
+        }
+    }
+}
+
+/// Clones are never locked.
+#[cfg(feature = "alloc")]
+impl<T: Copy> Clone for LockedSlice<T> {
+    fn clone(&self) -> Self {
+        use alloc::borrow::ToOwned;
+        Self::from(self.deref().to_owned().into_boxed_slice())
+    }
+}
+
+#[cfg(feature = "locked_memory")]
+unsafe impl<T: Send> Send for SliceStorage<T> {}
+#[cfg(feature = "locked_memory")]
+unsafe impl<T: Sync> Sync for SliceStorage<T> {}
+
+/// Locked pages, with an inaccessible guard page before and after them.
+#[cfg(feature = "locked_memory")]
+struct Pages {
+    /// The first guard page.
+    base: NonNull<u8>,
+
+    /// The length of the mapping, including the guard pages.
+    mapped_len: usize,
+
+    /// The locked pages.
+    locked: NonNull<u8>,
+    locked_len: usize,
+
+    /// Where the value is stored, at the end of the locked pages so that an
+    /// overrun of the value reaches the second guard page immediately.
+    data: NonNull<u8>,
+}
+
+#[cfg(feature = "locked_memory")]
+impl Pages {
+    fn new(layout: core::alloc::Layout) -> Option<Self> {
+        let page_len = sys::page_len();
+        // The alignment of the value must not exceed that of the pages.
+        if layout.align() > page_len {
+            return None;
+        }
+        let locked_len = round_up(core::cmp::max(layout.size(), 1), page_len)?;
+        let mapped_len = locked_len.checked_add(2 * page_len)?;
+        let base = sys::map(mapped_len)?;
+        let locked = unsafe { NonNull::new_unchecked(base.as_ptr().add(page_len)) };
+        if !sys::lock(locked, locked_len) {
+            sys::unmap(base, mapped_len);
+            return None;
+        }
+        let offset = locked_len - round_up(layout.size(), layout.align())?;
+        let data = unsafe { NonNull::new_unchecked(locked.as_ptr().add(offset)) };
+        Some(Self {
+            base,
+            mapped_len,
+            locked,
+            locked_len,
+            data,
+        })
+    }
+}
+
+#[cfg(feature = "locked_memory")]
+impl Drop for Pages {
+    fn drop(&mut self) {
+        let locked =
+            unsafe { core::slice::from_raw_parts_mut(self.locked.as_ptr(), self.locked_len) };
+        crate::zeroize::zeroize(locked);
+        sys::unlock(self.locked, self.locked_len);
+        sys::unmap(self.base, self.mapped_len);
+    }
+}
+
+#[cfg(feature = "locked_memory")]
+fn round_up(value: usize, multiple: usize) -> Option<usize> {
+    Some(value.checked_add(multiple - 1)? / multiple * multiple)
+}
+
+#[cfg(all(feature = "locked_memory", target_os = "linux"))]
+mod sys {
+    use core::ptr::NonNull;
+    use libc;
+
+    pub fn page_len() -> usize { unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize } }
+
+    /// Maps `len` bytes of inaccessible memory.
+    pub fn map(len: usize) -> Option<NonNull<u8>> {
+        let ptr = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                len,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return None;
+        }
+        NonNull::new(ptr as *mut u8)
+    }
+
+    /// Makes the `len` bytes at `ptr` accessible and locks them.
+    pub fn lock(ptr: NonNull<u8>, len: usize) -> bool {
+        let ptr = ptr.as_ptr() as *mut libc::c_void;
+        if unsafe { libc::mprotect(ptr, len, libc::PROT_READ | libc::PROT_WRITE) } != 0 {
+            return false;
+        }
+        // Core dumps would write the secrets to disk too. This is only
+        // advisory, so failures are ignored.
+        let _ = unsafe { libc::madvise(ptr, len, libc::MADV_DONTDUMP) };
+        unsafe { libc::mlock(ptr, len) == 0 }
+    }
+
+    pub fn unlock(ptr: NonNull<u8>, len: usize) {
+        let _ = unsafe { libc::munlock(ptr.as_ptr() as *const libc::c_void, len) };
+    }
+
+    pub fn unmap(ptr: NonNull<u8>, len: usize) {
+        let _ = unsafe { libc::munmap(ptr.as_ptr() as *mut libc::c_void, len) };
+    }
+}
+
+#[cfg(all(feature = "locked_memory", windows))]
+mod sys {
+    use core::ptr::NonNull;
+
+    type BOOL = i32;
+    type DWORD = u32;
+    type LPVOID = *mut u8;
+    type SIZE_T = usize;
+
+    #[repr(C)]
+    struct SYSTEM_INFO {
+        wProcessorArchitecture: u16,
+        wReserved: u16,
+        dwPageSize: DWORD,
+        lpMinimumApplicationAddress: LPVOID,
+        lpMaximumApplicationAddress: LPVOID,
+        dwActiveProcessorMask: usize,
+        dwNumberOfProcessors: DWORD,
+        dwProcessorType: DWORD,
+        dwAllocationGranularity: DWORD,
+        wProcessorLevel: u16,
+        wProcessorRevision: u16,
+    }
+
+    const MEM_COMMIT: DWORD = 0x1000;
+    const MEM_RESERVE: DWORD = 0x2000;
+    const MEM_RELEASE: DWORD = 0x8000;
+    const PAGE_NOACCESS: DWORD = 0x01;
+    const PAGE_READWRITE: DWORD = 0x04;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemInfo(lpSystemInfo: *mut SYSTEM_INFO);
+        fn VirtualAlloc(
+            lpAddress: LPVOID, dwSize: SIZE_T, flAllocationType: DWORD, flProtect: DWORD,
+        ) -> LPVOID;
+        fn VirtualFree(lpAddress: LPVOID, dwSize: SIZE_T, dwFreeType: DWORD) -> BOOL;
+        fn VirtualLock(lpAddress: LPVOID, dwSize: SIZE_T) -> BOOL;
+        fn VirtualProtect(
+            lpAddress: LPVOID, dwSize: SIZE_T, flNewProtect: DWORD, lpflOldProtect: *mut DWORD,
+        ) -> BOOL;
+        fn VirtualUnlock(lpAddress: LPVOID, dwSize: SIZE_T) -> BOOL;
+    }
+
+    pub fn page_len() -> usize {
+        let mut info = core::mem::MaybeUninit::<SYSTEM_INFO>::uninit();
+        unsafe {
+            GetSystemInfo(info.as_mut_ptr());
+            info.assume_init().dwPageSize as usize
+        }
+    }
+
+    /// Maps `len` bytes of inaccessible memory.
+    pub fn map(len: usize) -> Option<NonNull<u8>> {
+        NonNull::new(unsafe {
+            VirtualAlloc(core::ptr::null_mut(), len, MEM_RESERVE | MEM_COMMIT, PAGE_NOACCESS)
+        })
+    }
+
+    /// Makes the `len` bytes at `ptr` accessible and locks them.
+    pub fn lock(ptr: NonNull<u8>, len: usize) -> bool {
+        let mut old_protect = 0;
+        if unsafe { VirtualProtect(ptr.as_ptr(), len, PAGE_READWRITE, &mut old_protect) } == 0 {
+            return false;
+        }
+        unsafe { VirtualLock(ptr.as_ptr(), len) != 0 }
+    }
+
+    pub fn unlock(ptr: NonNull<u8>, len: usize) {
+        let _ = unsafe { VirtualUnlock(ptr.as_ptr(), len) };
+    }
+
+    pub fn unmap(ptr: NonNull<u8>, _len: usize) {
+        let _ = unsafe { VirtualFree(ptr.as_ptr(), 0, MEM_RELEASE) };
+    }
+}
+
+// Memory isn't locked on other platforms.
+#[cfg(all(feature = "locked_memory", not(any(target_os = "linux", windows))))]
+mod sys {
+    use core::ptr::NonNull;
+
+    pub fn page_len() -> usize { 4096 }
+
+    pub fn map(_len: usize) -> Option<NonNull<u8>> { None }
+
+    pub fn lock(_ptr: NonNull<u8>, _len: usize) -> bool { false }
+
+    pub fn unlock(_ptr: NonNull<u8>, _len: usize) {}
+
+    pub fn unmap(_ptr: NonNull<u8>, _len: usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locked() {
+        let mut value = Locked::new([1u64; 3]);
+        assert_eq!(*value, [1; 3]);
+        value[1] = 2;
+        assert_eq!(*value.clone(), [1, 2, 1]);
+        // Locking may fail, e.g. because of `RLIMIT_MEMLOCK`.
+        assert!(cfg!(feature = "locked_memory") || !value.is_locked());
+
+        // Values that need to be dropped are dropped.
+        let rc = std::rc::Rc::new(());
+        let locked = Locked::new(rc.clone());
+        assert_eq!(std::rc::Rc::strong_count(&rc), 2);
+        drop(locked);
+        assert_eq!(std::rc::Rc::strong_count(&rc), 1);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_locked_slice() {
+        for len in &[0, 1, 4096, 4097] {
+            let values = LockedSlice::from(vec![7u8; *len].into_boxed_slice());
+            assert!(!values.is_locked());
+            let mut locked = values.clone();
+            locked.lock();
+            assert_eq!(&locked[..], &values[..]);
+            if *len > 0 {
+                locked[len - 1] = 8;
+                assert_eq!(locked[len - 1], 8);
+            }
+            assert!(cfg!(feature = "locked_memory") || !locked.is_locked());
+            assert!(!locked.clone().is_locked());
+        }
+    }
+
+    #[cfg(feature = "locked_memory")]
+    #[test]
+    fn test_pages_alignment() {
+        for &(size, align) in &[(1, 1), (3, 1), (24, 8), (4096, 16), (5000, 64)] {
+            let layout = core::alloc::Layout::from_size_align(size, align).unwrap();
+            if let Some(pages) = Pages::new(layout) {
+                let data = pages.data.as_ptr() as usize;
+                let end = pages.locked.as_ptr() as usize + pages.locked_len;
+                assert_eq!(data % align, 0);
+                assert!(data + size <= end);
+                assert!(end - (data + size) < align);
+            }
+        }
+    }
+}
@@ -0,0 +1,387 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! [minisign] and [signify] signature files.
+//!
+//! Both tools sign with Ed25519 and share the same public key encoding: an
+//! untrusted comment line followed by the Base64 encoding of the algorithm
+//! identifier `Ed`, an 8-byte key ID, and the 32-byte public key.
+//!
+//! A signify signature file is an untrusted comment line followed by the
+//! Base64 encoding of `Ed`, the key ID, and the Ed25519 signature of the
+//! message.
+//!
+//! A minisign signature file additionally has a trusted comment line and a
+//! "global" signature of the message signature concatenated with the trusted
+//! comment, so that the trusted comment cannot be modified without detection.
+//! `sign()` produces pre-hashed (`ED`) minisign signatures, where the message
+//! is hashed with BLAKE2b-512 before signing, which is what current versions of
+//! minisign produce by default. `verify()` accepts both pre-hashed and legacy
+//! (`Ed`) minisign signatures.
+//!
+//! Secret key files are not supported; those are (differently) encrypted by
+//! each tool. Use `Ed25519KeyPair` to hold the signing key instead.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{minisign, rand, signature::Ed25519KeyPair};
+//!
+//! # fn sign_and_verify() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let pkcs8_bytes = Ed25519KeyPair::generate_pkcs8(&rng)?;
+//! let key_pair = Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&pkcs8_bytes))?;
+//! let mut key_id = [0u8; minisign::KEY_ID_LEN];
+//! rand::SecureRandom::fill(&rng, &mut key_id)?;
+//!
+//! let public_key = minisign::PublicKey::from_key_pair(key_id, &key_pair);
+//! let public_key_file = public_key.to_file("minisign public key")?;
+//!
+//! let signature_file = minisign::sign(
+//!     &key_pair,
+//!     key_id,
+//!     b"release.tar.gz contents",
+//!     "signature from ring",
+//!     "timestamp:1555555555\tfile:release.tar.gz",
+//! )?;
+//!
+//! let public_key = minisign::PublicKey::from_file(&public_key_file)?;
+//! let signature = minisign::SignatureFile::parse(&signature_file)?;
+//! public_key.verify(b"release.tar.gz contents", &signature)?;
+//! assert_eq!(
+//!     signature.trusted_comment(),
+//!     Some("timestamp:1555555555\tfile:release.tar.gz")
+//! );
+//! # Ok(())
+//! # }
+//! # fn main() { sign_and_verify().unwrap() }
+//! ```
+//!
+//! [minisign]: https://jedisct1.github.io/minisign/
+//! [signify]: https://man.openbsd.org/signify
+
+use crate::{blake2b, error, io::base64, signature};
+use alloc::{string::String, vec::Vec};
+use untrusted;
+
+/// The length of a minisign/signify key ID.
+pub const KEY_ID_LEN: usize = 8;
+
+/// A minisign/signify key ID.
+pub type KeyId = [u8; KEY_ID_LEN];
+
+const PUBLIC_KEY_LEN: usize = signature::ED25519_PUBLIC_KEY_LEN;
+const SIGNATURE_LEN: usize = 64;
+
+const ALG_ED25519: [u8; 2] = *b"Ed";
+const ALG_ED25519_PREHASHED: [u8; 2] = *b"ED";
+
+const UNTRUSTED_COMMENT_PREFIX: &str = "untrusted comment: ";
+const TRUSTED_COMMENT_PREFIX: &str = "trusted comment: ";
+
+/// A minisign/signify Ed25519 public key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PublicKey {
+    key_id: KeyId,
+    public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+impl PublicKey {
+    /// Constructs a public key from its key ID and raw Ed25519 public key.
+    pub fn new(key_id: KeyId, public_key: &[u8]) -> Result<Self, error::Unspecified> {
+        if public_key.len() != PUBLIC_KEY_LEN {
+            return Err(error::Unspecified);
+        }
+        let mut r = Self {
+            key_id,
+            public_key: [0; PUBLIC_KEY_LEN],
+        };
+        r.public_key.copy_from_slice(public_key);
+        Ok(r)
+    }
+
+    /// Like `new()`, but usable in `const` and `static` initializers, e.g. to
+    /// embed the public key that verifies firmware updates.
+    #[inline]
+    pub const fn from_bytes(key_id: KeyId, public_key: [u8; PUBLIC_KEY_LEN]) -> Self {
+        Self { key_id, public_key }
+    }
+
+    /// Returns the public key of `key_pair`, identified by `key_id`.
+    pub fn from_key_pair(key_id: KeyId, key_pair: &signature::Ed25519KeyPair) -> Self {
+        let mut public_key = [0; PUBLIC_KEY_LEN];
+        public_key.copy_from_slice(key_pair.public_key_bytes());
+        Self { key_id, public_key }
+    }
+
+    /// Parses the contents of a minisign or signify public key file.
+    pub fn from_file(contents: &str) -> Result<Self, error::Unspecified> {
+        let mut lines = Lines::new(contents);
+        let _ = lines.untrusted_comment()?;
+        let key = lines.next()?;
+        lines.finish()?;
+        Self::from_base64(key)
+    }
+
+    /// Parses the Base64-encoded public key line, as found on the second line
+    /// of a public key file or passed to `minisign -P`.
+    pub fn from_base64(encoded: &str) -> Result<Self, error::Unspecified> {
+        let bytes = base64::decode(encoded, &base64::STANDARD)?;
+        if bytes.len() != 2 + KEY_ID_LEN + PUBLIC_KEY_LEN || bytes[..2] != ALG_ED25519 {
+            return Err(error::Unspecified);
+        }
+        let (key_id, public_key) = bytes[2..].split_at(KEY_ID_LEN);
+        let mut r = Self::new([0; KEY_ID_LEN], public_key)?;
+        r.key_id.copy_from_slice(key_id);
+        Ok(r)
+    }
+
+    /// Returns the Base64-encoded public key line.
+    pub fn to_base64(&self) -> String {
+        let mut bytes = Vec::with_capacity(2 + KEY_ID_LEN + PUBLIC_KEY_LEN);
+        bytes.extend_from_slice(&ALG_ED25519);
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(&self.public_key);
+        base64::encode(&bytes, &base64::STANDARD)
+    }
+
+    /// Returns the contents of a public key file with the given untrusted
+    /// comment.
+    ///
+    /// Fails if `untrusted_comment` contains a line break.
+    pub fn to_file(&self, untrusted_comment: &str) -> Result<String, error::Unspecified> {
+        let mut r = comment_line(UNTRUSTED_COMMENT_PREFIX, untrusted_comment)?;
+        r.push_str(&self.to_base64());
+        r.push('\n');
+        Ok(r)
+    }
+
+    /// The key ID.
+    pub fn key_id(&self) -> &KeyId { &self.key_id }
+
+    /// The raw Ed25519 public key.
+    pub fn public_key_bytes(&self) -> &[u8] { &self.public_key }
+
+    /// Verifies that `signature` is a valid signature of `msg` by this key.
+    ///
+    /// If `signature` has a trusted comment then the trusted comment is
+    /// verified too.
+    pub fn verify(&self, msg: &[u8], signature: &SignatureFile) -> Result<(), error::Unspecified> {
+        if signature.key_id != self.key_id {
+            return Err(error::Unspecified);
+        }
+
+        let prehashed;
+        let signed_msg = if signature.algorithm == ALG_ED25519_PREHASHED {
+            prehashed = blake2b::digest(msg);
+            prehashed.as_ref()
+        } else {
+            msg
+        };
+        self.verify_ed25519(signed_msg, &signature.signature)?;
+
+        if let Some((trusted_comment, global_signature)) = &signature.trusted {
+            let mut global_msg = Vec::with_capacity(SIGNATURE_LEN + trusted_comment.len());
+            global_msg.extend_from_slice(&signature.signature);
+            global_msg.extend_from_slice(trusted_comment.as_bytes());
+            self.verify_ed25519(&global_msg, global_signature)?;
+        }
+
+        Ok(())
+    }
+
+    fn verify_ed25519(&self, msg: &[u8], sig: &[u8; SIGNATURE_LEN]) -> Result<(), error::Unspecified> {
+        signature::verify(
+            &signature::ED25519,
+            untrusted::Input::from(&self.public_key),
+            untrusted::Input::from(msg),
+            untrusted::Input::from(sig),
+        )
+    }
+}
+
+/// A parsed minisign or signify signature file.
+#[derive(Clone, Debug)]
+pub struct SignatureFile {
+    untrusted_comment: String,
+    algorithm: [u8; 2],
+    key_id: KeyId,
+    signature: [u8; SIGNATURE_LEN],
+    trusted: Option<(String, [u8; SIGNATURE_LEN])>,
+}
+
+impl SignatureFile {
+    /// Parses the contents of a minisign or signify signature file.
+    ///
+    /// A file with two lines is parsed as a signify signature; a file with
+    /// four lines is parsed as a minisign signature. Pre-hashed signatures
+    /// are only accepted in the minisign format.
+    pub fn parse(contents: &str) -> Result<Self, error::Unspecified> {
+        let mut lines = Lines::new(contents);
+        let untrusted_comment = String::from(lines.untrusted_comment()?);
+
+        let bytes = base64::decode(lines.next()?, &base64::STANDARD)?;
+        if bytes.len() != 2 + KEY_ID_LEN + SIGNATURE_LEN {
+            return Err(error::Unspecified);
+        }
+        let mut algorithm = [0; 2];
+        algorithm.copy_from_slice(&bytes[..2]);
+        let mut key_id = [0; KEY_ID_LEN];
+        key_id.copy_from_slice(&bytes[2..][..KEY_ID_LEN]);
+        let mut signature = [0; SIGNATURE_LEN];
+        signature.copy_from_slice(&bytes[(2 + KEY_ID_LEN)..]);
+
+        let trusted = if lines.at_end() {
+            None
+        } else {
+            let trusted_comment = lines.next()?;
+            if !trusted_comment.starts_with(TRUSTED_COMMENT_PREFIX) {
+                return Err(error::Unspecified);
+            }
+            let trusted_comment = String::from(&trusted_comment[TRUSTED_COMMENT_PREFIX.len()..]);
+            let global_signature = base64::decode(lines.next()?, &base64::STANDARD)?;
+            if global_signature.len() != SIGNATURE_LEN {
+                return Err(error::Unspecified);
+            }
+            let mut global = [0; SIGNATURE_LEN];
+            global.copy_from_slice(&global_signature);
+            Some((trusted_comment, global))
+        };
+        lines.finish()?;
+
+        match (algorithm, &trusted) {
+            (ALG_ED25519, _) | (ALG_ED25519_PREHASHED, Some(_)) => (),
+            _ => {
+                return Err(error::Unspecified);
+            },
+        }
+
+        Ok(Self {
+            untrusted_comment,
+            algorithm,
+            key_id,
+            signature,
+            trusted,
+        })
+    }
+
+    /// The ID of the key that (supposedly) made the signature.
+    pub fn key_id(&self) -> &KeyId { &self.key_id }
+
+    /// The untrusted comment, without the `untrusted comment: ` prefix.
+    ///
+    /// The untrusted comment is not covered by any signature.
+    pub fn untrusted_comment(&self) -> &str { &self.untrusted_comment }
+
+    /// The trusted comment, without the `trusted comment: ` prefix, if this
+    /// is a minisign signature.
+    ///
+    /// The trusted comment should only be relied upon after
+    /// `PublicKey::verify()` succeeds for this signature.
+    pub fn trusted_comment(&self) -> Option<&str> {
+        self.trusted.as_ref().map(|(comment, _)| comment.as_str())
+    }
+}
+
+/// Returns the contents of a pre-hashed minisign signature file for `msg`.
+///
+/// Fails if either comment contains a line break.
+pub fn sign(
+    key_pair: &signature::Ed25519KeyPair, key_id: KeyId, msg: &[u8], untrusted_comment: &str,
+    trusted_comment: &str,
+) -> Result<String, error::Unspecified> {
+    let mut r = comment_line(UNTRUSTED_COMMENT_PREFIX, untrusted_comment)?;
+    let trusted_comment_line = comment_line(TRUSTED_COMMENT_PREFIX, trusted_comment)?;
+
+    let sig = key_pair.sign(blake2b::digest(msg).as_ref());
+    r.push_str(&encode_signature_line(ALG_ED25519_PREHASHED, &key_id, sig.as_ref()));
+    r.push('\n');
+
+    let mut global_msg = Vec::with_capacity(SIGNATURE_LEN + trusted_comment.len());
+    global_msg.extend_from_slice(sig.as_ref());
+    global_msg.extend_from_slice(trusted_comment.as_bytes());
+    let global_sig = key_pair.sign(&global_msg);
+
+    r.push_str(&trusted_comment_line);
+    r.push_str(&base64::encode(global_sig.as_ref(), &base64::STANDARD));
+    r.push('\n');
+    Ok(r)
+}
+
+/// Returns the contents of a signify signature file for `msg`.
+///
+/// Fails if `untrusted_comment` contains a line break.
+pub fn sign_signify(
+    key_pair: &signature::Ed25519KeyPair, key_id: KeyId, msg: &[u8], untrusted_comment: &str,
+) -> Result<String, error::Unspecified> {
+    let mut r = comment_line(UNTRUSTED_COMMENT_PREFIX, untrusted_comment)?;
+    let sig = key_pair.sign(msg);
+    r.push_str(&encode_signature_line(ALG_ED25519, &key_id, sig.as_ref()));
+    r.push('\n');
+    Ok(r)
+}
+
+fn encode_signature_line(algorithm: [u8; 2], key_id: &KeyId, sig: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(2 + KEY_ID_LEN + SIGNATURE_LEN);
+    bytes.extend_from_slice(&algorithm);
+    bytes.extend_from_slice(key_id);
+    bytes.extend_from_slice(sig);
+    base64::encode(&bytes, &base64::STANDARD)
+}
+
+fn comment_line(prefix: &str, comment: &str) -> Result<String, error::Unspecified> {
+    if comment.contains(|c| c == '\n' || c == '\r') {
+        return Err(error::Unspecified);
+    }
+    let mut r = String::with_capacity(prefix.len() + comment.len() + 1);
+    r.push_str(prefix);
+    r.push_str(comment);
+    r.push('\n');
+    Ok(r)
+}
+
+/// Splits a file into lines, accepting both `\n` and `\r\n` line endings and
+/// an optional line ending at the end of the file.
+struct Lines<'a> {
+    lines: core::iter::Peekable<core::str::Lines<'a>>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(contents: &'a str) -> Self {
+        Self {
+            lines: contents.lines().peekable(),
+        }
+    }
+
+    fn next(&mut self) -> Result<&'a str, error::Unspecified> {
+        self.lines.next().ok_or(error::Unspecified)
+    }
+
+    fn untrusted_comment(&mut self) -> Result<&'a str, error::Unspecified> {
+        let line = self.next()?;
+        if !line.starts_with(UNTRUSTED_COMMENT_PREFIX) {
+            return Err(error::Unspecified);
+        }
+        Ok(&line[UNTRUSTED_COMMENT_PREFIX.len()..])
+    }
+
+    fn at_end(&mut self) -> bool { self.lines.peek().is_none() }
+
+    fn finish(mut self) -> Result<(), error::Unspecified> {
+        if !self.at_end() {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,865 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! OPAQUE, the augmented password-authenticated key exchange of [RFC 9807].
+//!
+//! The client registers a password with the server once and later logs in
+//! with it. The server never sees the password, not even during
+//! registration: the client combines it with a per-user key of the server's
+//! through an oblivious pseudorandom function (OPRF) that reveals neither to
+//! the other party. Unlike with SRP (`ring::srp`) or SPAKE2+
+//! (`ring::pake::spake2plus`), there's no salt that the server reveals, so an
+//! attacker can't start a dictionary attack before stealing the server's
+//! records.
+//!
+//! This implements OPAQUE-3DH with the P256-SHA256 configuration: the
+//! OPRF(P-256, SHA-256) of [RFC 9497], HKDF-SHA256, HMAC-SHA256, SHA-256, and
+//! Diffie-Hellman over P-256. `KeyStretching` selects the key stretching
+//! function that the client applies to the OPRF's output.
+//!
+//! Registration:
+//!
+//! 1. The client calls `ClientRegistration::start()` and sends `request()` to
+//!    the server.
+//! 2. The server responds with `ServerSetup::registration_response()`.
+//! 3. The client calls `ClientRegistration::finish()` and sends the
+//!    `RegistrationRecord` to the server, which stores it for the user.
+//!
+//! Login:
+//!
+//! 1. The client calls `ClientLogin::start()` and sends `ke1()`.
+//! 2. The server looks up the user's record, calls `ServerLogin::start()`,
+//!    and sends `ke2()`.
+//! 3. The client calls `ClientLogin::finish()`, which fails if the password
+//!    is wrong, and sends `ke3()`.
+//! 4. The server calls `ServerLogin::finish()`. Both now have the same
+//!    `SessionKey`.
+//!
+//! ```
+//! use ring::{opaque, rand};
+//!
+//! # fn main() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let stretching = opaque::KeyStretching::Identity; // Only for this example.
+//! let identities = opaque::Identities { client: Some(b"alice"), server: None };
+//!
+//! // The server generates and stores these once.
+//! let (oprf_seed, key_seed) = ([1u8; 32], [2u8; 32]);
+//! let server = opaque::ServerSetup::from_seeds(&oprf_seed, &key_seed)?;
+//!
+//! // Registration.
+//! let client = opaque::ClientRegistration::start(b"password", &rng)?;
+//! let response = server.registration_response(client.request(), b"alice")?;
+//! let (record, _export_key) =
+//!     client.finish(b"password", &response, &identities, stretching, &rng)?;
+//!
+//! // Login.
+//! let client = opaque::ClientLogin::start(b"password", &rng)?;
+//! let login = opaque::ServerLogin::start(
+//!     &server, &record, b"alice", client.ke1(), b"context", &identities, &rng)?;
+//! let client = client.finish(b"password", login.ke2(), b"context", &identities, stretching)?;
+//! let session_key = login.finish(client.ke3())?;
+//! assert_eq!(session_key.as_ref(), client.session_key().as_ref());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 9807]: https://tools.ietf.org/html/rfc9807
+//! [RFC 9497]: https://tools.ietf.org/html/rfc9497
+
+use crate::{
+    constant_time, digest,
+    ec::suite_b::oprf::{self as group, Element, Scalar, P256_ELEMENT_LEN as ELEMENT_LEN},
+    error, hkdf, hmac, oprf, rand,
+    zeroize::{self, Zeroizing},
+};
+
+#[cfg(feature = "alloc")]
+use crate::scrypt;
+
+/// The length of a public key.
+pub const PUBLIC_KEY_LEN: usize = ELEMENT_LEN;
+
+/// The length of the output of `ClientRegistration::request()`.
+pub const REGISTRATION_REQUEST_LEN: usize = ELEMENT_LEN;
+
+/// The length of the output of `ServerSetup::registration_response()`.
+pub const REGISTRATION_RESPONSE_LEN: usize = ELEMENT_LEN + PUBLIC_KEY_LEN;
+
+/// The length of an encoded `RegistrationRecord`.
+pub const REGISTRATION_RECORD_LEN: usize = PUBLIC_KEY_LEN + HASH_LEN + ENVELOPE_LEN;
+
+/// The length of the output of `ClientLogin::ke1()`.
+pub const KE1_LEN: usize = ELEMENT_LEN + NONCE_LEN + PUBLIC_KEY_LEN;
+
+/// The length of the output of `ServerLogin::ke2()`.
+pub const KE2_LEN: usize = CREDENTIAL_RESPONSE_LEN + NONCE_LEN + PUBLIC_KEY_LEN + HASH_LEN;
+
+/// The length of the output of `ClientSession::ke3()`.
+pub const KE3_LEN: usize = HASH_LEN;
+
+/// The length of a `SessionKey` or an `ExportKey`.
+pub const KEY_LEN: usize = HASH_LEN;
+
+const HASH_LEN: usize = digest::SHA256_OUTPUT_LEN;
+const NONCE_LEN: usize = 32;
+const SEED_LEN: usize = 32;
+const ENVELOPE_LEN: usize = NONCE_LEN + HASH_LEN;
+const MASKED_RESPONSE_LEN: usize = PUBLIC_KEY_LEN + ENVELOPE_LEN;
+const CREDENTIAL_RESPONSE_LEN: usize = ELEMENT_LEN + NONCE_LEN + MASKED_RESPONSE_LEN;
+
+/// The key stretching function, which makes each guess of a dictionary
+/// attack on a stolen record expensive. Registration and login must use the
+/// same one.
+#[derive(Clone, Copy, Debug)]
+pub enum KeyStretching {
+    /// No stretching. This is only appropriate if the password is itself a
+    /// strong secret.
+    Identity,
+
+    /// scrypt with N = 32768, r = 8, p = 1, and a salt of 16 zero bytes, as
+    /// RFC 9807 recommends. This needs 32 MiB of memory.
+    #[cfg(feature = "alloc")]
+    Scrypt,
+}
+
+impl KeyStretching {
+    fn stretch(self, input: &[u8], out: &mut [u8]) -> Result<(), error::Unspecified> {
+        match self {
+            KeyStretching::Identity => {
+                out.copy_from_slice(input);
+                Ok(())
+            },
+            #[cfg(feature = "alloc")]
+            KeyStretching::Scrypt => {
+                let params = scrypt::Params {
+                    log_n: 15,
+                    r: 8,
+                    p: 1,
+                };
+                scrypt::derive(&params, &[0; 16], input, out)
+            },
+        }
+    }
+}
+
+/// The identities of the client and the server that are bound into the
+/// registration and into each login; when `None`, the party's public key is
+/// used instead. Both parties must use the same identities, and the same
+/// ones for registration and login.
+#[derive(Clone, Copy, Debug)]
+pub struct Identities<'a> {
+    /// The client's identity, e.g. a user name.
+    pub client: Option<&'a [u8]>,
+
+    /// The server's identity, e.g. a domain name.
+    pub server: Option<&'a [u8]>,
+}
+
+/// The server's long-term secrets: the seed from which it derives each
+/// user's OPRF key, and its key pair.
+pub struct ServerSetup {
+    oprf_seed: [u8; HASH_LEN],
+    private_key: Scalar,
+    public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+impl ServerSetup {
+    /// Constructs the setup from `oprf_seed` and `key_seed`, which must each
+    /// be 32 bytes that the server generated randomly once and keeps secret.
+    /// The key pair is derived from `key_seed`.
+    pub fn from_seeds(oprf_seed: &[u8], key_seed: &[u8]) -> Result<Self, error::Unspecified> {
+        if oprf_seed.len() != HASH_LEN {
+            return Err(error::Unspecified);
+        }
+        let (private_key, public_key) = derive_key_pair(key_seed)?;
+        let mut r = Self {
+            oprf_seed: [0; HASH_LEN],
+            private_key,
+            public_key: [0; PUBLIC_KEY_LEN],
+        };
+        r.oprf_seed.copy_from_slice(oprf_seed);
+        group::P256.serialize(&public_key, &mut r.public_key);
+        Ok(r)
+    }
+
+    /// The server's public key.
+    #[inline]
+    pub fn public_key(&self) -> &[u8] { &self.public_key }
+
+    /// Responds to a client's registration request for the user with the
+    /// unique `credential_identifier`, e.g. a database key.
+    pub fn registration_response(
+        &self, request: &[u8], credential_identifier: &[u8],
+    ) -> Result<[u8; REGISTRATION_RESPONSE_LEN], error::Unspecified> {
+        let mut response = [0; REGISTRATION_RESPONSE_LEN];
+        let (evaluated_message, public_key) = response.split_at_mut(ELEMENT_LEN);
+        evaluated_message.copy_from_slice(&self.evaluate(request, credential_identifier)?);
+        public_key.copy_from_slice(&self.public_key);
+        Ok(response)
+    }
+
+    // Evaluates the OPRF on `blinded_message` with the user's OPRF key,
+    // `DeriveKeyPair(Expand(oprf_seed, credential_identifier || "OprfKey"),
+    // "OPAQUE-DeriveKeyPair")`.
+    fn evaluate(
+        &self, blinded_message: &[u8], credential_identifier: &[u8],
+    ) -> Result<[u8; ELEMENT_LEN], error::Unspecified> {
+        let mut seed = Zeroizing::new([0; SEED_LEN]);
+        expand(
+            &hmac::SigningKey::new(&digest::SHA256, &self.oprf_seed),
+            &[credential_identifier, b"OprfKey"],
+            &mut *seed,
+        );
+        let oprf_key = oprf_key(&*seed, b"OPAQUE-DeriveKeyPair")?;
+        let mut r = [0; ELEMENT_LEN];
+        r.copy_from_slice(oprf_key.blind_evaluate(blinded_message)?.as_ref());
+        Ok(r)
+    }
+}
+
+impl Drop for ServerSetup {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.oprf_seed) }
+}
+
+/// What the server stores for each user: the client's public key, the key
+/// that masks the server's response, and the envelope from which the client
+/// recovers its private key.
+#[derive(Clone)]
+pub struct RegistrationRecord {
+    bytes: [u8; REGISTRATION_RECORD_LEN],
+}
+
+impl RegistrationRecord {
+    /// Parses a record, e.g. the one that the client sent at the end of the
+    /// registration.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, error::Unspecified> {
+        if bytes.len() != REGISTRATION_RECORD_LEN {
+            return Err(error::Unspecified);
+        }
+        let _ = group::P256.deserialize(&bytes[..PUBLIC_KEY_LEN])?;
+        let mut r = Self {
+            bytes: [0; REGISTRATION_RECORD_LEN],
+        };
+        r.bytes.copy_from_slice(bytes);
+        Ok(r)
+    }
+
+    /// A record for a user who isn't registered, for which `ServerLogin`
+    /// responds like for a real user, so that the server doesn't reveal which
+    /// users are registered. The login then fails on the client.
+    pub fn fake(rng: &rand::SecureRandom) -> Result<Self, error::Unspecified> {
+        let mut r = Self {
+            bytes: [0; REGISTRATION_RECORD_LEN],
+        };
+        let (_, public_key) = generate_key_pair(rng)?;
+        let mut encoded = [0; PUBLIC_KEY_LEN];
+        group::P256.serialize(&public_key, &mut encoded);
+        r.bytes[..PUBLIC_KEY_LEN].copy_from_slice(&encoded);
+        rng.fill(&mut r.bytes[PUBLIC_KEY_LEN..(PUBLIC_KEY_LEN + HASH_LEN)])?;
+        Ok(r)
+    }
+
+    #[inline]
+    fn client_public_key(&self) -> &[u8] { &self.bytes[..PUBLIC_KEY_LEN] }
+
+    #[inline]
+    fn masking_key(&self) -> &[u8] { &self.bytes[PUBLIC_KEY_LEN..(PUBLIC_KEY_LEN + HASH_LEN)] }
+
+    #[inline]
+    fn envelope(&self) -> &[u8] { &self.bytes[(PUBLIC_KEY_LEN + HASH_LEN)..] }
+}
+
+impl AsRef<[u8]> for RegistrationRecord {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes }
+}
+
+/// The key that the client and the server share after a successful login.
+pub struct SessionKey([u8; KEY_LEN]);
+
+impl AsRef<[u8]> for SessionKey {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.0 }
+}
+
+impl Drop for SessionKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.0) }
+}
+
+/// A key that only the client can compute, the same for the registration
+/// and every login, e.g. to encrypt data that the client stores on the
+/// server.
+pub struct ExportKey([u8; KEY_LEN]);
+
+impl AsRef<[u8]> for ExportKey {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.0 }
+}
+
+impl Drop for ExportKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.0) }
+}
+
+/// The client's state during registration.
+pub struct ClientRegistration {
+    blind: oprf::Blind,
+}
+
+impl ClientRegistration {
+    /// Starts the registration of `password`.
+    pub fn start(password: &[u8], rng: &rand::SecureRandom) -> Result<Self, error::Unspecified> {
+        Ok(Self {
+            blind: oprf::blind(&oprf::P256_SHA256, oprf::Mode::Oprf, password, rng)?,
+        })
+    }
+
+    /// The request to send to the server.
+    #[inline]
+    pub fn request(&self) -> &[u8] { self.blind.blinded_element().as_ref() }
+
+    /// Finishes the registration with the server's response, returning the
+    /// record to send to the server and the export key.
+    pub fn finish(
+        self, password: &[u8], response: &[u8], identities: &Identities,
+        stretching: KeyStretching, rng: &rand::SecureRandom,
+    ) -> Result<(RegistrationRecord, ExportKey), error::Unspecified> {
+        if response.len() != REGISTRATION_RESPONSE_LEN {
+            return Err(error::Unspecified);
+        }
+        let (evaluated_message, server_public_key) = response.split_at(ELEMENT_LEN);
+        let _ = group::P256.deserialize(server_public_key)?;
+        let randomized_password =
+            randomized_password(password, &self.blind, evaluated_message, stretching)?;
+
+        let mut record = RegistrationRecord {
+            bytes: [0; REGISTRATION_RECORD_LEN],
+        };
+        let (client_public_key, rest) = record.bytes.split_at_mut(PUBLIC_KEY_LEN);
+        let (masking_key, envelope) = rest.split_at_mut(HASH_LEN);
+        let (nonce, auth_tag) = envelope.split_at_mut(NONCE_LEN);
+        rng.fill(nonce)?;
+        expand(&randomized_password, &[b"MaskingKey"], masking_key);
+        let keys = EnvelopeKeys::new(&randomized_password, nonce)?;
+        client_public_key.copy_from_slice(&keys.public_key);
+        auth_tag.copy_from_slice(
+            keys.auth_tag(nonce, server_public_key, identities)?
+                .as_ref(),
+        );
+        Ok((record, keys.export_key))
+    }
+}
+
+/// The client's state during a login.
+pub struct ClientLogin {
+    blind: oprf::Blind,
+    private_keyshare: Scalar,
+    ke1: [u8; KE1_LEN],
+}
+
+impl ClientLogin {
+    /// Starts a login with `password`.
+    pub fn start(password: &[u8], rng: &rand::SecureRandom) -> Result<Self, error::Unspecified> {
+        let blind = oprf::blind(&oprf::P256_SHA256, oprf::Mode::Oprf, password, rng)?;
+        let mut ke1 = [0; KE1_LEN];
+        ke1[..ELEMENT_LEN].copy_from_slice(blind.blinded_element().as_ref());
+        rng.fill(&mut ke1[ELEMENT_LEN..(ELEMENT_LEN + NONCE_LEN)])?;
+        let (private_keyshare, public_keyshare) = generate_key_pair(rng)?;
+        group::P256.serialize(&public_keyshare, &mut ke1[(ELEMENT_LEN + NONCE_LEN)..]);
+        Ok(Self {
+            blind,
+            private_keyshare,
+            ke1,
+        })
+    }
+
+    /// The first message, to send to the server.
+    #[inline]
+    pub fn ke1(&self) -> &[u8] { &self.ke1 }
+
+    /// Finishes the login with the server's response `ke2`. This fails if
+    /// the password is wrong, if the server isn't the one that the password
+    /// was registered with, or if `context` or `identities` differ from the
+    /// server's.
+    pub fn finish(
+        self, password: &[u8], ke2: &[u8], context: &[u8], identities: &Identities,
+        stretching: KeyStretching,
+    ) -> Result<ClientSession, error::Unspecified> {
+        if ke2.len() != KE2_LEN {
+            return Err(error::Unspecified);
+        }
+        let (evaluated_message, rest) = ke2.split_at(ELEMENT_LEN);
+        let (masking_nonce, rest) = rest.split_at(NONCE_LEN);
+        let (masked_response, rest) = rest.split_at(MASKED_RESPONSE_LEN);
+        let (_server_nonce, rest) = rest.split_at(NONCE_LEN);
+        let (server_public_keyshare, server_mac) = rest.split_at(PUBLIC_KEY_LEN);
+
+        let randomized_password =
+            randomized_password(password, &self.blind, evaluated_message, stretching)?;
+
+        // Unmask the server's public key and the envelope, and recover the
+        // client's key pair from the envelope.
+        let mut masking_key = Zeroizing::new([0; HASH_LEN]);
+        expand(&randomized_password, &[b"MaskingKey"], &mut *masking_key);
+        let mut response = [0; MASKED_RESPONSE_LEN];
+        expand(
+            &hmac::SigningKey::new(&digest::SHA256, &*masking_key),
+            &[masking_nonce, b"CredentialResponsePad"],
+            &mut response,
+        );
+        for (r, masked) in response.iter_mut().zip(masked_response) {
+            *r ^= masked;
+        }
+        let (server_public_key, envelope) = response.split_at(PUBLIC_KEY_LEN);
+        let (nonce, auth_tag) = envelope.split_at(NONCE_LEN);
+        let keys = EnvelopeKeys::new(&randomized_password, nonce)?;
+        let expected_auth_tag = keys.auth_tag(nonce, server_public_key, identities)?;
+        constant_time::verify_slices_are_equal(expected_auth_tag.as_ref(), auth_tag)?;
+
+        let server_public_key_element = group::P256.deserialize(server_public_key)?;
+        let server_public_keyshare = group::P256.deserialize(server_public_keyshare)?;
+        let mut ikm = Zeroizing::new([0; 3 * ELEMENT_LEN]);
+        diffie_hellman(&self.private_keyshare, &server_public_keyshare, &mut ikm[..ELEMENT_LEN])?;
+        diffie_hellman(
+            &self.private_keyshare,
+            &server_public_key_element,
+            &mut ikm[ELEMENT_LEN..(2 * ELEMENT_LEN)],
+        )?;
+        diffie_hellman(&keys.private_key, &server_public_keyshare, &mut ikm[(2 * ELEMENT_LEN)..])?;
+
+        let preamble = preamble(
+            context,
+            identity(identities.client, &keys.public_key)?,
+            &self.ke1,
+            identity(identities.server, server_public_key)?,
+            &ke2[..(KE2_LEN - HASH_LEN)],
+        )?;
+        let (server_mac_key, client_mac_key, session_key) = derive_keys(&*ikm, &preamble);
+        let expected_server_mac = hmac::sign(&server_mac_key, preamble.clone().finish().as_ref());
+        constant_time::verify_slices_are_equal(expected_server_mac.as_ref(), server_mac)?;
+
+        let mut ctx = preamble;
+        ctx.update(server_mac);
+        let mut r = ClientSession {
+            ke3: [0; KE3_LEN],
+            session_key,
+            export_key: keys.export_key,
+        };
+        r.ke3.copy_from_slice(hmac::sign(&client_mac_key, ctx.finish().as_ref()).as_ref());
+        Ok(r)
+    }
+}
+
+/// The client's state after a successful login.
+pub struct ClientSession {
+    ke3: [u8; KE3_LEN],
+    session_key: SessionKey,
+    export_key: ExportKey,
+}
+
+impl ClientSession {
+    /// The last message, to send to the server.
+    #[inline]
+    pub fn ke3(&self) -> &[u8] { &self.ke3 }
+
+    /// The key shared with the server.
+    #[inline]
+    pub fn session_key(&self) -> &SessionKey { &self.session_key }
+
+    /// The export key, the same as the one from the registration.
+    #[inline]
+    pub fn export_key(&self) -> &ExportKey { &self.export_key }
+}
+
+/// The server's state during a login.
+pub struct ServerLogin {
+    ke2: [u8; KE2_LEN],
+    expected_client_mac: hmac::Signature,
+    session_key: SessionKey,
+}
+
+impl ServerLogin {
+    /// Responds to the client's `ke1` for the user with the unique
+    /// `credential_identifier` and `record`. For unknown users, use the same
+    /// `credential_identifier` as a real user would have, and a
+    /// `RegistrationRecord::fake()` record.
+    pub fn start(
+        setup: &ServerSetup, record: &RegistrationRecord, credential_identifier: &[u8],
+        ke1: &[u8], context: &[u8], identities: &Identities, rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        if ke1.len() != KE1_LEN {
+            return Err(error::Unspecified);
+        }
+        let (blinded_message, client_public_keyshare) = ke1.split_at(ELEMENT_LEN + NONCE_LEN);
+        let blinded_message = &blinded_message[..ELEMENT_LEN];
+        let client_public_keyshare = group::P256.deserialize(client_public_keyshare)?;
+        let client_public_key = group::P256.deserialize(record.client_public_key())?;
+
+        let mut ke2 = [0; KE2_LEN];
+        let private_keyshare = {
+            let (evaluated_message, rest) = ke2.split_at_mut(ELEMENT_LEN);
+            let (masking_nonce, rest) = rest.split_at_mut(NONCE_LEN);
+            let (masked_response, rest) = rest.split_at_mut(MASKED_RESPONSE_LEN);
+            let (server_nonce, rest) = rest.split_at_mut(NONCE_LEN);
+            let (server_public_keyshare, _) = rest.split_at_mut(PUBLIC_KEY_LEN);
+
+            let evaluated = setup.evaluate(blinded_message, credential_identifier)?;
+            evaluated_message.copy_from_slice(&evaluated);
+            rng.fill(masking_nonce)?;
+            expand(
+                &hmac::SigningKey::new(&digest::SHA256, record.masking_key()),
+                &[masking_nonce, b"CredentialResponsePad"],
+                masked_response,
+            );
+            let plaintext = setup.public_key.iter().chain(record.envelope());
+            for (masked, plaintext) in masked_response.iter_mut().zip(plaintext) {
+                *masked ^= plaintext;
+            }
+
+            rng.fill(server_nonce)?;
+            let (private_keyshare, public_keyshare) = generate_key_pair(rng)?;
+            let mut encoded = [0; PUBLIC_KEY_LEN];
+            group::P256.serialize(&public_keyshare, &mut encoded);
+            server_public_keyshare.copy_from_slice(&encoded);
+            private_keyshare
+        };
+
+        let mut ikm = Zeroizing::new([0; 3 * ELEMENT_LEN]);
+        diffie_hellman(&private_keyshare, &client_public_keyshare, &mut ikm[..ELEMENT_LEN])?;
+        diffie_hellman(
+            &setup.private_key,
+            &client_public_keyshare,
+            &mut ikm[ELEMENT_LEN..(2 * ELEMENT_LEN)],
+        )?;
+        diffie_hellman(&private_keyshare, &client_public_key, &mut ikm[(2 * ELEMENT_LEN)..])?;
+
+        let preamble = preamble(
+            context,
+            identity(identities.client, record.client_public_key())?,
+            ke1,
+            identity(identities.server, &setup.public_key)?,
+            &ke2[..(KE2_LEN - HASH_LEN)],
+        )?;
+        let (server_mac_key, client_mac_key, session_key) = derive_keys(&*ikm, &preamble);
+        let server_mac = hmac::sign(&server_mac_key, preamble.clone().finish().as_ref());
+        ke2[(KE2_LEN - HASH_LEN)..].copy_from_slice(server_mac.as_ref());
+
+        let mut ctx = preamble;
+        ctx.update(server_mac.as_ref());
+        Ok(Self {
+            ke2,
+            expected_client_mac: hmac::sign(&client_mac_key, ctx.finish().as_ref()),
+            session_key,
+        })
+    }
+
+    /// The response to send to the client.
+    #[inline]
+    pub fn ke2(&self) -> &[u8] { &self.ke2 }
+
+    /// Verifies the client's last message, returning the session key if it
+    /// is valid. An invalid `ke3` usually means that the client used a wrong
+    /// password.
+    pub fn finish(self, ke3: &[u8]) -> Result<SessionKey, error::Unspecified> {
+        constant_time::verify_slices_are_equal(self.expected_client_mac.as_ref(), ke3)?;
+        Ok(self.session_key)
+    }
+}
+
+// The keys that the client derives from the randomized password and the
+// envelope nonce.
+struct EnvelopeKeys {
+    auth_key: Zeroizing<[u8; HASH_LEN]>,
+    export_key: ExportKey,
+    private_key: Scalar,
+    public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+impl EnvelopeKeys {
+    fn new(
+        randomized_password: &hmac::SigningKey, nonce: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        let mut auth_key = Zeroizing::new([0; HASH_LEN]);
+        expand(randomized_password, &[nonce, b"AuthKey"], &mut *auth_key);
+        let mut export_key = ExportKey([0; KEY_LEN]);
+        expand(randomized_password, &[nonce, b"ExportKey"], &mut export_key.0);
+        let mut seed = Zeroizing::new([0; SEED_LEN]);
+        expand(randomized_password, &[nonce, b"PrivateKey"], &mut *seed);
+        let (private_key, public_key) = derive_key_pair(&*seed)?;
+        let mut r = Self {
+            auth_key,
+            export_key,
+            private_key,
+            public_key: [0; PUBLIC_KEY_LEN],
+        };
+        group::P256.serialize(&public_key, &mut r.public_key);
+        Ok(r)
+    }
+
+    // `MAC(auth_key, nonce || cleartext_credentials)`, where
+    // `cleartext_credentials` is `server_public_key`, then the server's and
+    // the client's identities, each prefixed by its length.
+    fn auth_tag(
+        &self, nonce: &[u8], server_public_key: &[u8], identities: &Identities,
+    ) -> Result<hmac::Signature, error::Unspecified> {
+        let server_identity = identity(identities.server, server_public_key)?;
+        let client_identity = identity(identities.client, &self.public_key)?;
+        let key = hmac::SigningKey::new(&digest::SHA256, &*self.auth_key);
+        let mut ctx = hmac::SigningContext::with_key(&key);
+        ctx.update(nonce);
+        ctx.update(server_public_key);
+        ctx.update(&oprf::len_prefix(server_identity)?);
+        ctx.update(server_identity);
+        ctx.update(&oprf::len_prefix(client_identity)?);
+        ctx.update(client_identity);
+        Ok(ctx.sign())
+    }
+}
+
+// `DeriveDiffieHellmanKeyPair(seed)`.
+fn derive_key_pair(seed: &[u8]) -> Result<(Scalar, Element), error::Unspecified> {
+    if seed.len() != SEED_LEN {
+        return Err(error::Unspecified);
+    }
+    let key = oprf_key(seed, b"OPAQUE-DeriveDiffieHellmanKeyPair")?;
+    let private_key = group::P256.deserialize_scalar(key.scalar())?;
+    Ok((private_key, group::P256.deserialize(key.public_key().as_ref())?))
+}
+
+// `DeriveKeyPair(seed, info)` of OPRF(P-256, SHA-256).
+fn oprf_key(seed: &[u8], info: &[u8]) -> Result<oprf::PrivateKey, error::Unspecified> {
+    oprf::PrivateKey::derive(&oprf::P256_SHA256, oprf::Mode::Oprf, seed, info)
+}
+
+// `GenerateAuthKeyPair()`.
+fn generate_key_pair(rng: &rand::SecureRandom) -> Result<(Scalar, Element), error::Unspecified> {
+    let mut seed = Zeroizing::new([0; SEED_LEN]);
+    rng.fill(&mut *seed)?;
+    derive_key_pair(&*seed)
+}
+
+// `DiffieHellman(private_key, public_key)`, the encoding of the product.
+fn diffie_hellman(
+    private_key: &Scalar, public_key: &Element, out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    let mut encoded = [0; ELEMENT_LEN];
+    group::P256.serialize(&group::P256.mul(private_key, public_key)?, &mut encoded);
+    out.copy_from_slice(&encoded);
+    zeroize::zeroize(&mut encoded);
+    Ok(())
+}
+
+// An identity, or `public_key` if it's `None`. Identities can't be empty.
+fn identity<'a>(
+    identity: Option<&'a [u8]>, public_key: &'a [u8],
+) -> Result<&'a [u8], error::Unspecified> {
+    match identity {
+        Some([]) => Err(error::Unspecified),
+        Some(identity) => Ok(identity),
+        None => Ok(public_key),
+    }
+}
+
+// The `randomized_password`, `Extract("", oprf_output ||
+// Stretch(oprf_output))`, as an HMAC key for `Expand`.
+fn randomized_password(
+    password: &[u8], blind: &oprf::Blind, evaluated_message: &[u8], stretching: KeyStretching,
+) -> Result<hmac::SigningKey, error::Unspecified> {
+    let oprf_output = blind.finalize(password, evaluated_message)?;
+    let mut ikm = Zeroizing::new([0; 2 * HASH_LEN]);
+    let (output, stretched) = ikm.split_at_mut(HASH_LEN);
+    output.copy_from_slice(oprf_output.as_ref());
+    stretching.stretch(oprf_output.as_ref(), stretched)?;
+    Ok(hkdf::extract(&hmac::SigningKey::new(&digest::SHA256, &[]), &*ikm))
+}
+
+// A hash context that has consumed the preamble, `"OPAQUEv1-"` followed by
+// `context`, `client_identity`, `ke1`, `server_identity`, and `ke2` without
+// the server's MAC, in which `context` and the identities are prefixed by
+// their lengths.
+fn preamble(
+    context: &[u8], client_identity: &[u8], ke1: &[u8], server_identity: &[u8],
+    ke2_without_mac: &[u8],
+) -> Result<digest::Context, error::Unspecified> {
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    ctx.update(b"OPAQUEv1-");
+    ctx.update(&oprf::len_prefix(context)?);
+    ctx.update(context);
+    ctx.update(&oprf::len_prefix(client_identity)?);
+    ctx.update(client_identity);
+    ctx.update(ke1);
+    ctx.update(&oprf::len_prefix(server_identity)?);
+    ctx.update(server_identity);
+    ctx.update(ke2_without_mac);
+    Ok(ctx)
+}
+
+// `DeriveKeys(ikm, preamble)`: the keys of the server's and the client's
+// MACs and the session key.
+fn derive_keys(
+    ikm: &[u8], preamble: &digest::Context,
+) -> (hmac::SigningKey, hmac::SigningKey, SessionKey) {
+    let preamble_hash = preamble.clone().finish();
+    let prk = hkdf::extract(&hmac::SigningKey::new(&digest::SHA256, &[]), ikm);
+    let mut handshake_secret = Zeroizing::new([0; HASH_LEN]);
+    expand_label(&prk, b"HandshakeSecret", preamble_hash.as_ref(), &mut *handshake_secret);
+    let mut session_key = SessionKey([0; KEY_LEN]);
+    expand_label(&prk, b"SessionKey", preamble_hash.as_ref(), &mut session_key.0);
+
+    let handshake_secret = hmac::SigningKey::new(&digest::SHA256, &*handshake_secret);
+    let mac_key = |label: &[u8]| {
+        let mut key = Zeroizing::new([0; HASH_LEN]);
+        expand_label(&handshake_secret, label, &[], &mut *key);
+        hmac::SigningKey::new(&digest::SHA256, &*key)
+    };
+    (mac_key(b"ServerMAC"), mac_key(b"ClientMAC"), session_key)
+}
+
+// `Expand-Label(secret, label, context, out.len())`.
+fn expand_label(secret: &hmac::SigningKey, label: &[u8], context: &[u8], out: &mut [u8]) {
+    const PREFIX: &[u8] = b"OPAQUE-";
+    expand(
+        secret,
+        &[
+            &(out.len() as u16).to_be_bytes(),
+            &[(PREFIX.len() + label.len()) as u8],
+            PREFIX,
+            label,
+            &[context.len() as u8],
+            context,
+        ],
+        out,
+    );
+}
+
+// HKDF-Expand, where `info` is the concatenation of its parts.
+fn expand(prk: &hmac::SigningKey, info: &[&[u8]], out: &mut [u8]) {
+    let mut t: Option<hmac::Signature> = None;
+    for (i, chunk) in out.chunks_mut(HASH_LEN).enumerate() {
+        let mut ctx = hmac::SigningContext::with_key(prk);
+        if let Some(t) = &t {
+            ctx.update(t.as_ref());
+        }
+        for part in info {
+            ctx.update(part);
+        }
+        ctx.update(&[(i + 1) as u8]);
+        let block = ctx.sign();
+        chunk.copy_from_slice(&block.as_ref()[..chunk.len()]);
+        t = Some(block);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_opaque() {
+        test::from_file("src/opaque_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let stretching = match test_case.consume_string("KSF").as_str() {
+                "Identity" => KeyStretching::Identity,
+                "Scrypt" => KeyStretching::Scrypt,
+                _ => unreachable!(),
+            };
+            let password = test_case.consume_bytes("password");
+            let credential_identifier = test_case.consume_bytes("credential_identifier");
+            let client_identity = test_case.consume_bytes("client_identity");
+            let server_identity = test_case.consume_bytes("server_identity");
+            let context = test_case.consume_bytes("context");
+            let oprf_seed = test_case.consume_bytes("oprf_seed");
+            let server_keypair_seed = test_case.consume_bytes("server_keypair_seed");
+            let blind_registration = test_case.consume_bytes("blind_registration");
+            let envelope_nonce = test_case.consume_bytes("envelope_nonce");
+            let blind_login = test_case.consume_bytes("blind_login");
+            let client_nonce = test_case.consume_bytes("client_nonce");
+            let client_keyshare_seed = test_case.consume_bytes("client_keyshare_seed");
+            let masking_nonce = test_case.consume_bytes("masking_nonce");
+            let server_nonce = test_case.consume_bytes("server_nonce");
+            let server_keyshare_seed = test_case.consume_bytes("server_keyshare_seed");
+            let server_public_key = test_case.consume_bytes("server_public_key");
+            let registration_request = test_case.consume_bytes("registration_request");
+            let registration_response = test_case.consume_bytes("registration_response");
+            let registration_record = test_case.consume_bytes("registration_record");
+            let export_key = test_case.consume_bytes("export_key");
+            let ke1 = test_case.consume_bytes("KE1");
+            let ke2 = test_case.consume_bytes("KE2");
+            let ke3 = test_case.consume_bytes("KE3");
+            let session_key = test_case.consume_bytes("session_key");
+
+            fn optional(identity: &[u8]) -> Option<&[u8]> {
+                if identity.is_empty() {
+                    None
+                } else {
+                    Some(identity)
+                }
+            }
+            let identities = Identities {
+                client: optional(&client_identity),
+                server: optional(&server_identity),
+            };
+
+            let setup = ServerSetup::from_seeds(&oprf_seed, &server_keypair_seed).unwrap();
+            assert_eq!(setup.public_key(), &server_public_key[..]);
+
+            let rng = test::rand::FixedSliceRandom {
+                bytes: &blind_registration,
+            };
+            let client = ClientRegistration::start(&password, &rng).unwrap();
+            assert_eq!(client.request(), &registration_request[..]);
+            let response =
+                setup.registration_response(client.request(), &credential_identifier).unwrap();
+            assert_eq!(&response[..], &registration_response[..]);
+            let rng = test::rand::FixedSliceRandom {
+                bytes: &envelope_nonce,
+            };
+            let (record, export) =
+                client.finish(&password, &response, &identities, stretching, &rng).unwrap();
+            assert_eq!(record.as_ref(), &registration_record[..]);
+            assert_eq!(export.as_ref(), &export_key[..]);
+
+            let random: [&[u8]; 3] = [&blind_login, &client_nonce, &client_keyshare_seed];
+            let rng = test::rand::FixedSliceSequenceRandom::new(&random);
+            let client = ClientLogin::start(&password, &rng).unwrap();
+            assert_eq!(client.ke1(), &ke1[..]);
+
+            let record = RegistrationRecord::from_bytes(&registration_record).unwrap();
+            let random: [&[u8]; 3] = [&masking_nonce, &server_nonce, &server_keyshare_seed];
+            let rng = test::rand::FixedSliceSequenceRandom::new(&random);
+            let server = ServerLogin::start(
+                &setup,
+                &record,
+                &credential_identifier,
+                client.ke1(),
+                &context,
+                &identities,
+                &rng,
+            )
+            .unwrap();
+            assert_eq!(server.ke2(), &ke2[..]);
+
+            let client = client
+                .finish(&password, server.ke2(), &context, &identities, stretching)
+                .unwrap();
+            assert_eq!(client.ke3(), &ke3[..]);
+            assert_eq!(client.session_key().as_ref(), &session_key[..]);
+            assert_eq!(client.export_key().as_ref(), &export_key[..]);
+            assert_eq!(server.finish(client.ke3()).unwrap().as_ref(), &session_key[..]);
+
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,878 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Oblivious pseudorandom functions: the OPRF and VOPRF modes of [RFC 9497].
+//!
+//! A server holds a `PrivateKey` and a client an input, e.g. a password. The
+//! client blinds its input with `blind()` and sends the blinded element to
+//! the server; the server evaluates the function on it without learning the
+//! input; and the client unblinds the result with `Blind::finalize()`, which
+//! gives it the output of the function on its input without learning the
+//! key. The same input always gives the same output under the same key.
+//!
+//! In the verifiable mode, `Mode::Voprf`, the server also proves that it used
+//! the key of its public key, so that the client can check that every
+//! evaluation used the same key, e.g. to detect a server that tags clients
+//! with per-client keys. Batches of inputs can be evaluated with a single
+//! proof with `PrivateKey::blind_evaluate_batch()` and `finalize_batch()`.
+//!
+//! The ciphersuites are `P256_SHA256`, OPRF(P-256, SHA-256);
+//! `P384_SHA384`, OPRF(P-384, SHA-384); and `RISTRETTO255_SHA512`,
+//! OPRF(ristretto255, SHA-512).
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{oprf, rand};
+//!
+//! # fn evaluate() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let suite = &oprf::RISTRETTO255_SHA512;
+//! let key = oprf::PrivateKey::derive(suite, oprf::Mode::Voprf, &[7; 32], b"key info")?;
+//! let public_key = key.public_key().as_ref().to_vec();
+//!
+//! // The client blinds its input...
+//! let blind = oprf::blind(suite, oprf::Mode::Voprf, b"input", &rng)?;
+//!
+//! // ...the server evaluates the function on the blinded element...
+//! let (evaluated, proof) =
+//!     key.blind_evaluate_with_proof(blind.blinded_element().as_ref(), &rng)?;
+//!
+//! // ...and the client verifies the proof and unblinds the result.
+//! let output =
+//!     blind.finalize_verifiable(b"input", evaluated.as_ref(), proof.as_ref(), &public_key)?;
+//!
+//! let blind = oprf::blind(suite, oprf::Mode::Voprf, b"input", &rng)?;
+//! let (evaluated, proof) =
+//!     key.blind_evaluate_with_proof(blind.blinded_element().as_ref(), &rng)?;
+//! let again =
+//!     blind.finalize_verifiable(b"input", evaluated.as_ref(), proof.as_ref(), &public_key)?;
+//! assert_eq!(output.as_ref(), again.as_ref());
+//! # Ok(())
+//! # }
+//! # fn main() { evaluate().unwrap() }
+//! ```
+//!
+//! [RFC 9497]: https://tools.ietf.org/html/rfc9497
+
+use crate::{
+    constant_time, digest,
+    ec::{
+        curve25519::ristretto255,
+        hash_to_curve::expand_message_xmd,
+        suite_b::oprf::{self as nist, MAX_ELEMENT_LEN, MAX_SCALAR_LEN},
+    },
+    error, rand,
+    zeroize::{self, Zeroizing},
+};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// An OPRF ciphersuite: a prime-order group and a hash function.
+pub struct Suite {
+    id: &'static str,
+    group: &'static Group,
+    hash: &'static digest::Algorithm,
+}
+
+derive_debug_via_self!(Suite, self.id);
+
+/// OPRF(P-256, SHA-256).
+pub static P256_SHA256: Suite = Suite {
+    id: "P256-SHA256",
+    group: &P256,
+    hash: &digest::SHA256,
+};
+
+/// OPRF(ristretto255, SHA-512).
+pub static RISTRETTO255_SHA512: Suite = Suite {
+    id: "ristretto255-SHA512",
+    group: &RISTRETTO255,
+    hash: &digest::SHA512,
+};
+
+/// OPRF(P-384, SHA-384).
+pub static P384_SHA384: Suite = Suite {
+    id: "P384-SHA384",
+    group: &P384,
+    hash: &digest::SHA384,
+};
+
+/// The mode of the protocol.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// The base mode, in which the client can't verify the server's
+    /// evaluations.
+    Oprf = 0,
+
+    /// The verifiable mode, in which the server proves that its evaluations
+    /// used the key of its public key.
+    Voprf = 1,
+}
+
+/// An encoded group element: a blinded element, an evaluated element, or a
+/// public key.
+#[derive(Clone, Copy)]
+pub struct Element {
+    bytes: [u8; MAX_ELEMENT_LEN],
+    len: usize,
+}
+
+impl Element {
+    fn new(suite: &Suite) -> Self {
+        Self {
+            bytes: [0; MAX_ELEMENT_LEN],
+            len: suite.group.element_len,
+        }
+    }
+
+    fn as_mut(&mut self) -> &mut [u8] { &mut self.bytes[..self.len] }
+}
+
+impl AsRef<[u8]> for Element {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+derive_debug_via_self!(Element, self.as_ref());
+
+/// A proof that evaluations used the key of a public key: the encoded
+/// scalars `c` and `s` of RFC 9497 Section 2.2.1.
+#[derive(Clone, Copy)]
+pub struct Proof {
+    bytes: [u8; 2 * MAX_SCALAR_LEN],
+    len: usize,
+}
+
+impl AsRef<[u8]> for Proof {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+derive_debug_via_self!(Proof, self.as_ref());
+
+/// A server's private key.
+pub struct PrivateKey {
+    context: Context,
+    scalar: [u8; MAX_SCALAR_LEN],
+    public_key: Element,
+}
+
+impl PrivateKey {
+    /// `DeriveKeyPair(seed, info)`: derives a key from a secret `seed`, which
+    /// should be 32 bytes, and a public `info`.
+    pub fn derive(
+        suite: &'static Suite, mode: Mode, seed: &[u8], info: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        let context = Context { suite, mode };
+        let info_len = len_prefix(info)?;
+        let mut scalar = [0; MAX_SCALAR_LEN];
+        for counter in 0..=255u8 {
+            if context
+                .hash_to_scalar(
+                    &[seed, &info_len, info, &[counter]],
+                    b"DeriveKeyPair",
+                    &mut scalar[..context.scalar_len()],
+                )
+                .is_ok()
+            {
+                return Self::from_scalar(context, scalar);
+            }
+        }
+        Err(error::Unspecified)
+    }
+
+    /// Generates a random key.
+    pub fn generate(
+        suite: &'static Suite, mode: Mode, rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        let context = Context { suite, mode };
+        let mut scalar = [0; MAX_SCALAR_LEN];
+        (suite.group.random_scalar)(rng, &mut scalar[..context.scalar_len()])?;
+        Self::from_scalar(context, scalar)
+    }
+
+    fn from_scalar(
+        context: Context, scalar: [u8; MAX_SCALAR_LEN],
+    ) -> Result<Self, error::Unspecified> {
+        let mut r = Self {
+            context,
+            scalar,
+            public_key: Element::new(context.suite),
+        };
+        let scalar = &r.scalar[..context.scalar_len()];
+        (context.suite.group.mul_base)(scalar, r.public_key.as_mut())?;
+        Ok(r)
+    }
+
+    /// The public key, with which clients verify proofs in `Mode::Voprf`.
+    #[inline]
+    pub fn public_key(&self) -> &Element { &self.public_key }
+
+    /// `BlindEvaluate(skS, blindedElement)` in `Mode::Oprf`.
+    ///
+    /// Fails in `Mode::Voprf`, in which the client needs a proof.
+    pub fn blind_evaluate(&self, blinded_element: &[u8]) -> Result<Element, error::Unspecified> {
+        if self.context.mode != Mode::Oprf {
+            return Err(error::Unspecified);
+        }
+        self.evaluate_blinded(blinded_element)
+    }
+
+    /// `BlindEvaluate(skS, pkS, blindedElement)` in `Mode::Voprf`: the
+    /// evaluated element and the proof.
+    pub fn blind_evaluate_with_proof(
+        &self, blinded_element: &[u8], rng: &rand::SecureRandom,
+    ) -> Result<(Element, Proof), error::Unspecified> {
+        if self.context.mode != Mode::Voprf {
+            return Err(error::Unspecified);
+        }
+        let evaluated = self.evaluate_blinded(blinded_element)?;
+        let proof = self.generate_proof(&[blinded_element], &[evaluated.as_ref()], rng)?;
+        Ok((evaluated, proof))
+    }
+
+    /// Evaluates a batch of blinded elements in `Mode::Voprf`, with a
+    /// single proof for all of them.
+    #[cfg(feature = "alloc")]
+    pub fn blind_evaluate_batch(
+        &self, blinded_elements: &[&[u8]], rng: &rand::SecureRandom,
+    ) -> Result<(Vec<Element>, Proof), error::Unspecified> {
+        if self.context.mode != Mode::Voprf || blinded_elements.is_empty() {
+            return Err(error::Unspecified);
+        }
+        let evaluated = blinded_elements
+            .iter()
+            .map(|blinded_element| self.evaluate_blinded(blinded_element))
+            .collect::<Result<Vec<_>, _>>()?;
+        let evaluated_bytes: Vec<&[u8]> = evaluated.iter().map(|e| e.as_ref()).collect();
+        let proof = self.generate_proof(blinded_elements, &evaluated_bytes, rng)?;
+        Ok((evaluated, proof))
+    }
+
+    /// `Evaluate(skS, input)`: the output of the function on `input`,
+    /// computed by the server without a client, e.g. to check an output
+    /// that a client presents later.
+    pub fn evaluate(&self, input: &[u8]) -> Result<digest::Digest, error::Unspecified> {
+        let suite = self.context.suite;
+        let mut input_element = Element::new(suite);
+        let dst = self.context.dst(b"HashToGroup-");
+        (suite.group.hash_to_group)(input, &dst, input_element.as_mut())?;
+        let issued_element = self.evaluate_blinded(input_element.as_ref())?;
+        finalize_hash(suite, input, &issued_element)
+    }
+
+    fn evaluate_blinded(&self, blinded_element: &[u8]) -> Result<Element, error::Unspecified> {
+        let mut evaluated = Element::new(self.context.suite);
+        (self.context.suite.group.mul)(self.scalar(), blinded_element, evaluated.as_mut())?;
+        Ok(evaluated)
+    }
+
+    // `GenerateProof(skS, G, pkS, blindedElements, evaluatedElements)`.
+    fn generate_proof(
+        &self, c: &[&[u8]], d: &[&[u8]], rng: &rand::SecureRandom,
+    ) -> Result<Proof, error::Unspecified> {
+        let context = &self.context;
+        let group = context.suite.group;
+        let scalar_len = group.scalar_len;
+        let (m, z) = context.composites(Some(self.scalar()), self.public_key.as_ref(), c, d)?;
+
+        let mut r = Zeroizing::new([0; MAX_SCALAR_LEN]);
+        let r = &mut r[..scalar_len];
+        (group.random_scalar)(rng, r)?;
+        let mut t2 = Element::new(context.suite);
+        (group.mul_base)(r, t2.as_mut())?;
+        let mut t3 = Element::new(context.suite);
+        (group.mul)(r, m.as_ref(), t3.as_mut())?;
+
+        let mut proof = Proof {
+            bytes: [0; 2 * MAX_SCALAR_LEN],
+            len: 2 * scalar_len,
+        };
+        let (proof_c, proof_s) = proof.bytes[..(2 * scalar_len)].split_at_mut(scalar_len);
+        context.challenge(self.public_key.as_ref(), &m, &z, &t2, &t3, proof_c)?;
+        proof_s.copy_from_slice(r);
+        (group.scalar_mul_sub)(proof_c, self.scalar(), proof_s)?;
+        Ok(proof)
+    }
+
+    // The encoded scalar, for OPAQUE's `DeriveDiffieHellmanKeyPair`.
+    pub(crate) fn scalar(&self) -> &[u8] { &self.scalar[..self.context.scalar_len()] }
+}
+
+impl Drop for PrivateKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.scalar) }
+}
+
+derive_debug_via_self!(PrivateKey, self.context.suite);
+
+/// A client's blind for an input, and the blinded element to send to the
+/// server.
+pub struct Blind {
+    context: Context,
+    scalar: [u8; MAX_SCALAR_LEN],
+    blinded_element: Element,
+}
+
+/// `Blind(input)`: blinds `input` for evaluation by the server.
+///
+/// Fails if `input` hashes to the identity element, which is practically
+/// impossible.
+pub fn blind(
+    suite: &'static Suite, mode: Mode, input: &[u8], rng: &rand::SecureRandom,
+) -> Result<Blind, error::Unspecified> {
+    let context = Context { suite, mode };
+    let group = suite.group;
+    let mut r = Blind {
+        context,
+        scalar: [0; MAX_SCALAR_LEN],
+        blinded_element: Element::new(suite),
+    };
+    (group.random_scalar)(rng, &mut r.scalar[..group.scalar_len])?;
+    let mut input_element = Element::new(suite);
+    (group.hash_to_group)(input, &context.dst(b"HashToGroup-"), input_element.as_mut())?;
+    let scalar = &r.scalar[..group.scalar_len];
+    (group.mul)(scalar, input_element.as_ref(), r.blinded_element.as_mut())?;
+    Ok(r)
+}
+
+impl Blind {
+    /// The blinded element, to send to the server.
+    #[inline]
+    pub fn blinded_element(&self) -> &Element { &self.blinded_element }
+
+    /// `Finalize(input, blind, evaluatedElement)` in `Mode::Oprf`: the output
+    /// of the function on `input`.
+    ///
+    /// Fails in `Mode::Voprf`, in which the proof must be verified.
+    pub fn finalize(
+        &self, input: &[u8], evaluated_element: &[u8],
+    ) -> Result<digest::Digest, error::Unspecified> {
+        if self.context.mode != Mode::Oprf {
+            return Err(error::Unspecified);
+        }
+        self.unblind(input, evaluated_element)
+    }
+
+    /// `Finalize(input, blind, evaluatedElement, blindedElement, pkS, proof)`
+    /// in `Mode::Voprf`: verifies that `proof` shows that the server's key is
+    /// that of `public_key` and returns the output of the function on
+    /// `input`.
+    pub fn finalize_verifiable(
+        &self, input: &[u8], evaluated_element: &[u8], proof: &[u8], public_key: &[u8],
+    ) -> Result<digest::Digest, error::Unspecified> {
+        if self.context.mode != Mode::Voprf {
+            return Err(error::Unspecified);
+        }
+        self.context.verify_proof(
+            public_key,
+            &[self.blinded_element.as_ref()],
+            &[evaluated_element],
+            proof,
+        )?;
+        self.unblind(input, evaluated_element)
+    }
+
+    fn unblind(
+        &self, input: &[u8], evaluated_element: &[u8],
+    ) -> Result<digest::Digest, error::Unspecified> {
+        let group = self.context.suite.group;
+        let mut inverse = Zeroizing::new([0; MAX_SCALAR_LEN]);
+        let inverse = &mut inverse[..group.scalar_len];
+        (group.scalar_inverse)(self.scalar(), inverse)?;
+        let mut unblinded_element = Element::new(self.context.suite);
+        (group.mul)(inverse, evaluated_element, unblinded_element.as_mut())?;
+        finalize_hash(self.context.suite, input, &unblinded_element)
+    }
+
+    fn scalar(&self) -> &[u8] { &self.scalar[..self.context.scalar_len()] }
+}
+
+impl Drop for Blind {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.scalar) }
+}
+
+derive_debug_via_self!(Blind, self.blinded_element);
+
+/// Verifies a batch of evaluations in `Mode::Voprf` with their single proof
+/// and returns the outputs for `inputs`, which were blinded by `blinds`.
+#[cfg(feature = "alloc")]
+pub fn finalize_batch(
+    blinds: &[Blind], inputs: &[&[u8]], evaluated_elements: &[&[u8]], proof: &[u8],
+    public_key: &[u8],
+) -> Result<Vec<digest::Digest>, error::Unspecified> {
+    let first = blinds.first().ok_or(error::Unspecified)?;
+    if inputs.len() != blinds.len()
+        || evaluated_elements.len() != blinds.len()
+        || blinds
+            .iter()
+            .any(|blind| blind.context.mode != Mode::Voprf || blind.context != first.context)
+    {
+        return Err(error::Unspecified);
+    }
+    let blinded_elements: Vec<&[u8]> =
+        blinds.iter().map(|blind| blind.blinded_element.as_ref()).collect();
+    first.context.verify_proof(public_key, &blinded_elements, evaluated_elements, proof)?;
+    blinds
+        .iter()
+        .zip(inputs)
+        .zip(evaluated_elements)
+        .map(|((blind, input), evaluated_element)| blind.unblind(input, evaluated_element))
+        .collect()
+}
+
+// The output for `input` from its unblinded element.
+fn finalize_hash(
+    suite: &Suite, input: &[u8], unblinded_element: &Element,
+) -> Result<digest::Digest, error::Unspecified> {
+    let mut ctx = digest::Context::new(suite.hash);
+    ctx.update(&len_prefix(input)?);
+    ctx.update(input);
+    ctx.update(&len_prefix(unblinded_element.as_ref())?);
+    ctx.update(unblinded_element.as_ref());
+    ctx.update(b"Finalize");
+    Ok(ctx.finish())
+}
+
+// The ciphersuite and the mode, which determine the `contextString`,
+// `"OPRFV1-" || I2OSP(mode, 1) || "-" || identifier`.
+#[derive(Clone, Copy)]
+struct Context {
+    suite: &'static Suite,
+    mode: Mode,
+}
+
+impl PartialEq for Context {
+    fn eq(&self, other: &Self) -> bool {
+        self.suite.id == other.suite.id && self.mode == other.mode
+    }
+}
+
+impl Context {
+    #[inline]
+    fn scalar_len(&self) -> usize { self.suite.group.scalar_len }
+
+    // The parts of `prefix || contextString`.
+    fn dst<'a>(&'a self, prefix: &'a [u8]) -> [&'a [u8]; 5] {
+        let mode: &'static [u8] = match self.mode {
+            Mode::Oprf => &[Mode::Oprf as u8],
+            Mode::Voprf => &[Mode::Voprf as u8],
+        };
+        [prefix, b"OPRFV1-", mode, b"-", self.suite.id.as_bytes()]
+    }
+
+    // `HashToScalar(msg)` with the DST `dst_prefix || contextString`, failing
+    // if the result is zero.
+    fn hash_to_scalar(
+        &self, msg: &[&[u8]], dst_prefix: &[u8], out: &mut [u8],
+    ) -> Result<(), error::Unspecified> {
+        (self.suite.group.hash_to_scalar)(msg, &self.dst(dst_prefix), out)
+    }
+
+    // `ComputeCompositesFast(k, B, C, D)` if `k` is given and
+    // `ComputeComposites(B, C, D)` otherwise: `M` and `Z`, the combinations
+    // of `c` and `d` with the same pseudorandom weights.
+    fn composites(
+        &self, k: Option<&[u8]>, b: &[u8], c: &[&[u8]], d: &[&[u8]],
+    ) -> Result<(Element, Element), error::Unspecified> {
+        let group = self.suite.group;
+        if c.is_empty() || c.len() != d.len() || c.len() > usize::from(u16::max_value()) {
+            return Err(error::Unspecified);
+        }
+
+        let seed_dst = self.dst(b"Seed-");
+        let seed_dst_len: usize = seed_dst.iter().map(|part| part.len()).sum();
+        let mut ctx = digest::Context::new(self.suite.hash);
+        ctx.update(&len_prefix(b)?);
+        ctx.update(b);
+        ctx.update(&(seed_dst_len as u16).to_be_bytes());
+        for part in seed_dst.iter() {
+            ctx.update(part);
+        }
+        let seed = ctx.finish();
+
+        let mut m: Option<Element> = None;
+        let mut z: Option<Element> = None;
+        for (i, (c_i, d_i)) in c.iter().zip(d).enumerate() {
+            let mut weight = [0; MAX_SCALAR_LEN];
+            let weight = &mut weight[..group.scalar_len];
+            self.hash_to_scalar(
+                &[
+                    &len_prefix(seed.as_ref())?,
+                    seed.as_ref(),
+                    &(i as u16).to_be_bytes(),
+                    &len_prefix(c_i)?,
+                    c_i,
+                    &len_prefix(d_i)?,
+                    d_i,
+                    b"Composite",
+                ],
+                b"HashToScalar-",
+                weight,
+            )?;
+            self.accumulate(&mut m, weight, c_i)?;
+            if k.is_none() {
+                self.accumulate(&mut z, weight, d_i)?;
+            }
+        }
+        let m = m.ok_or(error::Unspecified)?;
+        let z = match k {
+            Some(k) => {
+                let mut z = Element::new(self.suite);
+                (group.mul)(k, m.as_ref(), z.as_mut())?;
+                z
+            },
+            None => z.ok_or(error::Unspecified)?,
+        };
+        Ok((m, z))
+    }
+
+    // `sum = weight * element + sum`.
+    fn accumulate(
+        &self, sum: &mut Option<Element>, weight: &[u8], element: &[u8],
+    ) -> Result<(), error::Unspecified> {
+        let group = self.suite.group;
+        let mut term = Element::new(self.suite);
+        (group.mul)(weight, element, term.as_mut())?;
+        *sum = Some(match sum {
+            Some(sum) => {
+                let mut r = Element::new(self.suite);
+                (group.add)(sum.as_ref(), term.as_ref(), r.as_mut())?;
+                r
+            },
+            None => term,
+        });
+        Ok(())
+    }
+
+    // The challenge `c`, the hash of `B`, `M`, `Z`, `t2`, and `t3`.
+    fn challenge(
+        &self, b: &[u8], m: &Element, z: &Element, t2: &Element, t3: &Element, out: &mut [u8],
+    ) -> Result<(), error::Unspecified> {
+        let element_len = len_prefix(m.as_ref())?;
+        self.hash_to_scalar(
+            &[
+                &len_prefix(b)?,
+                b,
+                &element_len,
+                m.as_ref(),
+                &element_len,
+                z.as_ref(),
+                &element_len,
+                t2.as_ref(),
+                &element_len,
+                t3.as_ref(),
+                b"Challenge",
+            ],
+            b"HashToScalar-",
+            out,
+        )
+    }
+
+    // `VerifyProof(G, pkS, blindedElements, evaluatedElements, proof)`.
+    fn verify_proof(
+        &self, public_key: &[u8], c: &[&[u8]], d: &[&[u8]], proof: &[u8],
+    ) -> Result<(), error::Unspecified> {
+        let group = self.suite.group;
+        if proof.len() != 2 * group.scalar_len {
+            return Err(error::Unspecified);
+        }
+        let (proof_c, proof_s) = proof.split_at(group.scalar_len);
+        let (m, z) = self.composites(None, public_key, c, d)?;
+
+        // t2 = s*G + c*pkS and t3 = s*M + c*Z.
+        let combine = |p: Option<&[u8]>, q: &[u8]| -> Result<Element, error::Unspecified> {
+            let mut s_p = Element::new(self.suite);
+            match p {
+                Some(p) => (group.mul)(proof_s, p, s_p.as_mut())?,
+                None => (group.mul_base)(proof_s, s_p.as_mut())?,
+            }
+            let mut c_q = Element::new(self.suite);
+            (group.mul)(proof_c, q, c_q.as_mut())?;
+            let mut r = Element::new(self.suite);
+            (group.add)(s_p.as_ref(), c_q.as_ref(), r.as_mut())?;
+            Ok(r)
+        };
+        let t2 = combine(None, public_key)?;
+        let t3 = combine(Some(m.as_ref()), z.as_ref())?;
+
+        let mut expected_c = [0; MAX_SCALAR_LEN];
+        let expected_c = &mut expected_c[..group.scalar_len];
+        self.challenge(public_key, &m, &z, &t2, &t3, expected_c)?;
+        constant_time::verify_slices_are_equal(expected_c, proof_c)
+    }
+}
+
+/// `I2OSP(len(value), 2)`, failing if `value` is too long.
+pub(crate) fn len_prefix(value: &[u8]) -> Result<[u8; 2], error::Unspecified> {
+    if value.len() > usize::from(u16::max_value()) {
+        return Err(error::Unspecified);
+    }
+    Ok((value.len() as u16).to_be_bytes())
+}
+
+// The operations of a prime-order group on encoded scalars and elements,
+// which write their results to `out`. Scalars are nonzero and less than the
+// order, and elements are never the identity element; every operation fails
+// if an input is invalid or if its result would be the identity element.
+struct Group {
+    scalar_len: usize,
+    element_len: usize,
+
+    // `HashToGroup(msg)` with the DST `dst`, the concatenation of its parts.
+    hash_to_group: fn(msg: &[u8], dst: Parts, out: &mut [u8]) -> Written,
+
+    // `HashToScalar(msg)` with the DST `dst`, failing if the result is zero.
+    hash_to_scalar: fn(msg: Parts, dst: Parts, out: &mut [u8]) -> Written,
+
+    random_scalar: fn(rng: &rand::SecureRandom, out: &mut [u8]) -> Written,
+
+    scalar_inverse: fn(a: &[u8], out: &mut [u8]) -> Written,
+
+    // `r = r - c*k`.
+    scalar_mul_sub: fn(c: &[u8], k: &[u8], r: &mut [u8]) -> Written,
+
+    // `a*p`.
+    mul: fn(a: &[u8], p: &[u8], out: &mut [u8]) -> Written,
+
+    mul_base: fn(a: &[u8], out: &mut [u8]) -> Written,
+
+    add: fn(p: &[u8], q: &[u8], out: &mut [u8]) -> Written,
+}
+
+type Written = Result<(), error::Unspecified>;
+
+// A byte string, as the concatenation of its parts.
+type Parts<'a> = &'a [&'a [u8]];
+
+// The closures can't capture the curve, so a macro instantiates them.
+macro_rules! nist_group {
+    ( $curve:expr, $scalar_len:expr ) => {
+        Group {
+            scalar_len: $scalar_len,
+            element_len: 1 + $scalar_len,
+            hash_to_group: |msg, dst, out| {
+                $curve.serialize(&$curve.hash_to_curve(msg, dst)?, out);
+                Ok(())
+            },
+            hash_to_scalar: |msg, dst, out| {
+                $curve.serialize_scalar(&$curve.hash_to_scalar(msg, dst)?, out);
+                Ok(())
+            },
+            random_scalar: |rng, out| {
+                $curve.serialize_scalar(&$curve.random_scalar(rng)?, out);
+                Ok(())
+            },
+            scalar_inverse: |a, out| {
+                let inverse = $curve.scalar_inverse(&$curve.deserialize_scalar(a)?);
+                $curve.serialize_scalar(&inverse, out);
+                Ok(())
+            },
+            scalar_mul_sub: |c, k, r| {
+                let c = $curve.deserialize_scalar(c)?;
+                let k = $curve.deserialize_scalar(k)?;
+                let difference = $curve.scalar_mul_sub(&$curve.deserialize_scalar(r)?, &c, &k);
+                $curve.serialize_scalar(&difference, r);
+                Ok(())
+            },
+            mul: |a, p, out| {
+                let product = $curve.mul(&$curve.deserialize_scalar(a)?, &$curve.deserialize(p)?)?;
+                $curve.serialize(&product, out);
+                Ok(())
+            },
+            mul_base: |a, out| {
+                $curve.serialize(&$curve.mul_base(&$curve.deserialize_scalar(a)?)?, out);
+                Ok(())
+            },
+            add: |p, q, out| {
+                let sum = $curve.add(&$curve.deserialize(p)?, &$curve.deserialize(q)?)?;
+                $curve.serialize(&sum, out);
+                Ok(())
+            },
+        }
+    };
+}
+
+static P256: Group = nist_group!(nist::P256, 256 / 8);
+static P384: Group = nist_group!(nist::P384, 384 / 8);
+
+static RISTRETTO255: Group = Group {
+    scalar_len: ristretto255::SCALAR_LEN,
+    element_len: ristretto255::ELEMENT_LEN,
+    hash_to_group: |msg, dst, out| {
+        let mut uniform_bytes = [0; ristretto255::UNIFORM_BYTES_LEN];
+        expand_message_xmd(&digest::SHA512, &[msg], dst, &mut uniform_bytes);
+        ristretto255_encode(&ristretto255::from_uniform_bytes(&uniform_bytes), out)
+    },
+    hash_to_scalar: |msg, dst, out| {
+        let mut uniform_bytes = [0; ristretto255::UNREDUCED_SCALAR_LEN];
+        expand_message_xmd(&digest::SHA512, msg, dst, &mut uniform_bytes);
+        let r = ristretto255::scalar_reduced(&uniform_bytes);
+        if constant_time::eq(&r, &[0; ristretto255::SCALAR_LEN]).expose() {
+            return Err(error::Unspecified);
+        }
+        out.copy_from_slice(&r);
+        Ok(())
+    },
+    // Rejection sampling of 253-bit values, about half of which are less
+    // than the order.
+    random_scalar: |rng, out| {
+        let mut r = Zeroizing::new([0; ristretto255::SCALAR_LEN]);
+        for _ in 0..100 {
+            rng.fill(&mut *r)?;
+            r[ristretto255::SCALAR_LEN - 1] &= 0x1f;
+            if ristretto255::scalar_is_valid(&r).expose() {
+                out.copy_from_slice(&*r);
+                return Ok(());
+            }
+        }
+        Err(error::Unspecified)
+    },
+    scalar_inverse: |a, out| {
+        out.copy_from_slice(&ristretto255::scalar_inverse(&ristretto255_scalar(a)?));
+        Ok(())
+    },
+    scalar_mul_sub: |c, k, r| {
+        let minus_c = ristretto255::scalar_negated(&ristretto255_scalar(c)?);
+        let k = ristretto255_scalar(k)?;
+        let difference = ristretto255::scalar_mul_add(&minus_c, &k, &ristretto255_scalar(r)?);
+        r.copy_from_slice(&difference);
+        Ok(())
+    },
+    mul: |a, p, out| {
+        let p = ristretto255_element(p)?;
+        ristretto255_encode(&p.mul(&ristretto255_scalar(a)?), out)
+    },
+    mul_base: |a, out| {
+        ristretto255_encode(&ristretto255::ExtPoint::mul_base(&ristretto255_scalar(a)?), out)
+    },
+    add: |p, q, out| {
+        ristretto255_encode(&ristretto255_element(p)?.add(&ristretto255_element(q)?), out)
+    },
+};
+
+fn ristretto255_scalar(a: &[u8]) -> Result<ristretto255::Scalar, error::Unspecified> {
+    let mut r = [0; ristretto255::SCALAR_LEN];
+    if a.len() != r.len() {
+        return Err(error::Unspecified);
+    }
+    r.copy_from_slice(a);
+    if !ristretto255::scalar_is_valid(&r).expose() {
+        return Err(error::Unspecified);
+    }
+    Ok(r)
+}
+
+// Decodes an element other than the identity element.
+fn ristretto255_element(p: &[u8]) -> Result<ristretto255::ExtPoint, error::Unspecified> {
+    let mut encoded = [0; ristretto255::ELEMENT_LEN];
+    if p.len() != encoded.len() {
+        return Err(error::Unspecified);
+    }
+    encoded.copy_from_slice(p);
+    let p = ristretto255::decode(&encoded)?;
+    if ristretto255::is_identity(&p).expose() {
+        return Err(error::Unspecified);
+    }
+    Ok(p)
+}
+
+// Encodes `p`, failing if it's the identity element.
+fn ristretto255_encode(
+    p: &ristretto255::ExtPoint, out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    let encoded = ristretto255::encode(p);
+    if encoded == [0; ristretto255::ELEMENT_LEN] {
+        return Err(error::Unspecified);
+    }
+    out.copy_from_slice(&encoded);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_oprf() {
+        test::from_file("src/oprf_tests.txt", |section, test_case| {
+            let (suite, mode) = match section {
+                "OPRF(P-256, SHA-256)" => (&P256_SHA256, Mode::Oprf),
+                "VOPRF(P-256, SHA-256)" => (&P256_SHA256, Mode::Voprf),
+                "OPRF(P-384, SHA-384)" => (&P384_SHA384, Mode::Oprf),
+                "VOPRF(P-384, SHA-384)" => (&P384_SHA384, Mode::Voprf),
+                "OPRF(ristretto255, SHA-512)" => (&RISTRETTO255_SHA512, Mode::Oprf),
+                "VOPRF(ristretto255, SHA-512)" => (&RISTRETTO255_SHA512, Mode::Voprf),
+                _ => unreachable!(),
+            };
+            let seed = test_case.consume_bytes("Seed");
+            let info = test_case.consume_bytes("KeyInfo");
+            let sk = test_case.consume_bytes("skSm");
+            let pk = match mode {
+                Mode::Oprf => None,
+                Mode::Voprf => Some(test_case.consume_bytes("pkSm")),
+            };
+            let input = test_case.consume_bytes("Input");
+            let blind_scalar = test_case.consume_bytes("Blind");
+            let blinded_element = test_case.consume_bytes("BlindedElement");
+            let evaluation_element = test_case.consume_bytes("EvaluationElement");
+            let proof_random = match mode {
+                Mode::Oprf => None,
+                Mode::Voprf => Some(test_case.consume_bytes("ProofRandomScalar")),
+            };
+            let expected_proof = match mode {
+                Mode::Oprf => None,
+                Mode::Voprf => Some(test_case.consume_bytes("Proof")),
+            };
+            let output = test_case.consume_bytes("Output");
+
+            let key = PrivateKey::derive(suite, mode, &seed, &info).unwrap();
+            assert_eq!(key.scalar(), &sk[..]);
+            if let Some(pk) = &pk {
+                assert_eq!(key.public_key().as_ref(), &pk[..]);
+            }
+
+            let rng = test::rand::FixedSliceRandom {
+                bytes: &blind_scalar,
+            };
+            let blind = blind(suite, mode, &input, &rng).unwrap();
+            assert_eq!(blind.blinded_element().as_ref(), &blinded_element[..]);
+
+            let result = match (&pk, &proof_random, &expected_proof) {
+                (Some(pk), Some(proof_random), Some(expected_proof)) => {
+                    assert!(key.blind_evaluate(&blinded_element).is_err());
+                    let rng = test::rand::FixedSliceRandom {
+                        bytes: proof_random,
+                    };
+                    let (evaluated, proof) =
+                        key.blind_evaluate_with_proof(&blinded_element, &rng).unwrap();
+                    assert_eq!(evaluated.as_ref(), &evaluation_element[..]);
+                    assert_eq!(proof.as_ref(), &expected_proof[..]);
+                    assert!(blind.finalize(&input, &evaluation_element).is_err());
+                    blind.finalize_verifiable(&input, &evaluation_element, expected_proof, pk)
+                },
+                _ => {
+                    let evaluated = key.blind_evaluate(&blinded_element).unwrap();
+                    assert_eq!(evaluated.as_ref(), &evaluation_element[..]);
+                    blind.finalize(&input, &evaluation_element)
+                },
+            };
+            assert_eq!(result.unwrap().as_ref(), &output[..]);
+            assert_eq!(key.evaluate(&input).unwrap().as_ref(), &output[..]);
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! HMAC-based one-time passwords: HOTP and TOTP.
+//!
+//! [HOTP] codes are computed from a counter that the client and the server
+//! each keep, and [TOTP] codes from the current time, in steps of usually 30
+//! seconds, with `T0` = 0. Both are commonly used for two-factor
+//! authentication, with the secret key shared through an `otpauth://` URI.
+//! The key is usually 20 bytes with HMAC-SHA1, the default of most
+//! authenticator apps, but HMAC-SHA256 and HMAC-SHA512 are also supported.
+//!
+//! `verify_hotp` and `verify_totp` compare codes in constant time and accept
+//! codes from a window of counters or time steps, to allow for a client
+//! that is ahead or a clock that is off. They return the counter or time step
+//! of the code that matched; the caller must store it and reject any code
+//! that isn't for a later one, so that each code is accepted only once.
+//!
+//! # Example
+//!
+//! ```
+//! use core::num::NonZeroU64;
+//! use ring::{digest, otp};
+//!
+//! # fn otp() -> Result<(), ring::error::Unspecified> {
+//! let key = otp::Key::new(&digest::SHA1, b"12345678901234567890", 6)?;
+//! let period = NonZeroU64::new(30).unwrap();
+//!
+//! // The authenticator app computes the code at the time it is shown...
+//! let code = otp::totp(&key, period, 1_111_111_109);
+//! assert_eq!(code.as_str(), "081804");
+//!
+//! // ...and the server accepts it when it is entered, a little later.
+//! let time_step = otp::verify_totp(&key, period, 1_111_111_115, 1, code.as_ref())?;
+//! assert_eq!(time_step, 1_111_111_109 / 30);
+//! # Ok(())
+//! # }
+//! # fn main() { otp().unwrap() }
+//! ```
+//!
+//! [HOTP]: https://tools.ietf.org/html/rfc4226
+//! [TOTP]: https://tools.ietf.org/html/rfc6238
+
+use crate::{constant_time, digest, error, hmac};
+use core::num::NonZeroU64;
+
+/// The minimum number of digits of a code, as required by RFC 4226.
+pub const MIN_DIGITS: usize = 6;
+
+/// The maximum number of digits of a code.
+pub const MAX_DIGITS: usize = 9;
+
+/// A key for computing and verifying codes of a fixed number of digits.
+pub struct Key {
+    key: hmac::SigningKey,
+    digits: usize,
+}
+
+impl Key {
+    /// Constructs a key from the shared secret `value`, for codes of
+    /// `digits` digits.
+    ///
+    /// Fails unless `digits` is between `MIN_DIGITS` and `MAX_DIGITS`.
+    pub fn new(
+        digest_alg: &'static digest::Algorithm, value: &[u8], digits: usize,
+    ) -> Result<Self, error::Unspecified> {
+        if !(MIN_DIGITS..=MAX_DIGITS).contains(&digits) {
+            return Err(error::Unspecified);
+        }
+        Ok(Self {
+            key: hmac::SigningKey::new(digest_alg, value),
+            digits,
+        })
+    }
+
+    /// The number of digits of the codes.
+    #[inline]
+    pub fn digits(&self) -> usize { self.digits }
+}
+
+derive_debug_via_self!(Key, self.digits);
+
+/// A one-time password: a string of decimal digits, with leading zeros.
+#[derive(Clone, Copy)]
+pub struct Code {
+    digits: [u8; MAX_DIGITS],
+    len: usize,
+}
+
+impl Code {
+    /// The code as a string.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        // The code is only ASCII digits.
+        core::str::from_utf8(self.as_ref()).unwrap()
+    }
+}
+
+impl AsRef<[u8]> for Code {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.digits[..self.len] }
+}
+
+derive_debug_via_self!(Code, self.as_str());
+
+/// Computes the HOTP code for `counter`.
+pub fn hotp(key: &Key, counter: u64) -> Code {
+    let mac = hmac::sign(&key.key, &counter.to_be_bytes());
+    let mac = mac.as_ref();
+
+    // Dynamic truncation, RFC 4226 Section 5.3.
+    let offset = usize::from(mac[mac.len() - 1] & 0x0f);
+    let mut truncated = [0; 4];
+    truncated.copy_from_slice(&mac[offset..][..4]);
+    let mut value = u32::from_be_bytes(truncated) & 0x7fff_ffff;
+
+    let mut code = Code {
+        digits: [0; MAX_DIGITS],
+        len: key.digits,
+    };
+    for digit in code.digits[..key.digits].iter_mut().rev() {
+        *digit = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+    code
+}
+
+/// Verifies the HOTP code `code` for the counters from `counter` to
+/// `counter + look_ahead`, returning the counter of the code that matched.
+/// The next code to verify is then for the counter after it.
+pub fn verify_hotp(
+    key: &Key, counter: u64, look_ahead: u64, code: &[u8],
+) -> Result<u64, error::Unspecified> {
+    let last = counter.saturating_add(look_ahead);
+    verify(key, counter, last, code)
+}
+
+/// Computes the TOTP code for the UNIX time `time`, in seconds, with time
+/// steps of `period` seconds.
+pub fn totp(key: &Key, period: NonZeroU64, time: u64) -> Code { hotp(key, time / period.get()) }
+
+/// Verifies the TOTP code `code` for the UNIX time `time`, in seconds,
+/// accepting the codes of up to `window` time steps before or after the
+/// current one. Returns the time step of the code that matched.
+///
+/// A `window` of 1 is what RFC 6238 recommends, to allow for the delay
+/// between the code being generated and being entered.
+pub fn verify_totp(
+    key: &Key, period: NonZeroU64, time: u64, window: u64, code: &[u8],
+) -> Result<u64, error::Unspecified> {
+    let time_step = time / period.get();
+    let first = time_step.saturating_sub(window);
+    let last = time_step.saturating_add(window);
+    verify(key, first, last, code)
+}
+
+// Every code in the window is computed and compared, so that the time taken
+// doesn't depend on which code, if any, matched.
+fn verify(key: &Key, first: u64, last: u64, code: &[u8]) -> Result<u64, error::Unspecified> {
+    if code.len() != key.digits {
+        return Err(error::Unspecified);
+    }
+    let mut matched = None;
+    let mut counter = first;
+    loop {
+        let is_match = constant_time::verify_slices_are_equal(hotp(key, counter).as_ref(), code);
+        if is_match.is_ok() && matched.is_none() {
+            matched = Some(counter);
+        }
+        if counter == last {
+            break;
+        }
+        counter += 1;
+    }
+    matched.ok_or(error::Unspecified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_digits() {
+        for digits in 0..=(MAX_DIGITS + 1) {
+            let key = Key::new(&digest::SHA256, b"key", digits);
+            assert_eq!(key.is_ok(), (MIN_DIGITS..=MAX_DIGITS).contains(&digits));
+        }
+    }
+
+    #[test]
+    fn test_code_leading_zeros() {
+        // From RFC 6238 Appendix B. Shorter codes are the last digits of
+        // longer ones.
+        let key = b"12345678901234567890";
+        let counter = 1_111_111_109 / 30;
+        let code = |digits| hotp(&Key::new(&digest::SHA1, key, digits).unwrap(), counter);
+        assert_eq!(code(6).as_str(), "081804");
+        assert_eq!(code(8).as_str(), "07081804");
+        assert!(code(9).as_str().ends_with("07081804"));
+    }
+
+    #[test]
+    fn test_verify_window_edges() {
+        let key = Key::new(&digest::SHA256, b"key", 6).unwrap();
+        let code = hotp(&key, 0);
+        assert_eq!(verify(&key, 0, 0, code.as_ref()), Ok(0));
+        assert!(verify(&key, 1, 5, code.as_ref()).is_err());
+
+        let code = hotp(&key, u64::max_value());
+        assert_eq!(
+            verify_hotp(&key, u64::max_value() - 1, 10, code.as_ref()),
+            Ok(u64::max_value())
+        );
+        let period = NonZeroU64::new(1).unwrap();
+        assert_eq!(
+            verify_totp(&key, period, u64::max_value(), 1, code.as_ref()),
+            Ok(u64::max_value())
+        );
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Password-authenticated key exchange: SPAKE2 and SPAKE2+.
+//!
+//! In [SPAKE2] both parties know the password. In [SPAKE2+] only the prover
+//! (the client) knows the password, and the verifier (the server or device)
+//! only stores `w0` and `L`, derived from it; this is what Matter ("PASE")
+//! device commissioning uses.
+//!
+//! Both derive the values `w`, `w0`, and `w1` from a password using a slow
+//! function, such as `pbkdf2` or `scrypt`, which is the caller's choice. The
+//! functions here reduce them modulo the group order, so they may be up to
+//! twice as long as a scalar; e.g. Matter uses 40-byte values for P-256.
+//!
+//! [SPAKE2]: https://tools.ietf.org/html/rfc9382
+//! [SPAKE2+]: https://tools.ietf.org/html/rfc9383
+
+use crate::{constant_time, digest, ec, error, hkdf, hmac, zeroize};
+
+pub mod spake2;
+pub mod spake2plus;
+
+/// A SPAKE2 or SPAKE2+ cipher suite: a curve, a hash function, HKDF, and
+/// HMAC.
+pub struct Algorithm {
+    curve: &'static ec::suite_b::pake::Curve,
+    digest_alg: &'static digest::Algorithm,
+}
+
+/// P-256, SHA-256, HKDF-SHA256, and HMAC-SHA256.
+pub static P256_SHA256_HKDF_HMAC: Algorithm = Algorithm {
+    curve: &ec::suite_b::pake::P256,
+    digest_alg: &digest::SHA256,
+};
+
+/// P-384, SHA-512, HKDF-SHA512, and HMAC-SHA512.
+pub static P384_SHA512_HKDF_HMAC: Algorithm = Algorithm {
+    curve: &ec::suite_b::pake::P384,
+    digest_alg: &digest::SHA512,
+};
+
+/// An encoded curve point, e.g. a share or SPAKE2+'s `L`, in uncompressed
+/// form.
+#[derive(Clone)]
+pub struct Point {
+    bytes: [u8; ec::PUBLIC_KEY_MAX_LEN],
+    len: usize,
+}
+
+impl Point {
+    fn new(alg: &Algorithm) -> Self {
+        Self {
+            bytes: [0; ec::PUBLIC_KEY_MAX_LEN],
+            len: alg.curve.point_len(),
+        }
+    }
+
+    #[inline]
+    fn as_mut_bytes(&mut self) -> &mut [u8] { &mut self.bytes[..self.len] }
+}
+
+impl AsRef<[u8]> for Point {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+/// The key that both parties share once the exchange is complete, half as
+/// long as the output of the suite's hash function.
+pub struct SharedKey {
+    bytes: [u8; digest::MAX_OUTPUT_LEN / 2],
+    len: usize,
+}
+
+impl AsRef<[u8]> for SharedKey {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+impl Drop for SharedKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.bytes) }
+}
+
+/// Overwrites the bytes with zeros, leaving it empty.
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for SharedKey {
+    fn zeroize(&mut self) {
+        zeroize::zeroize(&mut self.bytes);
+        self.len = 0;
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for SharedKey {}
+
+/// The state of either party after it has received the peer's share: its
+/// own key confirmation message, the one it expects from the peer, and the
+/// shared key.
+pub struct KeyConfirmation {
+    confirmation: hmac::Signature,
+    expected: hmac::Signature,
+    shared_key: SharedKey,
+}
+
+impl KeyConfirmation {
+    /// The key confirmation message to send to the peer.
+    #[inline]
+    pub fn confirmation(&self) -> &[u8] { self.confirmation.as_ref() }
+
+    /// Verifies the peer's key confirmation message, returning the shared
+    /// key if it is valid. A wrong confirmation usually means that the peer
+    /// used a different password.
+    pub fn verify_peer(self, peer_confirmation: &[u8]) -> Result<SharedKey, error::Unspecified> {
+        constant_time::verify_slices_are_equal(self.expected.as_ref(), peer_confirmation)?;
+        Ok(self.shared_key)
+    }
+}
+
+// Computes the keys from the transcript `TT`, the concatenation of `tt` in
+// which each part is prefixed by its length as an 8-byte little-endian
+// integer.
+//
+// `Hash(TT)` is split in two halves, the shared key and `Ka`, in the order
+// given by `shared_key_first`. Then
+// `KcA || KcB = HKDF(nil, Ka, "ConfirmationKeys" || aad)` and the key
+// confirmation messages are `HMAC(KcA, TT)` and `HMAC(KcB, TT)` or, given
+// `shares`, `HMAC(KcA, share_b)` and `HMAC(KcB, share_a)`.
+fn key_confirmation(
+    alg: &Algorithm, tt: &[&[u8]], shared_key_first: bool, aad: &[u8],
+    shares: Option<(&[u8], &[u8])>, is_a: bool,
+) -> KeyConfirmation {
+    let mut ctx = digest::Context::new(alg.digest_alg);
+    for_each_tt_part(tt, |part| ctx.update(part));
+    let hash = ctx.finish();
+    let (first, second) = hash.as_ref().split_at(alg.digest_alg.output_len / 2);
+    let (shared_key, ka) = if shared_key_first {
+        (first, second)
+    } else {
+        (second, first)
+    };
+
+    // HKDF-Expand for an output no longer than the hash is the first block,
+    // `HMAC(PRK, info || 0x01)`.
+    let prk = hkdf::extract(&hmac::SigningKey::new(alg.digest_alg, &[]), ka);
+    let mut ctx = hmac::SigningContext::with_key(&prk);
+    ctx.update(b"ConfirmationKeys");
+    ctx.update(aad);
+    ctx.update(&[1]);
+    let mut kc = [0; digest::MAX_OUTPUT_LEN];
+    let kc = &mut kc[..alg.digest_alg.output_len];
+    kc.copy_from_slice(ctx.sign().as_ref());
+    let (kc_a, kc_b) = kc.split_at(kc.len() / 2);
+
+    let mac = |key: &[u8], share: Option<&[u8]>| {
+        let mut ctx = hmac::SigningContext::with_key(&hmac::SigningKey::new(alg.digest_alg, key));
+        match share {
+            Some(share) => ctx.update(share),
+            None => for_each_tt_part(tt, |part| ctx.update(part)),
+        }
+        ctx.sign()
+    };
+    let confirmation_a = mac(kc_a, shares.map(|(_, share_b)| share_b));
+    let confirmation_b = mac(kc_b, shares.map(|(share_a, _)| share_a));
+    zeroize::zeroize(kc);
+
+    let mut r = SharedKey {
+        bytes: [0; digest::MAX_OUTPUT_LEN / 2],
+        len: shared_key.len(),
+    };
+    r.bytes[..shared_key.len()].copy_from_slice(shared_key);
+    let (confirmation, expected) = if is_a {
+        (confirmation_a, confirmation_b)
+    } else {
+        (confirmation_b, confirmation_a)
+    };
+    KeyConfirmation {
+        confirmation,
+        expected,
+        shared_key: r,
+    }
+}
+
+fn for_each_tt_part(tt: &[&[u8]], mut f: impl FnMut(&[u8])) {
+    for part in tt {
+        f(&(part.len() as u64).to_le_bytes());
+        f(part);
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! [SPAKE2], where both parties know the password.
+//!
+//! `A` is the party that sends `pA = x*G + w*M` and `B` is the party that
+//! sends `pB = y*G + w*N`. Both compute `K = x*(pB - w*N) = y*(pA - w*M)`
+//! and the transcript
+//!
+//! ```text
+//! TT = len(A) || A || len(B) || B || len(pA) || pA || len(pB) || pB
+//!      || len(K) || K || len(w) || w
+//! ```
+//!
+//! where `A` and `B` are the identities, which may be empty, each length is
+//! an 8-byte little-endian integer, the points are encoded uncompressed, and
+//! `w` is encoded big-endian, padded to the length of a scalar. Then
+//! `Ke || Ka = Hash(TT)`, `KcA || KcB = HKDF(nil, Ka, "ConfirmationKeys" ||
+//! AAD)`, `A`'s confirmation is `HMAC(KcA, TT)`, `B`'s confirmation is
+//! `HMAC(KcB, TT)`, and the shared key is `Ke`.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{pake::{self, spake2}, rand};
+//!
+//! # fn exchange() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let alg = &pake::P256_SHA256_HKDF_HMAC;
+//! // In practice, `w` is derived from the password with, e.g., scrypt.
+//! let w = [0x42; 40];
+//!
+//! let a = spake2::Spake2::start_a(alg, &w, &rng)?;
+//! let b = spake2::Spake2::start_b(alg, &w, &rng)?;
+//! let (a_share, b_share) = (a.share().to_vec(), b.share().to_vec());
+//!
+//! let a = a.finish(b"client", b"server", &b_share, b"")?;
+//! let b = b.finish(b"client", b"server", &a_share, b"")?;
+//! let a_confirmation = a.confirmation().to_vec();
+//! let a_key = a.verify_peer(b.confirmation())?;
+//! let b_key = b.verify_peer(&a_confirmation)?;
+//! assert_eq!(a_key.as_ref(), b_key.as_ref());
+//! # Ok(())
+//! # }
+//! # fn main() { exchange().unwrap() }
+//! ```
+//!
+//! [SPAKE2]: https://tools.ietf.org/html/rfc9382
+
+use super::{key_confirmation, Algorithm, KeyConfirmation, Point};
+use crate::{
+    ec::{
+        self,
+        suite_b::pake::{Blinding, Scalar},
+    },
+    error, rand,
+    zeroize::Zeroizing,
+};
+
+/// The state of `A` or `B` after it has computed its share.
+pub struct Spake2 {
+    alg: &'static Algorithm,
+    is_a: bool,
+    x: Zeroizing<Scalar>,
+    w: Zeroizing<Scalar>,
+    share: Point,
+}
+
+impl Spake2 {
+    /// Starts the exchange as `A`, computing `pA`.
+    ///
+    /// `w` is the password-derived value as a big-endian integer, which is
+    /// reduced modulo the group order. It may be up to twice as long as a
+    /// scalar.
+    pub fn start_a(
+        alg: &'static Algorithm, w: &[u8], rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        Self::start(alg, true, w, rng)
+    }
+
+    /// Starts the exchange as `B`, computing `pB`.
+    ///
+    /// `w` is as for `start_a()`.
+    pub fn start_b(
+        alg: &'static Algorithm, w: &[u8], rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        Self::start(alg, false, w, rng)
+    }
+
+    fn start(
+        alg: &'static Algorithm, is_a: bool, w: &[u8], rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        let curve = alg.curve;
+        let w = Zeroizing::new(curve.scalar_reduced(w)?);
+        let x = Zeroizing::new(curve.random_scalar(rng)?);
+        let mut share = Point::new(alg);
+        curve.share(&x, &w, blinding(is_a), share.as_mut_bytes())?;
+        Ok(Self {
+            alg,
+            is_a,
+            x,
+            w,
+            share,
+        })
+    }
+
+    /// This party's share, `pA` or `pB`, to send to the peer.
+    #[inline]
+    pub fn share(&self) -> &[u8] { self.share.as_ref() }
+
+    /// Computes the shared key and the key confirmation messages from the
+    /// peer's share.
+    ///
+    /// `id_a` and `id_b` are the identities of `A` and `B`, either of which
+    /// may be empty, and `aad` is additional data that is bound to the
+    /// confirmation keys.
+    pub fn finish(
+        self, id_a: &[u8], id_b: &[u8], peer_share: &[u8], aad: &[u8],
+    ) -> Result<KeyConfirmation, error::Unspecified> {
+        let curve = self.alg.curve;
+        let peer = curve.unblind(peer_share, &self.w, blinding(!self.is_a))?;
+        let mut k = Zeroizing::new([0; ec::PUBLIC_KEY_MAX_LEN]);
+        let k = &mut k[..curve.point_len()];
+        curve.mul(&self.x, &peer, k)?;
+        let mut w = Zeroizing::new([0; ec::SCALAR_MAX_BYTES]);
+        let w = &mut w[..curve.scalar_len()];
+        curve.scalar_to_bytes(&self.w, w);
+
+        let (p_a, p_b) = if self.is_a {
+            (self.share(), peer_share)
+        } else {
+            (peer_share, self.share())
+        };
+        let tt = [id_a, id_b, p_a, p_b, &k[..], &w[..]];
+        Ok(key_confirmation(self.alg, &tt, true, aad, None, self.is_a))
+    }
+}
+
+// `A` blinds with M and `B` blinds with N.
+fn blinding(is_a: bool) -> Blinding {
+    if is_a {
+        Blinding::M
+    } else {
+        Blinding::N
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::*, *};
+    use crate::test;
+
+    #[test]
+    fn test_spake2() {
+        test::from_file("src/pake/spake2_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let alg = match test_case.consume_string("Curve").as_str() {
+                "P256" => &P256_SHA256_HKDF_HMAC,
+                "P384" => &P384_SHA512_HKDF_HMAC,
+                _ => unreachable!(),
+            };
+            let w = test_case.consume_bytes("w");
+            let x = test_case.consume_bytes("x");
+            let y = test_case.consume_bytes("y");
+            let id_a = test_case.consume_bytes("A");
+            let id_b = test_case.consume_bytes("B");
+            let aad = test_case.consume_bytes("AAD");
+            let p_a = test_case.consume_bytes("pA");
+            let p_b = test_case.consume_bytes("pB");
+            let ke = test_case.consume_bytes("Ke");
+            let c_a = test_case.consume_bytes("cA");
+            let c_b = test_case.consume_bytes("cB");
+
+            let a = Spake2::start_a(alg, &w, &test::rand::FixedSliceRandom { bytes: &x }).unwrap();
+            assert_eq!(a.share(), &p_a[..]);
+            let b = Spake2::start_b(alg, &w, &test::rand::FixedSliceRandom { bytes: &y }).unwrap();
+            assert_eq!(b.share(), &p_b[..]);
+
+            let a = a.finish(&id_a, &id_b, &p_b, &aad).unwrap();
+            assert_eq!(a.confirmation(), &c_a[..]);
+            let b = b.finish(&id_a, &id_b, &p_a, &aad).unwrap();
+            assert_eq!(b.confirmation(), &c_b[..]);
+            assert_eq!(a.verify_peer(&c_b).unwrap().as_ref(), &ke[..]);
+            assert_eq!(b.verify_peer(&c_a).unwrap().as_ref(), &ke[..]);
+
+            Ok(())
+        });
+    }
+    #[test]
+    fn test_spake2_rejects_invalid_shares() {
+        let rng = test::rand::FixedByteRandom { byte: 0x11 };
+        for alg in &[&P256_SHA256_HKDF_HMAC, &P384_SHA512_HKDF_HMAC] {
+            let curve = alg.curve;
+            let w = [0x42; 40];
+
+            // `w*N` unblinds to the point at infinity.
+            let w_scalar = curve.scalar_reduced(&w).unwrap();
+            let mut w_n = Point::new(alg);
+            curve.mul(&w_scalar, &curve.parse(curve.n).unwrap(), w_n.as_mut_bytes()).unwrap();
+
+            let mut not_on_curve = w_n.clone();
+            *not_on_curve.as_mut_bytes().last_mut().unwrap() ^= 1;
+
+            for invalid in &[w_n.as_ref(), not_on_curve.as_ref(), &w_n.as_ref()[1..], &[]] {
+                let a = Spake2::start_a(alg, &w, &rng).unwrap();
+                assert!(a.finish(b"", b"", invalid, b"").is_err());
+            }
+        }
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! [SPAKE2+], where only the prover knows the password.
+//!
+//! The prover derives `w0` and `w1` from the password; the verifier stores
+//! `w0` and `L = w1*G` (`compute_l()`). The prover sends
+//! `X = x*G + w0*M` and the verifier sends `Y = y*G + w0*N`. Both compute
+//! `Z = x*(Y - w0*N) = y*(X - w0*M)`, `V = w1*(Y - w0*N) = y*L`, and the
+//! transcript
+//!
+//! ```text
+//! TT = len(Context) || Context || len(idProver) || idProver
+//!      || len(idVerifier) || idVerifier || len(M) || M || len(N) || N
+//!      || len(X) || X || len(Y) || Y || len(Z) || Z || len(V) || V
+//!      || len(w0) || w0
+//! ```
+//!
+//! where each length is an 8-byte little-endian integer, the points are
+//! encoded uncompressed, and `w0` is encoded big-endian, padded to the length
+//! of a scalar. Then `Ka || Ke = Hash(TT)`,
+//! `KcA || KcB = HKDF(nil, Ka, "ConfirmationKeys")`, the prover's
+//! confirmation is `HMAC(KcA, Y)`, the verifier's confirmation is
+//! `HMAC(KcB, X)`, and the shared key is `Ke`. This is the construction that
+//! Matter's PASE uses, where `Context` is the hash of the commissioning
+//! messages that precede the exchange.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{pake::{self, spake2plus}, rand};
+//!
+//! # fn exchange() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let alg = &pake::P256_SHA256_HKDF_HMAC;
+//! // In practice, `w0s` and `w1s` are derived from the password with, e.g.,
+//! // PBKDF2.
+//! let (w0s, w1s) = ([0x42; 40], [0x43; 40]);
+//!
+//! // Registration: the verifier stores `w0s` and `L`.
+//! let l = spake2plus::compute_l(alg, &w1s)?;
+//!
+//! let prover = spake2plus::Prover::start(alg, &w0s, &w1s, &rng)?;
+//! let verifier = spake2plus::Verifier::start(alg, &w0s, l.as_ref(), &rng)?;
+//! let (x, y) = (prover.share().to_vec(), verifier.share().to_vec());
+//!
+//! let verifier = verifier.finish(b"context", b"", b"", &x)?;
+//! let prover = prover.finish(b"context", b"", b"", &y)?;
+//! let prover_confirmation = prover.confirmation().to_vec();
+//! let prover_key = prover.verify_peer(verifier.confirmation())?;
+//! let verifier_key = verifier.verify_peer(&prover_confirmation)?;
+//! assert_eq!(prover_key.as_ref(), verifier_key.as_ref());
+//! # Ok(())
+//! # }
+//! # fn main() { exchange().unwrap() }
+//! ```
+//!
+//! [SPAKE2+]: https://tools.ietf.org/html/rfc9383
+
+use super::{key_confirmation, Algorithm, KeyConfirmation, Point};
+use crate::{
+    ec::{
+        self,
+        suite_b::pake::{Blinding, Scalar},
+    },
+    error, rand,
+    zeroize::Zeroizing,
+};
+
+/// Computes `L = w1*G`, which the verifier stores along with `w0`.
+///
+/// `w1s` is the password-derived value as a big-endian integer, which is
+/// reduced modulo the group order. It may be up to twice as long as a scalar.
+pub fn compute_l(alg: &'static Algorithm, w1s: &[u8]) -> Result<Point, error::Unspecified> {
+    let w1 = Zeroizing::new(alg.curve.scalar_reduced(w1s)?);
+    let mut l = Point::new(alg);
+    alg.curve.mul_base(&w1, l.as_mut_bytes())?;
+    Ok(l)
+}
+
+/// The prover's state after it has computed its share `X`.
+pub struct Prover {
+    alg: &'static Algorithm,
+    x: Zeroizing<Scalar>,
+    w0: Zeroizing<Scalar>,
+    w1: Zeroizing<Scalar>,
+    share: Point,
+}
+
+impl Prover {
+    /// Starts the exchange, computing `X`.
+    ///
+    /// `w0s` and `w1s` are as for `compute_l()`.
+    pub fn start(
+        alg: &'static Algorithm, w0s: &[u8], w1s: &[u8], rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        let curve = alg.curve;
+        let w0 = Zeroizing::new(curve.scalar_reduced(w0s)?);
+        let w1 = Zeroizing::new(curve.scalar_reduced(w1s)?);
+        let x = Zeroizing::new(curve.random_scalar(rng)?);
+        let mut share = Point::new(alg);
+        curve.share(&x, &w0, Blinding::M, share.as_mut_bytes())?;
+        Ok(Self {
+            alg,
+            x,
+            w0,
+            w1,
+            share,
+        })
+    }
+
+    /// The prover's share `X`, to send to the verifier.
+    #[inline]
+    pub fn share(&self) -> &[u8] { self.share.as_ref() }
+
+    /// Computes the shared key and the key confirmation messages from the
+    /// verifier's share `Y`.
+    ///
+    /// `id_prover` and `id_verifier` may be empty.
+    pub fn finish(
+        self, context: &[u8], id_prover: &[u8], id_verifier: &[u8], verifier_share: &[u8],
+    ) -> Result<KeyConfirmation, error::Unspecified> {
+        let curve = self.alg.curve;
+        let unblinded = curve.unblind(verifier_share, &self.w0, Blinding::N)?;
+        let mut z = Zeroizing::new([0; ec::PUBLIC_KEY_MAX_LEN]);
+        let z = &mut z[..curve.point_len()];
+        curve.mul(&self.x, &unblinded, z)?;
+        let mut v = Zeroizing::new([0; ec::PUBLIC_KEY_MAX_LEN]);
+        let v = &mut v[..curve.point_len()];
+        curve.mul(&self.w1, &unblinded, v)?;
+        Ok(finish(
+            self.alg,
+            context,
+            (id_prover, id_verifier),
+            (self.share(), verifier_share),
+            (z, v),
+            &self.w0,
+            true,
+        ))
+    }
+}
+
+/// The verifier's state after it has computed its share `Y`.
+pub struct Verifier {
+    alg: &'static Algorithm,
+    y: Zeroizing<Scalar>,
+    w0: Zeroizing<Scalar>,
+    l: Point,
+    share: Point,
+}
+
+impl Verifier {
+    /// Starts the exchange, computing `Y`.
+    ///
+    /// `w0s` is as for `Prover::start()` and `l` is from `compute_l()`.
+    pub fn start(
+        alg: &'static Algorithm, w0s: &[u8], l: &[u8], rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        let curve = alg.curve;
+        let _ = curve.parse(l)?;
+        let mut l_copy = Point::new(alg);
+        l_copy.as_mut_bytes().copy_from_slice(l);
+        let w0 = Zeroizing::new(curve.scalar_reduced(w0s)?);
+        let y = Zeroizing::new(curve.random_scalar(rng)?);
+        let mut share = Point::new(alg);
+        curve.share(&y, &w0, Blinding::N, share.as_mut_bytes())?;
+        Ok(Self {
+            alg,
+            y,
+            w0,
+            l: l_copy,
+            share,
+        })
+    }
+
+    /// The verifier's share `Y`, to send to the prover.
+    #[inline]
+    pub fn share(&self) -> &[u8] { self.share.as_ref() }
+
+    /// Computes the shared key and the key confirmation messages from the
+    /// prover's share `X`.
+    ///
+    /// `id_prover` and `id_verifier` may be empty.
+    pub fn finish(
+        self, context: &[u8], id_prover: &[u8], id_verifier: &[u8], prover_share: &[u8],
+    ) -> Result<KeyConfirmation, error::Unspecified> {
+        let curve = self.alg.curve;
+        let unblinded = curve.unblind(prover_share, &self.w0, Blinding::M)?;
+        let mut z = Zeroizing::new([0; ec::PUBLIC_KEY_MAX_LEN]);
+        let z = &mut z[..curve.point_len()];
+        curve.mul(&self.y, &unblinded, z)?;
+        let mut v = Zeroizing::new([0; ec::PUBLIC_KEY_MAX_LEN]);
+        let v = &mut v[..curve.point_len()];
+        curve.mul(&self.y, &curve.parse(self.l.as_ref())?, v)?;
+        Ok(finish(
+            self.alg,
+            context,
+            (id_prover, id_verifier),
+            (prover_share, self.share()),
+            (z, v),
+            &self.w0,
+            false,
+        ))
+    }
+}
+
+fn finish(
+    alg: &Algorithm, context: &[u8], (id_prover, id_verifier): (&[u8], &[u8]),
+    (x, y): (&[u8], &[u8]), (z, v): (&[u8], &[u8]), w0: &Scalar, is_prover: bool,
+) -> KeyConfirmation {
+    let curve = alg.curve;
+    let mut w0_bytes = Zeroizing::new([0; ec::SCALAR_MAX_BYTES]);
+    let w0_bytes = &mut w0_bytes[..curve.scalar_len()];
+    curve.scalar_to_bytes(w0, w0_bytes);
+    let tt = [
+        context,
+        id_prover,
+        id_verifier,
+        curve.m,
+        curve.n,
+        x,
+        y,
+        z,
+        v,
+        &w0_bytes[..],
+    ];
+    key_confirmation(alg, &tt, false, &[], Some((x, y)), is_prover)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::*, *};
+    use crate::test;
+
+    #[test]
+    fn test_spake2plus() {
+        test::from_file("src/pake/spake2plus_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let alg = match test_case.consume_string("Curve").as_str() {
+                "P256" => &P256_SHA256_HKDF_HMAC,
+                "P384" => &P384_SHA512_HKDF_HMAC,
+                _ => unreachable!(),
+            };
+            let w0s = test_case.consume_bytes("w0s");
+            let w1s = test_case.consume_bytes("w1s");
+            let x = test_case.consume_bytes("x");
+            let y = test_case.consume_bytes("y");
+            let context = test_case.consume_bytes("Context");
+            let id_prover = test_case.consume_bytes("idProver");
+            let id_verifier = test_case.consume_bytes("idVerifier");
+            let l = test_case.consume_bytes("L");
+            let share_x = test_case.consume_bytes("X");
+            let share_y = test_case.consume_bytes("Y");
+            let ke = test_case.consume_bytes("Ke");
+            let c_a = test_case.consume_bytes("cA");
+            let c_b = test_case.consume_bytes("cB");
+
+            assert_eq!(compute_l(alg, &w1s).unwrap().as_ref(), &l[..]);
+
+            let rng = test::rand::FixedSliceRandom { bytes: &x };
+            let prover = Prover::start(alg, &w0s, &w1s, &rng).unwrap();
+            assert_eq!(prover.share(), &share_x[..]);
+            let rng = test::rand::FixedSliceRandom { bytes: &y };
+            let verifier = Verifier::start(alg, &w0s, &l, &rng).unwrap();
+            assert_eq!(verifier.share(), &share_y[..]);
+
+            let prover = prover.finish(&context, &id_prover, &id_verifier, &share_y).unwrap();
+            assert_eq!(prover.confirmation(), &c_a[..]);
+            let verifier = verifier.finish(&context, &id_prover, &id_verifier, &share_x).unwrap();
+            assert_eq!(verifier.confirmation(), &c_b[..]);
+            assert_eq!(prover.verify_peer(&c_b).unwrap().as_ref(), &ke[..]);
+            assert_eq!(verifier.verify_peer(&c_a).unwrap().as_ref(), &ke[..]);
+
+            Ok(())
+        });
+    }
+}
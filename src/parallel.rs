@@ -0,0 +1,66 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Spreading independent work across threads, for the `parallel` feature.
+
+use std::thread;
+
+/// Calls `f` on each element of `items`, splitting `items` into contiguous
+/// chunks that are processed by up to `max_threads` threads, or fewer if the
+/// system has fewer CPUs. Each thread calls `init` once to create the state
+/// (e.g. scratch buffers) that it passes to `f`.
+///
+/// A panic in `f` is propagated to the caller once all threads have finished.
+pub(crate) fn for_each_mut<T, S, I, F>(items: &mut [T], max_threads: usize, init: I, f: F)
+where
+    T: Send,
+    I: Fn() -> S + Sync,
+    F: Fn(&mut S, &mut T) + Sync,
+{
+    let available = thread::available_parallelism().map_or(1, |n| n.get());
+    let threads = available.min(max_threads).min(items.len());
+    if threads <= 1 {
+        let mut state = init();
+        items.iter_mut().for_each(|item| f(&mut state, item));
+        return;
+    }
+
+    let chunk_len = (items.len() + threads - 1) / threads;
+    let (init, f) = (&init, &f);
+    thread::scope(|scope| {
+        for chunk in items.chunks_mut(chunk_len) {
+            let _ = scope.spawn(move || {
+                let mut state = init();
+                chunk.iter_mut().for_each(|item| f(&mut state, item));
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_for_each_mut() {
+        for len in 0..20 {
+            for max_threads in 1..6 {
+                let mut items: Vec<usize> = (0..len).collect();
+                for_each_mut(&mut items, max_threads, || 1, |one, x| *x += *one);
+                assert_eq!(items, (1..=len).collect::<Vec<_>>());
+            }
+        }
+    }
+}
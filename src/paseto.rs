@@ -0,0 +1,371 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! [PASETO] version 4 tokens.
+//!
+//! `v4.local` tokens are encrypted and authenticated with a shared
+//! `LocalKey`: the message is encrypted with XChaCha20, and authenticated
+//! with keyed BLAKE2b, under keys derived from the key and a random nonce.
+//! `v4.public` tokens are signed with Ed25519 and aren't encrypted.
+//!
+//! A token may have a footer, e.g. the identifier of the key, which is
+//! authenticated but not encrypted, and may be bound to an implicit
+//! assertion, which is authenticated but isn't part of the token, e.g. the
+//! identifier of the user that the token must be presented by. The caller
+//! must know the footer of a token to decrypt or verify it; `footer()`
+//! returns it, without authenticating it, so that the key can be chosen.
+//!
+//! The message is usually a JSON object of claims, e.g. `exp`, which is
+//! the caller's to encode and check; these functions only protect its bytes.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{paseto, rand, signature};
+//!
+//! # fn paseto() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let claims = br#"{"sub":"alice","exp":"2039-01-01T00:00:00+00:00"}"#;
+//!
+//! let key = paseto::LocalKey::new(&[0x42; paseto::LOCAL_KEY_LEN])?;
+//! let token = paseto::encrypt(&key, claims, b"", b"", &rng)?;
+//! assert!(token.starts_with("v4.local."));
+//! assert_eq!(paseto::decrypt(&key, &token, b"", b"")?, &claims[..]);
+//!
+//! let pkcs8_bytes = signature::Ed25519KeyPair::generate_pkcs8(&rng)?;
+//! let key_pair = signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&pkcs8_bytes))?;
+//! let token = paseto::sign(&key_pair, claims, br#"{"kid":"1"}"#, b"");
+//! assert_eq!(paseto::footer(&token)?, br#"{"kid":"1"}"#);
+//! let public_key = key_pair.public_key_bytes();
+//! assert_eq!(paseto::verify(public_key, &token, br#"{"kid":"1"}"#, b"")?, &claims[..]);
+//! # Ok(())
+//! # }
+//! # fn main() { paseto().unwrap() }
+//! ```
+//!
+//! [PASETO]: https://github.com/paseto-standard/paseto-spec/blob/master/docs/01-Protocol-Versions/Version4.md
+
+use crate::{
+    aead::chacha,
+    blake2b, constant_time, error,
+    io::base64,
+    polyfill::{self, convert::*},
+    rand, signature, zeroize,
+};
+use alloc::{string::String, vec::Vec};
+use untrusted;
+
+/// The length of a `LocalKey`.
+pub const LOCAL_KEY_LEN: usize = 32;
+
+const LOCAL_HEADER: &str = "v4.local.";
+const PUBLIC_HEADER: &str = "v4.public.";
+
+const NONCE_LEN: usize = 32;
+const MAC_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+
+// The length of the output of the encryption key derivation: the key and
+// the XChaCha20 nonce.
+const ENCRYPTION_KEY_AND_NONCE_LEN: usize = chacha::KEY_LEN + 24;
+
+/// A key for `v4.local` tokens.
+pub struct LocalKey {
+    value: [u8; LOCAL_KEY_LEN],
+}
+
+impl LocalKey {
+    /// Constructs a key from its `LOCAL_KEY_LEN` bytes, which must be
+    /// random.
+    pub fn new(value: &[u8]) -> Result<Self, error::Unspecified> {
+        if value.len() != LOCAL_KEY_LEN {
+            return Err(error::Unspecified);
+        }
+        let mut r = Self {
+            value: [0; LOCAL_KEY_LEN],
+        };
+        r.value.copy_from_slice(value);
+        Ok(r)
+    }
+
+    // Returns the XChaCha20 key and nonce, and the authentication key, for
+    // the nonce `n`.
+    fn derive_keys(&self, n: &[u8]) -> (blake2b::Output, blake2b::Output) {
+        let encryption_key_and_nonce = blake2b::keyed(
+            &self.value,
+            ENCRYPTION_KEY_AND_NONCE_LEN,
+            &[b"paseto-encryption-key", n],
+        );
+        let authentication_key =
+            blake2b::keyed(&self.value, MAC_LEN, &[b"paseto-auth-key-for-aead", n]);
+        (encryption_key_and_nonce, authentication_key)
+    }
+}
+
+impl Drop for LocalKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.value) }
+}
+
+/// Encrypts `message` into a `v4.local` token with the footer `footer`,
+/// which is omitted if it is empty, bound to `implicit_assertion`.
+pub fn encrypt(
+    key: &LocalKey, message: &[u8], footer: &[u8], implicit_assertion: &[u8],
+    rng: &rand::SecureRandom,
+) -> Result<String, error::Unspecified> {
+    let mut payload = Vec::with_capacity(NONCE_LEN + message.len() + MAC_LEN);
+    payload.resize(NONCE_LEN, 0);
+    rng.fill(&mut payload)?;
+    payload.extend_from_slice(message);
+
+    let (n, c) = payload.split_at_mut(NONCE_LEN);
+    let (encryption_key_and_nonce, authentication_key) = key.derive_keys(n);
+    xchacha20_xor_in_place(&encryption_key_and_nonce, c);
+    let t = blake2b::keyed(
+        authentication_key.as_ref(),
+        MAC_LEN,
+        &[&pae(&[LOCAL_HEADER.as_bytes(), n, c, footer, implicit_assertion])],
+    );
+    payload.extend_from_slice(t.as_ref());
+
+    Ok(encode(LOCAL_HEADER, &payload, footer))
+}
+
+/// Decrypts the `v4.local` token `token`, whose footer must be `footer`,
+/// with `key`, checking that it is bound to `implicit_assertion`.
+pub fn decrypt(
+    key: &LocalKey, token: &str, footer: &[u8], implicit_assertion: &[u8],
+) -> Result<Vec<u8>, error::Unspecified> {
+    let mut payload = decode(LOCAL_HEADER, token, footer)?;
+    if payload.len() < NONCE_LEN + MAC_LEN {
+        return Err(error::Unspecified);
+    }
+    let mac_start = payload.len() - MAC_LEN;
+    let (n_and_c, t) = payload.split_at_mut(mac_start);
+    let (n, c) = n_and_c.split_at_mut(NONCE_LEN);
+    let (encryption_key_and_nonce, authentication_key) = key.derive_keys(n);
+    let expected_t = blake2b::keyed(
+        authentication_key.as_ref(),
+        MAC_LEN,
+        &[&pae(&[LOCAL_HEADER.as_bytes(), n, c, footer, implicit_assertion])],
+    );
+    constant_time::verify_slices_are_equal(expected_t.as_ref(), t)?;
+
+    xchacha20_xor_in_place(&encryption_key_and_nonce, c);
+    let message_len = c.len();
+    let _ = payload.drain(..NONCE_LEN);
+    payload.truncate(message_len);
+    Ok(payload)
+}
+
+/// Signs `message` into a `v4.public` token with the footer `footer`, which
+/// is omitted if it is empty, bound to `implicit_assertion`.
+pub fn sign(
+    key_pair: &signature::Ed25519KeyPair, message: &[u8], footer: &[u8],
+    implicit_assertion: &[u8],
+) -> String {
+    let signature =
+        key_pair.sign(&pae(&[PUBLIC_HEADER.as_bytes(), message, footer, implicit_assertion]));
+    let mut payload = Vec::with_capacity(message.len() + SIGNATURE_LEN);
+    payload.extend_from_slice(message);
+    payload.extend_from_slice(signature.as_ref());
+    encode(PUBLIC_HEADER, &payload, footer)
+}
+
+/// Verifies the `v4.public` token `token`, whose footer must be `footer`,
+/// with the Ed25519 public key `public_key`, checking that it is bound to
+/// `implicit_assertion`, and returns the message.
+pub fn verify(
+    public_key: &[u8], token: &str, footer: &[u8], implicit_assertion: &[u8],
+) -> Result<Vec<u8>, error::Unspecified> {
+    let mut payload = decode(PUBLIC_HEADER, token, footer)?;
+    if payload.len() < SIGNATURE_LEN {
+        return Err(error::Unspecified);
+    }
+    let message_len = payload.len() - SIGNATURE_LEN;
+    {
+        let (message, sig) = payload.split_at(message_len);
+        signature::verify(
+            &signature::ED25519,
+            untrusted::Input::from(public_key),
+            untrusted::Input::from(&pae(&[
+                PUBLIC_HEADER.as_bytes(),
+                message,
+                footer,
+                implicit_assertion,
+            ])),
+            untrusted::Input::from(sig),
+        )?;
+    }
+    payload.truncate(message_len);
+    Ok(payload)
+}
+
+/// Returns the footer of `token`, which is empty if it has none, without
+/// authenticating it.
+///
+/// This is for choosing the key with which to decrypt or verify the token,
+/// e.g. with a key identifier in the footer. The footer must not be trusted
+/// until the token has been decrypted or verified.
+pub fn footer(token: &str) -> Result<Vec<u8>, error::Unspecified> {
+    let rest = if token.starts_with(LOCAL_HEADER) {
+        &token[LOCAL_HEADER.len()..]
+    } else if token.starts_with(PUBLIC_HEADER) {
+        &token[PUBLIC_HEADER.len()..]
+    } else {
+        return Err(error::Unspecified);
+    };
+    let (_, footer) = split_footer(rest)?;
+    match footer {
+        Some(footer) => base64::decode(footer, &base64::URL_SAFE_NO_PAD),
+        None => Ok(Vec::new()),
+    }
+}
+
+// `header || base64url(payload)`, followed by `"." || base64url(footer)`
+// unless the footer is empty.
+fn encode(header: &str, payload: &[u8], footer: &[u8]) -> String {
+    let mut token = String::from(header);
+    token.push_str(&base64::encode(payload, &base64::URL_SAFE_NO_PAD));
+    if !footer.is_empty() {
+        token.push('.');
+        token.push_str(&base64::encode(footer, &base64::URL_SAFE_NO_PAD));
+    }
+    token
+}
+
+// Returns the payload of `token` after checking its header and that its
+// footer is `expected_footer`.
+fn decode(
+    header: &str, token: &str, expected_footer: &[u8],
+) -> Result<Vec<u8>, error::Unspecified> {
+    if !token.starts_with(header) {
+        return Err(error::Unspecified);
+    }
+    let (payload, footer) = split_footer(&token[header.len()..])?;
+    match footer {
+        Some(footer) => {
+            let footer = base64::decode(footer, &base64::URL_SAFE_NO_PAD)?;
+            if footer.is_empty() {
+                return Err(error::Unspecified);
+            }
+            constant_time::verify_slices_are_equal(&footer, expected_footer)?;
+        },
+        None if !expected_footer.is_empty() => return Err(error::Unspecified),
+        None => (),
+    }
+    base64::decode(payload, &base64::URL_SAFE_NO_PAD)
+}
+
+// Splits the part of a token after its header into the payload and the
+// footer, if any.
+fn split_footer(rest: &str) -> Result<(&str, Option<&str>), error::Unspecified> {
+    let mut parts = rest.splitn(3, '.');
+    let payload = parts.next().ok_or(error::Unspecified)?;
+    let footer = parts.next();
+    if parts.next().is_some() {
+        return Err(error::Unspecified);
+    }
+    Ok((payload, footer))
+}
+
+// The pre-authentication encoding, `PAE()`: the number of pieces and then
+// each piece, each preceded by its length, as 64-bit little-endian integers
+// with the most significant bit cleared.
+fn pae(pieces: &[&[u8]]) -> Vec<u8> {
+    fn le64(n: usize) -> [u8; 8] {
+        (polyfill::u64_from_usize(n) & (u64::max_value() >> 1)).to_le_bytes()
+    }
+
+    let mut out = Vec::with_capacity(8 + pieces.iter().map(|p| 8 + p.len()).sum::<usize>());
+    out.extend_from_slice(&le64(pieces.len()));
+    for piece in pieces {
+        out.extend_from_slice(&le64(piece.len()));
+        out.extend_from_slice(piece);
+    }
+    out
+}
+
+// XChaCha20 with the key and 24-byte nonce `key_and_nonce`, from a counter
+// of zero.
+fn xchacha20_xor_in_place(key_and_nonce: &blake2b::Output, in_out: &mut [u8]) {
+    let (key, nonce) = key_and_nonce.as_ref().split_at(chacha::KEY_LEN);
+    let (hchacha20_nonce, chacha20_nonce) = nonce.split_at(chacha::HCHACHA20_NONCE_LEN);
+    let subkey = chacha::Key::new(&chacha::hchacha20(
+        key.try_into_().unwrap(),
+        hchacha20_nonce.try_into_().unwrap(),
+    ));
+    let mut nonce = [0; chacha::NONCE_LEN];
+    nonce[4..].copy_from_slice(chacha20_nonce);
+    chacha::chacha20_xor_in_place(&subkey, &chacha::make_counter(&nonce, 0), in_out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_paseto() {
+        test::from_file("src/paseto_tests.txt", |section, test_case| {
+            let message = test_case.consume_bytes("Message");
+            let expected_footer = test_case.consume_bytes("Footer");
+            let implicit_assertion = test_case.consume_bytes("ImplicitAssertion");
+            let token = match section {
+                "v4.local" => {
+                    let key = LocalKey::new(&test_case.consume_bytes("Key")).unwrap();
+                    let nonce = test_case.consume_bytes("Nonce");
+                    let expected = test_case.consume_string("Token");
+                    let rng = test::rand::FixedSliceRandom { bytes: &nonce };
+                    let token =
+                        encrypt(&key, &message, &expected_footer, &implicit_assertion, &rng)
+                            .unwrap();
+                    assert_eq!(token, expected);
+                    let decrypted = decrypt(&key, &token, &expected_footer, &implicit_assertion);
+                    assert_eq!(decrypted, Ok(message));
+                    token
+                },
+                "v4.public" => {
+                    let seed = test_case.consume_bytes("Seed");
+                    let public_key = test_case.consume_bytes("PublicKey");
+                    let expected = test_case.consume_string("Token");
+                    let key_pair = signature::Ed25519KeyPair::from_seed_and_public_key(
+                        untrusted::Input::from(&seed),
+                        untrusted::Input::from(&public_key),
+                    )
+                    .unwrap();
+                    let token = sign(&key_pair, &message, &expected_footer, &implicit_assertion);
+                    assert_eq!(token, expected);
+                    let verified =
+                        verify(&public_key, &token, &expected_footer, &implicit_assertion);
+                    assert_eq!(verified, Ok(message));
+                    token
+                },
+                _ => unreachable!(),
+            };
+            assert_eq!(footer(&token), Ok(expected_footer));
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_pae() {
+        // From the examples of the specification's `PAE()`.
+        assert_eq!(pae(&[]), &[0; 8][..]);
+        assert_eq!(pae(&[b""]), &[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0][..]);
+        assert_eq!(
+            pae(&[b"test"]),
+            &[1, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, b't', b'e', b's', b't'][..]
+        );
+    }
+}
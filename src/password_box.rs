@@ -0,0 +1,254 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Password-based encryption of small secrets.
+//!
+//! `protect` encrypts a plaintext, e.g. a private key or an API token, with
+//! a password, into a self-describing blob, and `open` decrypts it. The key
+//! is derived from the password and a random salt with scrypt or
+//! PBKDF2-HMAC-SHA256, and the plaintext is encrypted with
+//! ChaCha20-Poly1305. The blob is
+//!
+//! ```text
+//! "PWB" || version || kdf || kdf parameters || salt || nonce
+//!       || ciphertext || tag
+//! ```
+//!
+//! where `version` is 1; `kdf` is 1 for scrypt, followed by the 1-byte
+//! log2(N) (r = 8 and p = 1), or 2 for PBKDF2-HMAC-SHA256, followed by the
+//! 4-byte big-endian iteration count; the salt is 16 bytes; the nonce is 12
+//! bytes; and everything before the ciphertext is authenticated as the
+//! additional data.
+//!
+//! The KDF parameters are read from the blob, not given to `open`, so that
+//! they can be increased over time; `kdf()` returns them, so that blobs
+//! protected with weaker parameters can be re-protected. To keep a crafted
+//! blob from making `open` do an unbounded amount of work, it fails if
+//! they exceed `MAX_SCRYPT_LOG_N` or `MAX_PBKDF2_ITERATIONS`.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{password_box, rand};
+//!
+//! # fn protect() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! // In practice, use at least `password_box::DEFAULT_KDF`.
+//! let kdf = password_box::Kdf::Scrypt { log_n: 10 };
+//! let blob = password_box::protect(kdf, b"correct horse", b"secret", &rng)?;
+//!
+//! assert_eq!(password_box::kdf(&blob)?, kdf);
+//! assert_eq!(password_box::open(b"correct horse", &blob)?, b"secret");
+//! assert!(password_box::open(b"battery staple", &blob).is_err());
+//! # Ok(())
+//! # }
+//! # fn main() { protect().unwrap() }
+//! ```
+
+use crate::{aead, digest, error, pbkdf2, rand, scrypt, zeroize};
+use alloc::vec::Vec;
+use core::num::NonZeroU32;
+use untrusted;
+
+/// How the key is derived from the password.
+#[allow(variant_size_differences)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kdf {
+    /// scrypt with N = 2**`log_n`, r = 8, and p = 1.
+    Scrypt {
+        /// log2(N), the CPU/memory cost parameter.
+        log_n: u8,
+    },
+
+    /// PBKDF2 with HMAC-SHA256.
+    Pbkdf2HmacSha256 {
+        /// The number of iterations.
+        iterations: NonZeroU32,
+    },
+}
+
+/// scrypt with N = 2**17, which uses 128 MiB and takes a few tenths of a
+/// second.
+pub const DEFAULT_KDF: Kdf = Kdf::Scrypt { log_n: 17 };
+
+/// The largest log2(N) of scrypt that `open` accepts, which uses 1 GiB.
+pub const MAX_SCRYPT_LOG_N: u8 = 20;
+
+/// The largest number of PBKDF2 iterations that `open` accepts.
+pub const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+const MAGIC: &[u8] = b"PWB";
+const VERSION: u8 = 1;
+
+const KDF_SCRYPT: u8 = 1;
+const KDF_PBKDF2_HMAC_SHA256: u8 = 2;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+static AEAD_ALG: &aead::Algorithm = &aead::CHACHA20_POLY1305;
+
+/// Encrypts `plaintext` with a key derived from `password` using `kdf`.
+pub fn protect(
+    kdf: Kdf, password: &[u8], plaintext: &[u8], rng: &rand::SecureRandom,
+) -> Result<Vec<u8>, error::Unspecified> {
+    let mut out = Vec::with_capacity(
+        MAGIC.len() + 2 + 4 + SALT_LEN + NONCE_LEN + plaintext.len() + AEAD_ALG.tag_len(),
+    );
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    match kdf {
+        Kdf::Scrypt { log_n } => {
+            out.push(KDF_SCRYPT);
+            out.push(log_n);
+        },
+        Kdf::Pbkdf2HmacSha256 { iterations } => {
+            out.push(KDF_PBKDF2_HMAC_SHA256);
+            out.extend_from_slice(&iterations.get().to_be_bytes());
+        },
+    }
+    let salt_start = out.len();
+    out.resize(salt_start + SALT_LEN + NONCE_LEN, 0);
+    rng.fill(&mut out[salt_start..])?;
+    let header_len = out.len();
+    let (salt, nonce) = out[salt_start..].split_at(SALT_LEN);
+
+    let mut key = [0; KEY_LEN];
+    derive_key(kdf, salt, password, &mut key)?;
+    let sealing_key = aead::SealingKey::new(AEAD_ALG, &key);
+    zeroize::zeroize(&mut key);
+    let sealing_key = sealing_key?;
+    let nonce = {
+        let mut n = [0; NONCE_LEN];
+        n.copy_from_slice(nonce);
+        n
+    };
+
+    out.extend_from_slice(plaintext);
+    out.resize(out.len() + AEAD_ALG.tag_len(), 0);
+    let (header, in_out) = out.split_at_mut(header_len);
+    let _ = aead::seal_in_place(&sealing_key, &nonce, header, in_out, AEAD_ALG.tag_len())?;
+    Ok(out)
+}
+
+/// Decrypts `blob` with `password`.
+///
+/// Fails if the password is wrong, if `blob` was modified or isn't one that
+/// `protect` returned, or if its KDF parameters are too large.
+pub fn open(password: &[u8], blob: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+    let (kdf, header_len) = parse_header(blob)?;
+    match kdf {
+        Kdf::Scrypt { log_n } if log_n > MAX_SCRYPT_LOG_N => return Err(error::Unspecified),
+        Kdf::Pbkdf2HmacSha256 { iterations } if iterations.get() > MAX_PBKDF2_ITERATIONS => {
+            return Err(error::Unspecified);
+        },
+        _ => (),
+    }
+    let (header, ciphertext_and_tag) = blob.split_at(header_len);
+    if ciphertext_and_tag.len() < AEAD_ALG.tag_len() {
+        return Err(error::Unspecified);
+    }
+    let (salt, nonce) = header[(header_len - SALT_LEN - NONCE_LEN)..].split_at(SALT_LEN);
+
+    let mut key = [0; KEY_LEN];
+    derive_key(kdf, salt, password, &mut key)?;
+    let opening_key = aead::OpeningKey::new(AEAD_ALG, &key);
+    zeroize::zeroize(&mut key);
+    let opening_key = opening_key?;
+
+    let mut in_out = ciphertext_and_tag.to_vec();
+    let plaintext_len = aead::open_in_place(&opening_key, nonce, header, 0, &mut in_out)?.len();
+    in_out.truncate(plaintext_len);
+    Ok(in_out)
+}
+
+/// Returns the KDF parameters with which `blob` was protected.
+pub fn kdf(blob: &[u8]) -> Result<Kdf, error::Unspecified> { Ok(parse_header(blob)?.0) }
+
+// Returns the KDF and the length of the header, through the nonce.
+fn parse_header(blob: &[u8]) -> Result<(Kdf, usize), error::Unspecified> {
+    untrusted::Input::from(blob).read_all(error::Unspecified, |input| {
+        if input.skip_and_get_input(MAGIC.len())? != untrusted::Input::from(MAGIC)
+            || input.read_byte()? != VERSION
+        {
+            return Err(error::Unspecified);
+        }
+        let kdf = match input.read_byte()? {
+            KDF_SCRYPT => Kdf::Scrypt {
+                log_n: input.read_byte()?,
+            },
+            KDF_PBKDF2_HMAC_SHA256 => {
+                let mut iterations = [0; 4];
+                iterations.copy_from_slice(input.skip_and_get_input(4)?.as_slice_less_safe());
+                let iterations = NonZeroU32::new(u32::from_be_bytes(iterations))
+                    .ok_or(error::Unspecified)?;
+                Kdf::Pbkdf2HmacSha256 { iterations }
+            },
+            _ => return Err(error::Unspecified),
+        };
+        let _ = input.skip_and_get_input(SALT_LEN + NONCE_LEN)?;
+        let header_len = blob.len() - input.skip_to_end().len();
+        Ok((kdf, header_len))
+    })
+}
+
+fn derive_key(
+    kdf: Kdf, salt: &[u8], password: &[u8], out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    match kdf {
+        Kdf::Scrypt { log_n } => {
+            let params = scrypt::Params { log_n, r: 8, p: 1 };
+            scrypt::derive(&params, salt, password, out)
+        },
+        Kdf::Pbkdf2HmacSha256 { iterations } => {
+            pbkdf2::derive(&digest::SHA256, iterations, salt, password, out);
+            Ok(())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_password_box() {
+        test::from_file("src/password_box_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let params = match test_case.consume_string("KDF").as_str() {
+                "scrypt" => Kdf::Scrypt {
+                    log_n: test_case.consume_usize("LogN") as u8,
+                },
+                "PBKDF2-HMAC-SHA256" => Kdf::Pbkdf2HmacSha256 {
+                    iterations: NonZeroU32::new(test_case.consume_usize("Iterations") as u32)
+                        .unwrap(),
+                },
+                _ => unreachable!(),
+            };
+            let password = test_case.consume_bytes("Password");
+            let random = test_case.consume_bytes("Random");
+            let plaintext = test_case.consume_bytes("Plaintext");
+            let expected = test_case.consume_bytes("Blob");
+
+            let rng = test::rand::FixedSliceRandom { bytes: &random };
+            let blob = protect(params, &password, &plaintext, &rng).unwrap();
+            assert_eq!(blob, expected);
+            assert_eq!(kdf(&blob), Ok(params));
+            assert_eq!(open(&password, &blob), Ok(plaintext));
+            Ok(())
+        });
+    }
+}
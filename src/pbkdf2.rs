@@ -15,7 +15,8 @@
 //! PBKDF2 derivation and verification.
 //!
 //! Use `derive` to derive PBKDF2 outputs. Use `verify` to verify secret
-//! against previously-derived outputs.
+//! against previously-derived outputs. With the `parallel` feature,
+//! `verify_batch` verifies many secrets at once using multiple threads.
 //!
 //! PBKDF2 is specified in [RFC 2898 Section 5.2] with test vectors given in
 //! [RFC 6070]. See also [NIST Special Publication 800-132].
@@ -112,9 +113,12 @@
 //!     assert!(db.verify_password("alice", "@74d7]404j|W}6u").is_ok());
 //! }
 
-use crate::{constant_time, digest, error, hmac, polyfill};
+use crate::{constant_time, digest, error, hmac, polyfill, zeroize};
 use core::num::NonZeroU32;
 
+#[cfg(feature = "parallel")]
+use {crate::parallel, alloc::vec::Vec};
+
 /// Fills `out` with the key derived using PBKDF2 with the given inputs.
 ///
 /// Do not use `derive` as part of verifying a secret; use `verify` instead, to
@@ -184,6 +188,7 @@ fn derive_block(
 
         u = hmac::sign(secret, u.as_ref());
     }
+    u.zeroize();
 }
 
 /// Verifies that a previously-derived (e.g., using `derive`) PBKDF2 value
@@ -244,6 +249,7 @@ pub fn verify(
 
         matches &= current_block_matches;
     }
+    zeroize::zeroize(&mut derived_buf);
 
     if matches == 0 {
         return Err(error::Unspecified);
@@ -251,3 +257,37 @@ pub fn verify(
 
     Ok(())
 }
+
+/// Verifies each `(salt, secret, previously_derived)` in `batch` like
+/// `verify()` does, spreading the verifications across threads.
+///
+/// The result for `batch[i]` is at index `i` of the returned vector. This is
+/// useful for checking many password hashes at once, e.g. when migrating a
+/// password database, where calling `verify()` for each one on a single
+/// thread is the bottleneck.
+///
+/// # Panics
+///
+/// `verify_batch` panics if any `previously_derived.len()` is larger than
+/// (2**32 - 1) * the digest algorithm's output length, per the PBKDF2
+/// specification.
+#[cfg(feature = "parallel")]
+pub fn verify_batch(
+    digest_alg: &'static digest::Algorithm, iterations: NonZeroU32,
+    batch: &[(&[u8], &[u8], &[u8])],
+) -> Vec<Result<(), error::Unspecified>> {
+    let mut results: Vec<_> = batch
+        .iter()
+        .map(|input| (input, Err(error::Unspecified)))
+        .collect();
+    parallel::for_each_mut(
+        &mut results,
+        usize::max_value(),
+        || (),
+        |_, (input, result)| {
+            let (salt, secret, previously_derived) = **input;
+            *result = verify(digest_alg, iterations, salt, secret, previously_derived);
+        },
+    );
+    results.into_iter().map(|(_, result)| result).collect()
+}
@@ -0,0 +1,629 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Reading PKCS#12 (PFX, `.p12`) files ([RFC 7292]).
+//!
+//! `Pfx::from_der()` verifies the file's password-based MAC, decrypts its
+//! contents, and returns the private keys, as unencrypted PKCS#8 documents
+//! suitable for the key pair types' `from_pkcs8()` constructors, and the
+//! DER-encoded X.509 certificates it contains.
+//!
+//! The MAC may use HMAC with SHA-1, SHA-256, SHA-384, or SHA-512. Encrypted
+//! contents and shrouded key bags must use PBES2, as `pkcs8::decrypt()`
+//! requires; that is what OpenSSL 3 generates by default. The PBES1 schemes
+//! of RFC 7292 Appendix C, which use RC2, RC4, and triple DES, are not
+//! supported, nor is the public-key integrity mode or public-key encryption
+//! of the contents. Files without a MAC are rejected. CRLs, secret bags, and
+//! certificates of types other than X.509 are ignored.
+//!
+//! [RFC 7292]: https://tools.ietf.org/html/rfc7292
+
+use crate::{digest, error, hmac, io::der, pkcs8, zeroize};
+use core::{self, num::NonZeroU32};
+use alloc::vec::Vec;
+use untrusted;
+
+/// The contents of a PKCS#12 file.
+#[derive(Debug)]
+pub struct Pfx {
+    private_keys: Vec<PrivateKey>,
+    certificates: Vec<Certificate>,
+}
+
+impl Pfx {
+    /// Parses the DER-encoded PKCS#12 file `input`, verifying its MAC and
+    /// decrypting its contents using `password`.
+    ///
+    /// A wrong password is reported as `DecryptionFailed`, as is any
+    /// modification of the file's contents. Iteration counts above
+    /// `pkcs8::MAX_ITERATIONS`, for either the MAC or the encryption of the
+    /// contents, are rejected as `TooLarge`.
+    pub fn from_der(input: untrusted::Input, password: &str) -> Result<Self, error::KeyRejected> {
+        let (auth_safe, mac_data) =
+            input.read_all(error::KeyRejected::invalid_encoding(), |input| {
+                der::nested(
+                    input,
+                    der::Tag::Sequence,
+                    error::KeyRejected::invalid_encoding(),
+                    parse_pfx,
+                )
+            })?;
+        let mac_data = mac_data.ok_or_else(error::KeyRejected::wrong_algorithm)?;
+        mac_data.verify(auth_safe, password)?;
+
+        let mut pfx = Self {
+            private_keys: Vec::new(),
+            certificates: Vec::new(),
+        };
+        auth_safe.read_all(error::KeyRejected::invalid_encoding(), |input| {
+            der::nested(
+                input,
+                der::Tag::Sequence,
+                error::KeyRejected::invalid_encoding(),
+                |input| {
+                    while !input.at_end() {
+                        der::nested(
+                            input,
+                            der::Tag::Sequence,
+                            error::KeyRejected::invalid_encoding(),
+                            |input| pfx.read_content_info(input, password),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        })?;
+        Ok(pfx)
+    }
+
+    /// The private keys, in the order they appear in the file.
+    pub fn private_keys(&self) -> &[PrivateKey] { &self.private_keys }
+
+    /// The certificates, in the order they appear in the file.
+    ///
+    /// PKCS#12 doesn't define an order for the certificates. The certificate
+    /// for a private key can be found by comparing their `local_key_id()`s.
+    pub fn certificates(&self) -> &[Certificate] { &self.certificates }
+
+    // Reads a `ContentInfo` of the `AuthenticatedSafe`.
+    fn read_content_info(
+        &mut self, input: &mut untrusted::Reader, password: &str,
+    ) -> Result<(), error::KeyRejected> {
+        let content_type = oid(input)?;
+        let content = der::expect_tag_and_get_value(input, der::Tag::ContextSpecificConstructed0)
+            .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+        if content_type == &DATA_OID[..] {
+            let safe_contents = content.read_all(error::KeyRejected::invalid_encoding(), |input| {
+                der::expect_tag_and_get_value(input, der::Tag::OctetString)
+                    .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+            })?;
+            self.read_safe_contents(safe_contents, password)
+        } else if content_type == &ENCRYPTED_DATA_OID[..] {
+            let safe_contents = content.read_all(error::KeyRejected::invalid_encoding(), |input| {
+                der::nested(
+                    input,
+                    der::Tag::Sequence,
+                    error::KeyRejected::invalid_encoding(),
+                    |input| decrypt_encrypted_data(input, password),
+                )
+            })?;
+            self.read_safe_contents(untrusted::Input::from(&safe_contents), password)
+        } else {
+            Err(error::KeyRejected::wrong_algorithm())
+        }
+    }
+
+    fn read_safe_contents(
+        &mut self, safe_contents: untrusted::Input, password: &str,
+    ) -> Result<(), error::KeyRejected> {
+        safe_contents.read_all(error::KeyRejected::invalid_encoding(), |input| {
+            der::nested(
+                input,
+                der::Tag::Sequence,
+                error::KeyRejected::invalid_encoding(),
+                |input| {
+                    while !input.at_end() {
+                        der::nested(
+                            input,
+                            der::Tag::Sequence,
+                            error::KeyRejected::invalid_encoding(),
+                            |input| self.read_safe_bag(input, password),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        })
+    }
+
+    fn read_safe_bag(
+        &mut self, input: &mut untrusted::Reader, password: &str,
+    ) -> Result<(), error::KeyRejected> {
+        let bag_id = oid(input)?;
+        let bag_value = der::expect_tag_and_get_value(input, der::Tag::ContextSpecificConstructed0)
+            .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+        let local_key_id = if !input.at_end() {
+            der::nested(
+                input,
+                der::Tag::Set,
+                error::KeyRejected::invalid_encoding(),
+                parse_local_key_id,
+            )?
+        } else {
+            None
+        };
+
+        if bag_id == &KEY_BAG_OID[..] {
+            bag_value.read_all(error::KeyRejected::invalid_encoding(), |input| {
+                der::expect_tag_and_get_value(input, der::Tag::Sequence)
+                    .map(|_| ())
+                    .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+            })?;
+            self.private_keys.push(PrivateKey {
+                pkcs8: bag_value.as_slice_less_safe().to_vec(),
+                local_key_id,
+            });
+        } else if bag_id == &PKCS8_SHROUDED_KEY_BAG_OID[..] {
+            self.private_keys.push(PrivateKey {
+                pkcs8: pkcs8::decrypt(bag_value, password.as_bytes())?,
+                local_key_id,
+            });
+        } else if bag_id == &CERT_BAG_OID[..] {
+            let der = bag_value.read_all(error::KeyRejected::invalid_encoding(), |input| {
+                der::nested(
+                    input,
+                    der::Tag::Sequence,
+                    error::KeyRejected::invalid_encoding(),
+                    parse_cert_bag,
+                )
+            })?;
+            if let Some(der) = der {
+                self.certificates.push(Certificate {
+                    der: der.as_slice_less_safe().to_vec(),
+                    local_key_id,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A private key from a PKCS#12 file.
+///
+/// `private_key.as_ref()` is the unencrypted PKCS#8 document.
+pub struct PrivateKey {
+    pkcs8: Vec<u8>,
+    local_key_id: Option<Vec<u8>>,
+}
+
+derive_debug_via_self!(PrivateKey, self.local_key_id);
+
+impl PrivateKey {
+    /// The value of the key's `localKeyId` attribute, if it has one.
+    pub fn local_key_id(&self) -> Option<&[u8]> { self.local_key_id.as_ref().map(|id| &id[..]) }
+}
+
+impl AsRef<[u8]> for PrivateKey {
+    fn as_ref(&self) -> &[u8] { &self.pkcs8 }
+}
+
+impl Drop for PrivateKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.pkcs8) }
+}
+
+/// An X.509 certificate from a PKCS#12 file.
+///
+/// `certificate.as_ref()` is the DER-encoded certificate.
+#[derive(Clone, Debug)]
+pub struct Certificate {
+    der: Vec<u8>,
+    local_key_id: Option<Vec<u8>>,
+}
+
+impl Certificate {
+    /// The value of the certificate's `localKeyId` attribute, if it has one.
+    pub fn local_key_id(&self) -> Option<&[u8]> { self.local_key_id.as_ref().map(|id| &id[..]) }
+}
+
+impl AsRef<[u8]> for Certificate {
+    fn as_ref(&self) -> &[u8] { &self.der }
+}
+
+// 1.2.840.113549.1.7.{1, 6}
+const DATA_OID: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+const ENCRYPTED_DATA_OID: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x06];
+
+// 1.2.840.113549.1.12.10.1.{1, 2, 3}
+const KEY_BAG_OID: [u8; 11] = [
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x01,
+];
+const PKCS8_SHROUDED_KEY_BAG_OID: [u8; 11] = [
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x02,
+];
+const CERT_BAG_OID: [u8; 11] = [
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x03,
+];
+
+// 1.2.840.113549.1.9.21
+const LOCAL_KEY_ID_OID: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x15];
+
+// 1.2.840.113549.1.9.22.1
+const X509_CERTIFICATE_OID: [u8; 10] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x16, 0x01];
+
+// 1.3.14.3.2.26 and 2.16.840.1.101.3.4.2.{1, 2, 3}
+static MAC_DIGESTS: [(&digest::Algorithm, &[u8]); 4] = [
+    (&digest::SHA1, &[0x2b, 0x0e, 0x03, 0x02, 0x1a]),
+    (&digest::SHA256, &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01]),
+    (&digest::SHA384, &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02]),
+    (&digest::SHA512, &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03]),
+];
+
+// The diversifier for deriving the MAC key; RFC 7292 Appendix B.3.
+const MAC_KEY_ID: u8 = 3;
+
+// Returns the content of the `authSafe` and the `macData`, if present.
+fn parse_pfx<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<(untrusted::Input<'a>, Option<MacData<'a>>), error::KeyRejected> {
+    let version = der::small_nonnegative_integer(input)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    if version != 3 {
+        return Err(error::KeyRejected::version_not_supported());
+    }
+    // Only the password integrity mode, where the `authSafe` is of type
+    // `data`, is supported.
+    let auth_safe = der::nested(
+        input,
+        der::Tag::Sequence,
+        error::KeyRejected::invalid_encoding(),
+        |input| {
+            if oid(input)? != &DATA_OID[..] {
+                return Err(error::KeyRejected::wrong_algorithm());
+            }
+            der::nested(
+                input,
+                der::Tag::ContextSpecificConstructed0,
+                error::KeyRejected::invalid_encoding(),
+                |input| {
+                    der::expect_tag_and_get_value(input, der::Tag::OctetString)
+                        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+                },
+            )
+        },
+    )?;
+    let mac_data = if !input.at_end() {
+        Some(der::nested(
+            input,
+            der::Tag::Sequence,
+            error::KeyRejected::invalid_encoding(),
+            MacData::parse,
+        )?)
+    } else {
+        None
+    };
+    Ok((auth_safe, mac_data))
+}
+
+struct MacData<'a> {
+    digest_alg: &'static digest::Algorithm,
+    mac: untrusted::Input<'a>,
+    salt: untrusted::Input<'a>,
+    iterations: NonZeroU32,
+}
+
+impl<'a> MacData<'a> {
+    fn parse(input: &mut untrusted::Reader<'a>) -> Result<Self, error::KeyRejected> {
+        let (digest_alg, mac) = der::nested(
+            input,
+            der::Tag::Sequence,
+            error::KeyRejected::invalid_encoding(),
+            |input| {
+                let digest_alg = der::nested(
+                    input,
+                    der::Tag::Sequence,
+                    error::KeyRejected::invalid_encoding(),
+                    |input| {
+                        let oid = oid(input)?;
+                        // The parameters are supposed to be NULL but some
+                        // encoders omit them.
+                        if !input.at_end() {
+                            let _ = der::expect_tag_and_get_value(input, der::Tag::Null)
+                                .map_err(|error::Unspecified| {
+                                    error::KeyRejected::invalid_encoding()
+                                })?;
+                        }
+                        MAC_DIGESTS
+                            .iter()
+                            .find(|(_, digest_oid)| oid == *digest_oid)
+                            .map(|(alg, _)| *alg)
+                            .ok_or_else(error::KeyRejected::wrong_algorithm)
+                    },
+                )?;
+                let mac = der::expect_tag_and_get_value(input, der::Tag::OctetString)
+                    .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+                Ok((digest_alg, mac))
+            },
+        )?;
+        let salt = der::expect_tag_and_get_value(input, der::Tag::OctetString)
+            .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+        let iterations = if !input.at_end() {
+            pkcs8::pbes2::small_positive_integer(input)?
+        } else {
+            1
+        };
+        let iterations = pkcs8::pbes2::iteration_count(iterations)?;
+        Ok(Self {
+            digest_alg,
+            mac,
+            salt,
+            iterations,
+        })
+    }
+
+    fn verify(
+        &self, auth_safe: untrusted::Input, password: &str,
+    ) -> Result<(), error::KeyRejected> {
+        let mut key = [0u8; digest::MAX_OUTPUT_LEN];
+        let key = &mut key[..self.digest_alg.output_len];
+        derive(
+            self.digest_alg,
+            MAC_KEY_ID,
+            password,
+            self.salt.as_slice_less_safe(),
+            self.iterations,
+            key,
+        );
+        let verification_key = hmac::VerificationKey::new(self.digest_alg, key);
+        zeroize::zeroize(key);
+        hmac::verify(
+            &verification_key,
+            auth_safe.as_slice_less_safe(),
+            self.mac.as_slice_less_safe(),
+        )
+        .map_err(|error::Unspecified| error::KeyRejected::decryption_failed())
+    }
+}
+
+// Decrypts the value of an `EncryptedData` ([RFC 2315 Section 13]),
+// returning the `SafeContents` within it.
+//
+// [RFC 2315 Section 13]: https://tools.ietf.org/html/rfc2315#section-13
+fn decrypt_encrypted_data(
+    input: &mut untrusted::Reader, password: &str,
+) -> Result<Vec<u8>, error::KeyRejected> {
+    let version = der::small_nonnegative_integer(input)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    if version != 0 {
+        return Err(error::KeyRejected::version_not_supported());
+    }
+    der::nested(
+        input,
+        der::Tag::Sequence,
+        error::KeyRejected::invalid_encoding(),
+        |input| {
+            if oid(input)? != &DATA_OID[..] {
+                return Err(error::KeyRejected::invalid_encoding());
+            }
+            let scheme = der::expect_tag_and_get_value(input, der::Tag::Sequence)
+                .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+            // encryptedContent [0] IMPLICIT OCTET STRING. The constructed
+            // encoding is not DER.
+            let (tag, ciphertext) = der::read_tag_and_get_value(input)
+                .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+            if tag != der::CONTEXT_SPECIFIC {
+                return Err(error::KeyRejected::invalid_encoding());
+            }
+            pkcs8::pbes2::decrypt_content(
+                scheme,
+                ciphertext.as_slice_less_safe(),
+                password.as_bytes(),
+            )
+        },
+    )
+}
+
+// Parses the `bagAttributes` and returns the value of the `localKeyId`
+// attribute, if present. Other attributes are ignored.
+fn parse_local_key_id(
+    input: &mut untrusted::Reader,
+) -> Result<Option<Vec<u8>>, error::KeyRejected> {
+    let mut local_key_id = None;
+    while !input.at_end() {
+        der::nested(
+            input,
+            der::Tag::Sequence,
+            error::KeyRejected::invalid_encoding(),
+            |input| {
+                let attr_id = oid(input)?;
+                let values = der::expect_tag_and_get_value(input, der::Tag::Set)
+                    .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+                if attr_id == &LOCAL_KEY_ID_OID[..] {
+                    if local_key_id.is_some() {
+                        return Err(error::KeyRejected::invalid_encoding());
+                    }
+                    let value = values.read_all(error::KeyRejected::invalid_encoding(), |input| {
+                        der::expect_tag_and_get_value(input, der::Tag::OctetString)
+                            .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+                    })?;
+                    local_key_id = Some(value.as_slice_less_safe().to_vec());
+                }
+                Ok(())
+            },
+        )?;
+    }
+    Ok(local_key_id)
+}
+
+// Returns the DER-encoded certificate in a `CertBag`, or `None` if it isn't
+// an X.509 certificate.
+fn parse_cert_bag<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<Option<untrusted::Input<'a>>, error::KeyRejected> {
+    let cert_id = oid(input)?;
+    let cert_value = der::nested(
+        input,
+        der::Tag::ContextSpecificConstructed0,
+        error::KeyRejected::invalid_encoding(),
+        |input| Ok(input.skip_to_end()),
+    )?;
+    if cert_id != &X509_CERTIFICATE_OID[..] {
+        return Ok(None);
+    }
+    let der = cert_value.read_all(error::KeyRejected::invalid_encoding(), |input| {
+        der::expect_tag_and_get_value(input, der::Tag::OctetString)
+            .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+    })?;
+    Ok(Some(der))
+}
+
+fn oid<'a>(input: &mut untrusted::Reader<'a>) -> Result<untrusted::Input<'a>, error::KeyRejected> {
+    der::expect_tag_and_get_value(input, der::Tag::OID)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+}
+
+// The password as a NUL-terminated big-endian UTF-16 `BMPString`, per RFC 7292
+// Appendix B.1.
+fn bmp_string(password: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(2 * (password.len() + 1));
+    for c in password.encode_utf16().chain(core::iter::once(0)) {
+        out.extend_from_slice(&c.to_be_bytes());
+    }
+    out
+}
+
+// The PKCS#12 key derivation function of RFC 7292 Appendix B.2, with the
+// password encoded as a `BMPString` as Appendix B.1 specifies.
+fn derive(
+    digest_alg: &'static digest::Algorithm, id: u8, password: &str, salt: &[u8],
+    iterations: NonZeroU32, out: &mut [u8],
+) {
+    let v = digest_alg.block_len;
+    let diversifier = vec![id; v];
+    let mut password = bmp_string(password);
+
+    // I = S || P, where S and P are the salt and password repeated to a
+    // multiple of `v` bytes. `I` is allocated up front so that no copies of
+    // the password are left behind by reallocations.
+    let padded_len = |len: usize| v * ((len + v - 1) / v);
+    let mut i = Vec::with_capacity(padded_len(salt.len()) + padded_len(password.len()));
+    for value in &[salt, &password[..]] {
+        i.extend(value.iter().cycle().take(padded_len(value.len())));
+    }
+    zeroize::zeroize(&mut password);
+
+    for chunk in out.chunks_mut(digest_alg.output_len) {
+        let mut ctx = digest::Context::new(digest_alg);
+        ctx.update(&diversifier);
+        ctx.update(&i);
+        let mut a = ctx.finish();
+        for _ in 1..iterations.get() {
+            a = digest::digest(digest_alg, a.as_ref());
+        }
+        let a = a.as_ref();
+        chunk.copy_from_slice(&a[..chunk.len()]);
+
+        // I_j = (I_j + B + 1) mod 2**(8 * v) for each `v`-byte block I_j of
+        // I, where B is A repeated to `v` bytes.
+        for block in i.chunks_mut(v) {
+            let mut carry = 1u16;
+            for (k, b) in block.iter_mut().enumerate().rev() {
+                let sum = u16::from(*b) + u16::from(a[k % a.len()]) + carry;
+                *b = sum as u8;
+                carry = sum >> 8;
+            }
+        }
+    }
+    zeroize::zeroize(&mut i);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_derive() {
+        // From OpenSSL's PKCS#12 key derivation test vectors.
+        let vectors: &[(&str, &str, u8, u32, &str)] = &[
+            (
+                "smeg",
+                "0a58cf64530d823f",
+                1,
+                1,
+                "8aaae6297b6cb04642ab5b077851284eb7128f1a2a7fbca3",
+            ),
+            ("smeg", "0a58cf64530d823f", 2, 1, "79993dfe048d3b76"),
+            (
+                "queeg",
+                "05dec959acff72f7",
+                1,
+                1000,
+                "ed2034e36328830ff09df1e1a07dd357185dac0d4f9eb3d4",
+            ),
+        ];
+        for &(password, salt, id, iterations, expected) in vectors {
+            let salt = test::from_hex(salt).unwrap();
+            let expected = test::from_hex(expected).unwrap();
+            let mut out = vec![0u8; expected.len()];
+            derive(
+                &digest::SHA1,
+                id,
+                password,
+                &salt,
+                NonZeroU32::new(iterations).unwrap(),
+                &mut out,
+            );
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn test_bmp_string() {
+        assert_eq!(bmp_string(""), vec![0, 0]);
+        assert_eq!(
+            bmp_string("a\u{e9}\u{1f600}"),
+            vec![0x00, 0x61, 0x00, 0xe9, 0xd8, 0x3d, 0xde, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_mac_iterations_limit() {
+        let max = pkcs8::MAX_ITERATIONS;
+        for &(iterations, expected) in &[
+            (None, Ok(1)),
+            (Some(0), Err("InvalidEncoding")),
+            (Some(max), Ok(max)),
+            (Some(max + 1), Err("TooLarge")),
+            (Some(0xffff_ffff), Err("TooLarge")),
+        ] {
+            let mut mac_data = der::Writer::new();
+            mac_data.write_sequence(|digest_info| {
+                digest_info.write_sequence(|alg| {
+                    alg.write_oid(MAC_DIGESTS[1].1);
+                    alg.write_null();
+                });
+                digest_info.write_octet_string(&[0; 32]);
+            });
+            mac_data.write_octet_string(&[0; 8]);
+            if let Some(iterations) = iterations {
+                mac_data.write_integer(&u32::to_be_bytes(iterations));
+            }
+            let parsed = untrusted::Input::from(mac_data.as_ref())
+                .read_all(error::KeyRejected::invalid_encoding(), MacData::parse)
+                .map(|mac_data| mac_data.iterations.get())
+                .map_err(|e| e.description_());
+            assert_eq!(parsed, expected);
+        }
+    }
+}
@@ -14,12 +14,25 @@
 
 //! PKCS#8 is specified in [RFC 5958].
 //!
+//! Key pairs are constructed from unencrypted PKCS#8 documents using their
+//! `from_pkcs8()` constructors. This module provides the conversion between
+//! those documents and password-encrypted ones.
+//!
 //! [RFC 5958]: https://tools.ietf.org/html/rfc5958.
 
-use crate::{der, ec, error};
+use crate::{ec, error, io::der, zeroize};
 use core;
 use untrusted;
 
+#[cfg(feature = "alloc")]
+pub(crate) mod pbes2;
+
+#[cfg(feature = "alloc")]
+pub use self::pbes2::{
+    decrypt, encrypt, Cipher, Kdf, AES_128_CBC, AES_128_GCM, AES_256_CBC, AES_256_GCM,
+    MAX_ITERATIONS,
+};
+
 pub(crate) enum Version {
     V1Only,
     V1OrV2,
@@ -159,6 +172,22 @@ impl AsRef<[u8]> for Document {
     fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
 }
 
+impl Drop for Document {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.bytes) }
+}
+
+/// Overwrites the bytes with zeros, leaving it empty.
+#[cfg(feature = "zeroize")]
+impl ::zeroize::Zeroize for Document {
+    fn zeroize(&mut self) {
+        zeroize::zeroize(&mut self.bytes);
+        self.len = 0;
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Document {}
+
 pub(crate) fn wrap_key(template: &Template, private_key: &[u8], public_key: &[u8]) -> Document {
     let mut result = Document {
         bytes: [0; ec::PKCS8_DOCUMENT_MAX_LEN],
@@ -0,0 +1,701 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Password-based encryption of PKCS#8 documents.
+//!
+//! An `EncryptedPrivateKeyInfo` ([RFC 5958 Section 3]) is encrypted using
+//! PBES2 ([RFC 8018 Section 6.2]) with either PBKDF2 or scrypt ([RFC 7914])
+//! as the key derivation function and either AES-CBC or AES-GCM ([RFC 5084])
+//! as the cipher. The older PBES1 schemes, which use DES, RC2, and RC4, are
+//! not supported.
+//!
+//! [RFC 5958 Section 3]: https://tools.ietf.org/html/rfc5958#section-3
+//! [RFC 8018 Section 6.2]: https://tools.ietf.org/html/rfc8018#section-6.2
+//! [RFC 7914]: https://tools.ietf.org/html/rfc7914
+//! [RFC 5084]: https://tools.ietf.org/html/rfc5084
+
+use crate::{aead, aes_cbc, digest, error, io::der, pbkdf2, rand, scrypt, zeroize};
+use core::num::NonZeroU32;
+use alloc::vec::Vec;
+use untrusted;
+
+/// A key derivation function for deriving the encryption key from the
+/// password.
+#[derive(Clone, Copy, Debug)]
+pub enum Kdf {
+    /// PBKDF2 ([RFC 8018 Section 5.2]) with HMAC using the digest algorithm
+    /// `prf`.
+    ///
+    /// [RFC 8018 Section 5.2]: https://tools.ietf.org/html/rfc8018#section-5.2
+    Pbkdf2 {
+        /// The digest algorithm used with HMAC as the pseudorandom function.
+        prf: &'static digest::Algorithm,
+
+        /// The iteration count. When decrypting, counts above
+        /// `MAX_ITERATIONS` are rejected.
+        iterations: NonZeroU32,
+    },
+
+    /// scrypt ([RFC 7914]) with the cost parameter N = 2**`log_n`.
+    ///
+    /// When decrypting, parameters that would require more than 1 GiB of
+    /// memory, or more work than `scrypt::MAX_WORK` allows, are rejected.
+    ///
+    /// [RFC 7914]: https://tools.ietf.org/html/rfc7914
+    Scrypt {
+        /// log2(N), where N is the CPU/memory cost parameter.
+        log_n: u8,
+
+        /// The block size parameter.
+        r: u32,
+
+        /// The parallelization parameter.
+        p: u32,
+    },
+}
+
+/// The largest PBKDF2 iteration count that `decrypt()` accepts.
+///
+/// The key is derived before the password can be checked, so without a limit
+/// an untrusted file could make decryption run for hours. The limit is far
+/// above the counts that tools use in practice. PKCS#12 MAC iteration counts
+/// are limited in the same way.
+pub const MAX_ITERATIONS: u32 = 10_000_000;
+
+impl Kdf {
+    fn derive(
+        &self, salt: &[u8], password: &[u8], out: &mut [u8],
+    ) -> Result<(), error::Unspecified> {
+        match *self {
+            Kdf::Pbkdf2 { prf, iterations } => {
+                pbkdf2::derive(prf, iterations, salt, password, out);
+                Ok(())
+            },
+            Kdf::Scrypt { log_n, r, p } => {
+                scrypt::derive(&scrypt::Params { log_n, r, p }, salt, password, out)
+            },
+        }
+    }
+}
+
+/// A cipher for encrypting the PKCS#8 document.
+pub struct Cipher {
+    id: CipherID,
+    oid: &'static [u8],
+    key_len: usize,
+    mode: Mode,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CipherID {
+    AES_128_CBC,
+    AES_256_CBC,
+    AES_128_GCM,
+    AES_256_GCM,
+}
+
+enum Mode {
+    Cbc,
+    Gcm(&'static aead::Algorithm),
+}
+
+derive_debug_via_self!(Cipher, self.id);
+
+impl PartialEq for Cipher {
+    fn eq(&self, other: &Self) -> bool { self.id == other.id }
+}
+
+/// AES-128 in CBC mode with PKCS#7 padding.
+pub static AES_128_CBC: Cipher = Cipher {
+    id: CipherID::AES_128_CBC,
+    oid: &AES_128_CBC_OID,
+    key_len: 16,
+    mode: Mode::Cbc,
+};
+
+/// AES-256 in CBC mode with PKCS#7 padding.
+///
+/// This is what most tools use by default.
+pub static AES_256_CBC: Cipher = Cipher {
+    id: CipherID::AES_256_CBC,
+    oid: &AES_256_CBC_OID,
+    key_len: 32,
+    mode: Mode::Cbc,
+};
+
+/// AES-128 in GCM mode with a 128-bit tag, as specified in [RFC 5084].
+///
+/// [RFC 5084]: https://tools.ietf.org/html/rfc5084
+pub static AES_128_GCM: Cipher = Cipher {
+    id: CipherID::AES_128_GCM,
+    oid: &AES_128_GCM_OID,
+    key_len: 16,
+    mode: Mode::Gcm(&aead::AES_128_GCM),
+};
+
+/// AES-256 in GCM mode with a 128-bit tag, as specified in [RFC 5084].
+///
+/// [RFC 5084]: https://tools.ietf.org/html/rfc5084
+pub static AES_256_GCM: Cipher = Cipher {
+    id: CipherID::AES_256_GCM,
+    oid: &AES_256_GCM_OID,
+    key_len: 32,
+    mode: Mode::Gcm(&aead::AES_256_GCM),
+};
+
+static CIPHERS: [&Cipher; 4] = [&AES_128_CBC, &AES_256_CBC, &AES_128_GCM, &AES_256_GCM];
+
+// 1.2.840.113549.1.5.13
+const PBES2_OID: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0d];
+
+// 1.2.840.113549.1.5.12
+const PBKDF2_OID: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0c];
+
+// 1.3.6.1.4.1.11591.4.11
+const SCRYPT_OID: [u8; 9] = [0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x04, 0x0b];
+
+// 1.2.840.113549.2.{7, 9, 10, 11, 13}
+static PRFS: [(&digest::Algorithm, [u8; 8]); 5] = [
+    (&digest::SHA1, [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x07]),
+    (&digest::SHA256, [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x09]),
+    (&digest::SHA384, [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x0a]),
+    (&digest::SHA512, [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x0b]),
+    (&digest::SHA512_256, [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x0d]),
+];
+
+// 2.16.840.1.101.3.4.1.{2, 42, 6, 46}
+const AES_128_CBC_OID: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x02];
+const AES_256_CBC_OID: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+const AES_128_GCM_OID: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x06];
+const AES_256_GCM_OID: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2e];
+
+const SALT_LEN: usize = 16;
+const GCM_NONCE_LEN: usize = 12;
+const GCM_TAG_LEN: usize = 16;
+const MAX_KEY_LEN: usize = 32;
+
+/// Decrypts an `EncryptedPrivateKeyInfo`, returning the PKCS#8 document
+/// within it.
+///
+/// The result can be passed to the `from_pkcs8()` constructor of the
+/// appropriate key pair type. If `encrypted` is PEM-encoded then it must be
+/// decoded with `pem::decode(input, pem::ENCRYPTED_PRIVATE_KEY)` first.
+///
+/// A wrong password is reported as `DecryptionFailed` when it can be
+/// detected. AES-CBC isn't authenticated, so a wrong password is only
+/// detected when the padding or the structure of the decrypted document is
+/// invalid; AES-GCM detects it reliably. PBKDF2 iteration counts above
+/// `MAX_ITERATIONS` are rejected as `TooLarge`.
+pub fn decrypt(
+    encrypted: untrusted::Input, password: &[u8],
+) -> Result<Vec<u8>, error::KeyRejected> {
+    let (scheme, ciphertext) =
+        encrypted.read_all(error::KeyRejected::invalid_encoding(), |input| {
+            der::nested(
+                input,
+                der::Tag::Sequence,
+                error::KeyRejected::invalid_encoding(),
+                |input| {
+                    let scheme = der::nested(
+                        input,
+                        der::Tag::Sequence,
+                        error::KeyRejected::invalid_encoding(),
+                        parse_scheme,
+                    )?;
+                    let ciphertext = der::expect_tag_and_get_value(input, der::Tag::OctetString)
+                        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+                    Ok((scheme, ciphertext))
+                },
+            )
+        })?;
+    decrypt_(&scheme, ciphertext.as_slice_less_safe(), password)
+}
+
+/// Decrypts `ciphertext`, the content of a PKCS#7 `EncryptedContentInfo`
+/// ([RFC 2315 Section 10.1]), which must be a DER-encoded `SEQUENCE`.
+/// `scheme` is the value of its `contentEncryptionAlgorithm`.
+///
+/// [RFC 2315 Section 10.1]: https://tools.ietf.org/html/rfc2315#section-10.1
+pub(crate) fn decrypt_content(
+    scheme: untrusted::Input, ciphertext: &[u8], password: &[u8],
+) -> Result<Vec<u8>, error::KeyRejected> {
+    let scheme = scheme.read_all(error::KeyRejected::invalid_encoding(), parse_scheme)?;
+    decrypt_(&scheme, ciphertext, password)
+}
+
+fn decrypt_(
+    scheme: &Scheme, ciphertext: &[u8], password: &[u8],
+) -> Result<Vec<u8>, error::KeyRejected> {
+    let mut key = [0u8; MAX_KEY_LEN];
+    let key = &mut key[..scheme.cipher.key_len];
+    let plaintext = decrypt_with_key(scheme, key, ciphertext, password);
+    zeroize::zeroize(key);
+    let mut plaintext = plaintext?;
+
+    // With AES-CBC, a wrong password results in valid padding about once in
+    // every 256 attempts. Catch most of those cases here so that they are
+    // reported consistently.
+    let is_sequence = untrusted::Input::from(&plaintext)
+        .read_all(error::Unspecified, |input| {
+            der::expect_tag_and_get_value(input, der::Tag::Sequence).map(|_| ())
+        })
+        .is_ok();
+    if !is_sequence {
+        zeroize::zeroize(&mut plaintext);
+        return Err(error::KeyRejected::decryption_failed());
+    }
+
+    Ok(plaintext)
+}
+
+// Derives the key into `key` and decrypts `ciphertext` with it.
+fn decrypt_with_key(
+    scheme: &Scheme, key: &mut [u8], ciphertext: &[u8], password: &[u8],
+) -> Result<Vec<u8>, error::KeyRejected> {
+    scheme
+        .kdf
+        .derive(scheme.salt.as_slice_less_safe(), password, key)
+        .map_err(|error::Unspecified| error::KeyRejected::too_large())?;
+
+    let iv = scheme.iv.as_slice_less_safe();
+    Ok(match scheme.cipher.mode {
+        Mode::Cbc => {
+            let key = aes_cbc::Key::new(key)
+                .map_err(|error::Unspecified| error::KeyRejected::unexpected_error())?;
+            let mut iv_block = [0u8; aes_cbc::BLOCK_LEN];
+            iv_block.copy_from_slice(iv);
+            aes_cbc::decrypt_padded(&key, &iv_block, ciphertext)
+                .map_err(|error::Unspecified| error::KeyRejected::decryption_failed())?
+        },
+        Mode::Gcm(aead_alg) => {
+            let key = aead::OpeningKey::new(aead_alg, key)
+                .map_err(|error::Unspecified| error::KeyRejected::unexpected_error())?;
+            let mut in_out = ciphertext.to_vec();
+            let len = aead::open_in_place(&key, iv, &[], 0, &mut in_out)
+                .map_err(|error::Unspecified| error::KeyRejected::decryption_failed())?
+                .len();
+            in_out.truncate(len);
+            in_out
+        },
+    })
+}
+
+/// Encrypts the PKCS#8 document `pkcs8` with a key derived from `password`,
+/// returning the DER-encoded `EncryptedPrivateKeyInfo`.
+///
+/// A random salt and IV are generated using `rng`. The result can be
+/// PEM-encoded with `pem::encode(pem::ENCRYPTED_PRIVATE_KEY, &encrypted)`.
+///
+/// `kdf` should be chosen so that deriving the key is expensive; e.g.
+/// `Kdf::Scrypt { log_n: 17, r: 8, p: 1 }` or PBKDF2-HMAC-SHA256 with at
+/// least 100,000 iterations.
+pub fn encrypt(
+    pkcs8: &[u8], password: &[u8], kdf: &Kdf, cipher: &'static Cipher, rng: &rand::SecureRandom,
+) -> Result<Vec<u8>, error::Unspecified> {
+    let mut salt = [0u8; SALT_LEN];
+    rng.fill(&mut salt)?;
+
+    let mut key = [0u8; MAX_KEY_LEN];
+    let key = &mut key[..cipher.key_len];
+    let encrypted = kdf
+        .derive(&salt, password, key)
+        .and_then(|()| encrypt_with_key(cipher, key, pkcs8, rng));
+    zeroize::zeroize(key);
+    let (cipher_params, ciphertext) = encrypted?;
+
+    // hmacWithSHA1 is the default so DER requires it to be omitted.
+    let prf_oid = match *kdf {
+        Kdf::Pbkdf2 { prf, .. } if prf != &digest::SHA1 => Some(
+            PRFS.iter()
+                .find(|(alg, _)| *alg == prf)
+                .map(|(_, prf_oid)| &prf_oid[..])
+                .ok_or(error::Unspecified)?,
+        ),
+        _ => None,
+    };
+
+    let mut kdf_alg_id = der::Writer::new();
+    kdf_alg_id.write_sequence(|kdf_alg_id| match *kdf {
+        Kdf::Pbkdf2 { iterations, .. } => {
+            kdf_alg_id.write_oid(&PBKDF2_OID);
+            kdf_alg_id.write_sequence(|params| {
+                params.write_octet_string(&salt);
+                params.write_integer(&iterations.get().to_be_bytes());
+                if let Some(prf_oid) = prf_oid {
+                    params.write_sequence(|prf_alg_id| {
+                        prf_alg_id.write_oid(prf_oid);
+                        prf_alg_id.write_null();
+                    });
+                }
+            });
+        },
+        Kdf::Scrypt { log_n, r, p } => {
+            kdf_alg_id.write_oid(&SCRYPT_OID);
+            kdf_alg_id.write_sequence(|params| {
+                params.write_octet_string(&salt);
+                params.write_integer(&(1u32 << log_n).to_be_bytes());
+                params.write_integer(&r.to_be_bytes());
+                params.write_integer(&p.to_be_bytes());
+            });
+        },
+    });
+
+    let mut encrypted = der::Writer::new();
+    encrypted.write_sequence(|encrypted| {
+        encrypted.write_sequence(|alg_id| {
+            alg_id.write_oid(&PBES2_OID);
+            alg_id.write_sequence(|pbes2_params| {
+                pbes2_params.write_raw(kdf_alg_id.as_ref());
+                pbes2_params.write_sequence(|cipher_alg_id| {
+                    cipher_alg_id.write_oid(cipher.oid);
+                    cipher_alg_id.write_raw(cipher_params.as_ref());
+                });
+            });
+        });
+        encrypted.write_octet_string(&ciphertext);
+    });
+    Ok(encrypted.into_bytes())
+}
+
+// Encrypts `pkcs8` with `key`, returning the cipher's parameters and the
+// ciphertext.
+fn encrypt_with_key(
+    cipher: &Cipher, key: &[u8], pkcs8: &[u8], rng: &rand::SecureRandom,
+) -> Result<(der::Writer, Vec<u8>), error::Unspecified> {
+    Ok(match cipher.mode {
+        Mode::Cbc => {
+            let mut iv = [0u8; aes_cbc::BLOCK_LEN];
+            rng.fill(&mut iv)?;
+            let key = aes_cbc::Key::new(key)?;
+            let mut params = der::Writer::new();
+            params.write_octet_string(&iv);
+            (params, aes_cbc::encrypt_padded(&key, &iv, pkcs8))
+        },
+        Mode::Gcm(aead_alg) => {
+            let mut nonce = [0u8; GCM_NONCE_LEN];
+            rng.fill(&mut nonce)?;
+            let key = aead::SealingKey::new(aead_alg, key)?;
+            let mut in_out = Vec::with_capacity(pkcs8.len() + GCM_TAG_LEN);
+            in_out.extend_from_slice(pkcs8);
+            in_out.resize(pkcs8.len() + GCM_TAG_LEN, 0);
+            let len = aead::seal_in_place(&key, &nonce, &[], &mut in_out, GCM_TAG_LEN)?;
+            in_out.truncate(len);
+            let mut params = der::Writer::new();
+            params.write_sequence(|gcm_params| {
+                gcm_params.write_octet_string(&nonce);
+                // The ICV length defaults to 12, so it must be given
+                // explicitly.
+                gcm_params.write_integer(&(GCM_TAG_LEN as u32).to_be_bytes());
+            });
+            (params, in_out)
+        },
+    })
+}
+
+struct Scheme<'a> {
+    kdf: Kdf,
+    salt: untrusted::Input<'a>,
+    cipher: &'static Cipher,
+    iv: untrusted::Input<'a>,
+}
+
+// Parses the value of the `AlgorithmIdentifier` of the encryption scheme.
+fn parse_scheme<'a>(input: &mut untrusted::Reader<'a>) -> Result<Scheme<'a>, error::KeyRejected> {
+    let oid = der::expect_tag_and_get_value(input, der::Tag::OID)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    if oid != &PBES2_OID[..] {
+        return Err(error::KeyRejected::wrong_algorithm());
+    }
+    der::nested(
+        input,
+        der::Tag::Sequence,
+        error::KeyRejected::invalid_encoding(),
+        |input| {
+            let (kdf, salt, key_len) = der::nested(
+                input,
+                der::Tag::Sequence,
+                error::KeyRejected::invalid_encoding(),
+                parse_kdf,
+            )?;
+            let (cipher, iv) = der::nested(
+                input,
+                der::Tag::Sequence,
+                error::KeyRejected::invalid_encoding(),
+                parse_cipher,
+            )?;
+            match key_len {
+                Some(key_len) if key_len as usize != cipher.key_len => {
+                    Err(error::KeyRejected::inconsistent_components())
+                },
+                _ => Ok(Scheme {
+                    kdf,
+                    salt,
+                    cipher,
+                    iv,
+                }),
+            }
+        },
+    )
+}
+
+// Returns the KDF, the salt, and the key length, if one was given.
+fn parse_kdf<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<(Kdf, untrusted::Input<'a>, Option<u32>), error::KeyRejected> {
+    let oid = der::expect_tag_and_get_value(input, der::Tag::OID)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    if oid == &PBKDF2_OID[..] {
+        der::nested(
+            input,
+            der::Tag::Sequence,
+            error::KeyRejected::invalid_encoding(),
+            parse_pbkdf2_params,
+        )
+    } else if oid == &SCRYPT_OID[..] {
+        der::nested(
+            input,
+            der::Tag::Sequence,
+            error::KeyRejected::invalid_encoding(),
+            parse_scrypt_params,
+        )
+    } else {
+        Err(error::KeyRejected::wrong_algorithm())
+    }
+}
+
+// RFC 8018 Appendix A.2.
+fn parse_pbkdf2_params<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<(Kdf, untrusted::Input<'a>, Option<u32>), error::KeyRejected> {
+    // The `otherSource` alternative for the salt is not supported.
+    let salt = der::expect_tag_and_get_value(input, der::Tag::OctetString)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    let iterations = iteration_count(small_positive_integer(input)?)?;
+    let key_len = if input.peek(der::Tag::Integer as u8) {
+        Some(small_positive_integer(input)?)
+    } else {
+        None
+    };
+    let prf = if input.peek(der::Tag::Sequence as u8) {
+        der::nested(
+            input,
+            der::Tag::Sequence,
+            error::KeyRejected::invalid_encoding(),
+            |input| {
+                let oid = der::expect_tag_and_get_value(input, der::Tag::OID)
+                    .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+                // The parameters are supposed to be NULL but some encoders
+                // omit them.
+                if !input.at_end() {
+                    let _ = der::expect_tag_and_get_value(input, der::Tag::Null)
+                        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+                }
+                PRFS.iter()
+                    .find(|(_, prf_oid)| oid == &prf_oid[..])
+                    .map(|(alg, _)| *alg)
+                    .ok_or_else(error::KeyRejected::wrong_algorithm)
+            },
+        )?
+    } else {
+        &digest::SHA1
+    };
+    Ok((Kdf::Pbkdf2 { prf, iterations }, salt, key_len))
+}
+
+// RFC 7914 Section 7.1.
+fn parse_scrypt_params<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<(Kdf, untrusted::Input<'a>, Option<u32>), error::KeyRejected> {
+    let salt = der::expect_tag_and_get_value(input, der::Tag::OctetString)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    let n = small_positive_integer(input)?;
+    if !n.is_power_of_two() {
+        return Err(error::KeyRejected::invalid_encoding());
+    }
+    let log_n = n.trailing_zeros() as u8;
+    let r = small_positive_integer(input)?;
+    let p = small_positive_integer(input)?;
+    let key_len = if !input.at_end() {
+        Some(small_positive_integer(input)?)
+    } else {
+        None
+    };
+    Ok((Kdf::Scrypt { log_n, r, p }, salt, key_len))
+}
+
+// Returns the cipher and the IV (for CBC) or the nonce (for GCM).
+fn parse_cipher<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<(&'static Cipher, untrusted::Input<'a>), error::KeyRejected> {
+    let oid = der::expect_tag_and_get_value(input, der::Tag::OID)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    let cipher: &'static Cipher = CIPHERS
+        .iter()
+        .find(|cipher| oid == cipher.oid)
+        .ok_or_else(error::KeyRejected::wrong_algorithm)?;
+    let iv = match cipher.mode {
+        Mode::Cbc => {
+            let iv = der::expect_tag_and_get_value(input, der::Tag::OctetString)
+                .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+            if iv.len() != aes_cbc::BLOCK_LEN {
+                return Err(error::KeyRejected::invalid_encoding());
+            }
+            iv
+        },
+        // RFC 5084 Section 3.2.
+        Mode::Gcm(_) => der::nested(
+            input,
+            der::Tag::Sequence,
+            error::KeyRejected::invalid_encoding(),
+            |input| {
+                let nonce = der::expect_tag_and_get_value(input, der::Tag::OctetString)
+                    .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+                // The default ICV length of 12 isn't supported.
+                let tag_len = small_positive_integer(input)?;
+                if nonce.len() != GCM_NONCE_LEN || tag_len as usize != GCM_TAG_LEN {
+                    return Err(error::KeyRejected::wrong_algorithm());
+                }
+                Ok(nonce)
+            },
+        )?,
+    };
+    Ok((cipher, iv))
+}
+
+pub(crate) fn small_positive_integer(
+    input: &mut untrusted::Reader,
+) -> Result<u32, error::KeyRejected> {
+    let value = der::positive_integer(input)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    if value.len() > 4 {
+        return Err(error::KeyRejected::too_large());
+    }
+    Ok(value
+        .as_slice_less_safe()
+        .iter()
+        .fold(0, |acc, b| (acc << 8) | u32::from(*b)))
+}
+
+pub(crate) fn iteration_count(iterations: u32) -> Result<NonZeroU32, error::KeyRejected> {
+    if iterations > MAX_ITERATIONS {
+        return Err(error::KeyRejected::too_large());
+    }
+    NonZeroU32::new(iterations).ok_or_else(error::KeyRejected::invalid_encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_small_positive_integer() {
+        for &(value, expected) in &[
+            (1, &[0x02, 0x01, 0x01][..]),
+            (0x7f, &[0x02, 0x01, 0x7f][..]),
+            (0x80, &[0x02, 0x02, 0x00, 0x80][..]),
+            (2048, &[0x02, 0x02, 0x08, 0x00][..]),
+            (0xffff_ffff, &[0x02, 0x05, 0x00, 0xff, 0xff, 0xff, 0xff][..]),
+        ] {
+            let mut writer = der::Writer::new();
+            writer.write_integer(&u32::to_be_bytes(value));
+            assert_eq!(writer.as_ref(), expected);
+            let parsed = untrusted::Input::from(expected)
+                .read_all(error::KeyRejected::invalid_encoding(), small_positive_integer)
+                .unwrap();
+            assert_eq!(parsed, value);
+        }
+    }
+
+    #[test]
+    fn test_pbkdf2_iterations_limit() {
+        for &(iterations, expected) in &[
+            (0, Err("InvalidEncoding")),
+            (1, Ok(1)),
+            (MAX_ITERATIONS, Ok(MAX_ITERATIONS)),
+            (MAX_ITERATIONS + 1, Err("TooLarge")),
+            (0xffff_ffff, Err("TooLarge")),
+        ] {
+            let mut params = der::Writer::new();
+            params.write_octet_string(&[0; SALT_LEN]);
+            params.write_integer(&u32::to_be_bytes(iterations));
+            let parsed = untrusted::Input::from(params.as_ref())
+                .read_all(error::KeyRejected::invalid_encoding(), parse_pbkdf2_params)
+                .map(|(kdf, _, _)| match kdf {
+                    Kdf::Pbkdf2 { iterations, .. } => iterations.get(),
+                    Kdf::Scrypt { .. } => unreachable!(),
+                })
+                .map_err(|e| e.description_());
+            assert_eq!(parsed, expected);
+        }
+    }
+
+    #[test]
+    fn test_scrypt_work_limit() {
+        // N = 2**20 and r = 8 with p = 8 is the most work that's allowed.
+        for &p in &[9, 1 << 20] {
+            let scheme = Scheme {
+                kdf: Kdf::Scrypt { log_n: 20, r: 8, p },
+                salt: untrusted::Input::from(&[0; SALT_LEN]),
+                cipher: &AES_128_GCM,
+                iv: untrusted::Input::from(&[0; GCM_NONCE_LEN]),
+            };
+            assert_eq!(
+                decrypt_(&scheme, &[0; GCM_TAG_LEN], b"password")
+                    .err()
+                    .map(|e| e.description_()),
+                Some("TooLarge")
+            );
+        }
+    }
+
+    #[test]
+    fn test_decrypt_rejects_malformed() {
+        const PASSWORD: &[u8] = b"password";
+        let rng = test::rand::FixedByteRandom { byte: 0x5a };
+        let kdf = Kdf::Pbkdf2 {
+            prf: &digest::SHA256,
+            iterations: NonZeroU32::new(1).unwrap(),
+        };
+        let encrypted = encrypt(&[0x30, 0x00], PASSWORD, &kdf, &AES_128_CBC, &rng).unwrap();
+        assert_eq!(
+            decrypt(untrusted::Input::from(&encrypted), PASSWORD).unwrap(),
+            [0x30, 0x00]
+        );
+
+        // Trailing garbage.
+        let mut trailing = encrypted.clone();
+        trailing.push(0);
+        assert_eq!(
+            decrypt(untrusted::Input::from(&trailing), PASSWORD)
+                .err()
+                .map(|e| e.description_()),
+            Some("InvalidEncoding")
+        );
+
+        // An unsupported encryption scheme (PBES1 with MD5 and DES-CBC).
+        let mut pbes1 = encrypted.clone();
+        let oid_index = pbes1
+            .windows(PBES2_OID.len())
+            .position(|w| w == PBES2_OID)
+            .unwrap();
+        pbes1[oid_index + PBES2_OID.len() - 1] = 0x03;
+        assert_eq!(
+            decrypt(untrusted::Input::from(&pbes1), PASSWORD)
+                .err()
+                .map(|e| e.description_()),
+            Some("WrongAlgorithm")
+        );
+    }
+}
@@ -0,0 +1,685 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Privacy Pass tokens: the issuance protocols of [RFC 9578] and the tokens
+//! of [RFC 9577].
+//!
+//! An origin, e.g. a web site behind a CDN, challenges a client with a
+//! `TokenChallenge`, which `encode_token_challenge()` encodes. The client
+//! gets a token for the challenge from an issuer that it trusts, e.g. after
+//! solving a CAPTCHA or attesting its device, with a blinded token request,
+//! so that the issuer can't link the token to the issuance, and then
+//! redeems the token with the origin.
+//!
+//! There are two token types:
+//!
+//! * `TOKEN_TYPE_VOPRF`, privately verifiable tokens of VOPRF(P-384,
+//!   SHA-384) (see `oprf`). `VoprfIssuer` issues them and verifies them, so
+//!   the origin and the issuer must be the same or share the key. The client
+//!   uses `VoprfTokenRequest`.
+//!
+//! * `TOKEN_TYPE_BLIND_RSA`, publicly verifiable tokens, which are
+//!   RSASSA-PSS signatures with SHA-384 by blind RSA (RSABSSA-SHA384-PSS-
+//!   Deterministic of [RFC 9474]) under 2048-bit keys. `BlindRsaIssuer`
+//!   issues them, anyone with the issuer's `BlindRsaPublicKey` can verify
+//!   them, and the client uses `BlindRsaTokenRequest`. These require the
+//!   `rsa_signing` feature.
+//!
+//! Verifying a token only shows that the issuer issued it for the challenge;
+//! to accept each token only once, the origin must also record the nonces of
+//! the tokens that were redeemed, e.g. for the lifetime of the challenge.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{privacypass, rand};
+//!
+//! # fn issue() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let issuer = privacypass::VoprfIssuer::derive(&[7; 32], b"issuer key 1")?;
+//! let public_key = issuer.public_key().as_ref().to_vec();
+//!
+//! // The origin challenges the client...
+//! let challenge = privacypass::encode_token_challenge(
+//!     privacypass::TOKEN_TYPE_VOPRF,
+//!     b"issuer.example",
+//!     &[],
+//!     b"origin.example",
+//! )?;
+//!
+//! // ...the client requests a token from the issuer for the challenge...
+//! let request = privacypass::VoprfTokenRequest::new(&public_key, &challenge, &rng)?;
+//! let response = issuer.issue(request.as_ref(), &rng)?;
+//! let token = request.finalize(&response)?;
+//!
+//! // ...and the origin verifies it.
+//! let token = privacypass::Token::from_bytes(token.as_ref())?;
+//! issuer.verify(&challenge, &token)?;
+//! # Ok(())
+//! # }
+//! # fn main() { issue().unwrap() }
+//! ```
+//!
+//! [RFC 9474]: https://tools.ietf.org/html/rfc9474
+//! [RFC 9577]: https://tools.ietf.org/html/rfc9577
+//! [RFC 9578]: https://tools.ietf.org/html/rfc9578
+
+use crate::{constant_time, digest, error, oprf, rand};
+use alloc::vec::Vec;
+
+#[cfg(feature = "rsa_signing")]
+use crate::{
+    arithmetic::montgomery::R,
+    bits,
+    io::der,
+    rsa::{self, bigint, signing, verification, RSAEncoding, N},
+    spki,
+};
+#[cfg(feature = "rsa_signing")]
+use untrusted;
+
+/// The token type of privately verifiable tokens, VOPRF(P-384, SHA-384).
+pub const TOKEN_TYPE_VOPRF: u16 = 0x0001;
+
+/// The token type of publicly verifiable tokens, blind RSA with 2048-bit
+/// keys.
+pub const TOKEN_TYPE_BLIND_RSA: u16 = 0x0002;
+
+const NONCE_LEN: usize = 32;
+const CHALLENGE_DIGEST_LEN: usize = digest::SHA256_OUTPUT_LEN;
+const TOKEN_KEY_ID_LEN: usize = digest::SHA256_OUTPUT_LEN;
+
+// `token_type || nonce || challenge_digest || token_key_id`, the part of a
+// token that the authenticator authenticates.
+const TOKEN_INPUT_LEN: usize = 2 + NONCE_LEN + CHALLENGE_DIGEST_LEN + TOKEN_KEY_ID_LEN;
+
+// The lengths of an element and a scalar of P-384.
+const VOPRF_ELEMENT_LEN: usize = 1 + 48;
+const VOPRF_SCALAR_LEN: usize = 48;
+const VOPRF_AUTHENTICATOR_LEN: usize = digest::SHA384_OUTPUT_LEN;
+const VOPRF_REQUEST_LEN: usize = 2 + 1 + VOPRF_ELEMENT_LEN;
+
+static VOPRF_SUITE: &oprf::Suite = &oprf::P384_SHA384;
+
+// The length of the modulus, in bytes, which is also the length of a
+// blinded message, a blind signature, and an authenticator.
+const BLIND_RSA_MODULUS_LEN: usize = 2048 / 8;
+
+/// Encodes a `TokenChallenge` of RFC 9577 Section 2.1, which the origin
+/// sends to the client and with which it verifies the token.
+///
+/// `redemption_context` must be empty or 32 bytes, e.g. random bytes for a
+/// challenge that is only valid once.
+pub fn encode_token_challenge(
+    token_type: u16, issuer_name: &[u8], redemption_context: &[u8], origin_info: &[u8],
+) -> Result<Vec<u8>, error::Unspecified> {
+    if issuer_name.is_empty()
+        || issuer_name.len() > usize::from(u16::max_value())
+        || (!redemption_context.is_empty() && redemption_context.len() != 32)
+        || origin_info.len() > usize::from(u16::max_value())
+    {
+        return Err(error::Unspecified);
+    }
+    let mut challenge =
+        Vec::with_capacity(2 + 2 + issuer_name.len() + 1 + 32 + 2 + origin_info.len());
+    challenge.extend_from_slice(&token_type.to_be_bytes());
+    challenge.extend_from_slice(&(issuer_name.len() as u16).to_be_bytes());
+    challenge.extend_from_slice(issuer_name);
+    challenge.push(redemption_context.len() as u8);
+    challenge.extend_from_slice(redemption_context);
+    challenge.extend_from_slice(&(origin_info.len() as u16).to_be_bytes());
+    challenge.extend_from_slice(origin_info);
+    Ok(challenge)
+}
+
+/// A token: `token_type || nonce || challenge_digest || token_key_id ||
+/// authenticator`.
+#[derive(Clone)]
+pub struct Token {
+    bytes: Vec<u8>,
+}
+
+impl Token {
+    /// Decodes a token of a known type.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, error::Unspecified> {
+        if bytes.len() < 2 {
+            return Err(error::Unspecified);
+        }
+        let authenticator_len = match u16::from_be_bytes([bytes[0], bytes[1]]) {
+            TOKEN_TYPE_VOPRF => VOPRF_AUTHENTICATOR_LEN,
+            TOKEN_TYPE_BLIND_RSA => BLIND_RSA_MODULUS_LEN,
+            _ => return Err(error::Unspecified),
+        };
+        if bytes.len() != TOKEN_INPUT_LEN + authenticator_len {
+            return Err(error::Unspecified);
+        }
+        Ok(Self {
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// The token type.
+    #[inline]
+    pub fn token_type(&self) -> u16 { u16::from_be_bytes([self.bytes[0], self.bytes[1]]) }
+
+    /// The client's random nonce, which the origin can use to detect tokens
+    /// that are redeemed more than once.
+    #[inline]
+    pub fn nonce(&self) -> &[u8] { &self.bytes[2..][..NONCE_LEN] }
+
+    /// The SHA-256 digest of the `TokenChallenge`.
+    #[inline]
+    pub fn challenge_digest(&self) -> &[u8] {
+        &self.bytes[(2 + NONCE_LEN)..][..CHALLENGE_DIGEST_LEN]
+    }
+
+    /// The identifier of the issuer's key, the SHA-256 digest of its public
+    /// key.
+    #[inline]
+    pub fn token_key_id(&self) -> &[u8] {
+        &self.bytes[(TOKEN_INPUT_LEN - TOKEN_KEY_ID_LEN)..TOKEN_INPUT_LEN]
+    }
+
+    /// The authenticator: the output of the VOPRF or the RSA signature.
+    #[inline]
+    pub fn authenticator(&self) -> &[u8] { &self.bytes[TOKEN_INPUT_LEN..] }
+
+    fn token_input(&self) -> &[u8] { &self.bytes[..TOKEN_INPUT_LEN] }
+
+    // Checks that the token is of `token_type`, for the challenge
+    // `token_challenge`, and for the key `token_key_id`.
+    fn check(
+        &self, token_type: u16, token_challenge: &[u8], token_key_id: &[u8],
+    ) -> Result<(), error::Unspecified> {
+        let challenge_digest = digest::digest(&digest::SHA256, token_challenge);
+        if self.token_type() != token_type
+            || self.challenge_digest() != challenge_digest.as_ref()
+            || self.token_key_id() != token_key_id
+        {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+}
+
+impl AsRef<[u8]> for Token {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes }
+}
+
+derive_debug_via_self!(Token, self.nonce());
+
+// `token_type || nonce || challenge_digest || token_key_id` with a random
+// nonce.
+fn token_input(
+    token_type: u16, token_challenge: &[u8], token_key_id: &[u8], rng: &rand::SecureRandom,
+) -> Result<[u8; TOKEN_INPUT_LEN], error::Unspecified> {
+    let mut token_input = [0; TOKEN_INPUT_LEN];
+    let (token_type_bytes, rest) = token_input.split_at_mut(2);
+    let (nonce, rest) = rest.split_at_mut(NONCE_LEN);
+    let (challenge_digest, key_id) = rest.split_at_mut(CHALLENGE_DIGEST_LEN);
+    token_type_bytes.copy_from_slice(&token_type.to_be_bytes());
+    rng.fill(nonce)?;
+    challenge_digest.copy_from_slice(digest::digest(&digest::SHA256, token_challenge).as_ref());
+    key_id.copy_from_slice(token_key_id);
+    Ok(token_input)
+}
+
+// Returns the blinded element or message of a `TokenRequest`,
+// `token_type || truncated_token_key_id || blinded`, after checking the
+// token type and the last byte of the key identifier.
+fn parse_request<'a>(
+    request: &'a [u8], token_type: u16, token_key_id: &[u8], blinded_len: usize,
+) -> Result<&'a [u8], error::Unspecified> {
+    if request.len() != 2 + 1 + blinded_len
+        || request[..2] != token_type.to_be_bytes()
+        || request[2] != token_key_id[TOKEN_KEY_ID_LEN - 1]
+    {
+        return Err(error::Unspecified);
+    }
+    Ok(&request[3..])
+}
+
+fn token_key_id(public_key: &[u8]) -> [u8; TOKEN_KEY_ID_LEN] {
+    let mut id = [0; TOKEN_KEY_ID_LEN];
+    id.copy_from_slice(digest::digest(&digest::SHA256, public_key).as_ref());
+    id
+}
+
+/// An issuer, and verifier, of privately verifiable tokens.
+pub struct VoprfIssuer {
+    key: oprf::PrivateKey,
+    token_key_id: [u8; TOKEN_KEY_ID_LEN],
+}
+
+impl VoprfIssuer {
+    /// Derives the issuer's key from a secret `seed`, which should be 32
+    /// bytes, and a public `info`, like `oprf::PrivateKey::derive()`.
+    pub fn derive(seed: &[u8], info: &[u8]) -> Result<Self, error::Unspecified> {
+        Self::new(oprf::PrivateKey::derive(VOPRF_SUITE, oprf::Mode::Voprf, seed, info)?)
+    }
+
+    /// Generates a random key.
+    pub fn generate(rng: &rand::SecureRandom) -> Result<Self, error::Unspecified> {
+        Self::new(oprf::PrivateKey::generate(VOPRF_SUITE, oprf::Mode::Voprf, rng)?)
+    }
+
+    fn new(key: oprf::PrivateKey) -> Result<Self, error::Unspecified> {
+        let token_key_id = token_key_id(key.public_key().as_ref());
+        Ok(Self { key, token_key_id })
+    }
+
+    /// The public key, a compressed P-384 point, which clients need to
+    /// request tokens.
+    #[inline]
+    pub fn public_key(&self) -> &oprf::Element { self.key.public_key() }
+
+    /// The identifier of the key.
+    #[inline]
+    pub fn token_key_id(&self) -> &[u8] { &self.token_key_id }
+
+    /// Issues a token for the `TokenRequest` `request`, returning the
+    /// `TokenResponse`, `evaluate_msg || evaluate_proof`.
+    ///
+    /// The issuer can't tell which token it issued, or for which challenge.
+    pub fn issue(
+        &self, request: &[u8], rng: &rand::SecureRandom,
+    ) -> Result<Vec<u8>, error::Unspecified> {
+        let blinded_element =
+            parse_request(request, TOKEN_TYPE_VOPRF, &self.token_key_id, VOPRF_ELEMENT_LEN)?;
+        let (evaluated, proof) = self.key.blind_evaluate_with_proof(blinded_element, rng)?;
+        let mut response = Vec::with_capacity(VOPRF_ELEMENT_LEN + 2 * VOPRF_SCALAR_LEN);
+        response.extend_from_slice(evaluated.as_ref());
+        response.extend_from_slice(proof.as_ref());
+        Ok(response)
+    }
+
+    /// Verifies that `token` was issued with this key for the challenge
+    /// `token_challenge`.
+    pub fn verify(&self, token_challenge: &[u8], token: &Token) -> Result<(), error::Unspecified> {
+        token.check(TOKEN_TYPE_VOPRF, token_challenge, &self.token_key_id)?;
+        let expected = self.key.evaluate(token.token_input())?;
+        constant_time::verify_slices_are_equal(expected.as_ref(), token.authenticator())
+    }
+}
+
+derive_debug_via_self!(VoprfIssuer, self.key);
+
+/// A client's request for a privately verifiable token.
+pub struct VoprfTokenRequest {
+    blind: oprf::Blind,
+    public_key: [u8; VOPRF_ELEMENT_LEN],
+    token_input: [u8; TOKEN_INPUT_LEN],
+    request: [u8; VOPRF_REQUEST_LEN],
+}
+
+impl VoprfTokenRequest {
+    /// Constructs a request for a token for the challenge `token_challenge`
+    /// from the issuer with the public key `public_key`.
+    pub fn new(
+        public_key: &[u8], token_challenge: &[u8], rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        if public_key.len() != VOPRF_ELEMENT_LEN {
+            return Err(error::Unspecified);
+        }
+        let token_key_id = token_key_id(public_key);
+        let token_input = token_input(TOKEN_TYPE_VOPRF, token_challenge, &token_key_id, rng)?;
+        let blind = oprf::blind(VOPRF_SUITE, oprf::Mode::Voprf, &token_input, rng)?;
+
+        let mut r = Self {
+            blind,
+            public_key: [0; VOPRF_ELEMENT_LEN],
+            token_input,
+            request: [0; VOPRF_REQUEST_LEN],
+        };
+        r.public_key.copy_from_slice(public_key);
+        r.request[..2].copy_from_slice(&TOKEN_TYPE_VOPRF.to_be_bytes());
+        r.request[2] = token_key_id[TOKEN_KEY_ID_LEN - 1];
+        r.request[3..].copy_from_slice(r.blind.blinded_element().as_ref());
+        Ok(r)
+    }
+
+    /// Verifies the issuer's `TokenResponse` `response` and returns the
+    /// token.
+    pub fn finalize(self, response: &[u8]) -> Result<Token, error::Unspecified> {
+        if response.len() != VOPRF_ELEMENT_LEN + 2 * VOPRF_SCALAR_LEN {
+            return Err(error::Unspecified);
+        }
+        let (evaluated, proof) = response.split_at(VOPRF_ELEMENT_LEN);
+        let authenticator =
+            self.blind.finalize_verifiable(&self.token_input, evaluated, proof, &self.public_key)?;
+        let mut bytes = Vec::with_capacity(TOKEN_INPUT_LEN + VOPRF_AUTHENTICATOR_LEN);
+        bytes.extend_from_slice(&self.token_input);
+        bytes.extend_from_slice(authenticator.as_ref());
+        Ok(Token { bytes })
+    }
+}
+
+impl AsRef<[u8]> for VoprfTokenRequest {
+    /// The `TokenRequest`, to send to the issuer.
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.request }
+}
+
+derive_debug_via_self!(VoprfTokenRequest, self.blind);
+
+/// An issuer's public key for publicly verifiable tokens, with which clients
+/// request tokens and anyone can verify them. Feature: `rsa_signing`.
+#[cfg(feature = "rsa_signing")]
+pub struct BlindRsaPublicKey {
+    key: verification::Key,
+    spki: Vec<u8>,
+    token_key_id: [u8; TOKEN_KEY_ID_LEN],
+}
+
+#[cfg(feature = "rsa_signing")]
+impl BlindRsaPublicKey {
+    /// Parses a public key encoded as an X.509 `SubjectPublicKeyInfo` with
+    /// the `id-RSASSA-PSS` algorithm identifier of RFC 9578 Section 6.5,
+    /// the form in which issuers publish their keys.
+    ///
+    /// The modulus must be 2048 bits and the public exponent at least 65537.
+    pub fn from_spki_der(spki_der: &[u8]) -> Result<Self, error::KeyRejected> {
+        let invalid_encoding = error::KeyRejected::invalid_encoding;
+        let public_key = untrusted::Input::from(spki_der).read_all(invalid_encoding(), |input| {
+            der::nested(input, der::Tag::Sequence, invalid_encoding(), |input| {
+                let alg_id = der::expect_tag_and_get_value(input, der::Tag::Sequence)
+                    .map_err(|error::Unspecified| invalid_encoding())?;
+                if alg_id != untrusted::Input::from(spki::RSASSA_PSS_SHA384) {
+                    return Err(error::KeyRejected::wrong_algorithm());
+                }
+                der::bit_string_with_no_unused_bits(input)
+                    .map_err(|error::Unspecified| invalid_encoding())
+            })
+        })?;
+        let (n, e) = rsa::parse_public_key(public_key)
+            .map_err(|error::Unspecified| invalid_encoding())?;
+        let bits = bits::BitLength::from_usize_bytes(BLIND_RSA_MODULUS_LEN)
+            .map_err(|error::Unspecified| error::KeyRejected::unexpected_error())?;
+        let key = verification::Key::from_modulus_and_exponent(n, e, bits, bits, 65537)?;
+        Ok(Self {
+            key,
+            spki: spki_der.to_vec(),
+            token_key_id: token_key_id(spki_der),
+        })
+    }
+
+    /// The public key as a DER-encoded `SubjectPublicKeyInfo`, the form that
+    /// `from_spki_der()` takes.
+    #[inline]
+    pub fn spki_der(&self) -> &[u8] { &self.spki }
+
+    /// The identifier of the key.
+    #[inline]
+    pub fn token_key_id(&self) -> &[u8] { &self.token_key_id }
+
+    /// Verifies that `token` was issued with this key for the challenge
+    /// `token_challenge`.
+    pub fn verify(&self, token_challenge: &[u8], token: &Token) -> Result<(), error::Unspecified> {
+        token.check(TOKEN_TYPE_BLIND_RSA, token_challenge, &self.token_key_id)?;
+        verification::verify_rsa_key(
+            &verification::RSA_PSS_2048_8192_SHA384,
+            &self.key,
+            untrusted::Input::from(token.token_input()),
+            untrusted::Input::from(token.authenticator()),
+        )
+    }
+}
+
+#[cfg(feature = "rsa_signing")]
+derive_debug_via_self!(BlindRsaPublicKey, self.key);
+
+/// An issuer of publicly verifiable tokens. Feature: `rsa_signing`.
+#[cfg(feature = "rsa_signing")]
+pub struct BlindRsaIssuer {
+    key_pair: signing::KeyPair,
+    public_key: BlindRsaPublicKey,
+}
+
+#[cfg(feature = "rsa_signing")]
+impl BlindRsaIssuer {
+    /// Constructs an issuer with the key pair `key_pair`, whose modulus must
+    /// be 2048 bits.
+    pub fn new(key_pair: signing::KeyPair) -> Result<Self, error::KeyRejected> {
+        let rsa_public_key = key_pair.public_key();
+        let spki = spki::PublicKey::new(spki::RSASSA_PSS_SHA384, rsa_public_key.as_ref());
+        let public_key = BlindRsaPublicKey::from_spki_der(spki.to_spki_der().as_ref())?;
+        Ok(Self {
+            key_pair,
+            public_key,
+        })
+    }
+
+    /// The public key, to publish for clients and verifiers.
+    #[inline]
+    pub fn public_key(&self) -> &BlindRsaPublicKey { &self.public_key }
+
+    /// Issues a token for the `TokenRequest` `request`, returning the
+    /// `TokenResponse`, the blind signature.
+    ///
+    /// The issuer can't tell which token it issued, or for which challenge.
+    pub fn issue(&self, request: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+        let blinded_msg = parse_request(
+            request,
+            TOKEN_TYPE_BLIND_RSA,
+            &self.public_key.token_key_id,
+            BLIND_RSA_MODULUS_LEN,
+        )?;
+        // RFC 9474 Section 4.2, `BlindSign`; `private_operation()` also
+        // verifies the result.
+        let mut blind_sig = blinded_msg.to_vec();
+        self.key_pair.private_operation(&mut blind_sig)?;
+        Ok(blind_sig)
+    }
+}
+
+#[cfg(feature = "rsa_signing")]
+derive_debug_via_self!(BlindRsaIssuer, self.public_key);
+
+/// A client's request for a publicly verifiable token. Feature:
+/// `rsa_signing`.
+#[cfg(feature = "rsa_signing")]
+pub struct BlindRsaTokenRequest<'a> {
+    public_key: &'a BlindRsaPublicKey,
+    inverse: bigint::Elem<N, R>,
+    token_input: [u8; TOKEN_INPUT_LEN],
+    request: [u8; 2 + 1 + BLIND_RSA_MODULUS_LEN],
+}
+
+#[cfg(feature = "rsa_signing")]
+impl<'a> BlindRsaTokenRequest<'a> {
+    /// Constructs a request for a token for the challenge `token_challenge`
+    /// from the issuer with the public key `public_key`.
+    pub fn new(
+        public_key: &'a BlindRsaPublicKey, token_challenge: &[u8], rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        let token_input =
+            token_input(TOKEN_TYPE_BLIND_RSA, token_challenge, &public_key.token_key_id, rng)?;
+
+        // RFC 9474 Section 4.1, `Blind`.
+        let n = &public_key.key.n;
+        let mut encoded_msg = [0; BLIND_RSA_MODULUS_LEN];
+        let msg_hash = digest::digest(&digest::SHA384, &token_input);
+        rsa::RSA_PSS_SHA384.encode(&msg_hash, &mut encoded_msg, public_key.key.n_bits, rng)?;
+        let m = bigint::Elem::from_be_bytes_padded(untrusted::Input::from(&encoded_msg), n)?;
+        // `m` and `n` are coprime if and only if `m` has an inverse.
+        let _ = bigint::elem_inverse_blinded(&m, n, rng)?;
+        let r = bigint::elem_random(n, rng)?;
+        let inverse = bigint::elem_inverse_blinded(&r, n, rng)?;
+        let inverse = bigint::elem_mul(n.oneRR().as_ref(), inverse, n);
+        let x = bigint::elem_exp_vartime(r, public_key.key.e, n);
+        let z = bigint::elem_mul(&x, m, n);
+
+        let mut request = [0; 2 + 1 + BLIND_RSA_MODULUS_LEN];
+        request[..2].copy_from_slice(&TOKEN_TYPE_BLIND_RSA.to_be_bytes());
+        request[2] = public_key.token_key_id[TOKEN_KEY_ID_LEN - 1];
+        z.fill_be_bytes(&mut request[3..]);
+        Ok(Self {
+            public_key,
+            inverse,
+            token_input,
+            request,
+        })
+    }
+
+    /// Unblinds and verifies the issuer's `TokenResponse` `response` and
+    /// returns the token.
+    pub fn finalize(self, response: &[u8]) -> Result<Token, error::Unspecified> {
+        if response.len() != BLIND_RSA_MODULUS_LEN {
+            return Err(error::Unspecified);
+        }
+
+        // RFC 9474 Section 4.3, `Finalize`.
+        let n = &self.public_key.key.n;
+        let z = bigint::Elem::from_be_bytes_padded(untrusted::Input::from(response), n)?;
+        let s = bigint::elem_mul(&self.inverse, z, n);
+        let mut bytes = vec![0; TOKEN_INPUT_LEN + BLIND_RSA_MODULUS_LEN];
+        bytes[..TOKEN_INPUT_LEN].copy_from_slice(&self.token_input);
+        s.fill_be_bytes(&mut bytes[TOKEN_INPUT_LEN..]);
+        verification::verify_rsa_key(
+            &verification::RSA_PSS_2048_8192_SHA384,
+            &self.public_key.key,
+            untrusted::Input::from(&self.token_input),
+            untrusted::Input::from(&bytes[TOKEN_INPUT_LEN..]),
+        )?;
+        Ok(Token { bytes })
+    }
+}
+
+#[cfg(feature = "rsa_signing")]
+impl AsRef<[u8]> for BlindRsaTokenRequest<'_> {
+    /// The `TokenRequest`, to send to the issuer.
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.request }
+}
+
+#[cfg(feature = "rsa_signing")]
+derive_debug_via_self!(BlindRsaTokenRequest<'_>, self.public_key);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_privacypass() {
+        test::from_file("src/privacypass_tests.txt", |section, test_case| {
+            match section {
+                "VOPRF(P-384, SHA-384)" => test_voprf(test_case),
+                "BlindRSA(2048, SHA-384, PSS, Deterministic)" => test_blind_rsa(test_case),
+                _ => unreachable!(),
+            }
+            Ok(())
+        });
+    }
+
+    fn test_voprf(test_case: &mut test::TestCase) {
+        let seed = test_case.consume_bytes("Seed");
+        let info = test_case.consume_bytes("KeyInfo");
+        let public_key = test_case.consume_bytes("pkS");
+        let challenge = test_case.consume_bytes("TokenChallenge");
+        let nonce = test_case.consume_bytes("Nonce");
+        let blind = test_case.consume_bytes("Blind");
+        let expected_request = test_case.consume_bytes("TokenRequest");
+        let proof_random = test_case.consume_bytes("ProofRandomScalar");
+        let expected_response = test_case.consume_bytes("TokenResponse");
+        let expected_token = test_case.consume_bytes("Token");
+
+        let issuer = VoprfIssuer::derive(&seed, &info).unwrap();
+        assert_eq!(issuer.public_key().as_ref(), &public_key[..]);
+
+        let random: [&[u8]; 2] = [&nonce, &blind];
+        let rng = test::rand::FixedSliceSequenceRandom::new(&random);
+        let request = VoprfTokenRequest::new(&public_key, &challenge, &rng).unwrap();
+        assert_eq!(request.as_ref(), &expected_request[..]);
+
+        let rng = test::rand::FixedSliceRandom {
+            bytes: &proof_random,
+        };
+        let response = issuer.issue(request.as_ref(), &rng).unwrap();
+        assert_eq!(response, expected_response);
+
+        let token = request.finalize(&response).unwrap();
+        assert_eq!(token.as_ref(), &expected_token[..]);
+        let token = Token::from_bytes(&expected_token).unwrap();
+        assert_eq!(token.token_type(), TOKEN_TYPE_VOPRF);
+        assert_eq!(token.nonce(), &nonce[..]);
+        assert!(issuer.verify(&challenge, &token).is_ok());
+        assert!(issuer.verify(b"another challenge", &token).is_err());
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    fn test_blind_rsa(test_case: &mut test::TestCase) {
+        let spki = test_case.consume_bytes("pkS");
+        let challenge = test_case.consume_bytes("TokenChallenge");
+        let nonce = test_case.consume_bytes("Nonce");
+        let salt = test_case.consume_bytes("Salt");
+        let blind = test_case.consume_bytes("Blind");
+        let expected_request = test_case.consume_bytes("TokenRequest");
+        let expected_response = test_case.consume_bytes("TokenResponse");
+        let expected_token = test_case.consume_bytes("Token");
+
+        let key_pair = signing::KeyPair::from_der(untrusted::Input::from(include_bytes!(
+            "rsa/signature_rsa_example_private_key.der"
+        )))
+        .unwrap();
+        let issuer = BlindRsaIssuer::new(key_pair).unwrap();
+        assert_eq!(issuer.public_key().spki_der(), &spki[..]);
+        let public_key = BlindRsaPublicKey::from_spki_der(&spki).unwrap();
+
+        // The random values that blind the inversions don't change the
+        // result, so `blind` is reused for them.
+        let random: [&[u8]; 5] = [&nonce, &salt, &blind, &blind, &blind];
+        let rng = test::rand::FixedSliceSequenceRandom::new(&random);
+        let request = BlindRsaTokenRequest::new(&public_key, &challenge, &rng).unwrap();
+        assert_eq!(request.as_ref(), &expected_request[..]);
+
+        let response = issuer.issue(request.as_ref()).unwrap();
+        assert_eq!(response, expected_response);
+
+        let token = request.finalize(&response).unwrap();
+        assert_eq!(token.as_ref(), &expected_token[..]);
+        let token = Token::from_bytes(&expected_token).unwrap();
+        assert_eq!(token.token_type(), TOKEN_TYPE_BLIND_RSA);
+        assert!(public_key.verify(&challenge, &token).is_ok());
+        assert!(public_key.verify(b"another challenge", &token).is_err());
+    }
+
+    #[cfg(not(feature = "rsa_signing"))]
+    fn test_blind_rsa(test_case: &mut test::TestCase) {
+        for key in &[
+            "pkS",
+            "TokenChallenge",
+            "Nonce",
+            "Salt",
+            "Blind",
+            "TokenRequest",
+            "TokenResponse",
+            "Token",
+        ] {
+            let _ = test_case.consume_bytes(key);
+        }
+    }
+
+    #[test]
+    fn test_encode_token_challenge() {
+        assert_eq!(
+            encode_token_challenge(TOKEN_TYPE_BLIND_RSA, b"a", &[], b"bc").unwrap(),
+            &[0x00, 0x02, 0x00, 0x01, b'a', 0x00, 0x00, 0x02, b'b', b'c'][..]
+        );
+        assert!(encode_token_challenge(TOKEN_TYPE_VOPRF, b"", &[], &[]).is_err());
+        assert!(encode_token_challenge(TOKEN_TYPE_VOPRF, b"a", &[0; 31], &[]).is_err());
+        assert!(encode_token_challenge(TOKEN_TYPE_VOPRF, b"a", &[0; 32], &[]).is_ok());
+    }
+}
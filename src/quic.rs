@@ -0,0 +1,387 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! QUIC packet protection keys, as specified in [RFC 9001] for QUIC version
+//! 1 and in [RFC 9369] for QUIC version 2.
+//!
+//! A `Secret` is a TLS 1.3 traffic secret of one direction of a
+//! connection: either an Initial secret derived from the client's first
+//! Destination Connection ID by `initial_secrets()`, or a secret exported from
+//! the TLS handshake. The packet protection key and IV and the header
+//! protection key are derived from it with `HKDF-Expand-Label`. At a key
+//! update, `Secret::next()` derives the secret of the next key phase; the
+//! header protection key isn't updated.
+//!
+//! ```
+//! use ring::{aead, quic};
+//!
+//! # fn protect() -> Result<(), ring::error::Unspecified> {
+//! let connection_id = [0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
+//! let secrets = quic::initial_secrets(&quic::V1, &connection_id)?;
+//!
+//! let packet_key = secrets.client.packet_key();
+//! let header = [0xc3, 0x00, 0x00, 0x00, 0x01];
+//! let mut in_out = b"CRYPTO frames\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0".to_vec();
+//! let nonce = packet_key.nonce(2);
+//! let _ = aead::seal_in_place(&packet_key.sealing_key()?, &nonce, &header, &mut in_out, 16)?;
+//!
+//! let packet_key = quic::initial_secrets(&quic::V1, &connection_id)?.client.packet_key();
+//! let opening_key = packet_key.opening_key()?;
+//! let plaintext = aead::open_in_place(&opening_key, &nonce, &header, 0, &mut in_out)?;
+//! assert_eq!(plaintext, b"CRYPTO frames");
+//! # Ok(())
+//! # }
+//! # fn main() { protect().unwrap() }
+//! ```
+//!
+//! [RFC 9001]: https://tools.ietf.org/html/rfc9001
+//! [RFC 9369]: https://tools.ietf.org/html/rfc9369
+
+use crate::{
+    aead, digest, error, hkdf, hmac,
+    tls13::{self, expand_label},
+    zeroize,
+};
+
+/// A QUIC version's Initial salt and packet protection labels.
+pub struct Version {
+    initial_salt: [u8; INITIAL_SALT_LEN],
+    key_label: &'static [u8],
+    iv_label: &'static [u8],
+    hp_label: &'static [u8],
+    ku_label: &'static [u8],
+}
+
+/// QUIC version 1 (RFC 9001 Section 5).
+pub static V1: Version = Version {
+    initial_salt: [
+        0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c,
+        0xad, 0xcc, 0xbb, 0x7f, 0x0a,
+    ],
+    key_label: b"quic key",
+    iv_label: b"quic iv",
+    hp_label: b"quic hp",
+    ku_label: b"quic ku",
+};
+
+/// QUIC version 2 (RFC 9369 Section 3.3).
+pub static V2: Version = Version {
+    initial_salt: [
+        0x0d, 0xed, 0xe3, 0xde, 0xf7, 0x00, 0xa6, 0xdb, 0x81, 0x93, 0x81, 0xbe, 0x6e, 0x26, 0x9d,
+        0xcb, 0xf9, 0xbd, 0x2e, 0xd9,
+    ],
+    key_label: b"quicv2 key",
+    iv_label: b"quicv2 iv",
+    hp_label: b"quicv2 hp",
+    ku_label: b"quicv2 ku",
+};
+
+/// A TLS 1.3 cipher suite: the AEAD that protects packets and the hash of
+/// `HKDF-Expand-Label`.
+pub struct CipherSuite {
+    aead_algorithm: &'static aead::Algorithm,
+    digest_algorithm: &'static digest::Algorithm,
+}
+
+/// `TLS_AES_128_GCM_SHA256`, the cipher suite of the Initial secrets.
+pub static TLS13_AES_128_GCM_SHA256: CipherSuite = CipherSuite {
+    aead_algorithm: &aead::AES_128_GCM,
+    digest_algorithm: &digest::SHA256,
+};
+
+/// `TLS_AES_256_GCM_SHA384`.
+pub static TLS13_AES_256_GCM_SHA384: CipherSuite = CipherSuite {
+    aead_algorithm: &aead::AES_256_GCM,
+    digest_algorithm: &digest::SHA384,
+};
+
+/// `TLS_CHACHA20_POLY1305_SHA256`.
+pub static TLS13_CHACHA20_POLY1305_SHA256: CipherSuite = CipherSuite {
+    aead_algorithm: &aead::CHACHA20_POLY1305,
+    digest_algorithm: &digest::SHA256,
+};
+
+impl CipherSuite {
+    /// The AEAD that protects packets. Headers are protected with the block
+    /// cipher or stream cipher of the same AEAD.
+    #[inline]
+    pub fn aead_algorithm(&self) -> &'static aead::Algorithm { self.aead_algorithm }
+
+    /// The hash of the TLS 1.3 key schedule, whose length is also the length
+    /// of the secrets.
+    #[inline]
+    pub fn digest_algorithm(&self) -> &'static digest::Algorithm { self.digest_algorithm }
+}
+
+/// The longest connection ID, in bytes.
+pub const MAX_CONNECTION_ID_LEN: usize = 20;
+
+/// The length of packet protection nonces.
+pub const NONCE_LEN: usize = tls13::NONCE_LEN;
+
+/// The client and server Initial secrets.
+pub struct InitialSecrets {
+    /// The secret of the packets that the client sends.
+    pub client: Secret,
+
+    /// The secret of the packets that the server sends.
+    pub server: Secret,
+}
+
+/// Derives the Initial secrets from `connection_id`, the Destination
+/// Connection ID of the client's first Initial packet.
+pub fn initial_secrets(
+    version: &'static Version, connection_id: &[u8],
+) -> Result<InitialSecrets, error::Unspecified> {
+    if connection_id.len() > MAX_CONNECTION_ID_LEN {
+        return Err(error::Unspecified);
+    }
+    let suite = &TLS13_AES_128_GCM_SHA256;
+    let salt = hmac::SigningKey::new(suite.digest_algorithm, &version.initial_salt);
+    let initial_secret = hkdf::extract(&salt, connection_id);
+    let secret = |label: &[u8]| {
+        let mut value = [0; digest::MAX_OUTPUT_LEN];
+        let len = suite.digest_algorithm.output_len;
+        expand_label(&initial_secret, label, &[], &mut value[..len]);
+        Secret {
+            version,
+            suite,
+            value,
+        }
+    };
+    Ok(InitialSecrets {
+        client: secret(b"client in"),
+        server: secret(b"server in"),
+    })
+}
+
+/// A traffic secret of one direction of a connection.
+pub struct Secret {
+    version: &'static Version,
+    suite: &'static CipherSuite,
+    value: [u8; digest::MAX_OUTPUT_LEN],
+}
+
+impl Secret {
+    /// Constructs a secret from a traffic secret exported from the TLS
+    /// handshake, whose length must be the output length of the cipher
+    /// suite's hash.
+    pub fn new(
+        version: &'static Version, suite: &'static CipherSuite, value: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        if value.len() != suite.digest_algorithm.output_len {
+            return Err(error::Unspecified);
+        }
+        let mut secret = Self {
+            version,
+            suite,
+            value: [0; digest::MAX_OUTPUT_LEN],
+        };
+        secret.value[..value.len()].copy_from_slice(value);
+        Ok(secret)
+    }
+
+    /// The secret's cipher suite.
+    #[inline]
+    pub fn cipher_suite(&self) -> &'static CipherSuite { self.suite }
+
+    /// Derives the packet protection key and IV.
+    pub fn packet_key(&self) -> PacketKey {
+        let prk = self.prk();
+        let mut key = PacketKey {
+            aead_algorithm: self.suite.aead_algorithm,
+            key: [0; MAX_KEY_LEN],
+            iv: [0; NONCE_LEN],
+        };
+        let key_len = self.suite.aead_algorithm.key_len();
+        expand_label(&prk, self.version.key_label, &[], &mut key.key[..key_len]);
+        expand_label(&prk, self.version.iv_label, &[], &mut key.iv);
+        key
+    }
+
+    /// Derives the header protection key.
+    pub fn header_protection_key(&self) -> HeaderProtectionKey {
+        let mut key = HeaderProtectionKey {
+            aead_algorithm: self.suite.aead_algorithm,
+            key: [0; MAX_KEY_LEN],
+        };
+        let key_len = self.suite.aead_algorithm.key_len();
+        expand_label(&self.prk(), self.version.hp_label, &[], &mut key.key[..key_len]);
+        key
+    }
+
+    /// Derives the secret of the next key phase (RFC 9001 Section 6).
+    pub fn next(&self) -> Self {
+        let mut next = Self {
+            version: self.version,
+            suite: self.suite,
+            value: [0; digest::MAX_OUTPUT_LEN],
+        };
+        let len = self.suite.digest_algorithm.output_len;
+        expand_label(&self.prk(), self.version.ku_label, &[], &mut next.value[..len]);
+        next
+    }
+
+    fn prk(&self) -> hmac::SigningKey {
+        let len = self.suite.digest_algorithm.output_len;
+        hmac::SigningKey::new(self.suite.digest_algorithm, &self.value[..len])
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.value); }
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Secret {}
+
+/// A packet protection key and IV.
+pub struct PacketKey {
+    aead_algorithm: &'static aead::Algorithm,
+    key: [u8; MAX_KEY_LEN],
+    iv: [u8; NONCE_LEN],
+}
+
+impl PacketKey {
+    /// The key for protecting packets.
+    pub fn sealing_key(&self) -> Result<aead::SealingKey, error::Unspecified> {
+        aead::SealingKey::new(self.aead_algorithm, self.key_bytes())
+    }
+
+    /// The key for removing packet protection.
+    pub fn opening_key(&self) -> Result<aead::OpeningKey, error::Unspecified> {
+        aead::OpeningKey::new(self.aead_algorithm, self.key_bytes())
+    }
+
+    /// The nonce of the packet numbered `packet_number`: the IV XORed with
+    /// the packet number (RFC 9001 Section 5.3).
+    pub fn nonce(&self, packet_number: u64) -> [u8; NONCE_LEN] {
+        tls13::xor_nonce(&self.iv, packet_number)
+    }
+
+    fn key_bytes(&self) -> &[u8] { &self.key[..self.aead_algorithm.key_len()] }
+}
+
+impl Drop for PacketKey {
+    fn drop(&mut self) {
+        zeroize::zeroize(&mut self.key);
+        zeroize::zeroize(&mut self.iv);
+    }
+}
+
+/// A header protection key, for the AES or ChaCha20 header protection mask
+/// of RFC 9001 Section 5.4.
+pub struct HeaderProtectionKey {
+    aead_algorithm: &'static aead::Algorithm,
+    key: [u8; MAX_KEY_LEN],
+}
+
+impl HeaderProtectionKey {
+    /// The AEAD whose cipher computes the mask.
+    #[inline]
+    pub fn aead_algorithm(&self) -> &'static aead::Algorithm { self.aead_algorithm }
+}
+
+impl AsRef<[u8]> for HeaderProtectionKey {
+    fn as_ref(&self) -> &[u8] { &self.key[..self.aead_algorithm.key_len()] }
+}
+
+impl Drop for HeaderProtectionKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.key); }
+}
+
+const INITIAL_SALT_LEN: usize = 20;
+
+const MAX_KEY_LEN: usize = 32;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    fn check_secret(secret: &Secret, expected: &str, key: &str, iv: &str, hp: &str) {
+        let len = secret.suite.digest_algorithm.output_len;
+        assert_eq!(&secret.value[..len], &test::from_hex(expected).unwrap()[..]);
+        let packet_key = secret.packet_key();
+        assert_eq!(packet_key.key_bytes(), &test::from_hex(key).unwrap()[..]);
+        assert_eq!(&packet_key.iv[..], &test::from_hex(iv).unwrap()[..]);
+        let hp_key = secret.header_protection_key();
+        assert_eq!(hp_key.as_ref(), &test::from_hex(hp).unwrap()[..]);
+    }
+
+    // RFC 9001 Appendix A.1 and RFC 9369 Appendix A.1.
+    #[test]
+    fn test_initial_secrets() {
+        let connection_id = test::from_hex("8394c8f03e515708").unwrap();
+
+        let secrets = initial_secrets(&V1, &connection_id).unwrap();
+        check_secret(
+            &secrets.client,
+            "c00cf151ca5be075ed0ebfb5c80323c42d6b7db67881289af4008f1f6c357aea",
+            "1f369613dd76d5467730efcbe3b1a22d",
+            "fa044b2f42a3fd3b46fb255c",
+            "9f50449e04a0e810283a1e9933adedd2",
+        );
+        check_secret(
+            &secrets.server,
+            "3c199828fd139efd216c155ad844cc81fb82fa8d7446fa7d78be803acdda951b",
+            "cf3a5331653c364c88f0f379b6067e37",
+            "0ac1493ca1905853b0bba03e",
+            "c206b8d9b9f0f37644430b490eeaa314",
+        );
+
+        let secrets = initial_secrets(&V2, &connection_id).unwrap();
+        check_secret(
+            &secrets.client,
+            "14ec9d6eb9fd7af83bf5a668bc17a7e283766aade7ecd0891f70f9ff7f4bf47b",
+            "8b1a0bc121284290a29e0971b5cd045d",
+            "91f73e2351d8fa91660e909f",
+            "45b95e15235d6f45a6b19cbcb0294ba9",
+        );
+        check_secret(
+            &secrets.server,
+            "0263db1782731bf4588e7e4d93b7463907cb8cd8200b5da55a8bd488eafc37c1",
+            "82db637861d55e1d011f19ea71d5d2a7",
+            "dd13c276499c0249d3310652",
+            "edf6d05c83121201b436e16877593c3a",
+        );
+    }
+
+    // RFC 9001 Appendix A.5.
+    #[test]
+    fn test_chacha20_poly1305_key_update() {
+        let value =
+            test::from_hex("9ac312a7f877468ebe69422748ad00a15443f18203a07d6060f688f30f21632b")
+                .unwrap();
+        let secret = Secret::new(&V1, &TLS13_CHACHA20_POLY1305_SHA256, &value).unwrap();
+        check_secret(
+            &secret,
+            "9ac312a7f877468ebe69422748ad00a15443f18203a07d6060f688f30f21632b",
+            "c6d98ff3441c3fe1b2182094f69caa2ed4b716b65488960a7a984979fb23e1c8",
+            "e0459b3474bdd0e44a41c144",
+            "25a282b9e82f06f21f488917a4fc8f1b73573685608597d0efcb076b0ab7a7a4",
+        );
+        assert_eq!(
+            &secret.packet_key().nonce(654_360_564)[..],
+            &test::from_hex("e0459b3474bdd0e46d417eb0").unwrap()[..]
+        );
+
+        let next = secret.next();
+        assert_eq!(
+            &next.value[..32],
+            &test::from_hex("1223504755036d556342ee9361d253421a826c9ecdf3c7148684b36b714881f9")
+                .unwrap()[..]
+        );
+    }
+}
@@ -24,9 +24,73 @@
 //! can be replayed. Following this pattern also may help with sandboxing
 //! (seccomp filters on Linux in particular). See `SystemRandom`'s
 //! documentation for more details.
+//!
+//! `HmacDrbg` and `CtrDrbg` are the deterministic random bit generators of
+//! NIST SP 800-90A, for when the output must be reproducible from explicitly
+//! given entropy input, or when a DRBG is required between the operating
+//! system's entropy source and the consumers of random bytes. `SystemDrbg` is
+//! a `CtrDrbg` that reseeds itself from `SystemRandom`, including after a
+//! `fork()`. `BufferedRandom` is a `SystemRandom` that reads many bytes from
+//! the operating system at a time, for applications that need many small
+//! random values.
+//!
+//! `HealthTests` are the continuous health tests of NIST SP 800-90B for
+//! entropy sources. `CpuRandom`, which uses the x86 `RDSEED` and `RDRAND`
+//! instructions, applies them to its output, as does `JitterRandom`, a
+//! last-resort generator that collects entropy from CPU timing jitter.
 
 use crate::error;
 
+pub use self::{
+    buffered_random::{BufferedRandom, BUFFERED_RANDOM_BUFFER_LEN},
+    ctr_drbg::CtrDrbg,
+    entropy_source::{register_entropy_source, EntropySource},
+    health_tests::{HealthTests, HEALTH_TESTS_STARTUP_SAMPLES},
+    hmac_drbg::HmacDrbg,
+    jitter_random::{JitterRandom, JitterTimer},
+    random::{generate, generate_in_range, Random, RandomInteger, RandomlyConstructable},
+    system_drbg::SystemDrbg,
+};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub use self::cpu_random::{
+    cpu_entropy_source, CpuRandom, RDRAND_DEFAULT_MAX_RETRIES, RDSEED_DEFAULT_MAX_RETRIES,
+};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod cpu_random;
+
+mod buffered_random;
+mod ctr_drbg;
+mod entropy_source;
+mod health_tests;
+mod hmac_drbg;
+mod jitter_random;
+mod random;
+mod spin_lock;
+mod system_drbg;
+
+/// Deterministic implementations of `SecureRandom` for known-answer tests.
+///
+/// These are the same as the ones in `ring::test::rand`. **They are not
+/// secure**: their output is whatever the test gives them. They are for
+/// testing code that takes a `&SecureRandom` against test vectors that
+/// include the random values, e.g. the seed of an ECDSA or RSA-PSS
+/// signature; they must never be used to generate real keys, nonces, or
+/// signatures.
+///
+/// This module is only available with the `test_util` feature.
+#[cfg(feature = "test_util")]
+pub mod test_util {
+    pub use crate::test::rand::{FixedByteRandom, FixedSliceRandom, FixedSliceSequenceRandom};
+}
+
+// The maximum number of bytes per request and the maximum number of requests
+// between reseedings of `HmacDrbg` and `CtrDrbg` (NIST SP 800-90A Rev. 1
+// Tables 2 and 3).
+const DRBG_MAX_REQUEST_LEN: usize = (1 << 19) / 8;
+const DRBG_RESEED_INTERVAL: u64 = 1 << 48;
+
 /// A secure random number generator.
 pub trait SecureRandom: private::Sealed {
     /// Fills `dest` with random bytes.
@@ -60,9 +124,39 @@ pub trait SecureRandom: private::Sealed {
 /// On Windows, `fill` is implemented using the platform's API for secure
 /// random number generation.
 ///
-/// Otherwise, `fill()` is implemented by reading from `/dev/urandom`. (This is
-/// something that should be improved for any platform that adds something
-/// better.)
+/// On wasm32-unknown-unknown, with the `wasm32_unknown_unknown_js` feature,
+/// `fill()` is implemented using the Web Crypto API's
+/// `crypto.getRandomValues()`, which requires a browser-like JavaScript
+/// environment with a `window`, via `web-sys`.
+///
+/// On other Unix-like systems, `fill()` is implemented by reading from
+/// `/dev/urandom`. (This is something that should be improved for any
+/// platform that adds something better.)
+///
+/// Within an SGX enclave, `fill()` uses the CPU's `RDSEED` instruction, or
+/// `RDRAND` if `RDSEED` isn't supported, through `cpu_entropy_source()`: the
+/// first call constructs a `CpuRandom`, which runs the start-up health tests,
+/// and every call runs the continuous health tests. Each failed instruction
+/// is retried up to `RDSEED_DEFAULT_MAX_RETRIES` or
+/// `RDRAND_DEFAULT_MAX_RETRIES` times, after which `fill()` fails, as it does
+/// from then on once a health test fails; it never loops forever or returns
+/// bytes that failed the tests.
+///
+/// On x86_64 UEFI, e.g. x86_64-unknown-uefi, `fill()` also uses
+/// `cpu_entropy_source()`, as there is no operating system. Support for the
+/// instructions is detected with `CPUID`, so `fill()` fails on CPUs that
+/// support neither.
+///
+/// On all other targets, e.g. bare-metal targets, `fill()` fails unless an
+/// entropy source has been registered with `register_entropy_source()`. If
+/// one has been registered then `fill()` uses it on every target, instead of
+/// any of the above.
+///
+/// With the `getrandom` feature, `fill()` is implemented using the
+/// [`getrandom` crate] on every target, instead of any of the above, unless an
+/// entropy source has been registered. This supports the targets that the
+/// crate supports, e.g. Fuchsia and Hermit; on wasm32-unknown-unknown, the
+/// crate's own `js` feature must be enabled too.
 ///
 /// When `/dev/urandom` is used, a file handle for `/dev/urandom` won't be
 /// opened until `fill` is called. In particular, `SystemRandom::new()` will
@@ -78,6 +172,7 @@ pub trait SecureRandom: private::Sealed {
 /// `getrandom` and `read`.
 ///
 /// [`getrandom`]: http://man7.org/linux/man-pages/man2/getrandom.2.html
+/// [`getrandom` crate]: https://crates.io/crates/getrandom
 pub struct SystemRandom;
 
 impl SystemRandom {
@@ -88,28 +183,83 @@ impl SystemRandom {
 
 impl SecureRandom for SystemRandom {
     #[inline(always)]
-    fn fill(&self, dest: &mut [u8]) -> Result<(), error::Unspecified> { fill_impl(dest) }
+    fn fill(&self, dest: &mut [u8]) -> Result<(), error::Unspecified> {
+        match entropy_source::registered() {
+            Some(source) => source(dest),
+            None => fill_impl(dest),
+        }
+    }
 }
 
 impl private::Sealed for SystemRandom {}
 
-#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "ios", windows)))]
+#[cfg(feature = "getrandom")]
+fn fill_impl(dest: &mut [u8]) -> Result<(), error::Unspecified> {
+    getrandom::getrandom(dest).map_err(|_| error::Unspecified)
+}
+
+#[cfg(all(
+    not(feature = "getrandom"),
+    any(target_os = "redox", unix),
+    not(any(target_os = "linux", target_os = "macos", target_os = "ios"))
+))]
 use self::urandom::fill as fill_impl;
 
-#[cfg(any(
-    all(target_os = "linux", not(feature = "dev_urandom_fallback")),
-    windows
+#[cfg(all(
+    not(feature = "getrandom"),
+    any(
+        all(target_os = "linux", not(feature = "dev_urandom_fallback")),
+        windows,
+        all(
+            target_arch = "wasm32",
+            target_os = "unknown",
+            feature = "wasm32_unknown_unknown_js"
+        )
+    )
 ))]
 use self::sysrand::fill as fill_impl;
 
-#[cfg(all(target_os = "linux", feature = "dev_urandom_fallback"))]
+#[cfg(all(
+    not(feature = "getrandom"),
+    target_os = "linux",
+    feature = "dev_urandom_fallback"
+))]
 use self::sysrand_or_urandom::fill as fill_impl;
 
-#[cfg(any(target_os = "macos", target_os = "ios"))]
+#[cfg(all(
+    not(feature = "getrandom"),
+    any(target_os = "macos", target_os = "ios")
+))]
 use self::darwin::fill as fill_impl;
+
+#[cfg(all(
+    not(feature = "getrandom"),
+    any(
+        all(target_arch = "x86_64", target_env = "sgx"),
+        all(target_arch = "x86_64", target_os = "uefi")
+    )
+))]
+use self::cpu_random::cpu_entropy_source as fill_impl;
 use crate::private;
 
-#[cfg(target_os = "linux")]
+// Without a built-in implementation, `SystemRandom` only works with a
+// registered entropy source.
+#[cfg(not(any(
+    feature = "getrandom",
+    target_os = "redox",
+    unix,
+    windows,
+    all(target_arch = "x86_64", target_env = "sgx"),
+    all(target_arch = "x86_64", target_os = "uefi"),
+    all(
+        target_arch = "wasm32",
+        target_os = "unknown",
+        feature = "wasm32_unknown_unknown_js"
+    )
+)))]
+fn fill_impl(_: &mut [u8]) -> Result<(), error::Unspecified> { Err(error::Unspecified) }
+
+#[cfg(all(not(feature = "getrandom"), target_os = "linux"))]
 mod sysrand_chunk {
     use crate::{c, error};
     use libc;
@@ -136,7 +286,7 @@ mod sysrand_chunk {
     }
 }
 
-#[cfg(windows)]
+#[cfg(all(not(feature = "getrandom"), windows))]
 mod sysrand_chunk {
     use crate::{c, error};
     use core;
@@ -165,7 +315,47 @@ mod sysrand_chunk {
     }
 }
 
-#[cfg(any(target_os = "linux", windows))]
+#[cfg(all(
+    not(feature = "getrandom"),
+    target_arch = "wasm32",
+    target_os = "unknown",
+    feature = "wasm32_unknown_unknown_js"
+))]
+mod sysrand_chunk {
+    use crate::error;
+
+    #[inline]
+    pub fn chunk(mut dest: &mut [u8]) -> Result<usize, error::Unspecified> {
+        // This limit is specified in
+        // https://www.w3.org/TR/WebCryptoAPI/#Crypto-method-getRandomValues.
+        const MAX_LEN: usize = 65_536;
+        if dest.len() > MAX_LEN {
+            dest = &mut dest[..MAX_LEN];
+        }
+
+        let _ = web_sys::window()
+            .ok_or(error::Unspecified)?
+            .crypto()
+            .map_err(|_| error::Unspecified)?
+            .get_random_values_with_u8_array(dest)
+            .map_err(|_| error::Unspecified)?;
+
+        Ok(dest.len())
+    }
+}
+
+#[cfg(all(
+    not(feature = "getrandom"),
+    any(
+        target_os = "linux",
+        windows,
+        all(
+            target_arch = "wasm32",
+            target_os = "unknown",
+            feature = "wasm32_unknown_unknown_js"
+        )
+    )
+))]
 mod sysrand {
     use super::sysrand_chunk::chunk;
     use crate::error;
@@ -182,6 +372,7 @@ mod sysrand {
 
 // Keep the `cfg` conditions in sync with the conditions in lib.rs.
 #[cfg(all(
+    not(feature = "getrandom"),
     any(target_os = "redox", unix),
     not(any(target_os = "macos", target_os = "ios")),
     not(all(target_os = "linux", not(feature = "dev_urandom_fallback")))
@@ -214,7 +405,11 @@ mod urandom {
 }
 
 // Keep the `cfg` conditions in sync with the conditions in lib.rs.
-#[cfg(all(target_os = "linux", feature = "dev_urandom_fallback"))]
+#[cfg(all(
+    not(feature = "getrandom"),
+    target_os = "linux",
+    feature = "dev_urandom_fallback"
+))]
 mod sysrand_or_urandom {
     use crate::error;
 
@@ -244,7 +439,10 @@ mod sysrand_or_urandom {
     }
 }
 
-#[cfg(any(target_os = "macos", target_os = "ios"))]
+#[cfg(all(
+    not(feature = "getrandom"),
+    any(target_os = "macos", target_os = "ios")
+))]
 mod darwin {
     use crate::{c, error};
 
@@ -300,4 +498,17 @@ mod tests {
             }
         }
     }
+
+    #[cfg(feature = "getrandom")]
+    #[test]
+    fn test_system_random_getrandom() {
+        // `SystemRandom` uses the `getrandom` crate, instead of the code for
+        // the target, when no entropy source has been registered.
+        let mut buf = vec![0; 1 << 16];
+        assert!(super::fill_impl(&mut buf).is_ok());
+        assert!(buf.iter().any(|x| *x != 0));
+
+        let rng = rand::SystemRandom::new();
+        assert!(rng.fill(&mut buf).is_ok());
+    }
 }
@@ -0,0 +1,213 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use super::{spin_lock::SpinLock, system_drbg::process_id, SecureRandom, SystemRandom};
+use crate::{c, error, private, zeroize::zeroize};
+
+/// The number of bytes that `BufferedRandom` reads from `SystemRandom` at a
+/// time. `getrandom` on Linux never returns fewer bytes than requested for
+/// requests of up to this length.
+pub const BUFFERED_RANDOM_BUFFER_LEN: usize = 256;
+
+/// A `SystemRandom` that reads `BUFFERED_RANDOM_BUFFER_LEN` bytes at a time
+/// and returns them over several calls to `fill()`.
+///
+/// This is for when many small random values, e.g. nonces and salts, are
+/// needed, and one system call for each of them is too expensive. Requests
+/// of `BUFFERED_RANDOM_BUFFER_LEN` bytes or more are passed through to
+/// `SystemRandom` directly. Every byte is removed from the buffer, and
+/// overwritten with zeros, as it is returned, and the rest of the buffer is
+/// overwritten when the `BufferedRandom` is dropped.
+///
+/// A single `BufferedRandom` may be shared across multiple threads safely.
+/// The buffer is protected by a lock that isn't held while `SystemRandom` is
+/// being read from.
+///
+/// Buffering has caveats that `SystemRandom` doesn't have:
+///
+/// * After a `fork()`, the child process has a copy of the parent's buffer.
+///   On Unix-like systems the child's first `fill()` notices that the
+///   process ID has changed and discards the copy, so the two processes never
+///   return the same bytes. The processes are told apart by their process
+///   IDs, so a `BufferedRandom` must not be used in a process whose ancestor
+///   with the same process ID used it.
+/// * If another thread is in `fill()` when a multithreaded process forks,
+///   the child may deadlock in its first `fill()`.
+/// * The buffered bytes are in the process's memory until they are used, so
+///   they end up in core dumps and in snapshots of virtual machines; a
+///   virtual machine restored from a snapshot returns the same bytes each
+///   time it is restored.
+pub struct BufferedRandom {
+    buffer: SpinLock<Buffer>,
+}
+
+struct Buffer {
+    bytes: [u8; BUFFERED_RANDOM_BUFFER_LEN],
+
+    // `bytes[start..]` haven't been returned yet.
+    start: usize,
+
+    // The process ID when `bytes` was filled.
+    process_id: c::int,
+}
+
+impl core::fmt::Debug for BufferedRandom {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+        f.write_str("BufferedRandom")
+    }
+}
+
+impl BufferedRandom {
+    /// Constructs a new `BufferedRandom` with an empty buffer.
+    ///
+    /// Like `SystemRandom::new()`, this doesn't read any random bytes.
+    pub fn new() -> Self {
+        Self {
+            buffer: SpinLock::new(Buffer {
+                bytes: [0; BUFFERED_RANDOM_BUFFER_LEN],
+                start: BUFFERED_RANDOM_BUFFER_LEN,
+                process_id: 0,
+            }),
+        }
+    }
+}
+
+impl SecureRandom for BufferedRandom {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), error::Unspecified> {
+        if dest.len() >= BUFFERED_RANDOM_BUFFER_LEN {
+            return SystemRandom::new().fill(dest);
+        }
+        if self.buffer.lock().take(dest) {
+            return Ok(());
+        }
+
+        let mut bytes = [0; BUFFERED_RANDOM_BUFFER_LEN];
+        let filled = SystemRandom::new().fill(&mut bytes);
+        let mut buffer = self.buffer.lock();
+        if filled.is_ok() {
+            // Another thread may have refilled the buffer in the meantime;
+            // its bytes are discarded.
+            buffer.bytes = bytes;
+            buffer.start = 0;
+            buffer.process_id = process_id();
+        }
+        zeroize(&mut bytes);
+        filled?;
+        if !buffer.take(dest) {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+}
+
+impl private::Sealed for BufferedRandom {}
+
+impl Drop for BufferedRandom {
+    fn drop(&mut self) { zeroize(&mut self.buffer.lock().bytes); }
+}
+
+impl Buffer {
+    // Moves the next `dest.len()` bytes into `dest`, unless there aren't
+    // enough of them.
+    fn take(&mut self, dest: &mut [u8]) -> bool {
+        if self.process_id != process_id() {
+            zeroize(&mut self.bytes[self.start..]);
+            self.start = BUFFERED_RANDOM_BUFFER_LEN;
+        }
+        let available = &mut self.bytes[self.start..];
+        if available.len() < dest.len() {
+            return false;
+        }
+        let taken = &mut available[..dest.len()];
+        dest.copy_from_slice(taken);
+        zeroize(taken);
+        self.start += dest.len();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffered_random() {
+        let rng = BufferedRandom::new();
+        let mut seen = std::collections::HashSet::new();
+        for len in [0, 1, 7, 8, 32, 255, 256, 1000].iter().cycle().take(100) {
+            let mut buf = vec![0u8; *len];
+            assert!(rng.fill(&mut buf).is_ok());
+            if *len >= 8 {
+                assert!(seen.insert(buf));
+            }
+        }
+    }
+
+    #[test]
+    fn test_taken_bytes_are_zeroized() {
+        let rng = BufferedRandom::new();
+        let mut a = [0u8; 100];
+        rng.fill(&mut a).unwrap();
+        let buffer = rng.buffer.lock();
+        assert_eq!(buffer.start, 100);
+        assert!(buffer.bytes[..100].iter().all(|b| *b == 0));
+        assert!(buffer.bytes[100..].iter().any(|b| *b != 0));
+    }
+
+    #[test]
+    fn test_discard_after_fork() {
+        let rng = BufferedRandom::new();
+        let mut a = [0u8; 32];
+        rng.fill(&mut a).unwrap();
+
+        // Pretend that this is a child process of the process that filled
+        // the buffer.
+        let remaining = {
+            let mut buffer = rng.buffer.lock();
+            buffer.process_id ^= 1;
+            buffer.bytes[buffer.start..(buffer.start + 32)].to_vec()
+        };
+        let mut b = [0u8; 32];
+        rng.fill(&mut b).unwrap();
+        assert_ne!(&b[..], &remaining[..]);
+        let buffer = rng.buffer.lock();
+        assert_eq!(buffer.process_id, process_id());
+        assert_eq!(buffer.start, 32);
+    }
+
+    #[test]
+    fn test_threads() {
+        let rng = std::sync::Arc::new(BufferedRandom::new());
+        let threads = (0..4)
+            .map(|_| {
+                let rng = rng.clone();
+                std::thread::spawn(move || {
+                    let mut values = Vec::new();
+                    for _ in 0..100 {
+                        let mut value = [0u8; 16];
+                        rng.fill(&mut value).unwrap();
+                        values.push(value);
+                    }
+                    values
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut seen = std::collections::HashSet::new();
+        for thread in threads {
+            for value in thread.join().unwrap() {
+                assert!(seen.insert(value));
+            }
+        }
+    }
+}
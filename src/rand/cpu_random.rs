@@ -0,0 +1,262 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use super::{
+    health_tests::{HealthTests, HEALTH_TESTS_STARTUP_SAMPLES},
+    spin_lock::SpinLock,
+    SecureRandom,
+};
+use crate::{error, private};
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::{_rdrand32_step, _rdseed32_step};
+
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::{_rdrand32_step, _rdseed32_step};
+
+/// The number of times `CpuRandom::new()` retries a failed `RDRAND`
+/// instruction, as recommended by Intel.
+pub const RDRAND_DEFAULT_MAX_RETRIES: usize = 10;
+
+/// The number of times `CpuRandom::new()` retries a failed `RDSEED`
+/// instruction. `RDSEED` fails much more often than `RDRAND` because it can't
+/// return more than the entropy source produces.
+pub const RDSEED_DEFAULT_MAX_RETRIES: usize = 100;
+
+/// A secure random number generator that uses the x86 `RDSEED` instruction,
+/// or the `RDRAND` instruction on CPUs that don't support `RDSEED`.
+///
+/// `RDSEED` returns the output of the CPU's entropy source, conditioned but
+/// not expanded by a DRBG, so it is preferred; `RDRAND` returns the output of
+/// a CTR_DRBG that the CPU frequently reseeds from that entropy source.
+///
+/// Either instruction can fail transiently, e.g. when many cores use it at
+/// once. Each failed instruction is retried up to `max_retries` times, after
+/// which `fill()` fails instead of looping forever, so a broken or exhausted
+/// source is reported as an error.
+///
+/// The output is also checked by `HealthTests`, assuming a min-entropy of 8
+/// bits per byte: `new()` and `with_max_retries()` run the start-up tests on
+/// `HEALTH_TESTS_STARTUP_SAMPLES` bytes, and `fill()` runs the continuous
+/// tests on everything it returns. Once a test fails, the `CpuRandom` fails
+/// permanently.
+///
+/// Within an SGX enclave, `RDRAND` is assumed to be supported, as SGX requires
+/// it, and `RDSEED` is used only if the enclave is compiled with the `rdseed`
+/// target feature or `cpu::sgx::detect_features()` detects it. `SystemRandom`
+/// uses `cpu_entropy_source()` there unless another entropy source has been
+/// registered.
+pub struct CpuRandom {
+    uses_rdseed: bool,
+    max_retries: usize,
+    health_tests: SpinLock<HealthTests>,
+}
+
+impl core::fmt::Debug for CpuRandom {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+        f.debug_struct("CpuRandom")
+            .field("uses_rdseed", &self.uses_rdseed)
+            .field("max_retries", &self.max_retries)
+            .finish()
+    }
+}
+
+impl CpuRandom {
+    /// Constructs a `CpuRandom` that retries failed instructions up to
+    /// `RDSEED_DEFAULT_MAX_RETRIES` or `RDRAND_DEFAULT_MAX_RETRIES` times.
+    ///
+    /// Fails if the CPU supports neither `RDSEED` nor `RDRAND`, or if the
+    /// start-up health tests fail.
+    pub fn new() -> Result<Self, error::Unspecified> {
+        let (_, has_rdseed) = detect_features();
+        Self::with_max_retries(if has_rdseed {
+            RDSEED_DEFAULT_MAX_RETRIES
+        } else {
+            RDRAND_DEFAULT_MAX_RETRIES
+        })
+    }
+
+    /// Constructs a `CpuRandom` that retries failed instructions up to
+    /// `max_retries` times.
+    ///
+    /// Fails if the CPU supports neither `RDSEED` nor `RDRAND`, or if the
+    /// start-up health tests fail.
+    pub fn with_max_retries(max_retries: usize) -> Result<Self, error::Unspecified> {
+        let (has_rdrand, has_rdseed) = detect_features();
+        if !has_rdrand && !has_rdseed {
+            return Err(error::Unspecified);
+        }
+        let rng = Self {
+            uses_rdseed: has_rdseed,
+            max_retries,
+            health_tests: SpinLock::new(HealthTests::new(8)?),
+        };
+        rng.fill(&mut [0; HEALTH_TESTS_STARTUP_SAMPLES])?;
+        Ok(rng)
+    }
+
+    /// Whether `RDSEED`, rather than `RDRAND`, is used.
+    #[inline]
+    pub fn uses_rdseed(&self) -> bool { self.uses_rdseed }
+}
+
+impl SecureRandom for CpuRandom {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), error::Unspecified> {
+        let mut health_tests = self.health_tests.lock();
+        if self.uses_rdseed {
+            fill_with_retries(dest, self.max_retries, &mut health_tests, || {
+                let mut value = 0;
+                match unsafe { _rdseed32_step(&mut value) } {
+                    1 => Some(value),
+                    _ => None,
+                }
+            })
+        } else {
+            fill_with_retries(dest, self.max_retries, &mut health_tests, || {
+                let mut value = 0;
+                match unsafe { _rdrand32_step(&mut value) } {
+                    1 => Some(value),
+                    _ => None,
+                }
+            })
+        }
+    }
+}
+
+impl private::Sealed for CpuRandom {}
+
+/// An `EntropySource` that fills `dest` from a `CpuRandom` shared by the
+/// whole process, which is constructed by the first call.
+///
+/// This is the source of `SystemRandom` within an SGX enclave and on x86_64
+/// UEFI. On other x86 targets, registering it routes `SystemRandom` through
+/// `RDSEED` or `RDRAND`, with the health tests, instead of the operating
+/// system:
+///
+/// ```
+/// use ring::rand;
+///
+/// # if false {
+/// rand::register_entropy_source(rand::cpu_entropy_source).unwrap();
+/// # }
+/// ```
+pub fn cpu_entropy_source(dest: &mut [u8]) -> Result<(), error::Unspecified> {
+    static SHARED: SpinLock<Option<CpuRandom>> = SpinLock::new(None);
+    let mut shared = SHARED.lock();
+    if shared.is_none() {
+        *shared = Some(CpuRandom::new()?);
+    }
+    match &*shared {
+        Some(rng) => rng.fill(dest),
+        None => Err(error::Unspecified),
+    }
+}
+
+// Fills `dest` with the values returned by `step`, calling it again up to
+// `max_retries` times each time it fails, and runs `health_tests` on them.
+fn fill_with_retries(
+    dest: &mut [u8], max_retries: usize, health_tests: &mut HealthTests,
+    mut step: impl FnMut() -> Option<u32>,
+) -> Result<(), error::Unspecified> {
+    for chunk in dest.chunks_mut(4) {
+        let mut retries = 0;
+        let value = loop {
+            if let Some(value) = step() {
+                break value;
+            }
+            if retries == max_retries {
+                return Err(error::Unspecified);
+            }
+            retries += 1;
+            core::hint::spin_loop();
+        };
+        let value = value.to_le_bytes();
+        health_tests.test(&value)?;
+        chunk.copy_from_slice(&value[..chunk.len()]);
+    }
+    Ok(())
+}
+
+// Returns whether `RDRAND` and `RDSEED` are supported.
+#[cfg(target_env = "sgx")]
+fn detect_features() -> (bool, bool) { (true, crate::cpu::features().rdseed()) }
+
+#[cfg(not(target_env = "sgx"))]
+fn detect_features() -> (bool, bool) {
+    let features = crate::cpu::features();
+    (features.rdrand(), features.rdseed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_with_retries() {
+        // Each value is returned after `failures` failed steps.
+        fn fill(dest: &mut [u8], max_retries: usize, failures: usize) -> bool {
+            let mut health_tests = HealthTests::new(8).unwrap();
+            let mut step_count = 0;
+            fill_with_retries(dest, max_retries, &mut health_tests, || {
+                step_count += 1;
+                if step_count % (failures + 1) == 0 {
+                    Some(0x0403_0201)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+        }
+
+        let mut buf = [0u8; 6];
+        assert!(fill(&mut buf, 0, 0));
+        assert_eq!(buf, [1, 2, 3, 4, 1, 2]);
+        assert!(fill(&mut buf, 3, 3));
+        assert!(!fill(&mut buf, 2, 3));
+        assert!(!fill(&mut buf, 0, 1));
+        assert!(fill(&mut [], 0, 1));
+    }
+
+    #[test]
+    fn test_cpu_random() {
+        // Not every CPU that runs the tests supports either instruction.
+        let rng = match CpuRandom::new() {
+            Ok(rng) => rng,
+            Err(error::Unspecified) => return,
+        };
+        let mut buf = [0u8; 96];
+        assert!(rng.fill(&mut buf).is_ok());
+        assert!(buf.iter().any(|b| *b != 0));
+    }
+
+    #[test]
+    fn test_fill_with_retries_health_tests() {
+        // A stuck source fails the repetition count test: the sixth
+        // consecutive zero byte is rejected.
+        let mut health_tests = HealthTests::new(8).unwrap();
+        let mut buf = [0u8; 8];
+        assert!(fill_with_retries(&mut buf[..4], 0, &mut health_tests, || Some(0)).is_ok());
+        assert!(fill_with_retries(&mut buf, 0, &mut health_tests, || Some(0)).is_err());
+        assert!(fill_with_retries(&mut buf, 0, &mut health_tests, || Some(1)).is_err());
+    }
+
+    #[test]
+    fn test_cpu_entropy_source() {
+        let mut buf = [0u8; 96];
+        if CpuRandom::new().is_ok() {
+            assert!(cpu_entropy_source(&mut buf).is_ok());
+            assert!(cpu_entropy_source(&mut buf).is_ok());
+        }
+    }
+}
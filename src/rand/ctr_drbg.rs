@@ -0,0 +1,250 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use super::{SecureRandom, DRBG_MAX_REQUEST_LEN, DRBG_RESEED_INTERVAL};
+use crate::{
+    aes::{Block, Key, BLOCK_LEN},
+    error, private,
+};
+use core::cell::RefCell;
+
+// AES-256.
+const KEY_LEN: usize = 256 / 8;
+const SEED_LEN: usize = KEY_LEN + BLOCK_LEN;
+
+/// CTR_DRBG ([NIST SP 800-90A Rev. 1] Section 10.2.1) using AES-256 and the
+/// derivation function, a deterministic random bit generator.
+///
+/// The output is completely determined by the inputs given to `new()`,
+/// `reseed()`, and `generate()`, so the security of the output depends
+/// entirely on the entropy input coming from a good entropy source, e.g.
+/// `SystemRandom`. Prediction resistance isn't supported.
+///
+/// `fill()` is `generate()` without additional input, split into requests of
+/// at most 65536 bytes. A `CtrDrbg` can't be shared between threads.
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<ring::rand::CtrDrbg>();
+/// ```
+///
+/// [NIST SP 800-90A Rev. 1]:
+///     https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-90Ar1.pdf
+pub struct CtrDrbg {
+    state: RefCell<State>,
+}
+
+impl core::fmt::Debug for CtrDrbg {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+        f.write_str("CtrDrbg")
+    }
+}
+
+impl CtrDrbg {
+    /// Instantiates a CTR_DRBG.
+    ///
+    /// The security strength is 256 bits, so `entropy_input` must be at least
+    /// 32 bytes long; `nonce` should have at least 128 bits of entropy, or be
+    /// included in `entropy_input` instead.
+    pub fn new(
+        entropy_input: &[u8], nonce: &[u8], personalization_string: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        check_entropy_input_len(entropy_input)?;
+        let seed_material = derive(&[entropy_input, nonce, personalization_string])?;
+        let mut state = State {
+            key: Key::new(&[0; KEY_LEN])?,
+            v: [0; BLOCK_LEN],
+            reseed_counter: 1,
+        };
+        state.update(&seed_material)?;
+        Ok(Self {
+            state: RefCell::new(state),
+        })
+    }
+
+    /// Reseeds the DRBG with `entropy_input`, which has the same length
+    /// requirement as in `new()`, and `additional_input`, which may be empty.
+    pub fn reseed(
+        &mut self, entropy_input: &[u8], additional_input: &[u8],
+    ) -> Result<(), error::Unspecified> {
+        check_entropy_input_len(entropy_input)?;
+        let seed_material = derive(&[entropy_input, additional_input])?;
+        let state = self.state.get_mut();
+        state.update(&seed_material)?;
+        state.reseed_counter = 1;
+        Ok(())
+    }
+
+    /// Fills `out` with the next output of the DRBG, after mixing in
+    /// `additional_input`, which may be empty.
+    ///
+    /// Fails if `out` is longer than 65536 bytes, or if 2<sup>48</sup>
+    /// requests have been made since the last (re)seeding, in which case the
+    /// DRBG must be reseeded before it can be used again.
+    pub fn generate(
+        &mut self, out: &mut [u8], additional_input: &[u8],
+    ) -> Result<(), error::Unspecified> {
+        self.state.get_mut().generate(out, additional_input)
+    }
+
+    /// Whether `generate()` will fail until the DRBG is reseeded.
+    pub(super) fn reseed_required(&mut self) -> bool {
+        self.state.get_mut().reseed_counter > DRBG_RESEED_INTERVAL
+    }
+}
+
+impl SecureRandom for CtrDrbg {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), error::Unspecified> {
+        let mut state = self.state.try_borrow_mut().map_err(|_| error::Unspecified)?;
+        for chunk in dest.chunks_mut(DRBG_MAX_REQUEST_LEN) {
+            state.generate(chunk, &[])?;
+        }
+        Ok(())
+    }
+}
+
+impl private::Sealed for CtrDrbg {}
+
+struct State {
+    key: Key,
+    v: Block,
+    reseed_counter: u64,
+}
+
+impl State {
+    fn generate(
+        &mut self, out: &mut [u8], additional_input: &[u8],
+    ) -> Result<(), error::Unspecified> {
+        if out.len() > DRBG_MAX_REQUEST_LEN || self.reseed_counter > DRBG_RESEED_INTERVAL {
+            return Err(error::Unspecified);
+        }
+        let additional_input = if additional_input.is_empty() {
+            [0; SEED_LEN]
+        } else {
+            let additional_input = derive(&[additional_input])?;
+            self.update(&additional_input)?;
+            additional_input
+        };
+        for chunk in out.chunks_mut(BLOCK_LEN) {
+            let block = self.next_block();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        self.update(&additional_input)?;
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    // CTR_DRBG_Update.
+    fn update(&mut self, provided_data: &[u8; SEED_LEN]) -> Result<(), error::Unspecified> {
+        let mut temp = [0; SEED_LEN];
+        for (out, provided) in temp.chunks_mut(BLOCK_LEN).zip(provided_data.chunks(BLOCK_LEN)) {
+            let block = self.next_block();
+            for ((out, block), provided) in out.iter_mut().zip(&block).zip(provided) {
+                *out = block ^ provided;
+            }
+        }
+        self.key = Key::new(&temp[..KEY_LEN])?;
+        self.v.copy_from_slice(&temp[KEY_LEN..]);
+        Ok(())
+    }
+
+    fn next_block(&mut self) -> Block {
+        self.v = u128::from_be_bytes(self.v).wrapping_add(1).to_be_bytes();
+        let mut block = self.v;
+        self.key.encrypt_block(&mut block);
+        block
+    }
+}
+
+// Block_Cipher_df, where the input string is the concatenation of `input`,
+// producing `SEED_LEN` bytes.
+fn derive(input: &[&[u8]]) -> Result<[u8; SEED_LEN], error::Unspecified> {
+    let input_len = input.iter().map(|input| input.len()).sum::<usize>();
+    if input_len > (u32::max_value() as usize) {
+        return Err(error::Unspecified);
+    }
+
+    let mut key = [0; KEY_LEN];
+    for (i, b) in key.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let key = Key::new(&key)?;
+    let mut temp = [0; SEED_LEN];
+    for (i, temp) in temp.chunks_mut(BLOCK_LEN).enumerate() {
+        let mut iv = [0; BLOCK_LEN];
+        iv[..4].copy_from_slice(&(i as u32).to_be_bytes());
+        let mut bcc = Bcc::new(&key);
+        bcc.update(&iv);
+        bcc.update(&(input_len as u32).to_be_bytes());
+        bcc.update(&(SEED_LEN as u32).to_be_bytes());
+        for input in input {
+            bcc.update(input);
+        }
+        bcc.update(&[0x80]);
+        temp.copy_from_slice(&bcc.finish());
+    }
+
+    let key = Key::new(&temp[..KEY_LEN])?;
+    let mut x = [0; BLOCK_LEN];
+    x.copy_from_slice(&temp[KEY_LEN..]);
+    let mut out = [0; SEED_LEN];
+    for out in out.chunks_mut(BLOCK_LEN) {
+        key.encrypt_block(&mut x);
+        out.copy_from_slice(&x);
+    }
+    Ok(out)
+}
+
+// The CBC-MAC of the input, padded with zeros to a multiple of the block
+// length.
+struct Bcc<'a> {
+    key: &'a Key,
+    chaining_value: Block,
+    buffered_len: usize,
+}
+
+impl<'a> Bcc<'a> {
+    fn new(key: &'a Key) -> Self {
+        Self {
+            key,
+            chaining_value: [0; BLOCK_LEN],
+            buffered_len: 0,
+        }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        for b in input {
+            self.chaining_value[self.buffered_len] ^= b;
+            self.buffered_len += 1;
+            if self.buffered_len == BLOCK_LEN {
+                self.key.encrypt_block(&mut self.chaining_value);
+                self.buffered_len = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Block {
+        if self.buffered_len != 0 {
+            self.key.encrypt_block(&mut self.chaining_value);
+        }
+        self.chaining_value
+    }
+}
+
+fn check_entropy_input_len(entropy_input: &[u8]) -> Result<(), error::Unspecified> {
+    if entropy_input.len() < KEY_LEN {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
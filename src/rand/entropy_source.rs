@@ -0,0 +1,138 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use crate::error;
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A function that fills `dest` with bytes from an entropy source, e.g. a
+/// hardware true random number generator (TRNG).
+///
+/// It must either fill all of `dest` with unpredictable bytes or fail.
+pub type EntropySource = fn(dest: &mut [u8]) -> Result<(), error::Unspecified>;
+
+/// Registers `source` as the source of the random bytes of `SystemRandom`.
+///
+/// This is for targets, such as bare-metal embedded systems and enclaves,
+/// where *ring* has no built-in way to get random bytes from the platform;
+/// on those targets `SystemRandom::fill()` fails until a source is
+/// registered. Once registered, `source` is used by every `SystemRandom`
+/// *instead of* the operating system's generator, on every target.
+///
+/// Only one source can be registered, and it can't be unregistered. Fails if
+/// a source has already been registered. Register the source before using
+/// `SystemRandom`, e.g. at the start of `main()`.
+///
+/// On targets that the [`getrandom`] crate supports, such as wasm32,
+/// Fuchsia, and Hermit, enabling the `getrandom` feature is simpler than
+/// registering a source that calls it.
+///
+/// ```
+/// use ring::{error, rand};
+///
+/// fn fill_from_trng(dest: &mut [u8]) -> Result<(), error::Unspecified> {
+///     for b in dest.iter_mut() {
+///         *b = read_trng_byte()?;
+///     }
+///     Ok(())
+/// }
+/// # fn read_trng_byte() -> Result<u8, error::Unspecified> { Err(error::Unspecified) }
+///
+/// rand::register_entropy_source(fill_from_trng).unwrap();
+/// assert!(rand::register_entropy_source(fill_from_trng).is_err());
+/// ```
+///
+/// [`getrandom`]: https://crates.io/crates/getrandom
+pub fn register_entropy_source(source: EntropySource) -> Result<(), error::Unspecified> {
+    REGISTRATION.register(source)
+}
+
+/// Returns the registered entropy source, if any.
+pub(super) fn registered() -> Option<EntropySource> { REGISTRATION.get() }
+
+static REGISTRATION: Registration = Registration::new();
+
+struct Registration {
+    state: AtomicUsize,
+    source: UnsafeCell<Option<EntropySource>>,
+}
+
+// `source` is written only by the one thread that moves `state` from
+// `UNREGISTERED` to `REGISTERING`, and it is read only once `state` is
+// `REGISTERED`.
+unsafe impl Sync for Registration {}
+
+const UNREGISTERED: usize = 0;
+const REGISTERING: usize = 1;
+const REGISTERED: usize = 2;
+
+impl Registration {
+    const fn new() -> Self {
+        Self {
+            state: AtomicUsize::new(UNREGISTERED),
+            source: UnsafeCell::new(None),
+        }
+    }
+
+    fn register(&self, source: EntropySource) -> Result<(), error::Unspecified> {
+        if self
+            .state
+            .compare_exchange(UNREGISTERED, REGISTERING, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(error::Unspecified);
+        }
+        unsafe {
+            *self.source.get() = Some(source);
+        }
+        self.state.store(REGISTERED, Ordering::Release);
+        Ok(())
+    }
+
+    fn get(&self) -> Option<EntropySource> {
+        if self.state.load(Ordering::Acquire) != REGISTERED {
+            return None;
+        }
+        unsafe { *self.source.get() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill_with_ones(dest: &mut [u8]) -> Result<(), error::Unspecified> {
+        for b in dest {
+            *b = 1;
+        }
+        Ok(())
+    }
+
+    fn fail(_: &mut [u8]) -> Result<(), error::Unspecified> { Err(error::Unspecified) }
+
+    #[test]
+    fn test_registration() {
+        let registration = Registration::new();
+        assert!(registration.get().is_none());
+        assert!(registration.register(fill_with_ones).is_ok());
+        assert!(registration.register(fail).is_err());
+
+        let source = registration.get().unwrap();
+        let mut buf = [0u8; 3];
+        assert!(source(&mut buf).is_ok());
+        assert_eq!(buf, [1, 1, 1]);
+    }
+}
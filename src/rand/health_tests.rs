@@ -0,0 +1,193 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use crate::error;
+
+/// The continuous health tests of [NIST SP 800-90B] Section 4.4, the
+/// Repetition Count Test and the Adaptive Proportion Test, applied to a
+/// stream of one-byte samples from an entropy source.
+///
+/// The tests detect a source that is stuck on one value, or that produces
+/// one value much more often than its claimed min-entropy allows. The false
+/// positive probability of each test is 2<sup>-40</sup> per sample, and the
+/// adaptive proportion test uses a window of 512 samples.
+///
+/// Once a test fails, every later call to `test()` fails too, as SP 800-90B
+/// requires; construct new `HealthTests` after the source has been reset.
+///
+/// `CpuRandom` applies these tests to the output of `RDSEED` and `RDRAND`.
+/// Sources registered with `register_entropy_source()` can use them too:
+///
+/// ```
+/// use ring::{error, rand};
+///
+/// # fn read_samples(dest: &mut [u8]) -> Result<(), error::Unspecified> {
+/// #     use ring::rand::SecureRandom;
+/// #     rand::SystemRandom::new().fill(dest)
+/// # }
+/// let mut health_tests = rand::HealthTests::new(8)?;
+/// let mut samples = [0; rand::HEALTH_TESTS_STARTUP_SAMPLES];
+/// read_samples(&mut samples)?;
+/// health_tests.test(&samples)?;
+///
+/// // A source that is stuck fails the repetition count test.
+/// assert!(health_tests.test(&[0; 6]).is_err());
+/// # Ok::<(), error::Unspecified>(())
+/// ```
+///
+/// [NIST SP 800-90B]:
+///     https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-90B.pdf
+#[derive(Clone, Debug)]
+pub struct HealthTests {
+    repetition_count_cutoff: usize,
+    adaptive_proportion_cutoff: usize,
+    last_sample: u8,
+    repetition_count: usize,
+    window_sample: u8,
+    window_count: usize,
+    window_len: usize,
+    failed: bool,
+}
+
+/// The number of samples that should pass `HealthTests` when an entropy
+/// source starts up, before any of its output is used (SP 800-90B Section
+/// 4.3).
+pub const HEALTH_TESTS_STARTUP_SAMPLES: usize = 1024;
+
+const ADAPTIVE_PROPORTION_WINDOW_LEN: usize = 512;
+
+// The cutoffs for a false positive probability of 2^-40, indexed by the
+// assessed min-entropy per sample in bits, minus one (SP 800-90B Sections
+// 4.4.1 and 4.4.2). The repetition count cutoff is 1 + ceil(40 / H) and the
+// adaptive proportion cutoff is 1 + CRITBINOM(512, 2^-H, 1 - 2^-40).
+const REPETITION_COUNT_CUTOFFS: [usize; 8] = [41, 21, 15, 11, 9, 8, 7, 6];
+const ADAPTIVE_PROPORTION_CUTOFFS: [usize; 8] = [336, 201, 123, 78, 51, 35, 26, 19];
+
+impl HealthTests {
+    /// Constructs the health tests for a source whose assessed min-entropy is
+    /// `min_entropy_per_sample` bits per byte.
+    ///
+    /// Fails unless `min_entropy_per_sample` is in the range 1 through 8. A
+    /// source with less than one bit of min-entropy per byte must be
+    /// tested on its raw, larger samples instead.
+    pub fn new(min_entropy_per_sample: usize) -> Result<Self, error::Unspecified> {
+        if min_entropy_per_sample < 1 || min_entropy_per_sample > 8 {
+            return Err(error::Unspecified);
+        }
+        Ok(Self {
+            repetition_count_cutoff: REPETITION_COUNT_CUTOFFS[min_entropy_per_sample - 1],
+            adaptive_proportion_cutoff: ADAPTIVE_PROPORTION_CUTOFFS[min_entropy_per_sample - 1],
+            last_sample: 0,
+            repetition_count: 0,
+            window_sample: 0,
+            window_count: 0,
+            window_len: 0,
+            failed: false,
+        })
+    }
+
+    /// Runs both tests on `samples`, which follow the samples given to the
+    /// previous calls.
+    ///
+    /// Fails if either test fails on any sample, or has failed before.
+    pub fn test(&mut self, samples: &[u8]) -> Result<(), error::Unspecified> {
+        for &sample in samples {
+            if self.failed {
+                break;
+            }
+            self.failed = !self.test_repetition_count(sample)
+                || !self.test_adaptive_proportion(sample);
+        }
+        if self.failed {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+
+    // SP 800-90B Section 4.4.1.
+    fn test_repetition_count(&mut self, sample: u8) -> bool {
+        if self.repetition_count > 0 && sample == self.last_sample {
+            self.repetition_count += 1;
+        } else {
+            self.last_sample = sample;
+            self.repetition_count = 1;
+        }
+        self.repetition_count < self.repetition_count_cutoff
+    }
+
+    // SP 800-90B Section 4.4.2.
+    fn test_adaptive_proportion(&mut self, sample: u8) -> bool {
+        if self.window_len == ADAPTIVE_PROPORTION_WINDOW_LEN {
+            self.window_len = 0;
+        }
+        if self.window_len == 0 {
+            self.window_sample = sample;
+            self.window_count = 0;
+        }
+        if sample == self.window_sample {
+            self.window_count += 1;
+        }
+        self.window_len += 1;
+        self.window_count < self.adaptive_proportion_cutoff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_entropy_per_sample() {
+        assert!(HealthTests::new(0).is_err());
+        assert!(HealthTests::new(1).is_ok());
+        assert!(HealthTests::new(8).is_ok());
+        assert!(HealthTests::new(9).is_err());
+    }
+
+    #[test]
+    fn test_repetition_count() {
+        let mut health_tests = HealthTests::new(8).unwrap();
+        assert!(health_tests.test(&[1, 1, 1, 1, 1, 2, 2, 2, 2, 2]).is_ok());
+        assert!(health_tests.test(&[3, 3, 3]).is_ok());
+        // Repetitions are counted across calls.
+        assert!(health_tests.test(&[3, 3]).is_ok());
+        assert!(health_tests.test(&[3]).is_err());
+        // Failures are permanent.
+        assert!(health_tests.test(&[]).is_err());
+        assert!(health_tests.test(&[4]).is_err());
+
+        let mut health_tests = HealthTests::new(1).unwrap();
+        assert!(health_tests.test(&[0; 40]).is_ok());
+        assert!(health_tests.test(&[0]).is_err());
+    }
+
+    #[test]
+    fn test_adaptive_proportion() {
+        // 18 of every 512 samples equal to the window's first sample pass;
+        // none of them repeat consecutively.
+        let mut window = [0u8; ADAPTIVE_PROPORTION_WINDOW_LEN];
+        for (i, b) in window.iter_mut().enumerate() {
+            *b = if i % 2 == 0 && i < 36 { 0 } else { (i % 255) as u8 + 1 };
+        }
+        let mut health_tests = HealthTests::new(8).unwrap();
+        for _ in 0..4 {
+            assert!(health_tests.test(&window).is_ok());
+        }
+
+        // Once the 19th is in the same window, the test fails.
+        window[36] = 0;
+        assert!(health_tests.test(&window[..36]).is_ok());
+        assert!(health_tests.test(&window[36..]).is_err());
+    }
+}
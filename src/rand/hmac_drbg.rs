@@ -0,0 +1,169 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use super::{SecureRandom, DRBG_MAX_REQUEST_LEN, DRBG_RESEED_INTERVAL};
+use crate::{digest, error, hmac, private};
+use core::cell::RefCell;
+
+/// HMAC_DRBG ([NIST SP 800-90A Rev. 1] Section 10.1.2), a deterministic
+/// random bit generator.
+///
+/// The output is completely determined by the inputs given to `new()`,
+/// `reseed()`, and `generate()`, so the security of the output depends
+/// entirely on the entropy input coming from a good entropy source, e.g.
+/// `SystemRandom`. Prediction resistance isn't supported.
+///
+/// `fill()` is `generate()` without additional input, split into requests of
+/// at most 65536 bytes. An `HmacDrbg` can't be shared between threads.
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<ring::rand::HmacDrbg>();
+/// ```
+///
+/// [NIST SP 800-90A Rev. 1]:
+///     https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-90Ar1.pdf
+pub struct HmacDrbg {
+    digest_alg: &'static digest::Algorithm,
+    state: RefCell<State>,
+}
+
+derive_debug_via_self!(HmacDrbg, self.digest_alg);
+
+impl HmacDrbg {
+    /// Instantiates an HMAC_DRBG using HMAC with `digest_alg`.
+    ///
+    /// The security strength is 128 bits for SHA-1 and 256 bits for the other
+    /// digest algorithms. `entropy_input` must be at least that long; `nonce`
+    /// should have at least half that much entropy, or be included in
+    /// `entropy_input` instead.
+    pub fn new(
+        digest_alg: &'static digest::Algorithm, entropy_input: &[u8], nonce: &[u8],
+        personalization_string: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        check_entropy_input_len(digest_alg, entropy_input)?;
+        let len = digest_alg.output_len;
+        let mut state = State {
+            digest_alg,
+            k: hmac::SigningKey::new(digest_alg, &[0; digest::MAX_OUTPUT_LEN][..len]),
+            v: [1; digest::MAX_OUTPUT_LEN],
+            reseed_counter: 1,
+        };
+        state.update(&[entropy_input, nonce, personalization_string]);
+        Ok(Self {
+            digest_alg,
+            state: RefCell::new(state),
+        })
+    }
+
+    /// Reseeds the DRBG with `entropy_input`, which has the same length
+    /// requirement as in `new()`, and `additional_input`, which may be empty.
+    pub fn reseed(
+        &mut self, entropy_input: &[u8], additional_input: &[u8],
+    ) -> Result<(), error::Unspecified> {
+        check_entropy_input_len(self.digest_alg, entropy_input)?;
+        let state = self.state.get_mut();
+        state.update(&[entropy_input, additional_input]);
+        state.reseed_counter = 1;
+        Ok(())
+    }
+
+    /// Fills `out` with the next output of the DRBG, after mixing in
+    /// `additional_input`, which may be empty.
+    ///
+    /// Fails if `out` is longer than 65536 bytes, or if 2<sup>48</sup>
+    /// requests have been made since the last (re)seeding, in which case the
+    /// DRBG must be reseeded before it can be used again.
+    pub fn generate(
+        &mut self, out: &mut [u8], additional_input: &[u8],
+    ) -> Result<(), error::Unspecified> {
+        self.state.get_mut().generate(out, additional_input)
+    }
+}
+
+impl SecureRandom for HmacDrbg {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), error::Unspecified> {
+        let mut state = self.state.try_borrow_mut().map_err(|_| error::Unspecified)?;
+        for chunk in dest.chunks_mut(DRBG_MAX_REQUEST_LEN) {
+            state.generate(chunk, &[])?;
+        }
+        Ok(())
+    }
+}
+
+impl private::Sealed for HmacDrbg {}
+
+struct State {
+    digest_alg: &'static digest::Algorithm,
+    k: hmac::SigningKey,
+    // Only the first `digest_alg.output_len` bytes are used.
+    v: [u8; digest::MAX_OUTPUT_LEN],
+    reseed_counter: u64,
+}
+
+impl State {
+    fn generate(
+        &mut self, out: &mut [u8], additional_input: &[u8],
+    ) -> Result<(), error::Unspecified> {
+        if out.len() > DRBG_MAX_REQUEST_LEN || self.reseed_counter > DRBG_RESEED_INTERVAL {
+            return Err(error::Unspecified);
+        }
+        if !additional_input.is_empty() {
+            self.update(&[additional_input]);
+        }
+        for chunk in out.chunks_mut(self.digest_alg.output_len) {
+            self.next_v();
+            chunk.copy_from_slice(&self.v[..chunk.len()]);
+        }
+        self.update(&[additional_input]);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    // HMAC_DRBG_Update, where `provided_data` is the concatenation of the
+    // given slices.
+    fn update(&mut self, provided_data: &[&[u8]]) {
+        let len = self.digest_alg.output_len;
+        for &separator in &[0u8, 1u8] {
+            let mut ctx = hmac::SigningContext::with_key(&self.k);
+            ctx.update(&self.v[..len]);
+            ctx.update(&[separator]);
+            for data in provided_data {
+                ctx.update(data);
+            }
+            self.k = hmac::SigningKey::new(self.digest_alg, ctx.sign().as_ref());
+            self.next_v();
+            if provided_data.iter().all(|data| data.is_empty()) {
+                break;
+            }
+        }
+    }
+
+    fn next_v(&mut self) {
+        let len = self.digest_alg.output_len;
+        let v = hmac::sign(&self.k, &self.v[..len]);
+        self.v[..len].copy_from_slice(v.as_ref());
+    }
+}
+
+// NIST SP 800-90A Rev. 1 Section 10.1, Table 2.
+fn check_entropy_input_len(
+    digest_alg: &'static digest::Algorithm, entropy_input: &[u8],
+) -> Result<(), error::Unspecified> {
+    let security_strength_len = if digest_alg.output_len < 256 / 8 { 128 / 8 } else { 256 / 8 };
+    if entropy_input.len() < security_strength_len {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
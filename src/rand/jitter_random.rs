@@ -0,0 +1,198 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use super::{
+    health_tests::{HealthTests, HEALTH_TESTS_STARTUP_SAMPLES},
+    SecureRandom,
+};
+use crate::{digest, error, private};
+use core::cell::RefCell;
+
+/// A high-resolution timer, e.g. a CPU cycle counter, for `JitterRandom`.
+///
+/// The values only have to increase, with wrapping, and the difference
+/// between consecutive values should vary with the time taken by the code
+/// that runs in between.
+pub type JitterTimer = fn() -> u64;
+
+/// A last-resort secure random number generator that collects entropy from
+/// the variation in the execution time of the CPU, measured by a `timer`.
+///
+/// This is for environments, e.g. early boot or exotic targets, where neither
+/// `SystemRandom` nor `CpuRandom` works; wherever either of them works, use
+/// it instead. Every `fill()` measures the time taken by a loop of memory
+/// accesses many times, so it is very slow.
+///
+/// The entropy is conditioned conservatively: each 32 bytes of output is the
+/// SHA-256 digest of 1024 timing measurements, i.e. each measurement is
+/// assumed to have only a quarter of a bit of entropy. The low byte of each
+/// measurement is also checked by `HealthTests`, assuming a min-entropy of
+/// one bit, so that a timer that is too coarse or that doesn't advance is
+/// detected: `new()` runs the start-up tests, and once a test fails the
+/// `JitterRandom` fails permanently.
+///
+/// A `JitterRandom` can't be shared between threads.
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<ring::rand::JitterRandom>();
+/// ```
+///
+/// ```
+/// use ring::rand::{self, SecureRandom};
+///
+/// fn read_cycle_counter() -> u64 {
+///     // e.g. `RDTSC` on x86, `CNTVCT_EL0` on AArch64, or `mcycle` on RISC-V.
+/// #   std::time::SystemTime::now()
+/// #       .duration_since(std::time::UNIX_EPOCH)
+/// #       .unwrap()
+/// #       .as_nanos() as u64
+/// }
+///
+/// let rng = rand::JitterRandom::new(read_cycle_counter)?;
+/// let mut seed = [0; 32];
+/// rng.fill(&mut seed)?;
+/// # Ok::<(), ring::error::Unspecified>(())
+/// ```
+pub struct JitterRandom {
+    timer: JitterTimer,
+    health_tests: RefCell<HealthTests>,
+}
+
+impl core::fmt::Debug for JitterRandom {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+        f.write_str("JitterRandom")
+    }
+}
+
+// Each measurement is assumed to have a quarter of a bit of entropy.
+const MEASUREMENTS_PER_BLOCK: usize = 4 * 8 * BLOCK_LEN;
+
+const BLOCK_LEN: usize = 256 / 8;
+
+// The memory accessed between the timer readings. The accesses take a
+// varying amount of time depending on the state of the caches.
+const MEMORY_LEN: usize = 16 * 1024;
+const MEMORY_ACCESSES: usize = 128;
+
+// A prime number of bytes, which is larger than a typical cache line.
+const MEMORY_STRIDE: usize = 67;
+
+impl JitterRandom {
+    /// Constructs a `JitterRandom` that measures time with `timer`.
+    ///
+    /// Fails if the start-up health tests fail, e.g. because `timer` is too
+    /// coarse.
+    pub fn new(timer: JitterTimer) -> Result<Self, error::Unspecified> {
+        let rng = Self {
+            timer,
+            health_tests: RefCell::new(HealthTests::new(1)?),
+        };
+        {
+            let mut health_tests = rng.health_tests.borrow_mut();
+            let mut collector = Collector::new(timer);
+            for _ in 0..HEALTH_TESTS_STARTUP_SAMPLES {
+                let _ = collector.measure(&mut health_tests)?;
+            }
+        }
+        Ok(rng)
+    }
+}
+
+impl SecureRandom for JitterRandom {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), error::Unspecified> {
+        let mut health_tests = self.health_tests.try_borrow_mut().map_err(|_| error::Unspecified)?;
+        let mut collector = Collector::new(self.timer);
+        for chunk in dest.chunks_mut(BLOCK_LEN) {
+            let mut ctx = digest::Context::new(&digest::SHA256);
+            for _ in 0..MEASUREMENTS_PER_BLOCK {
+                let measurement = collector.measure(&mut health_tests)?;
+                ctx.update(&measurement.to_le_bytes());
+            }
+            chunk.copy_from_slice(&ctx.finish().as_ref()[..chunk.len()]);
+        }
+        Ok(())
+    }
+}
+
+impl private::Sealed for JitterRandom {}
+
+struct Collector {
+    timer: JitterTimer,
+    memory: [u8; MEMORY_LEN],
+    index: usize,
+}
+
+impl Collector {
+    fn new(timer: JitterTimer) -> Self {
+        Self {
+            timer,
+            memory: [0; MEMORY_LEN],
+            index: 0,
+        }
+    }
+
+    // Returns the time taken by a loop of memory accesses, after checking it
+    // with `health_tests`.
+    fn measure(&mut self, health_tests: &mut HealthTests) -> Result<u64, error::Unspecified> {
+        let start = (self.timer)();
+        // Start where the previous timing left off, so that the accesses
+        // depend on it.
+        self.index = (self.index + (start as usize)) % MEMORY_LEN;
+        for _ in 0..MEMORY_ACCESSES {
+            let b = &mut self.memory[self.index];
+            unsafe { core::ptr::write_volatile(b, core::ptr::read_volatile(b).wrapping_add(1)) };
+            self.index = (self.index + MEMORY_STRIDE) % MEMORY_LEN;
+        }
+        let measurement = (self.timer)().wrapping_sub(start);
+        health_tests.test(&measurement.to_le_bytes()[..1])?;
+        Ok(measurement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64
+    }
+
+    #[test]
+    fn test_jitter_random() {
+        let rng = JitterRandom::new(now).unwrap();
+        let mut a = [0u8; 33];
+        let mut b = [0u8; 33];
+        rng.fill(&mut a).unwrap();
+        rng.fill(&mut b).unwrap();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_stuck_timer() {
+        fn stuck() -> u64 { 1 }
+        assert!(JitterRandom::new(stuck).is_err());
+
+        // A timer that advances by the same amount every time.
+        fn counter() -> u64 {
+            use core::sync::atomic::{AtomicUsize, Ordering};
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            COUNTER.fetch_add(1, Ordering::Relaxed) as u64
+        }
+        assert!(JitterRandom::new(counter).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use super::SecureRandom;
+use crate::error;
+use core::ops::Range;
+
+/// A random value generated by `generate()` or `generate_in_range()`.
+///
+/// The value has to be explicitly `expose()`d, which makes it easy to find
+/// the places where random values are used.
+#[derive(Clone, Copy)]
+pub struct Random<T: RandomlyConstructable>(T);
+
+impl<T: RandomlyConstructable> Random<T> {
+    /// Returns the random value.
+    #[inline]
+    pub fn expose(self) -> T { self.0 }
+}
+
+/// A type whose values can be generated by `generate()`: `u32`, `u64`, and
+/// `[u8; N]`.
+///
+/// The integers are decoded from random bytes in little-endian order, so
+/// every value is equally likely.
+pub trait RandomlyConstructable: Sized + sealed::RandomlyConstructable {}
+
+/// An integer type whose values can be generated by `generate_in_range()`:
+/// `u32` and `u64`.
+pub trait RandomInteger: RandomlyConstructable + Copy + sealed::RandomInteger {}
+
+/// Generates a random value.
+///
+/// ```
+/// use ring::rand;
+///
+/// let rng = rand::SystemRandom::new();
+/// let key_id: [u8; 12] = rand::generate(&rng).unwrap().expose();
+/// let nonce = rand::generate::<u64>(&rng).unwrap().expose();
+/// # let _ = (key_id, nonce);
+/// ```
+pub fn generate<T: RandomlyConstructable>(
+    rng: &SecureRandom,
+) -> Result<Random<T>, error::Unspecified> {
+    T::generate(rng).map(Random)
+}
+
+/// Generates a random integer in `range`, which must not be empty, with every
+/// value in it equally likely.
+///
+/// Uniformity is achieved by rejection sampling, so the number of bytes read
+/// from `rng` varies; on average fewer than twice the size of `T` are read.
+///
+/// ```
+/// use ring::rand;
+///
+/// let rng = rand::SystemRandom::new();
+/// let jitter_ms = rand::generate_in_range(&rng, 100..250u32).unwrap().expose();
+/// assert!(jitter_ms >= 100 && jitter_ms < 250);
+/// ```
+pub fn generate_in_range<T: RandomInteger>(
+    rng: &SecureRandom, range: Range<T>,
+) -> Result<Random<T>, error::Unspecified> {
+    let start = range.start.into_u64();
+    let len = range.end.into_u64().checked_sub(start).ok_or(error::Unspecified)?;
+    if len == 0 {
+        return Err(error::Unspecified);
+    }
+    // Accept only the values below the largest multiple of `len` that is at
+    // most `T::MAX + 1`, i.e. reject the `(T::MAX + 1) % len` largest values.
+    let rejected_len = ((T::MAX % len) + 1) % len;
+    let max_accepted = T::MAX - rejected_len;
+    loop {
+        let value = T::generate(rng)?.into_u64();
+        if value <= max_accepted {
+            return Ok(Random(T::from_u64(start + (value % len))));
+        }
+    }
+}
+
+impl<const N: usize> RandomlyConstructable for [u8; N] {}
+
+impl<const N: usize> sealed::RandomlyConstructable for [u8; N] {
+    fn generate(rng: &SecureRandom) -> Result<Self, error::Unspecified> {
+        let mut value = [0; N];
+        rng.fill(&mut value)?;
+        Ok(value)
+    }
+}
+
+macro_rules! randomly_constructable_integer {
+    ( $int:ty ) => {
+        impl RandomlyConstructable for $int {}
+
+        impl sealed::RandomlyConstructable for $int {
+            fn generate(rng: &SecureRandom) -> Result<Self, error::Unspecified> {
+                let bytes = <[u8; core::mem::size_of::<$int>()]>::generate(rng)?;
+                Ok(<$int>::from_le_bytes(bytes))
+            }
+        }
+
+        impl RandomInteger for $int {}
+    };
+}
+
+randomly_constructable_integer!(u32);
+randomly_constructable_integer!(u64);
+
+impl sealed::RandomInteger for u32 {
+    const MAX: u64 = 0xffff_ffff;
+
+    #[inline]
+    fn into_u64(self) -> u64 { u64::from(self) }
+
+    #[inline]
+    fn from_u64(value: u64) -> Self { value as u32 }
+}
+
+impl sealed::RandomInteger for u64 {
+    const MAX: u64 = u64::max_value();
+
+    #[inline]
+    fn into_u64(self) -> u64 { self }
+
+    #[inline]
+    fn from_u64(value: u64) -> Self { value }
+}
+
+mod sealed {
+    use super::SecureRandom;
+    use crate::error;
+
+    pub trait RandomlyConstructable: Sized {
+        fn generate(rng: &SecureRandom) -> Result<Self, error::Unspecified>;
+    }
+
+    pub trait RandomInteger {
+        const MAX: u64;
+
+        fn into_u64(self) -> u64;
+
+        // `value` is at most `MAX`.
+        fn from_u64(value: u64) -> Self;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_generate() {
+        const BYTES: &[u8] = &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        let bytes = [&BYTES[..3], &[], &BYTES[..4], BYTES];
+        let rng = test::rand::FixedSliceSequenceRandom::new(&bytes);
+        assert_eq!(generate::<[u8; 3]>(&rng).unwrap().expose(), [1, 2, 3]);
+        assert_eq!(generate::<[u8; 0]>(&rng).unwrap().expose(), []);
+        assert_eq!(generate::<u32>(&rng).unwrap().expose(), 0x0403_0201);
+        assert_eq!(generate::<u64>(&rng).unwrap().expose(), 0x0807_0605_0403_0201);
+    }
+
+    #[test]
+    fn test_generate_in_range() {
+        // 2**32 % 3 == 1, so for a range of length 3 only 0xffff_ffff is
+        // rejected.
+        let rng = test::rand::FixedSliceSequenceRandom::new(&[
+            &[0xff, 0xff, 0xff, 0xff],
+            &[0xfe, 0xff, 0xff, 0xff],
+        ]);
+        assert_eq!(generate_in_range(&rng, 10..13u32).unwrap().expose(), 10 + (0xffff_fffe % 3));
+
+        let rng = test::rand::FixedByteRandom { byte: 0xff };
+        assert_eq!(generate_in_range(&rng, 5..6u64).unwrap().expose(), 5);
+        assert_eq!(generate_in_range(&rng, 0..256u32).unwrap().expose(), 255);
+        assert!(generate_in_range(&rng, 5..5u32).is_err());
+        assert!(generate_in_range(&rng, Range { start: 6, end: 5u32 }).is_err());
+    }
+}
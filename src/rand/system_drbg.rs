@@ -0,0 +1,171 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use super::{CtrDrbg, SecureRandom, SystemRandom, DRBG_MAX_REQUEST_LEN};
+use crate::{c, error, private};
+use core::cell::RefCell;
+
+const ENTROPY_INPUT_LEN: usize = 256 / 8;
+const NONCE_LEN: usize = 128 / 8;
+
+/// A `CtrDrbg` that is seeded, and automatically reseeded, from
+/// `SystemRandom`.
+///
+/// This is for when the random bytes for many small requests should come
+/// from a DRBG instead of directly from the operating system. It is reseeded
+/// whenever the `CtrDrbg` requires it, and, on Unix-like systems, by the
+/// first `fill()` in a child process after a `fork()`, so that the children
+/// of a pre-forking server don't produce the same bytes as each other or as
+/// their parent. The processes are told apart by their process IDs, so a
+/// `SystemDrbg` must not be used in a process whose ancestor with the same
+/// process ID used it.
+///
+/// A `SystemDrbg` can't be shared between threads.
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<ring::rand::SystemDrbg>();
+/// ```
+pub struct SystemDrbg {
+    inner: RefCell<Inner>,
+}
+
+struct Inner {
+    drbg: CtrDrbg,
+
+    // The process ID when `drbg` was last (re)seeded.
+    process_id: c::int,
+}
+
+impl core::fmt::Debug for SystemDrbg {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+        f.write_str("SystemDrbg")
+    }
+}
+
+impl SystemDrbg {
+    /// Instantiates the DRBG with entropy input and a nonce from
+    /// `SystemRandom`.
+    pub fn new() -> Result<Self, error::Unspecified> {
+        let mut seed = [0; ENTROPY_INPUT_LEN + NONCE_LEN];
+        SystemRandom::new().fill(&mut seed)?;
+        let (entropy_input, nonce) = seed.split_at(ENTROPY_INPUT_LEN);
+        let drbg = CtrDrbg::new(entropy_input, nonce, &[])?;
+        Ok(Self {
+            inner: RefCell::new(Inner {
+                drbg,
+                process_id: process_id(),
+            }),
+        })
+    }
+
+    /// Reseeds the DRBG with entropy input from `SystemRandom`.
+    pub fn reseed(&self) -> Result<(), error::Unspecified> {
+        let mut inner = self.inner.try_borrow_mut().map_err(|_| error::Unspecified)?;
+        inner.reseed()
+    }
+}
+
+impl SecureRandom for SystemDrbg {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), error::Unspecified> {
+        let mut inner = self.inner.try_borrow_mut().map_err(|_| error::Unspecified)?;
+        if inner.process_id != process_id() {
+            inner.reseed()?;
+        }
+        for chunk in dest.chunks_mut(DRBG_MAX_REQUEST_LEN) {
+            if inner.drbg.reseed_required() {
+                inner.reseed()?;
+            }
+            inner.drbg.generate(chunk, &[])?;
+        }
+        Ok(())
+    }
+}
+
+impl private::Sealed for SystemDrbg {}
+
+impl Inner {
+    fn reseed(&mut self) -> Result<(), error::Unspecified> {
+        let mut entropy_input = [0; ENTROPY_INPUT_LEN];
+        SystemRandom::new().fill(&mut entropy_input)?;
+        self.drbg.reseed(&entropy_input, &[])?;
+        self.process_id = process_id();
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+pub(super) fn process_id() -> c::int {
+    extern "C" {
+        fn getpid() -> c::int;
+    }
+    unsafe { getpid() }
+}
+
+// There is no `fork()` to detect.
+#[cfg(not(unix))]
+pub(super) fn process_id() -> c::int { 0 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reseed_after_fork() {
+        let rng = SystemDrbg::new().unwrap();
+        let mut a = [0u8; 32];
+        rng.fill(&mut a).unwrap();
+        rng.reseed().unwrap();
+
+        // Pretend that this is a child process of the process that last
+        // reseeded the DRBG.
+        rng.inner.borrow_mut().process_id ^= 1;
+        let mut b = [0u8; 32];
+        rng.fill(&mut b).unwrap();
+        assert_eq!(rng.inner.borrow().process_id, process_id());
+        assert_ne!(a, b);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_fork() {
+        let rng = SystemDrbg::new().unwrap();
+        let mut parent = [0u8; 32];
+        let mut child = [0u8; 32];
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        match unsafe { libc::fork() } {
+            0 => {
+                // Only do async-signal-safe things other than `fill()` in the
+                // child.
+                let written = match rng.fill(&mut child) {
+                    Ok(()) => unsafe { libc::write(fds[1], child.as_ptr() as *const _, 32) },
+                    Err(error::Unspecified) => -1,
+                };
+                unsafe { libc::_exit(if written == 32 { 0 } else { 1 }) };
+            },
+            pid => {
+                assert!(pid > 0);
+                rng.fill(&mut parent).unwrap();
+                let read = unsafe { libc::read(fds[0], child.as_mut_ptr() as *mut _, 32) };
+                assert_eq!(read, 32);
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                assert_eq!(status, 0);
+            },
+        }
+        assert_ne!(parent, child);
+    }
+}
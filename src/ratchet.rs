@@ -0,0 +1,561 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The [Double Ratchet] with header encryption, using X25519, SHA-256, and
+//! ChaCha20-Poly1305.
+//!
+//! The parties start from secrets they have agreed on, e.g. with X3DH: the
+//! root key and the first two header keys of `SharedKeys`. The initiator
+//! also knows the responder's ratchet public key, and sends first.
+//!
+//! * `KDF_RK(rk, dh_out)` is HKDF-SHA256 with salt `rk`, secret `dh_out`, and
+//!   info `"ring ratchet root"`, giving the new root key, the chain key, and
+//!   the next header key, 32 bytes each.
+//! * `KDF_CK(ck)` gives the message key `HMAC-SHA256(ck, 0x01)` and the next
+//!   chain key `HMAC-SHA256(ck, 0x02)`.
+//! * A message is encrypted with ChaCha20-Poly1305, using the key and the
+//!   nonce given by HKDF-SHA256 with a salt of 32 zero bytes, secret `mk`,
+//!   and info `"ring ratchet message"`. The associated data is the encrypted
+//!   header followed by `SHA-256(associated_data)`.
+//! * A header, the sender's ratchet public key followed by `PN` and `N` as
+//!   4-byte big-endian integers, is encrypted with ChaCha20-Poly1305 under the
+//!   header key and a random nonce, which precedes it; a header key is used
+//!   for many headers.
+//!
+//! The keys of messages that were skipped, because they haven't arrived yet,
+//! are kept in a `SkippedMessageKeys` store, which the caller provides so
+//! that it can keep them however it keeps the rest of its state.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{ratchet, rand};
+//!
+//! # fn exchange() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! // In practice, these come from the key agreement, e.g. X3DH, and
+//! // `bob_key_pair` is Bob's signed prekey.
+//! let shared_keys = ratchet::SharedKeys {
+//!     root_key: &[1; ratchet::KEY_LEN],
+//!     initiator_header_key: &[2; ratchet::KEY_LEN],
+//!     responder_next_header_key: &[3; ratchet::KEY_LEN],
+//! };
+//! let bob_key_pair = ratchet::KeyPair::generate(&rng)?;
+//! let bob_public_key = bob_key_pair.public_key().to_vec();
+//!
+//! let mut alice = ratchet::Ratchet::new_initiator(&shared_keys, &bob_public_key, &rng)?;
+//! let mut bob = ratchet::Ratchet::new_responder(&shared_keys, bob_key_pair);
+//!
+//! let mut header = [0; ratchet::ENCRYPTED_HEADER_LEN];
+//! let mut in_out = b"hello, Bob\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0".to_vec();
+//! alice.encrypt_in_place(b"ad", &mut header, &mut in_out, &rng)?;
+//!
+//! let mut skipped = ratchet::SkippedMessageKeyStore::new(1000);
+//! let plaintext = bob.decrypt_in_place(b"ad", &header, &mut in_out, &mut skipped, &rng)?;
+//! assert_eq!(plaintext, b"hello, Bob");
+//! # Ok(())
+//! # }
+//! # fn main() { exchange().unwrap() }
+//! ```
+//!
+//! [Double Ratchet]: https://signal.org/docs/specifications/doubleratchet/
+
+use crate::{aead, agreement, digest, ec, error, hkdf, hmac, rand, zeroize::Zeroizing};
+use untrusted;
+
+#[cfg(feature = "alloc")]
+use {crate::zeroize, alloc::vec::Vec};
+
+/// The length of a root key, chain key, header key, or message key.
+pub const KEY_LEN: usize = 32;
+
+/// The length of a ratchet public key.
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// The length of the tag that follows the ciphertext of a message.
+pub const TAG_LEN: usize = 16;
+
+const NONCE_LEN: usize = 12;
+
+const HEADER_LEN: usize = PUBLIC_KEY_LEN + 4 + 4;
+
+/// The length of an encrypted header.
+pub const ENCRYPTED_HEADER_LEN: usize = NONCE_LEN + HEADER_LEN + TAG_LEN;
+
+/// The most messages of a single chain that may be skipped.
+pub const MAX_SKIP: u32 = 1000;
+
+/// The secrets that the parties have agreed on before the exchange.
+pub struct SharedKeys<'a> {
+    /// The first root key, `SK`.
+    pub root_key: &'a [u8; KEY_LEN],
+
+    /// The header key of the initiator's first sending chain.
+    pub initiator_header_key: &'a [u8; KEY_LEN],
+
+    /// The header key of the responder's first sending chain.
+    pub responder_next_header_key: &'a [u8; KEY_LEN],
+}
+
+/// An X25519 ratchet key pair.
+pub struct KeyPair {
+    private_key: ec::PrivateKey,
+    public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+impl KeyPair {
+    /// Generates a new key pair.
+    pub fn generate(rng: &rand::SecureRandom) -> Result<Self, error::Unspecified> {
+        let private_key = ec::PrivateKey::generate(agreement::X25519.i.curve, rng)?;
+        Self::new(private_key)
+    }
+
+    /// Constructs a key pair from the 32-byte private key.
+    pub fn from_private_key(private_key: &[u8]) -> Result<Self, error::Unspecified> {
+        let private_key = untrusted::Input::from(private_key);
+        let private_key = ec::PrivateKey::from_bytes(agreement::X25519.i.curve, private_key)?;
+        Self::new(private_key)
+    }
+
+    fn new(private_key: ec::PrivateKey) -> Result<Self, error::Unspecified> {
+        let mut public_key = [0; PUBLIC_KEY_LEN];
+        private_key.compute_public_key(agreement::X25519.i.curve, &mut public_key)?;
+        Ok(Self {
+            private_key,
+            public_key,
+        })
+    }
+
+    /// The public key.
+    #[inline]
+    pub fn public_key(&self) -> &[u8] { &self.public_key }
+
+    fn agree(
+        &self, root_key: &[u8; KEY_LEN], peer_public_key: &[u8; PUBLIC_KEY_LEN],
+    ) -> Result<RootKeys, error::Unspecified> {
+        let mut dh_out = Zeroizing::new([0; PUBLIC_KEY_LEN]);
+        (agreement::X25519.i.ecdh)(
+            &mut *dh_out,
+            &self.private_key,
+            untrusted::Input::from(&peer_public_key[..]),
+        )?;
+        Ok(kdf_rk(root_key, &*dh_out))
+    }
+}
+
+/// Storage for the keys of skipped messages.
+///
+/// The keys of a receiving chain are filed under the chain's header key,
+/// which is also what identifies a message's chain once its header has been
+/// decrypted.
+pub trait SkippedMessageKeys {
+    /// Stores the key of message `n` of the chain with the header key
+    /// `header_key`.
+    fn insert(
+        &mut self, header_key: &[u8; KEY_LEN], n: u32, message_key: &[u8; KEY_LEN],
+    ) -> Result<(), error::Unspecified>;
+
+    /// Calls `decrypt_header` with each header key under which keys are
+    /// stored, until it returns `Some(n)`, meaning that the header was
+    /// encrypted with that key and has the message number `n`. Then removes
+    /// and returns the key of message `n` of that header key's chain, or
+    /// returns `None` if there isn't one.
+    fn take(
+        &mut self, decrypt_header: &mut FnMut(&[u8; KEY_LEN]) -> Option<u32>,
+    ) -> Option<[u8; KEY_LEN]>;
+}
+
+/// A `SkippedMessageKeys` store in memory, which keeps up to a given number
+/// of keys, dropping the oldest one to make room for a new one.
+#[cfg(feature = "alloc")]
+pub struct SkippedMessageKeyStore {
+    entries: Vec<([u8; KEY_LEN], u32, [u8; KEY_LEN])>,
+    max_len: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl SkippedMessageKeyStore {
+    /// Constructs an empty store of up to `max_len` keys.
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            max_len,
+        }
+    }
+
+    /// The number of keys stored.
+    #[inline]
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    /// Whether no keys are stored.
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+}
+
+#[cfg(feature = "alloc")]
+impl SkippedMessageKeys for SkippedMessageKeyStore {
+    fn insert(
+        &mut self, header_key: &[u8; KEY_LEN], n: u32, message_key: &[u8; KEY_LEN],
+    ) -> Result<(), error::Unspecified> {
+        if self.max_len == 0 {
+            return Err(error::Unspecified);
+        }
+        if self.entries.len() == self.max_len {
+            let (mut header_key, _, mut message_key) = self.entries.remove(0);
+            zeroize::zeroize(&mut header_key);
+            zeroize::zeroize(&mut message_key);
+        }
+        self.entries.push((*header_key, n, *message_key));
+        Ok(())
+    }
+
+    fn take(
+        &mut self, decrypt_header: &mut FnMut(&[u8; KEY_LEN]) -> Option<u32>,
+    ) -> Option<[u8; KEY_LEN]> {
+        // The keys of a chain are inserted together, so only the first of a
+        // run of entries with the same header key needs to be tried.
+        let mut previous = None;
+        for (header_key, ..) in self.entries.iter() {
+            if previous == Some(header_key) {
+                continue;
+            }
+            previous = Some(header_key);
+            if let Some(n) = decrypt_header(header_key) {
+                let header_key = *header_key;
+                let i = self.entries.iter().position(|&(h, m, _)| h == header_key && m == n)?;
+                let (mut header_key, _, message_key) = self.entries.remove(i);
+                zeroize::zeroize(&mut header_key);
+                return Some(message_key);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for SkippedMessageKeyStore {
+    fn drop(&mut self) {
+        for (header_key, _, message_key) in self.entries.iter_mut() {
+            zeroize::zeroize(header_key);
+            zeroize::zeroize(message_key);
+        }
+    }
+}
+
+/// The state of either party.
+pub struct Ratchet {
+    dh_self: KeyPair,
+    root_key: Zeroizing<[u8; KEY_LEN]>,
+    sending_chain_key: Option<Zeroizing<[u8; KEY_LEN]>>,
+    receiving_chain_key: Option<Zeroizing<[u8; KEY_LEN]>>,
+    sending_header_key: Option<Zeroizing<[u8; KEY_LEN]>>,
+    receiving_header_key: Option<Zeroizing<[u8; KEY_LEN]>>,
+    next_sending_header_key: Zeroizing<[u8; KEY_LEN]>,
+    next_receiving_header_key: Zeroizing<[u8; KEY_LEN]>,
+    sending_n: u32,
+    receiving_n: u32,
+    previous_sending_n: u32,
+}
+
+impl Ratchet {
+    /// Starts the exchange as the initiator, which knows the responder's
+    /// ratchet public key and sends the first message.
+    pub fn new_initiator(
+        shared_keys: &SharedKeys, responder_public_key: &[u8], rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        let responder_public_key = public_key(responder_public_key)?;
+        let dh_self = KeyPair::generate(rng)?;
+        let keys = dh_self.agree(shared_keys.root_key, &responder_public_key)?;
+        Ok(Self {
+            dh_self,
+            root_key: keys.root_key,
+            sending_chain_key: Some(keys.chain_key),
+            receiving_chain_key: None,
+            sending_header_key: Some(Zeroizing::new(*shared_keys.initiator_header_key)),
+            receiving_header_key: None,
+            next_sending_header_key: keys.next_header_key,
+            next_receiving_header_key: Zeroizing::new(*shared_keys.responder_next_header_key),
+            sending_n: 0,
+            receiving_n: 0,
+            previous_sending_n: 0,
+        })
+    }
+
+    /// Starts the exchange as the responder, with the key pair whose public
+    /// key the initiator knows. The responder can't send until it has
+    /// received a message.
+    pub fn new_responder(shared_keys: &SharedKeys, key_pair: KeyPair) -> Self {
+        Self {
+            dh_self: key_pair,
+            root_key: Zeroizing::new(*shared_keys.root_key),
+            sending_chain_key: None,
+            receiving_chain_key: None,
+            sending_header_key: None,
+            receiving_header_key: None,
+            next_sending_header_key: Zeroizing::new(*shared_keys.responder_next_header_key),
+            next_receiving_header_key: Zeroizing::new(*shared_keys.initiator_header_key),
+            sending_n: 0,
+            receiving_n: 0,
+            previous_sending_n: 0,
+        }
+    }
+
+    /// Encrypts the next message.
+    ///
+    /// The plaintext is `in_out[..(in_out.len() - TAG_LEN)]`; it is replaced
+    /// by the ciphertext and the tag is written over the last `TAG_LEN`
+    /// bytes. The encrypted header is written to `encrypted_header`. Both
+    /// must be sent to the peer, along with `associated_data` unless the
+    /// peer already knows it.
+    pub fn encrypt_in_place(
+        &mut self, associated_data: &[u8], encrypted_header: &mut [u8; ENCRYPTED_HEADER_LEN],
+        in_out: &mut [u8], rng: &rand::SecureRandom,
+    ) -> Result<(), error::Unspecified> {
+        let (chain_key, header_key) = match (&self.sending_chain_key, &self.sending_header_key) {
+            (Some(chain_key), Some(header_key)) => (chain_key, header_key),
+            _ => return Err(error::Unspecified),
+        };
+        if in_out.len() < TAG_LEN || self.sending_n == u32::max_value() {
+            return Err(error::Unspecified);
+        }
+        let (next_chain_key, message_key) = kdf_ck(chain_key);
+
+        let mut header = [0; HEADER_LEN];
+        header[..PUBLIC_KEY_LEN].copy_from_slice(&self.dh_self.public_key);
+        header[PUBLIC_KEY_LEN..][..4].copy_from_slice(&self.previous_sending_n.to_be_bytes());
+        header[PUBLIC_KEY_LEN + 4..].copy_from_slice(&self.sending_n.to_be_bytes());
+        {
+            let (nonce, ciphertext) = encrypted_header.split_at_mut(NONCE_LEN);
+            rng.fill(nonce)?;
+            ciphertext[..HEADER_LEN].copy_from_slice(&header);
+            let key = aead::SealingKey::new_chacha20_poly1305(header_key);
+            let _ = aead::seal_in_place(&key, nonce, &[], ciphertext, TAG_LEN)?;
+        }
+
+        let (key, nonce) = message_key_and_nonce(&message_key);
+        let key = aead::SealingKey::new_chacha20_poly1305(&key);
+        let ad = message_ad(associated_data, encrypted_header);
+        let _ = aead::seal_in_place(&key, &nonce, &ad, in_out, TAG_LEN)?;
+
+        self.sending_chain_key = Some(next_chain_key);
+        self.sending_n += 1;
+        Ok(())
+    }
+
+    /// Decrypts a message, returning the plaintext.
+    ///
+    /// `in_out` is the ciphertext followed by the tag. The keys of messages
+    /// that were skipped to reach this one are added to `skipped`, and the
+    /// key of this one is taken from it if the message was skipped earlier.
+    ///
+    /// If decryption fails, the state is unchanged; in particular, a forged
+    /// message doesn't advance the ratchet.
+    pub fn decrypt_in_place<'a>(
+        &mut self, associated_data: &[u8], encrypted_header: &[u8], in_out: &'a mut [u8],
+        skipped: &mut SkippedMessageKeys, rng: &rand::SecureRandom,
+    ) -> Result<&'a mut [u8], error::Unspecified> {
+        let ad = message_ad(associated_data, encrypted_header);
+
+        let mut decrypt_skipped_header =
+            |header_key: &[u8; KEY_LEN]| decrypt_header(header_key, encrypted_header).map(|h| h.n);
+        if let Some(message_key) = skipped.take(&mut decrypt_skipped_header) {
+            return open(&Zeroizing::new(message_key), &ad, in_out);
+        }
+
+        let current_header = self
+            .receiving_header_key
+            .as_ref()
+            .and_then(|header_key| decrypt_header(header_key, encrypted_header));
+        let (header, new_chain) = match current_header {
+            Some(header) => (header, None),
+            None => {
+                let header = decrypt_header(&self.next_receiving_header_key, encrypted_header)
+                    .ok_or(error::Unspecified)?;
+                if header.pn > self.receiving_n.saturating_add(MAX_SKIP) {
+                    return Err(error::Unspecified);
+                }
+                let receiving = self.dh_self.agree(&self.root_key, &header.dh)?;
+                let dh_self = KeyPair::generate(rng)?;
+                let sending = dh_self.agree(&receiving.root_key, &header.dh)?;
+                (header, Some((receiving, dh_self, sending)))
+            },
+        };
+
+        // The chain that the message is in, and the number of its next
+        // message.
+        let (chain_key, header_key, chain_n) = match &new_chain {
+            Some((receiving, ..)) => (&receiving.chain_key, &self.next_receiving_header_key, 0),
+            None => match (&self.receiving_chain_key, &self.receiving_header_key) {
+                (Some(chain_key), Some(header_key)) => (chain_key, header_key, self.receiving_n),
+                _ => return Err(error::Unspecified),
+            },
+        };
+        if header.n < chain_n || header.n - chain_n > MAX_SKIP || header.n == u32::max_value() {
+            return Err(error::Unspecified);
+        }
+        let mut message_chain_key = Zeroizing::new(**chain_key);
+        for _ in chain_n..header.n {
+            message_chain_key = kdf_ck(&message_chain_key).0;
+        }
+        let (next_chain_key, message_key) = kdf_ck(&message_chain_key);
+        let plaintext_len = open(&message_key, &ad, in_out)?.len();
+
+        // The message is authentic, so keep the keys of the messages before
+        // it and then advance the ratchet.
+        if new_chain.is_some() {
+            if let (Some(chain_key), Some(header_key)) =
+                (&self.receiving_chain_key, &self.receiving_header_key)
+            {
+                skip(skipped, chain_key, header_key, self.receiving_n, header.pn)?;
+            }
+        }
+        skip(skipped, chain_key, header_key, chain_n, header.n)?;
+
+        if let Some((receiving, dh_self, sending)) = new_chain {
+            self.previous_sending_n = self.sending_n;
+            self.sending_n = 0;
+            self.sending_header_key = Some(Zeroizing::new(*self.next_sending_header_key));
+            self.receiving_header_key = Some(Zeroizing::new(*self.next_receiving_header_key));
+            self.next_receiving_header_key = receiving.next_header_key;
+            self.dh_self = dh_self;
+            self.root_key = sending.root_key;
+            self.sending_chain_key = Some(sending.chain_key);
+            self.next_sending_header_key = sending.next_header_key;
+        }
+        self.receiving_chain_key = Some(next_chain_key);
+        self.receiving_n = header.n + 1;
+
+        Ok(&mut in_out[..plaintext_len])
+    }
+}
+
+struct RootKeys {
+    root_key: Zeroizing<[u8; KEY_LEN]>,
+    chain_key: Zeroizing<[u8; KEY_LEN]>,
+    next_header_key: Zeroizing<[u8; KEY_LEN]>,
+}
+
+struct Header {
+    dh: [u8; PUBLIC_KEY_LEN],
+    pn: u32,
+    n: u32,
+}
+
+fn public_key(bytes: &[u8]) -> Result<[u8; PUBLIC_KEY_LEN], error::Unspecified> {
+    if bytes.len() != PUBLIC_KEY_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut public_key = [0; PUBLIC_KEY_LEN];
+    public_key.copy_from_slice(bytes);
+    Ok(public_key)
+}
+
+fn kdf_rk(root_key: &[u8; KEY_LEN], dh_out: &[u8]) -> RootKeys {
+    let mut okm = Zeroizing::new([0; 3 * KEY_LEN]);
+    let salt = hmac::SigningKey::new(&digest::SHA256, root_key);
+    hkdf::extract_and_expand(&salt, dh_out, b"ring ratchet root", &mut *okm);
+    let key = |i: usize| {
+        let mut key = Zeroizing::new([0; KEY_LEN]);
+        key.copy_from_slice(&okm[(i * KEY_LEN)..][..KEY_LEN]);
+        key
+    };
+    RootKeys {
+        root_key: key(0),
+        chain_key: key(1),
+        next_header_key: key(2),
+    }
+}
+
+// Returns the next chain key and the message key.
+fn kdf_ck(chain_key: &[u8; KEY_LEN]) -> (Zeroizing<[u8; KEY_LEN]>, Zeroizing<[u8; KEY_LEN]>) {
+    let key = hmac::SigningKey::new(&digest::SHA256, chain_key);
+    let derive = |constant: u8| {
+        let mut r = Zeroizing::new([0; KEY_LEN]);
+        r.copy_from_slice(hmac::sign(&key, &[constant]).as_ref());
+        r
+    };
+    (derive(2), derive(1))
+}
+
+fn message_key_and_nonce(
+    message_key: &[u8; KEY_LEN],
+) -> (Zeroizing<[u8; KEY_LEN]>, [u8; NONCE_LEN]) {
+    let mut okm = Zeroizing::new([0; KEY_LEN + NONCE_LEN]);
+    let salt = hmac::SigningKey::new(&digest::SHA256, &[0; digest::SHA256_OUTPUT_LEN]);
+    hkdf::extract_and_expand(&salt, message_key, b"ring ratchet message", &mut *okm);
+    let mut key = Zeroizing::new([0; KEY_LEN]);
+    key.copy_from_slice(&okm[..KEY_LEN]);
+    let mut nonce = [0; NONCE_LEN];
+    nonce.copy_from_slice(&okm[KEY_LEN..]);
+    (key, nonce)
+}
+
+fn message_ad(
+    associated_data: &[u8], encrypted_header: &[u8],
+) -> [u8; ENCRYPTED_HEADER_LEN + digest::SHA256_OUTPUT_LEN] {
+    let mut ad = [0; ENCRYPTED_HEADER_LEN + digest::SHA256_OUTPUT_LEN];
+    // A header of the wrong length won't decrypt, so it doesn't matter how
+    // it is encoded here.
+    let header_len = core::cmp::min(encrypted_header.len(), ENCRYPTED_HEADER_LEN);
+    ad[..header_len].copy_from_slice(&encrypted_header[..header_len]);
+    ad[ENCRYPTED_HEADER_LEN..]
+        .copy_from_slice(digest::digest(&digest::SHA256, associated_data).as_ref());
+    ad
+}
+
+fn open<'a>(
+    message_key: &[u8; KEY_LEN], ad: &[u8], in_out: &'a mut [u8],
+) -> Result<&'a mut [u8], error::Unspecified> {
+    let (key, nonce) = message_key_and_nonce(message_key);
+    let key = aead::OpeningKey::new_chacha20_poly1305(&key);
+    aead::open_in_place(&key, &nonce, ad, 0, in_out)
+}
+
+fn decrypt_header(header_key: &[u8; KEY_LEN], encrypted_header: &[u8]) -> Option<Header> {
+    if encrypted_header.len() != ENCRYPTED_HEADER_LEN {
+        return None;
+    }
+    let mut in_out = [0; ENCRYPTED_HEADER_LEN];
+    in_out.copy_from_slice(encrypted_header);
+    let (nonce, ciphertext) = in_out.split_at_mut(NONCE_LEN);
+    let key = aead::OpeningKey::new_chacha20_poly1305(header_key);
+    let header = aead::open_in_place(&key, nonce, &[], 0, ciphertext).ok()?;
+    let mut dh = [0; PUBLIC_KEY_LEN];
+    dh.copy_from_slice(&header[..PUBLIC_KEY_LEN]);
+    let number = |i: usize| {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&header[(PUBLIC_KEY_LEN + (4 * i))..][..4]);
+        u32::from_be_bytes(bytes)
+    };
+    Some(Header {
+        dh,
+        pn: number(0),
+        n: number(1),
+    })
+}
+
+// Stores the keys of messages `from..until` of the chain whose next message
+// is `from`.
+fn skip(
+    skipped: &mut SkippedMessageKeys, chain_key: &[u8; KEY_LEN], header_key: &[u8; KEY_LEN],
+    from: u32, until: u32,
+) -> Result<(), error::Unspecified> {
+    let mut chain_key = Zeroizing::new(*chain_key);
+    for n in from..until {
+        let (next_chain_key, message_key) = kdf_ck(&chain_key);
+        skipped.insert(header_key, n, &message_key)?;
+        chain_key = next_chain_key;
+    }
+    Ok(())
+}
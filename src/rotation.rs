@@ -0,0 +1,290 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Enforcing the rotation of long-lived keys.
+//!
+//! A `Managed` key is an `aead::SealingKey` or an `hmac::SigningKey` with a
+//! rotation `Policy`: when the key was created, when it should be rotated,
+//! when it expires, and how many times it may be used. `needs_rotation()`
+//! tells when a replacement key should be put into service, and once the key
+//! has expired or has been used the maximum number of times, sealing and
+//! signing with it fail.
+//!
+//! Opening and verifying aren't limited, so that data that was protected
+//! before the key expired can still be read while it is being migrated to the
+//! new key; use the `aead::OpeningKey` or `hmac::verify_with_own_key()`
+//! directly for that.
+//!
+//! *ring* doesn't read the time itself, because not every target has a
+//! clock. Each `Managed` key is given a `Clock` to read it from instead, e.g.
+//! `system_clock` when the `std` feature is enabled.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{aead, rotation};
+//!
+//! # fn seal() -> Result<(), ring::error::Unspecified> {
+//! let now = rotation::system_clock();
+//! let policy = rotation::Policy::new(now)
+//!     .rotate_after(now.plus_seconds(30 * 24 * 60 * 60))
+//!     .not_after(now.plus_seconds(90 * 24 * 60 * 60))
+//!     .max_uses(1 << 32);
+//! let key = aead::SealingKey::new(&aead::AES_256_GCM, &[0; 32])?;
+//! let key = rotation::Managed::new(key, policy, rotation::system_clock);
+//!
+//! let mut in_out = b"hello, world".to_vec();
+//! in_out.extend_from_slice(&[0; aead::MAX_TAG_LEN]);
+//! let _ = key.seal_in_place(&[0; 12], b"", &mut in_out, aead::MAX_TAG_LEN)?;
+//! assert!(!key.needs_rotation());
+//! # Ok(())
+//! # }
+//! # fn main() { seal().unwrap() }
+//! ```
+
+use crate::{aead, error, hmac};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// A point in time, in seconds since the Unix epoch.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Time(u64);
+
+impl Time {
+    /// The time `seconds` seconds after the Unix epoch.
+    #[inline]
+    pub fn from_seconds_since_unix_epoch(seconds: u64) -> Self { Time(seconds) }
+
+    /// The number of seconds since the Unix epoch.
+    #[inline]
+    pub fn seconds_since_unix_epoch(&self) -> u64 { self.0 }
+
+    /// The time `seconds` seconds later, saturating at the latest
+    /// representable time.
+    #[inline]
+    pub fn plus_seconds(&self, seconds: u64) -> Self { Time(self.0.saturating_add(seconds)) }
+}
+
+/// A function that returns the current time.
+pub type Clock = fn() -> Time;
+
+/// A `Clock` that reads the system time.
+///
+/// Times before the Unix epoch are returned as the epoch itself.
+#[cfg(feature = "std")]
+pub fn system_clock() -> Time {
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    Time(since_epoch.as_secs())
+}
+
+/// When a key was created and when it must be rotated.
+///
+/// A policy without limits, as returned by `Policy::new()`, doesn't restrict
+/// the key at all.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Policy {
+    created: Time,
+    rotate_after: Option<Time>,
+    not_after: Option<Time>,
+    max_uses: Option<u64>,
+}
+
+impl Policy {
+    /// A policy for a key that was created at `created`, without any limits.
+    #[inline]
+    pub fn new(created: Time) -> Self {
+        Policy {
+            created,
+            rotate_after: None,
+            not_after: None,
+            max_uses: None,
+        }
+    }
+
+    /// The key should be rotated from `time` on, but can still be used.
+    #[inline]
+    pub fn rotate_after(self, time: Time) -> Self {
+        Policy {
+            rotate_after: Some(time),
+            ..self
+        }
+    }
+
+    /// The key can't be used after `time`.
+    #[inline]
+    pub fn not_after(self, time: Time) -> Self {
+        Policy {
+            not_after: Some(time),
+            ..self
+        }
+    }
+
+    /// The key can't be used more than `max_uses` times.
+    #[inline]
+    pub fn max_uses(self, max_uses: u64) -> Self {
+        Policy {
+            max_uses: Some(max_uses),
+            ..self
+        }
+    }
+
+    /// When the key was created.
+    #[inline]
+    pub fn created(&self) -> Time { self.created }
+
+    /// Whether the key can be used at `now` when it has been used `uses`
+    /// times before.
+    fn allows(&self, now: Time, uses: u64) -> bool {
+        self.not_after.map_or(true, |not_after| now <= not_after)
+            && self.max_uses.map_or(true, |max_uses| uses < max_uses)
+    }
+
+    /// Whether the key should be rotated at `now` when it has been used
+    /// `uses` times.
+    fn needs_rotation(&self, now: Time, uses: u64) -> bool {
+        !self.allows(now, uses) || self.rotate_after.map_or(false, |rotate| now >= rotate)
+    }
+}
+
+/// A key whose use is limited by a rotation `Policy`.
+pub struct Managed<K> {
+    key: K,
+    policy: Policy,
+    clock: Clock,
+    uses: AtomicU64,
+}
+
+impl<K> core::fmt::Debug for Managed<K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+        f.debug_struct("Managed")
+            .field("policy", &self.policy)
+            .field("uses", &self.uses())
+            .finish()
+    }
+}
+
+impl<K> Managed<K> {
+    /// Limits the use of `key` by `policy`, reading the time from `clock`.
+    pub fn new(key: K, policy: Policy, clock: Clock) -> Self {
+        Managed {
+            key,
+            policy,
+            clock,
+            uses: AtomicU64::new(0),
+        }
+    }
+
+    /// The key's policy.
+    #[inline]
+    pub fn policy(&self) -> &Policy { &self.policy }
+
+    /// How many times the key has been used, including the uses that were
+    /// refused.
+    #[inline]
+    pub fn uses(&self) -> u64 { self.uses.load(Ordering::Relaxed) }
+
+    /// Whether the key should be replaced, because it has expired or has
+    /// been used the maximum number of times, or because it is due to be
+    /// rotated.
+    pub fn needs_rotation(&self) -> bool {
+        self.policy.needs_rotation((self.clock)(), self.uses())
+    }
+
+    /// Counts a use of the key, failing if the policy doesn't allow it.
+    fn use_key(&self) -> Result<&K, error::Unspecified> {
+        let now = (self.clock)();
+        let uses = self.uses.fetch_add(1, Ordering::Relaxed);
+        if !self.policy.allows(now, uses) {
+            return Err(error::Unspecified);
+        }
+        Ok(&self.key)
+    }
+}
+
+impl Managed<aead::SealingKey> {
+    /// Like `aead::seal_in_place()`, but fails if the policy doesn't allow
+    /// another use of the key.
+    pub fn seal_in_place(
+        &self, nonce: &[u8], ad: &[u8], in_out: &mut [u8], out_suffix_capacity: usize,
+    ) -> Result<usize, error::Unspecified> {
+        aead::seal_in_place(self.use_key()?, nonce, ad, in_out, out_suffix_capacity)
+    }
+}
+
+impl Managed<hmac::SigningKey> {
+    /// Like `hmac::sign()`, but fails if the policy doesn't allow another use
+    /// of the key.
+    pub fn sign(&self, data: &[u8]) -> Result<hmac::Signature, error::Unspecified> {
+        Ok(hmac::sign(self.use_key()?, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest;
+
+    static NOW: AtomicU64 = AtomicU64::new(1000);
+
+    fn test_clock() -> Time { Time(NOW.load(Ordering::SeqCst)) }
+
+    #[test]
+    fn test_policy() {
+        let policy = Policy::new(Time(1000))
+            .rotate_after(Time(2000))
+            .not_after(Time(3000))
+            .max_uses(10);
+        assert_eq!(policy.created(), Time(1000));
+        assert!(policy.allows(Time(1000), 0));
+        assert!(policy.allows(Time(3000), 9));
+        assert!(!policy.allows(Time(3001), 0));
+        assert!(!policy.allows(Time(1000), 10));
+
+        assert!(!policy.needs_rotation(Time(1999), 9));
+        assert!(policy.needs_rotation(Time(2000), 0));
+        assert!(policy.needs_rotation(Time(1000), 10));
+
+        let unlimited = Policy::new(Time(0));
+        assert!(unlimited.allows(Time(u64::max_value()), u64::max_value()));
+        assert!(!unlimited.needs_rotation(Time(u64::max_value()), u64::max_value()));
+    }
+
+    #[test]
+    fn test_managed() {
+        // The tests share `NOW`, so only this one changes it.
+        let policy = Policy::new(Time(1000)).not_after(Time(2000)).max_uses(3);
+        let key = Managed::new(hmac::SigningKey::new(&digest::SHA256, b"key"), policy, test_clock);
+        for _ in 0..3 {
+            assert_eq!(
+                key.sign(b"data").unwrap().as_ref(),
+                hmac::sign(&key.key, b"data").as_ref()
+            );
+        }
+        assert!(key.needs_rotation());
+        assert!(key.sign(b"data").is_err());
+        assert_eq!(key.uses(), 4);
+
+        let key = aead::SealingKey::new(&aead::CHACHA20_POLY1305, &[0; 32]).unwrap();
+        let policy = Policy::new(Time(1000)).not_after(Time(2000));
+        let key = Managed::new(key, policy, test_clock);
+        let mut in_out = [0u8; 16 + aead::MAX_TAG_LEN];
+        assert!(key.seal_in_place(&[0; 12], &[], &mut in_out, aead::MAX_TAG_LEN).is_ok());
+        assert!(!key.needs_rotation());
+        NOW.store(2001, Ordering::SeqCst);
+        assert!(key.needs_rotation());
+        assert!(key.seal_in_place(&[0; 12], &[], &mut in_out, aead::MAX_TAG_LEN).is_err());
+        NOW.store(1000, Ordering::SeqCst);
+    }
+}
@@ -18,7 +18,7 @@
 // components.
 
 /// RSA signatures.
-use crate::{bits, der, error, limb};
+use crate::{bits, error, io::der, limb};
 use untrusted;
 
 mod padding;
@@ -37,7 +37,12 @@ const PUBLIC_KEY_PUBLIC_MODULUS_MAX_LEN: usize = bigint::MODULUS_MAX_LIMBS * lim
 
 // Keep in sync with the documentation comment for `KeyPair`.
 #[cfg(feature = "rsa_signing")]
-const PRIVATE_KEY_PUBLIC_MODULUS_MAX_BITS: bits::BitLength = bits::BitLength::from_usize_bits(4096);
+const PRIVATE_KEY_PUBLIC_MODULUS_MAX_BITS: bits::BitLength =
+    bits::BitLength::from_usize_bits(if crate::spki::RSA_MODULUS_MAX_BITS < 4096 {
+        crate::spki::RSA_MODULUS_MAX_BITS
+    } else {
+        4096
+    });
 
 /// Parameters for RSA verification.
 pub struct RSAParameters {
@@ -57,7 +62,7 @@ enum RSAParametersID {
     RSA_PSS_2048_8192_SHA512,
 }
 
-fn parse_public_key(
+pub(crate) fn parse_public_key(
     input: untrusted::Input,
 ) -> Result<(untrusted::Input, untrusted::Input), error::Unspecified> {
     input.read_all(error::Unspecified, |input| {
@@ -79,4 +84,4 @@ pub mod verification;
 #[cfg(feature = "rsa_signing")]
 pub mod signing;
 
-mod bigint;
+pub(crate) mod bigint;
@@ -19,11 +19,13 @@ use super::{
 /// RSA PKCS#1 1.5 signatures.
 use crate::{
     arithmetic::montgomery::R,
-    bits, der, digest,
+    bits, digest,
     error::{self, KeyRejected},
-    pkcs8, rand,
+    io::der,
+    locked::LockedSlice,
+    pem, pkcs8, rand, spki, zeroize,
 };
-use std;
+use alloc::vec::Vec;
 use untrusted;
 
 /// An RSA key pair, used for signing. Feature: `rsa_signing`.
@@ -39,6 +41,8 @@ pub struct KeyPair {
     qq: bigint::Modulus<QQ>,
     q_mod_n: bigint::Elem<N, R>,
     public_key: verification::Key,
+    public_key_encoded: spki::PublicKey,
+    pkcs1_der: LockedSlice<u8>,
 }
 
 derive_debug_via_self!(KeyPair, self.public_key);
@@ -48,8 +52,9 @@ impl KeyPair {
     ///
     /// Only two-prime (not multi-prime) keys are supported. The public modulus
     /// (n) must be at least 2047 bits. The public modulus must be no larger
-    /// than 4096 bits. It is recommended that the public modulus be exactly
-    /// 2048 or 3072 bits. The public exponent must be at least 65537.
+    /// than 4096 bits, or than the limit set by the `rsa_max_bits_*` features.
+    /// It is recommended that the public modulus be exactly 2048 or 3072 bits.
+    /// The public exponent must be at least 65537.
     ///
     /// This will generate a 2048-bit RSA private key of the correct form using
     /// OpenSSL's command line tool:
@@ -70,6 +75,11 @@ impl KeyPair {
     ///      openssl pkcs8 -topk8 -nocrypt -outform der > rsa-2048-private-key.pk8
     /// ```
     ///
+    /// *ring* doesn't implement RSA key generation, so there is no prime
+    /// search in *ring* to spread across threads; the time it takes to
+    /// generate a key, which for 4096-bit keys can be many seconds, is spent
+    /// in whatever tool generates it.
+    ///
     /// Often, keys generated for use in OpenSSL-based software are stored in
     /// the Base64 “PEM” format without the PKCS#8 wrapper. Such keys can be
     /// converted to binary PKCS#8 form using the OpenSSL command line tool like
@@ -143,15 +153,54 @@ impl KeyPair {
     pub fn from_pkcs8(input: untrusted::Input) -> Result<Self, KeyRejected> {
         const RSA_ENCRYPTION: &[u8] = include_bytes!("../data/alg-rsa-encryption.der");
         let (der, _) = pkcs8::unwrap_key_(&RSA_ENCRYPTION, pkcs8::Version::V1Only, input)?;
-        Self::from_der(der)
+        Self::from_pkcs1_der(der)
+    }
+
+    /// Like `from_pkcs8()`, but the PKCS#8 document is PEM-encoded with the
+    /// label `PRIVATE KEY`.
+    pub fn from_pkcs8_pem(input: &str) -> Result<Self, KeyRejected> {
+        pem::decode_private_key(input, pem::PRIVATE_KEY, Self::from_pkcs8)
+    }
+
+    /// Like `from_pkcs8()`, but takes ownership of the PKCS#8 document so
+    /// that it is overwritten when it is no longer needed.
+    #[cfg(feature = "zeroize")]
+    pub fn from_pkcs8_zeroizing(input: ::zeroize::Zeroizing<Vec<u8>>) -> Result<Self, KeyRejected> {
+        Self::from_pkcs8(untrusted::Input::from(&input[..]))
+    }
+
+    /// Like `from_pkcs8()`, but the PKCS#8 document is encrypted with
+    /// `password` as described in `pkcs8::decrypt()`.
+    pub fn from_encrypted_pkcs8(
+        input: untrusted::Input, password: &[u8],
+    ) -> Result<Self, KeyRejected> {
+        let der = pkcs8::decrypt(input, password)?;
+        Self::from_pkcs8(untrusted::Input::from(&der))
+    }
+
+    /// Parses an RSA private key that is either a PKCS#8 document or a bare
+    /// `RSAPrivateKey`.
+    ///
+    /// The two forms are distinguished by the field that follows the leading
+    /// version number: a PKCS#8 `PrivateKeyInfo` has an `AlgorithmIdentifier`
+    /// there, whereas an `RSAPrivateKey` has the modulus. If the input looks
+    /// like PKCS#8 then this is equivalent to `from_pkcs8()`; otherwise it is
+    /// equivalent to `from_pkcs1_der()`.
+    pub fn from_der(input: untrusted::Input) -> Result<Self, KeyRejected> {
+        if is_pkcs8(input) {
+            Self::from_pkcs8(input)
+        } else {
+            Self::from_pkcs1_der(input)
+        }
     }
 
     /// Parses an RSA private key that is not inside a PKCS#8 wrapper.
     ///
     /// The private key must be encoded as a binary DER-encoded ASN.1
-    /// `RSAPrivateKey` as described in [RFC 3447 Appendix A.1.2]). In all other
-    /// respects, this is just like `from_pkcs8()`. See the documentation for
-    /// `from_pkcs8()` for more details.
+    /// `RSAPrivateKey` as described in [RFC 3447 Appendix A.1.2]), which is
+    /// what `openssl genrsa` and `openssl rsa -outform der` produce. In all
+    /// other respects, this is just like `from_pkcs8()`. See the documentation
+    /// for `from_pkcs8()` for more details.
     ///
     /// It is recommended to use `from_pkcs8()` (with a PKCS#8-encoded key)
     /// instead.
@@ -161,18 +210,31 @@ impl KeyPair {
     ///
     /// [NIST SP-800-56B rev. 1]:
     ///     http://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-56Br1.pdf
-    pub fn from_der(input: untrusted::Input) -> Result<Self, KeyRejected> {
-        input.read_all(KeyRejected::invalid_encoding(), |input| {
+    pub fn from_pkcs1_der(input: untrusted::Input) -> Result<Self, KeyRejected> {
+        input.read_all(KeyRejected::invalid_encoding(), |reader| {
             der::nested(
-                input,
+                reader,
                 der::Tag::Sequence,
-                error::KeyRejected::invalid_encoding(),
-                Self::from_der_reader,
+                KeyRejected::invalid_encoding(),
+                |reader| Self::from_der_reader(reader, input),
             )
         })
     }
 
-    fn from_der_reader(input: &mut untrusted::Reader) -> Result<Self, KeyRejected> {
+    /// Like `from_der()`, but the `RSAPrivateKey` is PEM-encoded with the
+    /// label `RSA PRIVATE KEY`, as is traditionally done by OpenSSL.
+    ///
+    /// Legacy encrypted keys, which have `Proc-Type` and `DEK-Info` headers,
+    /// are rejected.
+    pub fn from_der_pem(input: &str) -> Result<Self, KeyRejected> {
+        pem::decode_private_key(input, pem::RSA_PRIVATE_KEY, Self::from_pkcs1_der)
+    }
+
+    // `encoded` is the entire `RSAPrivateKey`, including the `SEQUENCE` tag
+    // and length, of which `input` reads the contents.
+    fn from_der_reader(
+        input: &mut untrusted::Reader, encoded: untrusted::Input,
+    ) -> Result<Self, KeyRejected> {
         let version = der::small_nonnegative_integer(input)
             .map_err(|error::Unspecified| KeyRejected::invalid_encoding())?;
         if version != 0 {
@@ -237,6 +299,9 @@ impl KeyPair {
             super::PRIVATE_KEY_PUBLIC_MODULUS_MAX_BITS,
             65537,
         )?;
+        // `n` and `e` can only be encoded now that their lengths are checked.
+        let public_key_encoded =
+            spki::PublicKey::from_rsa_components(n.as_slice_less_safe(), e.as_slice_less_safe());
 
         // 6.4.1.4.3 says to skip 6.4.1.2.1 Step 2.
 
@@ -360,20 +425,134 @@ impl KeyPair {
 
         let qq = bigint::elem_mul(&q_mod_n, q_mod_n_decoded, &public_key.n).into_modulus::<QQ>()?;
 
-        Ok(Self {
+        let mut key_pair = Self {
             p,
             q,
             qInv,
             q_mod_n,
             qq,
             public_key,
-        })
+            public_key_encoded,
+            pkcs1_der: LockedSlice::from(encoded.as_slice_less_safe().to_vec().into_boxed_slice()),
+        };
+        key_pair.lock();
+        Ok(key_pair)
+    }
+
+    /// Moves the private values into locked memory, if possible.
+    fn lock(&mut self) {
+        self.p.lock();
+        self.q.lock();
+        self.qInv.lock();
+        self.q_mod_n.lock();
+        self.qq.lock();
+        self.pkcs1_der.lock();
     }
 
+    /// Returns the public key, which is encoded as an `RSAPublicKey`.
+    pub fn public_key(&self) -> spki::PublicKey { self.public_key_encoded.clone() }
+
+    /// Returns the private key as a DER-encoded `RSAPrivateKey`, the form
+    /// that `from_pkcs1_der()` accepts and that OpenSSL uses by default.
+    ///
+    /// The result is the `RSAPrivateKey` exactly as it was given to the
+    /// constructor; in particular, if `p < q` in the original encoding then
+    /// they are not swapped. The result can be PEM-encoded with
+    /// `pem::encode(pem::RSA_PRIVATE_KEY, &der)`.
+    pub fn to_pkcs1_der(&self) -> Vec<u8> { self.pkcs1_der.to_vec() }
+
     /// Returns the length in bytes of the key pair's public modulus.
     ///
     /// A signature has the same length as the public modulus.
     pub fn public_modulus_len(&self) -> usize { self.public_key.modulus_len() }
+
+    /// Replaces the big-endian-encoded value in `in_out`, which must be
+    /// exactly `public_modulus_len()` bytes and less than the public modulus,
+    /// with its RSA private key operation, i.e. RSASP1 and RSADP.
+    pub(crate) fn private_operation(&self, in_out: &mut [u8]) -> Result<(), error::Unspecified> {
+        if in_out.len() != self.public_modulus_len() {
+            return Err(error::Unspecified);
+        }
+
+        // RFC 8017 Section 5.1.2: RSADP, using the Chinese Remainder Theorem
+        // with Garner's algorithm.
+
+        let n = &self.public_key.n;
+
+        // Step 1. The value zero is also rejected.
+        let base = bigint::Elem::from_be_bytes_padded(untrusted::Input::from(in_out), n)?;
+
+        // Step 2
+        let c = base;
+
+        // Step 2.b.i.
+        let m_1 = elem_exp_consttime(&c, &self.p)?;
+        let c_mod_qq = bigint::elem_reduced_once(&c, &self.qq);
+        let m_2 = elem_exp_consttime(&c_mod_qq, &self.q)?;
+
+        // Step 2.b.ii isn't needed since there are only two primes.
+
+        // Step 2.b.iii.
+        let p = &self.p.modulus;
+        let m_2 = bigint::elem_widen(m_2, p);
+        let m_1_minus_m_2 = bigint::elem_sub(m_1, &m_2, p);
+        let h = bigint::elem_mul(&self.qInv, m_1_minus_m_2, p);
+
+        // Step 2.b.iv. The reduction in the modular multiplication isn't
+        // necessary because `h < p` and `p * q == n` implies `h * q < n`.
+        // Modular arithmetic is used simply to avoid implementing
+        // non-modular arithmetic.
+        let h = bigint::elem_widen(h, n);
+        let q_times_h = bigint::elem_mul(&self.q_mod_n, h, n);
+        let m_2 = bigint::elem_widen(m_2, n);
+        let m = bigint::elem_add(m_2, q_times_h, n);
+
+        // Step 2.b.v isn't needed since there are only two primes.
+
+        // Verify the result to protect against fault attacks as described
+        // in "On the Importance of Checking Cryptographic Protocols for
+        // Faults" by Dan Boneh, Richard A. DeMillo, and Richard J. Lipton.
+        // This check is cheap assuming `e` is small, which is ensured during
+        // `KeyPair` construction. Note that this is the only validation of `e`
+        // that is done other than basic checks on its size, oddness, and
+        // minimum value, since the relationship of `e` to `d`, `p`, and `q` is
+        // not verified during `KeyPair` construction.
+        {
+            let verify = bigint::elem_exp_vartime(m.clone(), self.public_key.e, n);
+            let verify = verify.into_unencoded(n);
+            bigint::elem_verify_equal_consttime(&verify, &c)?;
+        }
+
+        // Step 3.
+        //
+        // See Falko Strenzke, "Manger's Attack revisited", ICICS 2010.
+        m.fill_be_bytes(in_out);
+
+        Ok(())
+    }
+}
+
+// The primes and the values computed from them are zeroized when their limbs
+// are dropped.
+impl Drop for KeyPair {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.pkcs1_der) }
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for KeyPair {}
+
+// Returns true if `input` starts like a PKCS#8 `PrivateKeyInfo`, i.e. a
+// `SEQUENCE` containing an `INTEGER` followed by a `SEQUENCE`. The rest of the
+// input isn't checked; whichever parser is chosen will do that.
+fn is_pkcs8(input: untrusted::Input) -> bool {
+    let mut reader = untrusted::Reader::new(input);
+    let contents = match der::expect_tag_and_get_value(&mut reader, der::Tag::Sequence) {
+        Ok(contents) => contents,
+        Err(error::Unspecified) => return false,
+    };
+    let mut contents = untrusted::Reader::new(contents);
+    der::expect_tag_and_get_value(&mut contents, der::Tag::Integer).is_ok()
+        && contents.peek(der::Tag::Sequence as u8)
 }
 
 struct PrivatePrime<M: Prime> {
@@ -382,6 +561,11 @@ struct PrivatePrime<M: Prime> {
 }
 
 impl<M: Prime + Clone> PrivatePrime<M> {
+    fn lock(&mut self) {
+        self.modulus.lock();
+        self.exponent.lock();
+    }
+
     /// Constructs a `PrivatePrime` from the private prime `p` and `dP` where
     /// dP == d % (p - 1).
     fn new(p: bigint::Nonnegative, dP: untrusted::Input) -> Result<Self, KeyRejected> {
@@ -462,12 +646,12 @@ unsafe impl bigint::NotMuchSmallerModulus<QQ> for Q {}
 //
 // TODO: Remove this; it's not needed if we don't have RSA blinding.
 pub struct SigningState {
-    key_pair: std::sync::Arc<KeyPair>,
+    key_pair: alloc::sync::Arc<KeyPair>,
 }
 
 impl SigningState {
     /// Construct a signing state appropriate for use with the given key pair.
-    pub fn new(key_pair: std::sync::Arc<KeyPair>) -> Result<Self, error::Unspecified> {
+    pub fn new(key_pair: alloc::sync::Arc<KeyPair>) -> Result<Self, error::Unspecified> {
         Ok(SigningState { key_pair })
     }
 
@@ -500,66 +684,9 @@ impl SigningState {
             return Err(error::Unspecified);
         }
 
-        let SigningState { key_pair: key } = self;
-
         let m_hash = digest::digest(padding_alg.digest_alg(), msg);
         padding_alg.encode(&m_hash, signature, mod_bits, rng)?;
-
-        // RFC 8017 Section 5.1.2: RSADP, using the Chinese Remainder Theorem
-        // with Garner's algorithm.
-
-        let n = &key.public_key.n;
-
-        // Step 1. The value zero is also rejected.
-        let base = bigint::Elem::from_be_bytes_padded(untrusted::Input::from(signature), n)?;
-
-        // Step 2
-        let c = base;
-
-        // Step 2.b.i.
-        let m_1 = elem_exp_consttime(&c, &key.p)?;
-        let c_mod_qq = bigint::elem_reduced_once(&c, &key.qq);
-        let m_2 = elem_exp_consttime(&c_mod_qq, &key.q)?;
-
-        // Step 2.b.ii isn't needed since there are only two primes.
-
-        // Step 2.b.iii.
-        let p = &key.p.modulus;
-        let m_2 = bigint::elem_widen(m_2, p);
-        let m_1_minus_m_2 = bigint::elem_sub(m_1, &m_2, p);
-        let h = bigint::elem_mul(&key.qInv, m_1_minus_m_2, p);
-
-        // Step 2.b.iv. The reduction in the modular multiplication isn't
-        // necessary because `h < p` and `p * q == n` implies `h * q < n`.
-        // Modular arithmetic is used simply to avoid implementing
-        // non-modular arithmetic.
-        let h = bigint::elem_widen(h, n);
-        let q_times_h = bigint::elem_mul(&key.q_mod_n, h, n);
-        let m_2 = bigint::elem_widen(m_2, n);
-        let m = bigint::elem_add(m_2, q_times_h, n);
-
-        // Step 2.b.v isn't needed since there are only two primes.
-
-        // Verify the result to protect against fault attacks as described
-        // in "On the Importance of Checking Cryptographic Protocols for
-        // Faults" by Dan Boneh, Richard A. DeMillo, and Richard J. Lipton.
-        // This check is cheap assuming `e` is small, which is ensured during
-        // `KeyPair` construction. Note that this is the only validation of `e`
-        // that is done other than basic checks on its size, oddness, and
-        // minimum value, since the relationship of `e` to `d`, `p`, and `q` is
-        // not verified during `KeyPair` construction.
-        {
-            let verify = bigint::elem_exp_vartime(m.clone(), key.public_key.e, n);
-            let verify = verify.into_unencoded(n);
-            bigint::elem_verify_equal_consttime(&verify, &c)?;
-        }
-
-        // Step 3.
-        //
-        // See Falko Strenzke, "Manger's Attack revisited", ICICS 2010.
-        m.fill_be_bytes(signature);
-
-        Ok(())
+        self.key_pair.private_operation(signature)
     }
 }
 
@@ -78,10 +78,69 @@ impl Key {
     /// Returns the length in bytes of the modulus.
     ///
     /// A signature has the same length as the public modulus.
-    #[cfg(feature = "rsa_signing")]
     pub fn modulus_len(&self) -> usize { self.n_bits.as_usize_bytes_rounded_up() }
 }
 
+/// An RSA public key that has been parsed and validated once, for verifying
+/// many signatures with it.
+///
+/// `signature::verify()` parses the public key and computes the Montgomery
+/// parameters of its modulus for every signature. A `ParsedPublicKey` keeps
+/// them instead, which speeds up repeated verification with the same key,
+/// e.g. the key of a certificate that is used for many connections.
+///
+/// Only available with the `alloc` feature.
+#[derive(Debug)]
+pub struct ParsedPublicKey(Key);
+
+impl ParsedPublicKey {
+    /// Parses a public key encoded as an ASN.1 `RSAPublicKey` (see
+    /// [RFC 3447 Appendix A.1.1]), the encoding that `signature::verify()`
+    /// takes for RSA.
+    ///
+    /// The key is checked to be acceptable for at least one of the
+    /// `RSA_PKCS1_*` and `RSA_PSS_*` algorithms; `verify()` checks that it is
+    /// acceptable for the algorithm that is used.
+    ///
+    /// [RFC 3447 Appendix A.1.1]: https://tools.ietf.org/html/rfc3447#appendix-A.1.1
+    pub fn from_der(input: untrusted::Input) -> Result<Self, error::KeyRejected> {
+        let (n, e) = parse_public_key(input).map_err(|_| error::KeyRejected::invalid_encoding())?;
+        Self::from_modulus_and_exponent(n, e)
+    }
+
+    /// Like `from_der()`, but the modulus `n` and the exponent `e` are given
+    /// separately, like for `signature::primitive::verify_rsa()`.
+    pub fn from_modulus_and_exponent(
+        n: untrusted::Input, e: untrusted::Input,
+    ) -> Result<Self, error::KeyRejected> {
+        let max_bits = bits::BitLength::from_usize_bytes(PUBLIC_KEY_PUBLIC_MODULUS_MAX_LEN)
+            .map_err(|error::Unspecified| error::KeyRejected::unexpected_error())?;
+        let key = Key::from_modulus_and_exponent(n, e, MIN_BITS, max_bits, 3)?;
+        Ok(ParsedPublicKey(key))
+    }
+
+    /// Returns the length in bytes of the modulus, which is also the length of
+    /// the signatures.
+    pub fn modulus_len(&self) -> usize { self.0.modulus_len() }
+
+    /// Verifies the signature `signature` of message `msg`, like
+    /// `signature::verify(params, public_key, msg, signature)`.
+    pub fn verify(
+        &self, params: &RSAParameters, msg: untrusted::Input, signature: untrusted::Input,
+    ) -> Result<(), error::Unspecified> {
+        cpu::cache_detected_features();
+        // Like in `Key::from_modulus_and_exponent()`.
+        let n_bits_rounded_up = bits::BitLength::from_usize_bytes(self.modulus_len())?;
+        if n_bits_rounded_up < params.min_bits {
+            return Err(error::Unspecified);
+        }
+        verify_rsa_key(params, &self.0, msg, signature)
+    }
+}
+
+/// The smallest `min_bits` of all of the `RSAParameters`.
+const MIN_BITS: bits::BitLength = bits::BitLength::from_usize_bits(2048);
+
 impl signature::VerificationAlgorithm for RSAParameters {
     fn verify(
         &self, public_key: untrusted::Input, msg: untrusted::Input, signature: untrusted::Input,
@@ -119,7 +178,7 @@ macro_rules! rsa_params {
       $doc_str:expr ) => {
         #[doc=$doc_str]
         ///
-        /// Only available in `use_heap` mode.
+        /// Only available with the `alloc` feature.
         pub static $VERIFY_ALGORITHM: RSAParameters = RSAParameters {
             padding_alg: $PADDING_ALGORITHM,
             min_bits: bits::BitLength::from_usize_bits($min_bits),
@@ -237,8 +296,14 @@ pub(crate) fn verify_rsa_(
     // exponent value is 2**16 + 1, but it isn't clear if this is just for
     // signing or also for verification. We support exponents of 3 and larger
     // for compatibility with other commonly-used crypto libraries.
-    let Key { n, e, n_bits } = Key::from_modulus_and_exponent(n, e, params.min_bits, max_bits, 3)?;
+    let key = Key::from_modulus_and_exponent(n, e, params.min_bits, max_bits, 3)?;
+    verify_rsa_key(params, &key, msg, signature)
+}
 
+pub(crate) fn verify_rsa_key(
+    params: &RSAParameters, Key { n, e, n_bits }: &Key, msg: untrusted::Input,
+    signature: untrusted::Input,
+) -> Result<(), error::Unspecified> {
     // The signature must be the same length as the modulus, in bytes.
     if signature.len() != n_bits.as_usize_bytes_rounded_up() {
         return Err(error::Unspecified);
@@ -247,14 +312,14 @@ pub(crate) fn verify_rsa_(
     // RFC 8017 Section 5.2.2: RSAVP1.
 
     // Step 1.
-    let s = bigint::Elem::from_be_bytes_padded(signature, &n)?;
+    let s = bigint::Elem::from_be_bytes_padded(signature, n)?;
     if s.is_zero() {
         return Err(error::Unspecified);
     }
 
     // Step 2.
-    let m = bigint::elem_exp_vartime(s, e, &n);
-    let m = m.into_unencoded(&n);
+    let m = bigint::elem_exp_vartime(s, *e, n);
+    let m = m.into_unencoded(n);
 
     // Step 3.
     let mut decoded = [0u8; PUBLIC_KEY_PUBLIC_MODULUS_MAX_LEN];
@@ -264,6 +329,6 @@ pub(crate) fn verify_rsa_(
     // Verify the padded message is correct.
     let m_hash = digest::digest(params.padding_alg.digest_alg(), msg.as_slice_less_safe());
     untrusted::Input::from(decoded).read_all(error::Unspecified, |m| {
-        params.padding_alg.verify(&m_hash, m, n_bits)
+        params.padding_alg.verify(&m_hash, m, *n_bits)
     })
 }
@@ -0,0 +1,255 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Implementations of the traits of the RustCrypto project's `digest`,
+//! `signature`, and `aead` crates, so that libraries that are generic over
+//! those traits can use *ring*.
+//!
+//! * `Sha1`, `Sha256`, `Sha384`, `Sha512`, and `Sha512_256` implement
+//!   `digest::Digest`, via `Update`, `FixedOutput`, and `Reset`.
+//! * `signature::Ed25519KeyPair`, `signature::ECDSAKeyPair`, and
+//!   `signature::KeyPair` implement `signature::Signer<signature::Signature>`,
+//!   and `VerifyingKey` implements `signature::Verifier`. ECDSA signing uses
+//!   `rand::SystemRandom`. `signature::Signature` implements
+//!   `signature::SignatureEncoding`.
+//! * `Aes128Gcm`, `Aes256Gcm`, and `ChaCha20Poly1305` implement
+//!   `aead::KeyInit` and `aead::AeadInPlace`, and so `aead::Aead`.
+//!
+//! The traits' crates are the dependencies `rustcrypto_digest`,
+//! `rustcrypto_signature`, and `rustcrypto_aead`, so that their names don't
+//! collide with *ring*'s modules.
+//!
+//! ```
+//! use ring::rustcrypto::Sha256;
+//! use rustcrypto_digest::Digest;
+//!
+//! fn hash<D: Digest>(data: &[u8]) -> Vec<u8> { D::digest(data).to_vec() }
+//!
+//! assert_eq!(
+//!     &hash::<Sha256>(b"abc")[..],
+//!     ring::digest::digest(&ring::digest::SHA256, b"abc").as_ref()
+//! );
+//! ```
+
+use crate::{aead, digest, error, rand, signature, signature_impl};
+use core::{self, convert::TryFrom};
+use rustcrypto_aead::{
+    consts::{U0, U12, U16, U32},
+    AeadCore, AeadInPlace, KeyInit, KeySizeUser,
+};
+use rustcrypto_digest::{
+    consts::{U20, U48, U64},
+    FixedOutput, FixedOutputReset, HashMarker, OutputSizeUser, Reset, Update,
+};
+use rustcrypto_signature::{SignatureEncoding, Signer, Verifier};
+use untrusted;
+
+macro_rules! hash {
+    ( $name:ident, $algorithm:expr, $output_size:ty, $doc:expr ) => {
+        #[doc = $doc]
+        #[derive(Clone)]
+        pub struct $name(digest::Context);
+
+        derive_debug_via_self!($name, self.0.algorithm());
+
+        impl Default for $name {
+            #[inline]
+            fn default() -> Self { $name(digest::Context::new($algorithm)) }
+        }
+
+        impl HashMarker for $name {}
+
+        impl OutputSizeUser for $name {
+            type OutputSize = $output_size;
+        }
+
+        impl Update for $name {
+            #[inline]
+            fn update(&mut self, data: &[u8]) { self.0.update(data) }
+        }
+
+        impl FixedOutput for $name {
+            fn finalize_into(self, out: &mut rustcrypto_digest::Output<Self>) {
+                out.copy_from_slice(self.0.finish().as_ref())
+            }
+        }
+
+        impl Reset for $name {
+            #[inline]
+            fn reset(&mut self) { *self = Self::default() }
+        }
+
+        impl FixedOutputReset for $name {
+            fn finalize_into_reset(&mut self, out: &mut rustcrypto_digest::Output<Self>) {
+                core::mem::take(self).finalize_into(out)
+            }
+        }
+    };
+}
+
+hash!(Sha1, &digest::SHA1, U20, "SHA-1, as `digest::SHA1`.");
+hash!(Sha256, &digest::SHA256, U32, "SHA-256, as `digest::SHA256`.");
+hash!(Sha384, &digest::SHA384, U48, "SHA-384, as `digest::SHA384`.");
+hash!(Sha512, &digest::SHA512, U64, "SHA-512, as `digest::SHA512`.");
+hash!(Sha512_256, &digest::SHA512_256, U32, "SHA-512/256, as `digest::SHA512_256`.");
+
+impl Signer<signature::Signature> for signature::Ed25519KeyPair {
+    #[inline]
+    fn try_sign(&self, msg: &[u8]) -> Result<signature::Signature, rustcrypto_signature::Error> {
+        Ok(self.sign(msg))
+    }
+}
+
+impl Signer<signature::Signature> for signature::ECDSAKeyPair {
+    fn try_sign(&self, msg: &[u8]) -> Result<signature::Signature, rustcrypto_signature::Error> {
+        self.sign(untrusted::Input::from(msg), &rand::SystemRandom::new())
+            .map_err(signature_error)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Signer<signature::Signature> for signature::KeyPair {
+    fn try_sign(&self, msg: &[u8]) -> Result<signature::Signature, rustcrypto_signature::Error> {
+        signature::sign(self, &rand::SystemRandom::new(), untrusted::Input::from(msg))
+            .map_err(signature_error)
+    }
+}
+
+/// A public key and the algorithm to verify signatures with it, e.g.
+/// `signature::ED25519` and the 32-byte public key, which implements
+/// `signature::Verifier` with `signature::verify()`.
+pub struct VerifyingKey<B: AsRef<[u8]>> {
+    algorithm: &'static signature::VerificationAlgorithm,
+    public_key: B,
+}
+
+impl<B: AsRef<[u8]>> core::fmt::Debug for VerifyingKey<B> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("VerifyingKey")
+            .field("algorithm", &self.algorithm)
+            .finish()
+    }
+}
+
+impl<B: AsRef<[u8]>> VerifyingKey<B> {
+    /// Constructs a verifying key. The public key is parsed each time a
+    /// signature is verified.
+    #[inline]
+    pub fn new(algorithm: &'static signature::VerificationAlgorithm, public_key: B) -> Self {
+        VerifyingKey {
+            algorithm,
+            public_key,
+        }
+    }
+}
+
+impl<B: AsRef<[u8]>> Verifier<signature::Signature> for VerifyingKey<B> {
+    fn verify(
+        &self, msg: &[u8], signature: &signature::Signature,
+    ) -> Result<(), rustcrypto_signature::Error> {
+        signature::verify(
+            self.algorithm,
+            untrusted::Input::from(self.public_key.as_ref()),
+            untrusted::Input::from(msg),
+            untrusted::Input::from(signature.as_ref()),
+        )
+        .map_err(signature_error)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for signature::Signature {
+    type Error = rustcrypto_signature::Error;
+
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        if bytes.len() > signature_impl::MAX_LEN {
+            return Err(rustcrypto_signature::Error::new());
+        }
+        Ok(signature_impl::signature_from_bytes(bytes))
+    }
+}
+
+impl SignatureEncoding for signature::Signature {
+    type Repr = signature::Signature;
+}
+
+fn signature_error(error::Unspecified: error::Unspecified) -> rustcrypto_signature::Error {
+    rustcrypto_signature::Error::new()
+}
+
+macro_rules! aead {
+    ( $name:ident, $algorithm:expr, $key_size:ty, $doc:expr ) => {
+        #[doc = $doc]
+        pub struct $name {
+            sealing_key: aead::SealingKey,
+            opening_key: aead::OpeningKey,
+        }
+
+        derive_debug_via_self!($name, self.sealing_key.algorithm());
+
+        impl KeySizeUser for $name {
+            type KeySize = $key_size;
+        }
+
+        impl KeyInit for $name {
+            fn new(key: &rustcrypto_aead::Key<Self>) -> Self {
+                // `key` has the right length, which is the only reason the
+                // keys could be rejected.
+                $name {
+                    sealing_key: aead::SealingKey::new($algorithm, key).unwrap(),
+                    opening_key: aead::OpeningKey::new($algorithm, key).unwrap(),
+                }
+            }
+        }
+
+        impl AeadCore for $name {
+            type NonceSize = U12;
+            type TagSize = U16;
+            type CiphertextOverhead = U0;
+        }
+
+        impl AeadInPlace for $name {
+            fn encrypt_in_place_detached(
+                &self, nonce: &rustcrypto_aead::Nonce<Self>, associated_data: &[u8],
+                buffer: &mut [u8],
+            ) -> Result<rustcrypto_aead::Tag<Self>, rustcrypto_aead::Error> {
+                aead::seal_in_place_separate_tag(&self.sealing_key, nonce, associated_data, buffer)
+                    .map(rustcrypto_aead::Tag::<Self>::from)
+                    .map_err(|error::Unspecified| rustcrypto_aead::Error)
+            }
+
+            fn decrypt_in_place_detached(
+                &self, nonce: &rustcrypto_aead::Nonce<Self>, associated_data: &[u8],
+                buffer: &mut [u8], tag: &rustcrypto_aead::Tag<Self>,
+            ) -> Result<(), rustcrypto_aead::Error> {
+                aead::open_in_place_separate_tag(
+                    &self.opening_key,
+                    nonce,
+                    associated_data,
+                    buffer,
+                    tag,
+                )
+                .map_err(|error::Unspecified| rustcrypto_aead::Error)
+            }
+        }
+    };
+}
+
+aead!(Aes128Gcm, &aead::AES_128_GCM, U16, "AES-128 in GCM mode, as `aead::AES_128_GCM`.");
+aead!(Aes256Gcm, &aead::AES_256_GCM, U32, "AES-256 in GCM mode, as `aead::AES_256_GCM`.");
+aead!(
+    ChaCha20Poly1305,
+    &aead::CHACHA20_POLY1305,
+    U32,
+    "ChaCha20-Poly1305, as `aead::CHACHA20_POLY1305`."
+);
@@ -0,0 +1,275 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The scrypt password-based key derivation function ([RFC 7914]).
+//!
+//! [RFC 7914]: https://tools.ietf.org/html/rfc7914
+
+use crate::{digest, error, pbkdf2};
+use alloc::vec::Vec;
+use core::num::NonZeroU32;
+
+/// The maximum amount of memory, in bytes, that `derive()` will use for
+/// either the `V` array or the `B` array. Parameters that need more than this
+/// are rejected so that untrusted parameters, e.g. from a key file, can't be
+/// used to exhaust memory. With the `parallel` feature, the lanes of `B` are
+/// mixed concurrently only as far as their `V` arrays together fit in
+/// `MAX_MEMORY`.
+pub const MAX_MEMORY: usize = 1 << 30;
+
+/// The maximum of N * r * p that `derive()` accepts. The time that scrypt
+/// takes is proportional to this product, which `MAX_MEMORY` doesn't bound
+/// because the lanes can be mixed one after another in the same `V` array.
+/// The limit allows e.g. N = 2**20 with r = 8 and p = 8.
+pub const MAX_WORK: u64 = 1 << 26;
+
+/// The parameters of scrypt.
+#[derive(Clone, Copy, Debug)]
+pub struct Params {
+    /// log2(N), where N is the CPU/memory cost parameter.
+    pub log_n: u8,
+
+    /// The block size parameter.
+    pub r: u32,
+
+    /// The parallelization parameter.
+    pub p: u32,
+}
+
+impl Params {
+    // Returns the block length (128 * r) and N.
+    fn validate(&self) -> Result<(usize, usize), error::Unspecified> {
+        // RFC 7914 Section 2: N > 1, N < 2**(128 * r / 8), and
+        // p <= ((2**32 - 1) * 32) / (128 * r).
+        if self.log_n == 0
+            || self.r == 0
+            || self.p == 0
+            || u64::from(self.log_n) >= 16 * u64::from(self.r)
+            || u64::from(self.r) * u64::from(self.p) >= (1 << 30)
+        {
+            return Err(error::Unspecified);
+        }
+        let block_len = 128usize
+            .checked_mul(self.r as usize)
+            .ok_or(error::Unspecified)?;
+        let n = 1usize
+            .checked_shl(u32::from(self.log_n))
+            .ok_or(error::Unspecified)?;
+        let v_len = block_len.checked_mul(n).ok_or(error::Unspecified)?;
+        let b_len = block_len
+            .checked_mul(self.p as usize)
+            .ok_or(error::Unspecified)?;
+        if v_len > MAX_MEMORY || b_len > MAX_MEMORY {
+            return Err(error::Unspecified);
+        }
+        if (1u64 << self.log_n) * u64::from(self.r) * u64::from(self.p) > MAX_WORK {
+            return Err(error::Unspecified);
+        }
+        Ok((block_len, n))
+    }
+}
+
+/// Fills `out` with the scrypt key derived from `password` and `salt`.
+pub fn derive(
+    params: &Params, salt: &[u8], password: &[u8], out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    if out.is_empty() {
+        return Err(error::Unspecified);
+    }
+    let (block_len, n) = params.validate()?;
+    let one = NonZeroU32::new(1).unwrap();
+
+    let mut b = vec![0u8; block_len * (params.p as usize)];
+    pbkdf2::derive(&digest::SHA256, one, salt, password, &mut b);
+
+    let new_buffers = || RoMixBuffers {
+        x: vec![0u32; block_len / 4],
+        v: vec![0u32; (block_len / 4) * n],
+        scratch: vec![0u32; block_len / 4],
+    };
+
+    // Each thread needs its own `V`, so only use as many threads as there
+    // are `V` arrays that fit in `MAX_MEMORY`.
+    #[cfg(feature = "parallel")]
+    {
+        let mut lanes: Vec<&mut [u8]> = b.chunks_mut(block_len).collect();
+        let max_threads = MAX_MEMORY / (block_len * n);
+        crate::parallel::for_each_mut(&mut lanes, max_threads, new_buffers, |buffers, lane| {
+            buffers.mix_lane(lane, n)
+        });
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        let mut buffers = new_buffers();
+        for lane in b.chunks_mut(block_len) {
+            buffers.mix_lane(lane, n);
+        }
+    }
+
+    pbkdf2::derive(&digest::SHA256, one, &b, password, out);
+    Ok(())
+}
+
+struct RoMixBuffers {
+    x: Vec<u32>,
+    v: Vec<u32>,
+    scratch: Vec<u32>,
+}
+
+impl RoMixBuffers {
+    // Replaces `lane`, one `block_len`-byte chunk of `B`, with its `ro_mix`.
+    fn mix_lane(&mut self, lane: &mut [u8], n: usize) {
+        for (x, bytes) in self.x.iter_mut().zip(lane.chunks(4)) {
+            *x = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        ro_mix(&mut self.x, &mut self.v, &mut self.scratch, n);
+        for (x, bytes) in self.x.iter().zip(lane.chunks_mut(4)) {
+            bytes.copy_from_slice(&x.to_le_bytes());
+        }
+    }
+}
+
+// RFC 7914 Section 5.
+fn ro_mix(x: &mut [u32], v: &mut [u32], scratch: &mut [u32], n: usize) {
+    let words = x.len();
+    for i in 0..n {
+        v[(i * words)..((i + 1) * words)].copy_from_slice(x);
+        block_mix(x, scratch);
+    }
+    for _ in 0..n {
+        // Integerify: the first word of the last 64-byte sub-block.
+        let j = (x[words - 16] as usize) & (n - 1);
+        for (x, v) in x.iter_mut().zip(&v[(j * words)..((j + 1) * words)]) {
+            *x ^= *v;
+        }
+        block_mix(x, scratch);
+    }
+}
+
+// RFC 7914 Section 4.
+fn block_mix(b: &mut [u32], y: &mut [u32]) {
+    let r2 = b.len() / 16;
+    let mut x = [0u32; 16];
+    x.copy_from_slice(&b[((r2 - 1) * 16)..]);
+    for i in 0..r2 {
+        for (x, b) in x.iter_mut().zip(&b[(i * 16)..((i + 1) * 16)]) {
+            *x ^= *b;
+        }
+        salsa20_8(&mut x);
+        // Even blocks go in the first half and odd blocks in the second.
+        let dest = ((i % 2) * (r2 / 2) + (i / 2)) * 16;
+        y[dest..(dest + 16)].copy_from_slice(&x);
+    }
+    b.copy_from_slice(y);
+}
+
+// RFC 7914 Section 3.
+fn salsa20_8(b: &mut [u32; 16]) {
+    let mut x = *b;
+    for _ in 0..4 {
+        // Columns.
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 5, 9, 13, 1);
+        quarter_round(&mut x, 10, 14, 2, 6);
+        quarter_round(&mut x, 15, 3, 7, 11);
+        // Rows.
+        quarter_round(&mut x, 0, 1, 2, 3);
+        quarter_round(&mut x, 5, 6, 7, 4);
+        quarter_round(&mut x, 10, 11, 8, 9);
+        quarter_round(&mut x, 15, 12, 13, 14);
+    }
+    for (b, x) in b.iter_mut().zip(x.iter()) {
+        *b = b.wrapping_add(*x);
+    }
+}
+
+#[inline(always)]
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+    x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+    x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+    x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_salsa20_8() {
+        // RFC 7914 Section 8.
+        let input = test::from_hex(
+            "7e879a214f3ec9867ca940e641718f26baee555b8c61c1b50df846116dcd3b1d\
+             ee24f319df9b3d8514121e4b5ac5aa3276021d2909c74829edebc68db8b8c25e",
+        )
+        .unwrap();
+        let expected = test::from_hex(
+            "a41f859c6608cc993b81cacb020cef05044b2181a2fd337dfd7b1c6396682f29\
+             b4393168e3c9e6bcfe6bc5b7a06d96bae424cc102c91745c24ad673dc7618f81",
+        )
+        .unwrap();
+        let mut b = [0u32; 16];
+        for (b, bytes) in b.iter_mut().zip(input.chunks(4)) {
+            *b = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        salsa20_8(&mut b);
+        let actual: Vec<u8> = b.iter().flat_map(|b| b.to_le_bytes().to_vec()).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_scrypt() {
+        test::from_file("src/scrypt_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let password = test_case.consume_bytes("P");
+            let salt = test_case.consume_bytes("S");
+            let log_n = test_case.consume_usize("LogN");
+            let r = test_case.consume_usize("r");
+            let p = test_case.consume_usize("p");
+            let expected = test_case.consume_bytes("DK");
+
+            let params = Params {
+                log_n: log_n as u8,
+                r: r as u32,
+                p: p as u32,
+            };
+            let mut actual = vec![0u8; expected.len()];
+            derive(&params, &salt, &password, &mut actual).unwrap();
+            assert_eq!(actual, expected);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let mut out = [0u8; 32];
+        for &(log_n, r, p) in &[
+            (0, 8, 1),
+            (10, 0, 1),
+            (10, 8, 0),
+            (16, 1, 1),     // N >= 2**(128 * r / 8)
+            (21, 8, 1),     // Too much memory
+            (10, 1 << 15, 1 << 15),
+            (20, 8, 9),     // Too much work
+            (20, 8, 1 << 20),
+        ] {
+            assert!(derive(&Params { log_n, r, p }, b"salt", b"password", &mut out).is_err());
+        }
+        assert!(derive(&Params { log_n: 4, r: 1, p: 1 }, b"salt", b"password", &mut []).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Shamir secret sharing over GF(2**8).
+//!
+//! `split` splits a secret, e.g. a key encryption key for `keys::Handle`,
+//! into shares, any `threshold` of which can be combined with `combine` to
+//! recover it; fewer reveal nothing about it. Each byte is shared
+//! separately, with a random polynomial of degree `threshold - 1` over
+//! GF(2**8), with the polynomial of AES, whose value at 0 is the byte; the
+//! share with identifier `x` holds the values at `x`.
+//!
+//! Shamir's scheme alone can't detect a corrupted or mismatched share, so
+//! the secret is shared together with a random 32-byte key, and each share
+//! carries an HMAC-SHA256 tag of its identifier, threshold, and values
+//! under that key. `combine` recovers the secret and the key, and only
+//! returns the secret if the tags of all of the shares it was given are
+//! valid. Since the key is shared like the secret, a share and its tag
+//! reveal nothing about the secret.
+//!
+//! A share is encoded by `Share::as_ref()` as a version byte, which is 1,
+//! the identifier, the threshold, the values, and the tag.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{rand, secret_sharing};
+//!
+//! # fn split() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let shares = secret_sharing::split(b"top secret key", 3, 5, &rng)?;
+//! let encoded: Vec<Vec<u8>> = shares.iter().map(|share| share.as_ref().to_vec()).collect();
+//!
+//! let shares = [&encoded[4], &encoded[0], &encoded[2]]
+//!     .iter()
+//!     .map(|encoded| secret_sharing::Share::from_bytes(encoded))
+//!     .collect::<Result<Vec<_>, _>>()?;
+//! assert_eq!(secret_sharing::combine(&shares)?, b"top secret key");
+//! assert!(secret_sharing::combine(&shares[..2]).is_err());
+//! # Ok(())
+//! # }
+//! # fn main() { split().unwrap() }
+//! ```
+
+use crate::{constant_time, digest, error, hmac, rand, zeroize};
+use alloc::vec::Vec;
+
+const VERSION: u8 = 1;
+
+const TAG_KEY_LEN: usize = 32;
+const TAG_LEN: usize = digest::SHA256_OUTPUT_LEN;
+
+// The version, the identifier, and the threshold.
+const HEADER_LEN: usize = 3;
+
+/// The largest number of shares, as there are only 255 nonzero elements of
+/// GF(2**8).
+pub const MAX_SHARES: usize = 255;
+
+/// A share of a secret.
+#[derive(Clone)]
+pub struct Share {
+    // The header, the values, and the tag.
+    bytes: Vec<u8>,
+}
+
+impl Share {
+    /// Decodes a share that was encoded with `as_ref()`.
+    ///
+    /// The tag is only verified by `combine`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, error::Unspecified> {
+        if bytes.len() < HEADER_LEN + TAG_KEY_LEN + TAG_LEN
+            || bytes[0] != VERSION
+            || bytes[1] == 0
+            || bytes[2] < 2
+        {
+            return Err(error::Unspecified);
+        }
+        Ok(Self {
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// The identifier of the share, from 1 to `MAX_SHARES`.
+    #[inline]
+    pub fn id(&self) -> u8 { self.bytes[1] }
+
+    /// The number of shares needed to recover the secret.
+    #[inline]
+    pub fn threshold(&self) -> u8 { self.bytes[2] }
+
+    fn values(&self) -> &[u8] { &self.bytes[HEADER_LEN..(self.bytes.len() - TAG_LEN)] }
+
+    fn tagged(&self) -> (&[u8], &[u8]) { self.bytes.split_at(self.bytes.len() - TAG_LEN) }
+}
+
+impl AsRef<[u8]> for Share {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes }
+}
+
+impl Drop for Share {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.bytes) }
+}
+
+derive_debug_via_self!(Share, self.id());
+
+/// Splits `secret` into `count` shares, with the identifiers 1 to `count`,
+/// any `threshold` of which recover it.
+///
+/// Fails unless `2 <= threshold <= count <= MAX_SHARES`.
+pub fn split(
+    secret: &[u8], threshold: usize, count: usize, rng: &rand::SecureRandom,
+) -> Result<Vec<Share>, error::Unspecified> {
+    if threshold < 2 || threshold > count || count > MAX_SHARES {
+        return Err(error::Unspecified);
+    }
+
+    // The secret and the tag key, followed by the other coefficients of the
+    // polynomials, from the coefficient of x to that of x**(threshold - 1).
+    let len = secret.len() + TAG_KEY_LEN;
+    let mut coefficients = vec![0; len * threshold];
+    coefficients[..secret.len()].copy_from_slice(secret);
+    rng.fill(&mut coefficients[secret.len()..])?;
+    let tag_key = hmac::SigningKey::new(&digest::SHA256, &coefficients[secret.len()..len]);
+
+    let shares = (1..=count)
+        .map(|id| {
+            let x = id as u8;
+            let mut bytes = Vec::with_capacity(HEADER_LEN + len + TAG_LEN);
+            bytes.extend_from_slice(&[VERSION, x, threshold as u8]);
+            for i in 0..len {
+                // Horner's method, from the highest coefficient.
+                let y = coefficients[i..]
+                    .iter()
+                    .step_by(len)
+                    .rev()
+                    .fold(0, |acc, c| gf_mul(acc, x) ^ c);
+                bytes.push(y);
+            }
+            let tag = hmac::sign(&tag_key, &bytes);
+            bytes.extend_from_slice(tag.as_ref());
+            Share { bytes }
+        })
+        .collect();
+    zeroize::zeroize(&mut coefficients);
+    Ok(shares)
+}
+
+/// Recovers the secret from `shares`.
+///
+/// Fails if there are fewer shares than their threshold, if they are from
+/// different splits or have the same identifier, or if any of them was
+/// modified. Only the first `threshold` shares are used to recover the
+/// secret, but the tags of all of them are verified.
+pub fn combine(shares: &[Share]) -> Result<Vec<u8>, error::Unspecified> {
+    let first = shares.first().ok_or(error::Unspecified)?;
+    let threshold = usize::from(first.threshold());
+    let len = first.values().len();
+    if shares.len() < threshold
+        || shares
+            .iter()
+            .any(|share| share.threshold() != first.threshold() || share.values().len() != len)
+    {
+        return Err(error::Unspecified);
+    }
+    for (i, share) in shares.iter().enumerate() {
+        if shares[..i].iter().any(|other| other.id() == share.id()) {
+            return Err(error::Unspecified);
+        }
+    }
+
+    // Lagrange interpolation at 0: the value is the sum of y_i * l_i(0), where
+    // l_i(0) is the product of x_j / (x_j - x_i) for j != i, and subtraction is
+    // XOR.
+    let shares_used = &shares[..threshold];
+    let mut recovered = vec![0; len];
+    for share in shares_used {
+        let x_i = share.id();
+        let (numerator, denominator) = shares_used
+            .iter()
+            .filter(|other| other.id() != x_i)
+            .fold((1, 1), |(n, d), other| {
+                (gf_mul(n, other.id()), gf_mul(d, other.id() ^ x_i))
+            });
+        let l_i = gf_mul(numerator, gf_inv(denominator));
+        for (r, y) in recovered.iter_mut().zip(share.values()) {
+            *r ^= gf_mul(*y, l_i);
+        }
+    }
+
+    let secret_len = len - TAG_KEY_LEN;
+    let tag_key = hmac::SigningKey::new(&digest::SHA256, &recovered[secret_len..]);
+    let mut valid = true;
+    for share in shares {
+        let (tagged, tag) = share.tagged();
+        valid &= constant_time::verify_slices_are_equal(hmac::sign(&tag_key, tagged).as_ref(), tag)
+            .is_ok();
+    }
+    zeroize::zeroize(&mut recovered[secret_len..]);
+    if !valid {
+        zeroize::zeroize(&mut recovered);
+        return Err(error::Unspecified);
+    }
+    recovered.truncate(secret_len);
+    Ok(recovered)
+}
+
+// Multiplication in GF(2**8) modulo x**8 + x**4 + x**3 + x + 1, without
+// branches or table lookups that depend on the operands.
+fn gf_mul(a: u8, b: u8) -> u8 {
+    let mut a = a;
+    let mut b = b;
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+// a**254, which is the inverse of `a` when it isn't zero.
+fn gf_inv(a: u8) -> u8 {
+    let a2 = gf_mul(a, a);
+    let a3 = gf_mul(a2, a);
+    let a6 = gf_mul(a3, a3);
+    let a12 = gf_mul(a6, a6);
+    let a15 = gf_mul(a12, a3);
+    let a30 = gf_mul(a15, a15);
+    let a60 = gf_mul(a30, a30);
+    let a120 = gf_mul(a60, a60);
+    let a240 = gf_mul(a120, a120);
+    let a252 = gf_mul(a240, a12);
+    gf_mul(a252, a2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_split() {
+        test::from_file("src/secret_sharing_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let secret = test_case.consume_bytes("Secret");
+            let threshold = test_case.consume_usize("Threshold");
+            let count = test_case.consume_usize("Count");
+            let random = test_case.consume_bytes("Random");
+            let expected: Vec<_> = (1..=count)
+                .map(|id| test_case.consume_bytes(&format!("Share{}", id)))
+                .collect();
+
+            let rng = test::rand::FixedSliceRandom { bytes: &random };
+            let shares = split(&secret, threshold, count, &rng).unwrap();
+            for (share, expected) in shares.iter().zip(expected.iter()) {
+                assert_eq!(share.as_ref(), &expected[..]);
+            }
+            assert_eq!(combine(&shares[(count - threshold)..]).unwrap(), secret);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_gf_mul() {
+        // From FIPS 197 Section 4.2.
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        assert_eq!(gf_mul(0x57, 0x13), 0xfe);
+        for a in 0..=255 {
+            assert_eq!(gf_mul(a, 0), 0);
+            assert_eq!(gf_mul(a, 1), a);
+            for b in 0..=255 {
+                assert_eq!(gf_mul(a, b), gf_mul(b, a));
+            }
+        }
+    }
+
+    #[test]
+    fn test_gf_inv() {
+        // From FIPS 197 Section 5.1.1.
+        assert_eq!(gf_inv(0x53), 0xca);
+        assert_eq!(gf_inv(0), 0);
+        for a in 1..=255 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Implementations of serde's `Serialize` and `Deserialize` for the types
+//! that aren't secret.
+//!
+//! Byte strings are lowercase hex digits in human-readable formats, e.g.
+//! JSON, and bytes in the others:
+//!
+//! * `signature::Signature` is the signature.
+//! * `spki::PublicKey` is its DER-encoded `SubjectPublicKeyInfo`, so that the
+//!   algorithm is kept, e.g. `signature::KeyPair::public_key()`.
+//! * `digest::Digest` is a pair of the algorithm and the value.
+//! * `&'static digest::Algorithm` and `&'static aead::Algorithm` are their
+//!   names, e.g. `"SHA-256"` and `"AES-128-GCM"`, which won't change.
+
+use crate::{
+    aead, digest,
+    io::hex,
+    signature, signature_impl,
+    spki::{self, PublicKey},
+};
+use core::fmt;
+use serde::{
+    de::{self, Unexpected},
+    ser::{self, SerializeTuple},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use untrusted;
+
+static DIGEST_ALGORITHMS: [(&str, &digest::Algorithm); 5] = [
+    ("SHA-1", &digest::SHA1),
+    ("SHA-256", &digest::SHA256),
+    ("SHA-384", &digest::SHA384),
+    ("SHA-512", &digest::SHA512),
+    ("SHA-512/256", &digest::SHA512_256),
+];
+
+static AEAD_ALGORITHMS: [(&str, &aead::Algorithm); 3] = [
+    ("AES-128-GCM", &aead::AES_128_GCM),
+    ("AES-256-GCM", &aead::AES_256_GCM),
+    ("CHACHA20-POLY1305", &aead::CHACHA20_POLY1305),
+];
+
+impl Serialize for digest::Algorithm {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_algorithm(&DIGEST_ALGORITHMS, self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for &'static digest::Algorithm {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(AlgorithmVisitor(&DIGEST_ALGORITHMS))
+    }
+}
+
+impl Serialize for aead::Algorithm {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_algorithm(&AEAD_ALGORITHMS, self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for &'static aead::Algorithm {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(AlgorithmVisitor(&AEAD_ALGORITHMS))
+    }
+}
+
+fn serialize_algorithm<T: PartialEq + 'static, S: Serializer>(
+    algorithms: &[(&'static str, &'static T)], algorithm: &T, serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let (name, _) = algorithms
+        .iter()
+        .find(|(_, known)| *known == algorithm)
+        .ok_or_else(|| <S::Error as ser::Error>::custom("unknown algorithm"))?;
+    serializer.serialize_str(name)
+}
+
+struct AlgorithmVisitor<T: 'static>(&'static [(&'static str, &'static T)]);
+
+impl<'de, T: 'static> de::Visitor<'de> for AlgorithmVisitor<T> {
+    type Value = &'static T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the name of an algorithm")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.0
+            .iter()
+            .find(|(name, _)| *name == v)
+            .map(|(_, algorithm)| *algorithm)
+            .ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+    }
+}
+
+impl Serialize for digest::Digest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(self.algorithm())?;
+        tuple.serialize_element(&ByteString(self.as_ref()))?;
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for digest::Digest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_tuple(2, DigestVisitor)
+    }
+}
+
+struct DigestVisitor;
+
+impl<'de> de::Visitor<'de> for DigestVisitor {
+    type Value = digest::Digest;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a digest algorithm and a digest value")
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let algorithm: &'static digest::Algorithm = match seq.next_element()? {
+            Some(algorithm) => algorithm,
+            None => return Err(de::Error::invalid_length(0, &self)),
+        };
+        let value: Bytes<{ digest::MAX_OUTPUT_LEN }> = match seq.next_element()? {
+            Some(value) => value,
+            None => return Err(de::Error::invalid_length(1, &self)),
+        };
+        digest::Digest::from_value(algorithm, value.as_ref())
+            .map_err(|_| de::Error::invalid_length(value.len, &"the algorithm's output length"))
+    }
+}
+
+impl Serialize for signature::Signature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ByteString(self.as_ref()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for signature::Signature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Bytes::<{ signature_impl::MAX_LEN }>::deserialize(deserializer)?;
+        Ok(signature_impl::signature_from_bytes(bytes.as_ref()))
+    }
+}
+
+impl Serialize for PublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ByteString(self.to_spki_der().as_ref()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Bytes::<{ spki::DOCUMENT_MAX_LEN }>::deserialize(deserializer)?;
+        PublicKey::from_spki_der(untrusted::Input::from(bytes.as_ref())).map_err(|_| {
+            de::Error::invalid_value(
+                Unexpected::Bytes(bytes.as_ref()),
+                &"a SubjectPublicKeyInfo with a supported algorithm",
+            )
+        })
+    }
+}
+
+// A byte string to serialize.
+struct ByteString<'a>(&'a [u8]);
+
+impl Serialize for ByteString<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+}
+
+// Formats the bytes as hex digits, without allocating.
+impl fmt::Display for ByteString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut digits = [0u8; 64];
+        for chunk in self.0.chunks(digits.len() / 2) {
+            f.write_str(hex::encode_to_slice(chunk, &mut digits).map_err(|_| fmt::Error)?)?;
+        }
+        Ok(())
+    }
+}
+
+// A deserialized byte string of at most `N` bytes.
+struct Bytes<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> AsRef<[u8]> for Bytes<N> {
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Bytes<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(BytesVisitor)
+        } else {
+            deserializer.deserialize_bytes(BytesVisitor)
+        }
+    }
+}
+
+struct BytesVisitor<const N: usize>;
+
+impl<'de, const N: usize> de::Visitor<'de> for BytesVisitor<N> {
+    type Value = Bytes<N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at most {} bytes, or twice as many hex digits", N)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        if v.len() % 2 != 0 || v.len() / 2 > N {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        let mut r = Bytes {
+            bytes: [0; N],
+            len: v.len() / 2,
+        };
+        hex::decode_to_slice(v, &mut r.bytes[..r.len])
+            .map_err(|_| E::invalid_value(Unexpected::Str(v), &self))?;
+        Ok(r)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        if v.len() > N {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        let mut r = Bytes {
+            bytes: [0; N],
+            len: v.len(),
+        };
+        r.bytes[..r.len].copy_from_slice(v);
+        Ok(r)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut r = Bytes {
+            bytes: [0; N],
+            len: 0,
+        };
+        while let Some(b) = seq.next_element()? {
+            if r.len == N {
+                return Err(de::Error::invalid_length(r.len + 1, &self));
+            }
+            r.bytes[r.len] = b;
+            r.len += 1;
+        }
+        Ok(r)
+    }
+}
@@ -0,0 +1,406 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Verification of the signatures of Intel SGX DCAP quotes, version 3, with
+//! ECDSA P-256 attestation keys, and of the certificate chains of the PCK
+//! (Provisioning Certification Key) that certifies them.
+//!
+//! A quote is verified in two steps:
+//!
+//! * `verify_pck_certificate_chain()` checks that each certificate of the
+//!   PCK certificate chain is signed by the next, up to the trusted root,
+//!   e.g. the Intel SGX Root CA, and returns the PCK's public key. The chain
+//!   is usually the PEM-encoded `certification_data()` of the quote, which
+//!   `pem::decode()` can decode a certificate at a time.
+//! * `verify_quote()` checks that the quoting enclave's report is signed by
+//!   the PCK, that the report binds the attestation key, and that the
+//!   attesting enclave's report is signed by the attestation key.
+//!
+//! Only the signatures, and the names that link the certificates, are
+//! checked: the caller must check the certificates' validity periods and
+//! revocation, the identity of the quoting enclave, the TCB level, and the
+//! identity and `report_data()` of the attesting enclave, e.g. against
+//! Intel's collateral and its own policy.
+
+use crate::{digest, error, io::der, signature, spki};
+use untrusted;
+
+/// The length of a report body.
+pub const REPORT_BODY_LEN: usize = 384;
+
+/// The type of `Quote::certification_data()` that is the PEM-encoded PCK
+/// certificate chain, starting with the PCK certificate.
+pub const CERTIFICATION_DATA_PCK_CERTIFICATE_CHAIN: u16 = 5;
+
+const HEADER_LEN: usize = 48;
+const SIGNATURE_LEN: usize = 64;
+const ATTESTATION_KEY_LEN: usize = 64;
+
+const QUOTE_VERSION: u16 = 3;
+const ATTESTATION_KEY_TYPE_ECDSA_P256: u16 = 2;
+const TEE_TYPE_SGX: u32 = 0;
+
+// The contents of the `AlgorithmIdentifier` of ecdsa-with-SHA256 (RFC 5758).
+const ECDSA_WITH_SHA256: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+
+/// Verifies that each of the DER-encoded certificates of `chain`, which
+/// starts with the PCK certificate, is signed by the next, and the last by
+/// the DER-encoded certificate `trusted_root`, which may end `chain`, and
+/// returns the PCK's public key, an uncompressed P-256 point.
+///
+/// The certificates must have ECDSA P-256 keys and be signed with ECDSA
+/// P-256 and SHA-256, and each certificate's issuer must be the subject of
+/// the next.
+pub fn verify_pck_certificate_chain<'a>(
+    chain: &[&'a [u8]], trusted_root: &[u8],
+) -> Result<&'a [u8], error::Unspecified> {
+    let chain = match chain.split_last() {
+        Some((last, rest)) if *last == trusted_root => rest,
+        _ => chain,
+    };
+    let (&pck_certificate, issuers) = chain.split_first().ok_or(error::Unspecified)?;
+
+    let pck_certificate = Certificate::parse(pck_certificate)?;
+    let mut certificate = pck_certificate;
+    for &issuer in issuers {
+        let issuer = Certificate::parse(issuer)?;
+        certificate.verify_issued_by(&issuer)?;
+        certificate = issuer;
+    }
+    certificate.verify_issued_by(&Certificate::parse(trusted_root)?)?;
+
+    Ok(pck_certificate.public_key.as_slice_less_safe())
+}
+
+/// A quote whose signatures `verify_quote()` has verified.
+pub struct Quote<'a> {
+    qe_svn: u16,
+    pce_svn: u16,
+    enclave_report: ReportBody<'a>,
+    qe_report: ReportBody<'a>,
+    certification_data_type: u16,
+    certification_data: &'a [u8],
+}
+
+/// Verifies the signatures of the quote `quote` with the PCK's public key
+/// `pck_public_key`, an uncompressed P-256 point, e.g. from
+/// `verify_pck_certificate_chain()`.
+pub fn verify_quote<'a>(
+    quote: &'a [u8], pck_public_key: &[u8],
+) -> Result<Quote<'a>, error::Unspecified> {
+    untrusted::Input::from(quote).read_all(error::Unspecified, |input| {
+        let signed_start = input.mark();
+        let version = read_u16(input)?;
+        let attestation_key_type = read_u16(input)?;
+        let tee_type = read_u32(input)?;
+        if version != QUOTE_VERSION
+            || attestation_key_type != ATTESTATION_KEY_TYPE_ECDSA_P256
+            || tee_type != TEE_TYPE_SGX
+        {
+            return Err(error::Unspecified);
+        }
+        let qe_svn = read_u16(input)?;
+        let pce_svn = read_u16(input)?;
+        // The QE vendor ID and the user data.
+        input.skip(HEADER_LEN - 12)?;
+        let enclave_report = input.skip_and_get_input(REPORT_BODY_LEN)?;
+        let signed = input.get_input_between_marks(signed_start, input.mark())?;
+
+        let signature_data_len = read_u32(input)?;
+        let signature_data = input.skip_and_get_input(signature_data_len as usize)?;
+        signature_data.read_all(error::Unspecified, |input| {
+            let enclave_report_signature = input.skip_and_get_input(SIGNATURE_LEN)?;
+            let attestation_key = input.skip_and_get_input(ATTESTATION_KEY_LEN)?;
+            let qe_report = input.skip_and_get_input(REPORT_BODY_LEN)?;
+            let qe_report_signature = input.skip_and_get_input(SIGNATURE_LEN)?;
+            let qe_authentication_data_len = read_u16(input)?;
+            let qe_authentication_data =
+                input.skip_and_get_input(usize::from(qe_authentication_data_len))?;
+            let certification_data_type = read_u16(input)?;
+            let certification_data_len = read_u32(input)?;
+            let certification_data = input.skip_and_get_input(certification_data_len as usize)?;
+
+            // The PCK signs the quoting enclave's report...
+            signature::verify(
+                &signature::ECDSA_P256_SHA256_FIXED,
+                untrusted::Input::from(pck_public_key),
+                qe_report,
+                qe_report_signature,
+            )?;
+
+            // ... which binds the attestation key...
+            let qe_report = ReportBody(qe_report.as_slice_less_safe());
+            let mut ctx = digest::Context::new(&digest::SHA256);
+            ctx.update(attestation_key.as_slice_less_safe());
+            ctx.update(qe_authentication_data.as_slice_less_safe());
+            let (hash, padding) = qe_report.report_data().split_at(32);
+            if hash != ctx.finish().as_ref() || padding.iter().any(|&b| b != 0) {
+                return Err(error::Unspecified);
+            }
+
+            // ... which signs the header and the attesting enclave's report.
+            let mut public_key = [0; 1 + ATTESTATION_KEY_LEN];
+            public_key[0] = 0x04;
+            public_key[1..].copy_from_slice(attestation_key.as_slice_less_safe());
+            signature::verify(
+                &signature::ECDSA_P256_SHA256_FIXED,
+                untrusted::Input::from(&public_key[..]),
+                signed,
+                enclave_report_signature,
+            )?;
+
+            Ok(Quote {
+                qe_svn,
+                pce_svn,
+                enclave_report: ReportBody(enclave_report.as_slice_less_safe()),
+                qe_report,
+                certification_data_type,
+                certification_data: certification_data.as_slice_less_safe(),
+            })
+        })
+    })
+}
+
+impl<'a> Quote<'a> {
+    /// The security version of the quoting enclave.
+    #[inline]
+    pub fn qe_svn(&self) -> u16 { self.qe_svn }
+
+    /// The security version of the Provisioning Certification Enclave.
+    #[inline]
+    pub fn pce_svn(&self) -> u16 { self.pce_svn }
+
+    /// The report of the attesting enclave.
+    #[inline]
+    pub fn enclave_report(&self) -> ReportBody<'a> { self.enclave_report }
+
+    /// The report of the quoting enclave.
+    #[inline]
+    pub fn qe_report(&self) -> ReportBody<'a> { self.qe_report }
+
+    /// The type of the certification data and the data, e.g. the PCK
+    /// certificate chain if the type is
+    /// `CERTIFICATION_DATA_PCK_CERTIFICATE_CHAIN`. The certification data
+    /// isn't authenticated by the quote.
+    #[inline]
+    pub fn certification_data(&self) -> (u16, &'a [u8]) {
+        (self.certification_data_type, self.certification_data)
+    }
+}
+
+/// The body of an enclave's report, `REPORTBODY`.
+#[derive(Clone, Copy)]
+pub struct ReportBody<'a>(&'a [u8]);
+
+impl<'a> ReportBody<'a> {
+    /// The security version of the CPU, `CPUSVN`.
+    #[inline]
+    pub fn cpu_svn(&self) -> &'a [u8] { &self.0[0..16] }
+
+    /// The enclave's attributes, `ATTRIBUTES`: the flags and `XFRM`.
+    #[inline]
+    pub fn attributes(&self) -> &'a [u8] { &self.0[48..64] }
+
+    /// The enclave's measurement, `MRENCLAVE`.
+    #[inline]
+    pub fn mr_enclave(&self) -> &'a [u8] { &self.0[64..96] }
+
+    /// The hash of the enclave signer's public key, `MRSIGNER`.
+    #[inline]
+    pub fn mr_signer(&self) -> &'a [u8] { &self.0[128..160] }
+
+    /// The enclave's product ID, `ISVPRODID`.
+    #[inline]
+    pub fn isv_prod_id(&self) -> u16 { u16::from_le_bytes([self.0[256], self.0[257]]) }
+
+    /// The enclave's security version, `ISVSVN`.
+    #[inline]
+    pub fn isv_svn(&self) -> u16 { u16::from_le_bytes([self.0[258], self.0[259]]) }
+
+    /// The data that the enclave included in its report, `REPORTDATA`, e.g.
+    /// the hash of the public key of a channel to it.
+    #[inline]
+    pub fn report_data(&self) -> &'a [u8] { &self.0[320..384] }
+}
+
+impl AsRef<[u8]> for ReportBody<'_> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { self.0 }
+}
+
+#[derive(Clone, Copy)]
+struct Certificate<'a> {
+    tbs_certificate: untrusted::Input<'a>,
+    issuer: untrusted::Input<'a>,
+    subject: untrusted::Input<'a>,
+    public_key: untrusted::Input<'a>,
+    signature: untrusted::Input<'a>,
+}
+
+impl<'a> Certificate<'a> {
+    fn parse(certificate: &'a [u8]) -> Result<Self, error::Unspecified> {
+        untrusted::Input::from(certificate).read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+                let tbs_start = input.mark();
+                let (issuer, subject, public_key) = der::nested(
+                    input,
+                    der::Tag::Sequence,
+                    error::Unspecified,
+                    parse_tbs_certificate,
+                )?;
+                let tbs_certificate = input.get_input_between_marks(tbs_start, input.mark())?;
+                expect_ecdsa_with_sha256(input)?;
+                let signature = der::bit_string_with_no_unused_bits(input)?;
+                Ok(Self {
+                    tbs_certificate,
+                    issuer,
+                    subject,
+                    public_key,
+                    signature,
+                })
+            })
+        })
+    }
+
+    fn verify_issued_by(&self, issuer: &Certificate) -> Result<(), error::Unspecified> {
+        if self.issuer != issuer.subject {
+            return Err(error::Unspecified);
+        }
+        signature::verify(
+            &signature::ECDSA_P256_SHA256_ASN1,
+            issuer.public_key,
+            self.tbs_certificate,
+            self.signature,
+        )
+    }
+}
+
+// Returns the issuer, the subject, and the public key of a `TBSCertificate`.
+fn parse_tbs_certificate<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<(untrusted::Input<'a>, untrusted::Input<'a>, untrusted::Input<'a>), error::Unspecified>
+{
+    let _version = der::expect_tag_and_get_value(input, der::Tag::ContextSpecificConstructed0)?;
+    let _serial_number = der::expect_tag_and_get_value(input, der::Tag::Integer)?;
+    expect_ecdsa_with_sha256(input)?;
+    let issuer = der::expect_tag_and_get_value(input, der::Tag::Sequence)?;
+    let _validity = der::expect_tag_and_get_value(input, der::Tag::Sequence)?;
+    let subject = der::expect_tag_and_get_value(input, der::Tag::Sequence)?;
+    let public_key = der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+        let alg_id = der::expect_tag_and_get_value(input, der::Tag::Sequence)?;
+        if alg_id != untrusted::Input::from(spki::EC_PUBLIC_KEY_P256) {
+            return Err(error::Unspecified);
+        }
+        der::bit_string_with_no_unused_bits(input)
+    })?;
+    // The unique identifiers and the extensions aren't needed.
+    let _ = input.skip_to_end();
+    Ok((issuer, subject, public_key))
+}
+
+fn expect_ecdsa_with_sha256(input: &mut untrusted::Reader) -> Result<(), error::Unspecified> {
+    let alg_id = der::expect_tag_and_get_value(input, der::Tag::Sequence)?;
+    if alg_id != untrusted::Input::from(ECDSA_WITH_SHA256) {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
+fn read_u16(input: &mut untrusted::Reader) -> Result<u16, error::Unspecified> {
+    let bytes = input.skip_and_get_input(2)?.as_slice_less_safe();
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(input: &mut untrusted::Reader) -> Result<u32, error::Unspecified> {
+    let bytes = input.skip_and_get_input(4)?.as_slice_less_safe();
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_attestation() {
+        test::from_file("src/sgx/attestation_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let root = test_case.consume_bytes("RootCertificate");
+            let other_root = test_case.consume_bytes("OtherRootCertificate");
+            let intermediate = test_case.consume_bytes("IntermediateCertificate");
+            let pck_certificate = test_case.consume_bytes("PckCertificate");
+            let pck_public_key = test_case.consume_bytes("PckPublicKey");
+            let quote = test_case.consume_bytes("Quote");
+            let qe_svn = test_case.consume_usize("QeSvn");
+            let pce_svn = test_case.consume_usize("PceSvn");
+            let mr_enclave = test_case.consume_bytes("MrEnclave");
+            let mr_signer = test_case.consume_bytes("MrSigner");
+            let isv_prod_id = test_case.consume_usize("IsvProdId");
+            let isv_svn = test_case.consume_usize("IsvSvn");
+            let report_data = test_case.consume_bytes("ReportData");
+            let qe_mr_signer = test_case.consume_bytes("QeMrSigner");
+            let qe_isv_svn = test_case.consume_usize("QeIsvSvn");
+            let certification_data = test_case.consume_bytes("CertificationData");
+
+            // The chain may or may not end with the root.
+            let chain: [&[u8]; 3] = [&pck_certificate, &intermediate, &root];
+            assert_eq!(verify_pck_certificate_chain(&chain, &root)?, &pck_public_key[..]);
+            assert_eq!(verify_pck_certificate_chain(&chain[..2], &root)?, &pck_public_key[..]);
+            assert!(verify_pck_certificate_chain(&chain[..2], &other_root).is_err());
+            assert!(verify_pck_certificate_chain(&chain[..1], &root).is_err());
+            let reversed: [&[u8]; 2] = [&intermediate, &pck_certificate];
+            assert!(verify_pck_certificate_chain(&reversed, &root).is_err());
+            assert!(verify_pck_certificate_chain(&[&root], &root).is_err());
+            assert!(verify_pck_certificate_chain(&[], &root).is_err());
+            for i in 0..pck_certificate.len() {
+                let mut tampered = pck_certificate.clone();
+                tampered[i] ^= 1;
+                let chain: [&[u8]; 2] = [&tampered, &intermediate];
+                assert!(verify_pck_certificate_chain(&chain, &root).is_err());
+            }
+
+            let verified = verify_quote(&quote, &pck_public_key)?;
+            assert_eq!(usize::from(verified.qe_svn()), qe_svn);
+            assert_eq!(usize::from(verified.pce_svn()), pce_svn);
+            let enclave_report = verified.enclave_report();
+            assert_eq!(enclave_report.as_ref(), &quote[HEADER_LEN..][..REPORT_BODY_LEN]);
+            assert_eq!(enclave_report.mr_enclave(), &mr_enclave[..]);
+            assert_eq!(enclave_report.mr_signer(), &mr_signer[..]);
+            assert_eq!(usize::from(enclave_report.isv_prod_id()), isv_prod_id);
+            assert_eq!(usize::from(enclave_report.isv_svn()), isv_svn);
+            assert_eq!(enclave_report.report_data(), &report_data[..]);
+            assert_eq!(verified.qe_report().mr_signer(), &qe_mr_signer[..]);
+            assert_eq!(usize::from(verified.qe_report().isv_svn()), qe_isv_svn);
+            assert_eq!(
+                verified.certification_data(),
+                (CERTIFICATION_DATA_PCK_CERTIFICATE_CHAIN, &certification_data[..])
+            );
+
+            let other_public_key = verify_pck_certificate_chain(&[&intermediate[..]], &root)?;
+            assert!(verify_quote(&quote, other_public_key).is_err());
+            assert!(verify_quote(&quote[..quote.len() - 1], &pck_public_key).is_err());
+
+            // Everything but the certification data, and its type, is
+            // authenticated.
+            let authenticated_len = quote.len() - certification_data.len() - 6;
+            for i in 0..authenticated_len {
+                let mut tampered = quote.clone();
+                tampered[i] ^= 1;
+                assert!(verify_quote(&tampered, &pck_public_key).is_err());
+            }
+
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Sealing: encrypting data within an enclave so that it can be stored
+//! outside it, e.g. on disk, and only decrypted within the same enclave, or
+//! within an enclave from the same signer, on the same CPU.
+//!
+//! The data is encrypted with AES-128-GCM under a seal key from `EGETKEY`,
+//! which the CPU derives from its own secrets, the identity of the enclave
+//! that the `Policy` selects, and a random key ID. The sealed data starts
+//! with the parameters of the key request: the policy, the enclave's
+//! `ISVSVN` and the CPU's `CPUSVN` when it was sealed, and the key ID. These
+//! aren't authenticated separately: if any is changed, `EGETKEY` derives a
+//! different key, and `unseal_data()` fails.
+//!
+//! Data sealed by one version of an enclave can be unsealed by later versions,
+//! with a greater or equal `ISVSVN`, and after microcode updates, with a
+//! greater or equal `CPUSVN`, but not by earlier ones.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ring::{rand, sgx::sealing};
+//!
+//! # fn seal() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let sealed = sealing::seal_data(sealing::Policy::MrSigner, b"ad", b"secret", &rng)?;
+//! assert_eq!(sealing::unseal_data(b"ad", &sealed)?, b"secret");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{aead, error, rand, zeroize};
+use alloc::vec::Vec;
+
+/// The enclave identity that a seal key is bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// The enclave's measurement, `MRENCLAVE`: only the same enclave can
+    /// unseal the data.
+    MrEnclave,
+
+    /// The enclave signer's identity, `MRSIGNER`, and the enclave's product
+    /// ID: any enclave with them, e.g. a later version of the same enclave,
+    /// can unseal the data.
+    MrSigner,
+}
+
+impl Policy {
+    // The `KEYPOLICY` bit.
+    fn bits(self) -> u16 {
+        match self {
+            Policy::MrEnclave => 0x0001,
+            Policy::MrSigner => 0x0002,
+        }
+    }
+
+    fn from_bits(bits: u16) -> Result<Self, error::Unspecified> {
+        match bits {
+            0x0001 => Ok(Policy::MrEnclave),
+            0x0002 => Ok(Policy::MrSigner),
+            _ => Err(error::Unspecified),
+        }
+    }
+}
+
+/// The length of the sealed data that precedes the ciphertext.
+pub const HEADER_LEN: usize = 2 + 2 + CPUSVN_LEN + KEY_ID_LEN + NONCE_LEN;
+
+/// The length of the tag that follows the ciphertext.
+pub const TAG_LEN: usize = 16;
+
+const CPUSVN_LEN: usize = 16;
+const KEY_ID_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SEAL_KEY_LEN: usize = 16;
+
+// `KEYNAME` of the seal key.
+const KEY_NAME_SEAL: u16 = 4;
+
+// The attributes, besides those of the policy, that the seal key is bound
+// to, as in the Intel SGX SDK: `INIT`, `DEBUG`, `MODE64BIT`, and the reserved
+// bits; not the `XFRM` features that the enclave may use.
+const ATTRIBUTE_FLAGS_MASK: u64 = 0xff00_0000_0000_000b;
+const ATTRIBUTE_XFRM_MASK: u64 = 0;
+const MISC_MASK: u32 = 0xf000_0000;
+
+/// Encrypts `plaintext` with a seal key for the enclave's identity that
+/// `policy` selects, authenticating `additional_data`, which isn't included.
+pub fn seal_data(
+    policy: Policy, additional_data: &[u8], plaintext: &[u8], rng: &rand::SecureRandom,
+) -> Result<Vec<u8>, error::Unspecified> {
+    let report = ereport();
+    let mut sealed = Vec::with_capacity(HEADER_LEN + plaintext.len() + TAG_LEN);
+    sealed.extend_from_slice(&policy.bits().to_le_bytes());
+    sealed.extend_from_slice(&report.0[REPORT_ISVSVN..][..2]);
+    sealed.extend_from_slice(&report.0[REPORT_CPUSVN..][..CPUSVN_LEN]);
+    sealed.resize(HEADER_LEN, 0);
+    rng.fill(&mut sealed[(HEADER_LEN - KEY_ID_LEN - NONCE_LEN)..])?;
+    sealed.extend_from_slice(plaintext);
+    sealed.resize(HEADER_LEN + plaintext.len() + TAG_LEN, 0);
+
+    let (header, in_out) = sealed.split_at_mut(HEADER_LEN);
+    let seal_key = egetkey(&key_request(header))?;
+    let key = aead::SealingKey::new(&aead::AES_128_GCM, &seal_key.0)?;
+    let nonce = &header[(HEADER_LEN - NONCE_LEN)..];
+    let _ = aead::seal_in_place(&key, nonce, additional_data, in_out, TAG_LEN)?;
+    Ok(sealed)
+}
+
+/// Decrypts `sealed`, which `seal_data()` sealed with `additional_data`,
+/// within the same enclave, or an enclave from the same signer, on the same
+/// CPU.
+pub fn unseal_data(additional_data: &[u8], sealed: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+    if sealed.len() < HEADER_LEN + TAG_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut sealed = sealed.to_vec();
+    let (header, in_out) = sealed.split_at_mut(HEADER_LEN);
+    let _ = Policy::from_bits(u16::from_le_bytes([header[0], header[1]]))?;
+    let seal_key = egetkey(&key_request(header))?;
+    let key = aead::OpeningKey::new(&aead::AES_128_GCM, &seal_key.0)?;
+    let nonce = &header[(HEADER_LEN - NONCE_LEN)..];
+    let plaintext_len = aead::open_in_place(&key, nonce, additional_data, 0, in_out)?.len();
+    let _ = sealed.drain(..HEADER_LEN);
+    sealed.truncate(plaintext_len);
+    Ok(sealed)
+}
+
+// `KEYREQUEST`, which `EGETKEY` requires to be 512-byte aligned.
+#[repr(C, align(512))]
+struct KeyRequest([u8; 512]);
+
+// The `KEYREQUEST` for the seal key of the sealed data's `header`.
+fn key_request(header: &[u8]) -> KeyRequest {
+    let (policy, rest) = header.split_at(2);
+    let (isvsvn, rest) = rest.split_at(2);
+    let (cpusvn, rest) = rest.split_at(CPUSVN_LEN);
+    let key_id = &rest[..KEY_ID_LEN];
+
+    let mut request = KeyRequest([0; 512]);
+    request.0[0..2].copy_from_slice(&KEY_NAME_SEAL.to_le_bytes());
+    request.0[2..4].copy_from_slice(policy);
+    request.0[4..6].copy_from_slice(isvsvn);
+    request.0[8..24].copy_from_slice(cpusvn);
+    request.0[24..32].copy_from_slice(&ATTRIBUTE_FLAGS_MASK.to_le_bytes());
+    request.0[32..40].copy_from_slice(&ATTRIBUTE_XFRM_MASK.to_le_bytes());
+    request.0[40..72].copy_from_slice(key_id);
+    request.0[72..76].copy_from_slice(&MISC_MASK.to_le_bytes());
+    request
+}
+
+// The output of `EGETKEY`, which it requires to be 16-byte aligned.
+#[repr(C, align(16))]
+struct SealKey([u8; SEAL_KEY_LEN]);
+
+impl Drop for SealKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.0) }
+}
+
+const ENCLU_EREPORT: u32 = 0;
+const ENCLU_EGETKEY: u32 = 1;
+
+fn egetkey(request: &KeyRequest) -> Result<SealKey, error::Unspecified> {
+    let request: *const KeyRequest = request;
+    let mut key = SealKey([0; SEAL_KEY_LEN]);
+    let status: u32;
+    unsafe {
+        core::arch::asm!(
+            // `rbx` is reserved by LLVM, so it can't be an operand.
+            "xchg %rbx, {0}",
+            "enclu",
+            "mov {0}, %rbx",
+            inout(reg) request => _,
+            inlateout("eax") ENCLU_EGETKEY => status,
+            in("rcx") key.0.as_mut_ptr(),
+            options(att_syntax, nostack),
+        );
+    }
+    // The status is e.g. `SGX_INVALID_ISVSVN` if the data was sealed by a
+    // later version of the enclave.
+    if status != 0 {
+        return Err(error::Unspecified);
+    }
+    Ok(key)
+}
+
+// `TARGETINFO`, `REPORTDATA`, and `REPORT`, with the alignment that
+// `EREPORT` requires.
+#[repr(C, align(512))]
+struct TargetInfo([u8; 512]);
+#[repr(C, align(128))]
+struct ReportData([u8; 64]);
+#[repr(C, align(512))]
+struct Report([u8; 432]);
+
+// The offsets of `CPUSVN` and `ISVSVN` in `REPORT`.
+const REPORT_CPUSVN: usize = 0;
+const REPORT_ISVSVN: usize = 258;
+
+// Returns the enclave's report of itself, for its `ISVSVN` and the CPU's
+// `CPUSVN`. The report's MAC isn't needed, so it is targeted at no enclave.
+fn ereport() -> Report {
+    let target_info = TargetInfo([0; 512]);
+    let target_info: *const TargetInfo = &target_info;
+    let report_data = ReportData([0; 64]);
+    let report_data: *const ReportData = &report_data;
+    let mut report = Report([0; 432]);
+    unsafe {
+        core::arch::asm!(
+            "xchg %rbx, {0}",
+            "enclu",
+            "mov {0}, %rbx",
+            inout(reg) target_info => _,
+            in("eax") ENCLU_EREPORT,
+            in("rcx") report_data,
+            in("rdx") report.0.as_mut_ptr(),
+            options(att_syntax, preserves_flags, nostack),
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_unseal() {
+        let rng = rand::CpuRandom::new().unwrap();
+        for &policy in &[Policy::MrEnclave, Policy::MrSigner] {
+            let sealed = seal_data(policy, b"ad", b"secret", &rng).unwrap();
+            assert_eq!(sealed.len(), HEADER_LEN + 6 + TAG_LEN);
+            assert_eq!(unseal_data(b"ad", &sealed).unwrap(), b"secret");
+            assert!(unseal_data(b"", &sealed).is_err());
+
+            // Every byte is authenticated, including those of the key
+            // request.
+            for i in 0..sealed.len() {
+                let mut tampered = sealed.clone();
+                tampered[i] ^= 1;
+                assert!(unseal_data(b"ad", &tampered).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_unseal_too_short() {
+        assert!(unseal_data(b"", &[0; HEADER_LEN + TAG_LEN - 1]).is_err());
+    }
+}
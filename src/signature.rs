@@ -41,7 +41,7 @@
 //! [RFC 3279 Section 2.2.3]. This is the form of ECDSA signature used in
 //! X.509-related structures and in TLS's `ServerKeyExchange` messages.
 //!
-//! The public key is encoding in uncompressed form using the
+//! The public key is encoding in uncompressed or compressed form using the
 //! Octet-String-to-Elliptic-Curve-Point algorithm in
 //! [SEC 1: Elliptic Curve Cryptography, Version 2.0].
 //!
@@ -60,7 +60,7 @@
 //! components) and a P-384 signature will be 96 bytes long (two 48-byte
 //! components). This is the form of ECDSA signature used PKCS#11 and DNSSEC.
 //!
-//! The public key is encoding in uncompressed form using the
+//! The public key is encoding in uncompressed or compressed form using the
 //! Octet-String-to-Elliptic-Curve-Point algorithm in
 //! [SEC 1: Elliptic Curve Cryptography, Version 2.0].
 //!
@@ -198,7 +198,7 @@
 //! ```
 //! use ring::{rand, signature};
 //!
-//! # #[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
+//! # #[cfg(all(feature = "rsa_signing", feature = "alloc"))]
 //! fn sign_and_verify_rsa(private_key_path: &std::path::Path,
 //!                        public_key_path: &std::path::Path)
 //!                        -> Result<(), MyError> {
@@ -237,14 +237,14 @@
 //!
 //! #[derive(Debug)]
 //! enum MyError {
-//! #  #[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
+//! #  #[cfg(all(feature = "rsa_signing", feature = "alloc"))]
 //!    IO(std::io::Error),
 //!    BadPrivateKey,
 //!    OOM,
 //!    BadSignature,
 //! }
 //!
-//! # #[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
+//! # #[cfg(all(feature = "rsa_signing", feature = "alloc"))]
 //! fn read_file(path: &std::path::Path) -> Result<Vec<u8>, MyError> {
 //!     use std::io::Read;
 //!
@@ -254,7 +254,7 @@
 //!     Ok(contents)
 //! }
 //! #
-//! # #[cfg(not(all(feature = "rsa_signing", feature = "use_heap")))]
+//! # #[cfg(not(all(feature = "rsa_signing", feature = "alloc")))]
 //! # fn sign_and_verify_rsa(_private_key_path: &std::path::Path,
 //! #                        _public_key_path: &std::path::Path)
 //! #                        -> Result<(), ()> {
@@ -270,14 +270,14 @@
 //! # }
 //! ```
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 use crate::rand;
 use crate::{cpu, error, private};
 use core;
 use untrusted;
 
-#[cfg(feature = "use_heap")]
-use std;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
 
 pub use crate::ec::suite_b::ecdsa::{
     signing::{
@@ -291,18 +291,24 @@ pub use crate::ec::suite_b::ecdsa::{
     },
 };
 
-pub use crate::ec::curve25519::ed25519::PUBLIC_KEY_LEN as ED25519_PUBLIC_KEY_LEN;
+pub use crate::ec::curve25519::ed25519::{
+    PUBLIC_KEY_LEN as ED25519_PUBLIC_KEY_LEN, SEED_LEN as ED25519_SEED_LEN,
+    SIGNATURE_LEN as ED25519_SIGNATURE_LEN,
+};
 
 pub use crate::ec::curve25519::ed25519::verification::{EdDSAParameters, ED25519};
 
+#[cfg(feature = "alloc")]
+pub use crate::ec::curve25519::ed25519::verification::PublicKeyPrecomputed as Ed25519PublicKeyPrecomputed;
+
 pub use crate::ec::curve25519::ed25519::signing::{
     KeyPair as Ed25519KeyPair, PKCS8_V2_LEN as ED25519_PKCS8_V2_LEN,
 };
 
-#[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
+#[cfg(all(feature = "rsa_signing", feature = "alloc"))]
 pub use crate::rsa::signing::{KeyPair as RSAKeyPair, SigningState as RSASigningState};
 
-#[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
+#[cfg(all(feature = "rsa_signing", feature = "alloc"))]
 pub use crate::rsa::{
     RSAEncoding,
 
@@ -319,43 +325,46 @@ pub use crate::rsa::{
     RSA_PSS_SHA512,
 };
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 pub use crate::rsa::RSAParameters;
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 pub use crate::rsa::verification::{
     RSA_PKCS1_2048_8192_SHA1, RSA_PKCS1_2048_8192_SHA256, RSA_PKCS1_2048_8192_SHA384,
     RSA_PKCS1_2048_8192_SHA512, RSA_PKCS1_3072_8192_SHA384, RSA_PSS_2048_8192_SHA256,
     RSA_PSS_2048_8192_SHA384, RSA_PSS_2048_8192_SHA512,
 };
 
-pub use crate::signature_impl::Signature;
+#[cfg(feature = "alloc")]
+pub use crate::rsa::verification::ParsedPublicKey as RSAParsedPublicKey;
+
+pub use crate::signature_impl::{Signature, MAX_LEN as ECDSA_MAX_SIGNATURE_LEN};
 
 /// Lower-level verification primitives. Usage of `ring::signature::verify()`
 /// is preferred when the public key and signature are encoded in standard
 /// formats, as it also handles the parsing.
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 pub mod primitive {
     pub use crate::rsa::verification::verify_rsa;
 }
 
 /// A key pair for signing.
 #[derive(Debug)]
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 pub struct KeyPair {
     inner: Box<KeyPairImpl + Send + Sync>,
 }
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 impl KeyPair {
     pub(crate) fn new<I: KeyPairImpl + Sync>(inner: I) -> Self {
         Self {
-            inner: std::boxed::Box::new(inner),
+            inner: Box::new(inner),
         }
     }
 }
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 pub(crate) trait KeyPairImpl: core::fmt::Debug + Send + 'static {
     fn sign(
         &self, rng: &rand::SecureRandom, msg: untrusted::Input,
@@ -363,7 +372,7 @@ pub(crate) trait KeyPairImpl: core::fmt::Debug + Send + 'static {
 }
 
 /// An algorithm for signing.
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 pub trait SigningAlgorithm: core::fmt::Debug + Sync + 'static + private::Sealed {
     /// Parses the key out of the given PKCS#8 document, verifying that it is
     /// valid for the algorithm.
@@ -373,7 +382,7 @@ pub trait SigningAlgorithm: core::fmt::Debug + Sync + 'static + private::Sealed
 /// Returns a key for signing that is parsed from a PKCS#8 document.
 ///
 /// The key is checked to ensure it is valid for the given algorithm.
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 #[inline]
 pub fn key_pair_from_pkcs8(
     alg: &'static SigningAlgorithm, input: untrusted::Input,
@@ -383,7 +392,7 @@ pub fn key_pair_from_pkcs8(
 
 /// Returns a signature of the given data using the given key. The signing may
 /// or may not use `rng`, depending on the `key_pair's algorithm.
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 #[inline]
 pub fn sign(
     key_pair: &KeyPair, rng: &rand::SecureRandom, msg: untrusted::Input,
@@ -391,6 +400,22 @@ pub fn sign(
     key_pair.inner.sign(rng, msg)
 }
 
+/// Like `sign()`, but writes the signature into the beginning of `out` and
+/// returns its length instead of returning a `Signature`.
+///
+/// Fails if `out` is too short to hold the signature.
+#[cfg(feature = "alloc")]
+pub fn sign_into(
+    key_pair: &KeyPair, rng: &rand::SecureRandom, msg: untrusted::Input, out: &mut [u8],
+) -> Result<usize, error::Unspecified> {
+    let signature = sign(key_pair, rng, msg)?;
+    let signature = signature.as_ref();
+    out.get_mut(..signature.len())
+        .ok_or(error::Unspecified)?
+        .copy_from_slice(signature);
+    Ok(signature.len())
+}
+
 /// A signature verification algorithm.
 pub trait VerificationAlgorithm: core::fmt::Debug + Sync + private::Sealed {
     /// Verify the signature `signature` of message `msg` with the public key
@@ -414,7 +439,7 @@ pub trait VerificationAlgorithm: core::fmt::Debug + Sync + private::Sealed {
 ///     InvalidSignature,
 /// }
 ///
-/// # #[cfg(feature = "use_heap")]
+/// # #[cfg(feature = "alloc")]
 /// fn verify_rsa_pkcs1_sha256(
 ///     public_key: untrusted::Input, msg: untrusted::Input, sig: untrusted::Input,
 /// ) -> Result<(), Error> {
@@ -430,3 +455,18 @@ pub fn verify(
     cpu::cache_detected_features();
     alg.verify(public_key, msg, signature)
 }
+
+/// Verify the signature of each `(msg, signature)` in `msgs_and_sigs` with
+/// the same ECDSA public key `public_key` using the algorithm `alg`.
+///
+/// This is faster than calling `verify()` for each of them, as the public key
+/// is parsed and validated, and a table of its multiples is computed, only
+/// once. Verification fails if any of the signatures is invalid; use
+/// `verify()` to find out which ones are.
+pub fn verify_batch_same_key(
+    alg: &ECDSAVerification, public_key: untrusted::Input,
+    msgs_and_sigs: &[(untrusted::Input, untrusted::Input)],
+) -> Result<(), error::Unspecified> {
+    cpu::cache_detected_features();
+    alg.verify_batch_same_key(public_key, msgs_and_sigs)
+}
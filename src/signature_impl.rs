@@ -37,6 +37,8 @@ pub fn signature_from_bytes(bytes: &[u8]) -> Signature {
     r
 }
 
+/// The maximum length of an ECDSA signature.
+///
 /// The longest signature is an ASN.1 P-384 signature where *r* and *s* are of
 /// maximum length with the leading high bit set on each. Then each component
 /// will have a tag, a one-byte length, and a one-byte “I'm not negative”
@@ -0,0 +1,319 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Public keys and their encoding as an X.509 `SubjectPublicKeyInfo`
+//! ([RFC 5280 Section 4.1.2.7]).
+//!
+//! The algorithm identifiers are those of [RFC 8410] for Ed25519 and X25519,
+//! [RFC 5480] for ECDSA and ECDH, and [RFC 3279] for RSA, except for the
+//! blind RSA keys of `privacypass`, which use [RFC 4055]'s `id-RSASSA-PSS`.
+//!
+//! [RFC 5280 Section 4.1.2.7]: https://tools.ietf.org/html/rfc5280#section-4.1.2.7
+//! [RFC 8410]: https://tools.ietf.org/html/rfc8410
+//! [RFC 5480]: https://tools.ietf.org/html/rfc5480
+//! [RFC 3279]: https://tools.ietf.org/html/rfc3279
+//! [RFC 4055]: https://tools.ietf.org/html/rfc4055
+
+use crate::{ec, io::der};
+use core;
+
+#[cfg(any(feature = "alloc", feature = "serde"))]
+use crate::error;
+
+#[cfg(feature = "alloc")]
+use crate::pem;
+
+// The values (not including the outermost `SEQUENCE` tag and length) of the
+// `AlgorithmIdentifier`s.
+pub(crate) const ED25519: &[u8] = include_bytes!("data/alg-ed25519.der");
+pub(crate) const X25519: &[u8] = include_bytes!("data/alg-x25519.der");
+pub(crate) const EC_PUBLIC_KEY_P256: &[u8] = include_bytes!("data/alg-ec-public-key-p256.der");
+pub(crate) const EC_PUBLIC_KEY_P384: &[u8] = include_bytes!("data/alg-ec-public-key-p384.der");
+#[cfg(any(test, feature = "alloc"))]
+pub(crate) const RSA_ENCRYPTION: &[u8] = include_bytes!("data/alg-rsa-encryption.der");
+
+// `id-RSASSA-PSS` with SHA-384, MGF1 with SHA-384, and a 48-byte salt, which
+// is how Privacy Pass identifies its blind RSA keys (RFC 9578 Section 6.5).
+#[cfg(any(test, feature = "rsa_signing"))]
+pub(crate) const RSASSA_PSS_SHA384: &[u8] = include_bytes!("data/alg-rsassa-pss-sha384.der");
+
+// The longest `AlgorithmIdentifier` value above, which is
+// `RSASSA_PSS_SHA384`.
+const ALG_ID_MAX_LEN: usize = 61;
+
+// The largest RSA modulus that is supported, in bits. It is 8192 bits unless
+// one of the `rsa_max_bits_*` features lowers it, in which case the smallest
+// one wins, so that the buffers sized for the largest modulus are smaller.
+pub(crate) const RSA_MODULUS_MAX_BITS: usize = if cfg!(feature = "rsa_max_bits_2048") {
+    2048
+} else if cfg!(feature = "rsa_max_bits_3072") {
+    3072
+} else if cfg!(feature = "rsa_max_bits_4096") {
+    4096
+} else {
+    8192
+};
+
+// An RSA `RSAPublicKey` for a modulus of `RSA_MODULUS_MAX_BITS` bits and an
+// exponent no larger than 2**33 - 1, which are the limits for RSA signature
+// verification, is the longest public key.
+pub(crate) const RSA_MODULUS_MAX_LEN: usize = RSA_MODULUS_MAX_BITS / 8;
+pub(crate) const RSA_EXPONENT_MAX_LEN: usize = 5;
+const PUBLIC_KEY_MAX_LEN: usize =
+    4 + (4 + 1 + RSA_MODULUS_MAX_LEN) + (2 + 1 + RSA_EXPONENT_MAX_LEN);
+
+pub(crate) const DOCUMENT_MAX_LEN: usize = 4 + (2 + ALG_ID_MAX_LEN) + (4 + 1 + PUBLIC_KEY_MAX_LEN);
+
+/// The public key of a key pair.
+///
+/// `public_key.as_ref()` is the public key in the form that
+/// `signature::verify()` and `agreement::agree_ephemeral()` expect it: the
+/// 32-byte public key for Ed25519 and X25519, the uncompressed point for the
+/// NIST P-256 and P-384 curves, and a DER-encoded `RSAPublicKey` for RSA. That
+/// is the value of the `subjectPublicKey` field of a `SubjectPublicKeyInfo`.
+#[derive(Clone)]
+pub struct PublicKey {
+    bytes: [u8; PUBLIC_KEY_MAX_LEN],
+    len: usize,
+    alg_id: &'static [u8],
+}
+
+impl PublicKey {
+    pub(crate) fn new(alg_id: &'static [u8], key: &[u8]) -> Self {
+        let mut r = Self {
+            bytes: [0; PUBLIC_KEY_MAX_LEN],
+            len: key.len(),
+            alg_id,
+        };
+        r.bytes[..key.len()].copy_from_slice(key);
+        r
+    }
+
+    pub(crate) fn from_ec_public_key(curve: &ec::Curve, key: &[u8]) -> Self {
+        let alg_id = match curve.id {
+            ec::CurveID::Curve25519 => X25519,
+            ec::CurveID::P256 => EC_PUBLIC_KEY_P256,
+            ec::CurveID::P384 => EC_PUBLIC_KEY_P384,
+        };
+        Self::new(alg_id, key)
+    }
+
+    /// Constructs an RSA public key by encoding `n` and `e`, which must be
+    /// big-endian-encoded positive integers without leading zeros and no
+    /// longer than `RSA_MODULUS_MAX_LEN` and `RSA_EXPONENT_MAX_LEN` bytes, as
+    /// an `RSAPublicKey`.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn from_rsa_components(n: &[u8], e: &[u8]) -> Self {
+        let mut r = Self {
+            bytes: [0; PUBLIC_KEY_MAX_LEN],
+            len: 0,
+            alg_id: RSA_ENCRYPTION,
+        };
+        let value_len = integer_len(n) + integer_len(e);
+        let mut writer = Writer {
+            bytes: &mut r.bytes,
+            len: 0,
+        };
+        writer.write_header(der::Tag::Sequence, value_len);
+        writer.write_integer(n);
+        writer.write_integer(e);
+        r.len = writer.len;
+        r
+    }
+
+    // Parses a DER-encoded `SubjectPublicKeyInfo` with one of the algorithm
+    // identifiers above. The key itself isn't checked.
+    #[cfg(any(feature = "alloc", feature = "serde"))]
+    pub(crate) fn from_spki_der(input: untrusted::Input) -> Result<Self, error::Unspecified> {
+        let known_alg_ids = [
+            ED25519,
+            X25519,
+            EC_PUBLIC_KEY_P256,
+            EC_PUBLIC_KEY_P384,
+            #[cfg(feature = "alloc")]
+            RSA_ENCRYPTION,
+            #[cfg(feature = "rsa_signing")]
+            RSASSA_PSS_SHA384,
+        ];
+        input.read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+                let alg_id = der::expect_tag_and_get_value(input, der::Tag::Sequence)?;
+                let key = der::bit_string_with_no_unused_bits(input)?;
+                let alg_id = known_alg_ids
+                    .iter()
+                    .find(|known| alg_id == **known)
+                    .ok_or(error::Unspecified)?;
+                if key.len() > PUBLIC_KEY_MAX_LEN {
+                    return Err(error::Unspecified);
+                }
+                Ok(Self::new(alg_id, key.as_slice_less_safe()))
+            })
+        })
+    }
+
+    /// Parses a PEM-encoded `SubjectPublicKeyInfo` with the label
+    /// `PUBLIC KEY`, e.g. the output of `openssl pkey -pubout`.
+    ///
+    /// Only the algorithms of the public keys that *ring* encodes are
+    /// supported. The key itself isn't checked until it is used, e.g. by
+    /// `signature::verify()`.
+    #[cfg(feature = "alloc")]
+    pub fn from_spki_pem(input: &str) -> Result<Self, error::Unspecified> {
+        let der = pem::decode(input, pem::PUBLIC_KEY)?;
+        Self::from_spki_der(untrusted::Input::from(&der))
+    }
+
+    #[cfg(feature = "alloc")]
+    pub(crate) fn alg_id(&self) -> &'static [u8] { self.alg_id }
+
+    /// Encodes the public key as a DER-encoded `SubjectPublicKeyInfo`.
+    ///
+    /// The result can be PEM-encoded with
+    /// `pem::encode(pem::PUBLIC_KEY, &spki)`, which `from_spki_pem()` parses.
+    pub fn to_spki_der(&self) -> Document {
+        let key = self.as_ref();
+        let alg_id_len = tlv_len(self.alg_id.len());
+        let subject_public_key_len = tlv_len(1 + key.len());
+
+        let mut r = Document {
+            bytes: [0; DOCUMENT_MAX_LEN],
+            len: 0,
+        };
+        let mut writer = Writer {
+            bytes: &mut r.bytes,
+            len: 0,
+        };
+        writer.write_header(der::Tag::Sequence, alg_id_len + subject_public_key_len);
+        writer.write_header(der::Tag::Sequence, self.alg_id.len());
+        writer.write_bytes(self.alg_id);
+        writer.write_header(der::Tag::BitString, 1 + key.len());
+        writer.write_bytes(&[0]); // No unused bits.
+        writer.write_bytes(key);
+        r.len = writer.len;
+        r
+    }
+}
+
+impl AsRef<[u8]> for PublicKey {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+impl core::fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
+        f.write_str("PublicKey(\"")?;
+        for b in self.as_ref() {
+            write!(f, "{:02x}", b)?;
+        }
+        f.write_str("\")")
+    }
+}
+
+/// A DER-encoded `SubjectPublicKeyInfo`.
+pub struct Document {
+    bytes: [u8; DOCUMENT_MAX_LEN],
+    len: usize,
+}
+
+impl AsRef<[u8]> for Document {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+derive_debug_via_self!(Document, self.as_ref());
+
+struct Writer<'a> {
+    bytes: &'a mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn write_header(&mut self, tag: der::Tag, value_len: usize) {
+        self.write_bytes(&[tag as u8]);
+        // Everything written here is shorter than 2**16 bytes.
+        if value_len < 0x80 {
+            self.write_bytes(&[value_len as u8]);
+        } else if value_len < 0x100 {
+            self.write_bytes(&[0x81, value_len as u8]);
+        } else {
+            self.write_bytes(&[0x82, (value_len >> 8) as u8, value_len as u8]);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn write_integer(&mut self, value: &[u8]) {
+        let needs_leading_zero = (value[0] & 0x80) != 0;
+        self.write_header(der::Tag::Integer, value.len() + (needs_leading_zero as usize));
+        if needs_leading_zero {
+            self.write_bytes(&[0]);
+        }
+        self.write_bytes(value);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes[self.len..(self.len + bytes.len())].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+}
+
+fn tlv_len(value_len: usize) -> usize {
+    let len_len = if value_len < 0x80 {
+        1
+    } else if value_len < 0x100 {
+        2
+    } else {
+        3
+    };
+    1 + len_len + value_len
+}
+
+#[cfg(feature = "alloc")]
+fn integer_len(value: &[u8]) -> usize { tlv_len(value.len() + (((value[0] & 0x80) != 0) as usize)) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alg_id_max_len() {
+        let alg_ids = [
+            ED25519,
+            X25519,
+            EC_PUBLIC_KEY_P256,
+            EC_PUBLIC_KEY_P384,
+            RSA_ENCRYPTION,
+            RSASSA_PSS_SHA384,
+        ];
+        assert_eq!(alg_ids.iter().map(|a| a.len()).max(), Some(ALG_ID_MAX_LEN));
+    }
+
+    #[test]
+    fn test_tlv_len() {
+        assert_eq!(tlv_len(0), 2);
+        assert_eq!(tlv_len(0x7f), 0x81);
+        assert_eq!(tlv_len(0x80), 0x83);
+        assert_eq!(tlv_len(0xff), 0x102);
+        assert_eq!(tlv_len(0x100), 0x104);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_from_rsa_components() {
+        let key = PublicKey::from_rsa_components(&[0x80, 0x01], &[0x01, 0x00, 0x01]);
+        assert_eq!(
+            key.as_ref(),
+            &[0x30, 0x0a, 0x02, 0x03, 0x00, 0x80, 0x01, 0x02, 0x03, 0x01, 0x00, 0x01][..]
+        );
+    }
+}
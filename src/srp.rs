@@ -0,0 +1,621 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! SRP-6a password-authenticated key exchange.
+//!
+//! The groups are those of [RFC 5054] Appendix A and the hash function is
+//! always SHA-256. `k`, `u`, `x`, the verifier `v`, and the premaster secret
+//! `S` are computed as in [RFC 5054] Section 2.5 and 2.6:
+//!
+//! * `k = H(N | PAD(g))`
+//! * `u = H(PAD(A) | PAD(B))`
+//! * `x = H(s | H(I | ":" | P))`
+//! * `v = g^x % N`
+//!
+//! The session key and the proofs are computed as in [RFC 2945] Section 3,
+//! except that `H` is SHA-256, where integers are encoded without leading
+//! zeros:
+//!
+//! * `K = H(S)`
+//! * `M1 = H(H(N) XOR H(g) | H(I) | s | A | B | K)`
+//! * `M2 = H(A | M1 | K)`
+//!
+//! This is what most other SRP-6a implementations that are configured for
+//! SHA-256 do. The private values `a` and `b` are 256 bits.
+//!
+//! The exchange is:
+//!
+//! 1. The client sends its username `I` and its public key `A`
+//!    (`Client::public_key()`).
+//! 2. The server looks up the salt `s` and verifier `v` that it stored for
+//!    `I` when the password was set (`compute_verifier()`), and sends `s` and
+//!    its public key `B` (`Server::public_key()`).
+//! 3. The client sends its proof `M1` (`Client::compute_proof()`).
+//! 4. The server checks `M1` and sends its proof `M2`
+//!    (`Server::verify_client()`).
+//! 5. The client checks `M2` (`ClientProof::verify_server()`).
+//!
+//! Both sides then share the session key `K`. Every check uses a constant-time
+//! comparison and every exponentiation with a secret exponent is constant
+//! time.
+//!
+//! With the `rsa_max_bits_2048` or `rsa_max_bits_3072` features, the groups
+//! that are larger than the limit are rejected.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{rand, srp};
+//!
+//! # fn exchange() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let group = &srp::GROUP_2048;
+//!
+//! // When the password is set, the server stores the salt and the verifier.
+//! let mut salt = [0u8; 16];
+//! rand::SecureRandom::fill(&rng, &mut salt)?;
+//! let verifier = srp::compute_verifier(group, b"alice", b"password", &salt)?;
+//!
+//! let client = srp::Client::new(group, &rng)?;
+//! let client_public_key = client.public_key().to_vec();
+//! let server = srp::Server::new(group, b"alice", &salt, &verifier, &rng)?;
+//!
+//! let client_proof =
+//!     client.compute_proof(b"alice", b"password", &salt, server.public_key())?;
+//! let server_proof = server.verify_client(&client_public_key, client_proof.proof())?;
+//! let client_key = client_proof.verify_server(server_proof.proof())?;
+//! assert_eq!(client_key.as_ref(), server_proof.session_key().as_ref());
+//! # Ok(())
+//! # }
+//! # fn main() { exchange().unwrap() }
+//! ```
+//!
+//! [RFC 5054]: https://tools.ietf.org/html/rfc5054
+//! [RFC 2945]: https://tools.ietf.org/html/rfc2945
+
+use crate::{
+    arithmetic::montgomery::{Unencoded, R},
+    constant_time, digest, error, rand,
+    rsa::bigint::{self, Elem, Modulus, PrivateExponent},
+    zeroize,
+};
+use alloc::vec::Vec;
+use untrusted;
+
+/// An SRP group: a safe prime `N` and a generator `g`.
+pub struct Group {
+    n: &'static [u8],
+    g: u8,
+}
+
+impl Group {
+    /// The length of `N`, in bytes. This is also the length of public keys
+    /// and verifiers.
+    #[inline]
+    pub fn modulus_len(&self) -> usize { self.n.len() }
+}
+
+/// The 2048-bit group of [RFC 5054] Appendix A, with `g = 2`.
+///
+/// [RFC 5054]: https://tools.ietf.org/html/rfc5054#appendix-A
+pub static GROUP_2048: Group = Group { n: &N_2048, g: 2 };
+
+/// The 3072-bit group of [RFC 5054] Appendix A, with `g = 5`.
+///
+/// [RFC 5054]: https://tools.ietf.org/html/rfc5054#appendix-A
+pub static GROUP_3072: Group = Group { n: &N_3072, g: 5 };
+
+/// The 4096-bit group of [RFC 5054] Appendix A, with `g = 5`.
+///
+/// [RFC 5054]: https://tools.ietf.org/html/rfc5054#appendix-A
+pub static GROUP_4096: Group = Group { n: &N_4096, g: 5 };
+
+/// The length of `M1`, `M2`, and the session key `K`.
+pub const PROOF_LEN: usize = digest::SHA256_OUTPUT_LEN;
+
+/// The length of a session key.
+pub const SESSION_KEY_LEN: usize = digest::SHA256_OUTPUT_LEN;
+
+// RFC 5054 Section 2.5: "The random values [a and b] ... SHOULD be at least 256
+// bits in length."
+const PRIVATE_KEY_LEN: usize = 32;
+
+/// Computes the verifier `v` that the server stores for `username`, in place
+/// of the password.
+///
+/// The result is `group.modulus_len()` bytes. `salt` should be at least 16
+/// random bytes, chosen whenever the password is set.
+pub fn compute_verifier(
+    group: &'static Group, username: &[u8], password: &[u8], salt: &[u8],
+) -> Result<Vec<u8>, error::Unspecified> {
+    let m = modulus(group)?;
+    let x = compute_x(username, password, salt);
+    let v = exp(generator(group, &m)?, x.as_ref(), &m)?;
+    Ok(to_padded(&v, group))
+}
+
+/// The client's state before it has received the server's public key.
+pub struct Client {
+    group: &'static Group,
+    m: Modulus<N>,
+    a: [u8; PRIVATE_KEY_LEN],
+    public_key: Vec<u8>,
+}
+
+impl Drop for Client {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.a) }
+}
+
+impl Client {
+    /// Generates the client's private key `a` and public key `A`.
+    pub fn new(
+        group: &'static Group, rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        let m = modulus(group)?;
+        let mut a = [0; PRIVATE_KEY_LEN];
+        rng.fill(&mut a)?;
+        // A = g^a % N
+        let public_key = to_padded(&exp(generator(group, &m)?, &a, &m)?, group);
+        Ok(Self {
+            group,
+            m,
+            a,
+            public_key,
+        })
+    }
+
+    /// The client's public key `A`, `group.modulus_len()` bytes.
+    #[inline]
+    pub fn public_key(&self) -> &[u8] { &self.public_key }
+
+    /// Computes the session key and the proof `M1` from the server's public
+    /// key `B` and the salt that the server sent.
+    ///
+    /// Fails if `B % N` is zero, if `B` isn't less than `N`, or if `u` is zero.
+    pub fn compute_proof(
+        self, username: &[u8], password: &[u8], salt: &[u8], server_public_key: &[u8],
+    ) -> Result<ClientProof, error::Unspecified> {
+        let group = self.group;
+        let m = &self.m;
+        let b_pub = peer_public_key(server_public_key, m)?;
+        let b_pub_padded = to_padded(&b_pub, group);
+        let u = compute_u(&self.public_key, &b_pub_padded)?;
+        let x = compute_x(username, password, salt);
+
+        // S = (B - k * g^x) ^ (a + u * x) % N, computed as
+        // (B - k * g^x)^a * ((B - k * g^x)^u)^x so that no arithmetic modulo
+        // the order of the group is needed.
+        let k = multiplier(group, m)?;
+        let g_x = exp(generator(group, m)?, x.as_ref(), m)?;
+        let base = bigint::elem_sub(b_pub, &bigint::elem_mul(&k, g_x, m), m);
+        let base_a = to_r(exp(base.clone(), &self.a, m)?, m);
+        let base_u_x = exp(exp(base, u.as_ref(), m)?, x.as_ref(), m)?;
+        let s = bigint::elem_mul(&base_a, base_u_x, m);
+
+        let session_key = session_key(&s, group);
+        let proof = compute_m1(
+            group,
+            username,
+            salt,
+            &self.public_key,
+            &b_pub_padded,
+            &session_key,
+        );
+        let server_proof = compute_m2(&self.public_key, &proof, &session_key);
+        Ok(ClientProof {
+            proof,
+            server_proof,
+            session_key,
+        })
+    }
+}
+
+/// The client's state after it has computed its proof `M1`.
+pub struct ClientProof {
+    proof: [u8; PROOF_LEN],
+    server_proof: [u8; PROOF_LEN],
+    session_key: SessionKey,
+}
+
+impl ClientProof {
+    /// The client's proof `M1`, to be sent to the server.
+    #[inline]
+    pub fn proof(&self) -> &[u8] { &self.proof }
+
+    /// Verifies the server's proof `M2`, returning the session key if it is
+    /// valid.
+    pub fn verify_server(self, server_proof: &[u8]) -> Result<SessionKey, error::Unspecified> {
+        constant_time::verify_slices_are_equal(&self.server_proof, server_proof)?;
+        Ok(self.session_key)
+    }
+}
+
+/// The server's state before it has received the client's proof.
+pub struct Server {
+    group: &'static Group,
+    m: Modulus<N>,
+    username: Vec<u8>,
+    salt: Vec<u8>,
+    v: Elem<N, Unencoded>,
+    b: [u8; PRIVATE_KEY_LEN],
+    public_key: Vec<u8>,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.b) }
+}
+
+impl Server {
+    /// Generates the server's private key `b` and public key `B` for the
+    /// user with the given username, salt, and verifier (from
+    /// `compute_verifier()`).
+    pub fn new(
+        group: &'static Group, username: &[u8], salt: &[u8], verifier: &[u8],
+        rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        let m = modulus(group)?;
+        let v = Elem::from_be_bytes_padded(untrusted::Input::from(verifier), &m)?;
+        let mut b = [0; PRIVATE_KEY_LEN];
+        rng.fill(&mut b)?;
+
+        // B = (k * v + g^b) % N
+        let k_v = bigint::elem_mul(&multiplier(group, &m)?, v.clone(), &m);
+        let g_b = exp(generator(group, &m)?, &b, &m)?;
+        let public_key = to_padded(&bigint::elem_add(k_v, g_b, &m), group);
+
+        Ok(Self {
+            group,
+            m,
+            username: username.to_vec(),
+            salt: salt.to_vec(),
+            v,
+            b,
+            public_key,
+        })
+    }
+
+    /// The server's public key `B`, `group.modulus_len()` bytes.
+    #[inline]
+    pub fn public_key(&self) -> &[u8] { &self.public_key }
+
+    /// Verifies the client's proof `M1` given the client's public key `A`.
+    ///
+    /// Fails if `A % N` is zero, if `A` isn't less than `N`, if `u` is zero,
+    /// or if the proof is wrong, e.g. because the client used the wrong
+    /// password.
+    pub fn verify_client(
+        self, client_public_key: &[u8], client_proof: &[u8],
+    ) -> Result<ServerProof, error::Unspecified> {
+        let group = self.group;
+        let m = &self.m;
+        let a_pub = peer_public_key(client_public_key, m)?;
+        let a_pub_padded = to_padded(&a_pub, group);
+        let u = compute_u(&a_pub_padded, &self.public_key)?;
+
+        // S = (A * v^u) ^ b % N
+        let v_u = exp(self.v.clone(), u.as_ref(), m)?;
+        let base = bigint::elem_mul(&to_r(a_pub, m), v_u, m);
+        let s = exp(base, &self.b, m)?;
+
+        let session_key = session_key(&s, group);
+        let expected = compute_m1(
+            group,
+            &self.username,
+            &self.salt,
+            &a_pub_padded,
+            &self.public_key,
+            &session_key,
+        );
+        constant_time::verify_slices_are_equal(&expected, client_proof)?;
+        let proof = compute_m2(&a_pub_padded, &expected, &session_key);
+        Ok(ServerProof { proof, session_key })
+    }
+}
+
+/// The server's state after it has verified the client's proof.
+pub struct ServerProof {
+    proof: [u8; PROOF_LEN],
+    session_key: SessionKey,
+}
+
+impl ServerProof {
+    /// The server's proof `M2`, to be sent to the client.
+    #[inline]
+    pub fn proof(&self) -> &[u8] { &self.proof }
+
+    /// The session key `K`.
+    #[inline]
+    pub fn session_key(&self) -> &SessionKey { &self.session_key }
+}
+
+/// A session key `K`, `SESSION_KEY_LEN` bytes.
+pub struct SessionKey([u8; SESSION_KEY_LEN]);
+
+impl AsRef<[u8]> for SessionKey {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.0 }
+}
+
+impl Drop for SessionKey {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.0) }
+}
+
+// The modulus `N` of an SRP group.
+enum N {}
+
+fn modulus(group: &Group) -> Result<Modulus<N>, error::Unspecified> {
+    let (m, _) = Modulus::from_be_bytes_with_bit_length(untrusted::Input::from(group.n))?;
+    Ok(m)
+}
+
+fn generator(group: &Group, m: &Modulus<N>) -> Result<Elem<N, Unencoded>, error::Unspecified> {
+    Elem::from_be_bytes_padded(untrusted::Input::from(&[group.g]), m)
+}
+
+// k = H(N | PAD(g)), in the Montgomery domain.
+fn multiplier(group: &Group, m: &Modulus<N>) -> Result<Elem<N, R>, error::Unspecified> {
+    let mut padded_g = vec![0; group.n.len()];
+    *padded_g.last_mut().unwrap() = group.g;
+    let k = hash(&[group.n, &padded_g]);
+    Ok(to_r(Elem::from_be_bytes_padded(untrusted::Input::from(k.as_ref()), m)?, m))
+}
+
+// x = H(s | H(I | ":" | P))
+fn compute_x(username: &[u8], password: &[u8], salt: &[u8]) -> digest::Digest {
+    hash(&[salt, hash(&[username, b":", password]).as_ref()])
+}
+
+// u = H(PAD(A) | PAD(B)), which must not be zero.
+fn compute_u(a_pub: &[u8], b_pub: &[u8]) -> Result<digest::Digest, error::Unspecified> {
+    let u = hash(&[a_pub, b_pub]);
+    if u.as_ref().iter().all(|&b| b == 0) {
+        return Err(error::Unspecified);
+    }
+    Ok(u)
+}
+
+// M1 = H(H(N) XOR H(g) | H(I) | s | A | B | K)
+fn compute_m1(
+    group: &Group, username: &[u8], salt: &[u8], a_pub: &[u8], b_pub: &[u8],
+    session_key: &SessionKey,
+) -> [u8; PROOF_LEN] {
+    let mut h_n_xor_h_g = [0; digest::SHA256_OUTPUT_LEN];
+    h_n_xor_h_g.copy_from_slice(hash(&[group.n]).as_ref());
+    for (r, h_g) in h_n_xor_h_g.iter_mut().zip(hash(&[&[group.g]]).as_ref()) {
+        *r ^= h_g;
+    }
+    let m1 = hash(&[
+        &h_n_xor_h_g,
+        hash(&[username]).as_ref(),
+        salt,
+        unpadded(a_pub),
+        unpadded(b_pub),
+        session_key.as_ref(),
+    ]);
+    let mut r = [0; PROOF_LEN];
+    r.copy_from_slice(m1.as_ref());
+    r
+}
+
+// M2 = H(A | M1 | K)
+fn compute_m2(a_pub: &[u8], m1: &[u8], session_key: &SessionKey) -> [u8; PROOF_LEN] {
+    let m2 = hash(&[unpadded(a_pub), m1, session_key.as_ref()]);
+    let mut r = [0; PROOF_LEN];
+    r.copy_from_slice(m2.as_ref());
+    r
+}
+
+// K = H(S)
+fn session_key(s: &Elem<N, Unencoded>, group: &Group) -> SessionKey {
+    let mut s = to_padded(s, group);
+    let k = hash(&[unpadded(&s)]);
+    zeroize::zeroize(&mut s);
+    let mut r = SessionKey([0; SESSION_KEY_LEN]);
+    r.0.copy_from_slice(k.as_ref());
+    r
+}
+
+// Parses the peer's public key, rejecting values that are zero modulo `N`.
+// Values that aren't reduced modulo `N` are rejected too; a peer never needs
+// to send them.
+fn peer_public_key(input: &[u8], m: &Modulus<N>) -> Result<Elem<N, Unencoded>, error::Unspecified> {
+    let r = Elem::from_be_bytes_padded(untrusted::Input::from(input), m)?;
+    if r.is_zero() {
+        return Err(error::Unspecified);
+    }
+    Ok(r)
+}
+
+fn exp(
+    base: Elem<N, Unencoded>, exponent: &[u8], m: &Modulus<N>,
+) -> Result<Elem<N, Unencoded>, error::Unspecified> {
+    let exponent = PrivateExponent::from_be_bytes_padded_any(untrusted::Input::from(exponent), m)?;
+    bigint::elem_exp_consttime(to_r(base, m), &exponent, m)
+}
+
+#[inline]
+fn to_r(a: Elem<N, Unencoded>, m: &Modulus<N>) -> Elem<N, R> {
+    bigint::elem_mul(m.oneRR().as_ref(), a, m)
+}
+
+fn to_padded(a: &Elem<N, Unencoded>, group: &Group) -> Vec<u8> {
+    let mut r = vec![0; group.n.len()];
+    a.fill_be_bytes(&mut r);
+    r
+}
+
+fn unpadded(a: &[u8]) -> &[u8] {
+    let leading_zeros = a.iter().take_while(|&&b| b == 0).count();
+    &a[leading_zeros..]
+}
+
+fn hash(parts: &[&[u8]]) -> digest::Digest {
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    for part in parts {
+        ctx.update(part);
+    }
+    ctx.finish()
+}
+
+const N_2048: [u8; 256] = [
+    0xac, 0x6b, 0xdb, 0x41, 0x32, 0x4a, 0x9a, 0x9b, 0xf1, 0x66, 0xde, 0x5e, 0x13, 0x89, 0x58, 0x2f,
+    0xaf, 0x72, 0xb6, 0x65, 0x19, 0x87, 0xee, 0x07, 0xfc, 0x31, 0x92, 0x94, 0x3d, 0xb5, 0x60, 0x50,
+    0xa3, 0x73, 0x29, 0xcb, 0xb4, 0xa0, 0x99, 0xed, 0x81, 0x93, 0xe0, 0x75, 0x77, 0x67, 0xa1, 0x3d,
+    0xd5, 0x23, 0x12, 0xab, 0x4b, 0x03, 0x31, 0x0d, 0xcd, 0x7f, 0x48, 0xa9, 0xda, 0x04, 0xfd, 0x50,
+    0xe8, 0x08, 0x39, 0x69, 0xed, 0xb7, 0x67, 0xb0, 0xcf, 0x60, 0x95, 0x17, 0x9a, 0x16, 0x3a, 0xb3,
+    0x66, 0x1a, 0x05, 0xfb, 0xd5, 0xfa, 0xaa, 0xe8, 0x29, 0x18, 0xa9, 0x96, 0x2f, 0x0b, 0x93, 0xb8,
+    0x55, 0xf9, 0x79, 0x93, 0xec, 0x97, 0x5e, 0xea, 0xa8, 0x0d, 0x74, 0x0a, 0xdb, 0xf4, 0xff, 0x74,
+    0x73, 0x59, 0xd0, 0x41, 0xd5, 0xc3, 0x3e, 0xa7, 0x1d, 0x28, 0x1e, 0x44, 0x6b, 0x14, 0x77, 0x3b,
+    0xca, 0x97, 0xb4, 0x3a, 0x23, 0xfb, 0x80, 0x16, 0x76, 0xbd, 0x20, 0x7a, 0x43, 0x6c, 0x64, 0x81,
+    0xf1, 0xd2, 0xb9, 0x07, 0x87, 0x17, 0x46, 0x1a, 0x5b, 0x9d, 0x32, 0xe6, 0x88, 0xf8, 0x77, 0x48,
+    0x54, 0x45, 0x23, 0xb5, 0x24, 0xb0, 0xd5, 0x7d, 0x5e, 0xa7, 0x7a, 0x27, 0x75, 0xd2, 0xec, 0xfa,
+    0x03, 0x2c, 0xfb, 0xdb, 0xf5, 0x2f, 0xb3, 0x78, 0x61, 0x60, 0x27, 0x90, 0x04, 0xe5, 0x7a, 0xe6,
+    0xaf, 0x87, 0x4e, 0x73, 0x03, 0xce, 0x53, 0x29, 0x9c, 0xcc, 0x04, 0x1c, 0x7b, 0xc3, 0x08, 0xd8,
+    0x2a, 0x56, 0x98, 0xf3, 0xa8, 0xd0, 0xc3, 0x82, 0x71, 0xae, 0x35, 0xf8, 0xe9, 0xdb, 0xfb, 0xb6,
+    0x94, 0xb5, 0xc8, 0x03, 0xd8, 0x9f, 0x7a, 0xe4, 0x35, 0xde, 0x23, 0x6d, 0x52, 0x5f, 0x54, 0x75,
+    0x9b, 0x65, 0xe3, 0x72, 0xfc, 0xd6, 0x8e, 0xf2, 0x0f, 0xa7, 0x11, 0x1f, 0x9e, 0x4a, 0xff, 0x73,
+];
+
+const N_3072: [u8; 384] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xc9, 0x0f, 0xda, 0xa2, 0x21, 0x68, 0xc2, 0x34,
+    0xc4, 0xc6, 0x62, 0x8b, 0x80, 0xdc, 0x1c, 0xd1, 0x29, 0x02, 0x4e, 0x08, 0x8a, 0x67, 0xcc, 0x74,
+    0x02, 0x0b, 0xbe, 0xa6, 0x3b, 0x13, 0x9b, 0x22, 0x51, 0x4a, 0x08, 0x79, 0x8e, 0x34, 0x04, 0xdd,
+    0xef, 0x95, 0x19, 0xb3, 0xcd, 0x3a, 0x43, 0x1b, 0x30, 0x2b, 0x0a, 0x6d, 0xf2, 0x5f, 0x14, 0x37,
+    0x4f, 0xe1, 0x35, 0x6d, 0x6d, 0x51, 0xc2, 0x45, 0xe4, 0x85, 0xb5, 0x76, 0x62, 0x5e, 0x7e, 0xc6,
+    0xf4, 0x4c, 0x42, 0xe9, 0xa6, 0x37, 0xed, 0x6b, 0x0b, 0xff, 0x5c, 0xb6, 0xf4, 0x06, 0xb7, 0xed,
+    0xee, 0x38, 0x6b, 0xfb, 0x5a, 0x89, 0x9f, 0xa5, 0xae, 0x9f, 0x24, 0x11, 0x7c, 0x4b, 0x1f, 0xe6,
+    0x49, 0x28, 0x66, 0x51, 0xec, 0xe4, 0x5b, 0x3d, 0xc2, 0x00, 0x7c, 0xb8, 0xa1, 0x63, 0xbf, 0x05,
+    0x98, 0xda, 0x48, 0x36, 0x1c, 0x55, 0xd3, 0x9a, 0x69, 0x16, 0x3f, 0xa8, 0xfd, 0x24, 0xcf, 0x5f,
+    0x83, 0x65, 0x5d, 0x23, 0xdc, 0xa3, 0xad, 0x96, 0x1c, 0x62, 0xf3, 0x56, 0x20, 0x85, 0x52, 0xbb,
+    0x9e, 0xd5, 0x29, 0x07, 0x70, 0x96, 0x96, 0x6d, 0x67, 0x0c, 0x35, 0x4e, 0x4a, 0xbc, 0x98, 0x04,
+    0xf1, 0x74, 0x6c, 0x08, 0xca, 0x18, 0x21, 0x7c, 0x32, 0x90, 0x5e, 0x46, 0x2e, 0x36, 0xce, 0x3b,
+    0xe3, 0x9e, 0x77, 0x2c, 0x18, 0x0e, 0x86, 0x03, 0x9b, 0x27, 0x83, 0xa2, 0xec, 0x07, 0xa2, 0x8f,
+    0xb5, 0xc5, 0x5d, 0xf0, 0x6f, 0x4c, 0x52, 0xc9, 0xde, 0x2b, 0xcb, 0xf6, 0x95, 0x58, 0x17, 0x18,
+    0x39, 0x95, 0x49, 0x7c, 0xea, 0x95, 0x6a, 0xe5, 0x15, 0xd2, 0x26, 0x18, 0x98, 0xfa, 0x05, 0x10,
+    0x15, 0x72, 0x8e, 0x5a, 0x8a, 0xaa, 0xc4, 0x2d, 0xad, 0x33, 0x17, 0x0d, 0x04, 0x50, 0x7a, 0x33,
+    0xa8, 0x55, 0x21, 0xab, 0xdf, 0x1c, 0xba, 0x64, 0xec, 0xfb, 0x85, 0x04, 0x58, 0xdb, 0xef, 0x0a,
+    0x8a, 0xea, 0x71, 0x57, 0x5d, 0x06, 0x0c, 0x7d, 0xb3, 0x97, 0x0f, 0x85, 0xa6, 0xe1, 0xe4, 0xc7,
+    0xab, 0xf5, 0xae, 0x8c, 0xdb, 0x09, 0x33, 0xd7, 0x1e, 0x8c, 0x94, 0xe0, 0x4a, 0x25, 0x61, 0x9d,
+    0xce, 0xe3, 0xd2, 0x26, 0x1a, 0xd2, 0xee, 0x6b, 0xf1, 0x2f, 0xfa, 0x06, 0xd9, 0x8a, 0x08, 0x64,
+    0xd8, 0x76, 0x02, 0x73, 0x3e, 0xc8, 0x6a, 0x64, 0x52, 0x1f, 0x2b, 0x18, 0x17, 0x7b, 0x20, 0x0c,
+    0xbb, 0xe1, 0x17, 0x57, 0x7a, 0x61, 0x5d, 0x6c, 0x77, 0x09, 0x88, 0xc0, 0xba, 0xd9, 0x46, 0xe2,
+    0x08, 0xe2, 0x4f, 0xa0, 0x74, 0xe5, 0xab, 0x31, 0x43, 0xdb, 0x5b, 0xfc, 0xe0, 0xfd, 0x10, 0x8e,
+    0x4b, 0x82, 0xd1, 0x20, 0xa9, 0x3a, 0xd2, 0xca, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+const N_4096: [u8; 512] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xc9, 0x0f, 0xda, 0xa2, 0x21, 0x68, 0xc2, 0x34,
+    0xc4, 0xc6, 0x62, 0x8b, 0x80, 0xdc, 0x1c, 0xd1, 0x29, 0x02, 0x4e, 0x08, 0x8a, 0x67, 0xcc, 0x74,
+    0x02, 0x0b, 0xbe, 0xa6, 0x3b, 0x13, 0x9b, 0x22, 0x51, 0x4a, 0x08, 0x79, 0x8e, 0x34, 0x04, 0xdd,
+    0xef, 0x95, 0x19, 0xb3, 0xcd, 0x3a, 0x43, 0x1b, 0x30, 0x2b, 0x0a, 0x6d, 0xf2, 0x5f, 0x14, 0x37,
+    0x4f, 0xe1, 0x35, 0x6d, 0x6d, 0x51, 0xc2, 0x45, 0xe4, 0x85, 0xb5, 0x76, 0x62, 0x5e, 0x7e, 0xc6,
+    0xf4, 0x4c, 0x42, 0xe9, 0xa6, 0x37, 0xed, 0x6b, 0x0b, 0xff, 0x5c, 0xb6, 0xf4, 0x06, 0xb7, 0xed,
+    0xee, 0x38, 0x6b, 0xfb, 0x5a, 0x89, 0x9f, 0xa5, 0xae, 0x9f, 0x24, 0x11, 0x7c, 0x4b, 0x1f, 0xe6,
+    0x49, 0x28, 0x66, 0x51, 0xec, 0xe4, 0x5b, 0x3d, 0xc2, 0x00, 0x7c, 0xb8, 0xa1, 0x63, 0xbf, 0x05,
+    0x98, 0xda, 0x48, 0x36, 0x1c, 0x55, 0xd3, 0x9a, 0x69, 0x16, 0x3f, 0xa8, 0xfd, 0x24, 0xcf, 0x5f,
+    0x83, 0x65, 0x5d, 0x23, 0xdc, 0xa3, 0xad, 0x96, 0x1c, 0x62, 0xf3, 0x56, 0x20, 0x85, 0x52, 0xbb,
+    0x9e, 0xd5, 0x29, 0x07, 0x70, 0x96, 0x96, 0x6d, 0x67, 0x0c, 0x35, 0x4e, 0x4a, 0xbc, 0x98, 0x04,
+    0xf1, 0x74, 0x6c, 0x08, 0xca, 0x18, 0x21, 0x7c, 0x32, 0x90, 0x5e, 0x46, 0x2e, 0x36, 0xce, 0x3b,
+    0xe3, 0x9e, 0x77, 0x2c, 0x18, 0x0e, 0x86, 0x03, 0x9b, 0x27, 0x83, 0xa2, 0xec, 0x07, 0xa2, 0x8f,
+    0xb5, 0xc5, 0x5d, 0xf0, 0x6f, 0x4c, 0x52, 0xc9, 0xde, 0x2b, 0xcb, 0xf6, 0x95, 0x58, 0x17, 0x18,
+    0x39, 0x95, 0x49, 0x7c, 0xea, 0x95, 0x6a, 0xe5, 0x15, 0xd2, 0x26, 0x18, 0x98, 0xfa, 0x05, 0x10,
+    0x15, 0x72, 0x8e, 0x5a, 0x8a, 0xaa, 0xc4, 0x2d, 0xad, 0x33, 0x17, 0x0d, 0x04, 0x50, 0x7a, 0x33,
+    0xa8, 0x55, 0x21, 0xab, 0xdf, 0x1c, 0xba, 0x64, 0xec, 0xfb, 0x85, 0x04, 0x58, 0xdb, 0xef, 0x0a,
+    0x8a, 0xea, 0x71, 0x57, 0x5d, 0x06, 0x0c, 0x7d, 0xb3, 0x97, 0x0f, 0x85, 0xa6, 0xe1, 0xe4, 0xc7,
+    0xab, 0xf5, 0xae, 0x8c, 0xdb, 0x09, 0x33, 0xd7, 0x1e, 0x8c, 0x94, 0xe0, 0x4a, 0x25, 0x61, 0x9d,
+    0xce, 0xe3, 0xd2, 0x26, 0x1a, 0xd2, 0xee, 0x6b, 0xf1, 0x2f, 0xfa, 0x06, 0xd9, 0x8a, 0x08, 0x64,
+    0xd8, 0x76, 0x02, 0x73, 0x3e, 0xc8, 0x6a, 0x64, 0x52, 0x1f, 0x2b, 0x18, 0x17, 0x7b, 0x20, 0x0c,
+    0xbb, 0xe1, 0x17, 0x57, 0x7a, 0x61, 0x5d, 0x6c, 0x77, 0x09, 0x88, 0xc0, 0xba, 0xd9, 0x46, 0xe2,
+    0x08, 0xe2, 0x4f, 0xa0, 0x74, 0xe5, 0xab, 0x31, 0x43, 0xdb, 0x5b, 0xfc, 0xe0, 0xfd, 0x10, 0x8e,
+    0x4b, 0x82, 0xd1, 0x20, 0xa9, 0x21, 0x08, 0x01, 0x1a, 0x72, 0x3c, 0x12, 0xa7, 0x87, 0xe6, 0xd7,
+    0x88, 0x71, 0x9a, 0x10, 0xbd, 0xba, 0x5b, 0x26, 0x99, 0xc3, 0x27, 0x18, 0x6a, 0xf4, 0xe2, 0x3c,
+    0x1a, 0x94, 0x68, 0x34, 0xb6, 0x15, 0x0b, 0xda, 0x25, 0x83, 0xe9, 0xca, 0x2a, 0xd4, 0x4c, 0xe8,
+    0xdb, 0xbb, 0xc2, 0xdb, 0x04, 0xde, 0x8e, 0xf9, 0x2e, 0x8e, 0xfc, 0x14, 0x1f, 0xbe, 0xca, 0xa6,
+    0x28, 0x7c, 0x59, 0x47, 0x4e, 0x6b, 0xc0, 0x5d, 0x99, 0xb2, 0x96, 0x4f, 0xa0, 0x90, 0xc3, 0xa2,
+    0x23, 0x3b, 0xa1, 0x86, 0x51, 0x5b, 0xe7, 0xed, 0x1f, 0x61, 0x29, 0x70, 0xce, 0xe2, 0xd7, 0xaf,
+    0xb8, 0x1b, 0xdd, 0x76, 0x21, 0x70, 0x48, 0x1c, 0xd0, 0x06, 0x91, 0x27, 0xd5, 0xb0, 0x5a, 0xa9,
+    0x93, 0xb4, 0xea, 0x98, 0x8d, 0x8f, 0xdd, 0xc1, 0x86, 0xff, 0xb7, 0xdc, 0x90, 0xa6, 0xc0, 0x8f,
+    0x4d, 0xf4, 0x35, 0xc9, 0x34, 0x06, 0x31, 0x99, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_srp() {
+        test::from_file("src/srp_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let group = match test_case.consume_string("Group").as_str() {
+                "2048" => &GROUP_2048,
+                "3072" => &GROUP_3072,
+                "4096" => &GROUP_4096,
+                _ => unreachable!(),
+            };
+            let username = test_case.consume_bytes("I");
+            let password = test_case.consume_bytes("P");
+            let salt = test_case.consume_bytes("s");
+            let a = test_case.consume_bytes("a");
+            let b = test_case.consume_bytes("b");
+            let v = test_case.consume_bytes("v");
+            let a_pub = test_case.consume_bytes("A");
+            let b_pub = test_case.consume_bytes("B");
+            let k = test_case.consume_bytes("K");
+            let m1 = test_case.consume_bytes("M1");
+            let m2 = test_case.consume_bytes("M2");
+
+            assert_eq!(compute_verifier(group, &username, &password, &salt).unwrap(), v);
+
+            let client = Client::new(group, &test::rand::FixedSliceRandom { bytes: &a }).unwrap();
+            assert_eq!(client.public_key(), &a_pub[..]);
+            let rng = test::rand::FixedSliceRandom { bytes: &b };
+            let server = Server::new(group, &username, &salt, &v, &rng).unwrap();
+            assert_eq!(server.public_key(), &b_pub[..]);
+
+            let client_proof = client.compute_proof(&username, &password, &salt, &b_pub).unwrap();
+            assert_eq!(client_proof.proof(), &m1[..]);
+            let server_proof = server.verify_client(&a_pub, &m1).unwrap();
+            assert_eq!(server_proof.proof(), &m2[..]);
+            assert_eq!(server_proof.session_key().as_ref(), &k[..]);
+            let session_key = client_proof.verify_server(&m2).unwrap();
+            assert_eq!(session_key.as_ref(), &k[..]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_srp_rejects_invalid_public_keys() {
+        let group = &GROUP_2048;
+        let rng = test::rand::FixedByteRandom { byte: 0x11 };
+        let salt = [0x22; 16];
+        let verifier = compute_verifier(group, b"alice", b"password", &salt).unwrap();
+
+        let zero = vec![0; group.modulus_len()];
+        let mut n_plus_one = group.n.to_vec();
+        *n_plus_one.last_mut().unwrap() += 1;
+        let mut too_long = vec![0];
+        too_long.extend_from_slice(&[0xff; 256]);
+
+        for invalid in &[&zero[..], &[], group.n, &n_plus_one, &too_long] {
+            let client = Client::new(group, &rng).unwrap();
+            assert!(client.compute_proof(b"alice", b"password", &salt, invalid).is_err());
+
+            let server = Server::new(group, b"alice", &salt, &verifier, &rng).unwrap();
+            let client_proof = [0; PROOF_LEN];
+            assert!(server.verify_client(invalid, &client_proof).is_err());
+        }
+    }
+}
@@ -0,0 +1,817 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! SRTP and SRTCP packet protection ([RFC 3711]), with the AES-GCM
+//! transforms of [RFC 7714].
+//!
+//! The protection profiles are the ones that DTLS-SRTP ([RFC 5764])
+//! negotiates for WebRTC. The master key and salt are usually exported from
+//! the DTLS handshake; a `Context` derives the session keys from them with
+//! the key derivation function of RFC 3711 Section 4.3, using a key
+//! derivation rate of zero, and keeps the rollover counters and replay
+//! windows of the streams it has seen, keyed by SSRC.
+//!
+//! A `Context` protects packets in one direction: an endpoint protects what
+//! it sends with its own write key and unprotects what it receives with a
+//! second `Context` made from its peer's write key. Master key identifiers
+//! (MKIs) aren't supported, and SRTCP packets that aren't encrypted are
+//! rejected.
+//!
+//! ```
+//! use ring::srtp;
+//!
+//! # fn protect_and_unprotect() -> Result<(), ring::error::Unspecified> {
+//! let profile = &srtp::AEAD_AES_128_GCM;
+//! let master_key = [0x11; 16];
+//! let master_salt = [0x22; 12];
+//! let mut sender = srtp::Context::new(profile, &master_key, &master_salt)?;
+//! let mut receiver = srtp::Context::new(profile, &master_key, &master_salt)?;
+//!
+//! let rtp_packet = [
+//!     0x80, 0x60, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0xca, 0xfe, 0xba, 0xbe, 0x61, 0x62,
+//! ];
+//! let mut in_out = rtp_packet.to_vec();
+//! in_out.resize(rtp_packet.len() + profile.rtp_overhead(), 0);
+//! sender.protect_rtp(&mut in_out)?;
+//! assert_eq!(receiver.unprotect_rtp(&mut in_out)?, &rtp_packet[..]);
+//!
+//! // A replayed packet is rejected.
+//! sender.protect_rtp(&mut in_out)?;
+//! assert!(receiver.unprotect_rtp(&mut in_out).is_err());
+//! # Ok(())
+//! # }
+//! # fn main() { protect_and_unprotect().unwrap() }
+//! ```
+//!
+//! [RFC 3711]: https://tools.ietf.org/html/rfc3711
+//! [RFC 5764]: https://tools.ietf.org/html/rfc5764
+//! [RFC 7714]: https://tools.ietf.org/html/rfc7714
+
+use crate::{aead, aes_cbc, constant_time, digest, error, hmac, zeroize};
+use alloc::collections::BTreeMap;
+
+/// An SRTP protection profile.
+pub struct Profile {
+    transform: Transform,
+    dtls_srtp_id: u16,
+    master_key_len: usize,
+    master_salt_len: usize,
+    rtp_tag_len: usize,
+    rtcp_tag_len: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Transform {
+    AesCmHmacSha1,
+    AesGcm,
+}
+
+/// `SRTP_AES128_CM_HMAC_SHA1_80`: AES-128 in counter mode with an 80-bit
+/// HMAC-SHA1 tag (RFC 3711).
+pub static AES128_CM_HMAC_SHA1_80: Profile = Profile {
+    transform: Transform::AesCmHmacSha1,
+    dtls_srtp_id: 0x0001,
+    master_key_len: 16,
+    master_salt_len: CM_SALT_LEN,
+    rtp_tag_len: 10,
+    rtcp_tag_len: 10,
+};
+
+/// `SRTP_AES128_CM_HMAC_SHA1_32`: like `AES128_CM_HMAC_SHA1_80`, but with a
+/// 32-bit tag for SRTP. SRTCP still uses an 80-bit tag (RFC 5764 Section
+/// 4.1.2).
+pub static AES128_CM_HMAC_SHA1_32: Profile = Profile {
+    transform: Transform::AesCmHmacSha1,
+    dtls_srtp_id: 0x0002,
+    master_key_len: 16,
+    master_salt_len: CM_SALT_LEN,
+    rtp_tag_len: 4,
+    rtcp_tag_len: 10,
+};
+
+/// `SRTP_AEAD_AES_128_GCM` (RFC 7714).
+pub static AEAD_AES_128_GCM: Profile = Profile {
+    transform: Transform::AesGcm,
+    dtls_srtp_id: 0x0007,
+    master_key_len: 16,
+    master_salt_len: GCM_SALT_LEN,
+    rtp_tag_len: GCM_TAG_LEN,
+    rtcp_tag_len: GCM_TAG_LEN,
+};
+
+/// `SRTP_AEAD_AES_256_GCM` (RFC 7714).
+pub static AEAD_AES_256_GCM: Profile = Profile {
+    transform: Transform::AesGcm,
+    dtls_srtp_id: 0x0008,
+    master_key_len: 32,
+    master_salt_len: GCM_SALT_LEN,
+    rtp_tag_len: GCM_TAG_LEN,
+    rtcp_tag_len: GCM_TAG_LEN,
+};
+
+impl Profile {
+    /// The profile's identifier in the DTLS `use_srtp` extension.
+    #[inline]
+    pub fn dtls_srtp_id(&self) -> u16 { self.dtls_srtp_id }
+
+    /// The length of the master key, in bytes.
+    #[inline]
+    pub fn master_key_len(&self) -> usize { self.master_key_len }
+
+    /// The length of the master salt, in bytes.
+    #[inline]
+    pub fn master_salt_len(&self) -> usize { self.master_salt_len }
+
+    /// The number of bytes that protecting an RTP packet adds to it.
+    #[inline]
+    pub fn rtp_overhead(&self) -> usize { self.rtp_tag_len }
+
+    /// The number of bytes that protecting an RTCP packet adds to it: the
+    /// SRTCP index and the tag.
+    #[inline]
+    pub fn rtcp_overhead(&self) -> usize { SRTCP_INDEX_LEN + self.rtcp_tag_len }
+}
+
+impl PartialEq for Profile {
+    fn eq(&self, other: &Self) -> bool { self.dtls_srtp_id == other.dtls_srtp_id }
+}
+
+impl Eq for Profile {}
+
+/// The session keys and salts that are derived from a master key.
+#[derive(Clone, Copy)]
+pub enum Label {
+    /// The SRTP encryption key.
+    RtpEncryption = 0,
+
+    /// The SRTP authentication key.
+    RtpAuthentication = 1,
+
+    /// The SRTP salt.
+    RtpSalt = 2,
+
+    /// The SRTCP encryption key.
+    RtcpEncryption = 3,
+
+    /// The SRTCP authentication key.
+    RtcpAuthentication = 4,
+
+    /// The SRTCP salt.
+    RtcpSalt = 5,
+}
+
+/// Derives the session key or salt `label` from `master_key`, which must be
+/// 16 or 32 bytes, and `master_salt`, which must be at most 14 bytes, filling
+/// `out`.
+///
+/// This is the key derivation function of RFC 3711 Section 4.3 with a key
+/// derivation rate of zero. The 12-byte master salts of the AES-GCM profiles
+/// are padded with zeros, as RFC 7714 Section 11 specifies.
+pub fn derive_session_key(
+    master_key: &[u8], master_salt: &[u8], label: Label, out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    let master_key = aes_cbc::Key::new(master_key)?;
+    kdf(&master_key, master_salt, label, out)
+}
+
+/// The SRTP and SRTCP state of one direction of a session.
+pub struct Context {
+    profile: &'static Profile,
+    rtp_keys: SessionKeys,
+    rtcp_keys: SessionKeys,
+    rtp_streams: BTreeMap<u32, Stream>,
+    rtcp_streams: BTreeMap<u32, Stream>,
+}
+
+impl Context {
+    /// Derives the session keys for `profile` from `master_key` and
+    /// `master_salt`, with no streams.
+    pub fn new(
+        profile: &'static Profile, master_key: &[u8], master_salt: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        if master_key.len() != profile.master_key_len
+            || master_salt.len() != profile.master_salt_len
+        {
+            return Err(error::Unspecified);
+        }
+        let master_key = aes_cbc::Key::new(master_key)?;
+        let rtp_keys = SessionKeys::new(
+            profile,
+            &master_key,
+            master_salt,
+            [Label::RtpEncryption, Label::RtpAuthentication, Label::RtpSalt],
+        )?;
+        let rtcp_keys = SessionKeys::new(
+            profile,
+            &master_key,
+            master_salt,
+            [Label::RtcpEncryption, Label::RtcpAuthentication, Label::RtcpSalt],
+        )?;
+        Ok(Self {
+            profile,
+            rtp_keys,
+            rtcp_keys,
+            rtp_streams: BTreeMap::new(),
+            rtcp_streams: BTreeMap::new(),
+        })
+    }
+
+    /// The context's protection profile.
+    #[inline]
+    pub fn profile(&self) -> &'static Profile { self.profile }
+
+    /// The rollover counter of the RTP stream `ssrc`, i.e. the number of
+    /// times its sequence number has wrapped around, or `None` if no packet
+    /// of the stream has been protected or unprotected.
+    pub fn rtp_rollover_counter(&self, ssrc: u32) -> Option<u32> {
+        self.rtp_streams.get(&ssrc).map(|stream| (stream.index >> 16) as u32)
+    }
+
+    /// Protects an RTP packet.
+    ///
+    /// The packet is `in_out[..(in_out.len() - profile.rtp_overhead())]`;
+    /// its payload is encrypted in place and the tag is written over the
+    /// last `profile.rtp_overhead()` bytes.
+    ///
+    /// The packet index is the lowest one, given the packets of the stream
+    /// protected so far, that is consistent with the sequence number. It
+    /// fails once the index would exceed 2**48 - 1; the session must be
+    /// rekeyed before then.
+    pub fn protect_rtp(&mut self, in_out: &mut [u8]) -> Result<(), error::Unspecified> {
+        let packet_len = in_out
+            .len()
+            .checked_sub(self.profile.rtp_tag_len)
+            .ok_or(error::Unspecified)?;
+        let header_len = rtp_header_len(&in_out[..packet_len])?;
+        let (ssrc, seq) = rtp_ssrc_and_seq(in_out);
+        let index = match self.rtp_streams.get(&ssrc) {
+            Some(stream) => stream.estimate_rtp_index(seq)?,
+            None => u64::from(seq),
+        };
+
+        match &self.rtp_keys {
+            SessionKeys::AesCmHmacSha1 {
+                key,
+                auth_key,
+                salt,
+            } => {
+                let roc = ((index >> 16) as u32).to_be_bytes();
+                aes_cm_xor(
+                    key,
+                    &aes_cm_iv(salt, ssrc, index),
+                    &mut in_out[header_len..packet_len],
+                )?;
+                let (authenticated, tag) = in_out.split_at_mut(packet_len);
+                let signature = hmac_sha1(auth_key, &[authenticated, &roc]);
+                tag.copy_from_slice(&signature.as_ref()[..self.profile.rtp_tag_len]);
+            },
+            SessionKeys::AesGcm {
+                sealing_key, salt, ..
+            } => {
+                let nonce = gcm_nonce(salt, ssrc, (index >> 16) as u32, index as u16);
+                let (header, payload_and_tag) = in_out.split_at_mut(header_len);
+                let _ =
+                    aead::seal_in_place(sealing_key, &nonce, header, payload_and_tag, GCM_TAG_LEN)?;
+            },
+        }
+
+        update_stream(&mut self.rtp_streams, ssrc, index);
+        Ok(())
+    }
+
+    /// Unprotects an SRTP packet, returning the RTP packet.
+    ///
+    /// The packet is rejected if it isn't authentic or if a packet with the
+    /// same index has already been unprotected. Packets more than 64 behind
+    /// the newest one of their stream are rejected too, since whether they
+    /// are replays can't be told. The state is only changed by authentic
+    /// packets.
+    pub fn unprotect_rtp<'a>(
+        &mut self, in_out: &'a mut [u8],
+    ) -> Result<&'a mut [u8], error::Unspecified> {
+        let packet_len = in_out
+            .len()
+            .checked_sub(self.profile.rtp_tag_len)
+            .ok_or(error::Unspecified)?;
+        let header_len = rtp_header_len(&in_out[..packet_len])?;
+        let (ssrc, seq) = rtp_ssrc_and_seq(in_out);
+        let index = match self.rtp_streams.get(&ssrc) {
+            Some(stream) => {
+                let index = stream.estimate_rtp_index(seq)?;
+                stream.check_replay(index)?;
+                index
+            },
+            None => u64::from(seq),
+        };
+
+        match &self.rtp_keys {
+            SessionKeys::AesCmHmacSha1 {
+                key,
+                auth_key,
+                salt,
+            } => {
+                let roc = ((index >> 16) as u32).to_be_bytes();
+                let (authenticated, tag) = in_out.split_at_mut(packet_len);
+                let signature = hmac_sha1(auth_key, &[authenticated, &roc]);
+                constant_time::verify_slices_are_equal(
+                    &signature.as_ref()[..self.profile.rtp_tag_len],
+                    tag,
+                )?;
+                aes_cm_xor(
+                    key,
+                    &aes_cm_iv(salt, ssrc, index),
+                    &mut in_out[header_len..packet_len],
+                )?;
+            },
+            SessionKeys::AesGcm {
+                opening_key, salt, ..
+            } => {
+                let nonce = gcm_nonce(salt, ssrc, (index >> 16) as u32, index as u16);
+                let (header, payload_and_tag) = in_out.split_at_mut(header_len);
+                let _ = aead::open_in_place(opening_key, &nonce, header, 0, payload_and_tag)?;
+            },
+        }
+
+        update_stream(&mut self.rtp_streams, ssrc, index);
+        Ok(&mut in_out[..packet_len])
+    }
+
+    /// Protects an RTCP packet or compound packet.
+    ///
+    /// The packet is `in_out[..(in_out.len() - profile.rtcp_overhead())]`;
+    /// everything after the first eight bytes is encrypted in place and the
+    /// SRTCP index and tag are written over the last
+    /// `profile.rtcp_overhead()` bytes. The SRTCP index of each stream counts
+    /// up from zero; it fails once the index would exceed 2**31 - 1.
+    pub fn protect_rtcp(&mut self, in_out: &mut [u8]) -> Result<(), error::Unspecified> {
+        let packet_len = in_out
+            .len()
+            .checked_sub(self.profile.rtcp_overhead())
+            .ok_or(error::Unspecified)?;
+        let ssrc = rtcp_ssrc(&in_out[..packet_len])?;
+        let index = match self.rtcp_streams.get(&ssrc) {
+            Some(stream) => stream.index + 1,
+            None => 0,
+        };
+        if index > MAX_SRTCP_INDEX {
+            return Err(error::Unspecified);
+        }
+        let e_and_index = (ENCRYPTED_FLAG | index as u32).to_be_bytes();
+
+        match &self.rtcp_keys {
+            SessionKeys::AesCmHmacSha1 {
+                key,
+                auth_key,
+                salt,
+            } => {
+                aes_cm_xor(
+                    key,
+                    &aes_cm_iv(salt, ssrc, index),
+                    &mut in_out[RTCP_HEADER_LEN..packet_len],
+                )?;
+                let (authenticated, tag) = in_out.split_at_mut(packet_len + SRTCP_INDEX_LEN);
+                authenticated[packet_len..].copy_from_slice(&e_and_index);
+                let signature = hmac_sha1(auth_key, &[authenticated]);
+                tag.copy_from_slice(&signature.as_ref()[..self.profile.rtcp_tag_len]);
+            },
+            SessionKeys::AesGcm {
+                sealing_key, salt, ..
+            } => {
+                let nonce = gcm_nonce(salt, ssrc, 0, 0);
+                let nonce = xor_srtcp_index(nonce, index as u32);
+                let (sealed, trailer) = in_out.split_at_mut(packet_len + GCM_TAG_LEN);
+                trailer.copy_from_slice(&e_and_index);
+                let (header, payload_and_tag) = sealed.split_at_mut(RTCP_HEADER_LEN);
+                let ad = rtcp_gcm_ad(header, &e_and_index);
+                let _ =
+                    aead::seal_in_place(sealing_key, &nonce, &ad, payload_and_tag, GCM_TAG_LEN)?;
+            },
+        }
+
+        update_stream(&mut self.rtcp_streams, ssrc, index);
+        Ok(())
+    }
+
+    /// Unprotects an SRTCP packet, returning the RTCP packet.
+    ///
+    /// Replays are rejected like they are by `unprotect_rtp()`.
+    pub fn unprotect_rtcp<'a>(
+        &mut self, in_out: &'a mut [u8],
+    ) -> Result<&'a mut [u8], error::Unspecified> {
+        let packet_len = in_out
+            .len()
+            .checked_sub(self.profile.rtcp_overhead())
+            .ok_or(error::Unspecified)?;
+        let ssrc = rtcp_ssrc(&in_out[..packet_len])?;
+        let trailer_start = match self.profile.transform {
+            Transform::AesCmHmacSha1 => packet_len,
+            Transform::AesGcm => packet_len + GCM_TAG_LEN,
+        };
+        let mut e_and_index = [0; SRTCP_INDEX_LEN];
+        e_and_index.copy_from_slice(&in_out[trailer_start..][..SRTCP_INDEX_LEN]);
+        let e_and_index_value = u32::from_be_bytes(e_and_index);
+        if e_and_index_value & ENCRYPTED_FLAG == 0 {
+            return Err(error::Unspecified);
+        }
+        let index = u64::from(e_and_index_value & !ENCRYPTED_FLAG);
+        if let Some(stream) = self.rtcp_streams.get(&ssrc) {
+            stream.check_replay(index)?;
+        }
+
+        match &self.rtcp_keys {
+            SessionKeys::AesCmHmacSha1 {
+                key,
+                auth_key,
+                salt,
+            } => {
+                let (authenticated, tag) = in_out.split_at_mut(packet_len + SRTCP_INDEX_LEN);
+                let signature = hmac_sha1(auth_key, &[authenticated]);
+                constant_time::verify_slices_are_equal(
+                    &signature.as_ref()[..self.profile.rtcp_tag_len],
+                    tag,
+                )?;
+                aes_cm_xor(
+                    key,
+                    &aes_cm_iv(salt, ssrc, index),
+                    &mut in_out[RTCP_HEADER_LEN..packet_len],
+                )?;
+            },
+            SessionKeys::AesGcm {
+                opening_key, salt, ..
+            } => {
+                let nonce = gcm_nonce(salt, ssrc, 0, 0);
+                let nonce = xor_srtcp_index(nonce, index as u32);
+                let (header, payload_and_tag) =
+                    in_out[..trailer_start].split_at_mut(RTCP_HEADER_LEN);
+                let ad = rtcp_gcm_ad(header, &e_and_index);
+                let _ = aead::open_in_place(opening_key, &nonce, &ad, 0, payload_and_tag)?;
+            },
+        }
+
+        update_stream(&mut self.rtcp_streams, ssrc, index);
+        Ok(&mut in_out[..packet_len])
+    }
+}
+
+// The session keys of SRTP or of SRTCP.
+#[allow(variant_size_differences)]
+enum SessionKeys {
+    AesCmHmacSha1 {
+        key: aes_cbc::Key,
+        auth_key: hmac::SigningKey,
+        salt: [u8; CM_SALT_LEN],
+    },
+    AesGcm {
+        sealing_key: aead::SealingKey,
+        opening_key: aead::OpeningKey,
+        salt: [u8; GCM_SALT_LEN],
+    },
+}
+
+impl SessionKeys {
+    fn new(
+        profile: &Profile, master_key: &aes_cbc::Key, master_salt: &[u8], labels: [Label; 3],
+    ) -> Result<Self, error::Unspecified> {
+        let [encryption, authentication, salt_label] = labels;
+        let mut key = [0; MAX_SESSION_KEY_LEN];
+        let key = &mut key[..profile.master_key_len];
+        kdf(master_key, master_salt, encryption, key)?;
+        let keys = match profile.transform {
+            Transform::AesCmHmacSha1 => {
+                let mut auth_key = [0; AUTH_KEY_LEN];
+                kdf(master_key, master_salt, authentication, &mut auth_key)?;
+                let mut salt = [0; CM_SALT_LEN];
+                kdf(master_key, master_salt, salt_label, &mut salt)?;
+                let keys = SessionKeys::AesCmHmacSha1 {
+                    key: aes_cbc::Key::new(key)?,
+                    auth_key: hmac::SigningKey::new(&digest::SHA1, &auth_key),
+                    salt,
+                };
+                zeroize::zeroize(&mut auth_key);
+                keys
+            },
+            Transform::AesGcm => {
+                let algorithm = match key.len() {
+                    16 => &aead::AES_128_GCM,
+                    _ => &aead::AES_256_GCM,
+                };
+                let mut salt = [0; GCM_SALT_LEN];
+                kdf(master_key, master_salt, salt_label, &mut salt)?;
+                SessionKeys::AesGcm {
+                    sealing_key: aead::SealingKey::new(algorithm, key)?,
+                    opening_key: aead::OpeningKey::new(algorithm, key)?,
+                    salt,
+                }
+            },
+        };
+        zeroize::zeroize(key);
+        Ok(keys)
+    }
+}
+
+// The newest packet index of a stream, and which of the 64 packets up to and
+// including it have been seen: bit `i` of `window` is set if packet
+// `index - i` has.
+struct Stream {
+    index: u64,
+    window: u64,
+}
+
+impl Stream {
+    // RFC 3711 Section 3.3.1.
+    fn estimate_rtp_index(&self, seq: u16) -> Result<u64, error::Unspecified> {
+        let roc = self.index >> 16;
+        let s_l = u32::from(self.index as u16);
+        let seq_value = u32::from(seq);
+        let v = if s_l < 0x8000 {
+            if seq_value > s_l + 0x8000 {
+                roc.checked_sub(1).ok_or(error::Unspecified)?
+            } else {
+                roc
+            }
+        } else if s_l - 0x8000 > seq_value {
+            roc + 1
+        } else {
+            roc
+        };
+        if v > u64::from(u32::max_value()) {
+            return Err(error::Unspecified);
+        }
+        Ok((v << 16) | u64::from(seq))
+    }
+
+    // RFC 3711 Section 3.3.2.
+    fn check_replay(&self, index: u64) -> Result<(), error::Unspecified> {
+        if index > self.index {
+            return Ok(());
+        }
+        let age = self.index - index;
+        if age >= REPLAY_WINDOW_LEN || (self.window >> age) & 1 == 1 {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+}
+
+fn update_stream(streams: &mut BTreeMap<u32, Stream>, ssrc: u32, index: u64) {
+    let stream = streams.entry(ssrc).or_insert(Stream { index, window: 0 });
+    if index > stream.index {
+        let shift = index - stream.index;
+        stream.window = if shift >= REPLAY_WINDOW_LEN { 0 } else { stream.window << shift };
+        stream.index = index;
+    }
+    stream.window |= 1 << (stream.index - index);
+}
+
+// RFC 3711 Section 4.3.1, with `r` zero.
+fn kdf(
+    master_key: &aes_cbc::Key, master_salt: &[u8], label: Label, out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    if master_salt.len() > CM_SALT_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut iv = [0; aes_cbc::BLOCK_LEN];
+    iv[..master_salt.len()].copy_from_slice(master_salt);
+    iv[CM_SALT_LEN - 7] ^= label as u8;
+    for b in out.iter_mut() {
+        *b = 0;
+    }
+    aes_cm_xor(master_key, &iv, out)
+}
+
+// RFC 3711 Section 4.1.1: `(salt * 2**16) ^ (ssrc * 2**64) ^ (index * 2**16)`.
+fn aes_cm_iv(salt: &[u8; CM_SALT_LEN], ssrc: u32, index: u64) -> [u8; aes_cbc::BLOCK_LEN] {
+    let mut iv = [0; aes_cbc::BLOCK_LEN];
+    iv[4..8].copy_from_slice(&ssrc.to_be_bytes());
+    iv[8..14].copy_from_slice(&index.to_be_bytes()[2..]);
+    for (iv, salt) in iv.iter_mut().zip(salt.iter()) {
+        *iv ^= *salt;
+    }
+    iv
+}
+
+// Encrypts or decrypts `in_out` with the keystream that starts at `iv`,
+// whose last 16 bits are the block counter.
+fn aes_cm_xor(
+    key: &aes_cbc::Key, iv: &[u8; aes_cbc::BLOCK_LEN], in_out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    if in_out.len() > MAX_AES_CM_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut counter = *iv;
+    for chunk in in_out.chunks_mut(aes_cbc::BLOCK_LEN) {
+        let mut keystream = counter;
+        key.encrypt_block(&mut keystream);
+        for (b, k) in chunk.iter_mut().zip(keystream.iter()) {
+            *b ^= *k;
+        }
+        let block_counter = u16::from_be_bytes([counter[14], counter[15]]).wrapping_add(1);
+        counter[14..].copy_from_slice(&block_counter.to_be_bytes());
+    }
+    Ok(())
+}
+
+fn hmac_sha1(key: &hmac::SigningKey, parts: &[&[u8]]) -> hmac::Signature {
+    let mut ctx = hmac::SigningContext::with_key(key);
+    for part in parts {
+        ctx.update(part);
+    }
+    ctx.sign()
+}
+
+// RFC 7714 Sections 8.1 and 9.1: `0x0000 || SSRC || ROC || SEQ`, XORed with
+// the salt. For SRTCP, `roc` and `seq` are zero and the SRTCP index is XORed
+// in afterwards.
+fn gcm_nonce(salt: &[u8; GCM_SALT_LEN], ssrc: u32, roc: u32, seq: u16) -> [u8; GCM_SALT_LEN] {
+    let mut nonce = [0; GCM_SALT_LEN];
+    nonce[2..6].copy_from_slice(&ssrc.to_be_bytes());
+    nonce[6..10].copy_from_slice(&roc.to_be_bytes());
+    nonce[10..].copy_from_slice(&seq.to_be_bytes());
+    for (n, salt) in nonce.iter_mut().zip(salt.iter()) {
+        *n ^= *salt;
+    }
+    nonce
+}
+
+fn xor_srtcp_index(mut nonce: [u8; GCM_SALT_LEN], index: u32) -> [u8; GCM_SALT_LEN] {
+    for (n, i) in nonce[8..].iter_mut().zip(index.to_be_bytes().iter()) {
+        *n ^= *i;
+    }
+    nonce
+}
+
+// RFC 7714 Section 9.2: the first eight bytes of the header, followed by the
+// E flag and SRTCP index.
+fn rtcp_gcm_ad(
+    header: &[u8], e_and_index: &[u8; SRTCP_INDEX_LEN],
+) -> [u8; RTCP_HEADER_LEN + SRTCP_INDEX_LEN] {
+    let mut ad = [0; RTCP_HEADER_LEN + SRTCP_INDEX_LEN];
+    ad[..RTCP_HEADER_LEN].copy_from_slice(header);
+    ad[RTCP_HEADER_LEN..].copy_from_slice(e_and_index);
+    ad
+}
+
+// The length of the fixed header, the CSRCs, and the header extension.
+fn rtp_header_len(packet: &[u8]) -> Result<usize, error::Unspecified> {
+    if packet.len() < RTP_HEADER_LEN || packet[0] >> 6 != RTP_VERSION {
+        return Err(error::Unspecified);
+    }
+    let mut len = RTP_HEADER_LEN + 4 * usize::from(packet[0] & 0x0f);
+    if packet[0] & 0x10 != 0 {
+        let extension_len = packet.get((len + 2)..(len + 4)).ok_or(error::Unspecified)?;
+        len += 4 + 4 * usize::from(u16::from_be_bytes([extension_len[0], extension_len[1]]));
+    }
+    if len > packet.len() {
+        return Err(error::Unspecified);
+    }
+    Ok(len)
+}
+
+fn rtp_ssrc_and_seq(packet: &[u8]) -> (u32, u16) {
+    let ssrc = u32::from_be_bytes([packet[8], packet[9], packet[10], packet[11]]);
+    let seq = u16::from_be_bytes([packet[2], packet[3]]);
+    (ssrc, seq)
+}
+
+fn rtcp_ssrc(packet: &[u8]) -> Result<u32, error::Unspecified> {
+    if packet.len() < RTCP_HEADER_LEN || packet[0] >> 6 != RTP_VERSION {
+        return Err(error::Unspecified);
+    }
+    Ok(u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]))
+}
+
+const RTP_VERSION: u8 = 2;
+
+const RTP_HEADER_LEN: usize = 12;
+
+const RTCP_HEADER_LEN: usize = 8;
+
+const SRTCP_INDEX_LEN: usize = 4;
+
+const ENCRYPTED_FLAG: u32 = 0x8000_0000;
+
+const MAX_SRTCP_INDEX: u64 = 0x7fff_ffff;
+
+const REPLAY_WINDOW_LEN: u64 = 64;
+
+const AUTH_KEY_LEN: usize = digest::SHA1_OUTPUT_LEN;
+
+const CM_SALT_LEN: usize = 14;
+
+const GCM_SALT_LEN: usize = 12;
+
+const GCM_TAG_LEN: usize = 16;
+
+const MAX_SESSION_KEY_LEN: usize = 32;
+
+// The 16-bit block counter limits the keystream of one packet.
+const MAX_AES_CM_LEN: usize = 0x1_0000 * aes_cbc::BLOCK_LEN;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    // RFC 3711 Appendix B.3.
+    #[test]
+    fn test_kdf() {
+        let master_key = test::from_hex("e1f97a0d3e018be0d64fa32c06de4139").unwrap();
+        let master_salt = test::from_hex("0ec675ad498afeebb6960b3aabe6").unwrap();
+        for &(label, expected) in &[
+            (Label::RtpEncryption, "c61e7a93744f39ee10734afe3ff7a087"),
+            (Label::RtpSalt, "30cbbc08863d8c85d49db34a9ae1"),
+            (Label::RtpAuthentication, "cebe321f6ff7716b6fd4ab49af256a156d38baa4"),
+        ] {
+            let expected = test::from_hex(expected).unwrap();
+            let mut out = vec![0; expected.len()];
+            derive_session_key(&master_key, &master_salt, label, &mut out).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn test_srtp() {
+        test::from_file("src/srtp_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let profile = match test_case.consume_string("Profile").as_str() {
+                "AES128_CM_HMAC_SHA1_80" => &AES128_CM_HMAC_SHA1_80,
+                "AES128_CM_HMAC_SHA1_32" => &AES128_CM_HMAC_SHA1_32,
+                "AEAD_AES_128_GCM" => &AEAD_AES_128_GCM,
+                "AEAD_AES_256_GCM" => &AEAD_AES_256_GCM,
+                _ => unreachable!(),
+            };
+            let master_key = test_case.consume_bytes("MasterKey");
+            let master_salt = test_case.consume_bytes("MasterSalt");
+            let is_rtp = test_case.consume_string("Type") == "RTP";
+            let index = test_case.consume_usize("Index") as u64;
+            let plaintext = test_case.consume_bytes("Plaintext");
+            let ciphertext = test_case.consume_bytes("Ciphertext");
+
+            // Put each context in the state in which the packet has `index`.
+            let new_context = || {
+                let mut context = Context::new(profile, &master_key, &master_salt).unwrap();
+                if index > 0 {
+                    let ssrc = if is_rtp {
+                        rtp_ssrc_and_seq(&plaintext).0
+                    } else {
+                        rtcp_ssrc(&plaintext).unwrap()
+                    };
+                    let streams =
+                        if is_rtp { &mut context.rtp_streams } else { &mut context.rtcp_streams };
+                    update_stream(streams, ssrc, index - 1);
+                }
+                context
+            };
+
+            let mut sender = new_context();
+            let mut in_out = plaintext.clone();
+            if is_rtp {
+                in_out.resize(plaintext.len() + profile.rtp_overhead(), 0);
+                sender.protect_rtp(&mut in_out).unwrap();
+            } else {
+                in_out.resize(plaintext.len() + profile.rtcp_overhead(), 0);
+                sender.protect_rtcp(&mut in_out).unwrap();
+            }
+            assert_eq!(in_out, ciphertext);
+
+            let mut receiver = new_context();
+            let unprotected = if is_rtp {
+                receiver.unprotect_rtp(&mut in_out).unwrap()
+            } else {
+                receiver.unprotect_rtcp(&mut in_out).unwrap()
+            };
+            assert_eq!(unprotected, &plaintext[..]);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_estimate_rtp_index() {
+        for &(highest, seq, expected) in &[
+            (0x0_0010, 0x0011, Some(0x0_0011)),
+            (0x0_0010, 0x0005, Some(0x0_0005)),
+            (0x0_0010, 0xfff0, None),
+            (0x1_0010, 0xfff0, Some(0x0_fff0)),
+            (0x0_fff0, 0x0002, Some(0x1_0002)),
+            (0x0_fff0, 0x9000, Some(0x0_9000)),
+            (0xffff_ffff_fff0, 0x0002, None),
+        ] {
+            let stream = Stream {
+                index: highest,
+                window: 1,
+            };
+            assert_eq!(stream.estimate_rtp_index(seq).ok(), expected);
+        }
+    }
+}
@@ -0,0 +1,587 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! OpenSSH public and private keys.
+//!
+//! Public keys are in the one-line format of `id_*.pub` and `authorized_keys`
+//! files: the algorithm name, the Base64 encoding of the public key in the SSH
+//! wire format ([RFC 4253 Section 6.6], [RFC 5656 Section 3.1], and
+//! [RFC 8709]), and an optional comment. `authorized_keys` options are not
+//! supported.
+//!
+//! Private keys are in the [`openssh-key-v1`] format that `ssh-keygen`
+//! generates, PEM-encoded with the label `OPENSSH PRIVATE KEY`. Only
+//! unencrypted keys are supported; keys encrypted with a passphrase are
+//! rejected with `KeyRejected::WrongAlgorithm`. Private keys are converted to
+//! and from PKCS#8 so that they can be used with `Ed25519KeyPair` and
+//! `ECDSAKeyPair`.
+//!
+//! The `kex` module implements the `curve25519-sha256` key exchange.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{rand, signature, ssh};
+//!
+//! # fn ssh() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let pkcs8_bytes = signature::Ed25519KeyPair::generate_pkcs8(&rng)?;
+//! let private_key = ssh::PrivateKey::from_pkcs8(
+//!     &ssh::ED25519,
+//!     untrusted::Input::from(&pkcs8_bytes),
+//!     "user@example.com",
+//! )?;
+//! let id_ed25519 = private_key.to_pem(&rng)?;
+//! let id_ed25519_pub = private_key.public_key().to_line();
+//!
+//! let private_key = ssh::PrivateKey::parse(&id_ed25519)?;
+//! let key_pair = signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(
+//!     private_key.to_pkcs8().as_ref(),
+//! ))?;
+//! let public_key = ssh::PublicKey::parse(&id_ed25519_pub)?;
+//! assert_eq!(public_key.key_bytes(), key_pair.public_key_bytes());
+//! assert_eq!(public_key.comment(), "user@example.com");
+//! # Ok(())
+//! # }
+//! # fn main() { ssh().unwrap() }
+//! ```
+//!
+//! [RFC 4253 Section 6.6]: https://tools.ietf.org/html/rfc4253#section-6.6
+//! [RFC 5656 Section 3.1]: https://tools.ietf.org/html/rfc5656#section-3.1
+//! [RFC 8709]: https://tools.ietf.org/html/rfc8709
+//! [`openssh-key-v1`]: https://cvsweb.openbsd.org/src/usr.bin/ssh/PROTOCOL.key
+
+use crate::{
+    ec::{self, curve25519::ed25519, suite_b},
+    error,
+    io::{self, base64},
+    pem, pkcs8, rand, signature,
+};
+use alloc::{string::String, vec::Vec};
+use untrusted;
+
+pub mod kex;
+
+/// The PEM label of an `openssh-key-v1` private key.
+pub const PRIVATE_KEY_PEM_LABEL: &str = "OPENSSH PRIVATE KEY";
+
+const AUTH_MAGIC: &[u8] = b"openssh-key-v1\0";
+const NONE: &[u8] = b"none";
+
+// The "block size" of the "none" cipher, to which the private section is
+// padded.
+const NONE_BLOCK_LEN: usize = 8;
+
+/// An SSH public key algorithm.
+pub struct Algorithm {
+    name: &'static str,
+    key_type: KeyType,
+    id: AlgorithmID,
+}
+
+enum KeyType {
+    Ed25519,
+    Ecdsa {
+        curve_name: &'static str,
+        curve: &'static ec::Curve,
+        pkcs8_template: &'static pkcs8::Template,
+    },
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum AlgorithmID {
+    ED25519,
+    ECDSA_SHA2_NISTP256,
+    ECDSA_SHA2_NISTP384,
+}
+
+derive_debug_via_self!(Algorithm, self.id);
+
+impl PartialEq for Algorithm {
+    fn eq(&self, other: &Self) -> bool { self.id == other.id }
+}
+
+impl Eq for Algorithm {}
+
+impl Algorithm {
+    /// The name of the algorithm, e.g. `ssh-ed25519`.
+    pub fn name(&self) -> &'static str { self.name }
+
+    fn public_key_len(&self) -> usize {
+        match self.key_type {
+            KeyType::Ed25519 => signature::ED25519_PUBLIC_KEY_LEN,
+            KeyType::Ecdsa { curve, .. } => curve.public_key_len,
+        }
+    }
+}
+
+/// `ssh-ed25519`, Ed25519.
+pub static ED25519: Algorithm = Algorithm {
+    name: "ssh-ed25519",
+    key_type: KeyType::Ed25519,
+    id: AlgorithmID::ED25519,
+};
+
+/// `ecdsa-sha2-nistp256`, ECDSA with P-256 and SHA-256.
+pub static ECDSA_SHA2_NISTP256: Algorithm = Algorithm {
+    name: "ecdsa-sha2-nistp256",
+    key_type: KeyType::Ecdsa {
+        curve_name: "nistp256",
+        curve: &suite_b::curve::P256,
+        pkcs8_template: &suite_b::ecdsa::signing::EC_PUBLIC_KEY_P256_PKCS8_V1_TEMPLATE,
+    },
+    id: AlgorithmID::ECDSA_SHA2_NISTP256,
+};
+
+/// `ecdsa-sha2-nistp384`, ECDSA with P-384 and SHA-384.
+pub static ECDSA_SHA2_NISTP384: Algorithm = Algorithm {
+    name: "ecdsa-sha2-nistp384",
+    key_type: KeyType::Ecdsa {
+        curve_name: "nistp384",
+        curve: &suite_b::curve::P384,
+        pkcs8_template: &suite_b::ecdsa::signing::EC_PUBLIC_KEY_P384_PKCS8_V1_TEMPLATE,
+    },
+    id: AlgorithmID::ECDSA_SHA2_NISTP384,
+};
+
+static ALGORITHMS: [&Algorithm; 3] = [&ED25519, &ECDSA_SHA2_NISTP256, &ECDSA_SHA2_NISTP384];
+
+fn algorithm_from_name(name: &[u8]) -> Option<&'static Algorithm> {
+    ALGORITHMS
+        .iter()
+        .cloned()
+        .find(|alg| alg.name.as_bytes() == name)
+}
+
+/// An SSH public key with its comment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PublicKey {
+    algorithm: &'static Algorithm,
+    key: Vec<u8>,
+    comment: String,
+}
+
+impl PublicKey {
+    /// Constructs a public key from its raw encoding: the 32-byte public key
+    /// for Ed25519, or the uncompressed point for ECDSA, as returned by
+    /// `Ed25519KeyPair::public_key_bytes()` and
+    /// `ECDSAKeyPair::public_key().as_ref()`.
+    ///
+    /// The length and form of `key` is checked, but an ECDSA key isn't
+    /// verified to be on the curve; that is done when a signature is verified.
+    /// Fails if `comment` contains a line break.
+    pub fn new(
+        algorithm: &'static Algorithm, key: &[u8], comment: &str,
+    ) -> Result<Self, error::Unspecified> {
+        if key.len() != algorithm.public_key_len() || comment.contains(|c| c == '\n' || c == '\r')
+        {
+            return Err(error::Unspecified);
+        }
+        if let KeyType::Ecdsa { .. } = algorithm.key_type {
+            // Uncompressed form.
+            if key[0] != 4 {
+                return Err(error::Unspecified);
+            }
+        }
+        Ok(Self {
+            algorithm,
+            key: key.to_vec(),
+            comment: String::from(comment),
+        })
+    }
+
+    /// Parses a public key line, e.g. the contents of an `id_ed25519.pub` file.
+    ///
+    /// The comment is everything after the Base64 text, with surrounding
+    /// whitespace removed. The algorithm name at the start of the line must
+    /// match the one encoded in the key.
+    pub fn parse(line: &str) -> Result<Self, error::Unspecified> {
+        let line = line.trim();
+        let mut fields = line.splitn(3, |c| c == ' ' || c == '\t');
+        let name = fields.next().ok_or(error::Unspecified)?;
+        let encoded = fields.next().ok_or(error::Unspecified)?;
+        let comment = fields.next().unwrap_or("").trim();
+        let blob = base64::decode(encoded, &base64::STANDARD)?;
+        let r = Self::from_blob(&blob, comment)?;
+        if r.algorithm.name != name {
+            return Err(error::Unspecified);
+        }
+        Ok(r)
+    }
+
+    /// Parses a public key in the SSH wire format, as used in the SSH
+    /// protocol and by SSH agents.
+    pub fn from_blob(blob: &[u8], comment: &str) -> Result<Self, error::Unspecified> {
+        untrusted::Input::from(blob).read_all(error::Unspecified, |input| {
+            let (algorithm, key) = read_public_key(input)?;
+            Self::new(algorithm, key.as_slice_less_safe(), comment)
+        })
+    }
+
+    /// Encodes the public key in the SSH wire format, without the comment.
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut r = Vec::new();
+        write_string(&mut r, self.algorithm.name.as_bytes());
+        if let KeyType::Ecdsa { curve_name, .. } = self.algorithm.key_type {
+            write_string(&mut r, curve_name.as_bytes());
+        }
+        write_string(&mut r, &self.key);
+        r
+    }
+
+    /// Encodes the public key as a line of an `authorized_keys` file, without
+    /// a trailing line break.
+    pub fn to_line(&self) -> String {
+        let mut r = String::from(self.algorithm.name);
+        r.push(' ');
+        r.push_str(&base64::encode(&self.to_blob(), &base64::STANDARD));
+        if !self.comment.is_empty() {
+            r.push(' ');
+            r.push_str(&self.comment);
+        }
+        r
+    }
+
+    /// The algorithm of the key.
+    pub fn algorithm(&self) -> &'static Algorithm { self.algorithm }
+
+    /// The raw public key, in the form that `signature::verify()` expects.
+    pub fn key_bytes(&self) -> &[u8] { &self.key }
+
+    /// The comment, which is often the user and host the key was generated
+    /// for.
+    pub fn comment(&self) -> &str { &self.comment }
+}
+
+/// An unencrypted `openssh-key-v1` private key.
+pub struct PrivateKey {
+    // The Ed25519 seed or the big-endian ECDSA private scalar, padded to the
+    // length of the curve's scalars.
+    private_key: Vec<u8>,
+    public_key: PublicKey,
+}
+
+derive_debug_via_self!(PrivateKey, self.public_key);
+
+impl PrivateKey {
+    /// Converts the PKCS#8 private key `input` for `algorithm`, as generated
+    /// by `Ed25519KeyPair::generate_pkcs8()` or
+    /// `ECDSAKeyPair::generate_pkcs8()`, to an SSH private key.
+    ///
+    /// For Ed25519, both PKCS#8 v1 and v2 documents are accepted, as with
+    /// `Ed25519KeyPair::from_pkcs8_maybe_unchecked()`. For ECDSA, the document
+    /// must be acceptable to `ECDSAKeyPair::from_pkcs8()`. Fails if `comment`
+    /// contains a line break.
+    pub fn from_pkcs8(
+        algorithm: &'static Algorithm, input: untrusted::Input, comment: &str,
+    ) -> Result<Self, error::KeyRejected> {
+        let (private_key, public_key) = match algorithm.key_type {
+            KeyType::Ed25519 => {
+                let key_pair = signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(input)?;
+                let (seed, _) = ed25519::signing::unwrap_pkcs8(pkcs8::Version::V1OrV2, input)?;
+                (
+                    seed.as_slice_less_safe().to_vec(),
+                    key_pair.public_key_bytes().to_vec(),
+                )
+            },
+            KeyType::Ecdsa {
+                curve,
+                pkcs8_template,
+                ..
+            } => {
+                let key_pair = suite_b::key_pair_from_pkcs8(curve, pkcs8_template, input)?;
+                (
+                    key_pair.private_key.bytes(curve).to_vec(),
+                    key_pair.public_key[..curve.public_key_len].to_vec(),
+                )
+            },
+        };
+        let public_key = PublicKey::new(algorithm, &public_key, comment)
+            .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+        Ok(Self {
+            private_key,
+            public_key,
+        })
+    }
+
+    /// Parses a PEM-encoded `openssh-key-v1` private key, e.g. the contents of
+    /// an `id_ed25519` file.
+    ///
+    /// The private key is verified to be consistent with the public key.
+    pub fn parse(input: &str) -> Result<Self, error::KeyRejected> {
+        let bytes = pem::decode(input, PRIVATE_KEY_PEM_LABEL)
+            .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+        untrusted::Input::from(&bytes).read_all(error::KeyRejected::invalid_encoding(), |input| {
+            let magic = input
+                .skip_and_get_input(AUTH_MAGIC.len())
+                .map_err(|untrusted::EndOfInput| error::KeyRejected::invalid_encoding())?;
+            if magic != AUTH_MAGIC {
+                return Err(error::KeyRejected::invalid_encoding());
+            }
+            let cipher_name = key_string(input)?;
+            let kdf_name = key_string(input)?;
+            let kdf_options = key_string(input)?;
+            if cipher_name != NONE || kdf_name != NONE || !kdf_options.is_empty() {
+                return Err(error::KeyRejected::wrong_algorithm());
+            }
+            if key_u32(input)? != 1 {
+                return Err(error::KeyRejected::invalid_encoding());
+            }
+            let public_key = key_string(input)?;
+            let private_section = key_string(input)?;
+            if private_section.len() % NONE_BLOCK_LEN != 0 {
+                return Err(error::KeyRejected::invalid_encoding());
+            }
+            let r = private_section.read_all(
+                error::KeyRejected::invalid_encoding(),
+                read_private_section,
+            )?;
+            if public_key != &r.public_key.to_blob()[..] {
+                return Err(error::KeyRejected::inconsistent_components());
+            }
+            Ok(r)
+        })
+    }
+
+    /// Encodes the private key as a PEM-encoded `openssh-key-v1` private key.
+    ///
+    /// `rng` is used to generate the "check" value that OpenSSH uses to detect
+    /// a wrong passphrase.
+    pub fn to_pem(&self, rng: &rand::SecureRandom) -> Result<String, error::Unspecified> {
+        let mut check = [0u8; 4];
+        rng.fill(&mut check)?;
+
+        let public_key = &self.public_key;
+        let mut private_section = Vec::new();
+        private_section.extend_from_slice(&check);
+        private_section.extend_from_slice(&check);
+        write_string(&mut private_section, public_key.algorithm.name.as_bytes());
+        match public_key.algorithm.key_type {
+            KeyType::Ed25519 => {
+                write_string(&mut private_section, &public_key.key);
+                // The "private key" is the seed followed by the public key.
+                write_u32(&mut private_section, 64);
+                private_section.extend_from_slice(&self.private_key);
+                private_section.extend_from_slice(&public_key.key);
+            },
+            KeyType::Ecdsa { curve_name, .. } => {
+                write_string(&mut private_section, curve_name.as_bytes());
+                write_string(&mut private_section, &public_key.key);
+                write_mpint(&mut private_section, io::Positive::from_be_bytes(&self.private_key)?);
+            },
+        }
+        write_string(&mut private_section, public_key.comment.as_bytes());
+        let mut padding = 1;
+        while private_section.len() % NONE_BLOCK_LEN != 0 {
+            private_section.push(padding);
+            padding += 1;
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(AUTH_MAGIC);
+        write_string(&mut bytes, NONE);
+        write_string(&mut bytes, NONE);
+        write_string(&mut bytes, &[]);
+        write_u32(&mut bytes, 1);
+        write_string(&mut bytes, &public_key.to_blob());
+        write_string(&mut bytes, &private_section);
+        pem::encode(PRIVATE_KEY_PEM_LABEL, &bytes)
+    }
+
+    /// Converts the private key to a PKCS#8 document that can be given to
+    /// `Ed25519KeyPair::from_pkcs8()` or `ECDSAKeyPair::from_pkcs8()`.
+    pub fn to_pkcs8(&self) -> pkcs8::Document {
+        let template = match self.public_key.algorithm.key_type {
+            KeyType::Ed25519 => &ed25519::signing::PKCS8_TEMPLATE,
+            KeyType::Ecdsa { pkcs8_template, .. } => pkcs8_template,
+        };
+        pkcs8::wrap_key(template, &self.private_key, &self.public_key.key)
+    }
+
+    /// The algorithm of the key.
+    pub fn algorithm(&self) -> &'static Algorithm { self.public_key.algorithm }
+
+    /// The public key, including the comment.
+    pub fn public_key(&self) -> &PublicKey { &self.public_key }
+}
+
+fn read_private_section(input: &mut untrusted::Reader) -> Result<PrivateKey, error::KeyRejected> {
+    let check1 = key_u32(input)?;
+    let check2 = key_u32(input)?;
+    if check1 != check2 {
+        return Err(error::KeyRejected::invalid_encoding());
+    }
+
+    let (algorithm, public_key) = read_public_key(input).map_err(|error::Unspecified| {
+        // Either the algorithm isn't supported or the encoding is bad; the
+        // former is much more likely.
+        error::KeyRejected::wrong_algorithm()
+    })?;
+    let private_key = match algorithm.key_type {
+        KeyType::Ed25519 => {
+            // The seed followed by the public key.
+            let private_key = key_string(input)?;
+            if private_key.len() != 64 {
+                return Err(error::KeyRejected::invalid_encoding());
+            }
+            let (seed, public_key_copy) = private_key.as_slice_less_safe().split_at(32);
+            if public_key_copy != public_key.as_slice_less_safe() {
+                return Err(error::KeyRejected::inconsistent_components());
+            }
+            let _ = signature::Ed25519KeyPair::from_seed_and_public_key(
+                untrusted::Input::from(seed),
+                public_key,
+            )?;
+            seed.to_vec()
+        },
+        KeyType::Ecdsa { curve, .. } => {
+            let private_key = read_mpint(input, curve.elem_and_scalar_len)?;
+            let _ = suite_b::key_pair_from_bytes(
+                curve,
+                untrusted::Input::from(&private_key),
+                public_key,
+            )?;
+            private_key
+        },
+    };
+    let comment = key_string(input)?;
+    let comment = core::str::from_utf8(comment.as_slice_less_safe())
+        .map_err(|_| error::KeyRejected::invalid_encoding())?;
+
+    // The padding is 1, 2, 3, ....
+    let mut expected = 1;
+    while !input.at_end() {
+        let padding = input
+            .read_byte()
+            .map_err(|untrusted::EndOfInput| error::KeyRejected::invalid_encoding())?;
+        if padding != expected {
+            return Err(error::KeyRejected::invalid_encoding());
+        }
+        expected += 1;
+    }
+
+    let public_key = PublicKey::new(algorithm, public_key.as_slice_less_safe(), comment)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    Ok(PrivateKey {
+        private_key,
+        public_key,
+    })
+}
+
+fn read_public_key<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<(&'static Algorithm, untrusted::Input<'a>), error::Unspecified> {
+    let name = read_string(input)?;
+    let algorithm = algorithm_from_name(name.as_slice_less_safe()).ok_or(error::Unspecified)?;
+    if let KeyType::Ecdsa { curve_name, .. } = algorithm.key_type {
+        if read_string(input)? != curve_name.as_bytes() {
+            return Err(error::Unspecified);
+        }
+    }
+    let key = read_string(input)?;
+    Ok((algorithm, key))
+}
+
+fn read_u32(input: &mut untrusted::Reader) -> Result<u32, error::Unspecified> {
+    let bytes = input.skip_and_get_input(4)?.as_slice_less_safe();
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// RFC 4251 Section 5 `string`.
+fn read_string<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<untrusted::Input<'a>, error::Unspecified> {
+    let len = read_u32(input)?;
+    Ok(input.skip_and_get_input(len as usize)?)
+}
+
+// Like `read_u32()` and `read_string()`, but for parsing private keys.
+fn key_u32(input: &mut untrusted::Reader) -> Result<u32, error::KeyRejected> {
+    read_u32(input).map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+}
+
+fn key_string<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<untrusted::Input<'a>, error::KeyRejected> {
+    read_string(input).map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())
+}
+
+// Reads a positive RFC 4251 Section 5 `mpint` and returns it as a big-endian
+// value of exactly `len` bytes.
+fn read_mpint(input: &mut untrusted::Reader, len: usize) -> Result<Vec<u8>, error::KeyRejected> {
+    let value = key_string(input)?.as_slice_less_safe();
+    if value.is_empty() || (value[0] & 0x80) != 0 {
+        // Zero or negative.
+        return Err(error::KeyRejected::invalid_component());
+    }
+    let positive = io::Positive::from_be_bytes(value)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_encoding())?;
+    // The leading zero is only allowed when it is needed to make the value
+    // positive.
+    if positive.to_signed_be_bytes()[..] != *value {
+        return Err(error::KeyRejected::invalid_encoding());
+    }
+    positive
+        .to_fixed_width(len)
+        .map_err(|error::Unspecified| error::KeyRejected::invalid_component())
+}
+
+fn write_u32(out: &mut Vec<u8>, value: u32) { out.extend_from_slice(&value.to_be_bytes()); }
+
+fn write_string(out: &mut Vec<u8>, value: &[u8]) {
+    write_u32(out, value.len() as u32);
+    out.extend_from_slice(value);
+}
+
+fn write_mpint(out: &mut Vec<u8>, value: io::Positive) {
+    write_string(out, &value.to_signed_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mpint_round_trip() {
+        for &(value, encoded) in &[
+            (&[0x00, 0x01][..], &[0, 0, 0, 1, 0x01][..]),
+            (&[0x7f, 0xff][..], &[0, 0, 0, 2, 0x7f, 0xff][..]),
+            (&[0x80, 0x00][..], &[0, 0, 0, 3, 0x00, 0x80, 0x00][..]),
+            (&[0x00, 0x80][..], &[0, 0, 0, 2, 0x00, 0x80][..]),
+        ] {
+            let mut actual = Vec::new();
+            write_mpint(&mut actual, io::Positive::from_be_bytes(value).unwrap());
+            assert_eq!(&actual[..], encoded);
+
+            let mut reader = untrusted::Reader::new(untrusted::Input::from(encoded));
+            assert_eq!(&read_mpint(&mut reader, 2).unwrap()[..], value);
+            assert!(reader.at_end());
+        }
+    }
+
+    #[test]
+    fn test_read_mpint_rejects_malformed() {
+        for &encoded in &[
+            &[0, 0, 0, 0][..],                   // Zero.
+            &[0, 0, 0, 1, 0x80][..],             // Negative.
+            &[0, 0, 0, 1, 0x00][..],             // Non-minimal zero.
+            &[0, 0, 0, 2, 0x00, 0x7f][..],       // Unnecessary leading zero.
+            &[0, 0, 0, 3, 0x01, 0x00, 0x00][..], // Too long.
+            &[0, 0, 0, 2, 0x01][..],             // Truncated.
+        ] {
+            let mut reader = untrusted::Reader::new(untrusted::Input::from(encoded));
+            assert!(read_mpint(&mut reader, 2).is_err());
+        }
+    }
+}
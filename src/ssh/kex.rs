@@ -0,0 +1,430 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! SSH key exchange with `curve25519-sha256` ([RFC 8731]).
+//!
+//! The client sends its ephemeral public key `Q_C` in `SSH_MSG_KEX_ECDH_INIT`
+//! and the server replies with its host key `K_S`, its ephemeral public key
+//! `Q_S`, and its signature of the exchange hash
+//!
+//! ```text
+//! H = SHA-256(string V_C || string V_S || string I_C || string I_S
+//!             || string K_S || string Q_C || string Q_S || mpint K)
+//! ```
+//!
+//! in `SSH_MSG_KEX_ECDH_REPLY`, where `V_C` and `V_S` are the identification
+//! strings without the trailing CR LF, `I_C` and `I_S` are the payloads of
+//! the `SSH_MSG_KEXINIT` messages, and `K` is the X25519 shared secret. The
+//! server signs `H` with its host key, e.g. with `Ed25519KeyPair`, and the
+//! client verifies that signature and that `K_S` is the server's host key.
+//!
+//! The IVs and keys of both directions are then derived from `K`, `H`, and
+//! the session identifier, which is the `H` of the first key exchange of the
+//! connection, as specified in [RFC 4253 Section 7.2].
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{rand, ssh::kex};
+//!
+//! # fn exchange() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let alg = &kex::CURVE25519_SHA256;
+//! let client = kex::KeyExchange::start(alg, &rng)?;
+//! let server = kex::KeyExchange::start(alg, &rng)?;
+//! let (q_c, q_s) = (client.public_key().to_vec(), server.public_key().to_vec());
+//!
+//! let (v_c, v_s) = (b"SSH-2.0-client", b"SSH-2.0-server");
+//! let (i_c, i_s) = (b"client KEXINIT", b"server KEXINIT");
+//! let k_s = b"server host key";
+//! let client = client.finish_client(&q_s, v_c, v_s, i_c, i_s, k_s)?;
+//! let server = server.finish_server(&q_c, v_c, v_s, i_c, i_s, k_s)?;
+//! assert_eq!(client.exchange_hash(), server.exchange_hash());
+//!
+//! // This is the first key exchange, so `H` is the session identifier.
+//! let session_id = client.exchange_hash().to_vec();
+//! let mut client_key = [0; 32];
+//! let mut server_key = [0; 32];
+//! client.derive(&session_id, kex::Key::ClientToServerEncryption, &mut client_key);
+//! server.derive(&session_id, kex::Key::ClientToServerEncryption, &mut server_key);
+//! assert_eq!(client_key, server_key);
+//! # Ok(())
+//! # }
+//! # fn main() { exchange().unwrap() }
+//! ```
+//!
+//! [RFC 8731]: https://tools.ietf.org/html/rfc8731
+//! [RFC 4253 Section 7.2]: https://tools.ietf.org/html/rfc4253#section-7.2
+
+use crate::{agreement, digest, error, rand, zeroize};
+use untrusted;
+
+/// An SSH key exchange method.
+pub struct Algorithm {
+    name: &'static str,
+    agreement_alg: &'static agreement::Algorithm,
+    digest_alg: &'static digest::Algorithm,
+}
+
+derive_debug_via_self!(Algorithm, self.name);
+
+impl Algorithm {
+    /// The name of the key exchange method, e.g. `curve25519-sha256`.
+    #[inline]
+    pub fn name(&self) -> &'static str { self.name }
+}
+
+/// `curve25519-sha256`, X25519 and SHA-256.
+pub static CURVE25519_SHA256: Algorithm = Algorithm {
+    name: "curve25519-sha256",
+    agreement_alg: &agreement::X25519,
+    digest_alg: &digest::SHA256,
+};
+
+/// `curve25519-sha256@libssh.org`, the name under which `curve25519-sha256`
+/// was introduced, and which OpenSSH still offers.
+pub static CURVE25519_SHA256_LIBSSH_ORG: Algorithm = Algorithm {
+    name: "curve25519-sha256@libssh.org",
+    agreement_alg: &agreement::X25519,
+    digest_alg: &digest::SHA256,
+};
+
+/// The X25519 public key is 32 bytes.
+const PUBLIC_KEY_LEN: usize = 32;
+
+/// The length of an `mpint` encoding of `K`: the length, and the 32 bytes of
+/// the shared secret, preceded by a zero byte if the high bit is set.
+const ENCODED_SHARED_SECRET_MAX_LEN: usize = 4 + 1 + 32;
+
+/// Either party's state before it has received the peer's public key.
+pub struct KeyExchange {
+    alg: &'static Algorithm,
+    private_key: agreement::EphemeralPrivateKey,
+    public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+impl KeyExchange {
+    /// Generates an ephemeral key pair.
+    pub fn start(
+        alg: &'static Algorithm, rng: &rand::SecureRandom,
+    ) -> Result<Self, error::Unspecified> {
+        let private_key = agreement::EphemeralPrivateKey::generate(alg.agreement_alg, rng)?;
+        let mut public_key = [0; PUBLIC_KEY_LEN];
+        private_key.compute_public_key(&mut public_key)?;
+        Ok(Self {
+            alg,
+            private_key,
+            public_key,
+        })
+    }
+
+    /// The ephemeral public key to send to the peer: `Q_C` for the client and
+    /// `Q_S` for the server.
+    #[inline]
+    pub fn public_key(&self) -> &[u8] { &self.public_key }
+
+    /// Finishes the client's side of the exchange with the server's ephemeral
+    /// public key `Q_S`.
+    ///
+    /// Fails if `server_public_key` isn't valid or if the shared secret is
+    /// zero, which happens when it is a point of small order.
+    pub fn finish_client(
+        self, server_public_key: &[u8], client_version: &[u8], server_version: &[u8],
+        client_kexinit: &[u8], server_kexinit: &[u8], server_host_key: &[u8],
+    ) -> Result<SessionKeys, error::Unspecified> {
+        let client_public_key = self.public_key;
+        self.finish(
+            server_public_key,
+            [
+                client_version,
+                server_version,
+                client_kexinit,
+                server_kexinit,
+                server_host_key,
+                &client_public_key,
+                server_public_key,
+            ],
+        )
+    }
+
+    /// Finishes the server's side of the exchange with the client's ephemeral
+    /// public key `Q_C`, like `finish_client()`.
+    pub fn finish_server(
+        self, client_public_key: &[u8], client_version: &[u8], server_version: &[u8],
+        client_kexinit: &[u8], server_kexinit: &[u8], server_host_key: &[u8],
+    ) -> Result<SessionKeys, error::Unspecified> {
+        let server_public_key = self.public_key;
+        self.finish(
+            client_public_key,
+            [
+                client_version,
+                server_version,
+                client_kexinit,
+                server_kexinit,
+                server_host_key,
+                client_public_key,
+                &server_public_key,
+            ],
+        )
+    }
+
+    fn finish(
+        self, peer_public_key: &[u8], strings: [&[u8]; 7],
+    ) -> Result<SessionKeys, error::Unspecified> {
+        let alg = self.alg;
+        let shared_secret = agreement::agree_ephemeral(
+            self.private_key,
+            alg.agreement_alg,
+            untrusted::Input::from(peer_public_key),
+            error::Unspecified,
+            |k| Ok(SharedSecret::new(k)),
+        )?;
+
+        let mut ctx = digest::Context::new(alg.digest_alg);
+        for value in strings.iter() {
+            ctx.update(&(value.len() as u32).to_be_bytes());
+            ctx.update(value);
+        }
+        ctx.update(shared_secret.as_ref());
+        Ok(SessionKeys {
+            alg,
+            shared_secret,
+            exchange_hash: ctx.finish(),
+        })
+    }
+}
+
+// `K`, encoded as an `mpint`.
+struct SharedSecret {
+    bytes: [u8; ENCODED_SHARED_SECRET_MAX_LEN],
+    len: usize,
+}
+
+impl SharedSecret {
+    // `k` is the X25519 output, which RFC 8731 treats as a big-endian
+    // unsigned integer. It is never zero.
+    fn new(k: &[u8]) -> Self {
+        let leading_zeros = k.iter().take_while(|b| **b == 0).count();
+        let k = &k[leading_zeros..];
+        let padding = usize::from((k[0] & 0x80) != 0);
+        let value_len = padding + k.len();
+        let mut r = Self {
+            bytes: [0; ENCODED_SHARED_SECRET_MAX_LEN],
+            len: 4 + value_len,
+        };
+        r.bytes[..4].copy_from_slice(&(value_len as u32).to_be_bytes());
+        r.bytes[(4 + padding)..r.len].copy_from_slice(k);
+        r
+    }
+}
+
+impl AsRef<[u8]> for SharedSecret {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+impl Drop for SharedSecret {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.bytes) }
+}
+
+/// Which of the values of [RFC 4253 Section 7.2] to derive.
+///
+/// [RFC 4253 Section 7.2]: https://tools.ietf.org/html/rfc4253#section-7.2
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Key {
+    /// The initial IV from the client to the server, `"A"`.
+    ClientToServerIv,
+
+    /// The initial IV from the server to the client, `"B"`.
+    ServerToClientIv,
+
+    /// The encryption key from the client to the server, `"C"`.
+    ClientToServerEncryption,
+
+    /// The encryption key from the server to the client, `"D"`.
+    ServerToClientEncryption,
+
+    /// The integrity key from the client to the server, `"E"`.
+    ClientToServerIntegrity,
+
+    /// The integrity key from the server to the client, `"F"`.
+    ServerToClientIntegrity,
+}
+
+impl Key {
+    fn letter(self) -> u8 {
+        match self {
+            Key::ClientToServerIv => b'A',
+            Key::ServerToClientIv => b'B',
+            Key::ClientToServerEncryption => b'C',
+            Key::ServerToClientEncryption => b'D',
+            Key::ClientToServerIntegrity => b'E',
+            Key::ServerToClientIntegrity => b'F',
+        }
+    }
+}
+
+/// The result of a key exchange: the shared secret `K` and the exchange hash
+/// `H`.
+pub struct SessionKeys {
+    alg: &'static Algorithm,
+    shared_secret: SharedSecret,
+    exchange_hash: digest::Digest,
+}
+
+impl SessionKeys {
+    /// The key exchange method.
+    #[inline]
+    pub fn algorithm(&self) -> &'static Algorithm { self.alg }
+
+    /// The exchange hash `H`, which the server signs with its host key, and
+    /// which is the session identifier if this is the first key exchange of
+    /// the connection.
+    #[inline]
+    pub fn exchange_hash(&self) -> &[u8] { self.exchange_hash.as_ref() }
+
+    /// Fills `out` with the IV or key `key` for the session `session_id`.
+    ///
+    /// The first block is `HASH(K || H || letter || session_id)` and each
+    /// following block is `HASH(K || H || K1 || ... || Kn)`, so `out` may be
+    /// as long as the cipher needs, e.g. 64 bytes for
+    /// `chacha20-poly1305@openssh.com`.
+    pub fn derive(&self, session_id: &[u8], key: Key, out: &mut [u8]) {
+        let digest_alg = self.alg.digest_alg;
+        let new_ctx = || {
+            let mut ctx = digest::Context::new(digest_alg);
+            ctx.update(self.shared_secret.as_ref());
+            ctx.update(self.exchange_hash.as_ref());
+            ctx
+        };
+
+        let mut ctx = new_ctx();
+        ctx.update(&[key.letter()]);
+        ctx.update(session_id);
+        let mut block = ctx.finish();
+        let mut ctx = new_ctx();
+        let mut chunks = out.chunks_mut(digest_alg.output_len).peekable();
+        while let Some(chunk) = chunks.next() {
+            chunk.copy_from_slice(&block.as_ref()[..chunk.len()]);
+            if chunks.peek().is_some() {
+                ctx.update(block.as_ref());
+                block = ctx.clone().finish();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_shared_secret_encoding() {
+        for &(k, encoded) in &[
+            (
+                &[0x01; 32][..],
+                &[&[0, 0, 0, 32][..], &[0x01; 32][..]].concat()[..],
+            ),
+            (
+                &[0x80; 32][..],
+                &[&[0, 0, 0, 33, 0][..], &[0x80; 32][..]].concat()[..],
+            ),
+            (
+                &[&[0, 0][..], &[0x7f; 30][..]].concat()[..],
+                &[&[0, 0, 0, 30][..], &[0x7f; 30][..]].concat()[..],
+            ),
+            (
+                &[&[0][..], &[0xff; 31][..]].concat()[..],
+                &[&[0, 0, 0, 32, 0][..], &[0xff; 31][..]].concat()[..],
+            ),
+        ] {
+            assert_eq!(SharedSecret::new(k).as_ref(), encoded);
+        }
+    }
+
+    #[test]
+    fn test_kex() {
+        test::from_file("src/ssh/kex_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let client_private_key = test_case.consume_bytes("ClientPrivateKey");
+            let server_private_key = test_case.consume_bytes("ServerPrivateKey");
+            let client_version = test_case.consume_bytes("V_C");
+            let server_version = test_case.consume_bytes("V_S");
+            let client_kexinit = test_case.consume_bytes("I_C");
+            let server_kexinit = test_case.consume_bytes("I_S");
+            let server_host_key = test_case.consume_bytes("K_S");
+            let q_c = test_case.consume_bytes("Q_C");
+            let q_s = test_case.consume_bytes("Q_S");
+            let session_id = test_case.consume_bytes("SessionId");
+            let h = test_case.consume_bytes("H");
+            let keys = [
+                (Key::ClientToServerIv, test_case.consume_bytes("IV_C2S")),
+                (Key::ServerToClientIv, test_case.consume_bytes("IV_S2C")),
+                (Key::ClientToServerEncryption, test_case.consume_bytes("Key_C2S")),
+                (Key::ServerToClientEncryption, test_case.consume_bytes("Key_S2C")),
+                (Key::ClientToServerIntegrity, test_case.consume_bytes("MAC_C2S")),
+                (Key::ServerToClientIntegrity, test_case.consume_bytes("MAC_S2C")),
+            ];
+
+            let start = |private_key: &[u8]| {
+                let rng = test::rand::FixedSliceRandom { bytes: private_key };
+                KeyExchange::start(&CURVE25519_SHA256, &rng).unwrap()
+            };
+            let client = start(&client_private_key);
+            let server = start(&server_private_key);
+            assert_eq!(client.public_key(), &q_c[..]);
+            assert_eq!(server.public_key(), &q_s[..]);
+            let client = client
+                .finish_client(
+                    &q_s,
+                    &client_version,
+                    &server_version,
+                    &client_kexinit,
+                    &server_kexinit,
+                    &server_host_key,
+                )
+                .unwrap();
+            let server = server
+                .finish_server(
+                    &q_c,
+                    &client_version,
+                    &server_version,
+                    &client_kexinit,
+                    &server_kexinit,
+                    &server_host_key,
+                )
+                .unwrap();
+
+            for session_keys in &[client, server] {
+                assert_eq!(session_keys.exchange_hash(), &h[..]);
+                for (key, expected) in keys.iter() {
+                    let mut actual = vec![0; expected.len()];
+                    session_keys.derive(&session_id, *key, &mut actual);
+                    assert_eq!(&actual, expected);
+                }
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_kex_rejects_small_order_point() {
+        let rng = test::rand::FixedSliceRandom { bytes: &[0x42; 32] };
+        let client = KeyExchange::start(&CURVE25519_SHA256, &rng).unwrap();
+        assert!(client.finish_client(&[0; 32], b"", b"", b"", b"", b"").is_err());
+        let client = KeyExchange::start(&CURVE25519_SHA256, &rng).unwrap();
+        assert!(client.finish_client(&[0; 31], b"", b"", b"", b"", b"").is_err());
+    }
+}
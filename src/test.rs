@@ -68,6 +68,25 @@
 //! Note that `consume_digest_alg` automatically maps the string "SHA1" to a
 //! reference to `digest::SHA1`, "SHA256" to `digest::SHA256`, etc.
 //!
+//! ## Large Inputs, Flags, and Includes
+//!
+//! Big test vector sets are easier to maintain with a few more constructs:
+//!
+//! ```text
+//! # The bytes of `Input` are the contents of the file, whose path is relative
+//! # to the top-level directory, like the path given to `from_file`.
+//! Input = @tests/example_input.bin
+//! Mode = Decrypt
+//! # A line with just a name is a flag; see `TestCase::consume_flag`.
+//! Deterministic
+//!
+//! # The test cases of another file are read here. Its sections don't apply
+//! # to the test cases that follow the include.
+//! !include tests/example_common_tests.txt
+//! ```
+//!
+//! `consume_enum` maps a value like `Decrypt` to one of a set of values.
+//!
 //! ## Output When a Test Fails
 //!
 //! When a test case fails, the framework automatically prints out the test
@@ -116,13 +135,15 @@
 //! stack trace to the line in the test code that panicked: entry 9 in the
 //! stack trace pointing to line 652 of the file `example.rs`.
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "alloc")]
 use crate::bits;
 
-use crate::{digest, error};
+#[cfg(any(test, feature = "alloc"))]
+use crate::{digest, error, io::hex};
 
+#[cfg(any(test, feature = "alloc"))]
+use alloc::{string::String, vec::Vec};
 use core;
-use std::{self, io::BufRead, string::String, vec::Vec};
 
 /// `compile_time_assert_clone::<T>();` fails to compile if `T` doesn't
 /// implement `Clone`.
@@ -147,11 +168,17 @@ pub fn compile_time_assert_debug<T: core::fmt::Debug>() {}
 /// A test case. A test case consists of a set of named attributes. Every
 /// attribute in the test case must be consumed exactly once; this helps catch
 /// typos and omissions.
+#[cfg(any(test, feature = "alloc"))]
 #[derive(Debug)]
 pub struct TestCase {
     attributes: Vec<(String, String, bool)>,
 }
 
+// The value of a flag, which is written as just its name.
+#[cfg(any(test, feature = "alloc"))]
+const FLAG_VALUE: &str = "(flag)";
+
+#[cfg(any(test, feature = "alloc"))]
 impl TestCase {
     /// Maps the string "true" to true and the string "false" to false.
     pub fn consume_bool(&mut self, key: &str) -> bool {
@@ -162,6 +189,27 @@ impl TestCase {
         }
     }
 
+    /// Returns whether the test case has the flag `name`, i.e. a line that
+    /// consists of just `name`.
+    pub fn consume_flag(&mut self, name: &str) -> bool {
+        match self.consume_optional_string(name) {
+            Some(ref value) if value == FLAG_VALUE => true,
+            Some(value) => panic!("{} is a flag, but it has the value {}", name, value),
+            None => false,
+        }
+    }
+
+    /// Maps the value of the attribute to the value paired with it in
+    /// `values`, and panics on other (erroneous) inputs.
+    pub fn consume_enum<T: Copy>(&mut self, key: &str, values: &[(&str, T)]) -> T {
+        let name = self.consume_string(key);
+        values
+            .iter()
+            .find(|&&(value_name, _)| value_name == name)
+            .map(|&(_, value)| value)
+            .unwrap_or_else(|| panic!("Invalid value for {}: {}", key, name))
+    }
+
     /// Maps the strings "SHA1", "SHA256", "SHA384", and "SHA512" to digest
     /// algorithms, maps "SHA224" to `None`, and panics on other (erroneous)
     /// inputs. "SHA224" is mapped to None because *ring* intentionally does
@@ -181,11 +229,14 @@ impl TestCase {
     }
 
     /// Returns the value of an attribute that is encoded as a sequence of an
-    /// even number of hex digits, or as a double-quoted UTF-8 string. The
+    /// even number of hex digits, or as a double-quoted UTF-8 string, or that
+    /// is the contents of the file named by `@` followed by its path. The
     /// empty (zero-length) value is represented as "".
     pub fn consume_bytes(&mut self, key: &str) -> Vec<u8> {
         let s = self.consume_string(key);
-        if s.starts_with('\"') {
+        if s.starts_with('@') {
+            read_file(&s[1..])
+        } else if s.starts_with('\"') {
             // The value is a quoted UTF-8 string.
 
             let mut bytes = Vec::with_capacity(s.as_bytes().len() - 2);
@@ -246,7 +297,7 @@ impl TestCase {
 
     /// Returns the value of an attribute that is an integer, in decimal
     /// notation, as a bit length.
-    #[cfg(feature = "use_heap")]
+    #[cfg(feature = "alloc")]
     pub fn consume_usize_bits(&mut self, key: &str) -> bits::BitLength {
         let s = self.consume_string(key);
         let bits = s.parse::<usize>().unwrap();
@@ -280,7 +331,7 @@ impl TestCase {
 ///
 /// On iOS, source are assumed to be copied in the application bundle, as
 /// a "src" directory along the test runner.
-#[cfg(target_os = "ios")]
+#[cfg(all(any(test, feature = "std"), target_os = "ios"))]
 pub fn ring_src_path() -> std::path::PathBuf {
     std::env::current_exe()
         .unwrap()
@@ -293,29 +344,48 @@ pub fn ring_src_path() -> std::path::PathBuf {
 ///
 /// On most platforms, the tests are run by cargo, so it's just the current
 /// working directory.
-#[cfg(not(target_os = "ios"))]
+#[cfg(all(any(test, feature = "std"), not(target_os = "ios")))]
 pub fn ring_src_path() -> std::path::PathBuf { std::path::PathBuf::from(".") }
 
 /// Reads test cases out of the file with the path given by
-/// `test_data_relative_file_path`, calling `f` on each vector until `f` fails
-/// or until all the test vectors have been read. `f` can indicate failure
-/// either by returning `Err()` or by panicking.
-pub fn from_file<F>(test_data_relative_file_path: &str, mut f: F)
+/// `test_data_relative_file_path`, and the files it includes, calling `f` on
+/// each vector until `f` fails or until all the test vectors have been read.
+/// `f` can indicate failure either by returning `Err()` or by panicking.
+#[cfg(any(test, all(feature = "alloc", feature = "std")))]
+pub fn from_file<F>(test_data_relative_file_path: &str, f: F)
 where
     F: FnMut(&str, &mut TestCase) -> Result<(), error::Unspecified>,
 {
-    let path = ring_src_path().join(test_data_relative_file_path);
-    let file = std::fs::File::open(path).unwrap();
-    let mut lines = std::io::BufReader::new(&file).lines();
+    let contents = read_file(test_data_relative_file_path);
+    let contents = core::str::from_utf8(&contents).unwrap();
+    run(Lines::new(test_data_relative_file_path, contents), f)
+}
 
+/// Like `from_file()`, but reads the test cases from `contents`, e.g. the
+/// contents of a file that was embedded with `include_str!`, so that it works
+/// without the `std` feature. `name` identifies the test cases in the output.
+///
+/// Without `std`, files can't be included or read with `@`, and a test case
+/// that fails makes the test panic right away, because panics can't be
+/// caught.
+#[cfg(any(test, feature = "alloc"))]
+pub fn from_str<F>(name: &str, contents: &str, f: F)
+where
+    F: FnMut(&str, &mut TestCase) -> Result<(), error::Unspecified>,
+{
+    run(Lines::new(name, contents), f)
+}
+
+#[cfg(any(test, feature = "alloc"))]
+fn run<F>(mut lines: Lines, mut f: F)
+where
+    F: FnMut(&str, &mut TestCase) -> Result<(), error::Unspecified>,
+{
     let mut current_section = String::from("");
     let mut failed = false;
 
-    #[allow(box_pointers)]
-    while let Some(mut test_case) = parse_test_case(&mut current_section, &mut lines) {
-        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            f(&current_section, &mut test_case)
-        }));
+    while let Some((path, mut test_case)) = parse_test_case(&mut current_section, &mut lines) {
+        let result = call(&mut f, &current_section, &mut test_case);
         let result = match result {
             Ok(Ok(())) => {
                 if !test_case
@@ -330,17 +400,12 @@ where
                 }
             },
             Ok(Err(_)) => Err("Test returned Err(error::Unspecified)."),
-            Err(_) => Err("Test panicked."),
+            Err(()) => Err("Test panicked."),
         };
 
         if let Err(msg) = result {
             failed = true;
-
-            println!("{}: {}", test_data_relative_file_path, msg);
-            for (name, value, consumed) in test_case.attributes {
-                let consumed_str = if consumed { "" } else { " (unconsumed)" };
-                println!("{}{} = {}", name, consumed_str, value);
-            }
+            report(&path, msg, &test_case);
         };
     }
 
@@ -349,8 +414,67 @@ where
     }
 }
 
+// Calls `f`, returning `Err(())` if it panicked.
+#[cfg(any(test, all(feature = "alloc", feature = "std")))]
+fn call<F>(
+    f: &mut F, section: &str, test_case: &mut TestCase,
+) -> Result<Result<(), error::Unspecified>, ()>
+where
+    F: FnMut(&str, &mut TestCase) -> Result<(), error::Unspecified>,
+{
+    #[allow(box_pointers)]
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(section, test_case)))
+        .map_err(|_| ())
+}
+
+#[cfg(all(feature = "alloc", not(any(test, feature = "std"))))]
+fn call<F>(
+    f: &mut F, section: &str, test_case: &mut TestCase,
+) -> Result<Result<(), error::Unspecified>, ()>
+where
+    F: FnMut(&str, &mut TestCase) -> Result<(), error::Unspecified>,
+{
+    Ok(f(section, test_case))
+}
+
+// Prints the test case that failed, or panics with it when it can't be
+// printed.
+#[cfg(any(test, feature = "alloc"))]
+fn report(path: &str, msg: &str, test_case: &TestCase) {
+    use core::fmt::Write;
+
+    let mut report = String::new();
+    let _ = writeln!(report, "{}: {}", path, msg);
+    for (name, value, consumed) in &test_case.attributes {
+        let consumed_str = if *consumed { "" } else { " (unconsumed)" };
+        if value == FLAG_VALUE {
+            let _ = writeln!(report, "{}{}", name, consumed_str);
+        } else {
+            let _ = writeln!(report, "{}{} = {}", name, consumed_str, value);
+        }
+    }
+
+    #[cfg(any(test, feature = "std"))]
+    print!("{}", report);
+
+    #[cfg(not(any(test, feature = "std")))]
+    panic!("{}", report);
+}
+
+#[cfg(any(test, all(feature = "alloc", feature = "std")))]
+fn read_file(path: &str) -> Vec<u8> {
+    std::fs::read(ring_src_path().join(path))
+        .unwrap_or_else(|e| panic!("Can't read {}: {}", path, e))
+}
+
+#[cfg(all(feature = "alloc", not(any(test, feature = "std"))))]
+fn read_file(path: &str) -> Vec<u8> {
+    panic!("Can't read {} without the `std` feature", path)
+}
+
 /// Decode an string of hex digits into a sequence of bytes. The input must
 /// have an even number of digits.
+#[cfg(any(test, feature = "alloc"))]
 pub fn from_hex(hex_str: &str) -> Result<Vec<u8>, String> {
     if hex_str.len() % 2 != 0 {
         return Err(String::from(
@@ -367,33 +491,98 @@ pub fn from_hex(hex_str: &str) -> Result<Vec<u8>, String> {
     Ok(result)
 }
 
+#[cfg(any(test, feature = "alloc"))]
 fn from_hex_digit(d: u8) -> Result<u8, String> {
-    if d >= b'0' && d <= b'9' {
-        Ok(d - b'0')
-    } else if d >= b'a' && d <= b'f' {
-        Ok(d - b'a' + 10u8)
-    } else if d >= b'A' && d <= b'F' {
-        Ok(d - b'A' + 10u8)
-    } else {
-        Err(format!("Invalid hex digit '{}'", d as char))
-    }
+    hex::decode_digit(d).expose().ok_or_else(|| format!("Invalid hex digit '{}'", d as char))
+}
+
+// Files that include each other more deeply than this are rejected, so that a
+// file that includes itself doesn't recurse forever.
+#[cfg(any(test, feature = "alloc"))]
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+// The lines of a test file and of the files it includes.
+#[cfg(any(test, feature = "alloc"))]
+struct Lines {
+    // The path and the remaining lines of each file that is being read, and
+    // the section that was current when it was included, innermost last.
+    files: Vec<(String, alloc::vec::IntoIter<String>, String)>,
+
+    // The section to restore before the next line is read, once the test
+    // case at the end of an included file has been handled.
+    section_to_restore: Option<String>,
 }
 
-type FileLines<'a> = std::io::Lines<std::io::BufReader<&'a std::fs::File>>;
+#[cfg(any(test, feature = "alloc"))]
+impl Lines {
+    fn new(path: &str, contents: &str) -> Self {
+        let mut lines = Self {
+            files: Vec::new(),
+            section_to_restore: None,
+        };
+        lines.push(path, contents, "");
+        lines
+    }
+
+    fn include(&mut self, path: &str, current_section: &str) {
+        let contents = read_file(path);
+        self.push(path, core::str::from_utf8(&contents).unwrap(), current_section);
+    }
+
+    fn push(&mut self, path: &str, contents: &str, current_section: &str) {
+        if self.files.len() == MAX_INCLUDE_DEPTH {
+            panic!("Includes are nested too deeply at {}", path);
+        }
+        let lines: Vec<String> = contents.lines().map(String::from).collect();
+        self.files.push((
+            String::from(path),
+            lines.into_iter(),
+            String::from(current_section),
+        ));
+    }
+
+    fn path(&self) -> &str { self.files.last().map_or("", |(path, ..)| path) }
+
+    // Returns the next line, or `None` at the end of the outermost file. The
+    // end of an included file is returned as a blank line, so that a test
+    // case can't continue past it, and the section that was current when the
+    // file was included is restored when the line after it is read.
+    fn next(&mut self, current_section: &mut String) -> Option<String> {
+        if let Some(section) = self.section_to_restore.take() {
+            *current_section = section;
+        }
+        let line = self.files.last_mut()?.1.next();
+        match line {
+            Some(line) => Some(line),
+            None => {
+                let (_, _, section) = self.files.pop()?;
+                if self.files.is_empty() {
+                    return None;
+                }
+                self.section_to_restore = Some(section);
+                Some(String::new())
+            },
+        }
+    }
+}
 
-fn parse_test_case(current_section: &mut String, lines: &mut FileLines) -> Option<TestCase> {
+#[cfg(any(test, feature = "alloc"))]
+fn parse_test_case(
+    current_section: &mut String, lines: &mut Lines,
+) -> Option<(String, TestCase)> {
     let mut attributes = Vec::new();
+    let mut path = String::new();
 
     let mut is_first_line = true;
     loop {
-        let line = match lines.next() {
-            None => None,
-            Some(result) => Some(result.unwrap()),
-        };
+        let line = lines.next(current_section);
 
-        if cfg!(feature = "test_logging") {
-            if let Some(text) = &line {
-                println!("Line: {}", text);
+        #[cfg(any(test, feature = "std"))]
+        {
+            if cfg!(feature = "test_logging") {
+                if let Some(text) = &line {
+                    println!("Line: {}", text);
+                }
             }
         }
 
@@ -406,13 +595,13 @@ fn parse_test_case(current_section: &mut String, lines: &mut FileLines) -> Optio
 
             // End of the file on a non-empty test cases ends the test case.
             None => {
-                return Some(TestCase { attributes });
+                return Some((path, TestCase { attributes }));
             },
 
             // A blank line ends a test case if the test case isn't empty.
             Some(ref line) if line.is_empty() => {
                 if !is_first_line {
-                    return Some(TestCase { attributes });
+                    return Some((path, TestCase { attributes }));
                 }
                 // Ignore leading blank lines.
             },
@@ -429,17 +618,24 @@ fn parse_test_case(current_section: &mut String, lines: &mut FileLines) -> Optio
                 let _ = current_section.remove(0);
             },
 
+            Some(ref line) if line.starts_with("!include ") => {
+                assert!(is_first_line);
+                lines.include(line["!include ".len()..].trim(), current_section);
+            },
+
             Some(ref line) => {
+                if is_first_line {
+                    path = String::from(lines.path());
+                }
                 is_first_line = false;
 
                 let parts: Vec<&str> = line.splitn(2, " = ").collect();
-                if parts.len() != 2 {
-                    panic!("Syntax error: Expected Key = Value.");
+                let (key, value) = match parts[..] {
+                    [key, value] => (key.trim(), value.trim()),
+                    [flag] if is_flag_name(flag) => (flag, FLAG_VALUE),
+                    _ => panic!("Syntax error: Expected Key = Value."),
                 };
 
-                let key = parts[0].trim();
-                let value = parts[1].trim();
-
                 // Don't allow the value to be ommitted. An empty value can be
                 // represented as an empty quoted string.
                 assert_ne!(value.len(), 0);
@@ -451,6 +647,14 @@ fn parse_test_case(current_section: &mut String, lines: &mut FileLines) -> Optio
     }
 }
 
+#[cfg(any(test, feature = "alloc"))]
+fn is_flag_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
 /// Deterministic implementations of `ring::rand::SecureRandom`.
 ///
 /// These implementations are particularly useful for testing implementations
@@ -507,6 +711,17 @@ pub mod rand {
         pub current: core::cell::UnsafeCell<usize>,
     }
 
+    impl<'a> FixedSliceSequenceRandom<'a> {
+        /// Constructs a `FixedSliceSequenceRandom` that will return the
+        /// entries of `bytes`, starting with the first.
+        pub fn new(bytes: &'a [&'a [u8]]) -> Self {
+            Self {
+                bytes,
+                current: core::cell::UnsafeCell::new(0),
+            }
+        }
+    }
+
     impl<'a> rand::SecureRandom for FixedSliceSequenceRandom<'a> {
         fn fill(&self, dest: &mut [u8]) -> Result<(), error::Unspecified> {
             let current = unsafe { *self.current.get() };
@@ -533,6 +748,222 @@ pub mod rand {
 
 }
 
+#[cfg(feature = "alloc")]
+pub mod acvp;
+
+/// Test cases from [Wycheproof] JSON files.
+///
+/// A Wycheproof file is a JSON object whose `testGroups` member is an array of
+/// test groups. Each group has the attributes that its test cases share, e.g.
+/// the key, and a `tests` array of test cases. Each test case has a `tcId`, a
+/// `comment`, `flags`, a `result` of "valid", "invalid", or "acceptable", and
+/// the inputs and outputs of the operation. Attributes are looked up by name;
+/// the members of nested objects are named with dots, e.g. "key.pk". Unlike
+/// `test::from_file()`, attributes don't have to be consumed, because the
+/// files have attributes, like PEM encodings of the keys, that *ring*'s tests
+/// don't need.
+///
+/// Wycheproof isn't distributed with *ring*; see `tests/wycheproof_tests.rs`.
+///
+/// [Wycheproof]: https://github.com/google/wycheproof
+#[cfg(all(feature = "alloc", any(test, feature = "std")))]
+pub mod wycheproof {
+    use super::{from_hex, ring_src_path};
+    use crate::{error, jwk::json};
+    use alloc::{string::String, vec::Vec};
+
+    /// The expected result of a test case.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Expected {
+        /// The operation must succeed.
+        Valid,
+
+        /// The operation must fail.
+        Invalid,
+
+        /// The operation may succeed or fail, e.g. because the input is
+        /// malformed in a way that many implementations tolerate, or because
+        /// it uses a weak but legal parameter.
+        Acceptable,
+    }
+
+    /// A group of test cases and the attributes that they share.
+    #[derive(Debug)]
+    pub struct TestGroup {
+        value: json::Value,
+    }
+
+    impl TestGroup {
+        /// Returns whether the group has the attribute `key`.
+        pub fn has(&self, key: &str) -> bool { lookup(&self.value, key).is_some() }
+
+        /// Returns the value of a hex-encoded attribute.
+        pub fn bytes(&self, key: &str) -> Vec<u8> { bytes(&self.value, key) }
+
+        /// Returns the value of a string attribute.
+        pub fn string(&self, key: &str) -> String { string(&self.value, key) }
+
+        /// Returns the value of an integer attribute.
+        pub fn usize(&self, key: &str) -> usize { usize(&self.value, key) }
+    }
+
+    /// A test case.
+    #[derive(Debug)]
+    pub struct TestCase {
+        value: json::Value,
+    }
+
+    impl TestCase {
+        /// The `tcId` attribute, which is unique within the file.
+        pub fn id(&self) -> usize { usize(&self.value, "tcId") }
+
+        /// The expected result.
+        pub fn expected(&self) -> Expected {
+            match string(&self.value, "result").as_ref() {
+                "valid" => Expected::Valid,
+                "invalid" => Expected::Invalid,
+                "acceptable" => Expected::Acceptable,
+                s => panic!("Invalid result: {}", s),
+            }
+        }
+
+        /// Returns whether the test case has the flag `flag`.
+        pub fn has_flag(&self, flag: &str) -> bool {
+            match lookup(&self.value, "flags") {
+                Some(json::Value::Array(flags)) => flags
+                    .iter()
+                    .any(|f| f == &json::Value::String(String::from(flag))),
+                _ => false,
+            }
+        }
+
+        /// Returns the value of a hex-encoded attribute.
+        pub fn bytes(&self, key: &str) -> Vec<u8> { bytes(&self.value, key) }
+
+        /// Returns the value of a string attribute.
+        pub fn string(&self, key: &str) -> String { string(&self.value, key) }
+
+        /// Returns the value of an integer attribute.
+        pub fn usize(&self, key: &str) -> usize { usize(&self.value, key) }
+    }
+
+    /// Reads the test groups out of the Wycheproof file with the path given
+    /// by `test_data_relative_file_path` and calls `f` on each of their test
+    /// cases.
+    ///
+    /// `f` returns `None` if it doesn't apply to the test case, e.g. because
+    /// *ring* doesn't support the group's parameters, and otherwise the
+    /// result of the operation. A test case fails if it is valid and `f`
+    /// returns `Some(Err(_))`, if it is invalid and `f` returns `Some(Ok(()))`,
+    /// or if `f` panics. After all the test cases have been run, this panics
+    /// if any of them failed or if `f` didn't apply to any of them.
+    pub fn from_file<F>(test_data_relative_file_path: &str, mut f: F)
+    where
+        F: FnMut(&TestGroup, &TestCase) -> Option<Result<(), error::Unspecified>>,
+    {
+        let path = ring_src_path().join(test_data_relative_file_path);
+        let contents = std::fs::read_to_string(path).unwrap();
+        let mut file = match json::parse(&contents) {
+            Ok(json::Value::Object(members)) => members,
+            _ => panic!("Syntax error: Expected a JSON object."),
+        };
+        let groups = match take(&mut file, "testGroups") {
+            Some(json::Value::Array(groups)) => groups,
+            _ => panic!("No array named \"testGroups\""),
+        };
+
+        let mut count = 0;
+        let mut run = 0;
+        let mut failed = false;
+
+        for group in groups {
+            let mut group = match group {
+                json::Value::Object(members) => members,
+                _ => panic!("Syntax error: Expected a test group object."),
+            };
+            let test_cases = match take(&mut group, "tests") {
+                Some(json::Value::Array(test_cases)) => test_cases,
+                _ => panic!("No array named \"tests\""),
+            };
+            let group = TestGroup {
+                value: json::Value::Object(group),
+            };
+
+            for test_case in test_cases {
+                let test_case = TestCase { value: test_case };
+                count += 1;
+
+                #[allow(box_pointers)]
+                let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    f(&group, &test_case)
+                }));
+                let result = match (result, test_case.expected()) {
+                    (Ok(None), _) => continue,
+                    (Ok(Some(Err(_))), Expected::Valid) => Err("Valid test case was rejected."),
+                    (Ok(Some(Ok(()))), Expected::Invalid) => {
+                        Err("Invalid test case was accepted.")
+                    },
+                    (Ok(Some(_)), _) => Ok(()),
+                    (Err(_), _) => Err("Test panicked."),
+                };
+                run += 1;
+
+                if let Err(msg) = result {
+                    failed = true;
+                    println!(
+                        "{}: tcId {}: {}",
+                        test_data_relative_file_path,
+                        test_case.id(),
+                        msg
+                    );
+                    println!("{:?}", test_case.value);
+                }
+            }
+        }
+
+        if let Some(json::Value::Number(number_of_tests)) = take(&mut file, "numberOfTests") {
+            assert_eq!(count, number_of_tests.parse::<usize>().unwrap());
+        }
+        if run == 0 {
+            panic!("No test cases were run.");
+        }
+        if failed {
+            panic!("Test failed.");
+        }
+    }
+
+    // Removes the member named `name` from `members` and returns its value.
+    fn take(members: &mut Vec<(String, json::Value)>, name: &str) -> Option<json::Value> {
+        let i = members.iter().position(|(n, _)| n == name)?;
+        Some(members.remove(i).1)
+    }
+
+    fn lookup<'a>(value: &'a json::Value, key: &str) -> Option<&'a json::Value> {
+        key.split('.').try_fold(value, |value, name| value.get(name))
+    }
+
+    fn string(value: &json::Value, key: &str) -> String {
+        match lookup(value, key) {
+            Some(json::Value::String(s)) => s.clone(),
+            Some(_) => panic!("Attribute \"{}\" isn't a string", key),
+            None => panic!("No attribute named \"{}\"", key),
+        }
+    }
+
+    fn bytes(value: &json::Value, key: &str) -> Vec<u8> {
+        let s = string(value, key);
+        from_hex(&s).unwrap_or_else(|err_str| panic!("{} in {}", err_str, s))
+    }
+
+    fn usize(value: &json::Value, key: &str) -> usize {
+        match lookup(value, key) {
+            Some(json::Value::Number(n)) => n.parse::<usize>().unwrap(),
+            Some(_) => panic!("Attribute \"{}\" isn't an integer", key),
+            None => panic!("No attribute named \"{}\"", key),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{error, test};
@@ -545,6 +976,25 @@ mod tests {
         });
     }
 
+    #[test]
+    fn from_str_ok() {
+        test::from_str("test_1", include_str!("test_1_tests.txt"), |_, test_case| {
+            assert_eq!(test_case.consume_string("Key"), "Value");
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Test failed.")]
+    fn from_str_err() {
+        test::from_str("test_1", "Key = Value\n\nKey = Other\n", |_, test_case| {
+            if test_case.consume_string("Key") == "Other" {
+                return Err(error::Unspecified);
+            }
+            Ok(())
+        });
+    }
+
     #[test]
     #[should_panic(expected = "Test failed.")]
     fn one_err() {
@@ -620,4 +1070,102 @@ mod tests {
     #[test]
     #[should_panic]
     fn file_not_found() { test::from_file("src/test_file_not_found_tests.txt", |_, _| Ok(())); }
+
+    #[test]
+    fn include() {
+        let mut sections = Vec::new();
+        test::from_file("src/test_include_tests.txt", |section, test_case| {
+            sections.push((String::from(section), test_case.consume_string("Key")));
+            if section == "Outer" && sections.len() == 3 {
+                let blob = test_case.consume_bytes("Blob");
+                assert_eq!(blob, (0..=255).collect::<Vec<u8>>());
+                let mode = test_case.consume_enum("Mode", &[("Encrypt", true), ("Decrypt", false)]);
+                assert!(!mode);
+                assert!(test_case.consume_flag("Flagged"));
+                assert!(!test_case.consume_flag("Unflagged"));
+            }
+            Ok(())
+        });
+        let expected = [("Outer", "first"), ("Inner", "inner"), ("Outer", "last")];
+        assert_eq!(sections.len(), expected.len());
+        for (actual, expected) in sections.iter().zip(expected.iter()) {
+            assert_eq!((&actual.0[..], &actual.1[..]), *expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Test failed.")]
+    fn include_unconsumed_flag() {
+        test::from_file("src/test_include_tests.txt", |_, test_case| {
+            let _ = test_case.consume_string("Key");
+            if test_case.consume_optional_string("Blob").is_some() {
+                let _ = test_case.consume_string("Mode");
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Includes are nested too deeply")]
+    fn include_self() { test::from_file("src/test_include_self_tests.txt", |_, _| Ok(())); }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn wycheproof_ok() {
+        let mut ids = vec![];
+        test::wycheproof::from_file("src/test_wycheproof_tests.json", |group, test_case| {
+            if group.string("type") != "ExampleTest" {
+                return None;
+            }
+            ids.push(test_case.id());
+            assert_eq!(
+                test_case.has_flag("ModifiedMessage"),
+                test_case.expected() == test::wycheproof::Expected::Invalid
+            );
+            if test_case.bytes("msg") == group.bytes("key.value") {
+                Some(Ok(()))
+            } else {
+                Some(Err(error::Unspecified))
+            }
+        });
+        assert_eq!(ids, [1, 2, 3]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic(expected = "Test failed.")]
+    fn wycheproof_valid_rejected() {
+        test::wycheproof::from_file("src/test_wycheproof_tests.json", |_, test_case| {
+            match test_case.expected() {
+                test::wycheproof::Expected::Valid => Some(Err(error::Unspecified)),
+                _ => None,
+            }
+        });
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic(expected = "Test failed.")]
+    fn wycheproof_invalid_accepted() {
+        test::wycheproof::from_file("src/test_wycheproof_tests.json", |_, _| Some(Ok(())));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic(expected = "Test failed.")]
+    fn wycheproof_panics() {
+        test::wycheproof::from_file("src/test_wycheproof_tests.json", |_, test_case| {
+            if test_case.id() == 3 {
+                panic!("Oh Noes!");
+            }
+            None
+        });
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic(expected = "No test cases were run.")]
+    fn wycheproof_none_run() {
+        test::wycheproof::from_file("src/test_wycheproof_tests.json", |_, _| None);
+    }
 }
@@ -0,0 +1,491 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Responses to NIST [ACVP] test vector requests.
+//!
+//! The Automated Cryptographic Validation Protocol is how the CAVP, which
+//! validates the algorithm implementations of FIPS 140 modules, distributes
+//! test vectors. A request is a JSON file with a vector set: test groups of
+//! test cases with the inputs, and the parameters that they share. The
+//! response has the outputs for each test case, e.g. a digest or whether a
+//! signature is valid. `respond()` computes the responses with *ring*.
+//!
+//! These vector sets are supported:
+//!
+//! * `SHA-1`, `SHA2-256`, `SHA2-384`, `SHA2-512`, and `SHA2-512/256`: `AFT`
+//!   and `MCT` tests of byte-oriented messages.
+//! * `HMAC-SHA-1`, `HMAC-SHA2-256`, `HMAC-SHA2-384`, and `HMAC-SHA2-512`:
+//!   `AFT` tests with byte-oriented keys, messages, and MACs.
+//! * `ACVP-AES-GCM`: `AFT` tests with 128- and 256-bit keys, 96-bit external
+//!   IVs, and 128-bit tags.
+//! * `ECDSA` `sigGen`: P-256 with SHA2-256 and P-384 with SHA2-384.
+//! * `ECDSA` `sigVer`: P-256 and P-384 with SHA2-256 and SHA2-384.
+//! * `RSA` `sigVer`: `pkcs1v1.5` and `pss` with moduli of at least 2048 bits;
+//!   PSS salts must be as long as the digest.
+//!
+//! [ACVP]: https://github.com/usnistgov/ACVP
+
+use crate::{
+    aead, digest, error, hmac,
+    io::hex,
+    jwk::json::{self, Value},
+    rand, signature,
+};
+use alloc::{string::String, vec::Vec};
+
+/// Returns the response to the ACVP request `request`.
+///
+/// `request` is either a vector set or, as the ACVP server sends them, an
+/// array of an object with the `acvVersion` and some vector sets; the
+/// response has the same form. `rng` is used where *ring* generates the keys,
+/// i.e. for ECDSA `sigGen`.
+///
+/// Fails if `request` is malformed or if it has a test group that *ring*
+/// doesn't support; see the module documentation.
+pub fn respond(request: &str, rng: &rand::SecureRandom) -> Result<String, error::Unspecified> {
+    let request = json::parse(request)?;
+    let response = match &request {
+        Value::Array(elements) => Value::Array(
+            elements
+                .iter()
+                .map(|element| {
+                    if element.get("acvVersion").is_some() {
+                        Ok(element.clone())
+                    } else {
+                        respond_to_vector_set(element, rng)
+                    }
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        vector_set => respond_to_vector_set(vector_set, rng)?,
+    };
+    Ok(json::write(&response))
+}
+
+enum VectorSet {
+    Sha(&'static digest::Algorithm),
+    Hmac(&'static digest::Algorithm),
+    AesGcm,
+    EcdsaSigGen,
+    EcdsaSigVer,
+    RsaSigVer,
+}
+
+fn respond_to_vector_set(
+    vector_set: &Value, rng: &rand::SecureRandom,
+) -> Result<Value, error::Unspecified> {
+    let algorithm = string(vector_set, "algorithm")?;
+    let mode = match vector_set.get("mode") {
+        Some(_) => Some(string(vector_set, "mode")?),
+        None => None,
+    };
+    let kind = match (algorithm, mode) {
+        ("ACVP-AES-GCM", None) => VectorSet::AesGcm,
+        ("ECDSA", Some("sigGen")) => VectorSet::EcdsaSigGen,
+        ("ECDSA", Some("sigVer")) => VectorSet::EcdsaSigVer,
+        ("RSA", Some("sigVer")) => VectorSet::RsaSigVer,
+        (algorithm, None) if algorithm.starts_with("HMAC-") => {
+            VectorSet::Hmac(digest_alg(&algorithm[5..])?)
+        },
+        (algorithm, None) => VectorSet::Sha(digest_alg(algorithm)?),
+        _ => return Err(error::Unspecified),
+    };
+
+    let mut response = Vec::new();
+    for name in &["vsId", "algorithm", "mode", "revision"] {
+        if let Some(value) = vector_set.get(name) {
+            response.push((String::from(*name), value.clone()));
+        }
+    }
+    let groups = array(vector_set, "testGroups")?
+        .iter()
+        .map(|group| {
+            let mut members = vec![(String::from("tgId"), get(group, "tgId")?.clone())];
+            let tests = match &kind {
+                VectorSet::Sha(alg) => respond_sha(alg, group)?,
+                VectorSet::Hmac(alg) => respond_hmac(alg, group)?,
+                VectorSet::AesGcm => respond_aes_gcm(group)?,
+                VectorSet::EcdsaSigGen => respond_ecdsa_sig_gen(group, &mut members, rng)?,
+                VectorSet::EcdsaSigVer => respond_ecdsa_sig_ver(group)?,
+                VectorSet::RsaSigVer => respond_rsa_sig_ver(group)?,
+            };
+            members.push((String::from("tests"), Value::Array(tests)));
+            Ok(Value::Object(members))
+        })
+        .collect::<Result<_, error::Unspecified>>()?;
+    response.push((String::from("testGroups"), Value::Array(groups)));
+    Ok(Value::Object(response))
+}
+
+// Calls `f` on each test case of `group` and returns the responses, each the
+// test case's `tcId` followed by the members returned by `f`.
+fn respond_to_tests<F>(group: &Value, mut f: F) -> Result<Vec<Value>, error::Unspecified>
+where
+    F: FnMut(&Value) -> Result<Vec<(&'static str, Value)>, error::Unspecified>,
+{
+    array(group, "tests")?
+        .iter()
+        .map(|test| {
+            let mut members = vec![(String::from("tcId"), get(test, "tcId")?.clone())];
+            for (name, value) in f(test)? {
+                members.push((String::from(name), value));
+            }
+            Ok(Value::Object(members))
+        })
+        .collect()
+}
+
+fn respond_sha(
+    alg: &'static digest::Algorithm, group: &Value,
+) -> Result<Vec<Value>, error::Unspecified> {
+    let test_type = string(group, "testType")?;
+    respond_to_tests(group, |test| {
+        let msg = bytes(test, "msg")?;
+        let msg = msg.get(..bytes_len(test, "len")?).ok_or(error::Unspecified)?;
+        match test_type {
+            "AFT" => Ok(vec![("md", hex_value(digest::digest(alg, msg).as_ref()))]),
+            "MCT" => Ok(vec![("resultsArray", Value::Array(sha_mct(alg, msg)))]),
+            _ => Err(error::Unspecified),
+        }
+    })
+}
+
+// The Monte Carlo test of the ACVP SHA specification: each of the 100
+// results is the last of 1000 digests, each of the previous three, starting
+// from the seed or the previous result.
+fn sha_mct(alg: &'static digest::Algorithm, seed: &[u8]) -> Vec<Value> {
+    let mut md = Vec::from(seed);
+    (0..100)
+        .map(|_| {
+            let mut mds = [md.clone(), md.clone(), md.clone()];
+            for _ in 0..1000 {
+                let mut ctx = digest::Context::new(alg);
+                for md in &mds {
+                    ctx.update(md);
+                }
+                mds.rotate_left(1);
+                mds[2] = Vec::from(ctx.finish().as_ref());
+            }
+            md = mds[2].clone();
+            Value::Object(vec![(String::from("md"), hex_value(&md))])
+        })
+        .collect()
+}
+
+fn respond_hmac(
+    alg: &'static digest::Algorithm, group: &Value,
+) -> Result<Vec<Value>, error::Unspecified> {
+    if string(group, "testType")? != "AFT" {
+        return Err(error::Unspecified);
+    }
+    let _ = bytes_len(group, "keyLen")?;
+    let _ = bytes_len(group, "msgLen")?;
+    let mac_len = bytes_len(group, "macLen")?;
+    respond_to_tests(group, |test| {
+        let key = hmac::SigningKey::new(alg, &bytes(test, "key")?);
+        let mac = hmac::sign(&key, &bytes(test, "msg")?);
+        let mac = mac.as_ref().get(..mac_len).ok_or(error::Unspecified)?;
+        Ok(vec![("mac", hex_value(mac))])
+    })
+}
+
+fn respond_aes_gcm(group: &Value) -> Result<Vec<Value>, error::Unspecified> {
+    let aead_alg = match integer(group, "keyLen")? {
+        128 => &aead::AES_128_GCM,
+        256 => &aead::AES_256_GCM,
+        _ => return Err(error::Unspecified),
+    };
+    if string(group, "testType")? != "AFT"
+        || bytes_len(group, "ivLen")? != aead_alg.nonce_len()
+        || bytes_len(group, "tagLen")? != aead_alg.tag_len()
+    {
+        return Err(error::Unspecified);
+    }
+    if group.get("ivGen").is_some() && string(group, "ivGen")? != "external" {
+        return Err(error::Unspecified);
+    }
+    let _ = bytes_len(group, "payloadLen")?;
+    let _ = bytes_len(group, "aadLen")?;
+    let direction = string(group, "direction")?;
+    respond_to_tests(group, |test| {
+        let key = bytes(test, "key")?;
+        let nonce = bytes(test, "iv")?;
+        let aad = bytes(test, "aad")?;
+        match direction {
+            "encrypt" => {
+                let key = aead::SealingKey::new(aead_alg, &key)?;
+                let mut in_out = bytes(test, "pt")?;
+                let pt_len = in_out.len();
+                in_out.resize(pt_len + aead_alg.tag_len(), 0);
+                let _ = aead::seal_in_place(&key, &nonce, &aad, &mut in_out, aead_alg.tag_len())?;
+                let (ct, tag) = in_out.split_at(pt_len);
+                Ok(vec![("ct", hex_value(ct)), ("tag", hex_value(tag))])
+            },
+            "decrypt" => {
+                let key = aead::OpeningKey::new(aead_alg, &key)?;
+                let mut in_out = bytes(test, "ct")?;
+                in_out.extend_from_slice(&bytes(test, "tag")?);
+                match aead::open_in_place(&key, &nonce, &aad, 0, &mut in_out) {
+                    Ok(pt) => Ok(vec![("pt", hex_value(pt))]),
+                    Err(error::Unspecified) => Ok(vec![("testPassed", Value::Bool(false))]),
+                }
+            },
+            _ => Err(error::Unspecified),
+        }
+    })
+}
+
+fn respond_ecdsa_sig_gen(
+    group: &Value, members: &mut Vec<(String, Value)>, rng: &rand::SecureRandom,
+) -> Result<Vec<Value>, error::Unspecified> {
+    let alg = match (string(group, "curve")?, string(group, "hashAlg")?) {
+        ("P-256", "SHA2-256") => &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+        ("P-384", "SHA2-384") => &signature::ECDSA_P384_SHA384_FIXED_SIGNING,
+        _ => return Err(error::Unspecified),
+    };
+    if let Some(Value::Bool(true)) = group.get("componentTest") {
+        return Err(error::Unspecified);
+    }
+
+    // Each group has a key of its own.
+    let pkcs8 = signature::ECDSAKeyPair::generate_pkcs8(alg, rng)?;
+    let key_pair = signature::ECDSAKeyPair::from_pkcs8(alg, untrusted::Input::from(pkcs8.as_ref()))
+        .map_err(|_| error::Unspecified)?;
+    let public_key = key_pair.public_key();
+    let (qx, qy) = public_key.as_ref()[1..].split_at((public_key.as_ref().len() - 1) / 2);
+    members.push((String::from("qx"), hex_value(qx)));
+    members.push((String::from("qy"), hex_value(qy)));
+
+    respond_to_tests(group, |test| {
+        let msg = bytes(test, "message")?;
+        let sig = key_pair.sign(untrusted::Input::from(&msg), rng)?;
+        let (r, s) = sig.as_ref().split_at(sig.as_ref().len() / 2);
+        Ok(vec![("r", hex_value(r)), ("s", hex_value(s))])
+    })
+}
+
+fn respond_ecdsa_sig_ver(group: &Value) -> Result<Vec<Value>, error::Unspecified> {
+    let (alg, scalar_len): (&signature::ECDSAVerification, _) =
+        match (string(group, "curve")?, string(group, "hashAlg")?) {
+            ("P-256", "SHA2-256") => (&signature::ECDSA_P256_SHA256_ASN1, 32),
+            ("P-256", "SHA2-384") => (&signature::ECDSA_P256_SHA384_ASN1, 32),
+            ("P-384", "SHA2-256") => (&signature::ECDSA_P384_SHA256_ASN1, 48),
+            ("P-384", "SHA2-384") => (&signature::ECDSA_P384_SHA384_ASN1, 48),
+            _ => return Err(error::Unspecified),
+        };
+    respond_to_tests(group, |test| {
+        let msg = bytes(test, "message")?;
+        let mut public_key = vec![4];
+        let passed = append_padded(&mut public_key, &bytes(test, "qx")?, scalar_len)
+            .and_then(|()| append_padded(&mut public_key, &bytes(test, "qy")?, scalar_len))
+            .and_then(|()| {
+                let sig = der_signature(&bytes(test, "r")?, &bytes(test, "s")?);
+                signature::verify(
+                    alg,
+                    untrusted::Input::from(&public_key),
+                    untrusted::Input::from(&msg),
+                    untrusted::Input::from(&sig),
+                )
+            })
+            .is_ok();
+        Ok(vec![("testPassed", Value::Bool(passed))])
+    })
+}
+
+// Appends `value`, a big-endian integer, to `out` as exactly `len` bytes.
+fn append_padded(out: &mut Vec<u8>, value: &[u8], len: usize) -> Result<(), error::Unspecified> {
+    let value = strip_leading_zeros(value);
+    let padding = len.checked_sub(value.len()).ok_or(error::Unspecified)?;
+    out.resize(out.len() + padding, 0);
+    out.extend_from_slice(value);
+    Ok(())
+}
+
+// Encodes `r` and `s` as an `Ecdsa-Sig-Value`, so that the ASN.1 verification
+// algorithms, which cover every combination of curve and digest, can be used.
+// Both are at most 48 bytes long, so every length fits in one byte.
+fn der_signature(r: &[u8], s: &[u8]) -> Vec<u8> {
+    fn integer(out: &mut Vec<u8>, value: &[u8]) {
+        let value = strip_leading_zeros(value);
+        let needs_zero = value.first().map_or(true, |b| b & 0x80 != 0);
+        out.push(0x02);
+        out.push((value.len() + usize::from(needs_zero)) as u8);
+        if needs_zero {
+            out.push(0);
+        }
+        out.extend_from_slice(value);
+    }
+    let mut value = Vec::new();
+    integer(&mut value, r);
+    integer(&mut value, s);
+    let mut sig = vec![0x30, value.len() as u8];
+    sig.extend_from_slice(&value);
+    sig
+}
+
+fn respond_rsa_sig_ver(group: &Value) -> Result<Vec<Value>, error::Unspecified> {
+    let hash_alg = string(group, "hashAlg")?;
+    let params = match (string(group, "sigType")?, hash_alg) {
+        ("pkcs1v1.5", "SHA-1") => &signature::RSA_PKCS1_2048_8192_SHA1,
+        ("pkcs1v1.5", "SHA2-256") => &signature::RSA_PKCS1_2048_8192_SHA256,
+        ("pkcs1v1.5", "SHA2-384") => &signature::RSA_PKCS1_2048_8192_SHA384,
+        ("pkcs1v1.5", "SHA2-512") => &signature::RSA_PKCS1_2048_8192_SHA512,
+        ("pss", "SHA2-256") => &signature::RSA_PSS_2048_8192_SHA256,
+        ("pss", "SHA2-384") => &signature::RSA_PSS_2048_8192_SHA384,
+        ("pss", "SHA2-512") => &signature::RSA_PSS_2048_8192_SHA512,
+        _ => return Err(error::Unspecified),
+    };
+    if string(group, "sigType")? == "pss"
+        && integer(group, "saltLen")? != digest_alg(hash_alg)?.output_len
+    {
+        return Err(error::Unspecified);
+    }
+    if integer(group, "modulo")? < 2048 {
+        return Err(error::Unspecified);
+    }
+    respond_to_tests(group, |test| {
+        // Older revisions have the public key in the group and newer ones
+        // have it in each test case.
+        let key = if test.get("n").is_some() { test } else { group };
+        let n = bytes(key, "n")?;
+        let e = bytes(key, "e")?;
+        let msg = bytes(test, "message")?;
+        let sig = bytes(test, "signature")?;
+        let passed = signature::primitive::verify_rsa(
+            params,
+            (
+                untrusted::Input::from(strip_leading_zeros(&n)),
+                untrusted::Input::from(strip_leading_zeros(&e)),
+            ),
+            untrusted::Input::from(&msg),
+            untrusted::Input::from(&sig),
+        )
+        .is_ok();
+        Ok(vec![("testPassed", Value::Bool(passed))])
+    })
+}
+
+fn digest_alg(name: &str) -> Result<&'static digest::Algorithm, error::Unspecified> {
+    match name {
+        "SHA-1" => Ok(&digest::SHA1),
+        "SHA2-256" => Ok(&digest::SHA256),
+        "SHA2-384" => Ok(&digest::SHA384),
+        "SHA2-512" => Ok(&digest::SHA512),
+        "SHA2-512/256" => Ok(&digest::SHA512_256),
+        _ => Err(error::Unspecified),
+    }
+}
+
+fn strip_leading_zeros(value: &[u8]) -> &[u8] {
+    &value[value.iter().take_while(|b| **b == 0).count()..]
+}
+
+fn hex_value(value: &[u8]) -> Value { Value::String(hex::encode(value).to_ascii_uppercase()) }
+
+fn get<'a>(value: &'a Value, name: &str) -> Result<&'a Value, error::Unspecified> {
+    value.get(name).ok_or(error::Unspecified)
+}
+
+fn string<'a>(value: &'a Value, name: &str) -> Result<&'a str, error::Unspecified> {
+    match get(value, name)? {
+        Value::String(s) => Ok(s),
+        _ => Err(error::Unspecified),
+    }
+}
+
+fn array<'a>(value: &'a Value, name: &str) -> Result<&'a [Value], error::Unspecified> {
+    match get(value, name)? {
+        Value::Array(elements) => Ok(elements),
+        _ => Err(error::Unspecified),
+    }
+}
+
+fn integer(value: &Value, name: &str) -> Result<usize, error::Unspecified> {
+    match get(value, name)? {
+        Value::Number(n) => n.parse().map_err(|_| error::Unspecified),
+        _ => Err(error::Unspecified),
+    }
+}
+
+fn bytes(value: &Value, name: &str) -> Result<Vec<u8>, error::Unspecified> {
+    hex::decode(string(value, name)?)
+}
+
+// Returns a length in bits, which must be a whole number of bytes, in bytes.
+fn bytes_len(value: &Value, name: &str) -> Result<usize, error::Unspecified> {
+    let bits = integer(value, name)?;
+    if bits % 8 != 0 {
+        return Err(error::Unspecified);
+    }
+    Ok(bits / 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_respond() {
+        let path = test::ring_src_path();
+        let request = std::fs::read_to_string(path.join("src/test_acvp_request.json")).unwrap();
+        let expected = std::fs::read_to_string(path.join("src/test_acvp_response.json")).unwrap();
+        let rng = test::rand::FixedByteRandom { byte: 1 };
+        let response = respond(&request, &rng).unwrap();
+        assert_eq!(json::parse(&response).unwrap(), json::parse(&expected).unwrap());
+    }
+
+    #[test]
+    fn test_respond_ecdsa_sig_gen() {
+        let request = "{\"vsId\":1,\"algorithm\":\"ECDSA\",\"mode\":\"sigGen\",\
+                       \"revision\":\"FIPS186-4\",\"testGroups\":[{\"tgId\":1,\
+                       \"testType\":\"AFT\",\"curve\":\"P-384\",\"hashAlg\":\"SHA2-384\",\
+                       \"componentTest\":false,\"tests\":[{\"tcId\":1,\"message\":\"\"},\
+                       {\"tcId\":2,\"message\":\"616263\"}]}]}";
+        let rng = rand::SystemRandom::new();
+        let response = json::parse(&respond(request, &rng).unwrap()).unwrap();
+        let group = &array(&response, "testGroups").unwrap()[0];
+        let mut public_key = vec![4];
+        public_key.extend_from_slice(&bytes(group, "qx").unwrap());
+        public_key.extend_from_slice(&bytes(group, "qy").unwrap());
+        for (test, msg) in array(group, "tests").unwrap().iter().zip(&[&b""[..], b"abc"]) {
+            let mut sig = bytes(test, "r").unwrap();
+            sig.extend_from_slice(&bytes(test, "s").unwrap());
+            assert!(signature::verify(
+                &signature::ECDSA_P384_SHA384_FIXED,
+                untrusted::Input::from(&public_key),
+                untrusted::Input::from(msg),
+                untrusted::Input::from(&sig),
+            )
+            .is_ok());
+        }
+    }
+
+    #[test]
+    fn test_respond_unsupported() {
+        let rng = test::rand::FixedByteRandom { byte: 1 };
+        for request in &[
+            "{\"algorithm\":\"SHA2-224\",\"testGroups\":[]}",
+            "{\"algorithm\":\"ECDSA\",\"mode\":\"keyGen\",\"testGroups\":[]}",
+            "{\"algorithm\":\"SHA2-256\",\"testGroups\":[{\"tgId\":1,\"testType\":\"AFT\",\
+             \"tests\":[{\"tcId\":1,\"msg\":\"00\",\"len\":1}]}]}",
+            "{\"algorithm\":\"ACVP-AES-GCM\",\"testGroups\":[{\"tgId\":1,\"testType\":\"AFT\",\
+             \"direction\":\"encrypt\",\"keyLen\":192,\"ivLen\":96,\"tagLen\":128,\
+             \"payloadLen\":0,\"aadLen\":0,\"tests\":[]}]}",
+            "[{\"acvVersion\":\"1.0\"},{\"algorithm\":\"SHA2-256\"}]",
+        ] {
+            assert!(respond(request, &rng).is_err(), "{}", request);
+        }
+    }
+}
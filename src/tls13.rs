@@ -0,0 +1,539 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The TLS 1.3 key schedule ([RFC 8446 Section 7]).
+//!
+//! A `KeySchedule` starts at the Early Secret, derived from the PSK if there
+//! is one. `derive_handshake_secret()` mixes in the (EC)DHE shared secret
+//! and `derive_master_secret()` advances to the Master Secret; each secret
+//! derived along the way is only available at its stage, and fails with
+//! `error::Unspecified` otherwise, or if the transcript hash wasn't computed
+//! with the key schedule's hash.
+//!
+//! ```
+//! use ring::{aead, digest, tls13};
+//!
+//! # fn handshake() -> Result<(), ring::error::Unspecified> {
+//! # let shared_secret = [0x42; 32];
+//! let mut transcript = digest::Context::new(&digest::SHA256);
+//! # transcript.update(b"ClientHello ServerHello");
+//! let mut key_schedule = tls13::KeySchedule::new(&digest::SHA256, None);
+//! key_schedule.derive_handshake_secret(Some(&shared_secret))?;
+//! let server_handshake_traffic_secret =
+//!     key_schedule.server_handshake_traffic_secret(&transcript.clone().finish())?;
+//! let traffic_key = server_handshake_traffic_secret.traffic_key(&aead::AES_128_GCM);
+//! let nonce = traffic_key.nonce(0);
+//! # let _ = nonce;
+//!
+//! // [...]
+//! # transcript.update(b"EncryptedExtensions Certificate CertificateVerify");
+//! let verify_data =
+//!     server_handshake_traffic_secret.finished_verify_data(&transcript.clone().finish())?;
+//! # transcript.update(verify_data.as_ref());
+//!
+//! key_schedule.derive_master_secret()?;
+//! let client_application_traffic_secret =
+//!     key_schedule.client_application_traffic_secret(&transcript.clone().finish())?;
+//! let next = client_application_traffic_secret.next();
+//! # let _ = next;
+//! # Ok(())
+//! # }
+//! # fn main() { handshake().unwrap() }
+//! ```
+//!
+//! [RFC 8446 Section 7]: https://tools.ietf.org/html/rfc8446#section-7
+
+use crate::{aead, constant_time, digest, error, hkdf, hmac, zeroize};
+
+/// The length of the per-record nonces, and of the IVs they are derived
+/// from.
+pub const NONCE_LEN: usize = 12;
+
+/// The kind of PSK that a binder key is for.
+#[derive(Clone, Copy)]
+pub enum PskKind {
+    /// A PSK that was established outside of TLS.
+    External,
+
+    /// A PSK from an earlier connection's `NewSessionTicket`.
+    Resumption,
+}
+
+/// The state of the key schedule of one connection.
+pub struct KeySchedule {
+    stage: Stage,
+    secret: Secret,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Early,
+    Handshake,
+    Master,
+}
+
+impl KeySchedule {
+    /// Derives the Early Secret from `psk`, or from zeros if the handshake
+    /// doesn't use a PSK.
+    pub fn new(digest_algorithm: &'static digest::Algorithm, psk: Option<&[u8]>) -> Self {
+        let zeros = [0; digest::MAX_OUTPUT_LEN];
+        let ikm = psk.unwrap_or(&zeros[..digest_algorithm.output_len]);
+        Self {
+            stage: Stage::Early,
+            secret: Secret::extract(digest_algorithm, &[], ikm),
+        }
+    }
+
+    /// The hash of the cipher suite.
+    #[inline]
+    pub fn digest_algorithm(&self) -> &'static digest::Algorithm { self.secret.digest_algorithm }
+
+    /// The key that the PSK binders are computed with, as finished keys are;
+    /// see `Secret::finished_verify_data()`.
+    pub fn binder_key(&self, kind: PskKind) -> Result<Secret, error::Unspecified> {
+        let label: &[u8] = match kind {
+            PskKind::External => b"ext binder",
+            PskKind::Resumption => b"res binder",
+        };
+        let empty = digest::digest(self.digest_algorithm(), &[]);
+        self.derive_secret(Stage::Early, label, &empty)
+    }
+
+    /// `client_early_traffic_secret`, given the hash of the ClientHello.
+    pub fn client_early_traffic_secret(
+        &self, transcript_hash: &digest::Digest,
+    ) -> Result<Secret, error::Unspecified> {
+        self.derive_secret(Stage::Early, b"c e traffic", transcript_hash)
+    }
+
+    /// `early_exporter_master_secret`, given the hash of the ClientHello.
+    pub fn early_exporter_master_secret(
+        &self, transcript_hash: &digest::Digest,
+    ) -> Result<ExporterMasterSecret, error::Unspecified> {
+        Ok(ExporterMasterSecret(self.derive_secret(
+            Stage::Early,
+            b"e exp master",
+            transcript_hash,
+        )?))
+    }
+
+    /// Advances from the Early Secret to the Handshake Secret, mixing in the
+    /// (EC)DHE shared secret, or zeros for `psk_ke` handshakes.
+    pub fn derive_handshake_secret(
+        &mut self, shared_secret: Option<&[u8]>,
+    ) -> Result<(), error::Unspecified> {
+        if self.stage != Stage::Early {
+            return Err(error::Unspecified);
+        }
+        let zeros = [0; digest::MAX_OUTPUT_LEN];
+        let ikm = shared_secret.unwrap_or(&zeros[..self.secret.len()]);
+        self.secret = self.secret.derive_next_stage(ikm);
+        self.stage = Stage::Handshake;
+        Ok(())
+    }
+
+    /// `client_handshake_traffic_secret`, given the hash of the transcript
+    /// through the ServerHello.
+    pub fn client_handshake_traffic_secret(
+        &self, transcript_hash: &digest::Digest,
+    ) -> Result<Secret, error::Unspecified> {
+        self.derive_secret(Stage::Handshake, b"c hs traffic", transcript_hash)
+    }
+
+    /// `server_handshake_traffic_secret`, given the hash of the transcript
+    /// through the ServerHello.
+    pub fn server_handshake_traffic_secret(
+        &self, transcript_hash: &digest::Digest,
+    ) -> Result<Secret, error::Unspecified> {
+        self.derive_secret(Stage::Handshake, b"s hs traffic", transcript_hash)
+    }
+
+    /// Advances from the Handshake Secret to the Master Secret.
+    pub fn derive_master_secret(&mut self) -> Result<(), error::Unspecified> {
+        if self.stage != Stage::Handshake {
+            return Err(error::Unspecified);
+        }
+        let zeros = [0; digest::MAX_OUTPUT_LEN];
+        self.secret = self.secret.derive_next_stage(&zeros[..self.secret.len()]);
+        self.stage = Stage::Master;
+        Ok(())
+    }
+
+    /// `client_application_traffic_secret_0`, given the hash of the
+    /// transcript through the server's Finished.
+    pub fn client_application_traffic_secret(
+        &self, transcript_hash: &digest::Digest,
+    ) -> Result<Secret, error::Unspecified> {
+        self.derive_secret(Stage::Master, b"c ap traffic", transcript_hash)
+    }
+
+    /// `server_application_traffic_secret_0`, given the hash of the
+    /// transcript through the server's Finished.
+    pub fn server_application_traffic_secret(
+        &self, transcript_hash: &digest::Digest,
+    ) -> Result<Secret, error::Unspecified> {
+        self.derive_secret(Stage::Master, b"s ap traffic", transcript_hash)
+    }
+
+    /// `exporter_master_secret`, given the hash of the transcript through
+    /// the server's Finished.
+    pub fn exporter_master_secret(
+        &self, transcript_hash: &digest::Digest,
+    ) -> Result<ExporterMasterSecret, error::Unspecified> {
+        Ok(ExporterMasterSecret(self.derive_secret(
+            Stage::Master,
+            b"exp master",
+            transcript_hash,
+        )?))
+    }
+
+    /// `resumption_master_secret`, given the hash of the transcript through
+    /// the client's Finished.
+    pub fn resumption_master_secret(
+        &self, transcript_hash: &digest::Digest,
+    ) -> Result<ResumptionMasterSecret, error::Unspecified> {
+        Ok(ResumptionMasterSecret(self.derive_secret(
+            Stage::Master,
+            b"res master",
+            transcript_hash,
+        )?))
+    }
+
+    // `Derive-Secret(secret, label, messages)`, where `transcript_hash` is
+    // `Transcript-Hash(messages)`.
+    fn derive_secret(
+        &self, stage: Stage, label: &[u8], transcript_hash: &digest::Digest,
+    ) -> Result<Secret, error::Unspecified> {
+        if self.stage != stage || transcript_hash.algorithm() != self.digest_algorithm() {
+            return Err(error::Unspecified);
+        }
+        Ok(self.secret.derive(label, transcript_hash.as_ref()))
+    }
+}
+
+/// A secret of the key schedule, as long as the output of its hash.
+///
+/// The value is available through `as_ref()`, e.g. to log it in the
+/// `SSLKEYLOGFILE` format or to construct a `quic::Secret`.
+pub struct Secret {
+    digest_algorithm: &'static digest::Algorithm,
+    value: [u8; digest::MAX_OUTPUT_LEN],
+}
+
+impl Secret {
+    /// Constructs a secret from its value, which must be as long as the
+    /// output of `digest_algorithm`.
+    pub fn new(
+        digest_algorithm: &'static digest::Algorithm, value: &[u8],
+    ) -> Result<Self, error::Unspecified> {
+        if value.len() != digest_algorithm.output_len {
+            return Err(error::Unspecified);
+        }
+        let mut secret = Self::zero(digest_algorithm);
+        secret.value[..value.len()].copy_from_slice(value);
+        Ok(secret)
+    }
+
+    /// The hash that the secret is used with.
+    #[inline]
+    pub fn digest_algorithm(&self) -> &'static digest::Algorithm { self.digest_algorithm }
+
+    /// Derives the record protection key and IV from a traffic secret
+    /// (RFC 8446 Section 7.3).
+    pub fn traffic_key(&self, aead_algorithm: &'static aead::Algorithm) -> TrafficKey {
+        let prk = self.prk();
+        let mut key = TrafficKey {
+            aead_algorithm,
+            key: [0; MAX_KEY_LEN],
+            iv: [0; NONCE_LEN],
+        };
+        expand_label(&prk, b"key", &[], &mut key.key[..aead_algorithm.key_len()]);
+        expand_label(&prk, b"iv", &[], &mut key.iv);
+        key
+    }
+
+    /// Derives the next application traffic secret from this one, for a
+    /// `KeyUpdate` (RFC 8446 Section 7.2).
+    pub fn next(&self) -> Self { self.derive(b"traffic upd", &[]) }
+
+    /// The `verify_data` of a Finished message, given the hash of the
+    /// transcript before it, using the finished key derived from this
+    /// secret; this is a handshake traffic secret or, for PSK binders, a
+    /// binder key (RFC 8446 Sections 4.4.4 and 4.2.11.2).
+    pub fn finished_verify_data(
+        &self, transcript_hash: &digest::Digest,
+    ) -> Result<hmac::Signature, error::Unspecified> {
+        if transcript_hash.algorithm() != self.digest_algorithm {
+            return Err(error::Unspecified);
+        }
+        let finished_key = self.derive(b"finished", &[]);
+        Ok(hmac::sign(&finished_key.prk(), transcript_hash.as_ref()))
+    }
+
+    /// Verifies the `verify_data` of a Finished message, in constant time.
+    pub fn verify_finished(
+        &self, transcript_hash: &digest::Digest, verify_data: &[u8],
+    ) -> Result<(), error::Unspecified> {
+        let expected = self.finished_verify_data(transcript_hash)?;
+        constant_time::verify_slices_are_equal(expected.as_ref(), verify_data)
+    }
+
+    /// `HKDF-Expand-Label(secret, label, context, out.len())`, for the keys
+    /// that other protocols derive from TLS 1.3 secrets. `label` doesn't
+    /// include the "tls13 " prefix.
+    pub fn expand_label(
+        &self, label: &[u8], context: &[u8], out: &mut [u8],
+    ) -> Result<(), error::Unspecified> {
+        try_expand_label(&self.prk(), label, context, out)
+    }
+
+    fn zero(digest_algorithm: &'static digest::Algorithm) -> Self {
+        Self {
+            digest_algorithm,
+            value: [0; digest::MAX_OUTPUT_LEN],
+        }
+    }
+
+    fn extract(digest_algorithm: &'static digest::Algorithm, salt: &[u8], ikm: &[u8]) -> Self {
+        let salt = hmac::SigningKey::new(digest_algorithm, salt);
+        let prk = hmac::sign(&salt, ikm);
+        let mut secret = Self::zero(digest_algorithm);
+        secret.value[..digest_algorithm.output_len].copy_from_slice(prk.as_ref());
+        secret
+    }
+
+    // `HKDF-Extract(Derive-Secret(secret, "derived", ""), ikm)`.
+    fn derive_next_stage(&self, ikm: &[u8]) -> Self {
+        let empty = digest::digest(self.digest_algorithm, &[]);
+        let derived = self.derive(b"derived", empty.as_ref());
+        Self::extract(self.digest_algorithm, derived.as_ref(), ikm)
+    }
+
+    // `HKDF-Expand-Label(secret, label, context, Hash.length)`, which is
+    // `Derive-Secret()` when `context` is a transcript hash.
+    fn derive(&self, label: &[u8], context: &[u8]) -> Self {
+        let mut secret = Self::zero(self.digest_algorithm);
+        let len = self.len();
+        expand_label(&self.prk(), label, context, &mut secret.value[..len]);
+        secret
+    }
+
+    fn len(&self) -> usize { self.digest_algorithm.output_len }
+
+    fn prk(&self) -> hmac::SigningKey {
+        hmac::SigningKey::new(self.digest_algorithm, self.as_ref())
+    }
+}
+
+impl AsRef<[u8]> for Secret {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.value[..self.len()] }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) { zeroize::zeroize(&mut self.value); }
+}
+
+#[cfg(feature = "zeroize")]
+impl ::zeroize::ZeroizeOnDrop for Secret {}
+
+/// `exporter_master_secret` or `early_exporter_master_secret`.
+pub struct ExporterMasterSecret(Secret);
+
+impl ExporterMasterSecret {
+    /// `TLS-Exporter(label, context, out.len())` (RFC 8446 Section 7.5).
+    /// Exporters without a context use an empty one.
+    pub fn export(
+        &self, label: &[u8], context: &[u8], out: &mut [u8],
+    ) -> Result<(), error::Unspecified> {
+        let digest_algorithm = self.0.digest_algorithm;
+        let empty = digest::digest(digest_algorithm, &[]);
+        let mut secret = Secret::zero(digest_algorithm);
+        let len = secret.len();
+        try_expand_label(&self.0.prk(), label, empty.as_ref(), &mut secret.value[..len])?;
+        let context_hash = digest::digest(digest_algorithm, context);
+        secret.expand_label(b"exporter", context_hash.as_ref(), out)
+    }
+}
+
+/// `resumption_master_secret`.
+pub struct ResumptionMasterSecret(Secret);
+
+impl ResumptionMasterSecret {
+    /// The PSK of the ticket of a `NewSessionTicket` with `ticket_nonce`
+    /// (RFC 8446 Section 4.6.1).
+    pub fn psk(&self, ticket_nonce: &[u8]) -> Result<Secret, error::Unspecified> {
+        let mut psk = Secret::zero(self.0.digest_algorithm);
+        let len = psk.len();
+        try_expand_label(&self.0.prk(), b"resumption", ticket_nonce, &mut psk.value[..len])?;
+        Ok(psk)
+    }
+}
+
+/// A record protection key and IV.
+pub struct TrafficKey {
+    aead_algorithm: &'static aead::Algorithm,
+    key: [u8; MAX_KEY_LEN],
+    iv: [u8; NONCE_LEN],
+}
+
+impl TrafficKey {
+    /// The key for protecting records.
+    pub fn sealing_key(&self) -> Result<aead::SealingKey, error::Unspecified> {
+        aead::SealingKey::new(self.aead_algorithm, self.key_bytes())
+    }
+
+    /// The key for removing record protection.
+    pub fn opening_key(&self) -> Result<aead::OpeningKey, error::Unspecified> {
+        aead::OpeningKey::new(self.aead_algorithm, self.key_bytes())
+    }
+
+    /// The nonce of the record with `sequence_number`: the IV XORed with the
+    /// sequence number (RFC 8446 Section 5.3).
+    pub fn nonce(&self, sequence_number: u64) -> [u8; NONCE_LEN] {
+        xor_nonce(&self.iv, sequence_number)
+    }
+
+    fn key_bytes(&self) -> &[u8] { &self.key[..self.aead_algorithm.key_len()] }
+}
+
+impl Drop for TrafficKey {
+    fn drop(&mut self) {
+        zeroize::zeroize(&mut self.key);
+        zeroize::zeroize(&mut self.iv);
+    }
+}
+
+/// `iv` XORed with `counter`, left-padded with zeros.
+pub(crate) fn xor_nonce(iv: &[u8; NONCE_LEN], counter: u64) -> [u8; NONCE_LEN] {
+    let mut nonce = *iv;
+    for (n, c) in nonce[(NONCE_LEN - 8)..].iter_mut().zip(counter.to_be_bytes().iter()) {
+        *n ^= *c;
+    }
+    nonce
+}
+
+/// `HKDF-Expand-Label(secret, label, context, out.len())` (RFC 8446 Section
+/// 7.1), where `label` doesn't include the "tls13 " prefix.
+///
+/// # Panics
+///
+/// `expand_label` panics if `try_expand_label` would fail.
+pub(crate) fn expand_label(
+    secret: &hmac::SigningKey, label: &[u8], context: &[u8], out: &mut [u8],
+) {
+    try_expand_label(secret, label, context, out).unwrap()
+}
+
+/// Like `expand_label`, but fails if the label or context is longer than 255
+/// bytes with the prefix, or if the output is too long for HKDF or for its
+/// 16-bit length.
+pub(crate) fn try_expand_label(
+    secret: &hmac::SigningKey, label: &[u8], context: &[u8], out: &mut [u8],
+) -> Result<(), error::Unspecified> {
+    const PREFIX: &[u8] = b"tls13 ";
+    if PREFIX.len() + label.len() > 255 || context.len() > 255 || out.len() > 0xffff {
+        return Err(error::Unspecified);
+    }
+    let mut info = [0; 2 + 1 + 255 + 1 + 255];
+    let mut info_len = 0;
+    for part in &[
+        &(out.len() as u16).to_be_bytes()[..],
+        &[(PREFIX.len() + label.len()) as u8],
+        PREFIX,
+        label,
+        &[context.len() as u8],
+        context,
+    ] {
+        info[info_len..][..part.len()].copy_from_slice(part);
+        info_len += part.len();
+    }
+    hkdf::try_expand(secret, &info[..info_len], out)
+}
+
+const MAX_KEY_LEN: usize = 32;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    fn from_hex(hex: &str) -> Vec<u8> { test::from_hex(hex).unwrap() }
+
+    // RFC 8448 Section 3, "Simple 1-RTT Handshake".
+    #[test]
+    fn test_simple_1rtt_handshake() {
+        let mut key_schedule = KeySchedule::new(&digest::SHA256, None);
+        assert_eq!(
+            key_schedule.secret.as_ref(),
+            &from_hex("33ad0a1c607ec03b09e6cd9893680ce210adf300aa1f2660e1b22e10f170f92a")[..]
+        );
+
+        let shared_secret =
+            from_hex("8bd4054fb55b9d63fdfbacf9f04b9f0d35e6d63f537563efd46272900f89492d");
+        key_schedule.derive_handshake_secret(Some(&shared_secret)).unwrap();
+        assert_eq!(
+            key_schedule.secret.as_ref(),
+            &from_hex("1dc826e93606aa6fdc0aadc12f741b01046aa6b99f691ed221a9f0ca043fbeac")[..]
+        );
+
+        let hello_hash =
+            from_hex("860c06edc07858ee8e78f0e7428c58edd6b43f2ca3e6e95f02ed063cf0e1cad8");
+        let client = key_schedule.secret.derive(b"c hs traffic", &hello_hash);
+        assert_eq!(
+            client.as_ref(),
+            &from_hex("b3eddb126e067f35a780b3abf45e2d8f3b1a950738f52e9600746a0e27a55a21")[..]
+        );
+        let server = key_schedule.secret.derive(b"s hs traffic", &hello_hash);
+        assert_eq!(
+            server.as_ref(),
+            &from_hex("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38")[..]
+        );
+        let traffic_key = server.traffic_key(&aead::AES_128_GCM);
+        assert_eq!(traffic_key.key_bytes(), &from_hex("3fce516009c21727d0f2e4e86ee403bc")[..]);
+        assert_eq!(&traffic_key.iv[..], &from_hex("5d313eb2671276ee13000b30")[..]);
+        let finished_key = server.derive(b"finished", &[]);
+        assert_eq!(
+            finished_key.as_ref(),
+            &from_hex("008d3b66f816ea559f96b537e885c31fc068bf492c652f01f288a1d8cdc19fc8")[..]
+        );
+        let verify_data = hmac::sign(
+            &finished_key.prk(),
+            &from_hex("edb7725fa7a3473b031ec8ef65a2485493900138a2b91291407d7951a06110ed"),
+        );
+        assert_eq!(
+            verify_data.as_ref(),
+            &from_hex("9b9b141d906337fbd2cbdce71df4deda4ab42c309572cb7fffee5454b78f0718")[..]
+        );
+
+        key_schedule.derive_master_secret().unwrap();
+        assert_eq!(
+            key_schedule.secret.as_ref(),
+            &from_hex("18df06843d13a08bf2a449844c5f8a478001bc4d4c627984d5a41da8d0402919")[..]
+        );
+
+        let resumption_master_secret = ResumptionMasterSecret(key_schedule.secret.derive(
+            b"res master",
+            &from_hex("209145a96ee8e2a122ff810047cc952684658d6049e86429426db87c54ad143d"),
+        ));
+        assert_eq!(
+            resumption_master_secret.0.as_ref(),
+            &from_hex("7df235f2031d2a051287d02b0241b0bfdaf86cc856231f2d5aba46c434ec196c")[..]
+        );
+        assert_eq!(
+            resumption_master_secret.psk(&[0, 0]).unwrap().as_ref(),
+            &from_hex("4ecd0eb6ec3b4d87f5d6028f922ca4c5851a277fd41311c9e62d2c9492e1c4f3")[..]
+        );
+    }
+}
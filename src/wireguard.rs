@@ -0,0 +1,795 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The cryptography of [WireGuard]: the `Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s`
+//! handshake, the transport data messages that it keys, and the cookie
+//! MACs that protect responders under load.
+//!
+//! This module builds and checks the messages; the caller keeps the rest of
+//! the protocol's state: the peers' indices, the timers, the replay windows,
+//! and the TAI64N timestamps, which the responder must check are newer than
+//! the last one it accepted from the peer.
+//!
+//! * The handshake: `initiate()` writes an initiation message for a peer and
+//!   returns the `InitiatorHandshake`; the responder checks it with
+//!   `consume_initiation()`, which returns the initiator's static public
+//!   key and timestamp, and writes the response with `Initiation::respond()`;
+//!   the initiator checks the response with
+//!   `InitiatorHandshake::consume_response()`. Each side then has the
+//!   `TransportKeys` of the session.
+//! * The cookies: a responder under load doesn't answer messages whose `mac2`
+//!   isn't `MAC(cookie, ...)`, where `cookie()` is a MAC of the source
+//!   address of the message, and replies with the cookie instead, encrypted
+//!   with XChaCha20-Poly1305 by `create_cookie_reply()`. The initiator
+//!   decrypts it with `consume_cookie_reply()`, and sets `mac2` of the
+//!   messages that it resends with `set_mac2()`.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{rand, wireguard};
+//!
+//! # fn handshake() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let alice = wireguard::StaticKeyPair::generate(&rng)?;
+//! let bob = wireguard::StaticKeyPair::generate(&rng)?;
+//! let preshared_key = [0; wireguard::KEY_LEN];
+//! let timestamp = [0x40, 0, 0, 0, 0x5f, 0x5e, 0x10, 0, 0, 0, 0, 0];
+//!
+//! let mut initiation = [0; wireguard::INITIATION_LEN];
+//! let handshake = wireguard::initiate(
+//!     &alice, bob.public_key(), &preshared_key, 1, &timestamp, &mut initiation, &rng,
+//! )?;
+//!
+//! let received = wireguard::consume_initiation(&bob, &initiation)?;
+//! assert_eq!(received.peer_public_key(), alice.public_key());
+//! let mut response = [0; wireguard::RESPONSE_LEN];
+//! let mut bob_keys = received.respond(&preshared_key, 2, &mut response, &rng)?;
+//!
+//! let alice_keys = handshake.consume_response(&alice, &response)?;
+//! let mut msg = bob_keys.seal(b"hello, Alice\0\0\0\0")?;
+//! let (counter, packet) = alice_keys.open(&mut msg)?;
+//! assert_eq!((counter, &packet[..]), (0, &b"hello, Alice\0\0\0\0"[..]));
+//! # Ok(())
+//! # }
+//! # fn main() { handshake().unwrap() }
+//! ```
+//!
+//! [WireGuard]: https://www.wireguard.com/papers/wireguard.pdf
+
+use crate::{
+    aead::{self, chacha},
+    agreement, blake2s, constant_time, ec, error,
+    polyfill::{self, convert::*},
+    rand,
+    zeroize::Zeroizing,
+};
+use alloc::vec::Vec;
+use untrusted;
+
+/// The length of a key: a static or ephemeral private key, or a preshared
+/// key.
+pub const KEY_LEN: usize = 32;
+
+/// The length of a static or ephemeral public key.
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// The length of a TAI64N timestamp.
+pub const TIMESTAMP_LEN: usize = 12;
+
+/// The length of a cookie.
+pub const COOKIE_LEN: usize = 16;
+
+/// The length of a handshake initiation message.
+pub const INITIATION_LEN: usize = 148;
+
+/// The length of a handshake response message.
+pub const RESPONSE_LEN: usize = 92;
+
+/// The length of a cookie reply message.
+pub const COOKIE_REPLY_LEN: usize = 64;
+
+/// The length of the header of a transport data message.
+pub const TRANSPORT_HEADER_LEN: usize = 16;
+
+/// The length of the tag that follows the packet in a transport data
+/// message.
+pub const TAG_LEN: usize = 16;
+
+/// The number of transport data messages that may be sent or received with
+/// the same `TransportKeys`, `REJECT-AFTER-MESSAGES`.
+pub const REJECT_AFTER_MESSAGES: u64 = u64::max_value() - (1 << 13);
+
+const CONSTRUCTION: &[u8] = b"Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s";
+const IDENTIFIER: &[u8] = b"WireGuard v1 zx2c4 Jason@zx2c4.com";
+const LABEL_MAC1: &[u8] = b"mac1----";
+const LABEL_COOKIE: &[u8] = b"cookie--";
+
+const INITIATION_TYPE: u8 = 1;
+const RESPONSE_TYPE: u8 = 2;
+const COOKIE_REPLY_TYPE: u8 = 3;
+const TRANSPORT_TYPE: u8 = 4;
+
+const HASH_LEN: usize = blake2s::OUTPUT_LEN;
+const MAC_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// A static X25519 key pair.
+pub struct StaticKeyPair {
+    private_key: ec::PrivateKey,
+    public_key: [u8; PUBLIC_KEY_LEN],
+
+    // `HASH(LABEL_MAC1 || public_key)` and `HASH(LABEL_COOKIE || public_key)`.
+    mac1_key: [u8; HASH_LEN],
+    cookie_key: [u8; HASH_LEN],
+}
+
+impl StaticKeyPair {
+    /// Generates a new key pair.
+    pub fn generate(rng: &rand::SecureRandom) -> Result<Self, error::Unspecified> {
+        let private_key = ec::PrivateKey::generate(agreement::X25519.i.curve, rng)?;
+        Self::new(private_key)
+    }
+
+    /// Constructs a key pair from the 32-byte private key.
+    pub fn from_private_key(private_key: &[u8]) -> Result<Self, error::Unspecified> {
+        let private_key = untrusted::Input::from(private_key);
+        let private_key = ec::PrivateKey::from_bytes(agreement::X25519.i.curve, private_key)?;
+        Self::new(private_key)
+    }
+
+    fn new(private_key: ec::PrivateKey) -> Result<Self, error::Unspecified> {
+        let mut public_key = [0; PUBLIC_KEY_LEN];
+        private_key.compute_public_key(agreement::X25519.i.curve, &mut public_key)?;
+        Ok(Self {
+            private_key,
+            public_key,
+            mac1_key: hash(&[LABEL_MAC1, &public_key]),
+            cookie_key: hash(&[LABEL_COOKIE, &public_key]),
+        })
+    }
+
+    /// The public key.
+    #[inline]
+    pub fn public_key(&self) -> &[u8; PUBLIC_KEY_LEN] { &self.public_key }
+}
+
+/// The initiator's state between the initiation and the response.
+pub struct InitiatorHandshake {
+    state: SymmetricState,
+    ephemeral_private_key: ec::PrivateKey,
+    preshared_key: Zeroizing<[u8; KEY_LEN]>,
+    sender_index: u32,
+}
+
+/// Writes the handshake initiation message to `out` for the peer whose
+/// static public key is `peer_public_key`, with the preshared key
+/// `preshared_key`, which is all zeros if the peers have none.
+///
+/// `sender_index` is the initiator's index for the session, and `timestamp`
+/// is the current TAI64N time. `mac2` is zero; see `set_mac2()`.
+pub fn initiate(
+    local: &StaticKeyPair, peer_public_key: &[u8; PUBLIC_KEY_LEN],
+    preshared_key: &[u8; KEY_LEN], sender_index: u32, timestamp: &[u8; TIMESTAMP_LEN],
+    out: &mut [u8; INITIATION_LEN], rng: &rand::SecureRandom,
+) -> Result<InitiatorHandshake, error::Unspecified> {
+    let ephemeral_private_key = ec::PrivateKey::generate(agreement::X25519.i.curve, rng)?;
+    let mut state = SymmetricState::new(peer_public_key);
+
+    polyfill::slice::fill(&mut out[..], 0);
+    out[0] = INITIATION_TYPE;
+    out[4..8].copy_from_slice(&sender_index.to_le_bytes());
+
+    let (ephemeral_public_key, rest) = out[8..].split_at_mut(PUBLIC_KEY_LEN);
+    ephemeral_private_key.compute_public_key(agreement::X25519.i.curve, ephemeral_public_key)?;
+    state.mix_hash(ephemeral_public_key);
+    state.mix_key(ephemeral_public_key);
+
+    let key = state.mix_key_and_get(&*dh(&ephemeral_private_key, peer_public_key)?);
+    let (encrypted_static, rest) = rest.split_at_mut(PUBLIC_KEY_LEN + TAG_LEN);
+    encrypted_static[..PUBLIC_KEY_LEN].copy_from_slice(&local.public_key);
+    state.encrypt_and_hash(&key, encrypted_static)?;
+
+    let key = state.mix_key_and_get(&*dh(&local.private_key, peer_public_key)?);
+    let encrypted_timestamp = &mut rest[..(TIMESTAMP_LEN + TAG_LEN)];
+    encrypted_timestamp[..TIMESTAMP_LEN].copy_from_slice(timestamp);
+    state.encrypt_and_hash(&key, encrypted_timestamp)?;
+
+    set_mac1(&hash(&[LABEL_MAC1, peer_public_key]), &mut out[..]);
+
+    Ok(InitiatorHandshake {
+        state,
+        ephemeral_private_key,
+        preshared_key: Zeroizing::new(*preshared_key),
+        sender_index,
+    })
+}
+
+impl InitiatorHandshake {
+    /// Checks the handshake response message `msg`, which must be to this
+    /// handshake, and returns the keys of the session.
+    ///
+    /// The handshake isn't consumed, so that a forged response doesn't
+    /// prevent the real one from being accepted.
+    pub fn consume_response(
+        &self, local: &StaticKeyPair, msg: &[u8],
+    ) -> Result<TransportKeys, error::Unspecified> {
+        if msg.len() != RESPONSE_LEN || msg[..4] != [RESPONSE_TYPE, 0, 0, 0] {
+            return Err(error::Unspecified);
+        }
+        check_mac1(&local.mac1_key, msg)?;
+        let peer_index = u32_from_le(&msg[4..8]);
+        if u32_from_le(&msg[8..12]) != self.sender_index {
+            return Err(error::Unspecified);
+        }
+
+        let mut state = self.state.clone();
+        let ephemeral_public_key: &[u8; PUBLIC_KEY_LEN] = msg[12..44].try_into_().unwrap();
+        state.mix_hash(ephemeral_public_key);
+        state.mix_key(ephemeral_public_key);
+        state.mix_key(&*dh(&self.ephemeral_private_key, ephemeral_public_key)?);
+        state.mix_key(&*dh(&local.private_key, ephemeral_public_key)?);
+        let key = state.mix_preshared_key(&self.preshared_key);
+
+        let mut encrypted_nothing = [0; TAG_LEN];
+        encrypted_nothing.copy_from_slice(&msg[44..60]);
+        state.decrypt_and_hash(&key, &mut encrypted_nothing)?;
+
+        Ok(state.split(true, self.sender_index, peer_index))
+    }
+}
+
+/// A handshake initiation message that `consume_initiation()` has checked.
+pub struct Initiation {
+    state: SymmetricState,
+    peer_ephemeral_public_key: [u8; PUBLIC_KEY_LEN],
+    peer_public_key: [u8; PUBLIC_KEY_LEN],
+    timestamp: [u8; TIMESTAMP_LEN],
+    sender_index: u32,
+}
+
+/// Checks the handshake initiation message `msg` to `local`.
+///
+/// The message is authenticated by the initiator's static key, but the
+/// caller must check that it is the key of a peer, that the timestamp is
+/// newer than the last one accepted from the peer, and, when under load,
+/// the message's `mac2`, before calling `Initiation::respond()`.
+pub fn consume_initiation(
+    local: &StaticKeyPair, msg: &[u8],
+) -> Result<Initiation, error::Unspecified> {
+    if msg.len() != INITIATION_LEN || msg[..4] != [INITIATION_TYPE, 0, 0, 0] {
+        return Err(error::Unspecified);
+    }
+    check_mac1(&local.mac1_key, msg)?;
+    let sender_index = u32_from_le(&msg[4..8]);
+
+    let mut state = SymmetricState::new(&local.public_key);
+    let peer_ephemeral_public_key: &[u8; PUBLIC_KEY_LEN] = msg[8..40].try_into_().unwrap();
+    state.mix_hash(peer_ephemeral_public_key);
+    state.mix_key(peer_ephemeral_public_key);
+
+    let key = state.mix_key_and_get(&*dh(&local.private_key, peer_ephemeral_public_key)?);
+    let mut encrypted_static = [0; PUBLIC_KEY_LEN + TAG_LEN];
+    encrypted_static.copy_from_slice(&msg[40..88]);
+    state.decrypt_and_hash(&key, &mut encrypted_static)?;
+    let mut peer_public_key = [0; PUBLIC_KEY_LEN];
+    peer_public_key.copy_from_slice(&encrypted_static[..PUBLIC_KEY_LEN]);
+
+    let key = state.mix_key_and_get(&*dh(&local.private_key, &peer_public_key)?);
+    let mut encrypted_timestamp = [0; TIMESTAMP_LEN + TAG_LEN];
+    encrypted_timestamp.copy_from_slice(&msg[88..116]);
+    state.decrypt_and_hash(&key, &mut encrypted_timestamp)?;
+    let mut timestamp = [0; TIMESTAMP_LEN];
+    timestamp.copy_from_slice(&encrypted_timestamp[..TIMESTAMP_LEN]);
+
+    Ok(Initiation {
+        state,
+        peer_ephemeral_public_key: *peer_ephemeral_public_key,
+        peer_public_key,
+        timestamp,
+        sender_index,
+    })
+}
+
+impl Initiation {
+    /// The initiator's static public key.
+    #[inline]
+    pub fn peer_public_key(&self) -> &[u8; PUBLIC_KEY_LEN] { &self.peer_public_key }
+
+    /// The initiator's TAI64N timestamp.
+    #[inline]
+    pub fn timestamp(&self) -> &[u8; TIMESTAMP_LEN] { &self.timestamp }
+
+    /// The initiator's index for the session.
+    #[inline]
+    pub fn sender_index(&self) -> u32 { self.sender_index }
+
+    /// Writes the handshake response message to `out`, with the preshared
+    /// key `preshared_key` of the peer, and returns the keys of the session.
+    ///
+    /// `sender_index` is the responder's index for the session. `mac2` is
+    /// zero; see `set_mac2()`.
+    pub fn respond(
+        self, preshared_key: &[u8; KEY_LEN], sender_index: u32, out: &mut [u8; RESPONSE_LEN],
+        rng: &rand::SecureRandom,
+    ) -> Result<TransportKeys, error::Unspecified> {
+        let ephemeral_private_key = ec::PrivateKey::generate(agreement::X25519.i.curve, rng)?;
+        let mut state = self.state;
+
+        polyfill::slice::fill(&mut out[..], 0);
+        out[0] = RESPONSE_TYPE;
+        out[4..8].copy_from_slice(&sender_index.to_le_bytes());
+        out[8..12].copy_from_slice(&self.sender_index.to_le_bytes());
+
+        {
+            let ephemeral_public_key = &mut out[12..44];
+            ephemeral_private_key
+                .compute_public_key(agreement::X25519.i.curve, ephemeral_public_key)?;
+            state.mix_hash(ephemeral_public_key);
+            state.mix_key(ephemeral_public_key);
+        }
+        state.mix_key(&*dh(&ephemeral_private_key, &self.peer_ephemeral_public_key)?);
+        state.mix_key(&*dh(&ephemeral_private_key, &self.peer_public_key)?);
+        let key = state.mix_preshared_key(preshared_key);
+        state.encrypt_and_hash(&key, &mut out[44..60])?;
+
+        set_mac1(&hash(&[LABEL_MAC1, &self.peer_public_key]), &mut out[..]);
+
+        Ok(state.split(false, sender_index, self.sender_index))
+    }
+}
+
+/// The keys of a session, for its transport data messages.
+pub struct TransportKeys {
+    sealing_key: aead::SealingKey,
+    opening_key: aead::OpeningKey,
+    local_index: u32,
+    peer_index: u32,
+    sending_counter: u64,
+}
+
+impl TransportKeys {
+    /// The local index of the session, which the peer's transport data
+    /// messages are addressed to.
+    #[inline]
+    pub fn local_index(&self) -> u32 { self.local_index }
+
+    /// The peer's index of the session.
+    #[inline]
+    pub fn peer_index(&self) -> u32 { self.peer_index }
+
+    /// Encrypts `packet`, which the caller has padded to a multiple of 16
+    /// bytes, into a transport data message with the next counter.
+    ///
+    /// Fails once `REJECT_AFTER_MESSAGES` messages have been sent.
+    pub fn seal(&mut self, packet: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+        let counter = self.sending_counter;
+        if counter >= REJECT_AFTER_MESSAGES {
+            return Err(error::Unspecified);
+        }
+        self.sending_counter += 1;
+
+        let mut msg = Vec::with_capacity(TRANSPORT_HEADER_LEN + packet.len() + TAG_LEN);
+        msg.extend_from_slice(&[TRANSPORT_TYPE, 0, 0, 0]);
+        msg.extend_from_slice(&self.peer_index.to_le_bytes());
+        msg.extend_from_slice(&counter.to_le_bytes());
+        msg.extend_from_slice(packet);
+        msg.extend_from_slice(&[0; TAG_LEN]);
+        let _ = aead::seal_in_place(
+            &self.sealing_key,
+            &aead_nonce(counter),
+            &[],
+            &mut msg[TRANSPORT_HEADER_LEN..],
+            TAG_LEN,
+        )?;
+        Ok(msg)
+    }
+
+    /// Decrypts the transport data message `msg`, which must be addressed to
+    /// `local_index()`, in place, and returns its counter and the packet.
+    ///
+    /// The caller must reject the packet if the counter is in its replay
+    /// window.
+    pub fn open<'a>(&self, msg: &'a mut [u8]) -> Result<(u64, &'a mut [u8]), error::Unspecified> {
+        if msg.len() < TRANSPORT_HEADER_LEN + TAG_LEN
+            || msg[..4] != [TRANSPORT_TYPE, 0, 0, 0]
+            || u32_from_le(&msg[4..8]) != self.local_index
+        {
+            return Err(error::Unspecified);
+        }
+        let mut counter = [0; 8];
+        counter.copy_from_slice(&msg[8..16]);
+        let counter = u64::from_le_bytes(counter);
+        if counter >= REJECT_AFTER_MESSAGES {
+            return Err(error::Unspecified);
+        }
+        let packet = aead::open_in_place(
+            &self.opening_key,
+            &aead_nonce(counter),
+            &[],
+            0,
+            &mut msg[TRANSPORT_HEADER_LEN..],
+        )?;
+        Ok((counter, packet))
+    }
+}
+
+/// The cookie of the source address `source`, e.g. the IP address and UDP
+/// port of the sender of a handshake message, under `secret`, which the
+/// responder changes to a new random value every two minutes.
+pub fn cookie(secret: &[u8; KEY_LEN], source: &[u8]) -> [u8; COOKIE_LEN] { mac(secret, &[source]) }
+
+/// Writes the cookie reply message to `out` for the handshake message `msg`
+/// to `local`, which carries `cookie`.
+pub fn create_cookie_reply(
+    local: &StaticKeyPair, msg: &[u8], cookie: &[u8; COOKIE_LEN],
+    out: &mut [u8; COOKIE_REPLY_LEN], rng: &rand::SecureRandom,
+) -> Result<(), error::Unspecified> {
+    let mac1_start = mac1_start(msg)?;
+    let mac1 = &msg[mac1_start..][..MAC_LEN];
+
+    polyfill::slice::fill(&mut out[..], 0);
+    out[0] = COOKIE_REPLY_TYPE;
+    out[4..8].copy_from_slice(&msg[4..8]);
+    let (nonce, encrypted_cookie) = out[8..].split_at_mut(NONCE_LEN);
+    rng.fill(nonce)?;
+    encrypted_cookie[..COOKIE_LEN].copy_from_slice(cookie);
+    let (key, nonce) = xaead_key_and_nonce(&local.cookie_key, nonce);
+    let key = aead::SealingKey::new_chacha20_poly1305(&key);
+    let _ = aead::seal_in_place(&key, &nonce, mac1, encrypted_cookie, TAG_LEN)?;
+    Ok(())
+}
+
+/// Decrypts the cookie reply message `reply` from the peer whose static
+/// public key is `peer_public_key`, to the handshake message `msg`, and
+/// returns the cookie.
+pub fn consume_cookie_reply(
+    peer_public_key: &[u8; PUBLIC_KEY_LEN], msg: &[u8], reply: &[u8],
+) -> Result<[u8; COOKIE_LEN], error::Unspecified> {
+    let mac1_start = mac1_start(msg)?;
+    let mac1 = &msg[mac1_start..][..MAC_LEN];
+    if reply.len() != COOKIE_REPLY_LEN
+        || reply[..4] != [COOKIE_REPLY_TYPE, 0, 0, 0]
+        || reply[4..8] != msg[4..8]
+    {
+        return Err(error::Unspecified);
+    }
+
+    let mut encrypted_cookie = [0; COOKIE_LEN + TAG_LEN];
+    encrypted_cookie.copy_from_slice(&reply[(8 + NONCE_LEN)..]);
+    let (key, nonce) =
+        xaead_key_and_nonce(&hash(&[LABEL_COOKIE, peer_public_key]), &reply[8..][..NONCE_LEN]);
+    let key = aead::OpeningKey::new_chacha20_poly1305(&key);
+    let _ = aead::open_in_place(&key, &nonce, mac1, 0, &mut encrypted_cookie)?;
+    let mut cookie = [0; COOKIE_LEN];
+    cookie.copy_from_slice(&encrypted_cookie[..COOKIE_LEN]);
+    Ok(cookie)
+}
+
+/// Sets `mac2` of the handshake message `msg` with the `cookie` from the
+/// peer's last cookie reply.
+pub fn set_mac2(msg: &mut [u8], cookie: &[u8; COOKIE_LEN]) -> Result<(), error::Unspecified> {
+    let mac2_start = mac1_start(msg)? + MAC_LEN;
+    let mac2 = mac(cookie, &[&msg[..mac2_start]]);
+    msg[mac2_start..].copy_from_slice(&mac2);
+    Ok(())
+}
+
+/// Checks `mac2` of the handshake message `msg`, whose sender's cookie is
+/// `cookie`.
+pub fn verify_mac2(msg: &[u8], cookie: &[u8; COOKIE_LEN]) -> Result<(), error::Unspecified> {
+    let mac2_start = mac1_start(msg)? + MAC_LEN;
+    let mac2 = mac(cookie, &[&msg[..mac2_start]]);
+    constant_time::verify_slices_are_equal(&mac2, &msg[mac2_start..])
+}
+
+// The offset of `mac1` in the handshake initiation or response message `msg`.
+fn mac1_start(msg: &[u8]) -> Result<usize, error::Unspecified> {
+    match (msg.len(), msg.get(..4)) {
+        (INITIATION_LEN, Some([INITIATION_TYPE, 0, 0, 0]))
+        | (RESPONSE_LEN, Some([RESPONSE_TYPE, 0, 0, 0])) => Ok(msg.len() - (2 * MAC_LEN)),
+        _ => Err(error::Unspecified),
+    }
+}
+
+fn set_mac1(mac1_key: &[u8; HASH_LEN], msg: &mut [u8]) {
+    let mac1_start = msg.len() - (2 * MAC_LEN);
+    let mac1 = mac(mac1_key, &[&msg[..mac1_start]]);
+    msg[mac1_start..][..MAC_LEN].copy_from_slice(&mac1);
+}
+
+fn check_mac1(mac1_key: &[u8; HASH_LEN], msg: &[u8]) -> Result<(), error::Unspecified> {
+    let mac1_start = msg.len() - (2 * MAC_LEN);
+    let mac1 = mac(mac1_key, &[&msg[..mac1_start]]);
+    constant_time::verify_slices_are_equal(&mac1, &msg[mac1_start..][..MAC_LEN])
+}
+
+// The chaining key `C` and the hash `H` of the handshake.
+struct SymmetricState {
+    chaining_key: Zeroizing<[u8; HASH_LEN]>,
+    hash: [u8; HASH_LEN],
+}
+
+impl Clone for SymmetricState {
+    fn clone(&self) -> Self {
+        Self {
+            chaining_key: Zeroizing::new(*self.chaining_key),
+            hash: self.hash,
+        }
+    }
+}
+
+impl SymmetricState {
+    fn new(responder_public_key: &[u8; PUBLIC_KEY_LEN]) -> Self {
+        let chaining_key = hash(&[CONSTRUCTION]);
+        let hash = self::hash(&[&self::hash(&[&chaining_key, IDENTIFIER]), responder_public_key]);
+        Self {
+            chaining_key: Zeroizing::new(chaining_key),
+            hash,
+        }
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) { self.hash = hash(&[&self.hash, data]) }
+
+    // `C = KDF1(C, input)`.
+    fn mix_key(&mut self, input: &[u8]) { kdf(&mut self.chaining_key, input, &mut []) }
+
+    // `(C, k) = KDF2(C, input)`.
+    fn mix_key_and_get(&mut self, input: &[u8]) -> Zeroizing<[u8; KEY_LEN]> {
+        let mut key = Zeroizing::new([0; KEY_LEN]);
+        kdf(&mut self.chaining_key, input, &mut [&mut *key]);
+        key
+    }
+
+    // `(C, T, k) = KDF3(C, Q); H = HASH(H || T)`.
+    fn mix_preshared_key(&mut self, preshared_key: &[u8; KEY_LEN]) -> Zeroizing<[u8; KEY_LEN]> {
+        let mut t = Zeroizing::new([0; HASH_LEN]);
+        let mut key = Zeroizing::new([0; KEY_LEN]);
+        kdf(&mut self.chaining_key, preshared_key, &mut [&mut *t, &mut *key]);
+        self.mix_hash(&*t);
+        key
+    }
+
+    // Encrypts `in_out`, whose last `TAG_LEN` bytes are the space for the
+    // tag, with `AEAD(key, 0, ..., H)`, and mixes the ciphertext into `H`.
+    fn encrypt_and_hash(
+        &mut self, key: &[u8; KEY_LEN], in_out: &mut [u8],
+    ) -> Result<(), error::Unspecified> {
+        let key = aead::SealingKey::new_chacha20_poly1305(key);
+        let _ = aead::seal_in_place(&key, &aead_nonce(0), &self.hash, in_out, TAG_LEN)?;
+        self.mix_hash(in_out);
+        Ok(())
+    }
+
+    fn decrypt_and_hash(
+        &mut self, key: &[u8; KEY_LEN], in_out: &mut [u8],
+    ) -> Result<(), error::Unspecified> {
+        let hash = self::hash(&[&self.hash, &in_out[..]]);
+        let key = aead::OpeningKey::new_chacha20_poly1305(key);
+        let _ = aead::open_in_place(&key, &aead_nonce(0), &self.hash, 0, in_out)?;
+        self.hash = hash;
+        Ok(())
+    }
+
+    // `(T_send_i, T_recv_i) = KDF2(C, "")`.
+    fn split(mut self, is_initiator: bool, local_index: u32, peer_index: u32) -> TransportKeys {
+        let mut responder_key = Zeroizing::new([0; KEY_LEN]);
+        kdf(&mut self.chaining_key, &[], &mut [&mut *responder_key]);
+        let initiator_key = &self.chaining_key;
+        let (sending_key, receiving_key) = if is_initiator {
+            (initiator_key, &responder_key)
+        } else {
+            (&responder_key, initiator_key)
+        };
+        TransportKeys {
+            sealing_key: aead::SealingKey::new_chacha20_poly1305(sending_key),
+            opening_key: aead::OpeningKey::new_chacha20_poly1305(receiving_key),
+            local_index,
+            peer_index,
+            sending_counter: 0,
+        }
+    }
+}
+
+fn hash(data: &[&[u8]]) -> [u8; HASH_LEN] {
+    let mut out = [0; HASH_LEN];
+    out.copy_from_slice(blake2s::digest(data).as_ref());
+    out
+}
+
+fn mac(key: &[u8], data: &[&[u8]]) -> [u8; MAC_LEN] {
+    let mut out = [0; MAC_LEN];
+    out.copy_from_slice(blake2s::keyed(key, MAC_LEN, data).as_ref());
+    out
+}
+
+// HMAC-BLAKE2s. WireGuard's keys are never longer than a BLAKE2s block.
+fn hmac(key: &[u8; HASH_LEN], data: &[&[u8]]) -> Zeroizing<[u8; HASH_LEN]> {
+    let mut pad = Zeroizing::new([0x36; blake2s::BLOCK_LEN]);
+    for (pad, key) in pad.iter_mut().zip(key.iter()) {
+        *pad ^= key;
+    }
+    let mut ctx = blake2s::Context::new();
+    ctx.update(&*pad);
+    for data in data {
+        ctx.update(data);
+    }
+    let inner = ctx.finish();
+
+    for pad in pad.iter_mut() {
+        *pad ^= 0x36 ^ 0x5c;
+    }
+    let mut out = Zeroizing::new([0; HASH_LEN]);
+    out.copy_from_slice(blake2s::digest(&[&*pad, inner.as_ref()]).as_ref());
+    out
+}
+
+// `KDF_n(chaining_key, input)`, for `n` of `1 + keys.len()`: the first output
+// replaces `chaining_key`, and the others are written to `keys`.
+fn kdf(chaining_key: &mut [u8; HASH_LEN], input: &[u8], keys: &mut [&mut [u8; KEY_LEN]]) {
+    let prk = hmac(chaining_key, &[input]);
+    let mut t = hmac(&prk, &[&[1]]);
+    chaining_key.copy_from_slice(&*t);
+    for (key, i) in keys.iter_mut().zip(2u8..) {
+        t = hmac(&prk, &[&*t, &[i]]);
+        key.copy_from_slice(&*t);
+    }
+}
+
+fn dh(
+    private_key: &ec::PrivateKey, public_key: &[u8; PUBLIC_KEY_LEN],
+) -> Result<Zeroizing<[u8; KEY_LEN]>, error::Unspecified> {
+    let mut out = Zeroizing::new([0; KEY_LEN]);
+    (agreement::X25519.i.ecdh)(&mut *out, private_key, untrusted::Input::from(&public_key[..]))?;
+    Ok(out)
+}
+
+// The ChaCha20-Poly1305 nonce for `counter`: 32 zero bits followed by the
+// little-endian counter.
+fn aead_nonce(counter: u64) -> [u8; chacha::NONCE_LEN] {
+    let mut nonce = [0; chacha::NONCE_LEN];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+// XChaCha20-Poly1305 is ChaCha20-Poly1305 keyed with the HChaCha20 subkey
+// of the first 16 bytes of the 24-byte nonce, with the last 8 bytes as the
+// nonce.
+fn xaead_key_and_nonce(
+    key: &[u8; KEY_LEN], nonce: &[u8],
+) -> (Zeroizing<[u8; KEY_LEN]>, [u8; chacha::NONCE_LEN]) {
+    let subkey = chacha::hchacha20(key, nonce[..chacha::HCHACHA20_NONCE_LEN].try_into_().unwrap());
+    let mut chacha_nonce = [0; chacha::NONCE_LEN];
+    chacha_nonce[4..].copy_from_slice(&nonce[chacha::HCHACHA20_NONCE_LEN..]);
+    (subkey, chacha_nonce)
+}
+
+fn u32_from_le(bytes: &[u8]) -> u32 { polyfill::slice::u32_from_le_u8(bytes.try_into_().unwrap()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_wireguard() {
+        test::from_file("src/wireguard_tests.txt", |section, test_case| {
+            match section {
+                "Handshake" => test_handshake(test_case),
+                "CookieReply" => test_cookie_reply(test_case),
+                _ => unreachable!(),
+            }
+            Ok(())
+        });
+    }
+
+    fn test_handshake(test_case: &mut test::TestCase) {
+        let initiator =
+            StaticKeyPair::from_private_key(&test_case.consume_bytes("InitiatorPrivateKey"))
+                .unwrap();
+        let responder =
+            StaticKeyPair::from_private_key(&test_case.consume_bytes("ResponderPrivateKey"))
+                .unwrap();
+        let preshared_key = test_case.consume_bytes("PresharedKey");
+        let preshared_key: &[u8; KEY_LEN] = preshared_key.as_slice().try_into_().unwrap();
+        let initiator_ephemeral = test_case.consume_bytes("InitiatorEphemeral");
+        let responder_ephemeral = test_case.consume_bytes("ResponderEphemeral");
+        let initiator_index = test_case.consume_usize("InitiatorIndex") as u32;
+        let responder_index = test_case.consume_usize("ResponderIndex") as u32;
+        let timestamp = test_case.consume_bytes("Timestamp");
+        let initiation = test_case.consume_bytes("Initiation");
+        let response = test_case.consume_bytes("Response");
+        let initiator_packet = test_case.consume_bytes("InitiatorPacket");
+        let initiator_message = test_case.consume_bytes("InitiatorMessage");
+        let responder_packet = test_case.consume_bytes("ResponderPacket");
+        let responder_message = test_case.consume_bytes("ResponderMessage");
+
+        let mut out = [0; INITIATION_LEN];
+        let rng = test::rand::FixedSliceRandom {
+            bytes: &initiator_ephemeral,
+        };
+        let handshake = initiate(
+            &initiator,
+            responder.public_key(),
+            preshared_key,
+            initiator_index,
+            timestamp.as_slice().try_into_().unwrap(),
+            &mut out,
+            &rng,
+        )
+        .unwrap();
+        assert_eq!(&out[..], &initiation[..]);
+
+        let received = consume_initiation(&responder, &initiation).unwrap();
+        assert_eq!(received.peer_public_key(), initiator.public_key());
+        assert_eq!(&received.timestamp()[..], &timestamp[..]);
+        assert_eq!(received.sender_index(), initiator_index);
+
+        let mut out = [0; RESPONSE_LEN];
+        let rng = test::rand::FixedSliceRandom {
+            bytes: &responder_ephemeral,
+        };
+        let mut responder_keys = received
+            .respond(preshared_key, responder_index, &mut out, &rng)
+            .unwrap();
+        assert_eq!(&out[..], &response[..]);
+
+        let mut initiator_keys = handshake.consume_response(&initiator, &response).unwrap();
+        assert_eq!(initiator_keys.local_index(), initiator_index);
+        assert_eq!(initiator_keys.peer_index(), responder_index);
+
+        let mut msg = initiator_keys.seal(&initiator_packet).unwrap();
+        assert_eq!(msg, initiator_message);
+        let (counter, packet) = responder_keys.open(&mut msg).unwrap();
+        assert_eq!(counter, 0);
+        assert_eq!(&packet[..], &initiator_packet[..]);
+
+        let mut msg = responder_keys.seal(&responder_packet).unwrap();
+        assert_eq!(msg, responder_message);
+        let (counter, packet) = initiator_keys.open(&mut msg).unwrap();
+        assert_eq!(counter, 0);
+        assert_eq!(&packet[..], &responder_packet[..]);
+    }
+
+    fn test_cookie_reply(test_case: &mut test::TestCase) {
+        let responder =
+            StaticKeyPair::from_private_key(&test_case.consume_bytes("ResponderPrivateKey"))
+                .unwrap();
+        let msg = test_case.consume_bytes("Message");
+        let secret = test_case.consume_bytes("Secret");
+        let source = test_case.consume_bytes("Source");
+        let nonce = test_case.consume_bytes("Nonce");
+        let expected_cookie = test_case.consume_bytes("Cookie");
+        let expected_reply = test_case.consume_bytes("CookieReply");
+        let msg_with_mac2 = test_case.consume_bytes("MessageWithMac2");
+
+        let cookie = cookie(secret.as_slice().try_into_().unwrap(), &source);
+        assert_eq!(&cookie[..], &expected_cookie[..]);
+
+        let mut reply = [0; COOKIE_REPLY_LEN];
+        let rng = test::rand::FixedSliceRandom { bytes: &nonce };
+        create_cookie_reply(&responder, &msg, &cookie, &mut reply, &rng).unwrap();
+        assert_eq!(&reply[..], &expected_reply[..]);
+        assert_eq!(
+            consume_cookie_reply(responder.public_key(), &msg, &reply).unwrap(),
+            cookie
+        );
+
+        let mut msg = msg;
+        assert!(verify_mac2(&msg, &cookie).is_err());
+        set_mac2(&mut msg, &cookie).unwrap();
+        assert_eq!(msg, msg_with_mac2);
+        assert!(verify_mac2(&msg, &cookie).is_ok());
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Overwriting secrets when they are no longer needed.
+//!
+//! Types that hold secrets, e.g. keys and the state of a digest of a key,
+//! implement `Drop` by zeroizing them. The compiler may remove writes to
+//! memory that isn't read afterwards, like the memory of a value that is
+//! being dropped, so the zeros are written with `write_volatile()`, followed
+//! by a `compiler_fence()` so that they aren't moved after the memory is
+//! reused.
+//!
+//! Zeroizing on drop doesn't wipe copies that were left behind when the value
+//! was moved, or that the compiler spilled to the stack.
+//!
+//! With the `zeroize` feature, the public types that are zeroized this way
+//! also implement the `zeroize` crate's `ZeroizeOnDrop` marker trait. This
+//! module is unrelated to that crate, which is always referred to as
+//! `::zeroize`.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Types for which all-zero memory is a valid value and that don't need to be
+/// dropped, i.e. integers and arrays and structs of them.
+pub(crate) unsafe trait Zeroable: Copy {}
+
+unsafe impl Zeroable for u8 {}
+unsafe impl Zeroable for u32 {}
+unsafe impl Zeroable for u64 {}
+unsafe impl Zeroable for usize {}
+unsafe impl<T: Zeroable> Zeroable for core::num::Wrapping<T> {}
+unsafe impl<T: Zeroable, const N: usize> Zeroable for [T; N] {}
+
+#[cfg(target_arch = "x86_64")]
+unsafe impl Zeroable for core::arch::x86_64::__m128i {}
+
+/// Overwrites `values` with zeros.
+pub(crate) fn zeroize<T: Zeroable>(values: &mut [T]) {
+    for value in values.iter_mut() {
+        unsafe { core::ptr::write_volatile(value, core::mem::zeroed()) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrites `value` with zeros.
+#[inline]
+pub(crate) fn zeroize_value<T: Zeroable>(value: &mut T) { zeroize(core::slice::from_mut(value)) }
+
+/// A secret that is overwritten with zeros when it is dropped, for types that
+/// are `Copy` and so can't implement `Drop` themselves, e.g. ECDSA nonces.
+pub(crate) struct Zeroizing<T: Zeroable>(T);
+
+impl<T: Zeroable> Zeroizing<T> {
+    #[inline]
+    pub(crate) fn new(value: T) -> Self { Zeroizing(value) }
+}
+
+impl<T: Zeroable> core::ops::Deref for Zeroizing<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T { &self.0 }
+}
+
+impl<T: Zeroable> core::ops::DerefMut for Zeroizing<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T { &mut self.0 }
+}
+
+impl<T: Zeroable> Drop for Zeroizing<T> {
+    fn drop(&mut self) { zeroize_value(&mut self.0) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zeroize() {
+        let mut bytes = [0xffu8; 33];
+        zeroize(&mut bytes[1..]);
+        assert_eq!(bytes[0], 0xff);
+        assert!(bytes[1..].iter().all(|b| *b == 0));
+
+        let mut words = [[u64::max_value(); 2]; 3];
+        zeroize_value(&mut words);
+        assert_eq!(words, [[0; 2]; 3]);
+    }
+
+    #[test]
+    fn test_zeroizing() {
+        let mut slot = core::mem::MaybeUninit::new(Zeroizing::new([0x5au8; 32]));
+        assert_eq!(**unsafe { slot.assume_init_ref() }, [0x5a; 32]);
+        unsafe { core::ptr::drop_in_place(slot.as_mut_ptr()) };
+        // `Zeroizing<[u8; 32]>` has no padding, so all of its bytes can be
+        // read after it has been dropped.
+        let bytes = unsafe { &*(slot.as_ptr() as *const [u8; 32]) };
+        assert_eq!(*bytes, [0; 32]);
+    }
+}
@@ -30,6 +30,7 @@
     variant_size_differences,
     warnings
 )]
+#![cfg(feature = "use_heap")]
 
 use ring::{aead, error, test};
 
@@ -365,6 +366,94 @@ fn test_aead_nonce_sizes(aead_alg: &'static aead::Algorithm) -> Result<(), error
     Ok(())
 }
 
+#[test]
+fn aead_detailed_errors() {
+    for aead_alg in [&aead::AES_128_GCM, &aead::CHACHA20_POLY1305].iter() {
+        let key_data = vec![0u8; aead_alg.key_len()];
+        let s_key = aead::SealingKey::new(aead_alg, &key_data).unwrap();
+        let o_key = aead::OpeningKey::new(aead_alg, &key_data).unwrap();
+        let nonce = [0u8; 12];
+        let seal = |nonce: &[u8], in_out: &mut [u8], out_suffix_capacity| {
+            aead::seal_in_place_detailed(&s_key, nonce, &[], in_out, out_suffix_capacity)
+                .map_err(|e| (e.operation(), e.reason()))
+        };
+        let open = |nonce: &[u8], in_prefix_len, in_out: &mut [u8]| {
+            aead::open_in_place_detailed(&o_key, nonce, &[], in_prefix_len, in_out)
+                .map(|plaintext| plaintext.len())
+                .map_err(|e| (e.operation(), e.reason()))
+        };
+
+        let mut sealed = [0u8; 20];
+        assert_eq!(seal(&nonce, &mut sealed, 16), Ok(20));
+        assert_eq!(open(&nonce, 0, &mut sealed.clone()), Ok(4));
+
+        assert_eq!(
+            seal(&nonce[..8], &mut [0; 20], 16),
+            Err((
+                "aead::seal_in_place",
+                error::Reason::WrongLength {
+                    input: "nonce",
+                    expected: 12,
+                    actual: 8,
+                }
+            ))
+        );
+        assert_eq!(
+            seal(&nonce, &mut [0; 20], 15),
+            Err((
+                "aead::seal_in_place",
+                error::Reason::TooShort {
+                    input: "out_suffix_capacity",
+                    min: 16,
+                    actual: 15,
+                }
+            ))
+        );
+        assert_eq!(
+            seal(&nonce, &mut [0; 10], 16),
+            Err((
+                "aead::seal_in_place",
+                error::Reason::TooShort {
+                    input: "in_out",
+                    min: 16,
+                    actual: 10,
+                }
+            ))
+        );
+        assert_eq!(
+            open(&[0; 13], 0, &mut sealed.clone()),
+            Err((
+                "aead::open_in_place",
+                error::Reason::WrongLength {
+                    input: "nonce",
+                    expected: 12,
+                    actual: 13,
+                }
+            ))
+        );
+        assert_eq!(
+            open(&nonce, 5, &mut sealed.clone()),
+            Err((
+                "aead::open_in_place",
+                error::Reason::TooShort {
+                    input: "ciphertext_and_tag_modified_in_place",
+                    min: 21,
+                    actual: 20,
+                }
+            ))
+        );
+
+        // A wrong tag isn't described further.
+        let mut tampered = sealed;
+        tampered[19] ^= 1;
+        assert_eq!(
+            open(&nonce, 0, &mut tampered),
+            Err(("aead::open_in_place", error::Reason::Unspecified))
+        );
+        assert!(aead::open_in_place(&o_key, &nonce, &[], 0, &mut tampered).is_err());
+    }
+}
+
 #[test]
 fn aead_chacha20_poly1305_openssh() {
     // TODO: test_aead_key_sizes(...);
@@ -396,7 +485,7 @@ fn aead_chacha20_poly1305_openssh() {
             let mut tag = [0u8; aead::chacha20_poly1305_openssh::TAG_LEN];
             let mut s_in_out = plaintext.clone();
             let s_key = aead::chacha20_poly1305_openssh::SealingKey::new(&key_bytes);
-            let () = s_key.seal_in_place(sequence_num, &mut s_in_out[..], &mut tag);
+            assert!(s_key.seal_in_place(sequence_num, &mut s_in_out[..], &mut tag).is_ok());
             assert_eq!(&ct, &s_in_out);
             assert_eq!(&expected_tag, &tag);
             let o_key = aead::chacha20_poly1305_openssh::OpeningKey::new(&key_bytes);
@@ -412,3 +501,62 @@ fn aead_chacha20_poly1305_openssh() {
         },
     );
 }
+
+#[test]
+fn aead_chacha20_poly1305_openssh_short_packets() {
+    use aead::chacha20_poly1305_openssh::{OpeningKey, SealingKey, KEY_LEN, TAG_LEN};
+
+    let key_bytes = [0u8; KEY_LEN];
+    let s_key = SealingKey::new(&key_bytes);
+    let o_key = OpeningKey::new(&key_bytes);
+    let mut tag = [0u8; TAG_LEN];
+    for len in 0..4 {
+        let mut in_out = vec![0u8; len];
+        assert!(s_key.seal_in_place(0, &mut in_out, &mut tag).is_err());
+        assert!(o_key.open_in_place(0, &mut in_out, &tag).is_err());
+    }
+    let mut in_out = [0u8; 4];
+    assert!(s_key.seal_in_place(0, &mut in_out, &mut tag).is_ok());
+    assert_eq!(o_key.open_in_place(0, &mut in_out, &tag), Ok(&[][..]));
+}
+
+#[test]
+fn aead_static_keys() {
+    const KEY_LEN: usize = aead::CHACHA20_POLY1305.key_len();
+    const KEY_BYTES: [u8; KEY_LEN] = [7; KEY_LEN];
+    static SEALING_KEY: aead::SealingKey = aead::SealingKey::new_chacha20_poly1305(&KEY_BYTES);
+    static OPENING_KEY: aead::OpeningKey = aead::OpeningKey::new_chacha20_poly1305(&KEY_BYTES);
+    assert_eq!(SEALING_KEY.algorithm(), &aead::CHACHA20_POLY1305);
+    assert_eq!(OPENING_KEY.algorithm(), &aead::CHACHA20_POLY1305);
+
+    // The keys are the same as the ones constructed at run time.
+    let nonce = [0u8; 12];
+    let mut sealed = b"message".to_vec();
+    sealed.extend_from_slice(&[0; aead::MAX_TAG_LEN]);
+    let mut expected = sealed.clone();
+    let s_key = aead::SealingKey::new(&aead::CHACHA20_POLY1305, &KEY_BYTES).unwrap();
+    let len = aead::seal_in_place(&SEALING_KEY, &nonce, b"ad", &mut sealed, aead::MAX_TAG_LEN);
+    assert_eq!(
+        len,
+        aead::seal_in_place(&s_key, &nonce, b"ad", &mut expected, aead::MAX_TAG_LEN)
+    );
+    assert_eq!(sealed, expected);
+    let plaintext = aead::open_in_place(&OPENING_KEY, &nonce, b"ad", 0, &mut sealed).unwrap();
+    assert_eq!(plaintext, b"message");
+
+    use aead::chacha20_poly1305_openssh::{OpeningKey, SealingKey, TAG_LEN};
+    static SSH_SEALING_KEY: SealingKey = SealingKey::new(&[9; 64]);
+    static SSH_OPENING_KEY: OpeningKey = OpeningKey::new(&[9; 64]);
+    let mut in_out = *b"\x00\x00\x00\x04data";
+    let mut tag = [0u8; TAG_LEN];
+    assert!(SSH_SEALING_KEY.seal_in_place(1, &mut in_out, &mut tag).is_ok());
+    assert_eq!(SSH_OPENING_KEY.open_in_place(1, &mut in_out, &tag), Ok(&b"data"[..]));
+}
+
+#[test]
+fn aead_traits() {
+    test::compile_time_assert_send::<aead::SealingKey>();
+    test::compile_time_assert_sync::<aead::SealingKey>();
+    test::compile_time_assert_send::<aead::OpeningKey>();
+    test::compile_time_assert_sync::<aead::OpeningKey>();
+}
@@ -0,0 +1,222 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{age, rand};
+
+fn encrypt_to(
+    recipients: &[&age::X25519Recipient], plaintext: &[u8], rng: &rand::SecureRandom,
+) -> Vec<u8> {
+    let file_key = age::FileKey::generate(rng).unwrap();
+    let stanzas = recipients
+        .iter()
+        .map(|recipient| recipient.wrap(&file_key, rng).unwrap())
+        .collect();
+    age::encrypt(&file_key, stanzas, plaintext, rng).unwrap()
+}
+
+#[test]
+fn age_x25519_round_trip() {
+    let rng = rand::SystemRandom::new();
+    let alice = age::X25519Identity::generate(&rng).unwrap();
+    let bob = age::X25519Identity::generate(&rng).unwrap();
+    let carol = age::X25519Identity::generate(&rng).unwrap();
+
+    for &len in &[0, 1, age::CHUNK_LEN - 1, age::CHUNK_LEN, age::CHUNK_LEN + 1, 3 * age::CHUNK_LEN]
+    {
+        let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let file = encrypt_to(&[alice.recipient(), bob.recipient()], &plaintext, &rng);
+        for identity in &[&alice, &bob] {
+            let decrypted = age::decrypt(&file, |stanzas| identity.unwrap(stanzas)).unwrap();
+            assert_eq!(decrypted, plaintext);
+        }
+        assert!(age::decrypt(&file, |stanzas| carol.unwrap(stanzas)).is_err());
+    }
+}
+
+#[test]
+fn age_identity_encoding_round_trip() {
+    let rng = rand::SystemRandom::new();
+    let identity = age::X25519Identity::generate(&rng).unwrap();
+    let encoded = identity.to_bech32();
+    assert!(encoded.starts_with("AGE-SECRET-KEY-1"));
+    let decoded = age::X25519Identity::from_bech32(&encoded).unwrap();
+    assert_eq!(decoded.recipient(), identity.recipient());
+
+    let recipient = identity.recipient().to_bech32();
+    assert!(recipient.starts_with("age1"));
+    assert_eq!(age::X25519Recipient::from_bech32(&recipient).unwrap(), *identity.recipient());
+    assert_eq!(
+        age::X25519Recipient::from_public_key(identity.recipient().public_key_bytes()).unwrap(),
+        *identity.recipient()
+    );
+}
+
+#[test]
+fn age_scrypt() {
+    let rng = rand::SystemRandom::new();
+    let file_key = age::FileKey::generate(&rng).unwrap();
+    let stanza = age::scrypt_wrap(b"passphrase", 10, &file_key, &rng).unwrap();
+    assert_eq!(stanza.tag, "scrypt");
+    let file = age::encrypt(&file_key, vec![stanza.clone()], b"plaintext", &rng).unwrap();
+
+    let decrypted =
+        age::decrypt(&file, |stanzas| age::scrypt_unwrap(b"passphrase", 18, stanzas)).unwrap();
+    assert_eq!(decrypted, b"plaintext");
+    assert!(age::decrypt(&file, |stanzas| age::scrypt_unwrap(b"passw0rd", 18, stanzas)).is_err());
+    // The work factor is above the limit.
+    assert!(age::decrypt(&file, |stanzas| age::scrypt_unwrap(b"passphrase", 9, stanzas)).is_err());
+
+    // A scrypt stanza must be the only one.
+    let identity = age::X25519Identity::generate(&rng).unwrap();
+    let x25519_stanza = identity.recipient().wrap(&file_key, &rng).unwrap();
+    let file = age::encrypt(&file_key, vec![stanza, x25519_stanza], b"plaintext", &rng).unwrap();
+    assert!(age::decrypt(&file, |stanzas| age::scrypt_unwrap(b"passphrase", 18, stanzas)).is_err());
+}
+
+#[test]
+fn age_header_round_trip() {
+    let rng = rand::SystemRandom::new();
+    let file_key = age::FileKey::generate(&rng).unwrap();
+    let stanzas = vec![
+        age::Stanza {
+            tag: "grease-a".into(),
+            args: vec!["x".into(), "!~".into()],
+            body: vec![],
+        },
+        age::Stanza {
+            tag: "grease-b".into(),
+            args: vec![],
+            body: vec![0xab; 48],
+        },
+        age::Stanza {
+            tag: "grease-c".into(),
+            args: vec![],
+            body: vec![0xcd; 100],
+        },
+    ];
+    let header = age::Header::new(&file_key, stanzas.clone()).unwrap();
+    let mut file = header.to_bytes();
+    file.extend_from_slice(b"payload");
+
+    let (parsed, payload) = age::Header::parse(&file).unwrap();
+    assert_eq!(parsed.stanzas(), &stanzas[..]);
+    assert_eq!(payload, b"payload");
+    assert!(parsed.verify(&file_key).is_ok());
+    assert!(parsed.verify(&age::FileKey::generate(&rng).unwrap()).is_err());
+    assert_eq!(parsed.to_bytes(), header.to_bytes());
+
+    // An argument may not be empty or have a space.
+    for bad in &["", "a b"] {
+        let stanza = age::Stanza {
+            tag: "grease".into(),
+            args: vec![(*bad).into()],
+            body: vec![],
+        };
+        assert!(age::Header::new(&file_key, vec![stanza]).is_err());
+    }
+    assert!(age::Header::new(&file_key, vec![]).is_err());
+}
+
+#[test]
+fn age_rejects_invalid_files() {
+    let rng = rand::SystemRandom::new();
+    let identity = age::X25519Identity::generate(&rng).unwrap();
+    let file_key = age::FileKey::generate(&rng).unwrap();
+    let stanzas = vec![
+        age::Stanza {
+            tag: "grease".into(),
+            args: vec!["a".into()],
+            body: vec![],
+        },
+        identity.recipient().wrap(&file_key, &rng).unwrap(),
+    ];
+    let file = age::encrypt(&file_key, stanzas, &[7; age::CHUNK_LEN + 10], &rng).unwrap();
+    let decrypt = |file: &[u8]| age::decrypt(file, |stanzas| identity.unwrap(stanzas));
+    assert!(decrypt(&file).is_ok());
+
+    let (_, payload) = age::Header::parse(&file).unwrap();
+    let header_len = file.len() - payload.len();
+
+    // A modified header fails the MAC.
+    let text = String::from_utf8_lossy(&file[..header_len]).into_owned();
+    let mut modified = text.replacen("-> grease a", "-> grease b", 1).into_bytes();
+    modified.extend_from_slice(payload);
+    assert!(decrypt(&modified).is_err());
+
+    // The header must be canonical.
+    for (from, to) in &[("\n", "\r\n"), ("-> X25519 ", "-> X25519  "), ("---", "--- ")] {
+        let mut modified = text.replacen(from, to, 1).into_bytes();
+        modified.extend_from_slice(payload);
+        assert!(decrypt(&modified).is_err());
+    }
+
+    // A modified payload fails.
+    let mut modified = file.clone();
+    modified[header_len + age::PAYLOAD_NONCE_LEN + 1] ^= 1;
+    assert!(decrypt(&modified).is_err());
+
+    // Truncation at a chunk boundary is detected, because the last chunk is
+    // marked.
+    let first_chunk_end = header_len + age::PAYLOAD_NONCE_LEN + age::CHUNK_LEN + age::TAG_LEN;
+    assert!(decrypt(&file[..first_chunk_end]).is_err());
+    assert!(decrypt(&file[..(file.len() - 1)]).is_err());
+    assert!(decrypt(&file[..(header_len + age::PAYLOAD_NONCE_LEN)]).is_err());
+
+    // An empty last chunk is only allowed for an empty file.
+    let mut extended = file.clone();
+    extended.extend_from_slice(&[0; age::TAG_LEN]);
+    assert!(decrypt(&extended).is_err());
+}
+
+#[test]
+fn age_payload_chunks() {
+    let rng = rand::SystemRandom::new();
+    let file_key = age::FileKey::generate(&rng).unwrap();
+    let nonce = [1; age::PAYLOAD_NONCE_LEN];
+
+    let mut sealer = age::PayloadSealer::new(&file_key, &nonce);
+    let mut short = vec![0; 10 + age::TAG_LEN];
+    // Only the last chunk may be short.
+    assert!(sealer.seal_chunk_in_place(&mut short, false).is_err());
+    let mut first = vec![1; age::CHUNK_LEN + age::TAG_LEN];
+    sealer.seal_chunk_in_place(&mut first, false).unwrap();
+    sealer.seal_chunk_in_place(&mut short, true).unwrap();
+    // Nothing may follow the last chunk.
+    let mut after = vec![0; 10 + age::TAG_LEN];
+    assert!(sealer.seal_chunk_in_place(&mut after, true).is_err());
+
+    let mut opener = age::PayloadOpener::new(&file_key, &nonce);
+    assert_eq!(opener.open_chunk_in_place(&mut first, false).unwrap(), &[1; age::CHUNK_LEN][..]);
+    assert_eq!(opener.open_chunk_in_place(&mut short, true).unwrap(), &[0; 10][..]);
+}
@@ -30,9 +30,16 @@
     variant_size_differences,
     warnings
 )]
+#![cfg(feature = "use_heap")]
 
 use ring::{agreement, error, rand, test};
 
+#[test]
+fn agreement_traits() {
+    test::compile_time_assert_send::<agreement::EphemeralPrivateKey>();
+    test::compile_time_assert_sync::<agreement::EphemeralPrivateKey>();
+}
+
 #[test]
 fn agreement_agree_ephemeral() {
     let rng = rand::SystemRandom::new();
@@ -59,6 +66,20 @@ fn agreement_agree_ephemeral() {
                 assert!(my_private.compute_public_key(computed_public).is_ok());
                 assert_eq!(computed_public, &my_public[..]);
 
+                let mut computed_compressed = [0u8; agreement::PUBLIC_KEY_MAX_LEN];
+                let computed_compressed =
+                    &mut computed_compressed[..my_private.compressed_public_key_len()];
+                assert!(my_private
+                    .compute_public_key_compressed(computed_compressed)
+                    .is_ok());
+                if curve_name == "X25519" {
+                    assert_eq!(computed_compressed, &my_public[..]);
+                } else {
+                    let (prefix, x) = computed_compressed.split_at(1);
+                    assert_eq!(prefix[0], 2 | (my_public[my_public.len() - 1] & 1));
+                    assert_eq!(x, &my_public[1..(1 + x.len())]);
+                }
+
                 assert!(agreement::agree_ephemeral(
                     my_private,
                     alg,
@@ -97,6 +118,63 @@ fn agreement_agree_ephemeral() {
     });
 }
 
+#[test]
+fn agreement_x25519_multi() {
+    let rng = rand::SystemRandom::new();
+
+    let mut my_private_keys = Vec::new();
+    let mut peer_public_keys = Vec::new();
+    let mut expected = Vec::new();
+    test::from_file("tests/agreement_tests.txt", |section, test_case| {
+        assert_eq!(section, "");
+
+        let curve_name = test_case.consume_string("Curve");
+        let alg = alg_from_curve_name(&curve_name);
+        let peer_public = test_case.consume_bytes("PeerQ");
+
+        let my_private = match test_case.consume_optional_string("Error") {
+            None => {
+                let my_private = test_case.consume_bytes("D");
+                let rng = test::rand::FixedSliceRandom { bytes: &my_private };
+                let my_private = agreement::EphemeralPrivateKey::generate(alg, &rng)?;
+                let _ = test_case.consume_bytes("MyQ");
+                let output = test_case.consume_bytes("Output");
+                expected.push(if alg == &agreement::X25519 { Ok(output) } else { Err(()) });
+                my_private
+            },
+            Some(_) => {
+                expected.push(Err(()));
+                agreement::EphemeralPrivateKey::generate(alg, &rng)?
+            },
+        };
+        my_private_keys.push(my_private);
+        peer_public_keys.push(peer_public);
+        Ok(())
+    });
+
+    // Pair the peer public keys with X25519 keys too, so that X25519 keys
+    // fill several batches, ending with a partial one.
+    let n = expected.len();
+    for i in 0..(n + 5) {
+        let private_key = [(i as u8).wrapping_add(0x42); 32];
+        let rng = test::rand::FixedSliceRandom { bytes: &private_key };
+        let alg = &agreement::X25519;
+        my_private_keys.push(agreement::EphemeralPrivateKey::generate(alg, &rng).unwrap());
+        let peer_public = peer_public_keys[i % n].clone();
+        expected.push(x25519_(&private_key, &peer_public).map_err(|_| ()));
+        peer_public_keys.push(peer_public);
+    }
+
+    let peer_public_keys: Vec<untrusted::Input> = peer_public_keys
+        .iter()
+        .map(|peer_public| untrusted::Input::from(peer_public))
+        .collect();
+    let actual = agreement::x25519_multi(my_private_keys, &peer_public_keys, (), |key_material| {
+        Ok(Vec::from(key_material))
+    });
+    assert_eq!(actual, expected);
+}
+
 #[test]
 fn test_agreement_ecdh_x25519_rfc_iterated() {
     let mut k = h("0900000000000000000000000000000000000000000000000000000000000000");
@@ -0,0 +1,166 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "unstable_bigint")]
+
+use ring::{arithmetic::bigint, rand, test};
+
+#[test]
+fn bigint_traits() {
+    test::compile_time_assert_send::<bigint::Modulus>();
+    test::compile_time_assert_sync::<bigint::Modulus>();
+    test::compile_time_assert_send::<bigint::Elem>();
+    test::compile_time_assert_sync::<bigint::Elem>();
+}
+
+#[test]
+fn bigint_test_vectors() {
+    let rng = rand::SystemRandom::new();
+
+    test::from_file("tests/bigint_tests.txt", |section, test_case| {
+        assert_eq!(section, "");
+
+        let m = bigint::Modulus::from_be_bytes(&test_case.consume_bytes("M")).unwrap();
+        let a = m.elem(&test_case.consume_bytes("A")).unwrap();
+        let b = m.elem(&test_case.consume_bytes("B")).unwrap();
+        let e = test_case.consume_bytes("E");
+
+        let check = |name: &str, actual: bigint::Elem, test_case: &mut test::TestCase| {
+            let expected = test_case.consume_bytes(name);
+            assert!(actual.verify_equal(&m.elem(&expected).unwrap()).is_ok());
+            let mut encoded = vec![0; m.elem_len()];
+            actual.fill_be_bytes(&mut encoded).unwrap();
+            assert_eq!(encoded, expected);
+        };
+        check("Sum", a.add(&b).unwrap(), test_case);
+        check("Difference", a.sub(&b).unwrap(), test_case);
+        check("Product", a.mul(&b).unwrap(), test_case);
+        check("Power", a.exp(&e).unwrap(), test_case);
+
+        let inverse = test_case.consume_bytes("Inverse");
+        for computed in &[a.inverse(&rng).unwrap(), a.inverse_vartime().unwrap()] {
+            let mut encoded = vec![0; m.elem_len()];
+            computed.fill_be_bytes(&mut encoded).unwrap();
+            assert_eq!(encoded, inverse);
+        }
+        Ok(())
+    });
+}
+
+#[test]
+fn bigint_modulus_rejects_invalid() {
+    let p = P256;
+    assert!(bigint::Modulus::from_be_bytes(&p).is_ok());
+
+    // Even.
+    let mut even = p;
+    even[31] = 0x42;
+    assert!(bigint::Modulus::from_be_bytes(&even).is_err());
+
+    // Leading zeros.
+    let mut padded = [0; 33];
+    padded[1..].copy_from_slice(&p);
+    assert!(bigint::Modulus::from_be_bytes(&padded).is_err());
+
+    // Too small.
+    assert!(bigint::Modulus::from_be_bytes(&p[1..]).is_err());
+    assert!(bigint::Modulus::from_be_bytes(&[]).is_err());
+
+    // Too large.
+    let too_large = vec![0xff; bigint::MODULUS_MAX_BITS / 8 + 1];
+    assert!(bigint::Modulus::from_be_bytes(&too_large).is_err());
+}
+
+#[test]
+fn bigint_elem_rejects_invalid() {
+    let rng = rand::SystemRandom::new();
+
+    let m = bigint::Modulus::from_be_bytes(&P512).unwrap();
+    let other = bigint::Modulus::from_be_bytes(&P512).unwrap();
+
+    // Elements and exponents must be less than the modulus.
+    assert!(m.elem(&P512).is_err());
+    assert!(m.elem(&[0xff; 64]).is_err());
+    assert!(m.elem(&[0; 65]).is_err());
+    let a = m.elem(&[2]).unwrap();
+    assert!(a.exp(&P512).is_err());
+    assert!(a.exp(&[1]).is_ok());
+
+    // Zero has no inverse.
+    let zero = m.elem(&[0]).unwrap();
+    assert!(zero.is_zero());
+    assert!(zero.inverse(&rng).is_err());
+    assert!(zero.inverse_vartime().is_err());
+
+    // Elements of different moduli can't be combined, even if the moduli are
+    // equal.
+    let b = other.elem(&[2]).unwrap();
+    assert!(a.add(&b).is_err());
+    assert!(a.sub(&b).is_err());
+    assert!(a.mul(&b).is_err());
+    assert!(a.verify_equal(&b).is_err());
+
+    let mut out = [0; 63];
+    assert!(a.fill_be_bytes(&mut out).is_err());
+}
+
+#[test]
+fn bigint_exp_rejects_unsupported_modulus() {
+    let m = bigint::Modulus::from_be_bytes(&P256).unwrap();
+    let a = m.elem(&[2]).unwrap();
+    assert!(a.exp(&[1]).is_err());
+}
+
+#[test]
+fn bigint_random() {
+    let rng = rand::SystemRandom::new();
+    for p in &[&P256[..], &P512[..]] {
+        let m = bigint::Modulus::from_be_bytes(p).unwrap();
+        let a = m.random(&rng).unwrap();
+        assert!(!a.is_zero());
+        let one = m.elem(&[1]).unwrap();
+        let product = a.mul(&a.inverse(&rng).unwrap()).unwrap();
+        assert!(product.verify_equal(&one).is_ok());
+    }
+}
+
+// The largest primes less than 2**256 and 2**512.
+static P256: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x43,
+];
+static P512: [u8; 64] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfd, 0xc7,
+];
@@ -0,0 +1,254 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+// `unsafe_code` isn't forbidden because the C API is unsafe to call.
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "capi")]
+
+use ring::{aead, capi::*, digest, hmac, test};
+use std::ptr;
+
+// Calls `f` with an output buffer of `max_out_len` bytes, returning the output
+// if `f` succeeds.
+fn output(
+    max_out_len: usize, f: impl FnOnce(*mut u8, *mut usize, usize) -> i32,
+) -> Option<Vec<u8>> {
+    let mut out = vec![0xff; max_out_len];
+    let mut out_len = 0;
+    if f(out.as_mut_ptr(), &mut out_len, max_out_len) != 1 {
+        return None;
+    }
+    out.truncate(out_len);
+    Some(out)
+}
+
+#[test]
+fn capi_digest() {
+    let msg = b"abc";
+    let expected = digest::digest(&digest::SHA256, msg);
+    let alg = RING_digest_sha256();
+    unsafe {
+        assert_eq!(RING_digest_output_len(alg), 32);
+        let out = output(64, |out, out_len, max_out_len| {
+            RING_digest(alg, out, out_len, max_out_len, msg.as_ptr(), msg.len())
+        });
+        assert_eq!(out.unwrap(), expected.as_ref());
+
+        // The output must fit, and the algorithm mustn't be null.
+        assert!(output(31, |out, out_len, max_out_len| {
+            RING_digest(alg, out, out_len, max_out_len, msg.as_ptr(), msg.len())
+        })
+        .is_none());
+        assert!(output(64, |out, out_len, max_out_len| {
+            RING_digest(ptr::null(), out, out_len, max_out_len, msg.as_ptr(), msg.len())
+        })
+        .is_none());
+
+        // Null pointers are allowed for empty inputs.
+        let out = output(64, |out, out_len, max_out_len| {
+            RING_digest(alg, out, out_len, max_out_len, ptr::null(), 0)
+        });
+        assert_eq!(out.unwrap(), digest::digest(&digest::SHA256, b"").as_ref());
+
+        let ctx = RING_digest_ctx_new(RING_digest_sha384());
+        assert!(!ctx.is_null());
+        for b in msg.iter() {
+            assert_eq!(RING_digest_ctx_update(ctx, b, 1), 1);
+        }
+        let out = output(64, |out, out_len, max_out_len| {
+            RING_digest_ctx_final(ctx, out, out_len, max_out_len)
+        });
+        assert_eq!(out.unwrap(), digest::digest(&digest::SHA384, msg).as_ref());
+
+        RING_digest_ctx_free(RING_digest_ctx_new(alg));
+        RING_digest_ctx_free(ptr::null_mut());
+        assert!(RING_digest_ctx_new(ptr::null()).is_null());
+    }
+}
+
+#[test]
+fn capi_hmac() {
+    let key_value = b"key";
+    let msg = b"The quick brown fox jumps over the lazy dog";
+    let expected = hmac::sign(&hmac::SigningKey::new(&digest::SHA256, key_value), msg);
+    unsafe {
+        let key = RING_hmac_key_new(RING_digest_sha256(), key_value.as_ptr(), key_value.len());
+        assert!(!key.is_null());
+        let tag = output(64, |out, out_len, max_out_len| {
+            RING_hmac_sign(key, out, out_len, max_out_len, msg.as_ptr(), msg.len())
+        })
+        .unwrap();
+        assert_eq!(tag, expected.as_ref());
+
+        assert_eq!(RING_hmac_verify(key, msg.as_ptr(), msg.len(), tag.as_ptr(), tag.len()), 1);
+        assert_eq!(RING_hmac_verify(key, msg.as_ptr(), 1, tag.as_ptr(), tag.len()), 0);
+        assert_eq!(RING_hmac_verify(key, msg.as_ptr(), msg.len(), tag.as_ptr(), 31), 0);
+
+        RING_hmac_key_free(key);
+        RING_hmac_key_free(ptr::null_mut());
+    }
+}
+
+#[test]
+fn capi_aead() {
+    let algs = [
+        (RING_aead_aes_128_gcm(), &aead::AES_128_GCM),
+        (RING_aead_aes_256_gcm(), &aead::AES_256_GCM),
+        (RING_aead_chacha20_poly1305(), &aead::CHACHA20_POLY1305),
+    ];
+    let key = [0x42; 32];
+    let nonce = [7; 12];
+    let ad = b"header";
+    let msg = b"attack at dawn";
+
+    for &(c_alg, alg) in algs.iter() {
+        unsafe {
+            assert_eq!(RING_aead_key_len(c_alg), alg.key_len());
+            assert_eq!(RING_aead_nonce_len(c_alg), 12);
+            assert_eq!(RING_aead_tag_len(c_alg), 16);
+            assert!(RING_aead_ctx_new(c_alg, key.as_ptr(), alg.key_len() - 1).is_null());
+
+            let ctx = RING_aead_ctx_new(c_alg, key.as_ptr(), alg.key_len());
+            assert!(!ctx.is_null());
+
+            let seal = |msg: &[u8], max_out_len| {
+                output(max_out_len, |out, out_len, max_out_len| {
+                    RING_aead_seal(
+                        ctx, out, out_len, max_out_len, nonce.as_ptr(), nonce.len(),
+                        msg.as_ptr(), msg.len(), ad.as_ptr(), ad.len(),
+                    )
+                })
+            };
+            let open = |ciphertext: &[u8], max_out_len| {
+                output(max_out_len, |out, out_len, max_out_len| {
+                    RING_aead_open(
+                        ctx, out, out_len, max_out_len, nonce.as_ptr(), nonce.len(),
+                        ciphertext.as_ptr(), ciphertext.len(), ad.as_ptr(), ad.len(),
+                    )
+                })
+            };
+
+            // The output is the same as `aead::seal_in_place()`'s.
+            let sealing_key = aead::SealingKey::new(alg, &key[..alg.key_len()]).unwrap();
+            let mut expected = msg.to_vec();
+            expected.extend_from_slice(&[0; aead::MAX_TAG_LEN]);
+            let len = aead::seal_in_place(&sealing_key, &nonce, ad, &mut expected, 16).unwrap();
+            expected.truncate(len);
+
+            let sealed = seal(&msg[..], 100).unwrap();
+            assert_eq!(sealed, expected);
+            assert!(seal(&msg[..], msg.len() + 15).is_none());
+            assert_eq!(&open(&sealed, 100).unwrap()[..], &msg[..]);
+            assert_eq!(&open(&sealed, msg.len()).unwrap()[..], &msg[..]);
+            assert!(open(&sealed, msg.len() - 1).is_none());
+            assert!(open(&sealed[..15], 100).is_none());
+
+            let mut tampered = sealed.clone();
+            tampered[0] ^= 1;
+            assert!(open(&tampered, 100).is_none());
+
+            // In place.
+            let mut in_out = msg.to_vec();
+            in_out.extend_from_slice(&[0; aead::MAX_TAG_LEN]);
+            let p = in_out.as_mut_ptr();
+            let mut len = 0;
+            assert_eq!(
+                RING_aead_seal(
+                    ctx, p, &mut len, in_out.len(), nonce.as_ptr(), nonce.len(), p, msg.len(),
+                    ad.as_ptr(), ad.len(),
+                ),
+                1
+            );
+            assert_eq!(in_out, expected);
+            assert_eq!(
+                RING_aead_open(
+                    ctx, p, &mut len, in_out.len(), nonce.as_ptr(), nonce.len(), p,
+                    in_out.len(), ad.as_ptr(), ad.len(),
+                ),
+                1
+            );
+            assert_eq!(&in_out[..len], &msg[..]);
+
+            RING_aead_ctx_free(ctx);
+        }
+    }
+    unsafe { RING_aead_ctx_free(ptr::null_mut()) };
+}
+
+// RFC 8032 Section 7.1, TEST 1.
+#[test]
+fn capi_ed25519() {
+    let seed = test::from_hex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60")
+        .unwrap();
+    let public_key =
+        test::from_hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+            .unwrap();
+    let expected_sig = test::from_hex(
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bac\
+         c61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+    )
+    .unwrap();
+
+    unsafe {
+        assert!(RING_ed25519_key_pair_from_seed(seed.as_ptr(), 31).is_null());
+        let key_pair = RING_ed25519_key_pair_from_seed(seed.as_ptr(), seed.len());
+        assert!(!key_pair.is_null());
+
+        let out = output(32, |out, out_len, max_out_len| {
+            RING_ed25519_public_key(key_pair, out, out_len, max_out_len)
+        });
+        assert_eq!(out.unwrap(), public_key);
+
+        let sig = output(64, |out, out_len, max_out_len| {
+            RING_ed25519_sign(key_pair, out, out_len, max_out_len, ptr::null(), 0)
+        })
+        .unwrap();
+        assert_eq!(sig, expected_sig);
+        assert!(output(63, |out, out_len, max_out_len| {
+            RING_ed25519_sign(key_pair, out, out_len, max_out_len, ptr::null(), 0)
+        })
+        .is_none());
+
+        let verify = |msg: &[u8]| {
+            RING_ed25519_verify(
+                public_key.as_ptr(), public_key.len(), msg.as_ptr(), msg.len(), sig.as_ptr(),
+                sig.len(),
+            )
+        };
+        assert_eq!(verify(b""), 1);
+        assert_eq!(verify(b"x"), 0);
+
+        RING_ed25519_key_pair_free(key_pair);
+        RING_ed25519_key_pair_free(ptr::null_mut());
+
+        let garbage = [0u8; 10];
+        assert!(RING_ed25519_key_pair_from_pkcs8(garbage.as_ptr(), garbage.len()).is_null());
+    }
+}
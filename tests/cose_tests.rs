@@ -0,0 +1,121 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{agreement, cose, error, rand, test};
+
+#[test]
+fn cose_tests() {
+    test::from_file("tests/cose_tests.txt", |section, test_case| {
+        assert_eq!(section, "");
+        let cose_key = test_case.consume_bytes("COSE");
+        let cose_key = untrusted::Input::from(&cose_key);
+        if let Some(expected) = test_case.consume_optional_string("Error") {
+            let actual = cose::parse_public_key(cose_key).unwrap_err();
+            assert_eq!(actual.to_string(), expected);
+            return Ok(());
+        }
+        let spki = test_case.consume_bytes("SPKI");
+        let alg = test_case
+            .consume_optional_string("Alg")
+            .map(|alg| alg.parse::<i64>().unwrap());
+        let agreement_alg = test_case
+            .consume_optional_string("Agreement")
+            .map(|alg| match &alg[..] {
+                "X25519" => &agreement::X25519,
+                "ECDH_P256" => &agreement::ECDH_P256,
+                "ECDH_P384" => &agreement::ECDH_P384,
+                _ => unreachable!(),
+            });
+
+        let key = cose::parse_public_key(cose_key).unwrap();
+        assert_eq!(key.public_key().to_spki_der().as_ref(), &spki[..]);
+        assert_eq!(key.algorithm(), alg);
+        assert_eq!(key.agreement_algorithm(), agreement_alg);
+        Ok(())
+    });
+}
+
+#[test]
+fn cose_read_public_key_stops_at_end_of_key() {
+    // An OKP X25519 key followed by the start of a WebAuthn extensions map.
+    let mut input = vec![0xa3, 0x01, 0x01, 0x20, 0x04, 0x21, 0x58, 0x20];
+    input.extend_from_slice(&[0x09; 32]);
+    input.extend_from_slice(&[0xa1, 0x6b]);
+
+    let mut reader = untrusted::Reader::new(untrusted::Input::from(&input));
+    let key = cose::read_public_key(&mut reader).unwrap();
+    assert_eq!(key.public_key().as_ref(), &[0x09; 32]);
+    assert_eq!(reader.skip_to_end(), untrusted::Input::from(&[0xa1, 0x6b]));
+
+    assert!(cose::parse_public_key(untrusted::Input::from(&input)).is_err());
+}
+
+#[test]
+fn cose_agreement() {
+    let rng = rand::SystemRandom::new();
+    for &(alg, crv) in &[(&agreement::ECDH_P256, 1), (&agreement::ECDH_P384, 2)] {
+        let peer_private_key = agreement::EphemeralPrivateKey::generate(alg, &rng).unwrap();
+        let peer_public_key = peer_private_key.public_key().unwrap();
+        let point = peer_public_key.as_ref();
+        let (x, y) = point[1..].split_at((point.len() - 1) / 2);
+
+        let mut cose_key = vec![0xa4, 0x01, 0x02, 0x20, crv, 0x21, 0x58, x.len() as u8];
+        cose_key.extend_from_slice(x);
+        cose_key.extend_from_slice(&[0x22, 0x58, y.len() as u8]);
+        cose_key.extend_from_slice(y);
+        let key = cose::parse_public_key(untrusted::Input::from(&cose_key)).unwrap();
+        assert_eq!(key.public_key().as_ref(), point);
+        assert_eq!(key.agreement_algorithm(), Some(alg));
+
+        let my_private_key = agreement::EphemeralPrivateKey::generate(alg, &rng).unwrap();
+        let my_public_key = my_private_key.public_key().unwrap();
+        let mine = agreement::agree_ephemeral(
+            my_private_key,
+            key.agreement_algorithm().unwrap(),
+            untrusted::Input::from(key.public_key().as_ref()),
+            error::Unspecified,
+            |agreed_value| Ok(Vec::from(agreed_value)),
+        )
+        .unwrap();
+        let theirs = agreement::agree_ephemeral(
+            peer_private_key,
+            alg,
+            untrusted::Input::from(my_public_key.as_ref()),
+            error::Unspecified,
+            |agreed_value| Ok(Vec::from(agreed_value)),
+        )
+        .unwrap();
+        assert_eq!(mine, theirs);
+    }
+}
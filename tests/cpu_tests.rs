@@ -0,0 +1,105 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{aead, cpu, digest, error, test};
+
+// This is the only test in this file, so that no other test can use *ring*
+// before the features are disabled.
+#[test]
+fn cpu_disable_features() {
+    cpu::disable_features(
+        "aes,pclmulqdq,pmull,ssse3,avx,avx2,avx512f,avx512bw,avx512ifma,vaes,vpclmulqdq,movbe,\
+         neon,sha2,rdrand,rdseed",
+    )
+    .unwrap();
+    let features = cpu::features();
+    assert_eq!(format!("{:?}", features), "{}");
+
+    // The features can't be changed once they've been detected.
+    assert!(cpu::disable_features("aes").is_err());
+
+    // The self-tests pass with the fallback implementations too, which
+    // nothing requires outside SGX enclaves.
+    let capabilities = ring::init_checked().unwrap();
+    assert_eq!(capabilities.features(), features);
+    assert!(!capabilities.hardware_aes_gcm());
+
+    // The fallback implementations must produce the same results.
+    test_aead(&aead::AES_128_GCM, "tests/aead_aes_128_gcm_tests.txt");
+    test_aead(&aead::AES_256_GCM, "tests/aead_aes_256_gcm_tests.txt");
+    test_aead(&aead::CHACHA20_POLY1305, "tests/aead_chacha20_poly1305_tests.txt");
+
+    test::from_file("tests/digest_tests.txt", |section, test_case| {
+        assert_eq!(section, "");
+        let digest_alg = test_case.consume_digest_alg("Hash").unwrap();
+        let input = test_case.consume_bytes("Input");
+        let repeat = test_case.consume_usize("Repeat");
+        let expected = test_case.consume_bytes("Output");
+
+        let mut ctx = digest::Context::new(digest_alg);
+        for _ in 0..repeat {
+            ctx.update(&input);
+        }
+        assert_eq!(ctx.finish().as_ref(), &expected[..]);
+        Ok(())
+    });
+}
+
+fn test_aead(aead_alg: &'static aead::Algorithm, file_path: &str) {
+    test::from_file(file_path, |section, test_case| {
+        assert_eq!(section, "");
+        let key_bytes = test_case.consume_bytes("KEY");
+        let nonce = test_case.consume_bytes("NONCE");
+        let plaintext = test_case.consume_bytes("IN");
+        let ad = test_case.consume_bytes("AD");
+        let mut ct = test_case.consume_bytes("CT");
+        let tag = test_case.consume_bytes("TAG");
+        let error = test_case.consume_optional_string("FAILS");
+        if error.is_some() {
+            return Ok(());
+        }
+        ct.extend(tag);
+
+        let mut in_out = plaintext.clone();
+        in_out.resize(plaintext.len() + aead_alg.tag_len(), 0);
+        let s_key = aead::SealingKey::new(aead_alg, &key_bytes)?;
+        let len = aead::seal_in_place(&s_key, &nonce, &ad, &mut in_out, aead_alg.tag_len())?;
+        assert_eq!(&in_out[..len], &ct[..]);
+
+        let o_key = aead::OpeningKey::new(aead_alg, &key_bytes)?;
+        let opened = aead::open_in_place(&o_key, &nonce, &ad, 0, &mut in_out)?;
+        assert_eq!(&opened[..], &plaintext[..]);
+        Ok::<(), error::Unspecified>(())
+    });
+}
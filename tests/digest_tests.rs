@@ -30,6 +30,7 @@
     variant_size_differences,
     warnings
 )]
+#![cfg(feature = "use_heap")]
 
 use ring::{digest, test};
 
@@ -222,6 +223,22 @@ test_i_u_f!(digest_test_i_u_f_sha256, digest::SHA256);
 test_i_u_f!(digest_test_i_u_f_sha384, digest::SHA384);
 test_i_u_f!(digest_test_i_u_f_sha512, digest::SHA512);
 
+#[test]
+fn digest_test_update_nontemporal() {
+    let input: Vec<u8> = (0..(3 * 4096 + 200)).map(|i| i as u8).collect();
+    for alg in &[&digest::SHA1, &digest::SHA256, &digest::SHA384, &digest::SHA512] {
+        for &len in &[0, 1, 64, 4095, 4096, 4097, 2 * 4096, input.len()] {
+            for &prefix_len in &[0, 1, 100] {
+                let mut ctx = digest::Context::new(alg);
+                ctx.update(&input[..prefix_len]);
+                ctx.update_nontemporal(&input[prefix_len..len.max(prefix_len)]);
+                let expected = digest::digest(alg, &input[..len.max(prefix_len)]);
+                assert_eq!(ctx.finish().as_ref(), expected.as_ref());
+            }
+        }
+    }
+}
+
 /// See https://bugzilla.mozilla.org/show_bug.cgi?id=610162. This tests the
 /// calculation of 8GB of the byte 123.
 ///
@@ -355,3 +372,11 @@ fn digest_test_fmt() {
         &format!("{:?}", digest::digest(&digest::SHA512_256, b"hello, world"))
     );
 }
+
+#[test]
+fn digest_traits() {
+    test::compile_time_assert_send::<digest::Context>();
+    test::compile_time_assert_sync::<digest::Context>();
+    test::compile_time_assert_send::<digest::Digest>();
+    test::compile_time_assert_sync::<digest::Digest>();
+}
@@ -0,0 +1,145 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{dnssec, test};
+
+#[test]
+fn dnssec_algorithm_from_number() {
+    for &(number, alg) in &[
+        (8, &dnssec::RSASHA256),
+        (13, &dnssec::ECDSAP256SHA256),
+        (14, &dnssec::ECDSAP384SHA384),
+        (15, &dnssec::ED25519),
+    ] {
+        assert_eq!(dnssec::algorithm_from_number(number), Some(alg));
+        assert_eq!(alg.number(), number);
+    }
+
+    // RSAMD5, DSA, RSASHA1, ECC-GOST, and ED448 aren't supported.
+    for &number in &[0, 1, 3, 5, 7, 10, 12, 16, 255] {
+        assert_eq!(dnssec::algorithm_from_number(number), None);
+    }
+}
+
+#[test]
+fn dnssec_verify_rrset() {
+    test::from_file("tests/dnssec_tests.txt", |section, test_case| {
+        assert_eq!(section, "");
+        let dnskey_owner = test_case.consume_bytes("DNSKEYOwner");
+        let dnskey = test_case.consume_bytes("DNSKEY");
+        let expected_key_tag = test_case.consume_usize("KeyTag");
+        let owner = test_case.consume_bytes("Owner");
+        let rr_type = test_case.consume_usize("Type");
+        let rrsig = test_case.consume_bytes("RRSIG");
+        let rdatas = test_case
+            .consume_string("RDatas")
+            .split(',')
+            .map(|rdata| test::from_hex(rdata).unwrap())
+            .collect::<Vec<_>>();
+        let now = test_case.consume_usize("Now");
+        let expected_result = test_case.consume_string("Result");
+
+        let dnskey = dnssec::DnsKey::from_rdata(
+            untrusted::Input::from(&dnskey_owner),
+            untrusted::Input::from(&dnskey),
+        )
+        .unwrap();
+        assert_eq!(usize::from(dnskey.key_tag()), expected_key_tag);
+
+        let rrsig = dnssec::Rrsig::from_rdata(untrusted::Input::from(&rrsig)).unwrap();
+        assert_eq!(rrsig.key_tag(), dnskey.key_tag());
+
+        let rdatas = rdatas
+            .iter()
+            .map(|rdata| untrusted::Input::from(rdata))
+            .collect::<Vec<_>>();
+        let actual_result = dnssec::verify_rrset(
+            &dnskey,
+            &rrsig,
+            untrusted::Input::from(&owner),
+            rr_type as u16,
+            1,
+            &rdatas,
+            now as u32,
+        );
+        assert_eq!(actual_result.is_ok(), expected_result == "OK");
+
+        // The class is covered by the signature.
+        if actual_result.is_ok() {
+            assert!(dnssec::verify_rrset(
+                &dnskey,
+                &rrsig,
+                untrusted::Input::from(&owner),
+                rr_type as u16,
+                3,
+                &rdatas,
+                now as u32,
+            )
+            .is_err());
+        }
+
+        Ok(())
+    });
+}
+
+#[test]
+fn dnssec_rrsig_from_rdata() {
+    // The RRSIG from RFC 8080 Section 6.1.
+    let rdata = test::from_hex(
+        "000f0f0200000e1055d4fc6055b94ce00e1d076578616d706c6503636f6d00\
+         a0bf64ac9ba7ef17c138859c1878bb99a839fe1759aca5b0d798cf1ab1e98d07\
+         9102f4ddb3368f0fe40bb377f1f00e0cddedb799167d56b6e932783072ba8d02",
+    )
+    .unwrap();
+    let rrsig = dnssec::Rrsig::from_rdata(untrusted::Input::from(&rdata)).unwrap();
+    assert_eq!(rrsig.type_covered(), 15);
+    assert_eq!(rrsig.algorithm_number(), 15);
+    assert_eq!(rrsig.labels(), 2);
+    assert_eq!(rrsig.original_ttl(), 3600);
+    assert_eq!(rrsig.expiration(), 1440021600);
+    assert_eq!(rrsig.inception(), 1438207200);
+    assert_eq!(rrsig.key_tag(), 3613);
+    assert_eq!(
+        rrsig.signer_name(),
+        untrusted::Input::from(b"\x07example\x03com\x00")
+    );
+    assert_eq!(rrsig.signature().len(), 64);
+    assert!(rrsig.is_valid_at(1439000000));
+    assert!(!rrsig.is_valid_at(1438207199));
+    assert!(!rrsig.is_valid_at(1440021601));
+
+    // Truncated within the fixed fields and within the signer's name.
+    assert!(dnssec::Rrsig::from_rdata(untrusted::Input::from(&rdata[..17])).is_err());
+    assert!(dnssec::Rrsig::from_rdata(untrusted::Input::from(&rdata[..25])).is_err());
+}
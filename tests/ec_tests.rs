@@ -0,0 +1,154 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "unstable_ec")]
+
+use ring::{ec, rand, test};
+
+#[test]
+fn ec_traits() {
+    test::compile_time_assert_send::<ec::Point>();
+    test::compile_time_assert_sync::<ec::Point>();
+    test::compile_time_assert_send::<ec::Scalar>();
+    test::compile_time_assert_sync::<ec::Scalar>();
+    test::compile_time_assert_copy::<ec::EncodedPoint>();
+}
+
+#[test]
+fn ec_test_vectors() {
+    test::from_file("tests/ec_tests.txt", |section, test_case| {
+        let group = group_from_name(&test_case.consume_string("Group"));
+        match section {
+            "Operations" => {
+                let a = ec::Scalar::from_bytes(group, &test_case.consume_bytes("A")).unwrap();
+                let b = ec::Scalar::from_bytes(group, &test_case.consume_bytes("B")).unwrap();
+                let ag_bytes = test_case.consume_bytes("AG");
+                let ag_compressed = test_case.consume_bytes("AGCompressed");
+                let bg_bytes = test_case.consume_bytes("BG");
+
+                let ag = ec::Point::mul_base(&a).unwrap();
+                assert_eq!(ag.encode().as_ref(), &ag_bytes[..]);
+                assert_eq!(ag.encode_compressed().as_ref(), &ag_compressed[..]);
+                for encoded in &[&ag_bytes, &ag_compressed] {
+                    let p = ec::Point::from_bytes(group, encoded).unwrap();
+                    assert_eq!(p.encode().as_ref(), &ag_bytes[..]);
+                }
+                let bg = ec::Point::from_bytes(group, &bg_bytes).unwrap();
+
+                let abg = ag.mul(&b).unwrap();
+                assert_eq!(abg.encode().as_ref(), &test_case.consume_bytes("ABG")[..]);
+                assert_eq!(bg.mul(&a).unwrap().encode().as_ref(), abg.encode().as_ref());
+
+                let sum = ag.add(&bg).unwrap();
+                assert_eq!(sum.encode().as_ref(), &test_case.consume_bytes("Sum")[..]);
+                let double = ag.add(&ag).unwrap();
+                let expected = test_case.consume_bytes("Double");
+                assert_eq!(double.encode().as_ref(), &expected[..]);
+
+                let neg = ag.neg();
+                assert_eq!(neg.encode().as_ref(), &test_case.consume_bytes("Neg")[..]);
+                assert!(ag.add(&neg).is_err());
+                assert_eq!(neg.neg().encode().as_ref(), ag.encode().as_ref());
+            },
+
+            "InvalidPoints" => {
+                let point = test_case.consume_bytes("Point");
+                assert!(ec::Point::from_bytes(group, &point).is_err());
+            },
+
+            "InvalidScalars" => {
+                let scalar = test_case.consume_bytes("Scalar");
+                assert!(ec::Scalar::from_bytes(group, &scalar).is_err());
+            },
+
+            _ => unreachable!(),
+        }
+        Ok(())
+    });
+}
+
+#[test]
+fn ec_scalar_round_trip() {
+    let rng = rand::SystemRandom::new();
+    for group in GROUPS.iter() {
+        let a = ec::Scalar::generate(group, &rng).unwrap();
+        assert_eq!(a.as_ref().len(), group.scalar_len());
+        let b = ec::Scalar::from_bytes(group, a.as_ref()).unwrap();
+        assert_eq!(a.as_ref(), b.as_ref());
+
+        let p = ec::Point::mul_base(&a).unwrap();
+        assert_eq!(p.encode().as_ref().len(), group.point_len());
+        assert_eq!(
+            p.encode_compressed().as_ref().len(),
+            group.compressed_point_len()
+        );
+    }
+}
+
+#[test]
+fn ec_scalar_from_bytes_reduced() {
+    let rng = rand::SystemRandom::new();
+    for group in GROUPS.iter() {
+        let a = ec::Scalar::generate(group, &rng).unwrap();
+        let b = ec::Scalar::from_bytes_reduced(group, a.as_ref()).unwrap();
+        assert_eq!(a.as_ref(), b.as_ref());
+
+        let _ = ec::Scalar::from_bytes_reduced(group, &vec![0xff; 2 * group.scalar_len()]).unwrap();
+        assert!(ec::Scalar::from_bytes_reduced(group, &vec![0; 2 * group.scalar_len()]).is_err());
+        assert!(
+            ec::Scalar::from_bytes_reduced(group, &vec![1; 2 * group.scalar_len() + 1]).is_err()
+        );
+    }
+}
+
+#[test]
+fn ec_mixed_groups() {
+    let rng = rand::SystemRandom::new();
+    let a = ec::Scalar::generate(&ec::P256, &rng).unwrap();
+    let b = ec::Scalar::generate(&ec::P384, &rng).unwrap();
+    let p = ec::Point::mul_base(&a).unwrap();
+    let q = ec::Point::mul_base(&b).unwrap();
+    assert!(p.add(&q).is_err());
+    assert!(p.mul(&b).is_err());
+    assert!(ec::Point::from_bytes(&ec::P384, p.encode().as_ref()).is_err());
+}
+
+static GROUPS: [&ec::Group; 3] = [&ec::P256, &ec::P384, &ec::EDWARDS25519];
+
+fn group_from_name(name: &str) -> &'static ec::Group {
+    match name {
+        "P-256" => &ec::P256,
+        "P-384" => &ec::P384,
+        "edwards25519" => &ec::EDWARDS25519,
+        _ => unreachable!(),
+    }
+}
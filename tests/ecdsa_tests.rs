@@ -30,12 +30,12 @@
     variant_size_differences,
     warnings
 )]
+#![cfg(feature = "use_heap")]
 
 use ring::{rand, signature, test};
 
 // ECDSA *signing* tests are in src/ec/ecdsa/signing.rs.
 
-#[cfg(feature = "use_heap")]
 #[test]
 fn ecdsa_from_pkcs8_test() {
     test::from_file("tests/ecdsa_from_pkcs8_tests.txt", |section, test_case| {
@@ -119,7 +119,6 @@ fn ecdsa_generate_pkcs8_test() {
         println!();
         println!();
 
-        #[cfg(feature = "use_heap")]
         let _ =
             signature::key_pair_from_pkcs8(*alg, untrusted::Input::from(pkcs8.as_ref())).unwrap();
     }
@@ -157,6 +156,9 @@ fn signature_ecdsa_verify_asn1_test() {
         let actual_result = signature::verify(alg, public_key, msg, sig);
         assert_eq!(actual_result.is_ok(), expected_result == "P (0 )");
 
+        let batch_result = signature::verify_batch_same_key(alg, public_key, &[(msg, sig)]);
+        assert_eq!(batch_result.is_ok(), actual_result.is_ok());
+
         Ok(())
     });
 }
@@ -193,7 +195,103 @@ fn signature_ecdsa_verify_fixed_test() {
             let actual_result = signature::verify(alg, public_key, msg, sig);
             assert_eq!(actual_result.is_ok(), expected_result == "P (0 )");
 
+            let batch_result = signature::verify_batch_same_key(alg, public_key, &[(msg, sig)]);
+            assert_eq!(batch_result.is_ok(), actual_result.is_ok());
+
             Ok(())
         },
     );
 }
+
+#[test]
+fn signature_ecdsa_verify_batch_same_key_test() {
+    let rng = rand::SystemRandom::new();
+
+    let algs: [(_, &signature::ECDSAVerification); 4] = [
+        (&signature::ECDSA_P256_SHA256_ASN1_SIGNING, &signature::ECDSA_P256_SHA256_ASN1),
+        (&signature::ECDSA_P256_SHA256_FIXED_SIGNING, &signature::ECDSA_P256_SHA256_FIXED),
+        (&signature::ECDSA_P384_SHA384_ASN1_SIGNING, &signature::ECDSA_P384_SHA384_ASN1),
+        (&signature::ECDSA_P384_SHA384_FIXED_SIGNING, &signature::ECDSA_P384_SHA384_FIXED),
+    ];
+    for &(signing_alg, verification_alg) in algs.iter() {
+        let pkcs8 = signature::ECDSAKeyPair::generate_pkcs8(signing_alg, &rng).unwrap();
+        let key_pair =
+            signature::ECDSAKeyPair::from_pkcs8(signing_alg, untrusted::Input::from(pkcs8.as_ref()))
+                .unwrap();
+        let public_key = key_pair.public_key();
+        let public_key = untrusted::Input::from(public_key.as_ref());
+
+        let msgs: Vec<Vec<u8>> = (0u8..10).map(|i| vec![i; usize::from(i)]).collect();
+        let mut sigs: Vec<Vec<u8>> = msgs
+            .iter()
+            .map(|msg| {
+                let mut sig = [0u8; signature::ECDSA_MAX_SIGNATURE_LEN];
+                let len = key_pair.sign_into(&rng, untrusted::Input::from(msg), &mut sig).unwrap();
+                sig[..len].to_vec()
+            })
+            .collect();
+
+        let verify = |sigs: &[Vec<u8>]| {
+            let batch: Vec<_> = msgs
+                .iter()
+                .zip(sigs)
+                .map(|(msg, sig)| (untrusted::Input::from(msg), untrusted::Input::from(sig)))
+                .collect();
+            signature::verify_batch_same_key(verification_alg, public_key, &batch)
+        };
+        assert!(verify(&sigs).is_ok());
+        assert!(verify(&[]).is_ok());
+
+        // A single invalid signature makes the whole batch invalid.
+        let last = sigs.len() - 1;
+        sigs.swap(0, last);
+        assert!(verify(&sigs).is_err());
+
+        // The public key is checked even when there are no signatures.
+        let mut invalid_public_key = public_key.as_slice_less_safe().to_vec();
+        invalid_public_key[1] ^= 1;
+        let invalid_public_key = untrusted::Input::from(&invalid_public_key);
+        let result = signature::verify_batch_same_key(verification_alg, invalid_public_key, &[]);
+        assert!(result.is_err());
+    }
+}
+
+#[test]
+fn signature_ecdsa_verify_compressed_public_key_test() {
+    let rng = rand::SystemRandom::new();
+
+    let algs: [(_, &signature::ECDSAVerification); 2] = [
+        (&signature::ECDSA_P256_SHA256_ASN1_SIGNING, &signature::ECDSA_P256_SHA256_ASN1),
+        (&signature::ECDSA_P384_SHA384_FIXED_SIGNING, &signature::ECDSA_P384_SHA384_FIXED),
+    ];
+    for &(signing_alg, verification_alg) in algs.iter() {
+        let pkcs8 = signature::ECDSAKeyPair::generate_pkcs8(signing_alg, &rng).unwrap();
+        let key_pair =
+            signature::ECDSAKeyPair::from_pkcs8(signing_alg, untrusted::Input::from(pkcs8.as_ref()))
+                .unwrap();
+        let public_key = key_pair.public_key();
+        let compressed = key_pair.public_key_compressed();
+        let (public_key, compressed) = (public_key.as_ref(), compressed.as_ref());
+
+        // The compressed form is the X coordinate and the parity of Y.
+        assert_eq!(compressed.len(), 1 + (public_key.len() - 1) / 2);
+        assert_eq!(compressed[0], 2 | (public_key[public_key.len() - 1] & 1));
+        assert_eq!(&compressed[1..], &public_key[1..compressed.len()]);
+
+        let msg = untrusted::Input::from(b"hello, world");
+        let mut sig = [0u8; signature::ECDSA_MAX_SIGNATURE_LEN];
+        let len = key_pair.sign_into(&rng, msg, &mut sig).unwrap();
+        let sig = untrusted::Input::from(&sig[..len]);
+
+        let verify = |public_key: &[u8]| {
+            signature::verify(verification_alg, untrusted::Input::from(public_key), msg, sig)
+        };
+        assert!(verify(public_key).is_ok());
+        assert!(verify(compressed).is_ok());
+
+        // The other Y coordinate is the negated public key.
+        let mut negated = compressed.to_vec();
+        negated[0] ^= 1;
+        assert!(verify(&negated).is_err());
+    }
+}
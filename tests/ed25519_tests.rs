@@ -30,6 +30,7 @@
     variant_size_differences,
     warnings
 )]
+#![cfg(feature = "use_heap")]
 
 use ring::{
     signature::{self, Ed25519KeyPair},
@@ -71,6 +72,16 @@ fn test_signature_ed25519() {
         let actual_sig = key_pair.sign(&msg);
         assert_eq!(&expected_sig[..], actual_sig.as_ref());
 
+        let mut out = [0u8; signature::ED25519_SIGNATURE_LEN + 1];
+        assert_eq!(
+            key_pair.sign_into(&msg, &mut out).unwrap(),
+            signature::ED25519_SIGNATURE_LEN
+        );
+        assert_eq!(&expected_sig[..], &out[..signature::ED25519_SIGNATURE_LEN]);
+        assert!(key_pair
+            .sign_into(&msg, &mut out[..(signature::ED25519_SIGNATURE_LEN - 1)])
+            .is_err());
+
         // Test Signature verification.
         assert!(signature::verify(
             &signature::ED25519,
@@ -79,10 +90,61 @@ fn test_signature_ed25519() {
             untrusted::Input::from(&expected_sig)
         )
         .is_ok());
+
+        let precomputed = signature::Ed25519PublicKeyPrecomputed::new(public_key).unwrap();
+        assert_eq!(precomputed.as_ref(), public_key.as_slice_less_safe());
+        assert!(precomputed
+            .verify(untrusted::Input::from(&msg), untrusted::Input::from(&expected_sig))
+            .is_ok());
+        for i in 0..expected_sig.len() {
+            let mut modified_sig = expected_sig.clone();
+            modified_sig[i] ^= 1;
+            assert!(precomputed
+                .verify(untrusted::Input::from(&msg), untrusted::Input::from(&modified_sig))
+                .is_err());
+        }
+        assert!(precomputed
+            .verify(untrusted::Input::from(&msg), untrusted::Input::from(&expected_sig[1..]))
+            .is_err());
         Ok(())
     });
 }
 
+#[test]
+fn test_ed25519_public_key_precomputed() {
+    const PUBLIC_KEY: &[u8] = include_bytes!("ed25519_test_public_key.bin");
+
+    let public_key = untrusted::Input::from(PUBLIC_KEY);
+    assert!(signature::Ed25519PublicKeyPrecomputed::new(public_key).is_ok());
+    // The encoding of y = 2 isn't a point on the curve.
+    let mut not_on_curve = [0u8; 32];
+    not_on_curve[0] = 2;
+    for public_key in &[&PUBLIC_KEY[..31], &not_on_curve[..]] {
+        assert_eq!(
+            signature::Ed25519PublicKeyPrecomputed::new(untrusted::Input::from(public_key))
+                .err()
+                .map(|e| e.description_()),
+            Some("InvalidEncoding")
+        );
+    }
+
+    // The precomputed key agrees with `signature::verify()` for many messages.
+    let rng = ring::rand::SystemRandom::new();
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let key_pair = Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&pkcs8)).unwrap();
+    let public_key = untrusted::Input::from(key_pair.public_key_bytes());
+    let precomputed = signature::Ed25519PublicKeyPrecomputed::new(public_key).unwrap();
+    for len in 0..64 {
+        let msg = vec![len as u8; len];
+        let sig = key_pair.sign(&msg);
+        let msg = untrusted::Input::from(&msg);
+        let sig = untrusted::Input::from(sig.as_ref());
+        assert!(signature::verify(&signature::ED25519, public_key, msg, sig).is_ok());
+        assert!(precomputed.verify(msg, sig).is_ok());
+        assert!(precomputed.verify(untrusted::Input::from(b"other"), sig).is_err());
+    }
+}
+
 #[test]
 fn test_ed25519_from_seed_and_public_key_misuse() {
     const PRIVATE_KEY: &[u8] = include_bytes!("ed25519_test_private_key.bin");
@@ -116,6 +178,40 @@ fn test_ed25519_from_seed_and_public_key_misuse() {
     .is_err());
 }
 
+#[test]
+fn test_ed25519_from_seed_unchecked() {
+    const PRIVATE_KEY: &[u8] = include_bytes!("ed25519_test_private_key.bin");
+    const PUBLIC_KEY: &[u8] = include_bytes!("ed25519_test_public_key.bin");
+    assert_eq!(PRIVATE_KEY.len(), signature::ED25519_SEED_LEN);
+
+    let key_pair = Ed25519KeyPair::from_seed_unchecked(untrusted::Input::from(PRIVATE_KEY)).unwrap();
+    assert_eq!(key_pair.public_key_bytes(), PUBLIC_KEY);
+
+    // Truncated and extended seeds.
+    assert_eq!(
+        Ed25519KeyPair::from_seed_unchecked(untrusted::Input::from(&PRIVATE_KEY[..31]))
+            .err()
+            .map(|e| e.description_()),
+        Some("InvalidEncoding")
+    );
+    let mut extended = PRIVATE_KEY.to_vec();
+    extended.push(0);
+    assert!(Ed25519KeyPair::from_seed_unchecked(untrusted::Input::from(&extended)).is_err());
+
+    // A corrupted public key is detected by the checked variant.
+    let mut corrupted = PUBLIC_KEY.to_vec();
+    corrupted[0] ^= 1;
+    assert_eq!(
+        Ed25519KeyPair::from_seed_and_public_key(
+            untrusted::Input::from(PRIVATE_KEY),
+            untrusted::Input::from(&corrupted)
+        )
+        .err()
+        .map(|e| e.description_()),
+        Some("InconsistentComponents")
+    );
+}
+
 #[test]
 fn test_ed25519_from_pkcs8_unchecked() {
     // Just test that we can parse the input.
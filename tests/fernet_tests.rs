@@ -0,0 +1,97 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{
+    fernet,
+    io::base64::{self, Alphabet, Padding},
+    rand,
+};
+
+static BASE64: base64::Config = base64::Config::new(Alphabet::UrlSafe, Padding::Required);
+
+#[test]
+fn fernet_round_trip() {
+    let rng = rand::SystemRandom::new();
+    let key = fernet::Key::from_base64(&fernet::generate_key(&rng).unwrap()).unwrap();
+    let other_key = fernet::Key::from_base64(&fernet::generate_key(&rng).unwrap()).unwrap();
+    const NOW: u64 = 1_000_000_000;
+
+    for &plaintext in &[&b""[..], b"secret", &[0x42; 1000][..]] {
+        let token = fernet::encrypt(&key, plaintext, NOW, &rng).unwrap();
+        assert_eq!(fernet::decrypt(&key, &token, None, NOW).unwrap(), plaintext);
+        assert!(fernet::decrypt(&other_key, &token, None, NOW).is_err());
+
+        // The IV is random.
+        assert_ne!(token, fernet::encrypt(&key, plaintext, NOW, &rng).unwrap());
+
+        // Every byte is authenticated.
+        let decoded = base64::decode(&token, &BASE64).unwrap();
+        for i in 0..decoded.len() {
+            let mut modified = decoded.clone();
+            modified[i] ^= 1;
+            let modified = base64::encode(&modified, &BASE64);
+            assert!(fernet::decrypt(&key, &modified, None, NOW).is_err());
+        }
+    }
+}
+
+#[test]
+fn fernet_ttl() {
+    let rng = rand::SystemRandom::new();
+    let key = fernet::Key::from_base64(&fernet::generate_key(&rng).unwrap()).unwrap();
+    const NOW: u64 = 1_000_000_000;
+    let token = fernet::encrypt(&key, b"secret", NOW, &rng).unwrap();
+
+    let ttl = Some(100);
+    assert!(fernet::decrypt(&key, &token, ttl, NOW + 100).is_ok());
+    assert!(fernet::decrypt(&key, &token, ttl, NOW + 101).is_err());
+    assert!(fernet::decrypt(&key, &token, ttl, NOW - fernet::MAX_CLOCK_SKEW).is_ok());
+    assert!(fernet::decrypt(&key, &token, ttl, NOW - fernet::MAX_CLOCK_SKEW - 1).is_err());
+
+    // Without a TTL, the timestamp isn't checked.
+    assert!(fernet::decrypt(&key, &token, None, 0).is_ok());
+    assert!(fernet::decrypt(&key, &token, None, u64::max_value()).is_ok());
+    assert!(fernet::decrypt(&key, &token, Some(u64::max_value()), u64::max_value()).is_ok());
+}
+
+#[test]
+fn fernet_key_from_base64() {
+    let rng = rand::SystemRandom::new();
+    let encoded = fernet::generate_key(&rng).unwrap();
+    assert_eq!(encoded.len(), 44);
+    assert!(fernet::Key::from_base64(&encoded).is_ok());
+    assert!(fernet::Key::from_base64(encoded.trim_end_matches('=')).is_err());
+    assert!(fernet::Key::from_base64(&encoded[..40]).is_err());
+    assert!(fernet::Key::from_base64("").is_err());
+}
@@ -30,8 +30,9 @@
     variant_size_differences,
     warnings
 )]
+#![cfg(feature = "use_heap")]
 
-use ring::{error, hkdf, hmac, test};
+use ring::{digest, error, hkdf, hmac, test};
 
 #[test]
 fn hkdf_tests() {
@@ -56,6 +57,36 @@ fn hkdf_tests() {
         hkdf::extract_and_expand(&salt, &secret, &info, &mut out);
         assert_eq!(out, expected_out);
 
+        let mut out = vec![0u8; expected_out.len()];
+        assert!(hkdf::try_extract_and_expand(&salt, &secret, &info, &mut out).is_ok());
+        assert_eq!(out, expected_out);
+
         Ok(())
     });
 }
+
+#[test]
+fn hkdf_output_len_limit_test() {
+    for &digest_alg in &[&digest::SHA1, &digest::SHA256, &digest::SHA384, &digest::SHA512] {
+        let prk = hmac::SigningKey::new(digest_alg, b"prk");
+        let max_len = 255 * digest_alg.output_len;
+
+        let mut max = vec![0u8; max_len];
+        assert!(hkdf::try_expand(&prk, b"info", &mut max).is_ok());
+        let mut short = vec![0u8; digest_alg.output_len + 1];
+        hkdf::expand(&prk, b"info", &mut short);
+        assert_eq!(&max[..short.len()], &short[..]);
+
+        let mut too_long = vec![0u8; max_len + 1];
+        assert!(hkdf::try_expand(&prk, b"info", &mut too_long).is_err());
+        assert!(hkdf::try_extract_and_expand(&prk, b"secret", b"info", &mut too_long).is_err());
+        assert!(hkdf::try_expand(&prk, b"info", &mut []).is_ok());
+    }
+}
+
+#[test]
+#[should_panic]
+fn hkdf_expand_too_long_panics_test() {
+    let prk = hmac::SigningKey::new(&digest::SHA256, b"prk");
+    hkdf::expand(&prk, b"info", &mut [0u8; (255 * 32) + 1]);
+}
@@ -30,6 +30,7 @@
     variant_size_differences,
     warnings
 )]
+#![cfg(feature = "use_heap")]
 
 use ring::{digest, error, hmac, test};
 
@@ -76,6 +77,15 @@ fn hmac_test_case_inner(
         assert_eq!(is_ok, hmac::verify(&v_key, input, output).is_ok());
     }
 
+    // One-shot API, into a caller-provided buffer.
+    {
+        let mut out = [0u8; digest::MAX_OUTPUT_LEN + 1];
+        let len = hmac::sign_into(&s_key, input, &mut out).unwrap();
+        assert_eq!(len, digest_alg.output_len);
+        assert_eq!(is_ok, &out[..len] == output);
+        assert!(hmac::sign_into(&s_key, input, &mut out[..(len - 1)]).is_err());
+    }
+
     // Multi-part API, one single part.
     {
         let mut s_ctx = hmac::SigningContext::with_key(&s_key);
@@ -96,3 +106,29 @@ fn hmac_test_case_inner(
 
     Ok(())
 }
+
+// `sign()` takes a shortcut, which must agree with `SigningContext` for
+// inputs of every length relative to the block length.
+#[test]
+fn hmac_sign_matches_signing_context() {
+    let input: Vec<u8> = (0..300).map(|i| i as u8).collect();
+    for digest_alg in &[&digest::SHA1, &digest::SHA256, &digest::SHA384, &digest::SHA512] {
+        let key = hmac::SigningKey::new(digest_alg, &[1; 32]);
+        for len in 0..input.len() {
+            let input = &input[..len];
+            let mut ctx = hmac::SigningContext::with_key(&key);
+            ctx.update(input);
+            assert_eq!(hmac::sign(&key, input).as_ref(), ctx.sign().as_ref());
+        }
+    }
+}
+
+#[test]
+fn hmac_traits() {
+    test::compile_time_assert_send::<hmac::SigningKey>();
+    test::compile_time_assert_sync::<hmac::SigningKey>();
+    test::compile_time_assert_send::<hmac::VerificationKey>();
+    test::compile_time_assert_sync::<hmac::VerificationKey>();
+    test::compile_time_assert_send::<hmac::SigningContext>();
+    test::compile_time_assert_sync::<hmac::SigningContext>();
+}
@@ -0,0 +1,164 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{
+    agreement,
+    io::base64,
+    jose::jwe::{self, ContentEncryptionAlgorithm, KeyManagementAlgorithm},
+    jwk, rand,
+};
+
+static AGREEMENT_ALGORITHMS: [&agreement::Algorithm; 3] =
+    [&agreement::ECDH_P256, &agreement::ECDH_P384, &agreement::X25519];
+
+static ALGORITHMS: [(&KeyManagementAlgorithm, &ContentEncryptionAlgorithm); 4] = [
+    (&jwe::ECDH_ES, &jwe::A128GCM),
+    (&jwe::ECDH_ES, &jwe::A256GCM),
+    (&jwe::ECDH_ES_A256KW, &jwe::A128GCM),
+    (&jwe::ECDH_ES_A256KW, &jwe::A256GCM),
+];
+
+#[test]
+fn jwe_round_trip() {
+    let rng = rand::SystemRandom::new();
+    for &agreement_alg in AGREEMENT_ALGORITHMS.iter() {
+        let private_key = jwe::PrivateKey::generate(agreement_alg, &rng).unwrap();
+        let other_private_key = jwe::PrivateKey::generate(agreement_alg, &rng).unwrap();
+
+        // The recipient's key usually arrives as a JWK.
+        let jwk = jwk::encode_public_key(&private_key.public_key().unwrap());
+        let public_key = jwk::parse_public_key(&jwk).unwrap();
+
+        for &(alg, enc) in ALGORITHMS.iter() {
+            for &plaintext in &[&b""[..], b"hello", &[0x42; 1000][..]] {
+                let token = jwe::encrypt(&public_key, alg, enc, None, plaintext, &rng).unwrap();
+                assert_eq!(jwe::decrypt(&private_key, alg, enc, &token).unwrap(), plaintext);
+                assert_eq!(jwe::key_id(&token).unwrap(), None);
+                assert!(jwe::decrypt(&other_private_key, alg, enc, &token).is_err());
+
+                // The ephemeral key and IV are random.
+                let other_token = jwe::encrypt(&public_key, alg, enc, None, plaintext, &rng);
+                assert_ne!(other_token.unwrap(), token);
+
+                // Every part is authenticated.
+                let parts: Vec<&str> = token.split('.').collect();
+                for i in 0..parts.len() {
+                    let mut decoded = base64::decode(parts[i], &base64::URL_SAFE_NO_PAD).unwrap();
+                    if decoded.is_empty() {
+                        continue;
+                    }
+                    let last = decoded.len() - 1;
+                    decoded[last] ^= 1;
+                    let mut tampered = parts.clone();
+                    let encoded = base64::encode(&decoded, &base64::URL_SAFE_NO_PAD);
+                    tampered[i] = &encoded;
+                    let tampered = tampered.join(".");
+                    assert!(jwe::decrypt(&private_key, alg, enc, &tampered).is_err());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn jwe_key_id() {
+    let rng = rand::SystemRandom::new();
+    let private_key = jwe::PrivateKey::generate(&agreement::X25519, &rng).unwrap();
+    let public_key = private_key.public_key().unwrap();
+    let token =
+        jwe::encrypt(&public_key, &jwe::ECDH_ES, &jwe::A128GCM, Some("key-\"1\""), b"", &rng)
+            .unwrap();
+    assert_eq!(jwe::key_id(&token).unwrap(), Some(String::from("key-\"1\"")));
+    assert!(jwe::decrypt(&private_key, &jwe::ECDH_ES, &jwe::A128GCM, &token).is_ok());
+}
+
+#[test]
+fn jwe_wrong_curve() {
+    let rng = rand::SystemRandom::new();
+    let p256_key = jwe::PrivateKey::generate(&agreement::ECDH_P256, &rng).unwrap();
+    let p384_key = jwe::PrivateKey::generate(&agreement::ECDH_P384, &rng).unwrap();
+    let token = jwe::encrypt(
+        &p256_key.public_key().unwrap(),
+        &jwe::ECDH_ES,
+        &jwe::A256GCM,
+        None,
+        b"hello",
+        &rng,
+    )
+    .unwrap();
+    assert!(jwe::decrypt(&p384_key, &jwe::ECDH_ES, &jwe::A256GCM, &token).is_err());
+}
+
+#[test]
+fn jwe_malformed_tokens() {
+    let rng = rand::SystemRandom::new();
+    let private_key = jwe::PrivateKey::generate(&agreement::ECDH_P256, &rng).unwrap();
+    let public_key = private_key.public_key().unwrap();
+    let token =
+        jwe::encrypt(&public_key, &jwe::ECDH_ES, &jwe::A128GCM, None, b"hello", &rng).unwrap();
+    let decrypt = |token: &str| jwe::decrypt(&private_key, &jwe::ECDH_ES, &jwe::A128GCM, token);
+    assert!(decrypt(&token).is_ok());
+
+    // Too few or too many parts.
+    let (without_tag, _) = token.split_at(token.rfind('.').unwrap());
+    assert!(decrypt(without_tag).is_err());
+    assert!(decrypt(&(token.clone() + ".")).is_err());
+
+    // An encrypted key with `ECDH-ES`.
+    let parts: Vec<&str> = token.split('.').collect();
+    let with_encrypted_key = [parts[0], "AAAA", parts[2], parts[3], parts[4]].join(".");
+    assert!(decrypt(&with_encrypted_key).is_err());
+
+    // `crit` and `zip` aren't supported, even in an otherwise valid token.
+    let header = base64::decode(parts[0], &base64::URL_SAFE_NO_PAD).unwrap();
+    let header = String::from_utf8(header).unwrap();
+    for extra in &[r#","crit":["exp"],"exp":0}"#, r#","zip":"DEF"}"#] {
+        let header = String::from(&header[..(header.len() - 1)]) + extra;
+        let header = base64::encode(header.as_bytes(), &base64::URL_SAFE_NO_PAD);
+        let modified = [header.as_str(), parts[1], parts[2], parts[3], parts[4]].join(".");
+        assert!(decrypt(&modified).is_err());
+        assert!(jwe::key_id(&modified).is_err());
+    }
+
+    // Ed25519 keys can't be used for key agreement.
+    let ed25519 = jwk::parse_public_key(
+        r#"{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#,
+    )
+    .unwrap();
+    assert!(jwe::encrypt(&ed25519, &jwe::ECDH_ES, &jwe::A128GCM, None, b"", &rng).is_err());
+
+    // The private key must be valid for its curve.
+    assert!(jwe::PrivateKey::from_bytes(&agreement::ECDH_P256, &[0; 32]).is_err());
+    assert!(jwe::PrivateKey::from_bytes(&agreement::ECDH_P256, &[1; 31]).is_err());
+}
@@ -0,0 +1,140 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{digest, jwk, rand, signature, test};
+use std::error::Error;
+
+#[test]
+fn jwk_tests() {
+    test::from_file("tests/jwk_tests.txt", |section, test_case| {
+        let jwk_ = test_case.consume_string("JWK");
+        match &section[..] {
+            "public" => {
+                let encoded = test_case.consume_optional_string("Encoded");
+                let expected = match test_case.consume_optional_string("Error") {
+                    Some(expected) => {
+                        let actual = jwk::parse_public_key(&jwk_).unwrap_err();
+                        assert_eq!(actual.description(), expected);
+                        return Ok(());
+                    },
+                    None => test_case.consume_bytes("SPKI"),
+                };
+                let thumbprint = test_case.consume_bytes("Thumbprint");
+
+                let public_key = jwk::parse_public_key(&jwk_).unwrap();
+                assert_eq!(public_key.to_spki_der().as_ref(), &expected[..]);
+                assert_eq!(
+                    jwk::encode_public_key(&public_key),
+                    encoded.unwrap_or(jwk_)
+                );
+                assert_eq!(
+                    jwk::thumbprint(&public_key, &digest::SHA256).as_ref(),
+                    &thumbprint[..]
+                );
+            },
+
+            "private" => {
+                let expected_error = test_case.consume_optional_string("Error");
+                let pkcs8 = match expected_error {
+                    Some(ref expected) => {
+                        let actual = jwk::parse_private_key(&jwk_).unwrap_err();
+                        assert_eq!(actual.description(), expected);
+                        return Ok(());
+                    },
+                    None => test_case.consume_bytes("PKCS8"),
+                };
+
+                // RSA private keys require the `rsa_signing` feature.
+                if cfg!(not(feature = "rsa_signing")) && jwk_.contains("\"kty\":\"RSA\"") {
+                    for actual in &[
+                        jwk::parse_private_key(&jwk_).unwrap_err(),
+                        jwk::encode_private_key(untrusted::Input::from(&pkcs8)).unwrap_err(),
+                    ] {
+                        assert_eq!(actual.description(), "WrongAlgorithm");
+                    }
+                    return Ok(());
+                }
+
+                assert_eq!(
+                    jwk::encode_private_key(untrusted::Input::from(&pkcs8)).unwrap(),
+                    jwk_
+                );
+                let parsed = jwk::parse_private_key(&jwk_).unwrap();
+                assert_eq!(
+                    jwk::encode_private_key(untrusted::Input::from(&parsed)).unwrap(),
+                    jwk_
+                );
+            },
+
+            _ => unreachable!(),
+        }
+        Ok(())
+    });
+}
+
+#[test]
+fn jwk_generated_key_round_trip() {
+    let rng = rand::SystemRandom::new();
+
+    let pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let private_jwk = jwk::encode_private_key(untrusted::Input::from(&pkcs8)).unwrap();
+    let key_pair = signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(
+        &jwk::parse_private_key(&private_jwk).unwrap(),
+    ))
+    .unwrap();
+    let public_jwk = jwk::encode_public_key(&key_pair.public_key());
+    assert_eq!(
+        jwk::parse_public_key(&public_jwk).unwrap().as_ref(),
+        key_pair.public_key_bytes()
+    );
+
+    for alg in &[
+        &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+        &signature::ECDSA_P384_SHA384_ASN1_SIGNING,
+    ] {
+        let pkcs8 = signature::ECDSAKeyPair::generate_pkcs8(alg, &rng).unwrap();
+        let private_jwk = jwk::encode_private_key(untrusted::Input::from(pkcs8.as_ref())).unwrap();
+        let key_pair = signature::ECDSAKeyPair::from_pkcs8(
+            alg,
+            untrusted::Input::from(&jwk::parse_private_key(&private_jwk).unwrap()),
+        )
+        .unwrap();
+        let public_key = key_pair.public_key();
+        let public_jwk = jwk::encode_public_key(&public_key);
+        assert_eq!(
+            jwk::parse_public_key(&public_jwk).unwrap().as_ref(),
+            public_key.as_ref()
+        );
+    }
+}
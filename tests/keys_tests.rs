@@ -0,0 +1,258 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{aead, digest, error::KeyRejectedReason, hmac, keys, rand, signature};
+
+static KEY_TYPES: [&keys::KeyType; 9] = [
+    &keys::ED25519,
+    &keys::ECDSA_P256_SHA256_ASN1_SIGNING,
+    &keys::ECDSA_P384_SHA384_ASN1_SIGNING,
+    &keys::AES_128_GCM,
+    &keys::AES_256_GCM,
+    &keys::CHACHA20_POLY1305,
+    &keys::HMAC_SHA256,
+    &keys::HMAC_SHA384,
+    &keys::HMAC_SHA512,
+];
+
+static WRAPPING_ALGORITHMS: [&keys::WrappingAlgorithm; 4] = [
+    &keys::KW_AES_128,
+    &keys::KW_AES_256,
+    &keys::KW_AES_256_GCM,
+    &keys::KW_CHACHA20_POLY1305,
+];
+
+fn new_kek(algorithm: &'static keys::WrappingAlgorithm, value: u8) -> keys::Kek {
+    keys::Kek::new(algorithm, &vec![value; algorithm.key_len()]).unwrap()
+}
+
+/// Checks that `a` and `b` hold the same key.
+fn assert_same_key(a: &keys::Handle, b: &keys::Handle) {
+    let rng = rand::SystemRandom::new();
+    assert_eq!(a.key_type(), b.key_type());
+    if let Ok(public_key) = a.public_key() {
+        assert_eq!(b.public_key().unwrap().as_ref(), public_key.as_ref());
+        let verification_alg: &signature::VerificationAlgorithm = match a.key_type() {
+            t if *t == keys::ED25519 => &signature::ED25519,
+            t if *t == keys::ECDSA_P256_SHA256_ASN1_SIGNING => &signature::ECDSA_P256_SHA256_ASN1,
+            _ => &signature::ECDSA_P384_SHA384_ASN1,
+        };
+        let sig = b.sign(&rng, b"message").unwrap();
+        assert!(signature::verify(
+            verification_alg,
+            untrusted::Input::from(public_key.as_ref()),
+            untrusted::Input::from(b"message"),
+            untrusted::Input::from(sig.as_ref()),
+        )
+        .is_ok());
+    } else if let Ok(tag) = a.hmac_sign(b"message") {
+        assert!(b.hmac_verify(b"message", tag.as_ref()).is_ok());
+    } else {
+        let nonce = [0u8; 12];
+        let mut in_out = b"message".to_vec();
+        in_out.extend_from_slice(&[0; aead::MAX_TAG_LEN]);
+        let len = a.seal_in_place(&nonce, b"ad", &mut in_out, aead::MAX_TAG_LEN).unwrap();
+        let plaintext = b.open_in_place(&nonce, b"ad", 0, &mut in_out[..len]).unwrap();
+        assert_eq!(plaintext, b"message");
+    }
+}
+
+#[test]
+fn keys_wrap_unwrap_round_trip() {
+    let rng = rand::SystemRandom::new();
+    for key_type in KEY_TYPES.iter() {
+        let handle = keys::Handle::generate(key_type, keys::Extractable::No, &rng).unwrap();
+        assert_eq!(handle.key_type(), *key_type);
+        for algorithm in WRAPPING_ALGORITHMS.iter() {
+            let kek = new_kek(algorithm, 1);
+            let wrapped = handle.wrap(&kek, &rng).unwrap();
+            assert_eq!(&wrapped[..1], &[1]);
+            let unwrapped = keys::Handle::unwrap(&kek, &wrapped).unwrap();
+            assert_same_key(&handle, &unwrapped);
+
+            // The wrapped key is bound to the KEK, its algorithm, and the
+            // key type.
+            let other_kek = new_kek(algorithm, 2);
+            assert_eq!(
+                keys::Handle::unwrap(&other_kek, &wrapped).unwrap_err().reason(),
+                KeyRejectedReason::DecryptionFailed
+            );
+            for other_algorithm in WRAPPING_ALGORITHMS.iter().filter(|a| *a != algorithm) {
+                let other_kek = new_kek(other_algorithm, 1);
+                assert!(keys::Handle::unwrap(&other_kek, &wrapped).is_err());
+            }
+
+            // Unwrapping the PKCS#8 documents of ECDSA keys many times is slow
+            // without optimizations, and no different from the other keys.
+            if handle.public_key().is_ok() && **key_type != keys::ED25519 {
+                continue;
+            }
+            for i in 0..wrapped.len() {
+                let mut modified = wrapped.clone();
+                modified[i] ^= 1;
+                assert!(keys::Handle::unwrap(&kek, &modified).is_err());
+            }
+            for len in 0..wrapped.len() {
+                assert!(keys::Handle::unwrap(&kek, &wrapped[..len]).is_err());
+            }
+        }
+    }
+}
+
+#[test]
+fn keys_import() {
+    let rng = rand::SystemRandom::new();
+
+    // Imported keys are the same keys as the ones of the corresponding types.
+    let pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let key_pair = signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&pkcs8)).unwrap();
+    let handle =
+        keys::Handle::import(&keys::ED25519, keys::Extractable::No, untrusted::Input::from(&pkcs8))
+            .unwrap();
+    assert_eq!(
+        handle.sign(&rng, b"message").unwrap().as_ref(),
+        key_pair.sign(b"message").as_ref()
+    );
+
+    let handle = keys::Handle::import(
+        &keys::HMAC_SHA256,
+        keys::Extractable::No,
+        untrusted::Input::from(b"key"),
+    )
+    .unwrap();
+    let key = hmac::SigningKey::new(&digest::SHA256, b"key");
+    assert_eq!(
+        handle.hmac_sign(b"message").unwrap().as_ref(),
+        hmac::sign(&key, b"message").as_ref()
+    );
+
+    let key_bytes = [7u8; 16];
+    let handle = keys::Handle::import(
+        &keys::AES_128_GCM,
+        keys::Extractable::No,
+        untrusted::Input::from(&key_bytes),
+    )
+    .unwrap();
+    let mut in_out = b"message".to_vec();
+    in_out.extend_from_slice(&[0; aead::MAX_TAG_LEN]);
+    let len = handle.seal_in_place(&[0; 12], &[], &mut in_out, aead::MAX_TAG_LEN).unwrap();
+    let opening_key = aead::OpeningKey::new(&aead::AES_128_GCM, &key_bytes).unwrap();
+    assert_eq!(
+        aead::open_in_place(&opening_key, &[0; 12], &[], 0, &mut in_out[..len]).unwrap(),
+        b"message"
+    );
+
+    // Malformed keys are rejected.
+    for (key_type, material, reason) in &[
+        (&keys::ED25519, &b""[..], KeyRejectedReason::InvalidEncoding),
+        (&keys::ECDSA_P256_SHA256_ASN1_SIGNING, &pkcs8[..], KeyRejectedReason::WrongAlgorithm),
+        (&keys::AES_128_GCM, &[0; 15][..], KeyRejectedReason::TooSmall),
+        (&keys::AES_256_GCM, &[0; 33][..], KeyRejectedReason::TooLarge),
+        (&keys::HMAC_SHA256, &b""[..], KeyRejectedReason::TooSmall),
+    ] {
+        assert_eq!(
+            keys::Handle::import(key_type, keys::Extractable::Yes, untrusted::Input::from(material))
+                .unwrap_err()
+                .reason(),
+            *reason
+        );
+    }
+}
+
+#[test]
+fn keys_wrong_kind_of_key() {
+    let rng = rand::SystemRandom::new();
+    let generate = |key_type| keys::Handle::generate(key_type, keys::Extractable::No, &rng);
+    let signing = generate(&keys::ED25519).unwrap();
+    let aead = generate(&keys::CHACHA20_POLY1305).unwrap();
+    let hmac = generate(&keys::HMAC_SHA512).unwrap();
+
+    let mut in_out = [0u8; 32];
+    assert!(signing.seal_in_place(&[0; 12], &[], &mut in_out, 16).is_err());
+    assert!(signing.hmac_sign(b"").is_err());
+    assert!(aead.public_key().is_err());
+    assert!(aead.sign(&rng, b"").is_err());
+    assert!(aead.hmac_verify(b"", &[]).is_err());
+    assert!(hmac.sign(&rng, b"").is_err());
+    assert!(hmac.open_in_place(&[0; 12], &[], 0, &mut in_out).is_err());
+}
+
+#[test]
+fn keys_kek_length() {
+    for algorithm in WRAPPING_ALGORITHMS.iter() {
+        let key_len = algorithm.key_len();
+        assert!(keys::Kek::new(algorithm, &vec![0; key_len - 1]).is_err());
+        assert!(keys::Kek::new(algorithm, &vec![0; key_len + 1]).is_err());
+        assert_eq!(keys::Kek::new(algorithm, &vec![0; key_len]).unwrap().algorithm(), *algorithm);
+    }
+}
+
+#[test]
+fn keys_extractable() {
+    let rng = rand::SystemRandom::new();
+    let kek = new_kek(&keys::KW_AES_256, 1);
+    let material = [9u8; 32];
+
+    let handle = keys::Handle::import(
+        &keys::HMAC_SHA256,
+        keys::Extractable::Yes,
+        untrusted::Input::from(&material),
+    )
+    .unwrap();
+    assert_eq!(handle.extractable(), keys::Extractable::Yes);
+    assert_eq!(handle.export().unwrap(), &material[..]);
+    let unwrapped = keys::Handle::unwrap(&kek, &handle.wrap(&kek, &rng).unwrap()).unwrap();
+    assert_eq!(unwrapped.extractable(), keys::Extractable::Yes);
+    assert_eq!(unwrapped.export().unwrap(), &material[..]);
+
+    for key_type in KEY_TYPES.iter() {
+        let handle = keys::Handle::generate(key_type, keys::Extractable::No, &rng).unwrap();
+        assert_eq!(handle.extractable(), keys::Extractable::No);
+        assert!(handle.export().is_err());
+
+        // A non-extractable key stays non-extractable when it is unwrapped,
+        // and can't be made extractable by changing the wrapped key.
+        let mut wrapped = handle.wrap(&kek, &rng).unwrap();
+        let unwrapped = keys::Handle::unwrap(&kek, &wrapped).unwrap();
+        assert_eq!(unwrapped.extractable(), keys::Extractable::No);
+        assert!(unwrapped.export().is_err());
+        wrapped[3] = 1;
+        assert!(keys::Handle::unwrap(&kek, &wrapped).is_err());
+    }
+
+    // Both kinds of handle export the key material that `import()` accepts.
+    let handle = keys::Handle::generate(&keys::ED25519, keys::Extractable::Yes, &rng).unwrap();
+    let pkcs8 = handle.export().unwrap();
+    assert!(signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(pkcs8)).is_ok());
+}
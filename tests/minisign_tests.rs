@@ -0,0 +1,152 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{minisign, signature::Ed25519KeyPair};
+
+// Generated with Python's `cryptography` and `hashlib` packages, using the
+// seed 000102...1f and the key ID 0123456789abcdef.
+const PUBLIC_KEY_FILE: &str = "untrusted comment: minisign public key\n\
+                               RWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4\n";
+
+const MINISIGN_SIGNATURE_FILE: &str = "\
+untrusted comment: signature from minisign secret key\n\
+RUQBI0VniavN77lvL/rWP9ru04H7abmP58HMKdzr0HeGBqaIBygVarXfjzqY7eqxYeBKaCV1TVFdoVCKAml8WoE7FD5v06QFbww=\n\
+trusted comment: timestamp:1555555555\tfile:test.txt\n\
+MeSrjKeuUQCB0yiwzZjIPNIoTqE2Xrjfl7WE8qqcZdNHTtYoHPGXH4YiCJ95uPtvAUwAsp96WpkdApFRJIP3DQ==\n";
+
+const SIGNIFY_SIGNATURE_FILE: &str = "\
+untrusted comment: verify with test.pub\n\
+RWQBI0VniavN7+1CPiVk2XAGyASbO83EMD27Y2upPJdO79q5+HqNG19euWxusgvGfDVTn5KeGiSfadxI4dUoEHiHfQaYINOztwU=\n";
+
+const MESSAGE: &[u8] = b"test message\n";
+
+const KEY_ID: minisign::KeyId = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+
+fn test_key_pair() -> Ed25519KeyPair {
+    let seed: Vec<u8> = (0u8..32).collect();
+    Ed25519KeyPair::from_seed_unchecked(untrusted::Input::from(&seed)).unwrap()
+}
+
+#[test]
+fn minisign_public_key_round_trip() {
+    let key_pair = test_key_pair();
+    let public_key = minisign::PublicKey::from_key_pair(KEY_ID, &key_pair);
+    assert_eq!(public_key.to_file("minisign public key").unwrap(), PUBLIC_KEY_FILE);
+    assert_eq!(minisign::PublicKey::from_file(PUBLIC_KEY_FILE).unwrap(), public_key);
+    assert_eq!(public_key.key_id(), &KEY_ID);
+
+    const FROM_BYTES: minisign::PublicKey = minisign::PublicKey::from_bytes(KEY_ID, [1; 32]);
+    assert_eq!(minisign::PublicKey::new(KEY_ID, &[1; 32]).unwrap(), FROM_BYTES);
+
+    // The public key of minisign itself, as published in its documentation.
+    let minisign_key = minisign::PublicKey::from_base64(
+        "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3",
+    )
+    .unwrap();
+    assert_eq!(
+        minisign_key.key_id(),
+        &[0x1f, 0xe8, 0xb4, 0x42, 0x18, 0x0f, 0x62, 0xe7]
+    );
+}
+
+#[test]
+fn minisign_sign_and_verify() {
+    let key_pair = test_key_pair();
+    let actual = minisign::sign(
+        &key_pair,
+        KEY_ID,
+        MESSAGE,
+        "signature from minisign secret key",
+        "timestamp:1555555555\tfile:test.txt",
+    )
+    .unwrap();
+    assert_eq!(actual, MINISIGN_SIGNATURE_FILE);
+
+    let public_key = minisign::PublicKey::from_file(PUBLIC_KEY_FILE).unwrap();
+    let sig = minisign::SignatureFile::parse(MINISIGN_SIGNATURE_FILE).unwrap();
+    assert!(public_key.verify(MESSAGE, &sig).is_ok());
+    assert_eq!(
+        sig.trusted_comment(),
+        Some("timestamp:1555555555\tfile:test.txt")
+    );
+    assert!(public_key.verify(b"another message\n", &sig).is_err());
+
+    // Tampering with the trusted comment must be detected.
+    let tampered = MINISIGN_SIGNATURE_FILE.replace("test.txt", "evil.txt");
+    let tampered = minisign::SignatureFile::parse(&tampered).unwrap();
+    assert!(public_key.verify(MESSAGE, &tampered).is_err());
+
+    // CRLF line endings are accepted.
+    let crlf = MINISIGN_SIGNATURE_FILE.replace("\n", "\r\n");
+    let crlf = minisign::SignatureFile::parse(&crlf).unwrap();
+    assert!(public_key.verify(MESSAGE, &crlf).is_ok());
+}
+
+#[test]
+fn signify_sign_and_verify() {
+    let key_pair = test_key_pair();
+    let actual =
+        minisign::sign_signify(&key_pair, KEY_ID, MESSAGE, "verify with test.pub").unwrap();
+    assert_eq!(actual, SIGNIFY_SIGNATURE_FILE);
+
+    let public_key = minisign::PublicKey::from_file(PUBLIC_KEY_FILE).unwrap();
+    let sig = minisign::SignatureFile::parse(SIGNIFY_SIGNATURE_FILE).unwrap();
+    assert_eq!(sig.trusted_comment(), None);
+    assert_eq!(sig.untrusted_comment(), "verify with test.pub");
+    assert!(public_key.verify(MESSAGE, &sig).is_ok());
+
+    // A signature made with a different key ID isn't accepted.
+    let other_key = minisign::PublicKey::new([0; minisign::KEY_ID_LEN], key_pair.public_key_bytes())
+        .unwrap();
+    assert!(other_key.verify(MESSAGE, &sig).is_err());
+}
+
+#[test]
+fn minisign_rejects_malformed_files() {
+    assert!(minisign::sign(&test_key_pair(), KEY_ID, MESSAGE, "a\nb", "").is_err());
+    assert!(minisign::sign(&test_key_pair(), KEY_ID, MESSAGE, "", "a\rb").is_err());
+
+    for bad in &[
+        "",
+        "untrusted comment: x\n",
+        "comment: x\nRWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4\n",
+        "untrusted comment: x\nRWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4\nextra\n",
+    ] {
+        assert!(minisign::PublicKey::from_file(bad).is_err());
+    }
+
+    // A pre-hashed signature without a trusted comment isn't valid.
+    let truncated: String = MINISIGN_SIGNATURE_FILE.lines().take(2).collect::<Vec<_>>().join("\n");
+    assert!(minisign::SignatureFile::parse(&truncated).is_err());
+}
@@ -0,0 +1,165 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{opaque, rand};
+
+static STRETCHING: opaque::KeyStretching = opaque::KeyStretching::Identity;
+
+static IDENTITIES: opaque::Identities = opaque::Identities {
+    client: Some(b"alice"),
+    server: Some(b"example.com"),
+};
+
+fn register(
+    server: &opaque::ServerSetup, password: &[u8], rng: &rand::SecureRandom,
+) -> opaque::RegistrationRecord {
+    let client = opaque::ClientRegistration::start(password, rng).unwrap();
+    let response = server.registration_response(client.request(), b"alice").unwrap();
+    let (record, _) = client.finish(password, &response, &IDENTITIES, STRETCHING, rng).unwrap();
+    opaque::RegistrationRecord::from_bytes(record.as_ref()).unwrap()
+}
+
+fn new_server(rng: &rand::SecureRandom) -> opaque::ServerSetup {
+    let mut seeds = [0u8; 64];
+    rng.fill(&mut seeds).unwrap();
+    opaque::ServerSetup::from_seeds(&seeds[..32], &seeds[32..]).unwrap()
+}
+
+#[test]
+fn opaque_login() {
+    let rng = rand::SystemRandom::new();
+    let server = new_server(&rng);
+    let client = opaque::ClientRegistration::start(b"password", &rng).unwrap();
+    let response = server.registration_response(client.request(), b"alice").unwrap();
+    let (record, export_key) =
+        client.finish(b"password", &response, &IDENTITIES, STRETCHING, &rng).unwrap();
+
+    for _ in 0..2 {
+        let client = opaque::ClientLogin::start(b"password", &rng).unwrap();
+        let login = opaque::ServerLogin::start(
+            &server, &record, b"alice", client.ke1(), b"context", &IDENTITIES, &rng,
+        )
+        .unwrap();
+        let client = client
+            .finish(b"password", login.ke2(), b"context", &IDENTITIES, STRETCHING)
+            .unwrap();
+        assert_eq!(client.export_key().as_ref(), export_key.as_ref());
+        let session_key = login.finish(client.ke3()).unwrap();
+        assert_eq!(session_key.as_ref(), client.session_key().as_ref());
+    }
+}
+
+#[test]
+fn opaque_wrong_password() {
+    let rng = rand::SystemRandom::new();
+    let server = new_server(&rng);
+    let record = register(&server, b"password", &rng);
+    let client = opaque::ClientLogin::start(b"passw0rd", &rng).unwrap();
+    let login = opaque::ServerLogin::start(
+        &server, &record, b"alice", client.ke1(), b"", &IDENTITIES, &rng,
+    )
+    .unwrap();
+    assert!(client.finish(b"passw0rd", login.ke2(), b"", &IDENTITIES, STRETCHING).is_err());
+}
+
+#[test]
+fn opaque_fake_record() {
+    let rng = rand::SystemRandom::new();
+    let server = new_server(&rng);
+    let record = opaque::RegistrationRecord::fake(&rng).unwrap();
+    let client = opaque::ClientLogin::start(b"password", &rng).unwrap();
+    let login = opaque::ServerLogin::start(
+        &server, &record, b"mallory", client.ke1(), b"", &IDENTITIES, &rng,
+    )
+    .unwrap();
+    assert_eq!(login.ke2().len(), opaque::KE2_LEN);
+    assert!(client.finish(b"password", login.ke2(), b"", &IDENTITIES, STRETCHING).is_err());
+}
+
+#[test]
+fn opaque_mismatched_context_or_identities() {
+    let rng = rand::SystemRandom::new();
+    let server = new_server(&rng);
+    let record = register(&server, b"password", &rng);
+    let other_identities = opaque::Identities {
+        client: Some(b"alice"),
+        server: None,
+    };
+    for &(context, identities) in &[(&b"other"[..], &IDENTITIES), (&b""[..], &other_identities)] {
+        let client = opaque::ClientLogin::start(b"password", &rng).unwrap();
+        let login = opaque::ServerLogin::start(
+            &server, &record, b"alice", client.ke1(), b"", &IDENTITIES, &rng,
+        )
+        .unwrap();
+        assert!(client.finish(b"password", login.ke2(), context, identities, STRETCHING).is_err());
+    }
+}
+
+#[test]
+fn opaque_bad_ke3() {
+    let rng = rand::SystemRandom::new();
+    let server = new_server(&rng);
+    let record = register(&server, b"password", &rng);
+    let client = opaque::ClientLogin::start(b"password", &rng).unwrap();
+    let login = opaque::ServerLogin::start(
+        &server, &record, b"alice", client.ke1(), b"", &IDENTITIES, &rng,
+    )
+    .unwrap();
+    let client = client.finish(b"password", login.ke2(), b"", &IDENTITIES, STRETCHING).unwrap();
+    let mut ke3 = client.ke3().to_vec();
+    ke3[0] ^= 1;
+    assert!(login.finish(&ke3).is_err());
+}
+
+#[test]
+fn opaque_invalid_messages() {
+    let rng = rand::SystemRandom::new();
+    let server = new_server(&rng);
+    let request = [0; opaque::REGISTRATION_REQUEST_LEN];
+    assert!(server.registration_response(&request, b"alice").is_err());
+    let record = [0; opaque::REGISTRATION_RECORD_LEN];
+    assert!(opaque::RegistrationRecord::from_bytes(&record).is_err());
+    let record = register(&server, b"password", &rng);
+    let client = opaque::ClientLogin::start(b"password", &rng).unwrap();
+    assert!(opaque::ServerLogin::start(
+        &server,
+        &record,
+        b"alice",
+        &client.ke1()[1..],
+        b"",
+        &IDENTITIES,
+        &rng
+    )
+    .is_err());
+}
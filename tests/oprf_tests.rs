@@ -0,0 +1,181 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+
+use ring::{oprf, rand};
+
+static SUITES: [&oprf::Suite; 3] =
+    [&oprf::P256_SHA256, &oprf::P384_SHA384, &oprf::RISTRETTO255_SHA512];
+
+#[test]
+fn oprf_round_trip() {
+    let rng = rand::SystemRandom::new();
+    for suite in SUITES.iter() {
+        let key = oprf::PrivateKey::generate(suite, oprf::Mode::Oprf, &rng).unwrap();
+        let output = |input: &[u8]| {
+            let blind = oprf::blind(suite, oprf::Mode::Oprf, input, &rng).unwrap();
+            let evaluated = key.blind_evaluate(blind.blinded_element().as_ref()).unwrap();
+            blind.finalize(input, evaluated.as_ref()).unwrap()
+        };
+        assert_eq!(output(b"input").as_ref(), output(b"input").as_ref());
+        assert!(output(b"input").as_ref() != output(b"other input").as_ref());
+    }
+}
+
+#[test]
+fn oprf_rejects_invalid_elements() {
+    let rng = rand::SystemRandom::new();
+    for suite in SUITES.iter() {
+        let key = oprf::PrivateKey::generate(suite, oprf::Mode::Oprf, &rng).unwrap();
+        let len = key.public_key().as_ref().len();
+        assert!(key.blind_evaluate(&vec![0; len]).is_err());
+        assert!(key.blind_evaluate(&vec![0xff; len]).is_err());
+        assert!(key.blind_evaluate(&key.public_key().as_ref()[1..]).is_err());
+
+        let blind = oprf::blind(suite, oprf::Mode::Oprf, b"input", &rng).unwrap();
+        assert!(blind.finalize(b"input", &vec![0; len]).is_err());
+    }
+}
+
+#[test]
+fn voprf_round_trip() {
+    let rng = rand::SystemRandom::new();
+    for suite in SUITES.iter() {
+        let key = oprf::PrivateKey::generate(suite, oprf::Mode::Voprf, &rng).unwrap();
+        let other_key = oprf::PrivateKey::generate(suite, oprf::Mode::Voprf, &rng).unwrap();
+        let public_key = key.public_key().as_ref();
+
+        let blind = oprf::blind(suite, oprf::Mode::Voprf, b"input", &rng).unwrap();
+        let blinded_element = blind.blinded_element().as_ref();
+        assert!(key.blind_evaluate(blinded_element).is_err());
+        let (evaluated, proof) = key.blind_evaluate_with_proof(blinded_element, &rng).unwrap();
+        let (evaluated, proof) = (evaluated.as_ref(), proof.as_ref());
+        assert!(blind.finalize(b"input", evaluated).is_err());
+        let output = blind.finalize_verifiable(b"input", evaluated, proof, public_key).unwrap();
+
+        // A malicious server can't use another key, or modify the evaluated
+        // element or the proof.
+        let other_public_key = other_key.public_key().as_ref();
+        assert!(blind
+            .finalize_verifiable(b"input", evaluated, proof, other_public_key)
+            .is_err());
+        let (other_evaluated, other_proof) =
+            other_key.blind_evaluate_with_proof(blinded_element, &rng).unwrap();
+        assert!(blind
+            .finalize_verifiable(b"input", other_evaluated.as_ref(), proof, public_key)
+            .is_err());
+        let other_proof = other_proof.as_ref();
+        assert!(blind
+            .finalize_verifiable(b"input", other_evaluated.as_ref(), other_proof, public_key)
+            .is_err());
+        for i in 0..proof.len() {
+            let mut modified = proof.to_vec();
+            modified[i] ^= 1;
+            assert!(blind
+                .finalize_verifiable(b"input", evaluated, &modified, public_key)
+                .is_err());
+        }
+
+        let blind = oprf::blind(suite, oprf::Mode::Voprf, b"input", &rng).unwrap();
+        let (evaluated, proof) =
+            key.blind_evaluate_with_proof(blind.blinded_element().as_ref(), &rng).unwrap();
+        let again = blind
+            .finalize_verifiable(b"input", evaluated.as_ref(), proof.as_ref(), public_key)
+            .unwrap();
+        assert_eq!(output.as_ref(), again.as_ref());
+    }
+}
+
+#[test]
+fn voprf_batch() {
+    let rng = rand::SystemRandom::new();
+    let inputs: [&[u8]; 3] = [b"first", b"second", b"third"];
+    for suite in SUITES.iter() {
+        let key = oprf::PrivateKey::generate(suite, oprf::Mode::Voprf, &rng).unwrap();
+        let public_key = key.public_key().as_ref();
+        let blinds: Vec<oprf::Blind> = inputs
+            .iter()
+            .map(|input| oprf::blind(suite, oprf::Mode::Voprf, input, &rng).unwrap())
+            .collect();
+        let blinded_elements: Vec<&[u8]> =
+            blinds.iter().map(|blind| blind.blinded_element().as_ref()).collect();
+        let (evaluated, proof) = key.blind_evaluate_batch(&blinded_elements, &rng).unwrap();
+        let evaluated: Vec<&[u8]> = evaluated.iter().map(|e| e.as_ref()).collect();
+        let outputs =
+            oprf::finalize_batch(&blinds, &inputs, &evaluated, proof.as_ref(), public_key)
+                .unwrap();
+
+        // The outputs are the same as those of single evaluations.
+        for (input, output) in inputs.iter().zip(outputs.iter()) {
+            let blind = oprf::blind(suite, oprf::Mode::Voprf, input, &rng).unwrap();
+            let (evaluated, proof) =
+                key.blind_evaluate_with_proof(blind.blinded_element().as_ref(), &rng).unwrap();
+            let single = blind
+                .finalize_verifiable(input, evaluated.as_ref(), proof.as_ref(), public_key)
+                .unwrap();
+            assert_eq!(single.as_ref(), output.as_ref());
+        }
+
+        // The proof covers every element, in order.
+        let mut swapped = evaluated.clone();
+        swapped.swap(0, 1);
+        assert!(
+            oprf::finalize_batch(&blinds, &inputs, &swapped, proof.as_ref(), public_key).is_err()
+        );
+        assert!(oprf::finalize_batch(
+            &blinds[..2],
+            &inputs[..2],
+            &evaluated[..2],
+            proof.as_ref(),
+            public_key
+        )
+        .is_err());
+        assert!(oprf::finalize_batch(&[], &[], &[], proof.as_ref(), public_key).is_err());
+    }
+}
+
+#[test]
+fn oprf_derive_key() {
+    for suite in SUITES.iter() {
+        let derive = |mode, seed: &[u8], info: &[u8]| {
+            let key = oprf::PrivateKey::derive(suite, mode, seed, info).unwrap();
+            key.public_key().as_ref().to_vec()
+        };
+        let key = derive(oprf::Mode::Voprf, &[1; 32], b"info");
+        assert_eq!(key, derive(oprf::Mode::Voprf, &[1; 32], b"info"));
+        assert!(key != derive(oprf::Mode::Voprf, &[2; 32], b"info"));
+        assert!(key != derive(oprf::Mode::Voprf, &[1; 32], b"other info"));
+        assert!(key != derive(oprf::Mode::Oprf, &[1; 32], b"info"));
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+
+use core::num::NonZeroU64;
+use ring::{digest, otp, test};
+
+#[test]
+fn otp_tests() {
+    test::from_file("tests/otp_tests.txt", |section, test_case| {
+        assert_eq!(section, "");
+        let digest_alg = test_case.consume_digest_alg("Hash").unwrap();
+        let key = test_case.consume_bytes("Key");
+        let counter = test_case.consume_optional_string("Counter");
+        let time = test_case.consume_optional_string("Time");
+        let digits = test_case.consume_usize("Digits");
+        let expected = test_case.consume_string("Code");
+
+        let key = otp::Key::new(digest_alg, &key, digits).unwrap();
+        let period = NonZeroU64::new(30).unwrap();
+        let code = expected.as_bytes();
+        match (counter, time) {
+            (Some(counter), None) => {
+                let counter = counter.parse().unwrap();
+                assert_eq!(otp::hotp(&key, counter).as_str(), expected);
+                let first = counter.saturating_sub(2);
+                let verified = otp::verify_hotp(&key, first, 2, code);
+                assert_eq!(verified, Ok(counter));
+            },
+            (None, Some(time)) => {
+                let time: u64 = time.parse().unwrap();
+                assert_eq!(otp::totp(&key, period, time).as_str(), expected);
+                for &t in &[time, time - 30, time + 30] {
+                    let verified = otp::verify_totp(&key, period, t, 1, code);
+                    assert_eq!(verified, Ok(time / 30));
+                }
+                assert!(otp::verify_totp(&key, period, time + 60, 1, code).is_err());
+                if time >= 60 {
+                    assert!(otp::verify_totp(&key, period, time - 60, 1, code).is_err());
+                }
+            },
+            _ => unreachable!(),
+        }
+        Ok(())
+    });
+}
+
+#[test]
+fn otp_verify_rejects_wrong_code() {
+    let key = otp::Key::new(&digest::SHA1, b"12345678901234567890", 6).unwrap();
+    // The codes for counters 0 and 1 are 755224 and 287082.
+    assert_eq!(otp::verify_hotp(&key, 0, 0, b"755224"), Ok(0));
+    assert_eq!(otp::verify_hotp(&key, 0, 1, b"287082"), Ok(1));
+    assert!(otp::verify_hotp(&key, 0, 0, b"287082").is_err());
+    assert!(otp::verify_hotp(&key, 1, 5, b"755224").is_err());
+    assert!(otp::verify_hotp(&key, 0, 0, b"755225").is_err());
+    assert!(otp::verify_hotp(&key, 0, 0, b"0755224").is_err());
+    assert!(otp::verify_hotp(&key, 0, 0, b"55224").is_err());
+    assert!(otp::verify_hotp(&key, 0, 0, b"").is_err());
+
+    // Codes of another length or from another key are rejected.
+    let key_8 = otp::Key::new(&digest::SHA1, b"12345678901234567890", 8).unwrap();
+    assert!(otp::verify_hotp(&key_8, 0, 0, b"755224").is_err());
+    let other_key = otp::Key::new(&digest::SHA256, b"12345678901234567890", 6).unwrap();
+    assert!(otp::verify_hotp(&other_key, 0, 0, b"755224").is_err());
+}
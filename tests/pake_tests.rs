@@ -0,0 +1,125 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{
+    pake::{self, spake2, spake2plus},
+    rand,
+};
+
+static ALGORITHMS: [&pake::Algorithm; 2] =
+    [&pake::P256_SHA256_HKDF_HMAC, &pake::P384_SHA512_HKDF_HMAC];
+
+#[test]
+fn spake2_exchange() {
+    let rng = rand::SystemRandom::new();
+    for alg in ALGORITHMS.iter() {
+        for &(w_a, w_b) in &[(b"password", b"password"), (b"password", b"passw0rd")] {
+            let a = spake2::Spake2::start_a(alg, w_a, &rng).unwrap();
+            let b = spake2::Spake2::start_b(alg, w_b, &rng).unwrap();
+            let (a_share, b_share) = (a.share().to_vec(), b.share().to_vec());
+
+            let a = a.finish(b"client", b"server", &b_share, b"aad").unwrap();
+            let b = b.finish(b"client", b"server", &a_share, b"aad").unwrap();
+            let a_confirmation = a.confirmation().to_vec();
+            let a_key = a.verify_peer(b.confirmation());
+            let b_key = b.verify_peer(&a_confirmation);
+            if w_a == w_b {
+                assert_eq!(a_key.unwrap().as_ref(), b_key.unwrap().as_ref());
+            } else {
+                assert!(a_key.is_err());
+                assert!(b_key.is_err());
+            }
+        }
+    }
+}
+
+#[test]
+fn spake2_different_identities() {
+    let rng = rand::SystemRandom::new();
+    let alg = &pake::P256_SHA256_HKDF_HMAC;
+    let a = spake2::Spake2::start_a(alg, b"password", &rng).unwrap();
+    let b = spake2::Spake2::start_b(alg, b"password", &rng).unwrap();
+    let (a_share, b_share) = (a.share().to_vec(), b.share().to_vec());
+    let a = a.finish(b"client", b"server", &b_share, b"").unwrap();
+    let b = b.finish(b"client", b"mallory", &a_share, b"").unwrap();
+    assert!(a.verify_peer(b.confirmation()).is_err());
+}
+
+#[test]
+fn spake2plus_exchange() {
+    let rng = rand::SystemRandom::new();
+    for alg in ALGORITHMS.iter() {
+        let (w0s, w1s) = ([0x42; 40], [0x43; 40]);
+        let l = spake2plus::compute_l(alg, &w1s).unwrap();
+        for &prover_w1s in &[w1s, [0x44; 40]] {
+            let prover = spake2plus::Prover::start(alg, &w0s, &prover_w1s, &rng).unwrap();
+            let verifier = spake2plus::Verifier::start(alg, &w0s, l.as_ref(), &rng).unwrap();
+            let (x, y) = (prover.share().to_vec(), verifier.share().to_vec());
+
+            let prover = prover.finish(b"context", b"prover", b"verifier", &y).unwrap();
+            let verifier = verifier.finish(b"context", b"prover", b"verifier", &x).unwrap();
+            let prover_confirmation = prover.confirmation().to_vec();
+            let prover_key = prover.verify_peer(verifier.confirmation());
+            let verifier_key = verifier.verify_peer(&prover_confirmation);
+            if prover_w1s == w1s {
+                assert_eq!(prover_key.unwrap().as_ref(), verifier_key.unwrap().as_ref());
+            } else {
+                assert!(prover_key.is_err());
+                assert!(verifier_key.is_err());
+            }
+        }
+    }
+}
+
+#[test]
+fn spake2plus_invalid_inputs() {
+    let rng = rand::SystemRandom::new();
+    let alg = &pake::P256_SHA256_HKDF_HMAC;
+    let l = spake2plus::compute_l(alg, &[0x43; 40]).unwrap();
+
+    // `w0s` and `w1s` must be nonempty and at most twice as long as a scalar,
+    // and must not be zero modulo the group order.
+    assert!(spake2plus::compute_l(alg, &[]).is_err());
+    assert!(spake2plus::compute_l(alg, &[0; 32]).is_err());
+    assert!(spake2plus::compute_l(alg, &[0x43; 65]).is_err());
+    assert!(spake2plus::Verifier::start(alg, &[0; 40], l.as_ref(), &rng).is_err());
+
+    // `L` must be on the curve.
+    let mut bad_l = l.as_ref().to_vec();
+    *bad_l.last_mut().unwrap() ^= 1;
+    assert!(spake2plus::Verifier::start(alg, &[0x42; 40], &bad_l, &rng).is_err());
+
+    let verifier = spake2plus::Verifier::start(alg, &[0x42; 40], l.as_ref(), &rng).unwrap();
+    assert!(verifier.finish(b"", b"", b"", &bad_l).is_err());
+}
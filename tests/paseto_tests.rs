@@ -0,0 +1,151 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{io::base64, paseto, rand, signature};
+
+const FOOTER: &[u8] = br#"{"kid":"1"}"#;
+const IMPLICIT_ASSERTION: &[u8] = br#"{"user_id":"2"}"#;
+
+// Returns `token` with the `i`th byte of its payload flipped, re-encoded.
+fn tamper(token: &str, header: &str, i: usize) -> String {
+    let mut parts = token[header.len()..].splitn(2, '.');
+    let mut payload = base64::decode(parts.next().unwrap(), &base64::URL_SAFE_NO_PAD).unwrap();
+    payload[i] ^= 1;
+    let mut tampered = String::from(header);
+    tampered.push_str(&base64::encode(&payload, &base64::URL_SAFE_NO_PAD));
+    if let Some(footer) = parts.next() {
+        tampered.push('.');
+        tampered.push_str(footer);
+    }
+    tampered
+}
+
+#[test]
+fn paseto_local_round_trip() {
+    let rng = rand::SystemRandom::new();
+    let key = paseto::LocalKey::new(&[1; paseto::LOCAL_KEY_LEN]).unwrap();
+    let other_key = paseto::LocalKey::new(&[2; paseto::LOCAL_KEY_LEN]).unwrap();
+
+    for &message in &[&b""[..], br#"{"sub":"alice"}"#, &[0x42; 1000][..]] {
+        let token = paseto::encrypt(&key, message, FOOTER, IMPLICIT_ASSERTION, &rng).unwrap();
+        assert!(token.starts_with("v4.local."));
+        assert_eq!(
+            paseto::decrypt(&key, &token, FOOTER, IMPLICIT_ASSERTION).unwrap(),
+            message
+        );
+        assert_eq!(paseto::footer(&token).unwrap(), FOOTER);
+
+        assert!(paseto::decrypt(&other_key, &token, FOOTER, IMPLICIT_ASSERTION).is_err());
+        assert!(paseto::decrypt(&key, &token, b"", IMPLICIT_ASSERTION).is_err());
+        assert!(paseto::decrypt(&key, &token, br#"{"kid":"2"}"#, IMPLICIT_ASSERTION).is_err());
+        assert!(paseto::decrypt(&key, &token, FOOTER, b"").is_err());
+
+        // The nonce is random.
+        let other_token = paseto::encrypt(&key, message, FOOTER, IMPLICIT_ASSERTION, &rng).unwrap();
+        assert_ne!(token, other_token);
+
+        // Every byte is authenticated.
+        let payload_len = 32 + message.len() + 32;
+        for i in 0..payload_len {
+            let tampered = tamper(&token, "v4.local.", i);
+            assert!(paseto::decrypt(&key, &tampered, FOOTER, IMPLICIT_ASSERTION).is_err());
+        }
+    }
+}
+
+#[test]
+fn paseto_public_round_trip() {
+    let rng = rand::SystemRandom::new();
+    let pkcs8_bytes = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let key_pair =
+        signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&pkcs8_bytes)).unwrap();
+    let public_key = key_pair.public_key_bytes();
+    let other_pkcs8_bytes = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let other_key_pair =
+        signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&other_pkcs8_bytes)).unwrap();
+
+    for &message in &[&b""[..], br#"{"sub":"alice"}"#, &[0x42; 1000][..]] {
+        let token = paseto::sign(&key_pair, message, FOOTER, IMPLICIT_ASSERTION);
+        assert!(token.starts_with("v4.public."));
+        assert_eq!(
+            paseto::verify(public_key, &token, FOOTER, IMPLICIT_ASSERTION).unwrap(),
+            message
+        );
+        assert_eq!(paseto::footer(&token).unwrap(), FOOTER);
+
+        let other_public_key = other_key_pair.public_key_bytes();
+        assert!(paseto::verify(other_public_key, &token, FOOTER, IMPLICIT_ASSERTION).is_err());
+        assert!(paseto::verify(public_key, &token, b"", IMPLICIT_ASSERTION).is_err());
+        assert!(paseto::verify(public_key, &token, FOOTER, b"").is_err());
+
+        for i in 0..(message.len() + 64) {
+            let tampered = tamper(&token, "v4.public.", i);
+            assert!(paseto::verify(public_key, &tampered, FOOTER, IMPLICIT_ASSERTION).is_err());
+        }
+    }
+}
+
+#[test]
+fn paseto_malformed_tokens() {
+    let rng = rand::SystemRandom::new();
+    let key = paseto::LocalKey::new(&[1; paseto::LOCAL_KEY_LEN]).unwrap();
+    let token = paseto::encrypt(&key, b"message", b"", b"", &rng).unwrap();
+    let with_footer = paseto::encrypt(&key, b"message", FOOTER, b"", &rng).unwrap();
+
+    // An unexpected footer.
+    assert!(paseto::decrypt(&key, &with_footer, b"", b"").is_err());
+
+    // An explicitly-empty footer.
+    assert!(paseto::decrypt(&key, &(token.clone() + "."), b"", b"").is_err());
+
+    // Too many parts.
+    assert!(paseto::decrypt(&key, &(with_footer.clone() + ".e30"), FOOTER, b"").is_err());
+    assert!(paseto::footer(&(with_footer.clone() + ".e30")).is_err());
+
+    // Other versions and purposes.
+    for header in &["v3.local.", "v4.public.", "V4.local.", "v4.local"] {
+        let other = token.replacen("v4.local.", header, 1);
+        assert!(paseto::decrypt(&key, &other, b"", b"").is_err());
+    }
+
+    // Too short.
+    assert!(paseto::decrypt(&key, "v4.local.", b"", b"").is_err());
+    assert!(paseto::decrypt(&key, &token[..(token.len() - 2)], b"", b"").is_err());
+
+    // Padding.
+    assert!(paseto::decrypt(&key, &(token.clone() + "="), b"", b"").is_err());
+
+    assert!(paseto::LocalKey::new(&[1; paseto::LOCAL_KEY_LEN - 1]).is_err());
+    assert!(paseto::LocalKey::new(&[1; paseto::LOCAL_KEY_LEN + 1]).is_err());
+}
@@ -0,0 +1,106 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+
+use core::num::NonZeroU32;
+use ring::{password_box, rand};
+
+#[test]
+fn password_box_round_trip() {
+    let rng = rand::SystemRandom::new();
+    for &kdf in &[
+        password_box::Kdf::Scrypt { log_n: 4 },
+        password_box::Kdf::Pbkdf2HmacSha256 {
+            iterations: NonZeroU32::new(100).unwrap(),
+        },
+    ] {
+        for &plaintext in &[&b""[..], b"secret", &[0x42; 1000][..]] {
+            let blob = password_box::protect(kdf, b"password", plaintext, &rng).unwrap();
+            assert_eq!(password_box::kdf(&blob), Ok(kdf));
+            assert_eq!(password_box::open(b"password", &blob).unwrap(), plaintext);
+            assert!(password_box::open(b"passwore", &blob).is_err());
+            assert!(password_box::open(b"", &blob).is_err());
+
+            // The salt and nonce are random.
+            let other = password_box::protect(kdf, b"password", plaintext, &rng).unwrap();
+            assert_ne!(blob, other);
+
+            // Every byte, including those of the header, is authenticated.
+            for i in 0..blob.len() {
+                let mut modified = blob.clone();
+                modified[i] ^= 1;
+                assert!(password_box::open(b"password", &modified).is_err());
+            }
+            for len in 0..blob.len() {
+                assert!(password_box::open(b"password", &blob[..len]).is_err());
+            }
+            let mut extended = blob.clone();
+            extended.push(0);
+            assert!(password_box::open(b"password", &extended).is_err());
+        }
+    }
+}
+
+#[test]
+fn password_box_rejects_excessive_work() {
+    let rng = rand::SystemRandom::new();
+    let kdf = password_box::Kdf::Scrypt {
+        log_n: password_box::MAX_SCRYPT_LOG_N + 1,
+    };
+    // Protecting with such parameters would be slow, so modify the header.
+    let mut blob = password_box::protect(
+        password_box::Kdf::Scrypt { log_n: 4 },
+        b"password",
+        b"secret",
+        &rng,
+    )
+    .unwrap();
+    blob[5] = password_box::MAX_SCRYPT_LOG_N + 1;
+    assert_eq!(password_box::kdf(&blob), Ok(kdf));
+    assert!(password_box::open(b"password", &blob).is_err());
+
+    let mut blob = password_box::protect(
+        password_box::Kdf::Pbkdf2HmacSha256 {
+            iterations: NonZeroU32::new(1).unwrap(),
+        },
+        b"password",
+        b"secret",
+        &rng,
+    )
+    .unwrap();
+    blob[5..9].copy_from_slice(&(password_box::MAX_PBKDF2_ITERATIONS + 1).to_be_bytes());
+    assert!(password_box::open(b"password", &blob).is_err());
+    blob[5..9].copy_from_slice(&[0; 4]);
+    assert!(password_box::kdf(&blob).is_err());
+}
@@ -30,6 +30,7 @@
     variant_size_differences,
     warnings
 )]
+#![cfg(feature = "use_heap")]
 
 use ring::{digest, error, pbkdf2, test};
 use std::num::NonZeroU32;
@@ -75,3 +76,40 @@ pub fn pbkdf2_one_iteration() {
     let mut out = vec![0u8; 2];
     pbkdf2::derive(&digest::SHA256, iterations, &salt, &secret, &mut out);
 }
+
+#[cfg(feature = "parallel")]
+#[test]
+pub fn pbkdf2_verify_batch() {
+    let iterations = NonZeroU32::new(10).unwrap();
+    let salts: Vec<Vec<u8>> = (0..50u8).map(|i| vec![i; 16]).collect();
+    let secrets: Vec<Vec<u8>> = (0..50u8).map(|i| vec![i ^ 0x5c; usize::from(i)]).collect();
+    let derived: Vec<Vec<u8>> = salts
+        .iter()
+        .zip(&secrets)
+        .enumerate()
+        .map(|(i, (salt, secret))| {
+            let mut out = vec![0u8; 1 + (i * 7) % 70];
+            pbkdf2::derive(&digest::SHA256, iterations, salt, secret, &mut out);
+            if i % 3 == 0 {
+                let len = out.len();
+                out[i % len] ^= 1;
+            }
+            out
+        })
+        .collect();
+
+    let batch: Vec<(&[u8], &[u8], &[u8])> = salts
+        .iter()
+        .zip(&secrets)
+        .zip(&derived)
+        .map(|((salt, secret), derived)| (&salt[..], &secret[..], &derived[..]))
+        .collect();
+    let results = pbkdf2::verify_batch(&digest::SHA256, iterations, &batch);
+    assert_eq!(results.len(), batch.len());
+    for (i, (&(salt, secret, derived), result)) in batch.iter().zip(&results).enumerate() {
+        assert_eq!(*result, pbkdf2::verify(&digest::SHA256, iterations, salt, secret, derived));
+        assert_eq!(result.is_ok(), i % 3 != 0);
+    }
+
+    assert!(pbkdf2::verify_batch(&digest::SHA256, iterations, &[]).is_empty());
+}
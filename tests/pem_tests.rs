@@ -0,0 +1,94 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{pem, rand, signature};
+
+#[test]
+fn pem_ed25519_from_pkcs8_pem() {
+    let rng = rand::SystemRandom::new();
+    let pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let pem = pem::encode(pem::PRIVATE_KEY, &pkcs8[..]).unwrap();
+    let from_der = signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&pkcs8)).unwrap();
+    let from_pem = signature::Ed25519KeyPair::from_pkcs8_pem(&pem).unwrap();
+    assert_eq!(from_der.public_key_bytes(), from_pem.public_key_bytes());
+
+    // The label must be "PRIVATE KEY".
+    let wrong_label = pem::encode(pem::PUBLIC_KEY, &pkcs8[..]).unwrap();
+    assert!(signature::Ed25519KeyPair::from_pkcs8_pem(&wrong_label).is_err());
+}
+
+#[test]
+fn pem_ecdsa_from_pkcs8_pem() {
+    // Generated with `openssl genpkey`.
+    const PRIVATE_KEY_PEM: &str = include_str!("ecdsa_test_private_key_p256_pkcs8.pem");
+    let key_pair = signature::ECDSAKeyPair::from_pkcs8_pem(
+        &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+        PRIVATE_KEY_PEM,
+    )
+    .unwrap();
+    let der = pem::decode(PRIVATE_KEY_PEM, pem::PRIVATE_KEY).unwrap();
+    assert_eq!(
+        pem::encode(pem::PRIVATE_KEY, &der).unwrap(),
+        PRIVATE_KEY_PEM
+    );
+
+    let rng = rand::SystemRandom::new();
+    assert!(key_pair
+        .sign(untrusted::Input::from(b"message"), &rng)
+        .is_ok());
+
+    assert!(signature::ECDSAKeyPair::from_pkcs8_pem(
+        &signature::ECDSA_P384_SHA384_FIXED_SIGNING,
+        PRIVATE_KEY_PEM,
+    )
+    .is_err());
+}
+
+#[cfg(feature = "rsa_signing")]
+#[test]
+fn pem_rsa_from_pem() {
+    // `src/rsa/signature_rsa_example_private_key.der`, converted with
+    // `openssl rsa -traditional` and `openssl pkcs8 -topk8 -nocrypt`.
+    const RSA_PRIVATE_KEY_PEM: &str = include_str!("rsa_test_private_key_2048.pem");
+    const PKCS8_PEM: &str = include_str!("rsa_test_private_key_2048_pkcs8.pem");
+
+    let from_der = signature::RSAKeyPair::from_der_pem(RSA_PRIVATE_KEY_PEM).unwrap();
+    let from_pkcs8 = signature::RSAKeyPair::from_pkcs8_pem(PKCS8_PEM).unwrap();
+    assert_eq!(from_der.public_modulus_len(), 256);
+    assert_eq!(from_pkcs8.public_modulus_len(), 256);
+
+    // Each function requires its own label.
+    assert!(signature::RSAKeyPair::from_der_pem(PKCS8_PEM).is_err());
+    assert!(signature::RSAKeyPair::from_pkcs8_pem(RSA_PRIVATE_KEY_PEM).is_err());
+}
@@ -0,0 +1,125 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{pkcs12, signature};
+use std::error::Error;
+
+// Generated with `openssl pkcs12 -export` from OpenSSL 3.0. The password is
+// "password".
+const DEFAULT: &[u8] = include_bytes!("pkcs12_test_default.p12");
+const UNENCRYPTED: &[u8] = include_bytes!("pkcs12_test_unencrypted.p12");
+const AES_128_SHA512: &[u8] = include_bytes!("pkcs12_test_aes128_sha512.p12");
+const TRIPLE_DES: &[u8] = include_bytes!("pkcs12_test_3des.p12");
+const NO_MAC: &[u8] = include_bytes!("pkcs12_test_no_mac.p12");
+
+const PRIVATE_KEY: &[u8] = include_bytes!("pkcs12_test_private_key.der");
+const CERT: &[u8] = include_bytes!("pkcs12_test_cert.der");
+const CA_CERT: &[u8] = include_bytes!("pkcs12_test_ca_cert.der");
+
+fn parse(input: &[u8], password: &str) -> Result<pkcs12::Pfx, ring::error::KeyRejected> {
+    pkcs12::Pfx::from_der(untrusted::Input::from(input), password)
+}
+
+#[test]
+fn pkcs12_default() {
+    // PBES2 with AES-256-CBC for the key and the certificates, and a
+    // HMAC-SHA256 MAC.
+    let pfx = parse(DEFAULT, "password").unwrap();
+    assert_eq!(pfx.private_keys().len(), 1);
+    let private_key = &pfx.private_keys()[0];
+    assert_eq!(private_key.as_ref(), PRIVATE_KEY);
+
+    let certificates = pfx.certificates();
+    assert_eq!(certificates.len(), 2);
+    assert_eq!(certificates[0].as_ref(), CERT);
+    assert_eq!(certificates[1].as_ref(), CA_CERT);
+    assert!(private_key.local_key_id().is_some());
+    assert_eq!(certificates[0].local_key_id(), private_key.local_key_id());
+    assert_eq!(certificates[1].local_key_id(), None);
+
+    let key_pair = signature::ECDSAKeyPair::from_pkcs8(
+        &signature::ECDSA_P256_SHA256_ASN1_SIGNING,
+        untrusted::Input::from(private_key.as_ref()),
+    );
+    assert!(key_pair.is_ok());
+}
+
+#[test]
+fn pkcs12_other_algorithms() {
+    // An unencrypted key bag and certificates with a HMAC-SHA1 MAC, and a
+    // key encrypted with AES-128-CBC with a HMAC-SHA512 MAC.
+    for input in &[UNENCRYPTED, AES_128_SHA512] {
+        let pfx = parse(input, "password").unwrap();
+        assert_eq!(pfx.private_keys().len(), 1);
+        assert_eq!(pfx.private_keys()[0].as_ref(), PRIVATE_KEY);
+        assert_eq!(pfx.certificates().len(), 1);
+        assert_eq!(pfx.certificates()[0].as_ref(), CERT);
+    }
+}
+
+#[test]
+fn pkcs12_wrong_password() {
+    for input in &[DEFAULT, UNENCRYPTED, AES_128_SHA512] {
+        assert_eq!(
+            parse(input, "wrong").unwrap_err().description(),
+            "DecryptionFailed"
+        );
+    }
+}
+
+#[test]
+fn pkcs12_modified() {
+    // Change a byte of the certificate within the unencrypted contents.
+    let index = UNENCRYPTED
+        .windows(CERT.len())
+        .position(|window| window == CERT)
+        .unwrap();
+    let mut modified = UNENCRYPTED.to_vec();
+    modified[index + CERT.len() - 1] ^= 1;
+    assert_eq!(
+        parse(&modified, "password").unwrap_err().description(),
+        "DecryptionFailed"
+    );
+}
+
+#[test]
+fn pkcs12_unsupported() {
+    // PBES1 with triple DES, and no MAC.
+    for input in &[TRIPLE_DES, NO_MAC] {
+        assert_eq!(
+            parse(input, "password").unwrap_err().description(),
+            "WrongAlgorithm"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use core::num::NonZeroU32;
+use ring::{digest, error, pem, pkcs8, rand, signature, test};
+
+#[test]
+fn pkcs8_decrypt_test() {
+    test::from_file("tests/pkcs8_tests.txt", |section, test_case| {
+        assert_eq!(section, "");
+        let password = test_case.consume_bytes("Password");
+        let input = test_case.consume_bytes("Input");
+        let expected_output = test_case.consume_optional_string("Output");
+        let expected_error = test_case.consume_optional_string("Error");
+
+        let result = pkcs8::decrypt(untrusted::Input::from(&input), &password);
+        match (result, expected_output, expected_error) {
+            (Ok(actual), Some(expected), None) => {
+                assert_eq!(actual, test::from_hex(&expected).unwrap());
+            },
+            (Err(actual), None, Some(expected)) => {
+                assert_eq!(actual.description_(), expected);
+                assert_eq!(format!("{:?}", actual.reason()), expected);
+            },
+            (result, _, _) => unreachable!("{:?}", result),
+        }
+
+        Ok(())
+    });
+}
+
+#[test]
+fn pkcs8_encrypt_round_trip_test() {
+    const PASSWORD: &[u8] = b"password";
+    let rng = rand::SystemRandom::new();
+    let pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let key_pair = signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&pkcs8)).unwrap();
+
+    let kdfs = [
+        pkcs8::Kdf::Pbkdf2 {
+            prf: &digest::SHA1,
+            iterations: NonZeroU32::new(1).unwrap(),
+        },
+        pkcs8::Kdf::Pbkdf2 {
+            prf: &digest::SHA256,
+            iterations: NonZeroU32::new(100).unwrap(),
+        },
+        pkcs8::Kdf::Pbkdf2 {
+            prf: &digest::SHA512_256,
+            iterations: NonZeroU32::new(100).unwrap(),
+        },
+        pkcs8::Kdf::Scrypt {
+            log_n: 4,
+            r: 8,
+            p: 1,
+        },
+    ];
+    let ciphers = [
+        &pkcs8::AES_128_CBC,
+        &pkcs8::AES_256_CBC,
+        &pkcs8::AES_128_GCM,
+        &pkcs8::AES_256_GCM,
+    ];
+
+    for kdf in kdfs.iter() {
+        for cipher in ciphers.iter() {
+            let encrypted = pkcs8::encrypt(&pkcs8[..], PASSWORD, kdf, cipher, &rng).unwrap();
+            let decrypted = pkcs8::decrypt(untrusted::Input::from(&encrypted), PASSWORD).unwrap();
+            assert_eq!(&decrypted[..], &pkcs8[..]);
+
+            let from_encrypted = signature::Ed25519KeyPair::from_encrypted_pkcs8(
+                untrusted::Input::from(&encrypted),
+                PASSWORD,
+            )
+            .unwrap();
+            assert_eq!(
+                from_encrypted.public_key_bytes(),
+                key_pair.public_key_bytes()
+            );
+        }
+    }
+
+    // AES-GCM reliably detects a wrong password.
+    for cipher in [&pkcs8::AES_128_GCM, &pkcs8::AES_256_GCM].iter() {
+        let encrypted = pkcs8::encrypt(&pkcs8[..], PASSWORD, &kdfs[0], cipher, &rng).unwrap();
+        assert_eq!(
+            pkcs8::decrypt(untrusted::Input::from(&encrypted), b"passwore")
+                .err()
+                .map(|e| e.reason()),
+            Some(error::KeyRejectedReason::DecryptionFailed)
+        );
+    }
+}
+
+#[test]
+fn pkcs8_encrypt_rejects_invalid_scrypt_params_test() {
+    let rng = rand::SystemRandom::new();
+    let pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let kdf = pkcs8::Kdf::Scrypt {
+        log_n: 0,
+        r: 8,
+        p: 1,
+    };
+    assert!(pkcs8::encrypt(&pkcs8[..], b"password", &kdf, &pkcs8::AES_256_CBC, &rng).is_err());
+}
+
+#[test]
+fn pkcs8_ecdsa_from_encrypted_pkcs8_test() {
+    const PRIVATE_KEY_PEM: &str = include_str!("ecdsa_test_private_key_p256_pkcs8.pem");
+    let rng = rand::SystemRandom::new();
+    let der = pem::decode(PRIVATE_KEY_PEM, pem::PRIVATE_KEY).unwrap();
+    let kdf = pkcs8::Kdf::Scrypt {
+        log_n: 10,
+        r: 8,
+        p: 1,
+    };
+    let encrypted = pkcs8::encrypt(&der, b"password", &kdf, &pkcs8::AES_256_CBC, &rng).unwrap();
+    let encrypted_pem = pem::encode(pem::ENCRYPTED_PRIVATE_KEY, &encrypted).unwrap();
+
+    let encrypted = pem::decode(&encrypted_pem, pem::ENCRYPTED_PRIVATE_KEY).unwrap();
+    let key_pair = signature::ECDSAKeyPair::from_encrypted_pkcs8(
+        &signature::ECDSA_P256_SHA256_ASN1_SIGNING,
+        untrusted::Input::from(&encrypted),
+        b"password",
+    )
+    .unwrap();
+    assert!(key_pair
+        .sign(untrusted::Input::from(b"message"), &rng)
+        .is_ok());
+
+    assert!(signature::ECDSAKeyPair::from_encrypted_pkcs8(
+        &signature::ECDSA_P384_SHA384_ASN1_SIGNING,
+        untrusted::Input::from(&encrypted),
+        b"password",
+    )
+    .is_err());
+}
+
+#[cfg(feature = "rsa_signing")]
+#[test]
+fn pkcs8_rsa_from_encrypted_pkcs8_test() {
+    const PKCS8_PEM: &str = include_str!("rsa_test_private_key_2048_pkcs8.pem");
+    let rng = rand::SystemRandom::new();
+    let der = pem::decode(PKCS8_PEM, pem::PRIVATE_KEY).unwrap();
+    let kdf = pkcs8::Kdf::Pbkdf2 {
+        prf: &digest::SHA256,
+        iterations: NonZeroU32::new(1000).unwrap(),
+    };
+    let encrypted = pkcs8::encrypt(&der, b"password", &kdf, &pkcs8::AES_128_GCM, &rng).unwrap();
+    let key_pair =
+        signature::RSAKeyPair::from_encrypted_pkcs8(untrusted::Input::from(&encrypted), b"password")
+            .unwrap();
+    assert_eq!(key_pair.public_modulus_len(), 256);
+}
@@ -0,0 +1,132 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{privacypass, rand};
+
+fn token_challenge(token_type: u16) -> Vec<u8> {
+    privacypass::encode_token_challenge(token_type, b"issuer.example", &[], b"origin.example")
+        .unwrap()
+}
+
+#[test]
+fn privacypass_voprf_round_trip() {
+    let rng = rand::SystemRandom::new();
+    let issuer = privacypass::VoprfIssuer::generate(&rng).unwrap();
+    let public_key = issuer.public_key().as_ref().to_vec();
+    let challenge = token_challenge(privacypass::TOKEN_TYPE_VOPRF);
+
+    // A modified response is rejected by the client.
+    for i in 0..(49 + 48 + 48) {
+        let request = privacypass::VoprfTokenRequest::new(&public_key, &challenge, &rng).unwrap();
+        let mut response = issuer.issue(request.as_ref(), &rng).unwrap();
+        response[i] ^= 1;
+        assert!(request.finalize(&response).is_err());
+    }
+
+    let request = privacypass::VoprfTokenRequest::new(&public_key, &challenge, &rng).unwrap();
+    let response = issuer.issue(request.as_ref(), &rng).unwrap();
+    let token = request.finalize(&response).unwrap();
+    assert_eq!(token.token_type(), privacypass::TOKEN_TYPE_VOPRF);
+    assert_eq!(token.token_key_id(), issuer.token_key_id());
+    assert!(issuer.verify(&challenge, &token).is_ok());
+    assert!(issuer
+        .verify(&token_challenge(privacypass::TOKEN_TYPE_BLIND_RSA), &token)
+        .is_err());
+
+    // Every byte of the token is authenticated.
+    for i in 0..token.as_ref().len() {
+        let mut modified = token.as_ref().to_vec();
+        modified[i] ^= 1;
+        if let Ok(modified) = privacypass::Token::from_bytes(&modified) {
+            assert!(issuer.verify(&challenge, &modified).is_err());
+        }
+    }
+
+    // Tokens are only valid for the key that issued them.
+    let other = privacypass::VoprfIssuer::generate(&rng).unwrap();
+    assert!(other.verify(&challenge, &token).is_err());
+    let request = privacypass::VoprfTokenRequest::new(&public_key, &challenge, &rng).unwrap();
+    assert!(other.issue(request.as_ref(), &rng).is_err());
+}
+
+#[test]
+fn privacypass_token_from_bytes() {
+    assert!(privacypass::Token::from_bytes(&[]).is_err());
+    let mut bytes = vec![0; 2 + 32 + 32 + 32 + 48];
+    bytes[1] = 1;
+    assert!(privacypass::Token::from_bytes(&bytes).is_ok());
+    assert!(privacypass::Token::from_bytes(&bytes[..(bytes.len() - 1)]).is_err());
+    bytes[1] = 2;
+    assert!(privacypass::Token::from_bytes(&bytes).is_err());
+    bytes[1] = 3;
+    assert!(privacypass::Token::from_bytes(&bytes).is_err());
+}
+
+#[cfg(feature = "rsa_signing")]
+#[test]
+fn privacypass_blind_rsa_round_trip() {
+    use ring::signature;
+
+    let rng = rand::SystemRandom::new();
+    let key_pair = signature::RSAKeyPair::from_der(untrusted::Input::from(include_bytes!(
+        "../src/rsa/signature_rsa_example_private_key.der"
+    )))
+    .unwrap();
+    let issuer = privacypass::BlindRsaIssuer::new(key_pair).unwrap();
+    let public_key =
+        privacypass::BlindRsaPublicKey::from_spki_der(issuer.public_key().spki_der()).unwrap();
+    let challenge = token_challenge(privacypass::TOKEN_TYPE_BLIND_RSA);
+
+    let request = privacypass::BlindRsaTokenRequest::new(&public_key, &challenge, &rng).unwrap();
+    let response = issuer.issue(request.as_ref()).unwrap();
+    let mut modified = response.clone();
+    modified[0] ^= 1;
+    let token = request.finalize(&response).unwrap();
+    assert_eq!(token.token_type(), privacypass::TOKEN_TYPE_BLIND_RSA);
+    assert!(public_key.verify(&challenge, &token).is_ok());
+    assert!(public_key
+        .verify(&token_challenge(privacypass::TOKEN_TYPE_VOPRF), &token)
+        .is_err());
+
+    let request = privacypass::BlindRsaTokenRequest::new(&public_key, &challenge, &rng).unwrap();
+    assert!(request.finalize(&modified).is_err());
+
+    for i in 0..token.as_ref().len() {
+        let mut modified = token.as_ref().to_vec();
+        modified[i] ^= 1;
+        if let Ok(modified) = privacypass::Token::from_bytes(&modified) {
+            assert!(public_key.verify(&challenge, &modified).is_err());
+        }
+    }
+}
@@ -0,0 +1,405 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Round-trip properties checked on generated inputs.
+//!
+//! The test vector files exercise few lengths; these tests check, for many
+//! generated inputs with lengths biased towards block boundaries, that
+//! opening undoes sealing, that signatures verify, and that keys survive
+//! being exported and imported. Each property is checked for
+//! `RING_PROPERTY_TESTS_CASES` inputs (default 256) generated from the seed
+//! `RING_PROPERTY_TESTS_SEED`; a failure reports the seed and the case so it
+//! can be reproduced.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(all(feature = "property_tests", feature = "use_heap"))]
+
+use core::num::NonZeroU32;
+use ring::{
+    aead::{self, chacha20_poly1305_openssh as openssh},
+    digest, pem, pkcs8, rand, signature,
+};
+
+#[test]
+fn property_aead_seal_open() {
+    for alg in [
+        &aead::AES_128_GCM,
+        &aead::AES_256_GCM,
+        &aead::CHACHA20_POLY1305,
+    ]
+    .iter()
+    {
+        check("aead_seal_open", 256, |gen| aead_seal_open(gen, alg));
+    }
+}
+
+fn aead_seal_open(gen: &mut Gen, alg: &'static aead::Algorithm) {
+    let key_bytes = gen.bytes(alg.key_len());
+    let nonce = gen.bytes(alg.nonce_len());
+    let ad_len = gen.len(300, 16);
+    let ad = gen.bytes(ad_len);
+    let plaintext_len = gen.len(1100, 64);
+    let plaintext = gen.bytes(plaintext_len);
+    let tag_len = alg.tag_len();
+
+    let s_key = aead::SealingKey::new(alg, &key_bytes).unwrap();
+    let o_key = aead::OpeningKey::new(alg, &key_bytes).unwrap();
+
+    let mut ciphertext = plaintext.clone();
+    ciphertext.resize(plaintext.len() + tag_len, 0);
+    let ciphertext_len =
+        aead::seal_in_place(&s_key, &nonce, &ad, &mut ciphertext, tag_len).unwrap();
+    assert_eq!(ciphertext_len, plaintext.len() + tag_len);
+
+    // The ciphertext may follow a prefix that the output overwrites.
+    let prefix_len = gen.below(21);
+    let mut in_out = gen.bytes(prefix_len);
+    in_out.extend_from_slice(&ciphertext);
+    let opened = aead::open_in_place(&o_key, &nonce, &ad, prefix_len, &mut in_out).unwrap();
+    assert_eq!(&opened[..], &plaintext[..]);
+
+    // Changing any bit of the ciphertext, the tag, or the additional data is
+    // detected.
+    let mut tampered = ciphertext.clone();
+    gen.flip_bit(&mut tampered);
+    assert!(aead::open_in_place(&o_key, &nonce, &ad, 0, &mut tampered).is_err());
+    if !ad.is_empty() {
+        let mut tampered_ad = ad.clone();
+        gen.flip_bit(&mut tampered_ad);
+        let mut in_out = ciphertext.clone();
+        assert!(aead::open_in_place(&o_key, &nonce, &tampered_ad, 0, &mut in_out).is_err());
+    }
+}
+
+#[test]
+fn property_aead_openssh_seal_open() {
+    check("aead_openssh_seal_open", 256, |gen| {
+        let mut key_bytes = [0u8; openssh::KEY_LEN];
+        gen.fill(&mut key_bytes);
+        let s_key = openssh::SealingKey::new(&key_bytes);
+        let o_key = openssh::OpeningKey::new(&key_bytes);
+        let sequence_number = gen.next_u64() as u32;
+
+        let packet_len = openssh::PACKET_LENGTH_LEN + gen.len(1100, 64);
+        let packet = gen.bytes(packet_len);
+        let mut in_out = packet.clone();
+        let mut tag = [0u8; openssh::TAG_LEN];
+        s_key.seal_in_place(sequence_number, &mut in_out, &mut tag).unwrap();
+
+        let mut encrypted_packet_length = [0u8; openssh::PACKET_LENGTH_LEN];
+        encrypted_packet_length.copy_from_slice(&in_out[..openssh::PACKET_LENGTH_LEN]);
+        assert_eq!(
+            &o_key.decrypt_packet_length(sequence_number, encrypted_packet_length)[..],
+            &packet[..openssh::PACKET_LENGTH_LEN]
+        );
+
+        let mut tampered = in_out.clone();
+        gen.flip_bit(&mut tampered);
+        assert!(o_key.open_in_place(sequence_number, &mut tampered, &tag).is_err());
+        assert!(o_key
+            .open_in_place(sequence_number.wrapping_add(1), &mut in_out.clone(), &tag)
+            .is_err());
+
+        let opened = o_key.open_in_place(sequence_number, &mut in_out, &tag).unwrap();
+        assert_eq!(opened, &packet[openssh::PACKET_LENGTH_LEN..]);
+    });
+}
+
+#[test]
+fn property_ecdsa_sign_verify() {
+    let rng = rand::SystemRandom::new();
+    for &(signing_alg, verification_alg) in [
+        (
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &signature::ECDSA_P256_SHA256_FIXED,
+        ),
+        (
+            &signature::ECDSA_P384_SHA384_FIXED_SIGNING,
+            &signature::ECDSA_P384_SHA384_FIXED,
+        ),
+        (
+            &signature::ECDSA_P256_SHA256_ASN1_SIGNING,
+            &signature::ECDSA_P256_SHA256_ASN1,
+        ),
+        (
+            &signature::ECDSA_P384_SHA384_ASN1_SIGNING,
+            &signature::ECDSA_P384_SHA384_ASN1,
+        ),
+    ]
+    .iter()
+    {
+        check("ecdsa_sign_verify", 64, |gen| {
+            let pkcs8 = signature::ECDSAKeyPair::generate_pkcs8(signing_alg, &rng).unwrap();
+            let key_pair =
+                signature::ECDSAKeyPair::from_pkcs8(signing_alg, untrusted::Input::from(pkcs8.as_ref()))
+                    .unwrap();
+            let msg_len = gen.len(300, 64);
+            let msg = gen.bytes(msg_len);
+            let mut sig = [0u8; signature::ECDSA_MAX_SIGNATURE_LEN];
+            let sig_len = key_pair
+                .sign_into(&rng, untrusted::Input::from(&msg), &mut sig)
+                .unwrap();
+            check_signature(
+                verification_alg,
+                key_pair.public_key().as_ref(),
+                &msg,
+                &sig[..sig_len],
+                gen,
+            );
+        });
+    }
+}
+
+#[test]
+fn property_ed25519_sign_verify() {
+    let rng = rand::SystemRandom::new();
+    check("ed25519_sign_verify", 256, |gen| {
+        let pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let key_pair =
+            signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&pkcs8)).unwrap();
+        let msg_len = gen.len(300, 64);
+        let msg = gen.bytes(msg_len);
+        let sig = key_pair.sign(&msg);
+        check_signature(
+            &signature::ED25519,
+            key_pair.public_key_bytes(),
+            &msg,
+            sig.as_ref(),
+            gen,
+        );
+    });
+}
+
+#[cfg(feature = "rsa_signing")]
+#[test]
+fn property_rsa_sign_verify() {
+    const PRIVATE_KEY_PEM: &str = include_str!("rsa_test_private_key_2048.pem");
+    let rng = rand::SystemRandom::new();
+    let key_pair = signature::RSAKeyPair::from_der_pem(PRIVATE_KEY_PEM).unwrap();
+    let public_key = key_pair.public_key();
+    let mut signing_state =
+        signature::RSASigningState::new(std::sync::Arc::new(key_pair)).unwrap();
+    let algs: [(&'static signature::RSAEncoding, &signature::RSAParameters); 4] = [
+        (
+            &signature::RSA_PKCS1_SHA256,
+            &signature::RSA_PKCS1_2048_8192_SHA256,
+        ),
+        (
+            &signature::RSA_PKCS1_SHA512,
+            &signature::RSA_PKCS1_2048_8192_SHA512,
+        ),
+        (
+            &signature::RSA_PSS_SHA256,
+            &signature::RSA_PSS_2048_8192_SHA256,
+        ),
+        (
+            &signature::RSA_PSS_SHA512,
+            &signature::RSA_PSS_2048_8192_SHA512,
+        ),
+    ];
+    for &(padding_alg, verification_alg) in algs.iter() {
+        check("rsa_sign_verify", 16, |gen| {
+            let msg_len = gen.len(300, 64);
+            let msg = gen.bytes(msg_len);
+            let mut sig = vec![0u8; signing_state.key_pair().public_modulus_len()];
+            signing_state.sign(padding_alg, &rng, &msg, &mut sig).unwrap();
+            check_signature(verification_alg, public_key.as_ref(), &msg, &sig, gen);
+        });
+    }
+}
+
+// Checks that `sig` is a valid signature of `msg` and that changing the
+// message or the signature invalidates it.
+fn check_signature(
+    alg: &signature::VerificationAlgorithm, public_key: &[u8], msg: &[u8], sig: &[u8],
+    gen: &mut Gen,
+) {
+    let verify = |msg: &[u8], sig: &[u8]| {
+        signature::verify(
+            alg,
+            untrusted::Input::from(public_key),
+            untrusted::Input::from(msg),
+            untrusted::Input::from(sig),
+        )
+    };
+    assert!(verify(msg, sig).is_ok());
+    if !msg.is_empty() {
+        let mut tampered = msg.to_vec();
+        gen.flip_bit(&mut tampered);
+        assert!(verify(&tampered, sig).is_err());
+    }
+    let mut tampered = sig.to_vec();
+    gen.flip_bit(&mut tampered);
+    assert!(verify(msg, &tampered).is_err());
+}
+
+#[test]
+fn property_pkcs8_export_import() {
+    const PASSWORD: &[u8] = b"password";
+    let rng = rand::SystemRandom::new();
+    check("pkcs8_export_import", 64, |gen| {
+        let ed25519_pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let ecdsa_pkcs8 = signature::ECDSAKeyPair::generate_pkcs8(
+            &signature::ECDSA_P256_SHA256_ASN1_SIGNING,
+            &rng,
+        )
+        .unwrap();
+
+        for pkcs8 in [&ed25519_pkcs8[..], ecdsa_pkcs8.as_ref()].iter() {
+            let pem = pem::encode(pem::PRIVATE_KEY, pkcs8).unwrap();
+            assert_eq!(&pem::decode(&pem, pem::PRIVATE_KEY).unwrap()[..], *pkcs8);
+
+            let cipher = [&pkcs8::AES_128_CBC, &pkcs8::AES_256_GCM][gen.below(2)];
+            let kdf = pkcs8::Kdf::Pbkdf2 {
+                prf: &digest::SHA256,
+                iterations: NonZeroU32::new(1 + gen.below(4) as u32).unwrap(),
+            };
+            let encrypted = pkcs8::encrypt(pkcs8, PASSWORD, &kdf, cipher, &rng).unwrap();
+            let decrypted = pkcs8::decrypt(untrusted::Input::from(&encrypted), PASSWORD).unwrap();
+            assert_eq!(&decrypted[..], *pkcs8);
+        }
+
+        let ed25519 =
+            signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(&ed25519_pkcs8)).unwrap();
+        let ed25519_pem = pem::encode(pem::PRIVATE_KEY, &ed25519_pkcs8).unwrap();
+        let ed25519_again = signature::Ed25519KeyPair::from_pkcs8_pem(&ed25519_pem).unwrap();
+        assert_eq!(
+            ed25519.public_key_bytes(),
+            ed25519_again.public_key_bytes()
+        );
+    });
+}
+
+#[cfg(feature = "rsa_signing")]
+#[test]
+fn property_rsa_pkcs1_export_import() {
+    const PRIVATE_KEY_PEM: &str = include_str!("rsa_test_private_key_2048.pem");
+    let key_pair = signature::RSAKeyPair::from_der_pem(PRIVATE_KEY_PEM).unwrap();
+    let der = key_pair.to_pkcs1_der();
+    let imported = signature::RSAKeyPair::from_pkcs1_der(untrusted::Input::from(&der)).unwrap();
+    assert_eq!(imported.to_pkcs1_der(), der);
+    assert_eq!(
+        imported.public_key().as_ref(),
+        key_pair.public_key().as_ref()
+    );
+
+    // Every truncation of the encoding is rejected.
+    check("rsa_pkcs1_export_import", 256, |gen| {
+        let len = gen.below(der.len());
+        let truncated = untrusted::Input::from(&der[..len]);
+        assert!(signature::RSAKeyPair::from_pkcs1_der(truncated).is_err());
+    });
+}
+
+/// Checks a property for `cases` generated inputs, or
+/// `RING_PROPERTY_TESTS_CASES` if that is set, reporting the seed and the case
+/// that failed.
+fn check(name: &str, cases: usize, mut property: impl FnMut(&mut Gen)) {
+    let seed = env_u64("RING_PROPERTY_TESTS_SEED").unwrap_or(0x7269_6e67_7465_7374);
+    let cases = env_u64("RING_PROPERTY_TESTS_CASES").map_or(cases, |cases| cases as usize);
+    let mut gen = Gen::new(seed);
+    for case in 0..cases {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            property(&mut gen);
+        }));
+        if result.is_err() {
+            panic!(
+                "property {} failed for case {} with RING_PROPERTY_TESTS_SEED={}",
+                name, case, seed
+            );
+        }
+    }
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name).ok().map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a number", name))
+    })
+}
+
+/// A deterministic generator of test inputs (xorshift64*). It isn't
+/// `rand::SecureRandom`, which is sealed; keys are generated with
+/// `SystemRandom` and everything else with a `Gen`.
+#[derive(Debug)]
+struct Gen {
+    state: u64,
+}
+
+impl Gen {
+    fn new(seed: u64) -> Self {
+        Self {
+            state: seed | 1, // xorshift gets stuck at zero.
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a value in `0..n`, which must not be empty.
+    fn below(&mut self, n: usize) -> usize { (self.next_u64() % (n as u64)) as usize }
+
+    /// Returns a length of at most `max`. Half of the lengths are a multiple of
+    /// `block_len`, or one less or one more than one, where bugs tend to be.
+    fn len(&mut self, max: usize, block_len: usize) -> usize {
+        if self.below(2) == 0 {
+            return self.below(max + 1);
+        }
+        let blocks = self.below(max / block_len + 1);
+        match (blocks * block_len) as isize + self.below(3) as isize - 1 {
+            len if len < 0 => 0,
+            len => core::cmp::min(len as usize, max),
+        }
+    }
+
+    fn fill(&mut self, dest: &mut [u8]) {
+        for b in dest.iter_mut() {
+            *b = self.next_u64() as u8;
+        }
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; len];
+        self.fill(&mut bytes);
+        bytes
+    }
+
+    /// Flips one bit of `bytes`, which must not be empty.
+    fn flip_bit(&mut self, bytes: &mut [u8]) {
+        let bit = self.below(bytes.len() * 8);
+        bytes[bit / 8] ^= 1 << (bit % 8);
+    }
+}
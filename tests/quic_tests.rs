@@ -0,0 +1,124 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+
+use ring::{aead, quic};
+
+static SUITES: [&quic::CipherSuite; 3] = [
+    &quic::TLS13_AES_128_GCM_SHA256,
+    &quic::TLS13_AES_256_GCM_SHA384,
+    &quic::TLS13_CHACHA20_POLY1305_SHA256,
+];
+
+fn seal(key: &quic::PacketKey, packet_number: u64, header: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut in_out = payload.to_vec();
+    in_out.resize(payload.len() + aead::MAX_TAG_LEN, 0);
+    let nonce = key.nonce(packet_number);
+    let _ = aead::seal_in_place(
+        &key.sealing_key().unwrap(),
+        &nonce,
+        header,
+        &mut in_out,
+        aead::MAX_TAG_LEN,
+    )
+    .unwrap();
+    in_out
+}
+
+fn open(
+    key: &quic::PacketKey, packet_number: u64, header: &[u8], mut in_out: Vec<u8>,
+) -> Option<Vec<u8>> {
+    let nonce = key.nonce(packet_number);
+    aead::open_in_place(&key.opening_key().unwrap(), &nonce, header, 0, &mut in_out)
+        .ok()
+        .map(|plaintext| plaintext.to_vec())
+}
+
+#[test]
+fn quic_initial_secrets() {
+    let connection_id = [0x42; 8];
+    for &version in &[&quic::V1, &quic::V2] {
+        let client = quic::initial_secrets(version, &connection_id).unwrap();
+        let server = quic::initial_secrets(version, &connection_id).unwrap();
+        let header = [0xc0, 0x00, 0x00, 0x00, 0x01];
+
+        // Each side opens what the other seals with the same secret.
+        let sealed = seal(&client.client.packet_key(), 0, &header, b"ClientHello");
+        let opened = open(&server.client.packet_key(), 0, &header, sealed.clone());
+        assert_eq!(opened.as_ref().map(|p| &p[..]), Some(&b"ClientHello"[..]));
+
+        // The directions, packet numbers, and headers are all distinct.
+        assert!(open(&server.server.packet_key(), 0, &header, sealed.clone()).is_none());
+        assert!(open(&server.client.packet_key(), 1, &header, sealed.clone()).is_none());
+        assert!(open(&server.client.packet_key(), 0, &header[1..], sealed).is_none());
+        assert_ne!(
+            client.client.header_protection_key().as_ref(),
+            client.server.header_protection_key().as_ref()
+        );
+    }
+
+    // The versions have different salts, and connection IDs are limited to
+    // 20 bytes.
+    let v1 = quic::initial_secrets(&quic::V1, &connection_id).unwrap();
+    let v2 = quic::initial_secrets(&quic::V2, &connection_id).unwrap();
+    assert_ne!(
+        v1.client.header_protection_key().as_ref(),
+        v2.client.header_protection_key().as_ref()
+    );
+    assert!(quic::initial_secrets(&quic::V1, &[0; quic::MAX_CONNECTION_ID_LEN]).is_ok());
+    assert!(quic::initial_secrets(&quic::V1, &[0; quic::MAX_CONNECTION_ID_LEN + 1]).is_err());
+}
+
+#[test]
+fn quic_key_update() {
+    for &suite in SUITES.iter() {
+        let len = suite.digest_algorithm().output_len;
+        let secret = quic::Secret::new(&quic::V1, suite, &vec![0x17; len]).unwrap();
+        assert!(quic::Secret::new(&quic::V1, suite, &vec![0x17; len - 1]).is_err());
+        assert_eq!(
+            secret.header_protection_key().as_ref().len(),
+            suite.aead_algorithm().key_len()
+        );
+
+        let sealed = seal(&secret.packet_key(), 7, &[], b"STREAM");
+        let next = secret.next();
+        assert!(open(&next.packet_key(), 7, &[], sealed.clone()).is_none());
+        assert!(open(&secret.packet_key(), 7, &[], sealed).is_some());
+
+        // Both peers derive the same next secret.
+        let peer = quic::Secret::new(&quic::V1, suite, &vec![0x17; len]).unwrap().next();
+        let sealed = seal(&next.packet_key(), 8, &[], b"STREAM");
+        assert!(open(&peer.packet_key(), 8, &[], sealed).is_some());
+    }
+}
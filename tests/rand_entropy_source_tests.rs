@@ -0,0 +1,57 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+
+// Registering an entropy source affects every `SystemRandom` in the process,
+// so this is kept separate from the other tests.
+
+use ring::{
+    error,
+    rand::{self, SecureRandom},
+};
+
+fn fill_with_pattern(dest: &mut [u8]) -> Result<(), error::Unspecified> {
+    for (i, b) in dest.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    Ok(())
+}
+
+#[test]
+fn rand_register_entropy_source() {
+    rand::register_entropy_source(fill_with_pattern).unwrap();
+    assert!(rand::register_entropy_source(fill_with_pattern).is_err());
+
+    let mut buf = [0xff; 4];
+    rand::SystemRandom::new().fill(&mut buf).unwrap();
+    assert_eq!(buf, [0, 1, 2, 3]);
+}
@@ -0,0 +1,227 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{
+    digest, error,
+    rand::{self, SecureRandom},
+    test,
+};
+
+// The DRBG inputs of a test case, in the order they are used.
+struct Inputs {
+    entropy_input: Vec<u8>,
+    nonce: Vec<u8>,
+    personalization_string: Vec<u8>,
+    entropy_input_reseed: Vec<u8>,
+    additional_input_reseed: Vec<u8>,
+    additional_input_1: Vec<u8>,
+    additional_input_2: Vec<u8>,
+}
+
+impl Inputs {
+    fn consume(test_case: &mut test::TestCase) -> Self {
+        Self {
+            entropy_input: test_case.consume_bytes("EntropyInput"),
+            nonce: test_case.consume_bytes("Nonce"),
+            personalization_string: test_case.consume_bytes("PersonalizationString"),
+            entropy_input_reseed: test_case.consume_bytes("EntropyInputReseed"),
+            additional_input_reseed: test_case.consume_bytes("AdditionalInputReseed"),
+            additional_input_1: test_case.consume_bytes("AdditionalInput1"),
+            additional_input_2: test_case.consume_bytes("AdditionalInput2"),
+        }
+    }
+}
+
+#[test]
+fn rand_drbg_tests() {
+    test::from_file("tests/rand_tests.txt", |section, test_case| {
+        match section {
+            "HMAC_DRBG" => {
+                let digest_alg = test_case.consume_digest_alg("Digest").unwrap();
+                let inputs = Inputs::consume(test_case);
+                let expected = test_case.consume_bytes("ReturnedBits");
+                let new = || {
+                    rand::HmacDrbg::new(
+                        digest_alg,
+                        &inputs.entropy_input,
+                        &inputs.nonce,
+                        &inputs.personalization_string,
+                    )
+                };
+                check_drbg(&inputs, &expected, new()?, new()?)
+            },
+            "CTR_DRBG" => {
+                let inputs = Inputs::consume(test_case);
+                let expected = test_case.consume_bytes("ReturnedBits");
+                let new = || {
+                    rand::CtrDrbg::new(
+                        &inputs.entropy_input,
+                        &inputs.nonce,
+                        &inputs.personalization_string,
+                    )
+                };
+                check_drbg(&inputs, &expected, new()?, new()?)
+            },
+            _ => unreachable!(),
+        }
+    });
+}
+
+// Checks the output of `drbg` against `expected` and, when there is no
+// additional input to generate, checks that `fill()` of `drbg_to_fill`
+// produces the same output.
+fn check_drbg<D: Drbg>(
+    inputs: &Inputs, expected: &[u8], mut drbg: D, mut drbg_to_fill: D,
+) -> Result<(), error::Unspecified> {
+    let mut actual = vec![0; expected.len()];
+    drbg.reseed(&inputs.entropy_input_reseed, &inputs.additional_input_reseed)?;
+    drbg.generate(&mut actual, &inputs.additional_input_1)?;
+    drbg.generate(&mut actual, &inputs.additional_input_2)?;
+    assert_eq!(actual, expected);
+
+    if inputs.additional_input_1.is_empty() && inputs.additional_input_2.is_empty() {
+        let mut actual = vec![0; expected.len()];
+        drbg_to_fill.reseed(&inputs.entropy_input_reseed, &inputs.additional_input_reseed)?;
+        drbg_to_fill.fill(&mut actual)?;
+        drbg_to_fill.fill(&mut actual)?;
+        assert_eq!(actual, expected);
+    }
+    Ok(())
+}
+
+trait Drbg: SecureRandom {
+    fn reseed(
+        &mut self, entropy_input: &[u8], additional_input: &[u8],
+    ) -> Result<(), error::Unspecified>;
+    fn generate(
+        &mut self, out: &mut [u8], additional_input: &[u8],
+    ) -> Result<(), error::Unspecified>;
+}
+
+macro_rules! impl_drbg {
+    ( $drbg:ty ) => {
+        impl Drbg for $drbg {
+            fn reseed(
+                &mut self, entropy_input: &[u8], additional_input: &[u8],
+            ) -> Result<(), error::Unspecified> {
+                <$drbg>::reseed(self, entropy_input, additional_input)
+            }
+
+            fn generate(
+                &mut self, out: &mut [u8], additional_input: &[u8],
+            ) -> Result<(), error::Unspecified> {
+                <$drbg>::generate(self, out, additional_input)
+            }
+        }
+    };
+}
+
+impl_drbg!(rand::HmacDrbg);
+impl_drbg!(rand::CtrDrbg);
+
+#[test]
+fn rand_drbg_entropy_input_len() {
+    let entropy_input = [0x55; 32];
+    for &(digest_alg, min_len) in &[
+        (&digest::SHA1, 16),
+        (&digest::SHA256, 32),
+        (&digest::SHA384, 32),
+        (&digest::SHA512, 32),
+    ] {
+        let mut drbg =
+            rand::HmacDrbg::new(digest_alg, &entropy_input[..min_len], &[], &[]).unwrap();
+        assert!(rand::HmacDrbg::new(digest_alg, &entropy_input[..(min_len - 1)], &[], &[])
+            .is_err());
+        assert!(drbg.reseed(&entropy_input[..min_len], &[]).is_ok());
+        assert!(drbg.reseed(&entropy_input[..(min_len - 1)], &[]).is_err());
+    }
+
+    let mut drbg = rand::CtrDrbg::new(&entropy_input, &[], &[]).unwrap();
+    assert!(rand::CtrDrbg::new(&entropy_input[..31], &[], &[]).is_err());
+    assert!(drbg.reseed(&entropy_input, &[]).is_ok());
+    assert!(drbg.reseed(&entropy_input[..31], &[]).is_err());
+}
+
+#[test]
+fn rand_drbg_request_len() {
+    const MAX_REQUEST_LEN: usize = 65536;
+    let entropy_input = [0x55; 32];
+
+    let new = || rand::HmacDrbg::new(&digest::SHA256, &entropy_input, &[], &[]).unwrap();
+    check_request_len(MAX_REQUEST_LEN, new(), new());
+    let new = || rand::CtrDrbg::new(&entropy_input, &[], &[]).unwrap();
+    check_request_len(MAX_REQUEST_LEN, new(), new());
+}
+
+// Checks that `generate()` rejects requests longer than `max_request_len` and
+// that `fill()` splits them into requests of that length.
+fn check_request_len<D: Drbg>(max_request_len: usize, mut drbg: D, drbg_to_fill: D) {
+    let mut expected = vec![0; max_request_len + 100];
+    assert!(drbg.generate(&mut expected, &[]).is_err());
+    let (first, second) = expected.split_at_mut(max_request_len);
+    drbg.generate(first, &[]).unwrap();
+    drbg.generate(second, &[]).unwrap();
+
+    let mut actual = vec![0; expected.len()];
+    drbg_to_fill.fill(&mut actual).unwrap();
+    assert_eq!(actual, expected);
+}
+
+#[cfg(feature = "test_util")]
+#[test]
+fn rand_test_util() {
+    use ring::rand::test_util;
+
+    let rng = test_util::FixedByteRandom { byte: 0x01 };
+    assert_eq!(rand::generate::<u32>(&rng).unwrap().expose(), 0x0101_0101);
+
+    let rng = test_util::FixedSliceRandom { bytes: &[1, 2, 3, 4] };
+    assert_eq!(rand::generate::<[u8; 4]>(&rng).unwrap().expose(), [1, 2, 3, 4]);
+
+    let rng = test_util::FixedSliceSequenceRandom::new(&[&[1, 2], &[3, 4, 5, 6]]);
+    assert_eq!(rand::generate::<[u8; 2]>(&rng).unwrap().expose(), [1, 2]);
+    assert_eq!(rand::generate::<u32>(&rng).unwrap().expose(), 0x0605_0403);
+}
+
+#[test]
+fn rand_traits() {
+    test::compile_time_assert_send::<rand::SystemRandom>();
+    test::compile_time_assert_sync::<rand::SystemRandom>();
+
+    // The DRBGs can be moved to another thread but not shared.
+    test::compile_time_assert_send::<rand::HmacDrbg>();
+    test::compile_time_assert_send::<rand::CtrDrbg>();
+    test::compile_time_assert_send::<rand::SystemDrbg>();
+    test::compile_time_assert_send::<rand::JitterRandom>();
+}
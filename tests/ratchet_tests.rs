@@ -0,0 +1,200 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{error, rand, ratchet};
+
+static SHARED_KEYS: ratchet::SharedKeys = ratchet::SharedKeys {
+    root_key: &[1; ratchet::KEY_LEN],
+    initiator_header_key: &[2; ratchet::KEY_LEN],
+    responder_next_header_key: &[3; ratchet::KEY_LEN],
+};
+
+struct Message {
+    header: [u8; ratchet::ENCRYPTED_HEADER_LEN],
+    ciphertext: Vec<u8>,
+}
+
+fn new_pair(rng: &rand::SecureRandom) -> (ratchet::Ratchet, ratchet::Ratchet) {
+    let bob_key_pair = ratchet::KeyPair::generate(rng).unwrap();
+    let alice =
+        ratchet::Ratchet::new_initiator(&SHARED_KEYS, bob_key_pair.public_key(), rng).unwrap();
+    let bob = ratchet::Ratchet::new_responder(&SHARED_KEYS, bob_key_pair);
+    (alice, bob)
+}
+
+fn encrypt(sender: &mut ratchet::Ratchet, plaintext: &[u8], rng: &rand::SecureRandom) -> Message {
+    let mut header = [0; ratchet::ENCRYPTED_HEADER_LEN];
+    let mut ciphertext = plaintext.to_vec();
+    ciphertext.extend_from_slice(&[0; ratchet::TAG_LEN]);
+    sender.encrypt_in_place(b"ad", &mut header, &mut ciphertext, rng).unwrap();
+    Message { header, ciphertext }
+}
+
+fn decrypt(
+    receiver: &mut ratchet::Ratchet, message: &Message,
+    skipped: &mut ratchet::SkippedMessageKeyStore, rng: &rand::SecureRandom,
+) -> Result<Vec<u8>, error::Unspecified> {
+    let mut in_out = message.ciphertext.clone();
+    let plaintext = receiver.decrypt_in_place(b"ad", &message.header, &mut in_out, skipped, rng)?;
+    Ok(plaintext.to_vec())
+}
+
+#[test]
+fn ratchet_conversation() {
+    let rng = rand::SystemRandom::new();
+    let (mut alice, mut bob) = new_pair(&rng);
+    let mut alice_skipped = ratchet::SkippedMessageKeyStore::new(100);
+    let mut bob_skipped = ratchet::SkippedMessageKeyStore::new(100);
+
+    for round in 0..4u8 {
+        for i in 0..3u8 {
+            let m = encrypt(&mut alice, &[round, i], &rng);
+            assert_eq!(decrypt(&mut bob, &m, &mut bob_skipped, &rng).unwrap(), [round, i]);
+        }
+        let m = encrypt(&mut bob, &[round], &rng);
+        assert_eq!(decrypt(&mut alice, &m, &mut alice_skipped, &rng).unwrap(), [round]);
+    }
+    assert!(alice_skipped.is_empty());
+    assert!(bob_skipped.is_empty());
+}
+
+#[test]
+fn ratchet_responder_cannot_send_first() {
+    let rng = rand::SystemRandom::new();
+    let (_, mut bob) = new_pair(&rng);
+    let mut header = [0; ratchet::ENCRYPTED_HEADER_LEN];
+    let mut in_out = [0; ratchet::TAG_LEN];
+    assert!(bob.encrypt_in_place(b"", &mut header, &mut in_out, &rng).is_err());
+}
+
+#[test]
+fn ratchet_out_of_order() {
+    let rng = rand::SystemRandom::new();
+    let (mut alice, mut bob) = new_pair(&rng);
+    let mut alice_skipped = ratchet::SkippedMessageKeyStore::new(100);
+    let mut bob_skipped = ratchet::SkippedMessageKeyStore::new(100);
+
+    let a0 = encrypt(&mut alice, b"a0", &rng);
+    let a1 = encrypt(&mut alice, b"a1", &rng);
+    let a2 = encrypt(&mut alice, b"a2", &rng);
+    assert_eq!(decrypt(&mut bob, &a1, &mut bob_skipped, &rng).unwrap(), b"a1");
+    assert_eq!(bob_skipped.len(), 1);
+
+    let b0 = encrypt(&mut bob, b"b0", &rng);
+    assert_eq!(decrypt(&mut alice, &b0, &mut alice_skipped, &rng).unwrap(), b"b0");
+    let a3 = encrypt(&mut alice, b"a3", &rng);
+
+    // `a3` starts a new chain, so `a2` is skipped too.
+    assert_eq!(decrypt(&mut bob, &a3, &mut bob_skipped, &rng).unwrap(), b"a3");
+    assert_eq!(bob_skipped.len(), 2);
+    assert_eq!(decrypt(&mut bob, &a2, &mut bob_skipped, &rng).unwrap(), b"a2");
+    assert_eq!(decrypt(&mut bob, &a0, &mut bob_skipped, &rng).unwrap(), b"a0");
+    assert!(bob_skipped.is_empty());
+
+    // Each message can be decrypted only once.
+    assert!(decrypt(&mut bob, &a0, &mut bob_skipped, &rng).is_err());
+    assert!(decrypt(&mut bob, &a3, &mut bob_skipped, &rng).is_err());
+}
+
+#[test]
+fn ratchet_forgery_does_not_change_state() {
+    let rng = rand::SystemRandom::new();
+    let (mut alice, mut bob) = new_pair(&rng);
+    let mut skipped = ratchet::SkippedMessageKeyStore::new(100);
+
+    let _ = encrypt(&mut alice, b"a0", &rng);
+    let a1 = encrypt(&mut alice, b"a1", &rng);
+
+    let mut forged = Message {
+        header: a1.header,
+        ciphertext: a1.ciphertext.clone(),
+    };
+    forged.ciphertext[0] ^= 1;
+    assert!(decrypt(&mut bob, &forged, &mut skipped, &rng).is_err());
+    forged.ciphertext[0] ^= 1;
+    forged.header[ratchet::ENCRYPTED_HEADER_LEN - 1] ^= 1;
+    assert!(decrypt(&mut bob, &forged, &mut skipped, &rng).is_err());
+    assert!(skipped.is_empty());
+
+    let mut in_out = a1.ciphertext.clone();
+    assert!(bob.decrypt_in_place(b"other", &a1.header, &mut in_out, &mut skipped, &rng).is_err());
+
+    assert_eq!(decrypt(&mut bob, &a1, &mut skipped, &rng).unwrap(), b"a1");
+}
+
+#[test]
+fn ratchet_max_skip() {
+    let rng = rand::SystemRandom::new();
+    let (mut alice, mut bob) = new_pair(&rng);
+    let mut skipped = ratchet::SkippedMessageKeyStore::new(2000);
+
+    for _ in 0..=ratchet::MAX_SKIP {
+        let _ = encrypt(&mut alice, b"", &rng);
+    }
+    let m = encrypt(&mut alice, b"too far", &rng);
+    assert!(decrypt(&mut bob, &m, &mut skipped, &rng).is_err());
+}
+
+#[test]
+fn ratchet_store_drops_oldest() {
+    let rng = rand::SystemRandom::new();
+    let (mut alice, mut bob) = new_pair(&rng);
+    let mut skipped = ratchet::SkippedMessageKeyStore::new(2);
+
+    let a0 = encrypt(&mut alice, b"a0", &rng);
+    let a1 = encrypt(&mut alice, b"a1", &rng);
+    let a2 = encrypt(&mut alice, b"a2", &rng);
+    let a3 = encrypt(&mut alice, b"a3", &rng);
+    assert_eq!(decrypt(&mut bob, &a3, &mut skipped, &rng).unwrap(), b"a3");
+    assert_eq!(skipped.len(), 2);
+    assert!(decrypt(&mut bob, &a0, &mut skipped, &rng).is_err());
+    assert_eq!(decrypt(&mut bob, &a2, &mut skipped, &rng).unwrap(), b"a2");
+    assert_eq!(decrypt(&mut bob, &a1, &mut skipped, &rng).unwrap(), b"a1");
+}
+
+#[test]
+fn ratchet_key_pair_from_private_key() {
+    let rng = rand::SystemRandom::new();
+    let private_key = [0x42; 32];
+    let bob_key_pair = ratchet::KeyPair::from_private_key(&private_key).unwrap();
+    let mut alice =
+        ratchet::Ratchet::new_initiator(&SHARED_KEYS, bob_key_pair.public_key(), &rng).unwrap();
+    let bob_key_pair = ratchet::KeyPair::from_private_key(&private_key).unwrap();
+    let mut bob = ratchet::Ratchet::new_responder(&SHARED_KEYS, bob_key_pair);
+    let mut skipped = ratchet::SkippedMessageKeyStore::new(1);
+    let m = encrypt(&mut alice, b"hello", &rng);
+    assert_eq!(decrypt(&mut bob, &m, &mut skipped, &rng).unwrap(), b"hello");
+
+    assert!(ratchet::KeyPair::from_private_key(&private_key[1..]).is_err());
+}
@@ -30,12 +30,50 @@
     variant_size_differences,
     warnings
 )]
+#![cfg(feature = "use_heap")]
 
-#[cfg(feature = "use_heap")]
-use ring::{der, error, signature, test};
+use ring::{error, io::der, signature, test};
 
 #[cfg(feature = "rsa_signing")]
-use ring::rand;
+use ring::{pem, rand};
+
+// The largest modulus that this build of *ring* supports, as chosen with the
+// `rsa_max_bits_*` features.
+const MODULUS_MAX_BITS: usize = if cfg!(feature = "rsa_max_bits_2048") {
+    2048
+} else if cfg!(feature = "rsa_max_bits_3072") {
+    3072
+} else if cfg!(feature = "rsa_max_bits_4096") {
+    4096
+} else {
+    8192
+};
+
+/// Whether the modulus of `key`, an RSAPrivateKey that may be wrapped in a
+/// PKCS#8 document, is larger than this build of *ring* supports. Malformed
+/// keys aren't too large.
+#[cfg(feature = "rsa_signing")]
+fn private_modulus_too_large(key: untrusted::Input) -> bool {
+    let mut input = untrusted::Reader::new(key);
+    let key = match der::expect_tag_and_get_value(&mut input, der::Tag::Sequence) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+    let mut input = untrusted::Reader::new(key);
+    if der::small_nonnegative_integer(&mut input).is_err() {
+        return false;
+    }
+    if input.peek(der::Tag::Sequence as u8) {
+        let _ = der::expect_tag_and_get_value(&mut input, der::Tag::Sequence);
+        return match der::expect_tag_and_get_value(&mut input, der::Tag::OctetString) {
+            Ok(key) => private_modulus_too_large(key),
+            Err(_) => false,
+        };
+    }
+    der::positive_integer(&mut input)
+        .map(|n| n.len() * 8 > MODULUS_MAX_BITS)
+        .unwrap_or(false)
+}
 
 #[cfg(feature = "rsa_signing")]
 #[test]
@@ -49,6 +87,11 @@ fn rsa_from_pkcs8_test() {
         let input = untrusted::Input::from(&input);
 
         let error = test_case.consume_optional_string("Error");
+        let error = if private_modulus_too_large(input) {
+            Some("TooLarge".to_string())
+        } else {
+            error
+        };
 
         match (signature::RSAKeyPair::from_pkcs8(input), error) {
             (Ok(_), None) => (),
@@ -83,7 +126,7 @@ fn test_signature_rsa_pkcs1_sign() {
 
         let private_key = untrusted::Input::from(&private_key);
         let key_pair = signature::RSAKeyPair::from_der(private_key);
-        if result == "Fail-Invalid-Key" {
+        if result == "Fail-Invalid-Key" || private_modulus_too_large(private_key) {
             assert!(key_pair.is_err());
             return Ok(());
         }
@@ -123,6 +166,13 @@ fn test_signature_rsa_pss_sign() {
         if key_pair.is_err() && result == "Fail-Invalid-Key" {
             return Ok(());
         }
+        if private_modulus_too_large(private_key) {
+            assert!(key_pair.is_err());
+            for name in &["Msg", "Salt", "Sig"] {
+                let _ = test_case.consume_bytes(name);
+            }
+            return Ok(());
+        }
         let key_pair = key_pair.unwrap();
         let key_pair = std::sync::Arc::new(key_pair);
         let msg = test_case.consume_bytes("Msg");
@@ -149,7 +199,38 @@ fn test_rsa_key_pair_traits() {
     // TODO: Test that RSASigningState is NOT Sync.
 }
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "rsa_signing")]
+#[test]
+fn test_rsa_key_pair_pkcs1_and_pkcs8() {
+    let pkcs1 = pem::decode(
+        include_str!("rsa_test_private_key_2048.pem"),
+        pem::RSA_PRIVATE_KEY,
+    )
+    .unwrap();
+    let pkcs8 = pem::decode(
+        include_str!("rsa_test_private_key_2048_pkcs8.pem"),
+        pem::PRIVATE_KEY,
+    )
+    .unwrap();
+    let pkcs1 = untrusted::Input::from(&pkcs1);
+    let pkcs8 = untrusted::Input::from(&pkcs8);
+
+    // `from_der()` accepts either form.
+    for &input in &[pkcs1, pkcs8] {
+        let key_pair = signature::RSAKeyPair::from_der(input).unwrap();
+        assert_eq!(key_pair.to_pkcs1_der(), pkcs1.as_slice_less_safe());
+    }
+
+    let key_pair = signature::RSAKeyPair::from_pkcs1_der(pkcs1).unwrap();
+    assert_eq!(key_pair.to_pkcs1_der(), pkcs1.as_slice_less_safe());
+    let key_pair = signature::RSAKeyPair::from_pkcs8(pkcs8).unwrap();
+    assert_eq!(key_pair.to_pkcs1_der(), pkcs1.as_slice_less_safe());
+
+    // The explicit constructors accept only their own form.
+    assert!(signature::RSAKeyPair::from_pkcs1_der(pkcs8).is_err());
+    assert!(signature::RSAKeyPair::from_pkcs8(pkcs1).is_err());
+}
+
 #[test]
 fn test_signature_rsa_pkcs1_verify() {
     test::from_file("tests/rsa_pkcs1_verify_tests.txt", |section, test_case| {
@@ -170,18 +251,15 @@ fn test_signature_rsa_pkcs1_verify() {
         // Sanity check that we correctly DER-encoded the originally-
         // provided separate (n, e) components. When we add test vectors
         // for improperly-encoded signatures, we'll have to revisit this.
-        assert!(public_key
-            .read_all(error::Unspecified, |input| der::nested(
-                input,
-                der::Tag::Sequence,
-                error::Unspecified,
-                |input| {
-                    let _ = der::positive_integer(input)?;
+        let n = public_key
+            .read_all(error::Unspecified, |input| {
+                der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+                    let n = der::positive_integer(input)?;
                     let _ = der::positive_integer(input)?;
-                    Ok(())
-                }
-            ))
-            .is_ok());
+                    Ok(n)
+                })
+            })
+            .unwrap();
 
         let msg = test_case.consume_bytes("Msg");
         let msg = untrusted::Input::from(&msg);
@@ -192,13 +270,27 @@ fn test_signature_rsa_pkcs1_verify() {
         let expected_result = test_case.consume_string("Result");
 
         let actual_result = signature::verify(alg, public_key, msg, sig);
-        assert_eq!(actual_result.is_ok(), expected_result == "P");
+        assert_eq!(
+            actual_result.is_ok(),
+            expected_result == "P" && n.len() * 8 <= MODULUS_MAX_BITS
+        );
+
+        // Verifying with a `RSAParsedPublicKey` gives the same result.
+        match signature::RSAParsedPublicKey::from_der(public_key) {
+            Ok(key) => {
+                assert_eq!(key.verify(alg, msg, sig).is_ok(), actual_result.is_ok());
+                if n.len() * 8 < 3072 {
+                    let alg = &signature::RSA_PKCS1_3072_8192_SHA384;
+                    assert!(key.verify(alg, msg, sig).is_err());
+                }
+            },
+            Err(_) => assert!(actual_result.is_err()),
+        }
 
         Ok(())
     });
 }
 
-#[cfg(feature = "use_heap")]
 #[test]
 fn test_signature_rsa_pss_verify() {
     test::from_file("tests/rsa_pss_verify_tests.txt", |section, test_case| {
@@ -218,18 +310,15 @@ fn test_signature_rsa_pss_verify() {
         // Sanity check that we correctly DER-encoded the originally-
         // provided separate (n, e) components. When we add test vectors
         // for improperly-encoded signatures, we'll have to revisit this.
-        assert!(public_key
-            .read_all(error::Unspecified, |input| der::nested(
-                input,
-                der::Tag::Sequence,
-                error::Unspecified,
-                |input| {
-                    let _ = der::positive_integer(input)?;
+        let n = public_key
+            .read_all(error::Unspecified, |input| {
+                der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+                    let n = der::positive_integer(input)?;
                     let _ = der::positive_integer(input)?;
-                    Ok(())
-                }
-            ))
-            .is_ok());
+                    Ok(n)
+                })
+            })
+            .unwrap();
 
         let msg = test_case.consume_bytes("Msg");
         let msg = untrusted::Input::from(&msg);
@@ -240,7 +329,16 @@ fn test_signature_rsa_pss_verify() {
         let expected_result = test_case.consume_string("Result");
 
         let actual_result = signature::verify(alg, public_key, msg, sig);
-        assert_eq!(actual_result.is_ok(), expected_result == "P");
+        assert_eq!(
+            actual_result.is_ok(),
+            expected_result == "P" && n.len() * 8 <= MODULUS_MAX_BITS
+        );
+
+        // Verifying with a `RSAParsedPublicKey` gives the same result.
+        match signature::RSAParsedPublicKey::from_der(public_key) {
+            Ok(key) => assert_eq!(key.verify(alg, msg, sig).is_ok(), actual_result.is_ok()),
+            Err(_) => assert!(actual_result.is_err()),
+        }
 
         Ok(())
     });
@@ -248,7 +346,6 @@ fn test_signature_rsa_pss_verify() {
 
 // Test for `primitive::verify()`. Read public key parts from a file
 // and use them to verify a signature.
-#[cfg(feature = "use_heap")]
 #[test]
 fn test_signature_rsa_primitive_verification() {
     test::from_file(
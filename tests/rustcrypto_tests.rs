@@ -0,0 +1,169 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(all(feature = "use_heap", feature = "rustcrypto"))]
+
+use ring::{aead, digest, rand, rustcrypto, signature, test};
+use rustcrypto_aead::{Aead, AeadInPlace, KeyInit, Nonce, Payload};
+use rustcrypto_digest::{Digest, FixedOutputReset};
+use rustcrypto_signature::{SignatureEncoding, Signer, Verifier};
+use std::convert::TryFrom;
+
+fn check_digest<D: Digest + FixedOutputReset + Clone>(algorithm: &'static digest::Algorithm) {
+    let msg = b"The quick brown fox jumps over the lazy dog";
+    let expected = digest::digest(algorithm, msg);
+
+    assert_eq!(&D::digest(msg)[..], expected.as_ref());
+
+    let mut hasher = D::new();
+    for chunk in msg.chunks(7) {
+        Digest::update(&mut hasher, chunk);
+    }
+    assert_eq!(&hasher.clone().finalize()[..], expected.as_ref());
+    assert_eq!(&hasher.finalize_reset()[..], expected.as_ref());
+    assert_eq!(&hasher.finalize()[..], digest::digest(algorithm, b"").as_ref());
+    assert_eq!(<D as Digest>::output_size(), algorithm.output_len);
+}
+
+#[test]
+fn rustcrypto_digest() {
+    check_digest::<rustcrypto::Sha1>(&digest::SHA1);
+    check_digest::<rustcrypto::Sha256>(&digest::SHA256);
+    check_digest::<rustcrypto::Sha384>(&digest::SHA384);
+    check_digest::<rustcrypto::Sha512>(&digest::SHA512);
+    check_digest::<rustcrypto::Sha512_256>(&digest::SHA512_256);
+}
+
+fn check_sign_and_verify<S: Signer<signature::Signature>, V: Verifier<signature::Signature>>(
+    signer: &S, verifier: &V,
+) {
+    let msg = b"hello, world";
+    let signature = signer.try_sign(msg).unwrap();
+    assert!(verifier.verify(msg, &signature).is_ok());
+    assert!(verifier.verify(b"hello, world!", &signature).is_err());
+
+    let bytes = signature.to_bytes();
+    let parsed = signature::Signature::try_from(bytes.as_ref()).unwrap();
+    assert!(verifier.verify(msg, &parsed).is_ok());
+
+    let mut tampered = signature.as_ref().to_vec();
+    tampered[0] ^= 1;
+    let tampered = signature::Signature::try_from(&tampered[..]).unwrap();
+    assert!(verifier.verify(msg, &tampered).is_err());
+}
+
+#[test]
+fn rustcrypto_signature_ed25519() {
+    let rng = rand::SystemRandom::new();
+    let pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let key_pair =
+        signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(pkcs8.as_ref())).unwrap();
+    let verifying_key =
+        rustcrypto::VerifyingKey::new(&signature::ED25519, key_pair.public_key_bytes());
+    check_sign_and_verify(&key_pair, &verifying_key);
+
+    // Ed25519 signatures are deterministic.
+    assert_eq!(
+        Signer::<signature::Signature>::sign(&key_pair, b"").as_ref(),
+        key_pair.sign(b"").as_ref()
+    );
+}
+
+#[test]
+fn rustcrypto_signature_ecdsa() {
+    let rng = rand::SystemRandom::new();
+    let alg = &signature::ECDSA_P256_SHA256_FIXED_SIGNING;
+    let pkcs8 = signature::ECDSAKeyPair::generate_pkcs8(alg, &rng).unwrap();
+    let key_pair =
+        signature::ECDSAKeyPair::from_pkcs8(alg, untrusted::Input::from(pkcs8.as_ref())).unwrap();
+    let public_key = key_pair.public_key();
+    let verifying_key =
+        rustcrypto::VerifyingKey::new(&signature::ECDSA_P256_SHA256_FIXED, public_key.as_ref());
+    check_sign_and_verify(&key_pair, &verifying_key);
+
+    let key_pair = signature::key_pair_from_pkcs8(alg, untrusted::Input::from(pkcs8.as_ref()))
+        .unwrap();
+    check_sign_and_verify(&key_pair, &verifying_key);
+}
+
+#[test]
+fn rustcrypto_signature_too_long() {
+    let too_long = [0u8; signature::ECDSA_MAX_SIGNATURE_LEN + 1];
+    assert!(signature::Signature::try_from(&too_long[..]).is_err());
+    assert!(signature::Signature::try_from(&too_long[1..]).is_ok());
+}
+
+fn check_aead<A: Aead + AeadInPlace + KeyInit>(algorithm: &'static aead::Algorithm) {
+    let key_bytes = test::from_hex(
+        "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+    )
+    .unwrap();
+    let key_bytes = &key_bytes[..algorithm.key_len()];
+    let nonce = [7u8; 12];
+    let rustcrypto_nonce = Nonce::<A>::from_slice(&nonce);
+    let msg = b"attack at dawn";
+    let ad = b"header";
+
+    let cipher = A::new_from_slice(key_bytes).unwrap();
+    let ciphertext = cipher
+        .encrypt(rustcrypto_nonce, Payload { msg, aad: ad })
+        .unwrap();
+
+    // The output is the same as `aead::seal_in_place()`'s.
+    let key = aead::SealingKey::new(algorithm, key_bytes).unwrap();
+    let mut in_out = msg.to_vec();
+    in_out.extend_from_slice(&[0; aead::MAX_TAG_LEN]);
+    let len = aead::seal_in_place(&key, &nonce, ad, &mut in_out, aead::MAX_TAG_LEN).unwrap();
+    assert_eq!(ciphertext, &in_out[..len]);
+
+    let plaintext = cipher
+        .decrypt(rustcrypto_nonce, Payload { msg: &ciphertext, aad: ad })
+        .unwrap();
+    assert_eq!(&plaintext[..], &msg[..]);
+
+    // Wrong associated data, and a modified ciphertext, are rejected.
+    assert!(cipher
+        .decrypt(rustcrypto_nonce, Payload { msg: &ciphertext, aad: b"" })
+        .is_err());
+    let mut tampered = ciphertext.clone();
+    tampered[0] ^= 1;
+    assert!(cipher.decrypt(rustcrypto_nonce, &tampered[..]).is_err());
+
+    assert!(A::new_from_slice(&key_bytes[1..]).is_err());
+}
+
+#[test]
+fn rustcrypto_aead() {
+    check_aead::<rustcrypto::Aes128Gcm>(&aead::AES_128_GCM);
+    check_aead::<rustcrypto::Aes256Gcm>(&aead::AES_256_GCM);
+    check_aead::<rustcrypto::ChaCha20Poly1305>(&aead::CHACHA20_POLY1305);
+}
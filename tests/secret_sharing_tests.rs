@@ -0,0 +1,128 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+
+use ring::{rand, secret_sharing};
+
+#[test]
+fn secret_sharing_split_combine() {
+    let rng = rand::SystemRandom::new();
+    let secret = b"0123456789abcdef0123456789abcdef";
+    for &(threshold, count) in &[(2, 2), (2, 3), (3, 5), (5, 5), (255, 255)] {
+        let shares = secret_sharing::split(secret, threshold, count, &rng).unwrap();
+        assert_eq!(shares.len(), count);
+        for (i, share) in shares.iter().enumerate() {
+            assert_eq!(usize::from(share.id()), i + 1);
+            assert_eq!(usize::from(share.threshold()), threshold);
+        }
+
+        // Any `threshold` shares, in any order, and more than that.
+        assert_eq!(&secret_sharing::combine(&shares[..threshold]).unwrap(), secret);
+        assert_eq!(&secret_sharing::combine(&shares[(count - threshold)..]).unwrap(), secret);
+        let reversed: Vec<_> = shares.iter().rev().cloned().collect();
+        assert_eq!(&secret_sharing::combine(&reversed).unwrap(), secret);
+        assert!(secret_sharing::combine(&shares[..(threshold - 1)]).is_err());
+
+        // Shares survive encoding.
+        let decoded: Vec<_> = shares
+            .iter()
+            .map(|share| secret_sharing::Share::from_bytes(share.as_ref()).unwrap())
+            .collect();
+        assert_eq!(&secret_sharing::combine(&decoded).unwrap(), secret);
+    }
+}
+
+#[test]
+fn secret_sharing_all_subsets() {
+    let rng = rand::SystemRandom::new();
+    let shares = secret_sharing::split(b"secret", 3, 5, &rng).unwrap();
+    for a in 0..5 {
+        for b in 0..5 {
+            for c in 0..5 {
+                let subset = [shares[a].clone(), shares[b].clone(), shares[c].clone()];
+                let combined = secret_sharing::combine(&subset);
+                if a != b && b != c && a != c {
+                    assert_eq!(combined.unwrap(), b"secret");
+                } else {
+                    assert!(combined.is_err());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn secret_sharing_rejects_bad_shares() {
+    let rng = rand::SystemRandom::new();
+    let shares = secret_sharing::split(b"secret", 2, 3, &rng).unwrap();
+
+    // Every byte of a share is authenticated.
+    for i in 0..shares[0].as_ref().len() {
+        let mut modified = shares[0].as_ref().to_vec();
+        modified[i] ^= 1;
+        if let Ok(modified) = secret_sharing::Share::from_bytes(&modified) {
+            assert!(secret_sharing::combine(&[modified, shares[1].clone()]).is_err());
+        }
+    }
+    // A bad share is detected even when it isn't needed to recover the secret.
+    let mut modified = shares[2].as_ref().to_vec();
+    modified[5] ^= 1;
+    let modified = secret_sharing::Share::from_bytes(&modified).unwrap();
+    let all = [shares[0].clone(), shares[1].clone(), modified];
+    assert!(secret_sharing::combine(&all).is_err());
+
+    // Shares from different splits of the same secret.
+    let other = secret_sharing::split(b"secret", 2, 3, &rng).unwrap();
+    assert!(secret_sharing::combine(&[shares[0].clone(), other[1].clone()]).is_err());
+    let other = secret_sharing::split(b"secret", 3, 3, &rng).unwrap();
+    assert!(secret_sharing::combine(&[shares[0].clone(), other[1].clone()]).is_err());
+    let other = secret_sharing::split(b"secret!", 2, 3, &rng).unwrap();
+    assert!(secret_sharing::combine(&[shares[0].clone(), other[1].clone()]).is_err());
+
+    assert!(secret_sharing::combine(&[]).is_err());
+    assert!(secret_sharing::Share::from_bytes(&[]).is_err());
+    let truncated = &shares[0].as_ref()[..(shares[0].as_ref().len() - 7)];
+    assert!(secret_sharing::Share::from_bytes(truncated).is_err());
+}
+
+#[test]
+fn secret_sharing_split_rejects_bad_parameters() {
+    let rng = rand::SystemRandom::new();
+    for &(threshold, count) in &[(0, 3), (1, 3), (4, 3), (2, 256), (256, 256)] {
+        assert!(secret_sharing::split(b"secret", threshold, count, &rng).is_err());
+    }
+    // The empty secret can be shared.
+    let shares = secret_sharing::split(b"", 2, 2, &rng).unwrap();
+    assert_eq!(secret_sharing::combine(&shares).unwrap(), b"");
+}
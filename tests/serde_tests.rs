@@ -0,0 +1,145 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(all(feature = "use_heap", feature = "serde"))]
+
+use ring::{aead, digest, signature, spki, test};
+use serde::{
+    de::value::{self, BytesDeserializer},
+    Deserialize,
+};
+
+// The key pair and signature of RFC 8032 Section 7.1, TEST 1.
+const SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+const SIGNATURE: &str = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb88215\
+                         90a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
+const SPKI: &str = "302a300506032b6570032100d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a\
+                    68f707511a";
+
+fn key_pair() -> signature::Ed25519KeyPair {
+    let seed = test::from_hex(SEED).unwrap();
+    signature::Ed25519KeyPair::from_seed_unchecked(untrusted::Input::from(&seed)).unwrap()
+}
+
+fn quoted(s: &str) -> String { format!("\"{}\"", s) }
+
+#[test]
+fn serde_signature() {
+    let signature = key_pair().sign(b"");
+    let json = serde_json::to_string(&signature).unwrap();
+    assert_eq!(json, quoted(SIGNATURE));
+    let parsed: signature::Signature = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.as_ref(), signature.as_ref());
+
+    // Bytes, as in formats that aren't human-readable.
+    let bytes = test::from_hex(SIGNATURE).unwrap();
+    let deserializer = BytesDeserializer::<value::Error>::new(&bytes);
+    let parsed = signature::Signature::deserialize(deserializer).unwrap();
+    assert_eq!(parsed.as_ref(), &bytes[..]);
+
+    // Odd lengths, non-hex digits, and overlong signatures are rejected.
+    assert!(serde_json::from_str::<signature::Signature>("\"abc\"").is_err());
+    assert!(serde_json::from_str::<signature::Signature>("\"zz\"").is_err());
+    let too_long = quoted(&"00".repeat(signature::ECDSA_MAX_SIGNATURE_LEN + 1));
+    assert!(serde_json::from_str::<signature::Signature>(&too_long).is_err());
+}
+
+#[test]
+fn serde_public_key() {
+    let public_key = key_pair().public_key();
+    let json = serde_json::to_string(&public_key).unwrap();
+    assert_eq!(json, quoted(SPKI));
+    let parsed: spki::PublicKey = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.as_ref(), public_key.as_ref());
+    assert_eq!(parsed.to_spki_der().as_ref(), public_key.to_spki_der().as_ref());
+
+    // The algorithm must be known, and the key must be the whole document.
+    let unknown_alg = quoted(&SPKI.replace("2b6570", "2b6571"));
+    assert!(serde_json::from_str::<spki::PublicKey>(&unknown_alg).is_err());
+    let trailing = quoted(&format!("{}00", SPKI));
+    assert!(serde_json::from_str::<spki::PublicKey>(&trailing).is_err());
+}
+
+#[test]
+fn serde_digest() {
+    let digest = digest::digest(&digest::SHA256, b"abc");
+    let json = serde_json::to_string(&digest).unwrap();
+    assert_eq!(
+        json,
+        "[\"SHA-256\",\"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\"]"
+    );
+    let parsed: digest::Digest = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.algorithm(), &digest::SHA256);
+    assert_eq!(parsed.as_ref(), digest.as_ref());
+
+    for alg in &[&digest::SHA1, &digest::SHA384, &digest::SHA512, &digest::SHA512_256] {
+        let digest = digest::digest(alg, b"abc");
+        let parsed: digest::Digest =
+            serde_json::from_str(&serde_json::to_string(&digest).unwrap()).unwrap();
+        assert_eq!(parsed.algorithm(), *alg);
+        assert_eq!(parsed.as_ref(), digest.as_ref());
+    }
+
+    // The value must have the algorithm's output length.
+    assert!(serde_json::from_str::<digest::Digest>("[\"SHA-256\",\"ba78\"]").is_err());
+    assert!(serde_json::from_str::<digest::Digest>("[\"SHA-256\"]").is_err());
+    assert!(serde_json::from_str::<digest::Digest>("[\"MD5\",\"\"]").is_err());
+}
+
+#[test]
+fn serde_algorithms() {
+    let digest_algs = [
+        (&digest::SHA1, "SHA-1"),
+        (&digest::SHA256, "SHA-256"),
+        (&digest::SHA384, "SHA-384"),
+        (&digest::SHA512, "SHA-512"),
+        (&digest::SHA512_256, "SHA-512/256"),
+    ];
+    for (alg, name) in digest_algs.iter() {
+        assert_eq!(serde_json::to_string(alg).unwrap(), quoted(name));
+        let parsed: &digest::Algorithm = serde_json::from_str(&quoted(name)).unwrap();
+        assert_eq!(parsed, *alg);
+    }
+    assert!(serde_json::from_str::<&digest::Algorithm>("\"SHA256\"").is_err());
+
+    let aead_algs = [
+        (&aead::AES_128_GCM, "AES-128-GCM"),
+        (&aead::AES_256_GCM, "AES-256-GCM"),
+        (&aead::CHACHA20_POLY1305, "CHACHA20-POLY1305"),
+    ];
+    for (alg, name) in aead_algs.iter() {
+        assert_eq!(serde_json::to_string(alg).unwrap(), quoted(name));
+        let parsed: &aead::Algorithm = serde_json::from_str(&quoted(name)).unwrap();
+        assert_eq!(parsed, *alg);
+    }
+    assert!(serde_json::from_str::<&aead::Algorithm>("\"AES-192-GCM\"").is_err());
+}
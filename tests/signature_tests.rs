@@ -6,6 +6,21 @@ fn signature_impl_test() {
     test::compile_time_assert_debug::<signature::KeyPair>();
     #[cfg(feature = "use_heap")]
     test::compile_time_assert_send::<signature::KeyPair>();
+    #[cfg(feature = "use_heap")]
+    test::compile_time_assert_sync::<signature::KeyPair>();
+
+    test::compile_time_assert_send::<signature::ECDSAKeyPair>();
+    test::compile_time_assert_sync::<signature::ECDSAKeyPair>();
+    test::compile_time_assert_send::<signature::Ed25519KeyPair>();
+    test::compile_time_assert_sync::<signature::Ed25519KeyPair>();
+    #[cfg(feature = "use_heap")]
+    test::compile_time_assert_send::<signature::Ed25519PublicKeyPrecomputed>();
+    #[cfg(feature = "use_heap")]
+    test::compile_time_assert_sync::<signature::Ed25519PublicKeyPrecomputed>();
+    #[cfg(feature = "use_heap")]
+    test::compile_time_assert_send::<signature::RSAParsedPublicKey>();
+    #[cfg(feature = "use_heap")]
+    test::compile_time_assert_sync::<signature::RSAParsedPublicKey>();
 
     test::compile_time_assert_clone::<signature::Signature>();
     test::compile_time_assert_copy::<signature::Signature>();
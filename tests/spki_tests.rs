@@ -0,0 +1,158 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{agreement, pem, rand, signature, spki, test};
+
+#[test]
+fn spki_ed25519_test() {
+    // Generated with Python's `cryptography` package.
+    const SEED: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    const EXPECTED_SPKI: &str = "302a300506032b657003210003a107bff3ce10be1d70dd18e74bc09967e4d6\
+                                 309ba50d5f1ddc8664125531b8";
+
+    let seed = test::from_hex(SEED).unwrap();
+    let key_pair =
+        signature::Ed25519KeyPair::from_seed_unchecked(untrusted::Input::from(&seed)).unwrap();
+    let public_key = key_pair.public_key();
+    assert_eq!(public_key.as_ref(), key_pair.public_key_bytes());
+    assert_eq!(
+        public_key.to_spki_der().as_ref(),
+        &test::from_hex(EXPECTED_SPKI).unwrap()[..]
+    );
+
+    let signature = key_pair.sign(b"message");
+    assert!(signature::verify(
+        &signature::ED25519,
+        untrusted::Input::from(public_key.as_ref()),
+        untrusted::Input::from(b"message"),
+        untrusted::Input::from(signature.as_ref()),
+    )
+    .is_ok());
+}
+
+#[test]
+fn spki_ecdsa_test() {
+    // Generated with `openssl pkey -pubout`.
+    const PRIVATE_KEY_PEM: &str = include_str!("ecdsa_test_private_key_p256_pkcs8.pem");
+    const PUBLIC_KEY_PEM: &str = include_str!("ecdsa_test_public_key_p256.pem");
+
+    let key_pair = signature::ECDSAKeyPair::from_pkcs8_pem(
+        &signature::ECDSA_P256_SHA256_ASN1_SIGNING,
+        PRIVATE_KEY_PEM,
+    )
+    .unwrap();
+    let public_key = key_pair.public_key();
+    let spki = public_key.to_spki_der();
+    assert_eq!(
+        pem::encode(pem::PUBLIC_KEY, spki.as_ref()).unwrap(),
+        PUBLIC_KEY_PEM
+    );
+    let parsed = spki::PublicKey::from_spki_pem(PUBLIC_KEY_PEM).unwrap();
+    assert_eq!(parsed.as_ref(), public_key.as_ref());
+    assert_eq!(parsed.to_spki_der().as_ref(), spki.as_ref());
+    assert!(spki::PublicKey::from_spki_pem(PRIVATE_KEY_PEM).is_err());
+
+    let rng = rand::SystemRandom::new();
+    let signature = key_pair
+        .sign(untrusted::Input::from(b"message"), &rng)
+        .unwrap();
+    assert!(signature::verify(
+        &signature::ECDSA_P256_SHA256_ASN1,
+        untrusted::Input::from(public_key.as_ref()),
+        untrusted::Input::from(b"message"),
+        untrusted::Input::from(signature.as_ref()),
+    )
+    .is_ok());
+}
+
+#[cfg(feature = "rsa_signing")]
+#[test]
+fn spki_rsa_test() {
+    // Generated with `openssl pkey -pubout`.
+    const PRIVATE_KEY_PEM: &str = include_str!("rsa_test_private_key_2048_pkcs8.pem");
+    const PUBLIC_KEY_PEM: &str = include_str!("rsa_test_public_key_2048.pem");
+
+    let key_pair = signature::RSAKeyPair::from_pkcs8_pem(PRIVATE_KEY_PEM).unwrap();
+    let public_key = key_pair.public_key();
+    assert_eq!(
+        pem::encode(pem::PUBLIC_KEY, public_key.to_spki_der().as_ref()).unwrap(),
+        PUBLIC_KEY_PEM
+    );
+    let parsed = spki::PublicKey::from_spki_pem(PUBLIC_KEY_PEM).unwrap();
+    assert_eq!(parsed.as_ref(), public_key.as_ref());
+
+    let key_pair = std::sync::Arc::new(key_pair);
+    let mut signing_state = signature::RSASigningState::new(key_pair).unwrap();
+    let rng = rand::SystemRandom::new();
+    let mut signature = [0u8; 256];
+    signing_state
+        .sign(&signature::RSA_PKCS1_SHA256, &rng, b"message", &mut signature)
+        .unwrap();
+    assert!(signature::verify(
+        &signature::RSA_PKCS1_2048_8192_SHA256,
+        untrusted::Input::from(public_key.as_ref()),
+        untrusted::Input::from(b"message"),
+        untrusted::Input::from(&signature),
+    )
+    .is_ok());
+}
+
+#[test]
+fn spki_agreement_test() {
+    let rng = rand::SystemRandom::new();
+    for &(alg, spki_prefix) in &[
+        (&agreement::X25519, "302a300506032b656e032100"),
+        (
+            &agreement::ECDH_P256,
+            "3059301306072a8648ce3d020106082a8648ce3d030107034200",
+        ),
+        (
+            &agreement::ECDH_P384,
+            "3076301006072a8648ce3d020106052b81040022036200",
+        ),
+    ] {
+        let private_key = agreement::EphemeralPrivateKey::generate(alg, &rng).unwrap();
+        let mut expected = [0u8; agreement::PUBLIC_KEY_MAX_LEN];
+        let expected = &mut expected[..private_key.public_key_len()];
+        private_key.compute_public_key(expected).unwrap();
+
+        let public_key = private_key.public_key().unwrap();
+        assert_eq!(public_key.as_ref(), &expected[..]);
+
+        let spki_prefix = test::from_hex(spki_prefix).unwrap();
+        let spki = public_key.to_spki_der();
+        assert_eq!(&spki.as_ref()[..spki_prefix.len()], &spki_prefix[..]);
+        assert_eq!(&spki.as_ref()[spki_prefix.len()..], &expected[..]);
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "srp")]
+
+use ring::{rand, srp};
+
+#[test]
+fn srp_exchange() {
+    let rng = rand::SystemRandom::new();
+    for group in &[&srp::GROUP_2048, &srp::GROUP_3072, &srp::GROUP_4096] {
+        let salt = [0x5a; 16];
+        let verifier = srp::compute_verifier(group, b"alice", b"password", &salt).unwrap();
+        assert_eq!(verifier.len(), group.modulus_len());
+
+        let client = srp::Client::new(group, &rng).unwrap();
+        let client_public_key = client.public_key().to_vec();
+        assert_eq!(client_public_key.len(), group.modulus_len());
+        let server = srp::Server::new(group, b"alice", &salt, &verifier, &rng).unwrap();
+
+        let client_proof = client
+            .compute_proof(b"alice", b"password", &salt, server.public_key())
+            .unwrap();
+        let server_proof = server
+            .verify_client(&client_public_key, client_proof.proof())
+            .unwrap();
+        let session_key = client_proof.verify_server(server_proof.proof()).unwrap();
+        assert_eq!(session_key.as_ref(), server_proof.session_key().as_ref());
+        assert_eq!(session_key.as_ref().len(), srp::SESSION_KEY_LEN);
+    }
+}
+
+#[test]
+fn srp_wrong_password() {
+    let rng = rand::SystemRandom::new();
+    let group = &srp::GROUP_2048;
+    let salt = [0x5a; 16];
+    let verifier = srp::compute_verifier(group, b"alice", b"password", &salt).unwrap();
+
+    let client = srp::Client::new(group, &rng).unwrap();
+    let client_public_key = client.public_key().to_vec();
+    let server = srp::Server::new(group, b"alice", &salt, &verifier, &rng).unwrap();
+    let client_proof = client
+        .compute_proof(b"alice", b"passw0rd", &salt, server.public_key())
+        .unwrap();
+    assert!(server
+        .verify_client(&client_public_key, client_proof.proof())
+        .is_err());
+}
+
+#[test]
+fn srp_wrong_server_proof() {
+    let rng = rand::SystemRandom::new();
+    let group = &srp::GROUP_2048;
+    let salt = [0x5a; 16];
+    let verifier = srp::compute_verifier(group, b"alice", b"password", &salt).unwrap();
+
+    let client = srp::Client::new(group, &rng).unwrap();
+    let server = srp::Server::new(group, b"alice", &salt, &verifier, &rng).unwrap();
+    let client_proof = client
+        .compute_proof(b"alice", b"password", &salt, server.public_key())
+        .unwrap();
+    let mut server_proof = [0; srp::PROOF_LEN];
+    server_proof.copy_from_slice(client_proof.proof());
+    assert!(client_proof.verify_server(&server_proof).is_err());
+}
@@ -0,0 +1,181 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+
+use ring::srtp;
+
+static PROFILES: [&srtp::Profile; 4] = [
+    &srtp::AES128_CM_HMAC_SHA1_80,
+    &srtp::AES128_CM_HMAC_SHA1_32,
+    &srtp::AEAD_AES_128_GCM,
+    &srtp::AEAD_AES_256_GCM,
+];
+
+fn new_contexts(profile: &'static srtp::Profile) -> (srtp::Context, srtp::Context) {
+    let master_key = vec![0x5a; profile.master_key_len()];
+    let master_salt = vec![0xa5; profile.master_salt_len()];
+    let sender = srtp::Context::new(profile, &master_key, &master_salt).unwrap();
+    let receiver = srtp::Context::new(profile, &master_key, &master_salt).unwrap();
+    (sender, receiver)
+}
+
+fn rtp_packet(seq: u16, ssrc: u32) -> Vec<u8> {
+    let mut packet = vec![0x80, 0x60];
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(&[0; 4]);
+    packet.extend_from_slice(&ssrc.to_be_bytes());
+    packet.extend_from_slice(b"some media");
+    packet
+}
+
+fn rtcp_packet(ssrc: u32) -> Vec<u8> {
+    let mut packet = vec![0x80, 0xc9, 0x00, 0x04];
+    packet.extend_from_slice(&ssrc.to_be_bytes());
+    packet.extend_from_slice(&[0x42; 12]);
+    packet
+}
+
+fn protect_rtp(context: &mut srtp::Context, packet: &[u8]) -> Vec<u8> {
+    let mut in_out = packet.to_vec();
+    in_out.resize(packet.len() + context.profile().rtp_overhead(), 0);
+    context.protect_rtp(&mut in_out).unwrap();
+    in_out
+}
+
+fn protect_rtcp(context: &mut srtp::Context, packet: &[u8]) -> Vec<u8> {
+    let mut in_out = packet.to_vec();
+    in_out.resize(packet.len() + context.profile().rtcp_overhead(), 0);
+    context.protect_rtcp(&mut in_out).unwrap();
+    in_out
+}
+
+#[test]
+fn srtp_round_trip() {
+    for profile in PROFILES.iter() {
+        let (mut sender, mut receiver) = new_contexts(profile);
+        for seq in 0..3 {
+            let packet = rtp_packet(seq, 1);
+            let mut protected = protect_rtp(&mut sender, &packet);
+            assert_ne!(&protected[..packet.len()], &packet[..]);
+            assert_eq!(receiver.unprotect_rtp(&mut protected).unwrap(), &packet[..]);
+        }
+
+        let packet = rtcp_packet(1);
+        let mut protected = protect_rtcp(&mut sender, &packet);
+        assert_eq!(receiver.unprotect_rtcp(&mut protected).unwrap(), &packet[..]);
+    }
+}
+
+#[test]
+fn srtp_rejects_replays_and_forgeries() {
+    for profile in PROFILES.iter() {
+        let (mut sender, mut receiver) = new_contexts(profile);
+        let protected: Vec<Vec<u8>> =
+            (0..100).map(|seq| protect_rtp(&mut sender, &rtp_packet(seq, 1))).collect();
+
+        // A forged packet doesn't change the state, so the real one can still
+        // be unprotected.
+        let mut forged = protected[1].clone();
+        let last = forged.len() - 1;
+        forged[last] ^= 1;
+        assert!(receiver.unprotect_rtp(&mut forged).is_err());
+
+        // Packets may arrive out of order, but only once each.
+        for &i in &[1, 0, 99, 40] {
+            let mut packet = protected[i].clone();
+            assert!(receiver.unprotect_rtp(&mut packet).is_ok());
+            let mut packet = protected[i].clone();
+            assert!(receiver.unprotect_rtp(&mut packet).is_err());
+        }
+
+        // Packet 2 is too old to tell whether it is a replay.
+        let mut packet = protected[2].clone();
+        assert!(receiver.unprotect_rtp(&mut packet).is_err());
+
+        let rtcp: Vec<Vec<u8>> =
+            (0..2).map(|_| protect_rtcp(&mut sender, &rtcp_packet(1))).collect();
+        for packet in &rtcp {
+            assert!(receiver.unprotect_rtcp(&mut packet.clone()).is_ok());
+            assert!(receiver.unprotect_rtcp(&mut packet.clone()).is_err());
+        }
+    }
+}
+
+#[test]
+fn srtp_rollover_counter() {
+    for profile in PROFILES.iter() {
+        let (mut sender, mut receiver) = new_contexts(profile);
+        assert_eq!(sender.rtp_rollover_counter(7), None);
+        for &seq in &[0xfffd, 0xffff, 0x0001, 0xfffe, 0x0002] {
+            let packet = rtp_packet(seq, 7);
+            let mut protected = protect_rtp(&mut sender, &packet);
+            assert_eq!(receiver.unprotect_rtp(&mut protected).unwrap(), &packet[..]);
+        }
+        assert_eq!(sender.rtp_rollover_counter(7), Some(1));
+        assert_eq!(receiver.rtp_rollover_counter(7), Some(1));
+
+        // Each stream has its own rollover counter.
+        let mut protected = protect_rtp(&mut sender, &rtp_packet(0x0003, 8));
+        assert!(receiver.unprotect_rtp(&mut protected).is_ok());
+        assert_eq!(receiver.rtp_rollover_counter(8), Some(0));
+    }
+}
+
+#[test]
+fn srtp_rejects_malformed_packets() {
+    let (mut sender, mut receiver) = new_contexts(&srtp::AES128_CM_HMAC_SHA1_80);
+    let overhead = srtp::AES128_CM_HMAC_SHA1_80.rtp_overhead();
+
+    // Too short, not version 2, and an extension longer than the packet.
+    let mut too_short = vec![0x80; 11 + overhead];
+    assert!(sender.protect_rtp(&mut too_short).is_err());
+    let mut version_1 = rtp_packet(0, 1);
+    version_1[0] = 0x40;
+    version_1.resize(version_1.len() + overhead, 0);
+    assert!(sender.protect_rtp(&mut version_1).is_err());
+    let mut long_extension = rtp_packet(0, 1);
+    long_extension[0] |= 0x10;
+    long_extension[14] = 0xff;
+    long_extension.resize(long_extension.len() + overhead, 0);
+    assert!(sender.protect_rtp(&mut long_extension).is_err());
+
+    // SRTCP packets without the E flag.
+    let mut protected = protect_rtcp(&mut sender, &rtcp_packet(1));
+    let e_flag = protected.len() - srtp::AES128_CM_HMAC_SHA1_80.rtcp_overhead();
+    protected[e_flag] &= 0x7f;
+    assert!(receiver.unprotect_rtcp(&mut protected).is_err());
+
+    assert!(srtp::Context::new(&srtp::AEAD_AES_128_GCM, &[0; 16], &[0; 14]).is_err());
+    assert!(srtp::Context::new(&srtp::AEAD_AES_256_GCM, &[0; 16], &[0; 12]).is_err());
+}
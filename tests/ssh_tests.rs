@@ -0,0 +1,275 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{error, pem, rand, signature, ssh, test};
+
+// Generated with `ssh-keygen -t ed25519` and `ssh-keygen -t ecdsa -b 256/384`.
+// The check values are those in the private keys, so that equivalent keys
+// serialize to the same bytes.
+static KEYS: [(&ssh::Algorithm, &str, &str, [u8; 4]); 3] = [
+    (
+        &ssh::ED25519,
+        include_str!("ssh_test_private_key_ed25519"),
+        include_str!("ssh_test_public_key_ed25519.pub"),
+        [0x18, 0xf3, 0xf2, 0x08],
+    ),
+    (
+        &ssh::ECDSA_SHA2_NISTP256,
+        include_str!("ssh_test_private_key_ecdsa_p256"),
+        include_str!("ssh_test_public_key_ecdsa_p256.pub"),
+        [0x36, 0xde, 0x3d, 0x9a],
+    ),
+    (
+        &ssh::ECDSA_SHA2_NISTP384,
+        include_str!("ssh_test_private_key_ecdsa_p384"),
+        include_str!("ssh_test_public_key_ecdsa_p384.pub"),
+        [0xf7, 0xec, 0xb0, 0xe1],
+    ),
+];
+
+#[test]
+fn ssh_public_key_round_trip() {
+    for &(alg, _, public_key_line, _) in KEYS.iter() {
+        let public_key = ssh::PublicKey::parse(public_key_line).unwrap();
+        assert_eq!(public_key.algorithm(), alg);
+        assert_eq!(public_key.to_line(), public_key_line.trim_end());
+
+        let blob = public_key.to_blob();
+        assert_eq!(
+            ssh::PublicKey::from_blob(&blob, public_key.comment()).unwrap(),
+            public_key
+        );
+    }
+}
+
+#[test]
+fn ssh_public_key_parse_rejects_malformed() {
+    let (_, _, ed25519_line, _) = KEYS[0];
+    let (_, _, p256_line, _) = KEYS[1];
+    let ed25519_encoded = ed25519_line.split(' ').nth(1).unwrap();
+
+    // The algorithm name must match the key.
+    let mismatched = format!("ecdsa-sha2-nistp256 {}", ed25519_encoded);
+    assert!(ssh::PublicKey::parse(&mismatched).is_err());
+
+    // The Base64 text must be present and valid.
+    assert!(ssh::PublicKey::parse("ssh-ed25519").is_err());
+    assert!(ssh::PublicKey::parse(&format!("ssh-ed25519 {}!", ed25519_encoded)).is_err());
+
+    // Truncated keys are rejected.
+    let public_key = ssh::PublicKey::parse(p256_line).unwrap();
+    let blob = public_key.to_blob();
+    assert!(ssh::PublicKey::from_blob(&blob[..(blob.len() - 1)], "").is_err());
+
+    // Unsupported algorithms are rejected.
+    assert!(ssh::PublicKey::parse("ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAAAAQE=").is_err());
+
+    // Comments can't span lines.
+    assert!(ssh::PublicKey::new(&ssh::ED25519, public_key.key_bytes(), "a\nb").is_err());
+}
+
+#[test]
+fn ssh_private_key_from_ssh_keygen() {
+    for &(alg, private_key_pem, public_key_line, check) in KEYS.iter() {
+        let private_key = ssh::PrivateKey::parse(private_key_pem).unwrap();
+        let public_key = ssh::PublicKey::parse(public_key_line).unwrap();
+        assert_eq!(private_key.algorithm(), alg);
+        assert_eq!(private_key.public_key(), &public_key);
+
+        // Converting to PKCS#8 and back gives the same key, byte for byte.
+        let pkcs8 = private_key.to_pkcs8();
+        let pkcs8 = untrusted::Input::from(pkcs8.as_ref());
+        let converted = ssh::PrivateKey::from_pkcs8(alg, pkcs8, public_key.comment()).unwrap();
+        let rng = test::rand::FixedSliceRandom { bytes: &check };
+        assert_eq!(
+            pem::decode(&converted.to_pem(&rng).unwrap(), ssh::PRIVATE_KEY_PEM_LABEL).unwrap(),
+            pem::decode(private_key_pem, ssh::PRIVATE_KEY_PEM_LABEL).unwrap()
+        );
+
+        // The PKCS#8 document can be used for signing.
+        const MESSAGE: &[u8] = b"hello, world";
+        let rng = rand::SystemRandom::new();
+        let (sig, verification_alg): (_, &signature::VerificationAlgorithm) =
+            if alg == &ssh::ED25519 {
+                let key_pair = signature::Ed25519KeyPair::from_pkcs8(pkcs8).unwrap();
+                (key_pair.sign(MESSAGE), &signature::ED25519)
+            } else {
+                let (signing_alg, verification_alg) = if alg == &ssh::ECDSA_SHA2_NISTP256 {
+                    (
+                        &signature::ECDSA_P256_SHA256_ASN1_SIGNING,
+                        &signature::ECDSA_P256_SHA256_ASN1,
+                    )
+                } else {
+                    (
+                        &signature::ECDSA_P384_SHA384_ASN1_SIGNING,
+                        &signature::ECDSA_P384_SHA384_ASN1,
+                    )
+                };
+                let key_pair = signature::ECDSAKeyPair::from_pkcs8(signing_alg, pkcs8).unwrap();
+                let sig = key_pair
+                    .sign(untrusted::Input::from(MESSAGE), &rng)
+                    .unwrap();
+                (sig, verification_alg)
+            };
+        assert_eq!(
+            signature::verify(
+                verification_alg,
+                untrusted::Input::from(public_key.key_bytes()),
+                untrusted::Input::from(MESSAGE),
+                untrusted::Input::from(sig.as_ref()),
+            ),
+            Ok(())
+        );
+    }
+}
+
+#[test]
+fn ssh_private_key_generated_round_trip() {
+    let rng = rand::SystemRandom::new();
+    let pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let private_key =
+        ssh::PrivateKey::from_pkcs8(&ssh::ED25519, untrusted::Input::from(&pkcs8), "").unwrap();
+    let parsed = ssh::PrivateKey::parse(&private_key.to_pem(&rng).unwrap()).unwrap();
+    assert_eq!(parsed.public_key(), private_key.public_key());
+    assert_eq!(parsed.to_pkcs8().as_ref(), &pkcs8[..]);
+
+    // The PKCS#8 document must be for the given algorithm.
+    assert!(
+        ssh::PrivateKey::from_pkcs8(&ssh::ECDSA_SHA2_NISTP256, untrusted::Input::from(&pkcs8), "")
+            .is_err()
+    );
+}
+
+#[test]
+fn ssh_private_key_parse_rejects_unsupported() {
+    use std::error::Error;
+
+    // Encrypted with `ssh-keygen -N`.
+    let encrypted = include_str!("ssh_test_private_key_ed25519_encrypted");
+    assert_eq!(
+        ssh::PrivateKey::parse(encrypted).unwrap_err().description(),
+        "WrongAlgorithm"
+    );
+
+    // A PKCS#8 key isn't an OpenSSH key, even with the OpenSSH label.
+    let (_, private_key_pem, _, _) = KEYS[0];
+    let private_key = ssh::PrivateKey::parse(private_key_pem).unwrap();
+    let relabeled =
+        pem::encode(ssh::PRIVATE_KEY_PEM_LABEL, private_key.to_pkcs8().as_ref()).unwrap();
+    assert!(ssh::PrivateKey::parse(&relabeled).is_err());
+}
+
+#[test]
+fn ssh_private_key_parse_rejects_corrupted() {
+    for &(_, private_key_pem, _, check) in KEYS.iter() {
+        let bytes = pem::decode(private_key_pem, ssh::PRIVATE_KEY_PEM_LABEL).unwrap();
+        let check_index = bytes.windows(4).position(|w| w == check).unwrap();
+        // Corrupt the public key that precedes the private section, the first
+        // check value, the second check value, and the padding.
+        for &i in &[check_index - 20, check_index, check_index + 4, bytes.len() - 1] {
+            let mut corrupted = bytes.clone();
+            corrupted[i] ^= 1;
+            let corrupted = pem::encode(ssh::PRIVATE_KEY_PEM_LABEL, &corrupted).unwrap();
+            let result: Result<_, error::KeyRejected> = ssh::PrivateKey::parse(&corrupted);
+            assert!(result.is_err());
+        }
+    }
+}
+
+#[test]
+fn ssh_kex_curve25519_sha256() {
+    let rng = rand::SystemRandom::new();
+    let (_, private_key_pem, _, _) = KEYS[0];
+    let host_key = ssh::PrivateKey::parse(private_key_pem).unwrap();
+    let host_key_pair = signature::Ed25519KeyPair::from_pkcs8(untrusted::Input::from(
+        host_key.to_pkcs8().as_ref(),
+    ))
+    .unwrap();
+    let k_s = host_key.public_key().to_blob();
+    let (v_c, v_s) = (b"SSH-2.0-client", b"SSH-2.0-server");
+    let (i_c, i_s) = (b"client KEXINIT", b"server KEXINIT");
+
+    let mut session_id = None;
+    for &alg in &[&ssh::kex::CURVE25519_SHA256, &ssh::kex::CURVE25519_SHA256_LIBSSH_ORG] {
+        let client = ssh::kex::KeyExchange::start(alg, &rng).unwrap();
+        let server = ssh::kex::KeyExchange::start(alg, &rng).unwrap();
+        let (q_c, q_s) = (client.public_key().to_vec(), server.public_key().to_vec());
+
+        // The server signs `H` with its host key and the client verifies it.
+        let server = server.finish_server(&q_c, v_c, v_s, i_c, i_s, &k_s).unwrap();
+        let h_signature = host_key_pair.sign(server.exchange_hash());
+        let client = client.finish_client(&q_s, v_c, v_s, i_c, i_s, &k_s).unwrap();
+        assert_eq!(client.algorithm().name(), alg.name());
+        signature::verify(
+            &signature::ED25519,
+            untrusted::Input::from(host_key.public_key().key_bytes()),
+            untrusted::Input::from(client.exchange_hash()),
+            untrusted::Input::from(h_signature.as_ref()),
+        )
+        .unwrap();
+
+        // The second exchange is a re-key, which keeps the session identifier.
+        let session_id = session_id.get_or_insert_with(|| client.exchange_hash().to_vec());
+        let mut keys = Vec::new();
+        for &key in &[
+            ssh::kex::Key::ClientToServerIv,
+            ssh::kex::Key::ServerToClientIv,
+            ssh::kex::Key::ClientToServerEncryption,
+            ssh::kex::Key::ServerToClientEncryption,
+            ssh::kex::Key::ClientToServerIntegrity,
+            ssh::kex::Key::ServerToClientIntegrity,
+        ] {
+            let mut client_key = [0; 64];
+            let mut server_key = [0; 64];
+            client.derive(session_id, key, &mut client_key);
+            server.derive(session_id, key, &mut server_key);
+            assert_eq!(&client_key[..], &server_key[..]);
+            let mut short = [0; 16];
+            client.derive(session_id, key, &mut short);
+            assert_eq!(&short[..], &client_key[..16]);
+            keys.push(client_key.to_vec());
+        }
+        for (i, key) in keys.iter().enumerate() {
+            assert!(keys[(i + 1)..].iter().all(|other| other != key));
+        }
+
+        // A different host key changes `H`.
+        let client = ssh::kex::KeyExchange::start(alg, &rng).unwrap();
+        let server = ssh::kex::KeyExchange::start(alg, &rng).unwrap();
+        let (q_c, q_s) = (client.public_key().to_vec(), server.public_key().to_vec());
+        let client = client.finish_client(&q_s, v_c, v_s, i_c, i_s, b"other key").unwrap();
+        let server = server.finish_server(&q_c, v_c, v_s, i_c, i_s, &k_s).unwrap();
+        assert_ne!(client.exchange_hash(), server.exchange_hash());
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+
+use ring::{aead, digest, quic, tls13};
+
+fn hash(transcript: &[u8]) -> digest::Digest { digest::digest(&digest::SHA256, transcript) }
+
+// The secrets that both sides of a handshake derive.
+struct Handshake {
+    client_handshake: tls13::Secret,
+    server_handshake: tls13::Secret,
+    client_application: tls13::Secret,
+    server_application: tls13::Secret,
+    exporter: tls13::ExporterMasterSecret,
+    resumption: tls13::ResumptionMasterSecret,
+}
+
+fn handshake(psk: Option<&[u8]>, shared_secret: Option<&[u8]>) -> Handshake {
+    let mut key_schedule = tls13::KeySchedule::new(&digest::SHA256, psk);
+    key_schedule.derive_handshake_secret(shared_secret).unwrap();
+    let hello = hash(b"ClientHello ServerHello");
+    let client_handshake = key_schedule.client_handshake_traffic_secret(&hello).unwrap();
+    let server_handshake = key_schedule.server_handshake_traffic_secret(&hello).unwrap();
+    key_schedule.derive_master_secret().unwrap();
+    let server_finished = hash(b"ClientHello ... server Finished");
+    let client_finished = hash(b"ClientHello ... client Finished");
+    Handshake {
+        client_handshake,
+        server_handshake,
+        client_application: key_schedule
+            .client_application_traffic_secret(&server_finished)
+            .unwrap(),
+        server_application: key_schedule
+            .server_application_traffic_secret(&server_finished)
+            .unwrap(),
+        exporter: key_schedule.exporter_master_secret(&server_finished).unwrap(),
+        resumption: key_schedule.resumption_master_secret(&client_finished).unwrap(),
+    }
+}
+
+#[test]
+fn tls13_full_handshake() {
+    let shared_secret = [0x42; 32];
+    let client = handshake(None, Some(&shared_secret));
+    let server = handshake(None, Some(&shared_secret));
+    assert_eq!(client.server_handshake.as_ref(), server.server_handshake.as_ref());
+    assert_ne!(client.client_handshake.as_ref(), client.server_handshake.as_ref());
+    assert_ne!(client.client_application.as_ref(), client.server_application.as_ref());
+
+    // Records sealed by the server are opened by the client.
+    let server_key = server.server_application.traffic_key(&aead::CHACHA20_POLY1305);
+    let client_key = client.server_application.traffic_key(&aead::CHACHA20_POLY1305);
+    let mut in_out = b"record\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0".to_vec();
+    let _ = aead::seal_in_place(
+        &server_key.sealing_key().unwrap(),
+        &server_key.nonce(3),
+        &[0x17, 0x03, 0x03],
+        &mut in_out,
+        aead::MAX_TAG_LEN,
+    )
+    .unwrap();
+    let opened = aead::open_in_place(
+        &client_key.opening_key().unwrap(),
+        &client_key.nonce(3),
+        &[0x17, 0x03, 0x03],
+        0,
+        &mut in_out,
+    )
+    .unwrap();
+    assert_eq!(opened, b"record");
+    assert_ne!(client_key.nonce(3), client_key.nonce(4));
+
+    // Finished messages.
+    let transcript = hash(b"ClientHello ... CertificateVerify");
+    let verify_data = server.server_handshake.finished_verify_data(&transcript).unwrap();
+    assert!(client.server_handshake.verify_finished(&transcript, verify_data.as_ref()).is_ok());
+    assert!(client.client_handshake.verify_finished(&transcript, verify_data.as_ref()).is_err());
+    assert!(client
+        .server_handshake
+        .verify_finished(&hash(b"something else"), verify_data.as_ref())
+        .is_err());
+    let sha384_transcript = digest::digest(&digest::SHA384, b"ClientHello ... CertificateVerify");
+    assert!(client.server_handshake.finished_verify_data(&sha384_transcript).is_err());
+
+    // Key updates.
+    let next = client.client_application.next();
+    assert_eq!(next.as_ref(), server.client_application.next().as_ref());
+    assert_ne!(next.as_ref(), client.client_application.as_ref());
+
+    // Exporters.
+    let mut client_exported = [0; 32];
+    let mut server_exported = [0; 32];
+    client.exporter.export(b"EXPORTER-test", b"context", &mut client_exported).unwrap();
+    server.exporter.export(b"EXPORTER-test", b"context", &mut server_exported).unwrap();
+    assert_eq!(client_exported, server_exported);
+    server.exporter.export(b"EXPORTER-test", b"", &mut server_exported).unwrap();
+    assert_ne!(client_exported, server_exported);
+    assert!(client.exporter.export(&[b'x'; 250], b"", &mut client_exported).is_err());
+
+    // A secret can be used for QUIC.
+    let quic_secret = quic::Secret::new(
+        &quic::V1,
+        &quic::TLS13_AES_128_GCM_SHA256,
+        client.client_application.as_ref(),
+    )
+    .unwrap();
+    let _ = quic_secret.packet_key();
+}
+
+#[test]
+fn tls13_resumption() {
+    let first = handshake(None, Some(&[0x42; 32]));
+    let psk = first.resumption.psk(&[0, 1]).unwrap();
+    assert_ne!(psk.as_ref(), first.resumption.psk(&[0, 2]).unwrap().as_ref());
+
+    // The binder of the resumed ClientHello.
+    let key_schedule = tls13::KeySchedule::new(&digest::SHA256, Some(psk.as_ref()));
+    let truncated_client_hello = hash(b"truncated ClientHello");
+    let binder_key = key_schedule.binder_key(tls13::PskKind::Resumption).unwrap();
+    let binder = binder_key.finished_verify_data(&truncated_client_hello).unwrap();
+    let external = key_schedule.binder_key(tls13::PskKind::External).unwrap();
+    assert!(external.verify_finished(&truncated_client_hello, binder.as_ref()).is_err());
+    assert!(binder_key.verify_finished(&truncated_client_hello, binder.as_ref()).is_ok());
+
+    // Early data and its exporter.
+    let client_hello = hash(b"ClientHello");
+    let early = key_schedule.client_early_traffic_secret(&client_hello).unwrap();
+    let mut exported = [0; 16];
+    key_schedule
+        .early_exporter_master_secret(&client_hello)
+        .unwrap()
+        .export(b"EXPORTER-early", b"", &mut exported)
+        .unwrap();
+    let _ = early.traffic_key(&aead::AES_128_GCM);
+
+    // `psk_ke` and `psk_dhe_ke` handshakes derive different secrets.
+    let psk_ke = handshake(Some(psk.as_ref()), None);
+    let psk_dhe_ke = handshake(Some(psk.as_ref()), Some(&[0x43; 32]));
+    assert_ne!(psk_ke.client_application.as_ref(), psk_dhe_ke.client_application.as_ref());
+}
+
+#[test]
+fn tls13_stages() {
+    let hello = hash(b"ClientHello ServerHello");
+    let mut key_schedule = tls13::KeySchedule::new(&digest::SHA256, None);
+    assert!(key_schedule.client_handshake_traffic_secret(&hello).is_err());
+    assert!(key_schedule.derive_master_secret().is_err());
+    key_schedule.derive_handshake_secret(Some(&[0x42; 32])).unwrap();
+    assert!(key_schedule.derive_handshake_secret(Some(&[0x42; 32])).is_err());
+    assert!(key_schedule.binder_key(tls13::PskKind::External).is_err());
+    assert!(key_schedule.client_early_traffic_secret(&hello).is_err());
+    assert!(key_schedule.client_application_traffic_secret(&hello).is_err());
+    let sha384_hello = digest::digest(&digest::SHA384, b"ClientHello ServerHello");
+    assert!(key_schedule.client_handshake_traffic_secret(&sha384_hello).is_err());
+    key_schedule.derive_master_secret().unwrap();
+    assert!(key_schedule.derive_master_secret().is_err());
+    assert!(key_schedule.server_handshake_traffic_secret(&hello).is_err());
+    assert!(key_schedule.resumption_master_secret(&hello).is_ok());
+
+    assert!(tls13::Secret::new(&digest::SHA384, &[0; 48]).is_ok());
+    assert!(tls13::Secret::new(&digest::SHA384, &[0; 32]).is_err());
+}
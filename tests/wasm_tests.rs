@@ -0,0 +1,255 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+// `#[wasm_bindgen_test]` generates undocumented `#[no_mangle]` functions, so
+// `missing_docs` and `unsafe_code` aren't forbidden here.
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(all(target_arch = "wasm32", target_os = "unknown", feature = "alloc"))]
+
+//! Known-answer tests of the portable implementations that are used on
+//! wasm32-unknown-unknown. The other tests read their test vectors from
+//! files, which isn't possible there, so these embed a few of the same
+//! files. See BUILDING.md for how to run them.
+
+use ring::{aead, agreement, digest, error, signature, test};
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn wasm_digest() {
+    test::from_str(
+        "digest_tests.txt",
+        include_str!("digest_tests.txt"),
+        |section, test_case| {
+            assert_eq!(section, "");
+            let digest_alg = test_case.consume_digest_alg("Hash").unwrap();
+            let input = test_case.consume_bytes("Input");
+            let repeat = test_case.consume_usize("Repeat");
+            let expected = test_case.consume_bytes("Output");
+
+            let mut ctx = digest::Context::new(digest_alg);
+            for _ in 0..repeat {
+                ctx.update(&input);
+            }
+            assert_eq!(&expected, &ctx.finish().as_ref());
+
+            Ok(())
+        },
+    );
+}
+
+#[wasm_bindgen_test]
+fn wasm_aead_aes_128_gcm() {
+    test_aead(
+        &aead::AES_128_GCM,
+        "aead_aes_128_gcm_tests.txt",
+        include_str!("aead_aes_128_gcm_tests.txt"),
+    );
+}
+
+#[wasm_bindgen_test]
+fn wasm_aead_chacha20_poly1305() {
+    test_aead(
+        &aead::CHACHA20_POLY1305,
+        "aead_chacha20_poly1305_tests.txt",
+        include_str!("aead_chacha20_poly1305_tests.txt"),
+    );
+}
+
+fn test_aead(aead_alg: &'static aead::Algorithm, name: &str, contents: &str) {
+    test::from_str(name, contents, |section, test_case| {
+        assert_eq!(section, "");
+        let key_bytes = test_case.consume_bytes("KEY");
+        let nonce = test_case.consume_bytes("NONCE");
+        let plaintext = test_case.consume_bytes("IN");
+        let ad = test_case.consume_bytes("AD");
+        let mut ct = test_case.consume_bytes("CT");
+        let tag = test_case.consume_bytes("TAG");
+        if test_case.consume_optional_string("FAILS").is_some() {
+            return Ok(());
+        }
+        ct.extend(tag);
+
+        let tag_len = aead_alg.tag_len();
+        let mut in_out = plaintext.clone();
+        in_out.extend(vec![0; tag_len]);
+        let s_key = aead::SealingKey::new(aead_alg, &key_bytes)?;
+        let len = aead::seal_in_place(&s_key, &nonce, &ad, &mut in_out, tag_len)?;
+        assert_eq!(&in_out[..len], &ct[..]);
+
+        let o_key = aead::OpeningKey::new(aead_alg, &key_bytes)?;
+        let opened = aead::open_in_place(&o_key, &nonce, &ad, 0, &mut in_out[..len])?;
+        assert_eq!(opened, &plaintext[..]);
+
+        Ok(())
+    });
+}
+
+#[wasm_bindgen_test]
+fn wasm_agreement() {
+    test::from_str(
+        "agreement_tests.txt",
+        include_str!("agreement_tests.txt"),
+        |section, test_case| {
+            assert_eq!(section, "");
+            let curve_name = test_case.consume_string("Curve");
+            let alg = match curve_name.as_str() {
+                "P-256" => &agreement::ECDH_P256,
+                "P-384" => &agreement::ECDH_P384,
+                "X25519" => &agreement::X25519,
+                _ => panic!("Unsupported curve: {}", curve_name),
+            };
+            let peer_public = test_case.consume_bytes("PeerQ");
+            if test_case.consume_optional_string("Error").is_some() {
+                return Ok(());
+            }
+            let my_private = test_case.consume_bytes("D");
+            let my_public = test_case.consume_bytes("MyQ");
+            let output = test_case.consume_bytes("Output");
+
+            let rng = test::rand::FixedSliceRandom { bytes: &my_private };
+            let my_private = agreement::EphemeralPrivateKey::generate(alg, &rng)?;
+            let mut computed_public = [0u8; agreement::PUBLIC_KEY_MAX_LEN];
+            let computed_public = &mut computed_public[..my_private.public_key_len()];
+            my_private.compute_public_key(computed_public)?;
+            assert_eq!(computed_public, &my_public[..]);
+
+            agreement::agree_ephemeral(
+                my_private,
+                alg,
+                untrusted::Input::from(&peer_public),
+                error::Unspecified,
+                |key_material| {
+                    assert_eq!(key_material, &output[..]);
+                    Ok(())
+                },
+            )
+        },
+    );
+}
+
+#[wasm_bindgen_test]
+fn wasm_ecdsa_verify() {
+    test::from_str(
+        "ecdsa_verify_fixed_tests.txt",
+        include_str!("ecdsa_verify_fixed_tests.txt"),
+        |section, test_case| {
+            assert_eq!(section, "");
+            let curve_name = test_case.consume_string("Curve");
+            let digest_name = test_case.consume_string("Digest");
+            let alg = match (curve_name.as_str(), digest_name.as_str()) {
+                ("P-256", "SHA256") => &signature::ECDSA_P256_SHA256_FIXED,
+                ("P-384", "SHA384") => &signature::ECDSA_P384_SHA384_FIXED,
+                _ => panic!("Unsupported curve+digest: {}+{}", curve_name, digest_name),
+            };
+            let msg = test_case.consume_bytes("Msg");
+            let public_key = test_case.consume_bytes("Q");
+            let sig = test_case.consume_bytes("Sig");
+            let expected_result = test_case.consume_string("Result");
+
+            let actual_result = signature::verify(
+                alg,
+                untrusted::Input::from(&public_key),
+                untrusted::Input::from(&msg),
+                untrusted::Input::from(&sig),
+            );
+            assert_eq!(actual_result.is_ok(), expected_result == "P (0 )");
+
+            Ok(())
+        },
+    );
+}
+
+#[wasm_bindgen_test]
+fn wasm_ed25519_sign() {
+    test::from_str(
+        "ed25519_tests.txt",
+        include_str!("ed25519_tests.txt"),
+        |section, test_case| {
+            assert_eq!(section, "");
+            let seed = test_case.consume_bytes("SEED");
+            let public_key = test_case.consume_bytes("PUB");
+            let msg = test_case.consume_bytes("MESSAGE");
+            let expected_sig = test_case.consume_bytes("SIG");
+
+            let key_pair = signature::Ed25519KeyPair::from_seed_and_public_key(
+                untrusted::Input::from(&seed),
+                untrusted::Input::from(&public_key),
+            )?;
+            let actual_sig = key_pair.sign(&msg);
+            assert_eq!(&expected_sig[..], actual_sig.as_ref());
+
+            signature::verify(
+                &signature::ED25519,
+                untrusted::Input::from(&public_key),
+                untrusted::Input::from(&msg),
+                untrusted::Input::from(&expected_sig),
+            )
+        },
+    );
+}
+
+#[cfg(feature = "wasm32_unknown_unknown_js")]
+#[wasm_bindgen_test]
+fn wasm_system_random() {
+    use ring::rand::{self, SecureRandom};
+
+    let rng = rand::SystemRandom::new();
+    let mut a = [0u8; 32];
+    let mut b = [0u8; 32];
+    rng.fill(&mut a).unwrap();
+    rng.fill(&mut b).unwrap();
+    assert_ne!(a, b);
+
+    // More than `crypto.getRandomValues()` returns at once.
+    let mut large = vec![0u8; 65_536 + 1];
+    rng.fill(&mut large).unwrap();
+    assert_ne!(&large[65_536 - 32..65_536], &large[65_536 - 31..]);
+
+    // ECDSA key generation and signing use `SystemRandom`.
+    let pkcs8 =
+        signature::ECDSAKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            .unwrap();
+    let key_pair = signature::ECDSAKeyPair::from_pkcs8(
+        &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+        untrusted::Input::from(pkcs8.as_ref()),
+    )
+    .unwrap();
+    let sig = key_pair
+        .sign(untrusted::Input::from(&b"hello"[..]), &rng)
+        .unwrap();
+    assert!(signature::verify(
+        &signature::ECDSA_P256_SHA256_FIXED,
+        untrusted::Input::from(key_pair.public_key().as_ref()),
+        untrusted::Input::from(&b"hello"[..]),
+        untrusted::Input::from(sig.as_ref()),
+    )
+    .is_ok());
+}
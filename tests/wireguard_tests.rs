@@ -0,0 +1,199 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+use ring::{rand, wireguard};
+
+const TIMESTAMP: [u8; wireguard::TIMESTAMP_LEN] = [0x40, 0, 0, 0, 0x5f, 0x5e, 0x10, 0, 0, 0, 0, 1];
+
+struct Handshake {
+    initiator: wireguard::StaticKeyPair,
+    responder: wireguard::StaticKeyPair,
+    initiation: [u8; wireguard::INITIATION_LEN],
+    handshake: wireguard::InitiatorHandshake,
+}
+
+fn initiate(preshared_key: &[u8; wireguard::KEY_LEN], rng: &rand::SystemRandom) -> Handshake {
+    let initiator = wireguard::StaticKeyPair::generate(rng).unwrap();
+    let responder = wireguard::StaticKeyPair::generate(rng).unwrap();
+    let mut initiation = [0; wireguard::INITIATION_LEN];
+    let handshake = wireguard::initiate(
+        &initiator,
+        responder.public_key(),
+        preshared_key,
+        1,
+        &TIMESTAMP,
+        &mut initiation,
+        rng,
+    )
+    .unwrap();
+    Handshake {
+        initiator,
+        responder,
+        initiation,
+        handshake,
+    }
+}
+
+#[test]
+fn wireguard_handshake_and_transport() {
+    let rng = rand::SystemRandom::new();
+    let preshared_key = [7; wireguard::KEY_LEN];
+    let h = initiate(&preshared_key, &rng);
+
+    let received = wireguard::consume_initiation(&h.responder, &h.initiation).unwrap();
+    assert_eq!(received.peer_public_key(), h.initiator.public_key());
+    assert_eq!(received.timestamp(), &TIMESTAMP);
+    assert_eq!(received.sender_index(), 1);
+
+    let mut response = [0; wireguard::RESPONSE_LEN];
+    let mut responder_keys = received.respond(&preshared_key, 2, &mut response, &rng).unwrap();
+    let mut initiator_keys = h.handshake.consume_response(&h.initiator, &response).unwrap();
+    assert_eq!(initiator_keys.local_index(), 1);
+    assert_eq!(initiator_keys.peer_index(), 2);
+    assert_eq!(responder_keys.local_index(), 2);
+    assert_eq!(responder_keys.peer_index(), 1);
+
+    for i in 0..3u64 {
+        let packet = [i as u8; 32];
+        let mut msg = initiator_keys.seal(&packet).unwrap();
+        assert_eq!(msg.len(), wireguard::TRANSPORT_HEADER_LEN + 32 + wireguard::TAG_LEN);
+        let (counter, opened) = responder_keys.open(&mut msg).unwrap();
+        assert_eq!(counter, i);
+        assert_eq!(&opened[..], &packet[..]);
+
+        let mut msg = responder_keys.seal(&packet).unwrap();
+        let (counter, opened) = initiator_keys.open(&mut msg).unwrap();
+        assert_eq!(counter, i);
+        assert_eq!(&opened[..], &packet[..]);
+    }
+
+    // The keys of each direction are different, and a message is only
+    // accepted by the peer it is addressed to.
+    let mut msg = initiator_keys.seal(b"packet").unwrap();
+    assert!(initiator_keys.open(&mut msg.clone()).is_err());
+
+    // Every byte is authenticated.
+    for i in 0..msg.len() {
+        let mut tampered = msg.clone();
+        tampered[i] ^= 1;
+        assert!(responder_keys.open(&mut tampered).is_err());
+    }
+    assert!(responder_keys.open(&mut msg).is_ok());
+}
+
+#[test]
+fn wireguard_handshake_rejects_tampering() {
+    let rng = rand::SystemRandom::new();
+    let preshared_key = [0; wireguard::KEY_LEN];
+    let h = initiate(&preshared_key, &rng);
+
+    // `mac2` isn't covered by `mac1` or the handshake.
+    for i in 0..(wireguard::INITIATION_LEN - 16) {
+        let mut tampered = h.initiation;
+        tampered[i] ^= 1;
+        assert!(wireguard::consume_initiation(&h.responder, &tampered).is_err());
+    }
+    assert!(wireguard::consume_initiation(&h.initiator, &h.initiation).is_err());
+    assert!(wireguard::consume_initiation(&h.responder, &h.initiation[1..]).is_err());
+
+    let received = wireguard::consume_initiation(&h.responder, &h.initiation).unwrap();
+    let mut response = [0; wireguard::RESPONSE_LEN];
+    let _ = received.respond(&preshared_key, 2, &mut response, &rng).unwrap();
+    for i in 0..(wireguard::RESPONSE_LEN - 16) {
+        let mut tampered = response;
+        tampered[i] ^= 1;
+        assert!(h.handshake.consume_response(&h.initiator, &tampered).is_err());
+    }
+
+    // A forged response doesn't prevent the real one from being accepted.
+    assert!(h.handshake.consume_response(&h.initiator, &response).is_ok());
+}
+
+#[test]
+fn wireguard_preshared_key_mismatch() {
+    let rng = rand::SystemRandom::new();
+    let h = initiate(&[1; wireguard::KEY_LEN], &rng);
+
+    // The preshared key is only mixed into the response, so the responder
+    // can't tell that the keys differ until the initiator rejects it.
+    let received = wireguard::consume_initiation(&h.responder, &h.initiation).unwrap();
+    let mut response = [0; wireguard::RESPONSE_LEN];
+    let _ = received
+        .respond(&[2; wireguard::KEY_LEN], 2, &mut response, &rng)
+        .unwrap();
+    assert!(h.handshake.consume_response(&h.initiator, &response).is_err());
+}
+
+#[test]
+fn wireguard_cookie_reply() {
+    let rng = rand::SystemRandom::new();
+    let h = initiate(&[0; wireguard::KEY_LEN], &rng);
+    let secret = [3; wireguard::KEY_LEN];
+    let source = [192, 0, 2, 1, 0xca, 0x6c];
+
+    // The responder is under load and replies with a cookie.
+    let cookie = wireguard::cookie(&secret, &source);
+    assert!(wireguard::verify_mac2(&h.initiation, &cookie).is_err());
+    let mut reply = [0; wireguard::COOKIE_REPLY_LEN];
+    wireguard::create_cookie_reply(&h.responder, &h.initiation, &cookie, &mut reply, &rng)
+        .unwrap();
+
+    // Only the initiator of the message can use the cookie reply.
+    let responder_public_key = h.responder.public_key();
+    let received_cookie =
+        wireguard::consume_cookie_reply(responder_public_key, &h.initiation, &reply).unwrap();
+    assert_eq!(received_cookie, cookie);
+    assert!(wireguard::consume_cookie_reply(h.initiator.public_key(), &h.initiation, &reply)
+        .is_err());
+    for i in 0..reply.len() {
+        let mut tampered = reply;
+        tampered[i] ^= 1;
+        assert!(
+            wireguard::consume_cookie_reply(responder_public_key, &h.initiation, &tampered)
+                .is_err()
+        );
+    }
+
+    // The initiator resends the message with `mac2`, which the responder
+    // checks against the cookie of the source.
+    let mut initiation = h.initiation;
+    wireguard::set_mac2(&mut initiation, &received_cookie).unwrap();
+    assert!(wireguard::verify_mac2(&initiation, &cookie).is_ok());
+    assert!(wireguard::consume_initiation(&h.responder, &initiation).is_ok());
+    let other_cookie = wireguard::cookie(&secret, &[192, 0, 2, 2, 0xca, 0x6c]);
+    assert!(wireguard::verify_mac2(&initiation, &other_cookie).is_err());
+
+    // Transport data messages don't have MACs.
+    assert!(wireguard::set_mac2(&mut [4, 0, 0, 0], &cookie).is_err());
+}
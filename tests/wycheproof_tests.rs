@@ -0,0 +1,329 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+#![cfg(feature = "use_heap")]
+
+//! Tests with the [Wycheproof] test vectors.
+//!
+//! Wycheproof isn't distributed with *ring*; mk/travis.sh clones it into
+//! `third_party/wycheproof`. To run these tests elsewhere, do the same, e.g.
+//! `git clone https://github.com/google/wycheproof third_party/wycheproof`.
+//! The tests fail if any of their files are missing.
+//!
+//! [Wycheproof]: https://github.com/google/wycheproof
+
+use ring::{
+    aead, agreement, digest, error, hkdf, hmac, signature,
+    test::{
+        self,
+        wycheproof::{TestCase, TestGroup},
+    },
+};
+
+#[test]
+fn wycheproof_aes_gcm() {
+    test_files(&["aes_gcm_test.json"], |group, test_case| {
+        let aead_alg = match group.usize("keySize") {
+            128 => &aead::AES_128_GCM,
+            256 => &aead::AES_256_GCM,
+            _ => return None,
+        };
+        test_aead(aead_alg, group, test_case)
+    });
+}
+
+#[test]
+fn wycheproof_chacha20_poly1305() {
+    test_files(&["chacha20_poly1305_test.json"], |group, test_case| {
+        test_aead(&aead::CHACHA20_POLY1305, group, test_case)
+    });
+}
+
+fn test_aead(
+    aead_alg: &'static aead::Algorithm, group: &TestGroup, test_case: &TestCase,
+) -> Option<Result<(), error::Unspecified>> {
+    if group.usize("keySize") != aead_alg.key_len() * 8
+        || group.usize("ivSize") != aead_alg.nonce_len() * 8
+        || group.usize("tagSize") != aead_alg.tag_len() * 8
+    {
+        return None;
+    }
+    let key = test_case.bytes("key");
+    let nonce = test_case.bytes("iv");
+    let aad = test_case.bytes("aad");
+    let msg = test_case.bytes("msg");
+    let mut ct = test_case.bytes("ct");
+    ct.extend_from_slice(&test_case.bytes("tag"));
+
+    let o_key = aead::OpeningKey::new(aead_alg, &key).unwrap();
+    let mut o_in_out = ct.clone();
+    let opened = match aead::open_in_place(&o_key, &nonce, &aad, 0, &mut o_in_out) {
+        Ok(plaintext) => plaintext == &msg[..],
+        Err(error::Unspecified) => false,
+    };
+
+    // Whenever the ciphertext is accepted, sealing the message must produce
+    // it again.
+    if opened {
+        let s_key = aead::SealingKey::new(aead_alg, &key).unwrap();
+        let mut s_in_out = msg.clone();
+        s_in_out.extend_from_slice(&[0; aead::MAX_TAG_LEN][..aead_alg.tag_len()]);
+        let len = aead::seal_in_place(&s_key, &nonce, &aad, &mut s_in_out, aead_alg.tag_len());
+        assert_eq!(len, Ok(ct.len()));
+        assert_eq!(s_in_out, ct);
+    }
+
+    Some(if opened { Ok(()) } else { Err(error::Unspecified) })
+}
+
+#[test]
+fn wycheproof_ecdsa() {
+    test_files(
+        &[
+            "ecdsa_secp256r1_sha256_test.json",
+            "ecdsa_secp384r1_sha384_test.json",
+            "ecdsa_secp256r1_sha256_p1363_test.json",
+            "ecdsa_secp384r1_sha384_p1363_test.json",
+        ],
+        |group, test_case| {
+            let key = public_key(group);
+            let is_fixed = match group.string("type").as_ref() {
+                "EcdsaVerify" => false,
+                "EcdsaP1363Verify" => true,
+                _ => return None,
+            };
+            let alg = match (
+                group.string(&(key.to_owned() + ".curve")).as_ref(),
+                group.string("sha").as_ref(),
+                is_fixed,
+            ) {
+                ("secp256r1", "SHA-256", false) => &signature::ECDSA_P256_SHA256_ASN1,
+                ("secp256r1", "SHA-384", false) => &signature::ECDSA_P256_SHA384_ASN1,
+                ("secp384r1", "SHA-256", false) => &signature::ECDSA_P384_SHA256_ASN1,
+                ("secp384r1", "SHA-384", false) => &signature::ECDSA_P384_SHA384_ASN1,
+                ("secp256r1", "SHA-256", true) => &signature::ECDSA_P256_SHA256_FIXED,
+                ("secp384r1", "SHA-384", true) => &signature::ECDSA_P384_SHA384_FIXED,
+                _ => return None,
+            };
+            let public_key = group.bytes(&(key.to_owned() + ".uncompressed"));
+            let msg = test_case.bytes("msg");
+            let sig = test_case.bytes("sig");
+            Some(signature::verify(
+                alg,
+                untrusted::Input::from(&public_key),
+                untrusted::Input::from(&msg),
+                untrusted::Input::from(&sig),
+            ))
+        },
+    );
+}
+
+#[test]
+fn wycheproof_ed25519() {
+    test_files(&["ed25519_test.json", "eddsa_test.json"], |group, test_case| {
+        let key = public_key(group);
+        if group.string(&(key.to_owned() + ".curve")) != "edwards25519" {
+            return None;
+        }
+        let public_key = group.bytes(&(key.to_owned() + ".pk"));
+        let msg = test_case.bytes("msg");
+        let sig = test_case.bytes("sig");
+        Some(signature::verify(
+            &signature::ED25519,
+            untrusted::Input::from(&public_key),
+            untrusted::Input::from(&msg),
+            untrusted::Input::from(&sig),
+        ))
+    });
+}
+
+#[test]
+fn wycheproof_x25519() {
+    test_files(&["x25519_test.json"], |group, test_case| {
+        if group.string("curve") != "curve25519" {
+            return None;
+        }
+        let private_key = test_case.bytes("private");
+        let public_key = test_case.bytes("public");
+        let shared = test_case.bytes("shared");
+
+        let rng = test::rand::FixedSliceRandom { bytes: &private_key };
+        let private_key =
+            agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng).unwrap();
+        Some(agreement::agree_ephemeral(
+            private_key,
+            &agreement::X25519,
+            untrusted::Input::from(&public_key),
+            error::Unspecified,
+            |agreed_value| {
+                if agreed_value == &shared[..] {
+                    Ok(())
+                } else {
+                    Err(error::Unspecified)
+                }
+            },
+        ))
+    });
+}
+
+#[test]
+fn wycheproof_rsa_pkcs1() {
+    test_files(
+        &[
+            "rsa_signature_2048_sha256_test.json",
+            "rsa_signature_2048_sha384_test.json",
+            "rsa_signature_2048_sha512_test.json",
+            "rsa_signature_3072_sha256_test.json",
+            "rsa_signature_3072_sha384_test.json",
+            "rsa_signature_3072_sha512_test.json",
+            "rsa_signature_4096_sha384_test.json",
+            "rsa_signature_4096_sha512_test.json",
+        ],
+        |group, test_case| {
+            let params = match group.string("sha").as_ref() {
+                "SHA-1" => &signature::RSA_PKCS1_2048_8192_SHA1,
+                "SHA-256" => &signature::RSA_PKCS1_2048_8192_SHA256,
+                "SHA-384" => &signature::RSA_PKCS1_2048_8192_SHA384,
+                "SHA-512" => &signature::RSA_PKCS1_2048_8192_SHA512,
+                _ => return None,
+            };
+            test_rsa(params, group, test_case)
+        },
+    );
+}
+
+#[test]
+fn wycheproof_rsa_pss() {
+    test_files(
+        &[
+            "rsa_pss_2048_sha256_mgf1_32_test.json",
+            "rsa_pss_3072_sha256_mgf1_32_test.json",
+            "rsa_pss_4096_sha256_mgf1_32_test.json",
+            "rsa_pss_4096_sha512_mgf1_64_test.json",
+        ],
+        |group, test_case| {
+            let sha = group.string("sha");
+            let (params, salt_len) = match sha.as_ref() {
+                "SHA-256" => (&signature::RSA_PSS_2048_8192_SHA256, 32),
+                "SHA-384" => (&signature::RSA_PSS_2048_8192_SHA384, 48),
+                "SHA-512" => (&signature::RSA_PSS_2048_8192_SHA512, 64),
+                _ => return None,
+            };
+            // *ring* only supports MGF1 with the message digest algorithm and
+            // salts as long as the digest.
+            if group.string("mgf") != "MGF1"
+                || group.string("mgfSha") != sha
+                || group.usize("sLen") != salt_len
+            {
+                return None;
+            }
+            test_rsa(params, group, test_case)
+        },
+    );
+}
+
+fn test_rsa(
+    params: &signature::RSAParameters, group: &TestGroup, test_case: &TestCase,
+) -> Option<Result<(), error::Unspecified>> {
+    if group.usize("keySize") < 2048 {
+        return None;
+    }
+    // The modulus and the exponent are encoded like ASN.1 INTEGERs, with a
+    // leading zero byte when the high bit is set, but *ring* rejects leading
+    // zeros.
+    let n = group.bytes("n");
+    let e = group.bytes("e");
+    let n = &n[n.iter().take_while(|b| **b == 0).count()..];
+    let e = &e[e.iter().take_while(|b| **b == 0).count()..];
+    let msg = test_case.bytes("msg");
+    let sig = test_case.bytes("sig");
+    Some(signature::primitive::verify_rsa(
+        params,
+        (untrusted::Input::from(n), untrusted::Input::from(e)),
+        untrusted::Input::from(&msg),
+        untrusted::Input::from(&sig),
+    ))
+}
+
+#[test]
+fn wycheproof_hkdf() {
+    for (file, digest_alg) in &[
+        ("hkdf_sha1_test.json", &digest::SHA1),
+        ("hkdf_sha256_test.json", &digest::SHA256),
+        ("hkdf_sha384_test.json", &digest::SHA384),
+        ("hkdf_sha512_test.json", &digest::SHA512),
+    ] {
+        test_files(&[file], |_, test_case| {
+            let ikm = test_case.bytes("ikm");
+            let salt = test_case.bytes("salt");
+            let info = test_case.bytes("info");
+            let okm = test_case.bytes("okm");
+
+            let salt = hmac::SigningKey::new(digest_alg, &salt);
+            let mut out = vec![0u8; test_case.usize("size")];
+            Some(
+                hkdf::try_extract_and_expand(&salt, &ikm, &info, &mut out).and_then(|()| {
+                    if out == okm {
+                        Ok(())
+                    } else {
+                        Err(error::Unspecified)
+                    }
+                }),
+            )
+        });
+    }
+}
+
+// Calls `f` on the test cases of each of the files, which are in
+// `third_party/wycheproof/testvectors`.
+fn test_files<F>(files: &[&str], mut f: F)
+where
+    F: FnMut(&TestGroup, &TestCase) -> Option<Result<(), error::Unspecified>>,
+{
+    for file in files {
+        let path = String::from("third_party/wycheproof/testvectors/") + file;
+        if !test::ring_src_path().join(&path).exists() {
+            panic!("{} not found; see the comment at the top of this file.", path);
+        }
+        test::wycheproof::from_file(&path, |group, test_case| f(group, test_case));
+    }
+}
+
+// Older versions of Wycheproof name a test group's public key "key" and newer
+// ones name it "publicKey".
+fn public_key(group: &TestGroup) -> &'static str {
+    if group.has("publicKey") {
+        "publicKey"
+    } else {
+        "key"
+    }
+}
@@ -0,0 +1,457 @@
+// Copyright 2019 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Tests that secrets are zeroized when the values holding them are dropped.
+//!
+//! Each test constructs a value holding a known secret in a `Box`, using an
+//! allocator that keeps track of the blocks that the test allocates. The
+//! secret must be found in one of those blocks while the value is alive, and
+//! must not be found in any of them when they are freed.
+
+// `unsafe_code` isn't forbidden because the allocator needs it.
+#![forbid(
+    anonymous_parameters,
+    box_pointers,
+    legacy_directory_ownership,
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    variant_size_differences,
+    warnings
+)]
+
+use ring::{aead, agreement, digest, hmac, signature, test};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::{Mutex, MutexGuard},
+};
+
+#[global_allocator]
+static ALLOCATOR: InspectingAllocator = InspectingAllocator;
+
+/// Allocates with `System`, keeping track of the blocks allocated by the
+/// thread that is inspecting the heap, if any, and looking for the secret in
+/// each of those blocks when it is freed.
+struct InspectingAllocator;
+
+unsafe impl GlobalAlloc for InspectingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() && is_inspecting() {
+            lock(&INSPECTION).track(ptr, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if is_inspecting() {
+            lock(&INSPECTION).untrack(ptr);
+        }
+        System.dealloc(ptr, layout)
+    }
+}
+
+const MAX_SECRET_LEN: usize = 32;
+const MAX_BLOCKS: usize = 1024;
+
+/// The state of the allocator. It must never be locked by code that
+/// allocates or frees memory, or that code would deadlock.
+struct Inspection {
+    secret: [u8; MAX_SECRET_LEN],
+    secret_len: usize,
+
+    /// The addresses and sizes of the tracked blocks. Unused entries have a
+    /// null address.
+    blocks: [(usize, usize); MAX_BLOCKS],
+
+    found_in_freed_block: bool,
+}
+
+static INSPECTION: Mutex<Inspection> = Mutex::new(Inspection {
+    secret: [0; MAX_SECRET_LEN],
+    secret_len: 0,
+    blocks: [(0, 0); MAX_BLOCKS],
+    found_in_freed_block: false,
+});
+
+impl Inspection {
+    fn track(&mut self, ptr: *mut u8, size: usize) {
+        let entry = self
+            .blocks
+            .iter_mut()
+            .find(|(address, _)| *address == 0)
+            .expect("Too many blocks were allocated");
+        *entry = (ptr as usize, size);
+    }
+
+    unsafe fn untrack(&mut self, ptr: *mut u8) {
+        let secret = &self.secret[..self.secret_len];
+        if let Some(entry) = self.blocks.iter_mut().find(|(address, _)| *address == ptr as usize) {
+            if contains(std::slice::from_raw_parts(ptr, entry.1), secret) {
+                self.found_in_freed_block = true;
+            }
+            *entry = (0, 0);
+        }
+    }
+
+    /// Whether the secret is in any of the tracked blocks that are still
+    /// allocated.
+    unsafe fn found_in_live_block(&self) -> bool {
+        let secret = &self.secret[..self.secret_len];
+        self.blocks.iter().filter(|(address, _)| *address != 0).any(|&(address, size)| {
+            contains(std::slice::from_raw_parts(address as *const u8, size), secret)
+        })
+    }
+}
+
+thread_local! {
+    static INSPECTING: Cell<bool> = const { Cell::new(false) };
+}
+
+fn is_inspecting() -> bool { INSPECTING.try_with(Cell::get).unwrap_or(false) }
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A test that fails while inspecting poisons the mutexes; that shouldn't
+    // make the other tests fail too.
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    /// The secret was in a tracked block and wasn't in any block when it was
+    /// freed.
+    Zeroized,
+
+    /// The secret was in a block when it was freed.
+    NotZeroized,
+
+    /// The secret wasn't found in any tracked block while the value was
+    /// alive, so it can't be told whether it would have been zeroized, e.g.
+    /// because the value encodes the secret differently than expected.
+    Inconclusive,
+}
+
+/// Inspects the heap, looking for `secret`, while `new` constructs a value
+/// on the heap and while the value is dropped.
+fn inspect<T>(secret: &[u8], new: impl FnOnce() -> Box<T>) -> Outcome {
+    // The tests are run on several threads but there is only one `INSPECTION`.
+    static SERIAL: Mutex<()> = Mutex::new(());
+    let _serial = lock(&SERIAL);
+
+    {
+        let mut inspection = lock(&INSPECTION);
+        inspection.secret[..secret.len()].copy_from_slice(secret);
+        inspection.secret_len = secret.len();
+        inspection.blocks = [(0, 0); MAX_BLOCKS];
+        inspection.found_in_freed_block = false;
+    }
+
+    INSPECTING.with(|inspecting| inspecting.set(true));
+    let value = new();
+    let found_in_live_block = unsafe { lock(&INSPECTION).found_in_live_block() };
+    drop(value);
+    INSPECTING.with(|inspecting| inspecting.set(false));
+
+    let inspection = lock(&INSPECTION);
+    if inspection.found_in_freed_block {
+        Outcome::NotZeroized
+    } else if found_in_live_block {
+        Outcome::Zeroized
+    } else {
+        Outcome::Inconclusive
+    }
+}
+
+/// A secret of `MAX_SECRET_LEN` bytes that is unlikely to be in memory for
+/// any other reason.
+fn secret(seed: u8) -> [u8; MAX_SECRET_LEN] {
+    let mut secret = [0; MAX_SECRET_LEN];
+    for (i, b) in secret.iter_mut().enumerate() {
+        *b = (i as u8).wrapping_mul(37).wrapping_add(seed) | 1;
+    }
+    secret
+}
+
+#[test]
+fn zeroize_aead_aes_gcm_key() {
+    let key = secret(1);
+    for (alg, key) in &[(&aead::AES_128_GCM, &key[..16]), (&aead::AES_256_GCM, &key[..])] {
+        // Some implementations schedule the key into a form that doesn't
+        // contain the key bytes verbatim.
+        assert_ne!(
+            inspect(key, || Box::new(aead::SealingKey::new(alg, key).unwrap())),
+            Outcome::NotZeroized
+        );
+        assert_ne!(
+            inspect(key, || Box::new(aead::OpeningKey::new(alg, key).unwrap())),
+            Outcome::NotZeroized
+        );
+    }
+}
+
+#[test]
+fn zeroize_aead_chacha20_poly1305_key() {
+    let key = secret(2);
+    assert_eq!(
+        inspect(&key, || {
+            Box::new(aead::SealingKey::new(&aead::CHACHA20_POLY1305, &key).unwrap())
+        }),
+        Outcome::Zeroized
+    );
+}
+
+#[test]
+fn zeroize_aead_chacha20_poly1305_openssh_key() {
+    let mut key = [0; aead::chacha20_poly1305_openssh::KEY_LEN];
+    key[..MAX_SECRET_LEN].copy_from_slice(&secret(3));
+    key[MAX_SECRET_LEN..].copy_from_slice(&secret(4));
+    for half in key.chunks(MAX_SECRET_LEN) {
+        assert_eq!(
+            inspect(half, || Box::new(aead::chacha20_poly1305_openssh::OpeningKey::new(&key))),
+            Outcome::Zeroized
+        );
+    }
+}
+
+#[test]
+fn zeroize_digest_context() {
+    let data = secret(5);
+    assert_eq!(
+        inspect(&data, || {
+            let mut ctx = Box::new(digest::Context::new(&digest::SHA256));
+            ctx.update(&data);
+            ctx
+        }),
+        Outcome::Zeroized
+    );
+}
+
+#[test]
+fn zeroize_hmac_signing_context() {
+    let data = secret(6);
+    let key = hmac::SigningKey::new(&digest::SHA256, b"key");
+    assert_eq!(
+        inspect(&data, || {
+            let mut ctx = Box::new(hmac::SigningContext::with_key(&key));
+            ctx.update(&data);
+            ctx
+        }),
+        Outcome::Zeroized
+    );
+}
+
+#[test]
+fn zeroize_ed25519_key_pair() {
+    let seed = secret(7);
+    let h = digest::digest(&digest::SHA512, &seed);
+    let prefix = &h.as_ref()[32..];
+    assert_eq!(
+        inspect(prefix, || {
+            Box::new(
+                signature::Ed25519KeyPair::from_seed_unchecked(untrusted::Input::from(&seed))
+                    .unwrap(),
+            )
+        }),
+        Outcome::Zeroized
+    );
+}
+
+#[test]
+fn zeroize_pem_private_key() {
+    use ring::pem;
+
+    // The key pair doesn't keep the seed, so only the freed copies of the
+    // decoded PKCS#8 document are checked.
+    let seed = secret(13);
+    let rng = test::rand::FixedSliceRandom { bytes: &seed };
+    let pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let pkcs8 = pem::encode(pem::PRIVATE_KEY, &pkcs8).unwrap();
+    assert_ne!(
+        inspect(&seed, || {
+            Box::new(signature::Ed25519KeyPair::from_pkcs8_pem(&pkcs8).unwrap())
+        }),
+        Outcome::NotZeroized
+    );
+}
+
+#[test]
+fn zeroize_pkcs12_private_key() {
+    use ring::pkcs12;
+
+    // The P-256 private key in `pkcs12_test_default.p12`.
+    const PRIVATE_KEY: &[u8] = include_bytes!("pkcs12_test_private_key.der");
+    let start = PRIVATE_KEY.windows(2).position(|w| w == [0x04, 0x20]).unwrap() + 2;
+    let d = &PRIVATE_KEY[start..][..32];
+    assert_eq!(
+        inspect(d, || {
+            let input = untrusted::Input::from(include_bytes!("pkcs12_test_default.p12"));
+            Box::new(pkcs12::Pfx::from_der(input, "password").unwrap())
+        }),
+        Outcome::Zeroized
+    );
+}
+
+#[test]
+fn zeroize_agreement_ephemeral_private_key() {
+    let private_key = secret(8);
+    for alg in &[&agreement::X25519, &agreement::ECDH_P256] {
+        let rng = test::rand::FixedSliceRandom { bytes: &private_key };
+        assert_eq!(
+            inspect(&private_key, || {
+                Box::new(agreement::EphemeralPrivateKey::generate(alg, &rng).unwrap())
+            }),
+            Outcome::Zeroized
+        );
+    }
+}
+
+#[cfg(feature = "rsa_signing")]
+#[test]
+fn zeroize_rsa_key_pair() {
+    use ring::{error, io::der, pem};
+
+    const PRIVATE_KEY_PEM: &str = include_str!("rsa_test_private_key_2048.pem");
+    let private_key = pem::decode(PRIVATE_KEY_PEM, pem::RSA_PRIVATE_KEY).unwrap();
+
+    // RSAPrivateKey ::= SEQUENCE { version, n, e, d, p, ... }
+    let p = untrusted::Input::from(&private_key)
+        .read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+                let _version = der::small_nonnegative_integer(input)?;
+                for _ in 0..3 {
+                    let _ = der::positive_integer(input)?;
+                }
+                let p = der::positive_integer(input)?;
+                let _ = input.skip_to_end();
+                Ok(p)
+            })
+        })
+        .unwrap();
+    let p = p.as_slice_less_safe();
+
+    // The private key is kept encoded, and the arithmetic is done on limbs of
+    // *p*, which are little-endian on little-endian targets.
+    let mut p_limbs = p.to_vec();
+    p_limbs.reverse();
+    let mut secrets = vec![&p[..MAX_SECRET_LEN]];
+    if cfg!(target_endian = "little") {
+        secrets.push(&p_limbs[..MAX_SECRET_LEN]);
+    }
+
+    for secret in secrets {
+        let outcome = inspect(secret, || {
+            Box::new(signature::RSAKeyPair::from_der(untrusted::Input::from(&private_key)).unwrap())
+        });
+        if cfg!(feature = "locked_memory") {
+            // The private key may have been moved out of the heap into locked
+            // memory, after the copies on the heap were zeroized.
+            assert_ne!(outcome, Outcome::NotZeroized);
+        } else {
+            assert_eq!(outcome, Outcome::Zeroized);
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn zeroize_crate_traits() {
+    use ring::{pake, pkcs8};
+
+    fn assert_zeroize_on_drop<T: zeroize::ZeroizeOnDrop>() {}
+    fn assert_zeroize<T: zeroize::Zeroize>() {}
+
+    assert_zeroize_on_drop::<aead::OpeningKey>();
+    assert_zeroize_on_drop::<aead::SealingKey>();
+    assert_zeroize_on_drop::<agreement::EphemeralPrivateKey>();
+    assert_zeroize_on_drop::<digest::Context>();
+    assert_zeroize_on_drop::<hmac::SigningContext>();
+    assert_zeroize_on_drop::<hmac::SigningKey>();
+    assert_zeroize_on_drop::<hmac::VerificationKey>();
+    assert_zeroize_on_drop::<pkcs8::Document>();
+    assert_zeroize_on_drop::<signature::ECDSAKeyPair>();
+    assert_zeroize_on_drop::<signature::Ed25519KeyPair>();
+    #[cfg(feature = "rsa_signing")]
+    assert_zeroize_on_drop::<signature::RSAKeyPair>();
+
+    assert_zeroize::<pkcs8::Document>();
+    assert_zeroize::<pake::SharedKey>();
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn zeroize_pkcs8_document() {
+    use zeroize::Zeroize;
+
+    let rng = test::rand::FixedSliceRandom { bytes: &secret(9) };
+    let mut pkcs8 =
+        signature::ECDSAKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            .unwrap();
+    assert!(!pkcs8.as_ref().is_empty());
+    pkcs8.zeroize();
+    assert!(pkcs8.as_ref().is_empty());
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn zeroize_from_zeroizing() {
+    use zeroize::Zeroizing;
+
+    // The caller's copy of the key is freed by the constructor, within
+    // `inspect()`, so it must have been zeroized first.
+    let key = secret(10);
+    assert_eq!(
+        inspect(&key, || {
+            let key = Zeroizing::new(key.to_vec());
+            Box::new(aead::SealingKey::from_zeroizing(&aead::CHACHA20_POLY1305, key).unwrap())
+        }),
+        Outcome::Zeroized
+    );
+
+    // The keys below aren't kept verbatim, so only the freed copy is checked.
+    //
+    // The AEAD key above was copied onto the stack, which isn't zeroized.
+    // With `locked_memory`, most of the boxed `hmac::SigningKey` is padding
+    // that is copied from that stack, so a different key is used here.
+    let key = secret(12);
+    assert_ne!(
+        inspect(&key, || {
+            let key = Zeroizing::new(key.to_vec());
+            Box::new(hmac::SigningKey::from_zeroizing(&digest::SHA256, key))
+        }),
+        Outcome::NotZeroized
+    );
+
+    let seed = secret(11);
+    let rng = test::rand::FixedSliceRandom { bytes: &seed };
+    let pkcs8 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    assert_ne!(
+        inspect(&seed, || {
+            let pkcs8 = Zeroizing::new(pkcs8.as_ref().to_vec());
+            Box::new(signature::Ed25519KeyPair::from_pkcs8_zeroizing(pkcs8).unwrap())
+        }),
+        Outcome::NotZeroized
+    );
+}
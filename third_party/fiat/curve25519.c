@@ -1874,12 +1874,8 @@ static void table_select(ge_precomp *t, int pos, signed char b) {
 //
 // Preconditions:
 //   a[31] <= 127
-void GFp_x25519_ge_scalarmult_base(ge_p3 *h, const uint8_t *a) {
-  signed char e[64];
+static void radix16(signed char e[64], const uint8_t *a) {
   signed char carry;
-  ge_p1p1 r;
-  ge_p2 s;
-  ge_precomp t;
   int i;
 
   for (i = 0; i < 32; ++i) {
@@ -1898,6 +1894,16 @@ void GFp_x25519_ge_scalarmult_base(ge_p3 *h, const uint8_t *a) {
   }
   e[63] += carry;
   // each e[i] is between -8 and 8
+}
+
+void GFp_x25519_ge_scalarmult_base(ge_p3 *h, const uint8_t *a) {
+  signed char e[64];
+  ge_p1p1 r;
+  ge_p2 s;
+  ge_precomp t;
+  int i;
+
+  radix16(e, a);
 
   ge_p3_0(h);
   for (i = 1; i < 64; i += 2) {
@@ -1922,6 +1928,183 @@ void GFp_x25519_ge_scalarmult_base(ge_p3 *h, const uint8_t *a) {
   }
 }
 
+// table[8 * i + j] = (j + 1) * 256^i * A, for 0 <= i < 32 and 0 <= j < 8,
+// i.e. the same multiples of A as `k25519Precomp` has of B.
+void GFp_x25519_ge_precompute_vartime(ge_cached table[256], const ge_p3 *A) {
+  ge_p1p1 t;
+  ge_p2 s;
+  ge_p3 u;
+  ge_p3 p = *A;
+  int i;
+  int j;
+
+  for (i = 0; i < 32; ++i) {
+    x25519_ge_p3_to_cached(&table[8 * i], &p);
+    for (j = 1; j < 8; ++j) {
+      x25519_ge_add(&t, &p, &table[8 * i + j - 1]);
+      x25519_ge_p1p1_to_p3(&u, &t);
+      x25519_ge_p3_to_cached(&table[8 * i + j], &u);
+    }
+
+    // p = 256 * p
+    ge_p3_dbl(&t, &p);
+    for (j = 1; j < 8; ++j) {
+      x25519_ge_p1p1_to_p2(&s, &t);
+      ge_p2_dbl(&t, &s);
+    }
+    x25519_ge_p1p1_to_p3(&p, &t);
+  }
+}
+
+// h += b * (the multiple of A in `row`).
+static void ge_add_precomputed_vartime(ge_p3 *h, const ge_cached row[8],
+                                       signed char b) {
+  ge_p1p1 t;
+
+  if (b > 0) {
+    x25519_ge_add(&t, h, &row[b - 1]);
+  } else if (b < 0) {
+    x25519_ge_sub(&t, h, &row[-b - 1]);
+  } else {
+    return;
+  }
+  x25519_ge_p1p1_to_p3(h, &t);
+}
+
+// h += b * (the multiple of B in `row`).
+static void ge_madd_precomputed_vartime(ge_p3 *h, const ge_precomp row[8],
+                                        signed char b) {
+  ge_p1p1 t;
+
+  if (b > 0) {
+    ge_madd(&t, h, &row[b - 1]);
+  } else if (b < 0) {
+    ge_msub(&t, h, &row[-b - 1]);
+  } else {
+    return;
+  }
+  x25519_ge_p1p1_to_p3(h, &t);
+}
+
+// r = a * A + b * B
+// where `table` was computed from A by `GFp_x25519_ge_precompute_vartime`.
+// This is like `ge_double_scalarmult_vartime`, but uses the 4-bit fixed-base
+// method of `GFp_x25519_ge_scalarmult_base` for both A and B, so that it only
+// needs four doublings.
+//
+// Preconditions:
+//   a[31] <= 127
+//   b[31] <= 127
+void GFp_x25519_ge_double_scalarmult_precomputed_vartime(
+    ge_p2 *r, const uint8_t *a, const ge_cached table[256], const uint8_t *b) {
+  signed char ae[64];
+  signed char be[64];
+  ge_p1p1 t;
+  ge_p2 s;
+  ge_p3 h;
+  int i;
+
+  radix16(ae, a);
+  radix16(be, b);
+
+  ge_p3_0(&h);
+  for (i = 1; i < 64; i += 2) {
+    ge_add_precomputed_vartime(&h, &table[8 * (i / 2)], ae[i]);
+    ge_madd_precomputed_vartime(&h, k25519Precomp[i / 2], be[i]);
+  }
+
+  ge_p3_dbl(&t, &h);
+  x25519_ge_p1p1_to_p2(&s, &t);
+  ge_p2_dbl(&t, &s);
+  x25519_ge_p1p1_to_p2(&s, &t);
+  ge_p2_dbl(&t, &s);
+  x25519_ge_p1p1_to_p2(&s, &t);
+  ge_p2_dbl(&t, &s);
+  x25519_ge_p1p1_to_p3(&h, &t);
+
+  for (i = 0; i < 64; i += 2) {
+    ge_add_precomputed_vartime(&h, &table[8 * (i / 2)], ae[i]);
+    ge_madd_precomputed_vartime(&h, k25519Precomp[i / 2], be[i]);
+  }
+
+  ge_p3_to_p2(r, &h);
+}
+
+static void cached_cmov(ge_cached *t, const ge_cached *u, uint8_t b) {
+  fe_cmov(&t->YplusX, &u->YplusX, b);
+  fe_cmov(&t->YminusX, &u->YminusX, b);
+  fe_cmov(&t->Z, &u->Z, b);
+  fe_cmov(&t->T2d, &u->T2d, b);
+}
+
+// t = b * A, where table[j] = (j + 1) * A, like |table_select|.
+static void cached_select(ge_cached *t, const ge_cached table[8],
+                          signed char b) {
+  ge_cached minust;
+  uint8_t bnegative = negative(b);
+  uint8_t babs = b - ((uint8_t)((-bnegative) & b) << 1);
+  int j;
+
+  // The neutral element.
+  fe_loose_1(&t->YplusX);
+  fe_loose_1(&t->YminusX);
+  fe_loose_1(&t->Z);
+  fe_loose_0(&t->T2d);
+  for (j = 0; j < 8; ++j) {
+    cached_cmov(t, &table[j], equal(babs, j + 1));
+  }
+  fe_copy_ll(&minust.YplusX, &t->YminusX);
+  fe_copy_ll(&minust.YminusX, &t->YplusX);
+  fe_copy_ll(&minust.Z, &t->Z);
+
+  fe tmp;
+  fe_carry(&tmp, &t->T2d);
+  fe_neg(&minust.T2d, &tmp);
+
+  cached_cmov(t, &minust, bnegative);
+}
+
+// h = a * A, in constant time, with the signed 4-bit windows of
+// |GFp_x25519_ge_scalarmult_base| and a table of eight multiples of A.
+//
+// Preconditions:
+//   a[31] <= 127
+void GFp_x25519_ge_scalarmult(ge_p3 *h, const uint8_t *a, const ge_p3 *A) {
+  signed char e[64];
+  ge_cached table[8];
+  ge_cached t;
+  ge_p1p1 r;
+  ge_p2 s;
+  ge_p3 u;
+  int i;
+
+  x25519_ge_p3_to_cached(&table[0], A);
+  for (i = 1; i < 8; ++i) {
+    x25519_ge_add(&r, A, &table[i - 1]);
+    x25519_ge_p1p1_to_p3(&u, &r);
+    x25519_ge_p3_to_cached(&table[i], &u);
+  }
+
+  radix16(e, a);
+
+  ge_p3_0(h);
+  for (i = 63; i >= 0; --i) {
+    // h = 16 * h
+    ge_p3_dbl(&r, h);
+    x25519_ge_p1p1_to_p2(&s, &r);
+    ge_p2_dbl(&r, &s);
+    x25519_ge_p1p1_to_p2(&s, &r);
+    ge_p2_dbl(&r, &s);
+    x25519_ge_p1p1_to_p2(&s, &r);
+    ge_p2_dbl(&r, &s);
+    x25519_ge_p1p1_to_p3(h, &r);
+
+    cached_select(&t, table, e[i]);
+    x25519_ge_add(&r, h, &t);
+    x25519_ge_p1p1_to_p3(h, &r);
+  }
+}
+
 #endif
 
 static void slide(signed char *r, const uint8_t *a) {
@@ -2992,6 +3175,16 @@ void GFp_x25519_public_from_private(uint8_t out_public_value[32],
   fe_tobytes(out_public_value, &zminusy_inv);
 }
 
+void GFp_x25519_fe_add(fe *h, const fe *f, const fe *g) {
+  fe_loose t;
+  fe_add(&t, f, g);
+  fe_carry(h, &t);
+}
+
+void GFp_x25519_fe_frombytes(fe *h, const uint8_t s[32]) {
+  fe_frombytes(h, s);
+}
+
 void GFp_x25519_fe_invert(fe *out, const fe *z) {
   fe_invert(out, z);
 }
@@ -3010,6 +3203,20 @@ void GFp_x25519_fe_neg(fe *f) {
   fe_carry(f, &t);
 }
 
+void GFp_x25519_fe_pow22523(fe *out, const fe *z) {
+  fe_pow22523(out, z);
+}
+
+void GFp_x25519_fe_sq(fe *h, const fe *f) {
+  fe_sq_tt(h, f);
+}
+
+void GFp_x25519_fe_sub(fe *h, const fe *f, const fe *g) {
+  fe_loose t;
+  fe_sub(&t, f, g);
+  fe_carry(h, &t);
+}
+
 void GFp_x25519_fe_tobytes(uint8_t s[32], const fe *h) {
   fe_tobytes(s, h);
 }
@@ -3019,6 +3226,14 @@ void GFp_x25519_ge_double_scalarmult_vartime(ge_p2 *r, const uint8_t *a,
   ge_double_scalarmult_vartime(r, a, A, b);
 }
 
+void GFp_x25519_ge_add(ge_p3 *r, const ge_p3 *a, const ge_p3 *b) {
+  ge_cached c;
+  ge_p1p1 t;
+  x25519_ge_p3_to_cached(&c, b);
+  x25519_ge_add(&t, a, &c);
+  x25519_ge_p1p1_to_p3(r, &t);
+}
+
 void GFp_x25519_sc_mask(uint8_t a[32]) {
   a[0] &= 248;
   a[31] &= 127;
@@ -30,7 +30,12 @@
 #include "../../crypto/internal.h"
 
 
-#if defined(OPENSSL_ARM) && !defined(OPENSSL_NO_ASM) && !defined(OPENSSL_APPLE)
+#if defined(OPENSSL_ARM)
+#include <GFp/arm_arch.h>
+#endif
+
+#if defined(OPENSSL_ARM) && !defined(OPENSSL_NO_ASM) && \
+    !defined(OPENSSL_APPLE) && __ARM_MAX_ARCH__ >= 7
 #define BORINGSSL_X25519_NEON
 
 // x25519_NEON is defined in asm/x25519-arm.S.
@@ -109,6 +114,7 @@ typedef struct {
   fe_loose xy2d;
 } ge_precomp;
 
+// Keep in sync with `CachedPoint` in curve25519/ops.rs.
 typedef struct {
   fe_loose YplusX;
   fe_loose YminusX;
@@ -117,15 +123,25 @@ typedef struct {
 } ge_cached;
 
 // Prevent -Wmissing-prototypes warnings.
+void GFp_x25519_fe_add(fe *h, const fe *f, const fe *g);
+void GFp_x25519_fe_frombytes(fe *h, const uint8_t s[32]);
 void GFp_x25519_fe_invert(fe *out, const fe *z);
 uint8_t GFp_x25519_fe_isnegative(const fe *f);
 void GFp_x25519_fe_mul_ttt(fe *h, const fe *f, const fe *g);
 void GFp_x25519_fe_neg(/*in/out*/ fe *f);
+void GFp_x25519_fe_pow22523(fe *out, const fe *z);
+void GFp_x25519_fe_sq(fe *h, const fe *f);
+void GFp_x25519_fe_sub(fe *h, const fe *f, const fe *g);
 void GFp_x25519_fe_tobytes(uint8_t *s, const fe *h);
+void GFp_x25519_ge_add(ge_p3 *r, const ge_p3 *a, const ge_p3 *b);
 void GFp_x25519_ge_double_scalarmult_vartime(ge_p2 *r, const uint8_t *a,
                                              const ge_p3 *A,
                                              const uint8_t *b);
+void GFp_x25519_ge_double_scalarmult_precomputed_vartime(
+    ge_p2 *r, const uint8_t *a, const ge_cached table[256], const uint8_t *b);
+void GFp_x25519_ge_precompute_vartime(ge_cached table[256], const ge_p3 *A);
 int GFp_x25519_ge_frombytes_vartime(ge_p3 *h, const uint8_t *s);
+void GFp_x25519_ge_scalarmult(ge_p3 *h, const uint8_t *a, const ge_p3 *A);
 void GFp_x25519_ge_scalarmult_base(ge_p3 *h, const uint8_t a[32]);
 void GFp_x25519_sc_muladd(uint8_t *s, const uint8_t *a, const uint8_t *b,
                           const uint8_t *c);